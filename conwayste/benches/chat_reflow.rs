@@ -0,0 +1,84 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Benchmarks the word wrapping the chatbox does over its whole history whenever it is resized or
+//! its history is trimmed.
+//!
+//! Run with `cargo bench -p conwayste`.
+
+extern crate criterion;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+// The client is only a binary, so the wrapping code is brought in directly
+#[path = "../src/ui/wrap.rs"]
+mod wrap;
+
+const HISTORY_SIZES: [usize; 3] = [100, 1000, 10000]; // messages
+const CHARS_PER_LINE: usize = 40; // about what fits in the default chatbox
+
+/// Chat messages of varying lengths, including words too long for a line.
+fn history(messages: usize) -> Vec<String> {
+    let words = [
+        "glider",
+        "gun",
+        "anyone",
+        "up",
+        "for",
+        "a",
+        "rematch?",
+        "gg",
+        "wellplayed",
+        "lol",
+        "supercalifragilisticexpialidocious",
+        "that",
+        "spaceship",
+        "ate",
+        "my",
+        "whole",
+        "base",
+    ];
+    (0..messages)
+        .map(|i| {
+            let len = 1 + (i * 7) % 30; // words
+            (0..len)
+                .map(|j| words[(i + j * 3) % words.len()])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+fn bench_reflow(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chat reflow");
+    for &size in HISTORY_SIZES.iter() {
+        let messages = history(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &messages, |b, messages| {
+            b.iter(|| {
+                messages
+                    .iter()
+                    .map(|msg| wrap::wrap_words(msg, CHARS_PER_LINE).len())
+                    .sum::<usize>()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reflow);
+criterion_main!(benches);
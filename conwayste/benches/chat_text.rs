@@ -0,0 +1,118 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Benchmarks drawing a full chatbox history the way the chatbox does after a reflow, from
+//! wrapping the messages into lines to laying out and queueing their text, with and without the
+//! text cache. Laying out the glyphs needs the graphics of a ggez context, so this opens a window.
+//!
+//! Run with `cargo bench -p conwayste`.
+
+extern crate criterion;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ggez::graphics::{self, DrawParam, FilterMode, PxScale, Text, TextFragment};
+use ggez::mint::Point2;
+use ggez::{conf, Context, ContextBuilder};
+
+// The client is only a binary, so the code used is brought in directly
+#[allow(dead_code)]
+#[path = "../src/ui/textcache.rs"]
+mod textcache;
+#[path = "../src/ui/wrap.rs"]
+mod wrap;
+
+const HISTORY_LINES: usize = 200; // messages, as kept by the chatbox
+const CHARS_PER_LINE: usize = 40; // about what fits in the default chatbox
+
+/// Chat messages of varying lengths.
+fn history() -> Vec<String> {
+    let words = [
+        "glider",
+        "gun",
+        "anyone",
+        "up",
+        "for",
+        "a",
+        "rematch?",
+        "gg",
+        "lol",
+        "that",
+        "spaceship",
+        "ate",
+        "my",
+        "whole",
+        "base",
+    ];
+    (0..HISTORY_LINES)
+        .map(|i| {
+            let len = 1 + (i * 7) % 20; // words
+            (0..len)
+                .map(|j| words[(i + j * 3) % words.len()])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+fn fragment(line: String) -> TextFragment {
+    TextFragment::new(line).scale(PxScale::from(20.0))
+}
+
+/// Lays out and queues `text` on the next line down, as the chatbox does with each line it shows.
+fn queue_line(ctx: &mut Context, text: &Text, line: usize) {
+    let height = text.height(ctx) as f32; // lays out the glyphs, unless already done
+    let point = Point2 {
+        x: 0.0,
+        y: line as f32 * height,
+    };
+    graphics::queue_text(ctx, text, point, None);
+}
+
+fn bench_history_texts(c: &mut Criterion) {
+    let (mut ctx, _events_loop) = ContextBuilder::new("chat_text", "conwayste")
+        .window_mode(conf::WindowMode::default().dimensions(640.0, 480.0))
+        .build()
+        .expect("benchmarking text needs a window");
+    let ctx = &mut ctx;
+    let messages = history();
+    let mut group = c.benchmark_group("chat history texts");
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            let lines = messages.iter().flat_map(|msg| wrap::wrap_words(msg, CHARS_PER_LINE));
+            for (i, line) in lines.enumerate() {
+                queue_line(ctx, &Text::new(fragment(line)), i);
+            }
+            graphics::draw_queued_text(ctx, DrawParam::default(), None, FilterMode::Linear).unwrap();
+        });
+    });
+    group.bench_function("cached", |b| {
+        let mut cache = textcache::TextCache::new();
+        b.iter(|| {
+            let lines = messages.iter().flat_map(|msg| wrap::wrap_words(msg, CHARS_PER_LINE));
+            for (i, line) in lines.enumerate() {
+                queue_line(ctx, &cache.get(fragment(line)), i);
+            }
+            graphics::draw_queued_text(ctx, DrawParam::default(), None, FilterMode::Linear).unwrap();
+            cache.end_frame();
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_history_texts);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Announcements for screen readers and text-to-speech.
+//!
+//! Anything in the game can queue text with `announce`, such as the name of the widget that just
+//! got focus. Once per frame, the client calls `Announcer::flush`, which hands the queued text to
+//! the outside world: each announcement is appended as a line to a file that a screen reader or
+//! other tool can follow, and is passed to a command such as `spd-say` (speech-dispatcher).
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Child, Command};
+use std::sync::Mutex;
+
+use crate::config::AccessibilitySettings;
+
+lazy_static! {
+    static ref QUEUE: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+/// Queues `text` to be announced at the next `Announcer::flush`. Text queued while announcements
+/// are turned off is dropped at that point.
+pub fn announce<S: Into<String>>(text: S) {
+    QUEUE.lock().unwrap().push(text.into());
+}
+
+fn take_queued() -> Vec<String> {
+    std::mem::replace(&mut *QUEUE.lock().unwrap(), vec![])
+}
+
+/// Sends queued announcements wherever the `[accessibility]` settings say.
+pub struct Announcer {
+    enabled:  bool,
+    file:     String,
+    command:  Vec<String>, // program, then its arguments
+    speaking: Option<Child>,
+}
+
+impl Announcer {
+    pub fn new(settings: &AccessibilitySettings) -> Self {
+        Announcer {
+            enabled:  settings.announce,
+            file:     settings.announce_file.clone(),
+            command:  settings
+                .announce_command
+                .split_whitespace()
+                .map(|word| word.to_owned())
+                .collect(),
+            speaking: None,
+        }
+    }
+
+    /// Writes out and speaks everything queued since the last flush. Failures are logged rather
+    /// than returned, since the game should carry on either way.
+    pub fn flush(&mut self) {
+        let announcements = take_queued();
+        if !self.enabled || announcements.is_empty() {
+            return;
+        }
+
+        if !self.file.is_empty() {
+            let result = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.file)
+                .and_then(|mut f| {
+                    for text in &announcements {
+                        writeln!(f, "{}", text)?;
+                    }
+                    Ok(())
+                });
+            if let Err(e) = result {
+                warn!("Could not write announcements to {:?}: {}", self.file, e);
+            }
+        }
+
+        if let Some((program, args)) = self.command.split_first() {
+            // Only the latest announcement is spoken, and it cuts off the one before, so that moving
+            // quickly through the menus doesn't leave a backlog of speech.
+            if let Some(mut child) = self.speaking.take() {
+                let _ = child.kill(); // fails if it has already finished, which is fine
+                let _ = child.wait();
+            }
+            let text = announcements.last().unwrap(); // unwrap OK because checked for empty above
+            match Command::new(program).args(args).arg(text).spawn() {
+                Ok(child) => self.speaking = Some(child),
+                Err(e) => {
+                    warn!("Could not run announcement command {:?}: {}", program, e);
+                    self.command.clear(); // don't keep trying every frame
+                }
+            }
+        }
+    }
+}
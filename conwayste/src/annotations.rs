@@ -0,0 +1,432 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Annotations drawn over the board: labeled markers, arrows, and measurements of how far apart
+//! two cells are. They are only on the player's own screen until shared with their room, which
+//! shows them to everyone in it along with the name of whoever drew them.
+
+use ggez::graphics::{self, DrawMode, DrawParam, Font, Rect};
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+use netwayste::net::{Annotation, MAX_ANNOTATION_LABEL_LEN};
+
+use crate::constants::colors::{ANNOTATION_COLOR, ANNOTATION_SHARED_COLOR};
+use crate::i18n::tr_args;
+use crate::ui::draw_text;
+use crate::viewport::GridView;
+
+const MAX_ANNOTATIONS: usize = 64; // kept on the board; past this, the oldest go first
+const MIN_MARKER_RADIUS: f32 = 6.0; // pixels, so markers stay visible zoomed out
+const MIN_ARROWHEAD_LENGTH: f32 = 8.0; // pixels
+const LINE_WIDTH: f32 = 2.0; // pixels
+
+/// What clicking or dragging on the board does, other than placing cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationTool {
+    Off,
+    Marker,  // clicking places a marker
+    Arrow,   // dragging draws an arrow
+    Measure, // dragging measures the distance between two cells
+}
+
+impl AnnotationTool {
+    /// The tool picked after this one when cycling through them with a key.
+    pub fn next(self) -> Self {
+        match self {
+            AnnotationTool::Off => AnnotationTool::Marker,
+            AnnotationTool::Marker => AnnotationTool::Arrow,
+            AnnotationTool::Arrow => AnnotationTool::Measure,
+            AnnotationTool::Measure => AnnotationTool::Off,
+        }
+    }
+
+    /// Message ID of what the tool does.
+    pub fn message_id(self) -> &'static str {
+        match self {
+            AnnotationTool::Off => "annotation-tool-off",
+            AnnotationTool::Marker => "annotation-tool-marker",
+            AnnotationTool::Arrow => "annotation-tool-arrow",
+            AnnotationTool::Measure => "annotation-tool-measure",
+        }
+    }
+}
+
+/// An annotation on the board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placed {
+    pub annotation: Annotation,
+    pub author:     Option<String>, // None if the player drew it
+    id:             u32,            // unique among its author's annotations
+    shared:         bool,           // whether the player has shared it with the room
+}
+
+#[derive(Debug, Default)]
+pub struct Annotations {
+    placed:         Vec<Placed>, // oldest first
+    markers_placed: usize,       // for labeling the next marker
+    next_id:        u32,         // of the player's next annotation; kept on clearing, as others have the old ones
+}
+
+impl Annotations {
+    pub fn new() -> Self {
+        Annotations::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Placed> {
+        self.placed.iter()
+    }
+
+    /// Adds an annotation the player drew.
+    pub fn add(&mut self, annotation: Annotation) {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.push(Placed {
+            annotation,
+            author: None,
+            id,
+            shared: false,
+        });
+    }
+
+    /// Adds an annotation another player shared with the room, or updates it if they shared it
+    /// before, such as a marker they relabeled.
+    pub fn add_shared(&mut self, author: String, id: u32, annotation: Annotation) {
+        let existing = self
+            .placed
+            .iter_mut()
+            .find(|placed| placed.id == id && placed.author.as_ref() == Some(&author));
+        if let Some(placed) = existing {
+            placed.annotation = annotation;
+            return;
+        }
+        self.push(Placed {
+            annotation,
+            author: Some(author),
+            id,
+            shared: true,
+        });
+    }
+
+    fn push(&mut self, placed: Placed) {
+        if self.placed.len() >= MAX_ANNOTATIONS {
+            self.placed.remove(0);
+        }
+        self.placed.push(placed);
+    }
+
+    /// A label for the next marker placed by clicking: A through Z, then AA, AB, and so on.
+    pub fn next_marker_label(&mut self) -> String {
+        let mut n = self.markers_placed;
+        self.markers_placed += 1;
+        let mut label = vec![];
+        loop {
+            label.push((b'A' + (n % 26) as u8) as char);
+            if n < 26 {
+                break;
+            }
+            n = n / 26 - 1;
+        }
+        label.iter().rev().collect()
+    }
+
+    /// Gives the player's newest marker a new label, cut to MAX_ANNOTATION_LABEL_LEN characters.
+    /// Returns false if they haven't placed one. A shared marker has to be shared again for the
+    /// room to see the new label.
+    pub fn relabel_last_marker(&mut self, new_label: &str) -> bool {
+        let newest = self
+            .placed
+            .iter_mut()
+            .rev()
+            .find(|placed| placed.author.is_none() && matches!(placed.annotation, Annotation::Marker { .. }));
+        match newest {
+            Some(Placed {
+                annotation: Annotation::Marker { label, .. },
+                shared,
+                ..
+            }) => {
+                *label = new_label.chars().take(MAX_ANNOTATION_LABEL_LEN).collect();
+                *shared = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The player's annotations that the room hasn't been shown, with their IDs, which from now on
+    /// count as shared.
+    pub fn take_unshared(&mut self) -> Vec<(u32, Annotation)> {
+        self.placed
+            .iter_mut()
+            .filter(|placed| placed.author.is_none() && !placed.shared)
+            .map(|placed| {
+                placed.shared = true;
+                (placed.id, placed.annotation.clone())
+            })
+            .collect()
+    }
+
+    /// Removes every annotation, including those shared by others, from this player's board.
+    pub fn clear(&mut self) {
+        self.placed.clear();
+        self.markers_placed = 0;
+    }
+}
+
+/// How far apart two cells, each (column, row), are: the columns and rows between them, and the
+/// straight-line distance, all in cells.
+pub fn measure(from: (u32, u32), to: (u32, u32)) -> (u32, u32, f32) {
+    let columns = (from.0 as i64 - to.0 as i64).abs() as u32;
+    let rows = (from.1 as i64 - to.1 as i64).abs() as u32;
+    let distance = ((columns as f32).powi(2) + (rows as f32).powi(2)).sqrt();
+    (columns, rows, distance)
+}
+
+/// Draws the annotations over the board, and the arrow or measurement being dragged out, if any.
+/// Those drawn by other players are in another color, with their name.
+pub fn draw(
+    ctx: &mut Context,
+    font: Font,
+    viewport: &GridView,
+    placed: &[Placed],
+    in_progress: Option<Annotation>,
+) -> GameResult<()> {
+    let shown = viewport.get_rect();
+    let cell_size = viewport.get_cell_size();
+    let in_progress = in_progress.map(|annotation| Placed {
+        annotation,
+        author: None,
+        id: 0,
+        shared: false,
+    });
+    for placed in placed.iter().chain(in_progress.iter()) {
+        let (color, author) = match placed.author {
+            Some(ref name) => (*ANNOTATION_SHARED_COLOR, Some(name)),
+            None => (*ANNOTATION_COLOR, None),
+        };
+        let credited = |text: String| match author {
+            Some(name) => tr_args("annotation-shared-by", &[("name", name), ("label", &text)]),
+            None => text,
+        };
+        match placed.annotation {
+            Annotation::Marker { cell, ref label } => {
+                let center = cell_center(viewport, cell);
+                if !shown.contains(center) {
+                    continue;
+                }
+                let radius = (cell_size * 0.75).max(MIN_MARKER_RADIUS);
+                let ring = graphics::Mesh::new_circle(ctx, DrawMode::stroke(LINE_WIDTH), center, radius, 0.5, color)?;
+                graphics::draw(ctx, &ring, DrawParam::default())?;
+                let label_pos = Point2 {
+                    x: center.x + radius + 2.0,
+                    y: center.y - radius,
+                };
+                draw_text(ctx, font.clone(), color, credited(label.clone()), &label_pos)?;
+            }
+            Annotation::Arrow { from, to } => {
+                let (tail, tip) = (cell_center(viewport, from), cell_center(viewport, to));
+                draw_line(ctx, tail, tip, shown, color)?;
+                if from != to && shown.contains(tip) {
+                    // the head, pointing along the line
+                    let length = ((tip.x - tail.x).powi(2) + (tip.y - tail.y).powi(2)).sqrt();
+                    let (dx, dy) = ((tip.x - tail.x) / length, (tip.y - tail.y) / length);
+                    let head = cell_size.max(MIN_ARROWHEAD_LENGTH);
+                    let base = Point2 {
+                        x: tip.x - dx * head,
+                        y: tip.y - dy * head,
+                    };
+                    let corners = [
+                        tip,
+                        Point2 {
+                            x: base.x - dy * head / 2.0,
+                            y: base.y + dx * head / 2.0,
+                        },
+                        Point2 {
+                            x: base.x + dy * head / 2.0,
+                            y: base.y - dx * head / 2.0,
+                        },
+                    ];
+                    let arrowhead = graphics::Mesh::new_polygon(ctx, DrawMode::fill(), &corners, color)?;
+                    graphics::draw(ctx, &arrowhead, DrawParam::default())?;
+                }
+                if let (Some(name), true) = (author, shown.contains(tail)) {
+                    draw_text(ctx, font.clone(), color, name.clone(), &tail)?;
+                }
+            }
+            Annotation::Measure { from, to } => {
+                let (start, end) = (cell_center(viewport, from), cell_center(viewport, to));
+                draw_line(ctx, start, end, shown, color)?;
+                let middle = Point2 {
+                    x: (start.x + end.x) / 2.0,
+                    y: (start.y + end.y) / 2.0,
+                };
+                if shown.contains(middle) {
+                    let (columns, rows, distance) = measure(from, to);
+                    let text = tr_args(
+                        "annotation-measure",
+                        &[
+                            ("columns", &columns),
+                            ("rows", &rows),
+                            ("distance", &format!("{:.1}", distance)),
+                        ],
+                    );
+                    draw_text(ctx, font.clone(), color, credited(text), &middle)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The middle of a cell, (column, row), on screen.
+fn cell_center(viewport: &GridView, (col, row): (u32, u32)) -> Point2<f32> {
+    let origin = viewport.get_origin();
+    let cell_size = viewport.get_cell_size();
+    Point2 {
+        x: origin.x + (col as f32 + 0.5) * cell_size,
+        y: origin.y + (row as f32 + 0.5) * cell_size,
+    }
+}
+
+/// Draws the part of the line from `a` to `b` that is inside `shown`.
+fn draw_line(ctx: &mut Context, a: Point2<f32>, b: Point2<f32>, shown: Rect, color: graphics::Color) -> GameResult<()> {
+    if let Some((a, b)) = clip_line(a, b, shown) {
+        if a != b {
+            let line = graphics::Mesh::new_line(ctx, &[a, b], LINE_WIDTH, color)?;
+            graphics::draw(ctx, &line, DrawParam::default())?;
+        }
+    }
+    Ok(())
+}
+
+/// The part of the line from `a` to `b` inside `rect`, if any (Liang-Barsky).
+fn clip_line(a: Point2<f32>, b: Point2<f32>, rect: Rect) -> Option<(Point2<f32>, Point2<f32>)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    let edges = [
+        (-dx, a.x - rect.left()),
+        (dx, rect.right() - a.x),
+        (-dy, a.y - rect.top()),
+        (dy, rect.bottom() - a.y),
+    ];
+    for &(p, q) in edges.iter() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None; // parallel to this edge, and outside of it
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            exit = exit.min(q / p);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    let at = |t: f32| Point2 {
+        x: a.x + t * dx,
+        y: a.y + t * dy,
+    };
+    Some((at(enter), at(exit)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn marker(label: &str) -> Annotation {
+        Annotation::Marker {
+            cell:  (1, 2),
+            label: label.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_marker_labels_go_past_z() {
+        let mut annotations = Annotations::new();
+        let labels: Vec<String> = (0..28).map(|_| annotations.next_marker_label()).collect();
+        assert_eq!(labels[0], "A");
+        assert_eq!(labels[25], "Z");
+        assert_eq!(labels[26], "AA");
+        assert_eq!(labels[27], "AB");
+    }
+
+    #[test]
+    fn test_only_unshared_annotations_of_the_player_are_taken() {
+        let mut annotations = Annotations::new();
+        annotations.add(marker("A"));
+        annotations.add_shared("alice".to_owned(), 0, marker("hers"));
+        assert_eq!(annotations.take_unshared(), vec![(0, marker("A"))]);
+        assert_eq!(annotations.take_unshared(), vec![]);
+
+        // Relabeling shares the marker again under the same ID
+        assert!(annotations.relabel_last_marker("glider gun"));
+        assert_eq!(annotations.take_unshared(), vec![(0, marker("glider gun"))]);
+    }
+
+    #[test]
+    fn test_sharing_again_replaces_the_annotation() {
+        let mut annotations = Annotations::new();
+        annotations.add_shared("alice".to_owned(), 3, marker("A"));
+        annotations.add_shared("bob".to_owned(), 3, marker("A"));
+        annotations.add_shared("alice".to_owned(), 3, marker("glider gun"));
+        let shared: Vec<_> = annotations
+            .iter()
+            .map(|placed| (placed.author.clone().unwrap(), placed.annotation.clone()))
+            .collect();
+        assert_eq!(
+            shared,
+            vec![
+                ("alice".to_owned(), marker("glider gun")),
+                ("bob".to_owned(), marker("A")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oldest_annotation_goes_first_when_full() {
+        let mut annotations = Annotations::new();
+        for i in 0..=MAX_ANNOTATIONS {
+            annotations.add(marker(&i.to_string()));
+        }
+        assert_eq!(annotations.iter().count(), MAX_ANNOTATIONS);
+        assert_eq!(annotations.iter().next().unwrap().annotation, marker("1"));
+    }
+
+    #[test]
+    fn test_lines_are_clipped_to_the_board() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let point = |x, y| Point2 { x, y };
+        assert_eq!(
+            clip_line(point(-5.0, 5.0), point(5.0, 5.0), rect),
+            Some((point(0.0, 5.0), point(5.0, 5.0)))
+        );
+        assert_eq!(
+            clip_line(point(2.0, 2.0), point(8.0, 8.0), rect),
+            Some((point(2.0, 2.0), point(8.0, 8.0)))
+        );
+        assert_eq!(clip_line(point(-5.0, 20.0), point(5.0, 20.0), rect), None);
+        assert_eq!(clip_line(point(-5.0, 0.0), point(0.0, -5.0), rect), None);
+    }
+
+    #[test]
+    fn test_measure() {
+        assert_eq!(measure((1, 1), (4, 5)), (3, 4, 5.0));
+        assert_eq!(measure((4, 5), (1, 1)), (3, 4, 5.0));
+        assert_eq!(measure((7, 7), (7, 7)), (0, 0, 0.0));
+    }
+}
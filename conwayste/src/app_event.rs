@@ -0,0 +1,132 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The client's main loop. It does what `ggez::event::run` does, handing the window's events to an
+//! `EventHandler` and updating and drawing once they are handled, but also passes on the events
+//! `EventHandler` has no method for, like files dropped on the window, as `AppEvent`s.
+//!
+//! Gamepads are not polled, as the client has no use for them.
+
+use std::path::PathBuf;
+
+use ggez::event::{self, EventHandler};
+use ggez::graphics;
+use ggez::input::{keyboard, mouse};
+use ggez::Context;
+use winit::event::{ElementState, Event, KeyboardInput, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+
+/// What happened to the window that ggez doesn't tell an `EventHandler` about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppEvent {
+    FileDropped(PathBuf), // a file was dragged from elsewhere and dropped on the window
+}
+
+pub trait AppEventHandler: EventHandler {
+    fn app_event(&mut self, ctx: &mut Context, event: AppEvent);
+}
+
+/// Runs the game until it quits, as `ggez::event::run` would.
+pub fn run<S>(mut ctx: Context, events_loop: EventLoop<()>, mut state: S) -> !
+where
+    S: AppEventHandler + 'static,
+{
+    let mut last_mouse = mouse::position(&ctx);
+    events_loop.run(move |mut event, _, control_flow| {
+        if !ctx.continuing {
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
+        *control_flow = ControlFlow::Poll;
+        let ctx = &mut ctx;
+        event::process_event(ctx, &mut event);
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::Resized(size) => state.resize_event(ctx, size.width as f32, size.height as f32),
+                WindowEvent::CloseRequested => {
+                    if !state.quit_event(ctx) {
+                        event::quit(ctx);
+                    }
+                }
+                WindowEvent::Focused(gained) => state.focus_event(ctx, gained),
+                WindowEvent::ReceivedCharacter(ch) => state.text_input_event(ctx, ch),
+                WindowEvent::DroppedFile(path) => state.app_event(ctx, AppEvent::FileDropped(path)),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: key_state,
+                            virtual_keycode: Some(keycode),
+                            ..
+                        },
+                    ..
+                } => {
+                    let mods = keyboard::active_mods(ctx);
+                    match key_state {
+                        ElementState::Pressed => {
+                            let repeat = keyboard::is_key_repeated(ctx);
+                            state.key_down_event(ctx, keycode, mods, repeat);
+                        }
+                        ElementState::Released => state.key_up_event(ctx, keycode, mods),
+                    }
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (x, y) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => (x, y),
+                        MouseScrollDelta::PixelDelta(position) => {
+                            let scale_factor = graphics::window(ctx).scale_factor();
+                            let position = position.to_logical::<f32>(scale_factor);
+                            (position.x, position.y)
+                        }
+                    };
+                    state.mouse_wheel_event(ctx, x, y);
+                }
+                WindowEvent::MouseInput {
+                    state: button_state,
+                    button,
+                    ..
+                } => {
+                    let position = mouse::position(ctx);
+                    match button_state {
+                        ElementState::Pressed => state.mouse_button_down_event(ctx, button, position.x, position.y),
+                        ElementState::Released => state.mouse_button_up_event(ctx, button, position.x, position.y),
+                    }
+                }
+                WindowEvent::CursorMoved { .. } => {
+                    let position = mouse::position(ctx);
+                    let (dx, dy) = (position.x - last_mouse.x, position.y - last_mouse.y);
+                    last_mouse = position;
+                    state.mouse_motion_event(ctx, position.x, position.y, dx, dy);
+                }
+                _ => {}
+            },
+            Event::MainEventsCleared => {
+                ctx.timer_context.tick();
+                if let Err(e) = state.update(ctx) {
+                    error!("Error on EventHandler::update(): {:?}", e);
+                    event::quit(ctx);
+                    return;
+                }
+                if let Err(e) = state.draw(ctx) {
+                    error!("Error on EventHandler::draw(): {:?}", e);
+                    event::quit(ctx);
+                }
+            }
+            _ => {}
+        }
+    })
+}
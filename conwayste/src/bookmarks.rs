@@ -0,0 +1,164 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Camera bookmarks: up to nine places on the board, with the zoom they were seen at, that the
+//! player can jump back to with a key. Each room, puzzle and the sandbox has its own set, kept in
+//! the bookmarks folder of the data directory so they are there again next time.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::user_data::Folder;
+
+const FILE_NAME: &str = "bookmarks.json";
+pub const BOOKMARK_SLOTS: usize = 9; // one for each of the keys 1 to 9
+pub const MAX_BOOKMARK_NAME_LEN: usize = 32; // characters
+
+/// Where the camera was.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name:      String,
+    pub center:    (f32, f32), // (column, row) in the middle of the screen, in cells
+    pub cell_size: f32,
+}
+
+/// What the player asked to do with a bookmark, by slot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookmarkRequest {
+    Save(usize),
+    Jump(usize),
+}
+
+/// Where bookmarks are kept apart from each other's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Place {
+    Sandbox,
+    Puzzle(String), // by puzzle ID
+    Room(String),   // by room name
+}
+
+impl Place {
+    fn key(&self) -> String {
+        match self {
+            Place::Sandbox => "sandbox".to_owned(),
+            Place::Puzzle(id) => format!("puzzle/{}", id),
+            Place::Room(name) => format!("room/{}", name),
+        }
+    }
+}
+
+/// Every place's bookmarks, by place and then slot, where slot 0 is jumped to with the 1 key.
+pub struct Bookmarks {
+    path:     PathBuf,
+    by_place: BTreeMap<String, BTreeMap<usize, Bookmark>>,
+}
+
+impl Bookmarks {
+    /// Reads the bookmarks saved so far. If they can't be read, it is as if there were none.
+    pub fn load() -> Self {
+        Bookmarks::load_from(Folder::Bookmarks.path().join(FILE_NAME))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let by_place = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                warn!("Could not read the camera bookmarks in {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read the camera bookmarks in {:?}: {}", path, e);
+                }
+                BTreeMap::new()
+            }
+        };
+        Bookmarks { path, by_place }
+    }
+
+    /// The bookmark in `slot` at `place`, if one was saved.
+    pub fn get(&self, place: &Place, slot: usize) -> Option<&Bookmark> {
+        self.by_place.get(&place.key()).and_then(|bookmarks| bookmarks.get(&slot))
+    }
+
+    /// Saves `bookmark` in `slot` at `place`, replacing any there. Saving failures are logged.
+    pub fn set(&mut self, place: &Place, slot: usize, bookmark: Bookmark) {
+        if slot >= BOOKMARK_SLOTS {
+            return;
+        }
+        self.by_place.entry(place.key()).or_default().insert(slot, bookmark);
+        self.save();
+    }
+
+    /// Renames the bookmark in `slot` at `place`, cut to `MAX_BOOKMARK_NAME_LEN` characters.
+    /// Returns false if there's no bookmark there.
+    pub fn rename(&mut self, place: &Place, slot: usize, name: &str) -> bool {
+        let bookmark = match self.by_place.get_mut(&place.key()).and_then(|bookmarks| bookmarks.get_mut(&slot)) {
+            Some(bookmark) => bookmark,
+            None => return false,
+        };
+        bookmark.name = name.chars().take(MAX_BOOKMARK_NAME_LEN).collect();
+        self.save();
+        true
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.write() {
+            warn!("Could not save the camera bookmarks to {:?}: {}", self.path, e);
+        }
+    }
+
+    fn write(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let bytes = serde_json::to_vec(&self.by_place).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_are_kept_per_place() {
+        let dir = std::env::temp_dir().join(format!("conwayste-bookmarks-test-{}", std::process::id()));
+        let path = dir.join(FILE_NAME);
+        let mut bookmarks = Bookmarks::load_from(path.clone());
+        let room = Place::Room("lobby of doom".to_owned());
+        let bookmark = Bookmark {
+            name:      "glider gun".to_owned(),
+            center:    (100.5, 40.0),
+            cell_size: 4.0,
+        };
+        bookmarks.set(&room, 2, bookmark.clone());
+        bookmarks.set(&room, BOOKMARK_SLOTS, bookmark);
+        assert!(!bookmarks.rename(&room, 3, "nothing here"));
+        assert!(bookmarks.rename(&Place::Room("lobby of doom".to_owned()), 2, "the gun"));
+
+        let bookmarks = Bookmarks::load_from(path);
+        assert_eq!(bookmarks.get(&room, 2).map(|bookmark| bookmark.name.as_str()), Some("the gun"));
+        assert_eq!(bookmarks.get(&room, 2).map(|bookmark| bookmark.center), Some((100.5, 40.0)));
+        assert_eq!(bookmarks.get(&room, BOOKMARK_SLOTS), None);
+        assert_eq!(bookmarks.get(&Place::Sandbox, 2), None);
+        assert_eq!(bookmarks.get(&Place::Puzzle("spark".to_owned()), 2), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Buffering of the universe diffs the server sends. Normally each frame applies whatever arrived
+//! since the last one, but after the client stalls (a long frame, or the window being in the
+//! background) many diffs can arrive at once. Applying them all in one frame would be another
+//! stall, so they are played back a few per frame until the client is caught up. If so many pile
+//! up that the buffer overflows, they are thrown away and the server is asked for the whole
+//! universe again.
+
+use std::collections::VecDeque;
+
+use netwayste::net::GenStateDiffPart;
+
+/// More diffs than this waiting means the client has fallen behind.
+const CATCH_UP_THRESHOLD: usize = 2;
+
+/// How many diffs are applied per frame while catching up.
+const CATCH_UP_DIFFS_PER_FRAME: usize = 4;
+
+/// Past this many waiting diffs, catching up would take too long, so a resync is needed.
+const MAX_BUFFERED_DIFFS: usize = 120;
+
+pub struct DiffBuffer {
+    diffs:    VecDeque<GenStateDiffPart>,
+    catching: bool, // still working through a backlog; cleared once it is down to the threshold
+}
+
+impl DiffBuffer {
+    pub fn new() -> Self {
+        DiffBuffer {
+            diffs:    VecDeque::new(),
+            catching: false,
+        }
+    }
+
+    /// Adds a diff from the server. Returns false if the buffer overflowed, in which case it is
+    /// emptied and the caller should ask the server to resync.
+    pub fn push(&mut self, diff: GenStateDiffPart) -> bool {
+        if self.diffs.len() >= MAX_BUFFERED_DIFFS {
+            warn!(
+                "{} universe diffs are waiting to be applied; starting over",
+                self.diffs.len()
+            );
+            self.clear();
+            return false;
+        }
+        self.diffs.push_back(diff);
+        true
+    }
+
+    /// The diffs to apply this frame, oldest first.
+    pub fn take_for_frame(&mut self) -> Vec<GenStateDiffPart> {
+        if self.diffs.len() > CATCH_UP_THRESHOLD {
+            self.catching = true;
+        }
+        let count = if self.catching {
+            CATCH_UP_DIFFS_PER_FRAME.min(self.diffs.len())
+        } else {
+            self.diffs.len()
+        };
+        let diffs = self.diffs.drain(..count).collect();
+        if self.diffs.len() <= CATCH_UP_THRESHOLD {
+            self.catching = false;
+        }
+        diffs
+    }
+
+    /// How many generations the newest waiting diff is ahead of `latest_gen`, while catching up.
+    pub fn behind(&self, latest_gen: usize) -> Option<u32> {
+        if !self.catching {
+            return None;
+        }
+        self.diffs
+            .back()
+            .map(|diff| diff.gen1.saturating_sub(latest_gen as u32))
+    }
+
+    pub fn clear(&mut self) {
+        self.diffs.clear();
+        self.catching = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn diff(gen0: u32, gen1: u32) -> GenStateDiffPart {
+        GenStateDiffPart::new(0, 1, gen0, gen1, "!".to_owned())
+    }
+
+    #[test]
+    fn test_diffs_on_time_are_all_applied() {
+        let mut buffer = DiffBuffer::new();
+        assert!(buffer.push(diff(1, 2)));
+        assert!(buffer.push(diff(2, 3)));
+        assert_eq!(buffer.take_for_frame().len(), 2);
+        assert_eq!(buffer.behind(3), None);
+        assert!(buffer.take_for_frame().is_empty());
+    }
+
+    #[test]
+    fn test_backlog_is_played_back_a_few_per_frame() {
+        let mut buffer = DiffBuffer::new();
+        for gen in 1..11 {
+            assert!(buffer.push(diff(gen, gen + 1)));
+        }
+        let first = buffer.take_for_frame();
+        assert_eq!(first.len(), CATCH_UP_DIFFS_PER_FRAME);
+        assert_eq!(first[0].gen0, 1);
+        assert_eq!(buffer.behind(5), Some(6));
+
+        let second = buffer.take_for_frame();
+        assert_eq!(second.len(), CATCH_UP_DIFFS_PER_FRAME);
+        assert_eq!(buffer.behind(9), None); // down to the threshold
+        assert_eq!(buffer.take_for_frame().len(), 2);
+    }
+
+    #[test]
+    fn test_overflow_empties_the_buffer() {
+        let mut buffer = DiffBuffer::new();
+        for gen in 0..MAX_BUFFERED_DIFFS as u32 {
+            assert!(buffer.push(diff(gen, gen + 1)));
+        }
+        assert!(!buffer.push(diff(200, 201)));
+        assert!(buffer.take_for_frame().is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Drawing cells as instances of a single 1x1 white sprite, each scaled to the cell's rect and
+//! tinted with its color, so that every cell on screen goes to the GPU in one draw call.
+
+use ggez::graphics::{self, spritebatch::SpriteBatch, Color, DrawParam, Image, Rect};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use crate::constants::colors::WHITE;
+
+/// How the cells of the universe are put on screen. All draw the same picture; switching between
+/// them from the console shows what each buys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellRendering {
+    /// A mesh for each chunk of cells on screen, kept until a cell in it changes (see
+    /// `ChunkCache`), with the pattern being inserted in a batch over them.
+    Chunked,
+    /// One batch kept from frame to frame, holding only the cells on screen and, on top of them,
+    /// the pattern being inserted.
+    Instanced,
+    /// A new sprite and batch every frame, holding every cell in the universe, with a second one
+    /// for the pattern being inserted.
+    PerFrame,
+}
+
+impl CellRendering {
+    pub fn name(self) -> &'static str {
+        match self {
+            CellRendering::Chunked => "chunked",
+            CellRendering::Instanced => "instanced",
+            CellRendering::PerFrame => "per-frame",
+        }
+    }
+}
+
+/// Colored rects to be drawn together, in order.
+pub struct CellBatch {
+    batch:   SpriteBatch,
+    sprites: usize,
+}
+
+impl CellBatch {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let image = Image::solid(ctx, 1u16, WHITE)?; // 1x1 square
+        Ok(CellBatch {
+            batch:   SpriteBatch::new(image),
+            sprites: 0,
+        })
+    }
+
+    /// Empties the batch, keeping what it has allocated for the next frame.
+    pub fn clear(&mut self) {
+        self.batch.clear();
+        self.sprites = 0;
+    }
+
+    pub fn add_rect(&mut self, rect: Rect, color: Color) {
+        let param = DrawParam::new()
+            .dest(Point2 { x: rect.x, y: rect.y })
+            .scale(Vector2 { x: rect.w, y: rect.h })
+            .color(color);
+        self.batch.add(param);
+        self.sprites += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites
+    }
+
+    pub fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult<()> {
+        graphics::draw(ctx, &self.batch, param)
+    }
+}
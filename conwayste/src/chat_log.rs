@@ -0,0 +1,94 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Keeps a record of chat on disk, when the `log_chat` gameplay setting is on. Each day's chat is
+//! appended to its own file in the chat log directory, one message per line; the line breaks in a
+//! multi-line message are written as `⏎`.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use netwayste::net::ChatChannel;
+
+use crate::user_data::Folder;
+
+/// How the conversation a message was part of is marked in the log.
+fn label(channel: ChatChannel) -> &'static str {
+    match channel {
+        ChatChannel::Room => "room",
+        ChatChannel::Team => "team",
+        ChatChannel::Lobby => "lobby",
+    }
+}
+
+/// The file that chat sent at `time` is appended to.
+fn chat_log_path(dir: &Path, time: &DateTime<Local>) -> PathBuf {
+    dir.join(format!("{}.log", time.format("%Y-%m-%d")))
+}
+
+fn append_to(dir: &Path, channel: ChatChannel, sender: &str, message: &str, time: DateTime<Local>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(chat_log_path(dir, &time))?;
+    writeln!(
+        file,
+        "{} [{}] {}: {}",
+        time.format("%H:%M:%S"),
+        label(channel),
+        sender,
+        message.replace('\n', " ⏎ ")
+    )
+}
+
+/// Appends a message sent at `time` to that day's chat log. Failures are logged rather than
+/// returned, since losing a line of the log shouldn't interrupt the chat.
+pub fn append(channel: ChatChannel, sender: &str, message: &str, time: DateTime<Local>) {
+    let dir = Folder::Chat.path();
+    if let Err(e) = append_to(&dir, channel, sender, message, time) {
+        warn!("Could not write to the chat log in {:?}: {}", dir, e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_append_to_starts_a_file_each_day() {
+        let dir = std::env::temp_dir().join(format!("conwayste-chat-log-test-{}", std::process::id()));
+        let evening = Local.ymd(2021, 6, 1).and_hms(23, 59, 30);
+        let morning = Local.ymd(2021, 6, 2).and_hms(0, 0, 5);
+        append_to(&dir, ChatChannel::Room, "alice", "good night", evening).unwrap();
+        append_to(&dir, ChatChannel::Lobby, "bob", "anyone up?", morning).unwrap();
+        append_to(&dir, ChatChannel::Team, "alice", "flank left\nthen up", morning).unwrap();
+
+        let first_day = fs::read_to_string(dir.join("2021-06-01.log")).unwrap();
+        assert_eq!(first_day, "23:59:30 [room] alice: good night\n");
+        let second_day = fs::read_to_string(dir.join("2021-06-02.log")).unwrap();
+        assert_eq!(
+            second_day,
+            "00:00:05 [lobby] bob: anyone up?\n00:00:05 [team] alice: flank left ⏎ then up\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Drawing the universe as square chunks of cells, each with a mesh that is kept until a cell in
+//! it changes. Panning only moves where the meshes are drawn, and a universe where little is
+//! going on rebuilds few of them, so drawing costs about one draw call per chunk on screen rather
+//! than work for every cell.
+
+use std::collections::{BTreeMap, HashMap};
+
+use conway::universe::{CellState, Region, Universe};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use crate::colorblind::CellMark;
+use crate::viewport::GridView;
+
+const CHUNK_SIZE: usize = 64; // cells along each side; a whole word of each row of the universe
+const REBUILD_ZOOM_RATIO: f32 = 1.25; // meshes are scaled to the cell size until it is this far off
+const EVICT_AFTER_FRAMES: u64 = 600; // chunks not on screen for this long are dropped
+
+struct Chunk {
+    fingerprint: u64,          // of the cells the mesh was built from; see `Universe::region_fingerprint`
+    cell_size:   f32,          // the mesh was built for
+    mesh:        Option<Mesh>, // None if there's nothing in the chunk, as ggez can't make an empty mesh
+    cells:       usize,
+    last_drawn:  u64, // frame
+}
+
+/// The meshes of the chunks of one universe, and what they were built with.
+#[derive(Default)]
+pub struct ChunkCache {
+    chunks:     HashMap<(usize, usize), Chunk>, // by (column, row) of chunk
+    on_screen:  Vec<(usize, usize)>,            // chunks to draw this frame
+    colors:     BTreeMap<CellState, Color>,
+    marks:      bool,
+    visibility: Option<usize>,
+    frame:      u64,
+}
+
+impl ChunkCache {
+    pub fn new() -> Self {
+        ChunkCache::default()
+    }
+
+    /// Rebuilds the meshes of the chunks on screen whose cells changed, or that were built at too
+    /// different a zoom, and notes which to draw. Returns how many cells are in those chunks.
+    pub fn update(
+        &mut self,
+        ctx: &mut Context,
+        universe: &Universe,
+        viewport: &GridView,
+        visibility: Option<usize>,
+        colors: &BTreeMap<CellState, Color>,
+        marks: bool,
+    ) -> GameResult<usize> {
+        if self.colors != *colors || self.marks != marks || self.visibility != visibility {
+            self.chunks.clear();
+            self.colors = colors.clone();
+            self.marks = marks;
+            self.visibility = visibility;
+        }
+        self.frame += 1;
+        self.on_screen.clear();
+
+        let visible = match viewport.visible_cells().intersection(universe.region()) {
+            Some(visible) => visible,
+            None => return Ok(0),
+        };
+        let cell_size = viewport.get_cell_size();
+        let mut cells = 0;
+        for chunk_row in visible.top() as usize / CHUNK_SIZE..=visible.bottom() as usize / CHUNK_SIZE {
+            for chunk_col in visible.left() as usize / CHUNK_SIZE..=visible.right() as usize / CHUNK_SIZE {
+                let region = match chunk_region(chunk_col, chunk_row).intersection(universe.region()) {
+                    Some(region) => region,
+                    None => continue,
+                };
+                let fingerprint = universe.region_fingerprint(region, visibility);
+                let stale = match self.chunks.get(&(chunk_col, chunk_row)) {
+                    Some(chunk) => {
+                        let ratio = cell_size / chunk.cell_size;
+                        chunk.fingerprint != fingerprint
+                            || ratio >= REBUILD_ZOOM_RATIO
+                            || ratio <= 1.0 / REBUILD_ZOOM_RATIO
+                    }
+                    None => true,
+                };
+                if stale {
+                    let chunk = self.build(ctx, universe, (chunk_col, chunk_row), region, fingerprint, cell_size)?;
+                    self.chunks.insert((chunk_col, chunk_row), chunk);
+                }
+                let chunk = self.chunks.get_mut(&(chunk_col, chunk_row)).unwrap(); // inserted above if missing
+                chunk.last_drawn = self.frame;
+                cells += chunk.cells;
+                self.on_screen.push((chunk_col, chunk_row));
+            }
+        }
+
+        let frame = self.frame;
+        self.chunks
+            .retain(|_, chunk| frame - chunk.last_drawn < EVICT_AFTER_FRAMES);
+        Ok(cells)
+    }
+
+    /// Draws the chunks on screen, where `viewport` now puts them.
+    pub fn draw(&self, ctx: &mut Context, viewport: &GridView) -> GameResult<()> {
+        let cell_size = viewport.get_cell_size();
+        let origin = viewport.get_origin();
+        for &(chunk_col, chunk_row) in self.on_screen.iter() {
+            let chunk = &self.chunks[&(chunk_col, chunk_row)];
+            if let Some(ref mesh) = chunk.mesh {
+                let scale = cell_size / chunk.cell_size;
+                let dest = Point2 {
+                    x: origin.x + (chunk_col * CHUNK_SIZE) as f32 * cell_size,
+                    y: origin.y + (chunk_row * CHUNK_SIZE) as f32 * cell_size,
+                };
+                graphics::draw(
+                    ctx,
+                    mesh,
+                    DrawParam::new().dest(dest).scale(Vector2 { x: scale, y: scale }),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The mesh of the cells of `region`, the part of the chunk inside the universe, drawn
+    /// relative to the top left of the chunk.
+    fn build(
+        &self,
+        ctx: &mut Context,
+        universe: &Universe,
+        (chunk_col, chunk_row): (usize, usize),
+        region: Region,
+        fingerprint: u64,
+        cell_size: f32,
+    ) -> GameResult<Chunk> {
+        let (left, top) = (chunk_col * CHUNK_SIZE, chunk_row * CHUNK_SIZE);
+        let mut builder = MeshBuilder::new();
+        let mut cells = 0;
+        let mut result = Ok(());
+        universe.each_non_dead(region, self.visibility, &mut |col, row, state| {
+            if result.is_err() {
+                return;
+            }
+            let color = self.colors[&state];
+            // the 'minus one' leaves the lines of the grid between cells, as `GridView` does
+            let rect = Rect::new(
+                (col - left) as f32 * cell_size,
+                (row - top) as f32 * cell_size,
+                cell_size - 1.0,
+                cell_size - 1.0,
+            );
+            result = builder.rectangle(DrawMode::fill(), rect, color).map(|_| ());
+            cells += 1;
+
+            if let (true, CellState::Alive(Some(player_index))) = (self.marks, state) {
+                let mark_color = CellMark::color_on(color);
+                for mark_rect in CellMark::for_player(player_index).rects(rect) {
+                    if result.is_ok() {
+                        result = builder.rectangle(DrawMode::fill(), mark_rect, mark_color).map(|_| ());
+                    }
+                }
+            }
+        });
+        result?;
+
+        let mesh = if cells > 0 { Some(builder.build(ctx)?) } else { None };
+        Ok(Chunk {
+            fingerprint,
+            cell_size,
+            mesh,
+            cells,
+            last_drawn: self.frame,
+        })
+    }
+}
+
+fn chunk_region(chunk_col: usize, chunk_row: usize) -> Region {
+    Region::new(
+        (chunk_col * CHUNK_SIZE) as isize,
+        (chunk_row * CHUNK_SIZE) as isize,
+        CHUNK_SIZE,
+        CHUNK_SIZE,
+    )
+}
@@ -0,0 +1,175 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The client's command-line options. Those that are also settings take the place of the config
+//! file's for this run of the game only; see `config::Overrides`.
+
+use std::ffi::OsString;
+
+use clap::{App, Arg};
+
+use crate::config::Overrides;
+use crate::uri::Invite;
+
+/// What the game was asked to do on the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    pub connect:        Option<String>, // server to connect to, as host[:port] or ws://host[:port]
+    pub room:           Option<String>, // room to join once connected, from a conwayste:// link
+    pub config_path:    Option<String>, // config file to use instead of the usual one
+    pub overrides:      Overrides,
+    pub offline:        bool, // if true, nothing is sent over the internet
+    pub register_links: bool, // if true, conwayste:// links are made to open the game, which then exits
+}
+
+impl Args {
+    /// Parses the game's own command line, exiting with a message if it can't be parsed, or with
+    /// the help or version if asked for.
+    pub fn from_command_line() -> Args {
+        Args::parse(std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Parses `args`, the first of which is the program name.
+    pub fn parse<I, T>(args: I) -> Result<Args, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = App::new("conwayste")
+            .version(version!())
+            .about("multiplayer Conway's Game of Life")
+            .arg(
+                Arg::with_name("connect")
+                    .short("c")
+                    .long("connect")
+                    .value_name("HOST:PORT")
+                    .help("server to connect to; a port of 2016 is assumed if none is given")
+                    .takes_value(true)
+                    .validator(|server| validate_server(&server)),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .short("n")
+                    .long("name")
+                    .help("profile to play as, or a name to play under, for this run only")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("fullscreen")
+                    .short("f")
+                    .long("fullscreen")
+                    .help("start in fullscreen, for this run only"),
+            )
+            .arg(
+                Arg::with_name("config")
+                    .long("config")
+                    .value_name("PATH")
+                    .help("TOML file to read and save settings to, instead of the usual conwayste.toml")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("offline")
+                    .long("offline")
+                    .help("connect to nothing: no server, chat bridge, Discord or pattern downloads")
+                    .conflicts_with("connect"),
+            )
+            .arg(
+                Arg::with_name("link")
+                    .value_name("conwayste://HOST:PORT/ROOM")
+                    .help("link to a room on a server, to connect to it and join that room")
+                    .conflicts_with_all(&["connect", "offline"])
+                    .validator(|link| Invite::parse(&link).map(|_| ())),
+            )
+            .arg(
+                Arg::with_name("register-links")
+                    .long("register-links")
+                    .help("make conwayste:// links in web pages and chat open this game, then exit"),
+            )
+            .get_matches_from_safe(args)?;
+
+        let offline = matches.is_present("offline");
+        let mut overrides = Overrides {
+            name:       matches.value_of("name").map(|name| name.to_owned()),
+            fullscreen: Some(true).filter(|_| matches.is_present("fullscreen")),
+            ..Overrides::default()
+        };
+        if offline {
+            overrides.chat_bridge = Some(false);
+            overrides.discord = Some(false);
+        }
+        // unwrap OK because the validator already parsed it
+        let invite = matches.value_of("link").map(|link| Invite::parse(link).unwrap());
+        Ok(Args {
+            connect: matches
+                .value_of("connect")
+                .map(|server| server.to_owned())
+                .or_else(|| invite.as_ref().map(|invite| invite.server.clone())),
+            room: invite.and_then(|invite| invite.room),
+            config_path: matches.value_of("config").map(|path| path.to_owned()),
+            overrides,
+            offline,
+            register_links: matches.is_present("register-links"),
+        })
+    }
+}
+
+/// Checks that `server` is a host with an optional port, as `--connect` takes it.
+pub fn validate_server(server: &str) -> Result<(), String> {
+    let host = server.strip_prefix("ws://").unwrap_or(server).trim_end_matches('/');
+    let host = match host.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_err() => return Err(format!("bad port: {}", port)),
+        Some((host, _)) => host,
+        None => host,
+    };
+    if host.is_empty() {
+        return Err("no host given".to_owned());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(Args::parse(["conwayste"]).unwrap(), Args::default());
+
+        let args = Args::parse(["conwayste", "--connect", "localhost:2016", "--name", "Bob", "-f"]).unwrap();
+        assert_eq!(args.connect.as_deref(), Some("localhost:2016"));
+        assert_eq!(args.overrides.name.as_deref(), Some("Bob"));
+        assert_eq!(args.overrides.fullscreen, Some(true));
+        assert_eq!(args.overrides.discord, None);
+
+        let args = Args::parse(["conwayste", "--offline", "--config", "/tmp/c.toml"]).unwrap();
+        assert!(args.offline);
+        assert_eq!(args.config_path.as_deref(), Some("/tmp/c.toml"));
+        assert_eq!(args.overrides.chat_bridge, Some(false));
+        assert_eq!(args.overrides.discord, Some(false));
+
+        assert!(Args::parse(["conwayste", "--offline", "--connect", "localhost"]).is_err());
+        assert!(Args::parse(["conwayste", "--connect", "localhost:http"]).is_err());
+        assert!(Args::parse(["conwayste", "--connect", ":2016"]).is_err());
+
+        let args = Args::parse(["conwayste", "conwayste://localhost:2016/glider%20club"]).unwrap();
+        assert_eq!(args.connect.as_deref(), Some("localhost:2016"));
+        assert_eq!(args.room.as_deref(), Some("glider club"));
+        assert!(Args::parse(["conwayste", "--offline", "conwayste://localhost/general"]).is_err());
+        assert!(Args::parse(["conwayste", "localhost:2016"]).is_err());
+    }
+}
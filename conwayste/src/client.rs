@@ -28,7 +28,6 @@ extern crate conway;
 extern crate custom_error;
 #[macro_use]
 extern crate downcast_rs;
-extern crate env_logger;
 extern crate ggez;
 #[macro_use]
 extern crate log;
@@ -42,52 +41,120 @@ extern crate rand;
 extern crate lazy_static;
 extern crate chromatica;
 
+mod accessibility;
+mod annotations;
+mod app_event;
+mod bookmarks;
+mod catch_up;
+mod cell_batch;
+mod chat_log;
+mod chunk_cache;
+mod cli;
+mod colorblind;
 mod config;
+mod config_watcher;
+mod console;
 mod constants;
+mod crash;
+mod debug_overlay;
+mod density;
+mod discord;
+mod effects;
 #[macro_use]
 mod error;
+mod follow_camera;
+mod help;
+mod history;
+mod i18n;
 mod input;
+mod irc_bridge;
+mod keymap;
+mod layout_file;
+mod loader;
+mod lockstep;
+mod logging;
+mod menu_background;
 mod network;
+mod notifications;
+mod pattern_editor;
+mod pattern_import;
+mod pattern_library;
+mod prediction;
+mod puzzle;
+mod resources;
+mod room_map;
+mod room_settings;
+mod scripting;
+mod symmetry;
+mod theme;
+mod tutorial;
 mod ui;
 mod uilayout;
+mod uri;
+mod user_data;
 mod video;
 mod viewport;
 
-use chrono::Local;
-use log::LevelFilter;
-
-use conway::grids::CharGrid;
+use conway::grids::{BitGrid, BitOperation, CharGrid};
+use conway::rle::PatternFile;
+use conway::script::Script;
 use conway::universe::{BigBang, CellState, PlayerBuilder, Region, Universe};
-use netwayste::net::NetwaysteEvent;
+use netwayste::net::{
+    ChatChannel, GameOutcome, LeaderboardEntry, NetRegion, NetwaysteEvent, PlayerInfo, PlayerScore, RgbColor,
+    SharedPatternInfo, MAX_SET_CELLS, MAX_SHARED_PATTERN_LEN, MAX_SPAWN_REGIONS, SHARED_PATTERN_PAGE_LEN,
+    TYPING_NOTICE_INTERVAL_MS,
+};
 
 use ggez::conf;
 use ggez::event::*;
-use ggez::graphics::{self, Color, DrawParam, Font};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font};
 use ggez::mint::{Point2, Vector2};
 use ggez::timer;
 use ggez::{Context, ContextBuilder, GameError, GameResult};
 
+use id_tree::NodeId;
+
 use rand::Rng;
 
-use std::collections::BTreeMap;
-use std::env;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
 use std::error::Error;
-use std::io::Write; // For env logger
+use std::fs;
 use std::path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use annotations::AnnotationTool;
+use app_event::{AppEvent, AppEventHandler};
+use bookmarks::{Bookmark, BookmarkRequest, Place};
+use cell_batch::{CellBatch, CellRendering};
+use chunk_cache::ChunkCache;
 use constants::{
-    colors::*, DrawStyle, DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, DEFAULT_ZOOM_LEVEL, GRID_DRAW_STYLE,
-    INTRO_DURATION, INTRO_PAUSE_DURATION,
+    colors::*, BRIDGE_CHAT_TAB, CURRENT_PLAYER_ID, DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, DEFAULT_ZOOM_LEVEL,
+    GRID_DRAW_STYLE, IDLE_FRAME_SLEEP, IDLE_REDRAW_INTERVAL, INTRO_DURATION, INTRO_LOGO_FADE_IN, INTRO_LOGO_HEIGHT,
+    INTRO_PAUSE_DURATION, LOBBY_CHAT_TAB, MIN_GRID_LINE_CELL_SIZE, PROGRESS_BAR_WIDTH, ROOM_CHAT_TAB, SCRIPTS_DIR,
+    SCRIPT_FILE_EXTENSION, STATS_BAR_WIDTH, STATS_CHART_HEIGHT, TOAST_DURATION, VIEWPORT_REPORT_INTERVAL,
 };
-use input::{MouseAction, ScrollEvent};
+use discord::DiscordEvent;
+use follow_camera::Follow;
+use i18n::{tr, tr_args};
+use input::MouseAction;
+use irc_bridge::{BridgeEvent, ChatBridge};
+use keymap::{KeyAction, Near, Scope};
+use notifications::Notice;
+use pattern_import::DroppedFile;
+use pattern_library::{Category, LibraryPattern};
+use resources::Texture;
+use symmetry::Symmetry;
 use ui::{
+    common::FontInfo,
     context::{EmitEvent, Event, Handled, Handler, UIContext},
-    Chatbox, ChatboxPublishHandle, EventType, GameArea, GameAreaState, TextField,
+    Button, Chatbox, ChatboxPublishHandle, ColorPicker, Completer, EventType, GameArea, GameAreaState, Pane,
+    PlayerList, SenderAction, TextField, Widget,
 };
-use uilayout::{StaticNodeIds, UILayout};
+use uilayout::{SharedPatternsRequest, StaticNodeIds, UILayout};
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub enum Screen {
@@ -96,8 +163,16 @@ pub enum Screen {
     Options,
     ServerList,
     InRoom,
-    Run,  // TODO: break it out more to indicate whether waiting for game or playing game
-    Exit, // We're getting ready to quit the game, WRAP IT UP SON
+    Results,        // Final standings of a networked game
+    Stats,          // Summary of the games played
+    Leaderboards,   // The server's best players
+    Puzzles,        // Picking a single-player puzzle
+    PatternEditor,  // Drawing patterns for the pattern library
+    PatternLibrary, // Browsing the patterns to place in the game
+    SharedPatterns, // Browsing and sharing the patterns shared on the server
+    PatternImport,  // Downloading a pattern from a URL into the pattern library
+    Run,            // TODO: break it out more to indicate whether waiting for game or playing game
+    Exit,           // We're getting ready to quit the game, WRAP IT UP SON
 }
 
 // All game state
@@ -114,26 +189,167 @@ struct MainState {
     intro_viewport:     viewport::GridView,
     inputs:             input::InputManager,
     net_worker:         Arc<Mutex<Option<network::ConwaysteNetWorker>>>,
+    server:             Option<String>, // the server net_worker talks to, as given on the command line
+    invited_room:       Option<String>, // joined on logging in instead of "general"; from a conwayste:// link
+    offline:            bool,           // if true, nothing is sent over the internet
     recvd_first_resize: bool, // work around an apparent ggez bug where the first resize event is bogus
+    window_focused:     bool, // desktop notifications are only shown while it isn't
 
     // if Some(...), dragging doesn't draw anything
     current_intro_duration: f64,
+    registry:               loader::Registry, // resources, loaded while the intro plays
 
     ui_layout:       UILayout,
     static_node_ids: StaticNodeIds,
+
+    // Networked game scoring
+    scores:       Vec<PlayerScore>,    // latest standings of the running game, best first
+    game_outcome: Option<GameOutcome>, // outcome of the most recently finished game or series
+    series_over:  bool,                // whether game_outcome is for a whole series
+    intermission: Option<(u8, u8, Vec<PlayerScore>)>, // (round, total rounds, series standings)
+
+    // Record of the networked games played to the end
+    room_name:     Option<String>,                                  // the room we're in
+    team:          Option<u8>,                                      // the team we joined in that room, if any
+    current_match: Option<history::MatchInProgress>,                // the game being played, recorded when it ends
+    history:       history::History,                                // for the Stats screen
+    leaderboards:  BTreeMap<Option<String>, Vec<LeaderboardEntry>>, // by room name, or None for the server
+
+    // Single-player puzzles
+    puzzles:         Vec<puzzle::Puzzle>,
+    puzzle_progress: puzzle::Progress,
+    picked_puzzle:   Arc<Mutex<Option<usize>>>, // set by the buttons on the puzzle select screen
+    puzzle_attempt:  Option<puzzle::Attempt>,   // the puzzle being played
+    sandbox_uni:     Option<Universe>,          // the game area's own universe, put back after the puzzle
+
+    // Pattern library
+    library:        Vec<(LibraryPattern, Option<Texture>)>, // loaded each time the library is opened
+    picked_pattern: Arc<Mutex<Option<usize>>>,              // set by the buttons of the library's list
+    library_list:   Option<(String, Option<Category>, NodeId)>, // the search and category listed, and the list
+    thumbnails_due: bool,                                   // some of the library's thumbnails are loading
+    stamp:          Option<(String, PatternFile)>,          // (name, pattern) placed by clicking in the game
+    import:         Option<pattern_import::Download>,       // the pattern being downloaded, if any
+
+    // Patterns shared on the server
+    shared_page:    Option<(u32, u32, Vec<SharedPatternInfo>)>, // (offset, total, patterns) shown; None while asking
+    shared_list_id: Option<NodeId>,                             // the list of shared_page, once added
+    picked_shared:  Arc<Mutex<Option<usize>>>,                  // set by the buttons of the list
+
+    // Everyone in the room we know of, for completing names in chat
+    room_players: Arc<Mutex<Vec<String>>>,
+    roster:       Vec<PlayerInfo>, // everyone in the room, including us and bots, as the server last listed them
+    room_colors:  BTreeMap<String, Color>, // the colors the server gave players in the room, by name
+
+    // A new label for the player's newest marker, set by typing "/label <text>" in the chat
+    marker_label: Arc<Mutex<Option<String>>>,
+
+    // The bridge to a chat channel outside the game, such as a Twitch channel's
+    chat_bridge:         Arc<Mutex<Option<ChatBridge>>>,     // shared with the chat TextField
+    chat_bridge_applied: Option<config::ChatBridgeSettings>, // the settings last started with; None while off
+
+    // Discord Rich Presence, while turned on in the config
+    discord:          Option<(String, discord::DiscordPresence)>, // (application ID, connection)
+    discord_requests: VecDeque<discord::JoinRequest>,            // friends asking to join, oldest first
+    discord_prompt:   Option<(Screen, NodeId)>,                  // pane asking about the oldest, and its screen
+    discord_answer:   Arc<Mutex<Option<bool>>>,                  // set by the prompt's buttons; true to let them in
+
+    // Camera bookmarks, for each room and puzzle
+    bookmarks:     bookmarks::Bookmarks,
+    bookmark_name: Arc<Mutex<Option<String>>>, // set by typing "/bookmark <name>" in the chat
+    last_bookmark: Option<(Place, usize)>,     // (place, slot) last saved or gone to, which that renames
+    follow_camera: follow_camera::FollowCamera, // pans by itself to keep recent births on screen, once asked to
+
+    // The server only sends the part of the universe around what is on screen
+    in_room:           bool,
+    reported_viewport: Option<(Region, Instant)>, // (visible cells, when sent) last told to the server
+    universe_progress: Option<(u8, u8)>,          // (parts received, total parts) of a universe still arriving
+    net_game:          Option<prediction::Prediction>, // the running networked game, with our own changes made early
+    net_writable:      Option<Region>,            // where we may place cells in net_game; None if only watching
+    net_diffs:         catch_up::DiffBuffer,      // universe diffs from the server not yet applied to net_game
+
+    // Lockstep verification of networked games, turned on from the developer console
+    lockstep_enabled: bool,
+    lockstep:         Option<lockstep::LockstepCheck>, // Some once a game has started while enabled
+    show_lockstep:    bool,                            // draw the check's universe in place of the game
+
+    cell_batch:     RefCell<CellBatch>, // kept across frames; borrowed while drawing with &self
+    chunk_cache:    RefCell<ChunkCache>, // likewise
+    effects:        RefCell<effects::PostProcessing>, // likewise
+    trails:         RefCell<effects::Trails>, // likewise
+    cell_rendering: CellRendering,      // switched from the console to compare the ways
+
+    // User scripts; only those enabled in the config are run
+    scripts:            Vec<Script>,
+    last_scripted_gen:  Option<usize>,
+    chatbox_pub_handle: ChatboxPublishHandle, // for chat messages sent by scripts
+    custom_events:      Vec<Event>,           // from game code and scripts, for the widgets of the current screen
+
+    // Live reloading of the config file
+    config_watcher: Option<config_watcher::ConfigWatcher>,
+    layout_watcher: Option<layout_file::LayoutWatcher>, // debug builds only
+    toast:          Option<(String, Instant)>,          // (message, when shown); drawn briefly over every screen
+    last_drawn:     Option<(Screen, Instant)>,          // for skipping frames of a menu where nothing changes
+
+    // Where the parts of the in-game HUD go, from the HUD layout for streamer mode while it's on
+    hud:         layout_file::HudLayout,
+    hud_applied: Option<(bool, (f32, f32))>, // (streamer mode, screen size) the chat was last placed for
+    hud_hidden:  bool,                       // toggled with F4, for captures of nothing but the board
+
+    menu_look:       MenuLook,
+    menu_background: menu_background::MenuBackground,
+    announcer:       accessibility::Announcer,
+
+    debug_overlay: debug_overlay::DebugOverlay, // toggled with F3
+    help:          help::HelpOverlay,           // toggled with F1
+    tutorial:      tutorial::Tutorial,          // shown in the first game a player starts
+    console:       console::Console,            // toggled with the backtick key
+    inspector:     ui::WidgetInspector,         // toggled from the console
+    cursor:        ui::CursorManager,           // shows the cursor suiting the widget under the mouse
+    crash_prompt:  Option<NodeId>,              // pane asking whether to keep the crash reports of earlier runs
+}
+
+/// The settings the menus were last built with. When any of them changes in the config, the menus
+/// are rebuilt.
+#[derive(PartialEq)]
+struct MenuLook {
+    language:      String, // as set in the config, even if there's no such language
+    high_contrast: bool,
+    min_font_size: f32,
+}
+
+impl MenuLook {
+    fn from_config(config: &config::Config) -> Self {
+        let settings = config.get();
+        MenuLook {
+            language:      settings.user.language.clone(),
+            high_contrast: settings.accessibility.high_contrast,
+            min_font_size: settings.accessibility.min_font_size,
+        }
+    }
+
+    /// Makes these settings the ones that `tr`, the color scheme, and new fonts use.
+    fn apply(&self) {
+        if !i18n::set_language(&self.language) {
+            warn!("Unknown language {:?}; keeping the current one", self.language);
+        }
+        set_high_contrast(self.high_contrast);
+        ui::set_min_font_size(self.min_font_size);
+    }
 }
 
 // Support non-alive/dead/bg colors
 struct ColorSettings {
-    cell_colors: BTreeMap<CellState, Color>,
-    background:  Color,
+    cell_colors:   BTreeMap<CellState, Color>,
+    board:         theme::BoardColors,
+    player_colors: BTreeMap<usize, Color>, // as chosen, before the palette is applied
+    palette:       colorblind::Palette,
 }
 
 impl ColorSettings {
     fn get_color(&self, cell_or_none: Option<CellState>) -> Color {
         match cell_or_none {
             Some(cell) => self.cell_colors[&cell],
-            None => self.background,
+            None => self.board.background,
         }
     }
 
@@ -147,11 +363,93 @@ impl ColorSettings {
         let mut iter = colors.into_iter();
         Color::new(iter.next().unwrap(), iter.next().unwrap(), iter.next().unwrap(), 1.0)
     }
+
+    /// Colors the board, and the cells of no player, as a board theme does.
+    fn set_board_colors(&mut self, board: theme::BoardColors) {
+        self.board = board;
+        self.cell_colors.insert(CellState::Dead, board.dead);
+        self.cell_colors.insert(CellState::Alive(None), board.alive);
+        self.cell_colors.insert(CellState::Wall, board.wall);
+    }
+
+    /// Colors a player's cells with `color`, or the closest color in the palette.
+    fn set_player_color(&mut self, player_index: usize, color: Color) {
+        self.player_colors.insert(player_index, color);
+        self.cell_colors
+            .insert(CellState::Alive(Some(player_index)), self.palette.remap(color));
+    }
+
+    /// Switches to another palette, recoloring every player.
+    fn set_palette(&mut self, palette: colorblind::Palette) {
+        self.palette = palette;
+        for (&player_index, &color) in self.player_colors.iter() {
+            self.cell_colors
+                .insert(CellState::Alive(Some(player_index)), palette.remap(color));
+        }
+    }
+
+    /// Colors each player's cells with a shade from their team's color family. `teams` holds
+    /// (player index, team) pairs; members of the same team get successive shades.
+    fn apply_team_colors(&mut self, teams: &[(usize, u8)]) {
+        let mut members_colored: BTreeMap<u8, usize> = BTreeMap::new();
+        for &(player_index, team) in teams {
+            let family = &TEAM_COLOR_FAMILIES[team as usize % TEAM_COLOR_FAMILIES.len()];
+            let shade = members_colored.entry(team).or_insert(0);
+            let color = family[*shade % family.len()];
+            *shade += 1;
+            self.set_player_color(player_index, color);
+        }
+    }
+}
+
+/// The key the pattern library is kept under in the registry.
+const LIBRARY_KEY: &str = "library";
+
+/// The key a library pattern's thumbnail is kept under in the registry: where it's kept in the
+/// thumbnails folder, which is named after the pattern file's contents.
+fn thumbnail_key(pattern: &LibraryPattern) -> String {
+    format!("thumbnail:{}", pattern.thumbnail_path().display())
+}
+
+/// `path` as it is shown on screen. Paths usually have the name of the player's account in them, so
+/// streamer mode shows a placeholder instead.
+fn shown_path(config: &config::Config, path: &path::Path) -> String {
+    if config.get().video.streamer_mode {
+        tr("streamer-hidden-path")
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// `server`'s address as it is shown on screen. Viewers could use it to find the player's games, so
+/// streamer mode shows a placeholder instead.
+fn shown_server(config: &config::Config, server: &str) -> String {
+    if config.get().video.streamer_mode {
+        tr("streamer-hidden-server")
+    } else {
+        server.to_owned()
+    }
+}
+
+/// Loads the config file given on the command line, or the usual one, with the settings given on
+/// the command line in place of the file's.
+fn load_config(args: &cli::Args) -> Result<config::Config, Box<dyn Error>> {
+    let mut config = match args.config_path {
+        Some(ref path) => config::Config::with_path(path.clone()),
+        None => config::Config::new(),
+    };
+    config.set_overrides(args.overrides.clone());
+    config.load_or_create_default()?;
+    Ok(config)
 }
 
 fn get_text_entered_handler(
     mut chatbox_pub_handle: ChatboxPublishHandle,
     net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>,
+    chatted: Arc<AtomicBool>, // set when a message is sent, for the tutorial
+    marker_label: Arc<Mutex<Option<String>>>,
+    bookmark_name: Arc<Mutex<Option<String>>>,
+    chat_bridge: Arc<Mutex<Option<ChatBridge>>>,
 ) -> Handler {
     Box::new(
         move |_obj: &mut dyn EmitEvent, uictx: &mut UIContext, evt: &Event| -> Result<Handled, Box<dyn Error>> {
@@ -160,18 +458,251 @@ fn get_text_entered_handler(
             if text.is_empty() {
                 return Ok(Handled::NotHandled);
             }
-            let msg = format!("{}: {}", username, text);
+            let tab = chatbox_pub_handle.shown_tab();
+            if text == "/chatlog" {
+                let dir = user_data::Folder::Chat.path();
+                if let Err(e) = user_data::open_folder(&dir) {
+                    let shown_dir = shown_path(uictx.config, &dir);
+                    let msg = tr_args("chat-log-open-failed", &[("path", &shown_dir), ("error", &e)]);
+                    chatbox_pub_handle.add_message_to_tab(tab, msg);
+                }
+                return Ok(Handled::NotHandled);
+            }
+            if let Some(label) = text.strip_prefix("/label ") {
+                *marker_label.lock().unwrap() = Some(label.trim().to_owned());
+                return Ok(Handled::NotHandled);
+            }
+            if let Some(name) = text.strip_prefix("/bookmark ") {
+                *bookmark_name.lock().unwrap() = Some(name.trim().to_owned());
+                return Ok(Handled::NotHandled);
+            }
+            if tab == BRIDGE_CHAT_TAB {
+                let msg = match *chat_bridge.lock().unwrap() {
+                    Some(ref bridge) if bridge.can_say() => {
+                        bridge.say(text);
+                        format!("{}: {}", username, text)
+                    }
+                    Some(ref bridge) => tr_args("chat-bridge-read-only", &[("channel", bridge.channel())]),
+                    None => tr("chat-bridge-off"),
+                };
+                chatbox_pub_handle.add_message_to_tab(tab, msg);
+                return Ok(Handled::NotHandled);
+            }
+            // What's typed in the lobby tab goes to everyone on the server, and "/team <message>"
+            // to teammates only
+            let (nw_event, msg, channel, logged_text) = if tab == LOBBY_CHAT_TAB {
+                (
+                    NetwaysteEvent::LobbyChatMessage(text.clone()),
+                    format!("{}: {}", username, text),
+                    ChatChannel::Lobby,
+                    text.as_str(),
+                )
+            } else if let Some(team_text) = text.strip_prefix("/team ") {
+                (
+                    NetwaysteEvent::TeamChatMessage(team_text.to_owned()),
+                    format!("[team] {}: {}", username, team_text),
+                    ChatChannel::Team,
+                    team_text,
+                )
+            } else {
+                (
+                    NetwaysteEvent::ChatMessage(text.clone()),
+                    format!("{}: {}", username, text),
+                    ChatChannel::Room,
+                    text.as_str(),
+                )
+            };
+
+            chatbox_pub_handle.add_message_to_tab(tab, msg);
+            chatted.store(true, Ordering::Relaxed);
+            if uictx.config.get().gameplay.log_chat {
+                chat_log::append(channel, &username, logged_text, chrono::Local::now());
+            }
 
-            chatbox_pub_handle.add_message(msg);
+            if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                netwayste.try_send(nw_event);
+            }
+            if channel == ChatChannel::Room && uictx.config.get().chat_bridge.relay {
+                if let Some(ref bridge) = *chat_bridge.lock().unwrap() {
+                    bridge.say(logged_text);
+                }
+            }
+            Ok(Handled::NotHandled)
+        },
+    )
+}
 
+/// Lets the room know the player is typing in the chat TextField. Only chat to the room counts, and
+/// notices go out no more often than the server passes them on.
+fn get_typing_handler(
+    chatbox_pub_handle: ChatboxPublishHandle,
+    net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>,
+) -> Handler {
+    let mut last_notice: Option<Instant> = None;
+    let interval = Duration::from_millis(TYPING_NOTICE_INTERVAL_MS);
+    Box::new(
+        move |obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            let tf = obj.downcast_ref::<TextField>().unwrap(); // unwrap OK because only a TextField has this handler
+            if !tf.is_focused() || chatbox_pub_handle.shown_tab() != ROOM_CHAT_TAB {
+                return Ok(Handled::NotHandled);
+            }
+            // Commands, including "/team", aren't for the whole room
+            match tf.text() {
+                Some(ref text) if !text.starts_with('/') => {}
+                _ => return Ok(Handled::NotHandled),
+            }
+            if last_notice.map_or(false, |at| at.elapsed() < interval) {
+                return Ok(Handled::NotHandled);
+            }
             if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
-                netwayste.try_send(NetwaysteEvent::ChatMessage(text.clone()));
+                netwayste.try_send(NetwaysteEvent::Typing);
+                last_notice = Some(Instant::now());
             }
             Ok(Handled::NotHandled)
         },
     )
 }
 
+/// Commands typed in the chat TextField that the client handles itself or turns into other requests.
+const CHAT_COMMANDS: [&str; 4] = ["/bookmark", "/chatlog", "/label", "/team"];
+
+/// Completes chat commands at the start of the line, and the names of players in the room anywhere.
+fn get_chat_completer(room_players: Arc<Mutex<Vec<String>>>) -> Completer {
+    Box::new(move |before_cursor: &str| -> Vec<String> {
+        let word = before_cursor.rsplit(char::is_whitespace).next().unwrap_or("");
+        if word.starts_with('/') && word.len() == before_cursor.len() {
+            return CHAT_COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| command.to_string())
+                .collect();
+        }
+        let word = word.to_lowercase();
+        let mut names: Vec<String> = room_players
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&word))
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    })
+}
+
+fn get_team_select_handler(team: u8, net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>) -> Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                netwayste.try_send(NetwaysteEvent::SetTeam(Some(team)));
+            }
+            Ok(Handled::Handled)
+        },
+    )
+}
+
+fn get_start_game_handler(net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>) -> Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                netwayste.try_send(NetwaysteEvent::StartGame);
+            }
+            Ok(Handled::Handled)
+        },
+    )
+}
+
+fn get_add_bot_handler(net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>) -> Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                netwayste.try_send(NetwaysteEvent::AddBot);
+            }
+            Ok(Handled::Handled)
+        },
+    )
+}
+
+/// Asks the server for the color just picked, and remembers it in the active profile. The server
+/// replies with the color actually assigned, which may differ.
+fn get_color_picked_handler(net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>) -> Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            let picker = obj.downcast_ref::<ColorPicker>().unwrap(); // unwrap OK because it's always a ColorPicker
+            let [r, g, b] = picker.color;
+            uictx.config.set_profile_color(picker.color);
+            if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                netwayste.try_send(NetwaysteEvent::SetColor(RgbColor::new(r, g, b)));
+            }
+            Ok(Handled::Handled)
+        },
+    )
+}
+
+/// Tells the GameArea whether the server is running the game it shows.
+fn set_game_area_networked(ui_layout: &mut UILayout, static_node_ids: &StaticNodeIds, networked: bool) {
+    match GameArea::widget_from_screen_and_id_mut(ui_layout, Screen::Run, &static_node_ids.game_area_id) {
+        Ok(game_area) => game_area.set_networked(networked),
+        Err(e) => error!("failed to look up GameArea widget: {:?}", e),
+    }
+}
+
+/// Connects the room lobby widgets to the network. This must be done again whenever the menus
+/// are rebuilt.
+fn hook_up_in_room_handlers(
+    ui_layout: &mut UILayout,
+    static_node_ids: &StaticNodeIds,
+    net_worker: &Arc<Mutex<Option<network::ConwaysteNetWorker>>>,
+) {
+    for (team, button_id) in static_node_ids.team_button_ids.iter().enumerate() {
+        let w = ui_layout
+            .get_screen_layering_mut(Screen::InRoom)
+            .unwrap()
+            .get_widget_mut(button_id)
+            .unwrap();
+        let button = w.downcast_mut::<Button>().unwrap(); // unwrap OK because we know these IDs are for Buttons
+        button
+            .on(
+                EventType::Click,
+                get_team_select_handler(team as u8, net_worker.clone()),
+            )
+            .unwrap(); // unwrap OK because not in handler
+    }
+    {
+        let w = ui_layout
+            .get_screen_layering_mut(Screen::InRoom)
+            .unwrap()
+            .get_widget_mut(&static_node_ids.start_game_button_id)
+            .unwrap();
+        let button = w.downcast_mut::<Button>().unwrap(); // unwrap OK because we know this ID is for a Button
+        button
+            .on(EventType::Click, get_start_game_handler(net_worker.clone()))
+            .unwrap(); // unwrap OK because not in handler
+    }
+    {
+        let w = ui_layout
+            .get_screen_layering_mut(Screen::InRoom)
+            .unwrap()
+            .get_widget_mut(&static_node_ids.add_bot_button_id)
+            .unwrap();
+        let button = w.downcast_mut::<Button>().unwrap(); // unwrap OK because we know this ID is for a Button
+        button
+            .on(EventType::Click, get_add_bot_handler(net_worker.clone()))
+            .unwrap(); // unwrap OK because not in handler
+    }
+    {
+        let w = ui_layout
+            .get_screen_layering_mut(Screen::InRoom)
+            .unwrap()
+            .get_widget_mut(&static_node_ids.color_picker_id)
+            .unwrap();
+        let picker = w.downcast_mut::<ColorPicker>().unwrap(); // unwrap OK because we know this ID is for a ColorPicker
+        picker
+            .on(EventType::Click, get_color_picked_handler(net_worker.clone()))
+            .unwrap(); // unwrap OK because not in handler
+    }
+}
+
 // Then we implement the `ggez::game::GameState` trait on it, which
 // requires callbacks for creating the game state, updating it each
 // frame, and drawing it.
@@ -179,9 +710,8 @@ fn get_text_entered_handler(
 // The `GameState` trait also contains callbacks for event handling
 // that you can override if you wish, but the defaults are fine.
 impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<MainState> {
-        let mut config = config::Config::new();
-        config.load_or_create_default().map_err(|e| {
+    fn new(ctx: &mut Context, args: &cli::Args) -> GameResult<MainState> {
+        let config = load_config(args).map_err(|e| {
             let msg = format!("Error while loading config: {:?}", e);
             GameError::FilesystemError(msg)
         })?;
@@ -209,33 +739,16 @@ impl MainState {
             constants::UNIVERSE_HEIGHT_IN_CELLS,
         );
 
+        let board_colors = theme::board_colors(&config.get().theme);
         let mut color_settings = ColorSettings {
-            cell_colors: BTreeMap::new(),
-            background:  *UNIVERSE_BG_COLOR,
-        };
-        color_settings
-            .cell_colors
-            .insert(CellState::Dead, *CELL_STATE_DEAD_COLOR);
-        if GRID_DRAW_STYLE == DrawStyle::Line {
-            // black background - for a "tetris-like" effect
-            color_settings
-                .cell_colors
-                .insert(CellState::Alive(None), *CELL_STATE_BG_FILL_HOLLOW_COLOR);
-        } else {
-            // light background - default setting
-            color_settings
-                .cell_colors
-                .insert(CellState::Alive(None), *CELL_STATE_BG_FILL_SOLID_COLOR);
-        }
-        color_settings
-            .cell_colors
-            .insert(CellState::Alive(Some(0)), *CELL_STATE_ALIVE_PLAYER_0_COLOR); // 0 is red
-        color_settings
-            .cell_colors
-            .insert(CellState::Alive(Some(1)), *CELL_STATE_ALIVE_PLAYER_1_COLOR); // 1 is blue
-        color_settings
-            .cell_colors
-            .insert(CellState::Wall, *CELL_STATE_WALL_COLOR);
+            cell_colors:   BTreeMap::new(),
+            board:         board_colors,
+            player_colors: BTreeMap::new(),
+            palette:       colorblind::Palette::Normal,
+        };
+        color_settings.set_board_colors(board_colors);
+        color_settings.set_player_color(0, *CELL_STATE_ALIVE_PLAYER_0_COLOR); // 0 is red
+        color_settings.set_player_color(1, *CELL_STATE_ALIVE_PLAYER_1_COLOR); // 1 is blue
         color_settings.cell_colors.insert(CellState::Fog, *CELL_STATE_FOG_COLOR);
 
         // Note: fixed-width fonts are required!
@@ -252,13 +765,60 @@ impl MainState {
                 .birth()
         };
 
-        let mut config = config::Config::new();
-        config.load_or_create_default().map_err(|e| {
+        let mut config = load_config(args).map_err(|e| {
             let msg = format!("Error while loading config: {:?}", e);
             GameError::ConfigError(msg)
         })?;
 
-        let (mut ui_layout, static_node_ids) = UILayout::new(ctx, &config, font.clone()).unwrap(); // TODO: unwrap not OK!
+        let config_watcher = match config_watcher::ConfigWatcher::new(path::Path::new(config.path())) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                warn!("Not watching config file for changes: {}", e);
+                None
+            }
+        };
+
+        let layout_watcher = if cfg!(debug_assertions) {
+            match layout_file::LayoutWatcher::new() {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    warn!("Not watching layout files for changes: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let menu_look = MenuLook::from_config(&config);
+        menu_look.apply();
+        let announcer = accessibility::Announcer::new(&config.get().accessibility);
+        let hud = layout_file::HudLayout::load(config.get().video.streamer_mode);
+
+        // Loaded while the intro plays; the scripts are then compiled and listed in the options menu
+        let mut registry = loader::Registry::new();
+        registry.load(
+            ctx,
+            vec![
+                ("logo".to_owned(), loader::Job::Image("/conwayste.png".to_owned())),
+                (
+                    "scripts".to_owned(),
+                    loader::Job::Texts {
+                        dir:       path::PathBuf::from(SCRIPTS_DIR),
+                        extension: SCRIPT_FILE_EXTENSION,
+                    },
+                ),
+            ],
+        );
+
+        let (mut ui_layout, static_node_ids) = UILayout::new(ctx, &config, font.clone(), &[]).unwrap(); // TODO: unwrap not OK!
+        let debug_overlay = debug_overlay::DebugOverlay::new(ctx, font.clone()).unwrap(); // TODO: unwrap not OK!
+        let tutorial = tutorial::Tutorial::new(ctx, font.clone(), config.get().gameplay.tutorial_done).unwrap(); // TODO: unwrap not OK!
+        let crash_prompt = if crash::has_pending_reports() {
+            Some(ui_layout.add_crash_prompt(ctx, font.clone()).unwrap()) // TODO: unwrap not OK!
+        } else {
+            None
+        };
 
         // Update universe draw parameters for intro
         let intro_uni_draw_params = UniDrawParams {
@@ -271,8 +831,12 @@ impl MainState {
         // Add textfield handler
         // TODO: Chatbox gets a handle to Some(ClientNetWorker) to receive network messages. The
         // underlying implementation may change.
-        let net_worker = Arc::new(Mutex::new(None));
-        let chatbox_pub_handle = {
+        let net_worker = Arc::new(Mutex::new(args.connect.clone().map(|server| {
+            let mut netwayste = network::ConwaysteNetWorker::new(server);
+            netwayste.connect(&config);
+            netwayste
+        })));
+        let (chatbox_pub_handle, typing_pub_handle, script_chat_pub_handle) = {
             let chatbox_id = static_node_ids.chatbox_id.clone();
             let w = ui_layout
                 .get_screen_layering_mut(Screen::Run)
@@ -280,9 +844,21 @@ impl MainState {
                 .get_widget_mut(&chatbox_id)
                 .unwrap();
             let chatbox = w.downcast_ref::<Chatbox>().unwrap(); // unwrap OK because we know this ID is for a Chatbox
-            chatbox.new_handle()
+            (chatbox.new_handle(), chatbox.new_handle(), chatbox.new_handle())
         };
-        let text_entered_handler = get_text_entered_handler(chatbox_pub_handle, net_worker.clone());
+        let typing_handler = get_typing_handler(typing_pub_handle, net_worker.clone());
+        let marker_label = Arc::new(Mutex::new(None));
+        let bookmark_name = Arc::new(Mutex::new(None));
+        let chat_bridge = Arc::new(Mutex::new(None));
+        let text_entered_handler = get_text_entered_handler(
+            chatbox_pub_handle,
+            net_worker.clone(),
+            tutorial.chat_flag(),
+            marker_label.clone(),
+            bookmark_name.clone(),
+            chat_bridge.clone(),
+        );
+        let room_players = Arc::new(Mutex::new(vec![]));
         {
             let textfield_id = static_node_ids.chatbox_tf_id.clone();
             let w = ui_layout
@@ -292,7 +868,10 @@ impl MainState {
                 .unwrap();
             let tf = w.downcast_mut::<TextField>().unwrap();
             tf.on(EventType::TextEntered, text_entered_handler).unwrap(); // unwrap OK because not in handler
+            tf.on(EventType::Update, typing_handler).unwrap(); // unwrap OK because not in handler
+            tf.set_completer(get_chat_completer(room_players.clone()));
         }
+        hook_up_in_room_handlers(&mut ui_layout, &static_node_ids, &net_worker);
 
         let mut s = MainState {
             screen_stack: vec![Screen::Intro],
@@ -306,10 +885,87 @@ impl MainState {
             intro_viewport: intro_viewport,
             inputs: input::InputManager::new(),
             net_worker,
+            server: args.connect.clone(),
+            invited_room: args.room.clone(),
+            offline: args.offline,
             recvd_first_resize: false,
+            window_focused: true,
             current_intro_duration: 0.0,
+            registry,
             ui_layout: ui_layout,
             static_node_ids: static_node_ids,
+            scores: vec![],
+            game_outcome: None,
+            series_over: false,
+            intermission: None,
+            room_name: None,
+            team: None,
+            current_match: None,
+            history: history::History::load(),
+            leaderboards: BTreeMap::new(),
+            puzzles: puzzle::load_all(),
+            puzzle_progress: puzzle::Progress::load(),
+            picked_puzzle: Arc::new(Mutex::new(None)),
+            puzzle_attempt: None,
+            sandbox_uni: None,
+            library: vec![],
+            picked_pattern: Arc::new(Mutex::new(None)),
+            library_list: None,
+            thumbnails_due: false,
+            stamp: None,
+            import: None,
+            shared_page: None,
+            shared_list_id: None,
+            picked_shared: Arc::new(Mutex::new(None)),
+            room_players,
+            roster: vec![],
+            room_colors: BTreeMap::new(),
+            marker_label,
+            chat_bridge,
+            chat_bridge_applied: None,
+            discord: None,
+            discord_requests: VecDeque::new(),
+            discord_prompt: None,
+            discord_answer: Arc::new(Mutex::new(None)),
+            bookmarks: bookmarks::Bookmarks::load(),
+            bookmark_name,
+            last_bookmark: None,
+            follow_camera: follow_camera::FollowCamera::new(),
+            in_room: false,
+            reported_viewport: None,
+            universe_progress: None,
+            net_game: None,
+            net_writable: None,
+            net_diffs: catch_up::DiffBuffer::new(),
+            lockstep_enabled: false,
+            lockstep: None,
+            show_lockstep: false,
+            cell_batch: RefCell::new(CellBatch::new(ctx)?),
+            chunk_cache: RefCell::new(ChunkCache::new()),
+            effects: RefCell::new(effects::PostProcessing::new(ctx)),
+            trails: RefCell::new(effects::Trails::new()),
+            cell_rendering: CellRendering::Chunked,
+            scripts: vec![],
+            last_scripted_gen: None,
+            chatbox_pub_handle: script_chat_pub_handle,
+            custom_events: vec![],
+            config_watcher,
+            layout_watcher,
+            toast: None,
+            last_drawn: None,
+            hud,
+            hud_applied: None,
+            hud_hidden: false,
+            menu_look,
+            menu_background: menu_background::MenuBackground::new(ctx)?,
+            announcer,
+            debug_overlay,
+            help: help::HelpOverlay::new(),
+            tutorial,
+            console: console::Console::new(),
+            inspector: ui::WidgetInspector::new(),
+            cursor: ui::CursorManager::new(),
+            crash_prompt,
         };
 
         init_intro_screen(&mut s).unwrap();
@@ -320,18 +976,24 @@ impl MainState {
 
 impl EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let update_start = Instant::now();
+        self.debug_overlay.record_frame_time(timer::delta(ctx));
         let duration = timer::duration_to_f64(timer::delta(ctx)); // seconds
 
         self.receive_net_updates()?;
+        self.apply_net_diffs();
+        self.receive_resources(ctx);
 
         let screen = self.get_current_screen();
+        crash::set_screen(screen);
 
         // Handle special case screens
         // NOTE: each match arm except default must return
         match screen {
             Screen::Intro => {
-                // Any key should skip the intro
-                if self.inputs.key_info.key.is_some() || (self.current_intro_duration > INTRO_DURATION) {
+                // Any key should skip the intro; otherwise it lasts until everything is loaded
+                let intro_over = self.current_intro_duration > INTRO_DURATION && !self.registry.is_loading();
+                if self.inputs.key_info.key.is_some() || intro_over {
                     self.screen_stack.pop();
                     self.screen_stack.push(Screen::Menu);
                     self.inputs.key_info.key = None;
@@ -358,9 +1020,16 @@ impl EventHandler for MainState {
             }
             _ => {} // all others handled below
         }
+        if self.shows_menu_background(screen) {
+            self.menu_background.update();
+        }
+
+        self.inputs
+            .key_info
+            .advance_repeat(timer::delta(ctx), &self.config.get().input);
         let key = self.inputs.key_info.key;
         let keymods = self.inputs.key_info.modifier;
-        let is_shift = keymods & KeyMods::SHIFT > KeyMods::default();
+        let is_shift = keymods.contains(KeyMods::SHIFT);
         let is_repeating = self.inputs.key_info.repeating;
 
         let mouse_point = self.inputs.mouse_info.position;
@@ -388,12 +1057,32 @@ impl EventHandler for MainState {
                     error!("Error from layer.emit on update: {:?}", e);
                 });
 
+            for event in self.custom_events.drain(..) {
+                layer
+                    .emit(
+                        &event,
+                        ctx,
+                        &mut self.config,
+                        &mut self.screen_stack,
+                        &mut game_area_state,
+                        &mut self.static_node_ids,
+                        &mut self.viewport,
+                    )
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "Error from layer.emit on custom event {:?}: {:?}",
+                            event.what.custom_name(),
+                            e
+                        );
+                    });
+            }
+
             if self.inputs.mouse_info.prev_position != self.inputs.mouse_info.position {
                 let mouse_move = Event::new_mouse_move(
                     self.inputs.mouse_info.prev_position,
                     self.inputs.mouse_info.position,
                     self.inputs.mouse_info.mousebutton,
-                    is_shift,
+                    keymods,
                 );
                 layer
                     .emit(
@@ -411,10 +1100,34 @@ impl EventHandler for MainState {
                 self.inputs.mouse_info.prev_position = self.inputs.mouse_info.position;
             }
 
+            let scroll_delta = self.inputs.mouse_info.scroll_delta;
+            if scroll_delta.x != 0.0 || scroll_delta.y != 0.0 {
+                let scroll_event = Event::new_scroll(mouse_point, scroll_delta, keymods);
+                layer
+                    .emit(
+                        &scroll_event,
+                        ctx,
+                        &mut self.config,
+                        &mut self.screen_stack,
+                        &mut game_area_state,
+                        &mut self.static_node_ids,
+                        &mut self.viewport,
+                    )
+                    .unwrap_or_else(|e| {
+                        error!("Error from layer.emit on scroll: {:?}", e);
+                    });
+            }
+
             if let Some(action) = mouse_action {
+                if action == MouseAction::Click {
+                    crash::note_event(format!(
+                        "click at ({}, {}) on {:?}",
+                        mouse_point.x, mouse_point.y, screen
+                    ));
+                }
                 match action {
                     MouseAction::Drag => {
-                        let drag_event = Event::new_drag(mouse_point, self.inputs.mouse_info.mousebutton, is_shift);
+                        let drag_event = Event::new_drag(mouse_point, self.inputs.mouse_info.mousebutton, keymods);
                         layer
                             .emit(
                                 &drag_event,
@@ -430,7 +1143,7 @@ impl EventHandler for MainState {
                             });
                     }
                     MouseAction::Click => {
-                        let click_event = Event::new_click(mouse_point, self.inputs.mouse_info.mousebutton, is_shift);
+                        let click_event = Event::new_click(mouse_point, self.inputs.mouse_info.mousebutton, keymods);
                         layer
                             .emit(
                                 &click_event,
@@ -447,7 +1160,7 @@ impl EventHandler for MainState {
                     }
                     MouseAction::Held => {
                         let hold_event =
-                            Event::new_mouse_held(mouse_point, self.inputs.mouse_info.mousebutton, is_shift);
+                            Event::new_mouse_held(mouse_point, self.inputs.mouse_info.mousebutton, keymods);
                         layer
                             .emit(
                                 &hold_event,
@@ -462,15 +1175,14 @@ impl EventHandler for MainState {
                                 error!("Error from layer.emit on left click: {:?}", e);
                             });
                     }
-                    MouseAction::DoubleClick => {
-                        // TODO add support
-                        error!("Please add double click support in the client update event dispatcher.");
-                    }
                 }
             }
 
             if let Some(key) = key {
-                let key_event = Event::new_key_press(mouse_point, key, is_shift, is_repeating);
+                if !is_repeating {
+                    crash::note_event(format!("key {:?} on {:?}", key, screen));
+                }
+                let key_event = Event::new_key_press(mouse_point, key, keymods, is_repeating);
                 layer
                     .emit(
                         &key_event,
@@ -489,7 +1201,7 @@ impl EventHandler for MainState {
             let mut text_input = vec![];
             std::mem::swap(&mut self.inputs.text_input, &mut text_input);
             for character in text_input {
-                let key_event = Event::new_char_press(mouse_point, character, is_shift);
+                let key_event = Event::new_char_press(mouse_point, character, keymods);
                 layer
                     .emit(
                         &key_event,
@@ -525,6 +1237,10 @@ impl EventHandler for MainState {
                         )
                     });
             }
+
+            self.cursor.update(ctx, layer, mouse_point);
+        } else {
+            self.cursor.reset(ctx);
         }
 
         // Refresh the game area state after processing all events
@@ -538,11 +1254,67 @@ impl EventHandler for MainState {
                 game_area_state.running = false;
             }
 
+            // Clicking the collapsed chatbox expands it
+            self.fit_chat_pane();
+
+            self.run_scripts();
+            self.advance_tutorial();
+            self.check_puzzle();
+
+            let generation =
+                GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id)
+                    .map(|game_area| game_area.uni.latest_gen())
+                    .ok();
+            crash::set_generation(generation);
+
             if !is_shift {
                 // Arrow keys (but not Shift-<Arrow>!) move the player's view of the universe around
                 self.viewport.update(game_area_state.arrow_input);
             }
+            self.update_follow_camera(duration as f32, !is_shift && game_area_state.arrow_input != (0, 0));
+            self.report_viewport();
+            self.send_cell_changes();
+            self.update_annotations();
+            self.update_bookmarks();
+        } else if screen == Screen::InRoom {
+            self.submit_create_room_form();
+        }
+
+        let picked_puzzle = self.picked_puzzle.lock().unwrap().take();
+        if let Some(index) = picked_puzzle {
+            self.start_puzzle(index);
+        }
+
+        let save_requested =
+            std::mem::replace(&mut self.ui_layout.pattern_editor.lock().unwrap().save_requested, false);
+        if save_requested {
+            self.save_edited_pattern();
+        }
+
+        let picked_pattern = self.picked_pattern.lock().unwrap().take();
+        if let Some(index) = picked_pattern {
+            self.place_library_pattern(index);
+        } else if screen == Screen::PatternLibrary {
+            self.list_library_patterns(ctx);
+        }
+
+        let picked_shared = self.picked_shared.lock().unwrap().take();
+        if let Some(index) = picked_shared {
+            self.download_shared_pattern(index);
+        }
+        let shared_request = self.ui_layout.shared_request.lock().unwrap().take();
+        if let Some(request) = shared_request {
+            self.handle_shared_patterns_request(request);
         }
+        if screen == Screen::SharedPatterns && self.shared_list_id.is_none() {
+            self.list_shared_patterns(ctx);
+        }
+
+        let import_requested = std::mem::replace(&mut *self.ui_layout.import_request.lock().unwrap(), false);
+        if import_requested {
+            self.start_import();
+        }
+        self.poll_import();
 
         // Event processing may have updated the state of the current screen
         let new_screen = self.get_current_screen();
@@ -550,6 +1322,31 @@ impl EventHandler for MainState {
             .unwrap_or_else(|e| {
                 error!("Failed to transition_screen: {:?}", e);
             });
+        if new_screen != screen {
+            match new_screen {
+                Screen::Leaderboards => self.request_leaderboards(),
+                Screen::Puzzles => self.list_puzzles(ctx),
+                Screen::PatternLibrary => self.open_pattern_library(ctx),
+                Screen::SharedPatterns => self.request_shared_patterns(0),
+                _ => {}
+            }
+            if screen == Screen::Run && self.puzzle_attempt.is_some() {
+                self.end_puzzle();
+            }
+        }
+
+        self.reload_config_if_changed();
+        self.reload_layouts_if_changed(ctx);
+        self.apply_menu_look_if_changed(ctx);
+        keymap::set_keys(&self.config.get().keys);
+        self.apply_hud_if_changed(ctx);
+        self.update_chat_bridge();
+        self.update_discord_presence();
+        self.update_discord_prompt(ctx);
+        self.resolve_crash_prompt();
+        self.apply_palette_if_changed();
+        self.apply_board_theme_if_changed();
+        self.announcer.flush();
 
         // HACK: propagate any video-related config settings from UI handlers to self.video_settings
         // TODO: remove self.video_settings
@@ -560,15 +1357,27 @@ impl EventHandler for MainState {
 
         self.post_update()?;
 
+        self.debug_overlay.record_update_time(update_start.elapsed());
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
-
         let current_screen = self.get_current_screen();
+        if self.screen_is_idle(current_screen) {
+            // the last frame is still on screen; wait instead of drawing it again
+            timer::sleep(IDLE_FRAME_SLEEP);
+            return Ok(());
+        }
+        self.last_drawn = Some((current_screen, Instant::now()));
+        let hud_shown = current_screen != Screen::Run || !self.hud_hidden;
+
+        graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
 
         // Before drawing widgets, draw other stuff underneath
+        if self.shows_menu_background(current_screen) {
+            let screen_rect = graphics::screen_coordinates(ctx);
+            self.menu_background.draw(ctx, screen_rect.w, screen_rect.h)?;
+        }
         match current_screen {
             Screen::Intro => {
                 self.draw_intro(ctx).unwrap_or_else(|e| {
@@ -579,8 +1388,8 @@ impl EventHandler for MainState {
                 ui::draw_text(
                     ctx,
                     self.system_font.clone(),
-                    *MENU_TEXT_COLOR,
-                    String::from("Main Menu"),
+                    scheme().menu_text,
+                    tr("menu-title"),
                     &Point2 { x: 500.0, y: 100.0 },
                 )?;
             }
@@ -588,22 +1397,75 @@ impl EventHandler for MainState {
                 self.draw_universe(ctx).unwrap_or_else(|e| {
                     error!("Error from draw_universe: {}", e);
                 });
+                if !hud_shown {
+                    // nothing but the board
+                } else if let Some((received, total)) = self.universe_progress {
+                    self.draw_universe_progress(ctx, received, total)?;
+                } else if let Some(ref attempt) = self.puzzle_attempt {
+                    self.draw_puzzle_status(ctx, attempt)?;
+                } else if let Some(ref net_game) = self.net_game {
+                    if let Some(behind) = self.net_diffs.behind(net_game.universe().latest_gen()) {
+                        let text = tr_args("game-catching-up", &[("generations", &behind)]);
+                        let size = ui::text_size(ctx, self.system_font.clone(), text.clone());
+                        let position = self.hud_position(ctx, self.hud.status, size);
+                        ui::draw_text(ctx, self.system_font.clone(), scheme().menu_text, text, &position)?;
+                    }
+                }
             }
             Screen::InRoom => {
                 ui::draw_text(
                     ctx,
                     self.system_font.clone(),
-                    *MENU_TEXT_COLOR,
-                    String::from("In Room"),
+                    scheme().menu_text,
+                    tr("room-title"),
+                    &Point2 { x: 100.0, y: 100.0 },
+                )?;
+                self.draw_standings(ctx, &self.scores, &Point2 { x: 500.0, y: 150.0 })?;
+            }
+            Screen::Results => {
+                let title = if self.series_over {
+                    tr("results-series-title")
+                } else {
+                    tr("results-title")
+                };
+                ui::draw_text(
+                    ctx,
+                    self.system_font.clone(),
+                    scheme().menu_text,
+                    title,
                     &Point2 { x: 100.0, y: 100.0 },
                 )?;
+                if let Some((round, rounds, ref standings)) = self.intermission {
+                    ui::draw_text(
+                        ctx,
+                        self.system_font.clone(),
+                        scheme().menu_text,
+                        tr_args("results-series-progress", &[("round", &round), ("rounds", &rounds)]),
+                        &Point2 { x: 500.0, y: 150.0 },
+                    )?;
+                    self.draw_standings(ctx, standings, &Point2 { x: 500.0, y: 200.0 })?;
+                }
+                if let Some(ref outcome) = self.game_outcome {
+                    let winner_text = match outcome.winner {
+                        Some(ref winner) => tr_args("results-winner", &[("winner", winner)]),
+                        None => tr("results-tie"),
+                    };
+                    ui::draw_text(
+                        ctx,
+                        self.system_font.clone(),
+                        scheme().menu_text,
+                        winner_text,
+                        &Point2 { x: 100.0, y: 150.0 },
+                    )?;
+                    self.draw_standings(ctx, &outcome.standings, &Point2 { x: 100.0, y: 200.0 })?;
+                }
             }
             Screen::ServerList => {
                 ui::draw_text(
                     ctx,
                     self.system_font.clone(),
-                    *MENU_TEXT_COLOR,
-                    String::from("Server List"),
+                    scheme().menu_text,
+                    tr("server-list-title"),
                     &Point2 { x: 100.0, y: 100.0 },
                 )?;
             }
@@ -611,18 +1473,133 @@ impl EventHandler for MainState {
                 ui::draw_text(
                     ctx,
                     self.system_font.clone(),
-                    *MENU_TEXT_COLOR,
-                    String::from("Options"),
+                    scheme().menu_text,
+                    tr("options-title"),
                     &Point2 { x: 100.0, y: 100.0 },
                 )?;
             }
+            Screen::Stats => self.draw_stats(ctx)?,
+            Screen::Leaderboards => self.draw_leaderboards(ctx)?,
+            Screen::SharedPatterns => self.draw_shared_patterns(ctx)?,
+            Screen::PatternImport => self.draw_pattern_import(ctx)?,
+            Screen::Puzzles => {
+                ui::draw_text(
+                    ctx,
+                    self.system_font.clone(),
+                    scheme().menu_text,
+                    tr("puzzles-title"),
+                    &Point2 { x: 100.0, y: 100.0 },
+                )?;
+            }
+            Screen::PatternLibrary => {
+                ui::draw_text(
+                    ctx,
+                    self.system_font.clone(),
+                    scheme().menu_text,
+                    tr("library-title"),
+                    &Point2 { x: 100.0, y: 100.0 },
+                )?;
+                if let Some((ref search, category, _)) = self.library_list {
+                    if !self
+                        .library
+                        .iter()
+                        .any(|(pattern, _)| pattern.matches(search, category))
+                    {
+                        ui::draw_text(
+                            ctx,
+                            self.system_font.clone(),
+                            scheme().menu_text,
+                            tr("library-no-matches"),
+                            &Point2 { x: 110.0, y: 260.0 },
+                        )?;
+                    }
+                }
+            }
+            Screen::PatternEditor => {
+                let (generation, population) = {
+                    let editor = self.ui_layout.pattern_editor.lock().unwrap();
+                    (editor.generation(), editor.population())
+                };
+                ui::draw_text(
+                    ctx,
+                    self.system_font.clone(),
+                    scheme().menu_text,
+                    tr_args(
+                        "editor-generation",
+                        &[("generation", &generation), ("population", &population)],
+                    ),
+                    &Point2 { x: 20.0, y: 690.0 },
+                )?;
+            }
             Screen::Exit => {}
         }
 
-        if let Some(layering) = self.ui_layout.get_screen_layering_mut(current_screen) {
+        if !hud_shown {
+            // the chat is the only widget drawn on the in-game screen
+        } else if let Some(layering) = self.ui_layout.get_screen_layering_mut(current_screen) {
             layering.draw(ctx).unwrap_or_else(|e| {
                 error!("Error received during layering draw: {:?}", e);
             });
+            if self.inspector.visible {
+                let mouse = self.inputs.mouse_info.position;
+                self.inspector
+                    .draw(ctx, self.system_font.clone(), layering, mouse)
+                    .unwrap_or_else(|e| {
+                        error!("Error drawing the widget inspector: {:?}", e);
+                    });
+            }
+        }
+
+        if current_screen == Screen::Run && hud_shown && self.tutorial.is_running() {
+            if let Some(target) = self.tutorial_target() {
+                self.tutorial.draw(ctx, target).unwrap_or_else(|e| {
+                    error!("Error drawing the tutorial: {:?}", e);
+                });
+            }
+        }
+
+        if self.help.visible && hud_shown {
+            self.draw_help(ctx, current_screen).unwrap_or_else(|e| {
+                error!("Error drawing the key hints: {:?}", e);
+            });
+        }
+
+        if let Some((ref msg, shown_at)) = self.toast {
+            if shown_at.elapsed() < TOAST_DURATION {
+                if hud_shown {
+                    let size = ui::text_size(ctx, self.system_font.clone(), msg.clone());
+                    let position = self.hud_position(ctx, self.hud.toast, size);
+                    ui::draw_text(
+                        ctx,
+                        self.system_font.clone(),
+                        scheme().toast_text,
+                        msg.clone(),
+                        &position,
+                    )?;
+                }
+            } else {
+                self.toast = None;
+            }
+        }
+
+        if self.console.visible {
+            self.console.draw(ctx, self.system_font.clone())?;
+        }
+
+        if self.debug_overlay.visible {
+            let stats = match self.ui_layout.get_screen_layering(current_screen) {
+                Some(layering) => debug_overlay::DrawStats {
+                    widgets_drawn:     layering.widgets_drawn(),
+                    widget_tree_depth: layering.widget_tree_depth(),
+                },
+                None => debug_overlay::DrawStats {
+                    widgets_drawn:     0,
+                    widget_tree_depth: 0,
+                },
+            };
+            self.debug_overlay.draw(ctx, &stats).unwrap_or_else(|e| {
+                error!("Error drawing the debug overlay: {:?}", e);
+            });
         }
 
         graphics::present(ctx)?;
@@ -695,24 +1672,64 @@ impl EventHandler for MainState {
 
     /// Vertical scroll:   (y, positive away from and negative toward the user)
     /// Horizontal scroll: (x, positive to the right and negative to the left)
-    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
-        self.inputs.mouse_info.scroll_event = if y > 0.0 {
-            Some(ScrollEvent::ScrollUp)
-        } else if y < 0.0 {
-            Some(ScrollEvent::ScrollDown)
-        } else {
-            None
-        };
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.inputs.mouse_info.scroll_delta.x += x;
+        self.inputs.mouse_info.scroll_delta.y += y;
 
         if self.inputs.mouse_info.debug_print {
-            debug!("Wheel Event {:?}", self.inputs.mouse_info.scroll_event);
+            debug!("Wheel Event {:?}", (x, y));
         }
     }
 
     fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymod: KeyMods, repeat: bool) {
-        let key_as_int32 = keycode as i32;
-
-        // Winit's KeyCode definition has no perceptible ordering so I'm selectively defining what keys we'll accept...
+        match keymap::action(Scope::Everywhere, keycode, keymod) {
+            Some(KeyAction::Help) => {
+                if !repeat {
+                    self.help.visible = !self.help.visible;
+                }
+                return;
+            }
+            Some(KeyAction::DebugOverlay) => {
+                if !repeat {
+                    self.debug_overlay.visible = !self.debug_overlay.visible;
+                }
+                return;
+            }
+            Some(KeyAction::Console) => {
+                if !repeat {
+                    self.console.visible = !self.console.visible;
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        match keymap::action(Scope::Game, keycode, keymod) {
+            Some(KeyAction::CollapseChat) => {
+                if !repeat && self.get_current_screen() == Screen::Run {
+                    self.toggle_chat_collapsed();
+                }
+                return;
+            }
+            Some(KeyAction::ToggleHud) => {
+                if !repeat && self.get_current_screen() == Screen::Run {
+                    self.hud_hidden = !self.hud_hidden;
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        if self.console.visible {
+            if let Some(line) = self.console.key(keycode) {
+                self.run_console_command(&line);
+            }
+            return;
+        }
+
+        let key_as_int32 = keycode as i32;
+
+        // Winit's KeyCode definition has no perceptible ordering so I'm selectively defining what keys we'll accept...
         // for now at least
         if key_as_int32 < (KeyCode::Numlock as i32)
             || (key_as_int32 >= KeyCode::LAlt as i32 && key_as_int32 <= KeyCode::LWin as i32)
@@ -721,18 +1738,14 @@ impl EventHandler for MainState {
                 || key_as_int32 == KeyCode::Minus as i32
                 || key_as_int32 == KeyCode::Tab as i32)
         {
-            // NOTE: we need to exclude modifiers we are using below.
-            let is_modifier_key = keycode == KeyCode::LShift || keycode == KeyCode::RShift;
-            if self.inputs.key_info.key.is_none() && !is_modifier_key {
-                self.inputs.key_info.key = Some(keycode);
-            }
-
-            if self.inputs.key_info.key == Some(keycode) {
-                self.inputs.key_info.repeating = repeat;
+            // Modifier keys are not keys of their own; they go along with the key and mouse events
+            let is_modifier_key = input::modifier_of_key(keycode).is_some();
+            if !is_modifier_key && !repeat {
+                self.inputs.key_info.press(keycode);
             }
-
-            self.inputs.key_info.modifier = keymod;
         }
+        // Even for keys that aren't accepted, so that mouse events get the modifiers held down
+        self.inputs.key_info.modifier = keymod;
 
         if self.inputs.key_info.debug_print {
             debug!(
@@ -742,9 +1755,13 @@ impl EventHandler for MainState {
         }
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, keymod: KeyMods) {
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymod: KeyMods) {
         // TODO: should probably only clear key if keycode matches key_info.key
-        self.inputs.key_info.modifier &= !keymod; // clear whatever modifier key was released
+        self.inputs.key_info.release(keycode);
+        self.inputs.key_info.modifier = keymod;
+        if let Some(released) = input::modifier_of_key(keycode) {
+            self.inputs.key_info.modifier.remove(released);
+        }
         self.inputs.key_info.key = None;
         self.inputs.key_info.repeating = false;
 
@@ -762,6 +1779,14 @@ impl EventHandler for MainState {
             return;
         }
 
+        if character == '`' {
+            return; // toggles the console; see key_down_event
+        }
+        if self.console.visible {
+            self.console.type_char(character);
+            return;
+        }
+
         self.inputs.text_input.push(character);
     }
 
@@ -773,6 +1798,7 @@ impl EventHandler for MainState {
             return;
         }
         debug!("resize_event: {}, {}", width, height);
+        self.last_drawn = None; // draw the next frame at the new size
         let new_rect = graphics::Rect::new(0.0, 0.0, width, height);
         if self.uni_draw_params.player_id < 0 {
             self.intro_viewport.set_size(width, height);
@@ -790,6 +1816,10 @@ impl EventHandler for MainState {
             .unwrap();
     }
 
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        self.window_focused = gained;
+    }
+
     /// Called when the user requests that the window be closed (ggez gets a
     /// WindowEvent::CloseRequested event from winit)
     fn quit_event(&mut self, _ctx: &mut Context) -> bool {
@@ -826,6 +1856,14 @@ impl EventHandler for MainState {
     }
 }
 
+impl AppEventHandler for MainState {
+    fn app_event(&mut self, _ctx: &mut Context, event: AppEvent) {
+        match event {
+            AppEvent::FileDropped(path) => self.open_dropped_file(&path),
+        }
+    }
+}
+
 struct UniDrawParams {
     bg_color:     Color,
     fg_color:     Color,
@@ -839,310 +1877,2877 @@ impl MainState {
             .map(|gs| gs.get_game_area_state())
     }
 
-    fn draw_game_of_life(&self, ctx: &mut Context, universe: &Universe) -> Result<(), Box<dyn Error>> {
-        let viewport = if self.uni_draw_params.player_id >= 0 {
-            &self.viewport
-        } else {
-            // intro
-            &self.intro_viewport
+    /// Gives each enabled user script its turn, once per generation of the single-player game. A
+    /// script that fails is disabled, so that it doesn't fill the log with the same error.
+    fn run_scripts(&mut self) {
+        let enabled = self.config.get().gameplay.enabled_scripts.clone();
+        if enabled.is_empty() {
+            return;
+        }
+        let game_area = match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => game_area,
+            Err(e) => {
+                error!("Could not get game area to run scripts: {}", e);
+                return;
+            }
         };
-        let viewport_rect = viewport.get_rect();
+        let generation = game_area.uni.latest_gen();
+        if self.last_scripted_gen == Some(generation) {
+            return;
+        }
+        self.last_scripted_gen = Some(generation);
 
-        // grid background
-        let rectangle = graphics::Mesh::new_rectangle(
-            ctx,
-            GRID_DRAW_STYLE.to_draw_mode(),
-            graphics::Rect::new(0.0, 0.0, viewport_rect.w, viewport_rect.h),
-            self.uni_draw_params.bg_color,
-        )?;
-        graphics::draw(ctx, &rectangle, DrawParam::new().dest(viewport_rect.point()))?;
+        let mut messages = vec![];
+        let mut failed = vec![];
+        for script in self.scripts.iter_mut() {
+            if !enabled.iter().any(|name| name == script.name()) {
+                continue;
+            }
+            match script.on_generation(&mut game_area.uni) {
+                Ok(chat) => {
+                    messages.extend(chat.into_iter().map(|msg| (script.name().to_owned(), msg)));
+                    for (name, text) in script.take_events() {
+                        // no widget could handle an event of a name the code doesn't know
+                        if let Some(name) = EventType::known_custom_name(&name) {
+                            self.custom_events.push(Event::new_custom(name, text));
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Disabling script: {}", e);
+                    failed.push(script.name().to_owned());
+                }
+            }
+        }
 
-        // grid foreground (dead cells)
-        let full_rect = viewport.get_rect_from_origin();
+        if !failed.is_empty() {
+            self.config.modify(|settings| {
+                settings.gameplay.enabled_scripts.retain(|name| !failed.contains(name));
+            });
+        }
+        for (name, msg) in messages {
+            self.chatbox_pub_handle.add_message(format!("[{}] {}", name, msg));
+            if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+                netwayste.try_send(NetwaysteEvent::ChatMessage(msg));
+            }
+        }
+    }
 
-        let image = graphics::Image::solid(ctx, 1u16, WHITE)?; // 1x1 square
-        let mut main_spritebatch = graphics::spritebatch::SpriteBatch::new(image.clone());
-        let mut overlay_spritebatch = graphics::spritebatch::SpriteBatch::new(image);
+    /// Tells the server what part of the universe is on screen, if it has changed and we haven't
+    /// done so too recently.
+    fn report_viewport(&mut self) {
+        if !self.in_room {
+            return;
+        }
+        let visible = self.viewport.visible_cells();
+        if let Some((reported, sent_at)) = self.reported_viewport {
+            if reported == visible || sent_at.elapsed() < VIEWPORT_REPORT_INTERVAL {
+                return;
+            }
+        }
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            netwayste.try_send(NetwaysteEvent::SetViewport(NetRegion::from(visible)));
+            self.reported_viewport = Some((visible, Instant::now()));
+        }
+    }
 
-        // grid non-dead cells (walls, players, etc.)
+    /// Applies this frame's share of the universe diffs from the server. One that doesn't follow on
+    /// from what we have means diffs were lost, so the server is asked for the whole universe.
+    fn apply_net_diffs(&mut self) {
+        let net_game = match self.net_game {
+            Some(ref mut net_game) => net_game,
+            None => return,
+        };
+        for diff_part in self.net_diffs.take_for_frame() {
+            match net_game.apply_server_diff(&diff_part) {
+                Ok(Some(_)) => {}
+                Ok(None) if diff_part.gen0 as usize > net_game.universe().latest_gen() => {
+                    warn!(
+                        "Universe diff from generation {} doesn't follow on from {}; resyncing",
+                        diff_part.gen0,
+                        net_game.universe().latest_gen()
+                    );
+                    self.net_diffs.clear();
+                    if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+                        netwayste.try_send(NetwaysteEvent::ResyncUniverse);
+                    }
+                    return;
+                }
+                Ok(None) => debug!("Universe diff to generation {} is out of date", diff_part.gen1),
+                Err(e) => error!("Could not apply universe update: {:?}", e),
+            }
+        }
+    }
+
+    /// Makes the cells clicked in a networked game change on screen right away, and asks the server
+    /// to change them too.
+    fn send_cell_changes(&mut self) {
+        let (toggles, births) = match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => (game_area.take_net_toggles(), game_area.take_net_births()),
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
+        };
+        let net_game = match self.net_game {
+            Some(ref mut net_game) => net_game,
+            None => return,
+        };
+        // Cells outside of our writable region would only be refused by the server
+        let writable = self.net_writable;
+        let placeable = |universe: &Universe, col, row| net_placeable(writable, universe, col, row);
+        let (mut placed, mut blocked) = (0, 0);
+        let mut set_cells = vec![];
+        for (col, row) in toggles {
+            if !placeable(net_game.universe(), col, row) {
+                blocked += 1;
+            } else if let Some(alive) = net_game.toggle(col, row) {
+                set_cells.push((col as u32, row as u32, alive));
+            }
+        }
+        let birth_count = births.len();
+        for (col, row) in births {
+            if !placeable(net_game.universe(), col, row) {
+                blocked += 1;
+            } else if net_game.universe().cell_state(col, row) == CellState::Dead {
+                if let Some(alive) = net_game.toggle(col, row) {
+                    set_cells.push((col as u32, row as u32, alive));
+                    placed += 1;
+                }
+            }
+        }
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            // A pattern dropped in one go is sent in as few requests as the server takes
+            for batch in set_cells.chunks(MAX_SET_CELLS) {
+                netwayste.try_send(NetwaysteEvent::SetCells(batch.to_vec()));
+            }
+        }
+
+        if blocked > 0 {
+            let msg = if birth_count == 0 || placed == 0 {
+                tr("placement-blocked")
+            } else {
+                tr_args("placement-partial", &[("placed", &placed), ("blocked", &blocked)])
+            };
+            self.toast = Some((msg, Instant::now()));
+        }
+    }
+
+    /// Switches the camera to following the next player's births when asked to, then pans it
+    /// towards the recent births over `seconds`. Panning by hand stops it following.
+    fn update_follow_camera(&mut self, seconds: f32, panned: bool) {
+        let requested = match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => game_area.take_follow_request(),
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
+        };
+        let game_area = match GameArea::widget_from_screen_and_id(
+            &self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => game_area,
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
+        };
+        let universe = match self.net_game {
+            Some(ref net_game) => net_game.universe(),
+            None => &game_area.uni,
+        };
+
+        let follow = self.follow_camera.follow();
+        let next = if requested {
+            Some(follow.next(universe.num_players()))
+        } else if panned && follow != Follow::Off {
+            Some(Follow::Off)
+        } else {
+            None
+        };
+        if let Some(next) = next {
+            self.follow_camera.set_follow(next);
+            let msg = match next {
+                Follow::Off => tr("follow-off"),
+                Follow::Everyone => tr("follow-everyone"),
+                Follow::Player(player_id) => tr_args("follow-player", &[("number", &(player_id + 1))]),
+            };
+            self.toast = Some((msg, Instant::now()));
+        }
+
+        // only what the player can see through the fog is followed
         let visibility = if self.uni_draw_params.player_id >= 0 {
             Some(self.uni_draw_params.player_id as usize)
         } else {
-            // used for random coloring in intro
-            Some(0)
+            None
+        };
+        self.follow_camera.update(universe, visibility);
+        self.follow_camera.pan(&mut self.viewport, seconds);
+    }
+
+    /// Where the camera bookmarks are kept for what's being played.
+    fn bookmark_place(&self) -> Place {
+        if let Some(ref attempt) = self.puzzle_attempt {
+            Place::Puzzle(self.puzzles[attempt.puzzle].id.clone())
+        } else if let (true, Some(room_name)) = (self.net_game.is_some(), &self.room_name) {
+            Place::Room(room_name.clone())
+        } else {
+            Place::Sandbox
+        }
+    }
+
+    /// Saves the view as a camera bookmark or goes to one when asked to with the number keys, and
+    /// renames the last one saved or gone to when asked to with "/bookmark" in the chat.
+    fn update_bookmarks(&mut self) {
+        let request = match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => game_area.take_bookmark_request(),
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
         };
+        let place = self.bookmark_place();
+        match request {
+            Some(BookmarkRequest::Save(slot)) => {
+                let number = slot + 1;
+                let name = match self.bookmarks.get(&place, slot) {
+                    Some(bookmark) => bookmark.name.clone(),
+                    None => tr_args("bookmark-default-name", &[("number", &number)]),
+                };
+                let msg = tr_args("bookmark-saved", &[("name", &name), ("number", &number)]);
+                let bookmark = Bookmark {
+                    name,
+                    center: self.viewport.center(),
+                    cell_size: self.viewport.get_cell_size(),
+                };
+                self.bookmarks.set(&place, slot, bookmark);
+                self.last_bookmark = Some((place.clone(), slot));
+                self.toast = Some((msg, Instant::now()));
+            }
+            Some(BookmarkRequest::Jump(slot)) => match self.bookmarks.get(&place, slot) {
+                Some(bookmark) => {
+                    self.follow_camera.set_follow(Follow::Off);
+                    self.viewport.look_at(bookmark.center, bookmark.cell_size);
+                    let cell_size = self.viewport.get_cell_size();
+                    self.config.modify(|settings| {
+                        settings.gameplay.zoom = cell_size;
+                    });
+                    self.toast = Some((bookmark.name.clone(), Instant::now()));
+                    self.last_bookmark = Some((place.clone(), slot));
+                }
+                None => {
+                    let msg = tr_args("bookmark-empty", &[("number", &(slot + 1))]);
+                    self.toast = Some((msg, Instant::now()));
+                }
+            },
+            None => {}
+        }
 
-        // TODO: call each_non_dead with visible region (add method to viewport)
-        universe.each_non_dead_full(visibility, &mut |col, row, state| {
-            let color = if self.uni_draw_params.player_id >= 0 {
-                self.color_settings.get_color(Some(state))
+        let name = self.bookmark_name.lock().unwrap().take();
+        if let Some(name) = name.filter(|name| !name.is_empty()) {
+            let renamed = match self.last_bookmark {
+                Some((ref last_place, slot)) if *last_place == place => self.bookmarks.rename(&place, slot, &name),
+                _ => false,
+            };
+            let msg = if renamed {
+                tr_args("bookmark-renamed", &[("name", &name)])
             } else {
-                self.color_settings.get_random_color()
+                tr("bookmark-none")
             };
+            self.toast = Some((msg, Instant::now()));
+        }
+    }
 
-            if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
-                let p = graphics::DrawParam::new()
-                    .dest(Point2 { x: rect.x, y: rect.y })
-                    .scale(Vector2 { x: rect.w, y: rect.h })
-                    .color(color);
+    /// Renames the player's newest marker when asked to with "/label" in the chat, and shows the
+    /// room the annotations the player hasn't shared yet once they ask to.
+    fn update_annotations(&mut self) {
+        let label = self.marker_label.lock().unwrap().take();
+        let networked = self.net_game.is_some();
+        let (relabeled, shared) = match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => {
+                let relabeled = label.map(|label| game_area.relabel_last_marker(&label));
+                let shared = if !game_area.take_share_request() {
+                    None
+                } else if networked {
+                    Some(game_area.take_unshared_annotations())
+                } else {
+                    Some(vec![])
+                };
+                (relabeled, shared)
+            }
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
+        };
+        if relabeled == Some(false) {
+            self.toast = Some((tr("annotation-no-marker"), Instant::now()));
+        }
+        match shared {
+            Some(_) if !networked => self.toast = Some((tr("annotation-share-offline"), Instant::now())),
+            Some(annotations) => {
+                if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+                    for (id, annotation) in annotations {
+                        netwayste.try_send(NetwaysteEvent::ShareAnnotation(id, annotation));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
 
-                main_spritebatch.add(p);
+    /// Moves to a new room once the Create Room button in the room lobby is clicked. The button is
+    /// only enabled while the room name is one the server will take.
+    fn submit_create_room_form(&mut self) {
+        let create_room = &self.ui_layout.create_room;
+        let layer = match self.ui_layout.layers.get_mut(&Screen::InRoom) {
+            Some(layer) => layer,
+            None => return,
+        };
+        create_room.form.update(layer);
+        if !create_room.form.take_click(create_room.create) {
+            return;
+        }
+        let room_name = match create_room.form.text(layer, create_room.room_name) {
+            Some(room_name) => room_name,
+            None => return,
+        };
+        if let Ok(tf) = create_room.form.widget_mut(layer, create_room.room_name) {
+            tf.clear();
+        }
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            if self.in_room {
+                netwayste.try_send(NetwaysteEvent::LeaveRoom);
             }
-        });
+            netwayste.try_send(NetwaysteEvent::NewRoom(room_name.clone()));
+            netwayste.try_send(NetwaysteEvent::JoinRoom(room_name));
+        }
+    }
 
-        let mut insert_mode = None;
-        GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id).map(
-            |gamearea| {
-                insert_mode = gamearea.insert_mode();
-            },
-        )?;
+    /// Whether the Conway soup runs behind `screen`.
+    fn shows_menu_background(&self, screen: Screen) -> bool {
+        let is_menu = match screen {
+            Screen::Menu | Screen::Options | Screen::ServerList | Screen::Stats => true,
+            _ => false,
+        };
+        is_menu && self.config.get().video.menu_background
+    }
 
-        // TODO: truncate if outside of writable region
-        // TODO: move to new function
-        if let Some((ref grid, width, height)) = insert_mode {
-            let unwritable_flash_on = timer::time_since_start(ctx).subsec_millis() % 250 < 125; // 50% duty cycle, 250ms period
+    /// Whether nothing on screen has changed since the last frame was drawn, as on a menu nobody
+    /// is using, so drawing the frame can be skipped. Screens with a running universe, or
+    /// anything else that moves on its own like the soup behind the menus, are never idle.
+    fn screen_is_idle(&self, screen: Screen) -> bool {
+        let is_menu = match screen {
+            Screen::Menu | Screen::Options | Screen::ServerList | Screen::Stats => true,
+            _ => false,
+        };
+        let drawn_recently = match self.last_drawn {
+            Some((drawn_screen, drawn_at)) => drawn_screen == screen && drawn_at.elapsed() < IDLE_REDRAW_INTERVAL,
+            None => false,
+        };
+        let layering_changed = self
+            .ui_layout
+            .get_screen_layering(screen)
+            .map_or(true, |layering| layering.is_dirty());
+        is_menu
+            && !self.shows_menu_background(screen)
+            && drawn_recently
+            && !layering_changed
+            && self.toast.is_none()
+            && !self.console.visible
+            && !self.debug_overlay.visible
+            && !self.help.visible
+            && !self.inspector.visible
+    }
 
-            if self.uni_draw_params.player_id < 0 {
-                return Err(format!("Unexpected player ID {}", self.uni_draw_params.player_id).into());
+    /// Draws the hints of what the keys do on `screen`, next to the board, the chat, or the focused
+    /// widget.
+    fn draw_help(&self, ctx: &mut Context, screen: Screen) -> GameResult<()> {
+        let scope = if screen == Screen::Run {
+            Scope::Game
+        } else {
+            Scope::Menus
+        };
+        let layering = self.ui_layout.get_screen_layering(screen);
+        let anchor = |near| {
+            let id = match near {
+                Near::Board => Some(&self.static_node_ids.game_area_id),
+                Near::Chat => Some(&self.static_node_ids.chatbox_pane_id),
+                Near::Focus => layering.and_then(|layering| layering.focused_widget_id()),
+                Near::Screen => None,
+            }?;
+            layering
+                .and_then(|layering| layering.get_widget(id).ok())
+                .map(|widget| widget.rect())
+        };
+        self.help.draw(ctx, self.system_font.clone(), scope, anchor)
+    }
+
+    /// How many cells the player has placed on the board so far, or None if the game area can't be
+    /// found.
+    fn cells_placed(&self) -> Option<usize> {
+        match GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id) {
+            Ok(game_area) => Some(game_area.cells_placed()),
+            Err(e) => {
+                error!("Could not get the game area: {:?}", e);
+                None
             }
-            let player_cell_state = CellState::Alive(Some(self.uni_draw_params.player_id as usize));
-            let player_color = self.color_settings.get_color(Some(player_cell_state));
-            if let Some(cursor_cell) = viewport.game_coords_from_window(self.inputs.mouse_info.position) {
-                let (cursor_col, cursor_row) = (cursor_cell.col, cursor_cell.row);
-                grid.each_set(|grid_col, grid_row| {
-                    let col = (grid_col + cursor_col) as isize - width as isize / 2;
-                    let row = (grid_row + cursor_row) as isize - height as isize / 2;
-                    if col < 0 || row < 0 {
-                        // out of range
-                        return;
-                    }
-                    let (col, row) = (col as usize, row as usize);
-                    if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
-                        let mut color = player_color;
-                        // only error is due to player_id out of range, so unwrap OK here
-                        if !universe
-                            .writable(col, row, self.uni_draw_params.player_id as usize)
-                            .unwrap()
-                        {
-                            // not writable, so draw flashing red cells
-                            if unwritable_flash_on {
-                                color = *constants::colors::INSERT_PATTERN_UNWRITABLE;
-                            } else {
-                                return;
-                            }
-                        }
-                        color.a = 0.5; // semi-transparent since this is an overlay
-                        let p = graphics::DrawParam::new()
-                            .dest(Point2 { x: rect.x, y: rect.y })
-                            .scale(Vector2 { x: rect.w, y: rect.h })
-                            .color(color);
+        }
+    }
+
+    /// Begins keeping track of the networked game that just started, to record it once it ends.
+    fn start_match(&mut self) {
+        let room = self.room_name.clone().unwrap_or_default();
+        // the roster sent just before the game started has everyone playing in it, bots included
+        let players = self
+            .roster
+            .iter()
+            .filter(|player| player.index().is_some())
+            .map(|player| player.name().to_owned())
+            .collect();
+        self.current_match = Some(history::MatchInProgress::new(
+            room,
+            players,
+            self.cells_placed().unwrap_or(0),
+        ));
+    }
+
+    /// Asks the server for its leaderboard, and for the current room's if in one. The old ones are
+    /// forgotten, so that the screen says it's waiting until the new ones arrive.
+    fn request_leaderboards(&mut self) {
+        self.leaderboards.clear();
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            netwayste.try_send(NetwaysteEvent::GetLeaderboard(None));
+            if let Some(ref room_name) = self.room_name {
+                netwayste.try_send(NetwaysteEvent::GetLeaderboard(Some(room_name.clone())));
+            }
+        }
+    }
+
+    /// Adds the networked game that just ended with `outcome` to the match history.
+    fn record_match(&mut self, outcome: &GameOutcome) {
+        let game = match self.current_match.take() {
+            Some(game) => game,
+            None => return, // its start wasn't seen
+        };
+        let generations = self
+            .net_game
+            .as_ref()
+            .map(|net_game| net_game.universe().latest_gen())
+            .unwrap_or(0);
+        let record = game.finish(
+            &self.config.get().user.name,
+            outcome,
+            generations,
+            self.cells_placed().unwrap_or(0),
+            chrono::Local::now(),
+        );
+        self.history.add(record);
+    }
+
+    /// Saves the pattern editor's pattern to the pattern library under the name typed in for it,
+    /// and makes it the pattern placed by clicking in the game. How it went is shown as a toast.
+    fn save_edited_pattern(&mut self) {
+        let name = self
+            .ui_layout
+            .get_screen_layering(Screen::PatternEditor)
+            .and_then(|layer| layer.widget_id_by_name("pattern-name").cloned())
+            .and_then(|id| {
+                TextField::widget_from_screen_and_id(&self.ui_layout, Screen::PatternEditor, &id)
+                    .ok()
+                    .and_then(|tf| tf.text())
+            })
+            .unwrap_or_default();
+        let rle = match self.ui_layout.pattern_editor.lock().unwrap().to_rle(name.trim()) {
+            Some(rle) => rle,
+            None => {
+                self.toast = Some((tr("editor-empty"), Instant::now()));
+                return;
+            }
+        };
+
+        let message = match pattern_editor::save_to_library(&name, &rle) {
+            Ok(path) => {
+                info!("Saved the edited pattern to {:?}", path);
+                // unwrap OK because to_rle writes a valid pattern file
+                let pattern: PatternFile = rle.parse().unwrap();
+                self.set_stamp(name.trim(), pattern);
+                tr_args("editor-saved", &[("path", &shown_path(&self.config, &path))])
+            }
+            Err(e) => tr_args("editor-save-failed", &[("error", &e)]),
+        };
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// Makes `pattern`, called `name`, the one placed by clicking in the game.
+    fn set_stamp(&mut self, name: &str, pattern: PatternFile) {
+        match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => {
+                if let Err(e) = game_area.set_stamp(&pattern) {
+                    error!("Could not use the pattern for placing: {}", e);
+                    return;
+                }
+            }
+            Err(e) => {
+                error!("Could not find the game area to place the pattern in: {:?}", e);
+                return;
+            }
+        }
+        self.stamp = Some((name.to_owned(), pattern));
+    }
+
+    /// Loads the pattern library for the library screen just opened, on the registry's thread.
+    /// It's read again each time, so that patterns added since are listed.
+    fn open_pattern_library(&mut self, ctx: &mut Context) {
+        self.registry
+            .load(ctx, vec![(LIBRARY_KEY.to_owned(), loader::Job::Patterns)]);
+    }
+
+    /// Lists the patterns of the library once the registry has loaded it. The thumbnails not loaded
+    /// already are loaded, or drawn the first time they are needed, by the registry's thread, and
+    /// shown once they all are.
+    fn show_loaded_library(&mut self, ctx: &mut Context) {
+        let registry = &self.registry;
+        let mut jobs = vec![];
+        self.library = registry
+            .patterns(LIBRARY_KEY)
+            .unwrap_or(&[])
+            .iter()
+            .map(|pattern| {
+                let key = thumbnail_key(pattern);
+                let thumbnail = registry.image(&key).map(|image| Texture::whole(image.clone()));
+                if thumbnail.is_none() {
+                    jobs.push((key, loader::Job::Thumbnail(pattern.clone())));
+                }
+                (pattern.clone(), thumbnail)
+            })
+            .collect();
+        if !jobs.is_empty() {
+            self.registry.load(ctx, jobs);
+            self.thumbnails_due = true;
+        }
+        self.library_list = None;
+        self.list_library_patterns(ctx);
+    }
+
+    /// Puts the thumbnails the registry has finished loading in the library, and lists its
+    /// patterns again to show them.
+    fn show_loaded_thumbnails(&mut self, ctx: &mut Context) {
+        let registry = &self.registry;
+        for (pattern, thumbnail) in self.library.iter_mut().filter(|(_, thumbnail)| thumbnail.is_none()) {
+            *thumbnail = registry
+                .image(&thumbnail_key(pattern))
+                .map(|image| Texture::whole(image.clone()));
+        }
+        self.unlist_library_patterns();
+        self.list_library_patterns(ctx);
+    }
+
+    /// Lists the patterns in the library that match the search and category, if they are not the
+    /// ones listed already.
+    fn list_library_patterns(&mut self, ctx: &mut Context) {
+        let search = self
+            .ui_layout
+            .get_screen_layering(Screen::PatternLibrary)
+            .and_then(|layer| layer.widget_id_by_name("search").cloned())
+            .and_then(|id| {
+                TextField::widget_from_screen_and_id(&self.ui_layout, Screen::PatternLibrary, &id)
+                    .ok()
+                    .and_then(|tf| tf.text())
+            })
+            .unwrap_or_default();
+        let category = *self.ui_layout.library_filter.lock().unwrap();
+        if let Some((ref listed_search, listed_category, _)) = self.library_list {
+            if *listed_search == search && listed_category == category {
+                return;
+            }
+        }
+        self.unlist_library_patterns();
+
+        let rows: Vec<(usize, Option<Texture>, String, String)> = self
+            .library
+            .iter()
+            .enumerate()
+            .filter(|(_, (pattern, _))| pattern.matches(&search, category))
+            .map(|(i, (pattern, thumbnail))| {
+                let category_text = tr(&format!("library-category-{}", pattern.category.name()));
+                let description = if pattern.path.is_some() {
+                    tr_args("library-yours", &[("category", &category_text)])
+                } else {
+                    category_text
+                };
+                (i, thumbnail.clone(), pattern.name.clone(), description)
+            })
+            .collect();
+        match self
+            .ui_layout
+            .add_pattern_list(
+                ctx,
+                self.system_font.clone(),
+                Screen::PatternLibrary,
+                &rows,
+                &self.picked_pattern,
+            )
+        {
+            Ok(list_id) => self.library_list = Some((search, category, list_id)),
+            Err(e) => error!("Could not list the patterns: {:?}", e),
+        }
+    }
+
+    /// Removes the list of library patterns, if there is one.
+    fn unlist_library_patterns(&mut self) {
+        if let Some((_, _, list_id)) = self.library_list.take() {
+            if let Some(layer) = self.ui_layout.get_screen_layering_mut(Screen::PatternLibrary) {
+                if let Err(e) = layer.remove_widget(list_id) {
+                    error!("Could not remove the old list of patterns: {:?}", e);
+                }
+            }
+        }
+    }
 
-                        overlay_spritebatch.add(p);
+    /// Makes the pattern picked from the library's list the one placed by clicking in the game,
+    /// and goes back to the screen the library was opened from.
+    fn place_library_pattern(&mut self, index: usize) {
+        let pattern = match self.library.get(index) {
+            Some((pattern, _)) => pattern.clone(),
+            None => return,
+        };
+        self.set_stamp(&pattern.name, pattern.pattern.clone());
+        self.toast = Some((tr_args("library-picked", &[("name", &pattern.name)]), Instant::now()));
+        if self.get_current_screen() == Screen::PatternLibrary {
+            self.screen_stack.pop();
+        }
+    }
+
+    /// Opens a file dropped on the window. An RLE or plaintext pattern file becomes the pattern placed
+    /// by clicking in the game. A server's saved rooms are turned down with a toast saying where they
+    /// go instead, since only the server can restore them, and other files with one saying what can be
+    /// dropped.
+    fn open_dropped_file(&mut self, path: &path::Path) {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let len = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                let message = tr_args("drop-failed", &[("file", &file_name), ("error", &e.to_string())]);
+                self.toast = Some((message, Instant::now()));
+                return;
+            }
+        };
+        let message = match pattern_import::check_dropped_file(&file_name, len) {
+            DroppedFile::Pattern => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                let result = fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| pattern_import::parse(&stem, &text));
+                match result {
+                    Ok(imported) => {
+                        let name = imported.pattern.name.clone();
+                        info!("Placing the pattern {:?} dropped from {:?}", name, path);
+                        self.set_stamp(&name, imported.pattern.pattern);
+                        tr_args("drop-placing", &[("name", &name)])
+                    }
+                    Err(e) => tr_args("drop-failed", &[("file", &file_name), ("error", &e)]),
+                }
+            }
+            DroppedFile::TooLarge => {
+                let error = format!("larger than {} KiB", pattern_import::MAX_IMPORT_LEN / 1024);
+                tr_args("drop-failed", &[("file", &file_name), ("error", &error)])
+            }
+            DroppedFile::SavedRooms => tr_args("drop-saved-rooms", &[("file", &file_name)]),
+            DroppedFile::Unknown => tr_args("drop-unknown", &[("file", &file_name)]),
+        };
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// Starts downloading the pattern at the URL typed on the import screen, unless one is being
+    /// downloaded already.
+    fn start_import(&mut self) {
+        if self.offline {
+            self.toast = Some((tr("import-offline"), Instant::now()));
+            return;
+        }
+        if self.import.is_some() {
+            self.toast = Some((tr("import-busy"), Instant::now()));
+            return;
+        }
+        let url = self
+            .ui_layout
+            .get_screen_layering(Screen::PatternImport)
+            .and_then(|layer| layer.widget_id_by_name("url").cloned())
+            .and_then(|id| {
+                TextField::widget_from_screen_and_id(&self.ui_layout, Screen::PatternImport, &id)
+                    .ok()
+                    .and_then(|tf| tf.text())
+            })
+            .unwrap_or_default();
+        match pattern_import::Download::start(&url) {
+            Ok(download) => {
+                info!("Importing a pattern from {}", download.url());
+                self.import = Some(download);
+            }
+            Err(e) => self.toast = Some((tr_args("import-failed", &[("error", &e)]), Instant::now())),
+        }
+    }
+
+    /// Once the pattern being downloaded has arrived, saves it to the pattern library and makes it
+    /// the one placed by clicking in the game, then goes back to the library, which lists it.
+    fn poll_import(&mut self) {
+        let result = match self.import.as_mut().and_then(|download| download.poll()) {
+            Some(result) => result,
+            None => return,
+        };
+        self.import = None;
+        let imported = match result {
+            Ok(imported) => imported,
+            Err(e) => {
+                warn!("Could not import the pattern: {}", e);
+                self.toast = Some((tr_args("import-failed", &[("error", &e)]), Instant::now()));
+                return;
+            }
+        };
+        let name = imported.pattern.name.clone();
+        let message = match pattern_editor::save_to_library(&name, &imported.rle) {
+            Ok(path) => {
+                info!("Saved the imported pattern {:?} to {:?}", name, path);
+                self.set_stamp(&name, imported.pattern.pattern);
+                if self.get_current_screen() == Screen::PatternImport {
+                    self.screen_stack.pop();
+                }
+                tr_args("import-saved", &[("name", &name)])
+            }
+            Err(e) => tr_args("import-save-failed", &[("name", &name), ("error", &e)]),
+        };
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// Asks the server for the shared patterns, newest first, skipping the newest `offset`. The ones
+    /// listed are forgotten, so that the screen says it's waiting until the new ones arrive.
+    fn request_shared_patterns(&mut self, offset: u32) {
+        self.shared_page = None;
+        if let Some(list_id) = self.shared_list_id.take() {
+            if let Some(layer) = self.ui_layout.get_screen_layering_mut(Screen::SharedPatterns) {
+                if let Err(e) = layer.remove_widget(list_id) {
+                    error!("Could not remove the old list of shared patterns: {:?}", e);
+                }
+            }
+        }
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            netwayste.try_send(NetwaysteEvent::ListSharedPatterns(offset));
+        }
+    }
+
+    /// Lists the shared patterns that arrived from the server, once they have.
+    fn list_shared_patterns(&mut self, ctx: &mut Context) {
+        let rows: Vec<(usize, Option<Texture>, String, String)> = match self.shared_page {
+            Some((_, _, ref patterns)) => patterns
+                .iter()
+                .enumerate()
+                .map(|(i, info)| {
+                    let description = tr_args(
+                        "shared-by",
+                        &[
+                            ("author", &info.author),
+                            ("width", &info.width),
+                            ("height", &info.height),
+                        ],
+                    );
+                    (i, None, info.name.clone(), description)
+                })
+                .collect(),
+            None => return,
+        };
+        match self.ui_layout.add_pattern_list(
+            ctx,
+            self.system_font.clone(),
+            Screen::SharedPatterns,
+            &rows,
+            &self.picked_shared,
+        ) {
+            Ok(list_id) => self.shared_list_id = Some(list_id),
+            Err(e) => error!("Could not list the shared patterns: {:?}", e),
+        }
+    }
+
+    /// Acts on a button of the shared patterns screen: shares the pattern picked for placing, or
+    /// asks for newer or older shared patterns.
+    fn handle_shared_patterns_request(&mut self, request: SharedPatternsRequest) {
+        let (offset, total) = match self.shared_page {
+            Some((offset, total, _)) => (offset, total),
+            None => (0, 0),
+        };
+        let page_len = SHARED_PATTERN_PAGE_LEN as u32;
+        match request {
+            SharedPatternsRequest::Share => self.share_pattern(),
+            SharedPatternsRequest::PreviousPage if offset > 0 => {
+                self.request_shared_patterns(offset.saturating_sub(page_len))
+            }
+            SharedPatternsRequest::NextPage if offset + page_len < total => {
+                self.request_shared_patterns(offset + page_len)
+            }
+            _ => {} // no more that way
+        }
+    }
+
+    /// Sends the pattern picked for placing to the server, for everyone there to download. Only
+    /// patterns small enough for the server to take are sent.
+    fn share_pattern(&mut self) {
+        let (name, rle) = match self.stamp {
+            Some((ref name, ref pattern)) => (name.clone(), pattern.to_string()),
+            None => {
+                self.toast = Some((tr("shared-nothing-picked"), Instant::now()));
+                return;
+            }
+        };
+        if rle.len() > MAX_SHARED_PATTERN_LEN {
+            self.toast = Some((tr_args("shared-too-large", &[("name", &name)]), Instant::now()));
+            return;
+        }
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            netwayste.try_send(NetwaysteEvent::SharePattern(name, rle));
+        }
+    }
+
+    /// Asks the server for the shared pattern picked from the list. It's saved when it arrives.
+    fn download_shared_pattern(&mut self, index: usize) {
+        let pattern_id = match self.shared_page {
+            Some((_, _, ref patterns)) => match patterns.get(index) {
+                Some(info) => info.pattern_id,
+                None => return,
+            },
+            None => return,
+        };
+        if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+            netwayste.try_send(NetwaysteEvent::GetSharedPattern(pattern_id));
+        }
+    }
+
+    /// The room lobby's list of the other players in the room.
+    fn roster_list_id(&self) -> Option<NodeId> {
+        self.ui_layout
+            .get_screen_layering(Screen::InRoom)
+            .and_then(|layer| layer.widget_id_by_name("roster").cloned())
+    }
+
+    /// Lists the other players in the room, as the server last listed them, in the room lobby.
+    fn list_roster(&mut self) {
+        let own_name = &self.config.get().user.name;
+        let players = self
+            .roster
+            .iter()
+            .map(|player| player.name().to_owned())
+            .filter(|name| name != own_name)
+            .collect();
+        let roster_id = match self.roster_list_id() {
+            Some(id) => id,
+            None => return,
+        };
+        match PlayerList::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::InRoom, &roster_id) {
+            Ok(list) => list.set_players(players),
+            Err(e) => error!("Could not list the room's players: {:?}", e),
+        }
+    }
+
+    /// Colors the cells of everyone in the running game: players on a team in shades of their
+    /// team's color, and the rest in the color the server gave them.
+    fn color_roster(&mut self) {
+        let mut teams = vec![];
+        for player in self.roster.iter() {
+            let player_index = match player.index() {
+                Some(index) => index as usize,
+                None => continue, // not playing
+            };
+            if let Some(team) = player.team() {
+                teams.push((player_index, team));
+            } else if let Some(&color) = self.room_colors.get(player.name()) {
+                self.color_settings.set_player_color(player_index, color);
+            }
+        }
+        self.color_settings.apply_team_colors(&teams);
+    }
+
+    /// Saves a pattern downloaded from the server to the pattern library, and makes it the one
+    /// placed by clicking in the game. How it went is shown as a toast.
+    fn save_shared_pattern(&mut self, info: SharedPatternInfo, rle: String) {
+        let pattern: PatternFile = match rle.parse() {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                let error = e.to_string();
+                self.toast = Some((
+                    tr_args("shared-save-failed", &[("name", &info.name), ("error", &error)]),
+                    Instant::now(),
+                ));
+                return;
+            }
+        };
+        let message = match pattern_editor::save_to_library(&info.name, &rle) {
+            Ok(path) => {
+                info!("Saved the shared pattern {:?} to {:?}", info.name, path);
+                self.set_stamp(&info.name, pattern);
+                tr_args(
+                    "shared-downloaded",
+                    &[("name", &info.name), ("author", &info.author)],
+                )
+            }
+            Err(e) => tr_args("shared-save-failed", &[("name", &info.name), ("error", &e)]),
+        };
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// Rebuilds the puzzle select screen, so that it shows which puzzles the active profile has
+    /// solved.
+    fn list_puzzles(&mut self, ctx: &mut Context) {
+        let script_names: Vec<String> = self.scripts.iter().map(|script| script.name().to_owned()).collect();
+        if let Err(e) = self.ui_layout.rebuild_screen(
+            ctx,
+            &self.config,
+            self.system_font.clone(),
+            &script_names,
+            Screen::Puzzles,
+            &mut self.static_node_ids,
+        ) {
+            error!("Could not rebuild the puzzle select screen: {:?}", e);
+            return;
+        }
+        self.add_puzzle_list(ctx);
+    }
+
+    /// Adds the list of puzzles to the puzzle select screen, marking the puzzle of the day.
+    fn add_puzzle_list(&mut self, ctx: &mut Context) {
+        let player = &self.config.get().user.name;
+        let daily = puzzle::daily(self.puzzles.len(), chrono::Local::today().naive_local());
+        let rows: Vec<(String, String)> = self
+            .puzzles
+            .iter()
+            .enumerate()
+            .map(|(i, puzzle)| {
+                let status = match self.puzzle_progress.best(player, &puzzle.id) {
+                    Some(solution) => tr_args(
+                        "puzzles-solved",
+                        &[
+                            ("cells", &solution.cells_placed),
+                            ("generations", &solution.generations),
+                        ],
+                    ),
+                    None => tr("puzzles-unsolved"),
+                };
+                if Some(i) == daily {
+                    (puzzle.title.clone(), format!("{} - {}", tr("puzzles-daily"), status))
+                } else {
+                    (puzzle.title.clone(), status)
+                }
+            })
+            .collect();
+        if let Err(e) = self
+            .ui_layout
+            .add_puzzle_list(ctx, self.system_font.clone(), &rows, &self.picked_puzzle)
+        {
+            error!("Could not list the puzzles: {:?}", e);
+        }
+    }
+
+    /// Starts puzzle number `index` in the game area. The universe that was there is kept to be put
+    /// back when the puzzle is left.
+    fn start_puzzle(&mut self, index: usize) {
+        if self.net_game.is_some() {
+            info!("Not starting a puzzle during a networked game");
+            return;
+        }
+        let puzzle = &self.puzzles[index];
+        let (uni, attempt) = match puzzle.universe().and_then(|uni| Ok((uni, puzzle::Attempt::new(index, puzzle)?))) {
+            Ok(started) => started,
+            Err(e) => {
+                error!("Could not set up the universe for puzzle {:?}: {:?}", puzzle.id, e);
+                return;
+            }
+        };
+        let game_area_id = self.static_node_ids.game_area_id.clone();
+        match GameArea::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &game_area_id) {
+            Ok(game_area) => {
+                self.puzzle_attempt = Some(attempt);
+                self.sandbox_uni = Some(game_area.swap_universe(uni, true));
+            }
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+                return;
+            }
+        }
+
+        // Center the view on the board
+        let region = puzzle.region();
+        let cell_size = self.viewport.get_cell_size();
+        let view = self.viewport.get_rect();
+        let center_col = region.left() as f32 + region.width() as f32 / 2.0;
+        let center_row = region.top() as f32 + region.height() as f32 / 2.0;
+        self.viewport.set_origin(Point2 {
+            x: view.w / 2.0 - center_col * cell_size,
+            y: view.h / 2.0 - center_row * cell_size,
+        });
+        self.screen_stack.push(Screen::Run);
+    }
+
+    /// Checks the puzzle being played against its goal, and notes it as solved by the active
+    /// profile as soon as it is.
+    fn check_puzzle(&mut self) {
+        let attempt = match self.puzzle_attempt {
+            Some(ref mut attempt) => attempt,
+            None => return,
+        };
+        let game_area =
+            match GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id)
+            {
+                Ok(game_area) => game_area,
+                Err(_) => return,
+            };
+        let puzzle = &self.puzzles[attempt.puzzle];
+        if let Some(solution) = attempt.check(puzzle, &game_area.uni) {
+            info!("Solved puzzle {:?}: {:?}", puzzle.id, solution);
+            self.puzzle_progress
+                .add(&self.config.get().user.name, &puzzle.id, solution);
+        }
+    }
+
+    /// Puts the game area's own universe back after a puzzle.
+    fn end_puzzle(&mut self) {
+        self.puzzle_attempt = None;
+        let sandbox = match self.sandbox_uni.take() {
+            Some(sandbox) => sandbox,
+            None => return,
+        };
+        match GameArea::widget_from_screen_and_id_mut(
+            &mut self.ui_layout,
+            Screen::Run,
+            &self.static_node_ids.game_area_id,
+        ) {
+            Ok(game_area) => {
+                game_area.swap_universe(sandbox, false);
+            }
+            Err(e) => error!("failed to look up GameArea widget: {:?}", e),
+        }
+    }
+
+    /// Moves the tutorial along when the player does what the current step asks, and remembers in
+    /// the config once it's finished so it isn't shown again.
+    fn advance_tutorial(&mut self) {
+        if !self.tutorial.is_running() {
+            return;
+        }
+        let cells_placed = match self.cells_placed() {
+            Some(cells_placed) => cells_placed,
+            None => return,
+        };
+        let observed = tutorial::Observed {
+            cells_placed,
+            cell_size: self.viewport.get_cell_size(),
+        };
+        if self.tutorial.update(observed) {
+            info!("Tutorial finished");
+            self.config.modify(|settings| {
+                settings.gameplay.tutorial_done = true;
+            });
+        }
+    }
+
+    /// The point the tutorial's bubble points at for the current step: the middle of the board, or
+    /// the top of the chat's text field.
+    fn tutorial_target(&self) -> Option<Point2<f32>> {
+        let rect = match self.tutorial.step().target() {
+            tutorial::Target::Board => {
+                GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id)
+                    .map(|game_area| game_area.rect())
+            }
+            tutorial::Target::ChatField => {
+                TextField::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.chatbox_tf_id)
+                    .map(|tf| {
+                        let rect = tf.rect();
+                        graphics::Rect::new(rect.x, rect.y, rect.w, 0.0)
+                    })
+            }
+        };
+        match rect {
+            Ok(rect) => Some(Point2 {
+                x: rect.x + rect.w / 2.0,
+                y: rect.y + rect.h / 2.0,
+            }),
+            Err(e) => {
+                error!("Could not find what the tutorial points at: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Draws the board, through the glow and scanlines if they're switched on.
+    fn draw_game_of_life(&self, ctx: &mut Context, universe: &Universe) -> Result<(), Box<dyn Error>> {
+        let video = &self.config.get().video;
+        self.effects
+            .borrow_mut()
+            .draw_through(ctx, video.glow, video.scanlines, |ctx| self.draw_board(ctx, universe))
+    }
+
+    fn draw_board(&self, ctx: &mut Context, universe: &Universe) -> Result<(), Box<dyn Error>> {
+        let viewport = if self.uni_draw_params.player_id >= 0 {
+            &self.viewport
+        } else {
+            // intro
+            &self.intro_viewport
+        };
+        let viewport_rect = viewport.get_rect();
+
+        // grid background
+        let rectangle = graphics::Mesh::new_rectangle(
+            ctx,
+            GRID_DRAW_STYLE.to_draw_mode(),
+            graphics::Rect::new(0.0, 0.0, viewport_rect.w, viewport_rect.h),
+            self.uni_draw_params.bg_color,
+        )?;
+        graphics::draw(ctx, &rectangle, DrawParam::new().dest(viewport_rect.point()))?;
+
+        // grid foreground (dead cells)
+        let full_rect = viewport.get_rect_from_origin();
+
+        // The cells go in chunks or the batch kept for drawing them, unless comparing with the old
+        // way of making a new batch for them and another for the overlay every frame. The
+        // overlay goes in the kept batch, after any cells in it.
+        let mut kept_batch = self.cell_batch.borrow_mut();
+        let mut per_frame_batches = None;
+        let (main_batch, overlay_batch) = match self.cell_rendering {
+            CellRendering::Chunked | CellRendering::Instanced => {
+                kept_batch.clear();
+                (&mut *kept_batch, None)
+            }
+            CellRendering::PerFrame => {
+                let (main_batch, overlay_batch) =
+                    per_frame_batches.get_or_insert((CellBatch::new(ctx)?, CellBatch::new(ctx)?));
+                (main_batch, Some(overlay_batch))
+            }
+        };
+        let draw_cell_marks = self.config.get().accessibility.cell_marks && self.uni_draw_params.player_id >= 0;
+
+        // grid non-dead cells (walls, players, etc.)
+        let visibility = if self.uni_draw_params.player_id >= 0 {
+            Some(self.uni_draw_params.player_id as usize)
+        } else {
+            // used for random coloring in intro
+            Some(0)
+        };
+
+        // In the intro every cell gets a random color each frame, so there is nothing to keep
+        let rendering = match self.cell_rendering {
+            CellRendering::Chunked if self.uni_draw_params.player_id < 0 => CellRendering::Instanced,
+            rendering => rendering,
+        };
+        let mut chunk_cache = self.chunk_cache.borrow_mut();
+        let mut cells_drawn = 0;
+        let mut density = None;
+        if let Some(cells_per_pixel) = viewport.cells_per_pixel() {
+            // too far out for cells to be drawn one by one
+            let color = self.color_settings.get_color(Some(CellState::Alive(None)));
+            let (image, live) = density::density_image(ctx, universe, cells_per_pixel, visibility, color)?;
+            density = Some(image);
+            cells_drawn = live;
+        } else if rendering == CellRendering::Chunked {
+            let colors = &self.color_settings.cell_colors;
+            cells_drawn = chunk_cache.update(ctx, universe, viewport, visibility, colors, draw_cell_marks)?;
+        } else {
+            let region = match rendering {
+                CellRendering::PerFrame => universe.region(),
+                _ => viewport.visible_cells(),
+            };
+            universe.each_non_dead(region, visibility, &mut |col, row, state| {
+                let color = if self.uni_draw_params.player_id >= 0 {
+                    self.color_settings.get_color(Some(state))
+                } else {
+                    self.color_settings.get_random_color()
+                };
+
+                if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
+                    main_batch.add_rect(rect, color);
+                    cells_drawn += 1;
+
+                    if let (true, CellState::Alive(Some(player_index))) = (draw_cell_marks, state) {
+                        let mark_color = colorblind::CellMark::color_on(color);
+                        for mark_rect in colorblind::CellMark::for_player(player_index).rects(rect) {
+                            main_batch.add_rect(mark_rect, mark_color);
+                        }
+                    }
+                }
+            });
+        }
+
+        // lines between the cells, unless they'd be the color of the dead cells they're drawn over;
+        // the intro has none on its black board
+        let board_colors = self.color_settings.board;
+        if board_colors.grid != board_colors.dead
+            && self.uni_draw_params.player_id >= 0
+            && density.is_none()
+            && viewport.get_cell_size() >= MIN_GRID_LINE_CELL_SIZE
+        {
+            for line in viewport.grid_lines() {
+                main_batch.add_rect(line, board_colors.grid);
+            }
+        }
+
+        // where cells just died, under the cells alive now
+        if self.config.get().video.trails && density.is_none() {
+            let mut trails = self.trails.borrow_mut();
+            trails.update(universe, visibility);
+            trails.each(&mut |col, row, state, alpha| {
+                if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
+                    let mut color = self.color_settings.get_color(Some(state));
+                    color.a *= alpha;
+                    main_batch.add_rect(rect, color);
+                }
+            });
+        }
+
+        let mut insert_mode = None;
+        let mut selection = None;
+        let mut mirror = None;
+        let mut annotations = vec![];
+        let mut annotation_in_progress = None;
+        let mut annotation_tool = AnnotationTool::Off;
+        GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id).map(
+            |gamearea| {
+                insert_mode = gamearea.insert_mode();
+                selection = gamearea.selection();
+                mirror = Some(gamearea.mirror());
+                annotations = gamearea.annotations().iter().cloned().collect();
+                annotation_in_progress = gamearea.annotation_in_progress();
+                annotation_tool = gamearea.annotation_tool();
+            },
+        )?;
+
+        // The pattern to be placed, or in a networked game the cell to be clicked, is shown under
+        // the cursor, with the cells that can't be placed there flashing red
+        // TODO: move to new function
+        let mut overlay = vec![];
+        let ghost = match insert_mode {
+            Some(insert_mode) => Some(insert_mode),
+            None if self.net_game.is_some() && selection.is_none() && self.uni_draw_params.player_id >= 0 => {
+                let mut grid = BitGrid::new(1, 1);
+                grid.modify_bits_in_word(0, 0, 1 << 63, BitOperation::Set);
+                Some((grid, 1, 1))
+            }
+            None => None,
+        };
+        if let Some((ref grid, width, height)) = ghost {
+            let unwritable_flash_on = timer::time_since_start(ctx).subsec_millis() % 250 < 125; // 50% duty cycle, 250ms period
+
+            if self.uni_draw_params.player_id < 0 {
+                return Err(format!("Unexpected player ID {}", self.uni_draw_params.player_id).into());
+            }
+            let player_cell_state = CellState::Alive(Some(self.uni_draw_params.player_id as usize));
+            let player_color = self.color_settings.get_color(Some(player_cell_state));
+            if let Some(cursor_cell) = viewport.game_coords_from_window(self.inputs.mouse_info.position) {
+                let (cursor_col, cursor_row) = (cursor_cell.col, cursor_cell.row);
+                grid.each_set(|grid_col, grid_row| {
+                    let col = (grid_col + cursor_col) as isize - width as isize / 2;
+                    let row = (grid_row + cursor_row) as isize - height as isize / 2;
+                    if col < 0 || row < 0 {
+                        // out of range
+                        return;
+                    }
+                    let (col, row) = (col as usize, row as usize);
+                    if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
+                        let mut color = player_color;
+                        let writable = if self.net_game.is_some() {
+                            net_placeable(self.net_writable, universe, col, row)
+                        } else {
+                            // only error is due to player_id out of range, so unwrap OK here
+                            universe
+                                .writable(col, row, self.uni_draw_params.player_id as usize)
+                                .unwrap()
+                        };
+                        if !writable {
+                            // not writable, so draw flashing red cells
+                            if unwritable_flash_on {
+                                color = self.color_settings.palette.unwritable_color();
+                            } else {
+                                return;
+                            }
+                        }
+                        color.a = 0.5; // semi-transparent since this is an overlay
+                        overlay.push((rect, color));
+                    }
+                });
+            }
+        }
+
+        // the overlay is drawn over the cells, so in the same batch it goes after them
+        let overlay_batch = overlay_batch.unwrap_or(main_batch);
+        for (rect, color) in overlay {
+            overlay_batch.add_rect(rect, color);
+        }
+
+        self.debug_overlay.set_sprites_drawn(cells_drawn);
+        self.debug_overlay.set_cell_rendering(rendering);
+
+        if let Some(clipped_rect) = ui::intersection(full_rect, viewport_rect) {
+            let origin = graphics::DrawParam::new().dest(Point2 { x: 0.0, y: 0.0 });
+            let rectangle = graphics::Mesh::new_rectangle(
+                ctx,
+                GRID_DRAW_STYLE.to_draw_mode(),
+                clipped_rect,
+                self.uni_draw_params.fg_color,
+            )?;
+
+            graphics::draw(ctx, &rectangle, origin)?;
+            if let Some(ref image) = density {
+                graphics::draw(ctx, image, DrawParam::new().dest(viewport.get_origin()))?;
+            } else if rendering == CellRendering::Chunked {
+                chunk_cache.draw(ctx, viewport)?;
+            }
+            match per_frame_batches {
+                Some((ref main_batch, ref overlay_batch)) => {
+                    main_batch.draw(ctx, origin)?;
+                    overlay_batch.draw(ctx, origin)?;
+                }
+                None => kept_batch.draw(ctx, origin)?,
+            }
+
+            if let Some(region) = selection {
+                let cell_size = viewport.get_cell_size();
+                let grid_origin = viewport.get_origin();
+                let selection_rect = graphics::Rect::new(
+                    grid_origin.x + region.left() as f32 * cell_size,
+                    grid_origin.y + region.top() as f32 * cell_size,
+                    region.width() as f32 * cell_size,
+                    region.height() as f32 * cell_size,
+                );
+                if let Some(shown_rect) = ui::intersection(selection_rect, viewport_rect) {
+                    let outline =
+                        graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(2.0), shown_rect, *CELL_SELECTION_COLOR)?;
+                    graphics::draw(ctx, &outline, origin)?;
+                }
+            }
+
+            // the axes that drawn cells are mirrored about, with the symmetry's name by their center
+            if let Some(mirror) = mirror.filter(|mirror| mirror.symmetry != Symmetry::Off) {
+                let cell_size = viewport.get_cell_size();
+                let grid_origin = viewport.get_origin();
+                let (center_col, center_row) = mirror.center();
+                let center = Point2 {
+                    x: grid_origin.x + center_col * cell_size,
+                    y: grid_origin.y + center_row * cell_size,
+                };
+                let vertical_axis = graphics::Rect::new(center.x - 1.0, full_rect.y, 2.0, full_rect.h);
+                let horizontal_axis = graphics::Rect::new(full_rect.x, center.y - 1.0, full_rect.w, 2.0);
+                let axes = match mirror.symmetry {
+                    Symmetry::Horizontal => vec![vertical_axis],
+                    Symmetry::Vertical => vec![horizontal_axis],
+                    Symmetry::FourFold => vec![vertical_axis, horizontal_axis],
+                    _ => {
+                        // a cross marking the center of rotation
+                        let arm = cell_size.max(4.0);
+                        vec![
+                            graphics::Rect::new(center.x - 1.0, center.y - arm, 2.0, arm * 2.0),
+                            graphics::Rect::new(center.x - arm, center.y - 1.0, arm * 2.0, 2.0),
+                        ]
+                    }
+                };
+                for axis in axes {
+                    if let Some(shown_rect) = ui::intersection(axis, viewport_rect) {
+                        let line =
+                            graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), shown_rect, *SYMMETRY_AXIS_COLOR)?;
+                        graphics::draw(ctx, &line, origin)?;
+                    }
+                }
+                if viewport_rect.contains(center) {
+                    let label_pos = Point2 {
+                        x: center.x + 4.0,
+                        y: center.y + 4.0,
+                    };
+                    let label = tr(mirror.symmetry.message_id());
+                    ui::draw_text(ctx, self.system_font.clone(), *SYMMETRY_AXIS_COLOR, label, &label_pos)?;
+                }
+            }
+
+            annotations::draw(ctx, self.system_font.clone(), viewport, &annotations, annotation_in_progress)?;
+            // the annotation tool in use, next to the cursor
+            if annotation_tool != AnnotationTool::Off && !self.hud_hidden {
+                let mouse_pos = self.inputs.mouse_info.position;
+                let label_pos = Point2 {
+                    x: mouse_pos.x + 16.0,
+                    y: mouse_pos.y + 16.0,
+                };
+                let label = tr(annotation_tool.message_id());
+                ui::draw_text(ctx, self.system_font.clone(), *ANNOTATION_COLOR, label, &label_pos)?;
+            }
+        }
+
+        ////////// draw generation counter
+        if self.uni_draw_params.draw_counter && !self.hud_hidden {
+            let gen_counter = universe.latest_gen().to_string();
+            let size = ui::text_size(ctx, self.system_font.clone(), gen_counter.clone());
+            let position = self.hud_position(ctx, self.hud.generation, size);
+            ui::draw_text(ctx, self.system_font.clone(), scheme().gen_counter, gen_counter, &position)?;
+        }
+
+        Ok(())
+    }
+
+    fn center_intro_viewport(&mut self, win_width: f32, win_height: f32) {
+        let grid_width = self.intro_viewport.grid_width();
+        let grid_height = self.intro_viewport.grid_height();
+        let target_center_x = win_width / 2.0 - grid_width / 2.0;
+        let target_center_y = win_height / 2.0 - grid_height / 2.0;
+        self.intro_viewport.set_origin(Point2 {
+            x: target_center_x,
+            y: target_center_y,
+        });
+    }
+
+    /// Draws the intro: the logo fading in over the universe, the version, and how much of the
+    /// resources have loaded.
+    fn draw_intro(&mut self, ctx: &mut Context) -> Result<(), Box<dyn Error>> {
+        self.draw_game_of_life(ctx, &self.intro_uni)?;
+
+        let screen = graphics::screen_coordinates(ctx);
+        if let Some(logo) = self.registry.image("logo") {
+            let alpha = (self.current_intro_duration / INTRO_LOGO_FADE_IN).min(1.0) as f32;
+            let (w, h) = (logo.width() as f32, logo.height() as f32);
+            let scale = INTRO_LOGO_HEIGHT / h;
+            let param = DrawParam::new()
+                .dest(Point2 {
+                    x: screen.w / 2.0 - w * scale / 2.0,
+                    y: screen.h / 4.0 - INTRO_LOGO_HEIGHT / 2.0,
+                })
+                .scale(Vector2 { x: scale, y: scale })
+                .color(Color::new(1.0, 1.0, 1.0, alpha));
+            graphics::draw(ctx, logo, param)?;
+        }
+
+        ui::draw_text(
+            ctx,
+            self.system_font.clone(),
+            scheme().menu_text,
+            format!("v{}", version!()),
+            &Point2 {
+                x: 10.0,
+                y: screen.h - 40.0,
+            },
+        )?;
+        if self.registry.is_loading() {
+            let (done, total) = self.registry.progress();
+            let label = tr_args("intro-loading", &[("done", &done), ("total", &total)]);
+            let origin = Point2 {
+                x: 10.0,
+                y: screen.h - 110.0,
+            };
+            self.draw_progress(ctx, label, done as f32 / total as f32, origin)?;
+        }
+        Ok(())
+    }
+
+    /// Draws one line per score, best first, starting at `origin`.
+    fn draw_standings(&self, ctx: &mut Context, standings: &[PlayerScore], origin: &Point2<f32>) -> GameResult<()> {
+        for (i, standing) in standings.iter().enumerate() {
+            ui::draw_text(
+                ctx,
+                self.system_font.clone(),
+                scheme().menu_text,
+                format!("{}. {} - {}", i + 1, standing.name, standing.score),
+                &Point2 {
+                    x: origin.x,
+                    y: origin.y + 30.0 * i as f32,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draws the totals of the games the player has played, and a chart of the cells they placed on
+    /// each of the last few days.
+    fn draw_stats(&self, ctx: &mut Context) -> GameResult<()> {
+        let today = chrono::Local::today().naive_local();
+        let stats = self.history.stats(&self.config.get().user.name, today);
+        let win_rate = match stats.win_rate() {
+            Some(rate) => tr_args(
+                "stats-win-rate",
+                &[("wins", &stats.wins), ("percent", &((rate * 100.0).round() as u32))],
+            ),
+            None => tr("stats-no-games"),
+        };
+        let lines = [
+            tr("stats-title"),
+            tr_args("stats-games", &[("games", &stats.games)]),
+            win_rate,
+            tr_args("stats-cells-placed", &[("cells", &stats.cells_placed)]),
+            tr_args("stats-cells-by-day", &[("days", &history::CHART_DAYS)]),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            ui::draw_text(
+                ctx,
+                self.system_font.clone(),
+                scheme().menu_text,
+                line.clone(),
+                &Point2 {
+                    x: 100.0,
+                    y: 100.0 + 40.0 * i as f32,
+                },
+            )?;
+        }
+
+        let origin = Point2 { x: 100.0, y: 310.0 };
+        let most = stats.cells_by_day.iter().cloned().max().unwrap_or(0).max(1);
+        let mut builder = graphics::MeshBuilder::new();
+        let axis = graphics::Rect::new(
+            origin.x,
+            origin.y,
+            history::CHART_DAYS as f32 * STATS_BAR_WIDTH * 1.5,
+            STATS_CHART_HEIGHT,
+        );
+        builder.rectangle(DrawMode::stroke(1.0), axis, scheme().menu_text)?;
+        for (day, &cells) in stats.cells_by_day.iter().enumerate() {
+            if cells == 0 {
+                continue;
+            }
+            let height = STATS_CHART_HEIGHT * cells as f32 / most as f32;
+            let bar = graphics::Rect::new(
+                origin.x + (day as f32 * 1.5 + 0.25) * STATS_BAR_WIDTH,
+                axis.bottom() - height,
+                STATS_BAR_WIDTH,
+                height,
+            );
+            builder.rectangle(DrawMode::fill(), bar, scheme().menu_text)?;
+        }
+        let chart = builder.build(ctx)?;
+        graphics::draw(ctx, &chart, DrawParam::default())?;
+        Ok(())
+    }
+
+    /// Draws the server's leaderboard, and beside it the current room's if in one.
+    fn draw_leaderboards(&self, ctx: &mut Context) -> GameResult<()> {
+        let font = self.system_font.clone();
+        ui::draw_text(
+            ctx,
+            font,
+            scheme().menu_text,
+            tr("leaderboards-title"),
+            &Point2 { x: 100.0, y: 100.0 },
+        )?;
+        if self.net_worker.lock().unwrap().is_none() {
+            ui::draw_text(
+                ctx,
+                font,
+                scheme().menu_text,
+                tr("leaderboards-offline"),
+                &Point2 { x: 100.0, y: 150.0 },
+            )?;
+            return Ok(());
+        }
+
+        let scopes = std::iter::once(None).chain(self.room_name.clone().map(Some));
+        for (i, scope) in scopes.enumerate() {
+            let heading = match scope {
+                Some(ref room) => tr_args("leaderboards-room", &[("room", room)]),
+                None => tr("leaderboards-global"),
+            };
+            let mut lines = vec![heading];
+            match self.leaderboards.get(&scope) {
+                None => lines.push(tr("leaderboards-loading")),
+                Some(entries) if entries.is_empty() => lines.push(tr("leaderboards-empty")),
+                Some(entries) => {
+                    lines.push(format!(
+                        "    {:<16} {:>9} {:>8} {:>8}",
+                        tr("leaderboards-player"),
+                        tr("leaderboards-wins"),
+                        tr("leaderboards-cells"),
+                        tr("leaderboards-survival")
+                    ));
+                    for (rank, entry) in entries.iter().enumerate() {
+                        lines.push(format!(
+                            "{:>2}. {:<16} {:>9} {:>8} {:>8}",
+                            rank + 1,
+                            entry.player_name,
+                            entry.wins,
+                            entry.cells_captured,
+                            entry.longest_survival
+                        ));
+                    }
+                }
+            }
+            for (j, line) in lines.into_iter().enumerate() {
+                ui::draw_text(
+                    ctx,
+                    font,
+                    scheme().menu_text,
+                    line,
+                    &Point2 {
+                        x: 100.0 + 550.0 * i as f32,
+                        y: 150.0 + 30.0 * j as f32,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the import screen's title, and how far along the download is if there is one.
+    fn draw_pattern_import(&self, ctx: &mut Context) -> GameResult<()> {
+        ui::draw_text(
+            ctx,
+            self.system_font.clone(),
+            scheme().menu_text,
+            tr("import-title"),
+            &Point2 { x: 100.0, y: 100.0 },
+        )?;
+        let download = match self.import {
+            Some(ref download) => download,
+            None => {
+                return ui::draw_text(
+                    ctx,
+                    self.system_font.clone(),
+                    scheme().menu_text,
+                    tr("import-hint"),
+                    &Point2 { x: 100.0, y: 320.0 },
+                )
+            }
+        };
+        let (received, total) = download.progress();
+        let received_kib = (received + 1023) / 1024;
+        let (label, fraction) = match total {
+            Some(total) if total > 0 => (
+                tr_args(
+                    "import-downloading-of",
+                    &[("received", &received_kib), ("total", &((total + 1023) / 1024))],
+                ),
+                received as f32 / total as f32,
+            ),
+            _ => (tr_args("import-downloading", &[("received", &received_kib)]), 0.0),
+        };
+        self.draw_progress(ctx, label, fraction, Point2 { x: 100.0, y: 320.0 })
+    }
+
+    /// Draws which of the patterns shared on the server are listed, or why none are.
+    fn draw_shared_patterns(&self, ctx: &mut Context) -> GameResult<()> {
+        let font = self.system_font.clone();
+        ui::draw_text(
+            ctx,
+            font,
+            scheme().menu_text,
+            tr("shared-title"),
+            &Point2 { x: 100.0, y: 100.0 },
+        )?;
+        let status = if self.net_worker.lock().unwrap().is_none() {
+            tr("shared-offline")
+        } else {
+            match self.shared_page {
+                None => tr("shared-loading"),
+                Some((_, _, ref patterns)) if patterns.is_empty() => tr("shared-empty"),
+                Some((offset, total, ref patterns)) => tr_args(
+                    "shared-showing",
+                    &[
+                        ("first", &(offset + 1)),
+                        ("last", &(offset + patterns.len() as u32)),
+                        ("total", &total),
+                    ],
+                ),
+            }
+        };
+        ui::draw_text(ctx, font, scheme().menu_text, status, &Point2 { x: 100.0, y: 225.0 })
+    }
+
+    /// Draws the puzzle being played, how far along it is, and whether it's solved.
+    fn draw_puzzle_status(&self, ctx: &mut Context, attempt: &puzzle::Attempt) -> GameResult<()> {
+        const LINE_HEIGHT: f32 = 30.0;
+        let puzzle = &self.puzzles[attempt.puzzle];
+        let goal = &puzzle.goal;
+        let goal_text = match (goal.min_population, goal.max_population) {
+            (Some(min), Some(max)) => tr_args("puzzle-goal-range", &[("min", &min), ("max", &max)]),
+            (Some(min), None) => tr_args("puzzle-goal-min", &[("population", &min)]),
+            (None, Some(max)) => tr_args("puzzle-goal-max", &[("population", &max)]),
+            (None, None) => String::new(), // not allowed in a puzzle file
+        };
+        let status = match attempt.status {
+            puzzle::Status::Playing => tr("puzzle-playing"),
+            puzzle::Status::Solved(_) => tr("puzzle-solved"),
+            puzzle::Status::Failed(puzzle::Failure::TooManyCells) => tr("puzzle-too-many-cells"),
+            puzzle::Status::Failed(puzzle::Failure::OutOfGenerations) => tr("puzzle-out-of-generations"),
+        };
+        let lines = [
+            format!("{}: {}", puzzle.title, puzzle.description),
+            tr_args(
+                "puzzle-progress",
+                &[
+                    ("generation", &attempt.generations),
+                    ("generations", &goal.generations),
+                    ("population", &attempt.population),
+                    ("goal", &goal_text),
+                    ("cells", &attempt.cells_placed),
+                    ("max_cells", &puzzle.max_cells),
+                ],
+            ),
+            status,
+        ];
+        let width = lines
+            .iter()
+            .map(|line| ui::text_size(ctx, self.system_font.clone(), line.clone()).0)
+            .fold(0.0, f32::max);
+        let origin = self.hud_position(ctx, self.hud.status, (width, LINE_HEIGHT * lines.len() as f32));
+        for (i, line) in lines.iter().enumerate() {
+            ui::draw_text(
+                ctx,
+                self.system_font.clone(),
+                scheme().menu_text,
+                line.clone(),
+                &Point2 {
+                    x: origin.x,
+                    y: origin.y + LINE_HEIGHT * i as f32,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draws how much of the universe has arrived while the server sends it in parts, such as
+    /// after joining a game that is already running.
+    fn draw_universe_progress(&self, ctx: &mut Context, received: u8, total: u8) -> GameResult<()> {
+        let fraction = received as f32 / total.max(1) as f32;
+        let label = tr_args("game-loading-universe", &[("percent", &((fraction * 100.0) as u32))]);
+        let origin = self.hud_position(ctx, self.hud.status, (PROGRESS_BAR_WIDTH, 40.0)); // the label and the bar
+        self.draw_progress(ctx, label, fraction, origin)
+    }
+
+    /// Draws `label` at `origin`, over a bar filled to `fraction`.
+    fn draw_progress(&self, ctx: &mut Context, label: String, fraction: f32, origin: Point2<f32>) -> GameResult<()> {
+        ui::draw_text(ctx, self.system_font.clone(), scheme().menu_text, label, &origin)?;
+
+        let bar = graphics::Rect::new(origin.x, origin.y + 30.0, PROGRESS_BAR_WIDTH, 10.0);
+        let outline = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), bar, scheme().menu_text)?;
+        graphics::draw(ctx, &outline, DrawParam::default())?;
+        if fraction > 0.0 {
+            let filled = graphics::Rect::new(bar.x, bar.y, bar.w * fraction, bar.h);
+            let fill = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), filled, scheme().menu_text)?;
+            graphics::draw(ctx, &fill, DrawParam::default())?;
+        }
+        Ok(())
+    }
+
+    /// Draws the GameArea's universe to the screen.
+    fn draw_universe(&mut self, ctx: &mut Context) -> Result<(), Box<dyn Error>> {
+        // A mutable reference is used to notify the first generation is drawn
+        GameArea::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id)
+            .map(|gamearea| {
+            gamearea.first_gen_drawn();
+        })?;
+
+        if self.show_lockstep {
+            if let Some(ref check) = self.lockstep {
+                return self.draw_game_of_life(ctx, check.universe());
+            }
+        }
+        if let Some(ref net_game) = self.net_game {
+            return self.draw_game_of_life(ctx, net_game.universe());
+        }
+
+        // A non-mutable reference is used to draw the universe
+        match GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id) {
+            Ok(gamearea) => {
+                self.draw_game_of_life(ctx, &gamearea.uni)?;
+            }
+            Err(e) => {
+                error!("failed to look up GameArea widget: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn transition_screen(
+        &mut self,
+        ggez_ctx: &mut Context,
+        old_screen: Screen,
+        new_screen: Screen,
+        game_area_state: &mut GameAreaState,
+    ) -> Result<(), Box<dyn Error>> {
+        match old_screen {
+            Screen::Menu => {
+                if new_screen == Screen::Run {
+                    let game_area_id = self.static_node_ids.game_area_id.clone();
+                    if let Some(layering) = self.ui_layout.get_screen_layering_mut(Screen::Run) {
+                        layering.enter_focus(
+                            ggez_ctx,
+                            &mut self.config,
+                            &mut self.screen_stack,
+                            game_area_state,
+                            &mut self.static_node_ids,
+                            &mut self.viewport,
+                            &game_area_id,
+                        )?;
+                    }
+                    game_area_state.running = true;
+                }
+            }
+            Screen::Run => {
+                if new_screen == Screen::Menu {
+                    game_area_state.running = false;
+                }
+            }
+            _ => {}
+        }
+
+        if old_screen != new_screen {
+            // Emit a Save event on the old screen
+            if let Some(layering) = self.ui_layout.get_screen_layering_mut(old_screen) {
+                layering.emit(
+                    &Event::new_save(),
+                    ggez_ctx,
+                    &mut self.config,
+                    &mut self.screen_stack,
+                    game_area_state,
+                    &mut self.static_node_ids,
+                    &mut self.viewport,
+                )?;
+            }
+
+            // Emit a Load event on the new screen
+            if let Some(layering) = self.ui_layout.get_screen_layering_mut(new_screen) {
+                layering.emit(
+                    &Event::new_load(),
+                    ggez_ctx,
+                    &mut self.config,
+                    &mut self.screen_stack,
+                    game_area_state,
+                    &mut self.static_node_ids,
+                    &mut self.viewport,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // update
+    fn receive_net_updates(&mut self) -> GameResult<()> {
+        let net_worker = self.net_worker.clone(); // so the lock doesn't keep `self` borrowed
+        let mut net_worker_guard = net_worker.lock().unwrap();
+        if net_worker_guard.is_none() {
+            return Ok(());
+        }
+
+        let mut incoming_messages = vec![]; // (sender, if another player, message)
+        let mut incoming_lobby_messages = vec![];
+        let mut typing_players = vec![];
+        let log_chat = self.config.get().gameplay.log_chat;
+        let own_name = self.config.get().user.name.clone(); // for noticing mentions in chat
+
+        let net_worker = net_worker_guard.as_mut().unwrap();
+        for edit in self.ui_layout.room_map.lock().unwrap().take_edits() {
+            net_worker.try_send(NetwaysteEvent::EditRoomMap(edit));
+        }
+        for request in self.ui_layout.room_settings.lock().unwrap().take_requests() {
+            net_worker.try_send(request);
+        }
+        for e in net_worker.try_receive().into_iter() {
+            if let NetwaysteEvent::QueueLengths(..) = e {
+                // sent every tick, so it would crowd out everything else
+            } else {
+                crash::note_event(format!("net {:?}", e));
+            }
+            if e == NetwaysteEvent::ConnectionLost {
+                // and out of the room with it, as below
+                warn!("Lost the connection to the server");
+                self.toast = Some((tr("toast-connection-lost"), Instant::now()));
+                self.notify(Notice::Disconnected, tr("notify-disconnected"));
+            }
+            match e {
+                NetwaysteEvent::LoggedIn(server_version, opt_motd) => {
+                    info!("Logged in! Server version: v{}", server_version);
+                    if let Some(motd) = opt_motd {
+                        incoming_messages.push((None, tr_args("chat-server-message", &[("message", &motd)])));
+                    }
+                    self.screen_stack.push(Screen::ServerList); // XXX
+                                                                // do other stuff
+                    net_worker.try_send(NetwaysteEvent::List);
+                    let room = self.invited_room.take().unwrap_or_else(|| "general".to_owned());
+                    net_worker.try_send(NetwaysteEvent::JoinRoom(room));
+                }
+                NetwaysteEvent::JoinedRoom(room_name) => {
+                    println!("Joined Room: {}", room_name);
+                    crash::set_room(Some(room_name.clone()));
+                    self.room_name = Some(room_name);
+                    self.team = None;
+                    self.in_room = true;
+                    self.reported_viewport = None;
+                    if self.screen_stack.last() != Some(&Screen::InRoom) {
+                        self.screen_stack.push(Screen::InRoom); // XXX
+                    }
+                    let [r, g, b] = self.config.profile_color();
+                    net_worker.try_send(NetwaysteEvent::SetColor(RgbColor::new(r, g, b)));
+                    net_worker.try_send(NetwaysteEvent::GetChatHistory);
+                    net_worker.try_send(NetwaysteEvent::List); // who is here, for completing names in chat
+                    self.ui_layout.room_map.lock().unwrap().reset();
+                    self.ui_layout.room_settings.lock().unwrap().reset();
+                    net_worker.try_send(NetwaysteEvent::GetRoomMap);
+                }
+                NetwaysteEvent::PlayerColor(name, color) => {
+                    self.room_colors
+                        .insert(name.clone(), Color::from_rgb(color.r, color.g, color.b));
+                    self.color_roster();
+                    if name == self.config.get().user.name {
+                        let rgb = [color.r, color.g, color.b];
+                        if rgb != self.config.profile_color() {
+                            incoming_messages.push((None, tr("chat-color-adjusted")));
+                        }
+                        let id = self.static_node_ids.color_picker_id.clone();
+                        match ColorPicker::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::InRoom, &id) {
+                            Ok(picker) => picker.color = rgb,
+                            Err(e) => error!("Could not update ColorPicker with assigned color: {:?}", e),
+                        }
+                    } else {
+                        debug!("Player {} has color {:?}", name, color);
+                        // Everyone gets a color on joining, so this is how we hear about newcomers
+                        let mut room_players = self.room_players.lock().unwrap();
+                        if !room_players.contains(&name) {
+                            room_players.push(name);
+                        }
+                    }
+                }
+                NetwaysteEvent::JoinedTeam(team) => {
+                    // the roster that follows has everyone's team, ours included
+                    info!("Joined Team: {}", team + 1);
+                }
+                NetwaysteEvent::PlayerList(list) => {
+                    println!("PlayerList: {:?}", list);
+                    let own_name = &self.config.get().user.name;
+                    *self.room_players.lock().unwrap() = list.into_iter().filter(|name| name != own_name).collect();
+                }
+                NetwaysteEvent::Roster(players) => {
+                    debug!("Roster: {:?}", players);
+                    self.team = players
+                        .iter()
+                        .find(|player| player.name() == own_name)
+                        .and_then(|player| player.team());
+                    self.roster = players;
+                    self.color_roster();
+                    self.list_roster();
+                }
+                NetwaysteEvent::RoomSettings(settings) => {
+                    self.ui_layout.room_settings.lock().unwrap().set(settings);
+                }
+                NetwaysteEvent::Leaderboard(room_name, entries) => {
+                    self.leaderboards.insert(room_name, entries);
+                }
+                NetwaysteEvent::SharedPatternList(offset, total, patterns) => {
+                    self.shared_page = Some((offset, total, patterns));
+                    if let Some(list_id) = self.shared_list_id.take() {
+                        if let Some(layer) = self.ui_layout.get_screen_layering_mut(Screen::SharedPatterns) {
+                            if let Err(e) = layer.remove_widget(list_id) {
+                                error!("Could not remove the old list of shared patterns: {:?}", e);
+                            }
+                        }
+                    }
+                }
+                NetwaysteEvent::SharedPattern(info, rle) => self.save_shared_pattern(info, rle),
+                NetwaysteEvent::RoomMap(map) => {
+                    if let Err(e) = self.ui_layout.room_map.lock().unwrap().set_map(&map) {
+                        error!("Could not show the room's map: {}", e);
+                    }
+                }
+                NetwaysteEvent::RoomMapEdited(edit) => self.ui_layout.room_map.lock().unwrap().apply(&edit),
+                NetwaysteEvent::AnnotationShared(name, id, annotation) => {
+                    if self.config.is_muted(&name) {
+                        continue;
+                    }
+                    match GameArea::widget_from_screen_and_id_mut(
+                        &mut self.ui_layout,
+                        Screen::Run,
+                        &self.static_node_ids.game_area_id,
+                    ) {
+                        Ok(game_area) => game_area.add_shared_annotation(name, id, annotation),
+                        Err(e) => error!("failed to look up GameArea widget: {:?}", e),
+                    }
+                }
+                NetwaysteEvent::PatternShared(_pattern_id, name) => {
+                    self.toast = Some((tr_args("shared-sent", &[("name", &name)]), Instant::now()));
+                    if self.get_current_screen() == Screen::SharedPatterns {
+                        self.request_shared_patterns(0); // to show it at the top
+                    }
+                }
+                NetwaysteEvent::RoomList(list) => {
+                    println!("RoomList: {:?}", list);
+                }
+                NetwaysteEvent::UniverseProgress(received, total) => {
+                    self.universe_progress = Some((received, total));
+                }
+                NetwaysteEvent::UniverseUpdate(diff_part) => {
+                    self.universe_progress = None;
+                    debug!(
+                        "Universe update from generation {} to {}",
+                        diff_part.gen0, diff_part.gen1
+                    );
+                    if self.net_game.is_some() && !self.net_diffs.push(diff_part) {
+                        net_worker.try_send(NetwaysteEvent::ResyncUniverse);
+                    }
+                }
+                NetwaysteEvent::CellsSet(cells, generation) => {
+                    if let Some(ref mut net_game) = self.net_game {
+                        for (col, row) in cells {
+                            net_game.acknowledge(col as usize, row as usize, generation);
+                        }
+                    }
+                }
+                NetwaysteEvent::GameStarted(options, writable) => {
+                    self.universe_progress = None;
+                    self.show_lockstep = false;
+                    self.lockstep = None;
+                    self.net_diffs.clear();
+                    match prediction::Prediction::new(&options) {
+                        Ok(net_game) => self.net_game = Some(net_game),
+                        Err(e) => error!("Could not set up the universe for the game: {:?}", e),
+                    }
+                    self.net_writable = writable.as_ref().map(Region::from);
+                    set_game_area_networked(&mut self.ui_layout, &self.static_node_ids, true);
+                    self.start_match();
+                    let room = self.room_name.clone().unwrap_or_default();
+                    self.notify(Notice::MatchStarting, tr_args("notify-match-starting", &[("room", &room)]));
+                    if self.get_current_screen() != Screen::Run {
+                        self.screen_stack.push(Screen::Run);
+                    }
+                    if self.lockstep_enabled {
+                        match lockstep::LockstepCheck::new(&options) {
+                            Ok(check) => self.lockstep = Some(check),
+                            Err(e) => error!("Could not start lockstep check: {:?}", e),
+                        }
+                    }
+                }
+                NetwaysteEvent::CellInputs(generation, inputs) => {
+                    if let Some(ref mut check) = self.lockstep {
+                        check.add_inputs(generation, inputs);
+                    }
+                }
+                NetwaysteEvent::UniverseHash(universe_hash) => {
+                    if let Some(divergence) = self.lockstep.as_mut().and_then(|check| check.check(universe_hash)) {
+                        self.console.print(format!(
+                            "Lockstep: diverged from the server at generation {}; 'lockstep show' draws it",
+                            divergence.generation
+                        ));
+                    }
+                }
+                NetwaysteEvent::QueueLengths(tx_packets, rx_packets, chat_messages) => {
+                    self.debug_overlay
+                        .set_net_queue_lengths(tx_packets, rx_packets, chat_messages);
+                }
+                NetwaysteEvent::CorruptUpdates(count) => {
+                    self.debug_overlay.set_corrupt_updates(count);
+                }
+                NetwaysteEvent::ChatMessages(msgs) => {
+                    for m in msgs {
+                        if self.config.is_muted(&m.0) {
+                            continue;
+                        }
+                        if log_chat {
+                            chat_log::append(m.2, &m.0, &m.1, chrono::Local::now());
+                        }
+                        let msg = match m.2 {
+                            ChatChannel::Team => format!("[team] {}: {}", m.0, m.1), // as our own are shown
+                            _ => format!("{}: {}", m.0, m.1),
+                        };
+                        println!("{:?}", m); // print to stdout for dbg
+
+                        accessibility::announce(msg.clone());
+                        if notifications::mentions(&m.1, &own_name) {
+                            self.notify(Notice::Mentioned, msg.clone());
+                        }
+                        incoming_messages.push((Some(m.0), msg));
+                    }
+                }
+                NetwaysteEvent::ChatHistory(entries) => {
+                    let now = chrono::Local::now();
+                    for (name, message, age_secs, channel) in entries {
+                        if self.config.is_muted(&name) {
+                            continue;
+                        }
+                        let sent_at = now - chrono::Duration::seconds(age_secs as i64);
+                        if log_chat {
+                            chat_log::append(channel, &name, &message, sent_at);
+                        }
+                        let time = sent_at.format("%H:%M").to_string();
+                        let mut msg = tr_args(
+                            "chat-history-message",
+                            &[("time", &time), ("name", &name), ("message", &message)],
+                        );
+                        if channel == ChatChannel::Team {
+                            msg = format!("[team] {}", msg);
+                        }
+                        incoming_messages.push((Some(name), msg));
+                    }
+                }
+                NetwaysteEvent::PlayerTyping(name) => {
+                    if !self.config.is_muted(&name) {
+                        typing_players.push(name);
+                    }
+                }
+                NetwaysteEvent::LobbyChatMessages(msgs) => {
+                    for m in msgs {
+                        if self.config.is_muted(&m.0) {
+                            continue;
+                        }
+                        if log_chat {
+                            chat_log::append(ChatChannel::Lobby, &m.0, &m.1, chrono::Local::now());
+                        }
+                        let msg = format!("{}: {}", m.0, m.1);
+                        accessibility::announce(msg.clone());
+                        if notifications::mentions(&m.1, &own_name) {
+                            self.notify(Notice::Mentioned, msg.clone());
+                        }
+                        incoming_lobby_messages.push((Some(m.0), msg));
+                    }
+                }
+                NetwaysteEvent::Scores(scores) => {
+                    self.scores = scores;
+                }
+                NetwaysteEvent::GameFinished(outcome) => {
+                    info!("Game finished: {:?}", outcome);
+                    self.record_match(&outcome);
+                    self.scores = vec![];
+                    self.game_outcome = Some(outcome);
+                    self.series_over = false;
+                    self.intermission = None;
+                    self.net_game = None;
+                    self.net_writable = None;
+                    self.net_diffs.clear();
+                    set_game_area_networked(&mut self.ui_layout, &self.static_node_ids, false);
+                    if self.get_current_screen() != Screen::Results {
+                        self.screen_stack.push(Screen::Results);
+                    }
+                }
+                NetwaysteEvent::Intermission(round, rounds, standings) => {
+                    self.intermission = Some((round, rounds, standings));
+                }
+                NetwaysteEvent::SeriesFinished(outcome) => {
+                    info!("Series finished: {:?}", outcome);
+                    self.game_outcome = Some(outcome);
+                    self.series_over = true;
+                    self.intermission = None;
+                    if self.get_current_screen() != Screen::Results {
+                        self.screen_stack.push(Screen::Results);
+                    }
+                }
+                NetwaysteEvent::LeftRoom | NetwaysteEvent::ConnectionLost => {
+                    println!("Left Room");
+                    crash::set_room(None);
+                    self.room_name = None;
+                    self.current_match = None; // left before it ended
+                    self.in_room = false;
+                    self.room_players.lock().unwrap().clear();
+                    self.roster.clear();
+                    self.list_roster();
+                    self.room_colors.clear();
+                    self.universe_progress = None;
+                    self.net_game = None;
+                    self.net_writable = None;
+                    self.net_diffs.clear();
+                    self.ui_layout.room_map.lock().unwrap().reset();
+                    self.ui_layout.room_settings.lock().unwrap().reset();
+                    set_game_area_networked(&mut self.ui_layout, &self.static_node_ids, false);
+                }
+                NetwaysteEvent::BadRequest(error) => {
+                    println!("Server responded with Bad Request: {:?}", error);
+                    if self.get_current_screen() == Screen::SharedPatterns {
+                        // such as a pattern it wouldn't take, or one no longer shared
+                        self.toast = Some((tr_args("shared-refused", &[("error", &error)]), Instant::now()));
+                    } else if self.get_current_screen() == Screen::InRoom {
+                        // such as a map edit by someone other than the host
+                        self.toast = Some((tr_args("room-refused", &[("error", &error)]), Instant::now()));
+                    }
+                }
+                NetwaysteEvent::ServerError(error) => {
+                    println!("Server encountered an error: {:?}", error);
+                }
+                _ => {
+                    panic!(
+                        "Development panic: Unexpected NetwaysteEvent during netwayste receive update: {:?}",
+                        e
+                    );
+                }
+            }
+        }
+
+        let id = self.static_node_ids.chatbox_id.clone();
+        let tabbed_messages = incoming_messages
+            .into_iter()
+            .map(|(sender, msg)| (ROOM_CHAT_TAB, sender, msg))
+            .chain(
+                incoming_lobby_messages
+                    .into_iter()
+                    .map(|(sender, msg)| (LOBBY_CHAT_TAB, sender, msg)),
+            );
+        for (tab, opt_sender, msg) in tabbed_messages {
+            match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+                Ok(cb) => match opt_sender {
+                    Some(sender) => cb.add_message_from(tab, sender, msg),
+                    None => cb.add_message_to_tab(tab, msg),
+                },
+                Err(e) => error!("Could not add message to Chatbox on network message receive: {:?}", e),
+            }
+        }
+        if !typing_players.is_empty() {
+            match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+                Ok(cb) => {
+                    for name in typing_players {
+                        cb.set_typing(ROOM_CHAT_TAB, name);
+                    }
+                }
+                Err(e) => error!("Could not show who is typing in Chatbox: {:?}", e),
+            }
+        }
+
+        // What was picked from the menus opened by right-clicking chat senders, or players in the
+        // room lobby's list. How it went is noted in the chatbox, or in a toast for the lobby.
+        let mut actions = vec![]; // (action, player, picked from the chatbox's bridge tab?)
+        match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => {
+                let from_bridge = cb.shown_tab() == BRIDGE_CHAT_TAB;
+                for (action, name) in cb.take_sender_actions() {
+                    actions.push((action, name, from_bridge));
+                }
+            }
+            Err(e) => error!("Could not get chat sender actions from Chatbox: {:?}", e),
+        }
+        let from_roster = actions.len();
+        if let Some(roster_id) = self.roster_list_id() {
+            match PlayerList::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::InRoom, &roster_id) {
+                Ok(list) => {
+                    for (action, name) in list.take_actions() {
+                        actions.push((action, name, false));
+                    }
+                }
+                Err(e) => error!("Could not get player actions from the room's PlayerList: {:?}", e),
+            }
+        }
+        for (i, (action, name, from_bridge)) in actions.into_iter().enumerate() {
+            let note = match action {
+                SenderAction::Mute => {
+                    self.config.set_muted(&name, true);
+                    tr_args("chat-muted", &[("name", &name)])
+                }
+                SenderAction::Unmute => {
+                    self.config.set_muted(&name, false);
+                    tr_args("chat-unmuted", &[("name", &name)])
+                }
+                SenderAction::Report if from_bridge => {
+                    // not one of the server's players
+                    tr_args("chat-bridge-not-reported", &[("name", &name)])
+                }
+                SenderAction::Report => {
+                    net_worker.try_send(NetwaysteEvent::ReportPlayer(name.clone(), String::new()));
+                    tr_args("chat-reported", &[("name", &name)])
+                }
+            };
+            if let Ok(cb) = Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+                if action == SenderAction::Mute {
+                    cb.remove_messages_from(&name);
+                }
+                if i < from_roster {
+                    let tab = cb.shown_tab();
+                    cb.add_message_to_tab(tab, note.clone());
+                }
+            }
+            if i >= from_roster {
+                self.toast = Some((note, Instant::now()));
+            }
+        }
+        self.debug_overlay
+            .set_lockstep_status(self.lockstep.as_ref().map(|check| check.status()));
+
+        Ok(())
+    }
+
+    /// Applies edits made to the config file while the game is running. Most settings are read from
+    /// the config whenever they are needed, so only those copied elsewhere are applied here. An
+    /// invalid edit leaves the current settings in place and is reported on screen.
+    fn reload_config_if_changed(&mut self) {
+        let changed = match self.config_watcher {
+            Some(ref watcher) => watcher.poll_changed(),
+            None => false,
+        };
+        if !changed {
+            return;
+        }
+        if self.config.is_dirty() {
+            // The file is about to be overwritten with the changes made in the game
+            info!("Config file changed on disk, but ignoring it because there are unsaved changes");
+            return;
+        }
+        if let Err(e) = self.config.reload() {
+            warn!("Could not reload config: {}", e);
+            self.toast = Some((tr_args("toast-config-not-reloaded", &[("error", &e)]), Instant::now()));
+            return;
+        }
+        info!("Reloaded config from {}", self.config.path());
+
+        self.announcer = accessibility::Announcer::new(&self.config.get().accessibility);
+
+        let chat_history = self.config.get().gameplay.chat_history;
+        let id = self.static_node_ids.chatbox_id.clone();
+        match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => cb.set_history_lines(chat_history),
+            Err(e) => error!("Could not resize chat history after config reload: {:?}", e),
+        }
+    }
+
+    /// Rebuilds the screens whose layout files were saved, keeping what was typed into their named
+    /// text fields and the state of their named checkboxes. Only debug builds watch the files.
+    fn reload_layouts_if_changed(&mut self, ctx: &mut Context) {
+        let names = match self.layout_watcher {
+            Some(ref watcher) => watcher.poll_changed(),
+            None => return,
+        };
+        for name in names {
+            if name == "hud" || name == "hud_streamer" {
+                info!("HUD layout {:?} changed; placing the HUD again", name);
+                self.hud = layout_file::HudLayout::load(self.config.get().video.streamer_mode);
+                self.hud_applied = None;
+                continue;
+            }
+            let screen = match name.as_str() {
+                "main_menu" => Screen::Menu,
+                "options" => Screen::Options,
+                "in_room" => Screen::InRoom,
+                "results" => Screen::Results,
+                "stats" => Screen::Stats,
+                "leaderboards" => Screen::Leaderboards,
+                "puzzles" => Screen::Puzzles,
+                "pattern_editor" => Screen::PatternEditor,
+                "pattern_library" => Screen::PatternLibrary,
+                "shared_patterns" => Screen::SharedPatterns,
+                "pattern_import" => Screen::PatternImport,
+                _ => continue,
+            };
+            info!("Layout {:?} changed; rebuilding {:?}", name, screen);
+
+            let saved = match self.ui_layout.get_screen_layering(screen) {
+                Some(layer) => layout_file::save_widget_state(layer),
+                None => vec![],
+            };
+            let script_names: Vec<String> = self.scripts.iter().map(|script| script.name().to_owned()).collect();
+            if let Err(e) = self.ui_layout.rebuild_screen(
+                ctx,
+                &self.config,
+                self.system_font.clone(),
+                &script_names,
+                screen,
+                &mut self.static_node_ids,
+            ) {
+                error!("Could not rebuild {:?} from its layout: {:?}", screen, e);
+                continue;
+            }
+            if let Some(layer) = self.ui_layout.get_screen_layering_mut(screen) {
+                layout_file::restore_widget_state(layer, &saved);
+            }
+
+            match screen {
+                Screen::InRoom => {
+                    hook_up_in_room_handlers(&mut self.ui_layout, &self.static_node_ids, &self.net_worker);
+                    self.list_roster();
+                }
+                Screen::Puzzles => self.add_puzzle_list(ctx),
+                Screen::PatternLibrary => self.library_list = None, // listed again on the next update
+                Screen::SharedPatterns => self.shared_list_id = None, // likewise
+                Screen::Menu if self.crash_prompt.is_some() => {
+                    // it was on the old main menu
+                    match self.ui_layout.add_crash_prompt(ctx, self.system_font.clone()) {
+                        Ok(pane_id) => self.crash_prompt = Some(pane_id),
+                        Err(e) => error!("Could not show the crash prompt again: {:?}", e),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Collapses the in-game chat to a bar, or expands it back.
+    fn toggle_chat_collapsed(&mut self) {
+        let id = self.static_node_ids.chatbox_id.clone();
+        match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => {
+                let collapsed = cb.is_collapsed();
+                cb.set_collapsed(!collapsed);
+            }
+            Err(e) => {
+                error!("Could not collapse or expand the Chatbox: {:?}", e);
+                return;
+            }
+        }
+        self.fit_chat_pane();
+    }
+
+    /// Sizes the chat pane to the chatbox, whose height changes when it is collapsed or expanded,
+    /// and keeps the chat text field just below it. The text field grows on its own as lines are
+    /// typed, below the pane.
+    fn fit_chat_pane(&mut self) {
+        let id = self.static_node_ids.chatbox_id.clone();
+        let chatbox_rect = match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => cb.rect(),
+            Err(e) => {
+                error!("Could not get the size of the Chatbox: {:?}", e);
+                return;
+            }
+        };
+        let pane_rect = graphics::Rect::new(
+            chatbox_rect.x,
+            chatbox_rect.y,
+            chatbox_rect.w,
+            chatbox_rect.h + constants::CHAT_TEXTFIELD_HEIGHT,
+        );
+
+        let pane_id = self.static_node_ids.chatbox_pane_id.clone();
+        match Pane::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &pane_id) {
+            Ok(pane) if pane.rect() != pane_rect => {
+                pane.set_rect(pane_rect)
+                    .unwrap_or_else(|e| error!("Could not resize the chat pane: {:?}", e));
+            }
+            Ok(_) => {}
+            Err(e) => error!("Could not get the chat pane: {:?}", e),
+        }
+        let tf_id = self.static_node_ids.chatbox_tf_id.clone();
+        match TextField::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &tf_id) {
+            Ok(tf) => {
+                let position = tf.position();
+                if position.x != chatbox_rect.x || position.y != chatbox_rect.bottom() {
+                    tf.set_position(chatbox_rect.x, chatbox_rect.bottom());
+                }
+            }
+            Err(e) => error!("Could not get the chat text field: {:?}", e),
+        }
+    }
+
+    /// Rebuilds the menus if the language or accessibility settings in the config were changed,
+    /// from Options or by editing the file.
+    fn apply_menu_look_if_changed(&mut self, ctx: &mut Context) {
+        let menu_look = MenuLook::from_config(&self.config);
+        if menu_look == self.menu_look {
+            return;
+        }
+        info!(
+            "Rebuilding menus for language {:?}, high contrast {}, minimum font size {}",
+            menu_look.language, menu_look.high_contrast, menu_look.min_font_size
+        );
+        menu_look.apply();
+        self.menu_look = menu_look;
+
+        let script_names: Vec<String> = self.scripts.iter().map(|script| script.name().to_owned()).collect();
+        if let Err(e) = self.ui_layout.rebuild_menus(
+            ctx,
+            &self.config,
+            self.system_font.clone(),
+            &script_names,
+            &mut self.static_node_ids,
+        ) {
+            error!("Could not rebuild the menus: {:?}", e);
+            return;
+        }
+        hook_up_in_room_handlers(&mut self.ui_layout, &self.static_node_ids, &self.net_worker);
+        self.list_roster();
+        self.library_list = None;
+        self.shared_list_id = None;
+        if self.crash_prompt.is_some() {
+            // it was on the old main menu
+            match self.ui_layout.add_crash_prompt(ctx, self.system_font.clone()) {
+                Ok(pane_id) => self.crash_prompt = Some(pane_id),
+                Err(e) => error!("Could not show the crash prompt again: {:?}", e),
+            }
+        }
+
+        // The in-game screen is kept, so only its background follows the new color scheme. Its
+        // fonts keep their size until the next start.
+        let fill = Some(scheme().chat_pane_fill);
+        let pane_id = self.static_node_ids.chatbox_pane_id.clone();
+        match Pane::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &pane_id) {
+            Ok(pane) => pane.bg_color = fill,
+            Err(e) => error!("Could not recolor the chat pane: {:?}", e),
+        }
+        let tf_id = self.static_node_ids.chatbox_tf_id.clone();
+        match TextField::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &tf_id) {
+            Ok(tf) => tf.bg_color = fill,
+            Err(e) => error!("Could not recolor the chat text field: {:?}", e),
+        }
+    }
+
+    /// Places and sizes the in-game chat as the HUD layout says, once streamer mode is switched on or
+    /// off, the HUD layout file is reloaded, or the window changes size.
+    fn apply_hud_if_changed(&mut self, ctx: &mut Context) {
+        let streamer_mode = self.config.get().video.streamer_mode;
+        let screen_size = graphics::drawable_size(ctx);
+        match self.hud_applied {
+            Some(applied) if applied == (streamer_mode, screen_size) => return,
+            Some((was_streamer_mode, _)) if was_streamer_mode != streamer_mode => {
+                info!("Streamer mode {}", if streamer_mode { "on" } else { "off" });
+                self.hud = layout_file::HudLayout::load(streamer_mode);
+            }
+            _ => {}
+        }
+        self.hud_applied = Some((streamer_mode, screen_size));
+
+        let chat = self.hud.chat;
+        let [w, h] = chat.size;
+        let position = chat.spot().place(screen_size, (w, h));
+        let font_info = FontInfo::new(ctx, self.system_font.clone(), Some(graphics::PxScale::from(chat.font_size)));
+        let chatbox_rect = graphics::Rect::new(position.x, position.y, w, h - constants::CHAT_TEXTFIELD_HEIGHT);
+        let id = self.static_node_ids.chatbox_id.clone();
+        match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => cb
+                .set_layout(chatbox_rect, font_info)
+                .unwrap_or_else(|e| error!("Could not lay out the Chatbox: {:?}", e)),
+            Err(e) => error!("Could not get the Chatbox: {:?}", e),
+        }
+        let tf_id = self.static_node_ids.chatbox_tf_id.clone();
+        match TextField::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &tf_id) {
+            Ok(tf) => {
+                let (_, tf_h) = tf.size();
+                tf.set_size(w, tf_h)
+                    .unwrap_or_else(|e| error!("Could not resize the chat text field: {:?}", e));
+            }
+            Err(e) => error!("Could not get the chat text field: {:?}", e),
+        }
+        self.fit_chat_pane();
+    }
+
+    /// Starts, restarts or stops the chat bridge when its settings change, and shows what happened on
+    /// its channel in the chatbox's bridge tab, which is added the first time the bridge is on.
+    fn update_chat_bridge(&mut self) {
+        let settings = &self.config.get().chat_bridge;
+        let wanted = if settings.enabled { Some(settings.clone()) } else { None };
+        let mut tab_name = None;
+        let mut notes = vec![];
+        let mut messages = vec![];
+        {
+            let mut bridge = self.chat_bridge.lock().unwrap();
+            if wanted != self.chat_bridge_applied {
+                *bridge = None; // disconnects
+                if let Some(ref settings) = wanted {
+                    match ChatBridge::start(settings) {
+                        Ok(started) => {
+                            info!("Chat bridge connecting to {} on {}", started.channel(), settings.server);
+                            tab_name = Some(started.channel().to_owned());
+                            notes.push(tr_args("chat-bridge-connecting", &[("channel", started.channel())]));
+                            *bridge = Some(started);
+                        }
+                        Err(e) => {
+                            warn!("Could not start the chat bridge: {}", e);
+                            tab_name = Some(tr("chat-tab-bridge"));
+                            notes.push(tr_args("chat-bridge-not-started", &[("error", &e)]));
+                        }
+                    }
+                } else {
+                    info!("Chat bridge off");
+                    notes.push(tr("chat-bridge-off"));
+                }
+                self.chat_bridge_applied = wanted;
+            }
+            if let Some(ref bridge) = *bridge {
+                for event in bridge.poll() {
+                    match event {
+                        BridgeEvent::Joined => {
+                            notes.push(tr_args("chat-bridge-joined", &[("channel", bridge.channel())]));
+                        }
+                        BridgeEvent::Message(nick, text) => {
+                            if !self.config.is_muted(&nick) {
+                                messages.push((nick, text));
+                            }
+                        }
+                        BridgeEvent::Notice(text) => notes.push(text),
+                        BridgeEvent::Disconnected(reason) => {
+                            info!("Chat bridge disconnected: {}", reason);
+                            notes.push(tr_args("chat-bridge-disconnected", &[("reason", &reason)]));
+                        }
                     }
-                });
+                }
             }
         }
-
-        if let Some(clipped_rect) = ui::intersection(full_rect, viewport_rect) {
-            let origin = graphics::DrawParam::new().dest(Point2 { x: 0.0, y: 0.0 });
-            let rectangle = graphics::Mesh::new_rectangle(
-                ctx,
-                GRID_DRAW_STYLE.to_draw_mode(),
-                clipped_rect,
-                self.uni_draw_params.fg_color,
-            )?;
-
-            graphics::draw(ctx, &rectangle, origin)?;
-            graphics::draw(ctx, &main_spritebatch, origin)?;
-            graphics::draw(ctx, &overlay_spritebatch, origin)?;
+        if tab_name.is_none() && notes.is_empty() && messages.is_empty() {
+            return;
         }
 
-        // TODO: see if we need to do this
-        main_spritebatch.clear();
-        overlay_spritebatch.clear();
-
-        ////////// draw generation counter
-        if self.uni_draw_params.draw_counter {
-            let gen_counter = universe.latest_gen().to_string();
-            ui::draw_text(
-                ctx,
-                self.system_font.clone(),
-                *GEN_COUNTER_COLOR,
-                gen_counter,
-                &Point2 { x: 0.0, y: 0.0 },
-            )?;
+        let id = self.static_node_ids.chatbox_id.clone();
+        match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+            Ok(cb) => {
+                if let Some(name) = tab_name {
+                    if cb.tab_count() > BRIDGE_CHAT_TAB {
+                        cb.set_tab_name(BRIDGE_CHAT_TAB, name);
+                    } else {
+                        cb.add_tab(name);
+                    }
+                }
+                for note in notes {
+                    cb.add_message_to_tab(BRIDGE_CHAT_TAB, note);
+                }
+                for (nick, text) in messages {
+                    let msg = format!("{}: {}", nick, text);
+                    cb.add_message_from(BRIDGE_CHAT_TAB, nick, msg);
+                }
+            }
+            Err(e) => error!("Could not add chat bridge messages to Chatbox: {:?}", e),
         }
-
-        Ok(())
-    }
-
-    fn center_intro_viewport(&mut self, win_width: f32, win_height: f32) {
-        let grid_width = self.intro_viewport.grid_width();
-        let grid_height = self.intro_viewport.grid_height();
-        let target_center_x = win_width / 2.0 - grid_width / 2.0;
-        let target_center_y = win_height / 2.0 - grid_height / 2.0;
-        self.intro_viewport.set_origin(Point2 {
-            x: target_center_x,
-            y: target_center_y,
-        });
     }
 
-    fn draw_intro(&mut self, ctx: &mut Context) -> Result<(), Box<dyn Error>> {
-        self.draw_game_of_life(ctx, &self.intro_uni)
+    /// Shows a desktop notification of `notice`, if the window isn't focused and the config asks for
+    /// notifications of that.
+    fn notify(&self, notice: Notice, body: String) {
+        if !self.window_focused {
+            notifications::notify(&self.config.get().notifications, notice, body);
+        }
     }
 
-    /// Draws the GameArea's universe to the screen.
-    fn draw_universe(&mut self, ctx: &mut Context) -> Result<(), Box<dyn Error>> {
-        // A mutable reference is used to notify the first generation is drawn
-        GameArea::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id)
-            .map(|gamearea| {
-            gamearea.first_gen_drawn();
-        })?;
-
-        // A non-mutable reference is used to draw the universe
-        match GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id) {
-            Ok(gamearea) => {
-                self.draw_game_of_life(ctx, &gamearea.uni)?;
+    /// Starts or stops Discord Rich Presence as the config says, tells Discord what the player is
+    /// doing, and acts on what they did in Discord.
+    fn update_discord_presence(&mut self) {
+        let settings = &self.config.get().discord;
+        let application_id = settings.application_id.trim();
+        if !settings.presence || application_id.is_empty() {
+            if self.discord.take().is_some() {
+                info!("Discord Rich Presence off");
             }
-            Err(e) => {
-                error!("failed to look up GameArea widget: {:?}", e);
+            return;
+        }
+        match self.discord {
+            Some((ref id, _)) if id == application_id => {}
+            _ => {
+                info!("Discord Rich Presence on, as application {}", application_id);
+                self.discord = Some((application_id.to_owned(), discord::DiscordPresence::start(application_id)));
             }
         }
 
-        Ok(())
+        let presence = self.discord_presence();
+        let events = match self.discord {
+            Some((_, ref mut discord)) => {
+                discord.show(presence);
+                discord.poll()
+            }
+            None => return,
+        };
+        for event in events {
+            match event {
+                DiscordEvent::Join(secret) => self.join_from_discord(secret),
+                DiscordEvent::JoinRequest(request) => self.discord_requests.push_back(request),
+            }
+        }
     }
 
-    fn transition_screen(
-        &mut self,
-        ggez_ctx: &mut Context,
-        old_screen: Screen,
-        new_screen: Screen,
-        game_area_state: &mut GameAreaState,
-    ) -> Result<(), Box<dyn Error>> {
-        match old_screen {
-            Screen::Menu => {
-                if new_screen == Screen::Run {
-                    let game_area_id = self.static_node_ids.game_area_id.clone();
-                    if let Some(layering) = self.ui_layout.get_screen_layering_mut(Screen::Run) {
-                        layering.enter_focus(
-                            ggez_ctx,
-                            &mut self.config,
-                            &mut self.screen_stack,
-                            game_area_state,
-                            &mut self.static_node_ids,
-                            &mut self.viewport,
-                            &game_area_id,
-                        )?;
-                    }
-                    game_area_state.running = true;
-                }
+    /// Asks the player about the oldest join request from Discord not yet answered, and passes on
+    /// their answer once they give it.
+    fn update_discord_prompt(&mut self, ctx: &mut Context) {
+        if self.discord.is_none() {
+            self.discord_requests.clear();
+        }
+        let answer = self.discord_answer.lock().unwrap().take();
+        if let (Some(accept), Some(request)) = (answer, self.discord_requests.front()) {
+            if let Some((_, ref discord)) = self.discord {
+                discord.answer(request, accept);
             }
-            Screen::Run => {
-                if new_screen == Screen::Menu {
-                    game_area_state.running = false;
+            if accept {
+                let msg = tr_args("discord-join-request", &[("name", &request.name)]);
+                self.toast = Some((msg, Instant::now()));
+            }
+            self.discord_requests.pop_front();
+        }
+        let current_screen = self.get_current_screen();
+        let moved = self.discord_prompt.as_ref().map_or(false, |(screen, _)| *screen != current_screen);
+        if answer.is_some() || self.discord_requests.is_empty() || moved {
+            // taken down, or to be put up again on the screen the player went to
+            if let Some((screen, pane_id)) = self.discord_prompt.take() {
+                if let Some(layer) = self.ui_layout.get_screen_layering_mut(screen) {
+                    layer.remove_widget(pane_id).unwrap_or_else(|e| {
+                        error!("Could not remove the Discord join prompt: {:?}", e);
+                    });
                 }
             }
-            _ => {}
         }
 
-        if old_screen != new_screen {
-            // Emit a Save event on the old screen
-            if let Some(layering) = self.ui_layout.get_screen_layering_mut(old_screen) {
-                layering.emit(
-                    &Event::new_save(),
-                    ggez_ctx,
-                    &mut self.config,
-                    &mut self.screen_stack,
-                    game_area_state,
-                    &mut self.static_node_ids,
-                    &mut self.viewport,
-                )?;
+        let request = match self.discord_requests.front() {
+            Some(request) if self.discord_prompt.is_none() => request,
+            _ => return,
+        };
+        match self.ui_layout.add_discord_join_prompt(
+            ctx,
+            self.system_font.clone(),
+            current_screen,
+            &request.name,
+            &self.discord_answer,
+        ) {
+            Ok(pane_id) => self.discord_prompt = Some((current_screen, pane_id)),
+            Err(e) => {
+                // there's nowhere to ask, so turn them away rather than leave them waiting
+                warn!("Could not ask about {}'s Discord join request: {:?}", request.name, e);
+                *self.discord_answer.lock().unwrap() = Some(false);
             }
+        }
+    }
 
-            // Emit a Load event on the new screen
-            if let Some(layering) = self.ui_layout.get_screen_layering_mut(new_screen) {
-                layering.emit(
-                    &Event::new_load(),
-                    ggez_ctx,
-                    &mut self.config,
-                    &mut self.screen_stack,
-                    game_area_state,
-                    &mut self.static_node_ids,
-                    &mut self.viewport,
-                )?;
+    /// What the player is doing, for their Discord profile.
+    fn discord_presence(&self) -> discord::Presence {
+        let room = if self.in_room { self.room_name.clone() } else { None };
+        let (details, mut state) = match self.get_current_screen() {
+            Screen::Run if self.puzzle_attempt.is_some() => {
+                let attempt = self.puzzle_attempt.as_ref().unwrap(); // unwrap OK because of the guard
+                (tr("discord-puzzle"), Some(self.puzzles[attempt.puzzle].title.clone()))
+            }
+            Screen::Run | Screen::InRoom if room.is_some() && self.current_match.is_some() => {
+                (tr("discord-playing"), None)
+            }
+            Screen::Run | Screen::InRoom if room.is_some() => (tr("discord-waiting"), None),
+            Screen::Run => (tr("discord-sandbox"), None),
+            Screen::PatternEditor => (tr("discord-editor"), None),
+            _ => (tr("discord-menus"), None),
+        };
+        let started = self.current_match.as_ref().map(|current| {
+            let since = SystemTime::now() - current.started().elapsed();
+            // in whole seconds, so that it's the same from one frame to the next
+            UNIX_EPOCH + Duration::from_secs(since.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+        });
+        let party = room.map(|room| {
+            state = Some(tr_args("discord-room", &[("room", &room)]));
+            let server = self.server.clone();
+            let players = self.roster.len().max(1) as u32; // just us until the server lists the room
+            let joinable = self.net_worker.lock().unwrap().is_some();
+            discord::Party {
+                id:   format!("{}/{}", server.as_deref().unwrap_or(""), room),
+                size: (players, players.max(MAX_SPAWN_REGIONS as u32)),
+                join: server
+                    .filter(|_| joinable)
+                    .map(|server| discord::JoinSecret { server, room }),
             }
+        });
+        discord::Presence {
+            details,
+            state,
+            started,
+            party,
         }
+    }
 
-        Ok(())
+    /// Goes to the room a friend is in, once the player joined them from Discord. Only a room on
+    /// the server the game is connected to can be gone to.
+    fn join_from_discord(&mut self, secret: discord::JoinSecret) {
+        info!("Joining {:?} on {} from Discord", secret.room, secret.server);
+        let msg = match *(self.net_worker.lock().unwrap()) {
+            Some(ref mut netwayste) if self.server.as_deref() == Some(secret.server.as_str()) => {
+                if self.in_room {
+                    netwayste.try_send(NetwaysteEvent::LeaveRoom);
+                }
+                netwayste.try_send(NetwaysteEvent::JoinRoom(secret.room.clone()));
+                tr_args("discord-joining", &[("room", &secret.room)])
+            }
+            _ => tr_args(
+                "discord-join-elsewhere",
+                &[
+                    ("room", &secret.room),
+                    ("server", &shown_server(&self.config, &secret.server)),
+                ],
+            ),
+        };
+        self.toast = Some((msg, Instant::now()));
     }
 
-    // update
-    fn receive_net_updates(&mut self) -> GameResult<()> {
-        let mut net_worker_guard = self.net_worker.lock().unwrap();
-        if net_worker_guard.is_none() {
-            return Ok(());
-        }
+    /// Where to draw something `size` big in `spot` of the HUD.
+    fn hud_position(&self, ctx: &Context, spot: layout_file::HudSpot, size: (f32, f32)) -> Point2<f32> {
+        spot.place(graphics::drawable_size(ctx), size)
+    }
 
-        let mut incoming_messages = vec![];
+    /// Finishes loading whatever resources have been read, and puts them to use.
+    fn receive_resources(&mut self, ctx: &mut Context) {
+        for key in self.registry.poll(ctx) {
+            if key == "scripts" {
+                self.compile_scripts(ctx);
+            } else if key == LIBRARY_KEY {
+                self.show_loaded_library(ctx);
+            }
+        }
+        // Listed again just once, so that the list doesn't jump about as they come in
+        if self.thumbnails_due && !self.registry.is_loading() {
+            self.thumbnails_due = false;
+            self.show_loaded_thumbnails(ctx);
+        }
+    }
 
-        let net_worker = net_worker_guard.as_mut().unwrap();
-        for e in net_worker.try_receive().into_iter() {
-            match e {
-                NetwaysteEvent::LoggedIn(server_version) => {
-                    info!("Logged in! Server version: v{}", server_version);
-                    self.screen_stack.push(Screen::ServerList); // XXX
-                                                                // do other stuff
-                    net_worker.try_send(NetwaysteEvent::List);
-                    net_worker.try_send(NetwaysteEvent::JoinRoom("general".to_owned()));
-                }
-                NetwaysteEvent::JoinedRoom(room_name) => {
-                    println!("Joined Room: {}", room_name);
-                    self.screen_stack.push(Screen::InRoom); // XXX
-                }
-                NetwaysteEvent::PlayerList(list) => {
-                    println!("PlayerList: {:?}", list);
-                }
-                NetwaysteEvent::RoomList(list) => {
-                    println!("RoomList: {:?}", list);
-                }
-                NetwaysteEvent::UniverseUpdate => {
-                    println!("Universe update");
-                }
-                NetwaysteEvent::ChatMessages(msgs) => {
-                    for m in msgs {
-                        let msg = format!("{}: {}", m.0, m.1);
-                        println!("{:?}", m); // print to stdout for dbg
+    /// Compiles the user scripts that were loaded, and lists them in the options menu.
+    fn compile_scripts(&mut self, ctx: &mut Context) {
+        let sources = self.registry.texts("scripts").unwrap_or(&[]);
+        self.scripts = scripting::compile_scripts(sources, CURRENT_PLAYER_ID);
+        if self.scripts.is_empty() {
+            return; // the options menu was built saying there are none
+        }
 
-                        incoming_messages.push(msg);
-                    }
-                }
-                NetwaysteEvent::LeftRoom => {
-                    println!("Left Room");
-                }
-                NetwaysteEvent::BadRequest(error) => {
-                    println!("Server responded with Bad Request: {:?}", error);
-                }
-                NetwaysteEvent::ServerError(error) => {
-                    println!("Server encountered an error: {:?}", error);
-                }
-                _ => {
-                    panic!(
-                        "Development panic: Unexpected NetwaysteEvent during netwayste receive update: {:?}",
-                        e
-                    );
-                }
-            }
+        let saved = match self.ui_layout.get_screen_layering(Screen::Options) {
+            Some(layer) => layout_file::save_widget_state(layer),
+            None => vec![],
+        };
+        let script_names: Vec<String> = self.scripts.iter().map(|script| script.name().to_owned()).collect();
+        if let Err(e) = self.ui_layout.rebuild_screen(
+            ctx,
+            &self.config,
+            self.system_font.clone(),
+            &script_names,
+            Screen::Options,
+            &mut self.static_node_ids,
+        ) {
+            error!("Could not list the scripts in the options menu: {:?}", e);
+            return;
         }
+        if let Some(layer) = self.ui_layout.get_screen_layering_mut(Screen::Options) {
+            layout_file::restore_widget_state(layer, &saved);
+        }
+    }
 
-        let id = self.static_node_ids.chatbox_id.clone();
-        for msg in incoming_messages {
-            match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
-                Ok(cb) => cb.add_message(msg),
-                Err(e) => error!("Could not add message to Chatbox on network message receive: {:?}", e),
+    /// Recolors the players if the colorblind palette in the config was changed.
+    fn apply_palette_if_changed(&mut self) {
+        let name = &self.config.get().accessibility.colorblind_palette;
+        if name == self.color_settings.palette.name() {
+            return;
+        }
+        match colorblind::Palette::from_name(name) {
+            Some(palette) => {
+                info!("Switching to colorblind palette {:?}", palette);
+                self.color_settings.set_palette(palette);
+            }
+            None if self.color_settings.palette != colorblind::Palette::Normal => {
+                warn!("Unknown colorblind palette {:?}; using the usual colors", name);
+                self.color_settings.set_palette(colorblind::Palette::Normal);
             }
+            None => {}
         }
+    }
 
-        Ok(())
+    /// Recolors the board if its theme, or the colors of the custom one, were changed in the config.
+    fn apply_board_theme_if_changed(&mut self) {
+        let board_colors = theme::board_colors(&self.config.get().theme);
+        if board_colors == self.color_settings.board {
+            return;
+        }
+        info!("Switching to board theme {:?}", self.config.get().theme.name);
+        self.color_settings.set_board_colors(board_colors);
+        if self.uni_draw_params.player_id >= 0 {
+            // the intro keeps its black board
+            self.uni_draw_params.bg_color = board_colors.background;
+            self.uni_draw_params.fg_color = board_colors.dead;
+        }
     }
 
     fn post_update(&mut self) -> GameResult<()> {
@@ -1154,16 +4759,23 @@ impl MainState {
                     self.inputs.mouse_info.mousebutton = MouseButton::Other(0);
                     self.inputs.mouse_info.down_position = Point2 { x: 0.0, y: 0.0 };
                 }
-                MouseAction::Drag | MouseAction::Held | MouseAction::DoubleClick => {}
+                MouseAction::Drag | MouseAction::Held => {}
             }
         }
 
-        self.inputs.mouse_info.scroll_event = None;
+        self.inputs.mouse_info.scroll_delta = Vector2 { x: 0.0, y: 0.0 };
         self.inputs.key_info.key = None;
 
-        self.modify_game_area(Box::new(|game_area| {
-            game_area.set_arrow_input((0, 0));
-        }));
+        // An arrow key pans the view on every frame that it is held, not just when it repeats
+        let panning = match self.inputs.key_info.held_key() {
+            Some(KeyCode::Up) | Some(KeyCode::Down) | Some(KeyCode::Left) | Some(KeyCode::Right) => true,
+            _ => false,
+        };
+        if !panning {
+            self.modify_game_area(Box::new(|game_area| {
+                game_area.set_arrow_input((0, 0));
+            }));
+        }
 
         // Flush config
         self.config
@@ -1180,6 +4792,166 @@ impl MainState {
         }
     }
 
+    /// Once the player has answered the crash prompt, keeps or deletes the reports and takes the
+    /// prompt down.
+    fn resolve_crash_prompt(&mut self) {
+        let save = match crash::take_prompt_answer() {
+            Some(save) => save,
+            None => return,
+        };
+        let message = match crash::resolve_pending_reports(save) {
+            Ok(_) if save => tr_args(
+                "toast-crash-report-saved",
+                &[("dir", &shown_path(&self.config, &user_data::Folder::Logs.path()))],
+            ),
+            Ok(_) => tr("toast-crash-report-discarded"),
+            Err(e) => tr_args("toast-crash-report-failed", &[("error", &e.to_string())]),
+        };
+        self.toast = Some((message, Instant::now()));
+        if let Some(pane_id) = self.crash_prompt.take() {
+            if let Some(layer) = self.ui_layout.get_screen_layering_mut(Screen::Menu) {
+                layer.remove_widget(pane_id).unwrap_or_else(|e| {
+                    error!("Could not remove the crash prompt: {:?}", e);
+                });
+            }
+        }
+    }
+
+    /// Runs a line entered in the developer console, printing the result there.
+    fn run_console_command(&mut self, line: &str) {
+        use console::{ConsoleCommand, LockstepCommand};
+
+        let command = match console::parse(line) {
+            Ok(command) => command,
+            Err(msg) => {
+                self.console.print(msg);
+                return;
+            }
+        };
+        match command {
+            ConsoleCommand::Help => self.console.print_help(),
+            ConsoleCommand::Clear => self.console.clear(),
+            ConsoleCommand::Spawn { pattern, col, row } => {
+                // a pattern key from the config, like "2", or else RLE
+                let rle = match self.config.pattern(&pattern) {
+                    "" => pattern,
+                    rle => rle.to_owned(),
+                };
+                let result = GameArea::widget_from_screen_and_id_mut(
+                    &mut self.ui_layout,
+                    Screen::Run,
+                    &self.static_node_ids.game_area_id,
+                )
+                .map_err(|e| Box::new(e) as Box<dyn Error>)
+                .and_then(|game_area| game_area.spawn_pattern(&rle, col, row));
+                match result {
+                    Ok(()) => self.console.print(format!("Spawned at ({}, {})", col, row)),
+                    Err(e) => self.console.print(format!("Could not spawn pattern: {}", e)),
+                }
+            }
+            ConsoleCommand::Speed(gens_per_second) if self.in_room => match gens_per_second {
+                // The server runs the game, so it's asked to change the room's speed
+                Some(gens_per_second) if gens_per_second >= 1.0 && gens_per_second <= u8::MAX as f64 => {
+                    if let Some(ref mut netwayste) = *(self.net_worker.lock().unwrap()) {
+                        netwayste.try_send(NetwaysteEvent::SetGenerationRate(gens_per_second.round() as u8));
+                    }
+                    self.console.print("Asked the server to change the room's speed");
+                }
+                _ => self
+                    .console
+                    .print("In a room, the speed must be at least one generation per second"),
+            },
+            ConsoleCommand::Speed(gens_per_second) => {
+                self.modify_game_area(Box::new(move |game_area| game_area.set_speed(gens_per_second)));
+                match gens_per_second {
+                    Some(gens_per_second) => self
+                        .console
+                        .print(format!("Running at {} generations per second", gens_per_second)),
+                    None => self.console.print("Running at one generation per frame"),
+                }
+            }
+            ConsoleCommand::Tree => {
+                let screen = self.get_current_screen();
+                match self.ui_layout.get_screen_layering(screen) {
+                    Some(layering) => {
+                        let tree = layering.widget_tree_string();
+                        self.console.print(tree);
+                    }
+                    None => self.console.print(format!("{:?} has no widgets", screen)),
+                }
+            }
+            ConsoleCommand::Inspect => {
+                self.inspector.visible = !self.inspector.visible;
+                if self.inspector.visible {
+                    self.console.print("Inspecting widgets; point at one for its details");
+                } else {
+                    self.console.print("Stopped inspecting widgets");
+                }
+            }
+            ConsoleCommand::Notice(text) => {
+                let msg = tr_args("chat-server-message", &[("message", &text)]);
+                accessibility::announce(msg.clone());
+                let id = self.static_node_ids.chatbox_id.clone();
+                match Chatbox::widget_from_screen_and_id_mut(&mut self.ui_layout, Screen::Run, &id) {
+                    Ok(cb) => cb.add_message(msg),
+                    Err(e) => self
+                        .console
+                        .print(format!("Could not add notice to the chatbox: {:?}", e)),
+                }
+            }
+            ConsoleCommand::Cells(None) => self
+                .console
+                .print(format!("Cells are drawn {}", self.cell_rendering.name())),
+            ConsoleCommand::Cells(Some(rendering)) => {
+                self.cell_rendering = rendering;
+                self.console.print(format!("Drawing cells {}", rendering.name()));
+            }
+            ConsoleCommand::Lockstep(None) => {
+                let status = match self.lockstep {
+                    Some(ref check) => check.status(),
+                    None if self.lockstep_enabled => "Lockstep: on; checks start with the next game".to_owned(),
+                    None => "Lockstep: off".to_owned(),
+                };
+                self.console.print(status);
+            }
+            ConsoleCommand::Lockstep(Some(LockstepCommand::On)) => {
+                self.lockstep_enabled = true;
+                self.console
+                    .print("Networked games will be checked against the server, starting with the next one");
+            }
+            ConsoleCommand::Lockstep(Some(LockstepCommand::Off)) => {
+                self.lockstep_enabled = false;
+                self.lockstep = None;
+                self.show_lockstep = false;
+                self.console.print("Lockstep checks are off");
+            }
+            ConsoleCommand::Lockstep(Some(LockstepCommand::Show)) => {
+                match self.lockstep.as_ref().and_then(|check| check.divergence()) {
+                    Some(divergence) => {
+                        self.show_lockstep = !self.show_lockstep;
+                        if self.show_lockstep {
+                            self.console.print(format!(
+                                "Showing this client's universe at generation {}",
+                                divergence.generation
+                            ));
+                        } else {
+                            self.console.print("Showing the game again");
+                        }
+                    }
+                    None => self.console.print("Nothing to show; the game hasn't diverged"),
+                }
+            }
+            ConsoleCommand::Log { module, level } => {
+                logging::set_level(&module, level);
+                if module.is_empty() {
+                    self.console.print(format!("Log level is now {}", level));
+                } else {
+                    self.console.print(format!("Log level of {} is now {}", module, level));
+                }
+            }
+        }
+    }
+
     fn modify_game_area(&mut self, modification: Box<dyn Fn(&mut GameArea)>) {
         let screen = self.get_current_screen();
         match GameArea::widget_from_screen_and_id_mut(&mut self.ui_layout, screen, &self.static_node_ids.game_area_id) {
@@ -1202,6 +4974,13 @@ enum Orientation {
 // Toggle a horizontal, vertical, or diagonal line, as player with index 0. This is only used for
 // the intro currently. Part or all of the line can be outside of the Universe; if this is the
 // case, only the parts inside the Universe are toggled.
+/// Whether a cell may be placed at (`col`, `row`) in a networked game: it has to be in our
+/// `writable` region, and on neither a wall nor fog.
+fn net_placeable(writable: Option<Region>, universe: &Universe, col: usize, row: usize) -> bool {
+    writable.map_or(false, |region| region.contains(col as isize, row as isize))
+        && !matches!(universe.cell_state(col, row), CellState::Wall | CellState::Fog)
+}
+
 fn toggle_line(s: &mut MainState, orientation: Orientation, col: isize, row: isize, width: isize, height: isize) {
     let player_id = 0; // hardcode player ID, since this is just for the intro
     match orientation {
@@ -1546,26 +5325,22 @@ fn init_intro_screen(s: &mut MainState) -> Result<(), ()> {
 // do the work of creating our MainState and running our game,
 // * then just call `game.run()` which runs the `Game` mainloop.
 pub fn main() {
-    env_logger::Builder::new()
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "{} [{:5}] - {}",
-                Local::now().format("%H:%M:%S%.6f"),
-                record.level(),
-                record.args(),
-            )
-        })
-        .filter(None, LevelFilter::Debug)
-        .filter(Some("futures"), LevelFilter::Info)
-        .filter(Some("tokio_core"), LevelFilter::Info)
-        .filter(Some("tokio_reactor"), LevelFilter::Info)
-        .filter(Some("conway"), LevelFilter::Info)
-        .filter(Some("ggez"), LevelFilter::Warn)
-        .filter(Some("gfx_device_gl"), LevelFilter::Off)
-        .init();
+    let args = cli::Args::from_command_line();
+    logging::init();
+    if args.register_links {
+        match uri::register() {
+            Ok(()) => println!("conwayste:// links now open this copy of Conwayste."),
+            Err(e) => {
+                println!("Could not register conwayste:// links: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    user_data::migrate();
 
     color_backtrace::install();
+    crash::install_panic_hook();
 
     let mut cb = ContextBuilder::new("conwayste", "Aaronm04|Manghi")
         .window_setup(
@@ -1580,9 +5355,7 @@ pub fn main() {
                 .resizable(false),
         );
 
-    if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
-        let mut path = path::PathBuf::from(manifest_dir);
-        path.push("resources");
+    if let Some(path) = loader::manifest_resources_dir() {
         info!(
             "Found CARGO_MANIFEST_DIR; Adding ${{CARGO_MANIFEST_DIR}}/resources path: {:?}",
             path
@@ -1595,11 +5368,21 @@ pub fn main() {
         std::process::exit(1);
     });
 
-    match MainState::new(&mut ctx) {
+    // The network layer runs on tokio, and only when connecting. The game never returns from
+    // `app_event::run`, so the runtime lives as long as the game does.
+    let runtime = args.connect.as_ref().map(|_| {
+        tokio::runtime::Runtime::new().unwrap_or_else(|e| {
+            error!("Could not start the network layer: {}", e);
+            std::process::exit(1);
+        })
+    });
+    let _runtime_guard = runtime.as_ref().map(|runtime| runtime.enter());
+
+    match MainState::new(&mut ctx, &args) {
         Err(e) => {
             println!("Could not load Conwayste!");
             println!("Error: {}", e);
         }
-        Ok(game) => run(ctx, events_loop, game),
+        Ok(game) => app_event::run(ctx, events_loop, game),
     }
 }
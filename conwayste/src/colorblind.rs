@@ -0,0 +1,213 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Help for colorblind players to tell the players' cells apart.
+//!
+//! A `Palette` replaces each player's color with the closest one from a small set of colors that
+//! stay distinct for a kind of color blindness. Independently of that, each player's cells can be
+//! marked with a `CellMark`, so that they can be told apart without relying on hue at all.
+
+use ggez::graphics::{Color, Rect};
+
+use crate::constants::colors::{BLACK, INSERT_PATTERN_UNWRITABLE, WHITE};
+
+/// The Okabe-Ito colors, which stay distinct with either kind of red-green color blindness.
+const RED_GREEN_SAFE: &[[u8; 3]] = &[
+    [213, 94, 0],    // vermillion
+    [0, 114, 178],   // blue
+    [0, 158, 115],   // bluish green
+    [240, 228, 66],  // yellow
+    [230, 159, 0],   // orange
+    [86, 180, 233],  // sky blue
+    [204, 121, 167], // reddish purple
+    [0, 0, 0],
+];
+
+/// Colors that stay distinct with blue-yellow color blindness. Blue and green are kept apart by
+/// their lightness.
+const BLUE_YELLOW_SAFE: &[[u8; 3]] = &[
+    [216, 27, 96],   // red
+    [30, 136, 229],  // blue
+    [0, 77, 64],     // dark green
+    [255, 193, 7],   // amber
+    [127, 127, 127], // gray
+    [0, 0, 0],
+];
+
+/// A set of player colors chosen for a kind of color blindness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Normal, // players' colors are used as they are
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Normal,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+        Palette::Tritanopia,
+    ];
+
+    /// The palette named `name` in the config, like "deuteranopia", or None if there isn't one.
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.iter().copied().find(|palette| palette.name() == name)
+    }
+
+    /// The name of this palette in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Normal => "none",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// The palette after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Palette {
+        let i = Palette::ALL.iter().position(|&palette| palette == self).unwrap(); // unwrap OK because ALL has every palette
+        Palette::ALL[(i + 1) % Palette::ALL.len()]
+    }
+
+    fn colors(self) -> &'static [[u8; 3]] {
+        match self {
+            Palette::Normal => &[],
+            Palette::Deuteranopia | Palette::Protanopia => RED_GREEN_SAFE,
+            Palette::Tritanopia => BLUE_YELLOW_SAFE,
+        }
+    }
+
+    /// The palette color closest to `color`, or `color` itself for `Palette::Normal`.
+    pub fn remap(self, color: Color) -> Color {
+        let (r, g, b) = color.to_rgb();
+        let distance = |rgb: &&[u8; 3]| -> i32 {
+            let dr = rgb[0] as i32 - r as i32;
+            let dg = rgb[1] as i32 - g as i32;
+            let db = rgb[2] as i32 - b as i32;
+            dr * dr + dg * dg + db * db
+        };
+        match self.colors().iter().min_by_key(distance) {
+            Some(rgb) => Color::from_rgb(rgb[0], rgb[1], rgb[2]),
+            None => color,
+        }
+    }
+
+    /// The color of cells that can't be placed where the player is trying to insert a pattern.
+    pub fn unwritable_color(self) -> Color {
+        match self {
+            Palette::Normal => *INSERT_PATTERN_UNWRITABLE,
+            _ => BLACK, // red would be lost among the player colors
+        }
+    }
+}
+
+/// A shape drawn over each of a player's cells to tell them apart from other players' cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellMark {
+    Plain,
+    Dot,
+    Stripes,
+    Frame,
+}
+
+// Cells smaller than this many pixels across are too small to show a mark
+const MIN_MARKED_CELL_SIZE: f32 = 6.0;
+
+impl CellMark {
+    /// The mark for the player with this index. Players are given the marks in turn.
+    pub fn for_player(player_index: usize) -> CellMark {
+        const MARKS: [CellMark; 4] = [CellMark::Plain, CellMark::Dot, CellMark::Stripes, CellMark::Frame];
+        MARKS[player_index % MARKS.len()]
+    }
+
+    /// The rectangles making up this mark on a cell drawn at `cell`.
+    pub fn rects(self, cell: Rect) -> Vec<Rect> {
+        if cell.w < MIN_MARKED_CELL_SIZE || cell.h < MIN_MARKED_CELL_SIZE {
+            return vec![];
+        }
+        let (w, h) = (cell.w / 5.0, cell.h / 5.0); // thickness of lines
+        match self {
+            CellMark::Plain => vec![],
+            CellMark::Dot => vec![Rect::new(cell.x + 2.0 * w, cell.y + 2.0 * h, w, h)],
+            CellMark::Stripes => vec![
+                Rect::new(cell.x, cell.y + h, cell.w, h),
+                Rect::new(cell.x, cell.y + 3.0 * h, cell.w, h),
+            ],
+            CellMark::Frame => vec![
+                Rect::new(cell.x, cell.y, cell.w, h),
+                Rect::new(cell.x, cell.bottom() - h, cell.w, h),
+                Rect::new(cell.x, cell.y + h, w, cell.h - 2.0 * h),
+                Rect::new(cell.right() - w, cell.y + h, w, cell.h - 2.0 * h),
+            ],
+        }
+    }
+
+    /// The color to draw a mark with on a cell of `cell_color`, so that it stands out.
+    pub fn color_on(cell_color: Color) -> Color {
+        let luminance = 0.299 * cell_color.r + 0.587 * cell_color.g + 0.114 * cell_color.b;
+        if luminance > 0.5 {
+            BLACK
+        } else {
+            WHITE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_palette_names_round_trip() {
+        for &palette in Palette::ALL.iter() {
+            assert_eq!(Palette::from_name(palette.name()), Some(palette));
+        }
+        assert_eq!(Palette::from_name("sepia"), None);
+        assert_eq!(Palette::Tritanopia.next(), Palette::Normal);
+    }
+
+    #[test]
+    fn test_remap_keeps_default_players_apart() {
+        let red = Color::from_rgb(255, 0, 0);
+        let blue = Color::from_rgb(0, 0, 255);
+        assert_eq!(Palette::Normal.remap(red), red);
+        for &palette in Palette::ALL.iter() {
+            let (r, b) = (palette.remap(red), palette.remap(blue));
+            assert_ne!(r, b, "{:?}", palette);
+            if palette != Palette::Normal {
+                let rgb = r.to_rgb();
+                assert!(palette.colors().contains(&[rgb.0, rgb.1, rgb.2]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_marks_stay_inside_the_cell() {
+        let cell = Rect::new(10.0, 20.0, 10.0, 10.0);
+        for player_index in 0..4 {
+            for rect in CellMark::for_player(player_index).rects(cell) {
+                assert!(rect.left() >= cell.left() && rect.right() <= cell.right());
+                assert!(rect.top() >= cell.top() && rect.bottom() <= cell.bottom());
+            }
+        }
+        assert!(CellMark::Frame.rects(Rect::new(0.0, 0.0, 5.0, 5.0)).is_empty());
+    }
+}
@@ -18,11 +18,18 @@
 
 extern crate toml;
 
-use crate::constants::{CONFIG_FILE_PATH, DEFAULT_ZOOM_LEVEL, MIN_CONFIG_FLUSH_TIME};
+use crate::constants::{CHATBOX_HISTORY, DEFAULT_ZOOM_LEVEL, MAX_MIN_FONT_SIZE, MIN_CONFIG_FLUSH_TIME};
+use crate::i18n::DEFAULT_LANGUAGE;
+use crate::keymap;
+use crate::user_data;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
+use std::mem;
 use std::time::Instant;
 
+#[cfg(not(test))]
+use std::fs;
 use std::fs::OpenOptions;
 use std::io::Read;
 #[cfg(not(test))]
@@ -75,27 +82,65 @@ lazy_static! {
 
 /// Settings contains all of the user's configurable settings for this game. These *should* be
 /// modified within the game, but one can always edit this file directly. The game will fail to
-/// load if there are any errors parsing the `conwayste.toml` file.
+/// load if there are any errors parsing the `conwayste.toml` file. Edits made while the game is
+/// running are picked up by `Config::reload`.
 // Top-level view of config toml file
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Settings {
-    pub user:     UserNetSettings,
-    pub gameplay: GamePlaySettings,
-    pub video:    VideoSettings,
-    pub audio:    AudioSettings,
+    pub user:          UserNetSettings,
+    pub gameplay:      GamePlaySettings,
+    pub video:         VideoSettings,
+    pub theme:         ThemeSettings,
+    pub audio:         AudioSettings,
+    pub accessibility: AccessibilitySettings,
+    pub input:         InputSettings,
+    pub chat_bridge:   ChatBridgeSettings,
+    pub discord:       DiscordSettings,
+    pub notifications: NotificationSettings,
+    pub keys:          KeySettings,
 }
 
-/// This will decode from the [user] section and contains settings for this user relevant to
-/// network (multiplayer) game play.
+const DEFAULT_PLAYER_NAME: &str = "JohnConway";
+
+/// This will decode from the [user] section and contains settings for this user, mostly relevant
+/// to network (multiplayer) game play.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct UserNetSettings {
-    pub name: String,
+    pub name:     String, // name of the active profile, used when connecting and chatting
+    pub language: String, // code of the language the menus are shown in, like "en"
+    pub profiles: Vec<Profile>,
 }
 
 impl Default for UserNetSettings {
     fn default() -> Self {
         UserNetSettings {
-            name: "JohnConway".to_owned(),
+            name:     DEFAULT_PLAYER_NAME.to_owned(),
+            language: DEFAULT_LANGUAGE.to_owned(),
+            profiles: vec![Profile::default()],
+        }
+    }
+}
+
+/// One of possibly several people who play on this computer. Each `[[user.profiles]]` table needs
+/// only the settings that differ from the defaults.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    pub name:     String,
+    pub color:    [u8; 3],     // preferred cell color, as RGB
+    pub muted:    Vec<String>, // names of the players whose chat isn't shown
+    // RLE patterns keyed by number key ("2" through "0"); these override the ones under [gameplay].
+    // Last, since TOML can't have plain values after a table.
+    pub patterns: BTreeMap<String, String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            name:     DEFAULT_PLAYER_NAME.to_owned(),
+            color:    [0, 0, 255],
+            muted:    vec![],
+            patterns: BTreeMap::new(),
         }
     }
 }
@@ -103,17 +148,54 @@ impl Default for UserNetSettings {
 /// Graphics-related settings like resolution, fullscreen, and more!
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct VideoSettings {
-    pub resolution_x: f32,
-    pub resolution_y: f32,
-    pub fullscreen:   bool,
+    pub resolution_x:    f32,
+    pub resolution_y:    f32,
+    pub fullscreen:      bool,
+    pub menu_background: bool, // if true, a Conway soup runs behind the menus
+    pub glow:            bool, // if true, cells glow
+    pub scanlines:       bool, // if true, the board has the scanlines of an old CRT
+    pub trails:          bool, // if true, cells that just died leave fading trails
+    #[serde(default)]
+    pub streamer_mode:   bool, // if true, the HUD is laid out for streaming, and file paths are hidden
 }
 
 impl Default for VideoSettings {
     fn default() -> Self {
         VideoSettings {
-            resolution_x: 1024.0,
-            resolution_y: 768.0,
-            fullscreen:   false,
+            resolution_x:    1024.0,
+            resolution_y:    768.0,
+            fullscreen:      false,
+            menu_background: true,
+            glow:            false,
+            scanlines:       false,
+            trails:          false,
+            streamer_mode:   false,
+        }
+    }
+}
+
+/// The colors of the board. `name` picks the theme; the colors are those of the "custom" theme, each
+/// as RGB.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ThemeSettings {
+    pub name:       String,  // "light", "dark", "high-contrast", or "custom"
+    pub background: [u8; 3], // around the board
+    pub grid:       [u8; 3], // lines between the cells
+    pub dead:       [u8; 3],
+    pub alive:      [u8; 3], // cells of no player
+    pub wall:       [u8; 3],
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        // the colors of the light theme, to start a custom one from
+        ThemeSettings {
+            name:       "light".to_owned(),
+            background: [64, 64, 64],
+            grid:       [223, 223, 223],
+            dead:       [223, 223, 223],
+            alive:      [255, 255, 255],
+            wall:       [157, 140, 105],
         }
     }
 }
@@ -134,19 +216,224 @@ impl Default for AudioSettings {
     }
 }
 
+/// Settings for players who have trouble seeing the screen.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AccessibilitySettings {
+    pub announce:           bool,   // if true, focused widgets and chat messages are announced
+    pub announce_file:      String, // announcements are appended here, one per line; empty to disable
+    pub announce_command:   String, // run with each announcement as its last argument, like "spd-say"
+    pub high_contrast:      bool,
+    pub min_font_size:      f32,    // in pixels; 0 means the usual font sizes are used
+    pub colorblind_palette: String, // "none", "deuteranopia", "protanopia", or "tritanopia"
+    pub cell_marks:         bool,   // if true, each player's cells get a dot, stripes, or other mark
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        AccessibilitySettings {
+            announce:           false,
+            announce_file:      "announcements.txt".to_owned(),
+            announce_command:   "".to_owned(),
+            high_contrast:      false,
+            min_font_size:      0.0,
+            colorblind_palette: "none".to_owned(),
+            cell_marks:         false,
+        }
+    }
+}
+
+/// How clicks are told apart from double clicks and long presses, and how held keys repeat.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct InputSettings {
+    pub double_click_ms:        u64, // most time between the clicks of a double click
+    pub long_press_ms:          u64, // least time a button is held down for a long press
+    pub click_slop:             f32, // in pixels; how far the mouse may move and still be clicking in the same place
+    pub key_repeat_delay_ms:    u64, // how long a key is held before it starts repeating
+    pub key_repeat_interval_ms: u64, // time between repeats after that
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            double_click_ms:        400,
+            long_press_ms:          600,
+            click_slop:             6.0,
+            key_repeat_delay_ms:    400,
+            key_repeat_interval_ms: 35,
+        }
+    }
+}
+
+/// A chat channel on an IRC server, such as a Twitch channel's chat, shown in a tab of the in-game
+/// chat. See `irc_bridge`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ChatBridgeSettings {
+    pub enabled: bool,
+    pub server:  String, // host:port; Twitch's is "irc.chat.twitch.tv:6697", or 6667 without TLS
+    #[serde(default)]
+    pub tls:     bool,   // if true, the connection is encrypted; false in configs from before it could be
+    pub channel: String, // like "#conwayste"; on Twitch, the streamer's name
+    pub nick:    String, // empty to only read the channel, which Twitch allows anyone
+    pub token:   String, // the server password, like Twitch's "oauth:..."; empty for none
+    pub relay:   bool,   // if true, what you say to the room is also sent to the channel
+}
+
+impl Default for ChatBridgeSettings {
+    fn default() -> Self {
+        ChatBridgeSettings {
+            enabled: false,
+            server:  "irc.chat.twitch.tv:6697".to_owned(),
+            tls:     true,
+            channel: "".to_owned(),
+            nick:    "".to_owned(),
+            token:   "".to_owned(),
+            relay:   false,
+        }
+    }
+}
+
+/// Discord Rich Presence. See `discord`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DiscordSettings {
+    pub presence:       bool,   // if true, what you're doing in the game is shown on your Discord profile
+    pub application_id: String, // of the Discord application it's shown as, with a "logo" art asset
+}
+
+impl Default for DiscordSettings {
+    fn default() -> Self {
+        DiscordSettings {
+            presence:       false,
+            application_id: "".to_owned(),
+        }
+    }
+}
+
+/// Which desktop notifications are shown while the game's window isn't focused. See `notifications`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationSettings {
+    pub mentioned:      bool, // when someone says your name in chat
+    pub match_starting: bool, // when a game starts in your room
+    pub disconnected:   bool, // when the connection to the server is lost
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            mentioned:      true,
+            match_starting: true,
+            disconnected:   true,
+        }
+    }
+}
+
+/// The keys for each action, by name, like "F1" or "Space". An action with no keys can't be done
+/// from the keyboard. See `keymap`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct KeySettings {
+    pub help:                  Vec<String>,
+    pub debug_overlay:         Vec<String>,
+    pub console:               Vec<String>,
+    pub clear_pattern:         Vec<String>,
+    pub cycle_symmetry:        Vec<String>,
+    pub move_symmetry_center:  Vec<String>,
+    pub cycle_annotation_tool: Vec<String>,
+    pub share_annotations:     Vec<String>,
+    pub clear_annotations:     Vec<String>,
+    pub follow_activity:       Vec<String>,
+    pub focus_chat:            Vec<String>,
+    pub collapse_chat:         Vec<String>,
+    pub toggle_hud:            Vec<String>,
+    pub toggle_bot:            Vec<String>,
+    pub run_pause:             Vec<String>,
+    pub single_step:           Vec<String>,
+    pub zoom_in:               Vec<String>,
+    pub zoom_out:              Vec<String>,
+    pub erase_selection:       Vec<String>,
+    pub back:                  Vec<String>,
+    pub dump_pattern:          Vec<String>,
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        KeySettings {
+            help:                  keymap::default_key_names("help"),
+            debug_overlay:         keymap::default_key_names("debug_overlay"),
+            console:               keymap::default_key_names("console"),
+            clear_pattern:         keymap::default_key_names("clear_pattern"),
+            cycle_symmetry:        keymap::default_key_names("cycle_symmetry"),
+            move_symmetry_center:  keymap::default_key_names("move_symmetry_center"),
+            cycle_annotation_tool: keymap::default_key_names("cycle_annotation_tool"),
+            share_annotations:     keymap::default_key_names("share_annotations"),
+            clear_annotations:     keymap::default_key_names("clear_annotations"),
+            follow_activity:       keymap::default_key_names("follow_activity"),
+            focus_chat:            keymap::default_key_names("focus_chat"),
+            collapse_chat:         keymap::default_key_names("collapse_chat"),
+            toggle_hud:            keymap::default_key_names("toggle_hud"),
+            toggle_bot:            keymap::default_key_names("toggle_bot"),
+            run_pause:             keymap::default_key_names("run_pause"),
+            single_step:           keymap::default_key_names("single_step"),
+            zoom_in:               keymap::default_key_names("zoom_in"),
+            zoom_out:              keymap::default_key_names("zoom_out"),
+            erase_selection:       keymap::default_key_names("erase_selection"),
+            back:                  keymap::default_key_names("back"),
+            dump_pattern:          keymap::default_key_names("dump_pattern"),
+        }
+    }
+}
+
+impl KeySettings {
+    /// The keys in the field named `field`.
+    pub fn get(&self, field: &str) -> Option<&[String]> {
+        let keys: &[String] = match field {
+            "help" => &self.help,
+            "debug_overlay" => &self.debug_overlay,
+            "console" => &self.console,
+            "clear_pattern" => &self.clear_pattern,
+            "cycle_symmetry" => &self.cycle_symmetry,
+            "move_symmetry_center" => &self.move_symmetry_center,
+            "cycle_annotation_tool" => &self.cycle_annotation_tool,
+            "share_annotations" => &self.share_annotations,
+            "clear_annotations" => &self.clear_annotations,
+            "follow_activity" => &self.follow_activity,
+            "focus_chat" => &self.focus_chat,
+            "collapse_chat" => &self.collapse_chat,
+            "toggle_hud" => &self.toggle_hud,
+            "toggle_bot" => &self.toggle_bot,
+            "run_pause" => &self.run_pause,
+            "single_step" => &self.single_step,
+            "zoom_in" => &self.zoom_in,
+            "zoom_out" => &self.zoom_out,
+            "erase_selection" => &self.erase_selection,
+            "back" => &self.back,
+            "dump_pattern" => &self.dump_pattern,
+            _ => return None,
+        };
+        Some(keys)
+    }
+}
+
 /// Gameplay-related settings. Pretty empty for now.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GamePlaySettings {
-    pub zoom:     f32,
-    pub pattern2: String,
-    pub pattern3: String,
-    pub pattern4: String,
-    pub pattern5: String,
-    pub pattern6: String,
-    pub pattern7: String,
-    pub pattern8: String,
-    pub pattern9: String,
-    pub pattern0: String,
+    pub zoom:            f32,
+    pub pattern2:        String,
+    pub pattern3:        String,
+    pub pattern4:        String,
+    pub pattern5:        String,
+    pub pattern6:        String,
+    pub pattern7:        String,
+    pub pattern8:        String,
+    pub pattern9:        String,
+    pub pattern0:        String,
+    #[serde(default)]
+    pub bot_opponent:    bool, // if true, a bot plays the other side of the offline game
+    #[serde(default)]
+    pub enabled_scripts: Vec<String>, // names of the user scripts to run
+    pub chat_history:    usize, // number of chat messages kept in the chatbox
+    #[serde(default)]
+    pub log_chat:        bool, // if true, chat is appended to a file per day in the chat log directory
+    #[serde(default)]
+    pub tutorial_done:   bool, // if true, the tutorial isn't shown when a game starts
 }
 
 impl Default for GamePlaySettings {
@@ -188,6 +475,12 @@ impl Default for GamePlaySettings {
                 "9bo14b$6bo3bo8bo3b2o6bo13b$7b3o13bobo3b3o13b$25bo19b$25b2o!"
             )
             .to_owned(),
+
+            bot_opponent:    false,
+            enabled_scripts: vec![],
+            chat_history:    CHATBOX_HISTORY,
+            log_chat:        false,
+            tutorial_done:   false,
         }
     }
 }
@@ -201,12 +494,67 @@ impl Settings {
     }
 }
 
+/// Settings given on the command line, which take the place of the config file's for one run of
+/// the game. They're never saved, unless changed again in the game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub name:        Option<String>, // the profile to play as, or a name to play under
+    pub fullscreen:  Option<bool>,
+    pub chat_bridge: Option<bool>, // whether the chat bridge is enabled
+    pub discord:     Option<bool>, // whether Discord Rich Presence is on
+}
+
+impl Overrides {
+    /// Puts the overridden settings in `settings`, returning the values they replaced.
+    fn apply(&self, settings: &mut Settings) -> Overrides {
+        Overrides {
+            name:        self.name.clone().map(|name| mem::replace(&mut settings.user.name, name)),
+            fullscreen:  self.fullscreen.map(|on| mem::replace(&mut settings.video.fullscreen, on)),
+            chat_bridge: self.chat_bridge.map(|on| mem::replace(&mut settings.chat_bridge.enabled, on)),
+            discord:     self.discord.map(|on| mem::replace(&mut settings.discord.presence, on)),
+        }
+    }
+
+    /// Undoes `apply`, which returned `replaced`, for those settings that still have the values it
+    /// put in them.
+    fn undo(&self, replaced: &Overrides, settings: &mut Settings) {
+        fn put_back<T: Clone + PartialEq>(setting: &mut T, overridden: &Option<T>, replaced: &Option<T>) {
+            if let (Some(overridden), Some(replaced)) = (overridden, replaced) {
+                if setting == overridden {
+                    *setting = replaced.clone();
+                }
+            }
+        }
+        put_back(&mut settings.user.name, &self.name, &replaced.name);
+        put_back(&mut settings.video.fullscreen, &self.fullscreen, &replaced.fullscreen);
+        put_back(&mut settings.chat_bridge.enabled, &self.chat_bridge, &replaced.chat_bridge);
+        put_back(&mut settings.discord.presence, &self.discord, &replaced.discord);
+    }
+
+    /// Stops overriding the settings that were changed since `apply`, which returned `replaced`, so
+    /// that the changes are saved and kept when the settings are loaded again.
+    fn forget_changed(&mut self, replaced: &mut Overrides, settings: &Settings) {
+        fn forget<T: PartialEq>(setting: &T, overridden: &mut Option<T>, replaced: &mut Option<T>) {
+            if overridden.as_ref().map_or(false, |overridden| setting != overridden) {
+                *overridden = None;
+                *replaced = None;
+            }
+        }
+        forget(&settings.user.name, &mut self.name, &mut replaced.name);
+        forget(&settings.video.fullscreen, &mut self.fullscreen, &mut replaced.fullscreen);
+        forget(&settings.chat_bridge.enabled, &mut self.chat_bridge, &mut replaced.chat_bridge);
+        forget(&settings.discord.presence, &mut self.discord, &mut replaced.discord);
+    }
+}
+
 /// Config manages how Settings are loaded and stored to the filesystem.
 pub struct Config {
     settings:            Settings,        // The actual settings
-    path:                String,          // Path to config file. `conwayste.toml` by default.
+    path:                String,          // Path to config file. `user_data::config_file()` by default.
     dirty:               bool,            // Config needs to be flushed to disk?
     flush_time:          Option<Instant>, // Last time (if any) that we flushed to disk.
+    overrides:           Overrides,       // applied to `settings` whenever they're loaded
+    replaced:            Overrides,       // what `overrides` replaced, to be saved in their place
     #[cfg(test)]
     pub dummy_file_data: Option<String>, // for mocking file reads and writes
 }
@@ -214,13 +562,20 @@ pub struct Config {
 impl Config {
     /// Creates a Config with default settings.
     pub fn new() -> Config {
+        Config::with_path(user_data::config_file().to_string_lossy().into_owned())
+    }
+
+    /// Creates a Config with default settings, to be loaded from and saved to `path`.
+    pub fn with_path(path: String) -> Config {
         let config = Settings::new();
 
         Config {
             settings: config,
-            path: String::from(CONFIG_FILE_PATH),
+            path,
             dirty: false,
             flush_time: None,
+            overrides: Overrides::default(),
+            replaced: Overrides::default(),
             #[cfg(test)]
             dummy_file_data: None,
         }
@@ -249,6 +604,14 @@ impl Config {
         self
     }
 
+    /// Replaces the settings given by `overrides`, now and whenever the settings are loaded again,
+    /// without saving them. Each stops being overridden once it's changed in the game.
+    pub fn set_overrides(&mut self, overrides: Overrides) {
+        self.overrides.undo(&self.replaced, &mut self.settings);
+        self.replaced = overrides.apply(&mut self.settings);
+        self.overrides = overrides;
+    }
+
     /// Queries to see if the configuration file is dirty or not.
     pub fn is_dirty(&self) -> bool {
         self.dirty
@@ -304,7 +667,31 @@ impl Config {
             }
         }
         let result_string = toml::to_string(&result_map)?;
-        self.settings = toml::from_str(result_string.as_str())?;
+        let mut settings: Settings = toml::from_str(result_string.as_str())?;
+        if settings.gameplay.chat_history == 0 {
+            return Err(new_config_error(
+                "in section gameplay: chat_history must be at least 1".to_owned(),
+            ));
+        }
+        let min_font_size = settings.accessibility.min_font_size;
+        if !(0.0..=MAX_MIN_FONT_SIZE).contains(&min_font_size) {
+            return Err(new_config_error(format!(
+                "in section accessibility: min_font_size must be from 0 to {}",
+                MAX_MIN_FONT_SIZE
+            )));
+        }
+        keymap::check_keys(&settings.keys).map_err(|e| new_config_error(format!("in section keys: {}", e)))?;
+        self.replaced = self.overrides.apply(&mut settings);
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Reads the settings from `self.path` again, such as after the file was edited while the game
+    /// was running. Any unsaved changes made in the game are replaced. On error, the current
+    /// settings are kept.
+    pub fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        self.load()?;
+        self.set_clean();
         Ok(())
     }
 
@@ -330,9 +717,12 @@ impl Config {
         Ok(())
     }
 
-    /// Save to file unconditionally.
+    /// Save to file unconditionally. Settings that are still as overridden are saved as they were
+    /// before.
     pub fn force_flush(&mut self) -> Result<(), Box<dyn Error>> {
-        let full_toml_str = toml::to_string(&self.settings)?;
+        let mut saved = self.settings.clone();
+        self.overrides.undo(&self.replaced, &mut saved);
+        let full_toml_str = toml::to_string(&saved)?;
         let settings_map: TomlMap = toml::from_str(full_toml_str.as_str())?;
         let mut result_map = TomlMap::new();
         // compare each thing in DEFAULT_MAP vs settings_map; if different, add the latter to
@@ -366,6 +756,9 @@ impl Config {
 
         #[cfg(not(test))]
         {
+            if let Some(dir) = Path::new(&self.path).parent() {
+                fs::create_dir_all(dir)?;
+            }
             let mut foptions = OpenOptions::new();
             let mut f = foptions.write(true).create(true).open(&self.path)?;
             f.set_len(0)?;
@@ -421,6 +814,7 @@ impl Config {
         F: FnMut(&mut Settings),
     {
         f(&mut self.settings);
+        self.overrides.forget_changed(&mut self.replaced, &self.settings);
         self.set_dirty();
         // TODO: pass a clone of the settings above, and then validate afterwards. If validation
         // passes, then save the clone.
@@ -437,6 +831,139 @@ impl Config {
             settings.video.resolution_y = h;
         });
     }
+
+    /// The profile named by `[user] name`, if there is one. Configs from before profiles existed
+    /// have a name but no matching profile.
+    pub fn active_profile(&self) -> Option<&Profile> {
+        let user = &self.settings.user;
+        user.profiles.iter().find(|profile| profile.name == user.name)
+    }
+
+    /// The cell color preferred by the active profile, as RGB.
+    pub fn profile_color(&self) -> [u8; 3] {
+        self.active_profile().unwrap_or(&Profile::default()).color
+    }
+
+    pub fn set_profile_color(&mut self, color: [u8; 3]) {
+        self.modify(|settings| {
+            let user = &mut settings.user;
+            ensure_profile_exists(user);
+            let name = &user.name;
+            for profile in user.profiles.iter_mut().filter(|profile| profile.name == *name) {
+                profile.color = color;
+            }
+        });
+    }
+
+    /// Whether the active profile has muted the player named `name`.
+    pub fn is_muted(&self, name: &str) -> bool {
+        self.active_profile()
+            .map_or(false, |profile| profile.muted.iter().any(|muted| muted == name))
+    }
+
+    /// Mutes or unmutes the player named `name` for the active profile.
+    pub fn set_muted(&mut self, name: &str, muted: bool) {
+        self.modify(|settings| {
+            let user = &mut settings.user;
+            ensure_profile_exists(user);
+            let active_name = &user.name;
+            for profile in user.profiles.iter_mut().filter(|profile| profile.name == *active_name) {
+                profile.muted.retain(|muted_name| muted_name != name);
+                if muted {
+                    profile.muted.push(name.to_owned());
+                }
+            }
+        });
+    }
+
+    /// The RLE pattern placed by pressing the number key `key` ("2" through "0"), taken from the
+    /// active profile if it has one for that key.
+    pub fn pattern(&self, key: &str) -> &str {
+        if let Some(rle) = self.active_profile().and_then(|profile| profile.patterns.get(key)) {
+            return rle;
+        }
+        let gameplay = &self.settings.gameplay;
+        match key {
+            "2" => &gameplay.pattern2,
+            "3" => &gameplay.pattern3,
+            "4" => &gameplay.pattern4,
+            "5" => &gameplay.pattern5,
+            "6" => &gameplay.pattern6,
+            "7" => &gameplay.pattern7,
+            "8" => &gameplay.pattern8,
+            "9" => &gameplay.pattern9,
+            "0" => &gameplay.pattern0,
+            _ => "", // unexpected
+        }
+    }
+
+    /// Makes the profile after the active one active, wrapping around at the end of the list.
+    pub fn next_profile(&mut self) {
+        self.modify(|settings| {
+            let user = &mut settings.user;
+            ensure_profile_exists(user);
+            // unwrap OK because of ensure_profile_exists
+            let i = user
+                .profiles
+                .iter()
+                .position(|profile| profile.name == user.name)
+                .unwrap();
+            user.name = user.profiles[(i + 1) % user.profiles.len()].name.clone();
+        });
+    }
+
+    /// Adds a profile with default settings and a name not used by any other profile, and makes it
+    /// active.
+    pub fn add_profile(&mut self) {
+        self.modify(|settings| {
+            let user = &mut settings.user;
+            ensure_profile_exists(user);
+            let name = (user.profiles.len() + 1..)
+                .map(|n| format!("Player{}", n))
+                .find(|name| user.profiles.iter().all(|profile| profile.name != *name))
+                .unwrap(); // unwrap OK because the range is unbounded
+            user.profiles.push(Profile {
+                name: name.clone(),
+                ..Profile::default()
+            });
+            user.name = name;
+        });
+    }
+
+    /// Renames the active profile. Returns false, changing nothing, if another profile already has
+    /// the new name.
+    pub fn rename_active_profile(&mut self, new_name: &str) -> bool {
+        let user = &self.settings.user;
+        if user.name == new_name {
+            return true;
+        }
+        if user.profiles.iter().any(|profile| profile.name == new_name) {
+            return false;
+        }
+        self.modify(|settings| {
+            let user = &mut settings.user;
+            ensure_profile_exists(user);
+            let old_name = std::mem::replace(&mut user.name, new_name.to_owned());
+            for profile in user.profiles.iter_mut().filter(|profile| profile.name == old_name) {
+                profile.name = new_name.to_owned();
+            }
+        });
+        true
+    }
+}
+
+/// Adds a profile for `user.name` if there isn't one, so that a config from before profiles existed
+/// keeps its player name as a profile.
+fn ensure_profile_exists(user: &mut UserNetSettings) {
+    if user.profiles.iter().all(|profile| profile.name != user.name) {
+        user.profiles.insert(
+            0,
+            Profile {
+                name: user.name.clone(),
+                ..Profile::default()
+            },
+        );
+    }
 }
 
 #[cfg(test)]
@@ -593,6 +1120,144 @@ mod test {
         assert_eq!(existing_filedata, new_filedata); // since file was already there, should not be changed
     }
 
+    #[test]
+    fn test_profiles_from_file() {
+        let mut config = Config::new();
+        let existing_filedata = concat!(
+            "[user]\nname = \"Bob\"\n",
+            "[[user.profiles]]\nname = \"Alice\"\n",
+            "[[user.profiles]]\nname = \"Bob\"\ncolor = [255, 0, 0]\n",
+            "[user.profiles.patterns]\n2 = \"2o$2o!\"\n",
+        );
+        config.dummy_file_data = Some(existing_filedata.to_owned());
+        config.load_or_create_default().unwrap();
+
+        let profile = config.active_profile().unwrap();
+        assert_eq!(profile.name, "Bob");
+        assert_eq!(profile.color, [255, 0, 0]);
+        assert_eq!(config.pattern("2"), "2o$2o!");
+        assert_eq!(config.pattern("3"), config.get().gameplay.pattern3);
+
+        config.next_profile();
+        assert_eq!(config.get().user.name, "Alice");
+        assert_eq!(config.active_profile().unwrap().color, Profile::default().color);
+        assert_eq!(config.pattern("2"), config.get().gameplay.pattern2);
+        config.next_profile();
+        assert_eq!(config.get().user.name, "Bob");
+    }
+
+    #[test]
+    fn test_mute_is_per_profile() {
+        let mut config = Config::new();
+        config.set_muted("Mallory", true);
+        config.set_muted("Mallory", true);
+        assert!(config.is_muted("Mallory"));
+        assert_eq!(config.active_profile().unwrap().muted, ["Mallory"]);
+        config.force_flush().unwrap();
+        config.reload().unwrap();
+        assert!(config.is_muted("Mallory"));
+
+        config.add_profile();
+        assert!(!config.is_muted("Mallory"));
+        config.next_profile();
+        config.set_muted("Mallory", false);
+        assert!(!config.is_muted("Mallory"));
+    }
+
+    #[test]
+    fn test_add_and_rename_profiles() {
+        let mut config = Config::new();
+        // a config from before profiles existed
+        config.dummy_file_data = Some("[user]\nname = \"Carol\"\nprofiles = []\n".to_owned());
+        config.load_or_create_default().unwrap();
+        assert!(config.active_profile().is_none());
+
+        config.add_profile();
+        assert_eq!(config.get().user.name, "Player2");
+        let names: Vec<&str> = config.get().user.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Carol", "Player2"]);
+
+        assert_eq!(config.rename_active_profile("Carol"), false);
+        assert_eq!(config.rename_active_profile("Dave"), true);
+        assert_eq!(config.get().user.name, "Dave");
+        assert_eq!(config.active_profile().unwrap().name, "Dave");
+
+        config.force_flush().unwrap();
+        config.load_or_create_default().unwrap();
+        let names: Vec<&str> = config.get().user.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Carol", "Dave"]);
+    }
+
+    #[test]
+    fn test_reload_applies_edits() {
+        let mut config = Config::new();
+        config.dummy_file_data = Some("[gameplay]\nchat_history = 50\n".to_owned());
+        config.modify(|settings: &mut Settings| {
+            settings.video.fullscreen = true;
+        });
+        config.reload().unwrap();
+        assert_eq!(config.get().gameplay.chat_history, 50);
+        assert_eq!(config.get().video.fullscreen, false);
+        assert_eq!(config.is_dirty(), false);
+    }
+
+    #[test]
+    fn test_reload_keeps_settings_on_invalid_edit() {
+        let mut config = Config::new();
+        config.dummy_file_data = Some("[audio]\nmaster = 69\n".to_owned());
+        config.reload().unwrap();
+
+        config.dummy_file_data = Some("[audio]\nmaster = 42\n[gameplay]\nchat_history = 0\n".to_owned());
+        let box_err = config.reload().unwrap_err();
+        let err = box_err.downcast_ref::<ConfigError>().unwrap();
+        assert_eq!(err.msg.as_str(), "in section gameplay: chat_history must be at least 1");
+        assert_eq!(config.get().audio.master, 69);
+
+        config.dummy_file_data = Some("[audio\nmaster = 42\n".to_owned());
+        assert!(config.reload().is_err());
+        assert_eq!(config.get().audio.master, 69);
+    }
+
+    #[test]
+    fn test_min_font_size_is_checked() {
+        let mut config = Config::new();
+        config.dummy_file_data = Some("[accessibility]\nhigh_contrast = true\nmin_font_size = 24.0\n".to_owned());
+        config.load_or_create_default().unwrap();
+        assert_eq!(config.get().accessibility.high_contrast, true);
+        assert_eq!(config.get().accessibility.min_font_size, 24.0);
+
+        config.dummy_file_data = Some("[accessibility]\nmin_font_size = -1.0\n".to_owned());
+        let box_err = config.reload().unwrap_err();
+        let err = box_err.downcast_ref::<ConfigError>().unwrap();
+        assert_eq!(
+            err.msg.as_str(),
+            format!(
+                "in section accessibility: min_font_size must be from 0 to {}",
+                MAX_MIN_FONT_SIZE
+            )
+        );
+        assert_eq!(config.get().accessibility.min_font_size, 24.0);
+    }
+
+    #[test]
+    fn test_keys_are_checked() {
+        let mut config = Config::new();
+        assert_eq!(config.get().keys.run_pause, vec!["R".to_owned()]);
+        config.dummy_file_data = Some("[keys]\nrun_pause = [\"P\", \"Space\"]\ntoggle_bot = []\n".to_owned());
+        config.load_or_create_default().unwrap();
+        assert_eq!(config.get().keys.run_pause, vec!["P".to_owned(), "Space".to_owned()]);
+        assert!(config.get().keys.toggle_bot.is_empty());
+
+        config.dummy_file_data = Some("[keys]\nrun_pause = [\"Pause\"]\n".to_owned());
+        let box_err = config.reload().unwrap_err();
+        let err = box_err.downcast_ref::<ConfigError>().unwrap();
+        assert_eq!(
+            err.msg.as_str(),
+            "in section keys: run_pause: no key is named \"Pause\""
+        );
+        assert_eq!(config.get().keys.run_pause, vec!["P".to_owned(), "Space".to_owned()]);
+    }
+
     #[test]
     fn test_flush_should_not_happen_with_fresh_config() {
         let mut config = Config::new();
@@ -657,4 +1322,37 @@ mod test {
         let commented_default_lines: Vec<&str> = COMMENTED_DEFAULT_STRING.split("\n").collect();
         assert_eq!(&filedata_lines[3..], &commented_default_lines[..]);
     }
+
+    #[test]
+    fn test_overrides_are_not_saved() {
+        let mut config = Config::new();
+        config.dummy_file_data = Some("[user]\nname = \"Alice\"\n".to_owned());
+        config.set_overrides(Overrides {
+            name:       Some("Bob".to_owned()),
+            fullscreen: Some(true),
+            ..Overrides::default()
+        });
+        config.load_or_create_default().unwrap();
+        assert_eq!(config.get().user.name, "Bob");
+        assert_eq!(config.get().video.fullscreen, true);
+
+        config.modify(|settings| {
+            settings.gameplay.zoom = 10.0;
+        });
+        config.force_flush().unwrap();
+        let filedata = config.dummy_file_data.clone().unwrap();
+        assert!(filedata.contains("name = \"Alice\""));
+        assert!(!filedata.contains("fullscreen = true"));
+
+        // changed in the game, so no longer overridden, even when loaded again
+        config.modify(|settings| {
+            settings.video.fullscreen = false;
+            settings.user.name = "Carol".to_owned();
+        });
+        config.force_flush().unwrap();
+        assert!(config.dummy_file_data.as_ref().unwrap().contains("name = \"Carol\""));
+        config.reload().unwrap();
+        assert_eq!(config.get().user.name, "Carol");
+        assert_eq!(config.get().video.fullscreen, false);
+    }
 }
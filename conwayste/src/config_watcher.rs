@@ -0,0 +1,68 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Editors often save a file in several steps; events closer together than this are merged.
+pub const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// Notices when the config file is changed on disk, whether by the user or by the game itself.
+pub struct ConfigWatcher {
+    _watcher:  RecommendedWatcher, // watching stops when this is dropped
+    events:    Receiver<DebouncedEvent>,
+    file_name: OsString,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, DEBOUNCE_DELAY)?;
+        // Watch the directory rather than the file, since many editors save by writing a new file
+        // and renaming it over the old one.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(ConfigWatcher {
+            _watcher:  watcher,
+            events:    rx,
+            file_name: path.file_name().map(|name| name.to_owned()).unwrap_or_default(),
+        })
+    }
+
+    /// Returns true if the file has been created or written since the last call. Never blocks.
+    pub fn poll_changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => {
+                    changed |= path.file_name() == Some(self.file_name.as_os_str());
+                }
+                DebouncedEvent::Error(e, opt_path) => warn!("Error while watching config {:?}: {}", opt_path, e),
+                _ => {}
+            }
+        }
+        changed
+    }
+}
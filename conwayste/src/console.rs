@@ -0,0 +1,384 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! A drop-down console for developers, toggled with the backtick key. It only parses commands and
+//! keeps the text; the client runs them.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use ggez::graphics::{self, DrawMode, DrawParam, Font, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+use log::LevelFilter;
+
+use crate::cell_batch::CellRendering;
+use crate::constants::colors::scheme;
+use crate::ui::draw_text;
+
+const COMMANDS: &[&str] = &[
+    "cells", "clear", "help", "inspect", "lockstep", "log", "notice", "speed", "spawn", "tree",
+];
+const LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+const LOCKSTEP_ARGS: &[&str] = &["off", "on", "show"];
+const CELLS_ARGS: &[&str] = &["chunked", "instanced", "per-frame"];
+
+const HELP: &[&str] = &[
+    "spawn <pattern> <col> <row>  insert a pattern, by key (like 2) or as RLE",
+    "speed <generations/sec>      run at a fixed speed; 'speed frame' for one per frame",
+    "tree                         print the widget tree of the current screen",
+    "inspect                      show or hide the outlines and details of the widgets on screen",
+    "notice <text>                show text as if the server had sent it",
+    "log [module] <level>         set the log level of a module, or of everything",
+    "lockstep [on|off|show]       check networked games against the server; show draws where they split",
+    "cells [<way>]                how cells are drawn: chunked, or the older instanced or per-frame, to compare",
+    "clear                        clear this console",
+];
+
+const MAX_OUTPUT_LINES: usize = 500;
+const MAX_HISTORY: usize = 100;
+const HEIGHT_FRACTION: f32 = 0.4; // of the screen
+const LINE_HEIGHT: f32 = 22.0; // pixels
+const MARGIN: f32 = 8.0; // pixels
+
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    Spawn {
+        pattern: String,
+        col:     isize,
+        row:     isize,
+    },
+    Speed(Option<f64>), // generations per second; None for one per frame
+    Tree,
+    Inspect, // toggles the widget inspector
+    Notice(String),
+    Log {
+        module: String,
+        level:  LevelFilter,
+    }, // empty module for every module
+    Clear,
+    Lockstep(Option<LockstepCommand>), // None to print how the check is going
+    Cells(Option<CellRendering>),      // None to print how they are drawn now
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LockstepCommand {
+    On,
+    Off,
+    Show, // toggles drawing this client's universe at the first divergent generation
+}
+
+/// Parses a line typed into the console. The error is a message to show the user.
+pub fn parse(line: &str) -> Result<ConsoleCommand, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let number = |s: &str| s.parse::<isize>().map_err(|_| format!("not a number: {}", s));
+    match (command, args.as_slice()) {
+        ("help", []) => Ok(ConsoleCommand::Help),
+        ("clear", []) => Ok(ConsoleCommand::Clear),
+        ("spawn", [pattern, col, row]) => Ok(ConsoleCommand::Spawn {
+            pattern: (*pattern).to_owned(),
+            col:     number(col)?,
+            row:     number(row)?,
+        }),
+        ("speed", ["frame"]) => Ok(ConsoleCommand::Speed(None)),
+        ("speed", [gens_per_second]) => match gens_per_second.parse::<f64>() {
+            Ok(gens_per_second) if gens_per_second > 0.0 && gens_per_second.is_finite() => {
+                Ok(ConsoleCommand::Speed(Some(gens_per_second)))
+            }
+            _ => Err(format!("not a positive speed: {}", gens_per_second)),
+        },
+        ("tree", []) => Ok(ConsoleCommand::Tree),
+        ("inspect", []) => Ok(ConsoleCommand::Inspect),
+        ("notice", [_, ..]) => {
+            // keep the text as typed, spacing and all
+            let text = line.trim_start()["notice".len()..].trim();
+            Ok(ConsoleCommand::Notice(text.to_owned()))
+        }
+        ("log", [level]) => Ok(ConsoleCommand::Log {
+            module: String::new(),
+            level:  LevelFilter::from_str(level).map_err(|_| format!("not a log level: {}", level))?,
+        }),
+        ("log", [module, level]) => Ok(ConsoleCommand::Log {
+            module: (*module).to_owned(),
+            level:  LevelFilter::from_str(level).map_err(|_| format!("not a log level: {}", level))?,
+        }),
+        ("lockstep", []) => Ok(ConsoleCommand::Lockstep(None)),
+        ("lockstep", ["on"]) => Ok(ConsoleCommand::Lockstep(Some(LockstepCommand::On))),
+        ("lockstep", ["off"]) => Ok(ConsoleCommand::Lockstep(Some(LockstepCommand::Off))),
+        ("lockstep", ["show"]) => Ok(ConsoleCommand::Lockstep(Some(LockstepCommand::Show))),
+        ("cells", []) => Ok(ConsoleCommand::Cells(None)),
+        ("cells", ["chunked"]) => Ok(ConsoleCommand::Cells(Some(CellRendering::Chunked))),
+        ("cells", ["instanced"]) => Ok(ConsoleCommand::Cells(Some(CellRendering::Instanced))),
+        ("cells", ["per-frame"]) => Ok(ConsoleCommand::Cells(Some(CellRendering::PerFrame))),
+        ("", []) => Err(String::new()),
+        (command, _) if COMMANDS.contains(&command) => Err(format!("wrong arguments for {}; try help", command)),
+        (command, _) => Err(format!("unknown command: {}; try help", command)),
+    }
+}
+
+/// Completes the last word of `line`: a command name, or an argument of `log`, `lockstep` or
+/// `cells`.
+/// Returns the line with as much added as all the candidates share, and the candidates.
+pub fn complete(line: &str) -> (String, Vec<String>) {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let ends_in_space = line.ends_with(char::is_whitespace);
+    let (done, partial) = if ends_in_space || words.is_empty() {
+        (words.len(), "")
+    } else {
+        (words.len() - 1, words[words.len() - 1])
+    };
+
+    let choices: &[&str] = match (done, words.first()) {
+        (0, _) => COMMANDS,
+        (1, Some(&"log")) | (2, Some(&"log")) => LEVELS,
+        (1, Some(&"lockstep")) => LOCKSTEP_ARGS,
+        (1, Some(&"cells")) => CELLS_ARGS,
+        _ => &[],
+    };
+    let candidates: Vec<String> = choices
+        .iter()
+        .filter(|choice| choice.starts_with(partial))
+        .map(|choice| (*choice).to_owned())
+        .collect();
+
+    let mut completed = line.to_owned();
+    if let Some(first) = candidates.first() {
+        let shared = candidates.iter().fold(first.len(), |len, candidate| {
+            first
+                .chars()
+                .zip(candidate.chars())
+                .take(len)
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+        completed.push_str(&first[partial.len()..shared]);
+        if candidates.len() == 1 {
+            completed.push(' ');
+        }
+    }
+    (completed, candidates)
+}
+
+pub struct Console {
+    pub visible:   bool,
+    input:         String,
+    history:       Vec<String>,   // oldest first
+    history_index: Option<usize>, // which history entry is being shown in the input, if any
+    output:        VecDeque<String>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let mut console = Console {
+            visible:       false,
+            input:         String::new(),
+            history:       vec![],
+            history_index: None,
+            output:        VecDeque::new(),
+        };
+        console.print("Type help for a list of commands.");
+        console
+    }
+
+    pub fn type_char(&mut self, character: char) {
+        if !character.is_control() {
+            self.input.push(character);
+        }
+    }
+
+    /// Handles a key press. Returns the line entered when Return is pressed.
+    pub fn key(&mut self, keycode: KeyCode) -> Option<String> {
+        match keycode {
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                let line = std::mem::replace(&mut self.input, String::new());
+                self.history_index = None;
+                if line.trim().is_empty() {
+                    return None;
+                }
+                self.print(format!("> {}", line));
+                if self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                    if self.history.len() > MAX_HISTORY {
+                        self.history.remove(0);
+                    }
+                }
+                return Some(line);
+            }
+            KeyCode::Back => {
+                self.input.pop();
+            }
+            KeyCode::Up => {
+                let index = match self.history_index {
+                    Some(i) => i.saturating_sub(1),
+                    None if self.history.is_empty() => return None,
+                    None => self.history.len() - 1,
+                };
+                self.history_index = Some(index);
+                self.input = self.history[index].clone();
+            }
+            KeyCode::Down => match self.history_index {
+                Some(i) if i + 1 < self.history.len() => {
+                    self.history_index = Some(i + 1);
+                    self.input = self.history[i + 1].clone();
+                }
+                Some(_) => {
+                    self.history_index = None;
+                    self.input.clear();
+                }
+                None => {}
+            },
+            KeyCode::Tab => {
+                let (completed, candidates) = complete(&self.input);
+                if candidates.len() > 1 {
+                    self.print(candidates.join("  "));
+                }
+                self.input = completed;
+            }
+            KeyCode::Escape => {
+                self.visible = false;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn print<S: Into<String>>(&mut self, text: S) {
+        for line in text.into().lines() {
+            if self.output.len() == MAX_OUTPUT_LINES {
+                self.output.pop_front();
+            }
+            self.output.push_back(line.to_owned());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.output.clear();
+    }
+
+    pub fn print_help(&mut self) {
+        for line in HELP {
+            self.print(*line);
+        }
+    }
+
+    /// Draws the console across the top of the screen, with the latest output just above the input.
+    pub fn draw(&self, ctx: &mut Context, font: Font) -> GameResult<()> {
+        let (screen_w, screen_h) = graphics::drawable_size(ctx);
+        let height = (screen_h * HEIGHT_FRACTION).floor();
+        let mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, screen_w, height),
+            scheme().chat_pane_fill,
+        )?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+
+        let mut y = height - MARGIN - LINE_HEIGHT;
+        draw_text(
+            ctx,
+            font,
+            scheme().input_text,
+            format!("> {}_", self.input),
+            &Point2 { x: MARGIN, y },
+        )?;
+        for line in self.output.iter().rev() {
+            y -= LINE_HEIGHT;
+            if y < MARGIN {
+                break;
+            }
+            draw_text(ctx, font, scheme().chatbox_text, line.clone(), &Point2 { x: MARGIN, y })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse("spawn 2 10 -3"),
+            Ok(ConsoleCommand::Spawn {
+                pattern: "2".to_owned(),
+                col:     10,
+                row:     -3,
+            })
+        );
+        assert_eq!(parse("speed 30"), Ok(ConsoleCommand::Speed(Some(30.0))));
+        assert_eq!(parse("speed frame"), Ok(ConsoleCommand::Speed(None)));
+        assert!(parse("speed -1").is_err());
+        assert_eq!(
+            parse("  notice  server  restarting "),
+            Ok(ConsoleCommand::Notice("server  restarting".to_owned()))
+        );
+        assert_eq!(
+            parse("log conway trace"),
+            Ok(ConsoleCommand::Log {
+                module: "conway".to_owned(),
+                level:  LevelFilter::Trace,
+            })
+        );
+        assert_eq!(
+            parse("log warn"),
+            Ok(ConsoleCommand::Log {
+                module: String::new(),
+                level:  LevelFilter::Warn,
+            })
+        );
+        assert!(parse("log conway loud").is_err());
+        assert_eq!(parse("inspect"), Ok(ConsoleCommand::Inspect));
+        assert!(parse("inspect all").is_err());
+        assert_eq!(parse("lockstep"), Ok(ConsoleCommand::Lockstep(None)));
+        assert_eq!(
+            parse("lockstep show"),
+            Ok(ConsoleCommand::Lockstep(Some(LockstepCommand::Show)))
+        );
+        assert!(parse("lockstep maybe").is_err());
+        assert_eq!(parse("cells"), Ok(ConsoleCommand::Cells(None)));
+        assert_eq!(
+            parse("cells per-frame"),
+            Ok(ConsoleCommand::Cells(Some(CellRendering::PerFrame)))
+        );
+        assert!(parse("cells fast").is_err());
+        assert!(parse("spawn 2").is_err());
+        assert!(parse("launch").is_err());
+    }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(
+            complete("sp"),
+            ("sp".to_owned(), vec!["speed".to_owned(), "spawn".to_owned()])
+        );
+        assert_eq!(complete("spa"), ("spawn ".to_owned(), vec!["spawn".to_owned()]));
+        assert_eq!(
+            complete("log conway tr"),
+            ("log conway trace ".to_owned(), vec!["trace".to_owned()])
+        );
+        assert_eq!(complete("log e").1, vec!["error".to_owned()]);
+        assert_eq!(complete("lockstep o").1, vec!["off".to_owned(), "on".to_owned()]);
+        assert_eq!(complete("").1.len(), COMMANDS.len());
+        assert_eq!(complete("tree x"), ("tree x".to_owned(), vec![]));
+    }
+}
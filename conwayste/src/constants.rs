@@ -29,28 +29,141 @@ pub const INTRO_UNIVERSE_HEIGHT_IN_CELLS: usize = 256;
 pub const CURRENT_PLAYER_ID: usize = 1; // TODO:  get the player ID from server rather than hardcoding
 pub const FOG_RADIUS: usize = 4; // cells
 pub const HISTORY_SIZE: usize = 16;
+pub const BOT_PLAYER_ID: usize = 0; // the offline opponent
+pub const BOT_TURN_INTERVAL_IN_GENS: usize = 40;
+pub const MAX_TEAMS: usize = 4; // must match the server's limit
+pub const MAX_SERIES_ROUNDS: u8 = 9; // must match the server's limit
+pub const DEFAULT_MATCH_GENERATIONS: u32 = 1000; // the server's, for rooms that had no game length yet
+pub const MAX_ROOM_NAME_LEN: usize = 16; // must match the server's limit
 
 // Colors
 pub mod colors {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
     use crate::ui::common::color_with_alpha;
     use chromatica::css;
     use ggez::graphics::Color;
 
+    /// Colors of the user interface that change in high-contrast mode.
+    pub struct ColorScheme {
+        pub input_text:               Color,
+        pub input_invalid:            Color, // border and message of a text field that fails validation
+        pub input_selection:          Color, // behind selected text
+        pub chatbox_text:             Color,
+        pub chatbox_border:           Color,
+        pub chatbox_inactive_border:  Color,
+        pub chatbox_border_on_hover:  Color,
+        pub chat_pane_fill:           Color,
+        pub menu_text:                Color,
+        pub value_text:               Color, // for settings shown next to their names
+        pub button:                   Color, // a secondary button, which is the default
+        pub button_primary:           Color, // the main action of a screen
+        pub button_danger:            Color, // one that quits or throws something away
+        pub checkbox_text:            Color,
+        pub checkbox_border_on_hover: Color,
+        pub checkbox_toggled_fill:    Color,
+        pub color_picker_selected:    Color,
+        pub pane_border:              Color,
+        pub layer_transparency_bg:    Color,
+        pub toast_text:               Color,
+        pub gen_counter:              Color,
+        pub menu_background:          Color, // cells of the soup behind the menus
+        pub tutorial_bubble:          Color, // behind the tutorial's callouts and their pointers
+        pub tutorial_text:            Color,
+        pub editor_grid:              Color, // lines between the cells of the pattern editor
+        pub editor_cell:              Color, // live cells in the pattern editor
+        pub editor_preview:           Color, // where a line or rectangle being drawn will go
+        pub map_wall:                 Color, // walls on the room lobby's map
+        pub map_spawn_region:         Color, // outlines of the spawn regions on it
+        pub map_fog_free_region:      Color, // and of the fog-free regions
+    }
+
+    static HIGH_CONTRAST: AtomicBool = AtomicBool::new(false);
+
+    /// The colors to draw the user interface with. Widgets that pick their colors when they are
+    /// created only see a change once they are rebuilt.
+    pub fn scheme() -> &'static ColorScheme {
+        if HIGH_CONTRAST.load(Ordering::Relaxed) {
+            &HIGH_CONTRAST_SCHEME
+        } else {
+            &DEFAULT_SCHEME
+        }
+    }
+
+    pub fn set_high_contrast(enabled: bool) {
+        HIGH_CONTRAST.store(enabled, Ordering::Relaxed);
+    }
+
     lazy_static! {
         // To see what the colors look like: https://developer.mozilla.org/en-US/docs/Web/CSS/color_value#Color_keywords
         // TODO: probably can consoldate/remove many of these once the design is fleshed out more
-        pub static ref INPUT_TEXT_COLOR: Color = Color::from(css::DARKRED);
-        pub static ref CHATBOX_TEXT_COLOR: Color = Color::from(css::DARKRED);
-        pub static ref CHATBOX_BORDER_COLOR: Color = Color::from(css::FIREBRICK);
-        pub static ref CHATBOX_INACTIVE_BORDER_COLOR: Color = color_with_alpha(css::VIOLET, 0.5);
-        pub static ref CHATBOX_BORDER_ON_HOVER_COLOR: Color = Color::from(css::TEAL);
-        pub static ref MENU_TEXT_COLOR: Color = Color::from(css::WHITE);
+        static ref DEFAULT_SCHEME: ColorScheme = ColorScheme {
+            input_text:               Color::from(css::DARKRED),
+            input_invalid:            Color::from(css::RED),
+            input_selection:          color_with_alpha(css::GOLD, 0.5),
+            chatbox_text:             Color::from(css::DARKRED),
+            chatbox_border:           Color::from(css::FIREBRICK),
+            chatbox_inactive_border:  color_with_alpha(css::VIOLET, 0.5),
+            chatbox_border_on_hover:  Color::from(css::TEAL),
+            chat_pane_fill:           color_with_alpha(css::TURQUOISE, 0.33),
+            menu_text:                Color::from(css::WHITE),
+            value_text:               Color::from(css::AQUAMARINE),
+            button:                   color_with_alpha(css::DARKCYAN, 0.8),
+            button_primary:           color_with_alpha(css::SEAGREEN, 0.9),
+            button_danger:            color_with_alpha(css::FIREBRICK, 0.8),
+            checkbox_text:            Color::from(css::WHITE),
+            checkbox_border_on_hover: Color::from(css::VIOLET),
+            checkbox_toggled_fill:    Color::from(css::AZURE),
+            color_picker_selected:    Color::from(css::WHITE),
+            pane_border:              Color::from(css::FIREBRICK),
+            layer_transparency_bg:    color_with_alpha(css::HONEYDEW, 0.4),
+            toast_text:               Color::from(css::ORANGE),
+            gen_counter:              Color::from(css::RED),
+            menu_background:          color_with_alpha(css::TURQUOISE, 0.12),
+            tutorial_bubble:          color_with_alpha(css::MIDNIGHTBLUE, 0.9),
+            tutorial_text:            Color::from(css::WHITE),
+            editor_grid:              color_with_alpha(css::WHITE, 0.15),
+            editor_cell:              Color::from(css::AQUAMARINE),
+            editor_preview:           color_with_alpha(css::GOLD, 0.6),
+            map_wall:                 Color::from(css::SLATEGRAY),
+            map_spawn_region:         Color::from(css::SEAGREEN),
+            map_fog_free_region:      Color::from(css::LIGHTSKYBLUE),
+        };
+        // Light text and borders on solid black, avoiding translucency
+        static ref HIGH_CONTRAST_SCHEME: ColorScheme = ColorScheme {
+            input_text:               Color::from(css::WHITE),
+            input_invalid:            Color::from(css::ORANGERED),
+            input_selection:          Color::from(css::BLUE),
+            chatbox_text:             Color::from(css::WHITE),
+            chatbox_border:           Color::from(css::YELLOW),
+            chatbox_inactive_border:  Color::from(css::WHITE),
+            chatbox_border_on_hover:  Color::from(css::YELLOW),
+            chat_pane_fill:           Color::from(css::BLACK),
+            menu_text:                Color::from(css::WHITE),
+            value_text:               Color::from(css::YELLOW),
+            button:                   Color::from(css::BLUE),
+            button_primary:           Color::from(css::GREEN),
+            button_danger:            Color::from(css::RED),
+            checkbox_text:            Color::from(css::WHITE),
+            checkbox_border_on_hover: Color::from(css::YELLOW),
+            checkbox_toggled_fill:    Color::from(css::WHITE),
+            color_picker_selected:    Color::from(css::YELLOW),
+            pane_border:              Color::from(css::WHITE),
+            layer_transparency_bg:    color_with_alpha(css::BLACK, 0.8),
+            toast_text:               Color::from(css::YELLOW),
+            gen_counter:              Color::from(css::YELLOW),
+            menu_background:          color_with_alpha(css::WHITE, 0.08),
+            tutorial_bubble:          Color::from(css::BLACK),
+            tutorial_text:            Color::from(css::YELLOW),
+            editor_grid:              Color::from(css::DIMGRAY),
+            editor_cell:              Color::from(css::WHITE),
+            editor_preview:           Color::from(css::YELLOW),
+            map_wall:                 Color::from(css::WHITE),
+            map_spawn_region:         Color::from(css::LIME),
+            map_fog_free_region:      Color::from(css::CYAN),
+        };
+
         pub static ref MENU_TEXT_SELECTED_COLOR: Color = Color::from(css::LIME);
-        pub static ref CHECKBOX_TEXT_COLOR: Color = Color::from(css::WHITE);
-        pub static ref CHECKBOX_BORDER_ON_HOVER_COLOR: Color = Color::from(css::VIOLET);
-        pub static ref CHECKBOX_TOGGLED_FILL_COLOR: Color = Color::from(css::AZURE);
-        pub static ref CHAT_PANE_FILL_COLOR: Color = color_with_alpha(css::TURQUOISE, 0.33);
-        pub static ref PANE_BORDER_COLOR: Color = Color::from(css::FIREBRICK);
         pub static ref CELL_STATE_DEAD_COLOR: Color = Color::new(0.875, 0.875, 0.875, 1.0);
         pub static ref CELL_STATE_BG_FILL_SOLID_COLOR: Color = Color::from(css::WHITE);
         pub static ref CELL_STATE_BG_FILL_HOLLOW_COLOR: Color = Color::from(css::BLACK);
@@ -58,12 +171,23 @@ pub mod colors {
         pub static ref CELL_STATE_ALIVE_PLAYER_1_COLOR: Color = Color::from(css::BLUE);
         pub static ref CELL_STATE_WALL_COLOR: Color = Color::new(0.617, 0.55, 0.41, 1.0);
         pub static ref CELL_STATE_FOG_COLOR: Color = Color::new(0.780, 0.780, 0.780, 1.0);
-        pub static ref GEN_COUNTER_COLOR: Color = Color::from(css::RED);
         pub static ref UNIVERSE_BG_COLOR: Color = Color::new( 0.25,  0.25,  0.25, 1.0);
-        pub static ref LAYER_TRANSPARENCY_BG_COLOR: Color = color_with_alpha(css::HONEYDEW, 0.4);
         pub static ref OPTIONS_TEXT_FILL_COLOR: Color = Color::from(css::YELLOW);
         pub static ref OPTIONS_LABEL_TEXT_COLOR: Color = Color::from(css::WHITE);
         pub static ref INSERT_PATTERN_UNWRITABLE: Color = Color::from(css::RED);
+        pub static ref CELL_SELECTION_COLOR: Color = Color::from(css::GOLD);
+        pub static ref SYMMETRY_AXIS_COLOR: Color = color_with_alpha(css::CYAN, 0.6);
+        pub static ref ANNOTATION_COLOR: Color = Color::from(css::ORANGE);
+        pub static ref ANNOTATION_SHARED_COLOR: Color = Color::from(css::VIOLET);
+        pub static ref INSPECTOR_OUTLINE_COLOR: Color = color_with_alpha(css::MAGENTA, 0.6);
+        pub static ref INSPECTOR_HOVER_COLOR: Color = Color::from(css::LIME);
+        // One family of shades per team, so teammates look related but can still be told apart.
+        pub static ref TEAM_COLOR_FAMILIES: Vec<Vec<Color>> = vec![
+            vec![Color::from(css::RED), Color::from(css::FIREBRICK), Color::from(css::SALMON)],
+            vec![Color::from(css::BLUE), Color::from(css::ROYALBLUE), Color::from(css::LIGHTSKYBLUE)],
+            vec![Color::from(css::GREEN), Color::from(css::LIMEGREEN), Color::from(css::DARKOLIVEGREEN)],
+            vec![Color::from(css::GOLD), Color::from(css::ORANGE), Color::from(css::KHAKI)],
+        ];
     }
 
     pub const BLACK: Color = Color {
@@ -87,14 +211,25 @@ pub const DEFAULT_ZOOM_LEVEL: f32 = 5.0; // default cell size in pixels
                                          //pub const FPS: u32 = 25;
 pub const GRID_DRAW_STYLE: DrawStyle = DrawStyle::Fill;
 pub const INTRO_DURATION: f64 = 8.0; // seconds
+pub const INTRO_LOGO_FADE_IN: f64 = 2.0; // seconds
+pub const INTRO_LOGO_HEIGHT: f32 = 96.0; // pixels
 pub const INTRO_PAUSE_DURATION: f64 = 3.0; // seconds
 pub const MAX_CELL_SIZE: f32 = 40.0; // pixels
-pub const MIN_CELL_SIZE: f32 = 5.0; // pixels
+pub const MIN_CELL_SIZE: f32 = 5.0; // pixels; zooming out past this shows the density of cells instead
+pub const MIN_DENSITY_CELL_SIZE: f32 = 0.125; // pixels; 8x8 cells to a pixel
+pub const MIN_GRID_LINE_CELL_SIZE: f32 = 4.0; // pixels; below this, grid lines would hide the cells
 pub const PIXELS_SCROLLED_PER_FRAME: f32 = 50.0; // pixels
+pub const PROGRESS_BAR_WIDTH: f32 = 300.0; // pixels
+pub const STATS_BAR_WIDTH: f32 = 20.0; // pixels, for each day in the chart of cells placed
+pub const STATS_CHART_HEIGHT: f32 = 150.0; // pixels, of the tallest bar
+pub const VIEWPORT_REPORT_INTERVAL: Duration = Duration::from_millis(250); // least time between telling the server
 
 // persistent configuration
-pub const CONFIG_FILE_PATH: &str = "conwayste.toml";
+pub const CONFIG_FILE_NAME: &str = "conwayste.toml"; // in the config directory
 pub const MIN_CONFIG_FLUSH_TIME: Duration = Duration::from_millis(5000);
+pub const SCRIPTS_DIR: &str = "scripts";
+pub const SCRIPT_FILE_EXTENSION: &str = "rhai";
+pub const LAYOUTS_DIR: &str = "layouts"; // layout files here replace the built-in ones
 
 // user interface
 lazy_static! {
@@ -110,7 +245,26 @@ lazy_static! {
 pub const CHATBOX_BORDER_PIXELS: f32 = 1.0;
 pub const CHATBOX_LINE_SPACING: f32 = 2.0;
 pub const CHATBOX_HISTORY: usize = 20;
+pub const CHATBOX_SCROLL_LINES: f32 = 3.0; // per notch of the mouse wheel
 pub const CHAT_TEXTFIELD_HEIGHT: f32 = 25.0;
+pub const CHAT_TEXTFIELD_MAX_LINES: usize = 4; // grows to this many lines of text before scrolling
+pub const ROOM_CHAT_TAB: usize = 0; // tabs of the in-game chatbox
+pub const LOBBY_CHAT_TAB: usize = 1;
+pub const BRIDGE_CHAT_TAB: usize = 2; // added once the chat bridge is first turned on
+// Largest allowed `min_font_size` in the [accessibility] config section, in pixels. Larger text
+// would not fit in the menus.
+pub const MAX_MIN_FONT_SIZE: f32 = 32.0;
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+// When nothing on a menu changes, frames are skipped: the loop waits this long between them, and
+// draws the screen anyway once this long has passed since it last did.
+pub const IDLE_FRAME_SLEEP: Duration = Duration::from_millis(50);
+pub const IDLE_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+// How long "<name> is typing..." stays up in the chatbox unless the server says they still are.
+pub const CHAT_TYPING_TIMEOUT: Duration = Duration::from_secs(4);
+pub const CHAT_FLASH_DURATION: Duration = Duration::from_secs(3); // how long a collapsed chatbox shows a new message
+pub const SECTION_EXPAND_DURATION: Duration = Duration::from_millis(150); // for a collapsible section to open or close
+pub const SCROLL_PANE_STEP: f32 = 30.0; // pixels scrolled per notch of the mouse wheel
+pub const SCROLLBAR_WIDTH: f32 = 10.0;
 
 // Layering's tree data structure capacities. Arbitrarily chosen.
 pub const LAYERING_NODE_CAPACITY: usize = 100;
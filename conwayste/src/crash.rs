@@ -0,0 +1,208 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Crash reports for bug reports.
+//!
+//! While the game runs, the client keeps this module up to date on where the player is (screen,
+//! room, generation) and notes recent network and UI events. If the game panics, all of that is
+//! written out with a backtrace and the latest log lines as a pending report. On the next launch the
+//! player is asked whether to keep it; only then is it given its final name in the log directory.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+
+use crate::logging;
+use crate::user_data::Folder;
+use crate::Screen;
+
+const RECENT_EVENTS: usize = 50;
+const MAX_EVENT_LEN: usize = 200; // characters; longer events are cut short
+const PENDING_EXTENSION: &str = "pending";
+
+/// Where the player was when the game crashed.
+#[derive(Default)]
+struct Session {
+    screen:     Option<Screen>,
+    room:       Option<String>,
+    generation: Option<usize>, // of the game being played, if any
+}
+
+lazy_static! {
+    static ref SESSION: Mutex<Session> = Mutex::new(Session::default());
+    static ref EVENTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(RECENT_EVENTS));
+    static ref PROMPT_ANSWER: Mutex<Option<bool>> = Mutex::new(None); // Some(true) to save the report
+}
+
+pub fn set_screen(screen: Screen) {
+    SESSION.lock().unwrap().screen = Some(screen);
+}
+
+pub fn set_room(room: Option<String>) {
+    SESSION.lock().unwrap().room = room;
+}
+
+pub fn set_generation(generation: Option<usize>) {
+    SESSION.lock().unwrap().generation = generation;
+}
+
+/// Notes a network or UI event, to be listed in a crash report. Only the latest ones are kept.
+pub fn note_event<S: Into<String>>(event: S) {
+    let mut event = event.into();
+    if let Some((cut, _)) = event.char_indices().nth(MAX_EVENT_LEN) {
+        event.truncate(cut);
+        event.push('…');
+    }
+    let mut events = EVENTS.lock().unwrap();
+    if events.len() == RECENT_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(format!("{} {}", Local::now().format("%H:%M:%S%.3f"), event));
+}
+
+/// Makes panics write a pending crash report, before the panic hook that was installed already.
+/// Call after any other panic hook is installed.
+pub fn install_panic_hook() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match write_report(&Folder::Logs.path(), &info.to_string()) {
+            Ok(path) => eprintln!("Wrote a crash report to {:?}", path),
+            Err(e) => eprintln!("Could not write a crash report: {}", e),
+        }
+        previous_hook(info);
+    }));
+}
+
+fn write_report(dir: &Path, panic_message: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let now = Local::now();
+    let path = dir.join(format!(
+        "crash-{}.txt.{}",
+        now.format("%Y%m%d-%H%M%S"),
+        PENDING_EXTENSION
+    ));
+    let mut f = File::create(&path)?;
+    writeln!(f, "conwayste {} crashed at {}", version!(), now.to_rfc3339())?;
+    writeln!(f, "{}", panic_message)?;
+
+    // The panic may have happened while one of these locks was held, so don't wait on them.
+    writeln!(f, "\nSession:")?;
+    match SESSION.try_lock() {
+        Ok(session) => {
+            writeln!(f, "screen: {:?}", session.screen)?;
+            writeln!(f, "room: {}", session.room.as_deref().unwrap_or("(none)"))?;
+            match session.generation {
+                Some(generation) => writeln!(f, "generation: {}", generation)?,
+                None => writeln!(f, "generation: (no game)")?,
+            }
+        }
+        Err(_) => writeln!(f, "(unavailable)")?,
+    }
+    writeln!(f, "\nRecent events:")?;
+    if let Ok(events) = EVENTS.try_lock() {
+        for event in events.iter() {
+            writeln!(f, "{}", event)?;
+        }
+    }
+    writeln!(f, "\nLast log lines:")?;
+    for line in logging::recent_lines() {
+        writeln!(f, "{}", line)?;
+    }
+    writeln!(f, "\nBacktrace:\n{:?}", backtrace::Backtrace::new())?;
+    Ok(path)
+}
+
+/// Crash reports from earlier runs that the player hasn't decided about yet.
+fn pending_reports(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![], // no log directory yet, so no crashes
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == PENDING_EXTENSION))
+        .collect()
+}
+
+pub fn has_pending_reports() -> bool {
+    !pending_reports(&Folder::Logs.path()).is_empty()
+}
+
+/// Records the player's answer to the prompt shown on launch. The client acts on it with
+/// `take_prompt_answer`.
+pub fn answer_prompt(save: bool) {
+    *PROMPT_ANSWER.lock().unwrap() = Some(save);
+}
+
+pub fn take_prompt_answer() -> Option<bool> {
+    PROMPT_ANSWER.lock().unwrap().take()
+}
+
+/// Keeps or deletes the pending crash reports. Returns the paths of the kept ones.
+pub fn resolve_pending_reports(save: bool) -> io::Result<Vec<PathBuf>> {
+    resolve_pending_in(&Folder::Logs.path(), save)
+}
+
+fn resolve_pending_in(dir: &Path, save: bool) -> io::Result<Vec<PathBuf>> {
+    let mut saved = vec![];
+    for path in pending_reports(dir) {
+        if save {
+            let final_path = path.with_extension(""); // crash-<time>.txt
+            fs::rename(&path, &final_path)?;
+            saved.push(final_path);
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pending_reports_are_kept_or_deleted() {
+        let dir = std::env::temp_dir().join(format!("conwayste-crash-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        note_event(format!("chat: {}", "x".repeat(MAX_EVENT_LEN * 2)));
+
+        let path = write_report(&dir, "panicked at 'test'").unwrap();
+        assert_eq!(pending_reports(&dir), vec![path.clone()]);
+        let report = fs::read_to_string(&path).unwrap();
+        assert!(report.contains("panicked at 'test'"));
+        assert!(report.contains(&format!("chat: {}…", "x".repeat(MAX_EVENT_LEN - "chat: ".len()))));
+
+        let saved = resolve_pending_in(&dir, true).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].to_str().unwrap().ends_with(".txt"));
+        assert!(pending_reports(&dir).is_empty());
+
+        write_report(&dir, "panicked again").unwrap();
+        assert!(resolve_pending_in(&dir, false).unwrap().is_empty());
+        assert!(pending_reports(&dir).is_empty());
+        assert!(saved[0].exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! A panel of performance numbers for developers, toggled with F3 and drawn over every screen.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ggez::graphics::{self, Font, Rect};
+use ggez::mint::Point2;
+use ggez::{timer, Context};
+
+use id_tree::NodeId;
+
+use crate::cell_batch::CellRendering;
+use crate::constants::{self, colors::scheme};
+use crate::ui::{common::FontInfo, InsertLocation, Label, Layering, Pane, UIResult, Widget};
+
+const FRAME_HISTORY: usize = 300; // frames kept for the frame time percentiles
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250); // how often the text is updated
+const LINES: usize = 7;
+const LINE_HEIGHT: f32 = 20.0; // pixels
+const PANEL_WIDTH: f32 = 420.0; // pixels
+const MARGIN: f32 = 10.0; // pixels between the panel and the edge of the screen, and around the text
+
+/// What the client drew in the last frame, for the overlay to show.
+pub struct DrawStats {
+    pub widgets_drawn:     usize,
+    pub widget_tree_depth: usize,
+}
+
+pub struct DebugOverlay {
+    pub visible:       bool,
+    layering:          Layering, // drawn above everything else
+    pane_id:           NodeId,
+    label_ids:         Vec<NodeId>, // one per line of text
+    frame_times:       VecDeque<Duration>,
+    update_time:       Duration,
+    sprites_drawn:     Cell<usize>, // cells drawn in the universe; set while drawing with &self
+    cell_rendering:    Cell<CellRendering>, // how they were drawn
+    net_queue_lengths: Option<(usize, usize, usize)>, // (tx packets, rx packets, chat messages)
+    corrupt_updates:   u64,         // universe updates the network layer dropped for a bad checksum
+    lockstep_status:   Option<String>, // None unless a networked game is being checked
+    last_refresh:      Option<Instant>,
+}
+
+impl DebugOverlay {
+    pub fn new(ctx: &mut Context, font: Font) -> UIResult<Self> {
+        let mut layering = Layering::new();
+        let font_info = FontInfo::new(ctx, font, Some(*constants::DEFAULT_CHATBOX_FONT_SCALE));
+
+        let mut pane = Box::new(Pane::new(Rect::new(
+            0.0,
+            MARGIN,
+            PANEL_WIDTH,
+            LINES as f32 * LINE_HEIGHT + 2.0 * MARGIN,
+        )));
+        pane.bg_color = Some(scheme().chat_pane_fill);
+        let pane_id = layering.add_widget(pane, InsertLocation::AtCurrentLayer)?;
+
+        let mut label_ids = vec![];
+        for line in 0..LINES {
+            let label = Box::new(Label::new(
+                ctx,
+                font_info,
+                String::new(),
+                scheme().value_text,
+                Point2 {
+                    x: MARGIN,
+                    y: 2.0 * MARGIN + line as f32 * LINE_HEIGHT,
+                },
+            ));
+            label_ids.push(layering.add_widget(label, InsertLocation::AtCurrentLayer)?);
+        }
+
+        Ok(DebugOverlay {
+            visible: false,
+            layering,
+            pane_id,
+            label_ids,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            update_time: Duration::from_secs(0),
+            sprites_drawn: Cell::new(0),
+            cell_rendering: Cell::new(CellRendering::Chunked),
+            net_queue_lengths: None,
+            corrupt_updates: 0,
+            lockstep_status: None,
+            last_refresh: None,
+        })
+    }
+
+    /// Records how long the last frame took, from one update to the next.
+    pub fn record_frame_time(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// Records how long the client's update took.
+    pub fn record_update_time(&mut self, update_time: Duration) {
+        self.update_time = update_time;
+    }
+
+    pub fn set_sprites_drawn(&self, count: usize) {
+        self.sprites_drawn.set(count);
+    }
+
+    pub fn set_cell_rendering(&self, rendering: CellRendering) {
+        self.cell_rendering.set(rendering);
+    }
+
+    pub fn set_net_queue_lengths(&mut self, tx_packets: usize, rx_packets: usize, chat_messages: usize) {
+        self.net_queue_lengths = Some((tx_packets, rx_packets, chat_messages));
+    }
+
+    pub fn set_corrupt_updates(&mut self, count: u64) {
+        self.corrupt_updates = count;
+    }
+
+    pub fn set_lockstep_status(&mut self, status: Option<String>) {
+        self.lockstep_status = status;
+    }
+
+    fn lines(&self, ctx: &Context, stats: &DrawStats) -> Vec<String> {
+        let mut sorted: Vec<Duration> = self.frame_times.iter().copied().collect();
+        sorted.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut net = match self.net_queue_lengths {
+            Some((tx, rx, chat)) => format!("Net queues: tx {}, rx {}, chat {}", tx, rx, chat),
+            None => "Net queues: not connected".to_owned(),
+        };
+        if self.corrupt_updates > 0 {
+            net.push_str(&format!(", {} corrupt dropped", self.corrupt_updates));
+        }
+        vec![
+            format!("FPS: {:.1}", timer::fps(ctx)),
+            format!(
+                "Frame ms: p50 {:.1}  p95 {:.1}  p99 {:.1}",
+                ms(percentile(&sorted, 50.0)),
+                ms(percentile(&sorted, 95.0)),
+                ms(percentile(&sorted, 99.0))
+            ),
+            format!("Update ms: {:.2}", ms(self.update_time)),
+            format!(
+                "Drawn: {} widgets, {} cells ({})",
+                stats.widgets_drawn,
+                self.sprites_drawn.get(),
+                self.cell_rendering.get().name()
+            ),
+            format!("Widget tree depth: {}", stats.widget_tree_depth),
+            net,
+            self.lockstep_status.clone().unwrap_or_default(),
+        ]
+    }
+
+    /// Updates the text every so often, keeps the panel in the top right corner, and draws it.
+    pub fn draw(&mut self, ctx: &mut Context, stats: &DrawStats) -> UIResult<()> {
+        let due = self.last_refresh.map_or(true, |t| t.elapsed() >= REFRESH_INTERVAL);
+        if due {
+            self.last_refresh = Some(Instant::now());
+            let lines = self.lines(ctx, stats);
+            for (id, text) in self.label_ids.iter().zip(lines) {
+                let (label, texts) = self.layering.get_widget_and_texts_mut(id)?;
+                let label = label.downcast_mut::<Label>().unwrap(); // unwrap OK because these are all Labels
+                label.set_text(ctx, texts, text);
+            }
+        }
+
+        let (screen_w, _) = graphics::drawable_size(ctx);
+        let x = screen_w - PANEL_WIDTH - MARGIN;
+        let pane_x = self.layering.get_widget(&self.pane_id)?.position().x;
+        if x != pane_x {
+            let ids = std::iter::once(&self.pane_id).chain(self.label_ids.iter());
+            for id in ids {
+                let widget = self.layering.get_widget_mut(id)?;
+                let Point2 { x: old_x, y } = widget.position();
+                widget.set_position(old_x + x - pane_x, y);
+            }
+        }
+
+        self.layering.draw(ctx)
+    }
+}
+
+/// The frame time that `p` percent of `sorted` are at or below. Zero if there are none yet.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::from_secs(0);
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50.0), Duration::from_secs(0));
+
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&sorted, 0.0), Duration::from_millis(1));
+    }
+}
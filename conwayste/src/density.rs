@@ -0,0 +1,75 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Showing the universe zoomed out so far that each pixel holds a square of cells. Every pixel is
+//! shaded by how many of its cells are alive, so the shape of what's going on stays visible where
+//! drawing single cells would drop or alias most of them.
+
+use conway::universe::Universe;
+use ggez::graphics::{Color, FilterMode, Image};
+use ggez::{Context, GameResult};
+
+const MIN_SHADE: f32 = 0.25; // alpha of a pixel with a single live cell, so lone cells still show
+
+/// An image of the universe with a pixel for each `cells_per_pixel` square of cells, and the number
+/// of live cells in it.
+pub fn density_image(
+    ctx: &mut Context,
+    universe: &Universe,
+    cells_per_pixel: usize,
+    visibility: Option<usize>,
+    color: Color,
+) -> GameResult<(Image, usize)> {
+    let (columns, rows, counts) = universe.live_counts(cells_per_pixel, visibility);
+    let cells_per_square = (cells_per_pixel * cells_per_pixel) as u32;
+    let rgba = density_pixels(&counts, cells_per_square, color);
+    let mut image = Image::from_rgba8(ctx, columns as u16, rows as u16, &rgba)?;
+    image.set_filter(FilterMode::Nearest);
+    let live = counts.iter().map(|&count| count as usize).sum();
+    Ok((image, live))
+}
+
+/// RGBA pixels of `color`, more opaque the more of a square's cells are alive, and clear where
+/// none are. Opacity goes with the square root of the share alive, to tell sparse squares apart.
+fn density_pixels(counts: &[u32], cells_per_square: u32, color: Color) -> Vec<u8> {
+    let (r, g, b, _) = color.to_rgba();
+    let mut rgba = Vec::with_capacity(counts.len() * 4);
+    for &count in counts {
+        let alpha = if count == 0 {
+            0.0
+        } else {
+            (count as f32 / cells_per_square as f32).sqrt().max(MIN_SHADE)
+        };
+        rgba.extend_from_slice(&[r, g, b, (alpha * 255.0).round() as u8]);
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_denser_squares_are_more_opaque() {
+        let color = Color::from_rgb(10, 20, 30);
+        let pixels = density_pixels(&[0, 1, 16, 64], 64, color);
+        let alphas: Vec<u8> = pixels.chunks(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alphas, vec![0, 64, 128, 255]);
+        assert_eq!(&pixels[4..7], &[10, 20, 30]);
+    }
+}
@@ -0,0 +1,367 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Discord Rich Presence: what the player is doing in the game, shown on their Discord profile,
+//! with a button for friends to join the room they're in. A friend asking to join is passed on for
+//! the player to let in or turn away. It's off unless turned on in the config.
+//!
+//! Discord is talked to on a thread of its own, since every message sent waits for an answer, and
+//! is looked for again every so often if it isn't running. It only passes on a friend joining once
+//! it is next sent something, so the presence is sent again every few seconds even when it hasn't
+//! changed.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+use serde_json::{json, Value};
+
+const MIN_SEND_INTERVAL: Duration = Duration::from_secs(4); // Discord takes five updates in 20 seconds
+const REFRESH_INTERVAL: Duration = Duration::from_secs(8); // sent again this often, to hear of joins
+const RECONNECT_DELAY: Duration = Duration::from_secs(30); // between looks for Discord
+const LARGE_IMAGE: &str = "logo"; // art asset of the Discord application
+const OP_FRAME: u8 = 1;
+const OP_CLOSE: u32 = 2;
+
+/// What the player is doing, for their Discord profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Presence {
+    pub details: String,             // the first line, such as the screen they're on
+    pub state:   Option<String>,     // the second line, such as the room they're in
+    pub started: Option<SystemTime>, // shown as the time since
+    pub party:   Option<Party>,
+}
+
+/// The room the player is in, as Discord shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Party {
+    pub id:   String,
+    pub size: (u32, u32),        // (players, most players)
+    pub join: Option<JoinSecret>, // None if friends can't join from Discord
+}
+
+/// Where a friend who joins from Discord goes. Discord passes it on as a string, without looking
+/// inside.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinSecret {
+    pub server: String, // host:port
+    pub room:   String,
+}
+
+impl JoinSecret {
+    fn encode(&self) -> String {
+        format!("{}/{}", self.server, self.room)
+    }
+
+    fn decode(secret: &str) -> Option<Self> {
+        let (server, room) = secret.split_once('/')?;
+        if server.is_empty() || room.is_empty() {
+            return None;
+        }
+        Some(JoinSecret {
+            server: server.to_owned(),
+            room:   room.to_owned(),
+        })
+    }
+}
+
+/// A friend asking to join the player's room, to be answered with `DiscordPresence::answer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinRequest {
+    pub user_id: String,
+    pub name:    String,
+}
+
+/// What the player did in Discord, for the game to act on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscordEvent {
+    Join(JoinSecret),         // the player joined a friend's room
+    JoinRequest(JoinRequest), // a friend asked to join the player's room
+}
+
+/// What the game asks of the Discord thread.
+#[derive(Debug)]
+enum Command {
+    Show(Presence),
+    Answer(String, bool), // (user ID, true to let them in)
+}
+
+/// A message from Discord that the game acts on.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Answer, // to the presence just sent
+    Event(DiscordEvent),
+    Other,
+}
+
+/// The connection to Discord, for as long as this is kept.
+pub struct DiscordPresence {
+    shown:    Option<Presence>, // last passed to `show`
+    commands: mpsc::Sender<Command>,
+    events:   mpsc::Receiver<DiscordEvent>,
+}
+
+impl DiscordPresence {
+    /// Starts looking for Discord, to show presences as the Discord application `application_id`.
+    pub fn start(application_id: &str) -> Self {
+        let (commands, commands_rx) = mpsc::channel();
+        let (events_tx, events) = mpsc::channel();
+        let application_id = application_id.to_owned();
+        thread::spawn(move || run(application_id, commands_rx, events_tx));
+        DiscordPresence {
+            shown: None,
+            commands,
+            events,
+        }
+    }
+
+    /// Shows `presence` on the player's profile, if it's not already shown.
+    pub fn show(&mut self, presence: Presence) {
+        if self.shown.as_ref() != Some(&presence) {
+            self.shown = Some(presence.clone());
+            let _ = self.commands.send(Command::Show(presence)); // the thread only stops once this is dropped
+        }
+    }
+
+    /// Lets the friend who sent `request` into the player's room if `accept`, or else turns them
+    /// away.
+    pub fn answer(&self, request: &JoinRequest, accept: bool) {
+        let _ = self.commands.send(Command::Answer(request.user_id.clone(), accept));
+    }
+
+    /// What happened since the last call. Never blocks.
+    pub fn poll(&self) -> Vec<DiscordEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+/// Keeps the latest presence from `commands` on the player's profile, and passes on the player's
+/// answers to join requests, until the sender is dropped.
+fn run(application_id: String, commands: mpsc::Receiver<Command>, events: mpsc::Sender<DiscordEvent>) {
+    let mut client: Option<DiscordIpcClient> = None;
+    let mut presence = None;
+    let mut changed = false;
+    let mut answers = vec![]; // (user ID, accept) not yet sent
+    let mut last_look: Option<Instant> = None;
+    let mut last_sent: Option<Instant> = None;
+    loop {
+        let mut received = match commands.recv_timeout(MIN_SEND_INTERVAL) {
+            Ok(command) => vec![command],
+            Err(RecvTimeoutError::Timeout) => vec![],
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        received.extend(commands.try_iter());
+        for command in received {
+            match command {
+                Command::Show(latest) => {
+                    presence = Some(latest);
+                    changed = true;
+                }
+                Command::Answer(user_id, accept) => answers.push((user_id, accept)),
+            }
+        }
+
+        if client.is_none() && last_look.map_or(true, |at| at.elapsed() >= RECONNECT_DELAY) {
+            last_look = Some(Instant::now());
+            client = connect(&application_id);
+            last_sent = None;
+        }
+        let ipc = match client.as_mut() {
+            Some(ipc) => ipc,
+            None => continue,
+        };
+        if let Err(e) = answers.drain(..).try_for_each(|(user_id, accept)| send_answer(ipc, &user_id, accept)) {
+            info!("Lost the connection to Discord: {}", e);
+            client = None;
+            continue;
+        }
+        let presence = match presence.as_ref() {
+            Some(presence) => presence,
+            None => continue,
+        };
+        let due = match last_sent {
+            Some(at) if changed => at.elapsed() >= MIN_SEND_INTERVAL,
+            Some(at) => at.elapsed() >= REFRESH_INTERVAL,
+            None => true,
+        };
+        if !due {
+            continue;
+        }
+        match send_presence(ipc, presence, &events) {
+            Ok(()) => {
+                last_sent = Some(Instant::now());
+                changed = false;
+            }
+            Err(e) => {
+                info!("Lost the connection to Discord: {}", e);
+                client = None;
+            }
+        }
+    }
+
+    if let Some(mut ipc) = client {
+        let _ = ipc.clear_activity();
+        let _ = ipc.close();
+    }
+}
+
+/// Connects to Discord, if it's running, and asks to hear of friends joining.
+fn connect(application_id: &str) -> Option<DiscordIpcClient> {
+    let mut ipc = DiscordIpcClient::new(application_id);
+    if let Err(e) = ipc.connect() {
+        debug!("Discord not found: {}", e);
+        return None;
+    }
+    info!("Connected to Discord");
+    for evt in &["ACTIVITY_JOIN", "ACTIVITY_JOIN_REQUEST"] {
+        let subscribe = json!({ "cmd": "SUBSCRIBE", "evt": evt, "nonce": format!("subscribe-{}", evt) });
+        if let Err(e) = ipc.send(subscribe, OP_FRAME) {
+            info!("Could not ask Discord for {}: {}", evt, e);
+            return None;
+        }
+    }
+    Some(ipc)
+}
+
+/// Sets the player's activity to `presence`, then reads until Discord answers, passing on the
+/// events that came before the answer.
+fn send_presence(
+    ipc: &mut DiscordIpcClient,
+    presence: &Presence,
+    events: &mpsc::Sender<DiscordEvent>,
+) -> Result<(), String> {
+    ipc.set_activity(to_activity(presence)).map_err(|e| e.to_string())?;
+    loop {
+        let (op, message) = ipc.recv().map_err(|e| e.to_string())?;
+        if op == OP_CLOSE {
+            return Err(message["message"].as_str().unwrap_or("closed by Discord").to_owned());
+        }
+        match parse_reply(&message) {
+            Reply::Answer => return Ok(()),
+            Reply::Event(event) => {
+                let _ = events.send(event);
+            }
+            Reply::Other => {}
+        }
+    }
+}
+
+/// Lets a friend who asked to join into the player's room, or turns them away. Discord's answer is
+/// read, and ignored, along with the next presence's.
+fn send_answer(ipc: &mut DiscordIpcClient, user_id: &str, accept: bool) -> Result<(), String> {
+    let cmd = if accept {
+        "SEND_ACTIVITY_JOIN_INVITE"
+    } else {
+        "CLOSE_ACTIVITY_REQUEST"
+    };
+    let answer = json!({ "cmd": cmd, "args": { "user_id": user_id }, "nonce": format!("answer-{}", user_id) });
+    ipc.send(answer, OP_FRAME).map_err(|e| e.to_string())
+}
+
+fn to_activity(presence: &Presence) -> activity::Activity<'_> {
+    let mut activity = activity::Activity::new()
+        .details(presence.details.as_str())
+        .assets(activity::Assets::new().large_image(LARGE_IMAGE).large_text("Conwayste"));
+    if let Some(ref state) = presence.state {
+        activity = activity.state(state.as_str());
+    }
+    if let Some(started) = presence.started.and_then(|at| at.duration_since(UNIX_EPOCH).ok()) {
+        activity = activity.timestamps(activity::Timestamps::new().start(started.as_millis() as i64));
+    }
+    if let Some(ref party) = presence.party {
+        let size = [party.size.0 as i32, party.size.1 as i32];
+        activity = activity.party(activity::Party::new().id(party.id.as_str()).size(size));
+        if let Some(ref join) = party.join {
+            activity = activity.secrets(activity::Secrets::new().join(join.encode()));
+        }
+    }
+    activity
+}
+
+fn parse_reply(message: &Value) -> Reply {
+    match (message["cmd"].as_str(), message["evt"].as_str()) {
+        (Some("SET_ACTIVITY"), evt) => {
+            if evt == Some("ERROR") {
+                warn!("Discord did not take the presence: {}", message["data"]["message"]);
+            }
+            Reply::Answer
+        }
+        (Some("DISPATCH"), Some("ACTIVITY_JOIN")) => {
+            match message["data"]["secret"].as_str().and_then(JoinSecret::decode) {
+                Some(secret) => Reply::Event(DiscordEvent::Join(secret)),
+                None => Reply::Other,
+            }
+        }
+        (Some("DISPATCH"), Some("ACTIVITY_JOIN_REQUEST")) => {
+            let user = &message["data"]["user"];
+            match (user["id"].as_str(), user["username"].as_str()) {
+                (Some(id), Some(name)) => Reply::Event(DiscordEvent::JoinRequest(JoinRequest {
+                    user_id: id.to_owned(),
+                    name:    name.to_owned(),
+                })),
+                _ => Reply::Other,
+            }
+        }
+        _ => Reply::Other,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_join_secret_round_trip() {
+        let secret = JoinSecret {
+            server: "conwayste.example.com:2016".to_owned(),
+            room:   "glider gun club".to_owned(),
+        };
+        assert_eq!(JoinSecret::decode(&secret.encode()), Some(secret));
+        assert_eq!(JoinSecret::decode("no room"), None);
+        assert_eq!(JoinSecret::decode("localhost:2016/"), None);
+    }
+
+    #[test]
+    fn test_replies_from_discord() {
+        let answer = json!({ "cmd": "SET_ACTIVITY", "data": {}, "evt": null, "nonce": "1" });
+        assert_eq!(parse_reply(&answer), Reply::Answer);
+        let join = json!({ "cmd": "DISPATCH", "data": { "secret": "localhost:2016/general" }, "evt": "ACTIVITY_JOIN" });
+        assert_eq!(
+            parse_reply(&join),
+            Reply::Event(DiscordEvent::Join(JoinSecret {
+                server: "localhost:2016".to_owned(),
+                room:   "general".to_owned(),
+            }))
+        );
+        let request = json!({
+            "cmd": "DISPATCH",
+            "data": { "user": { "id": "53908232506183680", "username": "Mason" } },
+            "evt": "ACTIVITY_JOIN_REQUEST",
+        });
+        assert_eq!(
+            parse_reply(&request),
+            Reply::Event(DiscordEvent::JoinRequest(JoinRequest {
+                user_id: "53908232506183680".to_owned(),
+                name:    "Mason".to_owned(),
+            }))
+        );
+        let subscribed = json!({ "cmd": "SUBSCRIBE", "data": { "evt": "ACTIVITY_JOIN" }, "evt": null });
+        assert_eq!(parse_reply(&subscribed), Reply::Other);
+    }
+}
@@ -0,0 +1,217 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Optional eye candy for the board, each switched on in the video settings: a glow around the
+//! cells and CRT scanlines, made by drawing the board on a canvas and that through a shader, and
+//! trails where cells have recently died.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use conway::universe::{CellState, Universe};
+use gfx::{self, *};
+use ggez::graphics::{self, Canvas, Color, DrawParam, Shader};
+use ggez::mint::Vector2;
+use ggez::Context;
+
+const GLOW_STRENGTH: f32 = 1.2;
+const SCANLINE_DARKNESS: f32 = 0.35;
+const SCANLINE_PERIOD: f32 = 3.0; // pixels from one dark scanline to the next
+const TRAIL_GENS: u8 = 6; // a trail fades out over this many generations
+const TRAIL_ALPHA: f32 = 0.4; // of a trail left in the last generation
+
+gfx_defines! {
+    /// The uniforms of `resources/shaders/board_150.glslf`.
+    constant BoardEffects {
+        texel:     [f32; 2] = "u_Texel",
+        glow:      f32 = "u_Glow",
+        scanlines: f32 = "u_Scanlines",
+        lines:     f32 = "u_Lines",
+    }
+}
+
+/// The shader the board is drawn through when the glow or scanlines are on, and the canvas it's
+/// drawn on first.
+pub struct PostProcessing {
+    shader: Option<Shader<BoardEffects>>, // None if the graphics driver couldn't build it
+    canvas: Option<Canvas>,               // as big as the window; remade when it's resized
+}
+
+impl PostProcessing {
+    pub fn new(ctx: &mut Context) -> Self {
+        let consts = BoardEffects {
+            texel:     [0.0, 0.0],
+            glow:      0.0,
+            scanlines: 0.0,
+            lines:     0.0,
+        };
+        let shader = match Shader::new(
+            ctx,
+            "/shaders/board_150.glslv",
+            "/shaders/board_150.glslf",
+            consts,
+            "BoardEffects",
+            None,
+        ) {
+            Ok(shader) => Some(shader),
+            Err(e) => {
+                warn!("Board effects are unavailable: {}", e);
+                None
+            }
+        };
+        PostProcessing { shader, canvas: None }
+    }
+
+    /// Does the drawing of `draw`, through the glow and scanlines if either is on.
+    pub fn draw_through<F>(
+        &mut self,
+        ctx: &mut Context,
+        glow: bool,
+        scanlines: bool,
+        draw: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnOnce(&mut Context) -> Result<(), Box<dyn Error>>,
+    {
+        let shader = match self.shader {
+            Some(ref shader) if glow || scanlines => shader,
+            _ => return draw(ctx),
+        };
+
+        let (drawable_w, drawable_h) = graphics::drawable_size(ctx);
+        let stale = match self.canvas {
+            Some(ref canvas) => {
+                canvas.image().width() as f32 != drawable_w || canvas.image().height() as f32 != drawable_h
+            }
+            None => true,
+        };
+        if stale {
+            self.canvas = Some(Canvas::with_window_size(ctx)?);
+        }
+        let canvas = self.canvas.as_ref().unwrap(); // unwrap OK because it was made above if missing
+        graphics::set_canvas(ctx, Some(canvas));
+        graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+        let drawn = draw(ctx);
+        graphics::set_canvas(ctx, None);
+        drawn?;
+
+        let consts = BoardEffects {
+            texel:     [1.0 / drawable_w, 1.0 / drawable_h],
+            glow:      if glow { GLOW_STRENGTH } else { 0.0 },
+            scanlines: if scanlines { SCANLINE_DARKNESS } else { 0.0 },
+            lines:     drawable_h / SCANLINE_PERIOD,
+        };
+        let _lock = graphics::use_shader(ctx, shader);
+        shader.send(ctx, consts)?;
+        let screen = graphics::screen_coordinates(ctx);
+        let param = DrawParam::default().dest(screen.point()).scale(Vector2 {
+            x: screen.w / drawable_w,
+            y: screen.h / drawable_h,
+        });
+        graphics::draw(ctx, canvas, param)?;
+        Ok(())
+    }
+}
+
+/// The cells that died in the last few generations, anywhere in the universe, so that panning or
+/// zooming doesn't leave trails where cells merely went off screen.
+#[derive(Default)]
+pub struct Trails {
+    gen:    Option<usize>,                            // of the universe when last updated
+    alive:  HashMap<(usize, usize), CellState>,       // cells alive in that generation
+    fading: HashMap<(usize, usize), (CellState, u8)>, // (state when alive, generations left) of died cells
+}
+
+impl Trails {
+    pub fn new() -> Self {
+        Trails::default()
+    }
+
+    /// Notes which cells died since the last generation seen. A universe that went back a
+    /// generation, or skipped ahead past where trails fade, starts with none.
+    pub fn update(&mut self, universe: &Universe, visibility: Option<usize>) {
+        let gen = universe.latest_gen();
+        if self.gen == Some(gen) {
+            return;
+        }
+        let mut alive = HashMap::new();
+        universe.each_non_dead(universe.region(), visibility, &mut |col, row, state| {
+            if let CellState::Alive(_) = state {
+                alive.insert((col, row), state);
+            }
+        });
+
+        match self.gen {
+            Some(last) if gen > last && gen - last <= TRAIL_GENS as usize => {
+                let elapsed = (gen - last) as u8;
+                self.fading.retain(|cell, (_, left)| {
+                    *left = left.saturating_sub(elapsed);
+                    *left > 0 && !alive.contains_key(cell)
+                });
+                for (cell, state) in self.alive.drain() {
+                    if !alive.contains_key(&cell) {
+                        self.fading.insert(cell, (state, TRAIL_GENS));
+                    }
+                }
+            }
+            _ => self.fading.clear(),
+        }
+        self.alive = alive;
+        self.gen = Some(gen);
+    }
+
+    /// Calls `f` with the column, row, and state when alive of each cell with a trail, and the
+    /// alpha to draw the trail with.
+    pub fn each(&self, f: &mut dyn FnMut(usize, usize, CellState, f32)) {
+        for (&(col, row), &(state, left)) in self.fading.iter() {
+            f(col, row, state, TRAIL_ALPHA * left as f32 / TRAIL_GENS as f32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conway::universe::BigBang;
+
+    #[test]
+    fn test_trails_fade_where_cells_died() {
+        let mut uni = BigBang::new().width(64).height(64).birth().unwrap();
+        // a blinker, lying flat
+        for col in 10..13 {
+            uni.set_unchecked(col, 10, CellState::Alive(None));
+        }
+        let mut trails = Trails::new();
+        trails.update(&uni, None);
+        uni.next();
+        trails.update(&uni, None);
+
+        let mut cells = vec![];
+        trails.each(&mut |col, row, _, alpha| cells.push((col, row, alpha)));
+        cells.sort_by_key(|&(col, row, _)| (col, row));
+        assert_eq!(cells, vec![(10, 10, TRAIL_ALPHA), (12, 10, TRAIL_ALPHA)]);
+
+        // the ends come back to life a generation later, so their trails go
+        uni.next();
+        trails.update(&uni, None);
+        let mut cells = vec![];
+        trails.each(&mut |col, row, _, _| cells.push((col, row)));
+        cells.sort();
+        assert_eq!(cells, vec![(11, 9), (11, 11)]);
+    }
+}
@@ -0,0 +1,214 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! A camera that follows the action by itself, for watching a game or streaming it without
+//! touching the keys. It keeps the middle of the cells born in the last few generations, everyone's
+//! or one player's, in the middle of the screen, easing towards it rather than jumping.
+//!
+//! In a networked game the server only sends the universe around what is on screen, so activity
+//! far away goes unnoticed until the camera gets near it.
+
+use std::collections::{HashSet, VecDeque};
+
+use conway::universe::{CellState, Universe};
+
+use crate::viewport::GridView;
+
+const FOLLOW_GENS: usize = 16; // births in this many of the latest generations are followed
+const FOLLOW_SPEED: f32 = 2.0; // per second; the higher, the quicker the camera catches up
+
+/// Whose births the camera follows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Follow {
+    Off,
+    Everyone,
+    Player(usize), // by player ID
+}
+
+impl Follow {
+    /// What's followed after this when cycling with a key: everyone, then each of `players` in
+    /// turn, then nothing.
+    pub fn next(self, players: usize) -> Self {
+        match self {
+            Follow::Off => Follow::Everyone,
+            Follow::Everyone if players > 0 => Follow::Player(0),
+            Follow::Player(player_id) if player_id + 1 < players => Follow::Player(player_id + 1),
+            _ => Follow::Off,
+        }
+    }
+
+    fn includes(self, state: CellState) -> bool {
+        match (self, state) {
+            (Follow::Everyone, CellState::Alive(_)) => true,
+            (Follow::Player(player_id), CellState::Alive(Some(owner))) => owner == player_id,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FollowCamera {
+    follow: Follow,
+    gen:    Option<usize>,               // of the universe when last updated
+    alive:  HashSet<(usize, usize)>,     // followed cells alive in that generation
+    births: VecDeque<(f64, f64, usize)>, // (column sum, row sum, count) of the births in each recent generation
+}
+
+impl FollowCamera {
+    pub fn new() -> Self {
+        FollowCamera {
+            follow: Follow::Off,
+            gen:    None,
+            alive:  HashSet::new(),
+            births: VecDeque::new(),
+        }
+    }
+
+    pub fn follow(&self) -> Follow {
+        self.follow
+    }
+
+    /// Follows someone else's births, or stops following, forgetting the births seen so far.
+    pub fn set_follow(&mut self, follow: Follow) {
+        self.follow = follow;
+        self.gen = None;
+        self.alive.clear();
+        self.births.clear();
+    }
+
+    /// Notes the followed cells born since the last generation seen, as shown to `visibility`. A
+    /// universe that went back a generation, as it does when a new game starts, starts over.
+    pub fn update(&mut self, universe: &Universe, visibility: Option<usize>) {
+        let gen = universe.latest_gen();
+        if self.follow == Follow::Off || self.gen == Some(gen) {
+            return;
+        }
+        let follow = self.follow;
+        let mut alive = HashSet::new();
+        universe.each_non_dead_full(visibility, &mut |col, row, state| {
+            if follow.includes(state) {
+                alive.insert((col, row));
+            }
+        });
+
+        match self.gen {
+            Some(last) if gen > last => {
+                let (mut cols, mut rows, mut count) = (0.0, 0.0, 0);
+                for &(col, row) in alive.difference(&self.alive) {
+                    cols += col as f64;
+                    rows += row as f64;
+                    count += 1;
+                }
+                self.births.push_back((cols, rows, count));
+                while self.births.len() > FOLLOW_GENS {
+                    self.births.pop_front();
+                }
+            }
+            _ => self.births.clear(),
+        }
+        self.alive = alive;
+        self.gen = Some(gen);
+    }
+
+    /// The middle of the recent births, in cells, so that the middle of cell 3 is at 3.5. None when
+    /// nothing was born lately.
+    pub fn target(&self) -> Option<(f32, f32)> {
+        let (cols, rows, count) = self
+            .births
+            .iter()
+            .fold((0.0, 0.0, 0), |(cols, rows, count), &(c, r, n)| (cols + c, rows + r, count + n));
+        if count == 0 {
+            return None;
+        }
+        Some((
+            (cols / count as f64) as f32 + 0.5,
+            (rows / count as f64) as f32 + 0.5,
+        ))
+    }
+
+    /// Eases `viewport` towards the middle of the recent births over `seconds`, keeping its zoom.
+    pub fn pan(&self, viewport: &mut GridView, seconds: f32) {
+        let target = match self.target() {
+            Some(target) => target,
+            None => return,
+        };
+        let (col, row) = viewport.center();
+        let step = 1.0 - (-FOLLOW_SPEED * seconds).exp();
+        let center = (col + (target.0 - col) * step, row + (target.1 - row) * step);
+        viewport.look_at(center, viewport.get_cell_size());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conway::universe::{BigBang, PlayerBuilder, Region};
+
+    #[test]
+    fn test_follow_cycles_through_the_players() {
+        let mut follow = Follow::Off;
+        let mut seen = vec![];
+        for _ in 0..5 {
+            follow = follow.next(2);
+            seen.push(follow);
+        }
+        assert_eq!(
+            seen,
+            vec![
+                Follow::Everyone,
+                Follow::Player(0),
+                Follow::Player(1),
+                Follow::Off,
+                Follow::Everyone
+            ]
+        );
+        assert_eq!(Follow::Everyone.next(0), Follow::Off);
+    }
+
+    #[test]
+    fn test_target_is_the_middle_of_the_births() {
+        let players = vec![
+            PlayerBuilder::new(Region::new(0, 0, 32, 64)),
+            PlayerBuilder::new(Region::new(32, 0, 32, 64)),
+        ];
+        let mut uni = BigBang::new().width(64).height(64).add_players(players).birth().unwrap();
+        // a blinker of each player's, lying flat
+        for col in 10..13 {
+            uni.set(col, 10, CellState::Alive(Some(0)), 0);
+        }
+        for col in 50..53 {
+            uni.set(col, 40, CellState::Alive(Some(1)), 1);
+        }
+        let mut camera = FollowCamera::new();
+        camera.update(&uni, None);
+        assert_eq!(camera.target(), None);
+
+        camera.set_follow(Follow::Everyone);
+        camera.update(&uni, None);
+        uni.next();
+        camera.update(&uni, None);
+        // each blinker stood up, with a cell born above and below its middle
+        assert_eq!(camera.target(), Some((31.5, 25.5)));
+
+        camera.set_follow(Follow::Player(1));
+        camera.update(&uni, None);
+        uni.next();
+        camera.update(&uni, None);
+        assert_eq!(camera.target(), Some((51.5, 40.5)));
+    }
+}
@@ -0,0 +1,158 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Hints of what the keys do on the current screen, toggled with F1. They come from the key map,
+//! in panels next to the parts of the screen they're about: the keys for the board on the board,
+//! those for the chat beside it, and so on.
+
+use ggez::graphics::{self, DrawMode, DrawParam, Font, Rect};
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+
+use crate::constants::colors::scheme;
+use crate::i18n::tr;
+use crate::keymap::{self, Near, Scope};
+use crate::ui::{common::FontInfo, draw_text};
+
+const MARGIN: f32 = 10.0; // pixels between a panel and what it's next to, and around the text
+
+pub struct HelpOverlay {
+    pub visible: bool,
+}
+
+impl HelpOverlay {
+    pub fn new() -> Self {
+        HelpOverlay { visible: false }
+    }
+
+    /// Draws the hints for the keys of `scope` and those that work everywhere. `anchor` gives the
+    /// rectangle of what each panel is next to, or None if it isn't on screen, in which case the
+    /// panel goes in the top right corner.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        font: Font,
+        scope: Scope,
+        anchor: impl Fn(Near) -> Option<Rect>,
+    ) -> GameResult<()> {
+        let font_info = FontInfo::new(ctx, font, None);
+        let screen = graphics::screen_coordinates(ctx);
+        for &near in [Near::Screen, Near::Board, Near::Chat, Near::Focus].iter() {
+            let lines = hint_lines(scope, near);
+            if lines.is_empty() {
+                continue;
+            }
+            let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0); // fixed-width font
+            let size = (
+                longest as f32 * font_info.char_dimensions.x + 2.0 * MARGIN,
+                lines.len() as f32 * font_info.char_dimensions.y + 2.0 * MARGIN,
+            );
+            let anchor = if near == Near::Screen {
+                Some(screen)
+            } else {
+                anchor(near)
+            };
+            let panel = place(near, anchor, size, screen);
+
+            let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), panel, scheme().chat_pane_fill)?;
+            graphics::draw(ctx, &mesh, DrawParam::default())?;
+            let mut y = panel.y + MARGIN;
+            for line in lines {
+                draw_text(ctx, font, scheme().menu_text, line, &Point2 { x: panel.x + MARGIN, y })?;
+                y += font_info.char_dimensions.y;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The hints shown next to `near`, with the keys lined up in a column.
+fn hint_lines(scope: Scope, near: Near) -> Vec<String> {
+    let hints: Vec<(String, String)> = keymap::hinted(scope)
+        .filter(|binding| binding.near == near)
+        .map(|binding| (keymap::keys_text(binding), tr(binding.hint.unwrap_or_default())))
+        .collect();
+    let key_width = hints.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    hints
+        .into_iter()
+        .map(|(keys, what)| format!("{:width$}  {}", keys, what, width = key_width))
+        .collect()
+}
+
+/// Where a panel of `size` goes next to `anchor`, kept on `screen`. The keys that work everywhere
+/// go in the bottom right corner, those for the board in its top left corner, and the others to the
+/// right of what they're about, or to its left if there's no room.
+fn place(near: Near, anchor: Option<Rect>, size: (f32, f32), screen: Rect) -> Rect {
+    let (w, h) = size;
+    let (x, y) = match (near, anchor) {
+        (Near::Screen, _) => (screen.right() - w - MARGIN, screen.bottom() - h - MARGIN),
+        (_, None) => (screen.right() - w - MARGIN, screen.top() + MARGIN),
+        (Near::Board, Some(board)) => (board.left() + MARGIN, board.top() + MARGIN),
+        (_, Some(anchor)) => {
+            let x = if anchor.right() + MARGIN + w <= screen.right() {
+                anchor.right() + MARGIN
+            } else {
+                anchor.left() - MARGIN - w
+            };
+            (x, anchor.top())
+        }
+    };
+    Rect::new(
+        x.max(screen.left()).min(screen.right() - w),
+        y.max(screen.top()).min(screen.bottom() - h),
+        w,
+        h,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_place_beside_anchor_when_there_is_room() {
+        let screen = Rect::new(0.0, 0.0, 1000.0, 800.0);
+        let size = (200.0, 100.0);
+
+        let chat = Rect::new(10.0, 500.0, 300.0, 200.0);
+        assert_eq!(
+            place(Near::Chat, Some(chat), size, screen),
+            Rect::new(320.0, 500.0, 200.0, 100.0)
+        );
+
+        // no room on the right, so on the left
+        let button = Rect::new(700.0, 100.0, 250.0, 50.0);
+        assert_eq!(
+            place(Near::Focus, Some(button), size, screen),
+            Rect::new(490.0, 100.0, 200.0, 100.0)
+        );
+
+        // kept on screen
+        let low = Rect::new(10.0, 780.0, 100.0, 20.0);
+        assert_eq!(place(Near::Focus, Some(low), size, screen).bottom(), 800.0);
+
+        assert_eq!(
+            place(Near::Screen, Some(screen), size, screen),
+            Rect::new(790.0, 690.0, 200.0, 100.0)
+        );
+        assert_eq!(
+            place(Near::Focus, None, size, screen),
+            Rect::new(790.0, 10.0, 200.0, 100.0)
+        );
+    }
+}
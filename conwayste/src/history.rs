@@ -0,0 +1,287 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The record of the networked games played to the end, for the Stats screen. Each game is
+//! appended to a file in the history folder as one line of JSON, so a crash can at worst lose the
+//! line being written. Games left before they ended aren't recorded.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+use netwayste::net::{GameOutcome, PlayerScore};
+
+use crate::user_data::Folder;
+
+const FILE_NAME: &str = "matches.jsonl";
+pub const CHART_DAYS: usize = 14; // days of cells placed shown on the Stats screen
+
+/// A game played to the end.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MatchRecord {
+    pub finished:     String, // RFC 3339, local time
+    pub player:       String, // who we played as; each profile has its own stats
+    pub room:         String,
+    pub players:      Vec<String>,
+    pub duration:     u64, // seconds
+    pub generations:  u64,
+    pub winner:       Option<String>,   // None for a tie
+    pub won:          bool,             // whether the winner was us or our team
+    pub standings:    Vec<PlayerScore>, // final scores, best first
+    pub cells_placed: u64,
+}
+
+impl MatchRecord {
+    fn finished_on(&self) -> Option<NaiveDate> {
+        DateTime::parse_from_rfc3339(&self.finished)
+            .ok()
+            .map(|time| time.with_timezone(&Local).date().naive_local())
+    }
+}
+
+/// A game being played, which becomes a record when it ends.
+#[derive(Debug)]
+pub struct MatchInProgress {
+    room:         String,
+    players:      Vec<String>,
+    started:      Instant,
+    cells_placed: usize, // by the game area before this game began
+}
+
+impl MatchInProgress {
+    /// A game starting now in `room` between `players`. `cells_placed` is the game area's count of
+    /// cells placed so far, so that only those placed during this game are recorded.
+    pub fn new(room: String, players: Vec<String>, cells_placed: usize) -> Self {
+        MatchInProgress {
+            room,
+            players,
+            started: Instant::now(),
+            cells_placed,
+        }
+    }
+
+    /// When the game started.
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// The record of this game, which `player` finished with `outcome` after `generations`
+    /// generations, when the game area had placed `cells_placed` cells in all.
+    pub fn finish(
+        self,
+        player: &str,
+        outcome: &GameOutcome,
+        generations: usize,
+        cells_placed: usize,
+        now: DateTime<Local>,
+    ) -> MatchRecord {
+        let won = outcome.winning_players.iter().any(|name| name == player);
+        MatchRecord {
+            finished: now.to_rfc3339(),
+            player: player.to_owned(),
+            room: self.room,
+            players: self.players,
+            duration: self.started.elapsed().as_secs(),
+            generations: generations as u64,
+            winner: outcome.winner.clone(),
+            won,
+            standings: outcome.standings.clone(),
+            cells_placed: cells_placed.saturating_sub(self.cells_placed) as u64,
+        }
+    }
+}
+
+/// What the Stats screen shows for a player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub games:        usize,
+    pub wins:         usize,
+    pub cells_placed: u64,
+    pub cells_by_day: Vec<u64>, // cells placed on each of the last CHART_DAYS days, oldest first
+}
+
+impl Stats {
+    /// The fraction of games won, or None before any were played.
+    pub fn win_rate(&self) -> Option<f32> {
+        if self.games == 0 {
+            None
+        } else {
+            Some(self.wins as f32 / self.games as f32)
+        }
+    }
+}
+
+/// Every game recorded, loaded from the history folder.
+pub struct History {
+    path:    PathBuf,
+    records: Vec<MatchRecord>,
+}
+
+impl History {
+    /// Reads the games recorded so far. Lines that can't be read are skipped with a warning.
+    pub fn load() -> Self {
+        History::load_from(Folder::History.path().join(FILE_NAME))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let mut records = vec![];
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                    match serde_json::from_str(line) {
+                        Ok(record) => records.push(record),
+                        Err(e) => warn!("Skipping line {} of {:?}: {}", i + 1, path, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Could not read the match history in {:?}: {}", path, e),
+        }
+        History { path, records }
+    }
+
+    /// Adds a finished game, appending it to the file. Failures are logged rather than returned,
+    /// as the game is kept for this session either way.
+    pub fn add(&mut self, record: MatchRecord) {
+        if let Err(e) = append_to(&self.path, &record) {
+            warn!("Could not save the match to {:?}: {}", self.path, e);
+        }
+        self.records.push(record);
+    }
+
+    /// The totals for the games `player` played, with the cells they placed on each of the last
+    /// CHART_DAYS days up to `today`.
+    pub fn stats(&self, player: &str, today: NaiveDate) -> Stats {
+        let mut stats = Stats {
+            games:        0,
+            wins:         0,
+            cells_placed: 0,
+            cells_by_day: vec![0; CHART_DAYS],
+        };
+        let first_day = today - Duration::days(CHART_DAYS as i64 - 1);
+        for record in self.records.iter().filter(|record| record.player == player) {
+            stats.games += 1;
+            if record.won {
+                stats.wins += 1;
+            }
+            stats.cells_placed += record.cells_placed;
+            if let Some(day) = record.finished_on() {
+                if day >= first_day && day <= today {
+                    stats.cells_by_day[(day - first_day).num_days() as usize] += record.cells_placed;
+                }
+            }
+        }
+        stats
+    }
+}
+
+fn append_to(path: &Path, record: &MatchRecord) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writeln!(file, "{}", line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn outcome(winner: Option<&str>, winning_players: &[&str]) -> GameOutcome {
+        GameOutcome {
+            winner:          winner.map(|name| name.to_owned()),
+            winning_players: winning_players.iter().map(|name| name.to_string()).collect(),
+            standings:       vec![
+                PlayerScore {
+                    name:  "Team 2".to_owned(),
+                    score: 40,
+                },
+                PlayerScore {
+                    name:  "Team 1".to_owned(),
+                    score: 25,
+                },
+            ],
+        }
+    }
+
+    fn record(player: &str, winner: Option<&str>, cells_placed: usize, day: u32) -> MatchRecord {
+        record_with_winners(
+            player,
+            winner,
+            &winner.into_iter().collect::<Vec<_>>(),
+            cells_placed,
+            day,
+        )
+    }
+
+    fn record_with_winners(
+        player: &str,
+        winner: Option<&str>,
+        winning_players: &[&str],
+        cells_placed: usize,
+        day: u32,
+    ) -> MatchRecord {
+        let game = MatchInProgress::new("general".to_owned(), vec!["alice".to_owned(), "bob".to_owned()], 5);
+        let finished = Local.ymd(2021, 6, day).and_hms(20, 0, 0);
+        game.finish(
+            player,
+            &outcome(winner, winning_players),
+            300,
+            5 + cells_placed,
+            finished,
+        )
+    }
+
+    #[test]
+    fn test_finish_counts_a_team_win_as_ours() {
+        assert!(record_with_winners("alice", Some("Team 2"), &["carol", "alice"], 0, 1).won);
+        assert!(!record_with_winners("alice", Some("Team 2"), &["bob"], 0, 1).won);
+        assert!(record("alice", Some("alice"), 0, 1).won);
+        assert!(!record("alice", None, 0, 1).won);
+        assert_eq!(record("alice", None, 12, 1).cells_placed, 12);
+    }
+
+    #[test]
+    fn test_stats_survive_a_reload() {
+        let dir = std::env::temp_dir().join(format!("conwayste-history-test-{}", std::process::id()));
+        let path = dir.join(FILE_NAME);
+        let mut history = History::load_from(path.clone());
+        history.add(record("alice", Some("alice"), 10, 1));
+        history.add(record("alice", Some("bob"), 4, 14));
+        history.add(record("alice", None, 6, 14));
+        history.add(record("bob", Some("bob"), 100, 14));
+
+        let history = History::load_from(path);
+        let today = NaiveDate::from_ymd(2021, 6, 14);
+        let stats = history.stats("alice", today);
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.wins, 1);
+        assert_eq!(stats.win_rate(), Some(1.0 / 3.0));
+        assert_eq!(stats.cells_placed, 20);
+        assert_eq!(stats.cells_by_day[0], 10);
+        assert_eq!(stats.cells_by_day[CHART_DAYS - 1], 10);
+
+        assert_eq!(history.stats("carol", today).win_rate(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
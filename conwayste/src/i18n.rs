@@ -0,0 +1,180 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Translations of the text shown to the player.
+//!
+//! Each language has a file in `locales/` mapping message IDs to text, which is built into the
+//! client. Look up text with `tr`, or `tr_args` if it has `{placeholders}`. A message missing
+//! from the current language is shown in English instead.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The bundled languages, as (language code, name of the language in that language, messages).
+const LOCALES: &[(&str, &str, &str)] = &[
+    ("en", "English", include_str!("../locales/en.toml")),
+    ("es", "Español", include_str!("../locales/es.toml")),
+];
+
+struct Catalog {
+    messages: HashMap<String, String>,
+    fallback: HashMap<String, String>, // English
+}
+
+lazy_static! {
+    static ref CATALOG: RwLock<Catalog> = {
+        let fallback = parse_locale(DEFAULT_LANGUAGE);
+        RwLock::new(Catalog {
+            messages: fallback.clone(),
+            fallback,
+        })
+    };
+}
+
+fn find_locale(code: &str) -> Option<&'static (&'static str, &'static str, &'static str)> {
+    LOCALES.iter().find(|(locale_code, _, _)| *locale_code == code)
+}
+
+fn parse_locale(code: &str) -> HashMap<String, String> {
+    let (_, _, source) = find_locale(code).unwrap(); // unwrap OK because only called with known codes
+    toml::from_str(source).unwrap_or_else(|e| {
+        error!("Could not parse messages for language {:?}: {}", code, e);
+        HashMap::new()
+    })
+}
+
+/// The codes of the bundled languages, in the order they are offered to the player.
+pub fn languages() -> impl Iterator<Item = &'static str> {
+    LOCALES.iter().map(|(code, _, _)| *code)
+}
+
+/// The name of the language with this code, written in that language.
+pub fn language_name(code: &str) -> Option<&'static str> {
+    find_locale(code).map(|(_, name, _)| *name)
+}
+
+/// The language after `code` in the list of bundled languages, wrapping around to the first.
+pub fn next_language(code: &str) -> &'static str {
+    let i = LOCALES
+        .iter()
+        .position(|(locale_code, _, _)| *locale_code == code)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    LOCALES[i % LOCALES.len()].0
+}
+
+/// Switches the language of the text returned by `tr` and `tr_args`. Returns false, leaving the
+/// language unchanged, if there is no language with this code. Widgets that are already showing
+/// text are not affected; the client rebuilds the menus for that.
+pub fn set_language(code: &str) -> bool {
+    let language = match find_locale(code) {
+        Some((language, _, _)) => *language,
+        None => return false,
+    };
+    let messages = parse_locale(language);
+    CATALOG.write().unwrap().messages = messages;
+    true
+}
+
+/// Looks up the text of a message in the current language. If there is no such message, the ID
+/// itself is returned so the mistake is visible on screen.
+pub fn tr(id: &str) -> String {
+    let catalog = CATALOG.read().unwrap();
+    match catalog.messages.get(id).or_else(|| catalog.fallback.get(id)) {
+        Some(text) => text.clone(),
+        None => {
+            warn!("No text for message {:?}", id);
+            id.to_owned()
+        }
+    }
+}
+
+/// Like `tr`, but each `{name}` in the text is replaced by the value given for `name` in `args`.
+pub fn tr_args(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut text = tr(id);
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn message_ids(code: &str) -> HashSet<String> {
+        parse_locale(code).into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn test_all_languages_have_the_english_messages() {
+        let english = message_ids(DEFAULT_LANGUAGE);
+        assert!(!english.is_empty());
+        for code in languages() {
+            let ids = message_ids(code);
+            assert_eq!(
+                ids, english,
+                "messages differ between {} and {}",
+                code, DEFAULT_LANGUAGE
+            );
+        }
+    }
+
+    #[test]
+    fn test_placeholders_are_kept_in_translations() {
+        let english = parse_locale(DEFAULT_LANGUAGE);
+        let placeholders = |text: &str| -> Vec<String> {
+            text.split('{')
+                .skip(1)
+                .filter_map(|part| part.split('}').next().map(|name| name.to_owned()))
+                .collect()
+        };
+        for code in languages() {
+            for (id, text) in parse_locale(code) {
+                assert_eq!(
+                    placeholders(&text),
+                    placeholders(&english[&id]),
+                    "in {} for {}",
+                    code,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tr_args_fills_in_placeholders() {
+        assert_eq!(
+            tr_args("results-series-progress", &[("round", &2), ("rounds", &3)]),
+            "Series after round 2 of 3"
+        );
+        assert_eq!(tr("no-such-message"), "no-such-message");
+    }
+
+    #[test]
+    fn test_next_language_wraps_around() {
+        assert_eq!(next_language("en"), "es");
+        assert_eq!(next_language("es"), "en");
+        assert_eq!(next_language("xx"), "en");
+        assert_eq!(language_name("es"), Some("Español"));
+    }
+}
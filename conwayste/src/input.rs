@@ -16,29 +16,23 @@
  *  along with conwayste.  If not, see
  *  <http://www.gnu.org/licenses/>. */
 
+use crate::config::InputSettings;
 use conway::universe::CellState;
 use ggez::event::{KeyCode, KeyMods, MouseButton};
-use ggez::mint::Point2;
-use std::time::Instant;
-
-#[derive(PartialEq, Clone, Copy, Debug)]
-pub enum ScrollEvent {
-    ScrollUp,   // Away from the user
-    ScrollDown, // Towards the user
-}
+use ggez::mint::{Point2, Vector2};
+use std::time::{Duration, Instant};
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum MouseAction {
     Held,
     Drag,
     Click,
-    DoubleClick, // For Aaron TODO :)
 }
 
 pub struct MouseInfo {
     pub mousebutton:    MouseButton,
     pub action:         Option<MouseAction>,
-    pub scroll_event:   Option<ScrollEvent>,
+    pub scroll_delta:   Vector2<f32>, // Mouse wheel movement since the last update(); y is away from the user
     pub down_timestamp: Option<Instant>,
     pub down_position:  Point2<f32>,
     pub prev_position:  Point2<f32>, // Position change since the last update()
@@ -51,7 +45,7 @@ impl MouseInfo {
         MouseInfo {
             mousebutton:    MouseButton::Other(0),
             action:         None,
-            scroll_event:   None,
+            scroll_delta:   Vector2 { x: 0.0, y: 0.0 },
             down_timestamp: None,
             down_position:  Point2 { x: 0.0, y: 0.0 },
             prev_position:  Point2 { x: 0.0, y: 0.0 },
@@ -65,7 +59,7 @@ impl MouseInfo {
         if self.debug_print {
             debug!("Button: {:?}", self.mousebutton);
             debug!("Action: {:?}", self.action);
-            debug!("Scroll: {:?}", self.scroll_event);
+            debug!("Scroll: {:?}", self.scroll_delta);
             debug!("Down TS: {:?}", self.down_timestamp);
             debug!("Down Pos: {:?}", self.down_position);
             debug!("Position: {:?}", self.position);
@@ -73,11 +67,30 @@ impl MouseInfo {
     }
 }
 
+/// The key being held down, which repeats once it has been held long enough.
+struct HeldKey {
+    key:      KeyCode,
+    held_for: Duration,
+    repeats:  u64, // sent so far
+}
+
 pub struct KeyInfo {
     pub key:         Option<KeyCode>,
     pub repeating:   bool,
     pub modifier:    KeyMods,
     pub debug_print: bool,
+    held:            Option<HeldKey>,
+}
+
+/// The modifier that `keycode` is one of the keys for, if it is a modifier key.
+pub fn modifier_of_key(keycode: KeyCode) -> Option<KeyMods> {
+    match keycode {
+        KeyCode::LShift | KeyCode::RShift => Some(KeyMods::SHIFT),
+        KeyCode::LControl | KeyCode::RControl => Some(KeyMods::CTRL),
+        KeyCode::LAlt | KeyCode::RAlt => Some(KeyMods::ALT),
+        KeyCode::LWin | KeyCode::RWin => Some(KeyMods::LOGO),
+        _ => None,
+    }
 }
 
 impl KeyInfo {
@@ -87,6 +100,55 @@ impl KeyInfo {
             repeating:   false,
             modifier:    KeyMods::NONE,
             debug_print: false,
+            held:        None,
+        }
+    }
+
+    /// A key was pressed down. It is sent on the next update, and then repeated while held by
+    /// `advance_repeat`; the operating system's own repeats are ignored, so that the rate is the
+    /// same everywhere.
+    pub fn press(&mut self, keycode: KeyCode) {
+        if self.key.is_none() {
+            self.key = Some(keycode);
+            self.repeating = false;
+        }
+        self.held = Some(HeldKey {
+            key:      keycode,
+            held_for: Duration::from_secs(0),
+            repeats:  0,
+        });
+    }
+
+    /// A key was let go of.
+    pub fn release(&mut self, keycode: KeyCode) {
+        if self.held_key() == Some(keycode) {
+            self.held = None;
+        }
+    }
+
+    pub fn held_key(&self) -> Option<KeyCode> {
+        self.held.as_ref().map(|held| held.key)
+    }
+
+    /// Counts the held key as held for another `elapsed`, sending it again (as `key`, with
+    /// `repeating` set) once it has been held for the repeat delay, and every repeat interval after
+    /// that. At most one repeat is sent per update, however long it took.
+    pub fn advance_repeat(&mut self, elapsed: Duration, settings: &InputSettings) {
+        let held = match self.held {
+            Some(ref mut held) => held,
+            None => return,
+        };
+        held.held_for += elapsed;
+        let delay = Duration::from_millis(settings.key_repeat_delay_ms);
+        if held.held_for < delay {
+            return;
+        }
+        let interval = settings.key_repeat_interval_ms.max(1) as u128;
+        let due = 1 + ((held.held_for - delay).as_millis() / interval) as u64;
+        if due > held.repeats && self.key.is_none() {
+            held.repeats = due;
+            self.key = Some(held.key);
+            self.repeating = true;
         }
     }
 
@@ -118,3 +180,35 @@ impl InputManager {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_held_key_repeats_after_the_delay() {
+        let settings = InputSettings::default();
+        let frame = Duration::from_millis(10);
+        let mut key_info = KeyInfo::new();
+        let mut presses = vec![];
+
+        key_info.press(KeyCode::Back);
+        for _ in 0..60 {
+            key_info.advance_repeat(frame, &settings);
+            if let Some(key) = key_info.key.take() {
+                presses.push(key_info.repeating);
+                assert_eq!(key, KeyCode::Back);
+            }
+        }
+        // the press itself, then a repeat at the delay and one each interval for the rest of the
+        // 600 ms
+        let repeats = 1 + (600 - settings.key_repeat_delay_ms) / settings.key_repeat_interval_ms;
+        assert_eq!(presses.len() as u64, 1 + repeats);
+        assert!(!presses[0]);
+        assert!(presses[1..].iter().all(|&repeating| repeating));
+
+        key_info.release(KeyCode::Back);
+        key_info.advance_repeat(Duration::from_secs(1), &settings);
+        assert_eq!(key_info.key, None);
+    }
+}
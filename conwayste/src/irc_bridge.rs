@@ -0,0 +1,422 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! A bridge to a channel on an IRC server, such as a Twitch channel's chat, for streamers. What is
+//! said in the channel shows up in a tab of its own in the in-game chat, and what is typed in that
+//! tab, and if the settings say so what the player says to the room, goes to the channel.
+//!
+//! The connection is looked after by a tokio task, on a thread of its own so that the game loop
+//! never waits on it, and is made again a little while after it drops. It's encrypted with TLS,
+//! which Twitch takes on port 6697, unless the settings turn that off.
+
+use std::io;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep_until, Instant};
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::webpki::DNSNameRef;
+use tokio_rustls::TlsConnector;
+
+use crate::config::ChatBridgeSettings;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(15);
+const MAX_MESSAGE_LEN: usize = 400; // bytes of text in a message sent; IRC allows 512 for the whole line
+const MAX_LINE_LEN: usize = 8191 + 512; // bytes in a line received; IRCv3 allows 8191 of message tags
+
+/// What happened on the channel, for the in-game chat.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeEvent {
+    Joined,
+    Message(String, String), // (nick, text)
+    Notice(String),          // from the server, such as why logging in failed
+    Disconnected(String),    // why; it connects again after a while
+}
+
+/// A line from the server that the bridge acts on.
+#[derive(Debug, PartialEq)]
+enum Line {
+    Ping(String),
+    Welcome,                         // logged in, so the channel can be joined
+    Joined,                          // the end of the list of who is in the channel
+    Message(String, String, String), // (nick, channel, text)
+    Notice(String),
+    Error(String), // the connection is over
+}
+
+/// The connection to the channel, for as long as this is kept.
+pub struct ChatBridge {
+    channel:  String,
+    can_say:  bool,
+    outgoing: UnboundedSender<String>, // text for the channel; dropped to disconnect
+    events:   mpsc::Receiver<BridgeEvent>,
+}
+
+impl ChatBridge {
+    /// Starts connecting to the channel in `settings`. Fails with a message for the player if the
+    /// settings lack a server or channel.
+    pub fn start(settings: &ChatBridgeSettings) -> Result<Self, String> {
+        let server = settings.server.trim().to_owned();
+        if server.is_empty() {
+            return Err("no server is set".to_owned());
+        }
+        let channel = match settings.channel.trim() {
+            "" => return Err("no channel is set".to_owned()),
+            channel if channel.starts_with('#') => channel.to_lowercase(),
+            channel => format!("#{}", channel.to_lowercase()),
+        };
+        let can_say = !settings.nick.trim().is_empty();
+        let login = login_lines(settings.nick.trim(), settings.token.trim());
+        let tls = settings.tls;
+
+        let (outgoing, outgoing_rx) = unbounded_channel();
+        let (events_tx, events) = mpsc::channel();
+        let thread_channel = channel.clone();
+        thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = events_tx.send(BridgeEvent::Disconnected(e.to_string()));
+                    return;
+                }
+            };
+            runtime.block_on(run(server, tls, thread_channel, login, outgoing_rx, events_tx));
+        });
+        Ok(ChatBridge {
+            channel,
+            can_say,
+            outgoing,
+            events,
+        })
+    }
+
+    /// The channel joined, like "#conwayste".
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Whether messages can be sent to the channel, which takes logging in with a nick.
+    pub fn can_say(&self) -> bool {
+        self.can_say
+    }
+
+    /// Sends `text` to the channel, if it can be. Messages sent while disconnected are lost.
+    pub fn say(&self, text: &str) {
+        if self.can_say {
+            let _ = self.outgoing.send(text.to_owned()); // the task only stops once this is dropped
+        }
+    }
+
+    /// What happened since the last call. Never blocks.
+    pub fn poll(&self) -> Vec<BridgeEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+/// Keeps connecting to `server`, with TLS if `tls`, and joining `channel` until the bridge is
+/// dropped.
+async fn run(
+    server: String,
+    tls: bool,
+    channel: String,
+    login: Vec<String>,
+    mut outgoing: UnboundedReceiver<String>,
+    events: mpsc::Sender<BridgeEvent>,
+) {
+    loop {
+        let reason = match connect(&server, tls, &channel, &login, &mut outgoing, &events).await {
+            Ok(()) => return, // the bridge was dropped
+            Err(reason) => reason,
+        };
+        info!("Chat bridge to {} on {} disconnected: {}", channel, server, reason);
+        let _ = events.send(BridgeEvent::Disconnected(reason));
+        let reconnect_at = Instant::now() + RECONNECT_DELAY;
+        loop {
+            tokio::select! {
+                _ = sleep_until(reconnect_at) => break,
+                text = outgoing.recv() => if text.is_none() {
+                    return;
+                },
+            }
+        }
+    }
+}
+
+/// Connects to `server`, with TLS if `tls`, for one session.
+async fn connect(
+    server: &str,
+    tls: bool,
+    channel: &str,
+    login: &[String],
+    outgoing: &mut UnboundedReceiver<String>,
+    events: &mpsc::Sender<BridgeEvent>,
+) -> Result<(), String> {
+    let stream = TcpStream::connect(server).await.map_err(|e| e.to_string())?;
+    if !tls {
+        return session(stream, channel, login, outgoing, events).await;
+    }
+    let host = server.rsplit_once(':').map_or(server, |(host, _)| host);
+    let domain = DNSNameRef::try_from_ascii_str(host).map_err(|_| format!("{:?} is not a host name", host))?;
+    let mut config = ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(domain, stream)
+        .await
+        .map_err(|e| e.to_string())?;
+    session(stream, channel, login, outgoing, events).await
+}
+
+/// One connection to the server, which ends with why it was lost, or Ok once the bridge is dropped.
+async fn session<S: AsyncRead + AsyncWrite>(
+    stream: S,
+    channel: &str,
+    login: &[String],
+    outgoing: &mut UnboundedReceiver<String>,
+    events: &mpsc::Sender<BridgeEvent>,
+) -> Result<(), String> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut buf = vec![];
+    for line in login {
+        writer.write_all(line.as_bytes()).await.map_err(|e| e.to_string())?;
+    }
+
+    loop {
+        tokio::select! {
+            line = next_line(&mut reader, &mut buf) => {
+                let line = match line.map_err(|e| e.to_string())? {
+                    Some(line) => line,
+                    None => return Err("the server closed the connection".to_owned()),
+                };
+                let reply = match parse_line(&line) {
+                    Some(Line::Ping(token)) => Some(format!("PONG :{}\r\n", token)),
+                    Some(Line::Welcome) => Some(format!("JOIN {}\r\n", channel)),
+                    Some(Line::Joined) => {
+                        let _ = events.send(BridgeEvent::Joined);
+                        None
+                    }
+                    Some(Line::Message(nick, to, text)) => {
+                        if to.eq_ignore_ascii_case(channel) {
+                            let _ = events.send(BridgeEvent::Message(nick, text));
+                        }
+                        None
+                    }
+                    Some(Line::Notice(text)) => {
+                        let _ = events.send(BridgeEvent::Notice(text));
+                        None
+                    }
+                    Some(Line::Error(reason)) => return Err(reason),
+                    None => None,
+                };
+                if let Some(reply) = reply {
+                    writer.write_all(reply.as_bytes()).await.map_err(|e| e.to_string())?;
+                }
+            }
+            text = outgoing.recv() => {
+                let line = match text {
+                    Some(text) => privmsg(channel, &text),
+                    None => {
+                        let _ = writer.write_all(b"QUIT\r\n").await; // leaving anyway
+                        return Ok(());
+                    }
+                };
+                writer.write_all(line.as_bytes()).await.map_err(|e| e.to_string())?;
+            }
+        }
+    }
+}
+
+/// Reads the next line from `reader`, failing rather than holding on to a line longer than
+/// `MAX_LINE_LEN`. What has been read of a line is kept in `buf`, so that a read can be given up on
+/// and started again.
+async fn next_line<R: AsyncBufRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+    let limit = (MAX_LINE_LEN + 1).saturating_sub(buf.len()) as u64;
+    if (&mut *reader).take(limit).read_until(b'\n', buf).await? == 0 && buf.is_empty() {
+        return Ok(None);
+    }
+    if buf.last() != Some(&b'\n') && buf.len() > MAX_LINE_LEN {
+        buf.clear();
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the server sent too long a line"));
+    }
+    let line = String::from_utf8_lossy(buf).into_owned();
+    buf.clear();
+    Ok(Some(line))
+}
+
+/// The lines that log in as `nick` with the server password `token`, either of which may be empty.
+/// Without a nick, a made-up one is used, in the form Twitch takes for reading a channel anonymously.
+fn login_lines(nick: &str, token: &str) -> Vec<String> {
+    let nick = if nick.is_empty() {
+        format!("justinfan{}", rand::thread_rng().gen_range(10000..100000))
+    } else {
+        nick.to_owned()
+    };
+    let mut lines = vec![];
+    if !token.is_empty() {
+        lines.push(format!("PASS {}\r\n", token));
+    }
+    lines.push(format!("NICK {}\r\n", nick));
+    lines.push(format!("USER {} 0 * :{}\r\n", nick, nick));
+    lines
+}
+
+/// The line that says `text` in `channel`, kept to one line of at most `MAX_MESSAGE_LEN` bytes.
+fn privmsg(channel: &str, text: &str) -> String {
+    let mut text: String = text.chars().map(|ch| if ch == '\r' || ch == '\n' { ' ' } else { ch }).collect();
+    if text.len() > MAX_MESSAGE_LEN {
+        let mut end = MAX_MESSAGE_LEN;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    format!("PRIVMSG {} :{}\r\n", channel, text)
+}
+
+/// Reads a line from the server, leaving out what the bridge has no use for. Twitch's message tags
+/// are skipped, and of the CTCP messages only actions, as sent with "/me", are kept.
+fn parse_line(line: &str) -> Option<Line> {
+    let mut rest = line.trim_end_matches(&['\r', '\n'][..]);
+    if rest.starts_with('@') {
+        rest = rest.split_once(' ')?.1;
+    }
+    let mut prefix = None;
+    if let Some(prefixed) = rest.strip_prefix(':') {
+        let mut parts = prefixed.splitn(2, ' ');
+        prefix = parts.next();
+        rest = parts.next()?;
+    }
+    // The last parameter, after a colon, may have spaces in it
+    let (params, trailing) = match rest.find(" :") {
+        Some(i) => (&rest[..i], Some(&rest[i + 2..])),
+        None => (rest, None),
+    };
+    let mut words = params.split(' ').filter(|word| !word.is_empty());
+    let command = words.next()?.to_ascii_uppercase();
+    let middle: Vec<&str> = words.collect();
+    let last = trailing.or_else(|| middle.last().copied()).unwrap_or("").to_owned();
+
+    match command.as_str() {
+        "PING" => Some(Line::Ping(last)),
+        "001" => Some(Line::Welcome),
+        "366" => Some(Line::Joined),
+        "433" => Some(Line::Error("that nick is taken".to_owned())),
+        "ERROR" => Some(Line::Error(last)),
+        "NOTICE" => Some(Line::Notice(last)),
+        "PRIVMSG" => {
+            let nick = prefix?.split('!').next()?.to_owned();
+            let channel = middle.first()?.to_string();
+            let text = match last.strip_prefix('\u{1}') {
+                Some(ctcp) => format!("*{}*", ctcp.strip_prefix("ACTION ")?.trim_end_matches('\u{1}')),
+                None => last,
+            };
+            Some(Line::Message(nick, channel, text))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lines_from_the_server() {
+        assert_eq!(
+            parse_line("PING :tmi.twitch.tv\r\n"),
+            Some(Line::Ping("tmi.twitch.tv".to_owned()))
+        );
+        assert_eq!(
+            parse_line(":tmi.twitch.tv 001 justinfan12345 :Welcome, GLHF!"),
+            Some(Line::Welcome)
+        );
+        assert_eq!(
+            parse_line(":alice!alice@alice.tmi.twitch.tv PRIVMSG #conwayste :nice glider gun"),
+            Some(Line::Message(
+                "alice".to_owned(),
+                "#conwayste".to_owned(),
+                "nice glider gun".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_line("@badge-info=;color=#FF0000 :bob!bob@bob.tmi.twitch.tv PRIVMSG #conwayste :hi"),
+            Some(Line::Message("bob".to_owned(), "#conwayste".to_owned(), "hi".to_owned()))
+        );
+        assert_eq!(
+            parse_line(":bob!bob@host PRIVMSG #conwayste :\u{1}ACTION waves\u{1}"),
+            Some(Line::Message("bob".to_owned(), "#conwayste".to_owned(), "*waves*".to_owned()))
+        );
+        assert_eq!(parse_line(":bob!bob@host PRIVMSG #conwayste :\u{1}VERSION\u{1}"), None);
+        assert_eq!(
+            parse_line(":tmi.twitch.tv NOTICE * :Login authentication failed"),
+            Some(Line::Notice("Login authentication failed".to_owned()))
+        );
+        assert_eq!(
+            parse_line("ERROR :Closing link"),
+            Some(Line::Error("Closing link".to_owned()))
+        );
+        assert_eq!(parse_line(":alice!alice@host JOIN #conwayste"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn test_lines_to_the_server() {
+        assert_eq!(
+            login_lines("streamer", "oauth:secret"),
+            vec![
+                "PASS oauth:secret\r\n".to_owned(),
+                "NICK streamer\r\n".to_owned(),
+                "USER streamer 0 * :streamer\r\n".to_owned(),
+            ]
+        );
+        let anonymous = login_lines("", "");
+        assert_eq!(anonymous.len(), 2);
+        assert!(anonymous[0].starts_with("NICK justinfan"));
+
+        assert_eq!(privmsg("#conwayste", "two\nlines"), "PRIVMSG #conwayste :two lines\r\n");
+        let long = privmsg("#c", &"é".repeat(MAX_MESSAGE_LEN));
+        assert_eq!(long.len(), "PRIVMSG #c :\r\n".len() + MAX_MESSAGE_LEN);
+    }
+
+    #[test]
+    fn test_reading_lines_is_capped() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let mut buf = vec![];
+
+        let mut reader = BufReader::new(&b"PING :a\r\nPING :b"[..]);
+        let lines: Vec<_> = (0..3)
+            .map(|_| runtime.block_on(next_line(&mut reader, &mut buf)).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![Some("PING :a\r\n".to_owned()), Some("PING :b".to_owned()), None]
+        );
+
+        let flood = format!("PING :{}\r\n", "a".repeat(MAX_LINE_LEN));
+        let mut reader = BufReader::new(flood.as_bytes());
+        assert!(runtime.block_on(next_line(&mut reader, &mut buf)).is_err());
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,701 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Which keys do what. The game area and the client look keys up here rather than matching on
+//! them, and the hints shown with F1 are made from the same bindings, so rebinding a key changes
+//! both. `BINDINGS` has the usual keys; the `[keys]` section of the config can bind others in
+//! their place for the actions listed in `REBINDABLE`. The keys for getting around the menus are
+//! handled in the `ui` module's layering; they are listed here for the hints.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use ggez::input::keyboard::{KeyCode, KeyMods};
+
+use crate::config::KeySettings;
+
+/// Where a key does something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Everywhere,
+    Game,
+    Menus,
+}
+
+/// What a key's hint is shown next to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Near {
+    Screen, // the bottom right corner
+    Board,
+    Chat,
+    Focus, // the focused widget, or the top right corner if none is
+}
+
+/// What a key does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Help,
+    DebugOverlay,
+    Console,
+    ClearPattern,
+    PickPattern,
+    RotatePattern,
+    CycleSymmetry,
+    MoveSymmetryCenter,
+    CycleAnnotationTool,
+    ShareAnnotations,
+    ClearAnnotations,
+    SaveBookmark,
+    JumpToBookmark,
+    FollowActivity,
+    FocusChat,
+    CollapseChat,
+    ToggleHud,
+    ToggleBot,
+    RunPause,
+    SingleStep,
+    Pan,
+    ZoomIn,
+    ZoomOut,
+    EraseSelection,
+    Back,
+    DumpPattern,
+    NextWidget,
+    PreviousWidget,
+    NextTab,
+    Press,
+}
+
+/// Keys that do the same thing, held with `mods`.
+#[derive(Debug)]
+pub struct Binding {
+    pub action: KeyAction,
+    pub scope:  Scope,
+    pub keys:   &'static [KeyCode],
+    pub mods:   KeyMods,
+    pub hint:   Option<&'static str>, // message ID saying what the keys do; None to leave it out of the hints
+    pub near:   Near,
+}
+
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        action: KeyAction::Help,
+        scope:  Scope::Everywhere,
+        keys:   &[KeyCode::F1],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-help"),
+        near:   Near::Screen,
+    },
+    Binding {
+        action: KeyAction::DebugOverlay,
+        scope:  Scope::Everywhere,
+        keys:   &[KeyCode::F3],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-debug-overlay"),
+        near:   Near::Screen,
+    },
+    Binding {
+        action: KeyAction::Console,
+        scope:  Scope::Everywhere,
+        keys:   &[KeyCode::Grave],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-console"),
+        near:   Near::Screen,
+    },
+    Binding {
+        action: KeyAction::ClearPattern,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Key1],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-clear-pattern"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::PickPattern,
+        scope:  Scope::Game,
+        keys:   &[
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+            KeyCode::Key0,
+        ],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-pick-pattern"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::RotatePattern,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Left, KeyCode::Right],
+        mods:   KeyMods::SHIFT,
+        hint:   Some("keys-rotate-pattern"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::CycleSymmetry,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::M],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-cycle-symmetry"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::MoveSymmetryCenter,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::M],
+        mods:   KeyMods::SHIFT,
+        hint:   Some("keys-move-symmetry-center"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::CycleAnnotationTool,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::N],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-cycle-annotation-tool"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::ShareAnnotations,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::N],
+        mods:   KeyMods::SHIFT,
+        hint:   Some("keys-share-annotations"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::ClearAnnotations,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::N],
+        mods:   KeyMods::CTRL,
+        hint:   Some("keys-clear-annotations"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::Pan,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-pan"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::ZoomIn,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Plus, KeyCode::Equals],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-zoom-in"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::ZoomOut,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Minus],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-zoom-out"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::RunPause,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::R],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-run-pause"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::SingleStep,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Space],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-single-step"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::EraseSelection,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Delete, KeyCode::Back],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-erase-selection"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::Back,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Escape],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-game-back"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::DumpPattern,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::D],
+        mods:   KeyMods::NONE,
+        hint:   None, // for debugging
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::SaveBookmark,
+        scope:  Scope::Game,
+        keys:   &[
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ],
+        mods:   KeyMods::CTRL,
+        hint:   Some("keys-save-bookmark"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::JumpToBookmark,
+        scope:  Scope::Game,
+        keys:   &[
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ],
+        mods:   KeyMods::SHIFT,
+        hint:   Some("keys-jump-to-bookmark"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::FollowActivity,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::F],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-follow-activity"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::FocusChat,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::Return],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-focus-chat"),
+        near:   Near::Chat,
+    },
+    Binding {
+        action: KeyAction::CollapseChat,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::F2],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-collapse-chat"),
+        near:   Near::Chat,
+    },
+    Binding {
+        action: KeyAction::ToggleHud,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::F4],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-toggle-hud"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::ToggleBot,
+        scope:  Scope::Game,
+        keys:   &[KeyCode::B],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-toggle-bot"),
+        near:   Near::Board,
+    },
+    Binding {
+        action: KeyAction::NextWidget,
+        scope:  Scope::Menus,
+        keys:   &[KeyCode::Tab],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-next-widget"),
+        near:   Near::Focus,
+    },
+    Binding {
+        action: KeyAction::PreviousWidget,
+        scope:  Scope::Menus,
+        keys:   &[KeyCode::Tab],
+        mods:   KeyMods::SHIFT,
+        hint:   Some("keys-previous-widget"),
+        near:   Near::Focus,
+    },
+    Binding {
+        action: KeyAction::NextTab,
+        scope:  Scope::Menus,
+        keys:   &[KeyCode::Tab],
+        mods:   KeyMods::CTRL,
+        hint:   Some("keys-next-tab"),
+        near:   Near::Focus,
+    },
+    Binding {
+        action: KeyAction::Press,
+        scope:  Scope::Menus,
+        keys:   &[KeyCode::Space],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-press"),
+        near:   Near::Focus,
+    },
+    Binding {
+        action: KeyAction::Back,
+        scope:  Scope::Menus,
+        keys:   &[KeyCode::Escape],
+        mods:   KeyMods::NONE,
+        hint:   Some("keys-menu-back"),
+        near:   Near::Focus,
+    },
+];
+
+/// The actions whose keys the config can change, with their field in its `[keys]` section. The
+/// others tell what to do by which key was pressed, like the digits picking a pattern and the
+/// arrows panning, or are the menus' keys, which the `ui` module handles.
+const REBINDABLE: &[(&str, Scope, KeyAction)] = &[
+    ("help", Scope::Everywhere, KeyAction::Help),
+    ("debug_overlay", Scope::Everywhere, KeyAction::DebugOverlay),
+    ("console", Scope::Everywhere, KeyAction::Console),
+    ("clear_pattern", Scope::Game, KeyAction::ClearPattern),
+    ("cycle_symmetry", Scope::Game, KeyAction::CycleSymmetry),
+    ("move_symmetry_center", Scope::Game, KeyAction::MoveSymmetryCenter),
+    ("cycle_annotation_tool", Scope::Game, KeyAction::CycleAnnotationTool),
+    ("share_annotations", Scope::Game, KeyAction::ShareAnnotations),
+    ("clear_annotations", Scope::Game, KeyAction::ClearAnnotations),
+    ("follow_activity", Scope::Game, KeyAction::FollowActivity),
+    ("focus_chat", Scope::Game, KeyAction::FocusChat),
+    ("collapse_chat", Scope::Game, KeyAction::CollapseChat),
+    ("toggle_hud", Scope::Game, KeyAction::ToggleHud),
+    ("toggle_bot", Scope::Game, KeyAction::ToggleBot),
+    ("run_pause", Scope::Game, KeyAction::RunPause),
+    ("single_step", Scope::Game, KeyAction::SingleStep),
+    ("zoom_in", Scope::Game, KeyAction::ZoomIn),
+    ("zoom_out", Scope::Game, KeyAction::ZoomOut),
+    ("erase_selection", Scope::Game, KeyAction::EraseSelection),
+    ("back", Scope::Game, KeyAction::Back),
+    ("dump_pattern", Scope::Game, KeyAction::DumpPattern),
+];
+
+/// The keys bound to each action: those in `BINDINGS`, apart from any the config binds in their
+/// place.
+#[derive(Debug, Default)]
+struct Keymap {
+    settings: Option<KeySettings>, // as last set; None until then
+    rebound:  HashMap<(Scope, KeyAction), Vec<KeyCode>>,
+}
+
+impl Keymap {
+    /// The keymap with the keys in `settings`. Names that aren't keys are left out; `check_keys`
+    /// catches them when the config is loaded.
+    fn new(settings: &KeySettings) -> Self {
+        let mut rebound = HashMap::new();
+        for &(field, scope, action) in REBINDABLE {
+            let names = settings.get(field).unwrap_or(&[]);
+            let keys = names.iter().filter_map(|name| key_named(name)).collect();
+            rebound.insert((scope, action), keys);
+        }
+        Keymap {
+            settings: Some(settings.clone()),
+            rebound,
+        }
+    }
+
+    fn keys_of<'a>(&'a self, binding: &'a Binding) -> &'a [KeyCode] {
+        match self.rebound.get(&(binding.scope, binding.action)) {
+            Some(keys) => keys,
+            None => binding.keys,
+        }
+    }
+
+    fn action(&self, scope: Scope, key: KeyCode, mods: KeyMods) -> Option<KeyAction> {
+        BINDINGS
+            .iter()
+            .filter(|binding| {
+                binding.scope == scope && self.keys_of(binding).contains(&key) && mods.contains(binding.mods)
+            })
+            .max_by_key(|binding| binding.mods.bits().count_ones())
+            .map(|binding| binding.action)
+    }
+}
+
+lazy_static! {
+    static ref KEYMAP: RwLock<Keymap> = RwLock::new(Keymap::default());
+}
+
+/// Binds the keys set in the config. Called every frame, so that edits to the config take effect
+/// right away; nothing changes unless `settings` did.
+pub fn set_keys(settings: &KeySettings) {
+    if KEYMAP.read().unwrap().settings.as_ref() == Some(settings) {
+        return;
+    }
+    *KEYMAP.write().unwrap() = Keymap::new(settings);
+}
+
+/// An error naming the first key in `settings` that there's no such key as.
+pub fn check_keys(settings: &KeySettings) -> Result<(), String> {
+    for &(field, _, _) in REBINDABLE {
+        for name in settings.get(field).unwrap_or(&[]) {
+            if key_named(name).is_none() {
+                return Err(format!("{}: no key is named {:?}", field, name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The names of the keys `BINDINGS` has for the config field `field`, for the config's defaults.
+pub fn default_key_names(field: &str) -> Vec<String> {
+    REBINDABLE
+        .iter()
+        .find(|&&(name, _, _)| name == field)
+        .and_then(|&(_, scope, action)| {
+            BINDINGS
+                .iter()
+                .find(|binding| binding.scope == scope && binding.action == action)
+        })
+        .map(|binding| binding.keys.iter().map(|&key| key_name(key).to_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// What `key` does in `scope` while `mods` are held. Of the bindings whose modifiers are all held,
+/// the one with the most wins, so Shift-Left rotates a pattern while Shift-= still zooms in.
+pub fn action(scope: Scope, key: KeyCode, mods: KeyMods) -> Option<KeyAction> {
+    KEYMAP.read().unwrap().action(scope, key, mods)
+}
+
+/// The bindings whose hints are shown in `scope`, which also include those that work everywhere.
+/// Those left without keys by the config aren't shown.
+pub fn hinted(scope: Scope) -> impl Iterator<Item = &'static Binding> {
+    BINDINGS.iter().filter(move |binding| {
+        binding.hint.is_some()
+            && (binding.scope == scope || binding.scope == Scope::Everywhere)
+            && !KEYMAP.read().unwrap().keys_of(binding).is_empty()
+    })
+}
+
+/// How the keys of `binding` are written in its hint, like "Shift-Left/Right" or "2…0".
+pub fn keys_text(binding: &Binding) -> String {
+    keys_text_of(KEYMAP.read().unwrap().keys_of(binding), binding.mods)
+}
+
+fn keys_text_of(keys: &[KeyCode], mods: KeyMods) -> String {
+    let names: Vec<&str> = keys.iter().map(|&key| key_name(key)).collect();
+    let keys = if names.len() > 4 {
+        format!("{}…{}", names[0], names[names.len() - 1])
+    } else {
+        names.join("/")
+    };
+    let mut prefix = String::new();
+    if mods.contains(KeyMods::CTRL) {
+        prefix.push_str("Ctrl-");
+    }
+    if mods.contains(KeyMods::SHIFT) {
+        prefix.push_str("Shift-");
+    }
+    prefix + &keys
+}
+
+/// The number on `key`, if it's one of the number keys along the top of the keyboard.
+pub fn digit(key: KeyCode) -> Option<usize> {
+    let digits = [
+        KeyCode::Key0,
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    digits.iter().position(|&digit| digit == key)
+}
+
+/// The keys that can be named, in hints and in the config, with their names.
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Grave, "`"),
+    (KeyCode::Plus, "+"),
+    (KeyCode::Equals, "="),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Return, "Enter"),
+    (KeyCode::Escape, "Esc"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Back, "Backspace"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+];
+
+fn key_name(key: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|&&(named, _)| named == key)
+        .map_or("?", |&(_, name)| name)
+}
+
+/// The key with this name, ignoring case, as in "f1" for F1.
+fn key_named(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|&&(_, key_name)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(key, _)| key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_action_prefers_the_binding_with_most_modifiers_held() {
+        let action = |scope, key, mods| Keymap::default().action(scope, key, mods);
+        assert_eq!(action(Scope::Game, KeyCode::Left, KeyMods::NONE), Some(KeyAction::Pan));
+        assert_eq!(
+            action(Scope::Game, KeyCode::Left, KeyMods::SHIFT),
+            Some(KeyAction::RotatePattern)
+        );
+        assert_eq!(
+            action(Scope::Game, KeyCode::Equals, KeyMods::SHIFT),
+            Some(KeyAction::ZoomIn)
+        );
+        assert_eq!(action(Scope::Game, KeyCode::Up, KeyMods::SHIFT), Some(KeyAction::Pan));
+        assert_eq!(
+            action(Scope::Menus, KeyCode::Tab, KeyMods::CTRL),
+            Some(KeyAction::NextTab)
+        );
+        assert_eq!(action(Scope::Game, KeyCode::Tab, KeyMods::NONE), None);
+        assert_eq!(
+            action(Scope::Game, KeyCode::Key3, KeyMods::SHIFT),
+            Some(KeyAction::JumpToBookmark)
+        );
+        assert_eq!(action(Scope::Game, KeyCode::Key3, KeyMods::NONE), Some(KeyAction::PickPattern));
+        assert_eq!(digit(KeyCode::Key3), Some(3));
+    }
+
+    #[test]
+    fn test_every_hinted_key_has_a_name() {
+        let keys_text = |binding: &Binding| keys_text_of(binding.keys, binding.mods);
+        for binding in BINDINGS.iter().filter(|binding| binding.hint.is_some()) {
+            assert!(!keys_text(binding).contains('?'), "{:?}", binding);
+        }
+        let rotate = BINDINGS
+            .iter()
+            .find(|binding| binding.action == KeyAction::RotatePattern)
+            .unwrap();
+        assert_eq!(keys_text(rotate), "Shift-Left/Right");
+        let patterns = BINDINGS
+            .iter()
+            .find(|binding| binding.action == KeyAction::PickPattern)
+            .unwrap();
+        assert_eq!(keys_text(patterns), "2…0");
+    }
+
+    #[test]
+    fn test_keys_set_in_the_config_replace_the_usual_ones() {
+        let defaults = Keymap::new(&KeySettings::default());
+        assert_eq!(
+            defaults.action(Scope::Game, KeyCode::R, KeyMods::NONE),
+            Some(KeyAction::RunPause)
+        );
+
+        let mut settings = KeySettings::default();
+        settings.run_pause = vec!["p".to_owned()];
+        settings.toggle_bot = vec![];
+        assert_eq!(check_keys(&settings), Ok(()));
+        let keymap = Keymap::new(&settings);
+        assert_eq!(keymap.action(Scope::Game, KeyCode::R, KeyMods::NONE), None);
+        assert_eq!(
+            keymap.action(Scope::Game, KeyCode::P, KeyMods::NONE),
+            Some(KeyAction::RunPause)
+        );
+        assert_eq!(keymap.action(Scope::Game, KeyCode::B, KeyMods::NONE), None);
+        let run_pause = BINDINGS
+            .iter()
+            .find(|binding| binding.action == KeyAction::RunPause)
+            .unwrap();
+        assert_eq!(keys_text_of(keymap.keys_of(run_pause), run_pause.mods), "P");
+
+        settings.zoom_in = vec!["Shift-Up".to_owned()];
+        assert!(check_keys(&settings).is_err());
+    }
+}
@@ -0,0 +1,682 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Screens described in layout files rather than in code.
+//!
+//! A layout file is TOML with one `[[widget]]` table per widget, added to the screen in the order
+//! they are listed (which is also the order they get keyboard focus in):
+//!
+//! ```toml
+//! [[widget]]
+//! type = "pane"
+//! name = "menu"
+//! position = [20, 20]
+//! size = [410, 450]
+//!
+//! [[widget]]
+//! type = "button"
+//! parent = "menu"            # a pane listed earlier
+//! text = "menu-quit"         # a message ID, or a "$name" the code fills in
+//! position = [10, 310]
+//! size = [180, 50]           # at least big enough for the text
+//! on = { click = "quit" }    # events, and the handlers the code knows them by
+//! style = "danger"           # or "primary", or "secondary" (the default)
+//! image = "/conwayste.png"   # optional; an icon to the left of the text, given as for an image
+//!
+//! [[widget]]
+//! type = "label"
+//! text = "room-your-color"
+//! position = [20, 440]
+//! size = [300, 60]           # optional; the text is wrapped, and cut off with "…" if too long
+//! align = "center"           # or "left" (the default) or "right"
+//!
+//! [[widget]]
+//! type = "image"
+//! image = "/conwayste.png"   # in the resources directory
+//! position = [500, 40]
+//! size = [128, 128]
+//! scaling = "fit"            # the default; or "stretch" or "tile"
+//!
+//! [[widget]]
+//! type = "image"
+//! image = "/patterns.toml"   # a texture atlas; see src/resources.rs
+//! sprite = "glider"          # the region of the atlas to show
+//! position = [500, 180]
+//! size = [32, 32]
+//!
+//! [[widget]]
+//! type = "collapsible"
+//! name = "advanced"
+//! text = "options-advanced"  # the title, which is clicked to show or hide the content
+//! position = [20, 480]
+//! size = [300, 200]          # when expanded, including the title
+//! expanded = false           # the default
+//!
+//! [[widget]]
+//! type = "pane"              # the content of the section: the one widget whose parent it is
+//! parent = "advanced"
+//! position = [0, 0]          # relative to the area under the title
+//! size = [300, 170]
+//!
+//! [[widget]]
+//! type = "scroll-pane"       # like a collapsible, holds one widget, which may be bigger than it
+//! name = "patterns"
+//! position = [340, 480]
+//! size = [200, 200]
+//! ```
+//!
+//! The layouts for the standard screens are built into the client. A file of the same name in
+//! `layouts/` replaces one, so menus can be rearranged without recompiling; if it can't be read,
+//! or its widgets can't be made, the built-in layout is used instead.
+//!
+//! Debug builds also read the layouts from the source tree, so they can be edited in place, and
+//! rebuild a screen as soon as its layout file is saved.
+//!
+//! The in-game HUD isn't made of widgets, so its layout files, `hud.toml` and `hud_streamer.toml`
+//! for streamer mode, only say which corner or edge of the screen each part of it keeps to:
+//!
+//! ```toml
+//! [chat]
+//! anchor = "left"            # "top-left", "top", "top-right", "left", "center", "right",
+//!                            # "bottom-left", "bottom" or "bottom-right"
+//! offset = [20, 0]           # away from the edges it keeps to; from the center, right and down
+//! size = [360, 240]          # including the line messages are typed into
+//! font-size = 20
+//!
+//! [generation]               # likewise [status] and [toast], which have no size or font size
+//! anchor = "top"
+//! offset = [0, 10]
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+
+use ggez::graphics::Rect;
+use ggez::mint::Point2;
+use ggez::{Context, GameError};
+
+use id_tree::NodeId;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::config_watcher::DEBOUNCE_DELAY;
+use crate::constants::{self, colors::scheme};
+use crate::i18n::tr;
+use crate::resources::{Atlas, Texture};
+use crate::ui::context::Handled;
+use crate::ui::{
+    common::FontInfo, Alignment, Button, ButtonStyle, Checkbox, Collapsible, EmitEvent, Event, EventType, Image,
+    InsertLocation, Label, Layering, Pane, Scaling, ScrollPane, TextField, UIContext, UIError, UIResult, Widget,
+};
+
+/// The built-in layouts, as (name, contents).
+const BUNDLED_LAYOUTS: &[(&str, &str)] = &[
+    ("main_menu", include_str!("../layouts/main_menu.toml")),
+    ("options", include_str!("../layouts/options.toml")),
+    ("in_room", include_str!("../layouts/in_room.toml")),
+    ("results", include_str!("../layouts/results.toml")),
+    ("stats", include_str!("../layouts/stats.toml")),
+    ("leaderboards", include_str!("../layouts/leaderboards.toml")),
+    ("puzzles", include_str!("../layouts/puzzles.toml")),
+    ("pattern_editor", include_str!("../layouts/pattern_editor.toml")),
+    ("pattern_library", include_str!("../layouts/pattern_library.toml")),
+    ("shared_patterns", include_str!("../layouts/shared_patterns.toml")),
+    ("pattern_import", include_str!("../layouts/pattern_import.toml")),
+];
+
+/// The built-in layouts of the in-game HUD, as (name, contents).
+const BUNDLED_HUD_LAYOUTS: &[(&str, &str)] = &[
+    ("hud", include_str!("../layouts/hud.toml")),
+    ("hud_streamer", include_str!("../layouts/hud_streamer.toml")),
+];
+
+/// Where the built-in layouts come from, for debug builds to read in their place.
+const SOURCE_LAYOUTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/layouts");
+
+/// The directories layout files are read from, in the order they are tried.
+fn layout_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(constants::LAYOUTS_DIR)];
+    if cfg!(debug_assertions) {
+        dirs.push(PathBuf::from(SOURCE_LAYOUTS_DIR));
+    }
+    dirs
+}
+
+/// A handler that a layout file can name.
+pub type LayoutHandler = fn(&mut dyn EmitEvent, &mut UIContext, &Event) -> Result<Handled, Box<dyn Error>>;
+
+/// What the code offers the widgets of a layout.
+#[derive(Default)]
+pub struct LayoutBindings {
+    pub handlers: HashMap<&'static str, LayoutHandler>,
+    pub texts:    HashMap<&'static str, String>, // for `text = "$name"`, text that isn't a plain message
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum WidgetKind {
+    Pane,
+    Collapsible,
+    ScrollPane,
+    Button,
+    Label,
+    Checkbox,
+    TextField,
+    Image,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WidgetSpec {
+    #[serde(rename = "type")]
+    kind:       WidgetKind,
+    name:       Option<String>, // for the code, or other widgets, to refer to it by
+    parent:     Option<String>, // the name of a container; otherwise it goes in the screen itself
+    #[serde(default)]
+    next_layer: bool, // drawn above everything listed before it, like a dialog
+    position:   [f32; 2],       // relative to the parent
+    size:       Option<[f32; 2]>,
+    text:       Option<String>,
+    #[serde(default)]
+    checked:    bool,
+    #[serde(default)]
+    expanded:   bool, // whether a collapsible starts out showing its content
+    align:      Option<Alignment>, // of a label's lines of text
+    image:      Option<String>,    // the file an image, or the icon of a button, shows
+    sprite:     Option<String>,    // if given, `image` is a texture atlas and this names a region of it
+    scaling:    Option<Scaling>,
+    style:      Option<ButtonStyle>,
+    #[serde(default)]
+    on:         HashMap<String, String>, // event name to handler name
+}
+
+/// The widgets of a screen, as read from a layout file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutFile {
+    #[serde(default)]
+    widget: Vec<WidgetSpec>,
+}
+
+/// The layout called `name`, parsed with `parse`, from the first of the layout directories with a
+/// file for it that parses, or else from `bundled`.
+///
+/// # Panics
+/// If `bundled` has no layout with this name, or it doesn't parse.
+fn load_layout<T>(name: &str, bundled: &[(&str, &str)], parse: fn(&str) -> Result<T, toml::de::Error>) -> T {
+    load_layout_file(name, parse)
+        .map(|(_, layout)| layout)
+        .unwrap_or_else(|| bundled_layout(name, bundled, parse))
+}
+
+/// The layout called `name`, parsed with `parse`, and the path of the file it's from: the first of
+/// the layout directories with a file for it that parses. None if none of them have one.
+fn load_layout_file<T>(name: &str, parse: fn(&str) -> Result<T, toml::de::Error>) -> Option<(PathBuf, T)> {
+    for dir in layout_dirs() {
+        let path = dir.join(format!("{}.toml", name));
+        if let Ok(source) = fs::read_to_string(&path) {
+            match parse(&source) {
+                Ok(layout) => {
+                    info!("Using the layout in {:?}", path);
+                    return Some((path, layout));
+                }
+                Err(e) => error!("Could not parse the layout in {:?}, skipping it: {}", path, e),
+            }
+        }
+    }
+    None
+}
+
+/// The built-in layout called `name`, parsed with `parse`.
+///
+/// # Panics
+/// If `bundled` has no layout with this name, or it doesn't parse.
+fn bundled_layout<T>(name: &str, bundled: &[(&str, &str)], parse: fn(&str) -> Result<T, toml::de::Error>) -> T {
+    let (_, source) = bundled
+        .iter()
+        .find(|(bundled_name, _)| *bundled_name == name)
+        .expect("no built-in layout with this name");
+    parse(source).expect("built-in layout does not parse")
+}
+
+fn layout_error(reason: String) -> Box<UIError> {
+    Box::new(UIError::InvalidArgument { reason })
+}
+
+/// The texture named by the `image` and `sprite` of the widget, if it has an `image`.
+fn load_texture(ctx: &mut Context, spec: &WidgetSpec) -> UIResult<Option<Texture>> {
+    let path = match spec.image {
+        Some(ref path) => path,
+        None => return Ok(None),
+    };
+    let load_error = |e: GameError| layout_error(format!("could not load image {:?}: {}", path, e));
+    let texture = match spec.sprite {
+        Some(ref sprite) => Atlas::load(ctx, path)
+            .map_err(load_error)?
+            .texture(sprite)
+            .ok_or_else(|| layout_error(format!("{:?} has no sprite {:?}", path, sprite)))?,
+        None => Texture::load(ctx, path).map_err(load_error)?,
+    };
+    Ok(Some(texture))
+}
+
+fn event_type(name: &str) -> Option<EventType> {
+    match name {
+        "click" => Some(EventType::Click),
+        "double-click" => Some(EventType::DoubleClick),
+        "long-press" => Some(EventType::LongPress),
+        "update" => Some(EventType::Update),
+        "load" => Some(EventType::Load),
+        "save" => Some(EventType::Save),
+        "text-entered" => Some(EventType::TextEntered),
+        "value-changed" => Some(EventType::ValueChanged),
+        _ => None,
+    }
+}
+
+impl LayoutFile {
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    /// Builds the layout called `name` into `layer`, as `build` does, from `layouts/` if there is a
+    /// readable file for it there, or else the built-in one. Debug builds try the source tree's copy
+    /// before the built-in one. If the file's widgets can't all be made, such as when it names a
+    /// handler there isn't, those made are taken out of `layer` again and the built-in layout is
+    /// built instead.
+    ///
+    /// # Panics
+    /// If there is no built-in layout with this name, or it doesn't parse.
+    pub fn load_and_build(
+        name: &str,
+        ctx: &mut Context,
+        font_info: FontInfo,
+        layer: &mut Layering,
+        bindings: &LayoutBindings,
+    ) -> UIResult<HashMap<String, NodeId>> {
+        if let Some((path, layout)) = load_layout_file(name, LayoutFile::parse) {
+            let mut added = vec![];
+            match layout.build(ctx, font_info, layer, bindings, &mut added) {
+                Ok(ids) => return Ok(ids),
+                Err(e) => {
+                    error!("Could not build the layout in {:?}, using the built-in one: {}", path, e);
+                    for id in added {
+                        layer.remove_widget(id)?;
+                    }
+                }
+            }
+        }
+        bundled_layout(name, BUNDLED_LAYOUTS, LayoutFile::parse).build(ctx, font_info, layer, bindings, &mut vec![])
+    }
+
+    /// Creates the widgets and adds them to `layer`, which also knows the named ones by their names.
+    /// Returns the IDs of the named widgets. The widgets added to `layer` itself rather than to a
+    /// container are put in `added` as they are, so that they can be taken out again if a later one
+    /// can't be made.
+    fn build(
+        &self,
+        ctx: &mut Context,
+        font_info: FontInfo,
+        layer: &mut Layering,
+        bindings: &LayoutBindings,
+        added: &mut Vec<NodeId>,
+    ) -> UIResult<HashMap<String, NodeId>> {
+        let mut ids: HashMap<String, NodeId> = HashMap::new();
+        for spec in &self.widget {
+            let text = match spec.text {
+                Some(ref text) if text.starts_with('$') => bindings
+                    .texts
+                    .get(&text[1..])
+                    .cloned()
+                    .ok_or_else(|| layout_error(format!("no text is provided for {:?}", text)))?,
+                Some(ref id) => tr(id),
+                None => String::new(),
+            };
+            let [x, y] = spec.position;
+            let mut widget: Box<dyn Widget> = match spec.kind {
+                WidgetKind::Pane => {
+                    let [w, h] = spec
+                        .size
+                        .ok_or_else(|| layout_error(format!("pane {:?} has no size", spec.name)))?;
+                    Box::new(Pane::new(Rect::new(x, y, w, h)))
+                }
+                WidgetKind::Collapsible => {
+                    let [w, h] = spec
+                        .size
+                        .ok_or_else(|| layout_error(format!("collapsible {:?} has no size", spec.name)))?;
+                    Box::new(Collapsible::new(font_info, Rect::new(x, y, w, h), text, spec.expanded))
+                }
+                WidgetKind::ScrollPane => {
+                    let [w, h] = spec
+                        .size
+                        .ok_or_else(|| layout_error(format!("scroll pane {:?} has no size", spec.name)))?;
+                    Box::new(ScrollPane::new(Rect::new(x, y, w, h)))
+                }
+                WidgetKind::Button => {
+                    let mut button = Button::new(ctx, font_info, text);
+                    button.style = spec.style.unwrap_or(ButtonStyle::Secondary);
+                    if let Some(icon) = load_texture(ctx, spec)? {
+                        button.set_icon(icon);
+                    }
+                    let (min_w, min_h) = button.size(); // a new Button is just big enough for its icon and text
+                    let [w, h] = spec.size.unwrap_or([min_w, min_h]);
+                    button.set_rect(Rect::new(x, y, w.max(min_w), h.max(min_h)))?;
+                    Box::new(button)
+                }
+                WidgetKind::Label => {
+                    let mut label = Label::new(ctx, font_info, text, scheme().menu_text, Point2 { x, y });
+                    if let Some([w, h]) = spec.size {
+                        label.set_size(w, h)?;
+                        label.wrap = true;
+                        label.ellipsize = true;
+                    }
+                    label.align = spec.align.unwrap_or(Alignment::Left);
+                    Box::new(label)
+                }
+                WidgetKind::Checkbox => {
+                    let [w, h] = spec.size.unwrap_or([20.0, 20.0]);
+                    Box::new(Checkbox::new(ctx, spec.checked, font_info, text, Rect::new(x, y, w, h)))
+                }
+                WidgetKind::TextField => {
+                    let [w, h] = spec.size.unwrap_or([200.0, 30.0]);
+                    let mut tf = TextField::new(font_info, Rect::new(x, y, w, h));
+                    tf.hide_when_empty = false;
+                    tf.set_text(text);
+                    Box::new(tf)
+                }
+                WidgetKind::Image => {
+                    let texture = load_texture(ctx, spec)?
+                        .ok_or_else(|| layout_error(format!("image {:?} has no image file", spec.name)))?;
+                    let [w, h] = spec
+                        .size
+                        .ok_or_else(|| layout_error(format!("image {:?} has no size", spec.name)))?;
+                    let mut image = Image::new(texture, Rect::new(x, y, w, h));
+                    image.scaling = spec.scaling.unwrap_or(Scaling::Fit);
+                    Box::new(image)
+                }
+            };
+
+            for (event_name, handler_name) in &spec.on {
+                let what = event_type(event_name)
+                    .ok_or_else(|| layout_error(format!("there is no {:?} event", event_name)))?;
+                let handler = bindings
+                    .handlers
+                    .get(handler_name.as_str())
+                    .ok_or_else(|| layout_error(format!("there is no handler named {:?}", handler_name)))?;
+                let emitter = widget.as_emit_event().unwrap(); // unwrap OK because all of these widgets emit events
+                emitter
+                    .on(what, Box::new(*handler))
+                    .map_err(|e| layout_error(e.to_string()))?;
+            }
+
+            let location = match spec.parent {
+                Some(ref parent) => match ids.get(parent) {
+                    Some(parent_id) => InsertLocation::ToNestedContainer(parent_id),
+                    None => {
+                        return Err(layout_error(format!(
+                            "no container named {:?} comes before this",
+                            parent
+                        )))
+                    }
+                },
+                None if spec.next_layer => InsertLocation::AtNextLayer,
+                None => InsertLocation::AtCurrentLayer,
+            };
+            let id = layer.add_widget(widget, location)?;
+            if spec.parent.is_none() {
+                added.push(id.clone());
+            }
+            if let Some(ref name) = spec.name {
+                layer.set_widget_name(name, &id);
+                ids.insert(name.clone(), id);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+/// Which corner or edge of the screen a part of the HUD keeps to.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Where a part of the HUD goes.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HudSpot {
+    anchor: Anchor,
+    #[serde(default)]
+    offset: [f32; 2], // in pixels, away from the edges it keeps to
+}
+
+impl HudSpot {
+    /// The top left corner of something `size` big put in this spot, on a screen `screen` big.
+    pub fn place(&self, screen: (f32, f32), size: (f32, f32)) -> Point2<f32> {
+        let [offset_x, offset_y] = self.offset;
+        let x = match self.anchor {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => offset_x,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => (screen.0 - size.0) / 2.0 + offset_x,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => screen.0 - size.0 - offset_x,
+        };
+        let y = match self.anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => offset_y,
+            Anchor::Left | Anchor::Center | Anchor::Right => (screen.1 - size.1) / 2.0 + offset_y,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => screen.1 - size.1 - offset_y,
+        };
+        Point2 { x, y }
+    }
+}
+
+/// Where the in-game chat goes, and how big it and its text are.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ChatSpot {
+    anchor:        Anchor,
+    #[serde(default)]
+    offset:        [f32; 2],
+    pub size:      [f32; 2], // including the text field below the messages
+    pub font_size: f32,      // in pixels
+}
+
+impl ChatSpot {
+    pub fn spot(&self) -> HudSpot {
+        HudSpot {
+            anchor: self.anchor,
+            offset: self.offset,
+        }
+    }
+}
+
+/// Where the parts of the in-game HUD go, as read from a HUD layout file.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HudLayout {
+    pub chat:       ChatSpot,
+    pub generation: HudSpot, // the generation counter
+    pub status:     HudSpot, // how a puzzle is going, or how far behind the server the game is
+    pub toast:      HudSpot, // short messages, such as after saving something
+}
+
+impl HudLayout {
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    /// The HUD layout for streamer mode if `streamer_mode`, or else the usual one, from `layouts/`
+    /// if there is a readable file for it there, like `LayoutFile::load_and_build`.
+    pub fn load(streamer_mode: bool) -> Self {
+        let name = if streamer_mode { "hud_streamer" } else { "hud" };
+        load_layout(name, BUNDLED_HUD_LAYOUTS, HudLayout::parse)
+    }
+}
+
+/// What the player had entered into a named widget, to be put back once its screen is rebuilt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetState {
+    Text(String),
+    Checked(bool),
+}
+
+/// The contents of the named text fields and checkboxes in `layer`.
+pub fn save_widget_state(layer: &Layering) -> Vec<(String, WidgetState)> {
+    let mut saved = vec![];
+    for (name, id) in layer.named_widgets() {
+        if let Ok(tf) = TextField::widget_from_id(layer, id) {
+            saved.push((name.to_owned(), WidgetState::Text(tf.text().unwrap_or_default())));
+        } else if let Ok(checkbox) = Checkbox::widget_from_id(layer, id) {
+            saved.push((name.to_owned(), WidgetState::Checked(checkbox.is_checked())));
+        }
+    }
+    saved
+}
+
+/// Puts back what `save_widget_state` saved, into the widgets that have the same names and types.
+/// Widgets that were renamed or removed in the meantime are skipped.
+pub fn restore_widget_state(layer: &mut Layering, saved: &[(String, WidgetState)]) {
+    for (name, state) in saved {
+        let id = match layer.widget_id_by_name(name) {
+            Some(id) => id.clone(),
+            None => continue,
+        };
+        match state {
+            WidgetState::Text(text) => {
+                if let Ok(tf) = TextField::widget_from_id_mut(layer, &id) {
+                    tf.set_text(text.clone());
+                }
+            }
+            WidgetState::Checked(checked) => {
+                if let Ok(checkbox) = Checkbox::widget_from_id_mut(layer, &id) {
+                    checkbox.set_state((*checked).into());
+                }
+            }
+        }
+    }
+}
+
+/// Notices when layout files are saved, so debug builds can rebuild their screens right away.
+pub struct LayoutWatcher {
+    _watcher: RecommendedWatcher, // watching stops when this is dropped
+    events:   Receiver<DebouncedEvent>,
+}
+
+impl LayoutWatcher {
+    /// Watches whichever of the layout directories exist.
+    pub fn new() -> notify::Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, DEBOUNCE_DELAY)?;
+        for dir in layout_dirs() {
+            if dir.is_dir() {
+                watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            }
+        }
+        Ok(LayoutWatcher {
+            _watcher: watcher,
+            events:   rx,
+        })
+    }
+
+    /// The names of the layouts whose files were created or written since the last call. Never
+    /// blocks.
+    pub fn poll_changed(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for event in self.events.try_iter() {
+            match event {
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => {
+                    if path.extension().map_or(true, |ext| ext != "toml") {
+                        continue;
+                    }
+                    if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                        if !names.iter().any(|n| n == name) {
+                            names.push(name.to_owned());
+                        }
+                    }
+                }
+                DebouncedEvent::Error(e, opt_path) => warn!("Error while watching layouts {:?}: {}", opt_path, e),
+                _ => {}
+            }
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bundled_layouts_parse() {
+        for (name, source) in BUNDLED_LAYOUTS {
+            let layout = LayoutFile::parse(source).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let mut names = vec![];
+            for spec in &layout.widget {
+                if let Some(ref parent) = spec.parent {
+                    assert!(names.contains(parent), "{}: unknown parent {:?}", name, parent);
+                }
+                for event_name in spec.on.keys() {
+                    assert!(
+                        event_type(event_name).is_some(),
+                        "{}: unknown event {:?}",
+                        name,
+                        event_name
+                    );
+                }
+                if let Some(ref widget_name) = spec.name {
+                    names.push(widget_name.clone());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bundled_hud_layouts_parse() {
+        for (name, source) in BUNDLED_HUD_LAYOUTS {
+            HudLayout::parse(source).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+
+    #[test]
+    fn test_hud_spots_keep_to_their_edges() {
+        let screen = (1000.0, 800.0);
+        let spot: HudSpot = toml::from_str("anchor = \"bottom-right\"\noffset = [10, 20]").unwrap();
+        assert_eq!(spot.place(screen, (100.0, 50.0)), Point2 { x: 890.0, y: 730.0 });
+        let spot: HudSpot = toml::from_str("anchor = \"left\"\noffset = [10, 20]").unwrap();
+        assert_eq!(spot.place(screen, (100.0, 50.0)), Point2 { x: 10.0, y: 395.0 });
+        let spot: HudSpot = toml::from_str("anchor = \"top\"").unwrap();
+        assert_eq!(spot.place(screen, (100.0, 50.0)), Point2 { x: 450.0, y: 0.0 });
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let source = "[[widget]]\ntype = \"button\"\nposition = [0, 0]\ncolour = \"red\"\n";
+        assert!(LayoutFile::parse(source).is_err());
+        let source = "[[widget]]\ntype = \"slider\"\nposition = [0, 0]\n";
+        assert!(LayoutFile::parse(source).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Loading resources while the game starts, so the intro can show how far along it is rather than
+//! the window hanging. A thread reads the files, then the main thread makes images of them, as
+//! only it may use the graphics context. Each resource is kept in the `Registry` under a key,
+//! where the rest of the client finds it once it's loaded.
+//!
+//! The system font isn't loaded this way, as showing the progress needs it. Neither are the images
+//! named in layout files, which are loaded with the screens that show them. The client has no
+//! sounds.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use ggez::graphics::{FilterMode, Image};
+use ggez::{filesystem, Context};
+
+use crate::pattern_library::{self, LibraryPattern, THUMBNAIL_SIZE};
+use crate::resources;
+
+/// Something to load.
+#[derive(Debug, Clone)]
+pub enum Job {
+    Image(String), // path in the resource directories, like "/conwayste.png"
+    Texts {
+        dir:       PathBuf,
+        extension: &'static str, // only files with this extension are read
+    },
+    Thumbnail(LibraryPattern), // drawn, unless it already was and is in the thumbnails folder
+    Patterns,                  // every pattern in the library
+}
+
+/// What the thread read for a job.
+enum Read {
+    Image(Vec<u8>),
+    Texts(Vec<(String, String)>),
+    Pixels(Vec<u8>), // RGBA, THUMBNAIL_SIZE pixels square
+    Patterns(Vec<LibraryPattern>),
+}
+
+pub enum Resource {
+    Image(Image),
+    Texts(Vec<(String, String)>), // (file name without the extension, contents), sorted by name
+    Patterns(Vec<LibraryPattern>), // in the order `pattern_library::load_all` gives them
+}
+
+/// A batch of jobs being done on a thread of its own.
+struct Batch {
+    receiver: mpsc::Receiver<(String, Result<Read, String>)>,
+    left:     usize, // jobs not heard back about yet
+}
+
+/// The loaded resources, by key, and those still loading.
+#[derive(Default)]
+pub struct Registry {
+    resources: HashMap<String, Resource>,
+    batches:   Vec<Batch>,
+    done:      usize, // jobs finished, whether or not they succeeded
+    total:     usize,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Starts doing `jobs` in order on another thread, keeping each resource under its key.
+    pub fn load(&mut self, ctx: &Context, jobs: Vec<(String, Job)>) {
+        let dirs = resource_dirs(ctx);
+        let (sender, receiver) = mpsc::channel();
+        self.total += jobs.len();
+        self.batches.push(Batch {
+            receiver,
+            left: jobs.len(),
+        });
+        thread::spawn(move || {
+            for (key, job) in jobs {
+                if sender.send((key, read(&dirs, &job))).is_err() {
+                    return; // no one is waiting for the rest
+                }
+            }
+        });
+    }
+
+    /// Makes resources of what has been read since the last call, and returns the keys of the
+    /// jobs finished, including any that failed. Called every frame.
+    pub fn poll(&mut self, ctx: &mut Context) -> Vec<String> {
+        let mut finished = vec![];
+        let mut arrived = vec![];
+        for batch in self.batches.iter_mut() {
+            loop {
+                match batch.receiver.try_recv() {
+                    Ok(job_read) => {
+                        batch.left -= 1;
+                        arrived.push(job_read);
+                    }
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        if batch.left > 0 {
+                            error!("The loading thread quit with {} resources to go", batch.left);
+                            self.done += batch.left;
+                            batch.left = 0;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        self.batches.retain(|batch| batch.left > 0);
+
+        for (key, result) in arrived {
+            match result.and_then(|read| make(ctx, read)) {
+                Ok(resource) => {
+                    self.resources.insert(key.clone(), resource);
+                }
+                Err(e) => error!("Could not load resource {:?}: {}", key, e),
+            }
+            self.done += 1;
+            finished.push(key);
+        }
+        finished
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.done < self.total
+    }
+
+    /// (jobs finished, all jobs), for showing progress.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done, self.total)
+    }
+
+    pub fn image(&self, key: &str) -> Option<&Image> {
+        match self.resources.get(key) {
+            Some(Resource::Image(image)) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn texts(&self, key: &str) -> Option<&[(String, String)]> {
+        match self.resources.get(key) {
+            Some(Resource::Texts(texts)) => Some(texts),
+            _ => None,
+        }
+    }
+
+    pub fn patterns(&self, key: &str) -> Option<&[LibraryPattern]> {
+        match self.resources.get(key) {
+            Some(Resource::Patterns(patterns)) => Some(patterns),
+            _ => None,
+        }
+    }
+}
+
+/// The resources directory of the source tree, when run with `cargo run`.
+pub fn manifest_resources_dir() -> Option<PathBuf> {
+    env::var("CARGO_MANIFEST_DIR")
+        .ok()
+        .map(|manifest_dir| PathBuf::from(manifest_dir).join("resources"))
+}
+
+/// The directories ggez finds resources in, to be searched by another thread in the same order.
+fn resource_dirs(ctx: &Context) -> Vec<PathBuf> {
+    let mut dirs = vec![filesystem::resources_dir(ctx).to_owned()];
+    dirs.extend(manifest_resources_dir());
+    dirs
+}
+
+/// The file at `path`, like "/conwayste.png", in the first of `dirs` that has it.
+fn find_file(dirs: &[PathBuf], path: &str) -> Option<PathBuf> {
+    let relative = path.trim_start_matches('/');
+    dirs.iter().map(|dir| dir.join(relative)).find(|file| file.is_file())
+}
+
+fn read(dirs: &[PathBuf], job: &Job) -> Result<Read, String> {
+    match job {
+        Job::Image(path) => {
+            let file = find_file(dirs, path).ok_or_else(|| format!("{:?} is not in any of {:?}", path, dirs))?;
+            fs::read(&file)
+                .map(Read::Image)
+                .map_err(|e| format!("{:?}: {}", file, e))
+        }
+        Job::Texts { dir, extension } => Ok(Read::Texts(read_texts(dir, extension))),
+        Job::Thumbnail(pattern) => resources::thumbnail_pixels(pattern).map(Read::Pixels),
+        Job::Patterns => Ok(Read::Patterns(pattern_library::load_all())),
+    }
+}
+
+/// The (name, contents) of every file in `dir` ending in `extension`, sorted by name. A file's name
+/// is without the extension. A missing directory has none, and files that can't be read are
+/// logged and skipped.
+fn read_texts(dir: &Path, extension: &str) -> Vec<(String, String)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            info!("Not loading anything from {:?}: {}", dir, e);
+            return vec![];
+        }
+    };
+
+    let mut texts = vec![];
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != extension) {
+            continue;
+        }
+        // unwrap OK because a file with an extension has a stem
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        match fs::read_to_string(&path) {
+            Ok(text) => texts.push((name, text)),
+            Err(e) => error!("Could not read {:?}: {}", path, e),
+        }
+    }
+    texts.sort();
+    texts
+}
+
+/// The resource made from what was read for a job.
+fn make(ctx: &mut Context, read: Read) -> Result<Resource, String> {
+    match read {
+        Read::Image(bytes) => {
+            let mut image = Image::from_bytes(ctx, &bytes).map_err(|e| e.to_string())?;
+            image.set_filter(FilterMode::Nearest); // pixel art, like the board
+            Ok(Resource::Image(image))
+        }
+        Read::Texts(texts) => Ok(Resource::Texts(texts)),
+        Read::Pixels(pixels) => {
+            let size = THUMBNAIL_SIZE as u16;
+            let mut image = Image::from_rgba8(ctx, size, size, &pixels).map_err(|e| e.to_string())?;
+            image.set_filter(FilterMode::Nearest);
+            Ok(Resource::Image(image))
+        }
+        Read::Patterns(patterns) => Ok(Resource::Patterns(patterns)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_texts_by_extension_and_find_files() {
+        let dir = std::env::temp_dir().join(format!("conwayste-loader-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("spin.rhai"), "fn on_gen() {}").unwrap();
+        fs::write(dir.join("build.rhai"), "fn on_start() {}").unwrap();
+        fs::write(dir.join("notes.txt"), "not a script").unwrap();
+
+        assert_eq!(
+            read_texts(&dir, "rhai"),
+            vec![
+                ("build".to_owned(), "fn on_start() {}".to_owned()),
+                ("spin".to_owned(), "fn on_gen() {}".to_owned()),
+            ]
+        );
+        assert_eq!(read_texts(&dir.join("missing"), "rhai"), vec![]);
+
+        let dirs = vec![dir.join("missing"), dir.clone()];
+        assert_eq!(find_file(&dirs, "/notes.txt"), Some(dir.join("notes.txt")));
+        assert_eq!(find_file(&dirs, "/logo.png"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Lockstep verification, a debug mode for hunting desyncs. The client runs its own copy of a
+//! networked game from the options the server started it with, making the same cell changes the
+//! server made for players and bots. Each time the server sends a hash of its universe, the copy is
+//! stepped to that generation and the hashes are compared. The first generation where they differ
+//! is logged, and that copy is kept so it can be drawn.
+//!
+//! The server only sends the cell changes from rooms with `share_cell_inputs` on, since they show
+//! what the fog would hide. Without them, any game where cells are placed diverges.
+
+use std::collections::BTreeMap;
+
+use conway::universe::{BigBang, CellState, PlayerBuilder, Region, Universe};
+use conway::ConwayResult;
+use netwayste::net::{CellInput, GameOptions, UniverseHash};
+
+/// The first generation where the client's universe didn't match the server's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Divergence {
+    pub generation:  u32,
+    pub local_hash:  u64,
+    pub server_hash: u64,
+}
+
+pub struct LockstepCheck {
+    uni:         Universe,
+    inputs:      BTreeMap<u32, Vec<CellInput>>, // by generation; made once the universe gets there
+    checked_gen: Option<u32>,                    // latest generation found to match
+    divergence:  Option<Divergence>,
+}
+
+impl LockstepCheck {
+    /// Starts checking a game that the server just started with `options`.
+    pub fn new(options: &GameOptions) -> ConwayResult<Self> {
+        let players = options
+            .player_writable()
+            .iter()
+            .map(|region| PlayerBuilder::new(Region::from(region)))
+            .collect();
+        let mut uni = BigBang::new()
+            .width(options.width() as usize)
+            .height(options.height() as usize)
+            .server_mode(true) // like the server, so that nothing is hidden by fog
+            .history(options.history() as usize)
+            .fog_radius(options.fog_radius() as usize)
+            .add_players(players)
+            .birth()?;
+        options.add_map(&mut uni)?;
+        Ok(LockstepCheck {
+            uni,
+            inputs: BTreeMap::new(),
+            checked_gen: None,
+            divergence: None,
+        })
+    }
+
+    /// Holds on to the cell changes the server made just after advancing to `generation`, to make
+    /// the same ones here. The server sends these before any hash of that generation.
+    pub fn add_inputs(&mut self, generation: u32, inputs: Vec<CellInput>) {
+        if generation as usize <= self.uni.latest_gen() {
+            debug!(
+                "Lockstep: ignoring inputs for generation {}, already at {}",
+                generation,
+                self.uni.latest_gen()
+            );
+            return;
+        }
+        self.inputs.entry(generation).or_insert_with(Vec::new).extend(inputs);
+    }
+
+    /// Steps this universe to the generation of `server_hash` and compares. Returns the divergence
+    /// if this is where it is first found. Once diverged, nothing more is checked.
+    pub fn check(&mut self, server_hash: UniverseHash) -> Option<Divergence> {
+        if self.divergence.is_some() {
+            return None;
+        }
+        let generation = server_hash.generation as usize;
+        if generation < self.uni.latest_gen() {
+            debug!(
+                "Lockstep: ignoring hash for generation {}, already at {}",
+                generation,
+                self.uni.latest_gen()
+            );
+            return None;
+        }
+        while self.uni.latest_gen() < generation {
+            self.uni.next();
+            let inputs = self.inputs.remove(&(self.uni.latest_gen() as u32)).unwrap_or_default();
+            for input in inputs {
+                let player_index = input.player_index as usize;
+                let state = if input.alive {
+                    CellState::Alive(Some(player_index))
+                } else {
+                    CellState::Dead
+                };
+                // just as the server does, so a write it refused is refused here too
+                self.uni.set(input.col as usize, input.row as usize, state, player_index);
+            }
+        }
+
+        let local_hash = self.uni.state_hash();
+        if local_hash == server_hash.hash {
+            self.checked_gen = Some(server_hash.generation);
+            return None;
+        }
+        let divergence = Divergence {
+            generation: server_hash.generation,
+            local_hash,
+            server_hash: server_hash.hash,
+        };
+        error!(
+            "Lockstep: diverged from the server at generation {} (last matched {:?}); local hash {:016x}, server hash {:016x}. Local universe:\n{}",
+            divergence.generation, self.checked_gen, local_hash, server_hash.hash, self.uni
+        );
+        self.divergence = Some(divergence);
+        Some(divergence)
+    }
+
+    pub fn divergence(&self) -> Option<Divergence> {
+        self.divergence
+    }
+
+    /// This client's universe; at the first divergent generation, if there is one.
+    pub fn universe(&self) -> &Universe {
+        &self.uni
+    }
+
+    /// One line describing how the check is going, for the debug overlay.
+    pub fn status(&self) -> String {
+        match (self.divergence, self.checked_gen) {
+            (Some(d), _) => format!("Lockstep: diverged at gen {}", d.generation),
+            (None, Some(generation)) => format!("Lockstep: in step through gen {}", generation),
+            (None, None) => "Lockstep: waiting for the server's hash".to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server_universe(options: &GameOptions) -> Universe {
+        LockstepCheck::new(options).unwrap().uni
+    }
+
+    fn hash_of(uni: &Universe) -> UniverseHash {
+        UniverseHash {
+            generation: uni.latest_gen() as u32,
+            hash:       uni.state_hash(),
+        }
+    }
+
+    #[test]
+    fn test_check_finds_first_divergent_generation() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut check = LockstepCheck::new(&options).unwrap();
+        let mut server_uni = server_universe(&options);
+        for &(col, row) in &[(11, 10), (12, 11), (10, 12), (11, 12), (12, 12)] {
+            server_uni.toggle(col, row, 0).unwrap();
+            check.uni.toggle(col, row, 0).unwrap();
+        }
+
+        for _ in 0..10 {
+            server_uni.next();
+        }
+        assert_eq!(check.check(hash_of(&server_uni)), None);
+        assert_eq!(check.universe().latest_gen(), 11);
+        assert!(check.status().contains("through gen 11"));
+
+        // a block, which lasts, that the client never heard about
+        for &(col, row) in &[(50, 20), (51, 20), (50, 21), (51, 21)] {
+            server_uni.set_unchecked(col, row, CellState::Alive(Some(1)));
+        }
+        for _ in 0..5 {
+            server_uni.next();
+        }
+        let divergence = check.check(hash_of(&server_uni)).unwrap();
+        assert_eq!(divergence.generation, 16);
+        assert_eq!(check.divergence(), Some(divergence));
+
+        // kept at the divergent generation from then on
+        server_uni.next();
+        assert_eq!(check.check(hash_of(&server_uni)), None);
+        assert_eq!(check.universe().latest_gen(), 16);
+    }
+
+    #[test]
+    fn test_check_makes_the_servers_cell_changes() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut check = LockstepCheck::new(&options).unwrap();
+        let mut server_uni = server_universe(&options);
+        let glider = [(11, 10), (12, 11), (10, 12), (11, 12), (12, 12)];
+        let block = [(50, 20), (51, 20), (50, 21), (51, 21)];
+        let moves = [(3, 0, &glider[..], true), (5, 1, &block[..], true), (9, 1, &block[..1], false)];
+
+        for &(generation, player_index, cells, alive) in &moves {
+            while server_uni.latest_gen() < generation {
+                server_uni.next();
+            }
+            let state = if alive {
+                CellState::Alive(Some(player_index))
+            } else {
+                CellState::Dead
+            };
+            let mut inputs = vec![];
+            for &(col, row) in cells {
+                server_uni.set(col, row, state, player_index);
+                inputs.push(CellInput {
+                    player_index: player_index as u64,
+                    col:          col as u32,
+                    row:          row as u32,
+                    alive,
+                });
+            }
+            check.add_inputs(generation as u32, inputs);
+        }
+        for _ in 0..10 {
+            server_uni.next();
+        }
+
+        assert_eq!(check.check(hash_of(&server_uni)), None);
+        assert_eq!(check.universe().latest_gen(), 19);
+        assert_eq!(check.divergence(), None);
+        assert_ne!(check.uni.get_cell_state(51, 21, None), CellState::Dead); // the block was placed
+    }
+}
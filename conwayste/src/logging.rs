@@ -0,0 +1,216 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Sets up logging. Records go to stderr and to a log file in the user's data directory. The level
+//! of each module can be changed while the game is running, such as from the developer console, and
+//! the latest lines are kept in memory for crash reports.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use chrono::Local;
+use log::{LevelFilter, Record};
+
+use crate::user_data::Folder;
+
+const LOG_FILE_NAME: &str = "conwayste.log";
+const KEPT_LOG_FILES: usize = 5; // the current log file and the ones rotated out before it
+const MAX_LOG_FILE_SIZE: u64 = 4 * 1024 * 1024; // bytes; the file is rotated once it would pass this
+const RECENT_LINES: usize = 200; // log lines kept for crash reports
+
+lazy_static! {
+    /// Log level by module path prefix. The entry with an empty prefix applies to everything else.
+    static ref LEVELS: RwLock<Vec<(String, LevelFilter)>> = RwLock::new(
+        vec![
+            ("", LevelFilter::Debug),
+            ("futures", LevelFilter::Info),
+            ("tokio_core", LevelFilter::Info),
+            ("tokio_reactor", LevelFilter::Info),
+            ("conway", LevelFilter::Info),
+            ("ggez", LevelFilter::Warn),
+            ("gfx_device_gl", LevelFilter::Off),
+        ]
+        .into_iter()
+        .map(|(module, level)| (module.to_owned(), level))
+        .collect()
+    );
+    static ref RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(RECENT_LINES));
+    static ref LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+}
+
+/// The level for `target` in `levels`, from the entry with the longest matching module path.
+fn level_in(levels: &[(String, LevelFilter)], target: &str) -> LevelFilter {
+    levels
+        .iter()
+        .filter(|(module, _)| {
+            module.is_empty()
+                || target == module
+                || (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+        })
+        .max_by_key(|(module, _)| module.len())
+        .map_or(LevelFilter::Off, |(_, level)| *level)
+}
+
+/// Sets the log level of `module` and the modules inside it. An empty `module` sets the level of
+/// every module without a level of its own.
+pub fn set_level(module: &str, level: LevelFilter) {
+    let mut levels = LEVELS.write().unwrap();
+    match levels.iter_mut().find(|(m, _)| m == module) {
+        Some(entry) => entry.1 = level,
+        None => levels.push((module.to_owned(), level)),
+    }
+}
+
+/// The path of the log file rotated out `age` times; 0 is the one being written.
+fn log_file_path(dir: &Path, age: usize) -> PathBuf {
+    if age == 0 {
+        dir.join(LOG_FILE_NAME)
+    } else {
+        dir.join(format!("{}.{}", LOG_FILE_NAME, age))
+    }
+}
+
+/// Renames each log file in `dir` to the name of the next older one, dropping the oldest.
+fn rotate(dir: &Path) -> io::Result<()> {
+    for age in (1..KEPT_LOG_FILES).rev() {
+        let from = log_file_path(dir, age - 1);
+        if from.exists() {
+            fs::rename(&from, log_file_path(dir, age))?;
+        }
+    }
+    Ok(())
+}
+
+/// The log file being written. A new one is started each time the game is launched, and whenever it
+/// grows past `MAX_LOG_FILE_SIZE`.
+struct LogFile {
+    dir:     PathBuf,
+    file:    File,
+    written: u64, // bytes
+}
+
+impl LogFile {
+    fn open(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        rotate(&dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(log_file_path(&dir, 0))?;
+        Ok(LogFile { dir, file, written: 0 })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > MAX_LOG_FILE_SIZE {
+            *self = LogFile::open(self.dir.clone())?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+}
+
+/// Keeps a formatted log line for crash reports and writes it to the log file.
+fn record_line(record: &Record) {
+    let line = record.args().to_string();
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        if let Err(e) = file.write_line(&line) {
+            eprintln!("Could not write to the log file: {}", e);
+        }
+    }
+    let mut recent = RECENT.lock().unwrap();
+    if recent.len() == RECENT_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(line);
+}
+
+/// Installs the logger. Call once, at startup.
+pub fn init() {
+    let file_result = LogFile::open(Folder::Logs.path()).map(|file| *LOG_FILE.lock().unwrap() = Some(file));
+
+    fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} [{:5}] - {}",
+                Local::now().format("%H:%M:%S%.6f"),
+                record.level(),
+                message
+            ))
+        })
+        .filter(|metadata| metadata.level() <= level_in(&LEVELS.read().unwrap(), metadata.target()))
+        .chain(io::stderr())
+        .chain(fern::Output::call(record_line))
+        .apply()
+        .expect("logger was already installed");
+
+    match file_result {
+        Ok(()) => info!("Logging to {:?}", log_file_path(&Folder::Logs.path(), 0)),
+        Err(e) => warn!("Could not open a log file in {:?}: {}", Folder::Logs.path(), e),
+    }
+}
+
+/// The latest log lines, oldest first. This is called while panicking, so rather than wait for a
+/// thread that is logging, it gives up and returns nothing.
+pub fn recent_lines() -> Vec<String> {
+    match RECENT.try_lock() {
+        Ok(recent) => recent.iter().cloned().collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_level_in_uses_longest_matching_module() {
+        let levels: Vec<(String, LevelFilter)> = vec![
+            ("".to_owned(), LevelFilter::Debug),
+            ("conway".to_owned(), LevelFilter::Info),
+            ("conway::universe".to_owned(), LevelFilter::Trace),
+        ];
+        assert_eq!(level_in(&levels, "conwayste::client"), LevelFilter::Debug);
+        assert_eq!(level_in(&levels, "conway"), LevelFilter::Info);
+        assert_eq!(level_in(&levels, "conway::rle"), LevelFilter::Info);
+        assert_eq!(level_in(&levels, "conway::universe::tests"), LevelFilter::Trace);
+        assert_eq!(level_in(&[], "conway"), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_rotate_keeps_the_newest_files() {
+        let dir = std::env::temp_dir().join(format!("conwayste-log-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for launch in 0..KEPT_LOG_FILES + 2 {
+            rotate(&dir).unwrap();
+            fs::write(log_file_path(&dir, 0), launch.to_string()).unwrap();
+        }
+        let newest = KEPT_LOG_FILES + 1;
+        for age in 0..KEPT_LOG_FILES {
+            let contents = fs::read_to_string(log_file_path(&dir, age)).unwrap();
+            assert_eq!(contents, (newest - age).to_string());
+        }
+        assert!(!log_file_path(&dir, KEPT_LOG_FILES).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
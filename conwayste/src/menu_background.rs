@@ -0,0 +1,106 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The faint Conway soup behind the menus: a small universe of random cells that only this client
+//! runs, stepped a few times a second and reseeded once it has had time to settle down. It is
+//! switched off with `menu_background` in the video settings.
+
+use std::time::{Duration, Instant};
+
+use conway::universe::{BigBang, CellState, Universe};
+use ggez::graphics::{DrawParam, Rect};
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+use rand::Rng;
+
+use crate::cell_batch::CellBatch;
+use crate::constants::colors::scheme;
+
+const WIDTH: usize = 128; // cells; a multiple of 64, as universes must be
+const HEIGHT: usize = 80; // cells
+const STEP_INTERVAL: Duration = Duration::from_millis(150);
+const RESEED_AFTER_GENS: usize = 500; // a soup has mostly settled into still lifes and blinkers by then
+const SEED_DENSITY: f64 = 0.3; // share of cells alive in a new soup
+
+pub struct MenuBackground {
+    universe:  Universe,
+    gens:      usize, // since it was seeded
+    last_step: Instant,
+    batch:     CellBatch,
+}
+
+impl MenuBackground {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        Ok(MenuBackground {
+            universe:  soup(),
+            gens:      0,
+            last_step: Instant::now(),
+            batch:     CellBatch::new(ctx)?,
+        })
+    }
+
+    /// Steps the soup if it's time, or starts a new one.
+    pub fn update(&mut self) {
+        if self.last_step.elapsed() < STEP_INTERVAL {
+            return;
+        }
+        self.last_step = Instant::now();
+        if self.gens >= RESEED_AFTER_GENS {
+            self.universe = soup();
+            self.gens = 0;
+        } else {
+            self.universe.next();
+            self.gens += 1;
+        }
+    }
+
+    /// Draws the soup stretched over the whole screen, which is `screen_w` by `screen_h`.
+    pub fn draw(&mut self, ctx: &mut Context, screen_w: f32, screen_h: f32) -> GameResult<()> {
+        let cell_size = (screen_w / WIDTH as f32).max(screen_h / HEIGHT as f32);
+        let color = scheme().menu_background;
+        let batch = &mut self.batch;
+        batch.clear();
+        self.universe.each_non_dead_full(None, &mut |col, row, state| {
+            if let CellState::Alive(_) = state {
+                let rect = Rect::new(
+                    col as f32 * cell_size,
+                    row as f32 * cell_size,
+                    cell_size - 1.0,
+                    cell_size - 1.0,
+                );
+                batch.add_rect(rect, color);
+            }
+        });
+        batch.draw(ctx, DrawParam::new().dest(Point2 { x: 0.0, y: 0.0 }))
+    }
+}
+
+/// A new universe with random cells.
+fn soup() -> Universe {
+    // unwrap OK because the size is fixed and valid
+    let mut universe = BigBang::new().width(WIDTH).height(HEIGHT).birth().unwrap();
+    let mut rng = rand::thread_rng();
+    for row in 0..HEIGHT {
+        for col in 0..WIDTH {
+            if rng.gen_bool(SEED_DENSITY) {
+                universe.set_unchecked(col, row, CellState::Alive(None));
+            }
+        }
+    }
+    universe
+}
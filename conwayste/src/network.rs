@@ -27,6 +27,8 @@ use futures as Fut;
 use netwayste::client::ClientNetState;
 use netwayste::net::NetwaysteEvent;
 
+use crate::config::Config;
+
 pub struct ConwaysteNetWorker {
     sender:   Fut::channel::mpsc::UnboundedSender<NetwaysteEvent>,
     receiver: Fut::channel::mpsc::Receiver<NetwaysteEvent>,
@@ -34,13 +36,14 @@ pub struct ConwaysteNetWorker {
 
 impl ConwaysteNetWorker {
     // TODO: This will likely be refactored after the networking architecture update soon coming
-    #[allow(unused)]
-    pub fn new() -> Self {
+    /// Starts the network layer talking to `server`, given as `host[:port]`. Must be called from
+    /// within a tokio runtime.
+    pub fn new(server: String) -> Self {
         let (netwayste_request_sender, netwayste_request_receiver) = Fut::channel::mpsc::unbounded::<NetwaysteEvent>();
         let (netwayste_response_sender, netwayste_response_receiver) = Fut::channel::mpsc::channel::<NetwaysteEvent>(5);
 
         tokio::spawn(async {
-            match ClientNetState::start_network(netwayste_response_sender, netwayste_request_receiver).await {
+            match ClientNetState::start_network(server, netwayste_response_sender, netwayste_request_receiver).await {
                 Ok(()) => {}
                 Err(e) => error!("Error during ClientNetState: {}", e),
             }
@@ -52,6 +55,12 @@ impl ConwaysteNetWorker {
         }
     }
 
+    /// Logs in to the server as the active profile.
+    pub fn connect(&mut self, config: &Config) {
+        let name = config.get().user.name.clone();
+        self.try_send(NetwaysteEvent::Connect(name, version!().to_owned()));
+    }
+
     pub fn try_send(&mut self, nw_event: NetwaysteEvent) {
         match self.sender.unbounded_send(nw_event) {
             Ok(_) => {}
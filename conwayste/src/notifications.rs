@@ -0,0 +1,87 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Desktop notifications of what happens in the game while its window isn't focused, so that a
+//! player who switched to something else while waiting knows to come back. Which happenings are
+//! notified of is set in the `[notifications]` section of the config.
+
+use std::thread;
+
+use notify_rust::Notification;
+
+use crate::config::NotificationSettings;
+
+/// Something the player may want to come back to the game for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notice {
+    Mentioned,     // someone said the player's name in chat
+    MatchStarting, // a game started in the player's room
+    Disconnected,  // the connection to the server was lost
+}
+
+impl Notice {
+    fn wanted(self, settings: &NotificationSettings) -> bool {
+        match self {
+            Notice::Mentioned => settings.mentioned,
+            Notice::MatchStarting => settings.match_starting,
+            Notice::Disconnected => settings.disconnected,
+        }
+    }
+}
+
+/// Shows `body` in a notification of `notice`, if the settings ask for those. It's shown from a
+/// thread of its own, since on some desktops that takes a while.
+pub fn notify(settings: &NotificationSettings, notice: Notice, body: String) {
+    if !notice.wanted(settings) {
+        return;
+    }
+    thread::spawn(move || {
+        if let Err(e) = Notification::new().appname("conwayste").summary("Conwayste").body(&body).show() {
+            warn!("Could not show a notification: {}", e);
+        }
+    });
+}
+
+/// Whether `message` says `name` as a word of its own, in any case, such as "hi @Name!".
+pub fn mentions(message: &str, name: &str) -> bool {
+    let (message, name) = (message.to_lowercase(), name.to_lowercase());
+    if name.is_empty() {
+        return false;
+    }
+    let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+    message.match_indices(&name).any(|(start, _)| {
+        let before = message[..start].chars().next_back();
+        let after = message[start + name.len()..].chars().next();
+        !before.map_or(false, is_word_char) && !after.map_or(false, is_word_char)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mentions_are_whole_names() {
+        assert!(mentions("JohnConway: are you there?", "johnconway"));
+        assert!(mentions("nice glider, @JohnConway!", "JohnConway"));
+        assert!(mentions("over to you, john conway", "John Conway"));
+        assert!(!mentions("JohnConways everywhere", "JohnConway"));
+        assert!(!mentions("not_JohnConway", "JohnConway"));
+        assert!(!mentions("anything", ""));
+    }
+}
@@ -0,0 +1,390 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The pattern editor: a small grid of its own, apart from the game's universe, where a pattern is
+//! drawn, tried out under a choice of rules, and saved as an RLE file to the patterns folder. The
+//! last pattern saved becomes the one placed by clicking in the game.
+//!
+//! The preview runs here rather than in a `Universe`, which only knows Conway's rules.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use conway::grids::{BitGrid, CharGrid};
+
+use crate::user_data::Folder;
+
+pub const EDITOR_WIDTH: usize = 48; // cells
+pub const EDITOR_HEIGHT: usize = 32; // cells
+
+/// The rules the Rule button goes through, in B/S notation. The first is Conway's.
+pub const RULES: &[&str] = &[
+    "B3/S23",        // Life
+    "B36/S23",       // HighLife
+    "B3678/S34678",  // Day & Night
+    "B2/S",          // Seeds
+    "B3/S012345678", // Life without Death
+];
+
+/// Which numbers of live neighbors bring a dead cell to life, and which keep a live one alive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    birth:    [bool; 9],
+    survival: [bool; 9],
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        RULES[0].parse().unwrap() // unwrap OK because the built-in rules parse
+    }
+}
+
+impl Rule {
+    pub fn next_state(&self, alive: bool, neighbors: usize) -> bool {
+        if alive {
+            self.survival[neighbors]
+        } else {
+            self.birth[neighbors]
+        }
+    }
+
+    /// The rule after this one in `RULES`, or the first if this one isn't listed.
+    pub fn next(&self) -> Rule {
+        let index = RULES
+            .iter()
+            .position(|rule| rule.parse::<Rule>().ok() == Some(*self))
+            .map_or(0, |i| (i + 1) % RULES.len());
+        RULES[index].parse().unwrap() // unwrap OK because the built-in rules parse
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Reads a rule like "B36/S23", in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_uppercase();
+        let mut parts = s.splitn(2, '/');
+        let (birth, survival) = match (parts.next(), parts.next()) {
+            (Some(birth), Some(survival)) if birth.starts_with('B') && survival.starts_with('S') => {
+                (&birth[1..], &survival[1..])
+            }
+            _ => return Err(format!("{:?} is not a rule like B3/S23", s)),
+        };
+        let counts = |digits: &str| -> Result<[bool; 9], String> {
+            let mut counts = [false; 9];
+            for ch in digits.chars() {
+                match ch.to_digit(10) {
+                    Some(n) if n <= 8 => counts[n as usize] = true,
+                    _ => return Err(format!("{:?} is not a number of neighbors in {:?}", ch, s)),
+                }
+            }
+            Ok(counts)
+        };
+        Ok(Rule {
+            birth:    counts(birth)?,
+            survival: counts(survival)?,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |counts: &[bool; 9]| -> String {
+            (0..9)
+                .filter(|&n| counts[n])
+                .map(|n| std::char::from_digit(n as u32, 10).unwrap())
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+/// What dragging over the grid does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Pencil,    // brings the cells dragged over to life
+    Eraser,    // kills them
+    Line,      // from where the drag started to where it ends
+    Rectangle, // the outline of the rectangle with the start and end of the drag at its corners
+}
+
+/// The pattern being edited.
+#[derive(Debug)]
+pub struct PatternEditor {
+    pub rule:           Rule,
+    pub tool:           Tool,
+    pub running:        bool,              // the preview steps on its own
+    pub save_requested: bool,              // set by the Save button; the client saves on its next update
+    cells:              Vec<bool>,         // by row, then column
+    generation:         usize,             // of the preview; 0 is the pattern as drawn
+    drawn:              Option<Vec<bool>>, // the pattern as drawn, while a preview is shown
+}
+
+impl PatternEditor {
+    pub fn new() -> Self {
+        PatternEditor {
+            rule:           Rule::default(),
+            tool:           Tool::Pencil,
+            running:        false,
+            save_requested: false,
+            cells:          vec![false; EDITOR_WIDTH * EDITOR_HEIGHT],
+            generation:     0,
+            drawn:          None,
+        }
+    }
+
+    pub fn is_alive(&self, col: usize, row: usize) -> bool {
+        self.cells[row * EDITOR_WIDTH + col]
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&alive| alive).count()
+    }
+
+    /// Uses the tool on the cells from `from` to `to`, both (column, row) and on the grid. Editing
+    /// while a preview is shown keeps what the preview has come to as the new pattern.
+    pub fn apply_tool(&mut self, from: (usize, usize), to: (usize, usize)) {
+        self.running = false;
+        self.drawn = None;
+        self.generation = 0;
+        let cells = match self.tool {
+            Tool::Rectangle => rectangle_cells(from, to),
+            _ => line_cells(from, to),
+        };
+        let alive = self.tool != Tool::Eraser;
+        for (col, row) in cells {
+            self.cells[row * EDITOR_WIDTH + col] = alive;
+        }
+    }
+
+    /// Advances the preview by one generation under the rule. Cells beyond the edges of the grid are
+    /// dead.
+    pub fn step(&mut self) {
+        if self.drawn.is_none() {
+            self.drawn = Some(self.cells.clone());
+        }
+        let mut next = vec![false; self.cells.len()];
+        for row in 0..EDITOR_HEIGHT {
+            for col in 0..EDITOR_WIDTH {
+                let mut neighbors = 0;
+                for neighbor_row in row.saturating_sub(1)..=(row + 1).min(EDITOR_HEIGHT - 1) {
+                    for neighbor_col in col.saturating_sub(1)..=(col + 1).min(EDITOR_WIDTH - 1) {
+                        if (neighbor_col, neighbor_row) != (col, row) && self.is_alive(neighbor_col, neighbor_row) {
+                            neighbors += 1;
+                        }
+                    }
+                }
+                next[row * EDITOR_WIDTH + col] = self.rule.next_state(self.is_alive(col, row), neighbors);
+            }
+        }
+        self.cells = next;
+        self.generation += 1;
+    }
+
+    /// Stops the preview and goes back to the pattern as drawn.
+    pub fn reset(&mut self) {
+        self.running = false;
+        if let Some(drawn) = self.drawn.take() {
+            self.cells = drawn;
+        }
+        self.generation = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.running = false;
+        self.drawn = None;
+        self.generation = 0;
+        for alive in self.cells.iter_mut() {
+            *alive = false;
+        }
+    }
+
+    /// The pattern as drawn, cut down to its live cells, as the contents of an RLE file called
+    /// `name`. None if there are no live cells.
+    pub fn to_rle(&self, name: &str) -> Option<String> {
+        let cells = self.drawn.as_ref().unwrap_or(&self.cells);
+        let live: Vec<(usize, usize)> = (0..EDITOR_HEIGHT)
+            .flat_map(|row| (0..EDITOR_WIDTH).map(move |col| (col, row)))
+            .filter(|&(col, row)| cells[row * EDITOR_WIDTH + col])
+            .collect();
+        let left = live.iter().map(|&(col, _)| col).min()?;
+        let right = live.iter().map(|&(col, _)| col).max()?;
+        let top = live.iter().map(|&(_, row)| row).min()?;
+        let bottom = live.iter().map(|&(_, row)| row).max()?;
+        let (width, height) = (right - left + 1, bottom - top + 1);
+
+        let mut grid = BitGrid::new((width + 63) / 64, height);
+        for (col, row) in live {
+            grid.write_at_position(col - left, row - top, 'o', None);
+        }
+        Some(format!(
+            "#N {}\nx = {}, y = {}, rule = {}\n{}\n",
+            name,
+            width,
+            height,
+            self.rule,
+            grid.to_pattern(None).0
+        ))
+    }
+}
+
+/// The cells on a line from `from` to `to`, including both.
+pub fn line_cells(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (from_col, from_row) = (from.0 as isize, from.1 as isize);
+    let (to_col, to_row) = (to.0 as isize, to.1 as isize);
+    let steps = (to_col - from_col).abs().max((to_row - from_row).abs());
+    if steps == 0 {
+        return vec![from];
+    }
+    (0..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            let col = from_col as f32 + (to_col - from_col) as f32 * t;
+            let row = from_row as f32 + (to_row - from_row) as f32 * t;
+            (col.round() as usize, row.round() as usize)
+        })
+        .collect()
+}
+
+/// The cells on the edges of the rectangle with `from` and `to` at opposite corners.
+pub fn rectangle_cells(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+    let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+    let mut cells = vec![];
+    for col in left..=right {
+        cells.push((col, top));
+        cells.push((col, bottom));
+    }
+    for row in top..=bottom {
+        cells.push((left, row));
+        cells.push((right, row));
+    }
+    cells
+}
+
+/// Saves `rle` to the patterns folder as a file named after `name`, returning its path. If the
+/// folder already has a file of that name, a number is added so that it isn't replaced.
+pub fn save_to_library(name: &str, rle: &str) -> io::Result<PathBuf> {
+    let file_name: String = name
+        .trim()
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() || ch == '-' { ch } else { '_' })
+        .collect();
+    if file_name.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the pattern has no name"));
+    }
+    let dir = Folder::Patterns.path();
+    fs::create_dir_all(&dir)?;
+    let path = unused_path(&dir, &file_name);
+    fs::write(&path, rle)?;
+    Ok(path)
+}
+
+/// The path in `dir` of "`file_name`.rle", or if there is already such a file, of the first of
+/// "`file_name`_2.rle", "`file_name`_3.rle" and so on that there isn't.
+fn unused_path(dir: &Path, file_name: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.rle", file_name));
+    let mut number = 2;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.rle", file_name, number));
+        number += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conway::rle::PatternFile;
+
+    #[test]
+    fn test_rules_read_and_write_the_same() {
+        for rule in RULES {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), *rule);
+        }
+        assert_eq!("b36/s23".parse::<Rule>().unwrap().to_string(), "B36/S23");
+        assert!("B9/S23".parse::<Rule>().is_err());
+        assert!("23/3".parse::<Rule>().is_err());
+        assert_eq!(Rule::default().next().to_string(), "B36/S23");
+        assert_eq!(RULES[RULES.len() - 1].parse::<Rule>().unwrap().next(), Rule::default());
+    }
+
+    #[test]
+    fn test_preview_steps_and_resets_to_the_pattern_as_drawn() {
+        let mut editor = PatternEditor::new();
+        editor.tool = Tool::Line;
+        editor.apply_tool((4, 5), (6, 5)); // a blinker
+        editor.step();
+        assert!(editor.is_alive(5, 4) && editor.is_alive(5, 6) && !editor.is_alive(4, 5));
+        assert_eq!(editor.generation(), 1);
+        editor.reset();
+        assert!(editor.is_alive(4, 5) && !editor.is_alive(5, 4));
+        assert_eq!(editor.generation(), 0);
+
+        // Under Seeds, every live cell dies
+        editor.rule = "B2/S".parse().unwrap();
+        editor.step();
+        assert!(!editor.is_alive(5, 5));
+    }
+
+    #[test]
+    fn test_to_rle_keeps_just_the_live_cells() {
+        let mut editor = PatternEditor::new();
+        assert_eq!(editor.to_rle("nothing"), None);
+        editor.apply_tool((11, 10), (11, 10));
+        editor.apply_tool((12, 11), (12, 11));
+        editor.tool = Tool::Line;
+        editor.apply_tool((10, 12), (12, 12)); // a glider
+        editor.step();
+
+        // Saved as drawn, not as the preview has come to
+        let rle = editor.to_rle("glider").unwrap();
+        assert!(rle.starts_with("#N glider\nx = 3, y = 3, rule = B3/S23\n"));
+        let pattern: PatternFile = rle.parse().unwrap();
+        assert_eq!(pattern.pattern.0, "bo$2bo$3o!");
+    }
+
+    #[test]
+    fn test_rectangle_cells_are_its_outline() {
+        let mut cells = rectangle_cells((3, 4), (1, 2));
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), 8);
+        assert!(!cells.contains(&(2, 3)));
+        assert_eq!(line_cells((0, 0), (4, 2)).len(), 5);
+    }
+
+    #[test]
+    fn test_unused_path_does_not_replace_a_file() {
+        let dir = std::env::temp_dir().join(format!("conwayste-pattern-editor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unused_path(&dir, "glider"), dir.join("glider.rle"));
+        fs::write(dir.join("glider.rle"), "").unwrap();
+        fs::write(dir.join("glider_2.rle"), "").unwrap();
+        assert_eq!(unused_path(&dir, "glider"), dir.join("glider_3.rle"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,339 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Importing patterns from the web into the pattern library. A URL to an RLE (`.rle`) or plaintext
+//! (`.cells`) file, such as one of LifeWiki's, is downloaded on a thread of its own, checked, and
+//! saved to the patterns folder as RLE. The URL of a LifeWiki page is taken to mean the RLE file
+//! LifeWiki keeps for the pattern on it, so
+//! `https://conwaylife.com/wiki/Gosper_glider_gun` downloads
+//! `https://conwaylife.com/patterns/gosperglidergun.rle`.
+
+use std::io::Read;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use conway::grids::{BitGrid, CharGrid};
+use conway::rle::PatternFile;
+use reqwest::Url;
+
+use crate::pattern_library::LibraryPattern;
+
+pub const MAX_IMPORT_LEN: u64 = 1 << 20; // bytes downloaded, at most
+const MAX_IMPORT_SIDE: usize = 4096; // cells, both wide and high
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const LIFEWIKI_HOSTS: &[&str] = &["conwaylife.com", "www.conwaylife.com"];
+
+/// A pattern that was downloaded and checked, ready for saving to the library.
+pub struct Imported {
+    pub pattern: LibraryPattern,
+    pub rle:     String, // the contents of the file to save, converted to RLE if it wasn't
+}
+
+/// What the download thread tells the main thread.
+enum Message {
+    Progress(u64, Option<u64>), // (bytes received, bytes in all if the server said)
+    Finished(Result<Imported, String>),
+}
+
+/// What to do with a file dropped on the game window, going by its name and size.
+#[derive(Debug, PartialEq)]
+pub enum DroppedFile {
+    Pattern,    // an .rle or .cells file, for `parse`
+    TooLarge,   // a pattern file larger than `MAX_IMPORT_LEN`
+    SavedRooms, // a server's snapshot of its rooms, which only the server can restore
+    Unknown,
+}
+
+/// A pattern being downloaded.
+pub struct Download {
+    url:      Url,
+    receiver: mpsc::Receiver<Message>,
+    received: u64,
+    total:    Option<u64>,
+}
+
+impl Download {
+    /// Starts downloading the pattern at `url`. Fails with a message for the player if it isn't an
+    /// http or https URL.
+    pub fn start(url: &str) -> Result<Self, String> {
+        let url = pattern_file_url(url)?;
+        let (sender, receiver) = mpsc::channel();
+        let thread_url = url.clone();
+        thread::spawn(move || {
+            let result = fetch(&thread_url, &sender).and_then(|text| parse(&file_stem(&thread_url), &text));
+            let _ = sender.send(Message::Finished(result)); // no one may be waiting anymore
+        });
+        Ok(Download {
+            url,
+            receiver,
+            received: 0,
+            total: None,
+        })
+    }
+
+    /// The URL being downloaded, after any LifeWiki page was turned into its pattern file.
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// (bytes received, bytes in all if known), for showing progress.
+    pub fn progress(&self) -> (u64, Option<u64>) {
+        (self.received, self.total)
+    }
+
+    /// Notes the progress since the last call, and returns the pattern, or why there isn't one,
+    /// once the download is over. Called every frame.
+    pub fn poll(&mut self) -> Option<Result<Imported, String>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Progress(received, total)) => {
+                    self.received = received;
+                    self.total = total;
+                }
+                Ok(Message::Finished(result)) => return Some(result),
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Some(Err("the download stopped unexpectedly".to_owned()))
+                }
+            }
+        }
+    }
+}
+
+/// Reads `text` as an http or https URL, turning a LifeWiki page into the URL of its RLE file.
+pub fn pattern_file_url(text: &str) -> Result<Url, String> {
+    let mut url = Url::parse(text.trim()).map_err(|e| format!("not a URL: {}", e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("only http and https URLs can be imported".to_owned());
+    }
+    let is_lifewiki = url.host_str().map_or(false, |host| LIFEWIKI_HOSTS.contains(&host));
+    let page = url.path().strip_prefix("/wiki/").map(|page| page.to_owned());
+    if let (true, Some(page)) = (is_lifewiki, page) {
+        // LifeWiki names the file after the page, lowercased with only the letters and digits kept
+        let file_name: String = page
+            .chars()
+            .filter(|ch| ch.is_ascii_alphanumeric())
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect();
+        if file_name.is_empty() {
+            return Err("no pattern on that LifeWiki page".to_owned());
+        }
+        url.set_path(&format!("/patterns/{}.rle", file_name));
+        url.set_query(None);
+        url.set_fragment(None);
+    }
+    Ok(url)
+}
+
+/// The last part of the URL's path without its extension, for naming a pattern whose file doesn't.
+fn file_stem(url: &Url) -> String {
+    let file_name = url.path_segments().and_then(|segments| segments.last()).unwrap_or("");
+    match file_name.rfind('.') {
+        Some(dot) if dot > 0 => file_name[..dot].to_owned(),
+        _ => file_name.to_owned(),
+    }
+}
+
+/// Downloads the file at `url` as text, telling `sender` how far along it is.
+fn fetch(url: &Url, sender: &mpsc::Sender<Message>) -> Result<String, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut response = client.get(url.clone()).send().map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("the server answered {}", response.status()));
+    }
+    let total = response.content_length();
+    if total.map_or(false, |total| total > MAX_IMPORT_LEN) {
+        return Err(format!("the file is larger than {} KiB", MAX_IMPORT_LEN / 1024));
+    }
+
+    let mut body = vec![];
+    let mut chunk = [0u8; 8192];
+    loop {
+        let len = response.read(&mut chunk).map_err(|e| e.to_string())?;
+        if len == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..len]);
+        if body.len() as u64 > MAX_IMPORT_LEN {
+            return Err(format!("the file is larger than {} KiB", MAX_IMPORT_LEN / 1024));
+        }
+        let _ = sender.send(Message::Progress(body.len() as u64, total));
+    }
+    String::from_utf8(body).map_err(|_| "the file is not a text file".to_owned())
+}
+
+/// Checks the contents of a pattern file, RLE or plaintext, and makes a library pattern of it.
+/// `file_name` names the pattern if the file doesn't.
+pub fn parse(file_name: &str, text: &str) -> Result<Imported, String> {
+    let rle = if is_plaintext(text) {
+        cells_to_rle(text)?
+    } else {
+        text.to_owned()
+    };
+
+    // The size is checked before `LibraryPattern::parse` runs the pattern to sort it
+    let pattern: PatternFile = rle.parse().map_err(|e| format!("not a pattern file: {}", e))?;
+    let (width, height) = (pattern.width(), pattern.height());
+    if width == 0 || height == 0 {
+        return Err("the pattern is empty".to_owned());
+    }
+    if width > MAX_IMPORT_SIDE || height > MAX_IMPORT_SIDE {
+        return Err(format!("the pattern is larger than {} cells across", MAX_IMPORT_SIDE));
+    }
+    let (cells_width, cells_height) = pattern
+        .pattern
+        .calc_size()
+        .map_err(|e| format!("not a pattern file: {}", e))?;
+    if cells_width > width || cells_height > height {
+        return Err("the pattern's cells don't fit in its size".to_owned());
+    }
+
+    let pattern = LibraryPattern::parse(file_name, &rle).map_err(|e| format!("not a pattern file: {}", e))?;
+    Ok(Imported { pattern, rle })
+}
+
+/// Sorts out a dropped file named `file_name` and `len` bytes long. The game has no saved games
+/// of its own; the only saves are the `rooms-*.json` snapshots a server keeps, and those are
+/// told apart so the player can be pointed to the server's `snapshot_dir` instead.
+pub fn check_dropped_file(file_name: &str, len: u64) -> DroppedFile {
+    let lowercase = file_name.to_lowercase();
+    let extension = match lowercase.rsplit_once('.') {
+        Some((_, extension)) => extension,
+        None => return DroppedFile::Unknown,
+    };
+    match extension {
+        "rle" | "cells" if len > MAX_IMPORT_LEN => DroppedFile::TooLarge,
+        "rle" | "cells" => DroppedFile::Pattern,
+        "json" if lowercase.starts_with("rooms-") => DroppedFile::SavedRooms,
+        _ => DroppedFile::Unknown,
+    }
+}
+
+/// Whether `text` is in the plaintext format, whose lines are `!` comments or rows of `.` and `O`,
+/// rather than RLE.
+pub fn is_plaintext(text: &str) -> bool {
+    match text.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => line.starts_with('!') || line.chars().all(|ch| ch == '.' || ch == 'O'),
+        None => false,
+    }
+}
+
+/// Converts a plaintext pattern to RLE. A `!Name:` line becomes the `#N` line, and the other
+/// comments become `#C` lines.
+pub fn cells_to_rle(text: &str) -> Result<String, String> {
+    let mut comment_lines = vec![];
+    let mut rows: Vec<&str> = vec![];
+    for line in text.lines() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => comment_lines.push(format!("#N {}", name.trim())),
+                None => comment_lines.push(format!("#C {}", comment.trim())),
+            }
+        } else {
+            rows.push(line);
+        }
+    }
+    while rows.last().map_or(false, |row| row.is_empty()) {
+        rows.pop();
+    }
+
+    let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+    let height = rows.len();
+    if width == 0 || height == 0 {
+        return Err("the pattern is empty".to_owned());
+    }
+    if width > MAX_IMPORT_SIDE || height > MAX_IMPORT_SIDE {
+        return Err(format!("the pattern is larger than {} cells across", MAX_IMPORT_SIDE));
+    }
+    let mut grid = BitGrid::new((width + 63) / 64, height);
+    for (row, line) in rows.iter().enumerate() {
+        for (col, ch) in line.chars().enumerate() {
+            match ch {
+                'O' | '*' => grid.write_at_position(col, row, 'o', None),
+                '.' => {}
+                _ => return Err(format!("unexpected {:?} in row {} of the pattern", ch, row + 1)),
+            }
+        }
+    }
+
+    let mut rle = String::new();
+    for line in comment_lines {
+        rle.push_str(&line);
+        rle.push('\n');
+    }
+    rle.push_str(&format!("x = {}, y = {}\n{}\n", width, height, grid.to_pattern(None).0));
+    Ok(rle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lifewiki_pages_become_their_pattern_files() {
+        let url = pattern_file_url("https://conwaylife.com/wiki/Gosper_glider_gun#Variants").unwrap();
+        assert_eq!(url.as_str(), "https://conwaylife.com/patterns/gosperglidergun.rle");
+        let url = pattern_file_url(" https://example.com/wiki/glider.cells ").unwrap();
+        assert_eq!(url.as_str(), "https://example.com/wiki/glider.cells");
+        assert_eq!(file_stem(&url), "glider");
+        assert!(pattern_file_url("file:///etc/passwd").is_err());
+        assert!(pattern_file_url("glider.rle").is_err());
+    }
+
+    #[test]
+    fn test_dropped_files_are_sorted_by_name_and_size() {
+        assert_eq!(check_dropped_file("glider.rle", 100), DroppedFile::Pattern);
+        assert_eq!(check_dropped_file("Glider.CELLS", MAX_IMPORT_LEN), DroppedFile::Pattern);
+        assert_eq!(check_dropped_file("huge.rle", MAX_IMPORT_LEN + 1), DroppedFile::TooLarge);
+        assert_eq!(check_dropped_file("rooms-1634428800.json", 100), DroppedFile::SavedRooms);
+        assert_eq!(check_dropped_file("settings.json", 100), DroppedFile::Unknown);
+        assert_eq!(check_dropped_file("rle", 100), DroppedFile::Unknown);
+        assert_eq!(check_dropped_file("glider.rle.txt", 100), DroppedFile::Unknown);
+    }
+
+    #[test]
+    fn test_plaintext_patterns_are_converted_to_rle() {
+        let text = "!Name: Glider\n!A small spaceship.\n.O.\n..O\nOOO\n";
+        assert!(is_plaintext(text));
+        let rle = cells_to_rle(text).unwrap();
+        assert_eq!(rle, "#N Glider\n#C A small spaceship.\nx = 3, y = 3\nbo$2bo$3o!\n");
+
+        let imported = parse("glider", text).unwrap();
+        assert_eq!(imported.pattern.name, "Glider");
+        assert_eq!(imported.pattern.pattern.width(), 3);
+        assert_eq!(imported.pattern.pattern.height(), 3);
+    }
+
+    #[test]
+    fn test_rle_is_checked_and_named_after_the_file_if_need_be() {
+        let imported = parse("r_pentomino", "x = 3, y = 3\nb2o$2o$bo!\n").unwrap();
+        assert_eq!(imported.pattern.name, "r pentomino");
+        assert!(!is_plaintext(&imported.rle));
+
+        assert!(parse("nothing", "<html>Not found</html>").is_err());
+        assert!(parse("too_wide", "x = 5000, y = 1\no!\n").is_err());
+        assert!(parse("too_small", "x = 1, y = 1\n3o!\n").is_err());
+        assert!(cells_to_rle("!Name: Blank\n\n").is_err());
+        assert!(cells_to_rle(".O.\n.X.\n").is_err());
+    }
+}
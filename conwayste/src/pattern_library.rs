@@ -0,0 +1,411 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The pattern library: the patterns in `conwayste/patterns/`, which are built into the client,
+//! and the ones in the patterns folder of the data directory, such as those saved from the pattern
+//! editor. Each is an RLE file, named by its `#N` line or else its file name.
+//!
+//! Patterns are sorted into categories by running them for a while: one that comes back the same
+//! is a still life, an oscillator or a spaceship. Others, like guns, can't be told apart from a
+//! pattern that just grows, so their file says what they are with a comment line:
+//!
+//! ```text
+//! #N Gosper glider gun
+//! #C Category: gun
+//! x = 36, y = 9, rule = B3/S23
+//! ```
+//!
+//! The thumbnails shown in the library are drawn once and kept in the thumbnails folder, named
+//! after a hash of the file.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use conway::rle::PatternFile;
+use conway::{ConwayError, ConwayResult};
+
+use crate::pattern_editor::Rule;
+use crate::user_data::Folder;
+
+pub const THUMBNAIL_SIZE: usize = 64; // pixels, both wide and high
+pub const MAX_PATTERN_SIDE: usize = 4096; // cells, both wide and high; larger files are skipped
+
+const CLASSIFY_GENERATIONS: usize = 60; // the longest period looked for
+const CLASSIFY_MAX_POPULATION: usize = 5000; // beyond this, a pattern is not looked at further
+
+const BUNDLED_PATTERNS: &[(&str, &str)] = &[
+    ("block", include_str!("../patterns/block.rle")),
+    ("beehive", include_str!("../patterns/beehive.rle")),
+    ("loaf", include_str!("../patterns/loaf.rle")),
+    ("boat", include_str!("../patterns/boat.rle")),
+    ("blinker", include_str!("../patterns/blinker.rle")),
+    ("toad", include_str!("../patterns/toad.rle")),
+    ("beacon", include_str!("../patterns/beacon.rle")),
+    ("pulsar", include_str!("../patterns/pulsar.rle")),
+    ("pentadecathlon", include_str!("../patterns/pentadecathlon.rle")),
+    ("glider", include_str!("../patterns/glider.rle")),
+    ("lwss", include_str!("../patterns/lwss.rle")),
+    ("mwss", include_str!("../patterns/mwss.rle")),
+    ("hwss", include_str!("../patterns/hwss.rle")),
+    ("gosper_glider_gun", include_str!("../patterns/gosper_glider_gun.rle")),
+    ("simkin_glider_gun", include_str!("../patterns/simkin_glider_gun.rle")),
+    ("r_pentomino", include_str!("../patterns/r_pentomino.rle")),
+    ("acorn", include_str!("../patterns/acorn.rle")),
+    ("diehard", include_str!("../patterns/diehard.rle")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+    Other,
+}
+
+impl Category {
+    /// The categories the library can be filtered by.
+    pub const FILTERS: [Category; 4] = [
+        Category::StillLife,
+        Category::Oscillator,
+        Category::Spaceship,
+        Category::Gun,
+    ];
+
+    /// The name used in pattern files and message IDs.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::StillLife => "still-life",
+            Category::Oscillator => "oscillator",
+            Category::Spaceship => "spaceship",
+            Category::Gun => "gun",
+            Category::Other => "other",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Category> {
+        let name = name.trim().to_lowercase().replace(' ', "-");
+        [
+            Category::StillLife,
+            Category::Oscillator,
+            Category::Spaceship,
+            Category::Gun,
+            Category::Other,
+        ]
+        .iter()
+        .cloned()
+        .find(|category| category.name() == name)
+    }
+}
+
+/// A pattern in the library.
+#[derive(Debug, Clone)]
+pub struct LibraryPattern {
+    pub name:     String,
+    pub category: Category,
+    pub pattern:  PatternFile,
+    pub path:     Option<PathBuf>, // the file it was read from; None for the built-in patterns
+    hash:         u64,             // of the file's contents, for naming its thumbnail
+}
+
+impl LibraryPattern {
+    /// Reads the contents of an RLE file. `file_name` is the name if the file doesn't give one.
+    /// Patterns more than `MAX_PATTERN_SIDE` cells across are turned away before being run.
+    pub fn parse(file_name: &str, contents: &str) -> ConwayResult<Self> {
+        let pattern: PatternFile = contents.parse()?;
+        if pattern.width() > MAX_PATTERN_SIDE || pattern.height() > MAX_PATTERN_SIDE {
+            return Err(ConwayError::InvalidData {
+                reason: format!("the pattern is larger than {} cells across", MAX_PATTERN_SIDE),
+            });
+        }
+        let name = pattern
+            .comment_lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("#N"))
+            .map(|name| name.trim().to_owned())
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| file_name.replace('_', " "));
+        let category = pattern
+            .comment_lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("#C"))
+            .filter_map(|text| {
+                let mut parts = text.splitn(2, ':');
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) if key.trim().eq_ignore_ascii_case("category") => {
+                        Category::from_name(value)
+                    }
+                    _ => None,
+                }
+            })
+            .next();
+        let category = match category {
+            Some(category) => category,
+            None => classify(&pattern)?,
+        };
+        Ok(LibraryPattern {
+            name,
+            category,
+            pattern,
+            path: None,
+            hash: fnv1a(contents.as_bytes()),
+        })
+    }
+
+    /// Whether the pattern is in `category`, if one is given, and its name has `search` in it,
+    /// ignoring case.
+    pub fn matches(&self, search: &str, category: Option<Category>) -> bool {
+        category.map_or(true, |category| self.category == category)
+            && self.name.to_lowercase().contains(&search.trim().to_lowercase())
+    }
+
+    /// Where the thumbnail is kept once it has been drawn.
+    pub fn thumbnail_path(&self) -> PathBuf {
+        Folder::Thumbnails
+            .path()
+            .join(format!("{:016x}-{}.png", self.hash, THUMBNAIL_SIZE))
+    }
+
+    /// Draws the thumbnail: `THUMBNAIL_SIZE` pixels square, as RGBA, with the live cells white and
+    /// the rest transparent. The pattern is centered, and shrunk to fit if it's larger than that.
+    pub fn thumbnail(&self) -> ConwayResult<Vec<u8>> {
+        let (width, height) = (self.pattern.width(), self.pattern.height());
+        let side = width.max(height).max(1);
+        let (left, top) = ((side - width) / 2, (side - height) / 2);
+        let mut alive = vec![false; side * side];
+        self.pattern
+            .to_new_bit_grid()?
+            .each_set(|col, row| alive[(top + row) * side + left + col] = true);
+
+        let mut pixels = vec![0u8; THUMBNAIL_SIZE * THUMBNAIL_SIZE * 4];
+        // the cells a pixel shows, of which any one alive lights it up
+        let cells = |pixel: usize| {
+            let first = pixel * side / THUMBNAIL_SIZE;
+            let end = ((pixel + 1) * side / THUMBNAIL_SIZE).max(first + 1);
+            first..end
+        };
+        for y in 0..THUMBNAIL_SIZE {
+            for x in 0..THUMBNAIL_SIZE {
+                let lit = cells(y).any(|row| cells(x).any(|col| alive[row * side + col]));
+                if lit {
+                    let i = (y * THUMBNAIL_SIZE + x) * 4;
+                    pixels[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+                }
+            }
+        }
+        Ok(pixels)
+    }
+}
+
+/// Which category a pattern is in, from how it runs for up to `CLASSIFY_GENERATIONS` generations
+/// under the rule in its header line, or Conway's if it has none.
+pub fn classify(pattern: &PatternFile) -> ConwayResult<Category> {
+    let rule = pattern
+        .header_line
+        .rule
+        .as_ref()
+        .and_then(|rule| rule.parse::<Rule>().ok())
+        .unwrap_or_default();
+    let mut cells = HashSet::new();
+    pattern.to_new_bit_grid()?.each_set(|col, row| {
+        cells.insert((col as isize, row as isize));
+    });
+    let (start_shape, start_corner) = shape(&cells);
+    if start_shape.is_empty() {
+        return Ok(Category::Other);
+    }
+
+    for generation in 1..=CLASSIFY_GENERATIONS {
+        cells = step(&cells, &rule);
+        if cells.is_empty() || cells.len() > CLASSIFY_MAX_POPULATION {
+            break;
+        }
+        let (now_shape, now_corner) = shape(&cells);
+        if now_shape == start_shape {
+            return Ok(if now_corner != start_corner {
+                Category::Spaceship
+            } else if generation == 1 {
+                Category::StillLife
+            } else {
+                Category::Oscillator
+            });
+        }
+    }
+    Ok(Category::Other)
+}
+
+/// The live cells relative to the top left corner of their bounding box, in order, along with that
+/// corner.
+fn shape(cells: &HashSet<(isize, isize)>) -> (Vec<(isize, isize)>, (isize, isize)) {
+    let left = cells.iter().map(|&(col, _)| col).min().unwrap_or(0);
+    let top = cells.iter().map(|&(_, row)| row).min().unwrap_or(0);
+    let mut shape: Vec<(isize, isize)> = cells.iter().map(|&(col, row)| (col - left, row - top)).collect();
+    shape.sort();
+    (shape, (left, top))
+}
+
+/// The next generation of `cells`, on a board without edges.
+fn step(cells: &HashSet<(isize, isize)>, rule: &Rule) -> HashSet<(isize, isize)> {
+    let mut candidates = HashSet::new();
+    for &(col, row) in cells {
+        for neighbor_row in row - 1..=row + 1 {
+            for neighbor_col in col - 1..=col + 1 {
+                candidates.insert((neighbor_col, neighbor_row));
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .filter(|&(col, row)| {
+            let mut neighbors = 0;
+            for neighbor_row in row - 1..=row + 1 {
+                for neighbor_col in col - 1..=col + 1 {
+                    if (neighbor_col, neighbor_row) != (col, row) && cells.contains(&(neighbor_col, neighbor_row)) {
+                        neighbors += 1;
+                    }
+                }
+            }
+            rule.next_state(cells.contains(&(col, row)), neighbors)
+        })
+        .collect()
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hashers is the same in every build.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Every pattern: the built-in ones first, then those in the patterns folder by file name. Files
+/// that can't be read are skipped with a warning.
+pub fn load_all() -> Vec<LibraryPattern> {
+    let mut patterns: Vec<LibraryPattern> = BUNDLED_PATTERNS
+        .iter()
+        .map(|(file_name, text)| LibraryPattern::parse(file_name, text).expect("built-in pattern does not parse"))
+        .collect();
+    patterns.extend(load_dir(&Folder::Patterns.path()));
+    patterns
+}
+
+fn load_dir(dir: &Path) -> Vec<LibraryPattern> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "rle"))
+            .collect(),
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Could not list the patterns in {:?}: {}", dir, e);
+            }
+            return vec![];
+        }
+    };
+    paths.sort();
+    let mut patterns = vec![];
+    for path in paths {
+        let file_name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| LibraryPattern::parse(&file_name, &text).map_err(|e| e.to_string()));
+        match result {
+            Ok(mut pattern) => {
+                pattern.path = Some(path);
+                patterns.push(pattern);
+            }
+            Err(e) => warn!("Skipping the pattern in {:?}: {}", path, e),
+        }
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bundled(file_name: &str) -> LibraryPattern {
+        let (_, text) = BUNDLED_PATTERNS.iter().find(|(name, _)| *name == file_name).unwrap();
+        LibraryPattern::parse(file_name, text).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_patterns_are_sorted_by_running_them() {
+        let expected = [
+            ("block", Category::StillLife),
+            ("boat", Category::StillLife),
+            ("blinker", Category::Oscillator),
+            ("pulsar", Category::Oscillator),
+            ("pentadecathlon", Category::Oscillator),
+            ("glider", Category::Spaceship),
+            ("hwss", Category::Spaceship),
+            ("gosper_glider_gun", Category::Gun), // from its comment
+            ("r_pentomino", Category::Other),
+        ];
+        for &(file_name, category) in expected.iter() {
+            assert_eq!(bundled(file_name).category, category, "{}", file_name);
+        }
+        for (file_name, text) in BUNDLED_PATTERNS {
+            assert!(LibraryPattern::parse(file_name, text).is_ok(), "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn test_name_comes_from_the_file_or_its_name() {
+        assert_eq!(bundled("lwss").name, "Lightweight spaceship");
+        let pattern = LibraryPattern::parse("my_glider", "x = 3, y = 3\nbo$2bo$3o!\n").unwrap();
+        assert_eq!(pattern.name, "my glider");
+        assert_eq!(pattern.category, Category::Spaceship);
+    }
+
+    #[test]
+    fn test_matches_searches_names_within_a_category() {
+        let glider = bundled("glider");
+        assert!(glider.matches("GLID", None));
+        assert!(glider.matches("", Some(Category::Spaceship)));
+        assert!(!glider.matches("glider", Some(Category::Gun)));
+        assert!(bundled("simkin_glider_gun").matches(" glider ", Some(Category::Gun)));
+    }
+
+    #[test]
+    fn test_thumbnail_centers_the_pattern() {
+        let pixels = bundled("blinker").thumbnail().unwrap();
+        let lit = |x: usize, y: usize| pixels[(y * THUMBNAIL_SIZE + x) * 4 + 3] == 255;
+        // three cells across, the middle row of three
+        assert!(lit(0, 32) && lit(63, 32));
+        assert!(!lit(32, 0) && !lit(32, 63));
+
+        // wider than the thumbnail, so shrunk to fit
+        let pattern = LibraryPattern::parse("long_line", "x = 200, y = 1\n200o!\n").unwrap();
+        let pixels = pattern.thumbnail().unwrap();
+        let lit = |x: usize, y: usize| pixels[(y * THUMBNAIL_SIZE + x) * 4 + 3] == 255;
+        let rows: Vec<usize> = (0..THUMBNAIL_SIZE).filter(|&y| lit(0, y)).collect();
+        assert_eq!(rows.len(), 1);
+        assert!((0..THUMBNAIL_SIZE).all(|x| lit(x, rows[0])));
+    }
+
+    #[test]
+    fn test_huge_patterns_are_turned_away() {
+        let side = MAX_PATTERN_SIDE + 1;
+        let contents = format!("x = {}, y = 1\n{}o!\n", side, side);
+        assert!(LibraryPattern::parse("huge", &contents).is_err());
+        let contents = format!("x = 1, y = {}\n{}$o!\n", side, side - 1);
+        assert!(LibraryPattern::parse("tall", &contents).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Client-side prediction of cell changes in networked games. A cell the player toggles would
+//! otherwise only show up a round trip later, in a universe diff from the server, so the change is
+//! made to the client's universe right away and kept as pending. When a diff arrives, the pending
+//! changes are taken back out so the diff lands on the server's own state, then made again unless
+//! the diff already accounts for them. A change the server never acknowledges is dropped after a
+//! while, which rolls it back.
+
+use std::collections::BTreeMap;
+
+use conway::rle::Pattern;
+use conway::universe::{BigBang, CellState, GenStateDiff, PlayerBuilder, Region, Universe};
+use conway::{ConwayError, ConwayResult};
+use netwayste::net::{GameOptions, GenStateDiffPart};
+
+/// How many generations past the one a change was expected in to wait for the server to
+/// acknowledge it before rolling it back.
+const UNACKNOWLEDGED_TIMEOUT_GENS: u64 = 30;
+
+#[derive(Debug)]
+struct PendingChange {
+    col:          usize,
+    row:          usize,
+    alive:        bool,
+    generation:   u64,  // first generation the server shows it in; only a guess until acknowledged
+    acknowledged: bool, // the server has answered with the generation
+    prior:        Option<CellState>, // state before the change was made locally; None if not made
+}
+
+pub struct Prediction {
+    uni:      Universe,
+    pending:  BTreeMap<u64, PendingChange>, // by sequence, so they are redone in the order made
+    next_seq: u64,
+}
+
+impl Prediction {
+    /// Sets up a universe for a game that the server just started with `options`.
+    pub fn new(options: &GameOptions) -> ConwayResult<Self> {
+        let players = options
+            .player_writable()
+            .iter()
+            .map(|region| PlayerBuilder::new(Region::from(region)))
+            .collect();
+        let mut uni = BigBang::new()
+            .width(options.width() as usize)
+            .height(options.height() as usize)
+            .server_mode(true) // the server decides what is hidden by fog
+            .history(options.history() as usize)
+            .fog_radius(options.fog_radius() as usize)
+            .add_players(players)
+            .birth()?;
+        options.add_map(&mut uni)?;
+        Ok(Prediction {
+            uni,
+            pending: BTreeMap::new(),
+            next_seq: 0,
+        })
+    }
+
+    /// The universe as the player should see it: the server's latest, plus pending changes.
+    pub fn universe(&self) -> &Universe {
+        &self.uni
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Toggles a cell right away, and returns whether it is now alive, so that the server can be
+    /// asked to do the same. None if the cell can't be toggled because it is outside the universe,
+    /// a wall, or hidden by fog.
+    pub fn toggle(&mut self, col: usize, row: usize) -> Option<bool> {
+        if !self.uni.region().contains(col as isize, row as isize) {
+            return None;
+        }
+        let alive = match self.uni.cell_state(col, row) {
+            CellState::Dead => true,
+            CellState::Alive(_) => false,
+            CellState::Wall | CellState::Fog => return None,
+        };
+        let mut change = PendingChange {
+            col,
+            row,
+            alive,
+            generation: self.uni.latest_gen() as u64 + 1,
+            acknowledged: false,
+            prior: None,
+        };
+        make(&mut self.uni, &mut change);
+        self.pending.insert(self.next_seq, change);
+        self.next_seq += 1;
+        Some(alive)
+    }
+
+    /// The server has accepted a change to this cell, which it will first show in `generation`.
+    pub fn acknowledge(&mut self, col: usize, row: usize, generation: u64) {
+        let opt_change = self
+            .pending
+            .values_mut()
+            .find(|change| !change.acknowledged && change.col == col && change.row == row);
+        match opt_change {
+            Some(change) => {
+                change.generation = generation;
+                change.acknowledged = true;
+            }
+            None => debug!(
+                "Server acknowledged a change to ({}, {}) that is no longer pending",
+                col, row
+            ),
+        }
+    }
+
+    /// Applies a universe diff from the server. Pending changes from before the diff's generation
+    /// are settled, since the diff shows what really became of them; the rest are made again on
+    /// top of it. Returns the new generation, or None if the diff doesn't follow on from ours.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the diff is invalid, in which case the universe may be partly updated.
+    pub fn apply_server_diff(&mut self, diff_part: &GenStateDiffPart) -> ConwayResult<Option<usize>> {
+        if diff_part.gen0 >= diff_part.gen1 {
+            return Err(ConwayError::InvalidData {
+                reason: format!("diff from generation {} to {}", diff_part.gen0, diff_part.gen1),
+            });
+        }
+        let diff = GenStateDiff {
+            gen0:    diff_part.gen0 as usize,
+            gen1:    diff_part.gen1 as usize,
+            pattern: Pattern(diff_part.pattern_part.clone()),
+        };
+
+        for change in self.pending.values_mut().rev() {
+            unmake(&mut self.uni, change);
+        }
+        let result = self.uni.apply(&diff, None);
+        if let Ok(Some(new_gen)) = result {
+            let new_gen = new_gen as u64;
+            self.pending.retain(|_, change| {
+                if change.acknowledged {
+                    change.generation > new_gen
+                } else if change.generation + UNACKNOWLEDGED_TIMEOUT_GENS <= new_gen {
+                    debug!(
+                        "Rolling back change to ({}, {}); the server never acknowledged it",
+                        change.col, change.row
+                    );
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        for change in self.pending.values_mut() {
+            make(&mut self.uni, change);
+        }
+        result
+    }
+}
+
+/// Makes a pending change to the latest generation, remembering what it replaced. Walls and fog
+/// are left alone, since the server won't change them either.
+fn make(uni: &mut Universe, change: &mut PendingChange) {
+    let prior = uni.cell_state(change.col, change.row);
+    change.prior = match prior {
+        CellState::Wall | CellState::Fog => None,
+        _ => Some(prior),
+    };
+    if change.prior.is_some() {
+        let state = if change.alive {
+            CellState::Alive(None)
+        } else {
+            CellState::Dead
+        };
+        uni.set_unchecked(change.col, change.row, state);
+    }
+}
+
+/// Undoes `make`. Changes must be undone in the reverse of the order they were made.
+fn unmake(uni: &mut Universe, change: &mut PendingChange) {
+    if let Some(prior) = change.prior.take() {
+        uni.set_unchecked(change.col, change.row, prior);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn full_diff(uni: &Universe) -> GenStateDiffPart {
+        let diff = uni.diff(0, uni.latest_gen(), None).unwrap();
+        GenStateDiffPart::new(0, 1, diff.gen0 as u32, diff.gen1 as u32, diff.pattern.0)
+    }
+
+    fn server_universe(options: &GameOptions) -> Universe {
+        Prediction::new(options).unwrap().uni
+    }
+
+    #[test]
+    fn test_toggle_shows_before_server_diff() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut prediction = Prediction::new(&options).unwrap();
+        let mut server_uni = server_universe(&options);
+        server_uni.next();
+        assert_eq!(prediction.apply_server_diff(&full_diff(&server_uni)), Ok(Some(2)));
+
+        assert_eq!(prediction.toggle(10, 10), Some(true));
+        assert_eq!(prediction.universe().cell_state(10, 10), CellState::Alive(None));
+
+        // A diff from before the server made the change doesn't undo it
+        server_uni.next();
+        prediction.acknowledge(10, 10, 4);
+        assert_eq!(prediction.apply_server_diff(&full_diff(&server_uni)), Ok(Some(3)));
+        assert_eq!(prediction.universe().cell_state(10, 10), CellState::Alive(None));
+        assert_eq!(prediction.pending_count(), 1);
+
+        // Once the server's universe has it, it is settled
+        server_uni.next();
+        server_uni.set(10, 10, CellState::Alive(Some(0)), 0);
+        assert_eq!(prediction.apply_server_diff(&full_diff(&server_uni)), Ok(Some(4)));
+        assert_eq!(prediction.universe().cell_state(10, 10), CellState::Alive(Some(0)));
+        assert_eq!(prediction.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_misprediction_is_rolled_back() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut prediction = Prediction::new(&options).unwrap();
+        let mut server_uni = server_universe(&options);
+
+        assert_eq!(prediction.toggle(10, 10), Some(true));
+        assert_eq!(prediction.toggle(11, 10), Some(true));
+        prediction.acknowledge(11, 10, 2);
+
+        // The server went ahead without either cell: the acknowledged one is settled as soon as
+        // its generation arrives, and the other is given up on after a while
+        server_uni.next();
+        prediction.apply_server_diff(&full_diff(&server_uni)).unwrap();
+        assert_eq!(prediction.universe().cell_state(11, 10), CellState::Dead);
+        assert_eq!(prediction.universe().cell_state(10, 10), CellState::Alive(None));
+        while server_uni.latest_gen() < 2 + UNACKNOWLEDGED_TIMEOUT_GENS as usize {
+            server_uni.next();
+        }
+        prediction.apply_server_diff(&full_diff(&server_uni)).unwrap();
+        assert_eq!(prediction.universe().cell_state(10, 10), CellState::Dead);
+        assert_eq!(prediction.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_toggling_twice_restores_the_cell() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut prediction = Prediction::new(&options).unwrap();
+        let mut server_uni = server_universe(&options);
+        server_uni.set(20, 10, CellState::Alive(Some(0)), 0);
+        server_uni.next();
+        server_uni.set(20, 10, CellState::Alive(Some(0)), 0);
+        prediction.apply_server_diff(&full_diff(&server_uni)).unwrap();
+
+        assert_eq!(prediction.toggle(20, 10), Some(false));
+        assert_eq!(prediction.toggle(20, 10), Some(true));
+        server_uni.next();
+        server_uni.set(20, 10, CellState::Alive(Some(0)), 0);
+        prediction.apply_server_diff(&full_diff(&server_uni)).unwrap();
+        for _ in 0..UNACKNOWLEDGED_TIMEOUT_GENS {
+            server_uni.next();
+            server_uni.set(20, 10, CellState::Alive(Some(0)), 0);
+        }
+        prediction.apply_server_diff(&full_diff(&server_uni)).unwrap();
+        assert_eq!(prediction.universe().cell_state(20, 10), CellState::Alive(Some(0)));
+    }
+
+    #[test]
+    fn test_bad_generations_are_an_error() {
+        let options = GameOptions::free_for_all(64, 32, 16, 6, 2);
+        let mut prediction = Prediction::new(&options).unwrap();
+        let diff = GenStateDiffPart::new(0, 1, 0, 0, "!".to_owned());
+        assert!(prediction.apply_server_diff(&diff).is_err());
+    }
+}
@@ -0,0 +1,531 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Single-player puzzles: a board to start from, a limit on the cells the player may place on it,
+//! and a population to reach within some number of generations. The puzzles in
+//! `conwayste/puzzles/` are built into the client, and more can be added as TOML files in the
+//! puzzles folder of the data directory. A puzzle file looks like this:
+//!
+//! ```toml
+//! title = "Spark"
+//! description = "Three cells can become four."
+//! size = [16, 12]     # columns and rows of the board, which is where cells may be placed
+//! max_cells = 3       # cells the player may place
+//! board = """
+//! ....W
+//! .oo.W
+//! """                 # optional starting cells: `o` for a live cell, `W` for a wall, `.` for none
+//!
+//! [goal]
+//! generations = 5     # the population must be reached within this many generations
+//! min_population = 4  # live cells, counting the whole universe; `max_population` can be set too
+//! ```
+//!
+//! Solutions are checked by the client as the universe runs, so the generations are counted from
+//! the one the puzzle was started in. Which puzzles each profile has solved is kept in the puzzles
+//! folder, with the fewest cells each was solved with.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+
+use conway::universe::{BigBang, CellState, PlayerBuilder, Region, Universe};
+use conway::ConwayResult;
+
+use crate::constants::{
+    CURRENT_PLAYER_ID, FOG_RADIUS, HISTORY_SIZE, UNIVERSE_HEIGHT_IN_CELLS, UNIVERSE_WIDTH_IN_CELLS,
+};
+use crate::user_data::Folder;
+
+const BUNDLED_PUZZLES: &[(&str, &str)] = &[
+    ("spark", include_str!("../puzzles/spark.toml")),
+    ("bloom", include_str!("../puzzles/bloom.toml")),
+    ("clean_sweep", include_str!("../puzzles/clean_sweep.toml")),
+    ("boxed_in", include_str!("../puzzles/boxed_in.toml")),
+    ("second_wind", include_str!("../puzzles/second_wind.toml")),
+];
+
+const SOLVED_FILE_NAME: &str = "solved.json";
+
+/// A puzzle as read from its file.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Puzzle {
+    #[serde(skip)]
+    pub id:          String, // the file name, without the extension
+    pub title:       String,
+    pub description: String,
+    pub size:        (usize, usize),
+    pub max_cells:   usize,
+    #[serde(default)]
+    pub board:       String,
+    pub goal:        Goal,
+}
+
+/// What makes a puzzle solved.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Goal {
+    pub generations:    usize,
+    pub min_population: Option<usize>,
+    pub max_population: Option<usize>,
+}
+
+impl Goal {
+    fn is_met(&self, population: usize) -> bool {
+        self.min_population.map_or(true, |min| population >= min)
+            && self.max_population.map_or(true, |max| population <= max)
+    }
+}
+
+impl Puzzle {
+    /// Reads the puzzle with the file name `id` from `text`, checking that it can be played.
+    pub fn parse(id: &str, text: &str) -> Result<Puzzle, Box<dyn Error>> {
+        let mut puzzle: Puzzle = toml::from_str(text)?;
+        puzzle.id = id.to_owned();
+
+        let (width, height) = puzzle.size;
+        if width == 0 || height == 0 || width > UNIVERSE_WIDTH_IN_CELLS || height > UNIVERSE_HEIGHT_IN_CELLS {
+            return Err(format!("a board of {}x{} does not fit in the universe", width, height).into());
+        }
+        if puzzle.goal.generations == 0 {
+            return Err("the goal must allow at least one generation".into());
+        }
+        if puzzle.goal.min_population.is_none() && puzzle.goal.max_population.is_none() {
+            return Err("the goal needs a min_population, a max_population, or both".into());
+        }
+        for (row, line) in puzzle.board_lines().enumerate() {
+            if row >= height || line.chars().count() > width {
+                return Err(format!("row {} of the board is outside its size", row + 1).into());
+            }
+            if let Some(ch) = line.chars().find(|ch| !".oW".contains(*ch)) {
+                return Err(format!("row {} of the board has {:?}, not one of '.', 'o' or 'W'", row + 1, ch).into());
+            }
+        }
+        Ok(puzzle)
+    }
+
+    fn board_lines(&self) -> impl Iterator<Item = &str> {
+        self.board.lines().map(|line| line.trim())
+    }
+
+    /// Where the board is in the universe: in the middle of it.
+    pub fn region(&self) -> Region {
+        let (width, height) = self.size;
+        Region::new(
+            ((UNIVERSE_WIDTH_IN_CELLS - width) / 2) as isize,
+            ((UNIVERSE_HEIGHT_IN_CELLS - height) / 2) as isize,
+            width,
+            height,
+        )
+    }
+
+    /// A universe set up for the puzzle, in which the current player may only place cells on the
+    /// board. The other players, like the offline opponent, only get a single cell in the corner.
+    pub fn universe(&self) -> ConwayResult<Universe> {
+        let region = self.region();
+        let players = (0..=CURRENT_PLAYER_ID)
+            .map(|player_id| {
+                if player_id == CURRENT_PLAYER_ID {
+                    PlayerBuilder::new(region)
+                } else {
+                    PlayerBuilder::new(Region::new(0, 0, 1, 1)) // regions can't be empty
+                }
+            })
+            .collect();
+        let mut uni = BigBang::new()
+            .width(UNIVERSE_WIDTH_IN_CELLS)
+            .height(UNIVERSE_HEIGHT_IN_CELLS)
+            .server_mode(true)
+            .history(HISTORY_SIZE)
+            .fog_radius(FOG_RADIUS)
+            .add_players(players)
+            .birth()?;
+        for (row, line) in self.board_lines().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                let state = match ch {
+                    'o' => CellState::Alive(None),
+                    'W' => CellState::Wall,
+                    _ => continue,
+                };
+                let (col, row) = (region.left() as usize + col, region.top() as usize + row);
+                uni.set_unchecked(col, row, state);
+            }
+        }
+        Ok(uni)
+    }
+}
+
+/// Every puzzle: the built-in ones first, then those in the puzzles folder by file name. Files
+/// that can't be read are skipped with a warning.
+pub fn load_all() -> Vec<Puzzle> {
+    let mut puzzles: Vec<Puzzle> = BUNDLED_PUZZLES
+        .iter()
+        .map(|(id, text)| Puzzle::parse(id, text).expect("built-in puzzle does not parse"))
+        .collect();
+    puzzles.extend(load_dir(&Folder::Puzzles.path()));
+    puzzles
+}
+
+fn load_dir(dir: &Path) -> Vec<Puzzle> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+            .collect(),
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Could not list the puzzles in {:?}: {}", dir, e);
+            }
+            return vec![];
+        }
+    };
+    paths.sort();
+    let mut puzzles = vec![];
+    for path in paths {
+        let id = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.into())
+            .and_then(|text| Puzzle::parse(&id, &text));
+        match result {
+            Ok(puzzle) => puzzles.push(puzzle),
+            Err(e) => warn!("Skipping the puzzle in {:?}: {}", path, e),
+        }
+    }
+    puzzles
+}
+
+/// The index of the puzzle of the day among `count` puzzles, the same for everyone on `date`.
+pub fn daily(count: usize, date: NaiveDate) -> Option<usize> {
+    if count == 0 {
+        None
+    } else {
+        Some(date.num_days_from_ce() as usize % count)
+    }
+}
+
+/// How a puzzle was solved.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct Solution {
+    pub cells_placed: usize,
+    pub generations:  usize, // until the goal was met
+}
+
+/// Why an attempt at a puzzle failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    TooManyCells,
+    OutOfGenerations,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Playing,
+    Solved(Solution),
+    Failed(Failure),
+}
+
+/// A puzzle being played.
+pub struct Attempt {
+    pub puzzle:       usize, // index into the puzzles
+    pub status:       Status,
+    pub generations:  usize, // since the puzzle was started
+    pub population:   usize,
+    pub cells_placed: usize,
+    start_gen:        usize,
+    expected:         Universe, // the board as it would be without the cells placed since the last check
+}
+
+impl Attempt {
+    /// An attempt at puzzle number `index`, which is `puzzle`, starting from its board.
+    pub fn new(index: usize, puzzle: &Puzzle) -> ConwayResult<Self> {
+        let expected = puzzle.universe()?;
+        Ok(Attempt {
+            puzzle: index,
+            status: Status::Playing,
+            generations: 0,
+            population: population(&expected),
+            cells_placed: 0,
+            start_gen: expected.latest_gen(),
+            expected,
+        })
+    }
+
+    /// Checks `uni`, which started as the board of `puzzle`, against the puzzle's goal. This must
+    /// be done after every generation, and after cells are placed. Returns the solution if the
+    /// puzzle has just been solved. Once it is solved or failed, the status no longer changes.
+    pub fn check(&mut self, puzzle: &Puzzle, uni: &Universe) -> Option<Solution> {
+        if self.status != Status::Playing {
+            return None;
+        }
+        self.generations = uni.latest_gen().saturating_sub(self.start_gen);
+        self.population = population(uni);
+        self.count_placed(puzzle, uni);
+
+        if self.cells_placed > puzzle.max_cells {
+            self.status = Status::Failed(Failure::TooManyCells);
+        } else if self.generations > puzzle.goal.generations {
+            self.status = Status::Failed(Failure::OutOfGenerations);
+        } else if self.generations > 0 && puzzle.goal.is_met(self.population) {
+            let solution = Solution {
+                cells_placed: self.cells_placed,
+                generations:  self.generations,
+            };
+            self.status = Status::Solved(solution);
+            return Some(solution);
+        }
+        None
+    }
+
+    /// Runs the expected board up to the generation of `uni`, then counts the cells on the board
+    /// that came to life as the player's without the rules making them so. However they were
+    /// placed, by clicking, dragging, stamping a pattern or mirroring, they're then copied to the
+    /// expected board, along with any cells the player cleared.
+    fn count_placed(&mut self, puzzle: &Puzzle, uni: &Universe) {
+        while self.expected.latest_gen() < uni.latest_gen() {
+            self.expected.next();
+        }
+        let region = puzzle.region();
+        let board = |uni: &Universe| {
+            let mut cells = HashMap::new();
+            uni.each_non_dead(region, None, &mut |col, row, state| {
+                cells.insert((col, row), state);
+            });
+            cells
+        };
+        let (actual, expected) = (board(uni), board(&self.expected));
+        for (&(col, row), &state) in actual.iter() {
+            if expected.get(&(col, row)) != Some(&state) {
+                if state == CellState::Alive(Some(CURRENT_PLAYER_ID)) {
+                    self.cells_placed += 1;
+                }
+                self.expected.set_unchecked(col, row, state);
+            }
+        }
+        for &(col, row) in expected.keys().filter(|cell| !actual.contains_key(cell)) {
+            self.expected.set_unchecked(col, row, CellState::Dead);
+        }
+    }
+}
+
+/// The live cells in `uni`, whoever they belong to.
+fn population(uni: &Universe) -> usize {
+    let mut count = 0;
+    uni.each_non_dead_full(None, &mut |_, _, state| {
+        if let CellState::Alive(_) = state {
+            count += 1;
+        }
+    });
+    count
+}
+
+/// The puzzles each profile has solved, by player name and then puzzle ID.
+pub struct Progress {
+    path:   PathBuf,
+    solved: BTreeMap<String, BTreeMap<String, Solution>>,
+}
+
+impl Progress {
+    /// Reads which puzzles have been solved. If that can't be read, it is as if none had been.
+    pub fn load() -> Self {
+        Progress::load_from(Folder::Puzzles.path().join(SOLVED_FILE_NAME))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let solved = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                warn!("Could not read the solved puzzles in {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Could not read the solved puzzles in {:?}: {}", path, e);
+                }
+                BTreeMap::new()
+            }
+        };
+        Progress { path, solved }
+    }
+
+    /// The best solution `player` has found for the puzzle `id`, if they have solved it.
+    pub fn best(&self, player: &str, id: &str) -> Option<Solution> {
+        self.solved.get(player).and_then(|solved| solved.get(id)).cloned()
+    }
+
+    /// Notes that `player` solved the puzzle `id`, keeping the solution with the fewest cells
+    /// placed, then the fewest generations. Saving failures are logged.
+    pub fn add(&mut self, player: &str, id: &str, solution: Solution) {
+        let solved = self.solved.entry(player.to_owned()).or_default();
+        let is_better = match solved.get(id) {
+            Some(best) => (solution.cells_placed, solution.generations) < (best.cells_placed, best.generations),
+            None => true,
+        };
+        if !is_better {
+            return;
+        }
+        solved.insert(id.to_owned(), solution);
+        if let Err(e) = self.save() {
+            warn!("Could not save the solved puzzles to {:?}: {}", self.path, e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let bytes = serde_json::to_vec(&self.solved).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A built-in puzzle, without looking in the player's puzzles folder.
+    fn puzzle(id: &str) -> Puzzle {
+        let (id, text) = BUNDLED_PUZZLES.iter().find(|(bundled_id, _)| *bundled_id == id).unwrap();
+        Puzzle::parse(id, text).unwrap()
+    }
+
+    /// Places `cells`, given relative to the board, as the current player, then runs `puzzle` until
+    /// the attempt is over.
+    fn play(puzzle: &Puzzle, cells: &[(usize, usize)]) -> Status {
+        let mut uni = puzzle.universe().unwrap();
+        let mut attempt = Attempt::new(0, puzzle).unwrap();
+        let region = puzzle.region();
+        for &(col, row) in cells {
+            uni.toggle(
+                region.left() as usize + col,
+                region.top() as usize + row,
+                CURRENT_PLAYER_ID,
+            )
+            .unwrap();
+        }
+        while attempt.check(puzzle, &uni).is_none() && attempt.status == Status::Playing {
+            uni.next();
+        }
+        attempt.status
+    }
+
+    #[test]
+    fn test_bundled_puzzles_can_be_solved() {
+        // An L becomes a block
+        assert_eq!(
+            play(&puzzle("spark"), &[(7, 5), (8, 5), (7, 6)]),
+            Status::Solved(Solution {
+                cells_placed: 3,
+                generations:  1,
+            })
+        );
+        // The R-pentomino, in the open and walled in
+        let r_pentomino = [(24, 15), (25, 15), (23, 16), (24, 16), (24, 17)];
+        assert!(matches!(play(&puzzle("bloom"), &r_pentomino), Status::Solved(_)));
+        assert!(matches!(
+            play(&puzzle("boxed_in"), &[(6, 5), (7, 5), (5, 6), (6, 6), (6, 7)]),
+            Status::Solved(_)
+        ));
+        // A cell just above a block takes it down with it
+        assert_eq!(
+            play(&puzzle("clean_sweep"), &[(6, 5)]),
+            Status::Solved(Solution {
+                cells_placed: 1,
+                generations:  4,
+            })
+        );
+        // Two cells make the diagonal an R-pentomino
+        assert!(matches!(
+            play(&puzzle("second_wind"), &[(7, 5), (8, 5)]),
+            Status::Solved(_)
+        ));
+    }
+
+    #[test]
+    fn test_attempt_fails_on_too_many_cells_or_generations() {
+        let spark = puzzle("spark");
+        assert_eq!(
+            play(&spark, &[(2, 2), (3, 2), (2, 3), (3, 3)]),
+            Status::Failed(Failure::TooManyCells)
+        );
+        // A lone cell dies, so the population never reaches the goal
+        assert_eq!(play(&spark, &[(2, 2)]), Status::Failed(Failure::OutOfGenerations));
+    }
+
+    #[test]
+    fn test_cells_placed_without_clicking_are_counted() {
+        let spark = puzzle("spark");
+        let region = spark.region();
+        let mut uni = spark.universe().unwrap();
+        let mut attempt = Attempt::new(0, &spark).unwrap();
+        let (left, top) = (region.left() as usize, region.top() as usize);
+
+        // A stamped pattern, or a dragged line, sets cells rather than toggling them
+        let player_cell = CellState::Alive(Some(CURRENT_PLAYER_ID));
+        for &(col, row) in &[(2, 2), (3, 2)] {
+            uni.set(left + col, top + row, player_cell, CURRENT_PLAYER_ID);
+        }
+        attempt.check(&spark, &uni);
+        assert_eq!(attempt.cells_placed, 2);
+
+        // The pair dies out on its own, which is not counted, then two more are placed
+        uni.next();
+        attempt.check(&spark, &uni);
+        assert_eq!(attempt.cells_placed, 2);
+        for &(col, row) in &[(8, 8), (9, 8)] {
+            uni.set(left + col, top + row, player_cell, CURRENT_PLAYER_ID);
+        }
+        attempt.check(&spark, &uni);
+        assert_eq!(attempt.status, Status::Failed(Failure::TooManyCells));
+        assert_eq!(attempt.cells_placed, 4);
+    }
+
+    #[test]
+    fn test_parse_rejects_a_board_outside_its_size() {
+        let text = "title = \"t\"\ndescription = \"d\"\nsize = [4, 2]\nmax_cells = 1\nboard = \"\"\"\n....\n.....\n\"\"\"\n\n[goal]\ngenerations = 3\nmin_population = 1\n";
+        assert!(Puzzle::parse("t", text).is_err());
+        assert!(Puzzle::parse("t", &text.replace(".....", "....")).is_ok());
+        assert!(Puzzle::parse("t", &text.replace("min_population = 1", "")).is_err());
+    }
+
+    #[test]
+    fn test_progress_keeps_the_best_solution() {
+        let dir = std::env::temp_dir().join(format!("conwayste-puzzle-test-{}", std::process::id()));
+        let path = dir.join(SOLVED_FILE_NAME);
+        let mut progress = Progress::load_from(path.clone());
+        let solution = |cells_placed, generations| Solution {
+            cells_placed,
+            generations,
+        };
+        progress.add("alice", "bloom", solution(5, 40));
+        progress.add("alice", "bloom", solution(5, 50));
+        progress.add("alice", "bloom", solution(5, 30));
+
+        let progress = Progress::load_from(path);
+        assert_eq!(progress.best("alice", "bloom"), Some(solution(5, 30)));
+        assert_eq!(progress.best("bob", "bloom"), None);
+        assert_eq!(
+            daily(5, NaiveDate::from_ymd(2021, 6, 14)),
+            daily(5, NaiveDate::from_ymd(2021, 6, 19))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Images for the user interface, such as the logo, loaded from the resource directories ggez
+//! searches. Many small images, like pattern thumbnails, can be packed into one texture atlas: an
+//! image, plus a TOML file naming the regions of it:
+//!
+//! ```toml
+//! image = "/patterns.png"
+//!
+//! [regions]
+//! glider = [0, 0, 32, 32]    # x, y, width and height, in pixels
+//! acorn = [32, 0, 48, 24]
+//! ```
+//!
+//! The thumbnails of the pattern library are drawn by the client instead, on the loader's thread,
+//! and kept in the data directory so they are only drawn once.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use ggez::graphics::{FilterMode, Image, Rect};
+use ggez::{filesystem, Context, GameError, GameResult};
+
+use image::{ColorType, ImageFormat};
+use serde::Deserialize;
+
+use crate::pattern_library::{LibraryPattern, THUMBNAIL_SIZE};
+
+/// An image, or the region of one that holds a sprite from an atlas.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub image:  Image,
+    pub region: Rect, // as fractions of the image's width and height, like `DrawParam::src`
+}
+
+impl Texture {
+    /// The whole image at `path`, such as `"/conwayste.png"`. It stays sharp when drawn larger, as
+    /// the images are pixel art like the board.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Self> {
+        let mut image = Image::new(ctx, path)?;
+        image.set_filter(FilterMode::Nearest);
+        Ok(Texture::whole(image))
+    }
+
+    pub fn whole(image: Image) -> Self {
+        Texture {
+            image,
+            region: Rect::one(),
+        }
+    }
+
+    /// The width and height of the region, in pixels.
+    pub fn size(&self) -> (f32, f32) {
+        (
+            self.image.width() as f32 * self.region.w,
+            self.image.height() as f32 * self.region.h,
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AtlasSpec {
+    image:   String,
+    #[serde(default)]
+    regions: HashMap<String, [u32; 4]>,
+}
+
+/// An image holding many sprites, each of which can be drawn by name.
+#[derive(Debug)]
+pub struct Atlas {
+    image:   Image,
+    regions: HashMap<String, Rect>,
+}
+
+fn atlas_error(path: &str, reason: String) -> GameError {
+    GameError::ResourceLoadError(format!("Bad texture atlas {:?}: {}", path, reason))
+}
+
+/// The region of an image `width` by `height` pixels that is given in pixels as `[x, y, w, h]`,
+/// as fractions of the image's size.
+fn fractional_region(pixels: [u32; 4], width: u16, height: u16) -> Result<Rect, String> {
+    let [x, y, w, h] = pixels;
+    if w == 0 || h == 0 || x + w > width as u32 || y + h > height as u32 {
+        return Err(format!(
+            "{:?} is empty or goes past the edge of the {}x{} image",
+            pixels, width, height
+        ));
+    }
+    let (width, height) = (width as f32, height as f32);
+    Ok(Rect::new(
+        x as f32 / width,
+        y as f32 / height,
+        w as f32 / width,
+        h as f32 / height,
+    ))
+}
+
+impl Atlas {
+    /// Loads the atlas described by the TOML file at `path`, such as `"/patterns.toml"`, along with
+    /// the image it names.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Self> {
+        let mut source = String::new();
+        filesystem::open(ctx, path)?
+            .read_to_string(&mut source)
+            .map_err(|e| atlas_error(path, e.to_string()))?;
+        let spec: AtlasSpec = toml::from_str(&source).map_err(|e| atlas_error(path, e.to_string()))?;
+        let mut image = Image::new(ctx, &spec.image)?;
+        image.set_filter(FilterMode::Nearest);
+
+        let mut regions = HashMap::new();
+        for (name, pixels) in spec.regions {
+            let region = fractional_region(pixels, image.width(), image.height())
+                .map_err(|reason| atlas_error(path, format!("region {:?}: {}", name, reason)))?;
+            regions.insert(name, region);
+        }
+        Ok(Atlas { image, regions })
+    }
+
+    /// The sprite called `name`, if the atlas has one.
+    pub fn texture(&self, name: &str) -> Option<Texture> {
+        self.regions.get(name).map(|region| Texture {
+            image:  self.image.clone(),
+            region: *region,
+        })
+    }
+}
+
+/// The pixels of the thumbnail of a pattern in the library, as RGBA, from the thumbnails folder
+/// or drawn and saved there if it hasn't been yet. Doesn't need the graphics context, so that the
+/// loader's thread can do it.
+pub fn thumbnail_pixels(pattern: &LibraryPattern) -> Result<Vec<u8>, String> {
+    let size = THUMBNAIL_SIZE as u32;
+    let path = pattern.thumbnail_path();
+    let cached = fs::read(&path)
+        .ok()
+        .and_then(|bytes| image::load_from_memory_with_format(&bytes, ImageFormat::Png).ok())
+        .map(|thumbnail| thumbnail.to_rgba8())
+        .filter(|thumbnail| thumbnail.dimensions() == (size, size));
+    if let Some(thumbnail) = cached {
+        return Ok(thumbnail.into_raw());
+    }
+    let pixels = pattern
+        .thumbnail()
+        .map_err(|e| format!("Could not draw the thumbnail of {:?}: {}", pattern.name, e))?;
+    if let Err(e) = save_png(&path, &pixels, size) {
+        warn!(
+            "Could not save the thumbnail of {:?} to {:?}: {}",
+            pattern.name, path, e
+        );
+    }
+    Ok(pixels)
+}
+
+fn save_png(path: &Path, pixels: &[u8], size: u32) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    image::save_buffer_with_format(path, pixels, size, size, ColorType::Rgba8, ImageFormat::Png)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_atlas_regions_are_fractions_of_the_image() {
+        let spec: AtlasSpec = toml::from_str(
+            r#"
+            image = "/patterns.png"
+
+            [regions]
+            glider = [0, 0, 32, 32]
+            acorn = [32, 16, 96, 48]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(spec.image, "/patterns.png");
+        assert_eq!(
+            fractional_region(spec.regions["acorn"], 128, 64),
+            Ok(Rect::new(0.25, 0.25, 0.75, 0.75))
+        );
+        assert!(fractional_region(spec.regions["glider"], 128, 64).is_ok());
+        assert!(fractional_region([100, 0, 32, 32], 128, 64).is_err());
+        assert!(fractional_region([0, 0, 0, 32], 128, 64).is_err());
+    }
+}
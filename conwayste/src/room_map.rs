@@ -0,0 +1,334 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The map of the room the player is in: the walls, spawn regions and fog-free regions its host
+//! sets up for the next game, shown in the room lobby. Only the host may change it, so edits made
+//! here go to the server and are only shown once it passes them back along, as it does to everyone
+//! in the room.
+
+use conway::rle::Pattern;
+use conway::universe::Region;
+use netwayste::net::{MapEdit, NetRegion, RoomMap, MAX_FOG_FREE_REGIONS, MAX_SPAWN_REGIONS, MAX_WALL_EDIT_CELLS};
+
+use crate::constants::{UNIVERSE_HEIGHT_IN_CELLS as MAP_HEIGHT, UNIVERSE_WIDTH_IN_CELLS as MAP_WIDTH};
+use crate::pattern_editor::line_cells;
+
+/// What dragging over the map does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapTool {
+    Wall,    // paints walls on the cells dragged over
+    Erase,   // erases them
+    Spawn,   // sets the spawn region of the picked slot to the rectangle dragged out
+    FogFree, // sets the fog-free region of the picked slot to the rectangle dragged out
+}
+
+#[derive(Debug)]
+pub struct RoomMapEditor {
+    pub tool:          MapTool,
+    pub slot:          u8,                  // spawn region the Spawn tool sets: a player's, or a team's
+    pub fog_free_slot: u8,                  // fog-free region the FogFree tool sets
+    walls:             Vec<bool>,           // by row, then column
+    walls_revision:    u64,                 // bumped whenever the walls change
+    spawn_regions:     Vec<Option<Region>>, // by player index, or by team in a team game
+    fog_free_regions:  Vec<Option<Region>>, // seen by every player from the start
+    edits:             Vec<MapEdit>,        // made here and not yet sent to the server
+}
+
+impl RoomMapEditor {
+    pub fn new() -> Self {
+        RoomMapEditor {
+            tool:             MapTool::Wall,
+            slot:             0,
+            fog_free_slot:    0,
+            walls:            vec![false; MAP_WIDTH * MAP_HEIGHT],
+            walls_revision:   0,
+            spawn_regions:    vec![],
+            fog_free_regions: vec![],
+            edits:            vec![],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        MAP_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        MAP_HEIGHT
+    }
+
+    pub fn is_wall(&self, col: usize, row: usize) -> bool {
+        self.walls[row * MAP_WIDTH + col]
+    }
+
+    /// Changes whenever the walls do, so that what's drawn of them can be kept until then.
+    pub fn walls_revision(&self) -> u64 {
+        self.walls_revision
+    }
+
+    pub fn spawn_regions(&self) -> &[Option<Region>] {
+        &self.spawn_regions
+    }
+
+    pub fn fog_free_regions(&self) -> &[Option<Region>] {
+        &self.fog_free_regions
+    }
+
+    /// Forgets the map, such as on leaving the room.
+    pub fn reset(&mut self) {
+        for wall in self.walls.iter_mut() {
+            *wall = false;
+        }
+        self.walls_revision += 1;
+        self.spawn_regions.clear();
+        self.fog_free_regions.clear();
+        self.edits.clear();
+    }
+
+    /// Shows the map the server sent in place of this one.
+    pub fn set_map(&mut self, map: &RoomMap) -> Result<(), String> {
+        let pattern = Pattern(map.walls.clone());
+        let (width, height) = pattern.calc_size().map_err(|e| e.to_string())?;
+        if width > MAP_WIDTH || height > MAP_HEIGHT {
+            return Err(format!("the walls are {}x{}, larger than the map", width, height));
+        }
+        let grid = pattern
+            .to_new_bit_grid(MAP_WIDTH, MAP_HEIGHT)
+            .map_err(|e| e.to_string())?;
+        self.reset();
+        grid.each_set(|col, row| self.walls[row * MAP_WIDTH + col] = true);
+        self.spawn_regions = map
+            .spawn_regions
+            .iter()
+            .map(|region| region.as_ref().map(Region::from))
+            .collect();
+        self.fog_free_regions = map
+            .fog_free_regions
+            .iter()
+            .map(|region| region.as_ref().map(Region::from))
+            .collect();
+        Ok(())
+    }
+
+    /// Makes an edit to the map that the server passed along.
+    pub fn apply(&mut self, edit: &MapEdit) {
+        match *edit {
+            MapEdit::Walls { ref cells, wall } => {
+                for &(col, row) in cells {
+                    let (col, row) = (col as usize, row as usize);
+                    if col < MAP_WIDTH && row < MAP_HEIGHT {
+                        self.walls[row * MAP_WIDTH + col] = wall;
+                    }
+                }
+                self.walls_revision += 1;
+            }
+            MapEdit::SpawnRegion { slot, ref region } => {
+                set_region(&mut self.spawn_regions, slot, region);
+            }
+            MapEdit::FogFreeRegion { slot, ref region } => {
+                set_region(&mut self.fog_free_regions, slot, region);
+            }
+            MapEdit::Clear => {
+                let edits = std::mem::take(&mut self.edits);
+                self.reset();
+                self.edits = edits;
+            }
+        }
+    }
+
+    /// Uses the tool on the cells from `from` to `to`, both (column, row) and on the map, asking
+    /// the server for the change.
+    pub fn use_tool(&mut self, from: (usize, usize), to: (usize, usize)) {
+        match self.tool {
+            MapTool::Wall | MapTool::Erase => {
+                let wall = self.tool == MapTool::Wall;
+                let cells: Vec<(u32, u32)> = line_cells(from, to)
+                    .into_iter()
+                    .filter(|&(col, row)| self.is_wall(col, row) != wall)
+                    .map(|(col, row)| (col as u32, row as u32))
+                    .collect();
+                for cells in cells.chunks(MAX_WALL_EDIT_CELLS) {
+                    self.edits.push(MapEdit::Walls {
+                        cells: cells.to_vec(),
+                        wall,
+                    });
+                }
+            }
+            MapTool::Spawn => {
+                self.edits.push(MapEdit::SpawnRegion {
+                    slot:   self.slot,
+                    region: Some(NetRegion::from(dragged_region(from, to))),
+                });
+            }
+            MapTool::FogFree => {
+                self.edits.push(MapEdit::FogFreeRegion {
+                    slot:   self.fog_free_slot,
+                    region: Some(NetRegion::from(dragged_region(from, to))),
+                });
+            }
+        }
+    }
+
+    /// Asks the server to remove every wall, spawn region and fog-free region.
+    pub fn clear(&mut self) {
+        self.edits.push(MapEdit::Clear);
+    }
+
+    /// Picks the Spawn tool, or if it's already picked, the next slot for it.
+    pub fn pick_spawn_tool(&mut self) {
+        if self.tool == MapTool::Spawn {
+            self.slot = (self.slot + 1) % MAX_SPAWN_REGIONS;
+        }
+        self.tool = MapTool::Spawn;
+    }
+
+    /// Picks the FogFree tool, or if it's already picked, the next slot for it.
+    pub fn pick_fog_free_tool(&mut self) {
+        if self.tool == MapTool::FogFree {
+            self.fog_free_slot = (self.fog_free_slot + 1) % MAX_FOG_FREE_REGIONS;
+        }
+        self.tool = MapTool::FogFree;
+    }
+
+    /// The edits made since the last call, to be sent to the server.
+    pub fn take_edits(&mut self) -> Vec<MapEdit> {
+        std::mem::take(&mut self.edits)
+    }
+}
+
+/// The region with `from` and `to`, both (column, row), at opposite corners, as dragged out with
+/// the Spawn or FogFree tool.
+pub fn dragged_region(from: (usize, usize), to: (usize, usize)) -> Region {
+    let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+    let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+    Region::new(left as isize, top as isize, right - left + 1, bottom - top + 1)
+}
+
+fn set_region(regions: &mut Vec<Option<Region>>, slot: u8, region: &Option<NetRegion>) {
+    let slot = slot as usize;
+    if regions.len() <= slot {
+        regions.resize(slot + 1, None);
+    }
+    regions[slot] = region.as_ref().map(Region::from);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tools_only_ask_the_server() {
+        let mut editor = RoomMapEditor::new();
+        editor.use_tool((2, 3), (4, 3));
+        assert!(!editor.is_wall(2, 3));
+
+        let edits = editor.take_edits();
+        assert_eq!(
+            edits,
+            vec![MapEdit::Walls {
+                cells: vec![(2, 3), (3, 3), (4, 3)],
+                wall:  true,
+            }]
+        );
+        for edit in edits.iter() {
+            editor.apply(edit);
+        }
+        assert!(editor.is_wall(3, 3));
+
+        // Erasing only asks for the cells that are walls
+        editor.tool = MapTool::Erase;
+        editor.use_tool((4, 3), (6, 3));
+        assert_eq!(
+            editor.take_edits(),
+            vec![MapEdit::Walls {
+                cells: vec![(4, 3)],
+                wall:  false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_set_map_replaces_walls_and_spawn_regions() {
+        let mut editor = RoomMapEditor::new();
+        editor.apply(&MapEdit::Walls {
+            cells: vec![(0, 0)],
+            wall:  true,
+        });
+        editor.pick_spawn_tool();
+        editor.pick_spawn_tool();
+        editor.use_tool((5, 6), (1, 2));
+        let edits = editor.take_edits();
+        assert_eq!(
+            edits,
+            vec![MapEdit::SpawnRegion {
+                slot:   1,
+                region: Some(NetRegion::new(1, 2, 5, 5)),
+            }]
+        );
+
+        let map = RoomMap {
+            walls:            "b2o$bo!".to_owned(),
+            spawn_regions:    vec![None, Some(NetRegion::new(1, 2, 5, 5))],
+            fog_free_regions: vec![Some(NetRegion::new(10, 0, 4, 4))],
+        };
+        editor.set_map(&map).unwrap();
+        assert!(!editor.is_wall(0, 0));
+        assert!(editor.is_wall(1, 0) && editor.is_wall(2, 0) && editor.is_wall(1, 1));
+        assert_eq!(editor.spawn_regions(), &[None, Some(Region::new(1, 2, 5, 5))]);
+        assert_eq!(editor.fog_free_regions(), &[Some(Region::new(10, 0, 4, 4))]);
+
+        let too_wide = RoomMap {
+            walls: format!("{}o!", MAP_WIDTH),
+            ..RoomMap::default()
+        };
+        assert!(editor.set_map(&too_wide).is_err());
+    }
+    #[test]
+    fn test_fog_free_tool_sets_the_picked_slot() {
+        let mut editor = RoomMapEditor::new();
+        editor.pick_fog_free_tool();
+        editor.use_tool((3, 4), (6, 5));
+        editor.pick_fog_free_tool();
+        editor.use_tool((0, 0), (1, 1));
+        let edits = editor.take_edits();
+        assert_eq!(
+            edits,
+            vec![
+                MapEdit::FogFreeRegion {
+                    slot:   0,
+                    region: Some(NetRegion::new(3, 4, 4, 2)),
+                },
+                MapEdit::FogFreeRegion {
+                    slot:   1,
+                    region: Some(NetRegion::new(0, 0, 2, 2)),
+                },
+            ]
+        );
+        for edit in edits.iter() {
+            editor.apply(edit);
+        }
+        assert_eq!(
+            editor.fog_free_regions(),
+            &[Some(Region::new(3, 4, 4, 2)), Some(Region::new(0, 0, 2, 2))]
+        );
+
+        let revision = editor.walls_revision();
+        editor.apply(&MapEdit::Clear);
+        assert!(editor.fog_free_regions().is_empty());
+        assert_ne!(editor.walls_revision(), revision);
+    }
+}
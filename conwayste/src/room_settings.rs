@@ -0,0 +1,219 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! How the next game in the room the player is in is played, as shown and changed in the room
+//! lobby. As with the room's map, changes made here go to the server and are only shown once it
+//! sends the room's settings back.
+
+use netwayste::net::{NetRegion, NetwaysteEvent, RoomSettings, WinCondition};
+
+use crate::constants::{
+    DEFAULT_MATCH_GENERATIONS, MAX_SERIES_ROUNDS, MAX_TEAMS, UNIVERSE_HEIGHT_IN_CELLS, UNIVERSE_WIDTH_IN_CELLS,
+};
+
+#[derive(Debug)]
+pub struct RoomSettingsEditor {
+    settings:    Option<RoomSettings>, // None until the server sends them
+    generations: u32,                  // length of the room's games when they last had one, for having one again
+    requests:    Vec<NetwaysteEvent>,  // made here and not yet sent to the server
+}
+
+impl RoomSettingsEditor {
+    pub fn new() -> Self {
+        RoomSettingsEditor {
+            settings:    None,
+            generations: DEFAULT_MATCH_GENERATIONS,
+            requests:    vec![],
+        }
+    }
+
+    pub fn settings(&self) -> Option<&RoomSettings> {
+        self.settings.as_ref()
+    }
+
+    /// Takes the settings the server sent.
+    pub fn set(&mut self, settings: RoomSettings) {
+        match settings.win_condition {
+            WinCondition::MostLiveCells { generations } | WinCondition::TerritoryControl { generations, .. } => {
+                self.generations = generations;
+            }
+            WinCondition::Elimination => {}
+        }
+        self.settings = Some(settings);
+    }
+
+    /// Forgets the settings, such as on leaving the room.
+    pub fn reset(&mut self) {
+        self.settings = None;
+        self.requests.clear();
+    }
+
+    /// Asks for the next number of teams: none (free-for-all), then two up to the most allowed,
+    /// then none again. A single team is skipped, being no different from free-for-all.
+    pub fn next_team_count(&mut self) {
+        let settings = match self.settings {
+            Some(ref settings) => settings,
+            None => return,
+        };
+        let team_count = match settings.team_count as usize {
+            0 => 2,
+            n if n >= MAX_TEAMS => 0,
+            n => n + 1,
+        };
+        self.requests.push(NetwaysteEvent::SetTeamCount(team_count as u8));
+    }
+
+    /// Asks for the next length of series, counting up by two so there is no tie in rounds won,
+    /// then back to single games.
+    pub fn next_series_length(&mut self) {
+        let settings = match self.settings {
+            Some(ref settings) => settings,
+            None => return,
+        };
+        let rounds = match settings.series_length + 2 {
+            n if n > MAX_SERIES_ROUNDS => 1,
+            n => n,
+        };
+        self.requests.push(NetwaysteEvent::SetSeriesLength(rounds));
+    }
+
+    /// Asks for the next way of deciding who wins: the most live cells, then the most live cells in
+    /// the middle of the universe, then the last one with any live cells, then back to the first.
+    pub fn next_win_condition(&mut self) {
+        let settings = match self.settings {
+            Some(ref settings) => settings,
+            None => return,
+        };
+        let condition = match settings.win_condition {
+            WinCondition::MostLiveCells { generations } => WinCondition::TerritoryControl {
+                region: middle_territory(),
+                generations,
+            },
+            WinCondition::TerritoryControl { .. } => WinCondition::Elimination,
+            WinCondition::Elimination => WinCondition::MostLiveCells {
+                generations: self.generations,
+            },
+        };
+        self.requests.push(NetwaysteEvent::SetWinCondition(condition));
+    }
+
+    /// Returns the requests made since this was last called, for sending to the server.
+    pub fn take_requests(&mut self) -> Vec<NetwaysteEvent> {
+        self.requests.drain(..).collect()
+    }
+}
+
+/// The territory fought over in a territory control game: the middle quarter of the universe.
+pub fn middle_territory() -> NetRegion {
+    NetRegion::new(
+        UNIVERSE_WIDTH_IN_CELLS as i32 / 4,
+        UNIVERSE_HEIGHT_IN_CELLS as i32 / 4,
+        UNIVERSE_WIDTH_IN_CELLS as u32 / 2,
+        UNIVERSE_HEIGHT_IN_CELLS as u32 / 2,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings(team_count: u8, series_length: u8) -> RoomSettings {
+        RoomSettings {
+            team_count,
+            win_condition: WinCondition::Elimination,
+            series_length,
+        }
+    }
+
+    #[test]
+    fn test_team_count_cycles_past_one_team() {
+        let mut editor = RoomSettingsEditor::new();
+        editor.next_team_count();
+        assert!(
+            editor.take_requests().is_empty(),
+            "nothing to change until the server sends the settings"
+        );
+
+        let mut asked = vec![];
+        for &team_count in &[0, 2, 3, MAX_TEAMS as u8] {
+            editor.set(settings(team_count, 1));
+            editor.next_team_count();
+            asked.extend(editor.take_requests());
+        }
+        assert_eq!(
+            asked,
+            vec![
+                NetwaysteEvent::SetTeamCount(2),
+                NetwaysteEvent::SetTeamCount(3),
+                NetwaysteEvent::SetTeamCount(4),
+                NetwaysteEvent::SetTeamCount(0),
+            ]
+        );
+        // the settings only change once the server sends them back
+        assert_eq!(editor.settings().unwrap().team_count, MAX_TEAMS as u8);
+    }
+
+    #[test]
+    fn test_series_length_counts_up_by_two_then_back_to_single_games() {
+        let mut editor = RoomSettingsEditor::new();
+        let mut asked = vec![];
+        for &rounds in &[1, 3, MAX_SERIES_ROUNDS - 2, MAX_SERIES_ROUNDS] {
+            editor.set(settings(0, rounds));
+            editor.next_series_length();
+            asked.extend(editor.take_requests());
+        }
+        assert_eq!(
+            asked,
+            vec![
+                NetwaysteEvent::SetSeriesLength(3),
+                NetwaysteEvent::SetSeriesLength(5),
+                NetwaysteEvent::SetSeriesLength(MAX_SERIES_ROUNDS),
+                NetwaysteEvent::SetSeriesLength(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_win_condition_cycles_and_keeps_the_game_length() {
+        let mut editor = RoomSettingsEditor::new();
+        let mut room_settings = settings(0, 1);
+        room_settings.win_condition = WinCondition::MostLiveCells { generations: 300 };
+        editor.set(room_settings.clone());
+        let mut asked = vec![];
+        for _ in 0..3 {
+            editor.next_win_condition();
+            let requests = editor.take_requests();
+            if let Some(NetwaysteEvent::SetWinCondition(ref condition)) = requests.last() {
+                room_settings.win_condition = condition.clone();
+                editor.set(room_settings.clone()); // as the server sends it back
+            }
+            asked.extend(requests);
+        }
+        assert_eq!(
+            asked,
+            vec![
+                NetwaysteEvent::SetWinCondition(WinCondition::TerritoryControl {
+                    region:      middle_territory(),
+                    generations: 300,
+                }),
+                NetwaysteEvent::SetWinCondition(WinCondition::Elimination),
+                NetwaysteEvent::SetWinCondition(WinCondition::MostLiveCells { generations: 300 }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,32 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use conway::script::Script;
+
+/// Compiles scripts from their (name, source), to play as `player_id`. Those that don't compile are
+/// logged and skipped.
+pub fn compile_scripts(sources: &[(String, String)], player_id: usize) -> Vec<Script> {
+    let mut scripts = vec![];
+    for (name, source) in sources {
+        match Script::new(name, source, player_id) {
+            Ok(script) => scripts.push(script),
+            Err(e) => error!("Could not load script {:?}: {}", name, e),
+        }
+    }
+    scripts
+}
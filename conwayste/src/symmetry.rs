@@ -0,0 +1,151 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Drawing with symmetry. While a symmetry is picked, every cell the player draws or erases on the
+//! board is mirrored about an axis or a center, which makes oscillators and other symmetric
+//! patterns quick to put together.
+//!
+//! The center is kept in half cells so that it can sit either in the middle of a cell or on the
+//! line between two: a center of `c` mirrors column `col` onto `c - col`, so an even `c` is the
+//! middle of cell `c / 2`, and an odd one the line to the right of it.
+
+use conway::universe::Region;
+
+/// How cells are mirrored while drawing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    Off,
+    Horizontal, // left to right, about a vertical axis
+    Vertical,   // top to bottom, about a horizontal axis
+    FourFold,   // about both axes
+    Rotational, // turned half way around the center
+}
+
+impl Symmetry {
+    /// The symmetry picked after this one when cycling through them with a key.
+    pub fn next(self) -> Self {
+        match self {
+            Symmetry::Off => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::FourFold,
+            Symmetry::FourFold => Symmetry::Rotational,
+            Symmetry::Rotational => Symmetry::Off,
+        }
+    }
+
+    /// Message ID of the symmetry's name.
+    pub fn message_id(self) -> &'static str {
+        match self {
+            Symmetry::Off => "symmetry-off",
+            Symmetry::Horizontal => "symmetry-horizontal",
+            Symmetry::Vertical => "symmetry-vertical",
+            Symmetry::FourFold => "symmetry-four-fold",
+            Symmetry::Rotational => "symmetry-rotational",
+        }
+    }
+}
+
+/// A symmetry along with where its axes cross.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mirror {
+    pub symmetry: Symmetry,
+    center:       (isize, isize), // (column, row) in half cells
+}
+
+impl Mirror {
+    /// A mirror centered on a universe of `width` by `height` cells.
+    pub fn new(symmetry: Symmetry, width: usize, height: usize) -> Self {
+        Mirror {
+            symmetry,
+            center: (width as isize - 1, height as isize - 1),
+        }
+    }
+
+    /// Moves the center to the cell corner or middle nearest to (`x`, `y`), which are in cells, so
+    /// that the middle of cell 3 is at 3.5.
+    pub fn move_center(&mut self, x: f32, y: f32) {
+        self.center = ((x * 2.0 - 1.0).round() as isize, (y * 2.0 - 1.0).round() as isize);
+    }
+
+    /// Where the axes cross, in cells, the same way `move_center` takes it.
+    pub fn center(&self) -> (f32, f32) {
+        ((self.center.0 + 1) as f32 / 2.0, (self.center.1 + 1) as f32 / 2.0)
+    }
+
+    /// The cells that (`col`, `row`) is mirrored onto, not counting itself. A cell on an axis is
+    /// its own mirror image, so there can be fewer than the symmetry has.
+    pub fn images(&self, col: isize, row: isize) -> Vec<(isize, isize)> {
+        let (mirror_col, mirror_row) = (self.center.0 - col, self.center.1 - row);
+        let candidates = match self.symmetry {
+            Symmetry::Off => vec![],
+            Symmetry::Horizontal => vec![(mirror_col, row)],
+            Symmetry::Vertical => vec![(col, mirror_row)],
+            Symmetry::FourFold => vec![(mirror_col, row), (col, mirror_row), (mirror_col, mirror_row)],
+            Symmetry::Rotational => vec![(mirror_col, mirror_row)],
+        };
+        let mut images: Vec<(isize, isize)> = vec![];
+        for image in candidates {
+            if image != (col, row) && !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    /// Like `images`, leaving out those outside of `region`, which is usually the universe's.
+    pub fn images_within(&self, region: Region, col: usize, row: usize) -> Vec<(usize, usize)> {
+        self.images(col as isize, row as isize)
+            .into_iter()
+            .filter(|&(col, row)| region.contains(col, row))
+            .map(|(col, row)| (col as usize, row as usize))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_images_are_mirrored_about_the_center() {
+        let mut mirror = Mirror::new(Symmetry::Horizontal, 10, 10);
+        assert_eq!(mirror.images(2, 3), vec![(7, 3)]);
+
+        mirror.move_center(5.5, 5.5); // the middle of cell 5
+        assert_eq!(mirror.images(2, 3), vec![(8, 3)]);
+        assert_eq!(mirror.images(5, 3), vec![]);
+
+        mirror.symmetry = Symmetry::FourFold;
+        assert_eq!(mirror.images(2, 3), vec![(8, 3), (2, 7), (8, 7)]);
+        assert_eq!(mirror.images(5, 3), vec![(5, 7)]);
+
+        mirror.symmetry = Symmetry::Rotational;
+        assert_eq!(mirror.images(2, 3), vec![(8, 7)]);
+        assert_eq!(mirror.images(5, 5), vec![]);
+    }
+
+    #[test]
+    fn test_center_snaps_to_the_nearest_half_cell() {
+        let mut mirror = Mirror::new(Symmetry::Vertical, 10, 10);
+        assert_eq!(mirror.center(), (5.0, 5.0));
+        mirror.move_center(3.2, 3.4);
+        assert_eq!(mirror.center(), (3.0, 3.5));
+        assert_eq!(mirror.images(0, 0), vec![(0, 6)]);
+        assert_eq!(mirror.images_within(Region::new(0, 0, 10, 6), 0, 0), vec![]);
+    }
+}
@@ -0,0 +1,141 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Board themes: the colors of the board around and between the players' cells. One is picked in
+//! the options menu, or made up in the `[theme]` section of the config.
+
+use ggez::graphics::Color;
+
+use crate::config::ThemeSettings;
+use crate::constants::{colors::*, DrawStyle, GRID_DRAW_STYLE};
+
+/// The colors a theme gives the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardColors {
+    pub background: Color, // around the board
+    pub grid:       Color, // lines between the cells; none are drawn if it's the same as `dead`
+    pub dead:       Color,
+    pub alive:      Color, // cells of no player
+    pub wall:       Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoardTheme {
+    Light,
+    Dark,
+    HighContrast,
+    Custom, // the colors in the config
+}
+
+impl BoardTheme {
+    pub const ALL: [BoardTheme; 4] = [
+        BoardTheme::Light,
+        BoardTheme::Dark,
+        BoardTheme::HighContrast,
+        BoardTheme::Custom,
+    ];
+
+    /// The theme named `name` in the config, like "dark", or None if there isn't one.
+    pub fn from_name(name: &str) -> Option<BoardTheme> {
+        BoardTheme::ALL.iter().copied().find(|theme| theme.name() == name)
+    }
+
+    /// The name of this theme in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            BoardTheme::Light => "light",
+            BoardTheme::Dark => "dark",
+            BoardTheme::HighContrast => "high-contrast",
+            BoardTheme::Custom => "custom",
+        }
+    }
+
+    /// The colors of this theme. Those of `Custom` come from `settings`.
+    pub fn colors(self, settings: &ThemeSettings) -> BoardColors {
+        match self {
+            BoardTheme::Light => BoardColors {
+                background: *UNIVERSE_BG_COLOR,
+                grid:       *CELL_STATE_DEAD_COLOR,
+                dead:       *CELL_STATE_DEAD_COLOR,
+                alive:      if GRID_DRAW_STYLE == DrawStyle::Line {
+                    // black background - for a "tetris-like" effect
+                    *CELL_STATE_BG_FILL_HOLLOW_COLOR
+                } else {
+                    *CELL_STATE_BG_FILL_SOLID_COLOR
+                },
+                wall:       *CELL_STATE_WALL_COLOR,
+            },
+            BoardTheme::Dark => BoardColors {
+                background: Color::new(0.05, 0.05, 0.08, 1.0),
+                grid:       Color::new(0.16, 0.16, 0.2, 1.0),
+                dead:       Color::new(0.1, 0.1, 0.13, 1.0),
+                alive:      Color::new(0.8, 0.8, 0.85, 1.0),
+                wall:       Color::new(0.45, 0.35, 0.2, 1.0),
+            },
+            BoardTheme::HighContrast => BoardColors {
+                background: BLACK,
+                grid:       Color::new(0.4, 0.4, 0.4, 1.0),
+                dead:       BLACK,
+                alive:      WHITE,
+                wall:       Color::new(1.0, 0.85, 0.0, 1.0),
+            },
+            BoardTheme::Custom => {
+                let rgb = |[r, g, b]: [u8; 3]| Color::from_rgb(r, g, b);
+                BoardColors {
+                    background: rgb(settings.background),
+                    grid:       rgb(settings.grid),
+                    dead:       rgb(settings.dead),
+                    alive:      rgb(settings.alive),
+                    wall:       rgb(settings.wall),
+                }
+            }
+        }
+    }
+}
+
+/// The colors of the theme chosen in `settings`, or of the light one if it's not a theme.
+pub fn board_colors(settings: &ThemeSettings) -> BoardColors {
+    BoardTheme::from_name(&settings.name)
+        .unwrap_or(BoardTheme::Light)
+        .colors(settings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_theme_names_round_trip() {
+        for &theme in BoardTheme::ALL.iter() {
+            assert_eq!(BoardTheme::from_name(theme.name()), Some(theme));
+        }
+        assert_eq!(BoardTheme::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn test_custom_theme_uses_config_colors() {
+        let mut settings = ThemeSettings::default();
+        settings.name = "custom".to_owned();
+        settings.wall = [255, 0, 0];
+        let colors = board_colors(&settings);
+        assert_eq!(colors.wall, Color::from_rgb(255, 0, 0));
+
+        settings.name = "no-such-theme".to_owned();
+        assert_eq!(board_colors(&settings), BoardTheme::Light.colors(&settings));
+    }
+}
@@ -0,0 +1,311 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! The tutorial shown in the first game a player starts. A callout bubble points at part of the
+//! screen and says what to do there; once the player has done it, the next one is shown. When the
+//! last is done, the `tutorial_done` gameplay setting is turned on so it isn't shown again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use ggez::graphics::{self, DrawMode, Font, Rect};
+use ggez::mint::Point2;
+use ggez::Context;
+
+use id_tree::NodeId;
+
+use crate::constants::{self, colors::scheme};
+use crate::i18n::{tr, tr_args};
+use crate::ui::{common::FontInfo, InsertLocation, Label, Layering, Pane, UIResult, Widget};
+
+const PADDING: f32 = 12.0; // pixels around the text of a bubble
+const POINTER_LENGTH: f32 = 40.0; // pixels from a bubble to what it points at
+const POINTER_WIDTH: f32 = 20.0; // pixels, where the pointer meets the bubble
+
+/// Where the player is in the tutorial.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    PlaceCell,
+    Zoom,
+    Chat,
+    Done,
+}
+
+/// Something the player did that a step may be waiting for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    PlacedCell,
+    Zoomed,
+    Chatted,
+}
+
+/// What a step's bubble points at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Board,
+    ChatField,
+}
+
+impl Step {
+    const COUNT: usize = 3; // not counting Done
+
+    /// The step after this one if `action` is what it asks for, or else this one.
+    fn after(self, action: Action) -> Step {
+        match (self, action) {
+            (Step::PlaceCell, Action::PlacedCell) => Step::Zoom,
+            (Step::Zoom, Action::Zoomed) => Step::Chat,
+            (Step::Chat, Action::Chatted) => Step::Done,
+            (step, _) => step,
+        }
+    }
+
+    /// Counting from 1, for showing the player how far along they are.
+    fn number(self) -> usize {
+        match self {
+            Step::PlaceCell => 1,
+            Step::Zoom => 2,
+            Step::Chat => 3,
+            Step::Done => Step::COUNT,
+        }
+    }
+
+    fn message_id(self) -> &'static str {
+        match self {
+            Step::PlaceCell => "tutorial-place-cell",
+            Step::Zoom => "tutorial-zoom",
+            Step::Chat | Step::Done => "tutorial-chat",
+        }
+    }
+
+    pub fn target(self) -> Target {
+        match self {
+            Step::PlaceCell | Step::Zoom | Step::Done => Target::Board,
+            Step::Chat => Target::ChatField,
+        }
+    }
+}
+
+/// What the client has seen the player do so far, from which the actions are worked out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observed {
+    pub cells_placed: usize,
+    pub cell_size:    f32, // pixels; changes when the player zooms
+}
+
+/// The state machine of the tutorial, apart from drawing it.
+#[derive(Debug)]
+struct Progress {
+    step:    Step,
+    last:    Option<Observed>, // None until the first update
+    chatted: Arc<AtomicBool>,  // set by the chat's text field when a message is sent
+}
+
+impl Progress {
+    fn new(done: bool) -> Self {
+        Progress {
+            step:    if done { Step::Done } else { Step::PlaceCell },
+            last:    None,
+            chatted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Moves on through the steps for what the player did since the last update. Returns true if
+    /// that finished the tutorial.
+    fn update(&mut self, observed: Observed) -> bool {
+        let was_done = self.step == Step::Done;
+        let mut actions = vec![];
+        if let Some(last) = self.last {
+            if observed.cells_placed > last.cells_placed {
+                actions.push(Action::PlacedCell);
+            }
+            if observed.cell_size != last.cell_size {
+                actions.push(Action::Zoomed);
+            }
+        }
+        if self.chatted.swap(false, Ordering::Relaxed) {
+            actions.push(Action::Chatted);
+        }
+        self.last = Some(observed);
+
+        for action in actions {
+            self.step = self.step.after(action);
+        }
+        !was_done && self.step == Step::Done
+    }
+}
+
+/// The tutorial's callout bubble, drawn over the game.
+pub struct Tutorial {
+    progress:   Progress,
+    layering:   Layering, // drawn above everything else
+    pane_id:    NodeId,
+    label_id:   NodeId,
+    shown_step: Option<Step>, // the step whose text the label has
+}
+
+impl Tutorial {
+    /// A tutorial starting at the first step, or one that is already over if `done`.
+    pub fn new(ctx: &mut Context, font: Font, done: bool) -> UIResult<Self> {
+        let mut layering = Layering::new();
+        let font_info = FontInfo::new(ctx, font, Some(*constants::DEFAULT_UI_FONT_SCALE));
+
+        let mut pane = Box::new(Pane::new(Rect::new(0.0, 0.0, 1.0, 1.0)));
+        pane.bg_color = Some(scheme().tutorial_bubble);
+        let pane_id = layering.add_widget(pane, InsertLocation::AtCurrentLayer)?;
+        let label = Box::new(Label::new(
+            ctx,
+            font_info,
+            String::new(),
+            scheme().tutorial_text,
+            Point2 { x: 0.0, y: 0.0 },
+        ));
+        let label_id = layering.add_widget(label, InsertLocation::AtCurrentLayer)?;
+
+        Ok(Tutorial {
+            progress: Progress::new(done),
+            layering,
+            pane_id,
+            label_id,
+            shown_step: None,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.step != Step::Done
+    }
+
+    pub fn step(&self) -> Step {
+        self.progress.step
+    }
+
+    /// For the chat's text field to set when the player sends a message.
+    pub fn chat_flag(&self) -> Arc<AtomicBool> {
+        self.progress.chatted.clone()
+    }
+
+    /// Moves the tutorial along for what the player did since the last update. Returns true if that
+    /// finished it.
+    pub fn update(&mut self, observed: Observed) -> bool {
+        self.progress.update(observed)
+    }
+
+    /// Draws the bubble of the current step pointing at `target`, above it if there's room and
+    /// below it otherwise.
+    pub fn draw(&mut self, ctx: &mut Context, target: Point2<f32>) -> UIResult<()> {
+        let step = self.progress.step;
+        if step == Step::Done {
+            return Ok(());
+        }
+        if self.shown_step != Some(step) {
+            self.shown_step = Some(step);
+            let text = tr_args(
+                "tutorial-step",
+                &[
+                    ("step", &step.number()),
+                    ("steps", &Step::COUNT),
+                    ("message", &tr(step.message_id())),
+                ],
+            );
+            let (label, texts) = self.layering.get_widget_and_texts_mut(&self.label_id)?;
+            let label = label.downcast_mut::<Label>().unwrap(); // unwrap OK because it's always a Label
+            label.set_text(ctx, texts, text);
+        }
+
+        let (label_w, label_h) = self.layering.get_widget(&self.label_id)?.size();
+        let (w, h) = (label_w + 2.0 * PADDING, label_h + 2.0 * PADDING);
+        let screen = graphics::screen_coordinates(ctx);
+        let x = (target.x - w / 2.0).max(screen.x).min(screen.right() - w);
+        let above = target.y - POINTER_LENGTH - h >= screen.y;
+        let y = if above {
+            target.y - POINTER_LENGTH - h
+        } else {
+            target.y + POINTER_LENGTH
+        };
+        let bubble = Rect::new(x, y, w, h);
+        if self.layering.get_widget(&self.pane_id)?.rect() != bubble {
+            self.layering.get_widget_mut(&self.pane_id)?.set_rect(bubble)?;
+            self.layering
+                .get_widget_mut(&self.label_id)?
+                .set_position(x + PADDING, y + PADDING);
+        }
+
+        let base_y = if above { bubble.bottom() } else { bubble.top() };
+        let base_x = target
+            .x
+            .max(bubble.left() + POINTER_WIDTH)
+            .min(bubble.right() - POINTER_WIDTH);
+        let pointer = graphics::Mesh::new_polygon(
+            ctx,
+            DrawMode::fill(),
+            &[
+                Point2 {
+                    x: base_x - POINTER_WIDTH / 2.0,
+                    y: base_y,
+                },
+                Point2 {
+                    x: base_x + POINTER_WIDTH / 2.0,
+                    y: base_y,
+                },
+                target,
+            ],
+            scheme().tutorial_bubble,
+        )?;
+        graphics::draw(ctx, &pointer, graphics::DrawParam::default())?;
+        self.layering.draw(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn observed(cells_placed: usize, cell_size: f32) -> Observed {
+        Observed {
+            cells_placed,
+            cell_size,
+        }
+    }
+
+    #[test]
+    fn test_steps_advance_only_on_their_action() {
+        let mut progress = Progress::new(false);
+        assert!(!progress.update(observed(0, 5.0)));
+        assert_eq!(progress.step, Step::PlaceCell);
+
+        // zooming first doesn't count, as the tutorial asks for a cell first
+        assert!(!progress.update(observed(0, 6.0)));
+        assert_eq!(progress.step, Step::PlaceCell);
+        assert!(!progress.update(observed(1, 6.0)));
+        assert_eq!(progress.step, Step::Zoom);
+        assert!(!progress.update(observed(1, 5.0)));
+        assert_eq!(progress.step, Step::Chat);
+
+        progress.chatted.store(true, Ordering::Relaxed);
+        assert!(progress.update(observed(1, 5.0)));
+        assert_eq!(progress.step, Step::Done);
+        assert!(!progress.update(observed(2, 4.0)));
+    }
+
+    #[test]
+    fn test_done_tutorial_stays_done() {
+        let mut progress = Progress::new(true);
+        progress.chatted.store(true, Ordering::Relaxed);
+        assert!(!progress.update(observed(3, 5.0)));
+        assert_eq!(progress.step, Step::Done);
+    }
+}
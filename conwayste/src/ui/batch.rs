@@ -0,0 +1,117 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Drawing the shapes of many widgets with one mesh. Making a `Mesh` and drawing it for every
+//! rectangle of every widget each frame is slow once a screen has a few dozen widgets, so widgets
+//! that only draw shapes list them in a `ShapeBatch` instead (see `Widget::batch_shapes`). The
+//! layering collects the shapes of neighbouring widgets into one batch, and keeps the mesh built
+//! from it for as long as the same shapes are listed, which is until a widget moves, resizes or
+//! changes color.
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    FilledRect(Rect, Color),
+    StrokedRect(Rect, f32, Color), // the rect, the width of the line, and its color
+}
+
+/// Shapes to be drawn together, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShapeBatch {
+    shapes: Vec<Shape>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> Self {
+        ShapeBatch { shapes: vec![] }
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.shapes.push(Shape::FilledRect(rect, color));
+    }
+
+    pub fn stroke_rect(&mut self, rect: Rect, line_width: f32, color: Color) {
+        self.shapes.push(Shape::StrokedRect(rect, line_width, color));
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// The mesh of all the shapes, or None if there are none, as ggez can't make an empty mesh.
+    pub fn build(&self, ctx: &mut Context) -> GameResult<Option<Mesh>> {
+        if self.shapes.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = MeshBuilder::new();
+        for shape in &self.shapes {
+            match *shape {
+                Shape::FilledRect(rect, color) => builder.rectangle(DrawMode::fill(), rect, color)?,
+                Shape::StrokedRect(rect, line_width, color) => {
+                    builder.rectangle(DrawMode::stroke(line_width), rect, color)?
+                }
+            };
+        }
+        builder.build(ctx).map(Some)
+    }
+
+    /// Draws the shapes right away, for a widget drawn on its own.
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(mesh) = self.build(ctx)? {
+            graphics::draw(ctx, &mesh, DrawParam::default())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{widget::Widget, Pane};
+    use super::*;
+    use crate::constants::colors::scheme;
+
+    #[test]
+    fn test_a_pane_batches_the_same_shapes_until_it_changes() {
+        let mut pane = Pane::new(Rect::new(10.0, 10.0, 100.0, 50.0));
+        pane.bg_color = Some(Color::new(0.0, 0.0, 0.0, 0.5));
+        let batch_of = |pane: &Pane| {
+            let mut batch = ShapeBatch::new();
+            assert!(pane.batch_shapes(&mut batch));
+            batch
+        };
+
+        let batch = batch_of(&pane);
+        assert_eq!(
+            batch.shapes(),
+            &[
+                Shape::FilledRect(Rect::new(10.0, 10.0, 100.0, 50.0), Color::new(0.0, 0.0, 0.0, 0.5)),
+                Shape::StrokedRect(Rect::new(10.0, 10.0, 100.0, 50.0), 1.0, scheme().pane_border),
+            ]
+        );
+        assert_eq!(batch_of(&pane), batch);
+
+        pane.translate(ggez::mint::Vector2 { x: 5.0, y: 0.0 });
+        assert_ne!(batch_of(&pane), batch);
+    }
+}
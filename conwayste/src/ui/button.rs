@@ -23,30 +23,48 @@ use chromatica::css;
 use ggez::event::MouseButton;
 use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
 use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse::CursorIcon;
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
 use id_tree::NodeId;
 
+use serde::Deserialize;
+
 use super::{
     common::{center, color_with_alpha, FontInfo},
     context,
     context::{EmitEvent, Event, EventType, Handled, KeyCodeOrChar, MoveCross, UIContext},
     label::Label,
+    textcache::TextCache,
     widget::Widget,
     UIError, UIResult,
 };
 
+use crate::constants::colors::scheme;
+use crate::i18n::tr_args;
+use crate::resources::Texture;
+
+/// What kind of action a button stands for, which picks its color from the color scheme.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Danger,
+}
+
 pub struct Button {
     id:               Option<NodeId>,
     z_index:          usize,
     pub label:        Label,
-    pub button_color: Color,
-    pub draw_mode:    DrawMode,
+    pub style:        ButtonStyle,
+    icon:             Option<Texture>, // drawn to the left of the text, as tall as it
     pub dimensions:   Rect,
-    pub hover:        bool, // is mouse hovering over this?
-    pub focused:      bool, // has keyboard focus?
-    pub borderless:   bool,
+    pub hover:        bool,                 // is mouse hovering over this?
+    pub focused:      bool,                 // has keyboard focus?
+    pub pressed:      bool,                 // is the mouse button held down on this?
+    pub enabled:      bool,                 // when false, the button is dimmed and clicks are ignored
     pub handler_data: context::HandlerData, // required for impl_emit_event!
 }
 
@@ -62,6 +80,8 @@ impl fmt::Debug for Button {
 
 const BUTTON_LABEL_PADDING_W: f32 = 16.0; // in pixels
 const BUTTON_LABEL_PADDING_H: f32 = 16.0; // in pixels
+const BUTTON_ICON_GAP: f32 = 8.0; // in pixels, between the icon and the text
+const BUTTON_PRESSED_SHADE: f32 = 0.7; // how much of the color's brightness is left while pressed
 
 /// A named widget that can be clicked to result in an occuring action.
 impl Button {
@@ -88,7 +108,7 @@ impl Button {
     ///     "TestButton"
     /// );
     ///
-    /// b.draw(ctx);
+    /// b.draw(ctx, texts);
     /// ```
     ///
     pub fn new(ctx: &mut Context, font_info: FontInfo, button_text: String) -> Self {
@@ -101,29 +121,33 @@ impl Button {
             color_with_alpha(css::WHITE, 0.1),
             label_position,
         );
-        let label_dims = label.rect();
-
-        let dimensions = Rect::new(
-            30.0,
-            20.0,
-            label_dims.w + BUTTON_LABEL_PADDING_W,
-            label_dims.h + BUTTON_LABEL_PADDING_H,
-        );
-
         let mut b = Button {
             id: None,
             z_index: std::usize::MAX,
             label,
-            button_color: color_with_alpha(css::DARKCYAN, 0.8),
-            draw_mode: DrawMode::fill(),
-            dimensions,
+            style: ButtonStyle::Secondary,
+            icon: None,
+            dimensions: Rect::new(30.0, 20.0, 0.0, 0.0),
             hover: false,
             focused: false,
-            borderless: false,
+            pressed: false,
+            enabled: true,
             handler_data: context::HandlerData::new(),
         };
+        let (min_w, min_h) = b.min_size();
+        b.dimensions.w = min_w;
+        b.dimensions.h = min_h;
         b.center_label_text();
 
+        // registered before the handler below, so that the press ends even if the button is disabled
+        b.on(EventType::MouseButtonHeld, Box::new(Button::press_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        b.on(EventType::Click, Box::new(Button::press_handler)).unwrap(); // unwrap OK b/c not being called within handler
+
+        // registered first, so that a disabled button's click handlers are never reached
+        b.on(EventType::Click, Box::new(Button::disabled_click_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+
         // setup handler to allow changing appearance when it has keyboard focus
         b.on(EventType::GainFocus, Box::new(Button::focus_change_handler))
             .unwrap(); // unwrap OK b/c not being called within handler
@@ -139,19 +163,100 @@ impl Button {
         b
     }
 
-    /// Centers the label's text to the dimensions of the button
+    /// Replaces the button's text. The button keeps its size.
+    pub fn set_text(&mut self, ctx: &mut Context, texts: &mut TextCache, text: String) {
+        self.label.set_text(ctx, texts, text);
+        self.center_label_text();
+    }
+
+    /// Shows `icon` to the left of the text, making the button wider if they don't both fit.
+    pub fn set_icon(&mut self, icon: Texture) {
+        self.icon = Some(icon);
+        let (min_w, _) = self.min_size();
+        self.dimensions.w = self.dimensions.w.max(min_w);
+        self.center_label_text();
+    }
+
+    /// The width taken by the icon and the gap after it, if there is an icon.
+    fn icon_width(&self) -> f32 {
+        match self.icon {
+            Some(_) => self.label.dimensions.h + BUTTON_ICON_GAP,
+            None => 0.0,
+        }
+    }
+
+    /// The smallest the button can be while still fitting its icon and text.
+    fn min_size(&self) -> (f32, f32) {
+        (
+            self.icon_width() + self.label.dimensions.w + BUTTON_LABEL_PADDING_W,
+            self.label.dimensions.h + BUTTON_LABEL_PADDING_H,
+        )
+    }
+
+    /// Centers the label's text, along with the icon to its left, to the dimensions of the button
     fn center_label_text(&mut self) {
         let text_dims = self.label.rect();
-        let tmp_label_rect = Rect::new(self.dimensions.x, self.dimensions.y, text_dims.w, text_dims.h);
+        let icon_w = self.icon_width();
+        let tmp_label_rect = Rect::new(self.dimensions.x, self.dimensions.y, icon_w + text_dims.w, text_dims.h);
         let label_center_point = center(&tmp_label_rect);
         let button_center = center(&self.dimensions);
 
         self.label.set_position(
-            self.dimensions.x + (button_center.x - label_center_point.x),
+            self.dimensions.x + (button_center.x - label_center_point.x) + icon_w,
             self.dimensions.y + (button_center.y - label_center_point.y),
         );
     }
 
+    /// The color of the button's style in the color scheme, shaded while pressed and dimmed while
+    /// disabled.
+    fn color(&self) -> Color {
+        let mut color = match self.style {
+            ButtonStyle::Primary => scheme().button_primary,
+            ButtonStyle::Secondary => scheme().button,
+            ButtonStyle::Danger => scheme().button_danger,
+        };
+        if self.pressed {
+            color.r *= BUTTON_PRESSED_SHADE;
+            color.g *= BUTTON_PRESSED_SHADE;
+            color.b *= BUTTON_PRESSED_SHADE;
+        }
+        if !self.enabled {
+            color.a *= 0.4;
+        }
+        color
+    }
+
+    fn draw_icon(&self, ctx: &mut Context) -> GameResult<()> {
+        let icon = match self.icon {
+            Some(ref icon) => icon,
+            None => return Ok(()),
+        };
+        let side = self.label.dimensions.h;
+        let (w, h) = icon.size();
+        let scale = side / w.max(h);
+        let dest = Point2 {
+            x: self.label.dimensions.x - self.icon_width() + (side - w * scale) / 2.0,
+            y: self.label.dimensions.y + (side - h * scale) / 2.0,
+        };
+        let param = DrawParam::default()
+            .src(icon.region)
+            .dest(dest)
+            .scale(Vector2 { x: scale, y: scale });
+        graphics::draw(ctx, &icon.image, param)
+    }
+
+    /// Keeps track of whether the button is held down: from when the mouse button is pressed on it
+    /// until the click that releases it.
+    fn press_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because this will always be Button
+        button.pressed = event.what == EventType::MouseButtonHeld && button.enabled;
+        Ok(Handled::NotHandled)
+    }
+
     fn mouse_move_handler(
         obj: &mut dyn EmitEvent,
         _uictx: &mut UIContext,
@@ -164,12 +269,26 @@ impl Button {
             }
             MoveCross::Exit => {
                 button.hover = false;
+                button.pressed = false; // dragged off, so releasing the mouse button won't click it
             }
             MoveCross::None => {}
         };
         Ok(Handled::NotHandled)
     }
 
+    fn disabled_click_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        _event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because this will always be Button
+        if button.enabled {
+            Ok(Handled::NotHandled)
+        } else {
+            Ok(Handled::Handled)
+        }
+    }
+
     fn focus_change_handler(
         obj: &mut dyn EmitEvent,
         _uictx: &mut UIContext,
@@ -195,7 +314,7 @@ impl Button {
         }
         // create a synthetic click event
         let mouse_point = button.position();
-        let click_event = Event::new_click(mouse_point, MouseButton::Left, false);
+        let click_event = Event::new_click(mouse_point, MouseButton::Left, event.modifiers);
         Ok(button.emit(&click_event, uictx)?)
     }
 }
@@ -217,17 +336,18 @@ impl Widget for Button {
         self.z_index = new_z_index;
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let draw_mode = if self.hover || self.focused {
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let draw_mode = if self.hover || self.focused || self.pressed {
             DrawMode::fill()
         } else {
             DrawMode::stroke(2.0)
         };
 
-        let button = graphics::Mesh::new_rectangle(ctx, draw_mode, self.dimensions, self.button_color)?;
+        let button = graphics::Mesh::new_rectangle(ctx, draw_mode, self.dimensions, self.color())?;
         graphics::draw(ctx, &button, DrawParam::default())?;
 
-        self.label.draw(ctx)?;
+        self.draw_icon(ctx)?;
+        self.label.draw(ctx, texts)?;
 
         Ok(())
     }
@@ -243,9 +363,8 @@ impl Widget for Button {
             }));
         }
 
-        if new_dims.w < self.label.dimensions.w + BUTTON_LABEL_PADDING_W
-            || new_dims.h < self.label.dimensions.h + BUTTON_LABEL_PADDING_H
-        {
+        let (min_w, min_h) = self.min_size();
+        if new_dims.w < min_w || new_dims.h < min_h {
             return Err(Box::new(UIError::InvalidDimensions {
                 reason: format!(
                     "Cannot set the Button's size {}x{} smaller than the space taken by the
@@ -253,8 +372,8 @@ impl Widget for Button {
                     new_dims.w,
                     new_dims.h,
                     self.label.text(),
-                    self.label.dimensions.w + BUTTON_LABEL_PADDING_W,
-                    self.label.dimensions.h + BUTTON_LABEL_PADDING_H,
+                    min_w,
+                    min_h,
                     self.id()
                 ),
             }));
@@ -287,8 +406,8 @@ impl Widget for Button {
             }));
         }
 
-        if w < self.label.dimensions.w + BUTTON_LABEL_PADDING_W || h < self.label.dimensions.h + BUTTON_LABEL_PADDING_H
-        {
+        let (min_w, min_h) = self.min_size();
+        if w < min_w || h < min_h {
             return Err(Box::new(UIError::InvalidDimensions {
                 reason: format!(
                     "Cannot set the width or height of Button {:?} smaller than
@@ -319,6 +438,14 @@ impl Widget for Button {
     fn accepts_keyboard_events(&self) -> bool {
         true
     }
+
+    fn accessible_name(&self) -> Option<String> {
+        Some(tr_args("a11y-button", &[("label", &self.label.textfrag.text)]))
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
 }
 
 impl_emit_event!(Button, self.handler_data);
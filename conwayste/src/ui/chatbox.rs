@@ -19,9 +19,13 @@
 use std::collections::VecDeque;
 use std::error::Error;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Instant;
 
-use ggez::graphics::{self, Color, DrawMode, DrawParam, FilterMode, Rect, Text};
+use ggez::event::MouseButton;
+use ggez::graphics::{self, DrawMode, DrawParam, FilterMode, Rect};
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
@@ -30,24 +34,79 @@ use id_tree::NodeId;
 use super::{
     common::FontInfo,
     context::{EmitEvent, Event, EventType, Handled, HandlerData, MoveCross, UIContext},
+    contextmenu::ContextMenu,
+    textcache::TextCache,
     widget::Widget,
+    wrap::wrap_words,
     UIError, UIResult,
 };
 
 use crate::constants::{self, colors::*};
+use crate::i18n::{tr, tr_args};
+
+/// What can be done to the sender of a chat message, from the menu that right-clicking it opens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SenderAction {
+    Mute,
+    Unmute,
+    Report,
+}
+
+/// The sender menu's items, in order.
+pub(super) const SENDER_ACTIONS: [SenderAction; 3] = [SenderAction::Mute, SenderAction::Unmute, SenderAction::Report];
+
+/// What the sender menu's items say, in the order of `SENDER_ACTIONS`.
+pub(super) fn sender_menu_items() -> Vec<String> {
+    vec![tr("chat-menu-mute"), tr("chat-menu-unmute"), tr("chat-menu-report")]
+}
+
+/// One conversation in a chatbox. Only the shown tab's messages are drawn.
+struct ChatTab {
+    name:     String,
+    messages: VecDeque<(Option<String>, String)>, // (sender, if it was another player, message)
+    wrapped:  VecDeque<(bool, String)>, // lines of the messages, and whether more of the message follows
+    typing:   Vec<(String, Instant)>, // (player, when we last heard they were typing)
+    unread:   usize,                  // messages from other players since the tab was last seen
+    scrolled: usize,                  // lines scrolled back from the newest
+}
+
+impl ChatTab {
+    fn new(name: String, history_lines: usize) -> Self {
+        ChatTab {
+            name,
+            messages: VecDeque::with_capacity(history_lines),
+            wrapped: VecDeque::new(),
+            typing: vec![],
+            unread: 0,
+            scrolled: 0,
+        }
+    }
+
+    /// What the tab is called in the tab bar, with how many messages in it are unread.
+    fn label(&self) -> String {
+        if self.unread > 0 {
+            format!("{} ({})", self.name, self.unread)
+        } else {
+            self.name.clone()
+        }
+    }
+}
 
 pub struct Chatbox {
     id:            Option<NodeId>,
     z_index:       usize,
     history_lines: usize,
-    color:         Color,
-    messages:      VecDeque<String>,
-    wrapped:       VecDeque<(bool, Text)>,
+    tabs:          Vec<ChatTab>,
+    shown_tab:     Arc<AtomicUsize>, // shared with the publish handles
     dimensions:    Rect,
     hover:         bool,
     font_info:     FontInfo,
-    msg_sender:    Sender<String>,
-    msg_receiver:  Receiver<String>,
+    msg_sender:    Sender<(usize, String)>, // (tab, message)
+    msg_receiver:  Receiver<(usize, String)>,
+    sender_menu:   Option<(String, ContextMenu)>, // (sender, menu of what can be done to them)
+    actions:       Vec<(SenderAction, String)>,   // chosen from sender menus, but not yet taken by the client
+    full_height:   Option<f32>,                   // while collapsed to a bar, the height to expand back to
+    flash:         Option<(String, Instant)>,     // newest message while collapsed, and when it arrived
     handler_data:  HandlerData,
 }
 
@@ -77,25 +136,28 @@ impl Chatbox {
     /// let font = Font::Default;
     /// let chatbox_font_info = common::FontInfo::new(ctx, font, Some(20.0));
     /// let chatbox = Chatbox::new(chatbox_font_info, 20);
-    /// checkbox.draw(ctx);
+    /// checkbox.draw(ctx, texts);
     /// ```
     ///
     pub fn new(font_info: FontInfo, history_lines: usize) -> Self {
         // TODO: affix to bottom left corner once "anchoring"/"gravity" is implemented
         let rect = *constants::DEFAULT_CHATBOX_RECT;
-        let (msg_tx, msg_rx) = channel::<String>();
+        let (msg_tx, msg_rx) = channel::<(usize, String)>();
         let mut chatbox = Chatbox {
             id: None,
             z_index: std::usize::MAX,
             history_lines,
-            color: *CHATBOX_BORDER_COLOR,
-            messages: VecDeque::with_capacity(history_lines),
-            wrapped: VecDeque::new(),
+            tabs: vec![ChatTab::new(String::new(), history_lines)],
+            shown_tab: Arc::new(AtomicUsize::new(0)),
             dimensions: rect,
             hover: false,
             font_info,
             msg_sender: msg_tx,
             msg_receiver: msg_rx,
+            sender_menu: None,
+            actions: vec![],
+            full_height: None,
+            flash: None,
             handler_data: HandlerData::new(),
         };
         chatbox
@@ -104,28 +166,136 @@ impl Chatbox {
         chatbox
             .on(EventType::MouseMove, Box::new(Chatbox::mouse_move_handler))
             .unwrap(); // unwrap OK b/c not being called within handler
+        chatbox.on(EventType::Click, Box::new(Chatbox::click_handler)).unwrap(); // unwrap OK b/c not being called within handler
+        chatbox
+            .on(EventType::LongPress, Box::new(Chatbox::long_press_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        chatbox
+            .on(EventType::Scroll, Box::new(Chatbox::scroll_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
         chatbox
     }
 
     /// Returns a handle that enables you to asynchronously publish messages to this chatbox.
     pub fn new_handle(&self) -> ChatboxPublishHandle {
-        ChatboxPublishHandle::new(self.msg_sender.clone())
+        ChatboxPublishHandle::new(self.msg_sender.clone(), self.shown_tab.clone())
     }
 
-    fn update_handler(
-        obj: &mut dyn EmitEvent,
-        _uictx: &mut UIContext,
-        _evt: &Event,
-    ) -> Result<Handled, Box<dyn Error>> {
+    /// Adds a tab for another conversation, returning its index. The first tab is made along with
+    /// the chatbox; once there is more than one, their names are shown along the top and clicking
+    /// one shows it.
+    pub fn add_tab(&mut self, name: String) -> usize {
+        self.tabs.push(ChatTab::new(name, self.history_lines));
+        self.tabs.len() - 1
+    }
+
+    pub fn set_tab_name(&mut self, tab: usize, name: String) {
+        if let Some(chat_tab) = self.tabs.get_mut(tab) {
+            chat_tab.name = name;
+        }
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    /// Index of the tab whose messages are drawn.
+    pub fn shown_tab(&self) -> usize {
+        self.shown_tab.load(Ordering::Relaxed)
+    }
+
+    pub fn show_tab(&mut self, tab: usize) {
+        if tab < self.tabs.len() {
+            self.shown_tab.store(tab, Ordering::Relaxed);
+            if !self.is_collapsed() {
+                self.tabs[tab].unread = 0;
+            }
+        }
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.full_height.is_some()
+    }
+
+    /// Collapses the chatbox to a bar one line tall, or expands it back. While collapsed, the bar
+    /// counts the unread messages and briefly shows each new one; clicking it expands the chatbox.
+    /// The chatbox keeps its top edge, so whatever is below it has to be moved to follow.
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        if collapsed == self.is_collapsed() {
+            return;
+        }
+        if collapsed {
+            self.full_height = Some(self.dimensions.h);
+            self.dimensions.h = self.collapsed_height();
+            self.sender_menu = None;
+        } else {
+            self.dimensions.h = self.full_height.take().unwrap(); // unwrap OK because it was collapsed
+            self.flash = None;
+            let shown_tab = self.shown_tab();
+            self.tabs[shown_tab].unread = 0;
+        }
+    }
+
+    /// Height of the bar the chatbox collapses to.
+    fn collapsed_height(&self) -> f32 {
+        self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING * 2.0
+    }
+
+    /// Number of messages from other players that haven't been seen yet, in all tabs.
+    pub fn unread_count(&self) -> usize {
+        self.tabs.iter().map(|chat_tab| chat_tab.unread).sum()
+    }
+
+    /// Height of the row of tab names, or zero if there is only one tab.
+    fn tab_bar_height(&self) -> f32 {
+        if self.tabs.len() > 1 {
+            self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING
+        } else {
+            0.0
+        }
+    }
+
+    fn tab_name_rect(&self, tab: usize) -> Rect {
+        let char_width = self.font_info.char_dimensions.x;
+        let name_width = |chat_tab: &ChatTab| (chat_tab.label().chars().count() + 2) as f32 * char_width;
+        let x = self.tabs[..tab].iter().map(name_width).sum::<f32>();
+        Rect::new(
+            self.dimensions.x + x,
+            self.dimensions.y,
+            name_width(&self.tabs[tab]),
+            self.tab_bar_height(),
+        )
+    }
+
+    fn update_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event) -> Result<Handled, Box<dyn Error>> {
         let chatbox = obj.downcast_mut::<Chatbox>().unwrap(); // unwrap OK because it's always a Chatbox
+        let mut changed = false;
+        for chat_tab in chatbox.tabs.iter_mut() {
+            let typing = chat_tab.typing.len();
+            chat_tab
+                .typing
+                .retain(|(_, heard_at)| heard_at.elapsed() < constants::CHAT_TYPING_TIMEOUT);
+            changed |= chat_tab.typing.len() != typing;
+        }
         loop {
-            if let Ok(msg) = chatbox.msg_receiver.try_recv() {
+            if let Ok((tab, msg)) = chatbox.msg_receiver.try_recv() {
                 // TODO: maybe we should batch add these? Benchmark!
-                chatbox.add_message(msg);
+                chatbox.add_message_to_tab(tab, msg);
+                changed = true;
             } else {
                 break;
             }
         }
+        // the newest message flashed while collapsed goes away on its own
+        if let Some((_, arrived_at)) = chatbox.flash {
+            if arrived_at.elapsed() >= constants::CHAT_FLASH_DURATION {
+                chatbox.flash = None;
+                changed = true;
+            }
+        }
+        if changed {
+            uictx.request_redraw();
+        }
         Ok(Handled::NotHandled)
     }
 
@@ -141,13 +311,192 @@ impl Chatbox {
             }
             MoveCross::Exit => {
                 chatbox.hover = false;
+                chatbox.sender_menu = None;
             }
             MoveCross::None => {}
         };
         Ok(Handled::NotHandled)
     }
 
-    /// Adds a message to the chatbox
+    fn click_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let chatbox = obj.downcast_mut::<Chatbox>().unwrap(); // unwrap OK because it's always a Chatbox
+        let point = event.point.unwrap(); // unwrap OK because a Click always has a point
+
+        // Any click closes the sender menu, but only one on an item does something
+        if let Some((sender, menu)) = chatbox.sender_menu.take() {
+            if let Some(item) = menu.item_at(point) {
+                chatbox.actions.push((SENDER_ACTIONS[item], sender));
+            }
+            return Ok(Handled::Handled);
+        }
+        if chatbox.is_collapsed() {
+            chatbox.set_collapsed(false);
+            return Ok(Handled::Handled);
+        }
+        if event.button == Some(MouseButton::Right) {
+            if let Some(sender) = chatbox.sender_at(point) {
+                let sender = sender.to_owned();
+                chatbox.open_sender_menu(sender, point);
+                return Ok(Handled::Handled);
+            }
+        }
+        if chatbox.tabs.len() < 2 {
+            return Ok(Handled::NotHandled);
+        }
+        if let Some(tab) = (0..chatbox.tabs.len()).find(|&tab| chatbox.tab_name_rect(tab).contains(point)) {
+            chatbox.show_tab(tab);
+            return Ok(Handled::Handled);
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    /// Pressing and holding on the sender of a message opens the same menu as right-clicking it.
+    fn long_press_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let chatbox = obj.downcast_mut::<Chatbox>().unwrap(); // unwrap OK because it's always a Chatbox
+        let point = event.point.unwrap(); // unwrap OK because a LongPress always has a point
+        if chatbox.sender_menu.is_some() {
+            return Ok(Handled::NotHandled);
+        }
+        if let Some(sender) = chatbox.sender_at(point) {
+            let sender = sender.to_owned();
+            chatbox.open_sender_menu(sender, point);
+            return Ok(Handled::Handled);
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    /// The mouse wheel scrolls back through older messages, and forward again.
+    fn scroll_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let chatbox = obj.downcast_mut::<Chatbox>().unwrap(); // unwrap OK because it's always a Chatbox
+        if chatbox.is_collapsed() {
+            return Ok(Handled::NotHandled);
+        }
+        let delta = event.scroll_delta.unwrap(); // unwrap OK because a Scroll always has a delta
+        chatbox.scroll((delta.y * constants::CHATBOX_SCROLL_LINES).round() as isize);
+        Ok(Handled::Handled)
+    }
+
+    /// How many lines of messages fit in the chatbox, below the tab bar and above who is typing.
+    fn message_rows(&self) -> usize {
+        let rows = ((self.dimensions.h - self.tab_bar_height())
+            / (self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING)) as usize;
+        match self.typing_line() {
+            Some(_) => rows.saturating_sub(1),
+            None => rows,
+        }
+    }
+
+    /// Scrolls the shown tab back by `lines` toward older messages, or forward if negative, as far
+    /// as there are messages to show.
+    pub fn scroll(&mut self, lines: isize) {
+        let rows = self.message_rows();
+        let shown_tab = self.shown_tab();
+        let chat_tab = &mut self.tabs[shown_tab];
+        let most = chat_tab.wrapped.len().saturating_sub(rows) as isize;
+        chat_tab.scrolled = (chat_tab.scrolled as isize + lines).max(0).min(most) as usize;
+    }
+
+    /// The sender of the message drawn at `point`, if another player sent it.
+    fn sender_at(&self, point: Point2<f32>) -> Option<&str> {
+        if self.is_collapsed()
+            || !self.dimensions.contains(point)
+            || point.y < self.dimensions.y + self.tab_bar_height()
+        {
+            return None;
+        }
+        // Lines are drawn from the bottom up, newest first, above who is typing
+        let line = ((self.dimensions.bottom() - point.y) / self.font_info.char_dimensions.y) as usize;
+        let line = if self.typing_line().is_some() {
+            line.checked_sub(1)?
+        } else {
+            line
+        };
+        let chat_tab = &self.tabs[self.shown_tab()];
+        let line = line + chat_tab.scrolled;
+        let mut messages_after = 0;
+        for (i, (has_more, _)) in chat_tab.wrapped.iter().rev().enumerate() {
+            if i > 0 && !has_more {
+                messages_after += 1; // the last line of an older message
+            }
+            if i == line {
+                let index = chat_tab.messages.len().checked_sub(messages_after + 1)?;
+                return chat_tab.messages[index].0.as_deref();
+            }
+        }
+        None
+    }
+
+    /// Opens the menu of what can be done to `sender` at `point`, moved if need be to fit in the
+    /// chatbox, since clicks outside it don't reach the chatbox.
+    fn open_sender_menu(&mut self, sender: String, point: Point2<f32>) {
+        let mut menu = ContextMenu::new(sender_menu_items(), point, self.font_info);
+        let rect = menu.rect();
+        menu.move_to(Point2 {
+            x: rect.x.min(self.dimensions.right() - rect.w).max(self.dimensions.x),
+            y: rect.y.min(self.dimensions.bottom() - rect.h).max(self.dimensions.y),
+        });
+        self.sender_menu = Some((sender, menu));
+    }
+
+    /// Shows that `name` is typing a message for one of the chatbox's tabs, until their message
+    /// arrives or `CHAT_TYPING_TIMEOUT` passes without being told again.
+    pub fn set_typing(&mut self, tab: usize, name: String) {
+        let chat_tab = match self.tabs.get_mut(tab) {
+            Some(chat_tab) => chat_tab,
+            None => return,
+        };
+        let now = Instant::now();
+        match chat_tab.typing.iter_mut().find(|(typist, _)| *typist == name) {
+            Some((_, heard_at)) => *heard_at = now,
+            None => chat_tab.typing.push((name, now)),
+        }
+    }
+
+    /// The line saying who is typing in the shown tab, if anyone is.
+    fn typing_line(&self) -> Option<String> {
+        let typing = &self.tabs[self.shown_tab()].typing;
+        match typing.len() {
+            0 => None,
+            1 => Some(tr_args("chat-typing-one", &[("name", &typing[0].0)])),
+            _ => {
+                let names = typing
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some(tr_args("chat-typing-many", &[("names", &names)]))
+            }
+        }
+    }
+
+    /// Returns the actions chosen from sender menus since the last call, oldest first.
+    pub fn take_sender_actions(&mut self) -> Vec<(SenderAction, String)> {
+        std::mem::take(&mut self.actions)
+    }
+
+    /// Removes every message from `sender`, such as when they are muted.
+    pub fn remove_messages_from(&mut self, sender: &str) {
+        for chat_tab in self.tabs.iter_mut() {
+            chat_tab
+                .messages
+                .retain(|(msg_sender, _)| msg_sender.as_deref() != Some(sender));
+        }
+        self.reflow_messages();
+    }
+
+    /// Adds a message to the chatbox's first tab
     ///
     /// # Arguments
     /// * `msg` - New chat message
@@ -167,22 +516,59 @@ impl Chatbox {
     /// chatpane.add(Box::new(chatbox));
     ///
     /// //...
-    /// chatbox.draw(ctx);
+    /// chatbox.draw(ctx, texts);
     /// ```
     ///
     pub fn add_message(&mut self, msg: String) {
-        let mut texts = Chatbox::reflow_message(&msg, self.dimensions.w, &self.font_info);
-        self.wrapped.append(&mut texts);
+        self.add_message_to_tab(0, msg);
+    }
+
+    /// Adds a message to one of the chatbox's tabs. Messages for a tab that doesn't exist are dropped.
+    pub fn add_message_to_tab(&mut self, tab: usize, msg: String) {
+        self.add_chat(tab, None, msg);
+    }
+
+    /// Adds a message from another player to one of the chatbox's tabs. Right-clicking it opens a
+    /// menu of what can be done to `sender`.
+    pub fn add_message_from(&mut self, tab: usize, sender: String, msg: String) {
+        self.add_chat(tab, Some(sender), msg);
+    }
+
+    fn add_chat(&mut self, tab: usize, sender: Option<String>, msg: String) {
+        let mut lines = Chatbox::reflow_message(&msg, self.dimensions.w, &self.font_info);
+        let rows = self.message_rows();
+        let seen = !self.is_collapsed() && tab == self.shown_tab();
+        if sender.is_some() && self.is_collapsed() && tab < self.tabs.len() {
+            self.flash = Some((msg.clone(), Instant::now()));
+        }
+        let chat_tab = match self.tabs.get_mut(tab) {
+            Some(chat_tab) => chat_tab,
+            None => {
+                warn!("Dropping message for nonexistent chat tab {}: {:?}", tab, msg);
+                return;
+            }
+        };
+        if chat_tab.scrolled > 0 {
+            // keep showing the same lines, rather than scrolling them away
+            chat_tab.scrolled += lines.len();
+        }
+        chat_tab.wrapped.append(&mut lines);
 
-        self.messages.push_back(msg);
+        if let Some(ref sender) = sender {
+            chat_tab.typing.retain(|(typist, _)| typist != sender);
+            if !seen {
+                chat_tab.unread += 1;
+            }
+        }
+        chat_tab.messages.push_back((sender, msg));
 
-        // Remove any message(s) that exceed the alloted history. Any wrapped texts created from the
+        // Remove any message(s) that exceed the alloted history. Any wrapped lines created from the
         // message(s) also need to be removed
-        while self.messages.len() > self.history_lines {
-            self.messages.pop_front();
+        while chat_tab.messages.len() > self.history_lines {
+            chat_tab.messages.pop_front();
 
             let mut count = 0;
-            for (has_more, _) in self.wrapped.iter() {
+            for (has_more, _) in chat_tab.wrapped.iter() {
                 if *has_more {
                     count += 1;
                 } else {
@@ -190,90 +576,76 @@ impl Chatbox {
                 }
             }
             for _ in 0..count + 1 {
-                self.wrapped.remove(0);
+                chat_tab.wrapped.remove(0);
             }
         }
+        chat_tab.scrolled = chat_tab.scrolled.min(chat_tab.wrapped.len().saturating_sub(rows));
     }
 
-    fn reflow_messages(&mut self) {
-        self.wrapped.clear();
-        for msg in self.messages.iter_mut() {
-            let mut texts = Chatbox::reflow_message(msg, self.dimensions.w, &self.font_info);
-            self.wrapped.append(&mut texts);
+    /// Changes how many messages are kept, dropping the oldest if there are now too many.
+    pub fn set_history_lines(&mut self, history_lines: usize) {
+        self.history_lines = history_lines;
+        for chat_tab in self.tabs.iter_mut() {
+            if chat_tab.messages.len() > history_lines {
+                let excess = chat_tab.messages.len() - history_lines;
+                chat_tab.messages.drain(..excess);
+            }
         }
+        self.reflow_messages();
     }
 
-    fn count_chars(msg: &str) -> usize {
-        let mut count = 0;
-        for _ in msg.chars() {
-            count += 1;
+    /// Moves and resizes the chatbox to `rect`, its size when expanded, and shows the messages in
+    /// `font_info`, such as a bigger font for streaming. A collapsed chatbox stays collapsed, and
+    /// expands to the new size.
+    pub fn set_layout(&mut self, mut rect: Rect, font_info: FontInfo) -> UIResult<()> {
+        if rect.w == 0.0 || rect.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Chatbox {:?} to zero", self.id()),
+            }));
         }
-        count
+        self.font_info = font_info;
+        if self.is_collapsed() {
+            self.full_height = Some(rect.h);
+            rect.h = self.collapsed_height();
+        }
+        self.dimensions = rect;
+        self.reflow_messages();
+        Ok(())
     }
 
-    /// Breaks the message up into segments that are at most `width` long for the provided `font_info`
-    fn reflow_message(msg: &str, width: f32, font_info: &FontInfo) -> VecDeque<(bool, Text)> {
-        let mut texts = VecDeque::new();
-        let max_chars_per_line = (width / font_info.char_dimensions.x) as usize;
-        let mut s = String::with_capacity(max_chars_per_line);
-
-        let mut chars_added = 0;
-        for word in msg.split_whitespace() {
-            let word_chars = Chatbox::count_chars(word);
-
-            // If the word can fit on the next line, but not the current line
-            if chars_added != 0 && chars_added + word_chars > max_chars_per_line && word_chars <= max_chars_per_line {
-                let mut text = Text::new(s.clone());
-                font_info.apply(&mut text);
-                texts.push_back((true, text));
-                s.clear();
-                chars_added = 0;
-            }
-
-            if word_chars > max_chars_per_line {
-                // If word is too long to fit on a line, then break the word into multiple lines
-                for ch in word.chars() {
-                    if chars_added == max_chars_per_line {
-                        let mut text = Text::new(s.clone());
-                        font_info.apply(&mut text);
-                        texts.push_back((true, text));
-                        s.clear();
-                        chars_added = 0;
-                    }
-
-                    s.push(ch);
-                    chars_added += 1;
-                }
-                // add a space after the long word and continue forward
-                if !s.is_empty() {
-                    s.push(' ');
-                    chars_added += 1;
-                }
-                continue;
-            }
-
-            for ch in word.chars() {
-                s.push(ch);
-                chars_added += 1;
-            }
-
-            if chars_added + 1 <= max_chars_per_line {
-                s.push(' ');
-                chars_added += 1;
+    fn reflow_messages(&mut self) {
+        for chat_tab in self.tabs.iter_mut() {
+            chat_tab.wrapped.clear();
+            chat_tab.scrolled = 0; // the lines scrolled past are no longer the same
+            for (_, msg) in chat_tab.messages.iter() {
+                let mut lines = Chatbox::reflow_message(msg, self.dimensions.w, &self.font_info);
+                chat_tab.wrapped.append(&mut lines);
             }
         }
+    }
 
-        if !s.is_empty() {
-            let mut text = Text::new(s.clone());
-            font_info.apply(&mut text);
-            texts.push_back((true, text));
-        }
-
-        if let Some((ref mut has_more_texts, _)) = texts.back_mut() {
-            *has_more_texts = false;
-        }
-
-        texts
+    /// Breaks the message up into segments that are at most `width` long for the provided `font_info`.
+    /// Lines the sender broke the message into stay separate. Each line's text is only made when it
+    /// is drawn, from the cache, so reflowing to the same lines doesn't lay them out again.
+    fn reflow_message(msg: &str, width: f32, font_info: &FontInfo) -> VecDeque<(bool, String)> {
+        let max_chars_per_line = (width / font_info.char_dimensions.x) as usize;
+        let lines: Vec<String> = msg
+            .split('\n')
+            .flat_map(|line| {
+                let wrapped = wrap_words(line, max_chars_per_line);
+                if wrapped.is_empty() {
+                    vec![String::new()] // keep blank lines
+                } else {
+                    wrapped
+                }
+            })
+            .collect();
+        let line_count = lines.len();
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| (i + 1 < line_count, line)) // whether more lines of this message follow
+            .collect()
     }
 }
 
@@ -352,7 +724,7 @@ impl Widget for Chatbox {
         self.dimensions.translate(dest);
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
         // TODO: Add support to scroll through history
         if self.hover {
             // Add in a teal border while hovered. Color checkbox differently to indicate hovered state.
@@ -362,8 +734,12 @@ impl Widget for Chatbox {
                 self.dimensions.w + constants::CHATBOX_BORDER_PIXELS / 2.0 + 2.0,
                 self.dimensions.h + constants::CHATBOX_BORDER_PIXELS / 2.0 + 2.0,
             );
-            let hovered_border =
-                graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(2.0), border_rect, *CHATBOX_BORDER_ON_HOVER_COLOR)?;
+            let hovered_border = graphics::Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(2.0),
+                border_rect,
+                scheme().chatbox_border_on_hover,
+            )?;
             graphics::draw(ctx, &hovered_border, DrawParam::default())?;
         }
 
@@ -377,12 +753,41 @@ impl Widget for Chatbox {
             ctx,
             DrawMode::stroke(constants::CHATBOX_BORDER_PIXELS),
             text_entry_rect,
-            self.color,
+            scheme().chatbox_border,
         )?;
         graphics::draw(ctx, &border, DrawParam::default())?;
 
-        let mut max_lines =
-            (self.dimensions.h / (self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING)) as u32;
+        if self.is_collapsed() {
+            return self.draw_collapsed(ctx, texts);
+        }
+
+        let shown_tab = self.shown_tab();
+        if self.tabs.len() > 1 {
+            for (i, chat_tab) in self.tabs.iter().enumerate() {
+                let name_rect = self.tab_name_rect(i);
+                let color = if i == shown_tab {
+                    scheme().chatbox_text
+                } else {
+                    scheme().chatbox_inactive_border
+                };
+                let text = texts.get(self.font_info.fragment(chat_tab.label()));
+                let point = Point2 {
+                    x: name_rect.x + self.font_info.char_dimensions.x,
+                    y: name_rect.y,
+                };
+                graphics::queue_text(ctx, &text, point, Some(color));
+            }
+            let shown_name_border = graphics::Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(constants::CHATBOX_BORDER_PIXELS),
+                self.tab_name_rect(shown_tab),
+                scheme().chatbox_border,
+            )?;
+            graphics::draw(ctx, &shown_name_border, DrawParam::default())?;
+        }
+
+        let mut max_lines = ((self.dimensions.h - self.tab_bar_height())
+            / (self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING)) as u32;
 
         // Draw as many messages as we can fit in the dimensions of the chatbox, newest at the bottom
         let mut i = 0;
@@ -391,7 +796,22 @@ impl Widget for Chatbox {
             y: self.dimensions.y + self.dimensions.h - self.font_info.char_dimensions.y,
         };
 
-        for (_, wrapped_text) in self.wrapped.iter().rev() {
+        // Who is typing goes below the newest message
+        if let Some(typing) = self.typing_line() {
+            if max_lines > 0 {
+                let text = texts.get(self.font_info.fragment(typing));
+                let point = Point2 {
+                    x: bottom_left_corner.x + constants::CHATBOX_BORDER_PIXELS + 1.0,
+                    y: bottom_left_corner.y,
+                };
+                graphics::queue_text(ctx, &text, point, Some(scheme().chatbox_inactive_border));
+                max_lines -= 1;
+                i += 1;
+            }
+        }
+
+        let chat_tab = &self.tabs[shown_tab];
+        for (_, line) in chat_tab.wrapped.iter().rev().skip(chat_tab.scrolled) {
             if max_lines == 0 {
                 break;
             }
@@ -399,13 +819,18 @@ impl Widget for Chatbox {
                 x: bottom_left_corner.x + constants::CHATBOX_BORDER_PIXELS + 1.0,
                 y: bottom_left_corner.y - (i as f32 * self.font_info.char_dimensions.y),
             };
-            graphics::queue_text(ctx, wrapped_text, point, Some(*CHATBOX_TEXT_COLOR));
+            let text = texts.get(self.font_info.fragment(line.as_str()));
+            graphics::queue_text(ctx, &text, point, Some(scheme().chatbox_text));
             max_lines -= 1;
             i += 1;
         }
 
         graphics::draw_queued_text(ctx, DrawParam::default(), None, FilterMode::Linear)?;
 
+        if let Some((_, ref menu)) = self.sender_menu {
+            menu.draw(ctx)?;
+        }
+
         Ok(())
     }
 
@@ -414,22 +839,93 @@ impl Widget for Chatbox {
     }
 }
 
+impl Chatbox {
+    /// Draws the bar the chatbox is collapsed to: the newest message for a few seconds after it
+    /// arrives, and a badge with the number of unread messages on the right.
+    fn draw_collapsed(&self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let char_width = self.font_info.char_dimensions.x;
+        let unread = self.unread_count();
+        let badge = if unread > 0 {
+            Some(tr_args("chat-unread", &[("count", &unread)]))
+        } else {
+            None
+        };
+        let badge_width = badge
+            .as_ref()
+            .map_or(0.0, |badge| (badge.chars().count() + 2) as f32 * char_width);
+
+        let point = Point2 {
+            x: self.dimensions.x + constants::CHATBOX_BORDER_PIXELS + 1.0,
+            y: self.dimensions.y + constants::CHATBOX_LINE_SPACING,
+        };
+        let flashed = match self.flash {
+            Some((ref msg, arrived_at)) if arrived_at.elapsed() < constants::CHAT_FLASH_DURATION => {
+                Chatbox::reflow_message(msg, self.dimensions.w - badge_width, &self.font_info)
+                    .pop_front()
+                    .map(|(_, line)| texts.get(self.font_info.fragment(line)))
+            }
+            _ => None,
+        };
+        match flashed {
+            Some(text) => graphics::queue_text(ctx, &text, point, Some(scheme().chatbox_text)),
+            None => {
+                let text = texts.get(self.font_info.fragment(tr("chat-collapsed")));
+                graphics::queue_text(ctx, &text, point, Some(scheme().chatbox_inactive_border));
+            }
+        }
+
+        if let Some(badge) = badge {
+            let badge_rect = Rect::new(
+                self.dimensions.right() - badge_width,
+                self.dimensions.y,
+                badge_width,
+                self.dimensions.h,
+            );
+            let badge_border = graphics::Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(constants::CHATBOX_BORDER_PIXELS),
+                badge_rect,
+                scheme().chatbox_border_on_hover,
+            )?;
+            graphics::draw(ctx, &badge_border, DrawParam::default())?;
+            let text = texts.get(self.font_info.fragment(badge));
+            let badge_point = Point2 {
+                x: badge_rect.x + char_width,
+                y: point.y,
+            };
+            graphics::queue_text(ctx, &text, badge_point, Some(scheme().chatbox_text));
+        }
+
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, FilterMode::Linear)
+    }
+}
+
 widget_from_id!(Chatbox);
 impl_emit_event!(Chatbox, self.handler_data);
 
 pub struct ChatboxPublishHandle {
-    msg_sender: Sender<String>,
+    msg_sender: Sender<(usize, String)>,
+    shown_tab:  Arc<AtomicUsize>,
 }
 
 impl ChatboxPublishHandle {
     pub fn add_message(&mut self, msg: String) {
-        self.msg_sender.send(msg).unwrap_or_else(|_e| {
+        self.add_message_to_tab(0, msg);
+    }
+
+    pub fn add_message_to_tab(&mut self, tab: usize, msg: String) {
+        self.msg_sender.send((tab, msg)).unwrap_or_else(|_e| {
             error!("Chatbox has been dropped!");
         });
     }
 
-    pub fn new(msg_sender: Sender<String>) -> Self {
-        ChatboxPublishHandle { msg_sender }
+    /// Index of the tab the chatbox is showing.
+    pub fn shown_tab(&self) -> usize {
+        self.shown_tab.load(Ordering::Relaxed)
+    }
+
+    pub fn new(msg_sender: Sender<(usize, String)>, shown_tab: Arc<AtomicUsize>) -> Self {
+        ChatboxPublishHandle { msg_sender, shown_tab }
     }
 }
 
@@ -458,17 +954,40 @@ mod tests {
 
     // Read the next item from the iterator and compare it. Trailing whitespace is removed before
     // comparison.
-    fn compare_next(text_iter: &mut vec_deque::Iter<(bool, Text)>, expected: &str) {
-        assert_eq!(text_iter.next().unwrap().1.contents().trim_end(), expected.trim_end());
+    fn compare_next(text_iter: &mut vec_deque::Iter<(bool, String)>, expected: &str) {
+        assert_eq!(text_iter.next().unwrap().1.trim_end(), expected.trim_end());
     }
 
     // Tests
+    #[test]
+    fn chatbox_scroll_stays_within_history() {
+        let mut cb = max_chars_chatbox(20);
+        for i in 0..20 {
+            cb.add_message(format!("message {}", i));
+        }
+        let rows = cb.message_rows();
+        assert!(rows < 20);
+        let most = 20 - rows;
+
+        cb.scroll(100);
+        assert_eq!(cb.tabs[0].scrolled, most);
+        cb.scroll(-1);
+        assert_eq!(cb.tabs[0].scrolled, most - 1);
+        // a new message pushes out the oldest; the same lines stay in view while scrolled back
+        cb.add_message("newest".to_owned());
+        assert_eq!(cb.tabs[0].scrolled, most);
+        cb.scroll(-100);
+        assert_eq!(cb.tabs[0].scrolled, 0);
+        cb.add_message("newer still".to_owned());
+        assert_eq!(cb.tabs[0].scrolled, 0);
+    }
+
     #[test]
     fn chatbox_reflow_all_fit() {
         let mut cb = max_chars_chatbox(20);
         cb.add_message("what a great game".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "what a great game");
         assert!(text_iter.next().is_none());
     }
@@ -479,7 +998,7 @@ mod tests {
         let mut cb = max_chars_chatbox(s.len()); // note: won't work if any multi-byte characters
         cb.add_message(s.clone());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, &s);
         assert!(text_iter.next().is_none());
     }
@@ -489,7 +1008,7 @@ mod tests {
         let mut cb = max_chars_chatbox(12);
         cb.add_message("what a great game".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "what a great");
         compare_next(&mut text_iter, "game");
         assert!(text_iter.next().is_none());
@@ -500,7 +1019,7 @@ mod tests {
         let mut cb = max_chars_chatbox(15);
         cb.add_message("what a great game".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "what a great");
         compare_next(&mut text_iter, "game");
         assert!(text_iter.next().is_none());
@@ -511,7 +1030,7 @@ mod tests {
         let mut cb = max_chars_chatbox(13);
         cb.add_message("what a great game".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "what a great");
         compare_next(&mut text_iter, "game");
         assert!(text_iter.next().is_none());
@@ -522,7 +1041,7 @@ mod tests {
         let mut cb = max_chars_chatbox(9);
         cb.add_message("what an entertaining game".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "what an e");
         compare_next(&mut text_iter, "ntertaini");
         compare_next(&mut text_iter, "ng game");
@@ -534,9 +1053,124 @@ mod tests {
         let mut cb = max_chars_chatbox(10);
         cb.add_message("entertaining".to_owned());
         cb.reflow_messages();
-        let mut text_iter = cb.wrapped.iter();
+        let mut text_iter = cb.tabs[0].wrapped.iter();
         compare_next(&mut text_iter, "entertaini");
         compare_next(&mut text_iter, "ng");
         assert!(text_iter.next().is_none());
     }
+
+    #[test]
+    fn chatbox_reflow_keeps_line_breaks() {
+        let mut cb = max_chars_chatbox(10);
+        cb.add_message("one two\n\nthree four five".to_owned());
+        let mut text_iter = cb.tabs[0].wrapped.iter();
+        compare_next(&mut text_iter, "one two");
+        compare_next(&mut text_iter, "");
+        compare_next(&mut text_iter, "three four");
+        compare_next(&mut text_iter, "five");
+        assert!(text_iter.next().is_none());
+        assert_eq!(cb.tabs[0].wrapped.iter().filter(|(has_more, _)| !has_more).count(), 1);
+    }
+
+    #[test]
+    fn chatbox_tabs_keep_messages_apart() {
+        let mut cb = max_chars_chatbox(20);
+        let lobby = cb.add_tab("lobby".to_owned());
+        cb.add_message("in the room".to_owned());
+        cb.add_message_to_tab(lobby, "in the lobby".to_owned());
+        let mut text_iter = cb.tabs[lobby].wrapped.iter();
+        compare_next(&mut text_iter, "in the lobby");
+        assert!(text_iter.next().is_none());
+        assert_eq!(cb.tabs[0].messages.len(), 1);
+
+        cb.show_tab(lobby);
+        assert_eq!(cb.new_handle().shown_tab(), lobby);
+        cb.show_tab(5); // no such tab
+        assert_eq!(cb.shown_tab(), lobby);
+    }
+
+    #[test]
+    fn chatbox_sender_at_finds_message_under_point() {
+        let mut cb = max_chars_chatbox(10);
+        cb.add_message("welcome".to_owned());
+        cb.add_message_from(0, "alice".to_owned(), "alice: hello there".to_owned());
+        assert_eq!(cb.tabs[0].wrapped.len(), 4);
+
+        // Lines are 5 pixels tall, from the bottom of the chatbox at 123 up
+        let at = |y| Point2 { x: 10.0, y };
+        assert_eq!(cb.sender_at(at(122.0)), Some("alice"));
+        assert_eq!(cb.sender_at(at(110.0)), Some("alice"));
+        assert_eq!(cb.sender_at(at(105.0)), None); // "welcome" has no sender
+        assert_eq!(cb.sender_at(at(90.0)), None); // above the messages
+
+        cb.remove_messages_from("alice");
+        assert_eq!(cb.tabs[0].messages.len(), 1);
+        let mut text_iter = cb.tabs[0].wrapped.iter();
+        compare_next(&mut text_iter, "welcome");
+        assert!(text_iter.next().is_none());
+    }
+
+    #[test]
+    fn chatbox_typing_ends_when_their_message_arrives() {
+        let mut cb = max_chars_chatbox(10);
+        cb.set_typing(0, "alice".to_owned());
+        cb.set_typing(0, "bob".to_owned());
+        cb.set_typing(0, "alice".to_owned());
+        cb.set_typing(3, "carol".to_owned()); // no such tab
+        assert_eq!(cb.tabs[0].typing.len(), 2);
+
+        cb.add_message_from(0, "alice".to_owned(), "alice: hi".to_owned());
+        assert_eq!(cb.tabs[0].typing.len(), 1);
+        assert_eq!(cb.tabs[0].typing[0].0, "bob");
+
+        // The typing line takes the bottom line, so the message is one line up
+        let at = |y| Point2 { x: 10.0, y };
+        assert_eq!(cb.sender_at(at(122.0)), None);
+        assert_eq!(cb.sender_at(at(117.0)), Some("alice"));
+    }
+
+    #[test]
+    fn chatbox_counts_unread_messages_until_seen() {
+        let mut cb = max_chars_chatbox(10);
+        let lobby = cb.add_tab("lobby".to_owned());
+        cb.add_message_from(lobby, "bob".to_owned(), "bob: hey".to_owned());
+        assert_eq!(cb.tabs[lobby].label(), "lobby (1)");
+
+        cb.set_collapsed(true);
+        assert_eq!(cb.rect().h, 9.0); // one line
+        cb.add_message("you were muted".to_owned()); // not from another player
+        cb.add_message_from(0, "alice".to_owned(), "alice: hi".to_owned());
+        assert_eq!(cb.unread_count(), 2);
+        assert_eq!(cb.flash.as_ref().unwrap().0, "alice: hi");
+        assert_eq!(cb.sender_at(Point2 { x: 10.0, y: 5.0 }), None);
+
+        cb.set_collapsed(false);
+        assert_eq!(cb.rect().h, 123.0);
+        assert!(cb.flash.is_none());
+        assert_eq!(cb.unread_count(), 1); // only the shown tab was seen
+        cb.show_tab(lobby);
+        assert_eq!(cb.unread_count(), 0);
+        assert_eq!(cb.tabs[lobby].label(), "lobby");
+    }
+
+    #[test]
+    fn chatbox_laid_out_while_collapsed_expands_to_the_new_size() {
+        let mut cb = max_chars_chatbox(10);
+        cb.add_message("0123456789 0123456789".to_owned());
+        assert_eq!(cb.tabs[0].wrapped.len(), 2);
+        cb.set_collapsed(true);
+
+        let bigger_font = FontInfo {
+            font:            (),
+            scale:           PxScale::from(2.0),
+            char_dimensions: Vector2 { x: 10.0f32, y: 10.0f32 },
+        };
+        cb.set_layout(Rect::new(20.0, 30.0, 100.01, 200.0), bigger_font).unwrap();
+        assert_eq!(cb.rect(), Rect::new(20.0, 30.0, 100.01, 14.0)); // still one line, of the new font
+        assert_eq!(cb.tabs[0].wrapped.len(), 2); // 10 characters to a line, as before
+
+        cb.set_collapsed(false);
+        assert_eq!(cb.rect().h, 200.0);
+        assert!(cb.set_layout(Rect::new(0.0, 0.0, 0.0, 10.0), bigger_font).is_err());
+    }
 }
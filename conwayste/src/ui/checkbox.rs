@@ -22,21 +22,42 @@ use std::fmt;
 use ggez::event::MouseButton;
 use ggez::graphics::{self, DrawMode, DrawParam, Rect};
 use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse::CursorIcon;
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
 use id_tree::NodeId;
 
 use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, KeyCodeOrChar, MoveCross, UIContext};
-use super::{common::FontInfo, label::Label, widget::Widget, UIError, UIResult};
+use super::{common::FontInfo, label::Label, textcache::TextCache, widget::Widget, UIError, UIResult};
 
 use crate::constants::colors::*;
+use crate::i18n::tr_args;
+
+/// Whether a checkbox is checked. `Indeterminate` is for a checkbox standing for several things of
+/// which only some are on, like "select all" when some are selected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckState {
+    Unchecked,
+    Checked,
+    Indeterminate,
+}
+
+impl From<bool> for CheckState {
+    fn from(checked: bool) -> Self {
+        if checked {
+            CheckState::Checked
+        } else {
+            CheckState::Unchecked
+        }
+    }
+}
 
 pub struct Checkbox {
     id:               Option<NodeId>,
     z_index:          usize,
     pub label:        Label,
-    pub enabled:      bool,
+    state:            CheckState,
     pub dimensions:   Rect,
     pub focused:      bool,        // has keyboard focus?
     pub hover_box:    bool,        // hovering checkbox itself?
@@ -48,8 +69,8 @@ impl fmt::Debug for Checkbox {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Checkbox {{ id: {:?}, z-index: {}, Dimensions: {:?}, Checked: {} }}",
-            self.id, self.z_index, self.dimensions, self.enabled
+            "Checkbox {{ id: {:?}, z-index: {}, Dimensions: {:?}, State: {:?} }}",
+            self.id, self.z_index, self.dimensions, self.state
         )
     }
 }
@@ -57,7 +78,9 @@ impl fmt::Debug for Checkbox {
 const LABEL_OFFSET_X: f32 = 30.0;
 const LABEL_OFFSET_Y: f32 = -5.0;
 
-/// A standard checkbox widget that can be toggled between enabled or disabled
+/// A standard checkbox widget that can be toggled between checked and unchecked. It may also be set
+/// to `CheckState::Indeterminate` from code, which a click turns to checked. Each change of state,
+/// whether by the user or through `change_state` or `toggle`, is followed by a `ValueChanged` event.
 impl Checkbox {
     /// Creates a Checkbox widget.
     ///
@@ -83,7 +106,7 @@ impl Checkbox {
     ///     "Toggle Me",
     ///     Rect::new(10.0, 210.0, 20.0, 20.0)
     /// );
-    /// checkbox.draw(ctx);
+    /// checkbox.draw(ctx, texts);
     /// ```
     ///
     pub fn new(ctx: &mut Context, enabled: bool, font_info: FontInfo, text: String, dimensions: Rect) -> Self {
@@ -95,8 +118,8 @@ impl Checkbox {
         let mut cb = Checkbox {
             id: None,
             z_index: std::usize::MAX,
-            label: Label::new(ctx, font_info, text, *CHECKBOX_TEXT_COLOR, label_origin),
-            enabled,
+            label: Label::new(ctx, font_info, text, scheme().checkbox_text, label_origin),
+            state: enabled.into(),
             dimensions,
             focused: false,
             hover_box: false,
@@ -147,17 +170,48 @@ impl Checkbox {
         }
         // create a synthetic click event
         let mouse_point = checkbox.position();
-        let click_event = Event::new_click(mouse_point, MouseButton::Left, false);
+        let click_event = Event::new_click(mouse_point, MouseButton::Left, event.modifiers);
         Ok(checkbox.emit(&click_event, uictx)?)
     }
 
-    fn click_handler(obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event) -> Result<Handled, Box<dyn Error>> {
-        let mut checkbox = obj.downcast_mut::<Checkbox>().unwrap();
+    fn click_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event) -> Result<Handled, Box<dyn Error>> {
+        let checkbox = obj.downcast_mut::<Checkbox>().unwrap();
+        checkbox.toggle(uictx)?;
+        Ok(Handled::Handled)
+    }
+
+    pub fn state(&self) -> CheckState {
+        self.state
+    }
 
-        // toggle
-        checkbox.enabled = !checkbox.enabled;
+    /// True only if the checkbox is fully checked.
+    pub fn is_checked(&self) -> bool {
+        self.state == CheckState::Checked
+    }
 
-        Ok(Handled::Handled)
+    /// Sets the state without a `ValueChanged` event, as when bringing the checkbox in line with a
+    /// setting that changed elsewhere.
+    pub fn set_state(&mut self, state: CheckState) {
+        self.state = state;
+    }
+
+    /// Sets the state from a handler, sending the checkbox a `ValueChanged` event if it changed.
+    pub fn change_state(&mut self, uictx: &mut UIContext, state: CheckState) -> Result<Handled, Box<dyn Error>> {
+        if self.state == state {
+            return Ok(Handled::NotHandled);
+        }
+        self.state = state;
+        self.emit(&Event::new_value_changed(), uictx)
+    }
+
+    /// Checks the checkbox if it was unchecked or indeterminate, and otherwise unchecks it, as a
+    /// click does.
+    pub fn toggle(&mut self, uictx: &mut UIContext) -> Result<Handled, Box<dyn Error>> {
+        let state = match self.state {
+            CheckState::Checked => CheckState::Unchecked,
+            CheckState::Unchecked | CheckState::Indeterminate => CheckState::Checked,
+        };
+        self.change_state(uictx, state)
     }
 
     fn mouse_move_handler(
@@ -263,7 +317,7 @@ impl Widget for Checkbox {
         self.label.translate(dest);
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
         if self.hover_box || self.hover_label || self.focused {
             // Add in a violet border/fill while hovered. Color checkbox differently to indicate
             // hovering and/or keyboard focus.
@@ -278,30 +332,37 @@ impl Widget for Checkbox {
                 ctx,
                 DrawMode::stroke(2.0),
                 border_rect,
-                *CHECKBOX_BORDER_ON_HOVER_COLOR,
+                scheme().checkbox_border_on_hover,
             )?;
 
             graphics::draw(ctx, &hovered_border, DrawParam::default())?;
         }
 
-        let draw_mode = if self.enabled {
-            DrawMode::fill()
-        } else {
-            DrawMode::stroke(2.0)
+        let fill_rect = match self.state {
+            CheckState::Checked => Some(self.dimensions),
+            // a bar across the middle, as for a dash
+            CheckState::Indeterminate => Some(Rect::new(
+                self.dimensions.x + self.dimensions.w / 4.0,
+                self.dimensions.y + self.dimensions.h * 3.0 / 8.0,
+                self.dimensions.w / 2.0,
+                self.dimensions.h / 4.0,
+            )),
+            CheckState::Unchecked => None,
         };
-
-        let border = graphics::Mesh::new_rectangle(ctx, draw_mode, self.dimensions, *CHECKBOX_TOGGLED_FILL_COLOR)?;
-        graphics::draw(ctx, &border, DrawParam::default())?;
+        if let Some(fill_rect) = fill_rect {
+            let fill = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), fill_rect, scheme().checkbox_toggled_fill)?;
+            graphics::draw(ctx, &fill, DrawParam::default())?;
+        }
 
         let label_border = graphics::Mesh::new_rectangle(
             ctx,
             DrawMode::stroke(2.0),
             self.dimensions,
-            *CHECKBOX_TOGGLED_FILL_COLOR,
+            scheme().checkbox_toggled_fill,
         )?;
         graphics::draw(ctx, &label_border, DrawParam::default())?;
 
-        self.label.draw(ctx)?;
+        self.label.draw(ctx, texts)?;
 
         Ok(())
     }
@@ -315,6 +376,19 @@ impl Widget for Checkbox {
     fn accepts_keyboard_events(&self) -> bool {
         true
     }
+
+    fn accessible_name(&self) -> Option<String> {
+        let id = match self.state {
+            CheckState::Checked => "a11y-checkbox-checked",
+            CheckState::Unchecked => "a11y-checkbox-unchecked",
+            CheckState::Indeterminate => "a11y-checkbox-mixed",
+        };
+        Some(tr_args(id, &[("label", &self.label.textfrag.text)]))
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
 }
 
 impl_emit_event!(Checkbox, self.handler_data);
@@ -0,0 +1,597 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::{
+    common::{within_widget, FontInfo},
+    context::{self, EmitEvent, Event, EventType, Handled, KeyCodeOrChar, UIContext},
+    textcache::TextCache,
+    widget::{Container, Widget},
+    UIError, UIResult,
+};
+
+use crate::accessibility;
+use crate::constants::{colors::*, SECTION_EXPAND_DURATION};
+use crate::i18n::tr_args;
+
+const HEADER_PADDING: f32 = 6.0; // around the title
+const ARROW_SIZE: f32 = 8.0;
+
+/// A section with a title, which shows or hides what is in it when the title is clicked. It opens
+/// and closes over `SECTION_EXPAND_DURATION`; what is in it is only shown once it is all the way
+/// open. The widget added to it with `InsertLocation::ToNestedContainer` is its content, placed
+/// relative to the top-left corner of `content_rect()`; usually a `Pane`. Other widgets don't move
+/// out of the way, so handlers for its `ValueChanged` event can move them.
+pub struct Collapsible {
+    id:             Option<NodeId>,
+    z_index:        usize,
+    pub dimensions: Rect, // when expanded
+    font_info:      FontInfo,
+    title:          String,
+    content:        Option<NodeId>,
+    expanded:       bool,
+    openness:       f32, // from 0.0 (collapsed) to 1.0 (expanded); moves toward `expanded` on each Update
+    focus:          Option<SectionFocus>,
+    handler_data:   context::HandlerData, // required for impl_emit_event!
+}
+
+/// Where keyboard focus is within the section, when it has focus.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SectionFocus {
+    Header,  // Space or Return toggles it, and Tab moves into the content if it's shown
+    Content, // keys go to the content
+}
+
+impl fmt::Debug for Collapsible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Collapsible {{ id: {:?}, z-index: {}, Dimensions: {:?}, Title: {:?}, Expanded: {} }}",
+            self.id, self.z_index, self.dimensions, self.title, self.expanded
+        )
+    }
+}
+
+fn header_height(font_info: &FontInfo) -> f32 {
+    font_info.char_dimensions.y + 2.0 * HEADER_PADDING
+}
+
+/// How far open a section is after `elapsed` more time opening (or closing, if not `expanded`),
+/// when it takes `duration` to go all the way.
+fn step_openness(openness: f32, expanded: bool, elapsed: Duration, duration: Duration) -> f32 {
+    let step = if duration.as_secs_f32() > 0.0 {
+        elapsed.as_secs_f32() / duration.as_secs_f32()
+    } else {
+        1.0
+    };
+    if expanded {
+        (openness + step).min(1.0)
+    } else {
+        (openness - step).max(0.0)
+    }
+}
+
+impl Collapsible {
+    /// Creates a section that takes up `dimensions` when expanded, and just its title when not.
+    pub fn new(font_info: FontInfo, dimensions: Rect, title: String, expanded: bool) -> Self {
+        let mut section = Collapsible {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            font_info,
+            title,
+            content: None,
+            expanded,
+            openness: if expanded { 1.0 } else { 0.0 },
+            focus: None,
+            handler_data: context::HandlerData::new(),
+        };
+
+        context::forward_to_nested(
+            &mut section,
+            Collapsible::mouse_event_handler,
+            Collapsible::key_press_handler,
+            Collapsible::broadcast_handler,
+        );
+
+        section
+            .on(EventType::GainFocus, Box::new(Collapsible::gain_focus_handler))
+            .unwrap(); // unwrap OK
+        section
+            .on(EventType::LoseFocus, Box::new(Collapsible::lose_focus_handler))
+            .unwrap(); // unwrap OK
+
+        section
+    }
+
+    /// The area under the title, relative to the section's top-left corner, where the content goes.
+    pub fn content_rect(&self) -> Rect {
+        let header_h = header_height(&self.font_info);
+        Rect::new(
+            0.0,
+            header_h,
+            self.dimensions.w,
+            (self.dimensions.h - header_h).max(1.0),
+        )
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    /// Whether the section is all the way open, so that its content is shown. It no longer is as
+    /// soon as it starts closing.
+    pub fn is_open(&self) -> bool {
+        self.expanded && self.openness >= 1.0
+    }
+
+    /// Expands or collapses the section right away, without animating or emitting `ValueChanged`.
+    /// Use this to set up the section before it is shown.
+    pub fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+        self.openness = if expanded { 1.0 } else { 0.0 };
+    }
+
+    /// Starts expanding or collapsing the section, and emits `ValueChanged` if that changes whether
+    /// it is expanded. Collapsing it takes keyboard focus out of the content.
+    pub fn change_expanded(&mut self, uictx: &mut UIContext, expanded: bool) -> Result<Handled, Box<dyn Error>> {
+        if self.expanded == expanded {
+            return Ok(Handled::NotHandled);
+        }
+        if !expanded && self.focus == Some(SectionFocus::Content) {
+            self.emit_on_content(&Event::new_gain_or_lose_focus(EventType::LoseFocus), uictx)?;
+            self.focus = Some(SectionFocus::Header);
+        }
+        self.expanded = expanded;
+        self.emit(&Event::new_value_changed(), uictx)
+    }
+
+    pub fn toggle(&mut self, uictx: &mut UIContext) -> Result<Handled, Box<dyn Error>> {
+        self.change_expanded(uictx, !self.expanded)
+    }
+
+    fn header_rect(&self) -> Rect {
+        Rect::new(
+            self.dimensions.x,
+            self.dimensions.y,
+            self.dimensions.w,
+            header_height(&self.font_info),
+        )
+    }
+
+    /// Emits the event on the content, returning whether it handled the event and the events it
+    /// sent to this section.
+    fn emit_on_content(
+        &mut self,
+        event: &Event,
+        uictx: &mut UIContext,
+    ) -> Result<(Handled, Vec<Event>), Box<dyn Error>> {
+        let content_id = match self.content {
+            Some(ref content_id) => content_id.clone(),
+            None => return Ok((Handled::NotHandled, vec![])),
+        };
+        let (widget_ref, mut subuictx) = uictx.derive(&content_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+        if let Some(emittable) = widget_ref.as_emit_event() {
+            let handled = emittable.emit(event, &mut subuictx)?;
+            return Ok((handled, subuictx.collect_child_events()));
+        }
+        Ok((Handled::NotHandled, vec![]))
+    }
+
+    fn mouse_event_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let section = obj.downcast_mut::<Collapsible>().unwrap(); // unwrap OK
+        let point = event.point.unwrap(); // unwrap OK because mouse events always have a point
+
+        if within_widget(&point, &section.header_rect()) {
+            if event.what == EventType::Click {
+                section.toggle(uictx)?;
+                uictx.child_event(Event::new_child_request_focus());
+            }
+            return Ok(Handled::Handled);
+        }
+        if !section.is_open() {
+            return Ok(Handled::NotHandled);
+        }
+
+        let (handled, child_events) = section.emit_on_content(event, uictx)?;
+        for child_event in child_events {
+            match child_event.what {
+                EventType::ChildRequestsFocus => {
+                    section.focus = Some(SectionFocus::Content);
+                    uictx.child_event(child_event);
+                }
+                EventType::ChildReleasedFocus => uictx.child_event(child_event),
+                _ => warn!("Unhandled child event in Collapsible: {:?}", child_event.what),
+            }
+        }
+        Ok(handled)
+    }
+
+    fn key_press_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let section = obj.downcast_mut::<Collapsible>().unwrap(); // unwrap OK
+
+        if section.focus == Some(SectionFocus::Content) {
+            let (handled, child_events) = section.emit_on_content(event, uictx)?;
+            for child_event in child_events {
+                if child_event.what == EventType::ChildReleasedFocus {
+                    // focus went past the end of the content, and so past this section
+                    section.focus = None;
+                    uictx.child_event(child_event);
+                }
+            }
+            return Ok(handled);
+        }
+
+        match event.key {
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Space)) | Some(KeyCodeOrChar::KeyCode(KeyCode::Return)) => {
+                section.toggle(uictx)?;
+                if let Some(name) = section.accessible_name() {
+                    accessibility::announce(name);
+                }
+                Ok(Handled::Handled)
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Tab)) => {
+                if section.is_open() && section.content.is_some() {
+                    section.focus = Some(SectionFocus::Content);
+                    let (_, child_events) =
+                        section.emit_on_content(&Event::new_gain_or_lose_focus(EventType::GainFocus), uictx)?;
+                    for child_event in child_events {
+                        uictx.child_event(child_event);
+                    }
+                } else {
+                    section.focus = None;
+                    uictx.child_event(Event::new_child_released_focus());
+                }
+                Ok(Handled::Handled)
+            }
+            _ => Ok(Handled::NotHandled),
+        }
+    }
+
+    fn broadcast_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let section = obj.downcast_mut::<Collapsible>().unwrap(); // unwrap OK
+
+        if event.what == EventType::Update {
+            let openness = step_openness(
+                section.openness,
+                section.expanded,
+                uictx.frame_delta(),
+                SECTION_EXPAND_DURATION,
+            );
+            if openness != section.openness {
+                section.openness = openness;
+                uictx.request_redraw();
+            }
+        }
+        // a hidden content shouldn't react to the mouse moving over where it would be
+        if event.what == EventType::MouseMove && !section.is_open() {
+            return Ok(Handled::NotHandled);
+        }
+        let (_, child_events) = section.emit_on_content(event, uictx)?;
+        if child_events.len() != 0 {
+            warn!(
+                "[Collapsible] expected no {:?} child events to be collected from content; got {:?}",
+                event.what, child_events
+            );
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn gain_focus_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        _event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let section = obj.downcast_mut::<Collapsible>().unwrap(); // unwrap OK
+        if section.focus.is_none() {
+            section.focus = Some(SectionFocus::Header);
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn lose_focus_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let section = obj.downcast_mut::<Collapsible>().unwrap(); // unwrap OK
+        if section.focus == Some(SectionFocus::Content) {
+            let (_, child_events) = section.emit_on_content(event, uictx)?;
+            for child_event in child_events {
+                uictx.child_event(child_event);
+            }
+        }
+        section.focus = None;
+        Ok(Handled::NotHandled)
+    }
+}
+
+impl Widget for Collapsible {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    /// Only as tall as it is open.
+    fn rect(&self) -> Rect {
+        let header_h = header_height(&self.font_info);
+        let mut rect = self.dimensions;
+        rect.h = header_h + (self.dimensions.h - header_h).max(0.0) * self.openness;
+        rect
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Collapsible {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        let rect = self.rect();
+        (rect.w, rect.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Collapsible {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let header = self.header_rect();
+        if self.focus == Some(SectionFocus::Header) {
+            let fill = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), header, scheme().button)?;
+            graphics::draw(ctx, &fill, DrawParam::default())?;
+        }
+
+        // an arrow pointing right when collapsed, and turning to point down as it opens
+        let center = Point2 {
+            x: header.x + HEADER_PADDING + ARROW_SIZE / 2.0,
+            y: header.y + header.h / 2.0,
+        };
+        let angle = self.openness * std::f32::consts::FRAC_PI_2;
+        let corner = |x: f32, y: f32| Point2 {
+            x: center.x + x * angle.cos() - y * angle.sin(),
+            y: center.y + x * angle.sin() + y * angle.cos(),
+        };
+        let half = ARROW_SIZE / 2.0;
+        let arrow = graphics::Mesh::new_polygon(
+            ctx,
+            DrawMode::fill(),
+            &[corner(-half, -half), corner(half, 0.0), corner(-half, half)],
+            scheme().menu_text,
+        )?;
+        graphics::draw(ctx, &arrow, DrawParam::default())?;
+
+        let text = texts.get(self.font_info.fragment(self.title.as_str()));
+        let point = Point2 {
+            x: header.x + 2.0 * HEADER_PADDING + ARROW_SIZE,
+            y: header.y + HEADER_PADDING,
+        };
+        graphics::queue_text(ctx, &text, point, Some(scheme().menu_text));
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, graphics::FilterMode::Linear)?;
+
+        let border = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), self.rect(), scheme().pane_border)?;
+        graphics::draw(ctx, &border, DrawParam::default())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn takes_tab(&self) -> bool {
+        true
+    }
+
+    fn shows_child(&self, _child_id: &NodeId) -> bool {
+        self.is_open()
+    }
+
+    fn accessible_name(&self) -> Option<String> {
+        let id = if self.expanded {
+            "a11y-section-expanded"
+        } else {
+            "a11y-section-collapsed"
+        };
+        Some(tr_args(id, &[("label", &self.title)]))
+    }
+
+    fn as_container(&self) -> Option<&dyn Container> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn Container> {
+        Some(self)
+    }
+}
+
+impl Container for Collapsible {
+    /// The content goes under the section's title.
+    fn child_origin(&self) -> Vector2<f32> {
+        let content_rect = self.content_rect();
+        Vector2 {
+            x: self.dimensions.x + content_rect.x,
+            y: self.dimensions.y + content_rect.y,
+        }
+    }
+
+    /// Whether the section already has its content.
+    fn is_full(&self) -> bool {
+        self.content.is_some()
+    }
+
+    /// The widget becomes the content, and gets keyboard focus through the section.
+    fn child_added(&mut self, content_id: NodeId, _size: (f32, f32), _accepts_keyboard_events: bool) -> bool {
+        self.content = Some(content_id);
+        true
+    }
+
+    fn child_removed(&mut self, content_id: &NodeId) {
+        if self.content.as_ref() == Some(content_id) {
+            self.content = None;
+            if self.focus == Some(SectionFocus::Content) {
+                self.focus = Some(SectionFocus::Header);
+            }
+        }
+    }
+}
+
+widget_from_id!(Collapsible);
+impl_emit_event!(Collapsible, self.handler_data);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        harness::{dummy_font_info, Harness},
+        InsertLocation, Pane, TextField,
+    };
+    use super::*;
+    use crate::Screen;
+
+    #[test]
+    fn test_step_openness_stops_at_the_ends() {
+        let duration = Duration::from_millis(100);
+        let openness = step_openness(0.0, true, Duration::from_millis(25), duration);
+        assert!((openness - 0.25).abs() < 1e-6);
+        assert_eq!(step_openness(0.9, true, Duration::from_millis(25), duration), 1.0);
+        assert_eq!(step_openness(0.1, false, Duration::from_millis(25), duration), 0.0);
+        assert_eq!(
+            step_openness(0.5, true, Duration::from_millis(25), Duration::from_secs(0)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_clicking_the_title_hides_the_content_once_closed() {
+        let mut harness = Harness::new(Screen::Options);
+        let section = Collapsible::new(
+            dummy_font_info(),
+            Rect::new(10.0, 10.0, 200.0, 100.0),
+            "Advanced".to_owned(),
+            true,
+        );
+        let content_rect = section.content_rect();
+        let section_id = harness.add_widget(Box::new(section), InsertLocation::AtCurrentLayer);
+        let pane = Pane::new(Rect::new(0.0, 0.0, content_rect.w, content_rect.h));
+        let pane_id = harness.add_widget(Box::new(pane), InsertLocation::ToNestedContainer(&section_id));
+        let tf = TextField::new(dummy_font_info(), Rect::new(10.0, 10.0, 100.0, 20.0));
+        let tf_id = harness.add_widget(Box::new(tf), InsertLocation::ToNestedContainer(&pane_id));
+        let drawn = |harness: &Harness| -> Vec<NodeId> { harness.draw().into_iter().map(|(id, _)| id).collect() };
+
+        // the text field is at (20, 37), under the 17 pixel tall title
+        assert_eq!(
+            TextField::widget_from_id(&harness.layering, &tf_id).unwrap().rect(),
+            Rect::new(20.0, 37.0, 100.0, 20.0)
+        );
+        assert!(drawn(&harness).contains(&tf_id));
+
+        harness.click(50.0, 15.0).unwrap();
+        let section = Collapsible::widget_from_id(&harness.layering, &section_id).unwrap();
+        assert!(!section.is_expanded());
+        assert!(!drawn(&harness).contains(&tf_id)); // hidden as soon as it starts closing
+        assert_eq!(harness.layering.focused_widget_id(), Some(&section_id));
+
+        // a 60th of a second per frame
+        for _ in 0..10 {
+            harness.update().unwrap();
+        }
+        let section = Collapsible::widget_from_id(&harness.layering, &section_id).unwrap();
+        assert_eq!(section.rect(), Rect::new(10.0, 10.0, 200.0, 17.0));
+        harness.click(30.0, 45.0).unwrap(); // where the text field was, which takes focus away
+        harness.type_text("x").unwrap();
+        assert_eq!(
+            TextField::widget_from_id(&harness.layering, &tf_id).unwrap().text(),
+            None
+        );
+
+        harness.focus(&section_id).unwrap();
+        harness.press_key(KeyCode::Space).unwrap();
+        for _ in 0..10 {
+            harness.update().unwrap();
+        }
+        assert!(drawn(&harness).contains(&tf_id));
+        harness.press_key(KeyCode::Tab).unwrap(); // into the content
+        harness.type_text("y").unwrap();
+        assert_eq!(
+            TextField::widget_from_id(&harness.layering, &tf_id).unwrap().text(),
+            Some("y".to_owned())
+        );
+    }
+}
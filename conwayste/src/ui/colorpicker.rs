@@ -0,0 +1,271 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
+use ggez::input::mouse::CursorIcon;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, UIContext};
+use super::{textcache::TextCache, widget::Widget, UIError, UIResult};
+
+use crate::constants::colors::*;
+
+/// Colors offered as swatches, as RGB.
+const PALETTE: [[u8; 3]; 16] = [
+    [255, 0, 0],
+    [255, 128, 0],
+    [255, 215, 0],
+    [128, 255, 0],
+    [0, 200, 0],
+    [0, 255, 170],
+    [0, 200, 255],
+    [0, 0, 255],
+    [128, 0, 255],
+    [255, 0, 255],
+    [255, 105, 180],
+    [139, 69, 19],
+    [128, 128, 0],
+    [0, 128, 128],
+    [64, 64, 64],
+    [192, 192, 192],
+];
+const PALETTE_COLUMNS: usize = 8;
+const CHANNEL_BAR_HEIGHT: f32 = 16.0; // pixels
+const SPACING: f32 = 4.0; // pixels between the parts of the picker
+
+/// Lets the player pick a color, either from a grid of swatches or by setting its red, green, and
+/// blue amounts on three bars. Handlers for `Click` that are added with `.on` run after `color`
+/// has been updated, and only if the click changed it.
+pub struct ColorPicker {
+    id:               Option<NodeId>,
+    z_index:          usize,
+    pub color:        [u8; 3],
+    pub dimensions:   Rect,
+    pub handler_data: HandlerData, // required for impl_emit_event!
+}
+
+impl fmt::Debug for ColorPicker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ColorPicker {{ id: {:?}, z_index: {}, dimensions: {:?}, color: {:?} }}",
+            self.id, self.z_index, self.dimensions, self.color
+        )
+    }
+}
+
+impl ColorPicker {
+    /// Creates a ColorPicker widget with `color` selected. The swatches are sized to fit the width
+    /// of `dimensions`; the height should leave room for two rows of swatches and the three bars.
+    pub fn new(color: [u8; 3], dimensions: Rect) -> Self {
+        let mut picker = ColorPicker {
+            id: None,
+            z_index: std::usize::MAX,
+            color,
+            dimensions,
+            handler_data: HandlerData::new(),
+        };
+        picker
+            .on(EventType::Click, Box::new(ColorPicker::click_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        picker
+    }
+
+    fn swatch_size(&self) -> f32 {
+        self.dimensions.w / PALETTE_COLUMNS as f32
+    }
+
+    fn swatch_rect(&self, i: usize) -> Rect {
+        let size = self.swatch_size();
+        Rect::new(
+            self.dimensions.x + (i % PALETTE_COLUMNS) as f32 * size,
+            self.dimensions.y + (i / PALETTE_COLUMNS) as f32 * size,
+            size,
+            size,
+        )
+    }
+
+    /// The bar for the red (0), green (1) or blue (2) amount.
+    fn channel_bar_rect(&self, channel: usize) -> Rect {
+        let rows = (PALETTE.len() + PALETTE_COLUMNS - 1) / PALETTE_COLUMNS;
+        let top = self.dimensions.y + rows as f32 * self.swatch_size() + SPACING;
+        Rect::new(
+            self.dimensions.x,
+            top + channel as f32 * (CHANNEL_BAR_HEIGHT + SPACING),
+            self.dimensions.w - self.preview_size() - SPACING,
+            CHANNEL_BAR_HEIGHT,
+        )
+    }
+
+    fn preview_size(&self) -> f32 {
+        3.0 * CHANNEL_BAR_HEIGHT + 2.0 * SPACING
+    }
+
+    fn preview_rect(&self) -> Rect {
+        let first_bar = self.channel_bar_rect(0);
+        let size = self.preview_size();
+        Rect::new(self.dimensions.right() - size, first_bar.y, size, size)
+    }
+
+    fn click_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let picker = obj.downcast_mut::<ColorPicker>().unwrap(); // unwrap OK because this will always be ColorPicker
+        let point = event.point.unwrap(); // unwrap OK because a Click always has a point
+        let old_color = picker.color;
+
+        if let Some(i) = (0..PALETTE.len()).find(|&i| picker.swatch_rect(i).contains(point)) {
+            picker.color = PALETTE[i];
+        }
+        for channel in 0..3 {
+            let bar = picker.channel_bar_rect(channel);
+            if bar.contains(point) {
+                let amount = (point.x - bar.x) / bar.w * 255.0;
+                picker.color[channel] = amount.round().max(0.0).min(255.0) as u8;
+            }
+        }
+
+        if picker.color == old_color {
+            Ok(Handled::Handled)
+        } else {
+            Ok(Handled::NotHandled) // let the handlers added with .on see the new color
+        }
+    }
+}
+
+fn to_color(rgb: [u8; 3]) -> Color {
+    Color::from_rgb(rgb[0], rgb[1], rgb[2])
+}
+
+impl Widget for ColorPicker {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of ColorPicker {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of ColorPicker {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        for (i, &rgb) in PALETTE.iter().enumerate() {
+            let rect = self.swatch_rect(i);
+            let swatch = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), rect, to_color(rgb))?;
+            graphics::draw(ctx, &swatch, DrawParam::default())?;
+            if rgb == self.color {
+                let border =
+                    graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(2.0), rect, scheme().color_picker_selected)?;
+                graphics::draw(ctx, &border, DrawParam::default())?;
+            }
+        }
+
+        // Each bar is filled from the left in proportion to the amount of its channel
+        for channel in 0..3 {
+            let bar = self.channel_bar_rect(channel);
+            let mut channel_rgb = [0; 3];
+            channel_rgb[channel] = 255;
+            let channel_color = to_color(channel_rgb);
+            let amount = self.color[channel] as f32 / 255.0;
+            if amount > 0.0 {
+                let fill_rect = Rect::new(bar.x, bar.y, bar.w * amount, bar.h);
+                let fill = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), fill_rect, channel_color)?;
+                graphics::draw(ctx, &fill, DrawParam::default())?;
+            }
+            let outline = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), bar, channel_color)?;
+            graphics::draw(ctx, &outline, DrawParam::default())?;
+        }
+
+        let preview_rect = self.preview_rect();
+        let preview = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), preview_rect, to_color(self.color))?;
+        graphics::draw(ctx, &preview, DrawParam::default())?;
+        let outline =
+            graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), preview_rect, scheme().color_picker_selected)?;
+        graphics::draw(ctx, &outline, DrawParam::default())?;
+
+        Ok(())
+    }
+
+    /// convert to EmitEvent
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
+}
+
+impl_emit_event!(ColorPicker, self.handler_data);
+widget_from_id!(ColorPicker);
@@ -20,8 +20,28 @@ use ggez::graphics::{self, Color, DrawParam, Font, PxScale, Rect, Text, TextFrag
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use crate::constants::DEFAULT_UI_FONT_SCALE;
 
+// The `min_font_size` accessibility setting, as the bits of an f32 since there is no AtomicF32.
+static MIN_FONT_SIZE: AtomicU32 = AtomicU32::new(0); // 0.0_f32
+
+/// Sets the smallest size, in pixels, that text is drawn at. Text already laid out in widgets keeps
+/// its size until the widgets are rebuilt.
+pub fn set_min_font_size(size: f32) {
+    MIN_FONT_SIZE.store(size.to_bits(), Ordering::Relaxed);
+}
+
+/// Enlarges `scale` to the minimum font size, if it is smaller.
+fn with_min_font_size(scale: PxScale) -> PxScale {
+    let min = f32::from_bits(MIN_FONT_SIZE.load(Ordering::Relaxed));
+    PxScale {
+        x: scale.x.max(min),
+        y: scale.y.max(min),
+    }
+}
+
 #[macro_export]
 macro_rules! widget_from_id {
     ($type:ident) => {
@@ -119,7 +139,7 @@ pub fn draw_text(
     coords: &Point2<f32>,
 ) -> GameResult<(f32, f32)> {
     let text_fragment = TextFragment::new(text)
-        .scale(*DEFAULT_UI_FONT_SCALE)
+        .scale(with_min_font_size(*DEFAULT_UI_FONT_SCALE))
         .color(color)
         .font(font);
 
@@ -130,6 +150,16 @@ pub fn draw_text(
     Ok((text_width as f32, text_height as f32))
 }
 
+/// The width and height in pixels of `text` as `draw_text` would draw it, for finding where to
+/// draw it.
+pub fn text_size(ctx: &mut Context, font: Font, text: String) -> (f32, f32) {
+    let text_fragment = TextFragment::new(text)
+        .scale(with_min_font_size(*DEFAULT_UI_FONT_SCALE))
+        .font(font);
+    let graphics_text = Text::new(text_fragment);
+    (graphics_text.width(ctx) as f32, graphics_text.height(ctx) as f32)
+}
+
 /// Represents a font at a particular scale. Besides the ID of the font, it also includes the scale
 /// at which to draw it, and the dimensions of one character at that scale (this is only useful if
 /// the font is fixed width!).
@@ -149,9 +179,10 @@ pub struct FontInfo {
 }
 
 impl FontInfo {
-    /// Creates a FontInfo. If `scale` is `None`, uses `DEFAULT_UI_FONT_SCALE`.
+    /// Creates a FontInfo. If `scale` is `None`, uses `DEFAULT_UI_FONT_SCALE`. Either way, the
+    /// scale is raised to the minimum font size if needed.
     pub fn new(ctx: &mut Context, font: Font, scale: Option<PxScale>) -> Self {
-        let scale = scale.unwrap_or(*DEFAULT_UI_FONT_SCALE);
+        let scale = with_min_font_size(scale.unwrap_or(*DEFAULT_UI_FONT_SCALE));
         #[cfg(not(test))]
         {
             let text = "xxxxxxxxxx"; // 10 arbitrary characters
@@ -187,6 +218,18 @@ impl FontInfo {
         #[cfg(test)]
         let _ = text;
     }
+
+    /// A fragment holding `text` in the font and scale of this `FontInfo`.
+    pub fn fragment(&self, text: impl Into<String>) -> TextFragment {
+        #[cfg(not(test))]
+        let fragment = TextFragment::new(text.into()).font(self.font).scale(self.scale);
+
+        // no font if cfg(test)
+        #[cfg(test)]
+        let fragment = TextFragment::new(text.into()).scale(self.scale);
+
+        fragment
+    }
 }
 
 /// Determines if two rectangles overlap, and if so,
@@ -16,50 +16,67 @@
  *  along with conwayste.  If not, see
  *  <http://www.gnu.org/licenses/>. */
 
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use downcast_rs::Downcast;
-use enum_iterator::IntoEnumIterator;
 use ggez;
-use ggez::event::MouseButton;
+use ggez::event::{KeyMods, MouseButton};
 use ggez::graphics::Rect;
 use ggez::input::keyboard::KeyCode;
-use ggez::mint::Point2;
+use ggez::mint::{Point2, Vector2};
+use ggez::{graphics, timer};
 use id_tree::NodeId;
+use winit::dpi::LogicalPosition;
 
+use super::textcache::TextCache;
+use super::timer::{TimerId, Timers};
 use super::treeview::TreeView;
 use super::BoxedWidget;
 use crate::{config, uilayout::StaticNodeIds, viewport::GridView, Screen};
 
 /// Stores references to many things a handler is likely to need:
 ///
-/// * `ggez_context` - useful for game engine interactions.
+/// * `ggez_context` - useful for game engine interactions. UI tests run without one; see
+///   `UIContext::ggez_context`.
 /// * `config` - Conwayste configuration settings.
 /// * `widget_view` - a `TreeView` on the handler's widget and all widgets beneath it in the widget tree.
 /// * `screen_stack` - the layers of `Screen`s in the UI. Handlers are able to push or pop this stack.
+/// * `timers` - the timers of the widgets in the `Layering`; see `UIContext::start_timer`.
+/// * `redraw` - whether the `Layering` has changed since it was last drawn; see
+///   `UIContext::request_redraw`.
+/// * `texts` - the `Layering`'s cache of laid out text; see `UIContext::text_context`.
 pub struct UIContext<'a> {
-    pub ggez_context:     &'a mut ggez::Context,
+    ggez_context:         Option<&'a mut ggez::Context>, // None in headless UI tests
     pub config:           &'a mut config::Config,
     pub widget_view:      TreeView<'a, BoxedWidget>,
     pub screen_stack:     &'a mut Vec<Screen>,
     pub game_in_progress: bool,
     pub static_node_ids:  &'a mut StaticNodeIds,
     pub viewport:         &'a mut GridView,
+    timers:               &'a mut Timers,
+    redraw:               &'a mut bool,
+    texts:                &'a mut TextCache,
     child_events:         Vec<Event>,
 }
 
 impl<'a> UIContext<'a> {
     pub fn new(
-        ggez_context: &'a mut ggez::Context,
+        ggez_context: Option<&'a mut ggez::Context>,
         config: &'a mut config::Config,
         view: TreeView<'a, BoxedWidget>,
         screen_stack: &'a mut Vec<Screen>,
         game_in_progress: bool,
         static_node_ids: &'a mut StaticNodeIds,
         viewport: &'a mut GridView,
+        timers: &'a mut Timers,
+        redraw: &'a mut bool,
+        texts: &'a mut TextCache,
     ) -> Self {
         UIContext {
             ggez_context,
@@ -70,6 +87,9 @@ impl<'a> UIContext<'a> {
             game_in_progress,
             static_node_ids,
             viewport,
+            timers,
+            redraw,
+            texts,
         }
     }
 
@@ -91,7 +111,7 @@ impl<'a> UIContext<'a> {
         Ok((
             widget_ref,
             UIContext {
-                ggez_context:     self.ggez_context,
+                ggez_context:     self.ggez_context.as_deref_mut(),
                 config:           self.config,
                 widget_view:      subtree,
                 screen_stack:     self.screen_stack,
@@ -99,10 +119,94 @@ impl<'a> UIContext<'a> {
                 game_in_progress: self.game_in_progress,
                 static_node_ids:  self.static_node_ids,
                 viewport:         self.viewport,
+                timers:           self.timers,
+                redraw:           self.redraw,
+                texts:            self.texts,
             },
         ))
     }
 
+    /// Asks for the layering to be drawn again. Any event but `Update` is assumed to change how
+    /// widgets look, so this is for `Update` handlers that do, such as one that steps an
+    /// animation. Until something asks, an idle screen isn't redrawn.
+    pub fn request_redraw(&mut self) {
+        *self.redraw = true;
+    }
+
+    /// The ggez context, for handlers that need the game engine.
+    ///
+    /// # Panics
+    ///
+    /// This will panic in headless UI tests, which have no ggez context. Handlers that run in those
+    /// tests should use `frame_delta` and `set_ime_position` instead, which work without one.
+    pub fn ggez_context(&mut self) -> &mut ggez::Context {
+        self.ggez_context
+            .as_deref_mut()
+            .expect("no ggez context in a headless UIContext")
+    }
+
+    /// The ggez context along with the text cache of the layering, for handlers that change the text
+    /// of a widget, which is measured then.
+    ///
+    /// # Panics
+    ///
+    /// Like `ggez_context`, this will panic in headless UI tests.
+    pub fn text_context(&mut self) -> (&mut ggez::Context, &mut TextCache) {
+        let ctx = self
+            .ggez_context
+            .as_deref_mut()
+            .expect("no ggez context in a headless UIContext");
+        (ctx, &mut *self.texts)
+    }
+
+    /// Time taken by the last frame. Headless, every frame takes a 60th of a second.
+    pub fn frame_delta(&self) -> Duration {
+        match self.ggez_context {
+            Some(ref ctx) => timer::delta(ctx),
+            None => Duration::from_secs(1) / 60,
+        }
+    }
+
+    /// Tells the input method where the text cursor is, so it can show its window next to it. Does
+    /// nothing when headless.
+    pub fn set_ime_position(&mut self, point: Point2<f32>) {
+        if let Some(ref ctx) = self.ggez_context {
+            graphics::window(ctx).set_ime_position(LogicalPosition::new(point.x, point.y));
+        }
+    }
+
+    /// Starts a timer for the widget whose handler this is. After `delay`, and every `delay` after
+    /// that if `repeating`, the widget gets a `Timer` event carrying the returned ID, during an
+    /// update. The timer stops when cancelled or the widget is removed.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error when called with the `UIContext` of a whole `Layering`, rather than
+    /// that of a widget's handler.
+    pub fn start_timer(&mut self, delay: Duration, repeating: bool) -> Result<TimerId, Box<dyn Error>> {
+        let widget_id = self
+            .widget_view
+            .root_id()
+            .ok_or("start_timer called outside of a widget's handler")?
+            .clone();
+        Ok(self.timers.start(widget_id, delay, repeating))
+    }
+
+    /// Stops a timer started by `start_timer`. Does nothing if it is no longer running.
+    pub fn cancel_timer(&mut self, id: TimerId) {
+        self.timers.cancel(id);
+    }
+
+    /// Counts the timers down by the time the last frame took, returning those that ran out along
+    /// with their widgets.
+    pub(super) fn advance_timers(&mut self) -> Vec<(NodeId, TimerId)> {
+        if self.timers.is_empty() {
+            return vec![];
+        }
+        let delta = self.frame_delta();
+        self.timers.advance(delta)
+    }
+
     /// Return a Result containing a reference to a `Box<dyn Widget>` for the specified `NodeId` if
     /// it exists and is in view in the tree, or else a `NodeIdError`.
     #[allow(unused)]
@@ -187,11 +291,17 @@ impl<'a> Drop for UIContext<'a> {
 }
 
 /// The type of an event.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, IntoEnumIterator)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum EventType {
     None,
     Click,
+    // DoubleClick and LongPress are made by the Layering out of the Clicks and MouseButtonHelds it
+    // routes; see the `[input]` section of the config for how quick and how long they are.
     DoubleClick,
+    LongPress,
+    // The mouse wheel turned. Goes to the innermost widget under the mouse, then out to the widgets
+    // containing it until one handles it.
+    Scroll,
     KeyPress,
     MouseMove,
     Drag,
@@ -211,8 +321,37 @@ pub enum EventType {
     RequestFocus,
     Load,
     Save,
+    // One of the widget's timers ran out; see `UIContext::start_timer`. Sent to that widget only.
+    Timer,
+    // The widget's value, such as whether a checkbox is checked, changed. Sent by the widget to
+    // itself, after the change, whether the user or code made it.
+    ValueChanged,
+    // An event defined by game code or a script rather than the UI; see `EventType::custom`. Sent
+    // to every widget on the screen.
+    Custom(&'static str),
 }
 
+lazy_static! {
+    // The names of the custom event types the code has asked for, for finding one from a name only
+    // known while the game runs, such as one a script sends. There are only as many as the code has
+    // names for.
+    static ref CUSTOM_EVENT_NAMES: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+/// Something a custom event carries for its handlers, of whatever type its sender chose.
+#[derive(Clone)]
+pub struct Payload(Arc<dyn Any + Send + Sync>);
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Payload")
+    }
+}
+
+/// The modifier keys (Shift, Ctrl, Alt and the logo key) held down when a key or mouse event
+/// happened, such as `Modifiers::SHIFT | Modifiers::CTRL`.
+pub type Modifiers = KeyMods;
+
 /// Describes a MouseMove event in relation to a Rect.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MoveCross {
@@ -233,10 +372,13 @@ pub struct Event {
     pub prev_point:    Option<Point2<f32>>, // MouseMove / Drag
     pub button:        Option<MouseButton>, // Click
     pub key:           Option<KeyCodeOrChar>,
-    pub shift_pressed: bool,
+    pub modifiers:     Modifiers, // Key and mouse events
     pub key_repeating: bool,
     pub text:          Option<String>,
     pub node_id:       Option<NodeId>,
+    pub payload:       Option<Payload>,      // Custom
+    pub timer_id:      Option<TimerId>,      // Timer
+    pub scroll_delta:  Option<Vector2<f32>>, // Scroll; y is positive away from the user, x to the right
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -246,11 +388,14 @@ pub enum KeyCodeOrChar {
 }
 
 /// A slice containing all EventTypes related to the keyboard. Must have a key set.
-const KEY_EVENTS: &[EventType] = &[EventType::KeyPress];
+pub(super) const KEY_EVENTS: &[EventType] = &[EventType::KeyPress];
 
 /// A slice containing all EventTypes related to the mouse.
-const MOUSE_EVENTS: &[EventType] = &[
+pub(super) const MOUSE_EVENTS: &[EventType] = &[
     EventType::Click,
+    EventType::DoubleClick,
+    EventType::LongPress,
+    EventType::Scroll,
     EventType::MouseMove,
     EventType::Drag,
     EventType::MouseButtonHeld,
@@ -265,7 +410,7 @@ const FOCUS_EVENTS: &[EventType] = &[
 ];
 
 /// A slice containing all EventTypes related to events broadcasted to all widgets
-const BROADCASTED_EVENTS: &[EventType] = &[
+pub(super) const BROADCASTED_EVENTS: &[EventType] = &[
     EventType::Update,
     EventType::MouseMove,
     EventType::Load,
@@ -291,6 +436,33 @@ impl EventType {
     pub fn is_broadcast_event(self) -> bool {
         BROADCASTED_EVENTS.contains(&self)
     }
+
+    pub fn is_custom_event(self) -> bool {
+        match self {
+            EventType::Custom(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The type of the custom events called `name`, such as `"pattern_selected"`.
+    pub fn custom(name: &'static str) -> EventType {
+        CUSTOM_EVENT_NAMES.lock().unwrap().insert(name);
+        EventType::Custom(name)
+    }
+
+    /// The name passed to `custom` that is the same as `name`, if any. Nothing handles custom events
+    /// of any other name, since a handler can only be registered for a type made by `custom`.
+    pub fn known_custom_name(name: &str) -> Option<&'static str> {
+        CUSTOM_EVENT_NAMES.lock().unwrap().get(name).copied()
+    }
+
+    /// The name a custom event type was made from, if this is one.
+    pub fn custom_name(self) -> Option<&'static str> {
+        match self {
+            EventType::Custom(name) => Some(name),
+            _ => None,
+        }
+    }
 }
 
 impl Default for Event {
@@ -301,42 +473,83 @@ impl Default for Event {
             prev_point:    None,
             button:        None,
             key:           None,
-            shift_pressed: false,
+            modifiers:     Modifiers::NONE,
             key_repeating: false,
             text:          None,
             node_id:       None,
+            payload:       None,
+            timer_id:      None,
+            scroll_delta:  None,
         }
     }
 }
 
 impl Event {
-    pub fn new_char_press(mouse_point: Point2<f32>, character: char, is_shift: bool) -> Self {
+    pub fn new_char_press(mouse_point: Point2<f32>, character: char, modifiers: Modifiers) -> Self {
         Event {
             what: EventType::KeyPress,
             point: Some(mouse_point),
             key: Some(KeyCodeOrChar::Char(character)),
-            shift_pressed: is_shift,
+            modifiers,
             ..Default::default()
         }
     }
 
-    pub fn new_key_press(mouse_point: Point2<f32>, key_code: KeyCode, is_shift: bool, is_repeating: bool) -> Self {
+    pub fn new_key_press(
+        mouse_point: Point2<f32>,
+        key_code: KeyCode,
+        modifiers: Modifiers,
+        is_repeating: bool,
+    ) -> Self {
         Event {
             what: EventType::KeyPress,
             point: Some(mouse_point),
             key: Some(KeyCodeOrChar::KeyCode(key_code)),
-            shift_pressed: is_shift,
+            modifiers,
             key_repeating: is_repeating,
             ..Default::default()
         }
     }
 
-    pub fn new_click(mouse_point: Point2<f32>, mouse_button: MouseButton, is_shift: bool) -> Self {
+    pub fn new_click(mouse_point: Point2<f32>, mouse_button: MouseButton, modifiers: Modifiers) -> Self {
         Event {
             what: EventType::Click,
             point: Some(mouse_point),
             button: Some(mouse_button),
-            shift_pressed: is_shift,
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    /// Follows the `Click` that made it a double click.
+    pub fn new_double_click(mouse_point: Point2<f32>, mouse_button: MouseButton, modifiers: Modifiers) -> Self {
+        Event {
+            what: EventType::DoubleClick,
+            point: Some(mouse_point),
+            button: Some(mouse_button),
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    /// Sent once the button has been held long enough where it was pressed. If a widget handles
+    /// it, releasing the button doesn't make a `Click`.
+    pub fn new_long_press(mouse_point: Point2<f32>, mouse_button: MouseButton, modifiers: Modifiers) -> Self {
+        Event {
+            what: EventType::LongPress,
+            point: Some(mouse_point),
+            button: Some(mouse_button),
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_scroll(mouse_point: Point2<f32>, delta: Vector2<f32>, modifiers: Modifiers) -> Self {
+        Event {
+            what: EventType::Scroll,
+            point: Some(mouse_point),
+            scroll_delta: Some(delta),
+            modifiers,
             ..Default::default()
         }
     }
@@ -345,28 +558,36 @@ impl Event {
         prev_point: Point2<f32>,
         point: Point2<f32>,
         mouse_button: MouseButton,
-        is_shift: bool,
+        modifiers: Modifiers,
     ) -> Self {
         Event {
             what: EventType::MouseMove,
             point: Some(point),
             prev_point: Some(prev_point),
             button: Some(mouse_button),
-            shift_pressed: is_shift,
+            modifiers,
             ..Default::default()
         }
     }
 
-    pub fn new_mouse_held(mouse_point: Point2<f32>, mouse_button: MouseButton, is_shift: bool) -> Self {
+    pub fn new_mouse_held(mouse_point: Point2<f32>, mouse_button: MouseButton, modifiers: Modifiers) -> Self {
         Event {
             what: EventType::MouseButtonHeld,
             point: Some(mouse_point),
             button: Some(mouse_button),
-            shift_pressed: is_shift,
+            modifiers,
             ..Default::default()
         }
     }
 
+    pub fn shift_pressed(&self) -> bool {
+        self.modifiers.contains(Modifiers::SHIFT)
+    }
+
+    pub fn ctrl_pressed(&self) -> bool {
+        self.modifiers.contains(Modifiers::CTRL)
+    }
+
     /// For MouseMove events, indicate whether the mouse entered/exited the given box, or neither.
     /// Use this to implement on-hover displays.
     pub fn move_did_cross(&self, rect: Rect) -> MoveCross {
@@ -428,12 +649,27 @@ impl Event {
         }
     }
 
-    pub fn new_drag(mouse_point: Point2<f32>, mouse_button: MouseButton, is_shift: bool) -> Self {
+    pub fn new_timer(timer_id: TimerId) -> Self {
+        Event {
+            what: EventType::Timer,
+            timer_id: Some(timer_id),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_value_changed() -> Self {
+        Event {
+            what: EventType::ValueChanged,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_drag(mouse_point: Point2<f32>, mouse_button: MouseButton, modifiers: Modifiers) -> Self {
         Event {
             what: EventType::Drag,
             point: Some(mouse_point),
             button: Some(mouse_button),
-            shift_pressed: is_shift,
+            modifiers,
             ..Default::default()
         }
     }
@@ -453,6 +689,20 @@ impl Event {
         }
     }
 
+    /// A custom event of the type named `name`, carrying `payload` (which can be `()`).
+    pub fn new_custom<T: Any + Send + Sync>(name: &'static str, payload: T) -> Self {
+        Event {
+            what: EventType::custom(name),
+            payload: Some(Payload(Arc::new(payload))),
+            ..Default::default()
+        }
+    }
+
+    /// The payload of a custom event, if it has one of type `T`.
+    pub fn payload<T: Any>(&self) -> Option<&T> {
+        self.payload.as_ref().and_then(|payload| payload.0.downcast_ref::<T>())
+    }
+
     pub fn new_load() -> Self {
         Event {
             what: EventType::Load,
@@ -489,7 +739,47 @@ pub enum Handled {
 
 pub type Handler = Box<dyn FnMut(&mut dyn EmitEvent, &mut UIContext, &Event) -> Result<Handled, Box<dyn Error>> + Send>;
 
-pub type HandlerMap = HashMap<EventType, Vec<Handler>>;
+/// When a handler on a container, such as a Pane, runs relative to the widgets nested in it.
+/// Handlers on other widgets run in the same order, capture ones first, but there is nothing in
+/// between.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Before the event is passed on to the nested widgets. Returning `Handled` keeps it from them,
+    /// so that a modal pane or a tool can intercept the input.
+    Capture,
+    /// After the nested widgets, and only if none of them handled the event. This is what `.on`
+    /// registers.
+    Bubble,
+}
+
+/// Within a phase, handlers with a lower priority run first, and those with the same priority run
+/// in the order they were registered.
+pub type Priority = i32;
+
+pub const DEFAULT_PRIORITY: Priority = 0;
+
+/// The priority containers pass events on to their nested widgets at, in the capture phase. Capture
+/// handlers registered at this priority or above run only if no nested widget handled the event.
+pub const DISPATCH_PRIORITY: Priority = Priority::MAX;
+
+pub struct RegisteredHandler {
+    pub phase:    Phase,
+    pub priority: Priority,
+    pub handler:  Handler,
+}
+
+/// Handlers by event type, each list in the order they are called.
+pub type HandlerMap = HashMap<EventType, Vec<RegisteredHandler>>;
+
+/// Adds a handler after those that run before it or at the same time.
+pub fn insert_handler(handlers: &mut Vec<RegisteredHandler>, registered: RegisteredHandler) {
+    let key = (registered.phase, registered.priority);
+    let index = handlers
+        .iter()
+        .position(|other| (other.phase, other.priority) > key)
+        .unwrap_or(handlers.len());
+    handlers.insert(index, registered);
+}
 
 pub struct HandlerData {
     pub handlers:          Option<HandlerMap>,
@@ -523,7 +813,8 @@ impl HandlerData {
 }
 
 /// Trait for widgets that can handle various events. Use `.on` to register a handler and `.emit`
-/// to emit an event which will cause all handlers for the event's type to be called.
+/// to emit an event which will cause all handlers for the event's type to be called: those of the
+/// capture phase, then those of the bubble phase, each by priority (see `Phase`).
 ///
 /// Generally, this should be implemented on widgets using impl_emit_event!(...), rather than
 /// handwriting implementations for the two required methods.
@@ -552,7 +843,23 @@ pub trait EmitEvent: Downcast {
     /// # Errors
     ///
     /// * It is an error to call this from within a handler.
-    fn on(&mut self, what: EventType, f: Handler) -> Result<(), Box<dyn Error>>;
+    fn on(&mut self, what: EventType, f: Handler) -> Result<(), Box<dyn Error>> {
+        self.on_with_priority(what, Phase::Bubble, DEFAULT_PRIORITY, f)
+    }
+
+    /// Like `.on`, but the handler runs in the given phase, ahead of handlers in that phase with a
+    /// higher priority.
+    ///
+    /// # Errors
+    ///
+    /// * It is an error to call this from within a handler.
+    fn on_with_priority(
+        &mut self,
+        what: EventType,
+        phase: Phase,
+        priority: Priority,
+        f: Handler,
+    ) -> Result<(), Box<dyn Error>>;
 
     /// Emit an event -- call all handlers for this event's type (as long as they return NotHandled)
     ///
@@ -563,10 +870,47 @@ pub trait EmitEvent: Downcast {
     /// * The first error to be returned by a handler will be returned here, and no other handlers
     ///   will run.
     fn emit(&mut self, event: &Event, uictx: &mut UIContext) -> Result<Handled, Box<dyn Error>>;
+
+    /// The event type of each handler registered with `.on`, in the order they were registered.
+    fn registered_events(&self) -> &[EventType] {
+        &[]
+    }
 }
 
 impl_downcast!(EmitEvent);
 
+/// The handlers a container passes events on to its nested widgets with, as plain functions.
+pub type ForwardingHandler = fn(&mut dyn EmitEvent, &mut UIContext, &Event) -> Result<Handled, Box<dyn Error>>;
+
+/// Registers the handlers that a container, such as a `TabContainer`, passes events on to its
+/// nested widgets with, between the capture and bubble phases. Mouse events that are also
+/// broadcast, like `MouseMove`, only go to `broadcast`.
+pub fn forward_to_nested(
+    container: &mut dyn EmitEvent,
+    mouse: ForwardingHandler,
+    key: ForwardingHandler,
+    broadcast: ForwardingHandler,
+) {
+    let mut handlers: Vec<(EventType, Handler)> = vec![];
+    for &event_type in MOUSE_EVENTS {
+        if !event_type.is_broadcast_event() {
+            handlers.push((event_type, Box::new(mouse)));
+        }
+    }
+    for &event_type in KEY_EVENTS {
+        handlers.push((event_type, Box::new(key)));
+    }
+    for &event_type in BROADCASTED_EVENTS {
+        handlers.push((event_type, Box::new(broadcast)));
+    }
+    for (event_type, handler) in handlers {
+        // unwrap OK because we aren't calling from within a handler
+        container
+            .on_with_priority(event_type, Phase::Capture, DISPATCH_PRIORITY, handler)
+            .unwrap();
+    }
+}
+
 /// Implement EmitEvent for a widget (though strictly speaking non-widgets can implement it).
 ///
 /// # Example
@@ -594,10 +938,12 @@ macro_rules! impl_emit_event {
         use crate::ui::context::Handled as H;
         use H::*;
         impl crate::ui::context::EmitEvent for $widget_name {
-            /// Setup a handler for an event type
-            fn on(
+            /// Setup a handler for an event type, in a phase and at a priority
+            fn on_with_priority(
                 &mut self,
                 what: crate::ui::context::EventType,
+                phase: crate::ui::context::Phase,
+                priority: crate::ui::context::Priority,
                 hdlr: crate::ui::context::Handler,
             ) -> Result<(), Box<dyn std::error::Error>> {
                 let handlers =
@@ -613,14 +959,21 @@ macro_rules! impl_emit_event {
                             .into()
                         })?;
 
-                let handler_vec: &mut Vec<crate::ui::context::Handler>;
+                let handler_vec: &mut Vec<crate::ui::context::RegisteredHandler>;
                 if let Some(vref) = handlers.get_mut(&what) {
                     handler_vec = vref;
                 } else {
                     handlers.insert(what, vec![]);
                     handler_vec = handlers.get_mut(&what).unwrap();
                 }
-                handler_vec.push(hdlr);
+                crate::ui::context::insert_handler(
+                    handler_vec,
+                    crate::ui::context::RegisteredHandler {
+                        phase,
+                        priority,
+                        handler: hdlr,
+                    },
+                );
                 self.$handler_data_field.registered_events.push(what);
                 Ok(())
             }
@@ -646,8 +999,8 @@ macro_rules! impl_emit_event {
                 // handle regular (non-forwarded) events
                 if let Some(handler_vec) = handlers.get_mut(&event.what) {
                     // call each handler for this event type, until a Handled is returned
-                    for hdlr in handler_vec {
-                        let handled = hdlr(self, uictx, event)?;
+                    for registered in handler_vec {
+                        let handled = (registered.handler)(self, uictx, event)?;
                         if handled == Handled {
                             event_handled = Handled;
                             break;
@@ -666,8 +1019,8 @@ macro_rules! impl_emit_event {
                     for event in events {
                         if let Some(handler_vec) = handlers.get_mut(&event.what) {
                             // call each handler for this event type, until a Handled is returned
-                            for hdlr in handler_vec {
-                                let handled = hdlr(self, uictx, &event)?;
+                            for registered in handler_vec {
+                                let handled = (registered.handler)(self, uictx, &event)?;
                                 if handled == Handled {
                                     event_handled = Handled;
                                     break;
@@ -680,6 +1033,10 @@ macro_rules! impl_emit_event {
                 self.$handler_data_field.handlers = Some(handlers); // put it back
                 Ok(event_handled)
             }
+
+            fn registered_events(&self) -> &[crate::ui::context::EventType] {
+                &self.$handler_data_field.registered_events
+            }
         }
     };
 }
@@ -689,9 +1046,22 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_into_enum_iter() {
-        let all: Vec<EventType> = EventType::into_enum_iter().collect();
-        assert_eq!(all.len(), EventType::VARIANT_COUNT);
-        assert!(all.contains(&EventType::Click));
+    fn test_custom_event_types_by_name() {
+        let selected = EventType::custom("pattern_selected");
+        assert_eq!(EventType::custom("pattern_selected"), selected);
+        assert_ne!(EventType::custom("score_updated"), selected);
+        assert_eq!(selected.custom_name(), Some("pattern_selected"));
+        assert_eq!(EventType::Click.custom_name(), None);
+
+        // a name from a script is only known once the code has asked for it
+        let from_script = String::from("round_won");
+        assert_eq!(EventType::known_custom_name(&from_script), None);
+        EventType::custom("round_won");
+        assert_eq!(EventType::known_custom_name(&from_script), Some("round_won"));
+
+        let event = Event::new_custom("score_updated", 42u32);
+        assert!(event.what.is_custom_event());
+        assert_eq!(event.payload::<u32>(), Some(&42));
+        assert_eq!(event.payload::<String>(), None);
     }
 }
@@ -0,0 +1,116 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use ggez::graphics::{self, DrawMode, DrawParam, Rect, Text};
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+
+use super::common::FontInfo;
+use crate::constants::{self, colors::*};
+
+/// A list of actions that pops up where the player right-clicked. It isn't a widget of its own:
+/// the widget that opens it draws it on top of itself and asks it which item was clicked.
+#[derive(Debug, Clone)]
+pub struct ContextMenu {
+    items:     Vec<String>,
+    rect:      Rect,
+    font_info: FontInfo,
+}
+
+impl ContextMenu {
+    /// Creates a menu with its top left corner at `point`.
+    pub fn new(items: Vec<String>, point: Point2<f32>, font_info: FontInfo) -> Self {
+        let longest = items.iter().map(|item| item.chars().count()).max().unwrap_or(0);
+        let width = (longest + 2) as f32 * font_info.char_dimensions.x;
+        let height = items.len() as f32 * ContextMenu::item_height(&font_info);
+        ContextMenu {
+            items,
+            rect: Rect::new(point.x, point.y, width, height),
+            font_info,
+        }
+    }
+
+    /// How tall each item is, so a menu of `n` items is `n` times this.
+    pub fn item_height(font_info: &FontInfo) -> f32 {
+        font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING * 2.0
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn move_to(&mut self, point: Point2<f32>) {
+        self.rect.move_to(point);
+    }
+
+    /// Index of the item under `point`, if any.
+    pub fn item_at(&self, point: Point2<f32>) -> Option<usize> {
+        if !self.rect.contains(point) {
+            return None;
+        }
+        let index = ((point.y - self.rect.y) / ContextMenu::item_height(&self.font_info)) as usize;
+        Some(index.min(self.items.len() - 1))
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let background = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), self.rect, scheme().chat_pane_fill)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        let border = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::stroke(constants::CHATBOX_BORDER_PIXELS),
+            self.rect,
+            scheme().chatbox_border,
+        )?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        let item_height = ContextMenu::item_height(&self.font_info);
+        for (i, item) in self.items.iter().enumerate() {
+            let mut text = Text::new(item.clone());
+            self.font_info.apply(&mut text);
+            let point = Point2 {
+                x: self.rect.x + self.font_info.char_dimensions.x,
+                y: self.rect.y + i as f32 * item_height + constants::CHATBOX_LINE_SPACING,
+            };
+            graphics::queue_text(ctx, &text, point, Some(scheme().chatbox_text));
+        }
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, graphics::FilterMode::Linear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ggez::graphics::PxScale;
+    use ggez::mint::Vector2;
+
+    #[test]
+    fn context_menu_item_at() {
+        let font_info = FontInfo {
+            font:            (),
+            scale:           PxScale::from(1.0),
+            char_dimensions: Vector2 { x: 5.0f32, y: 6.0f32 },
+        };
+        let items = vec!["Mute".to_owned(), "Report".to_owned()];
+        let menu = ContextMenu::new(items, Point2 { x: 100.0, y: 50.0 }, font_info);
+        assert_eq!(menu.rect(), Rect::new(100.0, 50.0, 40.0, 20.0));
+        assert_eq!(menu.item_at(Point2 { x: 101.0, y: 51.0 }), Some(0));
+        assert_eq!(menu.item_at(Point2 { x: 139.0, y: 61.0 }), Some(1));
+        assert_eq!(menu.item_at(Point2 { x: 99.0, y: 51.0 }), None);
+        assert_eq!(menu.item_at(Point2 { x: 101.0, y: 71.0 }), None);
+    }
+}
@@ -0,0 +1,104 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Shows the mouse cursor that suits what the mouse is over, such as a text beam over a text field
+//! or a hand over a button, going by the `Widget::cursor` hints of the widgets under it.
+
+use ggez::input::mouse::{self, CursorIcon};
+use ggez::mint::Point2;
+use ggez::Context;
+
+use super::{common::within_widget, Layering};
+
+pub struct CursorManager {
+    shown: CursorIcon,
+}
+
+/// The cursor for `point`: the hint of the last widget of the top layer drawn there, which is the
+/// most deeply nested, or if it has none then that of the next one down, and so on. The parts of
+/// widgets scrolled out of sight don't count.
+fn cursor_at(layer: &Layering, point: &Point2<f32>) -> CursorIcon {
+    let (_, top_layer) = layer.widgets_to_draw();
+    top_layer
+        .iter()
+        .rev()
+        .filter(|id| layer.clip_rect(id).map_or(true, |clip| within_widget(point, &clip)))
+        .filter_map(|id| layer.get_widget(id).ok())
+        .filter(|widget| within_widget(point, &widget.rect()))
+        .find_map(|widget| widget.cursor())
+        .unwrap_or(CursorIcon::Default)
+}
+
+impl CursorManager {
+    pub fn new() -> Self {
+        CursorManager {
+            shown: CursorIcon::Default,
+        }
+    }
+
+    /// Shows the cursor for the widgets of `layer` under `point`, if it isn't shown already.
+    pub fn update(&mut self, ctx: &mut Context, layer: &Layering, point: Point2<f32>) {
+        let cursor = cursor_at(layer, &point);
+        if cursor != self.shown {
+            mouse::set_cursor_type(ctx, cursor);
+            self.shown = cursor;
+        }
+    }
+
+    /// Goes back to the plain arrow, as for screens without widgets.
+    pub fn reset(&mut self, ctx: &mut Context) {
+        if self.shown != CursorIcon::Default {
+            mouse::set_cursor_type(ctx, CursorIcon::Default);
+            self.shown = CursorIcon::Default;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{harness::dummy_font_info, GameArea, InsertLocation, Pane, TextField, Widget};
+    use super::*;
+    use ggez::graphics::Rect;
+
+    #[test]
+    fn test_innermost_widget_with_a_hint_decides() {
+        let mut layer = Layering::new();
+        let pane_id = layer
+            .add_widget(
+                Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        let mut tf = TextField::new(dummy_font_info(), Rect::new(10.0, 10.0, 100.0, 20.0));
+        tf.hide_when_empty = false;
+        layer
+            .add_widget(Box::new(tf), InsertLocation::ToNestedContainer(&pane_id))
+            .unwrap();
+        let mut game_area = GameArea::new();
+        game_area.set_rect(Rect::new(300.0, 0.0, 100.0, 100.0)).unwrap();
+        layer
+            .add_widget(Box::new(game_area), InsertLocation::AtCurrentLayer)
+            .unwrap();
+
+        let point = |x, y| Point2 { x, y };
+        assert_eq!(cursor_at(&layer, &point(20.0, 20.0)), CursorIcon::Text);
+        assert_eq!(cursor_at(&layer, &point(150.0, 150.0)), CursorIcon::Default); // the pane has no hint
+        assert_eq!(cursor_at(&layer, &point(350.0, 50.0)), CursorIcon::Crosshair);
+        assert_eq!(cursor_at(&layer, &point(500.0, 500.0)), CursorIcon::Default);
+    }
+}
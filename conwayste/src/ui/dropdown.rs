@@ -0,0 +1,377 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse::CursorIcon;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, KeyCodeOrChar, UIContext};
+use super::{
+    common::{within_widget, FontInfo},
+    contextmenu::ContextMenu,
+    textcache::TextCache,
+    widget::Widget,
+    UIError, UIResult,
+};
+
+use crate::constants::colors::*;
+use crate::i18n::tr_args;
+
+/// Shows one of a list of options, and the whole list under it once clicked, for picking another.
+/// Up and Down pick the option before or after without opening the list. Each change, whether by
+/// the player or through `change_selected`, is followed by a `ValueChanged` event.
+///
+/// While open, the list counts as part of the dropdown, so that clicks on it reach it; it should
+/// have room under it in whatever it is nested in.
+pub struct Dropdown {
+    id:               Option<NodeId>,
+    z_index:          usize,
+    font_info:        FontInfo,
+    pub dimensions:   Rect, // of the closed dropdown
+    options:          Vec<String>,
+    selected:         usize,
+    list:             Option<ContextMenu>, // while open
+    pub focused:      bool,                // has keyboard focus?
+    pub handler_data: HandlerData,         // required for impl_emit_event!
+}
+
+impl fmt::Debug for Dropdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Dropdown {{ id: {:?}, z-index: {}, Dimensions: {:?}, Selected: {:?} }}",
+            self.id,
+            self.z_index,
+            self.dimensions,
+            self.options.get(self.selected)
+        )
+    }
+}
+
+impl Dropdown {
+    /// Creates a dropdown of `options`, with the one at index `selected` showing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no options.
+    pub fn new(font_info: FontInfo, dimensions: Rect, options: Vec<String>, selected: usize) -> Self {
+        assert!(!options.is_empty(), "a dropdown needs options to pick from");
+        let selected = selected.min(options.len() - 1);
+        let mut dropdown = Dropdown {
+            id: None,
+            z_index: std::usize::MAX,
+            font_info,
+            dimensions,
+            options,
+            selected,
+            list: None,
+            focused: false,
+            handler_data: HandlerData::new(),
+        };
+
+        // unwrap OK b/c not being called within handler
+        dropdown
+            .on(EventType::GainFocus, Box::new(Dropdown::focus_change_handler))
+            .unwrap();
+        dropdown
+            .on(EventType::LoseFocus, Box::new(Dropdown::focus_change_handler))
+            .unwrap();
+        dropdown
+            .on(EventType::Click, Box::new(Dropdown::click_handler))
+            .unwrap();
+        dropdown
+            .on(EventType::KeyPress, Box::new(Dropdown::keypress_handler))
+            .unwrap();
+
+        dropdown
+    }
+
+    fn focus_change_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let dropdown = obj.downcast_mut::<Dropdown>().unwrap(); // unwrap OK because this will always be Dropdown
+        dropdown.focused = event.what == EventType::GainFocus;
+        if !dropdown.focused {
+            dropdown.list = None;
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn click_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, event: &Event) -> Result<Handled, Box<dyn Error>> {
+        let dropdown = obj.downcast_mut::<Dropdown>().unwrap(); // unwrap OK because this will always be Dropdown
+        let point = event.point.unwrap(); // unwrap OK because a Click event always has a point
+        match dropdown.list.take() {
+            Some(list) => {
+                if let Some(index) = list.item_at(point) {
+                    dropdown.change_selected(uictx, index)?;
+                }
+            }
+            None if within_widget(&point, &dropdown.dimensions) => dropdown.open(),
+            None => return Ok(Handled::NotHandled),
+        }
+        Ok(Handled::Handled)
+    }
+
+    fn keypress_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let dropdown = obj.downcast_mut::<Dropdown>().unwrap(); // unwrap OK because this will always be Dropdown
+        match event.key {
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Up)) if dropdown.selected > 0 => {
+                let index = dropdown.selected - 1;
+                dropdown.change_selected(uictx, index)?;
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Down)) if dropdown.selected + 1 < dropdown.options.len() => {
+                let index = dropdown.selected + 1;
+                dropdown.change_selected(uictx, index)?;
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Up)) | Some(KeyCodeOrChar::KeyCode(KeyCode::Down)) => {}
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Space)) | Some(KeyCodeOrChar::KeyCode(KeyCode::Return)) => {
+                if dropdown.list.take().is_none() {
+                    dropdown.open();
+                }
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Escape)) if dropdown.is_open() => dropdown.list = None,
+            _ => return Ok(Handled::NotHandled),
+        }
+        Ok(Handled::Handled)
+    }
+
+    fn open(&mut self) {
+        let point = Point2 {
+            x: self.dimensions.x,
+            y: self.dimensions.bottom(),
+        };
+        self.list = Some(ContextMenu::new(self.options.clone(), point, self.font_info));
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.list.is_some()
+    }
+
+    /// The index of the option showing.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.options[self.selected]
+    }
+
+    /// Shows another option without a `ValueChanged` event, as when bringing the dropdown in line
+    /// with a setting that changed elsewhere. An index past the end is ignored.
+    pub fn set_selected(&mut self, index: usize) {
+        if index < self.options.len() {
+            self.selected = index;
+        }
+    }
+
+    /// Shows another option from a handler, sending the dropdown a `ValueChanged` event if it
+    /// changed.
+    pub fn change_selected(&mut self, uictx: &mut UIContext, index: usize) -> Result<Handled, Box<dyn Error>> {
+        if index == self.selected || index >= self.options.len() {
+            return Ok(Handled::NotHandled);
+        }
+        self.selected = index;
+        self.emit(&Event::new_value_changed(), uictx)
+    }
+}
+
+impl Widget for Dropdown {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    /// Takes in the list while it is open.
+    fn rect(&self) -> Rect {
+        match self.list {
+            Some(ref list) => self.dimensions.combine_with(list.rect()),
+            None => self.dimensions,
+        }
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Dropdown {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        self.list = None;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+        self.list = None;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Dropdown {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+        self.list = None;
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let border_color = if self.focused {
+            scheme().checkbox_border_on_hover
+        } else {
+            scheme().pane_border
+        };
+        let border = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(2.0), self.dimensions, border_color)?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        // the option showing, then a "v" at the right end to show that there are others
+        let char_dimensions = self.font_info.char_dimensions;
+        let text_y = self.dimensions.y + (self.dimensions.h - char_dimensions.y) / 2.0;
+        let text = texts.get(self.font_info.fragment(self.selected_text()).color(scheme().value_text));
+        let text_point = Point2 {
+            x: self.dimensions.x + char_dimensions.x,
+            y: text_y,
+        };
+        graphics::draw(ctx, &*text, DrawParam::default().dest(text_point))?;
+        let arrow = texts.get(self.font_info.fragment("v").color(scheme().menu_text));
+        let arrow_point = Point2 {
+            x: self.dimensions.right() - char_dimensions.x * 2.0,
+            y: text_y,
+        };
+        graphics::draw(ctx, &*arrow, DrawParam::default().dest(arrow_point))?;
+
+        if let Some(ref list) = self.list {
+            list.draw(ctx)?;
+        }
+        Ok(())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accessible_name(&self) -> Option<String> {
+        Some(tr_args("a11y-dropdown", &[("value", &self.selected_text())]))
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
+}
+
+impl_emit_event!(Dropdown, self.handler_data);
+widget_from_id!(Dropdown);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        harness::{dummy_font_info, Harness},
+        InsertLocation,
+    };
+    use super::*;
+    use crate::Screen;
+
+    fn add_dropdown(harness: &mut Harness) -> NodeId {
+        let options = vec!["Low".to_owned(), "Medium".to_owned(), "High".to_owned()];
+        let dropdown = Dropdown::new(dummy_font_info(), Rect::new(0.0, 0.0, 100.0, 20.0), options, 1);
+        harness.add_widget(Box::new(dropdown), InsertLocation::AtCurrentLayer)
+    }
+
+    fn selected(harness: &Harness, dropdown_id: &NodeId) -> usize {
+        Dropdown::widget_from_id(&harness.layering, dropdown_id)
+            .unwrap()
+            .selected()
+    }
+
+    #[test]
+    fn test_clicking_an_option_in_the_list_picks_it() {
+        let mut harness = Harness::new(Screen::Options);
+        let dropdown_id = add_dropdown(&mut harness);
+        harness.click(50.0, 10.0).unwrap();
+        let dropdown = Dropdown::widget_from_id(&harness.layering, &dropdown_id).unwrap();
+        assert!(dropdown.is_open());
+        let list_rect = dropdown.list.as_ref().unwrap().rect();
+        assert_eq!(dropdown.rect().bottom(), list_rect.bottom());
+
+        // the third option, a third of the way from the bottom of the list
+        harness
+            .click(list_rect.x + 1.0, list_rect.bottom() - list_rect.h / 6.0)
+            .unwrap();
+        let dropdown = Dropdown::widget_from_id(&harness.layering, &dropdown_id).unwrap();
+        assert!(!dropdown.is_open());
+        assert_eq!(dropdown.selected_text(), "High");
+        assert_eq!(dropdown.rect(), Rect::new(0.0, 0.0, 100.0, 20.0));
+    }
+
+    #[test]
+    fn test_arrow_keys_pick_the_next_option_and_stop_at_the_ends() {
+        let mut harness = Harness::new(Screen::Options);
+        let dropdown_id = add_dropdown(&mut harness);
+        harness.focus(&dropdown_id).unwrap();
+        harness.press_key(KeyCode::Down).unwrap();
+        harness.press_key(KeyCode::Down).unwrap();
+        assert_eq!(selected(&harness, &dropdown_id), 2);
+        harness.press_key(KeyCode::Up).unwrap();
+        harness.press_key(KeyCode::Up).unwrap();
+        harness.press_key(KeyCode::Up).unwrap();
+        assert_eq!(selected(&harness, &dropdown_id), 0);
+    }
+}
@@ -0,0 +1,451 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use ggez::graphics::Rect;
+use ggez::mint::Point2;
+use ggez::Context;
+
+use id_tree::NodeId;
+
+use super::{
+    common::FontInfo,
+    context::{EmitEvent, Event, EventType, Handled, Handler, UIContext},
+    contextmenu::ContextMenu,
+    validator::Validator,
+    Button, Checkbox, Dropdown, InsertLocation, Label, Layering, Pane, Slider, TextField, UIError, UIResult, Widget,
+};
+use crate::constants::colors::scheme;
+
+const FORM_PADDING: f32 = 20.0; // between the pane's border and its widgets
+const FORM_ROW_SPACING: f32 = 15.0;
+const FORM_COLUMN_SPACING: f32 = 20.0; // between a field's label and the field
+const FORM_TEXT_FIELD_HEIGHT: f32 = 30.0;
+const FORM_CHECKBOX_SIZE: f32 = 20.0;
+const FORM_SLIDER_HEIGHT: f32 = 20.0;
+
+/// Refers to one of the widgets of a `Form`, of type `W`, for reading its value once built.
+pub struct FormHandle<W> {
+    index:  usize, // into the form's widget IDs, in the order they were added
+    widget: PhantomData<W>,
+}
+
+// Derived impls would require `W` itself to be `Clone` and `Copy`
+impl<W> Clone for FormHandle<W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<W> Copy for FormHandle<W> {
+}
+
+enum FormItem {
+    Text {
+        label:      String,
+        width:      f32,
+        text:       String,
+        validators: Vec<Validator>,
+    },
+    Checkbox {
+        label:   String,
+        checked: bool,
+    },
+    Value {
+        label: String,
+        value: String,
+    },
+    Slider {
+        label: String,
+        width: f32,
+        min:   i32,
+        max:   i32,
+        step:  i32,
+        value: i32,
+    },
+    Dropdown {
+        label:    String,
+        width:    f32,
+        options:  Vec<String>,
+        selected: usize,
+    },
+    Button {
+        text:   String,
+        submit: bool, // only pressable while the form is valid
+    },
+}
+
+/// Lays out a dialog: a column of labeled fields with a row of buttons under them, in a `Pane`.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut builder = FormBuilder::new(font_info);
+/// let name = builder.text_field("Name".to_owned(), 200.0, String::new());
+/// let ok = builder.submit_button("OK".to_owned());
+/// let form = builder.build(ctx, &mut layering, Point2 { x: 100.0, y: 100.0 }, InsertLocation::AtNextLayer)?;
+/// ```
+pub struct FormBuilder {
+    font_info: FontInfo,
+    items:     Vec<FormItem>,
+}
+
+impl FormBuilder {
+    pub fn new(font_info: FontInfo) -> Self {
+        FormBuilder {
+            font_info,
+            items: vec![],
+        }
+    }
+
+    fn add<W>(&mut self, item: FormItem) -> FormHandle<W> {
+        self.items.push(item);
+        FormHandle {
+            index:  self.items.len() - 1,
+            widget: PhantomData,
+        }
+    }
+
+    /// Adds a text field `width` pixels wide, starting out with `text`.
+    pub fn text_field(&mut self, label: String, width: f32, text: String) -> FormHandle<TextField> {
+        self.validated_text_field(label, width, text, vec![])
+    }
+
+    /// Adds a text field whose text must pass `validators` before the form can be submitted.
+    pub fn validated_text_field(
+        &mut self,
+        label: String,
+        width: f32,
+        text: String,
+        validators: Vec<Validator>,
+    ) -> FormHandle<TextField> {
+        self.add(FormItem::Text {
+            label,
+            width,
+            text,
+            validators,
+        })
+    }
+
+    /// Adds a checkbox. Its label is drawn beside it, so it spans both columns.
+    pub fn checkbox(&mut self, label: String, checked: bool) -> FormHandle<Checkbox> {
+        self.add(FormItem::Checkbox { label, checked })
+    }
+
+    /// Adds a value the player can read but not change, such as one kept current by an Update
+    /// handler.
+    pub fn value(&mut self, label: String, value: String) -> FormHandle<Label> {
+        self.add(FormItem::Value { label, value })
+    }
+
+    /// Adds a slider `width` pixels wide, from `min` to `max` by `step`, starting out at `value`.
+    pub fn slider(
+        &mut self,
+        label: String,
+        width: f32,
+        min: i32,
+        max: i32,
+        step: i32,
+        value: i32,
+    ) -> FormHandle<Slider> {
+        self.add(FormItem::Slider {
+            label,
+            width,
+            min,
+            max,
+            step,
+            value,
+        })
+    }
+
+    /// Adds a dropdown `width` pixels wide, showing the option at index `selected`. Its list opens
+    /// under it, over the rows below, and the form is made tall enough to hold it.
+    pub fn dropdown(
+        &mut self,
+        label: String,
+        width: f32,
+        options: Vec<String>,
+        selected: usize,
+    ) -> FormHandle<Dropdown> {
+        self.add(FormItem::Dropdown {
+            label,
+            width,
+            options,
+            selected,
+        })
+    }
+
+    /// Adds a button to the row at the bottom.
+    pub fn button(&mut self, text: String) -> FormHandle<Button> {
+        self.add(FormItem::Button { text, submit: false })
+    }
+
+    /// Adds a button to the row at the bottom that can only be pressed while the form is valid.
+    pub fn submit_button(&mut self, text: String) -> FormHandle<Button> {
+        self.add(FormItem::Button { text, submit: true })
+    }
+
+    /// Creates the widgets and adds them to `layer`, in a pane with its top left corner at `point`
+    /// that is inserted at `location`. The fields get keyboard focus in the order they were added,
+    /// followed by the buttons.
+    pub fn build(
+        self,
+        ctx: &mut Context,
+        layer: &mut Layering,
+        point: Point2<f32>,
+        location: InsertLocation,
+    ) -> UIResult<Form> {
+        let font_info = self.font_info;
+        let char_h = font_info.char_dimensions.y;
+
+        // Field labels go in a column as wide as the widest of them
+        let mut labels = vec![];
+        for item in &self.items {
+            let label = match item {
+                FormItem::Text { label, .. }
+                | FormItem::Value { label, .. }
+                | FormItem::Slider { label, .. }
+                | FormItem::Dropdown { label, .. } => Some(Label::new(
+                    ctx,
+                    font_info,
+                    label.clone(),
+                    scheme().menu_text,
+                    Point2 { x: 0.0, y: 0.0 },
+                )),
+                _ => None,
+            };
+            labels.push(label);
+        }
+        let label_column_w = labels.iter().flatten().map(|l| l.rect().w).fold(0.0, f32::max);
+        let field_x = FORM_PADDING + label_column_w + FORM_COLUMN_SPACING;
+
+        let pressed = Arc::new(Mutex::new(None));
+        let mut fields: Vec<(usize, Box<dyn Widget>)> = vec![]; // with the index of their handle
+        let mut buttons: Vec<(usize, Box<dyn Widget>)> = vec![];
+        let mut submit_indices = vec![];
+        let mut row_labels: Vec<Box<dyn Widget>> = vec![];
+        let mut y = FORM_PADDING;
+        let mut width = 0.0f32;
+        let mut list_bottom = 0.0f32; // of the lowest dropdown's list, when open
+        for (index, (item, label)) in self.items.into_iter().zip(labels).enumerate() {
+            let row_h = match item {
+                FormItem::Text {
+                    width: w,
+                    text,
+                    validators,
+                    ..
+                } => {
+                    let mut tf = TextField::new(font_info, Rect::new(field_x, y, w, FORM_TEXT_FIELD_HEIGHT));
+                    tf.hide_when_empty = false;
+                    tf.set_text(text);
+                    for validator in validators {
+                        tf.add_validator(validator);
+                    }
+                    width = width.max(field_x + w);
+                    fields.push((index, Box::new(tf)));
+                    FORM_TEXT_FIELD_HEIGHT
+                }
+                FormItem::Checkbox { label, checked } => {
+                    let checkbox_rect = Rect::new(FORM_PADDING, y, FORM_CHECKBOX_SIZE, FORM_CHECKBOX_SIZE);
+                    let checkbox = Checkbox::new(ctx, checked, font_info, label, checkbox_rect);
+                    width = width.max(checkbox.label.rect().right());
+                    fields.push((index, Box::new(checkbox)));
+                    FORM_CHECKBOX_SIZE.max(char_h)
+                }
+                FormItem::Value { value, .. } => {
+                    let value_point = Point2 { x: field_x, y };
+                    let value_label = Label::new(ctx, font_info, value, scheme().value_text, value_point);
+                    width = width.max(value_label.rect().right());
+                    fields.push((index, Box::new(value_label)));
+                    char_h
+                }
+                FormItem::Slider {
+                    width: w,
+                    min,
+                    max,
+                    step,
+                    value,
+                    ..
+                } => {
+                    let slider_rect = Rect::new(field_x, y, w, FORM_SLIDER_HEIGHT);
+                    let slider = Slider::new(font_info, slider_rect, min, max, step, value);
+                    width = width.max(field_x + w);
+                    fields.push((index, Box::new(slider)));
+                    FORM_SLIDER_HEIGHT
+                }
+                FormItem::Dropdown {
+                    width: w,
+                    options,
+                    selected,
+                    ..
+                } => {
+                    let dropdown_rect = Rect::new(field_x, y, w, FORM_TEXT_FIELD_HEIGHT);
+                    let list_h = options.len() as f32 * ContextMenu::item_height(&font_info);
+                    list_bottom = list_bottom.max(dropdown_rect.bottom() + list_h);
+                    let dropdown = Dropdown::new(font_info, dropdown_rect, options, selected);
+                    width = width.max(field_x + w);
+                    fields.push((index, Box::new(dropdown)));
+                    FORM_TEXT_FIELD_HEIGHT
+                }
+                FormItem::Button { text, submit } => {
+                    let mut button = Button::new(ctx, font_info, text);
+                    button
+                        .on(EventType::Click, get_form_click_handler(pressed.clone(), index))
+                        .unwrap(); // unwrap OK because not in handler
+                    if submit {
+                        submit_indices.push(index);
+                    }
+                    buttons.push((index, Box::new(button)));
+                    continue; // laid out below the rows
+                }
+            };
+            if let Some(mut label) = label {
+                // Centered on the row
+                label.set_position(FORM_PADDING, y + (row_h - label.rect().h) / 2.0);
+                row_labels.push(Box::new(label));
+            }
+            y += row_h + FORM_ROW_SPACING;
+        }
+
+        let mut button_x = FORM_PADDING;
+        let mut button_h = 0.0f32;
+        for (_, button) in &mut buttons {
+            let (w, h) = button.size();
+            button.set_position(button_x, y);
+            button_x += w + FORM_COLUMN_SPACING;
+            button_h = button_h.max(h);
+        }
+        let height = if buttons.is_empty() {
+            y - FORM_ROW_SPACING
+        } else {
+            width = width.max(button_x - FORM_COLUMN_SPACING);
+            y + button_h
+        };
+        let height = height.max(list_bottom);
+
+        let pane = Pane::new(Rect::new(point.x, point.y, width + FORM_PADDING, height + FORM_PADDING));
+        let pane_id = layer.add_widget(Box::new(pane), location)?;
+
+        // The row labels go last so that they come after everything that takes focus
+        let mut ids = vec![None; fields.len() + buttons.len()];
+        for (index, widget) in fields.into_iter().chain(buttons) {
+            ids[index] = Some(layer.add_widget(widget, InsertLocation::ToNestedContainer(&pane_id))?);
+        }
+        for label in row_labels {
+            layer.add_widget(label, InsertLocation::ToNestedContainer(&pane_id))?;
+        }
+        let ids: Vec<NodeId> = ids.into_iter().map(|id| id.unwrap()).collect(); // unwrap OK because every item was added
+        let submit_ids = submit_indices.iter().map(|&i| ids[i].clone()).collect();
+
+        Ok(Form {
+            pane_id,
+            ids,
+            submit_ids,
+            pressed,
+        })
+    }
+}
+
+/// Records that the button with the handle `index` was clicked, for `Form::take_click`.
+fn get_form_click_handler(pressed: Arc<Mutex<Option<usize>>>, index: usize) -> Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            *pressed.lock().unwrap() = Some(index);
+            Ok(Handled::Handled)
+        },
+    )
+}
+
+/// A dialog built by `FormBuilder`. Its widgets live in a `Layering`; this keeps track of them.
+#[derive(Debug, Clone)]
+pub struct Form {
+    pane_id:    NodeId,
+    ids:        Vec<NodeId>, // indexed by `FormHandle::index`
+    submit_ids: Vec<NodeId>,
+    pressed:    Arc<Mutex<Option<usize>>>, // index of the button last clicked, until taken
+}
+
+impl Form {
+    /// The pane holding the form, for moving or removing it.
+    pub fn pane_id(&self) -> &NodeId {
+        &self.pane_id
+    }
+
+    pub fn widget_id<W>(&self, handle: FormHandle<W>) -> &NodeId {
+        &self.ids[handle.index]
+    }
+
+    pub fn widget<'a, W: Widget>(&self, layer: &'a Layering, handle: FormHandle<W>) -> UIResult<&'a W> {
+        let id = self.widget_id(handle);
+        match layer.get_widget(id)?.downcast_ref::<W>() {
+            Some(widget) => Ok(widget),
+            None => Err(Box::new(UIError::WidgetNotFound {
+                reason: format!("{:?} is not the type of widget its form handle is for", id),
+            })),
+        }
+    }
+
+    pub fn widget_mut<'a, W: Widget>(&self, layer: &'a mut Layering, handle: FormHandle<W>) -> UIResult<&'a mut W> {
+        let id = self.widget_id(handle);
+        match layer.get_widget_mut(id)?.downcast_mut::<W>() {
+            Some(widget) => Ok(widget),
+            None => Err(Box::new(UIError::WidgetNotFound {
+                reason: format!("{:?} is not the type of widget its form handle is for", id),
+            })),
+        }
+    }
+
+    /// The trimmed text of a text field, if it isn't blank.
+    pub fn text(&self, layer: &Layering, handle: FormHandle<TextField>) -> Option<String> {
+        self.widget(layer, handle).ok().and_then(|tf| tf.text())
+    }
+
+    pub fn is_checked(&self, layer: &Layering, handle: FormHandle<Checkbox>) -> bool {
+        self.widget(layer, handle)
+            .map_or(false, |checkbox| checkbox.is_checked())
+    }
+
+    /// Whether all of the form's text fields pass their validators.
+    pub fn is_valid(&self, layer: &Layering) -> bool {
+        TextField::all_valid(layer, &self.ids)
+    }
+
+    /// Enables the submit buttons only while the form is valid. Call this after each round of
+    /// events.
+    pub fn update(&self, layer: &mut Layering) {
+        let valid = self.is_valid(layer);
+        for id in &self.submit_ids {
+            if let Ok(button) = Button::widget_from_id_mut(layer, id) {
+                button.enabled = valid;
+            }
+        }
+    }
+
+    /// Whether `button` was clicked since the last call. Disabled submit buttons can't be clicked.
+    pub fn take_click(&self, button: FormHandle<Button>) -> bool {
+        let mut pressed = self.pressed.lock().unwrap();
+        if *pressed == Some(button.index) {
+            *pressed = None;
+            return true;
+        }
+        false
+    }
+}
@@ -18,22 +18,30 @@
 
 use super::{
     context::{EmitEvent, Event, EventType, Handled, HandlerData, KeyCodeOrChar, UIContext},
+    textcache::TextCache,
     widget::Widget,
     UIError, UIResult,
 };
-use crate::{config::Config, constants::*, viewport::ZoomDirection};
+use crate::annotations::{AnnotationTool, Annotations};
+use crate::bookmarks::BookmarkRequest;
+use crate::keymap::{self, KeyAction, Scope};
+use crate::symmetry::{Mirror, Symmetry};
+use crate::{config::Config, constants::*, i18n::tr, viewport::ZoomDirection};
 use conway::{
+    bot::Bot,
     error::ConwayError,
     grids::{BitGrid, CharGrid, Rotation},
-    rle::Pattern,
+    rle::{Pattern, PatternFile},
     universe::{BigBang, CellState, PlayerBuilder, Region, Universe},
     ConwayResult,
 };
 use ggez::graphics::Rect;
 use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse::CursorIcon;
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 use id_tree::NodeId;
+use netwayste::net::Annotation;
 use std::error::Error;
 use std::fmt;
 
@@ -44,9 +52,30 @@ pub struct GameArea {
     dimensions:             Rect,
     handler_data:           HandlerData,
     pub uni:                Universe,
+    bot:                    Bot, // only plays if the bot_opponent setting is on
     game_state:             GameAreaState,
+    gens_per_second:        Option<f64>, // None means one generation per frame
+    gens_owed:              f64,         // fraction of a generation carried over to the next frame
+    networked:              bool,        // the server runs the game; clicks are queued in net_toggles
+    net_toggles:            Vec<(usize, usize)>, // (column, row) of cells clicked in a networked game
+    net_births:             Vec<(usize, usize)>, // (column, row) of cells a pattern was placed on in one
+    selection:              Option<Region>, // cells picked out by Shift-dragging
+    selection_anchor:       Option<(usize, usize)>, // (column, row) where the Shift-drag started
+    cells_placed:           usize,       // cells clicked to life by the player, in every game so far
+    mirror:                 Mirror,      // how the cells drawn are mirrored, if at all
+    annotations:            Annotations, // drawn over the board
+    annotation_tool:        AnnotationTool,
+    annotation_drag:        Option<((usize, usize), (usize, usize))>, // ends being dragged out, each (column, row)
+    share_requested:        bool,        // the player asked to show their annotations to the room
+    bookmark_request:       Option<BookmarkRequest>, // a camera bookmark to save or jump to
+    follow_requested:       bool,        // the player asked the camera to follow someone else's births
+    puzzle:                 bool,        // a puzzle is being played, so no bot and no skipped generations
 }
 
+// At most this many generations are run in one frame when a speed is set, so that a high speed
+// slows the game down rather than freezing it
+const MAX_GENS_PER_FRAME: usize = 64;
+
 impl fmt::Debug for GameArea {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("GameArea")
@@ -90,7 +119,24 @@ impl GameArea {
             dimensions:         Rect::default(),
             handler_data:       HandlerData::new(),
             uni:                uni,
+            bot:                Bot::new(BOT_PLAYER_ID, BOT_TURN_INTERVAL_IN_GENS),
             game_state:         GameAreaState::default(),
+            gens_per_second:    None,
+            gens_owed:          0.0,
+            networked:          false,
+            net_toggles:        vec![],
+            net_births:         vec![],
+            selection:          None,
+            selection_anchor:   None,
+            cells_placed:       0,
+            mirror:             Mirror::new(Symmetry::Off, UNIVERSE_WIDTH_IN_CELLS, UNIVERSE_HEIGHT_IN_CELLS),
+            annotations:        Annotations::new(),
+            annotation_tool:    AnnotationTool::Off,
+            annotation_drag:    None,
+            share_requested:    false,
+            bookmark_request:   None,
+            follow_requested:   false,
+            puzzle:             false,
         };
 
         // Set handlers for toggling has_keyboard_focus.
@@ -117,6 +163,9 @@ impl GameArea {
         game_area
             .on(EventType::Drag, Box::new(GameArea::mouse_handler))
             .unwrap();
+        game_area
+            .on(EventType::Scroll, Box::new(GameArea::scroll_handler))
+            .unwrap();
 
         game_area
     }
@@ -239,18 +288,38 @@ impl GameArea {
         Ok(Handled::NotHandled)
     }
 
-    fn update_handler(
-        obj: &mut dyn EmitEvent,
-        _uictx: &mut UIContext,
-        _evt: &Event,
-    ) -> Result<Handled, Box<dyn Error>> {
+    fn update_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event) -> Result<Handled, Box<dyn Error>> {
         // Unwrap OK because we are guaranteed a GameArea
         let game_area = obj.downcast_mut::<GameArea>().unwrap();
         let game_state = &mut game_area.game_state;
 
-        if game_state.first_gen_was_drawn && (game_state.running || game_state.single_step) {
+        if !game_state.first_gen_was_drawn || game_area.networked {
+            return Ok(NotHandled);
+        }
+        let gens = if game_state.single_step {
+            1
+        } else if !game_state.running {
+            0
+        } else if let Some(gens_per_second) = game_area.gens_per_second {
+            game_area.gens_owed += gens_per_second * uictx.frame_delta().as_secs_f64();
+            // A puzzle's goal is checked once per frame, which must not miss a generation
+            let most = if game_area.puzzle { 1 } else { MAX_GENS_PER_FRAME };
+            let gens = (game_area.gens_owed as usize).min(most);
+            game_area.gens_owed = (game_area.gens_owed - gens as f64).min(1.0);
+            gens
+        } else {
+            1
+        };
+        game_state.single_step = false;
+        if gens > 0 {
+            uictx.request_redraw();
+        }
+        let bot_opponent = uictx.config.get().gameplay.bot_opponent && !game_area.puzzle;
+        for _ in 0..gens {
             game_area.uni.next(); // next generation
-            game_state.single_step = false;
+            if bot_opponent {
+                game_area.bot.on_generation(&mut game_area.uni);
+            }
         }
 
         Ok(NotHandled)
@@ -268,103 +337,156 @@ impl GameArea {
             return Ok(NotHandled);
         }
 
+        let keycode = match evt.key {
+            Some(KeyCodeOrChar::KeyCode(keycode)) => keycode,
+            _ => return Ok(Handled),
+        };
         let game_area_state = &mut game_area.game_state;
 
-        if let Some(KeyCodeOrChar::KeyCode(keycode)) = evt.key {
-            match keycode {
-                KeyCode::Key1 => {
-                    // pressing 1 clears selection
-                    game_area_state.insert_mode = None;
-                }
-                k if k >= KeyCode::Key2 && k <= KeyCode::Key0 => {
-                    // select a pattern
-                    let grid_info_result = bit_pattern_from_char(&mut uictx.config, keycode);
-                    let grid_info = handle_error! {grid_info_result -> (BitGrid, usize, usize),
-                        ConwayError => |e| {
-                            return Err(format!("Invalid pattern bound to keycode {:?}: {}", keycode, e).into())
-                        }
-                    }?;
-                    game_area_state.insert_mode = Some(grid_info);
-                }
-                KeyCode::Return => {
-                    let chatbox_pane_id = uictx.static_node_ids.chatbox_pane_id.clone();
-                    uictx.child_event(Event::new_request_focus(chatbox_pane_id));
-                }
-                KeyCode::R => {
-                    if !evt.key_repeating {
-                        game_area_state.running = !game_area_state.running;
+        match keymap::action(Scope::Game, keycode, evt.modifiers) {
+            Some(KeyAction::ClearPattern) => {
+                game_area_state.insert_mode = None;
+            }
+            Some(KeyAction::PickPattern) => {
+                let grid_info_result = bit_pattern_from_char(&mut uictx.config, keycode);
+                let grid_info = handle_error! {grid_info_result -> (BitGrid, usize, usize),
+                    ConwayError => |e| {
+                        return Err(format!("Invalid pattern bound to keycode {:?}: {}", keycode, e).into())
                     }
-                }
-                KeyCode::Space => {
-                    game_area_state.single_step = true;
-                    game_area_state.running = false;
-                }
-                KeyCode::Up => {
-                    game_area_state.arrow_input = (0, -1);
-                }
-                KeyCode::Down => {
-                    game_area_state.arrow_input = (0, 1);
-                }
-                KeyCode::Left => {
-                    game_area_state.arrow_input = (-1, 0);
-                }
-                KeyCode::Right => {
-                    game_area_state.arrow_input = (1, 0);
-                }
-                KeyCode::Plus | KeyCode::Equals => {
-                    uictx.viewport.adjust_zoom_level(ZoomDirection::ZoomIn);
-                    let cell_size = uictx.viewport.get_cell_size();
-                    uictx.config.modify(|settings| {
-                        settings.gameplay.zoom = cell_size;
+                }?;
+                game_area_state.insert_mode = Some(grid_info);
+            }
+            Some(KeyAction::RotatePattern) => {
+                if let Some((ref mut grid, ref mut width, ref mut height)) = game_area_state.insert_mode {
+                    let rotation = if keycode == KeyCode::Left {
+                        Rotation::CCW
+                    } else {
+                        Rotation::CW
+                    };
+                    grid.rotate(*width, *height, rotation).unwrap_or_else(|e| {
+                        error!("Failed to rotate pattern {:?}: {:?}", rotation, e);
                     });
+                    // reverse the stored width and height
+                    let (new_width, new_height) = (*height, *width);
+                    *width = new_width;
+                    *height = new_height;
                 }
-                KeyCode::Minus => {
-                    uictx.viewport.adjust_zoom_level(ZoomDirection::ZoomOut);
-                    let cell_size = uictx.viewport.get_cell_size();
+            }
+            Some(KeyAction::CycleSymmetry) => {
+                game_area.mirror.symmetry = game_area.mirror.symmetry.next();
+            }
+            Some(KeyAction::MoveSymmetryCenter) => {
+                // to the cell middle or corner nearest the cursor
+                let point = evt.point.unwrap(); // unwrap OK because key presses have the mouse position
+                let origin = uictx.viewport.get_origin();
+                let cell_size = uictx.viewport.get_cell_size();
+                game_area
+                    .mirror
+                    .move_center((point.x - origin.x) / cell_size, (point.y - origin.y) / cell_size);
+            }
+            Some(KeyAction::CycleAnnotationTool) => {
+                game_area.annotation_tool = game_area.annotation_tool.next();
+                game_area.annotation_drag = None;
+            }
+            Some(KeyAction::ShareAnnotations) => {
+                game_area.share_requested = true;
+            }
+            Some(KeyAction::ClearAnnotations) => {
+                game_area.annotations.clear();
+            }
+            Some(KeyAction::SaveBookmark) => {
+                game_area.bookmark_request = keymap::digit(keycode).map(|digit| BookmarkRequest::Save(digit - 1));
+            }
+            Some(KeyAction::JumpToBookmark) => {
+                game_area.bookmark_request = keymap::digit(keycode).map(|digit| BookmarkRequest::Jump(digit - 1));
+            }
+            Some(KeyAction::FollowActivity) => {
+                game_area.follow_requested = true;
+            }
+            Some(KeyAction::FocusChat) => {
+                let chatbox_pane_id = uictx.static_node_ids.chatbox_pane_id.clone();
+                uictx.child_event(Event::new_request_focus(chatbox_pane_id));
+            }
+            Some(KeyAction::ToggleBot) => {
+                // A room's bots are up to the server; this one only plays offline
+                if game_area.networked || game_area.puzzle {
+                    return Ok(NotHandled);
+                }
+                if !evt.key_repeating {
                     uictx.config.modify(|settings| {
-                        settings.gameplay.zoom = cell_size;
+                        settings.gameplay.bot_opponent = !settings.gameplay.bot_opponent;
                     });
                 }
-                KeyCode::D => {
-                    // TODO: do something with this debug code
-                    let visibility = None; // can also do Some(player_id)
-                    let pat = game_area.uni.to_pattern(visibility);
-                    println!("PATTERN DUMP:\n{}", pat.0);
-                }
-                KeyCode::Escape => {
-                    uictx.pop_screen()?;
+            }
+            Some(KeyAction::RunPause) => {
+                if !evt.key_repeating {
+                    game_area_state.running = !game_area_state.running;
                 }
-                _ => {
-                    error!("Unrecognized keycode {:?} in GameArea keypress_handler", keycode);
+            }
+            Some(KeyAction::SingleStep) => {
+                game_area_state.single_step = true;
+                game_area_state.running = false;
+            }
+            Some(KeyAction::Pan) => {
+                game_area_state.arrow_input = match keycode {
+                    KeyCode::Up => (0, -1),
+                    KeyCode::Down => (0, 1),
+                    KeyCode::Left => (-1, 0),
+                    _ => (1, 0),
+                };
+            }
+            Some(KeyAction::ZoomIn) => {
+                GameArea::zoom(uictx, ZoomDirection::ZoomIn);
+            }
+            Some(KeyAction::ZoomOut) => {
+                GameArea::zoom(uictx, ZoomDirection::ZoomOut);
+            }
+            Some(KeyAction::EraseSelection) => {
+                if !game_area.erase_selection() {
                     return Ok(NotHandled);
                 }
             }
-        }
-
-        if evt.shift_pressed && game_area_state.arrow_input != (0, 0) {
-            if let Some((ref mut grid, ref mut width, ref mut height)) = game_area_state.insert_mode {
-                let rotation = match game_area_state.arrow_input {
-                    (-1, 0) => Some(Rotation::CCW),
-                    (1, 0) => Some(Rotation::CW),
-                    (0, 0) => unreachable!(),
-                    _ => None, // do nothing in this case
-                };
-                if let Some(rotation) = rotation {
-                    grid.rotate(*width, *height, rotation).unwrap_or_else(|e| {
-                        error!("Failed to rotate pattern {:?}: {:?}", rotation, e);
-                    });
-                    // reverse the stored width and height
-                    let (new_width, new_height) = (*height, *width);
-                    *width = new_width;
-                    *height = new_height;
-                } else {
-                    info!("Ignoring Shift-<Up/Down>");
+            Some(KeyAction::DumpPattern) => {
+                // TODO: do something with this debug code
+                let visibility = None; // can also do Some(player_id)
+                let pat = game_area.uni.to_pattern(visibility);
+                println!("PATTERN DUMP:\n{}", pat.0);
+            }
+            Some(KeyAction::Back) => {
+                if game_area.selection.take().is_none() {
+                    uictx.pop_screen()?;
                 }
             }
+            _ => {
+                error!("Unrecognized keycode {:?} in GameArea keypress_handler", keycode);
+                return Ok(NotHandled);
+            }
         }
         Ok(Handled)
     }
 
+    /// Zooms in or out, and remembers the new zoom level in the config.
+    fn zoom(uictx: &mut UIContext, direction: ZoomDirection) {
+        uictx.viewport.adjust_zoom_level(direction);
+        let cell_size = uictx.viewport.get_cell_size();
+        uictx.config.modify(|settings| {
+            settings.gameplay.zoom = cell_size;
+        });
+    }
+
+    /// Scrolling the mouse wheel away from the player zooms in, and toward them zooms out.
+    fn scroll_handler(_obj: &mut dyn EmitEvent, uictx: &mut UIContext, evt: &Event) -> Result<Handled, Box<dyn Error>> {
+        let delta = evt.scroll_delta.unwrap(); // unwrap OK because a Scroll always has a delta
+        if delta.y > 0.0 {
+            GameArea::zoom(uictx, ZoomDirection::ZoomIn);
+        } else if delta.y < 0.0 {
+            GameArea::zoom(uictx, ZoomDirection::ZoomOut);
+        } else {
+            return Ok(Handled::NotHandled);
+        }
+        Ok(Handled::Handled)
+    }
+
     fn mouse_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, evt: &Event) -> Result<Handled, Box<dyn Error>> {
         // Unwrap OK because we are guaranteed a GameArea
         let game_area = obj.downcast_mut::<GameArea>().unwrap();
@@ -376,22 +498,65 @@ impl GameArea {
         if let Some(MouseButton::Left) = evt.button {
             let mouse_pos = evt.point.unwrap(); //unwrap safe b/c mouse clicks must have a point
 
-            if let Some((ref grid, width, height)) = game_area_state.insert_mode {
+            if game_area.annotation_tool != AnnotationTool::Off {
+                // annotating rather than placing cells
+                if let Some(cell) = uictx.viewport.get_cell(mouse_pos) {
+                    game_area.annotate(evt.what, (cell.col, cell.row));
+                }
+                event_handled = Handled;
+            } else if let Some((ref grid, width, height)) = game_area_state.insert_mode {
                 // inserting a pattern
                 if evt.what == EventType::Click {
                     if let Some(cell) = uictx.viewport.get_cell(mouse_pos) {
                         let insert_col = cell.col as isize - (width / 2) as isize;
                         let insert_row = cell.row as isize - (height / 2) as isize;
-                        let dst_region = Region::new(insert_col, insert_row, width, height);
-                        game_area
-                            .uni
-                            .copy_from_bit_grid(grid, dst_region, Some(CURRENT_PLAYER_ID));
+                        // the pattern's cells, and where the symmetry mirrors them
+                        let mut births = vec![];
+                        let mut mirrored = vec![];
+                        let (mirror, uni_region) = (game_area.mirror, game_area.uni.region());
+                        grid.each_set(|grid_col, grid_row| {
+                            let col = insert_col + grid_col as isize;
+                            let row = insert_row + grid_row as isize;
+                            if col >= 0 && row >= 0 {
+                                let (col, row) = (col as usize, row as usize);
+                                births.push((col, row));
+                                mirrored.extend(mirror.images_within(uni_region, col, row));
+                            }
+                        });
+                        if game_area.networked {
+                            // the server is asked to bring each of them to life
+                            game_area.net_births.extend(births);
+                            game_area.net_births.extend(mirrored);
+                        } else {
+                            let dst_region = Region::new(insert_col, insert_row, width, height);
+                            game_area
+                                .uni
+                                .copy_from_bit_grid(grid, dst_region, Some(CURRENT_PLAYER_ID));
+                            for (col, row) in mirrored {
+                                let alive = CellState::Alive(Some(CURRENT_PLAYER_ID));
+                                game_area.uni.set(col, row, alive, CURRENT_PLAYER_ID);
+                            }
+                        }
 
                         event_handled = Handled;
                     } else {
                         error!("Failed to get cell coordinates from mouse position during Click");
                     }
                 }
+            } else if evt.what == EventType::MouseButtonHeld && evt.shift_pressed() {
+                // Shift-dragging selects the cells in a rectangle
+                if let Some(cell) = uictx.viewport.get_cell(mouse_pos) {
+                    game_area.selection_anchor = Some((cell.col, cell.row));
+                    game_area.select_to(cell.col, cell.row);
+                    event_handled = Handled;
+                }
+            } else if game_area.selection_anchor.is_some() && evt.what != EventType::MouseButtonHeld {
+                if evt.what == EventType::Click {
+                    game_area.selection_anchor = None;
+                } else if let Some(cell) = uictx.viewport.get_cell(mouse_pos) {
+                    game_area.select_to(cell.col, cell.row);
+                }
+                event_handled = Handled;
             } else {
                 // not inserting a pattern, just drawing single cells
                 match evt.what {
@@ -406,16 +571,58 @@ impl GameArea {
                             // Only make dead cells alive
                             if let Some(cell_state) = game_area_state.drag_draw {
                                 game_area.uni.set(cell.col, cell.row, cell_state, CURRENT_PLAYER_ID);
+                                let images = game_area.mirror.images_within(game_area.uni.region(), cell.col, cell.row);
+                                for (col, row) in images {
+                                    game_area.uni.set(col, row, cell_state, CURRENT_PLAYER_ID);
+                                }
                                 event_handled = Handled;
                             }
                         }
                     }
                     EventType::MouseButtonHeld => {
-                        // depress, no move yet
+                        // depress, no move yet; Ctrl erases, rather than toggling
+                        game_area.selection = None;
+                        game_area.selection_anchor = None;
                         if let Some(cell) = uictx.viewport.get_cell(mouse_pos) {
-                            if game_area_state.drag_draw.is_none() {
+                            let erasing = evt.ctrl_pressed();
+                            let images = game_area.mirror.images_within(game_area.uni.region(), cell.col, cell.row);
+                            if game_area.networked {
+                                let own = game_area.is_own_cell(cell.col, cell.row);
+                                if !erasing || own {
+                                    game_area.net_toggles.push((cell.col, cell.row));
+                                }
+                                if !erasing && !own {
+                                    game_area.cells_placed += 1;
+                                }
+                                // the mirror images end up the way the clicked cell does
+                                for (col, row) in images {
+                                    if !erasing && !own {
+                                        game_area.net_births.push((col, row));
+                                    } else if game_area.is_own_cell(col, row) {
+                                        game_area.net_toggles.push((col, row));
+                                    }
+                                }
+                                event_handled = Handled;
+                            } else if erasing {
+                                game_area
+                                    .uni
+                                    .set(cell.col, cell.row, CellState::Dead, CURRENT_PLAYER_ID);
+                                for (col, row) in images {
+                                    game_area.uni.set(col, row, CellState::Dead, CURRENT_PLAYER_ID);
+                                }
+                                game_area_state.drag_draw = Some(CellState::Dead);
+                                event_handled = Handled;
+                            } else if game_area_state.drag_draw.is_none() {
                                 game_area_state.drag_draw =
                                     game_area.uni.toggle(cell.col, cell.row, CURRENT_PLAYER_ID).ok();
+                                if let Some(cell_state) = game_area_state.drag_draw {
+                                    if let CellState::Alive(_) = cell_state {
+                                        game_area.cells_placed += 1;
+                                    }
+                                    for (col, row) in images {
+                                        game_area.uni.set(col, row, cell_state, CURRENT_PLAYER_ID);
+                                    }
+                                }
                                 event_handled = Handled;
                             }
                         } else {
@@ -438,19 +645,19 @@ impl GameArea {
 ///
 /// This will return an error if the selected RLE pattern is invalid.
 fn bit_pattern_from_char(config: &mut Config, keycode: KeyCode) -> Result<(BitGrid, usize, usize), Box<dyn Error>> {
-    let gameplay = &config.get().gameplay;
-    let rle_str = match keycode {
-        KeyCode::Key2 => &gameplay.pattern2,
-        KeyCode::Key3 => &gameplay.pattern3,
-        KeyCode::Key4 => &gameplay.pattern4,
-        KeyCode::Key5 => &gameplay.pattern5,
-        KeyCode::Key6 => &gameplay.pattern6,
-        KeyCode::Key7 => &gameplay.pattern7,
-        KeyCode::Key8 => &gameplay.pattern8,
-        KeyCode::Key9 => &gameplay.pattern9,
-        KeyCode::Key0 => &gameplay.pattern0,
+    let key = match keycode {
+        KeyCode::Key2 => "2",
+        KeyCode::Key3 => "3",
+        KeyCode::Key4 => "4",
+        KeyCode::Key5 => "5",
+        KeyCode::Key6 => "6",
+        KeyCode::Key7 => "7",
+        KeyCode::Key8 => "8",
+        KeyCode::Key9 => "9",
+        KeyCode::Key0 => "0",
         _ => "", // unexpected
     };
+    let rle_str = config.pattern(key);
     let pat = Pattern(rle_str.to_owned());
     let (width, height) = pat.calc_size()?; // calc_size will fail on invalid RLE -- return it
     let grid = pat.to_new_bit_grid(width, height)?;
@@ -522,7 +729,7 @@ impl Widget for GameArea {
         self.dimensions.translate(dest);
     }
 
-    fn draw(&mut self, _ctx: &mut Context) -> GameResult<()> {
+    fn draw(&mut self, _ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
         // no-op; dummy widget
         Ok(())
     }
@@ -536,6 +743,22 @@ impl Widget for GameArea {
     fn accepts_keyboard_events(&self) -> bool {
         true
     }
+
+    fn accessible_name(&self) -> Option<String> {
+        Some(tr("a11y-game-area"))
+    }
+
+    /// A grabbing hand while the arrow keys pan the view, a cell outline while a pattern is being
+    /// dropped, and otherwise a crosshair for picking out cells.
+    fn cursor(&self) -> Option<CursorIcon> {
+        if self.game_state.arrow_input != (0, 0) {
+            Some(CursorIcon::Grabbing)
+        } else if self.game_state.insert_mode.is_some() {
+            Some(CursorIcon::Cell)
+        } else {
+            Some(CursorIcon::Crosshair)
+        }
+    }
 }
 
 impl_emit_event!(GameArea, self.handler_data);
@@ -561,10 +784,218 @@ impl GameArea {
         self.game_state.drag_draw = dd;
     }
 
+    /// How many cells the player has clicked to life, in every game so far. It's never reset, so
+    /// the cells placed in one game are the difference between its counts at the start and end.
+    pub fn cells_placed(&self) -> usize {
+        self.cells_placed
+    }
+
     pub fn first_gen_drawn(&mut self) {
         self.game_state.first_gen_was_drawn = true;
     }
 
+    /// Sets how many generations run per second while the game is running. `None` runs one per
+    /// frame.
+    pub fn set_speed(&mut self, gens_per_second: Option<f64>) {
+        self.gens_per_second = gens_per_second;
+        self.gens_owed = 0.0;
+    }
+
+    /// Writes the RLE `pattern` into the universe as the current player's cells, with its top left
+    /// corner at `col` and `row`.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if `pattern` is invalid RLE.
+    pub fn spawn_pattern(&mut self, pattern: &str, col: isize, row: isize) -> Result<(), Box<dyn Error>> {
+        let pat = Pattern(pattern.to_owned());
+        let (width, height) = pat.calc_size()?;
+        let grid = pat.to_new_bit_grid(width, height)?;
+        self.uni
+            .copy_from_bit_grid(&grid, Region::new(col, row, width, height), Some(CURRENT_PLAYER_ID));
+        Ok(())
+    }
+
+    /// Puts `uni` in place of the universe shown, paused, and returns the old one. If `puzzle` is
+    /// set, the offline opponent doesn't play in it.
+    pub fn swap_universe(&mut self, uni: Universe, puzzle: bool) -> Universe {
+        self.puzzle = puzzle;
+        self.game_state.running = false;
+        self.game_state.single_step = false;
+        self.gens_owed = 0.0;
+        self.selection = None;
+        self.selection_anchor = None;
+        let region = uni.region();
+        self.mirror = Mirror::new(self.mirror.symmetry, region.width(), region.height());
+        self.annotations.clear();
+        self.annotation_drag = None;
+        std::mem::replace(&mut self.uni, uni)
+    }
+
+    /// Switches between a networked game, where the server runs the universe and clicked cells are
+    /// left for `take_net_toggles`, and a local one.
+    pub fn set_networked(&mut self, networked: bool) {
+        self.networked = networked;
+        self.net_toggles.clear();
+        self.net_births.clear();
+        self.annotations.clear();
+        self.share_requested = false;
+    }
+
+    /// Cells clicked in a networked game since the last call.
+    pub fn take_net_toggles(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.net_toggles)
+    }
+
+    /// Cells a pattern was placed on in a networked game since the last call. Unlike the clicked
+    /// ones, they only ever come to life.
+    pub fn take_net_births(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.net_births)
+    }
+
+    /// Places a marker on a click, or draws an arrow or a measurement from where the mouse button
+    /// went down to where it comes back up, with the annotation tool picked.
+    fn annotate(&mut self, what: EventType, (col, row): (usize, usize)) {
+        let tool = self.annotation_tool;
+        match what {
+            EventType::MouseButtonHeld if tool != AnnotationTool::Marker => {
+                self.annotation_drag = Some(((col, row), (col, row)));
+            }
+            EventType::Drag => {
+                if let Some((_, ref mut end)) = self.annotation_drag {
+                    *end = (col, row);
+                }
+            }
+            EventType::Click if tool == AnnotationTool::Marker => {
+                let label = self.annotations.next_marker_label();
+                self.annotations.add(Annotation::Marker {
+                    cell: (col as u32, row as u32),
+                    label,
+                });
+            }
+            EventType::Click => {
+                if let Some((start, _)) = self.annotation_drag.take() {
+                    let (from, to) = ((start.0 as u32, start.1 as u32), (col as u32, row as u32));
+                    if tool == AnnotationTool::Arrow {
+                        if from != to {
+                            self.annotations.add(Annotation::Arrow { from, to });
+                        }
+                    } else {
+                        self.annotations.add(Annotation::Measure { from, to });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
+
+    pub fn annotation_tool(&self) -> AnnotationTool {
+        self.annotation_tool
+    }
+
+    /// The arrow or measurement being dragged out, if any.
+    pub fn annotation_in_progress(&self) -> Option<Annotation> {
+        let ((from_col, from_row), (to_col, to_row)) = self.annotation_drag?;
+        let (from, to) = ((from_col as u32, from_row as u32), (to_col as u32, to_row as u32));
+        match self.annotation_tool {
+            AnnotationTool::Arrow => Some(Annotation::Arrow { from, to }),
+            AnnotationTool::Measure => Some(Annotation::Measure { from, to }),
+            _ => None,
+        }
+    }
+
+    /// Shows an annotation another player shared with the room.
+    pub fn add_shared_annotation(&mut self, author: String, id: u32, annotation: Annotation) {
+        self.annotations.add_shared(author, id, annotation);
+    }
+
+    /// Gives the player's newest marker a new label. Returns false if they haven't placed one.
+    pub fn relabel_last_marker(&mut self, label: &str) -> bool {
+        self.annotations.relabel_last_marker(label)
+    }
+
+    /// Whether the player asked to show their annotations to the room since the last call.
+    pub fn take_share_request(&mut self) -> bool {
+        std::mem::replace(&mut self.share_requested, false)
+    }
+
+    /// The camera bookmark the player asked to save or jump to since the last call, if any.
+    pub fn take_bookmark_request(&mut self) -> Option<BookmarkRequest> {
+        self.bookmark_request.take()
+    }
+
+    /// Whether the player asked the camera to follow the next player's births since the last call.
+    pub fn take_follow_request(&mut self) -> bool {
+        std::mem::replace(&mut self.follow_requested, false)
+    }
+
+    /// The player's annotations that the room hasn't been shown, which from now on count as shared.
+    pub fn take_unshared_annotations(&mut self) -> Vec<(u32, Annotation)> {
+        self.annotations.take_unshared()
+    }
+
+    /// How the cells drawn are mirrored. `M` picks the symmetry and Shift-M moves its center.
+    pub fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    /// The cells selected by Shift-dragging, if any. Delete or Backspace erases the player's cells
+    /// in it, and Escape or clicking elsewhere deselects it.
+    pub fn selection(&self) -> Option<Region> {
+        self.selection
+    }
+
+    /// Selects the cells between where the Shift-drag started and the given cell, inclusive.
+    fn select_to(&mut self, col: usize, row: usize) {
+        if let Some((anchor_col, anchor_row)) = self.selection_anchor {
+            let (left, right) = (anchor_col.min(col), anchor_col.max(col));
+            let (top, bottom) = (anchor_row.min(row), anchor_row.max(row));
+            self.selection = Some(Region::new(
+                left as isize,
+                top as isize,
+                right - left + 1,
+                bottom - top + 1,
+            ));
+        }
+    }
+
+    fn is_own_cell(&mut self, col: usize, row: usize) -> bool {
+        self.uni.get_cell_state(col, row, Some(CURRENT_PLAYER_ID)) == CellState::Alive(Some(CURRENT_PLAYER_ID))
+    }
+
+    /// Kills the player's cells in the selection. Returns false if nothing is selected.
+    fn erase_selection(&mut self) -> bool {
+        let selection = match self.selection.and_then(|region| region.intersection(self.uni.region())) {
+            Some(selection) => selection,
+            None => return false,
+        };
+        for row in selection.top()..=selection.bottom() {
+            for col in selection.left()..=selection.right() {
+                let (col, row) = (col as usize, row as usize);
+                if !self.is_own_cell(col, row) {
+                    continue;
+                }
+                if self.networked {
+                    self.net_toggles.push((col, row));
+                } else {
+                    self.uni.set(col, row, CellState::Dead, CURRENT_PLAYER_ID);
+                }
+            }
+        }
+        true
+    }
+
+    /// Makes `pattern` the one placed by clicking, as if picked with a number key.
+    pub fn set_stamp(&mut self, pattern: &PatternFile) -> ConwayResult<()> {
+        let grid = pattern.to_new_bit_grid()?;
+        self.game_state.insert_mode = Some((grid, pattern.width(), pattern.height()));
+        Ok(())
+    }
+
     pub fn insert_mode(&self) -> Option<(BitGrid, usize, usize)> {
         if let Some((bitgrid, row, col)) = &self.game_state.insert_mode {
             Some((bitgrid.clone(), *row, *col))
@@ -0,0 +1,210 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Makes `DoubleClick` and `LongPress` events out of the raw mouse events a `Layering` routes, using
+//! the thresholds in the `[input]` section of the config. Time is measured in frames, as counted by
+//! `Update` events, so gestures don't depend on how long it takes to handle one event.
+
+use std::time::Duration;
+
+use ggez::event::MouseButton;
+use ggez::mint::Point2;
+
+use super::context::{Event, EventType, Modifiers};
+use crate::config::InputSettings;
+
+/// The button being held down.
+#[derive(Debug)]
+struct Press {
+    button:        MouseButton,
+    point:         Point2<f32>,
+    modifiers:     Modifiers, // as the button was pressed
+    held:          Duration,
+    moved:         bool, // beyond the click slop, so it's a drag rather than a long press
+    long_pressed:  bool,
+    swallow_click: bool, // the long press was handled, so releasing the button doesn't click
+}
+
+#[derive(Debug)]
+struct LastClick {
+    button: MouseButton,
+    point:  Point2<f32>,
+    since:  Duration,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Gestures {
+    press:      Option<Press>,
+    last_click: Option<LastClick>,
+}
+
+fn distance(a: Point2<f32>, b: Point2<f32>) -> f32 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+impl Gestures {
+    pub fn new() -> Self {
+        Gestures::default()
+    }
+
+    /// Takes note of a raw mouse event, returning the events to route in its place: none if it is
+    /// the click that ends a handled long press, the click and then a `DoubleClick` if it is the
+    /// second click of one, or else just the event.
+    pub fn route(&mut self, event: &Event, input: &InputSettings) -> Vec<Event> {
+        let (point, button) = match (event.point, event.button) {
+            (Some(point), Some(button)) => (point, button),
+            _ => return vec![event.clone()],
+        };
+        match event.what {
+            EventType::MouseButtonHeld => {
+                if self.press.as_ref().map_or(true, |press| press.button != button) {
+                    self.press = Some(Press {
+                        button,
+                        point,
+                        modifiers: event.modifiers,
+                        held: Duration::from_secs(0),
+                        moved: false,
+                        long_pressed: false,
+                        swallow_click: false,
+                    });
+                }
+            }
+            EventType::Drag => {
+                if let Some(ref mut press) = self.press {
+                    if distance(press.point, point) > input.click_slop {
+                        press.moved = true;
+                    }
+                }
+            }
+            EventType::Click => {
+                if let Some(press) = self.press.take() {
+                    if press.swallow_click {
+                        self.last_click = None;
+                        return vec![];
+                    }
+                }
+                let is_double = self.last_click.as_ref().map_or(false, |last| {
+                    last.button == button
+                        && last.since <= Duration::from_millis(input.double_click_ms)
+                        && distance(last.point, point) <= input.click_slop
+                });
+                if is_double {
+                    self.last_click = None; // a third click starts over
+                    return vec![event.clone(), Event::new_double_click(point, button, event.modifiers)];
+                }
+                self.last_click = Some(LastClick {
+                    button,
+                    point,
+                    since: Duration::from_secs(0),
+                });
+            }
+            _ => {}
+        }
+        vec![event.clone()]
+    }
+
+    /// Counts the button and the last click as held and past for another `elapsed`, returning a
+    /// `LongPress` event if the button has now been held long enough in one place.
+    pub fn advance(&mut self, elapsed: Duration, input: &InputSettings) -> Option<Event> {
+        if let Some(ref mut last_click) = self.last_click {
+            last_click.since += elapsed;
+        }
+        let press = self.press.as_mut()?;
+        press.held += elapsed;
+        if press.moved || press.long_pressed || press.held < Duration::from_millis(input.long_press_ms) {
+            return None;
+        }
+        press.long_pressed = true;
+        Some(Event::new_long_press(press.point, press.button, press.modifiers))
+    }
+
+    /// Called when a widget handled the `LongPress` event returned by `advance`, which took the place
+    /// of the click that releasing the button would have made.
+    pub fn long_press_handled(&mut self) {
+        if let Some(ref mut press) = self.press {
+            press.swallow_click = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point2<f32> {
+        Point2 { x, y }
+    }
+
+    fn kinds(events: &[Event]) -> Vec<EventType> {
+        events.iter().map(|event| event.what).collect()
+    }
+
+    #[test]
+    fn test_double_click_needs_two_quick_clicks_in_one_place() {
+        let input = InputSettings::default();
+        let mut gestures = Gestures::new();
+        let click = |x, y| Event::new_click(point(x, y), MouseButton::Left, Modifiers::NONE);
+
+        assert_eq!(kinds(&gestures.route(&click(10.0, 10.0), &input)), [EventType::Click]);
+        gestures.advance(Duration::from_millis(100), &input);
+        assert_eq!(
+            kinds(&gestures.route(&click(12.0, 11.0), &input)),
+            [EventType::Click, EventType::DoubleClick]
+        );
+        assert_eq!(kinds(&gestures.route(&click(12.0, 11.0), &input)), [EventType::Click]);
+
+        assert_eq!(kinds(&gestures.route(&click(50.0, 50.0), &input)), [EventType::Click]);
+        gestures.advance(Duration::from_millis(input.double_click_ms + 1), &input);
+        assert_eq!(kinds(&gestures.route(&click(50.0, 50.0), &input)), [EventType::Click]);
+        assert_eq!(kinds(&gestures.route(&click(80.0, 50.0), &input)), [EventType::Click]);
+    }
+
+    #[test]
+    fn test_long_press_needs_a_still_button() {
+        let input = InputSettings::default();
+        let long_press = Duration::from_millis(input.long_press_ms);
+        let mut gestures = Gestures::new();
+
+        gestures.route(
+            &Event::new_mouse_held(point(10.0, 10.0), MouseButton::Left, Modifiers::NONE),
+            &input,
+        );
+        gestures.route(
+            &Event::new_drag(point(13.0, 10.0), MouseButton::Left, Modifiers::NONE),
+            &input,
+        );
+        assert!(gestures.advance(long_press / 2, &input).is_none());
+        let event = gestures.advance(long_press / 2, &input).unwrap();
+        assert_eq!(event.what, EventType::LongPress);
+        assert_eq!(event.point, Some(point(10.0, 10.0)));
+        assert!(gestures.advance(long_press, &input).is_none()); // only once per press
+        gestures.long_press_handled();
+        let click = Event::new_click(point(13.0, 10.0), MouseButton::Left, Modifiers::NONE);
+        assert!(gestures.route(&click, &input).is_empty());
+
+        gestures.route(
+            &Event::new_mouse_held(point(10.0, 10.0), MouseButton::Left, Modifiers::NONE),
+            &input,
+        );
+        gestures.route(
+            &Event::new_drag(point(30.0, 10.0), MouseButton::Left, Modifiers::NONE),
+            &input,
+        );
+        assert!(gestures.advance(long_press, &input).is_none());
+    }
+}
@@ -0,0 +1,800 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Runs a `Layering` without a window, for testing UI logic such as focus, handlers, and layout.
+//! Synthetic events go through the same code as the client's, except that there is no ggez
+//! context; handlers get a headless `UIContext` instead (see `UIContext::ggez_context`). Drawing
+//! is mocked by listing what would be drawn where.
+//!
+//! ```rust
+//! let mut harness = Harness::new(Screen::Menu);
+//! let tf_id = harness.add_widget(Box::new(TextField::new(dummy_font_info(), rect)), InsertLocation::AtCurrentLayer);
+//! harness.click(10.0, 10.0).unwrap();
+//! harness.type_text("hello").unwrap();
+//! assert_eq!(TextField::widget_from_id(&harness.layering, &tf_id).unwrap().text(), Some("hello".to_owned()));
+//! ```
+
+use std::error::Error;
+
+use ggez::event::MouseButton;
+use ggez::graphics::{PxScale, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::mint::{Point2, Vector2};
+use id_tree::{InsertBehavior, Node, NodeId, Tree};
+
+use super::{
+    common::FontInfo,
+    context::{Event, Handled, Modifiers, UIContext},
+    treeview::TreeView,
+    widget::Widget,
+    BoxedWidget, GameAreaState, InsertLocation, Layering,
+};
+use crate::config::Config;
+use crate::constants::{DEFAULT_ZOOM_LEVEL, UNIVERSE_HEIGHT_IN_CELLS, UNIVERSE_WIDTH_IN_CELLS};
+use crate::uilayout::StaticNodeIds;
+use crate::viewport::GridView;
+use crate::Screen;
+
+/// A font for widgets under test. Each character is 5 pixels square.
+pub fn dummy_font_info() -> FontInfo {
+    FontInfo {
+        font:            (), // dummy font because we can't create a real Font without ggez
+        scale:           PxScale::from(1.0),
+        char_dimensions: Vector2 { x: 5.0, y: 5.0 },
+    }
+}
+
+/// Node IDs from a throwaway tree, for handlers that look up the client's widgets. Looking one of
+/// them up in a `Layering` is an error rather than finding some other widget.
+fn placeholder_static_node_ids() -> StaticNodeIds {
+    let mut tree = Tree::new();
+    let id = tree.insert(Node::new(()), InsertBehavior::AsRoot).unwrap();
+    StaticNodeIds {
+        chatbox_id:           id.clone(),
+        chatbox_pane_id:      id.clone(),
+        chatbox_tf_id:        id.clone(),
+        game_area_id:         id.clone(),
+        team_button_ids:      vec![],
+        start_game_button_id: id.clone(),
+        add_bot_button_id:    id.clone(),
+        color_picker_id:      id,
+    }
+}
+
+/// A `Layering` along with everything else the client passes to it when emitting an event.
+pub struct Harness {
+    pub layering:        Layering,
+    pub config:          Config,
+    pub screen_stack:    Vec<Screen>,
+    pub game_area_state: GameAreaState,
+    pub static_node_ids: StaticNodeIds,
+    pub viewport:        GridView,
+    pub modifiers:       Modifiers,   // held down through every key and mouse event sent
+    mouse:               Point2<f32>, // where the mouse was last; key presses carry it, as in the client
+}
+
+impl Harness {
+    /// Creates a harness with an empty layering, showing `screen` on top of the main menu.
+    pub fn new(screen: Screen) -> Self {
+        let mut screen_stack = vec![Screen::Menu];
+        if screen != Screen::Menu {
+            screen_stack.push(screen);
+        }
+        Harness {
+            layering: Layering::new(),
+            config: Config::new(),
+            screen_stack,
+            game_area_state: GameAreaState::default(),
+            static_node_ids: placeholder_static_node_ids(),
+            viewport: GridView::new(DEFAULT_ZOOM_LEVEL, UNIVERSE_WIDTH_IN_CELLS, UNIVERSE_HEIGHT_IN_CELLS),
+            modifiers: Modifiers::NONE,
+            mouse: Point2 { x: 0.0, y: 0.0 },
+        }
+    }
+
+    /// Adds a widget to the layering. Panics if it can't be added, since the test can't go on.
+    pub fn add_widget(&mut self, widget: BoxedWidget, location: InsertLocation) -> NodeId {
+        self.layering.add_widget(widget, location).unwrap()
+    }
+
+    /// Emits an event on the layering, as the client does.
+    pub fn emit(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        self.layering.emit_with_context(
+            event,
+            None,
+            &mut self.config,
+            &mut self.screen_stack,
+            &mut self.game_area_state,
+            &mut self.static_node_ids,
+            &mut self.viewport,
+        )
+    }
+
+    /// Emits an event on one widget only, bypassing the layering's focus and hit testing. Returns
+    /// whether it was handled, and the events the widget sent to its parent.
+    pub fn emit_on(&mut self, id: &NodeId, event: &Event) -> Result<(Handled, Vec<Event>), Box<dyn Error>> {
+        let (widget_tree, timers, redraw, texts) = self.layering.uictx_parts_mut();
+        let mut uictx = UIContext::new(
+            None,
+            &mut self.config,
+            TreeView::new(widget_tree),
+            &mut self.screen_stack,
+            self.game_area_state.first_gen_was_drawn,
+            &mut self.static_node_ids,
+            &mut self.viewport,
+            timers,
+            redraw,
+            texts,
+        );
+        let (widget, mut subuictx) = uictx.derive(id)?;
+        let emittable = widget
+            .as_emit_event()
+            .ok_or_else(|| format!("{:?} is not an EmitEvent", id))?;
+        let handled = emittable.emit(event, &mut subuictx)?;
+        Ok((handled, subuictx.collect_child_events()))
+    }
+
+    /// Gives the widget keyboard focus, as the client does when a screen is shown.
+    pub fn focus(&mut self, id: &NodeId) -> Result<(), Box<dyn Error>> {
+        self.layering.enter_focus_with_context(
+            None,
+            &mut self.config,
+            &mut self.screen_stack,
+            &mut self.game_area_state,
+            &mut self.static_node_ids,
+            &mut self.viewport,
+            id,
+        )?;
+        Ok(())
+    }
+
+    /// Moves the mouse to the point and clicks the left button there.
+    pub fn click(&mut self, x: f32, y: f32) -> Result<(), Box<dyn Error>> {
+        self.move_mouse(x, y)?;
+        self.emit(&Event::new_click(self.mouse, MouseButton::Left, self.modifiers))
+    }
+
+    /// Moves the mouse to the point and clicks the right button there.
+    pub fn right_click(&mut self, x: f32, y: f32) -> Result<(), Box<dyn Error>> {
+        self.move_mouse(x, y)?;
+        self.emit(&Event::new_click(self.mouse, MouseButton::Right, self.modifiers))
+    }
+
+    /// Presses the left button at the first point, drags it through the rest, and releases it at the
+    /// last one, sending the same events as the client.
+    pub fn drag(&mut self, path: &[(f32, f32)]) -> Result<(), Box<dyn Error>> {
+        let (&(x, y), rest) = path.split_first().ok_or("a drag needs at least one point")?;
+        self.move_mouse(x, y)?;
+        self.emit(&Event::new_mouse_held(self.mouse, MouseButton::Left, self.modifiers))?;
+        for &(x, y) in rest {
+            self.move_mouse(x, y)?;
+            self.emit(&Event::new_drag(self.mouse, MouseButton::Left, self.modifiers))?;
+        }
+        self.emit(&Event::new_click(self.mouse, MouseButton::Left, self.modifiers))
+    }
+
+    /// Moves the mouse to the point and turns the wheel there, by `lines` away from the player.
+    pub fn scroll(&mut self, x: f32, y: f32, lines: f32) -> Result<(), Box<dyn Error>> {
+        self.move_mouse(x, y)?;
+        self.emit(&Event::new_scroll(
+            self.mouse,
+            Vector2 { x: 0.0, y: lines },
+            self.modifiers,
+        ))
+    }
+
+    fn move_mouse(&mut self, x: f32, y: f32) -> Result<(), Box<dyn Error>> {
+        let point = Point2 { x, y };
+        if point != self.mouse {
+            let prev_point = self.mouse;
+            self.mouse = point;
+            self.emit(&Event::new_mouse_move(
+                prev_point,
+                point,
+                MouseButton::Left,
+                self.modifiers,
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn press_key(&mut self, key: KeyCode) -> Result<(), Box<dyn Error>> {
+        self.emit(&Event::new_key_press(self.mouse, key, self.modifiers, false))
+    }
+
+    pub fn press_shift_key(&mut self, key: KeyCode) -> Result<(), Box<dyn Error>> {
+        self.emit(&Event::new_key_press(
+            self.mouse,
+            key,
+            self.modifiers | Modifiers::SHIFT,
+            false,
+        ))
+    }
+
+    /// Types each character of `text`.
+    pub fn type_text(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        for character in text.chars() {
+            self.emit(&Event::new_char_press(self.mouse, character, self.modifiers))?;
+        }
+        Ok(())
+    }
+
+    /// Runs one frame's worth of updates.
+    pub fn update(&mut self) -> Result<(), Box<dyn Error>> {
+        self.emit(&Event::new_update())
+    }
+
+    /// Stands in for `Layering::draw`: lists the widgets it would draw, in order, with where each
+    /// would be drawn.
+    pub fn draw(&self) -> Vec<(NodeId, Rect)> {
+        let (previous_layer, top_layer) = self.layering.widgets_to_draw();
+        previous_layer
+            .into_iter()
+            .chain(top_layer)
+            .map(|id| {
+                let rect = self.layering.get_widget(&id).unwrap().rect(); // unwrap OK b/c ID came from the layering
+                (id, rect)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use conway::universe::{CellState, Region};
+
+    use super::super::{
+        context::{EventType, Phase, DEFAULT_PRIORITY},
+        EmitEvent, GameArea, Pane, TextField,
+    };
+    use super::*;
+
+    const FIELD_W: f32 = 100.0;
+    const FIELD_H: f32 = 20.0;
+
+    fn add_text_field(harness: &mut Harness, x: f32, y: f32, location: InsertLocation) -> NodeId {
+        let tf = TextField::new(dummy_font_info(), Rect::new(x, y, FIELD_W, FIELD_H));
+        harness.add_widget(Box::new(tf), location)
+    }
+
+    fn text_of(harness: &Harness, id: &NodeId) -> Option<String> {
+        TextField::widget_from_id(&harness.layering, id).unwrap().text()
+    }
+
+    #[test]
+    fn test_click_focuses_text_field_and_keys_go_to_it() {
+        let mut harness = Harness::new(Screen::Options);
+        let first_id = add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+        let second_id = add_text_field(&mut harness, 0.0, 50.0, InsertLocation::AtCurrentLayer);
+        assert_eq!(harness.layering.focused_widget_id(), None);
+
+        harness.click(10.0, 60.0).unwrap();
+        assert_eq!(harness.layering.focused_widget_id(), Some(&second_id));
+        harness.type_text("abc").unwrap();
+        harness.update().unwrap(); // moves the input method's window, which is skipped headless
+        harness.press_key(KeyCode::Back).unwrap();
+
+        assert_eq!(text_of(&harness, &first_id), None);
+        assert_eq!(text_of(&harness, &second_id), Some("ab".to_owned()));
+    }
+
+    #[test]
+    fn test_tab_cycles_focus() {
+        let mut harness = Harness::new(Screen::Options);
+        let first_id = add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+        let second_id = add_text_field(&mut harness, 0.0, 50.0, InsertLocation::AtCurrentLayer);
+        harness.focus(&first_id).unwrap();
+
+        harness.press_key(KeyCode::Tab).unwrap();
+        assert_eq!(harness.layering.focused_widget_id(), Some(&second_id));
+        harness.type_text("x").unwrap();
+        harness.press_key(KeyCode::Tab).unwrap();
+        assert_eq!(harness.layering.focused_widget_id(), Some(&first_id));
+        harness.press_shift_key(KeyCode::Tab).unwrap();
+        assert_eq!(harness.layering.focused_widget_id(), Some(&second_id));
+
+        assert_eq!(text_of(&harness, &first_id), None);
+        assert_eq!(text_of(&harness, &second_id), Some("x".to_owned()));
+    }
+
+    #[test]
+    fn test_return_emits_text_entered_and_releases_focus() {
+        let mut harness = Harness::new(Screen::Run);
+        let tf_id = add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+        let entered = Arc::new(Mutex::new(vec![]));
+        let entered_clone = entered.clone();
+        TextField::widget_from_id_mut(&mut harness.layering, &tf_id)
+            .unwrap()
+            .on(
+                EventType::TextEntered,
+                Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                    entered_clone.lock().unwrap().push(evt.text.clone().unwrap());
+                    Ok(Handled::Handled)
+                }),
+            )
+            .unwrap();
+
+        let (_, child_events) = harness
+            .emit_on(&tf_id, &Event::new_gain_or_lose_focus(EventType::GainFocus))
+            .unwrap();
+        assert!(child_events.is_empty());
+        for character in "hi there ".chars() {
+            harness
+                .emit_on(
+                    &tf_id,
+                    &Event::new_char_press(harness.mouse, character, Modifiers::NONE),
+                )
+                .unwrap();
+        }
+        let (handled, child_events) = harness
+            .emit_on(
+                &tf_id,
+                &Event::new_key_press(harness.mouse, KeyCode::Return, Modifiers::NONE, false),
+            )
+            .unwrap();
+
+        assert_eq!(handled, Handled::Handled);
+        assert_eq!(child_events.len(), 1);
+        assert_eq!(child_events[0].what, EventType::ChildReleasedFocus);
+        assert_eq!(*entered.lock().unwrap(), vec!["hi there".to_owned()]);
+        assert_eq!(text_of(&harness, &tf_id), None);
+    }
+
+    #[test]
+    fn test_tabbing_out_of_a_pane_moves_on() {
+        let mut harness = Harness::new(Screen::Options);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let inner_id = add_text_field(&mut harness, 10.0, 10.0, InsertLocation::ToNestedContainer(&pane_id));
+        let outer_id = add_text_field(&mut harness, 0.0, 300.0, InsertLocation::AtCurrentLayer);
+
+        // widgets in a pane are placed relative to it
+        assert_eq!(
+            harness.layering.get_widget(&inner_id).unwrap().rect(),
+            Rect::new(10.0, 10.0, FIELD_W, FIELD_H)
+        );
+
+        harness.focus(&pane_id).unwrap();
+        harness.type_text("in").unwrap();
+        harness.press_key(KeyCode::Tab).unwrap();
+        assert_eq!(harness.layering.focused_widget_id(), Some(&outer_id));
+        harness.type_text("out").unwrap();
+
+        assert_eq!(text_of(&harness, &inner_id), Some("in".to_owned()));
+        assert_eq!(text_of(&harness, &outer_id), Some("out".to_owned()));
+    }
+
+    #[test]
+    fn test_pane_handlers_capture_and_bubble() {
+        let mut harness = Harness::new(Screen::Options);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let tf_id = add_text_field(&mut harness, 10.0, 10.0, InsertLocation::ToNestedContainer(&pane_id));
+
+        let log = Arc::new(Mutex::new(vec![]));
+        let blocked = Arc::new(Mutex::new(true));
+        let pane = Pane::widget_from_id_mut(&mut harness.layering, &pane_id).unwrap();
+        let (log_clone, blocked_clone) = (log.clone(), blocked.clone());
+        pane.on_with_priority(
+            EventType::Click,
+            Phase::Capture,
+            DEFAULT_PRIORITY,
+            Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| {
+                log_clone.lock().unwrap().push("capture");
+                if *blocked_clone.lock().unwrap() {
+                    Ok(Handled::Handled)
+                } else {
+                    Ok(Handled::NotHandled)
+                }
+            }),
+        )
+        .unwrap();
+        let log_clone = log.clone();
+        pane.on_with_priority(
+            EventType::Click,
+            Phase::Bubble,
+            10,
+            Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| {
+                log_clone.lock().unwrap().push("bubble, later");
+                Ok(Handled::NotHandled)
+            }),
+        )
+        .unwrap();
+        let log_clone = log.clone();
+        pane.on(
+            EventType::Click,
+            Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, _evt: &Event| {
+                log_clone.lock().unwrap().push("bubble");
+                Ok(Handled::NotHandled)
+            }),
+        )
+        .unwrap();
+        let take_log = || std::mem::replace(&mut *log.lock().unwrap(), vec![]);
+        let tf_focused = |harness: &Harness| {
+            TextField::widget_from_id(&harness.layering, &tf_id)
+                .unwrap()
+                .is_focused()
+        };
+
+        // the capture handler keeps the click from the text field
+        harness.click(20.0, 15.0).unwrap();
+        assert_eq!(take_log(), vec!["capture"]);
+        assert!(!tf_focused(&harness));
+
+        // the text field handles the click, so it doesn't bubble up
+        *blocked.lock().unwrap() = false;
+        harness.click(20.0, 16.0).unwrap();
+        assert_eq!(take_log(), vec!["capture"]);
+        assert!(tf_focused(&harness));
+
+        // nothing in the pane handles a click here
+        harness.click(150.0, 150.0).unwrap();
+        assert_eq!(take_log(), vec!["capture", "bubble", "bubble, later"]);
+    }
+
+    #[test]
+    fn test_timers_fire_during_updates_until_the_widget_is_removed() {
+        let mut harness = Harness::new(Screen::Run);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let started = Arc::new(Mutex::new(vec![]));
+        let started_clone = started.clone();
+        let fired = Arc::new(Mutex::new(vec![]));
+        let fired_clone = fired.clone();
+        let frame = Duration::from_secs(1) / 60; // as long as each headless frame takes
+        let pane = Pane::widget_from_id_mut(&mut harness.layering, &pane_id).unwrap();
+        pane.on(
+            EventType::custom("start_timers"),
+            Box::new(move |_obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event| {
+                let mut started = started_clone.lock().unwrap();
+                started.push(uictx.start_timer(frame * 2, false)?);
+                started.push(uictx.start_timer(frame * 3, true)?);
+                Ok(Handled::Handled)
+            }),
+        )
+        .unwrap();
+        pane.on(
+            EventType::Timer,
+            Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                fired_clone.lock().unwrap().push(evt.timer_id.unwrap());
+                Ok(Handled::Handled)
+            }),
+        )
+        .unwrap();
+
+        harness.emit(&Event::new_custom("start_timers", ())).unwrap();
+        let (once, every) = {
+            let started = started.lock().unwrap();
+            (started[0], started[1])
+        };
+        for _ in 0..6 {
+            harness.update().unwrap();
+        }
+        assert_eq!(*fired.lock().unwrap(), vec![once, every, every]);
+
+        harness.layering.remove_widget(pane_id).unwrap();
+        harness.update().unwrap();
+        harness.update().unwrap();
+        assert_eq!(fired.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_double_clicks_and_long_presses_reach_nested_widgets() {
+        let mut harness = Harness::new(Screen::Run);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let tf_id = add_text_field(&mut harness, 10.0, 10.0, InsertLocation::ToNestedContainer(&pane_id));
+        let log = Arc::new(Mutex::new(vec![]));
+        let tf = TextField::widget_from_id_mut(&mut harness.layering, &tf_id).unwrap();
+        for &what in &[EventType::Click, EventType::DoubleClick, EventType::LongPress] {
+            let log_clone = log.clone();
+            tf.on_with_priority(
+                what,
+                Phase::Capture,
+                DEFAULT_PRIORITY,
+                Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                    log_clone.lock().unwrap().push(evt.what);
+                    Ok(if evt.what == EventType::LongPress {
+                        Handled::Handled
+                    } else {
+                        Handled::NotHandled
+                    })
+                }),
+            )
+            .unwrap();
+        }
+
+        harness.click(15.0, 15.0).unwrap();
+        harness.update().unwrap();
+        harness.click(15.0, 15.0).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![EventType::Click, EventType::Click, EventType::DoubleClick]
+        );
+        log.lock().unwrap().clear();
+
+        // held a little longer than a long press; releasing the button then doesn't click
+        let long_press_frames = Config::new().get().input.long_press_ms * 60 / 1000;
+        harness
+            .emit(&Event::new_mouse_held(
+                harness.mouse,
+                MouseButton::Left,
+                Modifiers::NONE,
+            ))
+            .unwrap();
+        for _ in 0..long_press_frames + 5 {
+            harness.update().unwrap();
+        }
+        harness
+            .emit(&Event::new_click(harness.mouse, MouseButton::Left, Modifiers::NONE))
+            .unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![EventType::LongPress]);
+        harness.click(15.0, 15.0).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![EventType::LongPress, EventType::Click]);
+    }
+
+    #[test]
+    fn test_scroll_goes_to_the_innermost_widget_then_out() {
+        let mut harness = Harness::new(Screen::Run);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let tf_id = add_text_field(&mut harness, 10.0, 10.0, InsertLocation::ToNestedContainer(&pane_id));
+        let log = Arc::new(Mutex::new(vec![]));
+        let log_clone = log.clone();
+        Pane::widget_from_id_mut(&mut harness.layering, &pane_id)
+            .unwrap()
+            .on(
+                EventType::Scroll,
+                Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                    log_clone.lock().unwrap().push(("pane", evt.scroll_delta.unwrap().y));
+                    Ok(Handled::Handled)
+                }),
+            )
+            .unwrap();
+
+        // the text field doesn't scroll, so the pane does
+        harness.scroll(15.0, 15.0, 1.0).unwrap();
+        harness.scroll(150.0, 150.0, -2.0).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![("pane", 1.0), ("pane", -2.0)]);
+        log.lock().unwrap().clear();
+
+        let log_clone = log.clone();
+        TextField::widget_from_id_mut(&mut harness.layering, &tf_id)
+            .unwrap()
+            .on(
+                EventType::Scroll,
+                Box::new(move |_obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                    log_clone
+                        .lock()
+                        .unwrap()
+                        .push(("text field", evt.scroll_delta.unwrap().y));
+                    Ok(Handled::Handled)
+                }),
+            )
+            .unwrap();
+        harness.scroll(15.0, 15.0, 1.0).unwrap();
+        harness.scroll(150.0, 150.0, 1.0).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![("text field", 1.0), ("pane", 1.0)]);
+    }
+
+    #[test]
+    fn test_custom_events_reach_nested_widgets() {
+        let mut harness = Harness::new(Screen::Run);
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 200.0, 200.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let tf_id = add_text_field(&mut harness, 10.0, 10.0, InsertLocation::ToNestedContainer(&pane_id));
+        TextField::widget_from_id_mut(&mut harness.layering, &tf_id)
+            .unwrap()
+            .on(
+                EventType::custom("score_updated"),
+                Box::new(|obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| {
+                    let tf = obj.downcast_mut::<TextField>().unwrap();
+                    tf.set_text(format!("score {}", evt.payload::<u32>().unwrap()));
+                    Ok(Handled::Handled)
+                }),
+            )
+            .unwrap();
+
+        harness.emit(&Event::new_custom("pattern_selected", ())).unwrap();
+        assert_eq!(text_of(&harness, &tf_id), None);
+        harness.emit(&Event::new_custom("score_updated", 12u32)).unwrap();
+        assert_eq!(text_of(&harness, &tf_id), Some("score 12".to_owned()));
+    }
+
+    #[test]
+    fn test_shift_and_ctrl_select_text() {
+        let mut harness = Harness::new(Screen::Run);
+        let tf_id = add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+        let selected_text = |harness: &Harness| {
+            TextField::widget_from_id(&harness.layering, &tf_id)
+                .unwrap()
+                .selected_text()
+        };
+
+        harness.click(1.0, 1.0).unwrap();
+        harness.type_text("hello world").unwrap();
+        for _ in 0..5 {
+            harness.press_shift_key(KeyCode::Left).unwrap();
+        }
+        assert_eq!(selected_text(&harness), Some("world".to_owned()));
+        harness.type_text("there").unwrap();
+        assert_eq!(text_of(&harness, &tf_id), Some("hello there".to_owned()));
+        assert_eq!(selected_text(&harness), None);
+
+        harness.modifiers = Modifiers::CTRL;
+        harness.press_key(KeyCode::A).unwrap();
+        harness.modifiers = Modifiers::NONE;
+        assert_eq!(selected_text(&harness), Some("hello there".to_owned()));
+        harness.press_key(KeyCode::Back).unwrap();
+        assert_eq!(text_of(&harness, &tf_id), None);
+    }
+
+    #[test]
+    fn test_escape_without_focus_goes_back_a_screen() {
+        let mut harness = Harness::new(Screen::Options);
+        add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+
+        harness.press_key(KeyCode::Escape).unwrap();
+        assert_eq!(harness.screen_stack, vec![Screen::Menu]);
+    }
+
+    #[test]
+    fn test_drag_draws_cells() {
+        let mut harness = Harness::new(Screen::Run);
+        let mut game_area = GameArea::new();
+        game_area.set_rect(harness.viewport.get_rect()).unwrap();
+        let game_area_id = harness.add_widget(Box::new(game_area), InsertLocation::AtCurrentLayer);
+
+        // cells are DEFAULT_ZOOM_LEVEL pixels wide; this goes along row 3 from column 2 to 6
+        let y = 3.5 * DEFAULT_ZOOM_LEVEL;
+        let path: Vec<(f32, f32)> = (2..=6)
+            .map(|col| ((col as f32 + 0.5) * DEFAULT_ZOOM_LEVEL, y))
+            .collect();
+        harness.drag(&path).unwrap();
+
+        let game_area = GameArea::widget_from_id_mut(&mut harness.layering, &game_area_id).unwrap();
+        for col in 2..=6 {
+            assert_ne!(
+                game_area.uni.get_cell_state(col, 3, None),
+                CellState::Dead,
+                "col {}",
+                col
+            );
+        }
+        assert_eq!(game_area.uni.get_cell_state(7, 3, None), CellState::Dead);
+        assert_eq!(game_area.uni.get_cell_state(2, 4, None), CellState::Dead);
+    }
+
+    #[test]
+    fn test_ctrl_erases_and_shift_selects_cells() {
+        let mut harness = Harness::new(Screen::Run);
+        let mut game_area = GameArea::new();
+        game_area.set_rect(harness.viewport.get_rect()).unwrap();
+        let game_area_id = harness.add_widget(Box::new(game_area), InsertLocation::AtCurrentLayer);
+        harness.focus(&game_area_id).unwrap();
+        let cell = |col: usize, row: usize| {
+            (
+                (col as f32 + 0.5) * DEFAULT_ZOOM_LEVEL,
+                (row as f32 + 0.5) * DEFAULT_ZOOM_LEVEL,
+            )
+        };
+        let alive = |harness: &mut Harness, col: usize, row: usize| {
+            let game_area = GameArea::widget_from_id_mut(&mut harness.layering, &game_area_id).unwrap();
+            game_area.uni.get_cell_state(col, row, None) != CellState::Dead
+        };
+
+        // draw two rows of five cells, then Ctrl-drag over the first two of them
+        harness
+            .drag(&(2..=6).map(|col| cell(col, 3)).collect::<Vec<_>>())
+            .unwrap();
+        harness
+            .drag(&(2..=6).map(|col| cell(col, 4)).collect::<Vec<_>>())
+            .unwrap();
+        harness.modifiers = Modifiers::CTRL;
+        harness.drag(&[cell(2, 3), cell(3, 3)]).unwrap();
+        assert!(!alive(&mut harness, 2, 3));
+        assert!(!alive(&mut harness, 3, 3));
+        assert!(alive(&mut harness, 4, 3));
+
+        harness.modifiers = Modifiers::SHIFT;
+        harness.drag(&[cell(5, 3), cell(6, 4)]).unwrap();
+        harness.modifiers = Modifiers::NONE;
+        let selection = GameArea::widget_from_id(&harness.layering, &game_area_id)
+            .unwrap()
+            .selection();
+        assert_eq!(selection, Some(Region::new(5, 3, 2, 2)));
+        assert!(alive(&mut harness, 5, 3)); // selecting doesn't draw
+
+        harness.press_key(KeyCode::Delete).unwrap();
+        for &(col, row) in &[(5, 3), (6, 3), (5, 4), (6, 4)] {
+            assert!(!alive(&mut harness, col, row), "({}, {})", col, row);
+        }
+        assert!(alive(&mut harness, 4, 3));
+        assert!(alive(&mut harness, 4, 4));
+
+        // Escape deselects, rather than leaving the game
+        harness.press_key(KeyCode::Escape).unwrap();
+        assert_eq!(harness.screen_stack, vec![Screen::Menu, Screen::Run]);
+        let game_area = GameArea::widget_from_id(&harness.layering, &game_area_id).unwrap();
+        assert_eq!(game_area.selection(), None);
+    }
+
+    #[test]
+    fn test_draw_shows_the_top_two_layers() {
+        let mut harness = Harness::new(Screen::Menu);
+        let rect = |i: usize| Rect::new(10.0 * i as f32, 0.0, 50.0, 50.0);
+        let bottom_id = harness.add_widget(Box::new(Pane::new(rect(0))), InsertLocation::AtCurrentLayer);
+        let middle_id = harness.add_widget(Box::new(Pane::new(rect(1))), InsertLocation::AtNextLayer);
+        let middle_tf_id = add_text_field(&mut harness, 5.0, 5.0, InsertLocation::ToNestedContainer(&middle_id));
+        assert_eq!(harness.draw().len(), 3);
+
+        let top_id = harness.add_widget(Box::new(Pane::new(rect(2))), InsertLocation::AtNextLayer);
+        let drawn = harness.draw();
+        assert_eq!(
+            drawn,
+            vec![
+                (middle_id.clone(), rect(1)),
+                (middle_tf_id, Rect::new(15.0, 5.0, FIELD_W, FIELD_H)),
+                (top_id.clone(), rect(2)),
+            ]
+        );
+
+        harness.layering.remove_widget(top_id).unwrap();
+        let drawn_ids: Vec<NodeId> = harness.draw().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(drawn_ids[0], bottom_id);
+        assert_eq!(drawn_ids[1], middle_id);
+    }
+
+    #[test]
+    fn test_events_during_a_batch_update_wait_for_it_to_end() {
+        let mut harness = Harness::new(Screen::Options);
+        let tf_id = add_text_field(&mut harness, 0.0, 0.0, InsertLocation::AtCurrentLayer);
+        harness.click(5.0, 5.0).unwrap();
+
+        harness.layering.begin_update();
+        let dialog_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 50.0, 50.0))),
+            InsertLocation::AtNextLayer,
+        );
+        harness.type_text("ab").unwrap();
+        harness.layering.remove_widget(dialog_id).unwrap();
+        assert_eq!(harness.layering.highest_z_order, 1); // not checked until the batch ends
+        harness.update().unwrap();
+        assert_eq!(text_of(&harness, &tf_id), None);
+
+        harness.layering.end_update();
+        assert_eq!(harness.layering.highest_z_order, 0);
+        harness.type_text("c").unwrap();
+        assert_eq!(text_of(&harness, &tf_id), Some("abc".to_owned()));
+    }
+}
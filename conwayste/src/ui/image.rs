@@ -0,0 +1,229 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::fmt;
+
+use ggez::graphics::{self, DrawParam, Rect};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+use serde::Deserialize;
+
+use super::{
+    context::{EmitEvent, HandlerData},
+    textcache::TextCache,
+    widget::Widget,
+    UIError, UIResult,
+};
+use crate::resources::Texture;
+
+/// How an image fills the rect of its widget.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaling {
+    Stretch, // to the width and height of the rect
+    Fit,     // as large as fits without changing its shape, centered
+    Tile,    // repeated at its own size from the top left, with the last row and column cut off
+}
+
+pub struct Image {
+    id:             Option<NodeId>,
+    z_index:        usize,
+    pub dimensions: Rect,
+    pub texture:    Texture,
+    pub scaling:    Scaling,
+    handler_data:   HandlerData,
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Image {{ id: {:?}, z-index: {}, Dimensions: {:?}, Scaling: {:?} }}",
+            self.id, self.z_index, self.dimensions, self.scaling
+        )
+    }
+}
+
+/// Where to draw a texture whose region is `size` pixels, for it to fill `rect`. Each placement is
+/// the part of the region to draw (as fractions of the image, like `DrawParam::src`), where to draw
+/// it, and how much to scale it by.
+fn placements(scaling: Scaling, rect: Rect, region: Rect, size: (f32, f32)) -> Vec<(Rect, Point2<f32>, Vector2<f32>)> {
+    let (w, h) = size;
+    if w <= 0.0 || h <= 0.0 {
+        return vec![];
+    }
+    match scaling {
+        Scaling::Stretch => vec![(
+            region,
+            Point2 { x: rect.x, y: rect.y },
+            Vector2 {
+                x: rect.w / w,
+                y: rect.h / h,
+            },
+        )],
+        Scaling::Fit => {
+            let scale = (rect.w / w).min(rect.h / h);
+            let dest = Point2 {
+                x: rect.x + (rect.w - w * scale) / 2.0,
+                y: rect.y + (rect.h - h * scale) / 2.0,
+            };
+            vec![(region, dest, Vector2 { x: scale, y: scale })]
+        }
+        Scaling::Tile => {
+            let mut tiles = vec![];
+            let mut y = rect.y;
+            while y < rect.bottom() {
+                let shown_h = (rect.bottom() - y).min(h) / h;
+                let mut x = rect.x;
+                while x < rect.right() {
+                    let shown_w = (rect.right() - x).min(w) / w;
+                    let src = Rect::new(region.x, region.y, region.w * shown_w, region.h * shown_h);
+                    tiles.push((src, Point2 { x, y }, Vector2 { x: 1.0, y: 1.0 }));
+                    x += w;
+                }
+                y += h;
+            }
+            tiles
+        }
+    }
+}
+
+/// A widget showing a texture, which may be a whole image or a sprite from an atlas; see the
+/// `resources` module for loading them.
+impl Image {
+    /// Creates an Image widget that fits the texture in `dimensions`.
+    pub fn new(texture: Texture, dimensions: Rect) -> Self {
+        Image {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            texture,
+            scaling: Scaling::Fit,
+            handler_data: HandlerData::new(),
+        }
+    }
+}
+
+impl Widget for Image {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Image {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Image {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        let size = self.texture.size();
+        for (src, dest, scale) in placements(self.scaling, self.dimensions, self.texture.region, size) {
+            let param = DrawParam::default().src(src).dest(dest).scale(scale);
+            graphics::draw(ctx, &self.texture.image, param)?;
+        }
+        Ok(())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+}
+
+widget_from_id!(Image);
+impl_emit_event!(Image, self.handler_data);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_placements_for_each_scaling() {
+        let rect = Rect::new(10.0, 10.0, 50.0, 20.0);
+        let (region, dest, scale) = placements(Scaling::Stretch, rect, Rect::one(), (10.0, 10.0))[0];
+        assert_eq!(
+            (region, dest, scale),
+            (Rect::one(), Point2 { x: 10.0, y: 10.0 }, Vector2 { x: 5.0, y: 2.0 })
+        );
+
+        // twice as tall as it is wide, so the height decides
+        let fit = placements(Scaling::Fit, rect, Rect::one(), (10.0, 20.0));
+        assert_eq!(fit.len(), 1);
+        assert_eq!(fit[0].1, Point2 { x: 30.0, y: 10.0 });
+        assert_eq!(fit[0].2, Vector2 { x: 1.0, y: 1.0 });
+
+        // a sprite in the right half of an atlas, 20 x 20 pixels: two and a half across, one down
+        let sprite = Rect::new(0.5, 0.0, 0.5, 1.0);
+        let tiles = placements(Scaling::Tile, rect, sprite, (20.0, 20.0));
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[1].1, Point2 { x: 30.0, y: 10.0 });
+        assert_eq!(tiles[2].0, Rect::new(0.5, 0.0, 0.25, 1.0));
+    }
+}
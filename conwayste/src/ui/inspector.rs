@@ -0,0 +1,239 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! An overlay for debugging layouts, toggled with the `inspect` console command. It outlines every
+//! widget on the screen with its name and ID, and describes the one under the mouse in a panel: its
+//! place in the widget tree and the handlers registered on it.
+
+use ggez::graphics::{self, DrawMode, DrawParam, Font, PxScale, Rect, Text, TextFragment};
+use ggez::mint::Point2;
+use ggez::Context;
+
+use id_tree::NodeId;
+
+use super::{common::within_widget, draw_text, EventType, Layering, UIResult, Widget};
+use crate::constants::colors::{scheme, INSPECTOR_HOVER_COLOR, INSPECTOR_OUTLINE_COLOR};
+
+const TAG_SCALE: f32 = 14.0; // of the name and ID drawn on each widget
+const PANEL_WIDTH: f32 = 460.0; // pixels
+const LINE_HEIGHT: f32 = 22.0; // pixels
+const MARGIN: f32 = 10.0; // pixels
+
+pub struct WidgetInspector {
+    pub visible: bool,
+}
+
+/// The ID as it is shown by the inspector, like `#12`.
+fn short_id(id: &NodeId) -> String {
+    // NodeId keeps its index to itself, but shows it when debug printed
+    let debug = format!("{:?}", id);
+    match debug.rsplit("index: ").next() {
+        Some(rest) => format!("#{}", rest.trim_end_matches(|c: char| !c.is_ascii_digit())),
+        None => debug,
+    }
+}
+
+/// The widget's type, like `Button`.
+fn type_name(widget: &dyn Widget) -> String {
+    format!("{:?}", widget)
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// The widget's type, name if it has one, and ID, like `Button "quit" #12`.
+fn tag(layer: &Layering, id: &NodeId) -> String {
+    let type_name = layer
+        .get_widget(id)
+        .map(|widget| type_name(widget.as_ref()))
+        .unwrap_or_default();
+    match layer.widget_name(id) {
+        Some(name) => format!("{} {:?} {}", type_name, name, short_id(id)),
+        None => format!("{} {}", type_name, short_id(id)),
+    }
+}
+
+/// Handler counts by event type, in the order they were first registered, like `Click x2, Update`.
+fn handler_summary(events: &[EventType]) -> String {
+    let mut counts: Vec<(EventType, usize)> = vec![];
+    for what in events {
+        match counts.iter_mut().find(|(counted, _)| counted == what) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((*what, 1)),
+        }
+    }
+    if counts.is_empty() {
+        return "none".to_owned();
+    }
+    counts
+        .iter()
+        .map(|(what, count)| match count {
+            1 => format!("{:?}", what),
+            _ => format!("{:?} x{}", what, count),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The widget under `point`: of those in `ids` (in the order they are drawn), the last one drawn
+/// there, which is also the most deeply nested.
+fn hovered<'a>(layer: &Layering, ids: &'a [NodeId], point: &Point2<f32>) -> Option<&'a NodeId> {
+    ids.iter().rev().find(|id| {
+        layer
+            .get_widget(id)
+            .map_or(false, |widget| within_widget(point, &widget.rect()))
+    })
+}
+
+impl WidgetInspector {
+    pub fn new() -> Self {
+        WidgetInspector { visible: false }
+    }
+
+    /// The lines of the side panel describing the widget.
+    fn describe(layer: &mut Layering, id: &NodeId) -> UIResult<Vec<String>> {
+        let path = layer
+            .widget_path(id)?
+            .iter()
+            .map(|id| tag(layer, id))
+            .collect::<Vec<_>>();
+        let widget = layer.get_widget_mut(id)?;
+        let rect = widget.rect();
+        let z_index = widget.z_index();
+        let handlers = match widget.as_emit_event() {
+            Some(emitter) => handler_summary(emitter.registered_events()),
+            None => "does not emit events".to_owned(),
+        };
+
+        let mut lines = vec![
+            path.last().cloned().unwrap_or_default(),
+            format!("Rect: ({}, {}) {} x {}", rect.x, rect.y, rect.w, rect.h),
+            format!("Layer: {}", z_index),
+            format!("Handlers: {}", handlers),
+            "Path:".to_owned(),
+        ];
+        for (depth, step) in path.iter().enumerate() {
+            lines.push(format!("{}{}", "  ".repeat(depth + 1), step));
+        }
+        Ok(lines)
+    }
+
+    /// Outlines the widgets that `layer` draws, and describes the one under `mouse` in a panel on
+    /// the right side of the screen.
+    pub fn draw(&self, ctx: &mut Context, font: Font, layer: &mut Layering, mouse: Point2<f32>) -> UIResult<()> {
+        let (previous_layer, top_layer) = layer.widgets_to_draw();
+        let hovered_id = hovered(layer, &top_layer, &mouse).cloned();
+
+        for id in previous_layer.iter().chain(top_layer.iter()) {
+            let rect = layer.get_widget(id)?.rect();
+            let color = if Some(id) == hovered_id.as_ref() {
+                *INSPECTOR_HOVER_COLOR
+            } else {
+                *INSPECTOR_OUTLINE_COLOR
+            };
+            if rect.w > 0.0 && rect.h > 0.0 {
+                let outline = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), rect, color)?;
+                graphics::draw(ctx, &outline, DrawParam::default())?;
+            }
+            let fragment = TextFragment::new(tag(layer, id))
+                .scale(PxScale::from(TAG_SCALE))
+                .color(color)
+                .font(font);
+            graphics::queue_text(
+                ctx,
+                &Text::new(fragment),
+                Point2 {
+                    x: rect.x + 2.0,
+                    y: rect.y + 2.0,
+                },
+                None,
+            );
+        }
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, graphics::FilterMode::Linear)?;
+
+        let lines = match hovered_id {
+            Some(ref id) => WidgetInspector::describe(layer, id)?,
+            None => vec!["Point at a widget to inspect it".to_owned()],
+        };
+        let (screen_w, _) = graphics::drawable_size(ctx);
+        let panel = Rect::new(
+            screen_w - PANEL_WIDTH - MARGIN,
+            MARGIN,
+            PANEL_WIDTH,
+            lines.len() as f32 * LINE_HEIGHT + 2.0 * MARGIN,
+        );
+        let background = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), panel, scheme().chat_pane_fill)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        for (i, line) in lines.into_iter().enumerate() {
+            let point = Point2 {
+                x: panel.x + MARGIN,
+                y: panel.y + MARGIN + i as f32 * LINE_HEIGHT,
+            };
+            draw_text(ctx, font, scheme().value_text, line, &point)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{InsertLocation, Pane};
+    use super::*;
+
+    #[test]
+    fn test_hovered_is_the_innermost_widget() {
+        let mut layer = Layering::new();
+        let outer_id = layer
+            .add_widget(
+                Box::new(Pane::new(Rect::new(0.0, 0.0, 100.0, 100.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        let inner_id = layer
+            .add_widget(
+                Box::new(Pane::new(Rect::new(10.0, 10.0, 20.0, 20.0))),
+                InsertLocation::ToNestedContainer(&outer_id),
+            )
+            .unwrap();
+        layer.set_widget_name("inner", &inner_id);
+
+        let (_, ids) = layer.widgets_to_draw();
+        assert_eq!(hovered(&layer, &ids, &Point2 { x: 15.0, y: 15.0 }), Some(&inner_id));
+        assert_eq!(hovered(&layer, &ids, &Point2 { x: 50.0, y: 50.0 }), Some(&outer_id));
+        assert_eq!(hovered(&layer, &ids, &Point2 { x: 150.0, y: 50.0 }), None);
+
+        assert_eq!(
+            layer.widget_path(&inner_id).unwrap(),
+            vec![outer_id.clone(), inner_id.clone()]
+        );
+        assert_eq!(
+            tag(&layer, &inner_id),
+            format!("Pane \"inner\" {}", short_id(&inner_id))
+        );
+        assert!(short_id(&outer_id)[1..].parse::<usize>().is_ok());
+    }
+
+    #[test]
+    fn test_handler_summary() {
+        assert_eq!(handler_summary(&[]), "none");
+        assert_eq!(
+            handler_summary(&[EventType::Click, EventType::Update, EventType::Click]),
+            "Click x2, Update"
+        );
+    }
+}
@@ -16,22 +16,40 @@
  *  along with conwayste.  If not, see
  *  <http://www.gnu.org/licenses/>. */
 
+use std::error::Error;
 use std::fmt;
+use std::rc::Rc;
 
 #[cfg(test)]
 use ggez::graphics::Font;
-use ggez::graphics::{self, Color, DrawParam, Drawable, Rect, Text, TextFragment};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect, Text, TextFragment};
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
 use id_tree::NodeId;
+use serde::Deserialize;
 
 use super::{
     common::FontInfo,
-    context::{EmitEvent, HandlerData},
+    context::{EmitEvent, Event, EventType, Handled, HandlerData, MoveCross, UIContext},
+    textcache::TextCache,
     widget::Widget,
+    wrap::wrap_words,
     UIError, UIResult,
 };
+use crate::constants::{colors::scheme, CHATBOX_BORDER_PIXELS, CHATBOX_LINE_SPACING};
+
+const ELLIPSIS: char = '…';
+const TOOLTIP_MAX_CHARS: usize = 60; // per line of the tooltip showing the whole text
+
+/// Where a label puts each line of its text across its rect.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
 
 pub struct Label {
     id:             Option<NodeId>,
@@ -40,6 +58,12 @@ pub struct Label {
     z_index:        usize,
     pub textfrag:   TextFragment,
     pub dimensions: Rect,
+    pub align:      Alignment,
+    pub wrap:       bool, // break lines between words to fit the width, once the size is set
+    pub ellipsize:  bool, // cut off what doesn't fit the size, once it is set, ending with "…"
+    fixed_size:     bool, // the size was set, rather than following the text
+    truncated:      bool, // as last drawn, so the whole text is shown in a tooltip on hover
+    hovered:        bool,
     handler_data:   HandlerData,
 }
 
@@ -53,7 +77,44 @@ impl fmt::Debug for Label {
     }
 }
 
-/// A graphical widget representation of text
+/// The lines to draw `text` in, at most `max_chars` characters wide if wrapping or ellipsizing,
+/// and at most `max_lines` of them if ellipsizing. Line breaks in the text are kept. The second
+/// value is whether anything was cut off.
+fn fit_lines(text: &str, max_chars: usize, max_lines: usize, wrap: bool, ellipsize: bool) -> (Vec<String>, bool) {
+    let mut lines: Vec<String> = vec![];
+    for line in text.split('\n') {
+        let wrapped = if wrap { wrap_words(line, max_chars) } else { vec![] };
+        if wrapped.is_empty() {
+            lines.push(line.to_owned());
+        } else {
+            lines.extend(wrapped.into_iter().map(|line| line.trim_end().to_owned()));
+        }
+    }
+    if !ellipsize {
+        return (lines, false);
+    }
+
+    let mut truncated = false;
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.last_mut().unwrap(); // unwrap OK because max_lines is at least 1
+        last.push(ELLIPSIS);
+        truncated = true;
+    }
+    for line in lines.iter_mut() {
+        if line.chars().count() > max_chars {
+            let kept: String = line.chars().take(max_chars.saturating_sub(1)).collect();
+            *line = format!("{}{}", kept.trim_end(), ELLIPSIS);
+            truncated = true;
+        }
+    }
+    (lines, truncated)
+}
+
+/// A graphical widget representation of text. The label is as big as its text until its size is
+/// set; from then on, the text is lined up in it according to `align`, and `wrap` and `ellipsize`
+/// make it fit. If the text had to be cut off, the whole of it is shown while the mouse is over the
+/// label.
 impl Label {
     /// Creates a Label widget.
     ///
@@ -80,7 +141,7 @@ impl Label {
     ///     Color::from(css::WHITE)
     /// );
     ///
-    /// label.draw(ctx);
+    /// label.draw(ctx, texts);
     /// ```
     ///
     pub fn new(ctx: &mut Context, font_info: FontInfo, string: String, color: Color, dest: Point2<f32>) -> Self {
@@ -105,19 +166,44 @@ impl Label {
         let mut dimensions = <Text as Drawable>::dimensions(&text, ctx).unwrap();
         dimensions.move_to(dest);
 
-        Label {
+        let mut label = Label {
             id: None,
             font_info,
             color,
             z_index: std::usize::MAX,
             textfrag: text_fragment,
             dimensions,
+            align: Alignment::Left,
+            wrap: false,
+            ellipsize: false,
+            fixed_size: false,
+            truncated: false,
+            hovered: false,
             handler_data: HandlerData::new(),
+        };
+        label
+            .on(EventType::MouseMove, Box::new(Label::mouse_move_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        label
+    }
+
+    fn mouse_move_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let label = obj.downcast_mut::<Label>().unwrap(); // unwrap OK because this will always be Label
+        match event.move_did_cross(label.dimensions) {
+            MoveCross::Enter => label.hovered = true,
+            MoveCross::Exit => label.hovered = false,
+            MoveCross::None => {}
         }
+        Ok(Handled::NotHandled)
     }
 
-    /// Sets the text for this label. Note that the dimensions are changed by this.
-    pub fn set_text(&mut self, ctx: &mut Context, text: String) {
+    /// Sets the text for this label. Note that the dimensions are changed by this, unless the size
+    /// was set. They are measured with the `Text` from `texts`, which is then ready to be drawn.
+    pub fn set_text(&mut self, ctx: &mut Context, texts: &mut TextCache, text: String) {
         let dest = self.dimensions.point();
         let text_fragment;
         #[cfg(not(test))]
@@ -135,11 +221,13 @@ impl Label {
                 .font(Font::default());
         }
 
-        let text = Text::new(text_fragment.clone());
-        // unwrap safe b/c if this fails then the game is fundamentally broken and is not in a usable state
-        let mut dimensions = <Text as Drawable>::dimensions(&text, ctx).unwrap();
-        dimensions.move_to(dest);
-        self.dimensions = dimensions;
+        if !self.fixed_size {
+            let text = texts.get(text_fragment.clone());
+            // unwrap safe b/c if this fails then the game is fundamentally broken and is not in a usable state
+            let mut dimensions = <Text as Drawable>::dimensions(&*text, ctx).unwrap();
+            dimensions.move_to(dest);
+            self.dimensions = dimensions;
+        }
         self.textfrag = text_fragment;
     }
 
@@ -148,6 +236,68 @@ impl Label {
     pub fn text(&self) -> &str {
         &self.textfrag.text
     }
+
+    /// Text of this label's font and size, but holding `line` and drawn in `color`.
+    fn line_text(&self, texts: &mut TextCache, line: String, color: Color) -> Rc<Text> {
+        let mut fragment = self.textfrag.clone();
+        fragment.text = line;
+        fragment.color = Some(color);
+        texts.get(fragment)
+    }
+
+    /// Draws the lines that fit the size that was set, lined up according to `align`.
+    fn draw_fitted(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let char_dims = self.font_info.char_dimensions;
+        let max_chars = ((self.dimensions.w / char_dims.x) as usize).max(1);
+        let max_lines = ((self.dimensions.h / char_dims.y) as usize).max(1);
+        let (lines, truncated) = fit_lines(&self.textfrag.text, max_chars, max_lines, self.wrap, self.ellipsize);
+        self.truncated = truncated;
+
+        for (i, line) in lines.into_iter().enumerate() {
+            let text = self.line_text(texts, line, self.color);
+            let spare_w = self.dimensions.w - text.width(ctx) as f32;
+            let x = match self.align {
+                Alignment::Left => self.dimensions.x,
+                Alignment::Center => self.dimensions.x + spare_w / 2.0,
+                Alignment::Right => self.dimensions.x + spare_w,
+            };
+            let y = self.dimensions.y + i as f32 * char_dims.y;
+            graphics::draw(ctx, &*text, DrawParam::default().dest(Point2 { x, y }))?;
+        }
+        Ok(())
+    }
+
+    /// Shows the whole text in a box under the label, for when it had to be cut off.
+    fn draw_tooltip(&self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let char_dims = self.font_info.char_dimensions;
+        let (lines, _) = fit_lines(&self.textfrag.text, TOOLTIP_MAX_CHARS, usize::MAX, true, false);
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let tooltip = Rect::new(
+            self.dimensions.x,
+            self.dimensions.bottom() + CHATBOX_LINE_SPACING,
+            (longest + 2) as f32 * char_dims.x,
+            lines.len() as f32 * char_dims.y + CHATBOX_LINE_SPACING * 2.0,
+        );
+        let background = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), tooltip, scheme().chat_pane_fill)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        let border = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::stroke(CHATBOX_BORDER_PIXELS),
+            tooltip,
+            scheme().pane_border,
+        )?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        for (i, line) in lines.into_iter().enumerate() {
+            let text = self.line_text(texts, line, scheme().value_text);
+            let dest = Point2 {
+                x: tooltip.x + char_dims.x,
+                y: tooltip.y + CHATBOX_LINE_SPACING + i as f32 * char_dims.y,
+            };
+            graphics::draw(ctx, &*text, DrawParam::default().dest(dest))?;
+        }
+        Ok(())
+    }
 }
 
 impl Widget for Label {
@@ -181,6 +331,7 @@ impl Widget for Label {
         }
 
         self.dimensions = new_dims;
+        self.fixed_size = true;
         Ok(())
     }
 
@@ -206,6 +357,7 @@ impl Widget for Label {
 
         self.dimensions.w = w;
         self.dimensions.h = h;
+        self.fixed_size = true;
 
         Ok(())
     }
@@ -215,8 +367,16 @@ impl Widget for Label {
         self.dimensions.translate(dest);
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let text = Text::new(self.textfrag.clone());
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        if self.fixed_size {
+            self.draw_fitted(ctx, texts)?;
+            if self.truncated && self.hovered {
+                self.draw_tooltip(ctx, texts)?;
+            }
+            return Ok(());
+        }
+
+        let text = texts.get(self.textfrag.clone());
 
         // If the text is updated, we need to refresh the dimensions of the virtual rectangle bounding it.
         // unwrap safe b/c if this fails then the game is fundamentally broken and is not in a usable state
@@ -226,7 +386,7 @@ impl Widget for Label {
             self.dimensions.h = recalculated.h;
         }
 
-        graphics::draw(ctx, &text, DrawParam::default().dest(self.dimensions.point()))?;
+        graphics::draw(ctx, &*text, DrawParam::default().dest(self.dimensions.point()))?;
 
         Ok(())
     }
@@ -238,3 +398,32 @@ impl Widget for Label {
 
 widget_from_id!(Label);
 impl_emit_event!(Label, self.handler_data);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fit_lines_wraps_and_ellipsizes() {
+        let text = "Welcome to the room\nBe nice";
+        assert_eq!(
+            fit_lines(text, 10, 5, false, false),
+            (vec!["Welcome to the room".to_owned(), "Be nice".to_owned()], false)
+        );
+        assert_eq!(
+            fit_lines(text, 10, 5, true, true),
+            (
+                vec!["Welcome to".to_owned(), "the room".to_owned(), "Be nice".to_owned()],
+                false
+            )
+        );
+        assert_eq!(
+            fit_lines(text, 10, 2, true, true),
+            (vec!["Welcome to".to_owned(), "the room…".to_owned()], true)
+        );
+        assert_eq!(
+            fit_lines(text, 10, 1, false, true),
+            (vec!["Welcome t…".to_owned()], true)
+        );
+    }
+}
@@ -18,9 +18,9 @@
 
 use std::error::Error;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Rect};
 use ggez::input::keyboard::KeyCode;
 use ggez::mint::{Point2, Vector2};
 use ggez::Context;
@@ -28,14 +28,19 @@ use ggez::Context;
 use id_tree::{InsertBehavior, Node, NodeId, RemoveBehavior, Tree, TreeBuilder};
 
 use super::{
-    common::within_widget,
+    batch::ShapeBatch,
+    common::{intersection, within_widget},
     context::{Event, EventType, Handled, KeyCodeOrChar, UIContext},
     focus::{CycleType, FocusCycle},
+    gesture::Gestures,
+    textcache::TextCache,
+    timer::Timers,
     treeview,
     widget::Widget,
-    BoxedWidget, GameAreaState, Pane, UIError, UIResult,
+    BoxedWidget, GameAreaState, UIError, UIResult,
 };
 
+use crate::accessibility;
 use crate::config;
 use crate::constants::{colors::*, LAYERING_NODE_CAPACITY, LAYERING_SWAP_CAPACITY};
 use crate::uilayout::StaticNodeIds;
@@ -96,8 +101,19 @@ pub struct Layering {
     removed_node_ids:      HashSet<NodeId>, // Set of all node-ids that have been removed from the Tree
     pub highest_z_order:   usize,           // Number of layers allocated in the system + 1
     focus_cycles:          Vec<FocusCycle>, // For each layer, a "FocusCycle" keeping track of which widgets
-                                            // can be tabbed through to get focus, in which order, and which
-                                            // widget of these (if any) has focus.
+    // can be tabbed through to get focus, in which order, and which
+    // widget of these (if any) has focus.
+    widgets_drawn:         usize, // Number of widgets drawn by the last call to draw()
+    names:                 HashMap<String, NodeId>, // Widgets that can be looked up by name
+    timers:                Timers, // Started by the widgets' handlers
+    gestures:              Gestures, // Double clicks and long presses in progress
+    clip_canvas:           Option<Canvas>, // Where widgets that only show in part, like in a ScrollPane, are drawn
+    mesh_cache:            Vec<(ShapeBatch, graphics::Mesh)>, // The shapes of widgets drawn last frame
+    meshes_drawn:          usize, // How many of those have been drawn this frame
+    text_cache:            TextCache, // The texts of widgets drawn last frame and this one
+    dirty:                 bool,  // Whether anything has changed since the last call to draw()
+    update_depth:          usize, // How many calls to begin_update() have yet to be ended
+    deferred_events:       Vec<Event>, // Emitted during a batch update, to be handled once it ends
 }
 
 /// A `Layering` is a container of one or more widgets or panes (hereby referred to as widgets),
@@ -137,6 +153,17 @@ impl Layering {
             highest_z_order:   0,
             with_transparency: false,
             focus_cycles:      vec![FocusCycle::new(CycleType::Circular)], // empty focus cycle for z_order 0
+            widgets_drawn:     0,
+            names:             HashMap::new(),
+            timers:            Timers::new(),
+            gestures:          Gestures::new(),
+            clip_canvas:       None,
+            mesh_cache:        vec![],
+            meshes_drawn:      0,
+            text_cache:        TextCache::new(),
+            dirty:             true,
+            update_depth:      0,
+            deferred_events:   vec![],
         }
     }
 
@@ -146,9 +173,89 @@ impl Layering {
     }
 
     pub fn debug_display_widget_tree(&self) {
+        debug!("{}", self.widget_tree_string());
+    }
+
+    /// The widget tree drawn as text, one widget per line.
+    pub fn widget_tree_string(&self) -> String {
         let mut s = String::new();
         let _ = self.widget_tree.write_formatted(&mut s);
-        debug!("{}", s);
+        s
+    }
+
+    /// Gives the widget a name it can be found by. A name used before now refers to this widget.
+    pub fn set_widget_name(&mut self, name: &str, id: &NodeId) {
+        self.names.insert(name.to_owned(), id.clone());
+    }
+
+    /// The ID of the widget with this name, if it hasn't been removed.
+    pub fn widget_id_by_name(&self, name: &str) -> Option<&NodeId> {
+        self.names.get(name)
+    }
+
+    /// The name of the widget, if it has one.
+    pub fn widget_name(&self, id: &NodeId) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(_, named_id)| *named_id == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// All named widgets, as (name, ID), in no particular order.
+    pub fn named_widgets(&self) -> impl Iterator<Item = (&str, &NodeId)> {
+        self.names.iter().map(|(name, id)| (name.as_str(), id))
+    }
+
+    /// The containers the widget is nested in, outermost first, followed by the widget itself.
+    pub fn widget_path(&self, id: &NodeId) -> UIResult<Vec<NodeId>> {
+        let ancestors = self.widget_tree.ancestor_ids(id).map_err(|e| {
+            Box::new(UIError::WidgetNotFound {
+                reason: format!("{:?} not found in layering's widget tree: {:?}", id, e),
+            })
+        })?;
+        let root_id = self.widget_tree.root_node_id().unwrap();
+        let mut path: Vec<NodeId> = ancestors.filter(|ancestor| *ancestor != root_id).cloned().collect();
+        path.reverse();
+        path.push(id.clone());
+        Ok(path)
+    }
+
+    /// How deeply widgets are nested in containers. Widgets added to the layering directly are at a
+    /// depth of 1; an empty layering has a depth of 0.
+    pub fn widget_tree_depth(&self) -> usize {
+        let root_id = self.widget_tree.root_node_id().unwrap();
+        self.widget_tree
+            .traverse_level_order_ids(&root_id)
+            .unwrap()
+            .map(|node_id| self.widget_tree.ancestor_ids(&node_id).unwrap().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[cfg(test)]
+    pub(super) fn uictx_parts_mut(&mut self) -> (&mut Tree<BoxedWidget>, &mut Timers, &mut bool, &mut TextCache) {
+        (
+            &mut self.widget_tree,
+            &mut self.timers,
+            &mut self.dirty,
+            &mut self.text_cache,
+        )
+    }
+
+    /// Whether anything has changed since the last call to `draw`, so the screen needs drawing
+    /// again. A screen that hasn't changed can skip a frame.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Notes that the layering needs to be drawn again, for changes made to it from outside.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// The number of widgets drawn by the last call to `draw`.
+    pub fn widgets_drawn(&self) -> usize {
+        self.widgets_drawn
     }
 
     /// Collect all nodes in the tree belonging to the corresponding z_order. The node IDs are
@@ -173,6 +280,7 @@ impl Layering {
     ///
     /// A WidgetNotFound error will be returned if the node id is not found.
     pub fn get_widget_mut(&mut self, id: &NodeId) -> UIResult<&mut BoxedWidget> {
+        self.dirty = true; // the caller may change how it looks
         if let Ok(node) = self.widget_tree.get_mut(id) {
             Ok(node.data_mut())
         } else {
@@ -182,6 +290,19 @@ impl Layering {
         }
     }
 
+    /// Like `get_widget_mut`, but also returns the layering's text cache, for changing the text of a
+    /// widget outside of a handler.
+    pub fn get_widget_and_texts_mut(&mut self, id: &NodeId) -> UIResult<(&mut BoxedWidget, &mut TextCache)> {
+        self.dirty = true; // the caller may change how it looks
+        if let Ok(node) = self.widget_tree.get_mut(id) {
+            Ok((node.data_mut(), &mut self.text_cache))
+        } else {
+            Err(Box::new(UIError::WidgetNotFound {
+                reason: format!("{:?} not found in layering's widget list", id).to_owned(),
+            }))
+        }
+    }
+
     pub fn get_widget(&self, id: &NodeId) -> UIResult<&BoxedWidget> {
         if let Ok(node) = self.widget_tree.get(id) {
             Ok(node.data())
@@ -192,6 +313,18 @@ impl Layering {
         }
     }
 
+    /// Moves a widget about to be nested in `container` from where it is relative to the container
+    /// to where that is on the screen, and puts it on the container's layer. A widget nested in
+    /// something other than a container is left as it is.
+    fn place_in_container(container: &BoxedWidget, widget: &mut BoxedWidget) {
+        let origin = match container.as_container() {
+            Some(container) => container.child_origin(),
+            None => return,
+        };
+        widget.translate(origin);
+        widget.set_z_index(container.z_index());
+    }
+
     /// Add a widget to the layering, where the z-order is specified by the insert modifier.
     /// Widgets can be inserted at the current layer, at the next layer (one order higher), or nested
     /// to a widget-container (like a Pane). The widget's z-index is overridden by the destination
@@ -214,6 +347,7 @@ impl Layering {
             }));
         }
         let widget_accepts_keyboard_events = widget.accepts_keyboard_events();
+        self.dirty = true;
 
         // Unwrap safe because our tree will always have a dummy root node
         let root_id = self.widget_tree.root_node_id().unwrap().clone();
@@ -253,14 +387,15 @@ impl Layering {
                 // First find the node_id that corresponds to the container we're adding to
                 let node = self.widget_tree.get(&parent_id).unwrap();
                 let parent_dyn_widget = node.data();
-                if let Some(pane) = downcast_widget!(parent_dyn_widget, Pane) {
-                    // Prepare the widget for insertion at the Pane's layer, translated to
-                    // an offset from the Pane's top-left corner
-                    let point = pane.dimensions.point();
-                    let vector = Vector2 { x: point.x, y: point.y };
-                    widget.translate(vector);
-                    widget.set_z_index(pane.z_index());
+                if let Some(container) = parent_dyn_widget.as_container() {
+                    if container.is_full() {
+                        return Err(Box::new(UIError::InvalidAction {
+                            reason: format!("Cannot nest {:?}; {:?} is full", widget, parent_dyn_widget),
+                        }));
+                    }
                 }
+                Layering::place_in_container(parent_dyn_widget, &mut widget);
+                let widget_size = widget.size();
 
                 // Insert the node under the found node_id corresponding to the Pane
                 inserted_node_id = self
@@ -275,12 +410,10 @@ impl Layering {
                         }))
                     })?;
                 let parent_dyn_widget = self.widget_tree.get_mut(&parent_id).unwrap().data_mut();
-                if let Some(pane) = downcast_widget_mut!(parent_dyn_widget, Pane) {
-                    // notify the Pane that we added a widget to it (used for keyboard focus)
-                    if widget_accepts_keyboard_events {
-                        pane.add_widget(inserted_node_id.clone());
-                        added_to_pane_focus_cycle = true;
-                    }
+                if let Some(container) = parent_dyn_widget.as_container_mut() {
+                    // notify the container that we added a widget to it (used for keyboard focus)
+                    added_to_pane_focus_cycle =
+                        container.child_added(inserted_node_id.clone(), widget_size, widget_accepts_keyboard_events);
                 }
             }
         }
@@ -312,42 +445,37 @@ impl Layering {
     ///
     /// A WidgetNotFound error can be returned if a widget with the `widget_id` does not exist
     /// in the internal list of widgets.
-    // Implemented API for future use. TODO: Remove comment once function is used
-    #[allow(unused)]
     pub fn remove_widget(&mut self, id: NodeId) -> UIResult<()> {
         if !self.widget_exists(&id) {
             return Err(Box::new(UIError::WidgetNotFound {
                 reason: format!("{:?} not found in layer during removal", id).to_owned(),
             }));
         }
+        self.dirty = true;
 
-        // call remove_widget on its containing Pane, if any
+        // tell its container, if any, that it is gone
         let pane_id = self.widget_tree.ancestor_ids(&id).unwrap().nth(0); // unwrap OK (id is valid)
         if let Some(pane_id) = pane_id {
             let pane_id = pane_id.clone();
             let parent_dyn_widget = self.widget_tree.get_mut(&pane_id).unwrap().data_mut();
-            if let Some(pane) = downcast_widget_mut!(parent_dyn_widget, Pane) {
-                // notify the Pane that we removed a widget from it (used for keyboard focus)
-                pane.remove_widget(&id);
+            if let Some(container) = parent_dyn_widget.as_container_mut() {
+                container.child_removed(&id);
             }
         }
 
-        // Insert the node's children ids to the removed hash-set
-        if let Ok(children_ids) = self.widget_tree.children_ids(&id) {
-            // collect nodes to bypass issue with double borrow on ChildrenIds iterator
-            for node_id_ref in children_ids {
-                self.removed_node_ids.insert((*node_id_ref).clone());
-            }
+        // Forget the names and timers of the node and everything in it, and note their ids so that
+        // a reused one can be reported during widget insertion
+        let removed: Vec<NodeId> = self.widget_tree.traverse_pre_order_ids(&id).unwrap().collect(); // unwrap OK
+        self.names.retain(|_, named_id| !removed.contains(named_id));
+        self.timers.forget_widgets(&removed);
+        self.removed_node_ids.extend(removed.iter().cloned());
+
+        // Remove from the focus cycle of its layer, which need not be the top one
+        let z_index = self.widget_tree.get(&id).unwrap().data().z_index(); // unwrap OK (id is valid)
+        if let Some(focus_cycle) = self.focus_cycles.get_mut(z_index) {
+            focus_cycle.remove(&id);
         }
 
-        // Finally check the node itself
-        // clone is okay because the HashSet is intended to keep track of all removed widget ids
-        // result not checked as this is reported during widget insertion
-        self.removed_node_ids.insert(id.clone());
-
-        // Remove from focus cycle
-        self.focus_cycles[self.highest_z_order].remove(&id);
-
         // clone is okay because it is required
         self.widget_tree
             .remove_node(id.clone(), RemoveBehavior::DropChildren)
@@ -358,13 +486,118 @@ impl Layering {
                 }));
             })?;
 
-        // Determine if the highest z-order changes due to the widget removal by checking no other
-        // widgets are present at that z_order
+        if !self.is_updating() {
+            self.drop_empty_layers();
+        }
+
+        Ok(())
+    }
+
+    /// Determine if the highest z-order changes due to widget removals by checking no other
+    /// widgets are present at that z_order
+    fn drop_empty_layers(&mut self) {
         while self.highest_z_order != 0 && self.collect_node_ids(self.highest_z_order).is_empty() {
             self.highest_z_order -= 1;
             self.focus_cycles.pop();
         }
+    }
+
+    /// Starts a batch of changes, such as refilling a list with dozens of widgets. Until the batch
+    /// ends, the layering skips checking for emptied layers after each removal, and holds on to
+    /// the events emitted on it, other than `Update`, so that handlers never see the list half
+    /// built. Batches can be nested; only the end of the outermost one counts.
+    pub fn begin_update(&mut self) {
+        self.update_depth += 1;
+    }
 
+    /// Ends a batch of changes started by `begin_update`. The events held back are handled at the
+    /// start of the next call to `emit`, in the order they were emitted.
+    pub fn end_update(&mut self) {
+        if self.update_depth == 0 {
+            warn!("Layering::end_update called without a matching begin_update");
+            return;
+        }
+        self.update_depth -= 1;
+        if self.update_depth == 0 {
+            self.drop_empty_layers();
+        }
+    }
+
+    /// Whether a batch of changes started by `begin_update` is underway.
+    pub fn is_updating(&self) -> bool {
+        self.update_depth > 0
+    }
+
+    /// Replaces a widget with another, such as a row of a list that has changed. The new widget
+    /// takes the old one's place: it keeps its id, name, layer and place in the tab order, and is
+    /// positioned relative to the old one's container like a widget being added to it. Anything
+    /// nested in the old widget is removed along with it, as are its timers. If the old widget had
+    /// keyboard focus, it is taken away, so the new one starts out unfocused.
+    ///
+    /// # Errors
+    ///
+    /// A WidgetNotFound error can be returned if a widget with the `id` does not exist in the
+    /// layering.
+    /// A NodeIDCollision error can be returned if the new widget has been added to a layering.
+    pub fn replace_widget(&mut self, id: &NodeId, mut widget: BoxedWidget) -> UIResult<()> {
+        if !self.widget_exists(id) {
+            return Err(Box::new(UIError::WidgetNotFound {
+                reason: format!("{:?} not found in layer during replacement", id),
+            }));
+        }
+        if let Some(widget_id) = widget.id() {
+            return Err(Box::new(UIError::NodeIDCollision {
+                reason: format!(
+                    "Attempted to replace {:?} with widget of assigned ID {:?}.",
+                    id, widget_id
+                ),
+            }));
+        }
+
+        self.dirty = true;
+
+        let child_ids: Vec<NodeId> = self.widget_tree.children_ids(id).unwrap().cloned().collect(); // unwrap OK (id is valid)
+        for child_id in child_ids {
+            self.remove_widget(child_id)?;
+        }
+        self.timers.forget_widgets(&[id.clone()]);
+
+        let old_widget = self.widget_tree.get(id).unwrap().data(); // unwrap OK (id is valid)
+        let old_accepts_keyboard_events = old_widget.accepts_keyboard_events();
+        let z_index = old_widget.z_index();
+        widget.set_z_index(z_index);
+
+        // A widget in a container is in the container's focus cycle, if it has one, or else the
+        // container keeps track of it itself; the rest are in the focus cycle of their layer
+        let parent_id = self.widget_tree.ancestor_ids(id).unwrap().next().cloned(); // unwrap OK (id is valid)
+        let mut in_container = false;
+        if let Some(ref parent_id) = parent_id {
+            let parent = self.widget_tree.get(parent_id).unwrap().data(); // unwrap OK b/c it's in the tree
+            Layering::place_in_container(parent, &mut widget);
+            in_container = parent.as_container().is_some();
+        }
+        let focus_cycle = match parent_id {
+            Some(ref parent_id) if in_container => {
+                let parent = self.widget_tree.get_mut(parent_id).unwrap().data_mut(); // unwrap OK
+                parent
+                    .as_container_mut()
+                    .and_then(|container| container.focus_cycle_mut())
+            }
+            _ => self.focus_cycles.get_mut(z_index),
+        };
+        if let Some(focus_cycle) = focus_cycle {
+            if focus_cycle.focused_widget_id() == Some(id) {
+                focus_cycle.clear_focus();
+            }
+            match (old_accepts_keyboard_events, widget.accepts_keyboard_events()) {
+                (true, false) => focus_cycle.remove(id),
+                (false, true) => focus_cycle.push(id.clone()),
+                _ => {}
+            }
+        }
+
+        widget.set_id(id.clone());
+        *self.widget_tree.get_mut(id).unwrap().data_mut() = widget; // unwrap OK (id is valid)
         Ok(())
     }
 
@@ -422,6 +655,28 @@ impl Layering {
         static_node_ids: &mut StaticNodeIds,
         viewport: &mut GridView,
         id: &NodeId,
+    ) -> UIResult<()> {
+        self.enter_focus_with_context(
+            Some(ggez_context),
+            cfg,
+            screen_stack,
+            game_area_state,
+            static_node_ids,
+            viewport,
+            id,
+        )
+    }
+
+    /// Like `enter_focus`, but the ggez context is optional so that UI tests can run headless.
+    pub(super) fn enter_focus_with_context(
+        &mut self,
+        ggez_context: Option<&mut ggez::Context>,
+        cfg: &mut config::Config,
+        screen_stack: &mut Vec<Screen>,
+        game_area_state: &mut GameAreaState,
+        static_node_ids: &mut StaticNodeIds,
+        viewport: &mut GridView,
+        id: &NodeId,
     ) -> UIResult<()> {
         let mut focus_cycle = &mut self.focus_cycles[self.highest_z_order];
         if focus_cycle.find(id).is_none() {
@@ -441,6 +696,7 @@ impl Layering {
             // (I don't think this is possible?)
             return Ok(());
         }
+        self.dirty = true;
 
         let widget_view = treeview::TreeView::new(&mut self.widget_tree);
 
@@ -452,6 +708,9 @@ impl Layering {
             game_area_state.first_gen_was_drawn,
             static_node_ids,
             viewport,
+            &mut self.timers,
+            &mut self.dirty,
+            &mut self.text_cache,
         );
 
         Layering::switch_focus(&mut uictx, &mut focus_cycle, old_focused_widget.as_ref(), id)?;
@@ -470,15 +729,169 @@ impl Layering {
     }
     */
 
-    pub fn draw(&mut self, ctx: &mut Context) -> UIResult<()> {
-        if self.highest_z_order > 0 {
-            // Draw the previous layer
-            let node_ids = self.collect_node_ids(self.highest_z_order - 1);
+    /// The widgets that `draw` draws, in order: those of the layer below the top one (if there is
+    /// more than one layer), then those of the top layer. The transparent film goes between them.
+    /// Widgets hidden by their containers, such as the pages of the other tabs of a
+    /// `TabContainer`, are left out, as are those scrolled out of sight in a `ScrollPane`.
+    pub(super) fn widgets_to_draw(&self) -> (Vec<NodeId>, Vec<NodeId>) {
+        let in_sight = |id: &NodeId| match self.clip_rect(id) {
+            Some(clip) => {
+                let rect = self.widget_tree.get(id).unwrap().data().rect(); // unwrap OK b/c it's in the tree
+                intersection(clip, rect).is_some()
+            }
+            None => true,
+        };
+        let shown = |z_order| {
+            self.collect_node_ids(z_order)
+                .into_iter()
+                .filter(|id| self.is_shown(id) && in_sight(id))
+                .collect::<Vec<NodeId>>()
+        };
+        let previous_layer = if self.highest_z_order > 0 {
+            shown(self.highest_z_order - 1)
+        } else {
+            vec![]
+        };
+        (previous_layer, shown(self.highest_z_order))
+    }
+
+    /// Whether the widget is shown, which it is unless the container it is in hides it, or the
+    /// container that one is in hides that, and so on.
+    pub fn is_shown(&self, id: &NodeId) -> bool {
+        let ancestor_ids = match self.widget_tree.ancestor_ids(id) {
+            Ok(ancestor_ids) => ancestor_ids,
+            Err(_) => return false,
+        };
+        let mut child_id = id;
+        for ancestor_id in ancestor_ids {
+            let ancestor = self.widget_tree.get(ancestor_id).unwrap().data(); // unwrap OK b/c it's in the tree
+            if !ancestor.shows_child(child_id) {
+                return false;
+            }
+            child_id = ancestor_id;
+        }
+        true
+    }
 
-            for node_id in node_ids {
+    /// The part of the screen the widget can be seen in, if the containers it is in only show
+    /// part of what they hold: where all of their clip rects overlap.
+    pub fn clip_rect(&self, id: &NodeId) -> Option<Rect> {
+        let ancestor_ids = self.widget_tree.ancestor_ids(id).ok()?;
+        let mut clip: Option<Rect> = None;
+        for ancestor_id in ancestor_ids {
+            let ancestor = self.widget_tree.get(ancestor_id).unwrap().data(); // unwrap OK b/c it's in the tree
+            if let Some(ancestor_clip) = ancestor.clip_rect() {
+                clip = Some(match clip {
+                    Some(clip) => intersection(clip, ancestor_clip).unwrap_or(Rect::zero()),
+                    None => ancestor_clip,
+                });
+            }
+        }
+        clip
+    }
+
+    /// Draws the widgets in order. Each run of widgets that share a clip rect is drawn on the clip
+    /// canvas first, and only the part of it inside the clip rect is copied to the screen.
+    fn draw_widgets(&mut self, ctx: &mut Context, node_ids: Vec<NodeId>) -> UIResult<()> {
+        let mut runs: Vec<(Option<Rect>, Vec<NodeId>)> = vec![];
+        for node_id in node_ids {
+            let clip = self.clip_rect(&node_id);
+            match runs.last_mut() {
+                Some((run_clip, run)) if *run_clip == clip => run.push(node_id),
+                _ => runs.push((clip, vec![node_id])),
+            }
+        }
+
+        for (clip, run) in runs {
+            let clip = match clip {
+                Some(clip) => clip,
+                None => {
+                    self.draw_batched(ctx, run)?;
+                    continue;
+                }
+            };
+
+            // ggez has no scissor test, so draw on a canvas as big as the window and copy the clip
+            // rect of it, remaking the canvas when the window is resized
+            let (drawable_w, drawable_h) = graphics::drawable_size(ctx);
+            let stale = match self.clip_canvas {
+                Some(ref canvas) => {
+                    canvas.image().width() as f32 != drawable_w || canvas.image().height() as f32 != drawable_h
+                }
+                None => true,
+            };
+            if stale {
+                self.clip_canvas = Some(Canvas::with_window_size(ctx)?);
+            }
+            let canvas = self.clip_canvas.take().unwrap(); // unwrap OK because it was just made
+            graphics::set_canvas(ctx, Some(&canvas));
+            graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+            let drawn = self.draw_batched(ctx, run);
+            graphics::set_canvas(ctx, None);
+            drawn?;
+
+            let screen = graphics::screen_coordinates(ctx);
+            let src = Rect::new(
+                (clip.x - screen.x) / screen.w,
+                (clip.y - screen.y) / screen.h,
+                clip.w / screen.w,
+                clip.h / screen.h,
+            );
+            let param = DrawParam::default().src(src).dest(clip.point()).scale(Vector2 {
+                x: screen.w / drawable_w,
+                y: screen.h / drawable_h,
+            });
+            graphics::draw(ctx, &canvas, param)?;
+            self.clip_canvas = Some(canvas);
+        }
+        Ok(())
+    }
+
+    /// Draws the widgets in order, each run of them that only draw shapes as one mesh.
+    fn draw_batched(&mut self, ctx: &mut Context, node_ids: Vec<NodeId>) -> UIResult<()> {
+        let mut batch = ShapeBatch::new();
+        for node_id in node_ids {
+            let batched = self.widget_tree.get(&node_id).unwrap().data().batch_shapes(&mut batch);
+            if !batched {
+                self.draw_shapes(ctx, &mut batch)?;
                 let widget = self.widget_tree.get_mut(&node_id).unwrap().data_mut();
-                widget.draw(ctx)?;
+                widget.draw(ctx, &mut self.text_cache)?;
             }
+        }
+        self.draw_shapes(ctx, &mut batch)
+    }
+
+    /// Draws the shapes batched so far, and empties the batch. The meshes drawn last frame are
+    /// kept, in the order they were drawn, so that a mesh is only built again when its shapes
+    /// change.
+    fn draw_shapes(&mut self, ctx: &mut Context, batch: &mut ShapeBatch) -> UIResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::replace(batch, ShapeBatch::new());
+        let index = self.meshes_drawn;
+        self.meshes_drawn += 1;
+
+        let up_to_date = self.mesh_cache.get(index).map_or(false, |(shapes, _)| *shapes == batch);
+        if !up_to_date {
+            let mesh = batch.build(ctx)?.unwrap(); // unwrap OK because the batch isn't empty
+            if index < self.mesh_cache.len() {
+                self.mesh_cache[index] = (batch, mesh);
+            } else {
+                self.mesh_cache.push((batch, mesh));
+            }
+        }
+        graphics::draw(ctx, &self.mesh_cache[index].1, DrawParam::default())?;
+        Ok(())
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> UIResult<()> {
+        let (previous_layer, top_layer) = self.widgets_to_draw();
+        self.widgets_drawn = previous_layer.len() + top_layer.len();
+        self.meshes_drawn = 0;
+        if self.highest_z_order > 0 {
+            // Draw the previous layer
+            self.draw_widgets(ctx, previous_layer)?;
 
             if self.with_transparency {
                 // TODO: Get resolution from video-settings
@@ -486,19 +899,18 @@ impl Layering {
                     ctx,
                     DrawMode::fill(),
                     Rect::new(0.0, 0.0, 1920.0, 1080.0),
-                    *LAYER_TRANSPARENCY_BG_COLOR,
+                    scheme().layer_transparency_bg,
                 )?;
                 graphics::draw(ctx, &mesh, DrawParam::default())?;
             }
         }
 
-        let node_ids = self.collect_node_ids(self.highest_z_order);
-
-        for node_id in node_ids {
-            let widget = self.widget_tree.get_mut(&node_id).unwrap().data_mut();
-            widget.draw(ctx)?;
-        }
+        self.draw_widgets(ctx, top_layer)?;
 
+        // forget the meshes of widgets that are gone, and the texts no longer drawn
+        self.mesh_cache.truncate(self.meshes_drawn);
+        self.text_cache.end_frame();
+        self.dirty = false;
         Ok(())
     }
 
@@ -515,6 +927,60 @@ impl Layering {
         static_node_ids: &mut StaticNodeIds,
         viewport: &mut GridView,
     ) -> Result<(), Box<dyn Error>> {
+        self.emit_with_context(
+            event,
+            Some(ggez_context),
+            cfg,
+            screen_stack,
+            game_area_state,
+            static_node_ids,
+            viewport,
+        )
+    }
+
+    /// Like `emit`, but the ggez context is optional so that UI tests can run headless.
+    pub(super) fn emit_with_context(
+        &mut self,
+        event: &Event,
+        ggez_context: Option<&mut ggez::Context>,
+        cfg: &mut config::Config,
+        screen_stack: &mut Vec<Screen>,
+        game_area_state: &mut GameAreaState,
+        static_node_ids: &mut StaticNodeIds,
+        viewport: &mut GridView,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.is_updating() && event.what != EventType::Update {
+            self.deferred_events.push(event.clone());
+            return Ok(());
+        }
+        if event.what != EventType::Update {
+            // input, focus changes and custom events are all assumed to change how widgets look
+            self.dirty = true;
+        }
+        if !self.is_updating() && !self.deferred_events.is_empty() {
+            let mut ggez_context = ggez_context;
+            for deferred in std::mem::replace(&mut self.deferred_events, vec![]) {
+                self.emit_with_context(
+                    &deferred,
+                    ggez_context.as_mut().map(|ctx| &mut **ctx),
+                    cfg,
+                    screen_stack,
+                    game_area_state,
+                    static_node_ids,
+                    viewport,
+                )?;
+            }
+            return self.emit_with_context(
+                event,
+                ggez_context,
+                cfg,
+                screen_stack,
+                game_area_state,
+                static_node_ids,
+                viewport,
+            );
+        }
+
         let widget_view = treeview::TreeView::new(&mut self.widget_tree);
         let mut uictx = UIContext::new(
             ggez_context,
@@ -524,11 +990,31 @@ impl Layering {
             game_area_state.first_gen_was_drawn,
             static_node_ids,
             viewport,
+            &mut self.timers,
+            &mut self.dirty,
+            &mut self.text_cache,
         );
-        if event.is_broadcast_event() {
-            Layering::broadcast_event(event, &mut uictx)
+        if event.what.is_custom_event() {
+            Layering::emit_custom_event(event, &mut uictx)
+        } else if event.is_broadcast_event() {
+            Layering::broadcast_event(event, &mut uictx)?;
+            if event.what == EventType::Update {
+                Layering::fire_timers(&mut uictx)?;
+                let input = uictx.config.get().input.clone();
+                if let Some(long_press) = self.gestures.advance(uictx.frame_delta(), &input) {
+                    let focus_cycle = &mut self.focus_cycles[self.highest_z_order];
+                    if Layering::emit_mouse_event(&long_press, &mut uictx, focus_cycle)? == Handled::Handled {
+                        self.gestures.long_press_handled();
+                    }
+                }
+            }
+            Ok(())
         } else if event.is_mouse_event() {
-            Layering::emit_mouse_event(event, &mut uictx, &mut self.focus_cycles[self.highest_z_order])
+            let input = uictx.config.get().input.clone();
+            for routed in self.gestures.route(event, &input) {
+                Layering::emit_mouse_event(&routed, &mut uictx, &mut self.focus_cycles[self.highest_z_order])?;
+            }
+            Ok(())
         } else if event.is_key_event() {
             Layering::handle_keyboard_event(event, &mut uictx, &mut self.focus_cycles[self.highest_z_order])
         } else {
@@ -537,6 +1023,33 @@ impl Layering {
         }
     }
 
+    /// Sends a `Timer` event to the widget of each timer that ran out during the last frame.
+    fn fire_timers(uictx: &mut UIContext) -> Result<(), Box<dyn Error>> {
+        for (widget_id, timer_id) in uictx.advance_timers() {
+            uictx.request_redraw(); // like any other event, a timer is assumed to change something
+            let (widget_ref, mut subuictx) = uictx.derive(&widget_id)?;
+            if let Some(emittable) = widget_ref.as_emit_event() {
+                emittable.emit(&Event::new_timer(timer_id), &mut subuictx)?;
+                subuictx.collect_child_events(); // timers don't change focus
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a custom event to every widget in the view, each container before the widgets in it.
+    /// Containers don't pass custom events on themselves, since they can't know of every type.
+    fn emit_custom_event(event: &Event, uictx: &mut UIContext) -> Result<(), Box<dyn Error>> {
+        for child_id in uictx.widget_view.children_ids() {
+            let (widget_ref, mut subuictx) = uictx.derive(&child_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+            if let Some(emittable) = widget_ref.as_emit_event() {
+                emittable.emit(event, &mut subuictx)?;
+                subuictx.collect_child_events(); // custom events don't change focus
+            }
+            Layering::emit_custom_event(event, &mut subuictx)?;
+        }
+        Ok(())
+    }
+
     fn broadcast_event(event: &Event, uictx: &mut UIContext) -> Result<(), Box<dyn Error>> {
         for child_id in uictx.widget_view.children_ids() {
             // Get a mutable reference to a BoxedWidget, as well as a UIContext with a view on the
@@ -573,14 +1086,23 @@ impl Layering {
             let opt_widget = opt_child_id
                 .as_ref()
                 .map(|child_id| uictx.widget_view.get(child_id).unwrap().data());
-            if opt_child_id.is_some() && opt_widget.unwrap().downcast_ref::<Pane>().is_some() {
+            let child_takes_tab = opt_widget.map_or(false, |widget| {
+                widget.as_container().is_some() || (!event.shift_pressed() && widget.takes_tab())
+            });
+            if child_takes_tab {
                 let child_id = opt_child_id.unwrap();
                 let pane_events = Layering::emit_keyboard_event(event, uictx, &child_id)?;
 
                 // check if the Pane's focus dropped of the end of its open-ended focus "cycle"
-                Layering::handle_keyboard_child_event(key, focus_cycle, uictx, &pane_events[..], event.shift_pressed)?;
+                Layering::handle_keyboard_child_event(
+                    key,
+                    focus_cycle,
+                    uictx,
+                    &pane_events[..],
+                    event.shift_pressed(),
+                )?;
             } else {
-                if event.shift_pressed {
+                if event.shift_pressed() {
                     focus_cycle.focus_previous();
                 } else {
                     focus_cycle.focus_next();
@@ -689,6 +1211,11 @@ impl Layering {
             return Err(format!("Unexpected event type passed to Pane::emit_focus_change: {:?}", what).into());
         }
         let (widget_ref, mut subuictx) = uictx.derive(&focused_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+        if what == EventType::GainFocus {
+            if let Some(name) = widget_ref.accessible_name() {
+                accessibility::announce(name);
+            }
+        }
         if let Some(emittable) = widget_ref.as_emit_event() {
             let event = Event::new_gain_or_lose_focus(what);
             emittable.emit(&event, &mut subuictx)?;
@@ -712,13 +1239,14 @@ impl Layering {
         event: &Event,
         uictx: &mut UIContext,
         focus_cycle: &mut FocusCycle,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<Handled, Box<dyn Error>> {
         let point = event
             .point
             .as_ref()
             .ok_or_else(|| -> Box<dyn Error> { format!("event of type {:?} has no point", event.what).into() })?;
 
         let mut child_events = vec![];
+        let mut handled = Handled::NotHandled;
 
         for child_id in uictx.widget_view.children_ids() {
             // Get a mutable reference to a BoxedWidget, as well as a UIContext with a view on the
@@ -727,7 +1255,7 @@ impl Layering {
 
             if within_widget(point, &widget_ref.rect()) {
                 if let Some(emittable) = widget_ref.as_emit_event() {
-                    let handled = emittable.emit(event, &mut subuictx)?;
+                    handled = emittable.emit(event, &mut subuictx)?;
                     let pane_events = subuictx.collect_child_events();
                     if pane_events.len() != 0 {
                         for child_event in pane_events {
@@ -763,7 +1291,7 @@ impl Layering {
                 focus_cycle.clear_focus();
             }
         }
-        Ok(())
+        Ok(handled)
     }
 
     // Layers that do not have a default focus (like Run/Gamearea)
@@ -788,7 +1316,7 @@ impl Layering {
 
 #[cfg(test)]
 mod test {
-    use super::super::{common::FontInfo, Chatbox};
+    use super::super::{common::FontInfo, harness::Harness, Chatbox, Pane, TextField};
     use super::*;
     use crate::constants;
     use crate::ggez::{graphics::PxScale, mint::Vector2};
@@ -1098,4 +1626,133 @@ mod test {
 
         assert_eq!(all_ids.difference(&layer_info.removed_node_ids).count(), 0);
     }
+
+    #[test]
+    fn test_removing_a_pane_forgets_the_names_in_it() {
+        let mut layer_info = Layering::new();
+        let font_info = create_dummy_font();
+
+        let pane_id = layer_info
+            .add_widget(
+                Box::new(Pane::new(Rect::new(0.0, 0.0, 1.0, 1.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        let chatbox_id = layer_info
+            .add_widget(
+                Box::new(Chatbox::new(font_info, 5)),
+                InsertLocation::ToNestedContainer(&pane_id),
+            )
+            .unwrap();
+        layer_info.set_widget_name("pane", &pane_id);
+        layer_info.set_widget_name("chat", &chatbox_id);
+        assert_eq!(layer_info.widget_id_by_name("chat"), Some(&chatbox_id));
+        assert_eq!(layer_info.widget_name(&pane_id), Some("pane"));
+
+        layer_info.remove_widget(pane_id.clone()).unwrap();
+        assert_eq!(layer_info.widget_id_by_name("pane"), None);
+        assert_eq!(layer_info.widget_id_by_name("chat"), None);
+        assert_eq!(layer_info.named_widgets().count(), 0);
+    }
+
+    #[test]
+    fn test_replacing_a_widget_keeps_its_place() {
+        let mut layer_info = Layering::new();
+        let font_info = create_dummy_font();
+
+        let pane_id = layer_info
+            .add_widget(
+                Box::new(Pane::new(Rect::new(10.0, 10.0, 100.0, 100.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        let row_id = layer_info
+            .add_widget(
+                Box::new(Pane::new(Rect::new(0.0, 0.0, 100.0, 20.0))),
+                InsertLocation::ToNestedContainer(&pane_id),
+            )
+            .unwrap();
+        let chatbox_id = layer_info
+            .add_widget(
+                Box::new(Chatbox::new(font_info, 5)),
+                InsertLocation::ToNestedContainer(&row_id),
+            )
+            .unwrap();
+        layer_info.set_widget_name("row", &row_id);
+
+        layer_info
+            .replace_widget(&row_id, Box::new(Pane::new(Rect::new(0.0, 20.0, 100.0, 30.0))))
+            .unwrap();
+        assert_eq!(layer_info.widget_id_by_name("row"), Some(&row_id));
+        assert_eq!(
+            layer_info.get_widget(&row_id).unwrap().rect(),
+            Rect::new(10.0, 30.0, 100.0, 30.0)
+        );
+        assert!(!layer_info.widget_exists(&chatbox_id));
+        assert!(layer_info.removed_node_ids.contains(&chatbox_id));
+        assert!(layer_info
+            .replace_widget(&chatbox_id, Box::new(Pane::new(Rect::new(0.0, 0.0, 1.0, 1.0))))
+            .is_err());
+    }
+
+    #[test]
+    fn test_replacing_the_focused_widget_unfocuses_it() {
+        let mut layer_info = Layering::new();
+        let font_info = create_dummy_font();
+
+        let tf_id = layer_info
+            .add_widget(
+                Box::new(TextField::new(font_info, Rect::new(0.0, 0.0, 100.0, 20.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        layer_info.focus_cycles[0].set_focused(&tf_id);
+
+        layer_info
+            .replace_widget(
+                &tf_id,
+                Box::new(TextField::new(font_info, Rect::new(0.0, 0.0, 100.0, 20.0))),
+            )
+            .unwrap();
+        assert_eq!(layer_info.focused_widget_id(), None);
+        assert_eq!(layer_info.focus_cycles[0].as_slice(), &[tf_id.clone()]);
+    }
+
+    #[test]
+    fn test_only_changes_make_the_layering_dirty() {
+        let mut harness = Harness::new(Screen::Options);
+        let tf = TextField::new(create_dummy_font(), Rect::new(0.0, 0.0, 100.0, 20.0));
+        harness.add_widget(Box::new(tf), InsertLocation::AtCurrentLayer);
+        assert!(harness.layering.is_dirty());
+
+        harness.layering.dirty = false; // as if drawn
+        harness.update().unwrap();
+        assert!(!harness.layering.is_dirty());
+
+        harness.click(5.0, 5.0).unwrap();
+        assert!(harness.layering.is_dirty());
+    }
+
+    #[test]
+    fn test_widget_tree_depth() {
+        let mut layer_info = Layering::new();
+        assert_eq!(layer_info.widget_tree_depth(), 0);
+
+        let font_info = create_dummy_font();
+        let pane_id = layer_info
+            .add_widget(
+                Box::new(Pane::new(Rect::new(0.0, 0.0, 100.0, 100.0))),
+                InsertLocation::AtCurrentLayer,
+            )
+            .unwrap();
+        assert_eq!(layer_info.widget_tree_depth(), 1);
+
+        layer_info
+            .add_widget(
+                Box::new(Chatbox::new(font_info, 5)),
+                InsertLocation::ToNestedContainer(&pane_id),
+            )
+            .unwrap();
+        assert_eq!(layer_info.widget_tree_depth(), 2);
+    }
 }
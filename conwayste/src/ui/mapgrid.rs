@@ -0,0 +1,281 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ggez::graphics::{self, Color, DrawParam, Mesh, Rect};
+use ggez::input::mouse::{CursorIcon, MouseButton};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use conway::universe::Region;
+
+use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, UIContext};
+use super::{batch::ShapeBatch, textcache::TextCache, widget::Widget, UIError, UIResult};
+
+use crate::constants::colors::*;
+use crate::room_map::{self, MapTool, RoomMapEditor};
+
+/// The room's map in the room lobby, which draws its walls, spawn regions and fog-free regions and
+/// is drawn on with the map editor's tool. The editor is shared with the buttons beside the map,
+/// which pick the tool.
+pub struct MapGrid {
+    id:           Option<NodeId>,
+    z_index:      usize,
+    dimensions:   Rect,
+    handler_data: HandlerData, // required for impl_emit_event!
+    editor:       Arc<Mutex<RoomMapEditor>>,
+    drag:         Option<((usize, usize), (usize, usize))>, // (column, row) where it started, and where it is now
+    walls_mesh:   Option<(u64, Rect, Color, Option<Mesh>)>, // walls revision, dimensions and color it was built for
+}
+
+impl fmt::Debug for MapGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MapGrid {{ id: {:?}, z_index: {}, dimensions: {:?} }}",
+            self.id, self.z_index, self.dimensions
+        )
+    }
+}
+
+impl MapGrid {
+    /// Creates a MapGrid for `editor`. The cells are square, as large as fit in `dimensions`.
+    pub fn new(editor: Arc<Mutex<RoomMapEditor>>, dimensions: Rect) -> Self {
+        let mut grid = MapGrid {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            handler_data: HandlerData::new(),
+            editor,
+            drag: None,
+            walls_mesh: None,
+        };
+        // unwraps OK b/c not being called within handler
+        grid.on(EventType::MouseButtonHeld, Box::new(MapGrid::mouse_handler))
+            .unwrap();
+        grid.on(EventType::Drag, Box::new(MapGrid::mouse_handler)).unwrap();
+        grid.on(EventType::Click, Box::new(MapGrid::mouse_handler)).unwrap();
+        grid
+    }
+
+    fn cell_size(&self, editor: &RoomMapEditor) -> f32 {
+        (self.dimensions.w / editor.width() as f32).min(self.dimensions.h / editor.height() as f32)
+    }
+
+    /// The (column, row) of the cell at `point`, or the nearest one if it's off the map.
+    fn cell_at(&self, editor: &RoomMapEditor, point: Point2<f32>) -> (usize, usize) {
+        let size = self.cell_size(editor);
+        let col = ((point.x - self.dimensions.x) / size).max(0.0) as usize;
+        let row = ((point.y - self.dimensions.y) / size).max(0.0) as usize;
+        (col.min(editor.width() - 1), row.min(editor.height() - 1))
+    }
+
+    fn region_rect(&self, size: f32, region: &Region) -> Rect {
+        Rect::new(
+            self.dimensions.x + region.left() as f32 * size,
+            self.dimensions.y + region.top() as f32 * size,
+            region.width() as f32 * size,
+            region.height() as f32 * size,
+        )
+    }
+
+    /// Walls are painted and erased as the mouse moves; spawn and fog-free regions are set once it's
+    /// released.
+    fn mouse_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let grid = obj.downcast_mut::<MapGrid>().unwrap(); // unwrap OK because this will always be MapGrid
+        if event.button != Some(MouseButton::Left) {
+            return Ok(Handled::NotHandled);
+        }
+        let mut editor = grid.editor.lock().unwrap();
+        let cell = grid.cell_at(&editor, event.point.unwrap()); // unwrap OK because mouse events always have a point
+        let drawing_freehand = editor.tool == MapTool::Wall || editor.tool == MapTool::Erase;
+
+        match event.what {
+            EventType::MouseButtonHeld => {
+                grid.drag = Some((cell, cell));
+                if drawing_freehand {
+                    editor.use_tool(cell, cell);
+                }
+            }
+            EventType::Drag => {
+                if let Some((_, ref mut last)) = grid.drag {
+                    if drawing_freehand {
+                        editor.use_tool(*last, cell);
+                    }
+                    *last = cell;
+                }
+            }
+            _ => {
+                // released
+                if let Some((start, _)) = grid.drag.take() {
+                    if !drawing_freehand {
+                        editor.use_tool(start, cell);
+                    }
+                }
+            }
+        }
+        Ok(Handled::Handled)
+    }
+}
+
+impl Widget for MapGrid {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of MapGrid {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of MapGrid {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        let editor = self.editor.lock().unwrap();
+        let size = self.cell_size(&editor);
+        let map_rect = Rect::new(
+            self.dimensions.x,
+            self.dimensions.y,
+            size * editor.width() as f32,
+            size * editor.height() as f32,
+        );
+
+        let mut background = ShapeBatch::new();
+        background.fill_rect(map_rect, scheme().chat_pane_fill);
+        background.stroke_rect(map_rect, 1.0, scheme().editor_grid);
+        background.draw(ctx)?;
+
+        // The walls can cover thousands of cells, so their mesh is only rebuilt once they change,
+        // with a rectangle for each run of walls along a row
+        let revision = editor.walls_revision();
+        let wall_color = scheme().map_wall;
+        let is_stale = match self.walls_mesh {
+            Some((built_revision, built_dimensions, built_color, _)) => {
+                built_revision != revision || built_dimensions != self.dimensions || built_color != wall_color
+            }
+            None => true,
+        };
+        if is_stale {
+            let mut walls = ShapeBatch::new();
+            for row in 0..editor.height() {
+                let mut col = 0;
+                while col < editor.width() {
+                    let start = col;
+                    while col < editor.width() && editor.is_wall(col, row) {
+                        col += 1;
+                    }
+                    if col > start {
+                        let run = Region::new(start as isize, row as isize, col - start, 1);
+                        walls.fill_rect(self.region_rect(size, &run), wall_color);
+                    } else {
+                        col += 1;
+                    }
+                }
+            }
+            self.walls_mesh = Some((revision, self.dimensions, wall_color, walls.build(ctx)?));
+        }
+        if let Some((_, _, _, Some(ref mesh))) = self.walls_mesh {
+            graphics::draw(ctx, mesh, DrawParam::default())?;
+        }
+
+        let mut overlay = ShapeBatch::new();
+        for region in editor.spawn_regions().iter().flatten() {
+            overlay.stroke_rect(self.region_rect(size, region), 2.0, scheme().map_spawn_region);
+        }
+        for region in editor.fog_free_regions().iter().flatten() {
+            overlay.stroke_rect(self.region_rect(size, region), 2.0, scheme().map_fog_free_region);
+        }
+
+        // Where the spawn or fog-free region being dragged out will go
+        if let Some((start, end)) = self.drag {
+            if editor.tool == MapTool::Spawn || editor.tool == MapTool::FogFree {
+                let rect = self.region_rect(size, &room_map::dragged_region(start, end));
+                overlay.fill_rect(rect, scheme().editor_preview);
+            }
+        }
+        overlay.draw(ctx)
+    }
+
+    /// convert to EmitEvent
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Crosshair)
+    }
+}
+
+impl_emit_event!(MapGrid, self.handler_data);
+widget_from_id!(MapGrid);
@@ -17,37 +17,75 @@
  *  <http://www.gnu.org/licenses/>. */
 
 extern crate chromatica;
-extern crate env_logger;
 extern crate ggez;
 
 #[macro_use]
 pub(crate) mod common;
 #[macro_use]
 pub(crate) mod context;
+mod batch;
 mod button;
 mod chatbox;
 mod checkbox;
+mod collapsible;
+mod colorpicker;
+mod contextmenu;
+mod cursor;
+mod dropdown;
 mod focus;
+mod form;
 mod gamearea;
+mod gesture;
+#[cfg(test)]
+mod harness;
+mod image;
+mod inspector;
 mod label;
 mod layer;
+mod mapgrid;
 mod pane;
+mod patterngrid;
+mod playerlist;
+mod scrollpane;
+mod slider;
+mod tabcontainer;
+mod textcache;
 mod textfield;
+mod timer;
 mod treeview;
 pub(crate) mod ui_errors;
+mod validator;
 mod widget;
+mod wrap;
 
-pub use button::Button;
-pub use chatbox::{Chatbox, ChatboxPublishHandle};
-pub use checkbox::Checkbox;
-pub use common::{center, color_with_alpha, draw_text, intersection, point_offset, within_widget};
+pub use button::{Button, ButtonStyle};
+pub use chatbox::{Chatbox, ChatboxPublishHandle, SenderAction};
+pub use checkbox::{CheckState, Checkbox};
+pub use collapsible::Collapsible;
+pub use colorpicker::ColorPicker;
+pub use common::{
+    center, color_with_alpha, draw_text, intersection, point_offset, set_min_font_size, text_size, within_widget,
+};
 pub use context::{EmitEvent, Event, EventType, UIContext};
+pub use cursor::CursorManager;
+pub use dropdown::Dropdown;
+pub use form::{Form, FormBuilder, FormHandle};
 pub use gamearea::{GameArea, GameAreaState};
-pub use label::Label;
+pub use image::{Image, Scaling};
+pub use inspector::WidgetInspector;
+pub use label::{Alignment, Label};
 pub use layer::{InsertLocation, Layering};
+pub use mapgrid::MapGrid;
 pub use pane::Pane;
-pub use textfield::TextField;
+pub use patterngrid::PatternGrid;
+pub use playerlist::PlayerList;
+pub use scrollpane::ScrollPane;
+pub use slider::Slider;
+pub use tabcontainer::TabContainer;
+pub use textfield::{Completer, TextField};
+pub use timer::TimerId;
 pub use ui_errors::{UIError, UIResult};
+pub use validator::Validator;
 pub use widget::Widget;
 
 type BoxedWidget = Box<dyn Widget>;
@@ -19,25 +19,30 @@
 use std::error::Error;
 use std::fmt;
 
-use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
+use ggez::graphics::{Color, Rect};
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
-use enum_iterator::IntoEnumIterator;
 use id_tree::NodeId;
 
 use super::{
+    batch::ShapeBatch,
     common::within_widget,
     context,
     focus::{CycleType, FocusCycle},
-    widget::Widget,
+    textcache::TextCache,
+    widget::{Container, Widget},
     UIError, UIResult,
 };
 
 use ggez::input::keyboard::KeyCode;
 
-use context::{EmitEvent, Event, EventType, Handled, UIContext};
+use context::{
+    EmitEvent, Event, EventType, Handled, Handler, Phase, UIContext, BROADCASTED_EVENTS, DISPATCH_PRIORITY, KEY_EVENTS,
+    MOUSE_EVENTS,
+};
 
+use crate::accessibility;
 use crate::constants::colors::*;
 
 pub struct Pane {
@@ -82,20 +87,22 @@ impl Pane {
             handler_data: context::HandlerData::new(),
         };
 
-        // for each event type, auto-register some of the common event types to a common handler
-        for event_type in EventType::into_enum_iter() {
-            if event_type.is_mouse_event() {
-                // unwrap OK because we aren't calling from within a handler
-                pane.on(event_type, Box::new(Pane::mouse_event_handler)).unwrap();
-            } else if event_type.is_key_event() {
-                // unwrap OK because we aren't calling from within a handler
-                pane.on(event_type, Box::new(Pane::key_press_handler)).unwrap();
-            }
-
-            if event_type.is_broadcast_event() {
-                // unwrap OK because not called within handler
-                pane.on(event_type, Box::new(Pane::broadcast_handler)).unwrap();
-            }
+        // for each event type, auto-register some of the common event types to a common handler,
+        // which passes them on to the nested widgets between the capture and bubble phases
+        let mut handlers: Vec<(EventType, Handler)> = vec![];
+        for &event_type in MOUSE_EVENTS {
+            handlers.push((event_type, Box::new(Pane::mouse_event_handler)));
+        }
+        for &event_type in KEY_EVENTS {
+            handlers.push((event_type, Box::new(Pane::key_press_handler)));
+        }
+        for &event_type in BROADCASTED_EVENTS {
+            handlers.push((event_type, Box::new(Pane::broadcast_handler)));
+        }
+        for (event_type, handler) in handlers {
+            // unwrap OK because we aren't calling from within a handler
+            pane.on_with_priority(event_type, Phase::Capture, DISPATCH_PRIORITY, handler)
+                .unwrap();
         }
 
         pane.on(EventType::GainFocus, Box::new(Pane::gain_focus_handler))
@@ -183,16 +190,19 @@ impl Pane {
             let opt_widget = opt_child_id
                 .as_ref()
                 .map(|child_id| uictx.widget_view.get(child_id).unwrap().data());
-            if opt_child_id.is_some() && opt_widget.unwrap().downcast_ref::<Pane>().is_some() {
-                // there is a focused child pane
+            let child_takes_tab = opt_widget.map_or(false, |widget| {
+                widget.as_container().is_some() || (!event.shift_pressed() && widget.takes_tab())
+            });
+            if child_takes_tab {
+                // there is a focused child container, or a widget that uses Tab itself
 
                 let child_id = opt_child_id.unwrap();
-                let pane_events = Pane::emit_keyboard_event(event, uictx, &child_id)?;
+                let (_, pane_events) = Pane::emit_keyboard_event(event, uictx, &child_id)?;
 
-                pane.handle_events_from_child(uictx, &pane_events[..], event.shift_pressed)?;
+                pane.handle_events_from_child(uictx, &pane_events[..], event.shift_pressed())?;
             } else {
                 // either no focused child widget, or there is but it's not a Pane
-                if event.shift_pressed {
+                if event.shift_pressed() {
                     pane.focus_cycle.focus_previous();
                 } else {
                     pane.focus_cycle.focus_next();
@@ -219,15 +229,19 @@ impl Pane {
                 let event = Event::new_child_released_focus();
                 uictx.child_event(event);
             }
+            Ok(Handled::Handled)
         } else {
-            // regular key press logic (no focus changes)
+            // regular key press logic (no focus changes); bubble handlers on this pane get the key
+            // if the focused child didn't handle it
             let focused_id = pane.focus_cycle.focused_widget_id();
             if let Some(id) = focused_id {
-                let pane_events = Pane::emit_keyboard_event(event, uictx, id)?;
-                pane.handle_events_from_child(uictx, &pane_events[..], event.shift_pressed)?;
+                let (handled, pane_events) = Pane::emit_keyboard_event(event, uictx, id)?;
+                pane.handle_events_from_child(uictx, &pane_events[..], event.shift_pressed())?;
+                Ok(handled)
+            } else {
+                Ok(Handled::NotHandled)
             }
         }
-        Ok(Handled::Handled)
     }
 
     fn mouse_event_handler(
@@ -236,6 +250,7 @@ impl Pane {
         event: &Event,
     ) -> Result<Handled, Box<dyn Error>> {
         let mut child_events = vec![];
+        let mut handled_by_child = Handled::NotHandled;
         // Unwrap OK because we are guaranteed a Pane widget
         let pane = downcast_widget!(obj, Pane).unwrap();
 
@@ -254,6 +269,7 @@ impl Pane {
                     }
 
                     if handled == Handled::Handled {
+                        handled_by_child = handled;
                         break;
                     }
                 } else {
@@ -276,7 +292,8 @@ impl Pane {
             }
         }
 
-        Ok(Handled::NotHandled)
+        // bubble handlers on this pane run only if no child handled the event
+        Ok(handled_by_child)
     }
 
     fn handle_events_from_child(
@@ -315,23 +332,24 @@ impl Pane {
         Ok(())
     }
 
-    /// Forward this keyboard event to the specified child widget.
+    /// Forward this keyboard event to the specified child widget. Returns whether it handled the
+    /// event, and the events it sent to this pane.
     fn emit_keyboard_event(
         event: &context::Event,
         uictx: &mut UIContext,
         focused_id: &NodeId,
-    ) -> Result<Vec<Event>, Box<dyn Error>> {
+    ) -> Result<(Handled, Vec<Event>), Box<dyn Error>> {
         let (widget_ref, mut subuictx) = uictx.derive(&focused_id).unwrap(); // unwrap OK b/c NodeId valid & in view
         if let Some(emittable) = widget_ref.as_emit_event() {
             return emittable
                 .emit(event, &mut subuictx)
-                .map(|_| subuictx.collect_child_events());
+                .map(|handled| (handled, subuictx.collect_child_events()));
         } else {
             // We probably won't ever get here due to the FocusCycle only holding widgets that can
             // receive keyboard events.
             debug!("nothing to emit on; widget is not an EmitEvent");
         }
-        Ok(vec![])
+        Ok((Handled::NotHandled, vec![]))
     }
 
     /// Emit a GainFocus or LoseFocus event on the specified child widget.
@@ -345,6 +363,11 @@ impl Pane {
             return Err(format!("Unexpected event type passed to Pane::emit_focus_change: {:?}", what).into());
         }
         let (widget_ref, mut subuictx) = uictx.derive(&focused_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+        if what == EventType::GainFocus {
+            if let Some(name) = widget_ref.accessible_name() {
+                accessibility::announce(name);
+            }
+        }
         if let Some(emittable) = widget_ref.as_emit_event() {
             let event = Event::new_gain_or_lose_focus(what);
             emittable.emit(&event, &mut subuictx)?;
@@ -543,18 +566,20 @@ impl Widget for Pane {
     }
     */
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        let mut batch = ShapeBatch::new();
+        self.batch_shapes(&mut batch);
+        batch.draw(ctx)
+    }
+
+    fn batch_shapes(&self, batch: &mut ShapeBatch) -> bool {
         if let Some(bg_color) = self.bg_color {
-            let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), self.dimensions, bg_color)?;
-            graphics::draw(ctx, &mesh, DrawParam::default())?;
+            batch.fill_rect(self.dimensions, bg_color);
         }
-
         if self.border > 0.0 {
-            let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), self.dimensions, *PANE_BORDER_COLOR)?;
-            graphics::draw(ctx, &mesh, DrawParam::default())?;
+            batch.stroke_rect(self.dimensions, 1.0, scheme().pane_border);
         }
-
-        Ok(())
+        true
     }
 
     /// convert to EmitEvent
@@ -566,6 +591,38 @@ impl Widget for Pane {
     fn accepts_keyboard_events(&self) -> bool {
         true
     }
+
+    fn as_container(&self) -> Option<&dyn Container> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn Container> {
+        Some(self)
+    }
+}
+
+impl Container for Pane {
+    /// Widgets in a Pane are placed at an offset from its top-left corner.
+    fn child_origin(&self) -> Vector2<f32> {
+        let point = self.dimensions.point();
+        Vector2 { x: point.x, y: point.y }
+    }
+
+    /// Widgets that accept keyboard events go in the Pane's focus cycle.
+    fn child_added(&mut self, widget_id: NodeId, _size: (f32, f32), accepts_keyboard_events: bool) -> bool {
+        if accepts_keyboard_events {
+            self.add_widget(widget_id);
+        }
+        accepts_keyboard_events
+    }
+
+    fn child_removed(&mut self, widget_id: &NodeId) {
+        self.remove_widget(widget_id);
+    }
+
+    fn focus_cycle_mut(&mut self) -> Option<&mut FocusCycle> {
+        Some(&mut self.focus_cycle)
+    }
 }
 
 widget_from_id!(Pane);
@@ -0,0 +1,300 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ggez::graphics::{self, DrawMode, DrawParam, MeshBuilder, Rect};
+use ggez::input::mouse::{CursorIcon, MouseButton};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, UIContext};
+use super::{textcache::TextCache, widget::Widget, UIError, UIResult};
+
+use crate::constants::colors::*;
+use crate::pattern_editor::{self, PatternEditor, Tool, EDITOR_HEIGHT, EDITOR_WIDTH};
+
+const PREVIEW_GENS_PER_SECOND: f64 = 8.0;
+
+/// The grid of the pattern editor, which draws the pattern and is drawn on with the editor's tool.
+/// The editor is shared with the buttons around the grid, which pick the tool and run the preview.
+pub struct PatternGrid {
+    id:           Option<NodeId>,
+    z_index:      usize,
+    dimensions:   Rect,
+    handler_data: HandlerData, // required for impl_emit_event!
+    editor:       Arc<Mutex<PatternEditor>>,
+    drag:         Option<((usize, usize), (usize, usize))>, // (column, row) where it started, and where it is now
+    gens_owed:    f64,                                      // fraction of a generation carried over to the next frame
+}
+
+impl fmt::Debug for PatternGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PatternGrid {{ id: {:?}, z_index: {}, dimensions: {:?} }}",
+            self.id, self.z_index, self.dimensions
+        )
+    }
+}
+
+impl PatternGrid {
+    /// Creates a PatternGrid for `editor`. The cells are square, as large as fit in `dimensions`.
+    pub fn new(editor: Arc<Mutex<PatternEditor>>, dimensions: Rect) -> Self {
+        let mut grid = PatternGrid {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            handler_data: HandlerData::new(),
+            editor,
+            drag: None,
+            gens_owed: 0.0,
+        };
+        // unwraps OK b/c not being called within handler
+        grid.on(EventType::MouseButtonHeld, Box::new(PatternGrid::mouse_handler))
+            .unwrap();
+        grid.on(EventType::Drag, Box::new(PatternGrid::mouse_handler)).unwrap();
+        grid.on(EventType::Click, Box::new(PatternGrid::mouse_handler)).unwrap();
+        grid.on(EventType::Update, Box::new(PatternGrid::update_handler))
+            .unwrap();
+        grid
+    }
+
+    fn cell_size(&self) -> f32 {
+        (self.dimensions.w / EDITOR_WIDTH as f32).min(self.dimensions.h / EDITOR_HEIGHT as f32)
+    }
+
+    /// The (column, row) of the cell at `point`, or the nearest one if it's off the grid.
+    fn cell_at(&self, point: Point2<f32>) -> (usize, usize) {
+        let size = self.cell_size();
+        let col = ((point.x - self.dimensions.x) / size).max(0.0) as usize;
+        let row = ((point.y - self.dimensions.y) / size).max(0.0) as usize;
+        (col.min(EDITOR_WIDTH - 1), row.min(EDITOR_HEIGHT - 1))
+    }
+
+    fn cell_rect(&self, col: usize, row: usize) -> Rect {
+        let size = self.cell_size();
+        Rect::new(
+            self.dimensions.x + col as f32 * size,
+            self.dimensions.y + row as f32 * size,
+            size,
+            size,
+        )
+    }
+
+    /// The pencil and eraser work as the mouse moves; lines and rectangles once it's released.
+    fn mouse_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let grid = obj.downcast_mut::<PatternGrid>().unwrap(); // unwrap OK because this will always be PatternGrid
+        if event.button != Some(MouseButton::Left) {
+            return Ok(Handled::NotHandled);
+        }
+        let cell = grid.cell_at(event.point.unwrap()); // unwrap OK because mouse events always have a point
+        let mut editor = grid.editor.lock().unwrap();
+        let drawing_freehand = editor.tool == Tool::Pencil || editor.tool == Tool::Eraser;
+
+        match event.what {
+            EventType::MouseButtonHeld => {
+                grid.drag = Some((cell, cell));
+                if drawing_freehand {
+                    editor.apply_tool(cell, cell);
+                }
+            }
+            EventType::Drag => {
+                if let Some((_, ref mut last)) = grid.drag {
+                    if drawing_freehand {
+                        editor.apply_tool(*last, cell);
+                    }
+                    *last = cell;
+                }
+            }
+            _ => {
+                // released
+                if let Some((start, _)) = grid.drag.take() {
+                    if !drawing_freehand {
+                        editor.apply_tool(start, cell);
+                    }
+                }
+            }
+        }
+        Ok(Handled::Handled)
+    }
+
+    fn update_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        _event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let grid = obj.downcast_mut::<PatternGrid>().unwrap(); // unwrap OK because this will always be PatternGrid
+        let mut editor = grid.editor.lock().unwrap();
+        if !editor.running {
+            grid.gens_owed = 0.0;
+            return Ok(Handled::NotHandled);
+        }
+        grid.gens_owed += PREVIEW_GENS_PER_SECOND * uictx.frame_delta().as_secs_f64();
+        while grid.gens_owed >= 1.0 {
+            editor.step();
+            grid.gens_owed -= 1.0;
+        }
+        uictx.request_redraw();
+        Ok(Handled::NotHandled)
+    }
+}
+
+impl Widget for PatternGrid {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of PatternGrid {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of PatternGrid {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        let editor = self.editor.lock().unwrap();
+        let size = self.cell_size();
+        let grid_rect = Rect::new(
+            self.dimensions.x,
+            self.dimensions.y,
+            size * EDITOR_WIDTH as f32,
+            size * EDITOR_HEIGHT as f32,
+        );
+
+        let mut builder = MeshBuilder::new();
+        builder.rectangle(DrawMode::fill(), grid_rect, scheme().chat_pane_fill)?;
+        for col in 0..=EDITOR_WIDTH {
+            let x = grid_rect.x + col as f32 * size;
+            builder.line(
+                &[
+                    Point2 { x, y: grid_rect.y },
+                    Point2 {
+                        x,
+                        y: grid_rect.bottom(),
+                    },
+                ],
+                1.0,
+                scheme().editor_grid,
+            )?;
+        }
+        for row in 0..=EDITOR_HEIGHT {
+            let y = grid_rect.y + row as f32 * size;
+            builder.line(
+                &[
+                    Point2 { x: grid_rect.x, y },
+                    Point2 {
+                        x: grid_rect.right(),
+                        y,
+                    },
+                ],
+                1.0,
+                scheme().editor_grid,
+            )?;
+        }
+        for row in 0..EDITOR_HEIGHT {
+            for col in 0..EDITOR_WIDTH {
+                if editor.is_alive(col, row) {
+                    builder.rectangle(DrawMode::fill(), self.cell_rect(col, row), scheme().editor_cell)?;
+                }
+            }
+        }
+
+        // Where the line or rectangle being dragged out will go
+        if let Some((start, end)) = self.drag {
+            let cells = match editor.tool {
+                Tool::Line => pattern_editor::line_cells(start, end),
+                Tool::Rectangle => pattern_editor::rectangle_cells(start, end),
+                _ => vec![],
+            };
+            for (col, row) in cells {
+                builder.rectangle(DrawMode::fill(), self.cell_rect(col, row), scheme().editor_preview)?;
+            }
+        }
+
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())
+    }
+
+    /// convert to EmitEvent
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Crosshair)
+    }
+}
+
+impl_emit_event!(PatternGrid, self.handler_data);
+widget_from_id!(PatternGrid);
@@ -0,0 +1,356 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::event::MouseButton;
+use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::{
+    chatbox::{sender_menu_items, SenderAction, SENDER_ACTIONS},
+    common::FontInfo,
+    context::{EmitEvent, Event, EventType, Handled, HandlerData, MoveCross, UIContext},
+    contextmenu::ContextMenu,
+    textcache::TextCache,
+    widget::Widget,
+    UIError, UIResult,
+};
+
+use crate::constants::{self, colors::*};
+
+/// The names of the other players in the room, one to a line. Right-clicking or pressing and
+/// holding on one opens the same menu as a chat message's sender, and what is picked from it waits
+/// for the client in `take_actions`. Names that don't fit are left off the bottom.
+pub struct PlayerList {
+    id:           Option<NodeId>,
+    z_index:      usize,
+    font_info:    FontInfo,
+    dimensions:   Rect,
+    players:      Vec<String>,
+    menu:         Option<(String, ContextMenu)>, // (player, menu of what can be done to them)
+    actions:      Vec<(SenderAction, String)>,   // chosen from the menu, but not yet taken by the client
+    handler_data: HandlerData,
+}
+
+impl fmt::Debug for PlayerList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PlayerList {{ id: {:?}, z-index: {}, Dimensions: {:?}, Players: {:?} }}",
+            self.id, self.z_index, self.dimensions, self.players
+        )
+    }
+}
+
+impl PlayerList {
+    pub fn new(font_info: FontInfo, dimensions: Rect) -> Self {
+        let mut list = PlayerList {
+            id: None,
+            z_index: std::usize::MAX,
+            font_info,
+            dimensions,
+            players: vec![],
+            menu: None,
+            actions: vec![],
+            handler_data: HandlerData::new(),
+        };
+        list.on(EventType::Click, Box::new(PlayerList::click_handler)).unwrap(); // unwrap OK b/c not being called within handler
+        list.on(EventType::LongPress, Box::new(PlayerList::long_press_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        list.on(EventType::MouseMove, Box::new(PlayerList::mouse_move_handler))
+            .unwrap(); // unwrap OK b/c not being called within handler
+        list
+    }
+
+    /// Replaces the names listed, closing the menu if its player is no longer among them.
+    pub fn set_players(&mut self, players: Vec<String>) {
+        if let Some((ref player, _)) = self.menu {
+            if !players.contains(player) {
+                self.menu = None;
+            }
+        }
+        self.players = players;
+    }
+
+    /// Returns the actions chosen from the menu since the last call, oldest first.
+    pub fn take_actions(&mut self) -> Vec<(SenderAction, String)> {
+        std::mem::take(&mut self.actions)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.font_info.char_dimensions.y + constants::CHATBOX_LINE_SPACING
+    }
+
+    /// The player whose name is drawn at `point`, if any.
+    fn player_at(&self, point: Point2<f32>) -> Option<&str> {
+        if !self.dimensions.contains(point) {
+            return None;
+        }
+        let line = (point.y - self.dimensions.y - constants::CHATBOX_LINE_SPACING) / self.line_height();
+        if line < 0.0 {
+            return None;
+        }
+        self.players.get(line as usize).map(|player| player.as_str())
+    }
+
+    /// Opens the menu of what can be done to `player` at `point`, moved if need be to fit in the
+    /// list, since clicks outside it don't reach the list.
+    fn open_menu(&mut self, player: String, point: Point2<f32>) {
+        let mut menu = ContextMenu::new(sender_menu_items(), point, self.font_info);
+        let rect = menu.rect();
+        menu.move_to(Point2 {
+            x: rect.x.min(self.dimensions.right() - rect.w).max(self.dimensions.x),
+            y: rect.y.min(self.dimensions.bottom() - rect.h).max(self.dimensions.y),
+        });
+        self.menu = Some((player, menu));
+    }
+
+    fn click_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let list = obj.downcast_mut::<PlayerList>().unwrap(); // unwrap OK because it's always a PlayerList
+        let point = event.point.unwrap(); // unwrap OK because a Click always has a point
+
+        // Any click closes the menu, but only one on an item does something
+        if let Some((player, menu)) = list.menu.take() {
+            if let Some(item) = menu.item_at(point) {
+                list.actions.push((SENDER_ACTIONS[item], player));
+            }
+            return Ok(Handled::Handled);
+        }
+        if event.button == Some(MouseButton::Right) {
+            if let Some(player) = list.player_at(point) {
+                let player = player.to_owned();
+                list.open_menu(player, point);
+                return Ok(Handled::Handled);
+            }
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    /// Pressing and holding on a name opens the same menu as right-clicking it.
+    fn long_press_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let list = obj.downcast_mut::<PlayerList>().unwrap(); // unwrap OK because it's always a PlayerList
+        let point = event.point.unwrap(); // unwrap OK because a LongPress always has a point
+        if list.menu.is_some() {
+            return Ok(Handled::NotHandled);
+        }
+        if let Some(player) = list.player_at(point) {
+            let player = player.to_owned();
+            list.open_menu(player, point);
+            return Ok(Handled::Handled);
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn mouse_move_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let list = obj.downcast_mut::<PlayerList>().unwrap(); // unwrap OK because it's always a PlayerList
+        if event.move_did_cross(list.dimensions) == MoveCross::Exit {
+            list.menu = None;
+        }
+        Ok(Handled::NotHandled)
+    }
+}
+
+impl Widget for PlayerList {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of PlayerList {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        self.menu = None;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+        self.menu = None;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of PlayerList {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        self.menu = None;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+        self.menu = None;
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let border = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::stroke(constants::CHATBOX_BORDER_PIXELS),
+            self.dimensions,
+            scheme().pane_border,
+        )?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        let rows = ((self.dimensions.h - constants::CHATBOX_LINE_SPACING) / self.line_height()) as usize;
+        for (i, player) in self.players.iter().take(rows).enumerate() {
+            let text = texts.get(self.font_info.fragment(player.as_str()).color(scheme().chatbox_text));
+            let point = Point2 {
+                x: self.dimensions.x + self.font_info.char_dimensions.x,
+                y: self.dimensions.y + constants::CHATBOX_LINE_SPACING + i as f32 * self.line_height(),
+            };
+            graphics::draw(ctx, &*text, DrawParam::default().dest(point))?;
+        }
+
+        if let Some((_, ref menu)) = self.menu {
+            menu.draw(ctx)?;
+        }
+        Ok(())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+}
+
+impl_emit_event!(PlayerList, self.handler_data);
+widget_from_id!(PlayerList);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        harness::{dummy_font_info, Harness},
+        InsertLocation,
+    };
+    use super::*;
+    use crate::Screen;
+
+    fn add_list(harness: &mut Harness) -> NodeId {
+        let mut list = PlayerList::new(dummy_font_info(), Rect::new(0.0, 0.0, 100.0, 60.0));
+        list.set_players(vec!["alice".to_owned(), "bob".to_owned()]);
+        harness.add_widget(Box::new(list), InsertLocation::AtCurrentLayer)
+    }
+
+    fn list_mut<'a>(harness: &'a mut Harness, list_id: &NodeId) -> &'a mut PlayerList {
+        PlayerList::widget_from_id_mut(&mut harness.layering, list_id).unwrap()
+    }
+
+    #[test]
+    fn test_player_at_finds_the_name_under_point() {
+        let mut harness = Harness::new(Screen::InRoom);
+        let list_id = add_list(&mut harness);
+        let list = list_mut(&mut harness, &list_id);
+        // Lines are 5 pixels tall plus spacing, below the spacing at the top
+        let line = list.line_height();
+        let at = |y| Point2 { x: 10.0, y };
+        let top = constants::CHATBOX_LINE_SPACING;
+        assert_eq!(list.player_at(at(top + 1.0)), Some("alice"));
+        assert_eq!(list.player_at(at(top + line + 1.0)), Some("bob"));
+        assert_eq!(list.player_at(at(top + line * 2.0 + 1.0)), None);
+        assert_eq!(list.player_at(Point2 { x: 110.0, y: top + 1.0 }), None);
+    }
+
+    #[test]
+    fn test_right_clicking_a_name_and_picking_an_item_queues_the_action() {
+        let mut harness = Harness::new(Screen::InRoom);
+        let list_id = add_list(&mut harness);
+        let y = constants::CHATBOX_LINE_SPACING + list_mut(&mut harness, &list_id).line_height() + 1.0;
+        harness.right_click(10.0, y).unwrap();
+        let menu_rect = {
+            let list = list_mut(&mut harness, &list_id);
+            let (ref player, ref menu) = *list.menu.as_ref().unwrap();
+            assert_eq!(player, "bob");
+            menu.rect()
+        };
+        assert!(menu_rect.bottom() <= 60.0); // in the list, where clicks reach it
+
+        // Report, the last item
+        harness.click(menu_rect.x + 1.0, menu_rect.bottom() - 1.0).unwrap();
+        let list = list_mut(&mut harness, &list_id);
+        assert!(list.menu.is_none());
+        assert_eq!(list.take_actions(), vec![(SenderAction::Report, "bob".to_owned())]);
+        assert!(list.take_actions().is_empty());
+    }
+
+    #[test]
+    fn test_left_clicking_a_name_opens_nothing() {
+        let mut harness = Harness::new(Screen::InRoom);
+        let list_id = add_list(&mut harness);
+        harness.click(10.0, constants::CHATBOX_LINE_SPACING + 1.0).unwrap();
+        assert!(list_mut(&mut harness, &list_id).menu.is_none());
+    }
+
+    #[test]
+    fn test_menu_closes_when_its_player_leaves() {
+        let mut harness = Harness::new(Screen::InRoom);
+        let list_id = add_list(&mut harness);
+        harness.right_click(10.0, constants::CHATBOX_LINE_SPACING + 1.0).unwrap();
+        let list = list_mut(&mut harness, &list_id);
+        list.set_players(vec!["alice".to_owned()]);
+        assert!(list.menu.is_some());
+        list.set_players(vec!["bob".to_owned()]);
+        assert!(list.menu.is_none());
+    }
+}
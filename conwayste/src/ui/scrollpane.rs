@@ -0,0 +1,618 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::graphics::{Color, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::{
+    batch::ShapeBatch,
+    common::within_widget,
+    context::{self, EmitEvent, Event, EventType, Handled, KeyCodeOrChar, UIContext},
+    textcache::TextCache,
+    widget::{Container, Widget},
+    UIError, UIResult,
+};
+
+use crate::constants::{colors::*, SCROLLBAR_WIDTH, SCROLL_PANE_STEP};
+
+const MIN_THUMB_LENGTH: f32 = 20.0; // so that the thumb of a long list can still be grabbed
+
+/// A container that shows part of a bigger widget, its content, which is scrolled with the mouse
+/// wheel (Shift for sideways), the scrollbars, or Page Up and Page Down. The widget added to it with
+/// `InsertLocation::ToNestedContainer` is its content, placed relative to its top-left corner;
+/// usually a `Pane` as big as it needs to be. The layering only draws the content within the
+/// scroll pane. As it scrolls, the content and everything in it is moved, so events reach them
+/// where they are drawn.
+pub struct ScrollPane {
+    id:             Option<NodeId>,
+    z_index:        usize,
+    pub dimensions: Rect,
+    pub bg_color:   Option<Color>,
+    content:        Option<NodeId>,
+    content_size:   Vector2<f32>,
+    offset:         Vector2<f32>,         // how far the content is scrolled, right and down
+    dragging:       Option<(bool, f32)>,  // whether the vertical thumb is held, and where along it
+    handler_data:   context::HandlerData, // required for impl_emit_event!
+}
+
+impl fmt::Debug for ScrollPane {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ScrollPane {{ id: {:?}, z-index: {}, Dimensions: {:?}, Offset: {:?} }}",
+            self.id, self.z_index, self.dimensions, self.offset
+        )
+    }
+}
+
+/// Where the thumb of a scrollbar is along its track, and how long it is, for a content of
+/// `content_len` seen through a view of `view_len` and scrolled by `offset`.
+fn thumb_span(track_start: f32, track_len: f32, content_len: f32, view_len: f32, offset: f32) -> (f32, f32) {
+    let thumb_len = (track_len * view_len / content_len.max(1.0))
+        .max(MIN_THUMB_LENGTH)
+        .min(track_len);
+    let max_offset = (content_len - view_len).max(0.0);
+    let travel = track_len - thumb_len;
+    let start = if max_offset > 0.0 {
+        track_start + travel * offset / max_offset
+    } else {
+        track_start
+    };
+    (start, thumb_len)
+}
+
+/// The offset that puts the thumb at `thumb_start`; the inverse of `thumb_span`.
+fn offset_for_thumb(thumb_start: f32, track_start: f32, track_len: f32, content_len: f32, view_len: f32) -> f32 {
+    let (_, thumb_len) = thumb_span(track_start, track_len, content_len, view_len, 0.0);
+    let travel = track_len - thumb_len;
+    let max_offset = (content_len - view_len).max(0.0);
+    if travel <= 0.0 {
+        return 0.0;
+    }
+    ((thumb_start - track_start) / travel * max_offset)
+        .max(0.0)
+        .min(max_offset)
+}
+
+/// Moves the widgets in the view, and everything in them, by `delta`.
+fn translate_all(uictx: &mut UIContext, delta: Vector2<f32>) -> Result<(), Box<dyn Error>> {
+    for child_id in uictx.widget_view.children_ids() {
+        let (widget_ref, mut subuictx) = uictx.derive(&child_id)?;
+        widget_ref.translate(delta);
+        translate_all(&mut subuictx, delta)?;
+    }
+    Ok(())
+}
+
+impl ScrollPane {
+    pub fn new(dimensions: Rect) -> Self {
+        let mut scroll_pane = ScrollPane {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            bg_color: None,
+            content: None,
+            content_size: Vector2 { x: 0.0, y: 0.0 },
+            offset: Vector2 { x: 0.0, y: 0.0 },
+            dragging: None,
+            handler_data: context::HandlerData::new(),
+        };
+
+        context::forward_to_nested(
+            &mut scroll_pane,
+            ScrollPane::mouse_event_handler,
+            ScrollPane::key_press_handler,
+            ScrollPane::broadcast_handler,
+        );
+
+        scroll_pane
+            .on(EventType::GainFocus, Box::new(ScrollPane::focus_handler))
+            .unwrap(); // unwrap OK
+        scroll_pane
+            .on(EventType::LoseFocus, Box::new(ScrollPane::focus_handler))
+            .unwrap(); // unwrap OK
+
+        scroll_pane
+    }
+
+    /// How far the content is scrolled, right and down.
+    pub fn offset(&self) -> Vector2<f32> {
+        self.offset
+    }
+
+    /// Whether the content is too tall and too wide to be seen all at once, respectively; there is
+    /// a scrollbar for each that is.
+    fn scrollbars(&self) -> (bool, bool) {
+        let (w, h) = (self.dimensions.w, self.dimensions.h);
+        let mut vertical = self.content_size.y > h;
+        let horizontal = self.content_size.x > w - if vertical { SCROLLBAR_WIDTH } else { 0.0 };
+        vertical = vertical || self.content_size.y > h - if horizontal { SCROLLBAR_WIDTH } else { 0.0 };
+        (vertical, horizontal)
+    }
+
+    /// The part of the screen the content is seen through: all of the scroll pane but its
+    /// scrollbars.
+    pub fn viewport(&self) -> Rect {
+        let (vertical, horizontal) = self.scrollbars();
+        let mut viewport = self.dimensions;
+        if vertical {
+            viewport.w -= SCROLLBAR_WIDTH;
+        }
+        if horizontal {
+            viewport.h -= SCROLLBAR_WIDTH;
+        }
+        viewport
+    }
+
+    /// The track of the vertical scrollbar, if there is one.
+    fn vertical_track(&self) -> Option<Rect> {
+        let viewport = self.viewport();
+        match self.scrollbars() {
+            (true, _) => Some(Rect::new(viewport.right(), viewport.y, SCROLLBAR_WIDTH, viewport.h)),
+            _ => None,
+        }
+    }
+
+    /// The track of the horizontal scrollbar, if there is one.
+    fn horizontal_track(&self) -> Option<Rect> {
+        let viewport = self.viewport();
+        match self.scrollbars() {
+            (_, true) => Some(Rect::new(viewport.x, viewport.bottom(), viewport.w, SCROLLBAR_WIDTH)),
+            _ => None,
+        }
+    }
+
+    fn vertical_thumb(&self, track: Rect) -> Rect {
+        let (y, h) = thumb_span(track.y, track.h, self.content_size.y, self.viewport().h, self.offset.y);
+        Rect::new(track.x, y, track.w, h)
+    }
+
+    fn horizontal_thumb(&self, track: Rect) -> Rect {
+        let (x, w) = thumb_span(track.x, track.w, self.content_size.x, self.viewport().w, self.offset.x);
+        Rect::new(x, track.y, w, track.h)
+    }
+
+    /// The furthest the content can be scrolled, right and down.
+    fn max_offset(&self) -> Vector2<f32> {
+        let viewport = self.viewport();
+        Vector2 {
+            x: (self.content_size.x - viewport.w).max(0.0),
+            y: (self.content_size.y - viewport.h).max(0.0),
+        }
+    }
+
+    /// Scrolls the content to `offset`, or as close to it as it goes, moving everything in it.
+    pub fn scroll_to(&mut self, uictx: &mut UIContext, offset: Vector2<f32>) -> Result<(), Box<dyn Error>> {
+        let max_offset = self.max_offset();
+        let offset = Vector2 {
+            x: offset.x.max(0.0).min(max_offset.x),
+            y: offset.y.max(0.0).min(max_offset.y),
+        };
+        let delta = Vector2 {
+            x: self.offset.x - offset.x,
+            y: self.offset.y - offset.y,
+        };
+        self.offset = offset;
+        if delta.x != 0.0 || delta.y != 0.0 {
+            translate_all(uictx, delta)?;
+        }
+        Ok(())
+    }
+
+    fn scroll_by(&mut self, uictx: &mut UIContext, x: f32, y: f32) -> Result<(), Box<dyn Error>> {
+        let offset = Vector2 {
+            x: self.offset.x + x,
+            y: self.offset.y + y,
+        };
+        self.scroll_to(uictx, offset)
+    }
+
+    /// Emits the event on the content, returning whether it handled the event and the events it
+    /// sent to this scroll pane.
+    fn emit_on_content(
+        &mut self,
+        event: &Event,
+        uictx: &mut UIContext,
+    ) -> Result<(Handled, Vec<Event>), Box<dyn Error>> {
+        let content_id = match self.content {
+            Some(ref content_id) => content_id.clone(),
+            None => return Ok((Handled::NotHandled, vec![])),
+        };
+        let (widget_ref, mut subuictx) = uictx.derive(&content_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+        if let Some(emittable) = widget_ref.as_emit_event() {
+            let handled = emittable.emit(event, &mut subuictx)?;
+            return Ok((handled, subuictx.collect_child_events()));
+        }
+        Ok((Handled::NotHandled, vec![]))
+    }
+
+    /// Handles a mouse event on the scrollbars, if it is on one or a thumb is being dragged.
+    fn scrollbar_mouse_event(&mut self, uictx: &mut UIContext, event: &Event) -> Result<Handled, Box<dyn Error>> {
+        let point = event.point.unwrap(); // unwrap OK because mouse events always have a point
+
+        if let Some((vertical, grab)) = self.dragging {
+            match event.what {
+                EventType::Drag => {
+                    if vertical {
+                        let track = self.vertical_track().unwrap_or(self.dimensions);
+                        let view_h = self.viewport().h;
+                        let y = offset_for_thumb(point.y - grab, track.y, track.h, self.content_size.y, view_h);
+                        self.scroll_to(uictx, Vector2 { x: self.offset.x, y })?;
+                    } else {
+                        let track = self.horizontal_track().unwrap_or(self.dimensions);
+                        let view_w = self.viewport().w;
+                        let x = offset_for_thumb(point.x - grab, track.x, track.w, self.content_size.x, view_w);
+                        self.scroll_to(uictx, Vector2 { x, y: self.offset.y })?;
+                    }
+                    return Ok(Handled::Handled);
+                }
+                EventType::Click => {
+                    self.dragging = None;
+                    return Ok(Handled::Handled);
+                }
+                _ => {}
+            }
+        }
+
+        let viewport = self.viewport();
+        if let Some(track) = self.vertical_track().filter(|track| within_widget(&point, track)) {
+            let thumb = self.vertical_thumb(track);
+            if event.what == EventType::MouseButtonHeld && within_widget(&point, &thumb) {
+                self.dragging = Some((true, point.y - thumb.y));
+            } else if event.what == EventType::Click && !within_widget(&point, &thumb) {
+                // a page at a time toward where the track was clicked
+                let page = if point.y < thumb.y { -viewport.h } else { viewport.h };
+                self.scroll_by(uictx, 0.0, page)?;
+            }
+            return Ok(Handled::Handled);
+        }
+        if let Some(track) = self.horizontal_track().filter(|track| within_widget(&point, track)) {
+            let thumb = self.horizontal_thumb(track);
+            if event.what == EventType::MouseButtonHeld && within_widget(&point, &thumb) {
+                self.dragging = Some((false, point.x - thumb.x));
+            } else if event.what == EventType::Click && !within_widget(&point, &thumb) {
+                let page = if point.x < thumb.x { -viewport.w } else { viewport.w };
+                self.scroll_by(uictx, page, 0.0)?;
+            }
+            return Ok(Handled::Handled);
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn mouse_event_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let scroll_pane = obj.downcast_mut::<ScrollPane>().unwrap(); // unwrap OK
+        let point = event.point.unwrap(); // unwrap OK because mouse events always have a point
+
+        if scroll_pane.scrollbar_mouse_event(uictx, event)? == Handled::Handled {
+            return Ok(Handled::Handled);
+        }
+        if !within_widget(&point, &scroll_pane.viewport()) {
+            return Ok(Handled::NotHandled);
+        }
+
+        let (mut handled, child_events) = scroll_pane.emit_on_content(event, uictx)?;
+        for child_event in child_events {
+            match child_event.what {
+                EventType::ChildRequestsFocus | EventType::ChildReleasedFocus => uictx.child_event(child_event),
+                _ => warn!("Unhandled child event in ScrollPane: {:?}", child_event.what),
+            }
+        }
+
+        // the wheel scrolls the content, unless something in it used the wheel itself
+        if event.what == EventType::Scroll && handled == Handled::NotHandled {
+            let delta = event.scroll_delta.unwrap(); // unwrap OK because a Scroll always has a delta
+            let lines = -delta.y * SCROLL_PANE_STEP;
+            if event.shift_pressed() {
+                scroll_pane.scroll_by(uictx, lines, 0.0)?;
+            } else {
+                scroll_pane.scroll_by(uictx, delta.x * SCROLL_PANE_STEP, lines)?;
+            }
+            handled = Handled::Handled;
+        }
+        Ok(handled)
+    }
+
+    fn key_press_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let scroll_pane = obj.downcast_mut::<ScrollPane>().unwrap(); // unwrap OK
+
+        let (handled, child_events) = scroll_pane.emit_on_content(event, uictx)?;
+        for child_event in child_events {
+            if child_event.what == EventType::ChildReleasedFocus {
+                uictx.child_event(child_event);
+            }
+        }
+        if handled == Handled::Handled {
+            return Ok(handled);
+        }
+
+        let page = scroll_pane.viewport().h;
+        match event.key {
+            Some(KeyCodeOrChar::KeyCode(KeyCode::PageUp)) => scroll_pane.scroll_by(uictx, 0.0, -page)?,
+            Some(KeyCodeOrChar::KeyCode(KeyCode::PageDown)) => scroll_pane.scroll_by(uictx, 0.0, page)?,
+            _ => return Ok(Handled::NotHandled),
+        }
+        Ok(Handled::Handled)
+    }
+
+    fn broadcast_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let scroll_pane = obj.downcast_mut::<ScrollPane>().unwrap(); // unwrap OK
+
+        if event.what == EventType::Update {
+            // the content may have grown or shrunk since it was added
+            if let Some(ref content_id) = scroll_pane.content {
+                let (w, h) = uictx.widget_view.get(content_id)?.data().size();
+                let content_size = Vector2 { x: w, y: h };
+                if content_size != scroll_pane.content_size {
+                    scroll_pane.content_size = content_size;
+                    let offset = scroll_pane.offset;
+                    scroll_pane.scroll_to(uictx, offset)?;
+                    uictx.request_redraw();
+                }
+            }
+        }
+        let (_, child_events) = scroll_pane.emit_on_content(event, uictx)?;
+        if child_events.len() != 0 {
+            warn!(
+                "[ScrollPane] expected no {:?} child events to be collected from content; got {:?}",
+                event.what, child_events
+            );
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn focus_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, event: &Event) -> Result<Handled, Box<dyn Error>> {
+        let scroll_pane = obj.downcast_mut::<ScrollPane>().unwrap(); // unwrap OK
+        let (_, child_events) = scroll_pane.emit_on_content(event, uictx)?;
+        for child_event in child_events {
+            uictx.child_event(child_event);
+        }
+        Ok(Handled::NotHandled)
+    }
+}
+
+impl Widget for ScrollPane {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of ScrollPane {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of ScrollPane {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        let mut batch = ShapeBatch::new();
+        self.batch_shapes(&mut batch);
+        batch.draw(ctx)
+    }
+
+    fn batch_shapes(&self, batch: &mut ShapeBatch) -> bool {
+        if let Some(bg_color) = self.bg_color {
+            batch.fill_rect(self.dimensions, bg_color);
+        }
+
+        let mut bars = vec![];
+        if let Some(track) = self.vertical_track() {
+            bars.push((track, self.vertical_thumb(track)));
+        }
+        if let Some(track) = self.horizontal_track() {
+            bars.push((track, self.horizontal_thumb(track)));
+        }
+        for (track, thumb) in bars {
+            batch.stroke_rect(track, 1.0, scheme().pane_border);
+            batch.fill_rect(thumb, scheme().button);
+        }
+
+        batch.stroke_rect(self.dimensions, 1.0, scheme().pane_border);
+        true
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    /// Like a Pane, it can receive keyboard focus for the widgets in its content.
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn takes_tab(&self) -> bool {
+        true
+    }
+
+    fn clip_rect(&self) -> Option<Rect> {
+        Some(self.viewport())
+    }
+
+    fn as_container(&self) -> Option<&dyn Container> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn Container> {
+        Some(self)
+    }
+}
+
+impl Container for ScrollPane {
+    /// The content goes where it is scrolled to.
+    fn child_origin(&self) -> Vector2<f32> {
+        Vector2 {
+            x: self.dimensions.x - self.offset.x,
+            y: self.dimensions.y - self.offset.y,
+        }
+    }
+
+    /// Whether the scroll pane already has its content.
+    fn is_full(&self) -> bool {
+        self.content.is_some()
+    }
+
+    /// The widget becomes the content, and gets keyboard focus through the scroll pane.
+    fn child_added(&mut self, content_id: NodeId, (w, h): (f32, f32), _accepts_keyboard_events: bool) -> bool {
+        self.content = Some(content_id);
+        self.content_size = Vector2 { x: w, y: h };
+        true
+    }
+
+    fn child_removed(&mut self, content_id: &NodeId) {
+        if self.content.as_ref() == Some(content_id) {
+            self.content = None;
+            self.content_size = Vector2 { x: 0.0, y: 0.0 };
+            self.offset = Vector2 { x: 0.0, y: 0.0 };
+        }
+    }
+}
+
+widget_from_id!(ScrollPane);
+impl_emit_event!(ScrollPane, self.handler_data);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        harness::{dummy_font_info, Harness},
+        InsertLocation, Pane, TextField,
+    };
+    use super::*;
+    use crate::Screen;
+
+    #[test]
+    fn test_thumb_follows_the_offset_and_back() {
+        // a 100 pixel view of 400 pixels of content, on a 100 pixel track
+        assert_eq!(thumb_span(0.0, 100.0, 400.0, 100.0, 0.0), (0.0, 25.0));
+        assert_eq!(thumb_span(0.0, 100.0, 400.0, 100.0, 300.0), (75.0, 25.0));
+        assert_eq!(thumb_span(10.0, 100.0, 400.0, 100.0, 150.0), (47.5, 25.0));
+        assert_eq!(offset_for_thumb(47.5, 10.0, 100.0, 400.0, 100.0), 150.0);
+        assert_eq!(offset_for_thumb(500.0, 10.0, 100.0, 400.0, 100.0), 300.0);
+
+        // long content still gets a thumb that can be grabbed
+        assert_eq!(thumb_span(0.0, 100.0, 100_000.0, 100.0, 0.0).1, MIN_THUMB_LENGTH);
+    }
+
+    #[test]
+    fn test_scrolling_moves_the_content_into_view() {
+        let mut harness = Harness::new(Screen::Options);
+        let scroll_pane_id = harness.add_widget(
+            Box::new(ScrollPane::new(Rect::new(10.0, 10.0, 100.0, 100.0))),
+            InsertLocation::AtCurrentLayer,
+        );
+        let pane_id = harness.add_widget(
+            Box::new(Pane::new(Rect::new(0.0, 0.0, 90.0, 300.0))),
+            InsertLocation::ToNestedContainer(&scroll_pane_id),
+        );
+        let tf = TextField::new(dummy_font_info(), Rect::new(10.0, 250.0, 70.0, 20.0));
+        let tf_id = harness.add_widget(Box::new(tf), InsertLocation::ToNestedContainer(&pane_id));
+        let drawn = |harness: &Harness| -> Vec<NodeId> { harness.draw().into_iter().map(|(id, _)| id).collect() };
+
+        // the text field is at (20, 260), below the bottom of the scroll pane
+        assert!(!drawn(&harness).contains(&tf_id));
+        assert!(drawn(&harness).contains(&pane_id));
+
+        harness.scroll(50.0, 50.0, -10.0).unwrap(); // toward the player, so down, as far as it goes
+        let scroll_pane = ScrollPane::widget_from_id(&harness.layering, &scroll_pane_id).unwrap();
+        assert_eq!(scroll_pane.offset(), Vector2 { x: 0.0, y: 200.0 });
+        assert_eq!(
+            TextField::widget_from_id(&harness.layering, &tf_id).unwrap().rect(),
+            Rect::new(20.0, 60.0, 70.0, 20.0)
+        );
+        assert!(drawn(&harness).contains(&tf_id));
+
+        harness.click(30.0, 65.0).unwrap();
+        harness.type_text("z").unwrap();
+        assert_eq!(
+            TextField::widget_from_id(&harness.layering, &tf_id).unwrap().text(),
+            Some("z".to_owned())
+        );
+
+        // back up by dragging the thumb to the top of the track
+        harness.drag(&[(105.0, 100.0), (105.0, 50.0), (105.0, 30.0)]).unwrap();
+        let scroll_pane = ScrollPane::widget_from_id(&harness.layering, &scroll_pane_id).unwrap();
+        assert_eq!(scroll_pane.offset(), Vector2 { x: 0.0, y: 0.0 });
+        assert!(!drawn(&harness).contains(&tf_id));
+    }
+}
@@ -0,0 +1,351 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse::CursorIcon;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::context::{EmitEvent, Event, EventType, Handled, HandlerData, KeyCodeOrChar, UIContext};
+use super::{common::FontInfo, textcache::TextCache, widget::Widget, UIError, UIResult};
+
+use crate::constants::colors::*;
+use crate::i18n::tr_args;
+
+const SLIDER_VALUE_WIDTH: f32 = 50.0; // to the right of the track, for the value
+const SLIDER_TRACK_HEIGHT: f32 = 4.0;
+const SLIDER_THUMB_WIDTH: f32 = 10.0;
+
+/// Picks a whole number from a range by dragging a thumb along a track, with the number shown to
+/// the right of it. The arrow keys move it by a step, and Home and End to either end. Each change,
+/// whether by the player or through `change_value`, is followed by a `ValueChanged` event.
+pub struct Slider {
+    id:               Option<NodeId>,
+    z_index:          usize,
+    font_info:        FontInfo,
+    pub dimensions:   Rect,
+    min:              i32,
+    max:              i32,
+    step:             i32, // by which the arrow keys move it
+    value:            i32,
+    pub focused:      bool,        // has keyboard focus?
+    pub handler_data: HandlerData, // required for impl_emit_event!
+}
+
+impl fmt::Debug for Slider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Slider {{ id: {:?}, z-index: {}, Dimensions: {:?}, Value: {} of {}..={} }}",
+            self.id, self.z_index, self.dimensions, self.value, self.min, self.max
+        )
+    }
+}
+
+impl Slider {
+    /// Creates a slider from `min` to `max`, inclusive, starting out at `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` isn't less than `max` or `step` isn't positive.
+    pub fn new(font_info: FontInfo, dimensions: Rect, min: i32, max: i32, step: i32, value: i32) -> Self {
+        assert!(min < max, "a slider needs a range to pick from");
+        assert!(step > 0, "a slider's step must be positive");
+        let mut slider = Slider {
+            id: None,
+            z_index: std::usize::MAX,
+            font_info,
+            dimensions,
+            min,
+            max,
+            step,
+            value: value.max(min).min(max),
+            focused: false,
+            handler_data: HandlerData::new(),
+        };
+
+        // unwrap OK b/c not being called within handler
+        slider
+            .on(EventType::GainFocus, Box::new(Slider::focus_change_handler))
+            .unwrap();
+        slider
+            .on(EventType::LoseFocus, Box::new(Slider::focus_change_handler))
+            .unwrap();
+        // pressing the button, dragging, and letting it go all move the thumb to the mouse
+        for &event_type in &[EventType::MouseButtonHeld, EventType::Drag, EventType::Click] {
+            slider.on(event_type, Box::new(Slider::mouse_handler)).unwrap();
+        }
+        slider
+            .on(EventType::KeyPress, Box::new(Slider::keypress_handler))
+            .unwrap();
+
+        slider
+    }
+
+    fn focus_change_handler(
+        obj: &mut dyn EmitEvent,
+        _uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let slider = obj.downcast_mut::<Slider>().unwrap(); // unwrap OK because this will always be Slider
+        slider.focused = event.what == EventType::GainFocus;
+        Ok(Handled::NotHandled)
+    }
+
+    fn mouse_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, event: &Event) -> Result<Handled, Box<dyn Error>> {
+        let slider = obj.downcast_mut::<Slider>().unwrap(); // unwrap OK because this will always be Slider
+        let point = event.point.unwrap(); // unwrap OK because mouse events always have a point
+        let value = slider.value_at(point.x);
+        slider.change_value(uictx, value)?;
+        Ok(Handled::Handled)
+    }
+
+    fn keypress_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let slider = obj.downcast_mut::<Slider>().unwrap(); // unwrap OK because this will always be Slider
+        let value = match event.key {
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Left)) | Some(KeyCodeOrChar::KeyCode(KeyCode::Down)) => {
+                slider.value - slider.step
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Right)) | Some(KeyCodeOrChar::KeyCode(KeyCode::Up)) => {
+                slider.value + slider.step
+            }
+            Some(KeyCodeOrChar::KeyCode(KeyCode::Home)) => slider.min,
+            Some(KeyCodeOrChar::KeyCode(KeyCode::End)) => slider.max,
+            _ => return Ok(Handled::NotHandled),
+        };
+        slider.change_value(uictx, value)?;
+        Ok(Handled::Handled)
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Sets the value without a `ValueChanged` event, as when bringing the slider in line with a
+    /// setting that changed elsewhere. It is kept within the slider's range.
+    pub fn set_value(&mut self, value: i32) {
+        self.value = value.max(self.min).min(self.max);
+    }
+
+    /// Sets the value from a handler, sending the slider a `ValueChanged` event if it changed.
+    pub fn change_value(&mut self, uictx: &mut UIContext, value: i32) -> Result<Handled, Box<dyn Error>> {
+        let old_value = self.value;
+        self.set_value(value);
+        if self.value == old_value {
+            return Ok(Handled::NotHandled);
+        }
+        self.emit(&Event::new_value_changed(), uictx)
+    }
+
+    /// The part of the slider the thumb moves along, leaving room on the right for the value.
+    fn track_rect(&self) -> Rect {
+        let w = (self.dimensions.w - SLIDER_VALUE_WIDTH).max(SLIDER_THUMB_WIDTH);
+        Rect::new(self.dimensions.x, self.dimensions.y, w, self.dimensions.h)
+    }
+
+    /// The value for the thumb to be centered at `x`, rounded to the nearest step.
+    fn value_at(&self, x: f32) -> i32 {
+        let track = self.track_rect();
+        let travel = track.w - SLIDER_THUMB_WIDTH;
+        let fraction = ((x - track.x - SLIDER_THUMB_WIDTH / 2.0) / travel).max(0.0).min(1.0);
+        let steps = ((self.max - self.min) as f32 * fraction / self.step as f32).round() as i32;
+        self.min + steps * self.step
+    }
+
+    fn thumb_rect(&self) -> Rect {
+        let track = self.track_rect();
+        let fraction = (self.value - self.min) as f32 / (self.max - self.min) as f32;
+        let x = track.x + (track.w - SLIDER_THUMB_WIDTH) * fraction;
+        Rect::new(x, track.y, SLIDER_THUMB_WIDTH, track.h)
+    }
+}
+
+impl Widget for Slider {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Slider {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of Slider {:?} to zero", self.id()),
+            }));
+        }
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        let track = self.track_rect();
+        let thumb = self.thumb_rect();
+
+        // the track is filled in up to the thumb
+        let line_y = track.y + (track.h - SLIDER_TRACK_HEIGHT) / 2.0;
+        let line = Rect::new(track.x, line_y, track.w, SLIDER_TRACK_HEIGHT);
+        let filled = Rect::new(track.x, line_y, thumb.x - track.x, SLIDER_TRACK_HEIGHT);
+        let line_mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), line, scheme().pane_border)?;
+        graphics::draw(ctx, &line_mesh, DrawParam::default())?;
+        if filled.w > 0.0 {
+            let filled_mesh =
+                graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), filled, scheme().checkbox_toggled_fill)?;
+            graphics::draw(ctx, &filled_mesh, DrawParam::default())?;
+        }
+
+        let thumb_color = if self.focused {
+            scheme().checkbox_border_on_hover
+        } else {
+            scheme().checkbox_toggled_fill
+        };
+        let thumb_mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), thumb, thumb_color)?;
+        graphics::draw(ctx, &thumb_mesh, DrawParam::default())?;
+
+        let text = texts.get(
+            self.font_info
+                .fragment(self.value.to_string())
+                .color(scheme().value_text),
+        );
+        let text_point = Point2 {
+            x: track.right() + self.font_info.char_dimensions.x,
+            y: track.y + (track.h - self.font_info.char_dimensions.y) / 2.0,
+        };
+        graphics::draw(ctx, &*text, DrawParam::default().dest(text_point))?;
+
+        Ok(())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accessible_name(&self) -> Option<String> {
+        Some(tr_args("a11y-slider", &[("value", &self.value)]))
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        Some(CursorIcon::Hand)
+    }
+}
+
+impl_emit_event!(Slider, self.handler_data);
+widget_from_id!(Slider);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        harness::{dummy_font_info, Harness},
+        InsertLocation,
+    };
+    use super::*;
+    use crate::Screen;
+
+    // The track is 100 pixels wide, so the thumb's middle goes from x = 5 to x = 95
+    fn add_slider(harness: &mut Harness) -> NodeId {
+        let slider = Slider::new(dummy_font_info(), Rect::new(0.0, 0.0, 150.0, 20.0), 0, 100, 5, 50);
+        harness.add_widget(Box::new(slider), InsertLocation::AtCurrentLayer)
+    }
+
+    fn value(harness: &Harness, slider_id: &NodeId) -> i32 {
+        Slider::widget_from_id(&harness.layering, slider_id).unwrap().value()
+    }
+
+    #[test]
+    fn test_dragging_moves_the_value_by_whole_steps() {
+        let mut harness = Harness::new(Screen::Options);
+        let slider_id = add_slider(&mut harness);
+        harness.drag(&[(50.0, 10.0), (20.0, 10.0), (26.0, 10.0)]).unwrap();
+        assert_eq!(value(&harness, &slider_id), 25); // 23.3 rounded to a step
+
+        // the ends are past the middle of the thumb at either end
+        harness.click(1.0, 10.0).unwrap();
+        assert_eq!(value(&harness, &slider_id), 0);
+        harness.click(99.0, 10.0).unwrap();
+        assert_eq!(value(&harness, &slider_id), 100);
+    }
+
+    #[test]
+    fn test_arrow_keys_step_the_value_within_its_range() {
+        let mut harness = Harness::new(Screen::Options);
+        let slider_id = add_slider(&mut harness);
+        harness.focus(&slider_id).unwrap();
+        harness.press_key(KeyCode::Right).unwrap();
+        assert_eq!(value(&harness, &slider_id), 55);
+        harness.press_key(KeyCode::Home).unwrap();
+        harness.press_key(KeyCode::Left).unwrap();
+        assert_eq!(value(&harness, &slider_id), 0);
+        harness.press_key(KeyCode::End).unwrap();
+        assert_eq!(value(&harness, &slider_id), 100);
+    }
+}
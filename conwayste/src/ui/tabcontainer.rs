@@ -0,0 +1,516 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::error::Error;
+use std::fmt;
+
+use ggez::graphics::{self, DrawMode, DrawParam, Rect};
+use ggez::input::keyboard::KeyCode;
+use ggez::mint::{Point2, Vector2};
+use ggez::{Context, GameResult};
+
+use id_tree::NodeId;
+
+use super::{
+    common::{within_widget, FontInfo},
+    context::{self, EmitEvent, Event, EventType, Handled, KeyCodeOrChar, UIContext},
+    textcache::TextCache,
+    widget::{Container, Widget},
+    UIError, UIResult,
+};
+
+use crate::constants::colors::*;
+
+const TAB_PADDING: f32 = 8.0; // around the title of a tab
+
+/// A container showing one of its pages at a time, under a strip of tabs with the pages' titles.
+/// Each widget added to it with `InsertLocation::ToNestedContainer` becomes the page of the next
+/// tab, and is placed relative to the top-left corner of the area under the strip; usually it is a
+/// `Pane` the size of `page_rect()`. Only the page of the active tab is drawn and gets mouse and
+/// keyboard events, though all of them get broadcast events, so settings in them load and save.
+///
+/// Clicking a tab, or pressing Ctrl-Tab or Ctrl-Shift-Tab, switches to it and emits
+/// `ValueChanged` on the container.
+pub struct TabContainer {
+    id:             Option<NodeId>,
+    z_index:        usize,
+    pub dimensions: Rect,
+    font_info:      FontInfo,
+    titles:         Vec<String>,
+    pages:          Vec<NodeId>, // in the order of `titles`; there may be fewer pages than titles
+    active:         usize,
+    focused:        bool,
+    handler_data:   context::HandlerData, // required for impl_emit_event!
+}
+
+impl fmt::Debug for TabContainer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "TabContainer {{ id: {:?}, z-index: {}, Dimensions: {:?}, Titles: {:?}, Active: {} }}",
+            self.id, self.z_index, self.dimensions, self.titles, self.active
+        )
+    }
+}
+
+fn strip_height(font_info: &FontInfo) -> f32 {
+    font_info.char_dimensions.y + 2.0 * TAB_PADDING
+}
+
+/// The rectangles of tabs with these titles, side by side from the top-left corner of `rect`.
+fn tab_rects(rect: Rect, font_info: &FontInfo, titles: &[String]) -> Vec<Rect> {
+    let height = strip_height(font_info);
+    let mut x = rect.x;
+    titles
+        .iter()
+        .map(|title| {
+            let width = title.chars().count() as f32 * font_info.char_dimensions.x + 2.0 * TAB_PADDING;
+            let tab = Rect::new(x, rect.y, width, height);
+            x += width;
+            tab
+        })
+        .collect()
+}
+
+impl TabContainer {
+    /// Creates a container with a tab for each title. Add the pages in the same order.
+    pub fn new(font_info: FontInfo, dimensions: Rect, titles: Vec<String>) -> Self {
+        let mut tabs = TabContainer {
+            id: None,
+            z_index: std::usize::MAX,
+            dimensions,
+            font_info,
+            titles,
+            pages: vec![],
+            active: 0,
+            focused: false,
+            handler_data: context::HandlerData::new(),
+        };
+
+        context::forward_to_nested(
+            &mut tabs,
+            TabContainer::mouse_event_handler,
+            TabContainer::key_press_handler,
+            TabContainer::broadcast_handler,
+        );
+
+        tabs.on(EventType::GainFocus, Box::new(TabContainer::focus_handler))
+            .unwrap(); // unwrap OK
+        tabs.on(EventType::LoseFocus, Box::new(TabContainer::focus_handler))
+            .unwrap(); // unwrap OK
+
+        tabs
+    }
+
+    /// The area under the tab strip, relative to the container's top-left corner, where pages go.
+    pub fn page_rect(&self) -> Rect {
+        let strip_h = strip_height(&self.font_info);
+        Rect::new(0.0, strip_h, self.dimensions.w, (self.dimensions.h - strip_h).max(1.0))
+    }
+
+    /// The index of the tab being shown.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// The page of the tab being shown, if it was added.
+    pub fn active_page(&self) -> Option<&NodeId> {
+        self.pages.get(self.active)
+    }
+
+    /// Shows the tab with this index, without emitting `ValueChanged`. Use this to set up the
+    /// container before it is shown.
+    ///
+    /// # Errors
+    ///
+    /// An `InvalidAction` error is returned if there is no such tab.
+    pub fn set_active(&mut self, index: usize) -> UIResult<()> {
+        if index >= self.titles.len() {
+            return Err(Box::new(UIError::InvalidAction {
+                reason: format!("TabContainer {:?} has no tab {}", self.id, index),
+            }));
+        }
+        self.active = index;
+        Ok(())
+    }
+
+    /// Switches to the tab with this index, moving keyboard focus to its page if the container has
+    /// it, and emits `ValueChanged` if it wasn't the active tab already.
+    pub fn change_active(&mut self, uictx: &mut UIContext, index: usize) -> Result<Handled, Box<dyn Error>> {
+        if index == self.active || index >= self.titles.len() {
+            return Ok(Handled::NotHandled);
+        }
+        if self.focused {
+            self.emit_on_active_page(&Event::new_gain_or_lose_focus(EventType::LoseFocus), uictx)?;
+        }
+        self.active = index;
+        if self.focused {
+            self.emit_on_active_page(&Event::new_gain_or_lose_focus(EventType::GainFocus), uictx)?;
+        }
+        self.emit(&Event::new_value_changed(), uictx)
+    }
+
+    fn tab_rects(&self) -> Vec<Rect> {
+        tab_rects(self.dimensions, &self.font_info, &self.titles)
+    }
+
+    /// Emits the event on the active page, returning whether it handled the event and the events it
+    /// sent to this container.
+    fn emit_on_active_page(
+        &mut self,
+        event: &Event,
+        uictx: &mut UIContext,
+    ) -> Result<(Handled, Vec<Event>), Box<dyn Error>> {
+        let page_id = match self.active_page() {
+            Some(page_id) => page_id.clone(),
+            None => return Ok((Handled::NotHandled, vec![])),
+        };
+        let (widget_ref, mut subuictx) = uictx.derive(&page_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+        if let Some(emittable) = widget_ref.as_emit_event() {
+            let handled = emittable.emit(event, &mut subuictx)?;
+            return Ok((handled, subuictx.collect_child_events()));
+        }
+        Ok((Handled::NotHandled, vec![]))
+    }
+
+    fn mouse_event_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let tabs = obj.downcast_mut::<TabContainer>().unwrap(); // unwrap OK
+        let point = event.point.unwrap(); // unwrap OK because mouse events always have a point
+
+        if let Some(index) = tabs.tab_rects().iter().position(|tab| within_widget(&point, tab)) {
+            if event.what == EventType::Click {
+                tabs.change_active(uictx, index)?;
+            }
+            return Ok(Handled::Handled);
+        }
+
+        let page_rect = tabs
+            .active_page()
+            .and_then(|page_id| uictx.widget_view.get(page_id).ok())
+            .map(|node| node.data().rect());
+        if !page_rect.map_or(false, |rect| within_widget(&point, &rect)) {
+            return Ok(Handled::NotHandled);
+        }
+        let (handled, child_events) = tabs.emit_on_active_page(event, uictx)?;
+        for child_event in child_events {
+            match child_event.what {
+                EventType::ChildRequestsFocus | EventType::ChildReleasedFocus => uictx.child_event(child_event),
+                _ => warn!("Unhandled child event in TabContainer: {:?}", child_event.what),
+            }
+        }
+        Ok(handled)
+    }
+
+    fn key_press_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let tabs = obj.downcast_mut::<TabContainer>().unwrap(); // unwrap OK
+
+        if event.key == Some(KeyCodeOrChar::KeyCode(KeyCode::Tab)) && event.ctrl_pressed() {
+            let count = tabs.titles.len();
+            if count > 0 {
+                let index = if event.shift_pressed() {
+                    (tabs.active + count - 1) % count
+                } else {
+                    (tabs.active + 1) % count
+                };
+                tabs.change_active(uictx, index)?;
+            }
+            return Ok(Handled::Handled);
+        }
+
+        // the page says when Tab moves focus past its last widget, which moves it past this container
+        let (handled, child_events) = tabs.emit_on_active_page(event, uictx)?;
+        for child_event in child_events {
+            if child_event.what == EventType::ChildReleasedFocus {
+                uictx.child_event(child_event);
+            }
+        }
+        Ok(handled)
+    }
+
+    fn broadcast_handler(
+        obj: &mut dyn EmitEvent,
+        uictx: &mut UIContext,
+        event: &Event,
+    ) -> Result<Handled, Box<dyn Error>> {
+        let tabs = obj.downcast_mut::<TabContainer>().unwrap(); // unwrap OK
+
+        // hidden pages shouldn't react to the mouse moving over where they would be
+        let page_ids: Vec<NodeId> = if event.what == EventType::MouseMove {
+            tabs.active_page().cloned().into_iter().collect()
+        } else {
+            tabs.pages.clone()
+        };
+        for page_id in page_ids {
+            let (widget_ref, mut subuictx) = uictx.derive(&page_id).unwrap(); // unwrap OK b/c NodeId valid & in view
+            if let Some(emittable) = widget_ref.as_emit_event() {
+                emittable.emit(event, &mut subuictx)?;
+                let page_events = subuictx.collect_child_events();
+                if page_events.len() != 0 {
+                    warn!(
+                        "[TabContainer] expected no {:?} child events to be collected from page; got {:?}",
+                        event.what, page_events
+                    );
+                }
+            }
+        }
+        Ok(Handled::NotHandled)
+    }
+
+    fn focus_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, event: &Event) -> Result<Handled, Box<dyn Error>> {
+        let tabs = obj.downcast_mut::<TabContainer>().unwrap(); // unwrap OK
+        tabs.focused = event.what == EventType::GainFocus;
+        let (_, child_events) = tabs.emit_on_active_page(event, uictx)?;
+        for child_event in child_events {
+            uictx.child_event(child_event);
+        }
+        Ok(Handled::NotHandled)
+    }
+}
+
+impl Widget for TabContainer {
+    fn id(&self) -> Option<&NodeId> {
+        self.id.as_ref()
+    }
+
+    fn set_id(&mut self, new_id: NodeId) {
+        self.id = Some(new_id);
+    }
+
+    fn z_index(&self) -> usize {
+        self.z_index
+    }
+
+    fn set_z_index(&mut self, new_z_index: usize) {
+        self.z_index = new_z_index;
+    }
+
+    fn rect(&self) -> Rect {
+        self.dimensions
+    }
+
+    fn set_rect(&mut self, new_dims: Rect) -> UIResult<()> {
+        if new_dims.w == 0.0 || new_dims.h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of TabContainer {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions = new_dims;
+        Ok(())
+    }
+
+    fn position(&self) -> Point2<f32> {
+        self.dimensions.point().into()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.dimensions.x = x;
+        self.dimensions.y = y;
+    }
+
+    fn size(&self) -> (f32, f32) {
+        (self.dimensions.w, self.dimensions.h)
+    }
+
+    fn set_size(&mut self, w: f32, h: f32) -> UIResult<()> {
+        if w == 0.0 || h == 0.0 {
+            return Err(Box::new(UIError::InvalidDimensions {
+                reason: format!("Cannot set the width or height of TabContainer {:?} to zero", self.id()),
+            }));
+        }
+
+        self.dimensions.w = w;
+        self.dimensions.h = h;
+
+        Ok(())
+    }
+
+    fn translate(&mut self, dest: Vector2<f32>) {
+        self.dimensions.translate(dest);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texts: &mut TextCache) -> GameResult<()> {
+        for (i, (tab, title)) in self.tab_rects().iter().zip(self.titles.iter()).enumerate() {
+            if i == self.active {
+                let fill = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), *tab, scheme().button)?;
+                graphics::draw(ctx, &fill, DrawParam::default())?;
+            }
+            let border = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), *tab, scheme().pane_border)?;
+            graphics::draw(ctx, &border, DrawParam::default())?;
+
+            let text = texts.get(self.font_info.fragment(title.as_str()));
+            let point = Point2 {
+                x: tab.x + TAB_PADDING,
+                y: tab.y + TAB_PADDING,
+            };
+            graphics::queue_text(ctx, &text, point, Some(scheme().menu_text));
+        }
+        graphics::draw_queued_text(ctx, DrawParam::default(), None, graphics::FilterMode::Linear)?;
+
+        let mut page_area = self.page_rect();
+        page_area.translate(Vector2 {
+            x: self.dimensions.x,
+            y: self.dimensions.y,
+        });
+        let border = graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), page_area, scheme().pane_border)?;
+        graphics::draw(ctx, &border, DrawParam::default())
+    }
+
+    fn as_emit_event(&mut self) -> Option<&mut dyn EmitEvent> {
+        Some(self)
+    }
+
+    /// Like a Pane, it can receive keyboard focus for the widgets in its pages.
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn takes_tab(&self) -> bool {
+        true
+    }
+
+    fn shows_child(&self, child_id: &NodeId) -> bool {
+        self.active_page() == Some(child_id)
+    }
+
+    fn accessible_name(&self) -> Option<String> {
+        self.titles.get(self.active).cloned()
+    }
+
+    fn as_container(&self) -> Option<&dyn Container> {
+        Some(self)
+    }
+
+    fn as_container_mut(&mut self) -> Option<&mut dyn Container> {
+        Some(self)
+    }
+}
+
+impl Container for TabContainer {
+    /// Pages go in the area under the tab strip.
+    fn child_origin(&self) -> Vector2<f32> {
+        let page_rect = self.page_rect();
+        Vector2 {
+            x: self.dimensions.x + page_rect.x,
+            y: self.dimensions.y + page_rect.y,
+        }
+    }
+
+    /// Whether every tab has a page.
+    fn is_full(&self) -> bool {
+        self.pages.len() >= self.titles.len()
+    }
+
+    /// The widget becomes the page of the next tab, and gets keyboard focus through the container.
+    fn child_added(&mut self, page_id: NodeId, _size: (f32, f32), _accepts_keyboard_events: bool) -> bool {
+        self.pages.push(page_id);
+        true
+    }
+
+    /// The page's tab is removed along with it.
+    fn child_removed(&mut self, page_id: &NodeId) {
+        if let Some(index) = self.pages.iter().position(|id| id == page_id) {
+            self.pages.remove(index);
+            self.titles.remove(index);
+            if self.active > index {
+                self.active -= 1;
+            }
+            self.active = self.active.min(self.titles.len().saturating_sub(1));
+        }
+    }
+}
+
+widget_from_id!(TabContainer);
+impl_emit_event!(TabContainer, self.handler_data);
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        context::Modifiers,
+        harness::{dummy_font_info, Harness},
+        InsertLocation, Pane, TextField,
+    };
+    use super::*;
+    use crate::Screen;
+
+    #[test]
+    fn test_tab_rects_fit_their_titles() {
+        let titles = vec!["Video".to_owned(), "Network".to_owned()];
+        let rects = tab_rects(Rect::new(10.0, 20.0, 300.0, 200.0), &dummy_font_info(), &titles);
+        assert_eq!(
+            rects,
+            vec![Rect::new(10.0, 20.0, 41.0, 21.0), Rect::new(51.0, 20.0, 51.0, 21.0)]
+        );
+    }
+
+    #[test]
+    fn test_only_the_active_page_is_drawn_and_gets_events() {
+        let mut harness = Harness::new(Screen::Options);
+        let tabs = TabContainer::new(
+            dummy_font_info(),
+            Rect::new(10.0, 10.0, 200.0, 100.0),
+            vec!["Video".to_owned(), "Audio".to_owned()],
+        );
+        let page_rect = tabs.page_rect();
+        let tabs_id = harness.add_widget(Box::new(tabs), InsertLocation::AtCurrentLayer);
+        let mut field_ids = vec![];
+        for _ in 0..2 {
+            let page = Pane::new(Rect::new(0.0, 0.0, page_rect.w, page_rect.h));
+            let page_id = harness.add_widget(Box::new(page), InsertLocation::ToNestedContainer(&tabs_id));
+            let tf = TextField::new(dummy_font_info(), Rect::new(10.0, 10.0, 100.0, 20.0));
+            field_ids.push(harness.add_widget(Box::new(tf), InsertLocation::ToNestedContainer(&page_id)));
+        }
+        let drawn = |harness: &Harness| -> Vec<NodeId> { harness.draw().into_iter().map(|(id, _)| id).collect() };
+        let text_of = |harness: &Harness, i: usize| {
+            TextField::widget_from_id(&harness.layering, &field_ids[i])
+                .unwrap()
+                .text()
+        };
+
+        // both text fields are at (20, 41), under the tab strip
+        assert!(drawn(&harness).contains(&field_ids[0]));
+        assert!(!drawn(&harness).contains(&field_ids[1]));
+        harness.click(30.0, 50.0).unwrap();
+        harness.type_text("hi").unwrap();
+        assert_eq!(text_of(&harness, 0), Some("hi".to_owned()));
+        assert_eq!(text_of(&harness, 1), None);
+
+        harness.click(60.0, 20.0).unwrap(); // on the "Audio" tab
+        let tabs = TabContainer::widget_from_id(&harness.layering, &tabs_id).unwrap();
+        assert_eq!(tabs.active(), 1);
+        assert!(drawn(&harness).contains(&field_ids[1]));
+        assert!(!drawn(&harness).contains(&field_ids[0]));
+        harness.click(30.0, 50.0).unwrap();
+        harness.type_text("yo").unwrap();
+        assert_eq!(text_of(&harness, 0), Some("hi".to_owned()));
+        assert_eq!(text_of(&harness, 1), Some("yo".to_owned()));
+
+        harness.modifiers = Modifiers::CTRL;
+        harness.press_key(KeyCode::Tab).unwrap();
+        let tabs = TabContainer::widget_from_id(&harness.layering, &tabs_id).unwrap();
+        assert_eq!(tabs.active(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Reusing the `Text`s drawn from one frame to the next. A `Text` lays out its glyphs the first
+//! time it is measured or drawn and keeps that layout, so widgets that make a new `Text` for the
+//! same string every frame (or every time they reflow) pay for the layout again and again. Asking
+//! the cache instead hands back the same `Text` for as long as the string keeps being drawn with
+//! the same font, scale and color.
+//!
+//! Each `Layering` has a cache, which it hands to its widgets when drawing them and which handlers
+//! reach through the `UIContext`. Texts that were not asked for during the last frame are dropped
+//! by `end_frame`, which the layering calls after drawing.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ggez::graphics::{Font, Text, TextFragment};
+
+/// Everything about a fragment that changes how it is laid out or drawn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TextKey {
+    text:  String,
+    font:  Option<Font>,
+    scale: Option<(u32, u32)>, // bits of the x and y scales, as floats aren't `Hash`
+    color: Option<u32>,
+}
+
+impl TextKey {
+    fn of(fragment: &TextFragment) -> Self {
+        TextKey {
+            text:  fragment.text.clone(),
+            font:  fragment.font,
+            scale: fragment.scale.map(|scale| (scale.x.to_bits(), scale.y.to_bits())),
+            color: fragment.color.map(|color| color.to_rgba_u32()),
+        }
+    }
+}
+
+/// The texts asked for during this frame and the one before it.
+#[derive(Debug, Default)]
+pub struct TextCache {
+    current:  HashMap<TextKey, Rc<Text>>,
+    previous: HashMap<TextKey, Rc<Text>>,
+}
+
+impl TextCache {
+    pub fn new() -> Self {
+        TextCache::default()
+    }
+
+    /// The `Text` of `fragment`, made now if it wasn't asked for this frame or the last.
+    pub fn get(&mut self, fragment: TextFragment) -> Rc<Text> {
+        let key = TextKey::of(&fragment);
+        if let Some(text) = self.current.get(&key) {
+            return Rc::clone(text);
+        }
+        let text = self
+            .previous
+            .remove(&key)
+            .unwrap_or_else(|| Rc::new(Text::new(fragment)));
+        self.current.insert(key, Rc::clone(&text));
+        text
+    }
+
+    /// Forgets the texts that weren't asked for since the previous call.
+    pub fn end_frame(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// How many texts are kept.
+    pub fn len(&self) -> usize {
+        self.current.len() + self.previous.len()
+    }
+
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ggez::graphics::Color;
+
+    #[test]
+    fn test_texts_are_reused_until_a_frame_goes_by_without_them() {
+        let mut cache = TextCache::new();
+        let hello = cache.get(TextFragment::new("hello"));
+        assert!(Rc::ptr_eq(&hello, &cache.get(TextFragment::new("hello"))));
+        // any difference in how it's drawn makes another text
+        let red = cache.get(TextFragment::new("hello").color(Color::new(1.0, 0.0, 0.0, 1.0)));
+        assert!(!Rc::ptr_eq(&hello, &red));
+        assert_eq!(cache.len(), 2);
+
+        // still there a frame later
+        cache.end_frame();
+        assert!(Rc::ptr_eq(&hello, &cache.get(TextFragment::new("hello"))));
+
+        // the red one wasn't asked for during that frame, and the plain one isn't during the next
+        cache.end_frame();
+        assert_eq!(cache.len(), 1);
+        cache.end_frame();
+        assert!(cache.is_empty());
+        assert!(!Rc::ptr_eq(&hello, &cache.get(TextFragment::new("hello"))));
+    }
+}
@@ -18,10 +18,11 @@
 
 use std::error::Error;
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use ggez::event::KeyCode;
 use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
+use ggez::input::mouse::CursorIcon;
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
@@ -32,26 +33,54 @@ use super::common::draw_text;
 use super::{
     common::FontInfo,
     context::{EmitEvent, Event, EventType, Handled, HandlerData, KeyCodeOrChar, UIContext},
+    textcache::TextCache,
+    timer::TimerId,
+    validator::Validator,
     widget::Widget,
     UIError, UIResult,
 };
 
-use crate::constants::{colors::*, CHATBOX_BORDER_PIXELS};
+use crate::constants::{colors::*, CHATBOX_BORDER_PIXELS, CHATBOX_LINE_SPACING};
+use crate::i18n::{tr, tr_args};
 
 pub const BLINK_RATE_MS: u64 = 500;
 
+/// Supplies what Tab can complete the word at the cursor to. It is given the text before the cursor,
+/// and each candidate it returns replaces the last word of that text.
+pub type Completer = Box<dyn FnMut(&str) -> Vec<String> + Send>;
+
+/// Candidates from the last Tab, which further presses cycle through.
+struct Completion {
+    word_start: usize, // in chars
+    candidates: Vec<String>,
+    index:      usize, // of the candidate now in the text
+}
+
 pub struct TextField {
     id:                     Option<NodeId>,
     z_index:                usize,
     focused:                bool,
     text:                   String,
-    cursor_index:           usize, // Position of the cursor in the text fields' string
-    cursor_blink_timestamp: Option<Instant>, // last time the cursor blinked on/off
+    cursor_index:           usize, // Position of the cursor in the text fields' string, in chars (not bytes)
+    selection_anchor:       Option<usize>, // Where Shift-selecting started; the selection runs to the cursor
+    cursor_blink_suspended: bool,  // keep the cursor shown through the next blink, as it just moved
+    blink_timer:            Option<TimerId>, // running while focused
     draw_cursor:            bool,
     dimensions:             Rect,
     visible_start_index:    usize, // The index of the first character in `self.text` that is visible.
+    preedit:                Option<String>, // Text being composed in an input method, shown at the cursor
+    ime_position:           Option<Point2<f32>>, // Where the input method was last told the cursor is
+    completer:              Option<Completer>,
+    completion:             Option<Completion>, // Set while Tab is being pressed to cycle candidates
+    max_lines:              usize,              // More than one in multi-line mode
+    first_visible_line:     usize,              // In multi-line mode, the first of the lines scrolled into view
+    single_line_height:     f32,                // In multi-line mode, the height with one line of text
+    masked:                 bool,               // Text is drawn as bullets, for passwords
+    validators:             Vec<Validator>,
+    validation_error:       Option<String>, // Message from the first validator the text fails
     font_info:              FontInfo,
     pub bg_color:           Option<Color>,
+    pub hide_when_empty:    bool,        // not drawn while empty and unfocused, as for chat
     pub handler_data:       HandlerData, // required for impl_emit_event!
 }
 
@@ -85,7 +114,7 @@ impl TextField {
     ///
     /// let textfield = TextField::new(font_info, dimensions);
     ///
-    /// textfield.draw(ctx);
+    /// textfield.draw(ctx, texts);
     /// ```
     ///
     pub fn new(font_info: FontInfo, dimensions: Rect) -> TextField {
@@ -95,12 +124,25 @@ impl TextField {
             focused: false,
             text: String::new(),
             cursor_index: 0,
-            cursor_blink_timestamp: None,
+            selection_anchor: None,
+            cursor_blink_suspended: false,
+            blink_timer: None,
             draw_cursor: false,
             dimensions,
             visible_start_index: 0,
+            preedit: None,
+            ime_position: None,
+            completer: None,
+            completion: None,
+            max_lines: 1,
+            first_visible_line: 0,
+            single_line_height: dimensions.h,
+            masked: false,
+            validators: vec![],
+            validation_error: None,
             font_info,
             bg_color: None,
+            hide_when_empty: true,
             handler_data: HandlerData::new(),
         };
 
@@ -116,51 +158,76 @@ impl TextField {
             .unwrap();
         tf.on(EventType::Click, Box::new(TextField::on_click_handler)).unwrap();
 
-        // Handler for graphical updates
+        // Handlers for graphical updates
         tf.on(EventType::Update, Box::new(TextField::update_handler)).unwrap();
+        tf.on(EventType::Timer, Box::new(TextField::blink_handler)).unwrap();
         tf
     }
 
-    fn update_handler(
-        obj: &mut dyn EmitEvent,
-        _uictx: &mut UIContext,
-        _evt: &Event,
-    ) -> Result<Handled, Box<dyn Error>> {
+    fn update_handler(obj: &mut dyn EmitEvent, uictx: &mut UIContext, _evt: &Event) -> Result<Handled, Box<dyn Error>> {
         let tf = obj.downcast_mut::<TextField>().unwrap(); // unwrap OK because it's always a TextField
 
         if tf.focused {
-            if let Some(prev_blink_ms) = tf.cursor_blink_timestamp {
-                if Instant::now() - prev_blink_ms > Duration::from_millis(BLINK_RATE_MS) {
-                    tf.draw_cursor ^= true;
-                    tf.cursor_blink_timestamp = Some(Instant::now());
-                }
+            // Input methods for languages like Japanese show the text being composed, and the
+            // candidates for it, in a window of their own; keep it next to the cursor.
+            let cursor_pos = tf.cursor_screen_position();
+            if tf.ime_position != Some(cursor_pos) {
+                tf.ime_position = Some(cursor_pos);
+                let below_text = cursor_pos.y + tf.font_info.char_dimensions.y;
+                uictx.set_ime_position(Point2 {
+                    x: cursor_pos.x,
+                    y: below_text,
+                });
             }
         }
 
         Ok(Handled::NotHandled)
     }
 
+    fn blink_handler(obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event) -> Result<Handled, Box<dyn Error>> {
+        let tf = obj.downcast_mut::<TextField>().unwrap(); // unwrap OK because it's always a TextField
+        if tf.blink_timer.is_none() || evt.timer_id != tf.blink_timer {
+            return Ok(Handled::NotHandled);
+        }
+        if tf.cursor_blink_suspended {
+            tf.cursor_blink_suspended = false;
+        } else {
+            tf.draw_cursor ^= true;
+        }
+        Ok(Handled::Handled)
+    }
+
     fn gain_focus_handler(
         obj: &mut dyn EmitEvent,
-        _uictx: &mut UIContext,
+        uictx: &mut UIContext,
         _evt: &Event,
     ) -> Result<Handled, Box<dyn Error>> {
         let tf = obj.downcast_mut::<TextField>().unwrap(); // unwrap OK
         tf.focused = true;
         tf.draw_cursor = true;
-        tf.cursor_blink_timestamp = Some(Instant::now());
+        tf.cursor_blink_suspended = true;
+        if let Some(timer_id) = tf.blink_timer.take() {
+            uictx.cancel_timer(timer_id);
+        }
+        tf.blink_timer = Some(uictx.start_timer(Duration::from_millis(BLINK_RATE_MS), true)?);
         Ok(Handled::NotHandled)
     }
 
     fn lose_focus_handler(
         obj: &mut dyn EmitEvent,
-        _uictx: &mut UIContext,
+        uictx: &mut UIContext,
         _evt: &Event,
     ) -> Result<Handled, Box<dyn Error>> {
         let tf = obj.downcast_mut::<TextField>().unwrap(); // unwrap OK
 
         tf.focused = false;
         tf.draw_cursor = false;
+        if let Some(timer_id) = tf.blink_timer.take() {
+            uictx.cancel_timer(timer_id);
+        }
+        tf.preedit = None;
+        tf.ime_position = None;
+        tf.selection_anchor = None;
         Ok(Handled::NotHandled)
     }
 
@@ -179,6 +246,10 @@ impl TextField {
         (self.dimensions.w / self.font_info.char_dimensions.x) as usize
     }
 
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
     /// Returns the a string of the inputted text
     pub fn text(&self) -> Option<String> {
         let trimmed_str = self.text.trim();
@@ -192,6 +263,184 @@ impl TextField {
     pub fn set_text(&mut self, text: String) {
         self.text = text;
         self.cursor_index = 0;
+        self.selection_anchor = None;
+        self.fit_to_lines();
+        self.validate();
+    }
+
+    /// Lets the text span up to `max_lines` lines. Shift-Enter starts a new line, and lines too long
+    /// for the field wrap onto the next. The field grows downwards as lines are added, then scrolls.
+    pub fn set_multiline(&mut self, max_lines: usize) {
+        self.max_lines = max_lines.max(1);
+        self.single_line_height = self.dimensions.h;
+        self.fit_to_lines();
+    }
+
+    /// Draws each character as a bullet, for passwords. A masked field's text is also kept from
+    /// screen readers, and Tab doesn't complete it.
+    pub fn set_masked(&mut self, masked: bool) {
+        self.masked = masked;
+        self.completion = None;
+    }
+
+    /// Adds a rule the text must follow. While the text breaks any of them, the field has a red
+    /// border and, when focused, shows why under it.
+    pub fn add_validator(&mut self, validator: Validator) {
+        self.validators.push(validator);
+        self.validate();
+    }
+
+    /// Whether the text follows all of the field's validators.
+    pub fn is_valid(&self) -> bool {
+        self.validation_error.is_none()
+    }
+
+    /// Why the text is not valid, if it isn't.
+    pub fn validation_error(&self) -> Option<&str> {
+        self.validation_error.as_deref()
+    }
+
+    /// Whether all of the text fields in a form are valid, e.g. to decide whether its submit button
+    /// can be pressed. IDs that are not text fields are skipped.
+    pub fn all_valid(layer: &Layering, ids: &[NodeId]) -> bool {
+        ids.iter().all(|id| match TextField::widget_from_id(layer, id) {
+            Ok(tf) => tf.is_valid(),
+            Err(_) => true,
+        })
+    }
+
+    fn validate(&mut self) {
+        let text = &self.text;
+        self.validation_error = self.validators.iter().find_map(|v| v.check(text).err());
+    }
+
+    /// The text as drawn: the same, or one bullet per character if masked.
+    fn shown_text(&self) -> String {
+        if self.masked {
+            "•".repeat(self.char_count())
+        } else {
+            self.text.clone()
+        }
+    }
+
+    fn is_multiline(&self) -> bool {
+        self.max_lines > 1
+    }
+
+    fn line_height(&self) -> f32 {
+        self.font_info.char_dimensions.y + CHATBOX_LINE_SPACING
+    }
+
+    /// The lines the text is shown on in multi-line mode, as (index of first char, chars in line).
+    /// A newline ends a line without being shown itself.
+    fn visual_lines(&self) -> Vec<(usize, usize)> {
+        let width = self.max_visible_chars().max(1);
+        let mut lines = vec![];
+        let mut start = 0;
+        for hard_line in self.text.split('\n') {
+            let len = hard_line.chars().count();
+            let mut offset = 0;
+            loop {
+                let chunk = (len - offset).min(width);
+                lines.push((start + offset, chunk));
+                offset += chunk;
+                if offset >= len {
+                    break;
+                }
+            }
+            start += len + 1;
+        }
+        lines
+    }
+
+    /// The visual line the cursor is on, and its column in that line.
+    fn cursor_line_and_column(&self) -> (usize, usize) {
+        let lines = self.visual_lines();
+        let line = lines
+            .iter()
+            .rposition(|&(start, _)| start <= self.cursor_index)
+            .unwrap_or(0);
+        (line, self.cursor_index - lines[line].0)
+    }
+
+    /// In multi-line mode, resizes the field to fit its lines, up to `max_lines`, and scrolls so
+    /// that the cursor's line is in view.
+    fn fit_to_lines(&mut self) {
+        if !self.is_multiline() {
+            return;
+        }
+        let line_count = self.visual_lines().len();
+        let shown_lines = line_count.min(self.max_lines);
+        self.dimensions.h = self.single_line_height + (shown_lines - 1) as f32 * self.line_height();
+
+        let (line, _) = self.cursor_line_and_column();
+        if line < self.first_visible_line {
+            self.first_visible_line = line;
+        } else if line >= self.first_visible_line + self.max_lines {
+            self.first_visible_line = line + 1 - self.max_lines;
+        }
+        self.first_visible_line = self.first_visible_line.min(line_count - shown_lines);
+    }
+
+    /// Moves the cursor to the line above or below, in the same column if that line is long enough.
+    fn move_cursor_to_line(&mut self, down: bool) {
+        self.draw_cursor = true;
+        self.cursor_blink_suspended = true;
+
+        let lines = self.visual_lines();
+        let (line, column) = self.cursor_line_and_column();
+        let new_line = if down { Some(line + 1) } else { line.checked_sub(1) };
+        if let Some(&(start, len)) = new_line.and_then(|new_line| lines.get(new_line)) {
+            self.cursor_index = start + column.min(len);
+        }
+    }
+
+    /// Shows `preedit` at the cursor, underlined, as text that an input method is still composing.
+    /// The composed text is typed into the field as ordinary characters once the player confirms
+    /// it, after which this should be called with `None`.
+    #[allow(unused)] // TODO: call this from WindowEvent::Ime once ggez moves to winit 0.27, the first with it
+    pub fn set_preedit(&mut self, preedit: Option<String>) {
+        self.preedit = preedit.filter(|text| !text.is_empty());
+    }
+
+    fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Converts a position in chars to one in bytes, for indexing into `self.text`.
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    /// Where the cursor is drawn, at the top left of the character after it.
+    fn cursor_screen_position(&self) -> Point2<f32> {
+        if self.is_multiline() {
+            let (line, column) = self.cursor_line_and_column();
+            let row = line.saturating_sub(self.first_visible_line);
+            return Point2 {
+                x: self.text_position().x + column as f32 * self.font_info.char_dimensions.x,
+                y: self.text_position().y + row as f32 * self.line_height(),
+            };
+        }
+        let preedit_chars = self.preedit.as_ref().map(|text| text.chars().count()).unwrap_or(0);
+        let cursor_column =
+            (self.cursor_index + preedit_chars - self.visible_start_index).min(self.max_visible_chars());
+        Point2 {
+            x: self.text_position().x + cursor_column as f32 * self.font_info.char_dimensions.x,
+            y: self.text_position().y,
+        }
+    }
+
+    fn text_position(&self) -> Point2<f32> {
+        // 3.0 px added to y for central alignment
+        Point2 {
+            x: self.dimensions.x + CHATBOX_BORDER_PIXELS / 2.0 + 1.0,
+            y: self.dimensions.y + 3.0,
+        }
     }
 
     /// Handle a key.
@@ -200,8 +449,13 @@ impl TextField {
         if evt.key.is_none() {
             return Err("keyboard event does not have a key!".to_owned().into());
         }
+        if evt.key != Some(KeyCodeOrChar::KeyCode(KeyCode::Tab)) {
+            tf.completion = None;
+        }
+        let shift = evt.shift_pressed();
         match evt.key.unwrap() {
             KeyCodeOrChar::KeyCode(keycode) => match keycode {
+                KeyCode::Return if tf.is_multiline() && evt.shift_pressed() => tf.add_char_at_cursor('\n'),
                 KeyCode::Return => {
                     let forward_text = tf.handler_data.registered_events.contains(&EventType::TextEntered);
                     let text = tf.text();
@@ -216,13 +470,46 @@ impl TextField {
                     }
                     tf.release_focus(uictx);
                 }
-                KeyCode::Back => tf.remove_left_of_cursor(),
-                KeyCode::Delete => tf.remove_right_of_cursor(),
-                KeyCode::Left => tf.move_cursor_left(),
-                KeyCode::Right => tf.move_cursor_right(),
-                KeyCode::Home => tf.cursor_home(),
-                KeyCode::End => tf.cursor_end(),
+                KeyCode::Back => {
+                    if !tf.delete_selection() {
+                        tf.remove_left_of_cursor();
+                    }
+                }
+                KeyCode::Delete => {
+                    if !tf.delete_selection() {
+                        tf.remove_right_of_cursor();
+                    }
+                }
+                KeyCode::Left => {
+                    tf.extend_selection(shift);
+                    tf.move_cursor_left();
+                }
+                KeyCode::Right => {
+                    tf.extend_selection(shift);
+                    tf.move_cursor_right();
+                }
+                KeyCode::Home => {
+                    tf.extend_selection(shift);
+                    tf.cursor_home();
+                }
+                KeyCode::End => {
+                    tf.extend_selection(shift);
+                    tf.cursor_end();
+                }
+                KeyCode::A if evt.ctrl_pressed() => tf.select_all(),
                 KeyCode::Escape => tf.release_focus(uictx),
+                KeyCode::Tab => {
+                    tf.selection_anchor = None;
+                    tf.complete();
+                }
+                KeyCode::Up if tf.is_multiline() => {
+                    tf.extend_selection(shift);
+                    tf.move_cursor_to_line(false);
+                }
+                KeyCode::Down if tf.is_multiline() => {
+                    tf.extend_selection(shift);
+                    tf.move_cursor_to_line(true);
+                }
                 _ => return Ok(Handled::NotHandled),
             },
             KeyCodeOrChar::Char(ch) => {
@@ -233,27 +520,90 @@ impl TextField {
                 }
             }
         }
+        tf.fit_to_lines();
+        tf.validate();
         Ok(Handled::Handled)
     }
 
+    /// Lets Tab complete the word at the cursor, with candidates from `completer`. Pressing it again
+    /// goes on to the next candidate.
+    pub fn set_completer(&mut self, completer: Completer) {
+        self.completer = Some(completer);
+    }
+
+    /// Completes the word at the cursor, or replaces it with the next candidate if this follows
+    /// another Tab.
+    fn complete(&mut self) {
+        if let Some(ref mut completion) = self.completion {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let (word_start, candidate) = (completion.word_start, completion.candidates[completion.index].clone());
+            self.replace_word(word_start, &candidate);
+            return;
+        }
+        if self.masked {
+            return;
+        }
+        let cursor_byte_index = self.byte_index(self.cursor_index);
+        let completer = match self.completer {
+            Some(ref mut completer) => completer,
+            None => return,
+        };
+        let before_cursor = &self.text[..cursor_byte_index];
+        let candidates = completer(before_cursor);
+        if candidates.is_empty() {
+            return;
+        }
+        let word_byte_start = before_cursor
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| ch.is_whitespace())
+            .map_or(0, |(i, ch)| i + ch.len_utf8());
+        let word_start = before_cursor[..word_byte_start].chars().count();
+        self.replace_word(word_start, &candidates[0]);
+        self.completion = Some(Completion {
+            word_start,
+            candidates,
+            index: 0,
+        });
+    }
+
+    /// Replaces the text from `word_start` to the cursor with `word`, leaving the cursor after it.
+    fn replace_word(&mut self, word_start: usize, word: &str) {
+        self.draw_cursor = true;
+        self.cursor_blink_suspended = true;
+
+        let range = self.byte_index(word_start)..self.byte_index(self.cursor_index);
+        self.text.replace_range(range, word);
+        self.cursor_index = word_start + word.chars().count();
+        if self.visible_start_index > word_start {
+            self.visible_start_index = word_start;
+        }
+        if self.visible_start_index + self.max_visible_chars() < self.cursor_index {
+            self.visible_start_index = self.cursor_index - self.max_visible_chars();
+        }
+    }
+
     /// Sends a notification to the parent widget that we have released focus.
     fn release_focus(&mut self, uictx: &mut UIContext) {
         self.draw_cursor = false;
         self.focused = false;
+        self.preedit = None;
+        self.ime_position = None;
+        if let Some(timer_id) = self.blink_timer.take() {
+            uictx.cancel_timer(timer_id);
+        }
         let evt = Event::new_child_released_focus();
         uictx.child_event(evt);
     }
 
-    /// Adds a character at the current cursor position
+    /// Adds a character at the current cursor position, in place of the selected text if any
     fn add_char_at_cursor(&mut self, character: char) {
+        self.delete_selection();
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
-        if self.cursor_index == self.text.len() {
-            self.text.push(character);
-        } else {
-            self.text.insert(self.cursor_index, character);
-        }
+        let i = self.byte_index(self.cursor_index);
+        self.text.insert(i, character);
         self.cursor_index += 1;
         if self.visible_start_index + self.max_visible_chars() < self.cursor_index {
             self.visible_start_index = self.cursor_index - self.max_visible_chars();
@@ -263,10 +613,11 @@ impl TextField {
     /// Deletes a character to the left of the current cursor
     fn remove_left_of_cursor(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
         if self.cursor_index != 0 {
-            self.text.remove(self.cursor_index - 1);
+            let i = self.byte_index(self.cursor_index - 1);
+            self.text.remove(i);
             self.cursor_index -= 1;
             if self.visible_start_index > self.cursor_index {
                 self.visible_start_index = self.cursor_index;
@@ -277,30 +628,89 @@ impl TextField {
     /// Deletes a chracter to the right of the current cursor
     fn remove_right_of_cursor(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
-        let text_len = self.text.len();
+        let text_len = self.char_count();
 
         if text_len != 0 && self.cursor_index != text_len {
-            self.text.remove(self.cursor_index);
+            let i = self.byte_index(self.cursor_index);
+            self.text.remove(i);
+        }
+    }
+
+    /// The selected text, if any is selected.
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.text.chars().skip(start).take(end - start).collect())
+    }
+
+    /// Selects all of the text, leaving the cursor at the end.
+    pub fn select_all(&mut self) {
+        self.cursor_end();
+        self.selection_anchor = Some(0);
+    }
+
+    /// The selected text, as (first char, char after the last), if any is selected.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.selection_anchor?;
+        if anchor < self.cursor_index {
+            Some((anchor, self.cursor_index))
+        } else if anchor > self.cursor_index {
+            Some((self.cursor_index, anchor))
+        } else {
+            None
+        }
+    }
+
+    /// Called before the cursor moves. With Shift held, selects the text it moves over, starting
+    /// a selection where it is if there isn't one; without, deselects.
+    fn extend_selection(&mut self, shift: bool) {
+        if !shift {
+            self.selection_anchor = None;
+        } else if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_index);
         }
     }
 
+    /// Deletes the selected text, returning whether any was selected.
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.selection_anchor = None;
+        let (start, end) = match selection {
+            Some(selection) => selection,
+            None => return false,
+        };
+        self.draw_cursor = true;
+        self.cursor_blink_suspended = true;
+
+        let byte_range = self.byte_index(start)..self.byte_index(end);
+        self.text.replace_range(byte_range, "");
+        self.cursor_index = start;
+        if self.visible_start_index > start {
+            self.visible_start_index = start;
+        }
+        true
+    }
+
     /// Clears the contents of the text field
     pub fn clear(&mut self) {
         self.text.clear();
         self.cursor_index = 0;
+        self.selection_anchor = None;
         self.visible_start_index = 0;
-        self.cursor_blink_timestamp = None;
+        self.cursor_blink_suspended = false;
         self.draw_cursor = false;
+        self.first_visible_line = 0;
+        self.fit_to_lines();
+        self.validate();
     }
 
     /// Moves the cursor position to the right by one character
     fn move_cursor_right(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
-        if self.cursor_index < self.text.len() {
+        if self.cursor_index < self.char_count() {
             self.cursor_index += 1;
 
             if self.visible_start_index + self.max_visible_chars() < self.cursor_index {
@@ -312,7 +722,7 @@ impl TextField {
     /// Moves the cursor position to the left by one character
     fn move_cursor_left(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
         if self.cursor_index > 0 {
             self.cursor_index -= 1;
@@ -326,7 +736,7 @@ impl TextField {
     /// Moves the cursor before to the first character in the field
     fn cursor_home(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
         self.cursor_index = 0;
         self.visible_start_index = 0;
@@ -335,11 +745,12 @@ impl TextField {
     /// Moves the cursor after the last character in the field
     fn cursor_end(&mut self) {
         self.draw_cursor = true;
-        self.cursor_blink_timestamp = Some(Instant::now());
+        self.cursor_blink_suspended = true;
 
-        self.cursor_index = self.text.len();
-        if self.text.len() - self.visible_start_index > self.max_visible_chars() {
-            self.visible_start_index = self.text.len() - self.max_visible_chars();
+        let text_len = self.char_count();
+        self.cursor_index = text_len;
+        if text_len - self.visible_start_index > self.max_visible_chars() {
+            self.visible_start_index = text_len - self.max_visible_chars();
         }
     }
 }
@@ -361,8 +772,8 @@ impl Widget for TextField {
         self.z_index = new_z_index;
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        if !self.focused && self.text.is_empty() {
+    fn draw(&mut self, ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
+        if self.hide_when_empty && !self.focused && self.text.is_empty() {
             // textfield is hidden
             return Ok(());
         }
@@ -373,49 +784,89 @@ impl Widget for TextField {
         }
 
         let colored_rect;
-        if !self.text.is_empty() && !self.focused {
+        if !self.is_valid() {
             colored_rect = graphics::Mesh::new_rectangle(
                 ctx,
                 DrawMode::stroke(CHATBOX_BORDER_PIXELS),
                 self.dimensions,
-                *CHATBOX_INACTIVE_BORDER_COLOR,
+                scheme().input_invalid,
+            )?;
+        } else if !self.text.is_empty() && !self.focused {
+            colored_rect = graphics::Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(CHATBOX_BORDER_PIXELS),
+                self.dimensions,
+                scheme().chatbox_inactive_border,
             )?;
         } else {
             colored_rect = graphics::Mesh::new_rectangle(
                 ctx,
                 DrawMode::stroke(CHATBOX_BORDER_PIXELS),
                 self.dimensions,
-                *CHATBOX_BORDER_COLOR,
+                scheme().chatbox_border,
             )?;
         }
 
         graphics::draw(ctx, &colored_rect, DrawParam::default())?;
 
-        // 3.0 px added to y for central alignment
-        let text_pos = Point2 {
-            x: self.dimensions.x + CHATBOX_BORDER_PIXELS / 2.0 + 1.0,
-            y: self.dimensions.y + 3.0,
-        };
+        let text_pos = self.text_position();
+        if self.is_multiline() {
+            self.draw_lines(ctx, text_pos)?;
+            return self.draw_validation_error(ctx);
+        }
+
+        // Selected text is highlighted, except while an input method is composing
+        if let Some((start, end)) = self.selection().filter(|_| self.focused && self.preedit.is_none()) {
+            let first = start.max(self.visible_start_index);
+            let last = end.min(self.visible_start_index + self.max_visible_chars());
+            if last > first {
+                self.draw_selection(ctx, text_pos, first - self.visible_start_index, last - first)?;
+            }
+        }
 
-        let mut end = self.text.len();
-        if self.visible_start_index + self.max_visible_chars() < end {
-            end = self.visible_start_index + self.max_visible_chars();
+        // Any text being composed in an input method is shown at the cursor, as if already typed
+        let mut shown_text = self.shown_text();
+        if let Some(ref preedit) = self.preedit {
+            let preedit = if self.masked {
+                "•".repeat(preedit.chars().count())
+            } else {
+                preedit.clone()
+            };
+            let cursor_byte_index = shown_text
+                .char_indices()
+                .nth(self.cursor_index)
+                .map_or(shown_text.len(), |(i, _)| i);
+            shown_text.insert_str(cursor_byte_index, &preedit);
         }
-        let visible_text = self.text[self.visible_start_index..end].to_owned();
+        let visible_text: String = shown_text
+            .chars()
+            .skip(self.visible_start_index)
+            .take(self.max_visible_chars())
+            .collect();
 
         #[cfg(not(test))]
         {
-            draw_text(ctx, self.font_info.font, *INPUT_TEXT_COLOR, visible_text, &text_pos)?;
+            draw_text(ctx, self.font_info.font, scheme().input_text, visible_text, &text_pos)?;
         }
         #[cfg(test)]
         {
             let _ = visible_text; // suppress warning
         }
 
-        if self.draw_cursor {
-            let mut cursor_pos = text_pos.clone();
+        if self.preedit.is_some() {
+            let char_w = self.font_info.char_dimensions.x;
+            let start_x = text_pos.x + (self.cursor_index - self.visible_start_index) as f32 * char_w;
+            let end_x = self.cursor_screen_position().x;
+            if end_x > start_x {
+                let y = text_pos.y + self.font_info.char_dimensions.y;
+                let points = [Point2 { x: start_x, y }, Point2 { x: end_x, y }];
+                let underline = graphics::Mesh::new_line(ctx, &points, 1.0, scheme().input_text)?;
+                graphics::draw(ctx, &underline, DrawParam::default())?;
+            }
+        }
 
-            cursor_pos.x += (self.cursor_index - self.visible_start_index) as f32 * self.font_info.char_dimensions.x;
+        if self.draw_cursor {
+            let mut cursor_pos = self.cursor_screen_position();
 
             // Remove half the width of a character so the pipe character is at the beginning
             // of its area (like a cursor), not the center (like a character).
@@ -426,14 +877,14 @@ impl Widget for TextField {
                 draw_text(
                     ctx,
                     self.font_info.font,
-                    *INPUT_TEXT_COLOR,
+                    scheme().input_text,
                     String::from("|"),
                     &cursor_pos,
                 )?;
             }
         }
 
-        Ok(())
+        self.draw_validation_error(ctx)
     }
 
     fn rect(&self) -> Rect {
@@ -494,6 +945,134 @@ impl Widget for TextField {
     fn accepts_keyboard_events(&self) -> bool {
         true
     }
+
+    /// With a completer, Tab completes rather than moving on.
+    fn takes_tab(&self) -> bool {
+        self.completer.is_some() && !self.masked
+    }
+
+    fn accessible_name(&self) -> Option<String> {
+        if self.text.is_empty() {
+            Some(tr("a11y-text-field-empty"))
+        } else if self.masked {
+            Some(tr_args("a11y-text-field-masked", &[("count", &self.char_count())]))
+        } else {
+            Some(tr_args("a11y-text-field", &[("text", &self.text)]))
+        }
+    }
+
+    fn cursor(&self) -> Option<CursorIcon> {
+        if self.hide_when_empty && !self.focused && self.text.is_empty() {
+            None // hidden
+        } else {
+            Some(CursorIcon::Text)
+        }
+    }
+}
+
+impl TextField {
+    /// Draws the lines of a multi-line field that are scrolled into view, and the cursor.
+    fn draw_lines(&self, ctx: &mut Context, text_pos: Point2<f32>) -> GameResult<()> {
+        let lines = self.visual_lines();
+        let shown_text = self.shown_text();
+        let selection = self.selection().filter(|_| self.focused);
+        for (row, &(start, len)) in lines
+            .iter()
+            .skip(self.first_visible_line)
+            .take(self.max_lines)
+            .enumerate()
+        {
+            let line: String = shown_text.chars().skip(start).take(len).collect();
+            let line_pos = Point2 {
+                x: text_pos.x,
+                y: text_pos.y + row as f32 * self.line_height(),
+            };
+            if let Some((sel_start, sel_end)) = selection {
+                let (first, last) = (sel_start.max(start), sel_end.min(start + len));
+                if last > first {
+                    self.draw_selection(ctx, line_pos, first - start, last - first)?;
+                }
+            }
+            #[cfg(not(test))]
+            {
+                draw_text(ctx, self.font_info.font, scheme().input_text, line, &line_pos)?;
+            }
+            #[cfg(test)]
+            {
+                let _ = (&ctx, line, line_pos); // suppress warning
+            }
+        }
+
+        if self.draw_cursor {
+            let mut cursor_pos = self.cursor_screen_position();
+            cursor_pos.x -= self.font_info.char_dimensions.x / 2.0;
+            #[cfg(not(test))]
+            {
+                draw_text(
+                    ctx,
+                    self.font_info.font,
+                    scheme().input_text,
+                    String::from("|"),
+                    &cursor_pos,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Highlights `count` selected characters, starting `column` characters into the line drawn at
+    /// `line_pos`.
+    fn draw_selection(&self, ctx: &mut Context, line_pos: Point2<f32>, column: usize, count: usize) -> GameResult<()> {
+        let char_dims = self.font_info.char_dimensions;
+        let highlight = Rect::new(
+            line_pos.x + column as f32 * char_dims.x,
+            line_pos.y,
+            count as f32 * char_dims.x,
+            char_dims.y,
+        );
+        let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), highlight, scheme().input_selection)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())
+    }
+
+    /// Shows why the text is invalid in a box under the field, while it has focus.
+    fn draw_validation_error(&self, ctx: &mut Context) -> GameResult<()> {
+        let message = match self.validation_error {
+            Some(ref message) if self.focused => message,
+            _ => return Ok(()),
+        };
+        let char_dims = self.font_info.char_dimensions;
+        let tooltip = Rect::new(
+            self.dimensions.x,
+            self.dimensions.bottom() + CHATBOX_LINE_SPACING,
+            (message.chars().count() + 2) as f32 * char_dims.x,
+            char_dims.y + CHATBOX_LINE_SPACING * 2.0,
+        );
+        let background = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), tooltip, scheme().chat_pane_fill)?;
+        graphics::draw(ctx, &background, DrawParam::default())?;
+        let border = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::stroke(CHATBOX_BORDER_PIXELS),
+            tooltip,
+            scheme().input_invalid,
+        )?;
+        graphics::draw(ctx, &border, DrawParam::default())?;
+
+        #[cfg(not(test))]
+        {
+            let text_pos = Point2 {
+                x: tooltip.x + char_dims.x,
+                y: tooltip.y + CHATBOX_LINE_SPACING,
+            };
+            draw_text(
+                ctx,
+                self.font_info.font,
+                scheme().input_invalid,
+                message.clone(),
+                &text_pos,
+            )?;
+        }
+        Ok(())
+    }
 }
 
 widget_from_id!(TextField);
@@ -740,4 +1319,154 @@ mod test {
 
         assert_eq!(tf.text, "");
     }
+
+    #[test]
+    fn test_edit_multibyte_text() {
+        let mut tf = create_dummy_textfield();
+
+        for ch in "日本語".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+        assert_eq!(tf.cursor_index, 3);
+
+        tf.move_cursor_left();
+        tf.add_char_at_cursor('の');
+        assert_eq!(tf.text, "日本の語");
+
+        tf.remove_left_of_cursor();
+        tf.remove_right_of_cursor();
+        assert_eq!(tf.text, "日本");
+
+        tf.cursor_end();
+        assert_eq!(tf.cursor_index, 2);
+        tf.move_cursor_right();
+        assert_eq!(tf.cursor_index, 2);
+    }
+
+    #[test]
+    fn test_preedit_moves_cursor_but_not_text() {
+        let mut tf = create_dummy_textfield();
+        let char_w = tf.font_info.char_dimensions.x;
+        tf.add_char_at_cursor('A');
+        let x_before = tf.cursor_screen_position().x;
+
+        tf.set_preedit(Some("にほん".to_owned()));
+        assert_eq!(tf.cursor_screen_position().x, x_before + 3.0 * char_w);
+        assert_eq!(tf.text, "A");
+
+        // The input method then types the confirmed text
+        tf.set_preedit(None);
+        for ch in "日本".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+        assert_eq!(tf.text, "A日本");
+        assert_eq!(tf.cursor_screen_position().x, x_before + 2.0 * char_w);
+    }
+
+    #[test]
+    fn test_complete_cycles_through_candidates() {
+        let mut tf = create_dummy_textfield();
+        tf.set_completer(Box::new(|before_cursor: &str| {
+            let word = before_cursor.rsplit(' ').next().unwrap();
+            ["alice", "alfred", "bob"]
+                .iter()
+                .filter(|name| name.starts_with(word))
+                .map(|name| name.to_string())
+                .collect()
+        }));
+        for ch in "hi al".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+
+        tf.complete();
+        assert_eq!(tf.text, "hi alice");
+        assert_eq!(tf.cursor_index, 8);
+        tf.complete();
+        assert_eq!(tf.text, "hi alfred");
+        tf.complete();
+        assert_eq!(tf.text, "hi alice");
+
+        // A new word starts a new completion
+        tf.completion = None;
+        tf.add_char_at_cursor(' ');
+        tf.add_char_at_cursor('x');
+        tf.complete();
+        assert_eq!(tf.text, "hi alice x");
+        assert!(tf.completion.is_none());
+    }
+
+    #[test]
+    fn test_multiline_grows_then_scrolls() {
+        let mut tf = create_dummy_textfield(); // 20 chars wide, lines 7 pixels apart
+        tf.set_multiline(3);
+        for ch in "hello\nworld".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+        tf.fit_to_lines();
+        assert_eq!(tf.visual_lines(), vec![(0, 5), (6, 5)]);
+        assert_eq!(tf.rect().h, 107.0);
+        assert_eq!(tf.cursor_line_and_column(), (1, 5));
+
+        tf.move_cursor_to_line(false);
+        assert_eq!(tf.cursor_index, 5);
+        tf.cursor_end();
+
+        // A line too long for the field wraps, and past three lines the field scrolls
+        for _ in 0..25 {
+            tf.add_char_at_cursor('x');
+        }
+        tf.fit_to_lines();
+        assert_eq!(tf.visual_lines(), vec![(0, 5), (6, 20), (26, 10)]);
+        tf.add_char_at_cursor('\n');
+        tf.fit_to_lines();
+        assert_eq!(tf.rect().h, 114.0);
+        assert_eq!(tf.first_visible_line, 1);
+        assert_eq!(tf.text(), Some("hello\nworldxxxxxxxxxxxxxxxxxxxxxxxxx".to_owned()));
+
+        tf.clear();
+        assert_eq!(tf.rect().h, 100.0);
+        assert_eq!(tf.first_visible_line, 0);
+    }
+
+    #[test]
+    fn test_validators_flag_invalid_text() {
+        let mut tf = create_dummy_textfield();
+        tf.add_validator(Validator::Numeric);
+        tf.add_validator(Validator::MaxLength(3));
+        assert!(tf.is_valid());
+
+        for ch in "12".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+        tf.validate();
+        assert!(tf.is_valid());
+
+        tf.add_char_at_cursor('a');
+        tf.validate();
+        assert!(!tf.is_valid());
+        assert!(tf.validation_error().is_some());
+
+        tf.set_text("1234".to_owned());
+        assert!(!tf.is_valid());
+        tf.set_text("123".to_owned());
+        assert!(tf.is_valid());
+        tf.clear();
+        assert!(tf.is_valid());
+    }
+
+    #[test]
+    fn test_masked_text_is_shown_as_bullets() {
+        let mut tf = create_dummy_textfield();
+        tf.set_completer(Box::new(|_: &str| vec!["hunter2".to_owned()]));
+        tf.set_masked(true);
+        for ch in "pässword".chars() {
+            tf.add_char_at_cursor(ch);
+        }
+        assert_eq!(tf.shown_text(), "••••••••");
+        assert_eq!(tf.text(), Some("pässword".to_owned()));
+        assert!(!tf.takes_tab());
+        tf.complete();
+        assert_eq!(tf.text(), Some("pässword".to_owned()));
+        assert!(!format!("{:?}", tf).contains("pässword"));
+    }
 }
@@ -0,0 +1,131 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Timers that widgets start from their handlers with `UIContext::start_timer`. A `Layering` keeps
+//! the timers of its widgets and counts them down on each `Update`; when one runs out, its widget
+//! gets a `Timer` event carrying the timer's ID.
+
+use std::time::Duration;
+
+use id_tree::NodeId;
+
+pub type TimerId = u32;
+
+#[derive(Debug)]
+struct Timer {
+    id:        TimerId,
+    widget_id: NodeId,
+    remaining: Duration,
+    repeat:    Option<Duration>, // the period of a repeating timer
+}
+
+#[derive(Debug, Default)]
+pub struct Timers {
+    next_id: TimerId,
+    timers:  Vec<Timer>,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Timers::default()
+    }
+
+    /// Starts a timer for the widget that runs out after `delay`, and then every `delay` after
+    /// that if `repeating`.
+    pub fn start(&mut self, widget_id: NodeId, delay: Duration, repeating: bool) -> TimerId {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.timers.push(Timer {
+            id,
+            widget_id,
+            remaining: delay,
+            repeat: if repeating { Some(delay) } else { None },
+        });
+        id
+    }
+
+    /// Stops a timer. Does nothing if it already ran out (and wasn't repeating) or was cancelled.
+    pub fn cancel(&mut self, id: TimerId) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    /// Stops the timers of the widgets, because they were removed.
+    pub fn forget_widgets(&mut self, widget_ids: &[NodeId]) {
+        self.timers.retain(|timer| !widget_ids.contains(&timer.widget_id));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Counts the timers down by `elapsed`, returning those that ran out, with their widgets, in the
+    /// order they were started. A repeating timer that ran out more than once during `elapsed` is
+    /// returned just once, as a slow frame shouldn't make a widget catch up on every missed tick.
+    pub fn advance(&mut self, elapsed: Duration) -> Vec<(NodeId, TimerId)> {
+        let mut fired = vec![];
+        for timer in self.timers.iter_mut() {
+            if timer.remaining > elapsed {
+                timer.remaining -= elapsed;
+                continue;
+            }
+            fired.push((timer.widget_id.clone(), timer.id));
+            timer.remaining = match timer.repeat {
+                Some(period) => {
+                    let overshoot = (elapsed - timer.remaining).as_nanos() % period.as_nanos().max(1);
+                    period - Duration::from_nanos(overshoot as u64)
+                }
+                None => Duration::from_secs(0), // done
+            };
+        }
+        self.timers
+            .retain(|timer| timer.repeat.is_some() || timer.remaining > Duration::from_secs(0));
+        fired
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use id_tree::{InsertBehavior, Node, Tree};
+
+    #[test]
+    fn test_one_shot_and_repeating_timers() {
+        let mut tree = Tree::new();
+        let widget_id = tree.insert(Node::new(()), InsertBehavior::AsRoot).unwrap();
+        let ms = Duration::from_millis;
+
+        let mut timers = Timers::new();
+        let once = timers.start(widget_id.clone(), ms(100), false);
+        let every = timers.start(widget_id.clone(), ms(40), true);
+        let cancelled = timers.start(widget_id.clone(), ms(10), false);
+        timers.cancel(cancelled);
+
+        assert_eq!(timers.advance(ms(30)), vec![]);
+        assert_eq!(timers.advance(ms(30)), vec![(widget_id.clone(), every)]);
+        // ran out twice, but fires once; 20 ms is left until the next time
+        assert_eq!(
+            timers.advance(ms(80)),
+            vec![(widget_id.clone(), once), (widget_id.clone(), every)]
+        );
+        assert_eq!(timers.advance(ms(19)), vec![]);
+        assert_eq!(timers.advance(ms(1)), vec![(widget_id.clone(), every)]);
+
+        timers.forget_widgets(&[widget_id]);
+        assert!(timers.is_empty());
+    }
+}
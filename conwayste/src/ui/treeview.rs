@@ -160,6 +160,12 @@ impl<'a, T> TreeView<'a, T> {
         Ok((node_mut_ref, subtree))
     }
 
+    /// The NodeId of the Node this view is restricted on, or None if it is a view of the entire
+    /// tree.
+    pub fn root_id(&self) -> Option<&NodeId> {
+        self.restriction.root()
+    }
+
     /// If this tree has any Nodes at all, this will return (as Some) an iterator over the root
     /// node's children.
     #[allow(unused)]
@@ -0,0 +1,111 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+use std::fmt;
+
+use regex::Regex;
+
+use crate::i18n::{tr, tr_args};
+
+/// A rule the text in a `TextField` must follow. When the text breaks it, the field is drawn with
+/// a red border and the rule's message is shown under it.
+pub enum Validator {
+    /// No more than this many characters.
+    MaxLength(usize),
+    /// Only the digits 0-9.
+    Numeric,
+    /// The whole text matches the regular expression; otherwise the message is shown.
+    Pattern(Regex, String),
+    /// Returns the message to show if the text is not acceptable.
+    Custom(Box<dyn Fn(&str) -> Result<(), String> + Send>),
+}
+
+impl fmt::Debug for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Validator::MaxLength(max) => write!(f, "MaxLength({})", max),
+            Validator::Numeric => write!(f, "Numeric"),
+            Validator::Pattern(regex, _) => write!(f, "Pattern({:?})", regex.as_str()),
+            Validator::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Validator {
+    /// A `Pattern` validator. The pattern is anchored, so it must match all of the text.
+    ///
+    /// # Panics
+    /// If `pattern` is not a valid regular expression; patterns are written into the code, so
+    /// this is a bug.
+    pub fn pattern(pattern: &str, message: String) -> Self {
+        let anchored = format!("^(?:{})$", pattern);
+        Validator::Pattern(Regex::new(&anchored).expect("invalid validator pattern"), message)
+    }
+
+    /// Checks `text`, returning the message to show if it breaks this rule. Empty text is left to
+    /// whoever requires the field to be filled in.
+    pub fn check(&self, text: &str) -> Result<(), String> {
+        match self {
+            Validator::MaxLength(max) => {
+                if text.chars().count() > *max {
+                    return Err(tr_args("validation-too-long", &[("max", max)]));
+                }
+            }
+            Validator::Numeric => {
+                if !text.chars().all(|ch| ch.is_ascii_digit()) {
+                    return Err(tr("validation-not-numeric"));
+                }
+            }
+            Validator::Pattern(regex, message) => {
+                if !text.is_empty() && !regex.is_match(text) {
+                    return Err(message.clone());
+                }
+            }
+            Validator::Custom(check) => return check(text),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validators() {
+        assert!(Validator::MaxLength(3).check("abc").is_ok());
+        assert!(Validator::MaxLength(3).check("abcd").is_err());
+        assert!(Validator::MaxLength(3).check("äöü").is_ok());
+
+        assert!(Validator::Numeric.check("2048").is_ok());
+        assert!(Validator::Numeric.check("").is_ok());
+        assert!(Validator::Numeric.check("20x").is_err());
+
+        let name = Validator::pattern("[a-z]+", "lowercase only".to_owned());
+        assert!(name.check("room").is_ok());
+        assert!(name.check("").is_ok());
+        assert_eq!(name.check("my room"), Err("lowercase only".to_owned()));
+
+        let even = Validator::Custom(Box::new(|text| match text.parse::<u32>() {
+            Ok(n) if n % 2 == 0 => Ok(()),
+            _ => Err("even numbers only".to_owned()),
+        }));
+        assert!(even.check("4").is_ok());
+        assert_eq!(even.check("5"), Err("even numbers only".to_owned()));
+    }
+}
@@ -17,6 +17,7 @@
  *  <http://www.gnu.org/licenses/>. */
 
 use ggez::graphics::Rect;
+use ggez::input::mouse::CursorIcon;
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
 
@@ -24,7 +25,7 @@ use downcast_rs::Downcast;
 
 use id_tree::NodeId;
 
-use super::{context, UIResult};
+use super::{batch::ShapeBatch, context, focus::FocusCycle, textcache::TextCache, UIResult};
 
 /// A user interface element trait that defines graphical, interactive behavior to be specified.
 /// Relies on the `downcast_rs` crate to be able to transform widgets into their specific
@@ -48,7 +49,8 @@ pub trait Widget: Downcast + std::fmt::Debug {
     }
 
     /// Called upon each graphical draw tick. This should be where the widget's graphics takes place.
-    fn draw(&mut self, _ctx: &mut Context) -> GameResult<()> {
+    /// Any text is drawn from `texts`, the layering's cache, so that it is only laid out once.
+    fn draw(&mut self, _ctx: &mut Context, _texts: &mut TextCache) -> GameResult<()> {
         Ok(())
     }
 
@@ -88,6 +90,80 @@ pub trait Widget: Downcast + std::fmt::Debug {
     fn accepts_keyboard_events(&self) -> bool {
         false
     }
+
+    /// Whether Tab goes to this widget while it has focus, rather than moving focus on to the
+    /// next widget. Shift-Tab always moves focus.
+    fn takes_tab(&self) -> bool {
+        false
+    }
+
+    /// What a screen reader should say when this widget gains focus, if anything.
+    fn accessible_name(&self) -> Option<String> {
+        None
+    }
+
+    /// The mouse cursor to show while the mouse is over this widget, or `None` to leave it to the
+    /// widgets under it (and in the end the plain arrow).
+    fn cursor(&self) -> Option<CursorIcon> {
+        None
+    }
+
+    /// Whether the child widget, with everything in it, is shown. A container that hides some of
+    /// its children, like a `TabContainer`, returns false for them so that the layering doesn't
+    /// draw them; it should not pass events on to them either.
+    fn shows_child(&self, _child_id: &NodeId) -> bool {
+        true
+    }
+
+    /// The part of the screen the widgets in this container can be seen in, if it only shows part
+    /// of them, like a `ScrollPane`. The layering cuts off what is drawn outside it.
+    fn clip_rect(&self) -> Option<Rect> {
+        None
+    }
+
+    /// If all the widget draws is plain shapes, adds them to `batch` and returns true, so that the
+    /// layering can draw them in one mesh with those of the widgets drawn around it, rather than
+    /// calling `draw`.
+    fn batch_shapes(&self, _batch: &mut ShapeBatch) -> bool {
+        false
+    }
+
+    /// If other widgets can be nested in this one, like in a `Pane`, implementors should have this
+    /// return Some(self) here.
+    fn as_container(&self) -> Option<&dyn Container> {
+        None
+    }
+
+    /// Like `as_container`, but mutable.
+    fn as_container_mut(&mut self) -> Option<&mut dyn Container> {
+        None
+    }
 }
 
 impl_downcast!(Widget);
+
+/// A widget that other widgets are nested in with `InsertLocation::ToNestedContainer`. The
+/// layering tells it when they are added and removed, and Tab goes to it while it has focus, so
+/// that it can move focus among them.
+pub trait Container {
+    /// Where on the screen the widgets nested in this one are placed relative to.
+    fn child_origin(&self) -> Vector2<f32>;
+
+    /// Whether no more widgets can be nested in this one.
+    fn is_full(&self) -> bool {
+        false
+    }
+
+    /// Called by the layering when a widget of size `(w, h)` is nested in this one, after checking
+    /// `is_full`. Returns whether keyboard focus gets to it through this container rather than the
+    /// focus cycle of the layer.
+    fn child_added(&mut self, child_id: NodeId, size: (f32, f32), accepts_keyboard_events: bool) -> bool;
+
+    /// Called by the layering when a widget nested in this one is removed.
+    fn child_removed(&mut self, child_id: &NodeId);
+
+    /// The focus cycle of the nested widgets, if this container keeps one, like a `Pane`.
+    fn focus_cycle_mut(&mut self) -> Option<&mut FocusCycle> {
+        None
+    }
+}
@@ -0,0 +1,83 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Word wrapping for the chatbox. This only uses `std`, so that the chat reflow benchmark can
+//! include the file as a module of its own.
+
+fn count_chars(msg: &str) -> usize {
+    let mut count = 0;
+    for _ in msg.chars() {
+        count += 1;
+    }
+    count
+}
+
+/// Breaks the message up into lines that are at most `max_chars_per_line` characters long,
+/// between words where possible. A line may end with a space.
+pub fn wrap_words(msg: &str, max_chars_per_line: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut s = String::with_capacity(max_chars_per_line);
+
+    let mut chars_added = 0;
+    for word in msg.split_whitespace() {
+        let word_chars = count_chars(word);
+
+        // If the word can fit on the next line, but not the current line
+        if chars_added != 0 && chars_added + word_chars > max_chars_per_line && word_chars <= max_chars_per_line {
+            lines.push(s.clone());
+            s.clear();
+            chars_added = 0;
+        }
+
+        if word_chars > max_chars_per_line {
+            // If word is too long to fit on a line, then break the word into multiple lines
+            for ch in word.chars() {
+                if chars_added == max_chars_per_line {
+                    lines.push(s.clone());
+                    s.clear();
+                    chars_added = 0;
+                }
+
+                s.push(ch);
+                chars_added += 1;
+            }
+            // add a space after the long word and continue forward
+            if !s.is_empty() {
+                s.push(' ');
+                chars_added += 1;
+            }
+            continue;
+        }
+
+        for ch in word.chars() {
+            s.push(ch);
+            chars_added += 1;
+        }
+
+        if chars_added + 1 <= max_chars_per_line {
+            s.push(' ');
+            chars_added += 1;
+        }
+    }
+
+    if !s.is_empty() {
+        lines.push(s);
+    }
+
+    lines
+}
@@ -18,22 +18,35 @@
 
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::{Arc, Mutex};
 
 use ggez::graphics::{Font, Rect};
 use ggez::mint::Point2;
 use ggez::Context;
 
 use id_tree::NodeId;
+use netwayste::net::WinCondition;
 
-use crate::config::Config;
+use crate::colorblind::Palette;
+use crate::config::{Config, Settings};
 use crate::constants;
+use crate::crash;
+use crate::i18n::{self, tr, tr_args};
+use crate::layout_file::{LayoutBindings, LayoutFile, LayoutHandler};
+use crate::pattern_editor::{PatternEditor, Tool};
+use crate::pattern_library::Category;
+use crate::resources::Texture;
+use crate::room_map::{MapTool, RoomMapEditor};
+use crate::room_settings::RoomSettingsEditor;
+use crate::theme::BoardTheme;
 use crate::ui::{
-    color_with_alpha, common, context, Button, Chatbox, Checkbox, GameArea, InsertLocation, Label, Layering, Pane,
-    TextField, UIResult, Widget,
+    common, context, Button, ButtonStyle, Chatbox, Checkbox, ColorPicker, Dropdown, Form, FormBuilder, FormHandle,
+    GameArea, Image, InsertLocation, Label, Layering, MapGrid, Pane, PatternGrid, PlayerList, ScrollPane, Slider,
+    TabContainer, TextField, UIError, UIResult, Validator, Widget,
 };
+use crate::user_data;
 use crate::Screen;
 
-use chromatica::css;
 use context::{
     EmitEvent, // so we can call .on(...) on widgets that implement this
     EventType,
@@ -86,7 +99,30 @@ macro_rules! add_widget_from_screen_id {
 }
 
 pub struct UILayout {
-    pub layers: HashMap<Screen, Layering>,
+    pub layers:         HashMap<Screen, Layering>,
+    pub create_room:    CreateRoomForm,
+    pub pattern_editor: Arc<Mutex<PatternEditor>>, // kept when the pattern editor screen is rebuilt
+    pub library_filter: Arc<Mutex<Option<Category>>>, // the category the pattern library shows; None for all
+    pub shared_request: Arc<Mutex<Option<SharedPatternsRequest>>>, // set by the shared patterns screen's buttons
+    pub import_request: Arc<Mutex<bool>>, // set by the pattern import screen's Download button
+    pub room_map:       Arc<Mutex<RoomMapEditor>>, // the map of the room the player is in
+    pub room_settings:  Arc<Mutex<RoomSettingsEditor>>, // how the next game in that room is played
+}
+
+/// What the buttons of the shared patterns screen ask the client to do on its next update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SharedPatternsRequest {
+    Share, // the pattern picked for placing
+    PreviousPage,
+    NextPage,
+}
+
+/// The form in the room lobby for making a new room and moving to it. The client reads it once the
+/// button is clicked.
+pub struct CreateRoomForm {
+    pub form:      Form,
+    pub room_name: FormHandle<TextField>,
+    pub create:    FormHandle<Button>,
 }
 
 pub struct StaticNodeIds {
@@ -94,10 +130,14 @@ pub struct StaticNodeIds {
     // The fields below correspond to static ui elements that the client may need to interact with
     // regardless of what is displayed on screen. For example, new chat messages should always be
     // forwarded to the UI widget.
-    pub chatbox_id:      NodeId,
-    pub chatbox_pane_id: NodeId,
-    pub chatbox_tf_id:   NodeId,
-    pub game_area_id:    NodeId,
+    pub chatbox_id:           NodeId,
+    pub chatbox_pane_id:      NodeId,
+    pub chatbox_tf_id:        NodeId,
+    pub game_area_id:         NodeId,
+    pub team_button_ids:      Vec<NodeId>, // indexed by team
+    pub start_game_button_id: NodeId,
+    pub add_bot_button_id:    NodeId,
+    pub color_picker_id:      NodeId,
 }
 
 /// `UILayout` is responsible for the definition and storage of UI elements.
@@ -111,144 +151,763 @@ impl UILayout {
         self.layers.get_mut(&screen)
     }
 
+    /// The options menu. `script_names` are the user scripts that can be switched on and off.
     fn build_options_menu(
         ctx: &mut Context,
         config: &Config,
         default_font_info: common::FontInfo,
+        script_names: &[String],
     ) -> UIResult<Layering> {
         let mut layer_options = Layering::new();
-        let mut fullscreen_checkbox = Box::new(Checkbox::new(
-            ctx,
-            config.get().video.fullscreen,
+        let value_color = constants::colors::scheme().value_text;
+
+        // One tab per page below, in the same order
+        let tabs = TabContainer::new(
             default_font_info,
-            "Toggle FullScreen".to_owned(),
-            Rect::new(10.0, 210.0, 20.0, 20.0),
-        ));
+            Rect::new(10.0, 150.0, 600.0, 300.0),
+            vec![
+                tr("options-tab-video"),
+                tr("options-tab-audio"),
+                tr("options-tab-controls"),
+                tr("options-tab-network"),
+            ],
+        );
+        let page_rect = tabs.page_rect();
+        let tabs_id = layer_options.add_widget(Box::new(tabs), InsertLocation::AtCurrentLayer)?;
 
-        let name_color = color_with_alpha(css::WHITE, 1.0);
-        let value_color = color_with_alpha(css::AQUAMARINE, 1.0);
-        layer_options.add_widget(
-            Box::new(Label::new(
-                ctx,
-                default_font_info,
-                "Resolution".to_owned(),
-                name_color,
-                Point2 { x: 10.0, y: 300.0 },
-            )),
-            InsertLocation::AtCurrentLayer,
+        // there are more video settings than fit on the page
+        let video_scroll = Box::new(ScrollPane::new(Rect::new(0.0, 0.0, page_rect.w, page_rect.h)));
+        let video_scroll_id = layer_options.add_widget(video_scroll, InsertLocation::ToNestedContainer(&tabs_id))?;
+        let mut form = FormBuilder::new(default_font_info);
+        let resolution = form.value(tr("options-resolution"), "<no data>".to_owned());
+        let fullscreen = form.checkbox(tr("options-fullscreen"), config.get().video.fullscreen);
+        let streamer_mode = form.checkbox(tr("options-streamer-mode"), config.get().video.streamer_mode);
+        let high_contrast = form.checkbox(tr("options-high-contrast"), config.get().accessibility.high_contrast);
+        let cell_marks = form.checkbox(tr("options-cell-marks"), config.get().accessibility.cell_marks);
+        let menu_background = form.checkbox(tr("options-menu-background"), config.get().video.menu_background);
+        let glow = form.checkbox(tr("options-glow"), config.get().video.glow);
+        let scanlines = form.checkbox(tr("options-scanlines"), config.get().video.scanlines);
+        let trails = form.checkbox(tr("options-trails"), config.get().video.trails);
+        // "custom" uses the colors in the [theme] section of the config
+        let theme_names = BoardTheme::ALL
+            .iter()
+            .map(|theme| tr(&format!("theme-{}", theme.name())))
+            .collect();
+        let theme = form.dropdown(tr("options-theme"), 200.0, theme_names, theme_index(config));
+        let video_form = form.build(
+            ctx,
+            &mut layer_options,
+            Point2 { x: 0.0, y: 0.0 },
+            InsertLocation::ToNestedContainer(&video_scroll_id),
         )?;
 
-        let mut resolution_value_label = Box::new(Label::new(
-            ctx,
-            default_font_info,
-            "<no data>".to_owned(),
-            value_color,
-            Point2 { x: 200.0, y: 300.0 },
-        ));
-        resolution_value_label
-            .on(context::EventType::Update, Box::new(resolution_update_handler))
+        video_form
+            .widget_mut(&mut layer_options, resolution)?
+            .on(EventType::Update, Box::new(resolution_update_handler))
+            .unwrap();
+        let toggles: [(FormHandle<Checkbox>, fn(&mut Settings, bool)); 8] = [
+            (fullscreen, |settings, on| settings.video.fullscreen = on),
+            (streamer_mode, |settings, on| settings.video.streamer_mode = on),
+            // the client rebuilds the menus in the new colors
+            (high_contrast, |settings, on| settings.accessibility.high_contrast = on),
+            (cell_marks, |settings, on| settings.accessibility.cell_marks = on),
+            (menu_background, |settings, on| settings.video.menu_background = on),
+            (glow, |settings, on| settings.video.glow = on),
+            (scanlines, |settings, on| settings.video.scanlines = on),
+            (trails, |settings, on| settings.video.trails = on),
+        ];
+        for &(checkbox, set) in toggles.iter() {
+            video_form
+                .widget_mut(&mut layer_options, checkbox)?
+                .on(EventType::ValueChanged, get_setting_toggle_handler(set))
+                .unwrap(); // unwrap OK because not in handler
+        }
+        let theme_dropdown = video_form.widget_mut(&mut layer_options, theme)?;
+        theme_dropdown
+            .on(EventType::ValueChanged, Box::new(theme_select_handler))
+            .unwrap();
+        theme_dropdown
+            .on(EventType::Update, Box::new(theme_dropdown_update_handler))
             .unwrap();
-        layer_options.add_widget(resolution_value_label, InsertLocation::AtCurrentLayer)?;
 
-        // unwrap OK here because we are not calling .on from within a handler
-        fullscreen_checkbox
-            .on(EventType::Click, Box::new(fullscreen_toggle_handler))
+        // volumes are percentages
+        let mut form = FormBuilder::new(default_font_info);
+        let master = config.get().audio.master as i32;
+        let master_volume = form.slider(tr("options-master-volume"), 250.0, 0, 100, 5, master);
+        let music = config.get().audio.music as i32;
+        let music_volume = form.slider(tr("options-music-volume"), 250.0, 0, 100, 5, music);
+        let audio_form = form.build(
+            ctx,
+            &mut layer_options,
+            Point2 { x: 0.0, y: 0.0 },
+            InsertLocation::ToNestedContainer(&tabs_id),
+        )?;
+
+        audio_form
+            .widget_mut(&mut layer_options, master_volume)?
+            .on(EventType::ValueChanged, Box::new(master_volume_handler))
+            .unwrap();
+        audio_form
+            .widget_mut(&mut layer_options, music_volume)?
+            .on(EventType::ValueChanged, Box::new(music_volume_handler))
             .unwrap();
-        layer_options.add_widget(fullscreen_checkbox, InsertLocation::AtCurrentLayer)?;
 
-        let playername_label = Box::new(Label::new(
+        // there may be more scripts than fit on the page
+        let scripts_scroll = Box::new(ScrollPane::new(Rect::new(0.0, 0.0, page_rect.w, page_rect.h)));
+        let scripts_scroll_id =
+            layer_options.add_widget(scripts_scroll, InsertLocation::ToNestedContainer(&tabs_id))?;
+        let scripts_height = 20.0 + 30.0 * script_names.len() as f32;
+        let scripts_pane = if scripts_height > page_rect.h {
+            Pane::new(Rect::new(
+                0.0,
+                0.0,
+                page_rect.w - constants::SCROLLBAR_WIDTH,
+                scripts_height,
+            ))
+        } else {
+            Pane::new(Rect::new(0.0, 0.0, page_rect.w, page_rect.h))
+        };
+        let scripts_pane_id = layer_options.add_widget(
+            Box::new(scripts_pane),
+            InsertLocation::ToNestedContainer(&scripts_scroll_id),
+        )?;
+        if script_names.is_empty() {
+            layer_options.add_widget(
+                Box::new(Label::new(
+                    ctx,
+                    default_font_info,
+                    tr_args("options-no-scripts", &[("dir", &constants::SCRIPTS_DIR)]),
+                    value_color,
+                    Point2 { x: 10.0, y: 10.0 },
+                )),
+                InsertLocation::ToNestedContainer(&scripts_pane_id),
+            )?;
+        } else {
+            for (i, name) in script_names.iter().enumerate() {
+                let mut script_checkbox = Box::new(Checkbox::new(
+                    ctx,
+                    config.get().gameplay.enabled_scripts.contains(name),
+                    default_font_info,
+                    name.clone(),
+                    Rect::new(10.0, 10.0 + 30.0 * i as f32, 20.0, 20.0),
+                ));
+                // unwrap OK here because we are not calling .on from within a handler
+                script_checkbox
+                    .on(EventType::ValueChanged, Box::new(script_toggle_handler))
+                    .unwrap();
+                script_checkbox
+                    .on(EventType::Update, Box::new(script_checkbox_update_handler))
+                    .unwrap();
+                layer_options.add_widget(script_checkbox, InsertLocation::ToNestedContainer(&scripts_pane_id))?;
+            }
+        }
+
+        let mut form = FormBuilder::new(default_font_info);
+        let player_name = form.text_field(tr("options-player-name"), 200.0, String::new());
+        let network_form = form.build(
             ctx,
-            default_font_info,
-            "Player Name:".to_owned(),
-            name_color,
+            &mut layer_options,
             Point2 { x: 0.0, y: 0.0 },
-        ));
-        let pnlabel_x = playername_label.position().x;
-        let pnlabel_y = playername_label.position().y;
-        let pnlabel_r_edge = playername_label.size().0 + pnlabel_x;
-        let mut playername_tf = Box::new(TextField::new(
-            default_font_info,
-            Rect::new(pnlabel_r_edge + 20.0, pnlabel_y, 200.0, 30.0),
-        ));
+            InsertLocation::ToNestedContainer(&tabs_id),
+        )?;
+
+        layer_options.set_widget_name("player-name", network_form.widget_id(player_name));
+
+        // unwrap OK here because we are not calling .on from within a handler
+        let playername_tf = network_form.widget_mut(&mut layer_options, player_name)?;
         playername_tf.on(EventType::Load, Box::new(load_player_name)).unwrap();
         playername_tf.on(EventType::Save, Box::new(save_player_name)).unwrap();
 
-        let mut playername_pane = Box::new(Pane::new(Rect::new(10.0, 0.0, 0.0, 0.0)));
-        playername_pane.set_rect(Rect::new(
-            10.0,
-            400.0,
-            playername_label.size().0 + playername_tf.size().0,
-            f32::max(playername_label.size().1, playername_tf.size().1),
-        ))?;
-        playername_pane.border = 0.0;
-
-        let playername_pane_id = layer_options.add_widget(playername_pane, InsertLocation::AtCurrentLayer)?;
-        layer_options.add_widget(playername_label, InsertLocation::ToNestedContainer(&playername_pane_id))?;
-        layer_options.add_widget(playername_tf, InsertLocation::ToNestedContainer(&playername_pane_id))?;
+        LayoutFile::load_and_build("options", ctx, default_font_info, &mut layer_options, &layout_bindings(config))?;
 
         Ok(layer_options)
     }
 
-    fn build_main_menu(ctx: &mut Context, default_font_info: common::FontInfo) -> UIResult<Layering> {
+    fn build_main_menu(ctx: &mut Context, config: &Config, default_font_info: common::FontInfo) -> UIResult<Layering> {
         let mut layer_mainmenu = Layering::new();
+        LayoutFile::load_and_build("main_menu", ctx, default_font_info, &mut layer_mainmenu, &layout_bindings(config))?;
+        Ok(layer_mainmenu)
+    }
 
-        // Create a new pane, and add two test buttons to it.
-        let pane = Box::new(Pane::new(Rect::new_i32(20, 20, 410, 450)));
-        let mut serverlist_button = Box::new(Button::new(ctx, default_font_info, "Server List".to_owned()));
-        serverlist_button.set_rect(Rect::new(10.0, 10.0, 180.0, 50.0))?;
-        serverlist_button
-            .on(EventType::Click, Box::new(server_list_click_handler))
-            .unwrap(); // unwrap OK
+    /// The room lobby. Returns the layering along with the IDs of the team selection buttons, the
+    /// start game button, the add bot button, and the color picker, which the client hooks up to the
+    /// network, and the Create Room form.
+    fn build_in_room_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        room_map: &Arc<Mutex<RoomMapEditor>>,
+        room_settings: &Arc<Mutex<RoomSettingsEditor>>,
+    ) -> UIResult<(Layering, Vec<NodeId>, NodeId, NodeId, NodeId, CreateRoomForm)> {
+        let mut layer_inroom = Layering::new();
+
+        let mut bindings = layout_bindings(config);
+        bindings
+            .texts
+            .insert("map-spawn", map_spawn_button_text(&room_map.lock().unwrap()));
+        bindings
+            .texts
+            .insert("map-fog-free", map_fog_free_button_text(&room_map.lock().unwrap()));
+        bindings
+            .texts
+            .insert("room-teams", room_teams_button_text(&room_settings.lock().unwrap()));
+        bindings
+            .texts
+            .insert("room-series", room_series_button_text(&room_settings.lock().unwrap()));
+        bindings
+            .texts
+            .insert("room-win", room_win_button_text(&room_settings.lock().unwrap()));
+        let ids = LayoutFile::load_and_build("in_room", ctx, default_font_info, &mut layer_inroom, &bindings)?;
+        let named = |name: &str| {
+            ids.get(name).cloned().ok_or_else(|| {
+                Box::new(UIError::WidgetNotFound {
+                    reason: format!("the room lobby's layout has no widget named {:?}", name),
+                })
+            })
+        };
+        let start_game_button_id = named("start-game")?;
+        let add_bot_button_id = named("add-bot")?;
+        let teampane_id = named("teams")?;
 
-        let mut start_1p_game_button = Box::new(Button::new(
+        let mut team_button_ids = vec![];
+        for team in 0..constants::MAX_TEAMS {
+            let mut team_button = Box::new(Button::new(
+                ctx,
+                default_font_info,
+                tr_args("room-join-team", &[("team", &(team + 1))]),
+            ));
+            place_button(
+                &mut team_button,
+                Rect::new(10.0, 10.0 + 60.0 * team as f32, 180.0, 50.0),
+            )?;
+            let id = layer_inroom.add_widget(team_button, InsertLocation::ToNestedContainer(&teampane_id))?;
+            team_button_ids.push(id);
+        }
+
+        let color_picker = Box::new(ColorPicker::new(
+            config.profile_color(),
+            Rect::new(20.0, 470.0, 240.0, 124.0),
+        ));
+        let color_picker_id = layer_inroom.add_widget(color_picker, InsertLocation::AtCurrentLayer)?;
+
+        // The server only takes room names of ASCII characters, up to a length
+        let mut form = FormBuilder::new(default_font_info);
+        let room_name = form.validated_text_field(
+            tr("room-name"),
+            200.0,
+            String::new(),
+            vec![
+                Validator::MaxLength(constants::MAX_ROOM_NAME_LEN),
+                Validator::pattern("[A-Za-z0-9 _-]*", tr("validation-room-name")),
+            ],
+        );
+        let create = form.submit_button(tr("room-create"));
+        let form = form.build(
             ctx,
+            &mut layer_inroom,
+            Point2 { x: 500.0, y: 150.0 },
+            InsertLocation::AtCurrentLayer,
+        )?;
+        layer_inroom.set_widget_name("room-name", form.widget_id(room_name));
+
+        // The client lists the other players in it, and takes what is picked from their menus
+        let roster = Box::new(PlayerList::new(
             default_font_info,
-            "Start Single Player Game".to_owned(),
+            Rect::new(500.0, 330.0, 380.0, 110.0),
         ));
-        start_1p_game_button.set_rect(Rect::new(10.0, 70.0, 350.0, 50.0))?;
-        start_1p_game_button
-            .on(EventType::Click, Box::new(start_or_resume_game_click_handler))
-            .unwrap(); // unwrap OK
+        let roster_id = layer_inroom.add_widget(roster, InsertLocation::AtCurrentLayer)?;
+        layer_inroom.set_widget_name("roster", &roster_id);
 
-        let mut options_button = Box::new(Button::new(ctx, default_font_info, "Options".to_owned()));
-        options_button.set_rect(Rect::new(10.0, 130.0, 180.0, 50.0))?;
-        options_button
-            .on(EventType::Click, Box::new(options_click_handler))
-            .unwrap(); // unwrap OK
+        let map_grid = Box::new(MapGrid::new(room_map.clone(), Rect::new(280.0, 450.0, 512.0, 256.0)));
+        layer_inroom.add_widget(map_grid, InsertLocation::AtCurrentLayer)?;
+        let tools = [
+            ("map-wall", MapTool::Wall),
+            ("map-erase", MapTool::Erase),
+            ("map-spawn", MapTool::Spawn),
+            ("map-fog-free", MapTool::FogFree),
+        ];
+        for &(name, tool) in tools.iter() {
+            let button = layout_button(&mut layer_inroom, &ids, name)?;
+            button
+                .on(EventType::Click, get_map_tool_click_handler(room_map.clone(), tool))
+                .unwrap(); // unwrap OK because not in handler
+            button
+                .on(
+                    EventType::Update,
+                    get_map_tool_button_update_handler(room_map.clone(), tool),
+                )
+                .unwrap(); // unwrap OK because not in handler
+        }
+        let map_texts: [(&str, fn(&RoomMapEditor) -> String); 2] = [
+            ("map-spawn", map_spawn_button_text),
+            ("map-fog-free", map_fog_free_button_text),
+        ];
+        for &(name, text) in map_texts.iter() {
+            layout_button(&mut layer_inroom, &ids, name)?
+                .on(EventType::Update, get_map_text_update_handler(room_map.clone(), text))
+                .unwrap(); // unwrap OK because not in handler
+        }
+        layout_button(&mut layer_inroom, &ids, "map-clear")?
+            .on(EventType::Click, get_map_clear_click_handler(room_map.clone()))
+            .unwrap(); // unwrap OK because not in handler
+        let settings_buttons: [(&str, fn(&mut RoomSettingsEditor), fn(&RoomSettingsEditor) -> String); 3] = [
+            (
+                "room-teams",
+                RoomSettingsEditor::next_team_count,
+                room_teams_button_text,
+            ),
+            (
+                "room-series",
+                RoomSettingsEditor::next_series_length,
+                room_series_button_text,
+            ),
+            ("room-win", RoomSettingsEditor::next_win_condition, room_win_button_text),
+        ];
+        for &(name, action, text) in settings_buttons.iter() {
+            let button = layout_button(&mut layer_inroom, &ids, name)?;
+            button
+                .on(
+                    EventType::Click,
+                    get_room_settings_click_handler(room_settings.clone(), action),
+                )
+                .unwrap(); // unwrap OK because not in handler
+            button
+                .on(
+                    EventType::Update,
+                    get_room_settings_text_update_handler(room_settings.clone(), text),
+                )
+                .unwrap(); // unwrap OK because not in handler
+        }
 
-        let mut quit_button = Box::new(Button::new(ctx, default_font_info, "Quit".to_owned()));
-        quit_button.set_rect(Rect::new(10.0, 190.0, 180.0, 50.0))?;
-        quit_button.on(EventType::Click, Box::new(quit_click_handler)).unwrap(); // unwrap OK
+        Ok((
+            layer_inroom,
+            team_button_ids,
+            start_game_button_id,
+            add_bot_button_id,
+            color_picker_id,
+            CreateRoomForm {
+                form,
+                room_name,
+                create,
+            },
+        ))
+    }
 
-        let menupane_id = layer_mainmenu.add_widget(pane, InsertLocation::AtCurrentLayer)?;
-        // Add widgets in the order you want keyboard focus
-        layer_mainmenu.add_widget(serverlist_button, InsertLocation::ToNestedContainer(&menupane_id))?;
-        layer_mainmenu.add_widget(start_1p_game_button, InsertLocation::ToNestedContainer(&menupane_id))?;
-        layer_mainmenu.add_widget(options_button, InsertLocation::ToNestedContainer(&menupane_id))?;
-        layer_mainmenu.add_widget(quit_button, InsertLocation::ToNestedContainer(&menupane_id))?;
-        Ok(layer_mainmenu)
+    /// Shown when a networked game ends. The standings themselves are drawn by the client.
+    fn build_results_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+    ) -> UIResult<Layering> {
+        let mut layer_results = Layering::new();
+        LayoutFile::load_and_build("results", ctx, default_font_info, &mut layer_results, &layout_bindings(config))?;
+        Ok(layer_results)
     }
 
-    pub fn new(ctx: &mut Context, config: &Config, font: Font) -> UIResult<(UILayout, StaticNodeIds)> {
-        let mut ui_layers = HashMap::new();
+    /// The server's leaderboards. The tables are drawn by the client.
+    fn build_leaderboards_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+    ) -> UIResult<Layering> {
+        let mut layer_leaderboards = Layering::new();
+        LayoutFile::load_and_build(
+            "leaderboards",
+            ctx,
+            default_font_info,
+            &mut layer_leaderboards,
+            &layout_bindings(config),
+        )?;
+        Ok(layer_leaderboards)
+    }
 
-        let default_font_info = common::FontInfo::new(ctx, font, None);
+    /// The puzzle select screen. The puzzles themselves are listed by `add_puzzle_list`.
+    fn build_puzzles_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+    ) -> UIResult<Layering> {
+        let mut layer_puzzles = Layering::new();
+        LayoutFile::load_and_build("puzzles", ctx, default_font_info, &mut layer_puzzles, &layout_bindings(config))?;
+        Ok(layer_puzzles)
+    }
+
+    /// The summary of the games played. The numbers and the chart are drawn by the client.
+    fn build_stats_menu(ctx: &mut Context, config: &Config, default_font_info: common::FontInfo) -> UIResult<Layering> {
+        let mut layer_stats = Layering::new();
+        LayoutFile::load_and_build("stats", ctx, default_font_info, &mut layer_stats, &layout_bindings(config))?;
+        Ok(layer_stats)
+    }
+
+    /// The pattern editor: the grid that `editor` is drawn on, and the buttons for its tools and
+    /// preview. The client saves the pattern once the Save button sets `save_requested`, and draws
+    /// the generation and population.
+    fn build_pattern_editor_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        editor: &Arc<Mutex<PatternEditor>>,
+    ) -> UIResult<Layering> {
+        let mut layer_editor = Layering::new();
+        let mut bindings = layout_bindings(config);
+        bindings
+            .texts
+            .insert("rule", editor_rule_button_text(&editor.lock().unwrap()));
+        let ids = LayoutFile::load_and_build("pattern_editor", ctx, default_font_info, &mut layer_editor, &bindings)?;
+
+        let grid = Box::new(PatternGrid::new(editor.clone(), Rect::new(20.0, 80.0, 672.0, 448.0)));
+        layer_editor.add_widget(grid, InsertLocation::AtCurrentLayer)?;
+
+        let tools = [
+            ("pencil", Tool::Pencil),
+            ("eraser", Tool::Eraser),
+            ("line", Tool::Line),
+            ("rectangle", Tool::Rectangle),
+        ];
+        for &(name, tool) in tools.iter() {
+            let button = layout_button(&mut layer_editor, &ids, name)?;
+            button
+                .on(EventType::Click, get_tool_click_handler(editor.clone(), tool))
+                .unwrap(); // unwrap OK because not in handler
+            button
+                .on(EventType::Update, get_tool_button_update_handler(editor.clone(), tool))
+                .unwrap(); // unwrap OK because not in handler
+        }
+
+        let actions: [(&str, fn(&mut PatternEditor)); 5] = [
+            ("step", |editor| {
+                editor.running = false;
+                editor.step();
+            }),
+            ("run", |editor| editor.running = !editor.running),
+            ("reset", PatternEditor::reset),
+            ("clear", PatternEditor::clear),
+            ("rule", |editor| editor.rule = editor.rule.next()),
+        ];
+        for &(name, action) in actions.iter() {
+            layout_button(&mut layer_editor, &ids, name)?
+                .on(EventType::Click, get_editor_click_handler(editor.clone(), action))
+                .unwrap(); // unwrap OK because not in handler
+        }
+        layout_button(&mut layer_editor, &ids, "save")?
+            .on(
+                EventType::Click,
+                get_editor_click_handler(editor.clone(), |editor| editor.save_requested = true),
+            )
+            .unwrap(); // unwrap OK because not in handler
+
+        // The text of these follows the editor
+        layout_button(&mut layer_editor, &ids, "run")?
+            .on(
+                EventType::Update,
+                get_editor_text_update_handler(editor.clone(), |editor| {
+                    tr(if editor.running { "editor-pause" } else { "editor-run" })
+                }),
+            )
+            .unwrap(); // unwrap OK because not in handler
+        layout_button(&mut layer_editor, &ids, "rule")?
+            .on(
+                EventType::Update,
+                get_editor_text_update_handler(editor.clone(), editor_rule_button_text),
+            )
+            .unwrap(); // unwrap OK because not in handler
+
+        Ok(layer_editor)
+    }
+
+    /// The pattern library. The patterns are listed by `add_pattern_list`; the buttons above the
+    /// list pick the category in `filter`.
+    fn build_pattern_library_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        filter: &Arc<Mutex<Option<Category>>>,
+    ) -> UIResult<Layering> {
+        let mut layer_library = Layering::new();
+        let ids = LayoutFile::load_and_build(
+            "pattern_library",
+            ctx,
+            default_font_info,
+            &mut layer_library,
+            &layout_bindings(config),
+        )?;
+
+        let filters = std::iter::once(None).chain(Category::FILTERS.iter().cloned().map(Some));
+        for category in filters {
+            let name = category.map_or("all", Category::name);
+            let button = layout_button(&mut layer_library, &ids, name)?;
+            button
+                .on(EventType::Click, get_filter_click_handler(filter.clone(), category))
+                .unwrap(); // unwrap OK because not in handler
+            button
+                .on(
+                    EventType::Update,
+                    get_filter_button_update_handler(filter.clone(), category),
+                )
+                .unwrap(); // unwrap OK because not in handler
+        }
+        Ok(layer_library)
+    }
+
+    /// The patterns shared on the server. They are listed by `add_pattern_list`; the buttons put what
+    /// they ask for in `request`.
+    fn build_shared_patterns_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        request: &Arc<Mutex<Option<SharedPatternsRequest>>>,
+    ) -> UIResult<Layering> {
+        let mut layer_shared = Layering::new();
+        let ids = LayoutFile::load_and_build(
+            "shared_patterns",
+            ctx,
+            default_font_info,
+            &mut layer_shared,
+            &layout_bindings(config),
+        )?;
 
-        let layer_mainmenu = UILayout::build_main_menu(ctx, default_font_info)?;
+        let buttons = [
+            ("share", SharedPatternsRequest::Share),
+            ("previous", SharedPatternsRequest::PreviousPage),
+            ("next", SharedPatternsRequest::NextPage),
+        ];
+        for &(name, action) in buttons.iter() {
+            layout_button(&mut layer_shared, &ids, name)?
+                .on(
+                    EventType::Click,
+                    get_shared_request_click_handler(request.clone(), action),
+                )
+                .unwrap(); // unwrap OK because not in handler
+        }
+        Ok(layer_shared)
+    }
+
+    /// Importing a pattern from a URL. The Download button sets `request`, and the client reads the
+    /// URL on its next update.
+    fn build_pattern_import_menu(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        request: &Arc<Mutex<bool>>,
+    ) -> UIResult<Layering> {
+        let mut layer_import = Layering::new();
+        let ids = LayoutFile::load_and_build(
+            "pattern_import",
+            ctx,
+            default_font_info,
+            &mut layer_import,
+            &layout_bindings(config),
+        )?;
+
+        layout_button(&mut layer_import, &ids, "download")?
+            .on(EventType::Click, get_import_click_handler(request.clone()))
+            .unwrap(); // unwrap OK because not in handler
+        Ok(layer_import)
+    }
+
+    /// Builds the layerings of the menu screens, which are the ones with translated text, into
+    /// `ui_layers`. Returns the IDs of the room lobby widgets and its Create Room form, as from
+    /// `build_in_room_menu`.
+    fn build_menus(
+        ctx: &mut Context,
+        config: &Config,
+        default_font_info: common::FontInfo,
+        script_names: &[String],
+        pattern_editor: &Arc<Mutex<PatternEditor>>,
+        library_filter: &Arc<Mutex<Option<Category>>>,
+        shared_request: &Arc<Mutex<Option<SharedPatternsRequest>>>,
+        import_request: &Arc<Mutex<bool>>,
+        room_map: &Arc<Mutex<RoomMapEditor>>,
+        room_settings: &Arc<Mutex<RoomSettingsEditor>>,
+        ui_layers: &mut HashMap<Screen, Layering>,
+    ) -> UIResult<(Vec<NodeId>, NodeId, NodeId, NodeId, CreateRoomForm)> {
+        let layer_mainmenu = UILayout::build_main_menu(ctx, config, default_font_info)?;
         debug!("MENU WIDGET TREE");
         layer_mainmenu.debug_display_widget_tree();
         ui_layers.insert(Screen::Menu, layer_mainmenu);
 
-        let layer_options = UILayout::build_options_menu(ctx, config, default_font_info)?;
+        let layer_options = UILayout::build_options_menu(ctx, config, default_font_info, script_names)?;
         debug!("OPTIONS WIDGET TREE");
         layer_options.debug_display_widget_tree();
         ui_layers.insert(Screen::Options, layer_options);
 
+        let (layer_inroom, team_button_ids, start_game_button_id, add_bot_button_id, color_picker_id, create_room) =
+            UILayout::build_in_room_menu(ctx, config, default_font_info, room_map, room_settings)?;
+        debug!("IN ROOM WIDGET TREE");
+        layer_inroom.debug_display_widget_tree();
+        ui_layers.insert(Screen::InRoom, layer_inroom);
+
+        let layer_results = UILayout::build_results_menu(ctx, config, default_font_info)?;
+        debug!("RESULTS WIDGET TREE");
+        layer_results.debug_display_widget_tree();
+        ui_layers.insert(Screen::Results, layer_results);
+
+        let layer_stats = UILayout::build_stats_menu(ctx, config, default_font_info)?;
+        debug!("STATS WIDGET TREE");
+        layer_stats.debug_display_widget_tree();
+        ui_layers.insert(Screen::Stats, layer_stats);
+
+        let layer_leaderboards = UILayout::build_leaderboards_menu(ctx, config, default_font_info)?;
+        debug!("LEADERBOARDS WIDGET TREE");
+        layer_leaderboards.debug_display_widget_tree();
+        ui_layers.insert(Screen::Leaderboards, layer_leaderboards);
+
+        let layer_puzzles = UILayout::build_puzzles_menu(ctx, config, default_font_info)?;
+        debug!("PUZZLES WIDGET TREE");
+        layer_puzzles.debug_display_widget_tree();
+        ui_layers.insert(Screen::Puzzles, layer_puzzles);
+
+        let layer_editor = UILayout::build_pattern_editor_menu(ctx, config, default_font_info, pattern_editor)?;
+        debug!("PATTERN EDITOR WIDGET TREE");
+        layer_editor.debug_display_widget_tree();
+        ui_layers.insert(Screen::PatternEditor, layer_editor);
+
+        let layer_library = UILayout::build_pattern_library_menu(ctx, config, default_font_info, library_filter)?;
+        debug!("PATTERN LIBRARY WIDGET TREE");
+        layer_library.debug_display_widget_tree();
+        ui_layers.insert(Screen::PatternLibrary, layer_library);
+
+        let layer_shared = UILayout::build_shared_patterns_menu(ctx, config, default_font_info, shared_request)?;
+        debug!("SHARED PATTERNS WIDGET TREE");
+        layer_shared.debug_display_widget_tree();
+        ui_layers.insert(Screen::SharedPatterns, layer_shared);
+
+        let layer_import = UILayout::build_pattern_import_menu(ctx, config, default_font_info, import_request)?;
+        debug!("PATTERN IMPORT WIDGET TREE");
+        layer_import.debug_display_widget_tree();
+        ui_layers.insert(Screen::PatternImport, layer_import);
+
+        Ok((
+            team_button_ids,
+            start_game_button_id,
+            add_bot_button_id,
+            color_picker_id,
+            create_room,
+        ))
+    }
+
+    /// Replaces the menu screens with new ones, such as after the language was changed, and
+    /// updates the room lobby IDs in `static_node_ids`. The new lobby widgets have no network
+    /// handlers yet. The in-game screen is kept as is, so the chat history survives.
+    pub fn rebuild_menus(
+        &mut self,
+        ctx: &mut Context,
+        config: &Config,
+        font: Font,
+        script_names: &[String],
+        static_node_ids: &mut StaticNodeIds,
+    ) -> UIResult<()> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let (team_button_ids, start_game_button_id, add_bot_button_id, color_picker_id, create_room) =
+            UILayout::build_menus(
+                ctx,
+                config,
+                default_font_info,
+                script_names,
+                &self.pattern_editor,
+                &self.library_filter,
+                &self.shared_request,
+                &self.import_request,
+                &self.room_map,
+                &self.room_settings,
+                &mut self.layers,
+            )?;
+        self.create_room = create_room;
+        static_node_ids.team_button_ids = team_button_ids;
+        static_node_ids.start_game_button_id = start_game_button_id;
+        static_node_ids.add_bot_button_id = add_bot_button_id;
+        static_node_ids.color_picker_id = color_picker_id;
+        Ok(())
+    }
+
+    /// Replaces just one of the menu screens, such as after its layout file was edited. As with
+    /// `rebuild_menus`, new room lobby widgets have no network handlers yet. Other screens are left
+    /// as they are.
+    pub fn rebuild_screen(
+        &mut self,
+        ctx: &mut Context,
+        config: &Config,
+        font: Font,
+        script_names: &[String],
+        screen: Screen,
+        static_node_ids: &mut StaticNodeIds,
+    ) -> UIResult<()> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let layer = match screen {
+            Screen::Menu => UILayout::build_main_menu(ctx, config, default_font_info)?,
+            Screen::Options => UILayout::build_options_menu(ctx, config, default_font_info, script_names)?,
+            Screen::InRoom => {
+                let (layer, team_button_ids, start_game_button_id, add_bot_button_id, color_picker_id, create_room) =
+                    UILayout::build_in_room_menu(ctx, config, default_font_info, &self.room_map, &self.room_settings)?;
+                self.create_room = create_room;
+                static_node_ids.team_button_ids = team_button_ids;
+                static_node_ids.start_game_button_id = start_game_button_id;
+                static_node_ids.add_bot_button_id = add_bot_button_id;
+                static_node_ids.color_picker_id = color_picker_id;
+                layer
+            }
+            Screen::Results => UILayout::build_results_menu(ctx, config, default_font_info)?,
+            Screen::Stats => UILayout::build_stats_menu(ctx, config, default_font_info)?,
+            Screen::Leaderboards => UILayout::build_leaderboards_menu(ctx, config, default_font_info)?,
+            Screen::Puzzles => UILayout::build_puzzles_menu(ctx, config, default_font_info)?,
+            Screen::PatternEditor => {
+                UILayout::build_pattern_editor_menu(ctx, config, default_font_info, &self.pattern_editor)?
+            }
+            Screen::PatternLibrary => {
+                UILayout::build_pattern_library_menu(ctx, config, default_font_info, &self.library_filter)?
+            }
+            Screen::SharedPatterns => {
+                UILayout::build_shared_patterns_menu(ctx, config, default_font_info, &self.shared_request)?
+            }
+            Screen::PatternImport => {
+                UILayout::build_pattern_import_menu(ctx, config, default_font_info, &self.import_request)?
+            }
+            _ => {
+                return Err(Box::new(UIError::InvalidArgument {
+                    reason: format!("{:?} is not built from a layout file", screen),
+                }))
+            }
+        };
+        self.layers.insert(screen, layer);
+        Ok(())
+    }
+
+    pub fn new(
+        ctx: &mut Context,
+        config: &Config,
+        font: Font,
+        script_names: &[String],
+    ) -> UIResult<(UILayout, StaticNodeIds)> {
+        let mut ui_layers = HashMap::new();
+
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let pattern_editor = Arc::new(Mutex::new(PatternEditor::new()));
+        let library_filter = Arc::new(Mutex::new(None));
+        let shared_request = Arc::new(Mutex::new(None));
+        let import_request = Arc::new(Mutex::new(false));
+        let room_map = Arc::new(Mutex::new(RoomMapEditor::new()));
+        let room_settings = Arc::new(Mutex::new(RoomSettingsEditor::new()));
+
+        let (team_button_ids, start_game_button_id, add_bot_button_id, color_picker_id, create_room) =
+            UILayout::build_menus(
+                ctx,
+                config,
+                default_font_info,
+                script_names,
+                &pattern_editor,
+                &library_filter,
+                &shared_request,
+                &import_request,
+                &room_map,
+                &room_settings,
+                &mut ui_layers,
+            )?;
+
         // ==== In-Game (Run screen) ====
         let mut layer_ingame = Layering::new();
         let chat_pane_rect = *constants::DEFAULT_CHATBOX_RECT;
         let mut chatpane = Box::new(Pane::new(chat_pane_rect));
-        chatpane.bg_color = Some(*constants::colors::CHAT_PANE_FILL_COLOR);
+        chatpane.bg_color = Some(constants::colors::scheme().chat_pane_fill);
         let chatpane_id = layer_ingame.add_widget(chatpane, InsertLocation::AtCurrentLayer)?;
 
         let chatbox_rect = Rect::new(
@@ -258,8 +917,10 @@ impl UILayout {
             chat_pane_rect.h - constants::CHAT_TEXTFIELD_HEIGHT,
         );
         let chatbox_font_info = common::FontInfo::new(ctx, font, Some(*constants::DEFAULT_CHATBOX_FONT_SCALE));
-        let mut chatbox = Chatbox::new(chatbox_font_info, constants::CHATBOX_HISTORY);
+        let mut chatbox = Chatbox::new(chatbox_font_info, config.get().gameplay.chat_history);
         chatbox.set_rect(chatbox_rect)?;
+        chatbox.set_tab_name(constants::ROOM_CHAT_TAB, tr("chat-tab-room"));
+        chatbox.add_tab(tr("chat-tab-lobby")); // constants::LOBBY_CHAT_TAB
 
         let chatbox = Box::new(chatbox);
 
@@ -270,7 +931,8 @@ impl UILayout {
             constants::CHAT_TEXTFIELD_HEIGHT,
         );
         let mut textfield = Box::new(TextField::new(default_font_info, textfield_rect));
-        textfield.bg_color = Some(*constants::colors::CHAT_PANE_FILL_COLOR);
+        textfield.bg_color = Some(constants::colors::scheme().chat_pane_fill);
+        textfield.set_multiline(constants::CHAT_TEXTFIELD_MAX_LINES);
         let chatbox_id = layer_ingame.add_widget(chatbox, InsertLocation::ToNestedContainer(&chatpane_id))?;
         let chatbox_tf_id = layer_ingame.add_widget(textfield, InsertLocation::ToNestedContainer(&chatpane_id))?;
 
@@ -285,32 +947,680 @@ impl UILayout {
         ui_layers.insert(Screen::Run, layer_ingame);
 
         Ok((
-            UILayout { layers: ui_layers },
+            UILayout {
+                layers: ui_layers,
+                create_room,
+                pattern_editor,
+                library_filter,
+                shared_request,
+                import_request,
+                room_map,
+                room_settings,
+            },
             StaticNodeIds {
                 chatbox_id,
                 chatbox_pane_id: chatpane_id,
                 chatbox_tf_id,
                 game_area_id,
+                team_button_ids,
+                start_game_button_id,
+                add_bot_button_id,
+                color_picker_id,
             },
         ))
     }
+
+    /// Shows a dialog over the main menu asking whether to keep the crash reports from earlier runs.
+    /// Returns the ID of the dialog's pane, for removing it once the player has answered.
+    pub fn add_crash_prompt(&mut self, ctx: &mut Context, font: Font) -> UIResult<NodeId> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let layer_mainmenu = self.get_screen_layering_mut(Screen::Menu).unwrap(); // unwrap OK because build_menus added it
+
+        let mut pane = Box::new(Pane::new(Rect::new(200.0, 200.0, 620.0, 190.0)));
+        pane.bg_color = Some(constants::colors::scheme().chat_pane_fill);
+        let pane_id = layer_mainmenu.add_widget(pane, InsertLocation::AtNextLayer)?;
+
+        for (i, text) in [tr("crash-prompt-title"), tr("crash-prompt-question")]
+            .iter()
+            .enumerate()
+        {
+            let label = Box::new(Label::new(
+                ctx,
+                default_font_info,
+                text.clone(),
+                constants::colors::scheme().menu_text,
+                Point2 {
+                    x: 20.0,
+                    y: 20.0 + 35.0 * i as f32,
+                },
+            ));
+            layer_mainmenu.add_widget(label, InsertLocation::ToNestedContainer(&pane_id))?;
+        }
+
+        let mut save_button = Box::new(Button::new(ctx, default_font_info, tr("crash-prompt-save")));
+        place_button(&mut save_button, Rect::new(20.0, 110.0, 250.0, 50.0))?;
+        save_button
+            .on(EventType::Click, Box::new(crash_prompt_save_click_handler))
+            .unwrap(); // unwrap OK
+        layer_mainmenu.add_widget(save_button, InsertLocation::ToNestedContainer(&pane_id))?;
+
+        let mut discard_button = Box::new(Button::new(ctx, default_font_info, tr("crash-prompt-discard")));
+        discard_button.style = ButtonStyle::Danger;
+        place_button(&mut discard_button, Rect::new(350.0, 110.0, 250.0, 50.0))?;
+        discard_button
+            .on(EventType::Click, Box::new(crash_prompt_discard_click_handler))
+            .unwrap(); // unwrap OK
+        layer_mainmenu.add_widget(discard_button, InsertLocation::ToNestedContainer(&pane_id))?;
+
+        Ok(pane_id)
+    }
+
+    /// Shows a dialog over `screen` asking whether to let `name` join from Discord. Clicking a
+    /// button puts the answer in `answer`, for the client to pass on. Returns the ID of the dialog's
+    /// pane, for removing it once the player has answered.
+    pub fn add_discord_join_prompt(
+        &mut self,
+        ctx: &mut Context,
+        font: Font,
+        screen: Screen,
+        name: &str,
+        answer: &Arc<Mutex<Option<bool>>>,
+    ) -> UIResult<NodeId> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let layer = self.get_screen_layering_mut(screen).ok_or_else(|| {
+            Box::new(UIError::InvalidArgument {
+                reason: format!("{:?} has no layers to show a prompt on", screen),
+            })
+        })?;
+
+        let mut pane = Box::new(Pane::new(Rect::new(200.0, 200.0, 620.0, 155.0)));
+        pane.bg_color = Some(constants::colors::scheme().chat_pane_fill);
+        let pane_id = layer.add_widget(pane, InsertLocation::AtNextLayer)?;
+
+        let label = Box::new(Label::new(
+            ctx,
+            default_font_info,
+            tr_args("discord-join-prompt", &[("name", name)]),
+            constants::colors::scheme().menu_text,
+            Point2 { x: 20.0, y: 20.0 },
+        ));
+        layer.add_widget(label, InsertLocation::ToNestedContainer(&pane_id))?;
+
+        let mut accept_button = Box::new(Button::new(ctx, default_font_info, tr("discord-join-accept")));
+        place_button(&mut accept_button, Rect::new(20.0, 75.0, 250.0, 50.0))?;
+        accept_button
+            .on(EventType::Click, get_answer_click_handler(true, answer.clone()))
+            .unwrap(); // unwrap OK because not in handler
+        layer.add_widget(accept_button, InsertLocation::ToNestedContainer(&pane_id))?;
+
+        let mut decline_button = Box::new(Button::new(ctx, default_font_info, tr("discord-join-decline")));
+        place_button(&mut decline_button, Rect::new(350.0, 75.0, 250.0, 50.0))?;
+        decline_button
+            .on(EventType::Click, get_answer_click_handler(false, answer.clone()))
+            .unwrap(); // unwrap OK because not in handler
+        layer.add_widget(decline_button, InsertLocation::ToNestedContainer(&pane_id))?;
+
+        Ok(pane_id)
+    }
+
+    /// Lists the puzzles on the puzzle select screen, one row each with a button showing the text
+    /// and a label showing the status from `rows`. Clicking a button puts its index in `picked`,
+    /// for the client to start that puzzle.
+    pub fn add_puzzle_list(
+        &mut self,
+        ctx: &mut Context,
+        font: Font,
+        rows: &[(String, String)],
+        picked: &Arc<Mutex<Option<usize>>>,
+    ) -> UIResult<()> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let layer_puzzles = self.get_screen_layering_mut(Screen::Puzzles).unwrap(); // unwrap OK because build_menus added it
+
+        // there may be more puzzles than fit on the screen
+        let scroll_rect = Rect::new(100.0, 150.0, 900.0, 450.0);
+        let scroll_id =
+            layer_puzzles.add_widget(Box::new(ScrollPane::new(scroll_rect)), InsertLocation::AtCurrentLayer)?;
+        let list_height = 10.0 + 60.0 * rows.len() as f32;
+        let list_pane = if list_height > scroll_rect.h {
+            Pane::new(Rect::new(
+                0.0,
+                0.0,
+                scroll_rect.w - constants::SCROLLBAR_WIDTH,
+                list_height,
+            ))
+        } else {
+            Pane::new(Rect::new(0.0, 0.0, scroll_rect.w, scroll_rect.h))
+        };
+        let list_id = layer_puzzles.add_widget(Box::new(list_pane), InsertLocation::ToNestedContainer(&scroll_id))?;
+
+        for (i, (text, status)) in rows.iter().enumerate() {
+            let y = 10.0 + 60.0 * i as f32;
+            let mut button = Box::new(Button::new(ctx, default_font_info, text.clone()));
+            place_button(&mut button, Rect::new(10.0, y, 320.0, 50.0))?;
+            button
+                .on(EventType::Click, get_pick_click_handler(i, picked.clone()))
+                .unwrap(); // unwrap OK because not in handler
+            layer_puzzles.add_widget(button, InsertLocation::ToNestedContainer(&list_id))?;
+
+            let label = Box::new(Label::new(
+                ctx,
+                default_font_info,
+                status.clone(),
+                constants::colors::scheme().value_text,
+                Point2 { x: 350.0, y: y + 15.0 },
+            ));
+            layer_puzzles.add_widget(label, InsertLocation::ToNestedContainer(&list_id))?;
+        }
+        Ok(())
+    }
+
+    /// Lists patterns on `screen`, the pattern library or the shared patterns, one row each with the
+    /// thumbnail, if there is one, a button showing the name, and a label showing the description,
+    /// as in `rows`. Clicking a button puts the index from its row in `picked`. Returns the ID of the
+    /// list, for removing it to show other patterns.
+    pub fn add_pattern_list(
+        &mut self,
+        ctx: &mut Context,
+        font: Font,
+        screen: Screen,
+        rows: &[(usize, Option<Texture>, String, String)],
+        picked: &Arc<Mutex<Option<usize>>>,
+    ) -> UIResult<NodeId> {
+        let default_font_info = common::FontInfo::new(ctx, font, None);
+        let layer = self.get_screen_layering_mut(screen).unwrap(); // unwrap OK because build_menus added it
+
+        let scroll_rect = Rect::new(100.0, 250.0, 900.0, 370.0);
+        let scroll_id = layer.add_widget(Box::new(ScrollPane::new(scroll_rect)), InsertLocation::AtCurrentLayer)?;
+        let list_height = 10.0 + 74.0 * rows.len() as f32;
+        let list_pane = if list_height > scroll_rect.h {
+            Pane::new(Rect::new(
+                0.0,
+                0.0,
+                scroll_rect.w - constants::SCROLLBAR_WIDTH,
+                list_height,
+            ))
+        } else {
+            Pane::new(Rect::new(0.0, 0.0, scroll_rect.w, scroll_rect.h))
+        };
+        let list_id = layer.add_widget(Box::new(list_pane), InsertLocation::ToNestedContainer(&scroll_id))?;
+
+        for (row, (index, thumbnail, name, description)) in rows.iter().enumerate() {
+            let y = 10.0 + 74.0 * row as f32;
+            if let Some(thumbnail) = thumbnail {
+                let image = Box::new(Image::new(thumbnail.clone(), Rect::new(10.0, y, 64.0, 64.0)));
+                layer.add_widget(image, InsertLocation::ToNestedContainer(&list_id))?;
+            }
+
+            let mut button = Box::new(Button::new(ctx, default_font_info, name.clone()));
+            place_button(&mut button, Rect::new(90.0, y + 7.0, 320.0, 50.0))?;
+            button
+                .on(EventType::Click, get_pick_click_handler(*index, picked.clone()))
+                .unwrap(); // unwrap OK because not in handler
+            layer.add_widget(button, InsertLocation::ToNestedContainer(&list_id))?;
+
+            let label = Box::new(Label::new(
+                ctx,
+                default_font_info,
+                description.clone(),
+                constants::colors::scheme().value_text,
+                Point2 { x: 430.0, y: y + 22.0 },
+            ));
+            layer.add_widget(label, InsertLocation::ToNestedContainer(&list_id))?;
+        }
+        Ok(scroll_id)
+    }
+}
+
+// The client acts on what was picked, such as by starting the puzzle, on its next update
+fn get_pick_click_handler(index: usize, picked: Arc<Mutex<Option<usize>>>) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            *picked.lock().unwrap() = Some(index);
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_answer_click_handler(accept: bool, answer: Arc<Mutex<Option<bool>>>) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            *answer.lock().unwrap() = Some(accept);
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+/// The button named `name` in a layout built into `layer`, whose named widgets are `ids`.
+fn layout_button<'a>(layer: &'a mut Layering, ids: &HashMap<String, NodeId>, name: &str) -> UIResult<&'a mut Button> {
+    let id = ids.get(name).ok_or_else(|| {
+        Box::new(UIError::WidgetNotFound {
+            reason: format!("the layout has no button named {:?}", name),
+        })
+    })?;
+    Button::widget_from_id_mut(layer, id)
+}
+
+fn editor_rule_button_text(editor: &PatternEditor) -> String {
+    tr_args("editor-rule", &[("rule", &editor.rule)])
+}
+
+fn map_spawn_button_text(room_map: &RoomMapEditor) -> String {
+    tr_args("map-spawn", &[("slot", &(room_map.slot + 1))])
+}
+
+fn map_fog_free_button_text(room_map: &RoomMapEditor) -> String {
+    tr_args("map-fog-free", &[("slot", &(room_map.fog_free_slot + 1))])
 }
-fn fullscreen_toggle_handler(
+
+fn room_teams_button_text(room_settings: &RoomSettingsEditor) -> String {
+    match room_settings.settings() {
+        Some(settings) if settings.team_count > 0 => tr_args("room-teams", &[("count", &settings.team_count)]),
+        _ => tr("room-no-teams"),
+    }
+}
+
+fn room_series_button_text(room_settings: &RoomSettingsEditor) -> String {
+    match room_settings.settings() {
+        Some(settings) if settings.series_length > 1 => {
+            tr_args("room-series", &[("rounds", &settings.series_length)])
+        }
+        _ => tr("room-single-games"),
+    }
+}
+
+fn room_win_button_text(room_settings: &RoomSettingsEditor) -> String {
+    match room_settings.settings().map(|settings| &settings.win_condition) {
+        Some(WinCondition::MostLiveCells { .. }) | None => tr("room-win-most-cells"),
+        Some(WinCondition::TerritoryControl { .. }) => tr("room-win-territory"),
+        Some(WinCondition::Elimination) => tr("room-win-elimination"),
+    }
+}
+
+fn get_editor_click_handler(editor: Arc<Mutex<PatternEditor>>, action: fn(&mut PatternEditor)) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            action(&mut editor.lock().unwrap());
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_tool_click_handler(editor: Arc<Mutex<PatternEditor>>, tool: Tool) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            editor.lock().unwrap().tool = tool;
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_map_tool_click_handler(room_map: Arc<Mutex<RoomMapEditor>>, tool: MapTool) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let mut room_map = room_map.lock().unwrap();
+            match tool {
+                MapTool::Spawn => room_map.pick_spawn_tool(),
+                MapTool::FogFree => room_map.pick_fog_free_tool(),
+                _ => room_map.tool = tool,
+            }
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+/// A handler that stores whether the checkbox is checked, with `set`, in the setting it is for.
+fn get_setting_toggle_handler(set: fn(&mut Settings, bool)) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let checkbox = obj.downcast_ref::<Checkbox>().unwrap(); // unwrap OK because it's always a Checkbox
+            let checked = checkbox.is_checked();
+            uictx.config.modify(|settings| set(settings, checked));
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_room_settings_click_handler(
+    room_settings: Arc<Mutex<RoomSettingsEditor>>,
+    action: fn(&mut RoomSettingsEditor),
+) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            action(&mut room_settings.lock().unwrap());
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_map_clear_click_handler(room_map: Arc<Mutex<RoomMapEditor>>) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            room_map.lock().unwrap().clear();
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_shared_request_click_handler(
+    request: Arc<Mutex<Option<SharedPatternsRequest>>>,
+    action: SharedPatternsRequest,
+) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            *request.lock().unwrap() = Some(action);
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_import_click_handler(request: Arc<Mutex<bool>>) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            *request.lock().unwrap() = true;
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+fn get_filter_click_handler(filter: Arc<Mutex<Option<Category>>>, category: Option<Category>) -> context::Handler {
+    Box::new(
+        move |_obj: &mut dyn EmitEvent,
+              _uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            *filter.lock().unwrap() = category;
+            Ok(context::Handled::Handled)
+        },
+    )
+}
+
+/// Highlights the button of the category the pattern library shows.
+fn get_filter_button_update_handler(
+    filter: Arc<Mutex<Option<Category>>>,
+    category: Option<Category>,
+) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let style = if *filter.lock().unwrap() == category {
+                ButtonStyle::Primary
+            } else {
+                ButtonStyle::Secondary
+            };
+            if button.style != style {
+                button.style = style;
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+/// Highlights the button of the tool that is picked.
+fn get_tool_button_update_handler(editor: Arc<Mutex<PatternEditor>>, tool: Tool) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let style = if editor.lock().unwrap().tool == tool {
+                ButtonStyle::Primary
+            } else {
+                ButtonStyle::Secondary
+            };
+            if button.style != style {
+                button.style = style;
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+fn get_map_tool_button_update_handler(room_map: Arc<Mutex<RoomMapEditor>>, tool: MapTool) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let style = if room_map.lock().unwrap().tool == tool {
+                ButtonStyle::Primary
+            } else {
+                ButtonStyle::Secondary
+            };
+            if button.style != style {
+                button.style = style;
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+fn get_map_text_update_handler(
+    room_map: Arc<Mutex<RoomMapEditor>>,
+    text: fn(&RoomMapEditor) -> String,
+) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let text = text(&room_map.lock().unwrap());
+            if button.label.text() != text.as_str() {
+                let (ctx, texts) = uictx.text_context();
+                button.set_text(ctx, texts, text);
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+fn get_room_settings_text_update_handler(
+    room_settings: Arc<Mutex<RoomSettingsEditor>>,
+    text: fn(&RoomSettingsEditor) -> String,
+) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let text = text(&room_settings.lock().unwrap());
+            if button.label.text() != text.as_str() {
+                let (ctx, texts) = uictx.text_context();
+                button.set_text(ctx, texts, text);
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+fn get_editor_text_update_handler(
+    editor: Arc<Mutex<PatternEditor>>,
+    text: fn(&PatternEditor) -> String,
+) -> context::Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent,
+              uictx: &mut context::UIContext,
+              _evt: &context::Event|
+              -> Result<context::Handled, Box<dyn Error>> {
+            let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+            let text = text(&editor.lock().unwrap());
+            if button.label.text() != text.as_str() {
+                let (ctx, texts) = uictx.text_context();
+                button.set_text(ctx, texts, text);
+                uictx.request_redraw();
+            }
+            Ok(context::Handled::NotHandled)
+        },
+    )
+}
+
+// The client removes the crash prompt and tells the player where the reports went
+fn crash_prompt_save_click_handler(
+    _obj: &mut dyn EmitEvent,
+    _uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    crash::answer_prompt(true);
+    Ok(context::Handled::Handled)
+}
+
+fn crash_prompt_discard_click_handler(
+    _obj: &mut dyn EmitEvent,
+    _uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    crash::answer_prompt(false);
+    Ok(context::Handled::Handled)
+}
+
+fn master_volume_handler(
     obj: &mut dyn EmitEvent,
     uictx: &mut context::UIContext,
     _evt: &context::Event,
 ) -> Result<context::Handled, Box<dyn Error>> {
-    use context::Handled::*;
+    let slider = obj.downcast_ref::<Slider>().unwrap(); // unwrap OK because it's always a Slider
+    uictx.config.modify(|settings| {
+        settings.audio.master = slider.value() as u8;
+    });
+    Ok(context::Handled::Handled)
+}
 
-    // NOTE: the checkbox installed its own handler to toggle the `enabled` field on click
-    // We are running after it, since the handler registered first gets called first.
+fn music_volume_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let slider = obj.downcast_ref::<Slider>().unwrap(); // unwrap OK because it's always a Slider
+    uictx.config.modify(|settings| {
+        settings.audio.music = slider.value() as u8;
+    });
+    Ok(context::Handled::Handled)
+}
 
-    let checkbox = obj.downcast_ref::<Checkbox>().unwrap();
+/// Switches the script named by the checkbox's label on or off.
+fn script_toggle_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let checkbox = obj.downcast_ref::<Checkbox>().unwrap(); // unwrap OK because it's always a Checkbox
+    let name = checkbox.label.text().to_owned();
 
     uictx.config.modify(|settings| {
-        settings.video.fullscreen = checkbox.enabled;
+        let enabled_scripts = &mut settings.gameplay.enabled_scripts;
+        enabled_scripts.retain(|enabled_name| *enabled_name != name);
+        if checkbox.is_checked() {
+            enabled_scripts.push(name.clone());
+        }
     });
-    Ok(Handled)
+    Ok(context::Handled::Handled)
+}
+
+/// Unchecks the checkbox if the client has switched its script off, e.g. because it failed.
+fn script_checkbox_update_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let checkbox = obj.downcast_mut::<Checkbox>().unwrap(); // unwrap OK because it's always a Checkbox
+    let enabled = uictx
+        .config
+        .get()
+        .gameplay
+        .enabled_scripts
+        .iter()
+        .any(|name| name == checkbox.label.text());
+    if checkbox.state() != enabled.into() {
+        checkbox.set_state(enabled.into());
+        uictx.request_redraw();
+    }
+    Ok(context::Handled::NotHandled)
+}
+
+/// The handlers and computed text that the layout files of the menus can refer to.
+fn layout_bindings(config: &Config) -> LayoutBindings {
+    let mut bindings = LayoutBindings::default();
+    let handlers: [(&'static str, LayoutHandler); 28] = [
+        ("server-list", server_list_click_handler),
+        ("start-game", start_or_resume_game_click_handler),
+        ("options", options_click_handler),
+        ("stats", stats_click_handler),
+        ("leaderboards", leaderboards_click_handler),
+        ("shared-patterns", shared_patterns_click_handler),
+        ("puzzles", puzzles_click_handler),
+        ("pattern-editor", pattern_editor_click_handler),
+        ("pattern-library", pattern_library_click_handler),
+        ("pattern-import", pattern_import_click_handler),
+        ("profile", profile_click_handler),
+        ("profile-text", profile_button_update_handler),
+        ("new-profile", new_profile_click_handler),
+        ("quit", quit_click_handler),
+        ("language", language_click_handler),
+        ("palette", palette_click_handler),
+        ("palette-text", palette_button_update_handler),
+        ("open-data-folder", open_data_folder_click_handler),
+        ("results-back", results_back_click_handler),
+        ("stats-back", back_click_handler),
+        ("leaderboards-back", back_click_handler),
+        ("puzzles-back", back_click_handler),
+        ("pattern-editor-back", back_click_handler),
+        ("pattern-library-back", back_click_handler),
+        ("shared-patterns-back", back_click_handler),
+        ("pattern-import-back", back_click_handler),
+    ];
+    bindings.handlers.extend(handlers.iter().cloned());
+    bindings.texts.insert("profile", profile_button_text(config));
+    bindings.texts.insert("language", language_button_text(config));
+    bindings.texts.insert("palette", palette_button_text(config));
+    bindings
+}
+
+/// Sets the rect of `button`, but makes it larger if its text wouldn't fit, as can happen with a
+/// long translation or a large minimum font size.
+fn place_button(button: &mut Button, rect: Rect) -> UIResult<()> {
+    let (min_w, min_h) = button.size(); // a new Button is just big enough for its text
+    button.set_rect(Rect::new(rect.x, rect.y, rect.w.max(min_w), rect.h.max(min_h)))
 }
 
 fn server_list_click_handler(
@@ -322,6 +1632,132 @@ fn server_list_click_handler(
     Ok(context::Handled::Handled)
 }
 
+fn profile_button_text(config: &Config) -> String {
+    tr_args("menu-profile", &[("name", &config.get().user.name)])
+}
+
+fn language_button_text(config: &Config) -> String {
+    let code = config.get().user.language.as_str();
+    let name = i18n::language_name(code).unwrap_or(code);
+    tr_args("options-language", &[("language", &name)])
+}
+
+fn palette_button_text(config: &Config) -> String {
+    let name = &config.get().accessibility.colorblind_palette;
+    let palette = Palette::from_name(name).unwrap_or(Palette::Normal);
+    let palette_text = tr(&format!("palette-{}", palette.name()));
+    tr_args("options-palette", &[("palette", &palette_text)])
+}
+
+/// Switches to the next colorblind palette. The client recolors the players.
+fn palette_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.config.modify(|settings| {
+        let name = &settings.accessibility.colorblind_palette;
+        let palette = Palette::from_name(name).unwrap_or(Palette::Normal);
+        settings.accessibility.colorblind_palette = palette.next().name().to_owned();
+    });
+    Ok(context::Handled::Handled)
+}
+
+fn palette_button_update_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+    let text = palette_button_text(uictx.config);
+    if button.label.text() != text.as_str() {
+        let (ctx, texts) = uictx.text_context();
+        button.set_text(ctx, texts, text);
+        uictx.request_redraw();
+    }
+    Ok(context::Handled::NotHandled)
+}
+
+/// The index in `BoardTheme::ALL` of the board theme in the config.
+fn theme_index(config: &Config) -> usize {
+    let theme = BoardTheme::from_name(&config.get().theme.name).unwrap_or(BoardTheme::Light);
+    BoardTheme::ALL.iter().position(|&t| t == theme).unwrap() // unwrap OK because ALL has every theme
+}
+
+/// Switches to the board theme picked. The client recolors the board.
+fn theme_select_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let dropdown = obj.downcast_ref::<Dropdown>().unwrap(); // unwrap OK because it's always a Dropdown
+    let theme = BoardTheme::ALL[dropdown.selected()];
+    uictx.config.modify(|settings| {
+        settings.theme.name = theme.name().to_owned();
+    });
+    Ok(context::Handled::Handled)
+}
+
+/// Keeps the board theme dropdown in line with the config, which can be edited while the game runs.
+fn theme_dropdown_update_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let dropdown = obj.downcast_mut::<Dropdown>().unwrap(); // unwrap OK because it's always a Dropdown
+    let index = theme_index(uictx.config);
+    if dropdown.selected() != index {
+        dropdown.set_selected(index);
+        uictx.request_redraw();
+    }
+    Ok(context::Handled::NotHandled)
+}
+
+fn language_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.config.modify(|settings| {
+        settings.user.language = i18n::next_language(&settings.user.language).to_owned();
+    });
+    Ok(context::Handled::Handled)
+}
+
+fn profile_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.config.next_profile();
+    Ok(context::Handled::Handled)
+}
+
+fn new_profile_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.config.add_profile();
+    Ok(context::Handled::Handled)
+}
+
+/// Keeps the profile button's text current, since the profile can also be renamed under Options.
+fn profile_button_update_handler(
+    obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    let button = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because it's always a Button
+    let text = profile_button_text(uictx.config);
+    if button.label.text() != text.as_str() {
+        let (ctx, texts) = uictx.text_context();
+        button.set_text(ctx, texts, text);
+        uictx.request_redraw();
+    }
+    Ok(context::Handled::NotHandled)
+}
+
 fn options_click_handler(
     _obj: &mut dyn EmitEvent,
     uictx: &mut context::UIContext,
@@ -331,6 +1767,90 @@ fn options_click_handler(
     Ok(context::Handled::Handled)
 }
 
+fn stats_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::Stats);
+    Ok(context::Handled::Handled)
+}
+
+fn leaderboards_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::Leaderboards);
+    Ok(context::Handled::Handled)
+}
+
+fn puzzles_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::Puzzles);
+    Ok(context::Handled::Handled)
+}
+
+fn pattern_editor_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::PatternEditor);
+    Ok(context::Handled::Handled)
+}
+
+fn pattern_library_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::PatternLibrary);
+    Ok(context::Handled::Handled)
+}
+
+fn pattern_import_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::PatternImport);
+    Ok(context::Handled::Handled)
+}
+
+fn shared_patterns_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.push_screen(Screen::SharedPatterns);
+    Ok(context::Handled::Handled)
+}
+
+fn back_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    uictx.pop_screen()?;
+    Ok(context::Handled::Handled)
+}
+
+/// Goes back to the room lobby, past the game screens of every round of a series.
+fn results_back_click_handler(
+    _obj: &mut dyn EmitEvent,
+    uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    while uictx.current_screen() != Screen::InRoom {
+        uictx.pop_screen()?;
+    }
+    Ok(context::Handled::Handled)
+}
+
 fn start_or_resume_game_click_handler(
     obj: &mut dyn EmitEvent,
     uictx: &mut context::UIContext,
@@ -339,19 +1859,30 @@ fn start_or_resume_game_click_handler(
     let btn = obj.downcast_mut::<Button>().unwrap(); // unwrap OK because this is only registered on a button
 
     // TODO: don't do this anymore once we have an in-game menu that is above Screen::Run in screen_stack.
-    btn.label.set_text(uictx.ggez_context, "Resume Game".to_owned());
+    let (ctx, texts) = uictx.text_context();
+    btn.label.set_text(ctx, texts, tr("menu-resume-game"));
 
     uictx.push_screen(Screen::Run);
     Ok(context::Handled::Handled)
 }
 
+/// Shows the player where their chat logs, crash reports and such are kept.
+fn open_data_folder_click_handler(
+    _obj: &mut dyn EmitEvent,
+    _uictx: &mut context::UIContext,
+    _evt: &context::Event,
+) -> Result<context::Handled, Box<dyn Error>> {
+    user_data::open_folder(&user_data::data_dir())?;
+    Ok(context::Handled::Handled)
+}
+
 fn quit_click_handler(
     _obj: &mut dyn EmitEvent,
     uictx: &mut context::UIContext,
     _evt: &context::Event,
 ) -> Result<context::Handled, Box<dyn Error>> {
     info!("QUIT CLICKED");
-    ggez::event::quit(uictx.ggez_context);
+    ggez::event::quit(uictx.ggez_context());
     Ok(context::Handled::Handled)
 }
 
@@ -367,7 +1898,9 @@ fn resolution_update_handler(
     );
     let new_res_text = format!("{} x {}", x, y);
     if label.text() != new_res_text.as_str() {
-        label.set_text(uictx.ggez_context, new_res_text);
+        let (ctx, texts) = uictx.text_context();
+        label.set_text(ctx, texts, new_res_text);
+        uictx.request_redraw();
     }
     Ok(context::Handled::Handled)
 }
@@ -391,9 +1924,12 @@ fn save_player_name(
 ) -> Result<context::Handled, Box<dyn Error>> {
     let textfield = obj.downcast_mut::<TextField>().unwrap(); // unwrap OK because it's always a textfield
     if let Some(player_name) = textfield.text() {
-        uictx.config.modify(|c| {
-            c.user.name = player_name.clone();
-        });
+        if !uictx.config.rename_active_profile(&player_name) {
+            warn!(
+                "Not renaming profile to {:?} because another profile has that name",
+                player_name
+            );
+        }
     }
     Ok(context::Handled::NotHandled)
 }
@@ -404,5 +1940,8 @@ add_widget_from_screen_id_mut!(Label);
 add_widget_from_screen_id_mut!(Pane);
 add_widget_from_screen_id_mut!(TextField);
 add_widget_from_screen_id_mut!(Chatbox);
+add_widget_from_screen_id_mut!(ColorPicker);
 add_widget_from_screen_id_mut!(GameArea);
+add_widget_from_screen_id_mut!(PlayerList);
 add_widget_from_screen_id!(GameArea);
+add_widget_from_screen_id!(TextField);
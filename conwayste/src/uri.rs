@@ -0,0 +1,200 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! `conwayste://host[:port]/room` links, which start the game connected to a server and in one of
+//! its rooms. Once the scheme is registered with the desktop, which `register` does where it can,
+//! web pages and chat messages can link straight to a room.
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+use std::process::Command;
+
+use crate::cli;
+use crate::constants::MAX_ROOM_NAME_LEN;
+
+const SCHEME_PREFIX: &str = "conwayste://";
+#[cfg(all(unix, not(target_os = "macos")))]
+const DESKTOP_FILE_NAME: &str = "conwayste-link.desktop";
+
+/// Where a link goes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invite {
+    pub server: String,         // host[:port]
+    pub room:   Option<String>, // None for the room the game usually joins
+}
+
+impl Invite {
+    /// Reads a link such as `conwayste://example.com:2016/glider%20gun%20club`. A query, such as
+    /// `?password=...`, is allowed but ignored, since rooms don't have passwords. Room names the
+    /// server would turn down, being too long or having control characters, are refused here.
+    pub fn parse(link: &str) -> Result<Invite, String> {
+        let rest = match link.get(..SCHEME_PREFIX.len()) {
+            Some(scheme) if scheme.eq_ignore_ascii_case(SCHEME_PREFIX) => &link[SCHEME_PREFIX.len()..],
+            _ => return Err(format!("not a {} link", SCHEME_PREFIX)),
+        };
+        // unwrap OK because split always yields at least one piece
+        let rest = rest.split(&['?', '#'][..]).next().unwrap();
+        let (server, room) = rest.split_once('/').unwrap_or((rest, ""));
+        cli::validate_server(server)?;
+        let room = percent_decode(room.trim_end_matches('/'))?;
+        if room.chars().any(char::is_control) {
+            return Err(format!("control characters in room name {:?}", room));
+        }
+        if room.len() > MAX_ROOM_NAME_LEN {
+            return Err(format!("room name too long; max {} characters", MAX_ROOM_NAME_LEN));
+        }
+        Ok(Invite {
+            server: server.to_owned(),
+            room:   Some(room).filter(|room| !room.is_empty()),
+        })
+    }
+}
+
+/// Decodes the `%XX` escapes in `text`, such as `%20` for a space.
+fn percent_decode(text: &str) -> Result<String, String> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            decoded.push(byte);
+            continue;
+        }
+        let hex = [bytes.next(), bytes.next()];
+        let digit = |byte: Option<u8>| byte.and_then(|b| (b as char).to_digit(16));
+        match (digit(hex[0]), digit(hex[1])) {
+            (Some(high), Some(low)) => decoded.push((high * 16 + low) as u8),
+            _ => return Err(format!("bad escape in {:?}", text)),
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("not UTF-8 once decoded: {:?}", text))
+}
+
+/// Makes `conwayste://` links open this copy of the game, for the player only. On macOS, only the
+/// app bundle's Info.plist can do that.
+pub fn register() -> io::Result<()> {
+    register_program(&std::env::current_exe()?)
+}
+
+#[cfg(target_os = "windows")]
+fn register_program(program: &Path) -> io::Result<()> {
+    let key = r"HKCU\Software\Classes\conwayste";
+    let open_command = format!("\"{}\" \"%1\"", program.display());
+    run("reg", &["add", key, "/ve", "/d", "URL:Conwayste", "/f"])?;
+    run("reg", &["add", key, "/v", "URL Protocol", "/d", "", "/f"])?;
+    run("reg", &["add", &format!(r"{}\shell\open\command", key), "/ve", "/d", &open_command, "/f"])
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn register_program(program: &Path) -> io::Result<()> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?
+        .join("applications");
+    fs::create_dir_all(&dir)?;
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Conwayste\nExec={} %u\nNoDisplay=true\n\
+         MimeType=x-scheme-handler/conwayste;\n",
+        desktop_exec_arg(&program.display().to_string())?
+    );
+    fs::write(dir.join(DESKTOP_FILE_NAME), entry)?;
+    run("xdg-mime", &["default", DESKTOP_FILE_NAME, "x-scheme-handler/conwayste"])
+}
+
+/// `arg` quoted as one argument in the Exec key of a desktop entry, as the XDG Desktop Entry
+/// Specification asks: `"`, `` ` ``, `$` and `\` are escaped within the quotes, and the backslashes
+/// escaped again since the key's value is a string; `%` is doubled so it isn't taken for a field
+/// code. Control characters can't be written there at all.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_exec_arg(arg: &str) -> io::Result<String> {
+    if arg.chars().any(char::is_control) {
+        let msg = format!("cannot write {:?} in a desktop entry", arg);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' => quoted.push_str("\\\\"),
+            '\\' => quoted.push_str("\\\\\\"),
+            '%' => quoted.push('%'),
+            _ => {}
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos")))))]
+fn register_program(_program: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "links are registered by the app bundle's Info.plist on this platform",
+    ))
+}
+
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+fn run(program: &str, args: &[&str]) -> io::Result<()> {
+    let status = Command::new(program).args(args).status()?;
+    if !status.success() {
+        let msg = format!("{} failed: {}", program, status);
+        return Err(io::Error::new(io::ErrorKind::Other, msg));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        assert_eq!(
+            Invite::parse("conwayste://example.com:2016/glider%20gun%20club?password=hunter2"),
+            Ok(Invite {
+                server: "example.com:2016".to_owned(),
+                room:   Some("glider gun club".to_owned()),
+            })
+        );
+        assert_eq!(
+            Invite::parse("Conwayste://localhost/"),
+            Ok(Invite {
+                server: "localhost".to_owned(),
+                room:   None,
+            })
+        );
+        assert!(Invite::parse("https://example.com/general").is_err());
+        assert!(Invite::parse("conwayste:///general").is_err());
+        assert!(Invite::parse("conwayste://localhost:2016/bad%2").is_err());
+        assert!(Invite::parse("conwayste://localhost:2016/line%0Abreak").is_err());
+        assert!(Invite::parse("conwayste://localhost:2016/sixteen%20letters").is_ok());
+        assert!(Invite::parse("conwayste://localhost:2016/seventeen%20letters").is_err());
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn test_desktop_exec_arg_is_escaped() {
+        assert_eq!(desktop_exec_arg("/usr/bin/conwayste").unwrap(), r#""/usr/bin/conwayste""#);
+        assert_eq!(
+            desktop_exec_arg(r#"/home/a"b/`$x/c\d/100%/game"#).unwrap(),
+            r#""/home/a\\"b/\\`\\$x/c\\\\d/100%%/game""#
+        );
+        assert!(desktop_exec_arg("/tmp/new\nline/game").is_err());
+    }
+}
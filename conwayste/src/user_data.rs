@@ -0,0 +1,142 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of conwayste.
+ *
+ *  conwayste is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  conwayste is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with conwayste.  If not, see
+ *  <http://www.gnu.org/licenses/>. */
+
+//! Where the game keeps what it writes for the player: the config, logs, chat logs and so on. These
+//! go where the platform expects them: under `$XDG_CONFIG_HOME` and `$XDG_DATA_HOME` on Linux, in
+//! `AppData\Roaming` on Windows, and in `Library/Application Support` on macOS. Anything new that
+//! the game saves should get a `Folder` here rather than a path of its own.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::constants::CONFIG_FILE_NAME;
+
+const APP_DIR_NAME: &str = "conwayste";
+
+/// A folder in the data directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Folder {
+    Logs,       // log files and crash reports
+    Chat,       // chat logs
+    History,    // the games played, for the Stats screen
+    Puzzles,    // puzzles added by the player, and which have been solved
+    Patterns,   // patterns saved from the pattern editor
+    Thumbnails, // the pattern library's thumbnails, drawn once
+    Bookmarks,  // camera bookmarks, for each room and puzzle
+}
+
+impl Folder {
+    pub fn path(self) -> PathBuf {
+        let name = match self {
+            Folder::Logs => "logs",
+            Folder::Chat => "chat",
+            Folder::History => "history",
+            Folder::Puzzles => "puzzles",
+            Folder::Patterns => "patterns",
+            Folder::Thumbnails => "thumbnails",
+            Folder::Bookmarks => "bookmarks",
+        };
+        data_dir().join(name)
+    }
+}
+
+/// The directory everything but the config is kept in. If the platform has none, the working
+/// directory is used.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// The config file. If the platform has no config directory, it's in the working directory, where
+/// it was kept before there was a data directory.
+pub fn config_file() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(CONFIG_FILE_NAME)
+}
+
+/// Moves a config file left in the working directory by an older version of the game to where it's
+/// kept now. Failures are logged, and the default config is then used.
+pub fn migrate() {
+    let (old, new) = (Path::new(CONFIG_FILE_NAME), config_file());
+    match move_if_absent(old, &new) {
+        Ok(true) => info!("Moved the config file from {:?} to {:?}", old, new),
+        Ok(false) => {}
+        Err(e) => warn!("Could not move the config file from {:?} to {:?}: {}", old, new, e),
+    }
+}
+
+/// Moves the file at `from` to `to` unless there's no file at `from` or there already is one at
+/// `to`. Returns whether it was moved.
+fn move_if_absent(from: &Path, to: &Path) -> io::Result<bool> {
+    if !from.is_file() || to.exists() || from == to {
+        return Ok(false);
+    }
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Renaming fails across file systems, so copy instead then
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(true)
+}
+
+/// Opens `dir` in the system's file manager, creating it first if need be.
+pub fn open_folder(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(dir).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_move_if_absent_keeps_an_existing_file() {
+        let dir = std::env::temp_dir().join(format!("conwayste-user-data-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("conwayste.toml");
+        let new = dir.join("config").join("conwayste.toml");
+
+        assert!(!move_if_absent(&old, &new).unwrap());
+
+        fs::write(&old, "[user]\nname = \"alice\"\n").unwrap();
+        assert!(move_if_absent(&old, &new).unwrap());
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(&new).unwrap(), "[user]\nname = \"alice\"\n");
+
+        fs::write(&old, "[user]\nname = \"bob\"\n").unwrap();
+        assert!(!move_if_absent(&old, &new).unwrap());
+        assert_eq!(fs::read_to_string(&new).unwrap(), "[user]\nname = \"alice\"\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
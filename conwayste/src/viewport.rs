@@ -18,11 +18,13 @@
 
 extern crate ggez;
 
+use conway::universe::Region;
 use ggez::graphics::Rect;
 use ggez::mint::Point2;
 
 use crate::constants::{
-    DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, MAX_CELL_SIZE, MIN_CELL_SIZE, PIXELS_SCROLLED_PER_FRAME,
+    DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, MAX_CELL_SIZE, MIN_CELL_SIZE, MIN_DENSITY_CELL_SIZE,
+    PIXELS_SCROLLED_PER_FRAME,
 };
 use crate::ui;
 
@@ -88,14 +90,9 @@ impl GridView {
     ///   holds after the cell size change.
     pub fn adjust_zoom_level(&mut self, direction: ZoomDirection) {
         if (direction == ZoomDirection::ZoomIn && self.cell_size < MAX_CELL_SIZE)
-            || (direction == ZoomDirection::ZoomOut && self.cell_size > MIN_CELL_SIZE)
+            || (direction == ZoomDirection::ZoomOut && self.cell_size > MIN_DENSITY_CELL_SIZE)
         {
-            let zoom_dir: f32 = match direction {
-                ZoomDirection::ZoomIn => ZOOM_IN,
-                ZoomDirection::ZoomOut => ZOOM_OUT,
-            };
-
-            let next_cell_size = self.cell_size + zoom_dir;
+            let next_cell_size = next_cell_size(self.cell_size, direction);
             let old_cell_size = self.cell_size;
             let cell_size_delta = next_cell_size - old_cell_size;
 
@@ -113,8 +110,6 @@ impl GridView {
                     * (old_cell_center_y as f32 * next_cell_size as f32
                         - old_cell_center_y as f32 * old_cell_size as f32);
 
-                self.cell_size = next_cell_size;
-
                 let phi = self.columns as f32 * old_cell_size as f32;
                 let alpha = self.rect.w;
 
@@ -122,9 +117,12 @@ impl GridView {
                     self.grid_origin =
                         ui::point_offset(self.grid_origin, -cell_size_delta * delta_x, -cell_size_delta * delta_y);
                 }
-
-                self.adjust_panning(true, NO_INPUT);
             }
+
+            // Zoomed out far enough, the whole grid fits away from the center of the screen, but
+            // it can still be zoomed
+            self.cell_size = next_cell_size;
+            self.adjust_panning(true, NO_INPUT);
         }
     }
 
@@ -259,6 +257,17 @@ impl GridView {
         self.cell_size
     }
 
+    /// When zoomed out so far that a pixel holds a cell or more, the number of cells along each
+    /// side of the square a pixel holds. The universe is then drawn as the density of its cells,
+    /// since a cell a pixel wide would be all gap and no cell if drawn on its own.
+    pub fn cells_per_pixel(&self) -> Option<usize> {
+        if self.cell_size <= 1.0 {
+            Some((1.0 / self.cell_size).round() as usize)
+        } else {
+            None
+        }
+    }
+
     /// Gets a rectangle representing the grid in game coordinates.
     pub fn get_rect(&self) -> Rect {
         self.rect
@@ -274,6 +283,24 @@ impl GridView {
         self.grid_origin = point;
     }
 
+    /// The point in the middle of the screen, in cells, so that the middle of cell 3 is at 3.5.
+    pub fn center(&self) -> (f32, f32) {
+        (
+            (self.rect.w / 2.0 - self.grid_origin.x) / self.cell_size,
+            (self.rect.h / 2.0 - self.grid_origin.y) / self.cell_size,
+        )
+    }
+
+    /// Zooms to `cell_size`, within the zoom levels allowed, and moves the grid so that `center`,
+    /// in cells as `center` returns it, is in the middle of the screen.
+    pub fn look_at(&mut self, center: (f32, f32), cell_size: f32) {
+        self.cell_size = cell_size.max(MIN_DENSITY_CELL_SIZE).min(MAX_CELL_SIZE);
+        self.grid_origin = Point2 {
+            x: self.rect.w / 2.0 - center.0 * self.cell_size,
+            y: self.rect.h / 2.0 - center.1 * self.cell_size,
+        };
+    }
+
     /// Returns the width of the grid in pixels.
     pub fn grid_width(&self) -> f32 {
         self.columns as f32 * self.cell_size
@@ -292,6 +319,16 @@ impl GridView {
         Rect::new(origin.x, origin.y, full_width, full_height)
     }
 
+    /// The cells on screen, including any partly shown at the edges. May extend past the universe.
+    pub fn visible_cells(&self) -> Region {
+        let (left, top) = self.game_coords_from_window_unchecked(self.rect.point());
+        let (right, bottom) = self.game_coords_from_window_unchecked(Point2 {
+            x: self.rect.right() - 1.0,
+            y: self.rect.bottom() - 1.0,
+        });
+        Region::new(left, top, (right - left + 1) as usize, (bottom - top + 1) as usize)
+    }
+
     /// Attempt to return a tuple of cell coordinates within the game space.
     /// Can be outside of the playble space, it is the responsibility of the caller
     /// to sanitize the output.
@@ -323,8 +360,9 @@ impl GridView {
         let top = self.grid_origin.y + (row as f32) * self.cell_size;
         let bottom = self.grid_origin.y + (row + 1) as f32 * self.cell_size - 1.0;
 
-        assert!(left < right);
-        assert!(top < bottom);
+        if left >= right || top >= bottom {
+            return None; // too small to draw on its own; see `cells_per_pixel`
+        }
 
         // The 'minus one' above for right and bottom give it that grid-like feel :)
         let rect = Rect::new(left, top, right - left, bottom - top);
@@ -340,6 +378,30 @@ impl GridView {
         return None;
     }
 
+    /// The lines between the cells on screen, a pixel wide, in window coordinates. They fill the
+    /// gaps left by drawing each cell a pixel short of its size.
+    pub fn grid_lines(&self) -> Vec<Rect> {
+        let shown = match ui::intersection(self.get_rect_from_origin(), self.rect) {
+            Some(shown) => shown,
+            None => return vec![],
+        };
+        let visible = self.visible_cells();
+        let mut lines = vec![];
+        for col in visible.left()..=visible.right() {
+            let x = self.grid_origin.x + (col + 1) as f32 * self.cell_size - 1.0;
+            if x >= shown.left() && x < shown.right() {
+                lines.push(Rect::new(x, shown.y, 1.0, shown.h));
+            }
+        }
+        for row in visible.top()..=visible.bottom() {
+            let y = self.grid_origin.y + (row + 1) as f32 * self.cell_size - 1.0;
+            if y >= shown.top() && y < shown.bottom() {
+                lines.push(Rect::new(shown.x, y, shown.w, 1.0));
+            }
+        }
+        lines
+    }
+
     /// Sets the width of the GridView in window coordinates (pixels).
     pub fn set_width(&mut self, width: f32) {
         self.rect.w = width;
@@ -351,6 +413,20 @@ impl GridView {
     }
 }
 
+/// The cell size after zooming once from `cell_size`. Cell sizes step by a pixel down to
+/// `MIN_CELL_SIZE`, then drop to a pixel and halve from there, so a pixel always holds a whole
+/// power-of-two square of cells.
+fn next_cell_size(cell_size: f32, direction: ZoomDirection) -> f32 {
+    match direction {
+        ZoomDirection::ZoomIn if cell_size < 1.0 => cell_size * 2.0,
+        ZoomDirection::ZoomIn if cell_size < MIN_CELL_SIZE => MIN_CELL_SIZE,
+        ZoomDirection::ZoomIn => cell_size + ZOOM_IN,
+        ZoomDirection::ZoomOut if cell_size > MIN_CELL_SIZE => cell_size + ZOOM_OUT,
+        ZoomDirection::ZoomOut if cell_size > 1.0 => 1.0,
+        ZoomDirection::ZoomOut => cell_size / 2.0,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -389,6 +465,69 @@ mod test {
         assert_eq!(gv.game_coords_from_window_unchecked(outside), (-1, -1));
     }
 
+    #[test]
+    fn test_zooming_out_past_the_smallest_cells_shows_density() {
+        let mut gv = GridView::new(MIN_CELL_SIZE + 1.0, UNIVERSE_WIDTH_IN_CELLS, UNIVERSE_HEIGHT_IN_CELLS);
+        let mut sizes = vec![];
+        while gv.cell_size > MIN_DENSITY_CELL_SIZE {
+            gv.adjust_zoom_level(ZoomDirection::ZoomOut);
+            sizes.push((gv.cell_size, gv.cells_per_pixel()));
+        }
+        assert_eq!(
+            sizes,
+            vec![
+                (MIN_CELL_SIZE, None),
+                (1.0, Some(1)),
+                (0.5, Some(2)),
+                (0.25, Some(4)),
+                (0.125, Some(8)),
+            ]
+        );
+
+        // and back the same way
+        gv.adjust_zoom_level(ZoomDirection::ZoomOut);
+        assert_eq!(gv.cell_size, MIN_DENSITY_CELL_SIZE);
+        for &expected in &[0.25, 0.5, 1.0, MIN_CELL_SIZE, MIN_CELL_SIZE + 1.0] {
+            gv.adjust_zoom_level(ZoomDirection::ZoomIn);
+            assert_eq!(gv.cell_size, expected);
+        }
+    }
+
+    #[test]
+    fn test_one_pixel_cells_are_drawn_as_density() {
+        let gv = GridView::new(1.0, UNIVERSE_WIDTH_IN_CELLS, UNIVERSE_HEIGHT_IN_CELLS);
+        // no room for a cell once the gap for the grid is left
+        assert_eq!(gv.window_coords_from_game(Cell::new(3, 3)), None);
+        assert_eq!(gv.cells_per_pixel(), Some(1));
+
+        let gv = GridView::new(2.0, UNIVERSE_WIDTH_IN_CELLS, UNIVERSE_HEIGHT_IN_CELLS);
+        assert!(gv.window_coords_from_game(Cell::new(3, 3)).is_some());
+        assert_eq!(gv.cells_per_pixel(), None);
+    }
+
+    #[test]
+    fn test_gridview_visible_cells() {
+        let mut gv = gen_default_gridview();
+        gv.set_size(105.0, 50.0);
+        assert_eq!(gv.visible_cells(), Region::new(0, 0, 11, 5));
+
+        gv.set_origin(Point2 { x: -200.0, y: -100.0 });
+        assert_eq!(gv.visible_cells(), Region::new(20, 10, 11, 5));
+    }
+
+    #[test]
+    fn test_gridview_look_at_puts_the_point_in_the_middle() {
+        let mut gv = gen_default_gridview();
+        gv.set_size(200.0, 100.0);
+        gv.look_at((100.5, 40.0), 4.0);
+        assert_eq!(gv.get_cell_size(), 4.0);
+        assert_eq!(gv.center(), (100.5, 40.0));
+        assert_eq!(gv.get_cell(Point2 { x: 100.0, y: 50.0 }), Some(Cell::new(100, 40)));
+
+        gv.look_at((10.0, 10.0), MAX_CELL_SIZE * 2.0);
+        assert_eq!(gv.get_cell_size(), MAX_CELL_SIZE);
+    }
+
     #[test]
     fn test_gridview_game_coords_checked() {
         let gv = gen_default_gridview();
@@ -436,4 +575,19 @@ mod test {
         assert_eq!(gv.window_coords_from_game(outside1), None);
         assert_eq!(gv.window_coords_from_game(outside2), None);
     }
+
+    #[test]
+    fn test_gridview_grid_lines_fill_gaps_between_cells() {
+        let gv = gen_default_gridview();
+        let lines = gv.grid_lines();
+        let columns = (DEFAULT_SCREEN_WIDTH / gv.cell_size) as usize;
+        let rows = (DEFAULT_SCREEN_HEIGHT / gv.cell_size) as usize;
+        assert_eq!(lines.len(), columns + rows);
+        assert_eq!(lines[0], Rect::new(9.0, 0.0, 1.0, DEFAULT_SCREEN_HEIGHT));
+        assert_eq!(lines[columns], Rect::new(0.0, 9.0, DEFAULT_SCREEN_WIDTH, 1.0));
+
+        // each line is just right of a cell
+        let cell = gv.window_coords_from_game(Cell::new(0, 0)).unwrap();
+        assert_eq!(cell.right(), lines[0].left());
+    }
 }
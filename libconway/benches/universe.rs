@@ -0,0 +1,115 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Benchmarks for stepping a universe and applying the diffs a server sends to clients.
+//!
+//! Run with `cargo bench -p conway`.
+
+extern crate conway;
+extern crate criterion;
+extern crate rand;
+
+use conway::universe::*;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const HISTORY: usize = 16;
+const SIZES: [usize; 3] = [128, 512, 1024]; // width and height in cells; widths must be multiples of 64
+const DENSITIES: [f64; 3] = [0.05, 0.25, 0.5]; // fraction of cells alive at the start
+
+/// A universe with one player who can write anywhere, filled at random to `density` with a fixed
+/// seed, so that every run benchmarks the same cells.
+fn random_universe(size: usize, density: f64, server: bool) -> Universe {
+    let player = PlayerBuilder::new(Region::new(0, 0, size, size));
+    let mut uni = BigBang::new()
+        .width(size)
+        .height(size)
+        .server_mode(server)
+        .history(HISTORY)
+        .fog_radius(8)
+        .add_player(player)
+        .birth()
+        .unwrap();
+    if server {
+        let mut rng = StdRng::seed_from_u64(size as u64);
+        for row in 0..size {
+            for col in 0..size {
+                if rng.gen_bool(density) {
+                    uni.set(col, row, CellState::Alive(Some(0)), 0);
+                }
+            }
+        }
+    }
+    uni
+}
+
+fn bench_next(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Universe::next");
+    for &size in SIZES.iter() {
+        for &density in DENSITIES.iter() {
+            // The universe keeps evolving from one iteration to the next, so this measures the
+            // random start settling down, as it would in a game
+            let mut uni = random_universe(size, density, true);
+            let id = BenchmarkId::new(format!("{}x{}", size, size), density);
+            group.bench_function(id, |b| {
+                b.iter(|| uni.next());
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_apply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Universe::apply");
+    for &size in SIZES.iter() {
+        let density = 0.25;
+        let mut server = random_universe(size, density, true);
+        for _ in 0..4 {
+            server.next();
+        }
+        let latest = server.latest_gen();
+
+        // What a client that has just joined receives: everything, from the beginning of time
+        let full = server.diff(0, latest, None).unwrap();
+        group.bench_function(BenchmarkId::new("full", format!("{}x{}", size, size)), |b| {
+            b.iter_batched(
+                || random_universe(size, density, false),
+                |mut client| client.apply(&full, None).unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+
+        // What a client that is keeping up receives: one generation's worth of changes
+        let incremental = server.diff(latest - 1, latest, None).unwrap();
+        let base = server.diff(0, latest - 1, None).unwrap();
+        group.bench_function(BenchmarkId::new("incremental", format!("{}x{}", size, size)), |b| {
+            b.iter_batched(
+                || {
+                    let mut client = random_universe(size, density, false);
+                    client.apply(&base, None).unwrap();
+                    client
+                },
+                |mut client| client.apply(&incremental, None).unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_next, bench_apply);
+criterion_main!(benches);
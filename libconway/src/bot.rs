@@ -0,0 +1,208 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+use std::cmp;
+
+use crate::grids::CharGrid;
+use crate::rle::Pattern;
+use crate::universe::{CellState, Region, Universe};
+
+/// Enemy cells this close (in cells) to the bot's writable region are treated as a threat.
+pub const THREAT_DISTANCE: usize = 12;
+/// Every this many turns, the bot builds a glider gun rather than launching a single glider.
+pub const GUN_EVERY_N_TURNS: usize = 4;
+/// Cells between the spots the bot aims successive gliders and guns from.
+pub const PLACEMENT_SPREAD: isize = 12;
+
+// Where each turn's glider or gun goes relative to the bot's aim, in multiples of
+// PLACEMENT_SPREAD, so that it isn't drawn over the last one.
+const PLACEMENT_OFFSETS: [(isize, isize); 5] = [(0, 0), (1, -1), (-1, 1), (-1, -1), (1, 1)];
+
+// These all head (or fire) south-east. Other directions are had by mirroring.
+const GLIDER: &str = "bob$2bo$3o!";
+const GOSPER_GLIDER_GUN: &str = concat!(
+    "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$",
+    "12b2o!"
+);
+const BLOCK: &str = "2o$2o!";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotMoveKind {
+    GliderGun,
+    Glider,
+    StillLife,
+}
+
+/// A pattern a `Bot` has decided to draw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotMove {
+    pub kind:  BotMoveKind,
+    pub cells: Vec<(usize, usize)>, // (col, row) of each cell to bring to life
+}
+
+/// A computer opponent. On each of its turns it looks at the universe as its player sees it (that
+/// is, through the fog) and draws one pattern in its writable region: a still life between itself
+/// and any enemy cells that have come too close, or else a glider or glider gun aimed at the
+/// enemy.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    player_id:     usize,
+    turn_interval: usize, // generations between turns
+    turns_taken:   usize,
+}
+
+impl Bot {
+    /// Creates a bot playing as `player_id` that takes a turn every `turn_interval` generations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `turn_interval` is zero.
+    pub fn new(player_id: usize, turn_interval: usize) -> Self {
+        assert!(turn_interval != 0);
+        Bot {
+            player_id,
+            turn_interval,
+            turns_taken: 0,
+        }
+    }
+
+    pub fn player_id(&self) -> usize {
+        self.player_id
+    }
+
+    /// Should be called after every generation. If it is the bot's turn, this draws the bot's
+    /// next move into `uni` and returns it.
+    pub fn on_generation(&mut self, uni: &mut Universe) -> Option<BotMove> {
+        if !uni.latest_gen().is_multiple_of(self.turn_interval) {
+            return None;
+        }
+        let bot_move = self.plan(uni)?;
+        for &(col, row) in &bot_move.cells {
+            uni.set(col, row, CellState::Alive(Some(self.player_id)), self.player_id);
+        }
+        self.turns_taken += 1;
+        Some(bot_move)
+    }
+
+    /// Decides on the bot's next move without changing the universe. Returns None if nothing
+    /// fits in the bot's writable region.
+    pub fn plan(&self, uni: &Universe) -> Option<BotMove> {
+        let region = uni.writable_region(self.player_id);
+        let enemy_cells = self.visible_enemy_cells(uni);
+
+        let opt_nearest = enemy_cells
+            .iter()
+            .cloned()
+            .min_by_key(|&(col, row)| distance_to_region(region, col, row));
+        if let Some((col, row)) = opt_nearest {
+            if distance_to_region(region, col, row) <= THREAT_DISTANCE {
+                return place(BotMoveKind::StillLife, BLOCK, (false, false), region, (col, row));
+            }
+        }
+
+        // Aim for the middle of the enemy cells, or the middle of the universe if none are visible
+        let target = if enemy_cells.is_empty() {
+            (uni.width() / 2, uni.height() / 2)
+        } else {
+            let (col_sum, row_sum) = enemy_cells
+                .iter()
+                .fold((0, 0), |(col_sum, row_sum), &(col, row)| (col_sum + col, row_sum + row));
+            (col_sum / enemy_cells.len(), row_sum / enemy_cells.len())
+        };
+        let center_col = region.left() + region.width() as isize / 2;
+        let center_row = region.top() + region.height() as isize / 2;
+        let mirror = ((target.0 as isize) < center_col, (target.1 as isize) < center_row);
+
+        let (col_offset, row_offset) = PLACEMENT_OFFSETS[self.turns_taken % PLACEMENT_OFFSETS.len()];
+        let target = (
+            cmp::max(0, target.0 as isize + col_offset * PLACEMENT_SPREAD) as usize,
+            cmp::max(0, target.1 as isize + row_offset * PLACEMENT_SPREAD) as usize,
+        );
+        if self.turns_taken.is_multiple_of(GUN_EVERY_N_TURNS) {
+            if let Some(gun) = place(BotMoveKind::GliderGun, GOSPER_GLIDER_GUN, mirror, region, target) {
+                return Some(gun);
+            }
+        }
+        place(BotMoveKind::Glider, GLIDER, mirror, region, target)
+    }
+
+    fn visible_enemy_cells(&self, uni: &Universe) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        uni.each_non_dead_full(Some(self.player_id), &mut |col, row, state| {
+            if let CellState::Alive(Some(player_id)) = state {
+                if player_id != self.player_id {
+                    cells.push((col, row));
+                }
+            }
+        });
+        cells
+    }
+}
+
+/// Distance in cells from the given cell to the nearest cell of `region`, counting diagonal steps
+/// as one. Zero if the cell is inside.
+fn distance_to_region(region: Region, col: usize, row: usize) -> usize {
+    let axis_distance = |pos: isize, low: isize, high: isize| {
+        if pos < low {
+            low - pos
+        } else if pos > high {
+            pos - high
+        } else {
+            0
+        }
+    };
+    let col_distance = axis_distance(col as isize, region.left(), region.right());
+    let row_distance = axis_distance(row as isize, region.top(), region.bottom());
+    cmp::max(col_distance, row_distance) as usize
+}
+
+/// Lays out the pattern in `rle`, mirrored horizontally and/or vertically per `mirror`, centered as
+/// close to `target` as it can be while staying inside `region`. Returns None if it doesn't fit.
+fn place(
+    kind: BotMoveKind,
+    rle: &str,
+    mirror: (bool, bool),
+    region: Region,
+    target: (usize, usize),
+) -> Option<BotMove> {
+    let pattern = Pattern(rle.to_owned());
+    let (width, height) = pattern.calc_size().ok()?;
+    if width > region.width() || height > region.height() {
+        return None;
+    }
+    let grid = pattern.to_new_bit_grid(width, height).ok()?;
+
+    let clamp = |pos: isize, low: isize, high: isize| cmp::min(cmp::max(pos, low), high);
+    let left = clamp(
+        target.0 as isize - width as isize / 2,
+        region.left(),
+        region.right() + 1 - width as isize,
+    );
+    let top = clamp(
+        target.1 as isize - height as isize / 2,
+        region.top(),
+        region.bottom() + 1 - height as isize,
+    );
+
+    let mut cells = vec![];
+    grid.each_set(|col, row| {
+        let col = if mirror.0 { width - 1 - col } else { col };
+        let row = if mirror.1 { height - 1 - row } else { row };
+        cells.push(((left + col as isize) as usize, (top + row as isize) as usize));
+    });
+    Some(BotMove { kind, cells })
+}
@@ -17,7 +17,8 @@
 
 custom_error! {pub ConwayError
     InvalidData {reason: String} = "ConwayError->InvalidData->{reason}",
-    AccessDenied{reason: String} = "ConwayError->AccessDenied->{reason}"
+    AccessDenied{reason: String} = "ConwayError->AccessDenied->{reason}",
+    ScriptError {reason: String} = "ConwayError->ScriptError->{reason}"
 }
 
 pub type ConwayResult<T> = ::std::result::Result<T, ConwayError>;
@@ -50,6 +51,18 @@ impl PartialEq for ConwayError {
                     false
                 }
             }
+            ScriptError {
+                reason: ref self_reason,
+            } => {
+                if let ScriptError {
+                    reason: ref other_reason,
+                } = *other
+                {
+                    self_reason == other_reason
+                } else {
+                    false
+                }
+            }
         }
     }
 }
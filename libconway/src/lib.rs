@@ -21,9 +21,12 @@ extern crate env_logger;
 #[macro_use]
 extern crate custom_error;
 
+pub mod bot;
 pub mod error;
 pub mod grids;
 pub mod rle;
+#[cfg(feature = "scripting")]
+pub mod script;
 pub mod universe;
 
 pub use error::{ConwayError, ConwayResult};
@@ -22,6 +22,7 @@ use crate::error::{ConwayError, ConwayResult};
 use crate::grids::{BitGrid, CharGrid};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
 /// This contains just the RLE pattern string. For example: "4bobo$7b3o!"
@@ -65,6 +66,21 @@ impl PatternFile {
     }
 }
 
+impl fmt::Display for PatternFile {
+    /// Writes the contents of an RLE file, which `from_str` reads back the same.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.comment_lines {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "x = {}, y = {}", self.header_line.x, self.header_line.y)?;
+        if let Some(ref rule) = self.header_line.rule {
+            write!(f, ", rule = {}", rule)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", self.pattern.0)
+    }
+}
+
 impl FromStr for PatternFile {
     type Err = ConwayError;
 
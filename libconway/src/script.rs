@@ -0,0 +1,290 @@
+/*  Copyright 2021 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! User scripts, written in [Rhai](https://rhai.rs), that play on behalf of a player.
+//!
+//! A script may define any of these functions, which are called with `this` bound to a map the
+//! script can use to remember things between calls:
+//!
+//! * `on_start()` -- before the first generation the script sees.
+//! * `on_generation()` -- once per generation.
+//!
+//! Scripts can only reach the outside world through the functions below. Reads see the universe
+//! as it was when the call began; writes and chat messages take effect once the call returns.
+//!
+//! | Function                 | Description                                                      |
+//! |--------------------------|------------------------------------------------------------------|
+//! | `player_id()`            | The player the script plays as.                                  |
+//! | `generation()`           | The current generation.                                          |
+//! | `width()`, `height()`    | Size of the universe, in cells.                                  |
+//! | `writable_region()`      | Map with `left`, `top`, `width` and `height` of the player's region. |
+//! | `cell(col, row)`         | `"dead"`, `"mine"`, `"enemy"`, `"unowned"`, `"wall"`, or `"fog"`. |
+//! | `set_cell(col, row)`     | Brings a cell to life. False if it is outside the writable region. |
+//! | `place(rle, col, row)`   | Draws an RLE pattern with its upper-left corner at `(col, row)`. |
+//! | `chat(message)`          | Sends a chat message.                                            |
+//! | `emit(name, text)`       | Sends the game a custom event called `name`, carrying `text`.    |
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, AST};
+
+use crate::error::{ConwayError, ConwayResult};
+use crate::grids::CharGrid;
+use crate::rle::Pattern;
+use crate::universe::{CellState, Region, Universe};
+
+/// A call into a script is aborted after this many operations, so a runaway loop can't hang the
+/// game.
+pub const MAX_OPERATIONS_PER_CALL: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 4096;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+/// A script may bring at most this many cells to life per call.
+pub const MAX_CELLS_PER_CALL: usize = 1024;
+
+/// What the script can see and has asked to do during a single call.
+#[derive(Default)]
+struct ScriptState {
+    player_id:    usize,
+    generation:   usize,
+    width:        usize,
+    height:       usize,
+    region:       Option<Region>,
+    cells:        HashMap<(usize, usize), CellState>, // non-dead cells visible to the player
+    cells_to_set: Vec<(usize, usize)>,
+    chat:         Vec<String>,
+    events:       Vec<(String, String)>, // (name, text)
+}
+
+impl ScriptState {
+    fn in_region(&self, col: i64, row: i64) -> bool {
+        self.region
+            .is_some_and(|region| region.contains(col as isize, row as isize))
+    }
+
+    fn queue_cell(&mut self, col: i64, row: i64) -> bool {
+        if !self.in_region(col, row) || self.cells_to_set.len() >= MAX_CELLS_PER_CALL {
+            return false;
+        }
+        self.cells_to_set.push((col as usize, row as usize));
+        true
+    }
+}
+
+pub struct Script {
+    name:    String,
+    engine:  Engine,
+    ast:     AST,
+    memory:  Dynamic, // bound to `this` in the script's functions
+    started: bool,
+    state:   Rc<RefCell<ScriptState>>,
+}
+
+impl Script {
+    /// Compiles the script in `source`, to play as `player_id`. `name` is only used in messages.
+    pub fn new(name: &str, source: &str, player_id: usize) -> ConwayResult<Self> {
+        let state = Rc::new(RefCell::new(ScriptState {
+            player_id,
+            ..Default::default()
+        }));
+        let engine = new_engine(&state, name);
+        let ast = engine.compile(source).map_err(|e| ConwayError::ScriptError {
+            reason: format!("{}: {}", name, e),
+        })?;
+
+        Ok(Script {
+            name: name.to_owned(),
+            engine,
+            ast,
+            memory: Dynamic::from_map(Map::new()),
+            started: false,
+            state,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Lets the script take its turn for the latest generation of `uni`. Returns any chat messages
+    /// it sent.
+    pub fn on_generation(&mut self, uni: &mut Universe) -> ConwayResult<Vec<String>> {
+        self.load_state(uni);
+
+        if !self.started {
+            self.started = true;
+            self.call("on_start")?;
+        }
+        self.call("on_generation")?;
+
+        let mut state = self.state.borrow_mut();
+        let player_id = state.player_id;
+        for (col, row) in state.cells_to_set.drain(..) {
+            uni.set(col, row, CellState::Alive(Some(player_id)), player_id);
+        }
+        Ok(state.chat.drain(..).collect())
+    }
+
+    /// The custom events the script sent during its last turn, as (name, text), oldest first.
+    pub fn take_events(&mut self) -> Vec<(String, String)> {
+        self.state.borrow_mut().events.drain(..).collect()
+    }
+
+    /// Calls the script function `fn_name`, if the script defines it.
+    fn call(&mut self, fn_name: &str) -> ConwayResult<()> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|f| f.name == fn_name && f.params.is_empty())
+        {
+            return Ok(());
+        }
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.memory);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut rhai::Scope::new(), &self.ast, fn_name, ())
+            .map(|_| ())
+            .map_err(|e| ConwayError::ScriptError {
+                reason: format!("{}: {}() failed: {}", self.name, fn_name, e),
+            })
+    }
+
+    /// Takes a snapshot of what the script's player can see of `uni`.
+    fn load_state(&mut self, uni: &Universe) {
+        let mut state = self.state.borrow_mut();
+        state.generation = uni.latest_gen();
+        state.width = uni.width();
+        state.height = uni.height();
+        state.region = Some(uni.writable_region(state.player_id));
+        state.cells.clear();
+        state.cells_to_set.clear();
+        state.chat.clear();
+        state.events.clear();
+
+        let player_id = state.player_id;
+        let cells = &mut state.cells;
+        uni.each_non_dead_full(Some(player_id), &mut |col, row, cell| {
+            cells.insert((col, row), cell);
+        });
+    }
+}
+
+/// Builds an engine that exposes only the scripting API, with no access to files or modules and
+/// with limits on how much work a script can do.
+fn new_engine(state: &Rc<RefCell<ScriptState>>, name: &str) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS_PER_CALL);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let print_name = name.to_owned();
+    engine.on_print(move |text| info!("[script {}] {}", print_name, text));
+    let debug_name = name.to_owned();
+    engine.on_debug(move |text, _, _| debug!("[script {}] {}", debug_name, text));
+
+    let s = state.clone();
+    engine.register_fn("player_id", move || s.borrow().player_id as i64);
+    let s = state.clone();
+    engine.register_fn("generation", move || s.borrow().generation as i64);
+    let s = state.clone();
+    engine.register_fn("width", move || s.borrow().width as i64);
+    let s = state.clone();
+    engine.register_fn("height", move || s.borrow().height as i64);
+
+    let s = state.clone();
+    engine.register_fn("writable_region", move || {
+        let mut map = Map::new();
+        if let Some(region) = s.borrow().region {
+            map.insert("left".into(), (region.left() as i64).into());
+            map.insert("top".into(), (region.top() as i64).into());
+            map.insert("width".into(), (region.width() as i64).into());
+            map.insert("height".into(), (region.height() as i64).into());
+        }
+        map
+    });
+
+    let s = state.clone();
+    engine.register_fn("cell", move |col: i64, row: i64| -> String {
+        let state = s.borrow();
+        if col < 0 || row < 0 {
+            return "dead".to_owned();
+        }
+        let cell = state
+            .cells
+            .get(&(col as usize, row as usize))
+            .cloned()
+            .unwrap_or(CellState::Dead);
+        match cell {
+            CellState::Dead => "dead",
+            CellState::Alive(Some(player_id)) if player_id == state.player_id => "mine",
+            CellState::Alive(Some(_)) => "enemy",
+            CellState::Alive(None) => "unowned",
+            CellState::Wall => "wall",
+            CellState::Fog => "fog",
+        }
+        .to_owned()
+    });
+
+    let s = state.clone();
+    engine.register_fn("set_cell", move |col: i64, row: i64| {
+        s.borrow_mut().queue_cell(col, row)
+    });
+
+    let s = state.clone();
+    engine.register_fn("place", move |rle: &str, col: i64, row: i64| -> bool {
+        let pattern = Pattern(rle.to_owned());
+        let (width, height) = match pattern.calc_size() {
+            Ok(size) => size,
+            Err(_) => return false,
+        };
+        // Checked before the grid is made, since a short RLE can claim billions of cells
+        let fits = s
+            .borrow()
+            .region
+            .is_some_and(|region| width <= region.width() && height <= region.height());
+        if !fits {
+            return false;
+        }
+        let grid = match pattern.to_new_bit_grid(width, height) {
+            Ok(grid) => grid,
+            Err(_) => return false,
+        };
+        let mut state = s.borrow_mut();
+        let mut all_placed = true;
+        grid.each_set(|pattern_col, pattern_row| {
+            all_placed &= state.queue_cell(col + pattern_col as i64, row + pattern_row as i64);
+        });
+        all_placed
+    });
+
+    let s = state.clone();
+    engine.register_fn("chat", move |message: &str| {
+        s.borrow_mut().chat.push(message.to_owned());
+    });
+
+    let s = state.clone();
+    engine.register_fn("emit", move |name: &str, text: &str| {
+        s.borrow_mut().events.push((name.to_owned(), text.to_owned()));
+    });
+
+    engine
+}
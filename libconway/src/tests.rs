@@ -32,6 +32,28 @@ mod universe_tests {
         assert_eq!(uni.region(), universe_as_region);
     }
 
+    #[test]
+    fn lift_fog_clears_region_for_every_player() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let region = Region::new(200, 10, 8, 4);
+        let fog_in_region = |uni: &Universe, player_id| {
+            let mut count = 0;
+            uni.each_non_dead_full(Some(player_id), &mut |col, row, state| {
+                if state == CellState::Fog && region.contains(col as isize, row as isize) {
+                    count += 1;
+                }
+            });
+            count
+        };
+        assert_eq!(fog_in_region(&uni, 0), 32);
+        assert_eq!(fog_in_region(&uni, 1), 32);
+
+        uni.lift_fog(region);
+        assert_eq!(fog_in_region(&uni, 0), 0);
+        assert_eq!(fog_in_region(&uni, 1), 0);
+        uni.lift_fog(Region::new(250, 120, 20, 20)); // partly outside the universe
+    }
+
     #[test]
     fn new_universe_with_bad_dims() {
         let player0 = PlayerBuilder::new(Region::new(100, 70, 34, 16)); // used for the glider gun and predefined patterns
@@ -84,6 +106,73 @@ mod universe_tests {
         assert_eq!(uni.latest_gen(), gens + 1);
     }
 
+    #[test]
+    fn live_counts_adds_up_the_cells_of_each_square() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        for &(col, row) in &[(0, 0), (7, 7), (8, 0), (63, 9), (64, 9), (255, 127)] {
+            uni.set_unchecked(col, row, CellState::Alive(None));
+        }
+
+        let (columns, rows, counts) = uni.live_counts(8, None);
+        assert_eq!((columns, rows), (32, 16));
+        assert_eq!(counts[0], 2);
+        assert_eq!(counts[1], 1);
+        assert_eq!(counts[columns + 7], 1);
+        assert_eq!(counts[columns + 8], 1);
+        assert_eq!(counts[columns * rows - 1], 1);
+        assert_eq!(counts.iter().sum::<u32>(), 6);
+
+        let (columns, rows, counts) = uni.live_counts(64, None);
+        assert_eq!((columns, rows), (4, 2));
+        assert_eq!(counts, vec![4, 1, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn region_fingerprint_changes_only_with_the_cells_near_the_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let left = Region::new(0, 0, 64, 64);
+        let right = Region::new(64, 0, 64, 64);
+        let (left_before, right_before) = (uni.region_fingerprint(left, None), uni.region_fingerprint(right, None));
+
+        uni.toggle(10, 10, 1).unwrap();
+        assert_ne!(uni.region_fingerprint(left, None), left_before);
+        assert_eq!(uni.region_fingerprint(right, None), right_before);
+
+        // which player owns the cell matters too
+        let owned_by_1 = uni.region_fingerprint(left, None);
+        uni.set_unchecked(10, 10, CellState::Alive(None));
+        assert_ne!(uni.region_fingerprint(left, None), owned_by_1);
+
+        // regions past the edge of the universe only cover what's inside it
+        assert_eq!(
+            uni.region_fingerprint(Region::new(-10, -10, 74, 74), None),
+            uni.region_fingerprint(left, None)
+        );
+    }
+
+    #[test]
+    fn state_hash_matches_only_when_cells_match() {
+        let mut uni_a = generate_test_universe_with_default_params(UniType::Server);
+        let mut uni_b = generate_test_universe_with_default_params(UniType::Server);
+        assert_eq!(uni_a.state_hash(), uni_b.state_hash());
+
+        // glider; player 1 in one universe and player 0's unowned copy in the other
+        for &(col, row) in &[(11, 10), (12, 11), (10, 12), (11, 12), (12, 12)] {
+            uni_a.toggle(col, row, 1).unwrap();
+            uni_b.set_unchecked(col, row, CellState::Alive(None));
+        }
+        assert_eq!(uni_a.state_hash(), uni_b.state_hash());
+
+        for _ in 0..8 {
+            uni_a.next();
+            uni_b.next();
+        }
+        assert_eq!(uni_a.state_hash(), uni_b.state_hash());
+
+        uni_b.set_unchecked(70, 70, CellState::Alive(None));
+        assert_ne!(uni_a.state_hash(), uni_b.state_hash());
+    }
+
     #[test]
     fn set_unchecked_with_valid_rows_and_cols() {
         let mut uni = generate_test_universe_with_default_params(UniType::Server);
@@ -160,6 +249,19 @@ mod universe_tests {
         assert_eq!(cell_state, CellState::Dead);
     }
 
+    #[test]
+    fn cell_state_tells_walls_and_owners_apart() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(1, 1, CellState::Wall);
+        uni.set_unchecked(2, 2, CellState::Alive(None));
+        uni.set(55, 55, CellState::Alive(Some(1)), 1);
+
+        assert_eq!(uni.cell_state(1, 1), CellState::Wall);
+        assert_eq!(uni.cell_state(2, 2), CellState::Alive(None));
+        assert_eq!(uni.cell_state(55, 55), CellState::Alive(Some(1)));
+        assert_eq!(uni.cell_state(3, 3), CellState::Dead);
+    }
+
     #[test]
     fn toggle_checked_outside_a_player_writable_region_fails() {
         let mut uni = generate_test_universe_with_default_params(UniType::Server);
@@ -289,6 +391,60 @@ mod universe_tests {
         assert_eq!(src_uni.to_pattern(None), dst_uni.to_pattern(None));
     }
 
+    #[test]
+    fn universe_apply_full_diff_leaves_trailing_dead_cells_known() {
+        let mut src_uni = generate_test_universe_with_default_params(UniType::Server);
+        let mut dst_uni = generate_test_universe_with_default_params(UniType::Server);
+        src_uni.toggle(16, 15, 1).unwrap();
+        src_uni.next();
+        let diff = src_uni.diff(0, 2, None).unwrap();
+        assert_eq!(dst_uni.apply(&diff, None), Ok(Some(2)));
+        assert_eq!(dst_uni.cell_state(17, 15), CellState::Dead);
+        assert_eq!(dst_uni.cell_state(60, 60), CellState::Dead);
+    }
+
+    #[test]
+    fn universe_apply_diff_in_region() {
+        let mut src_uni = generate_test_universe_with_default_params(UniType::Server);
+        let mut dst_uni = generate_test_universe_with_default_params(UniType::Server);
+        let player1 = 1;
+        // one glider inside the region and one outside of it
+        for &(col, row) in &[(16, 15), (17, 16), (15, 17), (16, 17), (17, 17)] {
+            src_uni.toggle(col, row, player1).unwrap();
+            src_uni.toggle(col + 40, row + 40, player1).unwrap();
+        }
+        src_uni.next();
+        let region = Region::new(10, 10, 20, 20);
+        let diff = src_uni.diff_in_region(0, 2, None, region).unwrap();
+        assert_eq!(dst_uni.apply(&diff, None), Ok(Some(2)));
+        assert_eq!(
+            src_uni.player_cell_counts(Some(region)),
+            dst_uni.player_cell_counts(Some(region))
+        );
+        assert_eq!(
+            dst_uni.player_cell_counts(None),
+            src_uni.player_cell_counts(Some(region))
+        );
+
+        for _ in 0..3 {
+            src_uni.next();
+        }
+        let diff = src_uni.diff_in_region(2, 5, None, region).unwrap();
+        assert_eq!(dst_uni.apply(&diff, None), Ok(Some(5)));
+        assert_eq!(
+            src_uni.player_cell_counts(Some(region)),
+            dst_uni.player_cell_counts(Some(region))
+        );
+        assert_eq!(
+            dst_uni.player_cell_counts(None),
+            src_uni.player_cell_counts(Some(region))
+        );
+
+        assert!(src_uni
+            .diff_in_region(2, 5, None, Region::new(-30, -30, 10, 10))
+            .is_none());
+    }
+
     #[test]
     fn universe_apply_but_already_applied() {
         let mut s_uni = generate_test_universe_with_default_params(UniType::Server); // server
@@ -456,6 +612,17 @@ mod universe_tests {
         let player1 = 1;
         assert!(!uni.writable(100, 70, player1).unwrap());
     }
+
+    #[test]
+    fn player_cell_counts_counts_each_player_separately() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let _ = uni.toggle(100, 70, 0);
+        let _ = uni.toggle(101, 70, 0);
+        let _ = uni.toggle(10, 10, 1);
+
+        assert_eq!(uni.player_cell_counts(None), vec![2, 1]);
+        assert_eq!(uni.player_cell_counts(Some(Region::new(0, 0, 80, 80))), vec![0, 1]);
+    }
 }
 
 mod genstate_tests {
@@ -1157,6 +1324,17 @@ mod rle_tests {
         );
     }
 
+    #[test]
+    fn whole_file_round_trips_through_to_string() {
+        let file = "#N Glider\n#O Richard K. Guy\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        let pat: PatternFile = PatternFile::from_str(file).unwrap();
+        assert_eq!(pat.to_string(), file);
+        assert_eq!(PatternFile::from_str(&pat.to_string()).unwrap(), pat);
+
+        let no_rule: PatternFile = PatternFile::from_str("x = 2, y = 2\n2o$2o!").unwrap();
+        assert_eq!(no_rule.to_string(), "x = 2, y = 2\n2o$2o!\n");
+    }
+
     #[test]
     fn parse_whole_file_works_with_crap_at_the_end() {
         let pat: PatternFile = PatternFile::from_str("#N Gosper glider gun\n#C This was the first gun discovered.\n#C As its name suggests, it was discovered by Bill Gosper.\nx = 36, y = 9, rule = B3/S23\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\nobo$10bo5bo7bo$11bo3bo$12b2o!blah\n\nyaddayadda\n").unwrap();
@@ -1271,3 +1449,201 @@ mod rle_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod bot_tests {
+    use crate::bot::*;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    // Two players side by side, each with a 64x64 square
+    fn generate_side_by_side_universe() -> Universe {
+        let player0 = PlayerBuilder::new(Region::new(0, 0, 64, 64));
+        let player1 = PlayerBuilder::new(Region::new(64, 0, 64, 64));
+        BigBang::new()
+            .width(128)
+            .height(64)
+            .server_mode(true)
+            .history(GEN_BUFSIZE)
+            .fog_radius(16)
+            .add_players(vec![player0, player1])
+            .birth()
+            .unwrap()
+    }
+
+    #[test]
+    fn bot_opens_with_glider_gun_inside_its_region() {
+        let uni = generate_side_by_side_universe();
+        let bot = Bot::new(0, 1);
+
+        let bot_move = bot.plan(&uni).unwrap();
+        assert_eq!(bot_move.kind, BotMoveKind::GliderGun);
+        let region = uni.writable_region(0);
+        assert!(bot_move
+            .cells
+            .iter()
+            .all(|&(col, row)| region.contains(col as isize, row as isize)));
+    }
+
+    #[test]
+    fn bot_defends_against_nearby_enemy_cells() {
+        let mut uni = generate_side_by_side_universe();
+        // Fog is cleared around newly born cells, so our own blinker near the border lets the bot
+        // see the enemy block
+        for &(col, row) in &[(58, 20), (59, 20), (60, 20)] {
+            uni.set(col, row, CellState::Alive(Some(0)), 0);
+        }
+        for &(col, row) in &[(66, 20), (67, 20), (66, 21), (67, 21)] {
+            uni.set(col, row, CellState::Alive(Some(1)), 1);
+        }
+        uni.next();
+
+        let bot_move = Bot::new(0, 1).plan(&uni).unwrap();
+        assert_eq!(bot_move.kind, BotMoveKind::StillLife);
+        // As close to the enemy as the bot's region allows
+        assert!(bot_move.cells.contains(&(63, 20)));
+    }
+
+    #[test]
+    fn bot_on_generation_draws_its_move() {
+        let mut uni = generate_side_by_side_universe();
+        let mut bot = Bot::new(1, 2);
+        // Generation 1 isn't a multiple of the turn interval, so nothing happens
+        assert!(bot.on_generation(&mut uni).is_none());
+        uni.next();
+
+        let bot_move = bot.on_generation(&mut uni).unwrap();
+        assert_eq!(uni.player_cell_counts(None)[1], bot_move.cells.len());
+    }
+
+    #[test]
+    fn bot_places_successive_gliders_apart() {
+        let mut uni = generate_side_by_side_universe();
+        let mut bot = Bot::new(0, 1);
+        let gun = bot.on_generation(&mut uni).unwrap();
+        let first = bot.on_generation(&mut uni).unwrap();
+        let second = bot.on_generation(&mut uni).unwrap();
+        assert_eq!(first.kind, BotMoveKind::Glider);
+        assert_eq!(second.kind, BotMoveKind::Glider);
+        assert!(first.cells.iter().all(|cell| !gun.cells.contains(cell) && !second.cells.contains(cell)));
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod script_tests {
+    use crate::error::ConwayError;
+    use crate::script::*;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    #[test]
+    fn script_places_pattern_in_writable_region_and_chats() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = r#"
+            fn on_start() {
+                chat("glhf");
+            }
+            fn on_generation() {
+                let region = writable_region();
+                place("2o$2o!", region.left + 5, region.top + 5);
+            }
+        "#;
+        let mut script = Script::new("blocks", source, 1).unwrap();
+
+        let chat = script.on_generation(&mut uni).unwrap();
+        assert_eq!(chat, vec!["glhf".to_owned()]);
+        assert_eq!(uni.player_cell_counts(None), vec![0, 4]);
+        assert_eq!(uni.get_cell_state(5, 5, Some(1)), CellState::Alive(Some(1)));
+
+        // on_start is only called once
+        uni.next();
+        assert!(script.on_generation(&mut uni).unwrap().is_empty());
+    }
+
+    #[test]
+    fn script_emits_custom_events() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = r#"
+            fn on_generation() {
+                emit("score_updated", `${generation()}`);
+            }
+        "#;
+        let mut script = Script::new("scorer", source, 1).unwrap();
+
+        script.on_generation(&mut uni).unwrap();
+        let generation = uni.latest_gen();
+        assert_eq!(
+            script.take_events(),
+            vec![("score_updated".to_owned(), generation.to_string())]
+        );
+        assert!(script.take_events().is_empty());
+    }
+
+    #[test]
+    fn script_cannot_write_outside_writable_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = r#"
+            fn on_generation() {
+                if set_cell(200, 100) {
+                    chat("oops");
+                }
+            }
+        "#;
+        let mut script = Script::new("cheater", source, 1).unwrap();
+
+        assert!(script.on_generation(&mut uni).unwrap().is_empty());
+        assert_eq!(uni.player_cell_counts(None), vec![0, 0]);
+    }
+
+    #[test]
+    fn script_cannot_place_pattern_larger_than_writable_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = r#"
+            fn on_generation() {
+                if !place("999999999o$999999999o!", 0, 0) {
+                    chat("too big");
+                }
+            }
+        "#;
+        let mut script = Script::new("greedy", source, 1).unwrap();
+
+        assert_eq!(script.on_generation(&mut uni).unwrap(), vec!["too big".to_owned()]);
+        assert_eq!(uni.player_cell_counts(None), vec![0, 0]);
+    }
+
+    #[test]
+    fn script_remembers_state_in_this() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = r#"
+            fn on_start() {
+                this.turns = 0;
+            }
+            fn on_generation() {
+                this.turns += 1;
+                chat(`turn ${this.turns}`);
+            }
+        "#;
+        let mut script = Script::new("counter", source, 1).unwrap();
+
+        script.on_generation(&mut uni).unwrap();
+        uni.next();
+        assert_eq!(script.on_generation(&mut uni).unwrap(), vec!["turn 2".to_owned()]);
+    }
+
+    #[test]
+    fn script_runaway_loop_is_stopped() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let source = "fn on_generation() { loop { } }";
+        let mut script = Script::new("spinner", source, 1).unwrap();
+
+        match script.on_generation(&mut uni) {
+            Err(ConwayError::ScriptError { .. }) => {}
+            other @ _ => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn script_with_syntax_error_is_rejected() {
+        assert!(Script::new("broken", "fn on_generation( {", 1).is_err());
+    }
+}
@@ -639,6 +639,46 @@ impl<'a, 'b> CharGrid for GenStatePair<'a, 'b> {
     }
 }
 
+/// Another internal `CharGrid` wrapper, so that `to_pattern` can describe only part of a grid.
+/// Everything outside `region` comes out as `NO_OP_CHAR`, so applying the pattern leaves it alone.
+struct ClippedGrid<'a, G: CharGrid> {
+    grid:   &'a G,
+    region: Region, // must be within the grid
+}
+
+impl<'a, G: CharGrid> CharGrid for ClippedGrid<'a, G> {
+    fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    fn write_at_position(&mut self, _col: usize, _row: usize, _ch: char, _visibility: Option<usize>) {
+        unimplemented!("This is a read-only struct!");
+    }
+
+    fn is_valid(ch: char) -> bool {
+        ch == NO_OP_CHAR || G::is_valid(ch)
+    }
+
+    fn get_run(&self, col: usize, row: usize, visibility: Option<usize>) -> (usize, char) {
+        let left = self.region.left() as usize;
+        let right = self.region.right() as usize;
+        if !self.region.contains(col as isize, row as isize) {
+            let run = if col < left && self.region.contains(left as isize, row as isize) {
+                left - col
+            } else {
+                self.width() - col
+            };
+            return (run, NO_OP_CHAR);
+        }
+        let (run, ch) = self.grid.get_run(col, row, visibility);
+        (cmp::min(run, right + 1 - col), ch)
+    }
+}
+
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let cells = &self.gen_states[self.state_index].cells;
@@ -710,6 +750,30 @@ impl Universe {
         }
     }
 
+    /// Gets the full state of the cell at (`col`, `row`) in the latest generation, including walls
+    /// and which player, if any, owns a live cell. Cells that aren't known are `CellState::Fog`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` or `col` are out of range.
+    pub fn cell_state(&self, col: usize, row: usize) -> CellState {
+        let gen_state = &self.gen_states[self.state_index];
+        let word_col = col / 64;
+        let shift = 63 - (col & (64 - 1));
+
+        if (gen_state.known[row][word_col] >> shift) & 1 == 0 {
+            CellState::Fog
+        } else if (gen_state.wall_cells[row][word_col] >> shift) & 1 == 1 {
+            CellState::Wall
+        } else if (gen_state.cells[row][word_col] >> shift) & 1 == 1 {
+            let owner = (0..self.num_players)
+                .find(|&player_id| (gen_state.player_states[player_id].cells[row][word_col] >> shift) & 1 == 1);
+            CellState::Alive(owner)
+        } else {
+            CellState::Dead
+        }
+    }
+
     /// Sets the state of a cell in the latest generation, with minimal checking.  It doesn't
     /// support setting `CellState::Fog`.
     ///
@@ -1513,6 +1577,139 @@ impl Universe {
         self.each_non_dead(self.region(), visibility, callback);
     }
 
+    /// Number of players in this universe.
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// The region of the universe the specified player may write to.
+    ///
+    /// Panics if `player_id` is out of range.
+    pub fn writable_region(&self, player_id: usize) -> Region {
+        self.player_writable[player_id]
+    }
+
+    /// Count the live cells owned by each player in the current generation, indexed by player_id.
+    /// If `opt_region` is `Some(region)`, only cells inside that region are counted.
+    pub fn player_cell_counts(&self, opt_region: Option<Region>) -> Vec<usize> {
+        let gen_state = &self.gen_states[self.state_index];
+        gen_state
+            .player_states
+            .iter()
+            .map(|player_state| {
+                let mut count = 0;
+                player_state.cells.each_set(|col, row| {
+                    if opt_region.map_or(true, |region| region.contains(col as isize, row as isize)) {
+                        count += 1;
+                    }
+                });
+                count
+            })
+            .collect()
+    }
+
+    /// A hash of the cells in the current generation, for checking that two universes meant to be
+    /// in lockstep still are. It is FNV-1a, so it doesn't change between platforms or Rust versions.
+    /// Which player owns a live cell is left out: a client that only knows the writable regions of
+    /// a team game can't tell how many players share each one, so its player numbers may differ.
+    pub fn state_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut hash = FNV_OFFSET_BASIS;
+        self.each_non_dead_full(None, &mut |col, row, state| {
+            let kind: u8 = match state {
+                CellState::Dead => 0,
+                CellState::Alive(_) => 1,
+                CellState::Wall => 2,
+                CellState::Fog => 3,
+            };
+            let mut bytes = [0u8; 17];
+            bytes[..8].copy_from_slice(&(col as u64).to_le_bytes());
+            bytes[8..16].copy_from_slice(&(row as u64).to_le_bytes());
+            bytes[16] = kind;
+            for &byte in bytes.iter() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        });
+        hash
+    }
+
+    /// A hash of everything that decides how the cells of `region` look to the player in
+    /// `visibility` (or to an observer if `None`) in the current generation, for telling cheaply
+    /// whether any of them changed. Whole words are hashed, so it may also change when cells up to
+    /// 63 columns to either side of the region change. Unlike `state_hash`, it isn't meant to be
+    /// compared between universes or versions.
+    pub fn region_fingerprint(&self, region: Region, visibility: Option<usize>) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        let region = match region.intersection(self.region()) {
+            Some(region) => region,
+            None => return FNV_OFFSET_BASIS,
+        };
+        let gen_state = &self.gen_states[self.state_index];
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut mix = |word: u64| {
+            hash ^= word;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        };
+        let (first_word, last_word) = (region.left() as usize / 64, region.right() as usize / 64);
+        for row in region.top() as usize..=region.bottom() as usize {
+            for col_idx in first_word..=last_word {
+                mix(gen_state.cells[row][col_idx]);
+                mix(gen_state.wall_cells[row][col_idx]);
+                mix(gen_state.known[row][col_idx]);
+                for player_state in gen_state.player_states.iter() {
+                    mix(player_state.cells[row][col_idx]);
+                }
+                if let Some(player_id) = visibility {
+                    mix(gen_state.player_states[player_id].fog[row][col_idx]);
+                }
+            }
+        }
+        hash
+    }
+
+    /// Counts the live cells in each `block` by `block` square of the universe in the current
+    /// generation, for showing it zoomed out too far to draw single cells. Returns the number of
+    /// columns and rows of squares, and the counts row by row. Cells hidden from the player in
+    /// `visibility` by fog aren't counted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` isn't a power of two from 1 to 64, so that squares don't straddle words.
+    pub fn live_counts(&self, block: usize, visibility: Option<usize>) -> (usize, usize, Vec<u32>) {
+        assert!(block.is_power_of_two() && block <= 64);
+        let gen_state = &self.gen_states[self.state_index];
+        let (columns, rows) = ((self.width + block - 1) / block, (self.height + block - 1) / block);
+        let mut counts = vec![0; columns * rows];
+        let blocks_per_word = 64 / block;
+        let mask = if block == 64 { u64::MAX } else { (1 << block) - 1 };
+        for row in 0..self.height {
+            let counts_row = &mut counts[row / block * columns..(row / block + 1) * columns];
+            for col_idx in 0..self.width_in_words {
+                let mut word = gen_state.cells[row][col_idx];
+                if let Some(player_id) = visibility {
+                    word &= !gen_state.player_states[player_id].fog[row][col_idx];
+                }
+                if word == 0 {
+                    continue;
+                }
+                for i in 0..blocks_per_word {
+                    // the most significant bit is the leftmost cell
+                    let bits = (word >> (64 - block * (i + 1))) & mask;
+                    let column = col_idx * blocks_per_word + i;
+                    if bits != 0 && column < columns {
+                        counts_row[column] += bits.count_ones();
+                    }
+                }
+            }
+        }
+        (columns, rows, counts)
+    }
+
     /// Get a Region of the same size as the universe.
     pub fn region(&self) -> Region {
         Region::new(0, 0, self.width, self.height)
@@ -1542,6 +1739,19 @@ impl Universe {
         latest_gen.copy_from_bit_grid(src, region, opt_player_id);
     }
 
+    /// Clears the fog over `region` for every player in the latest generation, so that all of them
+    /// can see into it. Any part of `region` outside the universe is ignored.
+    pub fn lift_fog(&mut self, region: Region) {
+        let region = match region.intersection(self.region()) {
+            Some(region) => region,
+            None => return,
+        };
+        let latest_gen = &mut self.gen_states[self.state_index];
+        for player_state in latest_gen.player_states.iter_mut() {
+            player_state.fog.modify_region(region, BitOperation::Clear);
+        }
+    }
+
     /// Utility function to mutably borrow two separate GenStates from self.gen_states, specified
     /// by `idx0` and `idx1`.
     ///
@@ -1670,7 +1880,12 @@ impl Universe {
             gen1.clear();
             gen0.copy(gen1); // this is an |= operation, hence the clear before this
         } else {
-            self.gen_states[gen1_idx].clear();
+            // A full diff leaves out the dead cells at the end of each row, so start with
+            // everything known; cells hidden by fog are spelled out with '?'.
+            let gen_state = &mut self.gen_states[gen1_idx];
+            gen_state.clear();
+            let region = Region::new(0, 0, gen_state.width(), gen_state.height());
+            gen_state.known.modify_region(region, BitOperation::Set);
         }
 
         // 5) update self.generation, self.state_index, and self.gen_states[gen1_idx].gen_or_none
@@ -1696,6 +1911,34 @@ impl Universe {
     /// * Panics if `gen0` >= `gen1`.
     /// * Panics if `visibility` is out of range.
     pub fn diff(&self, gen0: usize, gen1: usize, visibility: Option<usize>) -> Option<GenStateDiff> {
+        self.diff_clipped(gen0, gen1, visibility, None)
+    }
+
+    /// Like `diff`, but only describes the cells in `region`. Applying the diff leaves cells
+    /// outside of it unchanged, or dead if `gen0` is zero. Returns `None` if `region` is entirely
+    /// outside the universe.
+    ///
+    /// # Panics
+    ///
+    /// Same as `diff`.
+    pub fn diff_in_region(
+        &self,
+        gen0: usize,
+        gen1: usize,
+        visibility: Option<usize>,
+        region: Region,
+    ) -> Option<GenStateDiff> {
+        let clipped = self.region().intersection(region)?;
+        self.diff_clipped(gen0, gen1, visibility, Some(clipped))
+    }
+
+    fn diff_clipped(
+        &self,
+        gen0: usize,
+        gen1: usize,
+        visibility: Option<usize>,
+        opt_region: Option<Region>,
+    ) -> Option<GenStateDiff> {
         assert!(gen0 < gen1, format!("expected gen0 < gen1, but {} >= {}", gen0, gen1));
         let mut opt_genstate0 = None;
         let mut opt_genstate1 = None;
@@ -1710,13 +1953,29 @@ impl Universe {
             }
         }
         if gen0 == 0 && opt_genstate1.is_some() {
-            let pattern = opt_genstate1.unwrap().to_pattern(visibility);
+            let gen_state1 = opt_genstate1.unwrap();
+            let pattern = match opt_region {
+                Some(region) => ClippedGrid {
+                    grid: gen_state1,
+                    region,
+                }
+                .to_pattern(visibility),
+                None => gen_state1.to_pattern(visibility),
+            };
             Some(GenStateDiff { gen0, gen1, pattern })
         } else {
             if opt_genstate0.is_none() || opt_genstate1.is_none() {
                 None
             } else {
-                Some(opt_genstate0.unwrap().diff(opt_genstate1.unwrap(), visibility))
+                let (gen_state0, gen_state1) = (opt_genstate0.unwrap(), opt_genstate1.unwrap());
+                match opt_region {
+                    Some(region) => {
+                        let pair = GenStatePair { gen_state0, gen_state1 };
+                        let pattern = ClippedGrid { grid: &pair, region }.to_pattern(visibility);
+                        Some(GenStateDiff { gen0, gen1, pattern })
+                    }
+                    None => Some(gen_state0.diff(gen_state1, visibility)),
+                }
             }
         }
     }
@@ -26,6 +26,7 @@ extern crate futures;
 extern crate netwayste;
 extern crate tokio;
 
+use std::env;
 use std::io::{self, Read, Write};
 use std::str::FromStr;
 use std::thread;
@@ -104,6 +105,10 @@ fn print_help() {
     info!("/join <room_name>      - join a room (when not in game)");
     info!("/leave                 - leave a room (when in game)");
     info!("/part                  - alias of leave");
+    info!("/lobby <message>       - chat with everyone on the server, even when in game");
+    info!("/history               - show the room's recent chat messages you haven't seen");
+    info!("/typing                - tell the room you are typing a message");
+    info!("/report <name> [why]   - report a player to the server's moderators");
     info!("/quit                  - exit the program");
     info!("...or just type text to chat!");
 }
@@ -161,6 +166,34 @@ fn build_command_request_action(cmd: String, args: Vec<String>) -> NetwaysteEven
                 debug!("Command failed: Expected no arguments to leave");
             }
         }
+        "lobby" => {
+            if args.len() > 0 {
+                new_event = NetwaysteEvent::LobbyChatMessage(args.join(" "));
+            } else {
+                debug!("Command failed: Expected a message for the lobby");
+            }
+        }
+        "history" => {
+            if args.len() == 0 {
+                new_event = NetwaysteEvent::GetChatHistory;
+            } else {
+                debug!("Command failed: Expected no arguments to history");
+            }
+        }
+        "typing" => {
+            if args.len() == 0 {
+                new_event = NetwaysteEvent::Typing;
+            } else {
+                debug!("Command failed: Expected no arguments to typing");
+            }
+        }
+        "report" => {
+            if let Some((player_name, reason)) = args.split_first() {
+                new_event = NetwaysteEvent::ReportPlayer(player_name.clone(), reason.join(" "));
+            } else {
+                debug!("Command failed: Expected the name of the player to report");
+            }
+        }
         "quit" | "q" | "exit" => {
             trace!("Peace out!");
             new_event = NetwaysteEvent::Disconnect;
@@ -205,8 +238,9 @@ async fn main() {
     let (ggez_client_request, nw_client_request) = mpsc::unbounded::<NetwaysteEvent>();
     let (nw_server_response, mut ggez_server_response) = mpsc::channel::<NetwaysteEvent>(5);
 
+    let server = env::args().nth(1).unwrap_or("localhost".to_owned());
     tokio::spawn(async {
-        match ClientNetState::start_network(nw_server_response, nw_client_request).await {
+        match ClientNetState::start_network(server, nw_server_response, nw_client_request).await {
             Ok(()) => {}
             Err(e) => error!("Error during ClientNetState: {}", e),
         }
@@ -0,0 +1,62 @@
+/*
+ * A networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Replays a conversation between a client and a server through `ClientNetState`, which filters
+//! what the server sends by sequence number and passes the rest on to conwayste.
+//!
+//! The input is a series of encoded packets. Requests are sent by the client as though the player
+//! asked for them; the server's packets are handled as though they had just arrived. The client's
+//! other packets are skipped, since it only ever sends them in reply.
+
+#![no_main]
+
+use std::net::SocketAddr;
+
+use bytes::BytesMut;
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+use netwayste::client::ClientNetState;
+use netwayste::net::{NetwaystePacketCodec, Packet, RequestAction, DEFAULT_PORT};
+use tokio_util::codec::Decoder;
+
+fuzz_target!(|data: &[u8]| {
+    let server_address = SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT));
+    // Nothing reads what would go to conwayste, so the receiver is dropped and sends fail at once.
+    let (channel_to_conwayste, _) = mpsc::channel(0);
+    let mut client_state = ClientNetState::new(channel_to_conwayste);
+    client_state.server_address = Some(server_address);
+    client_state.name = Some("fuzzer".to_owned()); // the player has a name before anything is sent
+
+    let mut buf = BytesMut::from(data);
+    while let Ok(Some(packet)) = NetwaystePacketCodec.decode(&mut buf) {
+        match packet {
+            Packet::Request { action, .. } => {
+                if let RequestAction::Connect { ref name, .. } = action {
+                    client_state.name = Some(name.to_owned());
+                }
+                client_state.action_to_packet(action);
+            }
+            Packet::UpdateReply { .. } | Packet::GetStatus { .. } | Packet::MtuProbeReply { .. } => {}
+            packet => {
+                block_on(client_state.handle_incoming_event(packet, server_address));
+            }
+        }
+    }
+});
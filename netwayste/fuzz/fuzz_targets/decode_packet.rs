@@ -0,0 +1,44 @@
+/*
+ * A networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Feeds a datagram to the packet decoder, the way `UdpFramed` does when one arrives.
+
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use netwayste::net::NetwaystePacketCodec;
+use tokio_util::codec::{Decoder, Encoder};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    loop {
+        let before = buf.len();
+        let packet = match NetwaystePacketCodec.decode(&mut buf) {
+            Ok(Some(packet)) => packet,
+            Ok(None) | Err(_) => break,
+        };
+
+        // The decoder skips ahead by the packet's encoded size, so if these bytes were not exactly
+        // what the packet encodes to, the next packet would be read from the wrong place.
+        let mut encoded = BytesMut::new();
+        NetwaystePacketCodec.encode(packet, &mut encoded).unwrap();
+        assert_eq!(&encoded[..], &data[data.len() - before..data.len() - buf.len()]);
+    }
+});
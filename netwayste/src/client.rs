@@ -17,7 +17,6 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::env;
 use std::error::Error;
 use std::net::SocketAddr;
 use std::process::exit;
@@ -33,9 +32,11 @@ use Fut::prelude::*;
 use Fut::select;
 
 use crate::net::{
-    bind, has_connection_timed_out, BroadcastChatMessage, NetwaysteEvent, NetwaystePacketCodec, NetworkManager,
-    NetworkQueue, Packet, RequestAction, ResponseCode, RoomList, DEFAULT_PORT, VERSION,
+    bind, has_connection_timed_out, BroadcastChatMessage, ChatChannel, ChatHistoryEntry, GameUpdate, GenPartInfo,
+    GenStateDiffPart, NetwaysteEvent, NetwaystePacketCodec, NetworkManager, NetworkQueue, Packet, RequestAction,
+    ResponseCode, RoomList, Transport, UniUpdate, DEFAULT_PORT, MAX_DIFF_PARTS, VERSION,
 };
+use crate::websocket::{WsTransport, DEFAULT_WEBSOCKET_PORT};
 
 use crate::utils::{LatencyFilter, PingPong};
 
@@ -44,21 +45,50 @@ const NETWORK_INTERVAL_IN_MS: u64 = 1000;
 
 pub const CLIENT_VERSION: &str = "0.0.1";
 
+/// The parts received so far of a universe diff that the server split up.
+struct DiffParts {
+    gen0:  u32,
+    gen1:  u32,
+    parts: Vec<Option<String>>, // pattern of each part, indexed by part number
+}
+
+impl DiffParts {
+    fn received(&self) -> usize {
+        self.parts.iter().filter(|part| part.is_some()).count()
+    }
+
+    fn have_bitmask(&self) -> u32 {
+        self.parts
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| part.is_some())
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+}
+
 pub struct ClientNetState {
     pub sequence:             u64, // Sequence number of requests
     pub response_sequence:    u64, // Value of the next expected sequence number from the server,
     // and indicates the sequence number of the next process-able rx packet
     pub name:                 Option<String>,
     pub room:                 Option<String>,
+    pub team:                 Option<u8>,
     pub cookie:               Option<String>,
     pub chat_msg_seq_num:     u64,
+    pub lobby_chat_seq:       u64, // like chat_msg_seq_num, but for the lobby chat channel
+    pub first_chat_seq:       Option<u64>, // earliest room chat seen since joining; with chat_msg_seq_num, bounds those seen
+    pub game_update_seq:      Option<u64>, // sequence number of the latest game update received
+    pub full_gen:             Option<u64>, // generation of the latest universe diff passed on to conwayste
+    pub universe_epoch:       u32, // server's epoch of the diffs full_gen and partial_diff come from
     pub tick:                 usize,
     pub network:              NetworkManager,
     pub last_received:        Option<Instant>,
     pub disconnect_initiated: bool,
     pub server_address:       Option<SocketAddr>,
     pub channel_to_conwayste: Fut::channel::mpsc::Sender<NetwaysteEvent>,
+    pub corrupt_updates:      u64, // universe updates dropped because their checksum was wrong
     latency_filter:           LatencyFilter,
+    partial_diff:             Option<DiffParts>, // universe diff we have only some of the parts of
 }
 
 impl ClientNetState {
@@ -68,15 +98,23 @@ impl ClientNetState {
             response_sequence:    0,
             name:                 None,
             room:                 None,
+            team:                 None,
             cookie:               None,
             chat_msg_seq_num:     0,
+            lobby_chat_seq:       0,
+            first_chat_seq:       None,
+            game_update_seq:      None,
+            full_gen:             None,
+            universe_epoch:       0,
             tick:                 0,
             network:              NetworkManager::new().with_message_buffering(),
             last_received:        None,
             disconnect_initiated: false,
             server_address:       None,
             channel_to_conwayste: channel_to_conwayste,
+            corrupt_updates:      0,
             latency_filter:       LatencyFilter::new(),
+            partial_diff:         None,
         }
     }
 
@@ -90,25 +128,41 @@ impl ClientNetState {
             ref mut response_sequence,
             name: ref _name,
             ref mut room,
+            ref mut team,
             ref mut cookie,
             ref mut chat_msg_seq_num,
+            ref mut lobby_chat_seq,
+            ref mut first_chat_seq,
+            ref mut game_update_seq,
+            ref mut full_gen,
+            ref mut universe_epoch,
             ref mut tick,
             ref mut network,
             ref mut last_received,
             ref mut disconnect_initiated,
             ref mut server_address,
             channel_to_conwayste: ref _channel_to_conwayste, // Don't clear the channel to conwayste
+            ref mut corrupt_updates,
             ref mut latency_filter,
+            ref mut partial_diff,
         } = *self;
         *sequence = 0;
         *response_sequence = 0;
         *room = None;
+        *team = None;
         *cookie = None;
         *chat_msg_seq_num = 0;
+        *lobby_chat_seq = 0;
+        *first_chat_seq = None;
+        *game_update_seq = None;
+        *full_gen = None;
+        *universe_epoch = 0;
         *tick = 0;
         *last_received = None;
         *disconnect_initiated = false;
         *server_address = None;
+        *corrupt_updates = 0;
+        *partial_diff = None;
         network.reset();
         latency_filter.reset();
 
@@ -169,6 +223,7 @@ impl ClientNetState {
             ResponseCode::LoggedIn {
                 ref cookie,
                 ref server_version,
+                motd: _,
             } => {
                 self.handle_logged_in(cookie.to_string(), server_version.to_string());
             }
@@ -178,12 +233,28 @@ impl ClientNetState {
             ResponseCode::JoinedRoom { ref room_name } => {
                 self.handle_joined_room(room_name);
             }
+            ResponseCode::JoinedTeam { team } => {
+                self.handle_joined_team(team);
+            }
             ResponseCode::PlayerList { ref players } => {
                 self.handle_player_list(players.to_vec());
             }
             ResponseCode::RoomList { ref rooms } => {
                 self.handle_room_list(rooms.to_vec());
             }
+            ResponseCode::CellsSet { ref cells, generation } => {
+                debug!("{} cells will be set in generation {}", cells.len(), generation);
+            }
+            ResponseCode::ChatHistory { messages } => {
+                // Only the messages not already passed along go to conwayste
+                self.handle_chat_history(messages).await;
+                return;
+            }
+            ResponseCode::Leaderboard { .. }
+            | ResponseCode::PatternShared { .. }
+            | ResponseCode::SharedPatternList { .. }
+            | ResponseCode::SharedPattern { .. }
+            | ResponseCode::RoomMap { .. } => {} // passed along below
             ResponseCode::KeepAlive => {}
             // errors
             ResponseCode::Unauthorized { error_msg: opt_error } => {
@@ -233,31 +304,90 @@ impl ClientNetState {
                 }
                 return vec![];
             }
-            // TODO game_updates, game_update_seq, universe_update,
+            // TODO universe_update
             Packet::Update {
                 chats,
-                game_updates: _,
-                game_update_seq: _,
-                universe_update: _,
+                game_updates,
+                game_update_seq,
+                universe_update,
+                universe_hash,
                 ping,
             } => {
+                let cookie = match self.cookie.clone() {
+                    Some(cookie) => cookie,
+                    None => {
+                        warn!("Ignoring update from {:?} since we are not logged in", addr);
+                        return vec![];
+                    }
+                };
+
+                // Drop the whole packet without replying, so that the server sends it all again
+                if let UniUpdate::Diff { ref diff, .. } = universe_update {
+                    if !diff.checksum_matches() {
+                        self.corrupt_updates += 1;
+                        warn!(
+                            "Dropping update from {:?} with a bad universe checksum ({} so far)",
+                            addr, self.corrupt_updates
+                        );
+                        self.channel_to_conwayste
+                            .send(NetwaysteEvent::CorruptUpdates(self.corrupt_updates))
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e);
+                            });
+                        return vec![];
+                    }
+                }
+
                 if chats.len() != 0 {
                     self.handle_incoming_chats(chats).await;
                 }
 
+                if let Some(first_seq) = game_update_seq {
+                    self.handle_incoming_game_updates(first_seq, game_updates).await;
+                }
+
+                if let UniUpdate::Diff { diff, epoch } = universe_update {
+                    self.handle_incoming_universe_diff(diff, epoch).await;
+                }
+
+                if let Some(universe_hash) = universe_hash {
+                    self.channel_to_conwayste
+                        .send(NetwaysteEvent::UniverseHash(universe_hash))
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e);
+                        });
+                }
+
                 // Reply to the update
                 let update_reply_packet = Packet::UpdateReply {
-                    cookie:               self.cookie.clone().unwrap(),
+                    cookie:               cookie,
                     last_chat_seq:        Some(self.chat_msg_seq_num),
-                    last_game_update_seq: None,
-                    last_full_gen:        None,
-                    partial_gen:          None,
+                    last_lobby_chat_seq:  Some(self.lobby_chat_seq),
+                    last_game_update_seq: self.game_update_seq,
+                    last_full_gen:        self.full_gen,
+                    partial_gen:          self.partial_gen(),
+                    universe_epoch:       self.universe_epoch,
                     pong:                 PingPong::pong(ping.nonce),
                 };
 
                 return vec![(update_reply_packet, addr)];
             }
-            Packet::Request { .. } | Packet::UpdateReply { .. } | Packet::GetStatus { .. } => {
+            Packet::MtuProbe { size, padding: _ } => {
+                let cookie = match self.cookie.clone() {
+                    Some(cookie) => cookie,
+                    None => {
+                        warn!("Ignoring MTU probe from {:?} since we are not logged in", addr);
+                        return vec![];
+                    }
+                };
+                return vec![(Packet::MtuProbeReply { cookie, size }, addr)];
+            }
+            Packet::Request { .. }
+            | Packet::UpdateReply { .. }
+            | Packet::GetStatus { .. }
+            | Packet::MtuProbeReply { .. } => {
                 warn!("Ignoring packet from server normally sent by clients: {:?}", packet);
                 return vec![];
             }
@@ -301,6 +431,9 @@ impl ClientNetState {
             if timed_out || self.disconnect_initiated {
                 if timed_out {
                     info!("Server is non-responsive, disconnecting.");
+                    if let Err(e) = self.channel_to_conwayste.try_send(NetwaysteEvent::ConnectionLost) {
+                        error!("Could not tell conwayste the connection was lost: {:?}", e);
+                    }
                 }
                 if self.disconnect_initiated {
                     info!("Disconnected from the server.")
@@ -325,6 +458,29 @@ impl ClientNetState {
         None
     }
 
+    /// Tells conwayste how many packets and chat messages are queued, for its debug overlay. Skipped
+    /// if conwayste has fallen behind on reading what it was sent.
+    fn report_queue_lengths(&mut self) {
+        if self.cookie.is_none() {
+            return;
+        }
+        let chat_messages = self
+            .network
+            .rx_chat_messages
+            .as_ref()
+            .map_or(0, |chat_queue| chat_queue.queue.len());
+        let nw_event = NetwaysteEvent::QueueLengths(
+            self.network.tx_packets.queue.len(),
+            self.network.rx_packets.queue.len(),
+            chat_messages,
+        );
+        if let Err(e) = self.channel_to_conwayste.try_send(nw_event) {
+            if !e.is_full() {
+                error!("Could not send queue lengths via channel_to_conwayste: {:?}", e);
+            }
+        }
+    }
+
     pub fn handle_response_ok(&mut self) -> Result<(), Box<dyn Error>> {
         info!("OK :)");
         return Ok(());
@@ -346,12 +502,20 @@ impl ClientNetState {
         info!("Joined room: {}", room_name);
     }
 
+    pub fn handle_joined_team(&mut self, team: u8) {
+        self.team = Some(team);
+        info!("Joined team: {}", team);
+    }
+
     pub fn handle_left_room(&mut self) {
         if self.in_game() {
             info!("Left room {}.", self.room.clone().unwrap());
         }
         self.room = None;
+        self.team = None;
         self.chat_msg_seq_num = 0;
+        self.first_chat_seq = None;
+        self.game_update_seq = None;
     }
 
     pub fn handle_player_list(&mut self, player_names: Vec<String>) {
@@ -373,8 +537,24 @@ impl ClientNetState {
         info!("---END GAME ROOM LIST---");
     }
 
-    pub async fn handle_incoming_chats(&mut self, mut chat_messages: Vec<BroadcastChatMessage>) {
-        chat_messages.retain(|ref chat_message| self.chat_msg_seq_num < chat_message.chat_seq.unwrap());
+    pub async fn handle_incoming_chats(&mut self, chat_messages: Vec<BroadcastChatMessage>) {
+        // The lobby's chats are numbered apart from the room's, and conwayste shows them apart too
+        let (lobby_chats, mut chat_messages): (Vec<_>, Vec<_>) = chat_messages
+            .into_iter()
+            .partition(|chat_message| chat_message.channel == ChatChannel::Lobby);
+        if !lobby_chats.is_empty() {
+            self.handle_incoming_lobby_chats(lobby_chats).await;
+            if chat_messages.is_empty() {
+                return;
+            }
+        }
+
+        // The server numbers every chat it broadcasts, so any without a number are bogus.
+        chat_messages.retain(|ref chat_message| {
+            chat_message
+                .chat_seq
+                .map_or(false, |chat_seq| self.chat_msg_seq_num < chat_seq)
+        });
 
         let mut to_conwayste_msgs = vec![];
 
@@ -385,6 +565,7 @@ impl ClientNetState {
         for chat_message in chat_messages {
             let chat_seq = chat_message.chat_seq.unwrap();
             self.chat_msg_seq_num = std::cmp::max(chat_seq, self.chat_msg_seq_num);
+            self.first_chat_seq = Some(self.first_chat_seq.map_or(chat_seq, |first| first.min(chat_seq)));
 
             let queue = self.network.rx_chat_messages.as_mut().unwrap();
             queue.buffer_item(chat_message.clone());
@@ -392,7 +573,7 @@ impl ClientNetState {
             if let Some(client_name) = self.name.as_ref() {
                 if client_name != &chat_message.player_name {
                     info!("{}: {}", chat_message.player_name, chat_message.message);
-                    to_conwayste_msgs.push((chat_message.player_name, chat_message.message));
+                    to_conwayste_msgs.push((chat_message.player_name, chat_message.message, chat_message.channel));
                 }
             } else {
                 panic!("Client name not set!");
@@ -406,8 +587,238 @@ impl ClientNetState {
         }
     }
 
+    /// Passes along the room's chat history, less any messages already received as chats. Those
+    /// are the ones numbered from `first_chat_seq` through `chat_msg_seq_num`.
+    pub async fn handle_chat_history(&mut self, messages: Vec<ChatHistoryEntry>) {
+        let seen = self.first_chat_seq.map(|first| first..=self.chat_msg_seq_num);
+        let mut to_conwayste_msgs = vec![];
+        for entry in messages {
+            let chat_seq = entry.chat_seq;
+            if seen.as_ref().map_or(false, |seen| seen.contains(&chat_seq)) {
+                continue;
+            }
+            self.chat_msg_seq_num = std::cmp::max(chat_seq, self.chat_msg_seq_num);
+            self.first_chat_seq = Some(self.first_chat_seq.map_or(chat_seq, |first| first.min(chat_seq)));
+            info!("[{}s ago] {}: {}", entry.age_secs, entry.player_name, entry.message);
+            to_conwayste_msgs.push((entry.player_name, entry.message, entry.age_secs, entry.channel));
+        }
+        if to_conwayste_msgs.is_empty() {
+            return;
+        }
+
+        let nw_response = NetwaysteEvent::ChatHistory(to_conwayste_msgs);
+        match self.channel_to_conwayste.send(nw_response).await {
+            Ok(_) => (),
+            Err(e) => error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e),
+        }
+    }
+
+    async fn handle_incoming_lobby_chats(&mut self, lobby_chats: Vec<BroadcastChatMessage>) {
+        let mut to_conwayste_msgs = vec![];
+        for chat_message in lobby_chats {
+            match chat_message.chat_seq {
+                Some(chat_seq) if self.lobby_chat_seq < chat_seq => self.lobby_chat_seq = chat_seq,
+                _ => continue,
+            }
+            if self.name.as_ref() != Some(&chat_message.player_name) {
+                info!("[lobby] {}: {}", chat_message.player_name, chat_message.message);
+                to_conwayste_msgs.push((chat_message.player_name, chat_message.message));
+            }
+        }
+        if to_conwayste_msgs.is_empty() {
+            return;
+        }
+
+        let nw_response = NetwaysteEvent::LobbyChatMessages(to_conwayste_msgs);
+        match self.channel_to_conwayste.send(nw_response).await {
+            Ok(_) => (),
+            Err(e) => error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e),
+        }
+    }
+
+    /// Passes along game updates we haven't seen yet. `first_seq` is the sequence number of the
+    /// first update in `game_updates`; the rest follow consecutively.
+    pub async fn handle_incoming_game_updates(&mut self, first_seq: u64, game_updates: Vec<GameUpdate>) {
+        for (seq, game_update) in (first_seq..).zip(game_updates) {
+            if self.game_update_seq.map_or(false, |latest_seq| seq <= latest_seq) {
+                continue;
+            }
+            self.game_update_seq = Some(seq);
+
+            let nw_response = match game_update {
+                GameUpdate::GameStart { options, players } => {
+                    info!("Game started");
+                    self.full_gen = None; // generations start over
+                    self.partial_diff = None;
+                    let writable = players
+                        .iter()
+                        .find(|player| self.name.as_deref() == Some(player.name()))
+                        .and_then(|player| {
+                            let index = player.index()? as usize;
+                            options.writable_region(index, player.team()).cloned()
+                        });
+                    NetwaysteEvent::GameStarted(options, writable)
+                }
+                GameUpdate::CellInputs { generation, inputs } => NetwaysteEvent::CellInputs(generation, inputs),
+                GameUpdate::ScoreUpdate { generation, scores } => {
+                    debug!("Scores at generation {}: {:?}", generation, scores);
+                    NetwaysteEvent::Scores(scores)
+                }
+                GameUpdate::PlayerList { players } => NetwaysteEvent::Roster(players),
+                GameUpdate::RoomSettings { settings } => NetwaysteEvent::RoomSettings(settings),
+                GameUpdate::PlayerColor { name, color } => {
+                    debug!("Player {} has color {:?}", name, color);
+                    NetwaysteEvent::PlayerColor(name, color)
+                }
+                GameUpdate::PlayerTyping { name } => {
+                    if self.name.as_ref() == Some(&name) {
+                        continue;
+                    }
+                    NetwaysteEvent::PlayerTyping(name)
+                }
+                GameUpdate::RoomMapEdit { edit } => {
+                    debug!("Room map edited: {:?}", edit);
+                    NetwaysteEvent::RoomMapEdited(edit)
+                }
+                GameUpdate::AnnotationShare { name, id, annotation } => {
+                    if self.name.as_ref() == Some(&name) {
+                        continue; // already shown, since we drew it
+                    }
+                    NetwaysteEvent::AnnotationShared(name, id, annotation)
+                }
+                GameUpdate::GameFinish { outcome } => {
+                    info!("Game over! Winner: {:?}", outcome.winner);
+                    NetwaysteEvent::GameFinished(outcome)
+                }
+                GameUpdate::Intermission {
+                    round,
+                    rounds,
+                    standings,
+                } => {
+                    info!("Round {} of {} finished.", round, rounds);
+                    NetwaysteEvent::Intermission(round, rounds, standings)
+                }
+                GameUpdate::SeriesFinish { outcome } => {
+                    info!("Series over! Winner: {:?}", outcome.winner);
+                    NetwaysteEvent::SeriesFinished(outcome)
+                }
+                // TODO: add support for the rest
+                _ => {
+                    debug!("Ignoring game update: {:?}", game_update);
+                    continue;
+                }
+            };
+
+            match self.channel_to_conwayste.send(nw_response).await {
+                Ok(_) => (),
+                Err(e) => error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e),
+            }
+        }
+    }
+
+    /// Passes along a universe diff if it follows on from the last one. The server bases each diff
+    /// on the generation we last told it we have, or on nothing, so anything else arrived out of
+    /// order. A diff in several parts is held onto until all of them are here, and conwayste is
+    /// told how many have come so far. A diff from another epoch means the server has started us
+    /// over, such as for a new viewport, so only the whole universe will do.
+    pub async fn handle_incoming_universe_diff(&mut self, diff: GenStateDiffPart, epoch: u32) {
+        if epoch != self.universe_epoch {
+            if diff.gen0 != 0 {
+                debug!(
+                    "Ignoring universe diff from generation {} in epoch {}; we are in epoch {}",
+                    diff.gen0, epoch, self.universe_epoch
+                );
+                return;
+            }
+            self.universe_epoch = epoch;
+            self.full_gen = None;
+            self.partial_diff = None;
+        }
+        let gen1 = diff.gen1 as u64;
+        if self.full_gen.map_or(false, |full_gen| gen1 <= full_gen)
+            || (diff.gen0 != 0 && Some(diff.gen0 as u64) != self.full_gen)
+        {
+            debug!(
+                "Ignoring universe diff from generation {} to {}; we have {:?}",
+                diff.gen0, diff.gen1, self.full_gen
+            );
+            return;
+        }
+        if diff.total_parts == 0 || diff.total_parts as usize > MAX_DIFF_PARTS || diff.part_number >= diff.total_parts {
+            warn!(
+                "Ignoring universe diff part {} of {}",
+                diff.part_number, diff.total_parts
+            );
+            return;
+        }
+
+        let diff = if diff.total_parts == 1 {
+            diff
+        } else {
+            match self.add_universe_diff_part(diff) {
+                Some(whole_diff) => whole_diff,
+                None => {
+                    // unwrap OK because add_universe_diff_part keeps the parts until they are all here
+                    let partial_diff = self.partial_diff.as_ref().unwrap();
+                    let progress =
+                        NetwaysteEvent::UniverseProgress(partial_diff.received() as u8, partial_diff.parts.len() as u8);
+                    if let Err(e) = self.channel_to_conwayste.send(progress).await {
+                        error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e);
+                    }
+                    return;
+                }
+            }
+        };
+        self.partial_diff = None;
+        self.full_gen = Some(gen1);
+
+        match self
+            .channel_to_conwayste
+            .send(NetwaysteEvent::UniverseUpdate(diff))
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e),
+        }
+    }
+
+    /// Adds a part of a universe diff to the ones we have. Parts of some other diff we were
+    /// collecting are thrown away, since the server has moved on from it. Returns the whole diff as
+    /// one part once every part is here.
+    fn add_universe_diff_part(&mut self, diff: GenStateDiffPart) -> Option<GenStateDiffPart> {
+        let same_diff = self.partial_diff.as_ref().map_or(false, |partial_diff| {
+            partial_diff.gen0 == diff.gen0
+                && partial_diff.gen1 == diff.gen1
+                && partial_diff.parts.len() == diff.total_parts as usize
+        });
+        if !same_diff {
+            self.partial_diff = Some(DiffParts {
+                gen0:  diff.gen0,
+                gen1:  diff.gen1,
+                parts: vec![None; diff.total_parts as usize],
+            });
+        }
+        // unwrap OK because of the check above
+        let partial_diff = self.partial_diff.as_mut().unwrap();
+        partial_diff.parts[diff.part_number as usize] = Some(diff.pattern_part);
+        if partial_diff.received() < partial_diff.parts.len() {
+            return None;
+        }
+        let pattern: String = partial_diff.parts.iter().flatten().map(String::as_str).collect();
+        Some(GenStateDiffPart::new(0, 1, diff.gen0, diff.gen1, pattern))
+    }
+
+    /// Which parts we have of a universe diff that hasn't fully arrived, for telling the server.
+    pub fn partial_gen(&self) -> Option<GenPartInfo> {
+        self.partial_diff.as_ref().map(|partial_diff| GenPartInfo {
+            gen0:         partial_diff.gen0,
+            gen1:         partial_diff.gen1,
+            have_bitmask: partial_diff.have_bitmask(),
+        })
+    }
+
     /// Prepare a request action to the connected server
-    fn action_to_packet(&mut self, action: RequestAction) -> Packet {
+    pub fn action_to_packet(&mut self, action: RequestAction) -> Packet {
         // Sequence number can increment once we're talking to a server
         if self.cookie != None {
             self.sequence += 1;
@@ -438,21 +849,33 @@ impl ClientNetState {
     }
 
     /// Main executor for the client-side network layer for conwayste and should be run from a thread.
-    /// Its two arguments are halves of a channel used for communication to send and receive Netwayste events.
+    /// It talks to `server`, given as `host[:port]`, and the other two arguments are halves of a channel
+    /// used for communication to send and receive Netwayste events.
+    /// A server given as `ws://host[:port]` is reached through its WebSocket bridge instead of UDP.
     pub async fn start_network(
+        server: String,
         channel_to_conwayste: Fut::channel::mpsc::Sender<NetwaysteEvent>,
-        mut channel_from_conwayste: Fut::channel::mpsc::UnboundedReceiver<NetwaysteEvent>,
+        channel_from_conwayste: Fut::channel::mpsc::UnboundedReceiver<NetwaysteEvent>,
     ) -> Result<(), Box<dyn std::error::Error + 'static>> {
         let has_port_re = Regex::new(r":\d{1,5}$").unwrap(); // match a colon followed by number up to 5 digits (16-bit port)
-        let mut server_str = env::args().nth(1).unwrap_or("localhost".to_owned());
+        let mut server_str = server;
+        let websocket = server_str.starts_with("ws://");
+        if websocket {
+            server_str = server_str["ws://".len()..].trim_end_matches('/').to_owned();
+        }
 
         // if no port, add the default port
         if !has_port_re.is_match(&server_str) {
             debug!("Appending default port to {:?}", server_str);
-            server_str = format!("{}:{}", server_str, DEFAULT_PORT);
+            let port = if websocket {
+                DEFAULT_WEBSOCKET_PORT
+            } else {
+                DEFAULT_PORT
+            };
+            server_str = format!("{}:{}", server_str, port);
         }
 
-        let addr_iter = tokio::net::lookup_host(server_str).await?;
+        let addr_iter = tokio::net::lookup_host(server_str.as_str()).await?;
         let addr_vec: Vec<SocketAddr> = addr_iter.collect();
 
         let addresses_resolved = addr_vec.len();
@@ -483,6 +906,16 @@ impl ClientNetState {
 
         trace!("Connecting to {:?}", addr);
 
+        if websocket {
+            let url = format!("ws://{}/", server_str);
+            let transport = WsTransport::connect(&url, addr).await.unwrap_or_else(|e| {
+                error!("Error while trying to connect to WebSocket bridge: {:?}", e);
+                exit(1)
+            });
+            trace!("Connected to WebSocket bridge at {:?}.", addr);
+            return ClientNetState::run(transport, addr, channel_to_conwayste, channel_from_conwayste).await;
+        }
+
         // Unwrap ok because bind will abort if unsuccessful
         let udp = bind(Some("0.0.0.0"), Some(0)).await.unwrap_or_else(|e| {
             error!("Error while trying to bind UDP socket: {:?}", e);
@@ -491,13 +924,25 @@ impl ClientNetState {
 
         let local_addr = udp.local_addr()?;
 
-        // Channels
-        let (mut udp_sink, udp_stream) = UdpFramed::new(udp, NetwaystePacketCodec).split();
-        let mut udp_stream = udp_stream.fuse();
-
         trace!("Locally bound to {:?}.", local_addr);
         trace!("Will connect to remote {:?}.", addr);
 
+        let transport = UdpFramed::new(udp, NetwaystePacketCodec);
+        ClientNetState::run(transport, addr, channel_to_conwayste, channel_from_conwayste).await
+    }
+
+    /// Passes packets between `transport` and the server at `addr`, and events between the
+    /// network layer and conwayste, until an error occurs.
+    async fn run<T: Transport>(
+        transport: T,
+        addr: SocketAddr,
+        channel_to_conwayste: Fut::channel::mpsc::Sender<NetwaysteEvent>,
+        mut channel_from_conwayste: Fut::channel::mpsc::UnboundedReceiver<NetwaysteEvent>,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>> {
+        // Channels
+        let (mut packet_sink, packet_stream) = transport.split();
+        let mut packet_stream = packet_stream.fuse();
+
         // initialize state
         let mut client_state = ClientNetState::new(channel_to_conwayste);
         client_state.server_address = Some(addr);
@@ -513,20 +958,21 @@ impl ClientNetState {
                 _ = tick_interval_stream.select_next_some() => {
                     if let Some(keep_alive_pkt) = client_state.handle_tick_event() {
                         // Unwrap safe b/c the connection to server is active
-                        udp_sink.send((keep_alive_pkt, client_state.server_address.unwrap())).await?;
+                        packet_sink.send((keep_alive_pkt, client_state.server_address.unwrap())).await?;
                     }
+                    client_state.report_queue_lengths();
                 },
                 _ = network_interval_stream.select_next_some() => {
                     let retransmissions = client_state.maintain_network_state().await;
                     for packet_addr_tuple in retransmissions {
-                        udp_sink.send(packet_addr_tuple).await?;
+                        packet_sink.send(packet_addr_tuple).await?;
                     }
                 },
-                addr_packet_result = udp_stream.select_next_some() => {
+                addr_packet_result = packet_stream.select_next_some() => {
                     if let Ok((packet, addr)) = addr_packet_result {
                         let responses = client_state.handle_incoming_event(packet, addr).await;
                         for response in responses {
-                            udp_sink.send(response).await?;
+                            packet_sink.send(response).await?;
                         }
                     }
                 },
@@ -536,7 +982,7 @@ impl ClientNetState {
 
                         client_state.latency_filter.start();
 
-                        udp_sink.send((Packet::GetStatus { ping },server_address)).await?;
+                        packet_sink.send((Packet::GetStatus { ping },server_address)).await?;
                     } else {
                         let action: RequestAction = NetwaysteEvent::build_request_action_from_netwayste_event(
                             netwayste_request,
@@ -549,13 +995,18 @@ impl ClientNetState {
                                     // TODO: Have the conwayste client provide this
                                     client_state.name = Some(name.to_owned());
                                 },
+                                RequestAction::ResyncUniverse => {
+                                    // Accept the next universe diff no matter where it starts
+                                    client_state.full_gen = None;
+                                    client_state.partial_diff = None;
+                                },
                                 _ => {}
                             }
 
                             let packet = client_state.action_to_packet(action);
                             let server_address = client_state.server_address.unwrap().clone();
 
-                            udp_sink.send((packet, server_address)).await?;
+                            packet_sink.send((packet, server_address)).await?;
                         }
                     }
                 }
@@ -0,0 +1,120 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Longest wait, in ticks, between universe updates to a client on a lossy link.
+const MAX_UPDATE_INTERVAL: u32 = 16;
+/// A universe update not acknowledged within this long is counted as lost.
+const LOSS_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Number of updates, acknowledged or lost, that the send rate is judged on.
+const SAMPLE_SIZE: u32 = 20;
+/// Losing at least this share of a sample halves the send rate.
+const BACKOFF_LOSS_PERCENT: u32 = 10;
+
+/// Paces the universe updates sent to one client. Each update carries everything since the last
+/// generation the client acknowledged, so updates that are skipped are coalesced into the next
+/// one. The interval between updates doubles when too many go unacknowledged, and shrinks by a
+/// tick after each sample without losses.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CongestionControl {
+    interval:     u32, // ticks between universe updates; 1 means every tick
+    ticks_waited: u32,
+    in_flight:    VecDeque<(u64, Instant)>, // (generation the update brings the client to, when sent)
+    acked:        u32,
+    lost:         u32,
+}
+
+impl CongestionControl {
+    pub fn new() -> Self {
+        CongestionControl {
+            interval:     1,
+            ticks_waited: 0,
+            in_flight:    VecDeque::new(),
+            acked:        0,
+            lost:         0,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Called once per tick. Returns whether a universe update may be sent this tick.
+    pub fn ready_to_send(&mut self, now: Instant) -> bool {
+        while let Some(&(_, sent_at)) = self.in_flight.front() {
+            if now.duration_since(sent_at) < LOSS_TIMEOUT {
+                break;
+            }
+            self.in_flight.pop_front();
+            self.lost += 1;
+            self.adjust();
+        }
+        self.ticks_waited += 1;
+        if self.ticks_waited < self.interval {
+            return false;
+        }
+        self.ticks_waited = 0;
+        true
+    }
+
+    /// Records that an update bringing the client to generation `gen1` was sent.
+    pub fn on_sent(&mut self, gen1: u64, now: Instant) {
+        self.in_flight.push_back((gen1, now));
+    }
+
+    /// Records that the client has the universe up to generation `full_gen`. Every update in
+    /// flight that it covers has arrived, or was made unnecessary by one that did.
+    pub fn on_acked(&mut self, full_gen: u64) {
+        while let Some(&(gen1, _)) = self.in_flight.front() {
+            if gen1 > full_gen {
+                break;
+            }
+            self.in_flight.pop_front();
+            self.acked += 1;
+            self.adjust();
+        }
+    }
+
+    /// Forgets the updates in flight, such as when the game starts over from generation one. The
+    /// send rate is kept, since the link is the same.
+    pub fn forget_in_flight(&mut self) {
+        self.in_flight.clear();
+    }
+
+    fn adjust(&mut self) {
+        let total = self.acked + self.lost;
+        if total < SAMPLE_SIZE {
+            return;
+        }
+        if self.lost * 100 >= BACKOFF_LOSS_PERCENT * total {
+            self.interval = (self.interval * 2).min(MAX_UPDATE_INTERVAL);
+            debug!(
+                "Loss of {}/{}; sending universe updates every {} ticks",
+                self.lost, total, self.interval
+            );
+        } else if self.lost == 0 && self.interval > 1 {
+            self.interval -= 1;
+        }
+        self.acked = 0;
+        self.lost = 0;
+    }
+}
@@ -0,0 +1,140 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The server's leaderboards: how each player has done in the games they finished, on the whole
+//! server and in each room. They are kept by player name, since players have no accounts, and
+//! saved to the `leaderboard_file` after every game if one is set. Bots aren't ranked.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use netwayste::net::LeaderboardEntry;
+use serde::{Deserialize, Serialize};
+
+/// A player in a running game, by their index in the universe.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Contender {
+    pub name:           String,
+    pub is_bot:         bool,
+    pub last_alive_gen: usize, // latest generation in which they had live cells
+}
+
+impl Contender {
+    pub fn new(name: String, is_bot: bool) -> Self {
+        Contender {
+            name,
+            is_bot,
+            last_alive_gen: 0,
+        }
+    }
+}
+
+/// How one player did in a finished game.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GameResult {
+    pub name:           String,
+    pub won:            bool, // on a team game, everyone on the winning team won
+    pub cells_captured: u64,  // live cells they owned at the end
+    pub survival:       u64,  // generations their cells stayed alive
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+struct Record {
+    wins:             u64,
+    cells_captured:   u64,
+    longest_survival: u64,
+}
+
+impl Record {
+    fn add(&mut self, result: &GameResult) {
+        if result.won {
+            self.wins += 1;
+        }
+        self.cells_captured += result.cells_captured;
+        self.longest_survival = self.longest_survival.max(result.survival);
+    }
+}
+
+type Records = BTreeMap<String, Record>; // by player name
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct Leaderboard {
+    global: Records,
+    rooms:  BTreeMap<String, Records>, // by room name
+}
+
+impl Leaderboard {
+    /// Reads the leaderboards saved at `path`. A missing file means nobody has finished a game yet.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Leaderboard::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Writes the leaderboards to `path`, by way of a temporary file so that a crash mid-write
+    /// can't leave them truncated.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Adds the results of a game finished in `room_name` to that room's leaderboard and the
+    /// server's.
+    pub fn record(&mut self, room_name: &str, results: &[GameResult]) {
+        let room = self.rooms.entry(room_name.to_owned()).or_default();
+        for result in results {
+            self.global.entry(result.name.clone()).or_default().add(result);
+            room.entry(result.name.clone()).or_default().add(result);
+        }
+    }
+
+    /// The best `len` players on the server, or in the room named `room_name`: most wins first, then
+    /// most cells captured, then longest survival. Empty for a room nobody has finished a game in.
+    pub fn top(&self, room_name: Option<&str>, len: usize) -> Vec<LeaderboardEntry> {
+        let records = match room_name {
+            Some(room_name) => match self.rooms.get(room_name) {
+                Some(records) => records,
+                None => return vec![],
+            },
+            None => &self.global,
+        };
+        let mut entries: Vec<LeaderboardEntry> = records
+            .iter()
+            .map(|(name, record)| LeaderboardEntry {
+                player_name:      name.clone(),
+                wins:             record.wins,
+                cells_captured:   record.cells_captured,
+                longest_survival: record.longest_survival,
+            })
+            .collect();
+        // Ties keep the order of the names, as the sort is stable
+        entries.sort_by(|a, b| {
+            (b.wins, b.cells_captured, b.longest_survival).cmp(&(a.wins, a.cells_captured, a.longest_survival))
+        });
+        entries.truncate(len);
+        entries
+    }
+}
@@ -32,6 +32,7 @@ extern crate serde;
 pub mod net;
 pub mod client;
 pub mod utils;
+pub mod websocket;
 
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,160 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use netwayste::net::{NetwaystePacketCodec, Packet, MAX_DIFF_PART_LEN};
+use tokio::net::UdpSocket;
+use tokio_util::codec::Encoder;
+
+/// Room in an update for everything besides the pattern of its universe diff part.
+const UPDATE_OVERHEAD: usize = 448;
+/// Packets this big are assumed to get through: a 1500 byte Ethernet frame less the IP and UDP
+/// headers.
+const MIN_PACKET_SIZE: u16 = (MAX_DIFF_PART_LEN + UPDATE_OVERHEAD) as u16;
+/// Largest packet probed for: a 9000 byte jumbo frame less the IP and UDP headers.
+const MAX_PACKET_SIZE: u16 = 8972;
+/// The search stops once the largest size is known to within this many bytes.
+const PROBE_PRECISION: u16 = 64;
+/// A probe not answered within this long is counted as lost.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// A size is given up on after this many of its probes are lost in a row.
+const PROBE_ATTEMPTS: u32 = 2;
+
+/// Finds the largest packet that gets through to one client, by a binary search with padded
+/// probes. Until the search is done, sizes that haven't been shown to get through aren't used.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PathMtu {
+    largest_ok:    u16,                    // largest packet known to get through
+    smallest_lost: u16,                    // smallest probe that never got through, or one past the largest tried
+    probe:         Option<(u16, Instant)>, // (size, when sent) of the probe awaiting a reply
+    lost_probes:   u32,                    // probes of the current size lost in a row
+}
+
+impl PathMtu {
+    pub fn new() -> Self {
+        PathMtu {
+            largest_ok:    MIN_PACKET_SIZE,
+            smallest_lost: MAX_PACKET_SIZE + 1,
+            probe:         None,
+            lost_probes:   0,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn largest_packet(&self) -> u16 {
+        self.largest_ok
+    }
+
+    /// Called periodically. Returns the size of probe packet to send now, if any.
+    pub fn next_probe(&mut self, now: Instant) -> Option<u16> {
+        if let Some((size, sent_at)) = self.probe {
+            if now.duration_since(sent_at) < PROBE_TIMEOUT {
+                return None;
+            }
+            self.lost_probes += 1;
+            if self.lost_probes < PROBE_ATTEMPTS {
+                self.probe = Some((size, now));
+                return Some(size);
+            }
+            self.smallest_lost = size;
+            self.probe = None;
+            self.lost_probes = 0;
+            trace!("MTU probe of {} bytes lost", size);
+        }
+        if self.smallest_lost - self.largest_ok <= PROBE_PRECISION {
+            return None;
+        }
+        let size = self.largest_ok + (self.smallest_lost - self.largest_ok) / 2;
+        self.probe = Some((size, now));
+        Some(size)
+    }
+
+    /// Records that a probe of `size` bytes got through. Only the size of the probe awaiting a reply
+    /// counts, so a client can't claim a larger size than was sent; a reply so late that its probe
+    /// was given up on is ignored too.
+    pub fn on_reply(&mut self, size: u16) {
+        if self.probe.map(|(probe_size, _)| probe_size) != Some(size) {
+            return;
+        }
+        self.largest_ok = size;
+        self.probe = None;
+        self.lost_probes = 0;
+        if self.smallest_lost - self.largest_ok <= PROBE_PRECISION {
+            debug!("Path MTU found: packets of {} bytes get through", self.largest_ok);
+        }
+    }
+
+    /// Most bytes of pattern to put in each part of a universe diff.
+    pub fn diff_part_len(&self) -> usize {
+        self.largest_ok as usize - UPDATE_OVERHEAD
+    }
+}
+
+/// Has packets sent from `socket` go out whole or not at all, so that a probe only gets through if
+/// the whole path takes packets its size. Routers would otherwise split up large probes, which then
+/// arrive in pieces and get through anyway.
+#[cfg(target_os = "linux")]
+pub fn forbid_fragmenting(socket: &UdpSocket) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // The PROBE setting also sends packets larger than the path MTU the kernel has cached
+    let (level, name, value) = if socket.local_addr()?.is_ipv4() {
+        (libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, libc::IP_PMTUDISC_PROBE)
+    } else {
+        (libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, libc::IPV6_PMTUDISC_PROBE)
+    };
+    // Safe since the value pointed to lives through the call, and its size is passed along
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn forbid_fragmenting(_socket: &UdpSocket) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "turning off fragmenting is only supported on Linux",
+    ))
+}
+
+/// Sends an MTU probe straight from `socket`, rather than through the framed sink the other packets
+/// go through. A probe too large to leave this host fails to send, which would leave it stuck in the
+/// sink; sent this way, it is just counted as lost once it times out.
+pub async fn send_probe(socket: &UdpSocket, probe: Packet, addr: SocketAddr) {
+    let mut buf = BytesMut::new();
+    // unwrap OK because encoding a packet can't fail
+    NetwaystePacketCodec.encode(probe, &mut buf).unwrap();
+    if let Err(e) = socket.send_to(&buf, addr).await {
+        trace!("MTU probe to {:?} not sent: {}", addr, e);
+    }
+}
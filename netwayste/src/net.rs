@@ -17,7 +17,7 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::cmp::{Ordering, PartialEq, PartialOrd};
+use std::cmp::{self, Ordering, PartialEq, PartialOrd};
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::net::{self, SocketAddr};
@@ -30,6 +30,10 @@ use crate::utils::PingPong;
 
 use bincode::{deserialize, serialize};
 use bytes::{Buf, BytesMut};
+use conway::rle::Pattern;
+use conway::universe::{CellState, Region, Universe};
+use conway::{ConwayError, ConwayResult};
+use futures::{Sink, Stream};
 use semver::{SemVerError, Version};
 use serde::{Deserialize, Serialize};
 use tokio::net::UdpSocket;
@@ -41,6 +45,20 @@ pub const DEFAULT_PORT: u16 = 2016;
 pub const TIMEOUT_IN_SECONDS: u64 = 5;
 pub const NETWORK_QUEUE_LENGTH: usize = 600; // spot testing with poor network (~675 cmds) showed a max of ~512 length
                                              // keep this for now until the performance issues are resolved
+pub const MAX_DIFF_PART_LEN: usize = 1024; // bytes of pattern per GenStateDiffPart, to keep packets under a typical MTU
+pub const MAX_DIFF_PARTS: usize = 32; // limited by GenPartInfo::have_bitmask
+pub const SEND_QUEUE_LENGTH: usize = 256; // per priority; the oldest packets are dropped beyond this
+pub const TYPING_NOTICE_INTERVAL_MS: u64 = 2000; // a client says its player is still typing at most this often
+pub const MAX_SHARED_PATTERN_LEN: usize = 1024; // bytes of RLE, so a shared pattern fits in a packet like a diff part
+pub const MAX_SHARED_PATTERN_NAME_LEN: usize = 32; // characters
+pub const SHARED_PATTERN_PAGE_LEN: usize = 10; // shared patterns listed per ListSharedPatterns request
+pub const MAX_ROOM_MAP_LEN: usize = 1024; // bytes of wall RLE, so a room's map fits in a packet like a diff part
+pub const MAX_SPAWN_REGIONS: u8 = 8; // spawn regions a room's map can set, one per player or team
+pub const MAX_FOG_FREE_REGIONS: u8 = 8; // regions a room's map can clear the fog from
+pub const MAX_WALL_EDIT_CELLS: usize = 256; // cells painted or erased by one EditRoomMap request
+pub const MAX_SET_CELLS: usize = 128; // cells changed by one SetCells request, so it fits in a packet like a diff part
+pub const MAX_ANNOTATION_LABEL_LEN: usize = 32; // characters in the label of a shared marker
+pub const MAX_CHAT_HISTORY_LEN: usize = 1024; // bytes of names and messages replayed, so they fit in a packet
 const RETRANSMISSION_THRESHOLD_IN_MS: Duration = Duration::from_millis(400);
 const RETRY_THRESHOLD: usize = 2; //
 const RETRY_AGGRESSIVE_THRESHOLD: usize = 5;
@@ -117,6 +135,118 @@ pub enum RequestAction {
         room_name: String,
     },
     LeaveRoom,
+    // Pick a team in the current room. None asks the server to place the player on the smallest
+    // team.
+    SetTeam {
+        team: Option<u8>,
+    },
+    // Like ChatMessage, but only delivered to players on the sender's team.
+    TeamChatMessage {
+        message: String,
+    },
+    // Chat with everyone on the server, whether in the lobby or in a room. Allowed from anywhere.
+    LobbyChatMessage {
+        message: String,
+    },
+    // Ask for the current room's most recent chat messages, such as right after joining it.
+    // Answered with ChatHistory.
+    GetChatHistory,
+    // The player is typing a chat message to the room. Sent again every TYPING_NOTICE_INTERVAL_MS
+    // while they keep at it; others stop showing it once that long has passed without another.
+    Typing,
+    // Split the current room into this many teams (zero means free-for-all). Not allowed once the
+    // game is running.
+    SetTeamCount {
+        team_count: u8,
+    },
+    // Choose how the winner of the next game in the current room is decided. Not allowed once the
+    // game is running.
+    SetWinCondition {
+        condition: WinCondition,
+    },
+    // Start a game in the current room with everyone who is in it.
+    StartGame,
+    // Play games in the current room as a series of this many rounds, with the same roster and
+    // cumulative scoring. One means single games. Not allowed once the game is running.
+    SetSeriesLength {
+        rounds: u8,
+    },
+    // How many generations per second the room's games run at. Only the room's host may set it,
+    // and it can be changed while a game is running.
+    SetGenerationRate {
+        gens_per_sec: u8,
+    },
+    // Fill a seat in the current room with a computer-controlled player. Only the room's host (the
+    // player who has been in it longest) may add or remove bots, and not once the game is running.
+    AddBot,
+    RemoveBot {
+        name: String,
+    },
+    // Ask for this cell color in the current room. If it is too close to another player's color,
+    // the server picks a nearby one instead; either way it tells the room with a PlayerColor update.
+    SetColor {
+        color: RgbColor,
+    },
+    // Tell the server which part of the universe is on screen, after scrolling or zooming. The
+    // server only sends universe diffs for the area around it.
+    ClientViewport {
+        viewport: NetRegion,
+    },
+    // Make up to MAX_SET_CELLS cells in the player's writable region, each (column, row, alive),
+    // alive or dead, starting with the server's next generation. If any is outside the region, none
+    // are changed. Answered with CellsSet, so the client knows which generation should show them.
+    SetCells {
+        cells: Vec<(u32, u32, bool)>,
+    },
+    // Forget which generation of the universe the client has, so that the next update is the
+    // whole universe around the viewport. For a client that has fallen too far behind to catch up.
+    ResyncUniverse,
+    // Make the server re-read its config file. Only honored if `password` matches the admin
+    // password in the server's config.
+    ReloadConfig {
+        password: String,
+    },
+    // Flag another player for the server's moderators. `reason` may be empty; the server adds what
+    // the player recently said in chat.
+    ReportPlayer {
+        player_name: String,
+        reason:      String,
+    },
+    // Ask for the best players on the server, or in the named room, by wins, cells captured and
+    // longest survival. Allowed from anywhere. Answered with Leaderboard.
+    GetLeaderboard {
+        room_name: Option<String>,
+    },
+    // Share a pattern with everyone on the server, as the contents of an RLE file of up to
+    // MAX_SHARED_PATTERN_LEN bytes. The player is its author. Allowed from anywhere. Answered with
+    // PatternShared.
+    SharePattern {
+        name: String,
+        rle:  String,
+    },
+    // Ask for up to SHARED_PATTERN_PAGE_LEN of the shared patterns, newest first, skipping the
+    // first `offset`. Answered with SharedPatternList.
+    ListSharedPatterns {
+        offset: u32,
+    },
+    // Ask for a shared pattern by the ID it was listed with. Answered with SharedPattern.
+    GetSharedPattern {
+        pattern_id: u64,
+    },
+    // Change the map of the current room, which its next game starts from. Only the room's host
+    // may, and not once the game is running. Everyone in the room is sent the edit as a RoomMapEdit.
+    EditRoomMap {
+        edit: MapEdit,
+    },
+    // Ask for the current room's map, such as right after joining it. Answered with RoomMap.
+    GetRoomMap,
+    // Show an annotation the player drew over the board to everyone in the current room. Everyone
+    // in the room is sent it as an AnnotationShare, along with the player's name. `id` is the
+    // player's own number for the annotation; sharing it again under the same ID replaces it.
+    ShareAnnotation {
+        id:         u32,
+        annotation: Annotation,
+    },
     // TODO: add support ("auto_match" bool key, see issue #101)
     SetClientOptions {
         key:   String,
@@ -164,17 +294,48 @@ pub enum ResponseCode {
     LoggedIn {
         cookie:         String,
         server_version: String,
-    }, // player is logged in -- (cookie, server version)
+        motd:           Option<String>, // message of the day
+    }, // player is logged in -- (cookie, server version, message of the day)
     JoinedRoom {
         room_name: String,
     }, // player has joined the room
     LeaveRoom, // player has left the room
+    JoinedTeam {
+        team: u8,
+    }, // player has been placed on a team in the current room
     PlayerList {
         players: Vec<String>,
     }, // list of players in room or lobby
     RoomList {
         rooms: Vec<RoomList>,
     }, // list of rooms and their statuses
+    CellsSet {
+        cells:      Vec<(u32, u32)>,
+        generation: u64,
+    }, // a SetCells request was accepted, and the changes to these cells will first be seen in this generation
+    ChatHistory {
+        messages: Vec<ChatHistoryEntry>,
+    }, // the room's most recent chat messages, up to MAX_CHAT_HISTORY_LEN bytes of them; oldest first
+    Leaderboard {
+        room_name: Option<String>,
+        entries:   Vec<LeaderboardEntry>,
+    }, // the best players in the room, or on the server if room_name is None; best first
+    PatternShared {
+        pattern_id: u64,
+        name:       String,
+    }, // a SharePattern request was accepted
+    SharedPatternList {
+        offset:   u32,
+        total:    u32,
+        patterns: Vec<SharedPatternInfo>,
+    }, // a page of the shared patterns, newest first; `total` is how many the server has
+    SharedPattern {
+        info: SharedPatternInfo,
+        rle:  String,
+    }, // a shared pattern asked for with GetSharedPattern
+    RoomMap {
+        map: RoomMap,
+    }, // the current room's map, asked for with GetRoomMap
 
     // errors
     BadRequest {
@@ -197,6 +358,90 @@ pub enum ResponseCode {
     KeepAlive, // Server's heart is beating
 }
 
+/// A chat message from before the player asked for the room's chat history.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ChatHistoryEntry {
+    pub chat_seq:    u64, // as in BroadcastChatMessage, so the client can tell which it already has
+    pub player_name: String,
+    pub message:     String,
+    pub age_secs:    u64,         // how long before the reply it was sent
+    pub channel:     ChatChannel, // Room, or Team if only the player's teammates were sent it
+}
+
+/// A player's line on a leaderboard, covering every game they finished on the server or in a room.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub player_name:      String,
+    pub wins:             u64,
+    pub cells_captured:   u64, // live cells owned at the end of each game, added up
+    pub longest_survival: u64, // most generations their cells stayed alive in one game
+}
+
+/// A pattern shared by a player, as listed for browsing. The RLE is sent separately, when the
+/// pattern is downloaded.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct SharedPatternInfo {
+    pub pattern_id: u64,
+    pub name:       String,
+    pub author:     String, // name of the player who shared it
+    pub width:      u32,    // in cells
+    pub height:     u32,
+}
+
+/// A change to a room's map, made by its host.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum MapEdit {
+    /// Paint walls on up to MAX_WALL_EDIT_CELLS cells, each (column, row), or erase them.
+    Walls { cells: Vec<(u32, u32)>, wall: bool },
+    /// Set where a player starts, or a team in a team game. None gives the slot its usual strip back.
+    SpawnRegion { slot: u8, region: Option<NetRegion> },
+    /// Set a region every player can see into from the start. None removes the slot's region.
+    FogFreeRegion { slot: u8, region: Option<NetRegion> },
+    /// Remove every wall, spawn region and fog-free region.
+    Clear,
+}
+
+/// Something a player drew over the board. Annotations are only on the player's own screen unless
+/// they share them with their room.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Annotation {
+    /// A marker on the cell (column, row), with a label of up to MAX_ANNOTATION_LABEL_LEN characters.
+    Marker { cell: (u32, u32), label: String },
+    /// An arrow from one cell to another, each (column, row).
+    Arrow { from: (u32, u32), to: (u32, u32) },
+    /// A line between two cells, each (column, row), labeled with how far apart they are.
+    Measure { from: (u32, u32), to: (u32, u32) },
+}
+
+/// How the next game in a room is played, as set with SetTeamCount, SetWinCondition and
+/// SetSeriesLength.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RoomSettings {
+    pub team_count:    u8, // zero means free-for-all
+    pub win_condition: WinCondition,
+    pub series_length: u8, // rounds per series; 1 means single games
+}
+
+/// What a room's host has set up for its next game. As in any game, fog covers everything but a
+/// player's own spawn region at the start, apart from the map's fog-free regions.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RoomMap {
+    pub walls:            String, // RLE of the wall cells, from the top-left corner of the universe
+    pub spawn_regions:    Vec<Option<NetRegion>>, // by player index, or by team in a team game
+    #[serde(default)] // not in snapshots from older servers
+    pub fog_free_regions: Vec<Option<NetRegion>>, // seen by every player from the start
+}
+
+/// Which conversation a chat message belongs to. A player in a room is in both the room's and the
+/// lobby's at once, and each is numbered and acknowledged separately. Team chats are numbered along
+/// with the room's.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ChatChannel {
+    Room,  // the room the player is in
+    Team,  // the player's team in that room
+    Lobby, // everyone on the server
+}
+
 // chat messages sent from server to all clients other than originating client
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BroadcastChatMessage {
@@ -205,6 +450,7 @@ pub struct BroadcastChatMessage {
     // internal to server
     pub player_name: String,
     pub message:     String, // should not contain newlines
+    pub channel:     ChatChannel,
 }
 
 impl PartialEq for BroadcastChatMessage {
@@ -240,9 +486,22 @@ impl BroadcastChatMessage {
             chat_seq:    Some(sequence),
             player_name: name,
             message:     msg,
+            channel:     ChatChannel::Room,
         }
     }
 
+    /// Moves this message to the lobby channel.
+    pub fn in_lobby(mut self) -> BroadcastChatMessage {
+        self.channel = ChatChannel::Lobby;
+        self
+    }
+
+    /// Moves this message to the team channel, for the sender's teammates only.
+    pub fn on_team(mut self) -> BroadcastChatMessage {
+        self.channel = ChatChannel::Team;
+        self
+    }
+
     fn sequence_number(&self) -> u64 {
         if let Some(v) = self.chat_seq {
             v
@@ -252,10 +511,30 @@ impl BroadcastChatMessage {
     }
 }
 
-// TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GameOutcome {
-    pub winner: Option<String>, // Some(<name>) if winner, or None, meaning it was a tie/forfeit
+    pub winner:          Option<String>, // Some(<name>) if winner, or None, meaning it was a tie/forfeit
+    pub standings:       Vec<PlayerScore>, // Final scores, best first
+    #[serde(default)] // not in snapshots from older servers
+    pub winning_players: Vec<String>, // names of the players on the winning side, such as a whole team
+}
+
+/// The score of a single player, or of a whole team in a team game.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct PlayerScore {
+    pub name:  String, // player name, or team name in a team game
+    pub score: u64,
+}
+
+/// How the server decides who won a game.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum WinCondition {
+    /// Whoever owns the most live cells once `generations` generations have passed.
+    MostLiveCells { generations: u32 },
+    /// The last player (or team) left with any live cells.
+    Elimination,
+    /// Whoever owns the most live cells inside `region` once `generations` generations have passed.
+    TerritoryControl { region: NetRegion, generations: u32 },
 }
 
 /// All options needed to initialize a Universe. Notably, num_players is absent, because it can be
@@ -269,6 +548,189 @@ pub struct GameOptions {
     history:         u16,
     player_writable: Vec<NetRegion>,
     fog_radius:      u32,
+    /// Number of teams; zero means free-for-all. When non-zero, `player_writable` is indexed by
+    /// team rather than by player, so that teammates share a writable region.
+    team_count:      u8,
+    win_condition:   WinCondition,
+    /// RLE of the wall cells set up by the room's host, from the top-left corner of the universe;
+    /// empty if there are none.
+    #[serde(default)] // not in snapshots from older servers
+    walls:           String,
+    /// Regions every player can see into from the start, set up by the room's host.
+    #[serde(default)] // not in snapshots from older servers
+    fog_free:        Vec<NetRegion>,
+}
+
+impl GameOptions {
+    /// Options for a team game. The universe is split into `team_count` vertical strips of equal
+    /// width, one per team. A `team_count` of zero gives a single strip covering the universe.
+    #[allow(unused)]
+    pub fn with_teams(width: u32, height: u32, history: u16, fog_radius: u32, team_count: u8) -> Self {
+        let strips = std::cmp::max(team_count as u32, 1);
+        GameOptions {
+            width,
+            height,
+            history,
+            player_writable: GameOptions::vertical_strips(width, height, strips),
+            fog_radius,
+            team_count,
+            win_condition: WinCondition::Elimination,
+            walls: String::new(),
+            fog_free: vec![],
+        }
+    }
+
+    /// Options for a free-for-all game, where each of the `num_players` players gets a vertical
+    /// strip of equal width.
+    #[allow(unused)]
+    pub fn free_for_all(width: u32, height: u32, history: u16, fog_radius: u32, num_players: u32) -> Self {
+        let strips = std::cmp::max(num_players, 1);
+        GameOptions {
+            width,
+            height,
+            history,
+            player_writable: GameOptions::vertical_strips(width, height, strips),
+            fog_radius,
+            team_count: 0,
+            win_condition: WinCondition::Elimination,
+            walls: String::new(),
+            fog_free: vec![],
+        }
+    }
+
+    /// Shifts the writable regions by `by` places, so that each player (or team) starts somewhere
+    /// else.
+    #[allow(unused)]
+    pub fn with_rotated_regions(mut self, by: usize) -> Self {
+        let len = self.player_writable.len();
+        if len > 0 {
+            self.player_writable.rotate_left(by % len);
+        }
+        self
+    }
+
+    /// Sets how the winner of the game is decided.
+    #[allow(unused)]
+    pub fn with_win_condition(mut self, win_condition: WinCondition) -> Self {
+        self.win_condition = win_condition;
+        self
+    }
+
+    /// Takes the walls, spawn regions and fog-free regions of a room's map. A spawn region
+    /// replaces the writable region of its player (or team), if there is one.
+    #[allow(unused)]
+    pub fn with_room_map(mut self, map: &RoomMap) -> Self {
+        for (slot, region) in map.spawn_regions.iter().enumerate() {
+            if let (Some(region), Some(writable)) = (region, self.player_writable.get_mut(slot)) {
+                *writable = region.clone();
+            }
+        }
+        self.walls = map.walls.clone();
+        self.fog_free = map.fog_free_regions.iter().flatten().cloned().collect();
+        self
+    }
+
+    fn vertical_strips(width: u32, height: u32, strips: u32) -> Vec<NetRegion> {
+        let strip_width = width / strips;
+        (0..strips)
+            .map(|i| NetRegion::new((i * strip_width) as i32, 0, strip_width, height))
+            .collect()
+    }
+
+    #[allow(unused)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[allow(unused)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[allow(unused)]
+    pub fn history(&self) -> u16 {
+        self.history
+    }
+
+    #[allow(unused)]
+    pub fn fog_radius(&self) -> u32 {
+        self.fog_radius
+    }
+
+    #[allow(unused)]
+    pub fn team_count(&self) -> u8 {
+        self.team_count
+    }
+
+    #[allow(unused)]
+    pub fn win_condition(&self) -> &WinCondition {
+        &self.win_condition
+    }
+
+    #[allow(unused)]
+    pub fn walls(&self) -> &str {
+        &self.walls
+    }
+
+    #[allow(unused)]
+    pub fn fog_free(&self) -> &[NetRegion] {
+        &self.fog_free
+    }
+
+    /// Puts the walls into `uni`, a universe just made from these options, and lifts the fog from
+    /// the fog-free regions.
+    #[allow(unused)]
+    pub fn add_map(&self, uni: &mut Universe) -> ConwayResult<()> {
+        for region in &self.fog_free {
+            uni.lift_fog(Region::from(region));
+        }
+        if self.walls.is_empty() {
+            return Ok(());
+        }
+        let region = uni.region();
+        let pattern = Pattern(self.walls.clone());
+        let (width, height) = pattern.calc_size()?;
+        if width > region.width() || height > region.height() {
+            return Err(ConwayError::InvalidData {
+                reason: format!("walls are {}x{}, larger than the universe", width, height),
+            });
+        }
+        pattern
+            .to_new_bit_grid(region.width(), region.height())?
+            .each_set(|col, row| uni.set_unchecked(col, row, CellState::Wall));
+        Ok(())
+    }
+
+    /// The writable regions: one per player or, in a team game, one per team.
+    #[allow(unused)]
+    pub fn player_writable(&self) -> &[NetRegion] {
+        &self.player_writable
+    }
+
+    /// The region a player may write to. In a team game this is the region of the player's team.
+    #[allow(unused)]
+    pub fn writable_region(&self, index: usize, team: Option<u8>) -> Option<&NetRegion> {
+        if self.team_count > 0 {
+            team.and_then(|t| self.player_writable.get(t as usize))
+        } else {
+            self.player_writable.get(index)
+        }
+    }
+}
+
+/// A player's cell color.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct RgbColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl RgbColor {
+    #[allow(unused)]
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        RgbColor { r, g, b }
+    }
 }
 
 /// Net-safe version of a libconway Region
@@ -280,6 +742,45 @@ pub struct NetRegion {
     height: u32,
 }
 
+impl NetRegion {
+    #[allow(unused)]
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        NetRegion {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+impl From<Region> for NetRegion {
+    fn from(region: Region) -> Self {
+        NetRegion::new(
+            region.left() as i32,
+            region.top() as i32,
+            region.width() as u32,
+            region.height() as u32,
+        )
+    }
+}
+
+impl From<&NetRegion> for Region {
+    fn from(region: &NetRegion) -> Self {
+        Region::new(
+            region.left as isize,
+            region.top as isize,
+            region.width as usize,
+            region.height as usize,
+        )
+    }
+}
+
 // TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct PlayerInfo {
@@ -287,6 +788,26 @@ pub struct PlayerInfo {
     name:  String,
     /// Index of player in Universe; None means this player is a lurker (non-participant)
     index: Option<u64>,
+    /// Team the player is on; None if the room has no teams or the player has not picked one.
+    team:  Option<u8>,
+}
+
+impl PlayerInfo {
+    pub fn new(name: String, index: Option<u64>, team: Option<u8>) -> Self {
+        PlayerInfo { name, index, team }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn index(&self) -> Option<u64> {
+        self.index
+    }
+
+    pub fn team(&self) -> Option<u8> {
+        self.team
+    }
 }
 
 // TODO: add support
@@ -300,11 +821,18 @@ pub enum GameUpdate {
     },
     GameStart {
         options: GameOptions,
+        /// Who plays in the game, with their index in the universe and their team, so that each
+        /// client can find the region it may write to.
+        players: Vec<PlayerInfo>,
     },
     PlayerList {
         /// List of names and other info of all users including current user.
         players: Vec<PlayerInfo>,
     },
+    /// The room's settings, sent on joining it and whenever they change.
+    RoomSettings {
+        settings: RoomSettings,
+    },
     PlayerChange {
         /// Most up to date player information.
         player:   PlayerInfo,
@@ -317,10 +845,50 @@ pub enum GameUpdate {
     PlayerLeave {
         name: String,
     },
+    /// The cell color assigned to a player, possibly adjusted from the one they asked for.
+    PlayerColor {
+        name:  String,
+        color: RgbColor,
+    },
+    /// A player in the room is typing a chat message.
+    PlayerTyping {
+        name: String,
+    },
+    /// The host changed the room's map.
+    RoomMapEdit {
+        edit: MapEdit,
+    },
+    /// A player in the room shared an annotation. It replaces any they shared before with the same ID.
+    AnnotationShare {
+        name:       String,
+        id:         u32,
+        annotation: Annotation,
+    },
+    /// The cells players and bots changed just after the universe advanced to `generation`, sent
+    /// only by rooms with `share_cell_inputs` on, since it shows cells hidden by the fog.
+    CellInputs {
+        generation: u32,
+        inputs:     Vec<CellInput>,
+    },
+    /// Current scores, sent periodically while a game is running.
+    ScoreUpdate {
+        generation: u32,
+        scores:     Vec<PlayerScore>, // best first
+    },
     /// Game ended but the user is allowed to stay.
     GameFinish {
         outcome: GameOutcome,
     },
+    /// A round of a series has finished (after its GameFinish) and the next one starts shortly.
+    Intermission {
+        round:     u8, // 1-based round that just finished
+        rounds:    u8,
+        standings: Vec<PlayerScore>, // cumulative, best first
+    },
+    /// The last round of a series has finished; the outcome holds the cumulative standings.
+    SeriesFinish {
+        outcome: GameOutcome,
+    },
     /// Kicks user back to lobby.
     RoomDeleted,
     /// New match. Server suggests we join this room.
@@ -334,7 +902,9 @@ pub enum GameUpdate {
 // TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum UniUpdate {
-    Diff { diff: GenStateDiffPart },
+    // The epoch goes up each time the server starts the client over with the whole universe, such
+    // as for a new viewport, so that acknowledgements of diffs from before then can be told apart.
+    Diff { diff: GenStateDiffPart, epoch: u32 },
     NoChange,
 }
 
@@ -347,9 +917,71 @@ pub struct GenStateDiffPart {
     pub gen0:         u32,    // zero means diff is based off the beginning of time
     pub gen1:         u32,    // This is the generation when this diff has been applied.
     pub pattern_part: String, // concatenated together to form a Pattern
+    pub checksum:     u32,    // CRC-32 of the fields above; see `compute_checksum`
+}
+
+impl GenStateDiffPart {
+    pub fn new(part_number: u8, total_parts: u8, gen0: u32, gen1: u32, pattern_part: String) -> Self {
+        let mut part = GenStateDiffPart {
+            part_number,
+            total_parts,
+            gen0,
+            gen1,
+            pattern_part,
+            checksum: 0,
+        };
+        part.checksum = part.compute_checksum();
+        part
+    }
+
+    /// CRC-32 of everything in this part but the checksum itself. The UDP checksum is only 16 bits
+    /// and optional over IPv4, so a part that was damaged on the way could otherwise be applied as
+    /// a garbled universe.
+    pub fn compute_checksum(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&[self.part_number, self.total_parts]);
+        hasher.update(&self.gen0.to_le_bytes());
+        hasher.update(&self.gen1.to_le_bytes());
+        hasher.update(self.pattern_part.as_bytes());
+        hasher.finalize()
+    }
+
+    pub fn checksum_matches(&self) -> bool {
+        self.checksum == self.compute_checksum()
+    }
+
+    /// Splits the pattern of a diff into parts of up to MAX_DIFF_PART_LEN bytes, or larger ones if
+    /// it would otherwise take more than MAX_DIFF_PARTS.
+    #[allow(unused)]
+    pub fn split(gen0: u32, gen1: u32, pattern: &str) -> Vec<GenStateDiffPart> {
+        GenStateDiffPart::split_into(gen0, gen1, pattern, MAX_DIFF_PART_LEN)
+    }
+
+    /// Like `split`, but with parts of up to `max_part_len` bytes, for links known to take bigger
+    /// packets.
+    pub fn split_into(gen0: u32, gen1: u32, pattern: &str, max_part_len: usize) -> Vec<GenStateDiffPart> {
+        let part_len = cmp::max(max_part_len, (pattern.len() + MAX_DIFF_PARTS - 1) / MAX_DIFF_PARTS);
+        let mut pieces = vec![];
+        let mut rest = pattern;
+        while rest.len() > part_len {
+            let mut end = part_len;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (piece, remainder) = rest.split_at(end);
+            pieces.push(piece);
+            rest = remainder;
+        }
+        pieces.push(rest);
+        let total_parts = pieces.len() as u8;
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, piece)| GenStateDiffPart::new(i as u8, total_parts, gen0, gen1, piece.to_owned()))
+            .collect()
+    }
 }
 
-// TODO: add support
 /// GenPartInfo is sent in the UpdateReply to indicate which GenStateDiffParts are needed.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GenPartInfo {
@@ -358,6 +990,23 @@ pub struct GenPartInfo {
     pub have_bitmask: u32, // bitmask indicating which parts for the specified diff are present; must be less than 1<<total_parts
 }
 
+/// The server's hash of its universe at some generation (see `Universe::state_hash`). A client
+/// simulating the game on its own compares these against its own universe to find desyncs.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct UniverseHash {
+    pub generation: u32,
+    pub hash:       u64,
+}
+
+/// A change to one cell that the server made on behalf of a player or bot, as in `Universe::set`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct CellInput {
+    pub player_index: u64, // player's index in the universe
+    pub col:          u32,
+    pub row:          u32,
+    pub alive:        bool,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct RoomList {
     pub room_name:    String,
@@ -388,19 +1037,23 @@ pub enum Packet {
         // leaving. Also note that the server may not send all GameUpdates or BroadcastChatMessages
         // in a single packet, since it could exceed the MTU.
         // TODO: limit chats and game_updates based on MTU!
+        // Chats from the lobby are sent to players in the lobby too, in updates with nothing else.
         chats:           Vec<BroadcastChatMessage>, // All non-acknowledged chats are sent each update
-        game_update_seq: Option<u64>,
-        game_updates:    Vec<GameUpdate>, // Information pertaining to a game tick update.
-        universe_update: UniUpdate,       // TODO: add support
-        ping:            PingPong,        // Used for server-to-client latency measurement (no room needed)
+        game_update_seq: Option<u64>,               // Sequence number of the first entry of game_updates
+        game_updates:    Vec<GameUpdate>,           // Information pertaining to a game tick update.
+        universe_update: UniUpdate,                 // TODO: add support
+        universe_hash:   Option<UniverseHash>,      // Latest hash while a game is running
+        ping:            PingPong,                  // Used for server-to-client latency measurement (no room needed)
     },
     UpdateReply {
         // in-game: sent by client in reply to server
         cookie:               String,
         last_chat_seq:        Option<u64>, // sequence number of latest chat msg. received from server
+        last_lobby_chat_seq:  Option<u64>, // likewise for the lobby chat channel
         last_game_update_seq: Option<u64>, // seq. number of latest game update from server
         last_full_gen:        Option<u64>, // generation number client is currently at
         partial_gen:          Option<GenPartInfo>, // partial gen info, if some but not all GenStateDiffParts recv'd
+        universe_epoch:       u32,         // epoch of the diffs last_full_gen and partial_gen are about
         pong:                 PingPong,    // Used for server-to-client latency measurement
     },
     GetStatus {
@@ -414,6 +1067,16 @@ pub enum Packet {
         server_name:    String,
         // TODO: max players?
     }, // Provide basic server information to the requester
+    MtuProbe {
+        // sent by server to find the largest packet that reaches the client; see `Packet::mtu_probe`
+        size:    u16, // of the whole packet once encoded
+        padding: Vec<u8>,
+    },
+    MtuProbeReply {
+        // sent by client in reply to an MtuProbe
+        cookie: String,
+        size:   u16,
+    },
 }
 
 impl Packet {
@@ -438,12 +1101,13 @@ impl Packet {
             game_updates: _,
             game_update_seq: _,
             universe_update,
+            universe_hash: _,
             ping: _,
         } = self
         {
             // TODO revisit once mechanics are fleshed out
             match universe_update {
-                UniUpdate::Diff { diff: part } => ((part.gen1 as u64) << 32) | (part.gen0 as u64),
+                UniUpdate::Diff { diff: part, .. } => ((part.gen1 as u64) << 32) | (part.gen0 as u64),
                 UniUpdate::NoChange => 0,
             }
         } else {
@@ -451,6 +1115,39 @@ impl Packet {
         }
     }
 
+    /// How urgently this packet should go out when there are more waiting than can be sent.
+    pub fn priority(&self) -> PacketPriority {
+        match self {
+            Packet::Update {
+                chats,
+                game_updates,
+                universe_update,
+                universe_hash: _,
+                game_update_seq: _,
+                ping: _,
+            } => match universe_update {
+                // Further parts of a big diff, sent in packets of their own
+                UniUpdate::Diff { diff, .. } if diff.part_number > 0 && chats.is_empty() && game_updates.is_empty() => {
+                    PacketPriority::Bulk
+                }
+                UniUpdate::NoChange if game_updates.is_empty() && !chats.is_empty() => PacketPriority::Chat,
+                _ => PacketPriority::GameState,
+            },
+            Packet::MtuProbe { .. } => PacketPriority::Bulk,
+            _ => PacketPriority::Control,
+        }
+    }
+
+    /// A probe padded out to `size` bytes once encoded, or as near as it can be if that's too small.
+    pub fn mtu_probe(size: u16) -> Packet {
+        let empty = Packet::MtuProbe { size, padding: vec![] };
+        let overhead = bincode::serialized_size(&empty).unwrap() as usize;
+        Packet::MtuProbe {
+            size,
+            padding: vec![0; (size as usize).saturating_sub(overhead)],
+        }
+    }
+
     #[allow(unused)]
     pub fn set_response_sequence(&mut self, new_ack: Option<u64>) {
         if let Packet::Request {
@@ -493,6 +1190,17 @@ impl Packet {
     }
 }
 
+/// Classes of outgoing packets, from least to most urgent. Control packets (requests, responses
+/// including keep-alives, update replies, and status) are small and hold everything else up if they
+/// are late, so they always go first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PacketPriority {
+    Bulk,      // later parts of a universe diff too big for one packet
+    Chat,      // updates with only chat messages
+    GameState, // updates with game updates or the start of a universe diff
+    Control,
+}
+
 impl fmt::Debug for Packet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -520,23 +1228,26 @@ impl fmt::Debug for Packet {
                 game_updates,
                 game_update_seq,
                 universe_update,
+                universe_hash,
                 ping: _,
             } => write!(
                 f,
-                "[Update] game_updates: {:?} universe_update: {:?}, game_update_seq: {:?}",
-                game_updates, universe_update, game_update_seq
+                "[Update] game_updates: {:?} universe_update: {:?}, universe_hash: {:?}, game_update_seq: {:?}",
+                game_updates, universe_update, universe_hash, game_update_seq
             ),
             Packet::UpdateReply {
                 cookie,
                 last_chat_seq,
+                last_lobby_chat_seq,
                 last_game_update_seq,
                 last_full_gen,
                 partial_gen,
+                universe_epoch,
                 pong: _,
             } => write!(
                 f,
-                "[UpdateReply] cookie: {:?} last_chat_seq: {:?} last_game_update_seq: {:?} last_full_gen: {:?} partial_gen: {:?}",
-                cookie, last_chat_seq, last_game_update_seq, last_full_gen, partial_gen
+                "[UpdateReply] cookie: {:?} last_chat_seq: {:?} last_lobby_chat_seq: {:?} last_game_update_seq: {:?} last_full_gen: {:?} partial_gen: {:?} universe_epoch: {}",
+                cookie, last_chat_seq, last_lobby_chat_seq, last_game_update_seq, last_full_gen, partial_gen, universe_epoch
             ),
             Packet::GetStatus { ping } => write!(f, "[GetStatus] nonce: {}", ping.nonce),
             Packet::Status {
@@ -550,6 +1261,8 @@ impl fmt::Debug for Packet {
                 "[Status] nonce: {} player_count: {} room_count: {} server_version: {:?} server_name: {:?}",
                 pong.nonce, player_count, room_count, server_version, server_name
             ),
+            Packet::MtuProbe { size, padding: _ } => write!(f, "[MtuProbe] size: {}", size),
+            Packet::MtuProbeReply { cookie, size } => write!(f, "[MtuProbeReply] cookie: {:?} size: {}", cookie, size),
         }
     }
 }
@@ -619,6 +1332,18 @@ impl Encoder<Packet> for NetwaystePacketCodec {
 }
 
 //////////////// Network interface ////////////////
+/// Anything packets can be sent and received over: a UDP socket framed with NetwaystePacketCodec,
+/// or a connection through the server's WebSocket bridge (see `websocket::WsTransport`).
+pub trait Transport:
+    Stream<Item = Result<(Packet, SocketAddr), io::Error>> + Sink<(Packet, SocketAddr), Error = io::Error> + Unpin
+{
+}
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<(Packet, SocketAddr), io::Error>> + Sink<(Packet, SocketAddr), Error = io::Error> + Unpin
+{
+}
+
 #[allow(dead_code)]
 pub async fn bind(opt_host: Option<&str>, opt_port: Option<u16>) -> Result<UdpSocket, NetError> {
     let host = if let Some(host) = opt_host { host } else { DEFAULT_HOST };
@@ -1204,6 +1929,39 @@ impl NetworkManager {
     }
 }
 
+/// Packets waiting to be sent, one queue per priority. Packets leave in priority order, and in the
+/// order they were queued within a priority. A full queue drops its oldest packet, which is safe
+/// because everything is either retransmitted or superseded by the next update.
+#[derive(Default)]
+pub struct SendQueue {
+    queues: [VecDeque<(Packet, SocketAddr)>; 4], // indexed by PacketPriority
+}
+
+impl SendQueue {
+    pub fn push(&mut self, packet: Packet, addr: SocketAddr) {
+        let queue = &mut self.queues[packet.priority() as usize];
+        if queue.len() >= SEND_QUEUE_LENGTH {
+            if let Some((dropped, _)) = queue.pop_front() {
+                trace!("Send queue full; dropping {:?}", dropped);
+            }
+        }
+        queue.push_back((packet, addr));
+    }
+
+    /// The next packet to send, if any.
+    pub fn pop(&mut self) -> Option<(Packet, SocketAddr)> {
+        self.queues.iter_mut().rev().find_map(|queue| queue.pop_front())
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 #[allow(dead_code)]
 pub enum NetwaysteEvent {
@@ -1217,19 +1975,69 @@ pub enum NetwaysteEvent {
     NewRoom(String),     // room name
     JoinRoom(String),    // room name
     LeaveRoom,
+    SetTeam(Option<u8>),      // team number, or None to be placed on the smallest team
+    TeamChatMessage(String),  // chat message for teammates only
+    LobbyChatMessage(String), // chat message for everyone on the server
+    GetChatHistory,
+    Typing,           // the player is typing a chat message; throttled by the caller
+    SetTeamCount(u8), // number of teams in the room
+    SetWinCondition(WinCondition),
+    StartGame,
+    SetSeriesLength(u8),   // number of rounds
+    SetGenerationRate(u8), // generations per second
+    AddBot,
+    RemoveBot(String), // bot name
+    SetColor(RgbColor),
+    SetViewport(NetRegion),  // cells on screen
+    SetCells(Vec<(u32, u32, bool)>), // each (column, row, alive)
+    ResyncUniverse,
+    ReportPlayer(String, String),   // (player name, reason)
+    GetLeaderboard(Option<String>), // room name, or None for the whole server
+    SharePattern(String, String),   // (name, contents of an RLE file)
+    ListSharedPatterns(u32),        // how many of the newest to skip
+    GetSharedPattern(u64),          // pattern ID
+    EditRoomMap(MapEdit),
+    GetRoomMap,
+    ShareAnnotation(u32, Annotation), // (ID, annotation)
 
     // Responses
-    LoggedIn(String),        // player is logged in -- (version)
-    JoinedRoom(String),      // player has joined the room
-    PlayerList(Vec<String>), // list of players in room or lobby with ping (ms)
-    RoomList(Vec<RoomList>), // (room name, # players, game has started?)
+    LoggedIn(String, Option<String>), // player is logged in -- (version, message of the day)
+    JoinedRoom(String),               // player has joined the room
+    JoinedTeam(u8),                   // player has been placed on this team
+    PlayerList(Vec<String>),          // list of players in room or lobby with ping (ms)
+    RoomList(Vec<RoomList>),          // (room name, # players, game has started?)
+    CellsSet(Vec<(u32, u32)>, u64),   // (cells, each (column, row), generation they first appear in)
+    Leaderboard(Option<String>, Vec<LeaderboardEntry>), // (room name, or None for the whole server; best first)
+    PatternShared(u64, String),       // (pattern ID, name)
+    SharedPatternList(u32, u32, Vec<SharedPatternInfo>), // (offset, total, patterns newest first)
+    SharedPattern(SharedPatternInfo, String), // (pattern, contents of an RLE file)
+    RoomMap(RoomMap),                 // the current room's map
     LeftRoom,
     BadRequest(String),
     ServerError(String),
+    ConnectionLost, // the server stopped answering, so the player is no longer logged in
 
     // Updates
-    ChatMessages(Vec<(String, String)>), // (player name, message)
-    UniverseUpdate,                      // TODO add libconway stuff for current universe gen
+    ChatMessages(Vec<(String, String, ChatChannel)>), // (player name, message, Room or Team)
+    LobbyChatMessages(Vec<(String, String)>), // (player name, message) in the lobby chat channel
+    ChatHistory(Vec<(String, String, u64, ChatChannel)>), // (player name, message, seconds ago, channel), oldest first
+    GameStarted(GameOptions, Option<NetRegion>), // (options, our writable region; None if only watching)
+    CellInputs(u32, Vec<CellInput>), // (generation, cell changes made just after reaching it)
+    Scores(Vec<PlayerScore>),      // standings of the running game, best first
+    Roster(Vec<PlayerInfo>),       // everyone in the room, including us and bots; sent when that changes
+    RoomSettings(RoomSettings),    // how the room's next game is played; sent on joining and when they change
+    PlayerColor(String, RgbColor), // (player name, assigned color)
+    PlayerTyping(String),          // player name; shown until TYPING_NOTICE_INTERVAL_MS passes without another
+    RoomMapEdited(MapEdit),        // the host changed the room's map
+    AnnotationShared(String, u32, Annotation), // (player name, ID, annotation) shared with the room by another player
+    GameFinished(GameOutcome),
+    Intermission(u8, u8, Vec<PlayerScore>), // (round just finished, total rounds, series standings)
+    SeriesFinished(GameOutcome),
+    UniverseUpdate(GenStateDiffPart), // changes to the part of the universe around the viewport
+    UniverseProgress(u8, u8),         // (parts received, total parts) of a universe diff still arriving
+    UniverseHash(UniverseHash),       // server's hash of the game's universe; see `UniverseHash`
+    QueueLengths(usize, usize, usize), // (tx packets awaiting ack, rx packets awaiting processing, chat messages)
+    CorruptUpdates(u64),              // universe updates dropped so far for failing their checksum
 
     // Server Status
     GetStatus(PingPong),
@@ -1280,6 +2088,148 @@ impl NetwaysteEvent {
                     RequestAction::None
                 }
             }
+            NetwaysteEvent::SetTeam(team) => {
+                if is_in_game {
+                    RequestAction::SetTeam { team }
+                } else {
+                    debug!("Command failed: You must be in a room to pick a team");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::TeamChatMessage(msg) => RequestAction::TeamChatMessage { message: msg },
+            NetwaysteEvent::LobbyChatMessage(msg) => RequestAction::LobbyChatMessage { message: msg },
+            NetwaysteEvent::GetChatHistory => {
+                if is_in_game {
+                    RequestAction::GetChatHistory
+                } else {
+                    debug!("Command failed: You must be in a room to get its chat history");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::Typing => {
+                if is_in_game {
+                    RequestAction::Typing
+                } else {
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetTeamCount(team_count) => {
+                if is_in_game {
+                    RequestAction::SetTeamCount { team_count }
+                } else {
+                    debug!("Command failed: You must be in a room to set the team count");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetWinCondition(condition) => {
+                if is_in_game {
+                    RequestAction::SetWinCondition { condition }
+                } else {
+                    debug!("Command failed: You must be in a room to set the win condition");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::StartGame => {
+                if is_in_game {
+                    RequestAction::StartGame
+                } else {
+                    debug!("Command failed: You must be in a room to start a game");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetSeriesLength(rounds) => {
+                if is_in_game {
+                    RequestAction::SetSeriesLength { rounds }
+                } else {
+                    debug!("Command failed: You must be in a room to set the series length");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetGenerationRate(gens_per_sec) => {
+                if is_in_game {
+                    RequestAction::SetGenerationRate { gens_per_sec }
+                } else {
+                    debug!("Command failed: You must be in a room to set the generation rate");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::AddBot => {
+                if is_in_game {
+                    RequestAction::AddBot
+                } else {
+                    debug!("Command failed: You must be in a room to add a bot");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::RemoveBot(name) => {
+                if is_in_game {
+                    RequestAction::RemoveBot { name }
+                } else {
+                    debug!("Command failed: You must be in a room to remove a bot");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetColor(color) => {
+                if is_in_game {
+                    RequestAction::SetColor { color }
+                } else {
+                    debug!("Command failed: You must be in a room to pick a color");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetViewport(viewport) => {
+                if is_in_game {
+                    RequestAction::ClientViewport { viewport }
+                } else {
+                    debug!("Command failed: You must be in a room to report the viewport");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetCells(cells) => {
+                if is_in_game {
+                    RequestAction::SetCells { cells }
+                } else {
+                    debug!("Command failed: You must be in a room to change cells");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::ResyncUniverse => {
+                if is_in_game {
+                    RequestAction::ResyncUniverse
+                } else {
+                    debug!("Command failed: You must be in a room to resync the universe");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::EditRoomMap(edit) => {
+                if is_in_game {
+                    RequestAction::EditRoomMap { edit }
+                } else {
+                    debug!("Command failed: You must be in a room to edit its map");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::GetRoomMap => {
+                if is_in_game {
+                    RequestAction::GetRoomMap
+                } else {
+                    debug!("Command failed: You must be in a room to get its map");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::ShareAnnotation(id, annotation) => {
+                if is_in_game {
+                    RequestAction::ShareAnnotation { id, annotation }
+                } else {
+                    debug!("Command failed: You must be in a room to share an annotation");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::ReportPlayer(player_name, reason) => RequestAction::ReportPlayer { player_name, reason },
+            NetwaysteEvent::GetLeaderboard(room_name) => RequestAction::GetLeaderboard { room_name },
+            NetwaysteEvent::SharePattern(name, rle) => RequestAction::SharePattern { name, rle },
+            NetwaysteEvent::ListSharedPatterns(offset) => RequestAction::ListSharedPatterns { offset },
+            NetwaysteEvent::GetSharedPattern(pattern_id) => RequestAction::GetSharedPattern { pattern_id },
             _ => {
                 panic!(
                     "Unexpected netwayste event during request action construction! {:?}",
@@ -1295,14 +2245,34 @@ impl NetwaysteEvent {
             ResponseCode::LoggedIn {
                 cookie: _,
                 server_version,
-            } => NetwaysteEvent::LoggedIn(server_version),
+                motd,
+            } => NetwaysteEvent::LoggedIn(server_version, motd),
             ResponseCode::JoinedRoom { room_name } => NetwaysteEvent::JoinedRoom(room_name),
+            ResponseCode::JoinedTeam { team } => NetwaysteEvent::JoinedTeam(team),
             ResponseCode::PlayerList { players } => NetwaysteEvent::PlayerList(players),
             ResponseCode::RoomList { rooms } => NetwaysteEvent::RoomList(rooms),
+            ResponseCode::CellsSet { cells, generation } => NetwaysteEvent::CellsSet(cells, generation),
+            ResponseCode::ChatHistory { messages } => NetwaysteEvent::ChatHistory(
+                messages
+                    .into_iter()
+                    .map(|entry| (entry.player_name, entry.message, entry.age_secs, entry.channel))
+                    .collect(),
+            ),
+            ResponseCode::Leaderboard { room_name, entries } => NetwaysteEvent::Leaderboard(room_name, entries),
+            ResponseCode::PatternShared { pattern_id, name } => NetwaysteEvent::PatternShared(pattern_id, name),
+            ResponseCode::SharedPatternList {
+                offset,
+                total,
+                patterns,
+            } => NetwaysteEvent::SharedPatternList(offset, total, patterns),
+            ResponseCode::SharedPattern { info, rle } => NetwaysteEvent::SharedPattern(info, rle),
+            ResponseCode::RoomMap { map } => NetwaysteEvent::RoomMap(map),
             ResponseCode::LeaveRoom => NetwaysteEvent::LeftRoom,
             ResponseCode::BadRequest { error_msg } => NetwaysteEvent::BadRequest(error_msg),
             ResponseCode::ServerError { error_msg } => NetwaysteEvent::ServerError(error_msg),
             ResponseCode::Unauthorized { error_msg } => NetwaysteEvent::BadRequest(error_msg),
+            ResponseCode::TooManyRequests { error_msg } => NetwaysteEvent::BadRequest(error_msg),
+            ResponseCode::NotConnected { error_msg } => NetwaysteEvent::BadRequest(error_msg),
             _ => {
                 panic!(
                     "Unexpected response code during netwayste event construction: {:?}",
@@ -0,0 +1,130 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Keeps the cell colors of the players in a room far enough apart to tell them apart.
+//!
+//! Colors are compared by hue, except for grays (and colors so dark or pale that their hue is
+//! hard to see), which are compared by brightness. A gray never collides with a colorful color.
+
+use netwayste::net::RgbColor;
+
+const MIN_HUE_SEPARATION: f32 = 30.0; // degrees
+const MIN_GRAY_SEPARATION: f32 = 0.2; // difference in value
+const MIN_SATURATION: f32 = 0.2; // below this, a color counts as gray
+const MIN_VALUE: f32 = 0.2; // below this, a color counts as gray (it looks black)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hsv {
+    h: f32, // degrees, [0, 360)
+    s: f32, // [0, 1]
+    v: f32, // [0, 1]
+}
+
+impl Hsv {
+    fn is_gray(&self) -> bool {
+        self.s < MIN_SATURATION || self.v < MIN_VALUE
+    }
+
+    fn collides_with(&self, other: &Hsv) -> bool {
+        match (self.is_gray(), other.is_gray()) {
+            (true, true) => (self.v - other.v).abs() < MIN_GRAY_SEPARATION,
+            (false, false) => hue_distance(self.h, other.h) < MIN_HUE_SEPARATION,
+            _ => false,
+        }
+    }
+}
+
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).abs() % 360.0;
+    d.min(360.0 - d)
+}
+
+fn to_hsv(color: RgbColor) -> Hsv {
+    let (r, g, b) = (color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    Hsv { h, s, v: max }
+}
+
+fn to_rgb(hsv: Hsv) -> RgbColor {
+    let c = hsv.v * hsv.s;
+    let h = hsv.h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = hsv.v - c;
+    let to_byte = |f: f32| ((f + m) * 255.0).round().max(0.0).min(255.0) as u8;
+    RgbColor::new(to_byte(r), to_byte(g), to_byte(b))
+}
+
+/// Returns `requested` if it can be told apart from every color in `taken`. Otherwise returns the
+/// nearest color that can, nudging the hue (or, for a gray, the brightness) a step at a time in
+/// alternating directions. If every step collides with something, `requested` is returned as is.
+pub fn resolve_color(requested: RgbColor, taken: &[RgbColor]) -> RgbColor {
+    let taken: Vec<Hsv> = taken.iter().map(|&color| to_hsv(color)).collect();
+    let wanted = to_hsv(requested);
+    let is_free = |candidate: &Hsv| taken.iter().all(|other| !candidate.collides_with(other));
+    if is_free(&wanted) {
+        return requested;
+    }
+
+    if wanted.is_gray() {
+        let steps = (1.0 / MIN_GRAY_SEPARATION).ceil() as i32;
+        for step in 1..=steps {
+            for &sign in &[1.0, -1.0] {
+                let v = wanted.v + sign * step as f32 * MIN_GRAY_SEPARATION;
+                let candidate = Hsv { v, ..wanted };
+                if v >= 0.0 && v <= 1.0 && is_free(&candidate) {
+                    return to_rgb(candidate);
+                }
+            }
+        }
+    } else {
+        // Half a separation per step, so a color squeezed between two others can still fit
+        let step_degrees = MIN_HUE_SEPARATION / 2.0;
+        let steps = (180.0 / step_degrees) as i32;
+        for step in 1..=steps {
+            for &sign in &[1.0, -1.0] {
+                let h = (wanted.h + sign * step as f32 * step_degrees).rem_euclid(360.0);
+                let candidate = Hsv { h, ..wanted };
+                if is_free(&candidate) {
+                    return to_rgb(candidate);
+                }
+            }
+        }
+    }
+    requested
+}
@@ -0,0 +1,70 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts packets from each address over one-second windows, and says when an address has sent
+/// more than its share.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    max_per_window: Option<u32>,                     // None means no limit
+    windows:        HashMap<IpAddr, (Instant, u32)>, // (start of window, packets in window)
+}
+
+impl RateLimiter {
+    pub fn new(max_per_sec: Option<u32>) -> Self {
+        RateLimiter {
+            max_per_window: max_per_sec,
+            windows:        HashMap::new(),
+        }
+    }
+
+    /// Changes the limit. Counts in the current windows carry over.
+    pub fn set_limit(&mut self, max_per_sec: Option<u32>) {
+        self.max_per_window = max_per_sec;
+        if max_per_sec.is_none() {
+            self.windows.clear();
+        }
+    }
+
+    /// Records a packet from `addr` arriving at `now`. Returns false if it should be dropped.
+    pub fn allow(&mut self, addr: IpAddr, now: Instant) -> bool {
+        let max = match self.max_per_window {
+            Some(max) => max,
+            None => return true,
+        };
+        let (start, count) = self.windows.entry(addr).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= max
+    }
+
+    /// Forgets addresses that haven't sent anything during the last window.
+    pub fn forget_idle(&mut self, now: Instant) {
+        self.windows
+            .retain(|_, (start, _)| now.duration_since(*start) < WINDOW * 2);
+    }
+}
@@ -20,52 +20,110 @@
 #[macro_use]
 extern crate log;
 
+mod congestion;
+mod leaderboard;
+mod mtu;
 #[macro_use]
 mod net;
+mod player_color;
+mod rate_limit;
+mod server_config;
+mod shared_patterns;
+mod snapshot;
 mod utils;
+mod ws_bridge;
 
 #[cfg(test)]
 #[macro_use]
 extern crate proptest;
 
+use congestion::CongestionControl;
+use leaderboard::{Contender, GameResult, Leaderboard};
+use mtu::PathMtu;
 use netwayste::net::{
-    bind, get_version, has_connection_timed_out, BroadcastChatMessage, NetwaystePacketCodec, NetworkManager,
-    NetworkQueue, Packet, RequestAction, ResponseCode, RoomList, UniUpdate, DEFAULT_HOST, DEFAULT_PORT, VERSION,
+    bind, get_version, has_connection_timed_out, Annotation, BroadcastChatMessage, CellInput, ChatChannel,
+    ChatHistoryEntry, GameOptions, GameOutcome, GameUpdate, GenPartInfo, GenStateDiffPart, MapEdit, NetRegion,
+    NetwaystePacketCodec, NetworkManager, NetworkQueue, Packet, PlayerInfo, PlayerScore, RequestAction, ResponseCode,
+    RgbColor, RoomList, RoomMap, RoomSettings, SendQueue, UniUpdate, UniverseHash, WinCondition, DEFAULT_HOST,
+    DEFAULT_PORT, MAX_ANNOTATION_LABEL_LEN, MAX_CHAT_HISTORY_LEN, MAX_DIFF_PARTS, MAX_FOG_FREE_REGIONS,
+    MAX_ROOM_MAP_LEN, MAX_SET_CELLS, MAX_SPAWN_REGIONS, MAX_WALL_EDIT_CELLS, SHARED_PATTERN_PAGE_LEN,
+    TYPING_NOTICE_INTERVAL_MS, VERSION,
 };
 use netwayste::utils::{LatencyFilter, PingPong};
+use rate_limit::RateLimiter;
+use server_config::{RoomDefaults, ServerConfig, DEFAULT_CONFIG_PATH};
+use shared_patterns::SharedPatterns;
+use snapshot::SnapshotStore;
+use ws_bridge::WsBridge;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt;
 use std::io::{self, ErrorKind, Write};
-use std::net::SocketAddr;
+use std::mem;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::Arc;
 use std::time::{self, Duration, Instant};
 
 use chrono::Local;
 use clap::{App, Arg};
+use conway::bot::Bot;
+use conway::grids::{BitGrid, BitOperation, CharGrid};
+use conway::rle::Pattern;
+use conway::universe::{BigBang, CellState, PlayerBuilder, Region, Universe};
 use futures as Fut;
 use log::LevelFilter;
 use rand::RngCore;
 use reqwest;
 use semver::Version;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tokio::time as TokioTime;
-use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::wrappers::{IntervalStream, UnboundedReceiverStream};
 use tokio_util::udp::UdpFramed;
 use Fut::prelude::*;
 use Fut::select;
 
 pub const TICK_INTERVAL_IN_MS: u64 = 10;
 pub const NETWORK_INTERVAL_IN_MS: u64 = 100; // Arbitrarily chosen
+pub const MAX_PACKETS_PER_TICK: usize = 64; // the rest wait in the send queue, most urgent first
 pub const HEARTBEAT_INTERVAL_IN_MS: u64 = 1000; // Arbitrarily chosen
 pub const REGISTER_INTERVAL_IN_MS: u64 = 10_000_000;
 pub const REGISTER_RETRIES: usize = 3;
 pub const REGISTER_RETRY_SLEEP: Duration = Duration::from_millis(5000);
 pub const REGISTRY_DEFAULT_URL: &str = "https://registry.conwayste.rs/addServer";
 pub const MAX_ROOM_NAME: usize = 16;
+pub const DEFAULT_MAX_ROOMS: usize = 64;
 pub const MAX_NUM_CHAT_MESSAGES: usize = 128;
 pub const MAX_AGE_CHAT_MESSAGES: usize = 60 * 5; // seconds
+pub const CHAT_HISTORY_LEN: usize = 20; // messages kept for players who join later, however old
+pub const MAX_TEAMS: u8 = 4;
+pub const MAX_NUM_GAME_UPDATES: usize = 64;
+pub const DEFAULT_GENERATIONS_PER_SEC: u8 = 10;
+pub const MAX_GENERATIONS_PER_SEC: u8 = 100;
+pub const MAX_GENERATIONS_PER_TICK: usize = 4; // a room that falls further behind than this skips ahead
+pub const SCORE_UPDATE_INTERVAL_IN_GENS: usize = 50;
+pub const ELIMINATION_GRACE_GENS: usize = 100; // Nobody can be eliminated before they've had a chance to draw
+pub const DEFAULT_MATCH_GENERATIONS: u32 = 1000;
+pub const MAX_SERIES_ROUNDS: u8 = 9;
+pub const INTERMISSION_GENS: usize = 50; // Pause between rounds of a series, in generation ticks
+pub const GAME_WIDTH: u32 = 256;
+pub const GAME_HEIGHT: u32 = 128;
+pub const GAME_HISTORY: u16 = 16;
+pub const GAME_FOG_RADIUS: u32 = 6;
+pub const INTEREST_CHUNK_SIZE: isize = 32; // clients get universe diffs for whole chunks of this many cells square
+pub const INTEREST_MARGIN: isize = 16; // cells around the viewport to also send, so panning doesn't show blanks
+pub const DIFF_PARTS_PER_UPDATE: usize = 8; // most universe diff parts sent to a client per network tick
+pub const MAX_BOTS_PER_ROOM: usize = 8;
+pub const BOT_TURN_INTERVAL_IN_GENS: usize = 40;
+pub const LEADERBOARD_LEN: usize = 10; // players sent per leaderboard
+pub const ANNOTATION_SHARE_WINDOW: Duration = Duration::from_secs(10);
+pub const MAX_ANNOTATION_SHARES_PER_WINDOW: u32 = 64; // enough to share a whole board of annotations at once
+pub const PATTERN_SHARE_WINDOW: Duration = Duration::from_secs(60);
+pub const MAX_PATTERN_SHARES_PER_WINDOW: u32 = 5;
 pub const SERVER_ID: PlayerID = PlayerID(u64::max_value()); // 0xFFFF....FFFF
 pub const DEFAULT_NAME: &str = "Leto II";
 
@@ -98,14 +156,37 @@ pub struct Player {
     pub game_info:      Option<PlayerInGameInfo>, // none means in lobby
     pub last_received:  time::Instant, // Time of last message received from player
     pub latency_filter: LatencyFilter, // Latency information
+    pub path_mtu:       PathMtu, // Largest packet that gets through to the player
+    pub lobby_chat_seq: Option<u64>, // Lobby chat messages the player has confirmed, like `chat_msg_seq_num`
+    pub pattern_shares: Option<(Instant, u32)>, // (start of window, patterns shared in it), for throttling
 }
 
 // info for a player as it relates to a game/room
 #[derive(PartialEq, Debug, Clone)]
 pub struct PlayerInGameInfo {
-    room_id:          RoomID,
-    chat_msg_seq_num: Option<u64>, // Server has confirmed the client has received messages up to this value.
-                                   // TODO: add support
+    room_id:             RoomID,
+    team:                Option<u8>, // None if the room has no teams or the player has not picked one
+    color:               Option<RgbColor>, // None until the player picks one
+    game_update_seq_num: Option<u64>, // Client has received game updates up to this value.
+    universe_index:      Option<usize>, // Player's index in the running game's universe; None if only watching
+    interest:            Option<Region>, // Part of the universe sent to the client; None until it reports its viewport
+    full_gen:            Option<u64>, // Client has the universe (within `interest`) up to this generation.
+    partial_gen:         Option<GenPartInfo>, // Diff the client has some of the parts of, if any
+    universe_epoch:      u32, // Goes up each time the client is started over with the whole universe
+    congestion:          CongestionControl, // Paces universe updates to what the client's link can take
+    chat_msg_seq_num:    Option<u64>, // Server has confirmed the client has received messages up to this value.
+    typing_at:           Option<Instant>, // when the player's typing was last passed on to the room
+    annotation_shares:   Option<(Instant, u32)>, // (start of window, annotations shared in it), for throttling
+}
+
+impl PlayerInGameInfo {
+    /// Forgets what the client has of the universe, so that it is sent all of it again, in a new
+    /// epoch so that acknowledgements already on their way are ignored.
+    fn start_universe_over(&mut self) {
+        self.full_gen = None;
+        self.partial_gen = None;
+        self.universe_epoch = self.universe_epoch.wrapping_add(1);
+    }
 }
 
 impl Player {
@@ -129,6 +210,50 @@ impl Player {
         }
     }
 
+    // Same as update_chat_seq_num, but for the lobby chat, which players get wherever they are.
+    pub fn update_lobby_chat_seq(&mut self, opt_lobby_chat_seq: Option<u64>) {
+        if self.lobby_chat_seq < opt_lobby_chat_seq {
+            self.lobby_chat_seq = opt_lobby_chat_seq;
+        }
+    }
+
+    // Same as update_chat_seq_num, but for game updates.
+    pub fn update_game_update_seq_num(&mut self, opt_game_update_seq_num: Option<u64>) {
+        if let Some(game_info) = self.game_info.as_mut() {
+            if game_info.game_update_seq_num < opt_game_update_seq_num {
+                game_info.game_update_seq_num = opt_game_update_seq_num;
+            }
+        }
+    }
+
+    // Same as update_chat_seq_num, but for the generation of the universe the client has. What the
+    // client says about diffs from an earlier epoch is about what it had before it was started over.
+    pub fn update_full_gen(&mut self, epoch: u32, opt_full_gen: Option<u64>) {
+        if let Some(game_info) = self.game_info.as_mut().filter(|game_info| game_info.universe_epoch == epoch) {
+            if game_info.full_gen < opt_full_gen {
+                game_info.full_gen = opt_full_gen;
+            }
+            if let Some(full_gen) = opt_full_gen {
+                game_info.congestion.on_acked(full_gen);
+            }
+        }
+    }
+
+    // Record which parts of a diff the client has, ignoring nonsense that would make us panic.
+    pub fn update_partial_gen(&mut self, epoch: u32, opt_partial_gen: Option<GenPartInfo>) {
+        if let Some(game_info) = self.game_info.as_mut().filter(|game_info| game_info.universe_epoch == epoch) {
+            game_info.partial_gen = opt_partial_gen.filter(|partial| partial.gen0 < partial.gen1);
+        }
+    }
+
+    // Returns Some(N), where N is the last game update the player has notified the Server it got,
+    // or None if it hasn't acknowledged any.
+    pub fn get_confirmed_game_update_seq_num(&self) -> Option<u64> {
+        self.game_info
+            .as_ref()
+            .and_then(|game_info| game_info.game_update_seq_num)
+    }
+
     // If the player has chatted, we'll return Some(N),
     // where N is the last chat message the player has
     // notified the Server it got.
@@ -165,28 +290,138 @@ pub struct ServerChatMessage {
     pub player_name: String,
     pub message:     String,
     pub timestamp:   Instant,
+    pub team:        Option<u8>, // Some(<team>) if only members of that team may see this message
 }
 
-#[derive(Clone, PartialEq)]
-pub struct Room {
-    pub room_id:        RoomID,
+/// One side of a game: a single player in a free-for-all, or a whole team. Scores are kept per side.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Side {
     pub name:           String,
-    pub player_ids:     Vec<PlayerID>,
-    pub game_running:   bool,
-    pub universe:       u64, // Temp until we integrate
-    pub latest_seq_num: u64,
-    pub messages:       VecDeque<ServerChatMessage>, // Front == Oldest, Back == Newest
+    pub player_indices: Vec<usize>, // indices of this side's players in the universe
+}
+
+/// A change a player asked for to one of the cells in their writable region. These are made just
+/// after the room's universe advances, so that no client has yet been sent the generation they
+/// change.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CellChange {
+    pub player_index: usize, // player's index in the universe
+    pub col:          usize,
+    pub row:          usize,
+    pub alive:        bool,
+}
+
+/// A computer-controlled player seated in a room.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RoomBot {
+    pub name: String,
+    pub team: Option<u8>,
+}
+
+/// One player's report of another, as written to the report log for moderators.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct PlayerReport {
+    pub time:            String, // when the report was received, in RFC 3339 format
+    pub reporter:        String,
+    pub reported:        String,
+    pub room:            Option<String>, // the reporter's room, if they were in one
+    pub reason:          String,
+    pub recent_messages: Vec<String>, // what the reported player said lately that the reporter could see
+}
+
+/// Progress of a multi-round match series.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Series {
+    pub round:              u8, // 1-based round currently being played, or just finished
+    pub standings:          Vec<PlayerScore>, // cumulative scores across finished rounds, best first
+    pub next_round_in_gens: Option<usize>, // Some(<countdown>) during the intermission
+    /// (name, team) of the players, not counting bots, who played the first round. Only they play
+    /// the rounds after it, on the same teams; anyone who joined the room since only watches.
+    #[serde(default)] // not in snapshots from older servers
+    pub roster:             Vec<(String, Option<u8>)>,
+}
+
+impl Default for Series {
+    fn default() -> Self {
+        Series {
+            round:              1,
+            standings:          vec![],
+            next_round_in_gens: None,
+            roster:             vec![],
+        }
+    }
+}
+
+impl Series {
+    /// Adds the scores of a finished round to the running totals.
+    pub fn add_round(&mut self, round_standings: &[PlayerScore]) {
+        for round_score in round_standings {
+            match self.standings.iter_mut().find(|total| total.name == round_score.name) {
+                Some(total) => total.score += round_score.score,
+                None => self.standings.push(round_score.clone()),
+            }
+        }
+        self.standings.sort_by(|a, b| b.score.cmp(&a.score));
+    }
+}
+
+pub struct Room {
+    pub room_id:                RoomID,
+    pub name:                   String,
+    pub player_ids:             Vec<PlayerID>,
+    pub bots:                   Vec<RoomBot>,
+    pub game_running:           bool,
+    pub team_count:             u8, // zero means free-for-all
+    pub win_condition:          WinCondition,
+    pub universe:               Option<Universe>, // Some while a game is running
+    pub universe_hash:          Option<UniverseHash>, // sent to clients to check they are in lockstep
+    pub game_options:           Option<GameOptions>, // Some while a game is running
+    pub sides:                  Vec<Side>,        // sides of the current or most recent game
+    pub active_bots:            Vec<Bot>,         // bots playing in the current game
+    pub contenders:             Vec<Contender>,   // players of the current game, by universe index
+    pub cell_changes:           Vec<CellChange>,  // made by players since the universe last advanced
+    pub share_cell_inputs:      bool,             // if true, players are sent every cell change, for lockstep checks
+    pub walls:                  BitGrid,          // set up by the host for the next game, GAME_WIDTH by GAME_HEIGHT
+    pub spawn_regions:          Vec<Option<NetRegion>>, // set up by the host; see RoomMap
+    pub fog_free_regions:       Vec<Option<NetRegion>>, // set up by the host; see RoomMap
+    pub series_length:          u8,               // number of rounds per series; 1 means single games
+    pub series:                 Option<Series>,   // Some while a series is being played
+    pub generations_per_sec:    u8,               // set by the host; independent of the server's tick rate
+    pub generation_clock_ms:    u64,              // time since the universe last advanced
+    pub latest_seq_num:         u64,
+    pub messages:               VecDeque<ServerChatMessage>, // Front == Oldest, Back == Newest
+    pub history:                VecDeque<ServerChatMessage>, // the last CHAT_HISTORY_LEN messages; Front == Oldest
+    pub latest_game_update_seq: u64,
+    pub game_updates:           VecDeque<(u64, GameUpdate)>, // (sequence number, update); Front == Oldest
 }
 
 pub struct ServerState {
-    pub tick:        usize,
-    pub name:        String,
-    pub reg_params:  Option<RegistryParams>,
-    pub players:     HashMap<PlayerID, Player>,
-    pub player_map:  HashMap<String, PlayerID>, // map cookie to player ID
-    pub rooms:       HashMap<RoomID, Room>,
-    pub room_map:    HashMap<String, RoomID>, // map room name to room ID
-    pub network_map: HashMap<PlayerID, NetworkManager>, // map Player ID to Player's network data
+    pub tick:             usize,
+    pub name:             String,
+    pub reg_params:       Option<RegistryParams>,
+    pub players:          HashMap<PlayerID, Player>,
+    pub player_map:       HashMap<String, PlayerID>, // map cookie to player ID
+    pub rooms:            HashMap<RoomID, Room>,
+    pub lobby:            Room, // only its chat is used; it isn't listed, and has no players
+    pub room_map:         HashMap<String, RoomID>, // map room name to room ID
+    pub network_map:      HashMap<PlayerID, NetworkManager>, // map Player ID to Player's network data
+    pub max_rooms:        usize,
+    pub motd:             Option<String>, // message of the day, sent to players as they log in
+    pub admin_password:   Option<String>,
+    pub banned_addresses: HashSet<IpAddr>,
+    pub rate_limiter:     RateLimiter,
+    pub room_defaults:    RoomDefaults,
+    pub reload_requested: bool, // set by an admin's ReloadConfig request; the main loop does the reload
+    pub report_log:       Option<String>, // file that players' reports are appended to, one JSON object per line
+    pub ms_per_tick:      u64,  // time that passes in the rooms' games on each tick
+    pub leaderboard:      Leaderboard,
+    pub leaderboard_file: Option<String>, // saved to after every finished game; None means kept in memory only
+    pub save_leaderboard: bool, // set when a game finishes, for the main loop to save the leaderboards
+    pub shared_patterns:  SharedPatterns,
+    pub patterns_file:    Option<String>, // shared patterns are saved to it after every share
+    pub save_patterns:    bool,           // set when a pattern is shared, for the main loop to save them
+    pub max_patterns:     usize,          // shared patterns kept, dropping the oldest; zero turns sharing off
+    pub probe_path_mtu:   bool,           // false if probes could be fragmented, so packets stay the default size
 }
 
 #[derive(Debug, Clone)]
@@ -247,6 +482,22 @@ pub fn validate_client_version(client_version: String) -> bool {
     server_version >= Version::parse(&client_version)
 }
 
+/// A grid with no walls on it, the size of the rooms' universes.
+fn new_wall_grid() -> BitGrid {
+    BitGrid::new((GAME_WIDTH as usize - 1) / 64 + 1, GAME_HEIGHT as usize)
+}
+
+/// Sets the region in `slot` of a map's spawn or fog-free regions, dropping trailing empty slots.
+fn set_map_region(regions: &mut Vec<Option<NetRegion>>, slot: usize, region: Option<NetRegion>) {
+    if regions.len() <= slot {
+        regions.resize(slot + 1, None);
+    }
+    regions[slot] = region;
+    while regions.last() == Some(&None) {
+        regions.pop();
+    }
+}
+
 impl ServerChatMessage {
     pub fn new(id: PlayerID, name: String, msg: String, seq_num: u64) -> Self {
         ServerChatMessage {
@@ -255,6 +506,21 @@ impl ServerChatMessage {
             message:     msg,
             seq_num:     seq_num,
             timestamp:   time::Instant::now(),
+            team:        None,
+        }
+    }
+
+    /// Restrict this message to the members of `team`.
+    pub fn to_team(mut self, team: u8) -> Self {
+        self.team = Some(team);
+        self
+    }
+
+    /// Team, if it was restricted to one, or else Room.
+    pub fn channel(&self) -> ChatChannel {
+        match self.team {
+            Some(_) => ChatChannel::Team,
+            None => ChatChannel::Room,
         }
     }
 }
@@ -264,13 +530,35 @@ impl Room {
     /// the players (via `player_ids`) immediately to it.
     pub fn new(name: String, player_ids: Vec<PlayerID>) -> Self {
         Room {
-            room_id:        RoomID(new_uuid()),
-            name:           name,
-            player_ids:     player_ids,
-            game_running:   false,
-            universe:       0,
-            messages:       VecDeque::<ServerChatMessage>::with_capacity(MAX_NUM_CHAT_MESSAGES),
-            latest_seq_num: 0,
+            room_id:                RoomID(new_uuid()),
+            name:                   name,
+            player_ids:             player_ids,
+            bots:                   vec![],
+            game_running:           false,
+            team_count:             0,
+            win_condition:          WinCondition::MostLiveCells {
+                generations: DEFAULT_MATCH_GENERATIONS,
+            },
+            universe:               None,
+            universe_hash:          None,
+            game_options:           None,
+            sides:                  vec![],
+            active_bots:            vec![],
+            contenders:             vec![],
+            cell_changes:           vec![],
+            share_cell_inputs:      false,
+            walls:                  new_wall_grid(),
+            spawn_regions:          vec![],
+            fog_free_regions:       vec![],
+            series_length:          1,
+            series:                 None,
+            generations_per_sec:    DEFAULT_GENERATIONS_PER_SEC,
+            generation_clock_ms:    0,
+            messages:               VecDeque::<ServerChatMessage>::with_capacity(MAX_NUM_CHAT_MESSAGES),
+            history:                VecDeque::with_capacity(CHAT_HISTORY_LEN),
+            latest_seq_num:         0,
+            latest_game_update_seq: 0,
+            game_updates:           VecDeque::with_capacity(MAX_NUM_GAME_UPDATES),
         }
     }
 
@@ -285,6 +573,35 @@ impl Room {
         }
     }
 
+    /// The host is the player who has been in the room the longest.
+    pub fn is_host(&self, player_id: PlayerID) -> bool {
+        self.player_ids.first() == Some(&player_id)
+    }
+
+    /// The walls, spawn regions and fog-free regions the host has set up for the next game.
+    pub fn room_map(&self) -> RoomMap {
+        RoomMap {
+            walls:            self.walls.to_pattern(None).0,
+            spawn_regions:    self.spawn_regions.clone(),
+            fog_free_regions: self.fog_free_regions.clone(),
+        }
+    }
+
+    /// Replaces the room's map, such as with one saved in a snapshot.
+    pub fn set_room_map(&mut self, map: RoomMap) -> Result<(), Box<dyn Error>> {
+        let pattern = Pattern(map.walls);
+        let (width, height) = pattern.calc_size()?;
+        if width > GAME_WIDTH as usize || height > GAME_HEIGHT as usize {
+            return Err(format!("the walls are {}x{}, larger than the universe", width, height).into());
+        }
+        self.walls = pattern.to_new_bit_grid(GAME_WIDTH as usize, GAME_HEIGHT as usize)?;
+        self.spawn_regions = map.spawn_regions;
+        self.spawn_regions.truncate(MAX_SPAWN_REGIONS as usize);
+        self.fog_free_regions = map.fog_free_regions;
+        self.fog_free_regions.truncate(MAX_FOG_FREE_REGIONS as usize);
+        Ok(())
+    }
+
     pub fn has_players(&mut self) -> bool {
         !self.player_ids.is_empty()
     }
@@ -295,8 +612,13 @@ impl Room {
         self.latest_seq_num
     }
 
-    /// Adds a new message to the room message queue
+    /// Adds a new message to the room message queue, and to the history replayed to players who
+    /// join later
     pub fn add_message(&mut self, new_message: ServerChatMessage) {
+        if self.history.len() >= CHAT_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(new_message.clone());
         self.messages.push_back(new_message);
     }
 
@@ -345,6 +667,373 @@ impl Room {
         let seq_num = self.increment_seq_num();
         self.add_message(ServerChatMessage::new(SERVER_ID, "Server".to_owned(), event, seq_num));
     }
+
+    /// Who plays in the running game: each contender, with their index in the universe and, in a
+    /// team game, their team.
+    pub fn game_players(&self) -> Vec<PlayerInfo> {
+        self.contenders
+            .iter()
+            .enumerate()
+            .map(|(i, contender)| {
+                let team = if self.team_count > 0 {
+                    self.sides
+                        .iter()
+                        .position(|side| side.player_indices.contains(&i))
+                        .map(|team| team as u8)
+                } else {
+                    None
+                };
+                PlayerInfo::new(contender.name.clone(), Some(i as u64), team)
+            })
+            .collect()
+    }
+
+    /// Where the player named `name` plays in the running game, as their index in the universe and,
+    /// in a team game, their team. None if they aren't one of its contenders.
+    pub fn seat_of(&self, name: &str) -> Option<(usize, Option<u8>)> {
+        if !self.game_running {
+            return None;
+        }
+        let index = self
+            .contenders
+            .iter()
+            .position(|contender| !contender.is_bot && contender.name == name)?;
+        let team = if self.team_count > 0 {
+            self.sides
+                .iter()
+                .position(|side| side.player_indices.contains(&index))
+                .map(|team| team as u8)
+        } else {
+            None
+        };
+        Some((index, team))
+    }
+
+    /// Queues a game update for all players in the room. Only the newest `MAX_NUM_GAME_UPDATES`
+    /// are kept.
+    pub fn add_game_update(&mut self, update: GameUpdate) {
+        while self.game_updates.len() >= MAX_NUM_GAME_UPDATES {
+            self.game_updates.pop_front();
+        }
+        self.latest_game_update_seq += 1;
+        self.game_updates.push_back((self.latest_game_update_seq, update));
+    }
+
+    /// Returns the game updates newer than `opt_acked_seq`, along with the sequence number of the
+    /// first of them.
+    pub fn collect_unacknowledged_game_updates(&self, opt_acked_seq: Option<u64>) -> (Option<u64>, Vec<GameUpdate>) {
+        let mut first_seq = None;
+        let mut updates = vec![];
+        for (seq, update) in self.game_updates.iter() {
+            if opt_acked_seq.map_or(true, |acked_seq| *seq > acked_seq) {
+                first_seq = first_seq.or(Some(*seq));
+                updates.push(update.clone());
+            }
+        }
+        (first_seq, updates)
+    }
+
+    /// Runs the room's clock forward by `elapsed_ms`, and returns how many generations are due.
+    pub fn generations_due(&mut self, elapsed_ms: u64) -> usize {
+        let interval_ms = 1000 / self.generations_per_sec.max(1) as u64;
+        self.generation_clock_ms += elapsed_ms;
+        let due = (self.generation_clock_ms / interval_ms) as usize;
+        self.generation_clock_ms %= interval_ms;
+        due.min(MAX_GENERATIONS_PER_TICK)
+    }
+
+    /// Counts down the intermission between rounds of a series, once per generation. Returns true
+    /// when the next round should start.
+    pub fn advance_intermission(&mut self) -> bool {
+        if let Some(series) = self.series.as_mut() {
+            if let Some(gens_left) = series.next_round_in_gens {
+                if gens_left > 1 {
+                    series.next_round_in_gens = Some(gens_left - 1);
+                } else {
+                    series.next_round_in_gens = None;
+                    series.round += 1;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// The names of the players on a side of the current or most recent game.
+    fn side_player_names(&self, side: usize) -> Vec<String> {
+        self.sides[side]
+            .player_indices
+            .iter()
+            .filter_map(|&i| self.contenders.get(i))
+            .map(|contender| contender.name.clone())
+            .collect()
+    }
+
+    /// Steps the running game forward one generation. Queues a score update, and updates the universe
+    /// hash, every `SCORE_UPDATE_INTERVAL_IN_GENS` generations, and the outcome once the win condition
+    /// is met. Returns how each player did if that ended the game.
+    pub fn advance_game(&mut self) -> Option<Vec<GameResult>> {
+        let uni = match self.universe.as_mut() {
+            Some(uni) if self.game_running => uni,
+            _ => return None,
+        };
+        uni.next();
+        let mut inputs = vec![];
+        for change in self.cell_changes.drain(..) {
+            let state = if change.alive {
+                CellState::Alive(Some(change.player_index))
+            } else {
+                CellState::Dead
+            };
+            uni.set(change.col, change.row, state, change.player_index);
+            inputs.push(CellInput {
+                player_index: change.player_index as u64,
+                col:          change.col as u32,
+                row:          change.row as u32,
+                alive:        change.alive,
+            });
+        }
+        for bot in self.active_bots.iter_mut() {
+            if let Some(bot_move) = bot.on_generation(uni) {
+                inputs.extend(bot_move.cells.iter().map(|&(col, row)| CellInput {
+                    player_index: bot.player_id() as u64,
+                    col:          col as u32,
+                    row:          row as u32,
+                    alive:        true,
+                }));
+            }
+        }
+        let generation = uni.latest_gen();
+        if self.share_cell_inputs && !inputs.is_empty() {
+            self.add_game_update(GameUpdate::CellInputs {
+                generation: generation as u32,
+                inputs,
+            });
+        }
+        let uni = self.universe.as_ref().unwrap(); // unwrap OK because checked above
+        let cell_counts = uni.player_cell_counts(None);
+        for (contender, &count) in self.contenders.iter_mut().zip(&cell_counts) {
+            if count > 0 {
+                contender.last_alive_gen = generation;
+            }
+        }
+        let scores = tally_scores(&self.win_condition, uni, &self.sides);
+        let standings = standings(&self.sides, &scores);
+
+        if let Some(opt_winner) = decide_winner(&self.win_condition, generation, &scores) {
+            let winners = opt_winner.map_or(&[][..], |side| self.sides[side].player_indices.as_slice());
+            let results = self
+                .contenders
+                .iter()
+                .zip(&cell_counts)
+                .enumerate()
+                .filter(|(_, (contender, _))| !contender.is_bot)
+                .map(|(i, (contender, &count))| GameResult {
+                    name:           contender.name.clone(),
+                    won:            winners.contains(&i),
+                    cells_captured: count as u64,
+                    survival:       contender.last_alive_gen as u64,
+                })
+                .collect();
+            let winner = opt_winner.map(|side| self.sides[side].name.clone());
+            match winner {
+                Some(ref name) => self.broadcast(format!("{} has won the game!", name)),
+                None => self.broadcast("The game ended in a tie.".to_owned()),
+            }
+            let winning_players = opt_winner.map_or(vec![], |side| self.side_player_names(side));
+            self.add_game_update(GameUpdate::GameFinish {
+                outcome: GameOutcome {
+                    winner,
+                    standings: standings.clone(),
+                    winning_players,
+                },
+            });
+            self.game_running = false;
+            self.universe = None;
+            self.universe_hash = None;
+            self.game_options = None;
+            self.active_bots.clear();
+            self.cell_changes.clear();
+
+            if let Some(mut series) = self.series.take() {
+                series.add_round(&standings);
+                if series.round < self.series_length {
+                    self.add_game_update(GameUpdate::Intermission {
+                        round:     series.round,
+                        rounds:    self.series_length,
+                        standings: series.standings.clone(),
+                    });
+                    series.next_round_in_gens = Some(INTERMISSION_GENS);
+                    self.series = Some(series);
+                } else {
+                    let winner = series_winner(&series.standings);
+                    match winner {
+                        Some(ref name) => self.broadcast(format!("{} has won the series!", name)),
+                        None => self.broadcast("The series ended in a tie.".to_owned()),
+                    }
+                    let winning_players = winner
+                        .as_ref()
+                        .and_then(|name| self.sides.iter().position(|side| side.name == *name))
+                        .map_or(vec![], |side| self.side_player_names(side));
+                    self.add_game_update(GameUpdate::SeriesFinish {
+                        outcome: GameOutcome {
+                            winner,
+                            standings: series.standings,
+                            winning_players,
+                        },
+                    });
+                }
+            }
+            return Some(results);
+        } else if generation % SCORE_UPDATE_INTERVAL_IN_GENS == 0 {
+            // The score update makes sure an update goes out to carry this.
+            self.universe_hash = Some(UniverseHash {
+                generation: generation as u32,
+                hash:       uni.state_hash(),
+            });
+            self.add_game_update(GameUpdate::ScoreUpdate {
+                generation: generation as u32,
+                scores:     standings,
+            });
+        }
+        None
+    }
+
+    /// The parts of a diff with the changes to the universe that a player in this room hasn't
+    /// confirmed having, limited to the chunks they are looking at. A diff the player already has
+    /// some of the parts of is carried on with, as long as both of its generations are still in
+    /// the universe's history. Empty if no game is running, the player hasn't said what they are
+    /// looking at, or they are up to date. Parts carry up to `part_len` bytes of pattern.
+    pub fn universe_parts_for(&self, game_info: &PlayerInGameInfo, part_len: usize) -> Vec<GenStateDiffPart> {
+        let (uni, interest) = match (self.universe.as_ref(), game_info.interest) {
+            (Some(uni), Some(interest)) if self.game_running => (uni, interest),
+            _ => return vec![],
+        };
+        let latest_gen = uni.latest_gen();
+        let visibility = game_info.universe_index;
+        let partial = match game_info.partial_gen {
+            Some(ref partial)
+                if (partial.gen0 == 0 || Some(partial.gen0 as u64) == game_info.full_gen)
+                    && Some(partial.gen1 as u64) > game_info.full_gen =>
+            {
+                uni.diff_in_region(partial.gen0 as usize, partial.gen1 as usize, visibility, interest)
+                    .map(|diff| (diff, partial.have_bitmask))
+            }
+            _ => None,
+        };
+        let opt_diff = partial.or_else(|| {
+            let diff = match game_info.full_gen {
+                Some(full_gen) if full_gen as usize >= latest_gen => return None,
+                // Falls back to everything if full_gen has gone out of the universe's history
+                Some(full_gen) if full_gen > 0 => uni
+                    .diff_in_region(full_gen as usize, latest_gen, visibility, interest)
+                    .or_else(|| uni.diff_in_region(0, latest_gen, visibility, interest)),
+                _ => uni.diff_in_region(0, latest_gen, visibility, interest),
+            };
+            diff.map(|diff| (diff, 0))
+        });
+        let (diff, have_bitmask) = match opt_diff {
+            Some(diff_and_bitmask) => diff_and_bitmask,
+            None => return vec![],
+        };
+        GenStateDiffPart::split_into(diff.gen0 as u32, diff.gen1 as u32, &diff.pattern.0, part_len)
+            .into_iter()
+            .filter(|part| part.part_number as usize >= MAX_DIFF_PARTS || have_bitmask & (1 << part.part_number) == 0)
+            .take(DIFF_PARTS_PER_UPDATE)
+            .collect()
+    }
+}
+
+/// The part of the universe to send to a client with this viewport: the viewport plus
+/// INTEREST_MARGIN cells on each side, grown to whole chunks and cut down to the universe. None if
+/// that misses the universe entirely.
+pub fn interest_region(viewport: Region, universe: Region) -> Option<Region> {
+    let chunk_start = |coord: isize| (coord - INTEREST_MARGIN).div_euclid(INTEREST_CHUNK_SIZE) * INTEREST_CHUNK_SIZE;
+    let chunk_end = |coord: isize| {
+        ((coord + INTEREST_MARGIN).div_euclid(INTEREST_CHUNK_SIZE) + 1) * INTEREST_CHUNK_SIZE
+        // exclusive
+    };
+    let left = chunk_start(viewport.left());
+    let top = chunk_start(viewport.top());
+    let width = chunk_end(viewport.right()) - left;
+    let height = chunk_end(viewport.bottom()) - top;
+    Region::new(left, top, width as usize, height as usize).intersection(universe)
+}
+
+/// The leader of the series standings, or None if tied for first.
+fn series_winner(standings: &[PlayerScore]) -> Option<String> {
+    match standings {
+        [first, second, ..] if first.score == second.score => None,
+        [first, ..] => Some(first.name.clone()),
+        [] => None,
+    }
+}
+
+/// Tallies the score of each side under `condition`: the number of live cells its players own,
+/// counting only those inside the contested region for territory control.
+pub fn tally_scores(condition: &WinCondition, uni: &Universe, sides: &[Side]) -> Vec<u64> {
+    let opt_region = match condition {
+        WinCondition::TerritoryControl { region, .. } => Some(Region::from(region)),
+        _ => None,
+    };
+    let counts = uni.player_cell_counts(opt_region);
+    sides
+        .iter()
+        .map(|side| side.player_indices.iter().map(|&i| counts[i] as u64).sum())
+        .collect()
+}
+
+/// Decides whether a game is over at `generation`. Returns None while it is still going, and
+/// otherwise Some(<index of the winning side>), or Some(None) for a tie.
+pub fn decide_winner(condition: &WinCondition, generation: usize, scores: &[u64]) -> Option<Option<usize>> {
+    match condition {
+        WinCondition::MostLiveCells { generations } | WinCondition::TerritoryControl { generations, .. } => {
+            if generation < *generations as usize {
+                return None;
+            }
+            let best = scores.iter().cloned().max().unwrap_or(0);
+            let leaders: Vec<usize> = (0..scores.len()).filter(|&i| scores[i] == best).collect();
+            if leaders.len() == 1 {
+                Some(Some(leaders[0]))
+            } else {
+                Some(None)
+            }
+        }
+        WinCondition::Elimination => {
+            if generation < ELIMINATION_GRACE_GENS {
+                return None;
+            }
+            let survivors: Vec<usize> = (0..scores.len()).filter(|&i| scores[i] > 0).collect();
+            match survivors.len() {
+                0 => Some(None),
+                1 => Some(Some(survivors[0])),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// The team with the fewest players, lowest team number first. None if there are no teams.
+fn smallest_team(sizes: &[usize]) -> Option<u8> {
+    sizes
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, size)| *size)
+        .map(|(team, _)| team as u8)
+}
+
+/// Pairs up sides with their scores, best first.
+pub fn standings(sides: &[Side], scores: &[u64]) -> Vec<PlayerScore> {
+    let mut standings: Vec<PlayerScore> = sides
+        .iter()
+        .zip(scores)
+        .map(|(side, &score)| PlayerScore {
+            name: side.name.clone(),
+            score,
+        })
+        .collect();
+    standings.sort_by(|a, b| b.score.cmp(&a.score));
+    standings
 }
 
 impl ServerState {
@@ -410,11 +1099,175 @@ impl ServerState {
                 players.push(p.name.clone());
             }
         });
+        players.extend(room.bots.iter().map(|bot| bot.name.clone()));
 
         return ResponseCode::PlayerList { players };
     }
 
     pub fn handle_chat_message(&mut self, player_id: PlayerID, msg: String) -> ResponseCode {
+        self.add_chat_message(player_id, msg, false)
+    }
+
+    pub fn handle_team_chat_message(&mut self, player_id: PlayerID, msg: String) -> ResponseCode {
+        self.add_chat_message(player_id, msg, true)
+    }
+
+    /// Replies with the most recent messages in the player's room that they may see, however old,
+    /// as many as fit in MAX_CHAT_HISTORY_LEN bytes.
+    pub fn chat_history(&self, player_id: PlayerID) -> ResponseCode {
+        let room = match self.get_room(player_id) {
+            Some(room) => room,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot get chat history because in lobby".to_owned(),
+                };
+            }
+        };
+        let team = self.get_team(player_id);
+        let now = Instant::now();
+        let mut len = 0;
+        let mut messages: Vec<ChatHistoryEntry> = room
+            .history
+            .iter()
+            .rev()
+            .filter(|msg| msg.team.is_none() || msg.team == team)
+            .take_while(|msg| {
+                len += msg.player_name.len() + msg.message.len();
+                len <= MAX_CHAT_HISTORY_LEN
+            })
+            .map(|msg| ChatHistoryEntry {
+                chat_seq:    msg.seq_num,
+                player_name: msg.player_name.clone(),
+                message:     msg.message.clone(),
+                age_secs:    now.duration_since(msg.timestamp).as_secs(),
+                channel:     msg.channel(),
+            })
+            .collect();
+        messages.reverse();
+        ResponseCode::ChatHistory { messages }
+    }
+
+    /// Replies with the best players in the room named `room_name`, or on the whole server if None.
+    pub fn get_leaderboard(&self, room_name: Option<String>) -> ResponseCode {
+        let entries = self.leaderboard.top(room_name.as_deref(), LEADERBOARD_LEN);
+        ResponseCode::Leaderboard { room_name, entries }
+    }
+
+    /// Shares a pattern from `player_id` with everyone on the server, and marks the shared patterns
+    /// for the main loop to save. Each player can share at most `MAX_PATTERN_SHARES_PER_WINDOW`
+    /// every `PATTERN_SHARE_WINDOW`, so that they can't push everyone else's patterns out.
+    pub fn share_pattern(&mut self, player_id: PlayerID, name: String, rle: String) -> ResponseCode {
+        let now = Instant::now();
+        let player = self.get_player_mut(player_id);
+        let (start, count) = player.pattern_shares.get_or_insert((now, 0));
+        if now.duration_since(*start) >= PATTERN_SHARE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= MAX_PATTERN_SHARES_PER_WINDOW {
+            return ResponseCode::TooManyRequests {
+                error_msg: "too many patterns shared; wait a minute".to_owned(),
+            };
+        }
+        let author = player.name.clone();
+        let info = match self.shared_patterns.add(&name, &author, rle, self.max_patterns) {
+            Ok(info) => info,
+            Err(error_msg) => return ResponseCode::BadRequest { error_msg },
+        };
+        if let Some((_, ref mut count)) = self.get_player_mut(player_id).pattern_shares {
+            *count += 1;
+        }
+        info!("Player {:?} shared the pattern {:?}", author, info.name);
+        self.save_patterns = true;
+        ResponseCode::PatternShared {
+            pattern_id: info.pattern_id,
+            name:       info.name,
+        }
+    }
+
+    /// Replies with a page of the shared patterns, newest first, skipping the newest `offset`.
+    pub fn list_shared_patterns(&self, offset: u32) -> ResponseCode {
+        ResponseCode::SharedPatternList {
+            offset,
+            total: self.shared_patterns.len() as u32,
+            patterns: self.shared_patterns.page(offset as usize, SHARED_PATTERN_PAGE_LEN),
+        }
+    }
+
+    /// Replies with the shared pattern with ID `pattern_id`, including its RLE.
+    pub fn get_shared_pattern(&self, pattern_id: u64) -> ResponseCode {
+        match self.shared_patterns.get(pattern_id) {
+            Some((info, rle)) => ResponseCode::SharedPattern {
+                info: info.clone(),
+                rle:  rle.to_owned(),
+            },
+            None => ResponseCode::BadRequest {
+                error_msg: "that pattern is no longer shared".to_owned(),
+            },
+        }
+    }
+
+    /// Records a report by one player about another for moderators: it is logged, and appended to
+    /// the report log if there is one. Along with the reason given, the report has the reported player's
+    /// messages still in the chat history that the reporter could see.
+    pub fn handle_report_player(&self, player_id: PlayerID, player_name: String, reason: String) -> ResponseCode {
+        let reported = match self.players.values().find(|player| player.name == player_name) {
+            Some(player) => player.player_id,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("no player named {:?} to report", player_name),
+                };
+            }
+        };
+        if reported == player_id {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot report yourself".to_owned(),
+            };
+        }
+
+        let team = self.get_team(player_id);
+        let room = self.get_room(player_id);
+        let recent_messages = room
+            .into_iter()
+            .chain(std::iter::once(&self.lobby))
+            .flat_map(|room| room.history.iter())
+            .filter(|msg| msg.player_id == reported && (msg.team.is_none() || msg.team == team))
+            .map(|msg| msg.message.clone())
+            .collect();
+        let report = PlayerReport {
+            time: chrono::Utc::now().to_rfc3339(),
+            reporter: self.get_player(player_id).name.clone(),
+            reported: player_name,
+            room: room.map(|room| room.name.clone()),
+            reason,
+            recent_messages,
+        };
+        let line = serde_json::to_string(&report).unwrap(); // unwrap OK because it's only strings
+        info!("Player report: {}", line);
+        if let Some(ref path) = self.report_log {
+            let result = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(e) = result {
+                error!("Could not write to the report log {:?}: {}", path, e);
+            }
+        }
+        ResponseCode::OK
+    }
+
+    /// Adds a message to the lobby chat, which every player gets, whether or not they are in a room.
+    pub fn handle_lobby_chat_message(&mut self, player_id: PlayerID, msg: String) -> ResponseCode {
+        let player_name = self.get_player(player_id).name.clone();
+        let seq_num = self.lobby.increment_seq_num();
+        self.lobby.discard_older_messages();
+        self.lobby
+            .add_message(ServerChatMessage::new(player_id, player_name, msg, seq_num));
+        ResponseCode::OK
+    }
+
+    fn add_chat_message(&mut self, player_id: PlayerID, msg: String, team_only: bool) -> ResponseCode {
         let player_in_game = self.is_player_in_game(player_id);
 
         if !player_in_game {
@@ -429,6 +1282,13 @@ impl ServerState {
             player.unwrap().name.clone()
         };
 
+        let opt_team = self.get_team(player_id);
+        if team_only && opt_team.is_none() {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot send team chat because not on a team".to_owned(),
+            };
+        }
+
         // User is in game, Server needs to broadcast this to Room
         let opt_room = self.get_room_mut(player_id);
 
@@ -442,2564 +1302,6371 @@ impl ServerState {
         let seq_num = room.increment_seq_num();
 
         room.discard_older_messages();
-        room.add_message(ServerChatMessage::new(player_id, player_name, msg, seq_num));
+        let mut chat_msg = ServerChatMessage::new(player_id, player_name, msg, seq_num);
+        if team_only {
+            chat_msg = chat_msg.to_team(opt_team.unwrap()); // unwrap OK because of check above
+        }
+        room.add_message(chat_msg);
 
         return ResponseCode::OK;
     }
 
-    pub fn list_rooms(&mut self) -> ResponseCode {
-        let mut rooms = vec![];
-        self.rooms.values().for_each(|gs| {
-            let room_details = RoomList {
-                room_name:    gs.name.clone(),
-                player_count: gs.player_ids.len() as u8,
-                in_progress:  gs.game_running,
-            };
-            rooms.push(room_details);
-        });
-        ResponseCode::RoomList { rooms }
+    /// Returns the team of the player, or None if the player is in the lobby or not on a team.
+    pub fn get_team(&self, player_id: PlayerID) -> Option<u8> {
+        self.players
+            .get(&player_id)
+            .and_then(|p| p.game_info.as_ref())
+            .and_then(|game_info| game_info.team)
     }
 
-    /// Creates a new room. Does _not_ check whether it already exists!
-    pub fn new_room(&mut self, name: String) -> RoomID {
-        let room = Room::new(name.clone(), vec![]);
-        let id = room.room_id;
+    /// Returns the number of players on each team in the room, indexed by team.
+    pub fn team_sizes(&self, room: &Room) -> Vec<usize> {
+        let mut sizes = vec![0; room.team_count as usize];
+        for player_id in &room.player_ids {
+            if let Some(team) = self.get_team(*player_id) {
+                if let Some(size) = sizes.get_mut(team as usize) {
+                    *size += 1;
+                }
+            }
+        }
+        for team in room.bots.iter().filter_map(|bot| bot.team) {
+            if let Some(size) = sizes.get_mut(team as usize) {
+                *size += 1;
+            }
+        }
+        sizes
+    }
 
-        self.room_map.insert(name, room.room_id);
-        self.rooms.insert(room.room_id, room);
-        id
+    /// Everyone in the room, bots included, with their index in the running game's universe and
+    /// their team.
+    pub fn roster(&self, room: &Room) -> Vec<PlayerInfo> {
+        let mut roster: Vec<PlayerInfo> = room
+            .player_ids
+            .iter()
+            .filter_map(|player_id| self.players.get(player_id))
+            .map(|player| {
+                let game_info = player.game_info.as_ref();
+                PlayerInfo::new(
+                    player.name.clone(),
+                    game_info
+                        .and_then(|game_info| game_info.universe_index)
+                        .filter(|_| room.game_running)
+                        .map(|i| i as u64),
+                    game_info.and_then(|game_info| game_info.team),
+                )
+            })
+            .collect();
+        roster.extend(room.bots.iter().map(|bot| {
+            let index = room
+                .contenders
+                .iter()
+                .position(|contender| contender.name == bot.name)
+                .filter(|_| room.game_running);
+            PlayerInfo::new(bot.name.clone(), index.map(|i| i as u64), bot.team)
+        }));
+        roster
     }
 
-    pub fn create_new_room(&mut self, opt_player_id: Option<PlayerID>, room_name: String) -> ResponseCode {
-        // validate length
-        if room_name.len() > MAX_ROOM_NAME {
+    /// Tells everyone in the room who is in it, after someone joined, left, or changed teams.
+    fn send_roster(&mut self, room_id: RoomID) {
+        let players = match self.rooms.get(&room_id) {
+            Some(room) => self.roster(room),
+            None => return,
+        };
+        let room = self.rooms.get_mut(&room_id).unwrap(); // unwrap OK because of check above
+        room.add_game_update(GameUpdate::PlayerList { players });
+    }
+
+    /// Tells everyone in the room how its next game is played, after someone joined or the
+    /// settings changed.
+    fn send_room_settings(&mut self, room_id: RoomID) {
+        let room = match self.rooms.get_mut(&room_id) {
+            Some(room) => room,
+            None => return,
+        };
+        let settings = RoomSettings {
+            team_count:    room.team_count,
+            win_condition: room.win_condition.clone(),
+            series_length: room.series_length,
+        };
+        room.add_game_update(GameUpdate::RoomSettings { settings });
+    }
+
+    /// Places the player on `opt_team`, or on the smallest team if None. Lowest team number wins a
+    /// tie.
+    pub fn set_team(&mut self, player_id: PlayerID, opt_team: Option<u8>) -> ResponseCode {
+        let (team_count, sizes) = match self.get_room(player_id) {
+            Some(room) if room.game_running || room.series.is_some() => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot change teams while game is running".to_owned(),
+                };
+            }
+            Some(room) => (room.team_count, self.team_sizes(room)),
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot pick a team because in lobby".to_owned(),
+                };
+            }
+        };
+
+        if team_count == 0 {
             return ResponseCode::BadRequest {
-                error_msg: format!("room name too long; max {} characters", MAX_ROOM_NAME),
+                error_msg: "room does not have teams".to_owned(),
             };
         }
 
-        if let Some(player_id) = opt_player_id {
-            if self.is_player_in_game(player_id) {
+        let team = match opt_team {
+            Some(team) if team < team_count => team,
+            Some(team) => {
                 return ResponseCode::BadRequest {
-                    error_msg: "cannot create room because in-game".to_owned(),
+                    error_msg: format!("no team {}; room has {} teams", team, team_count),
                 };
             }
-        }
-
-        // Create room if the room name is not already taken
-        if !self.room_map.get(&room_name).is_some() {
-            self.new_room(room_name);
+            None => {
+                // A player switching teams shouldn't count toward their old team's size
+                let mut sizes = sizes;
+                if let Some(old_team) = self.get_team(player_id) {
+                    sizes[old_team as usize] -= 1;
+                }
+                smallest_team(&sizes).unwrap() // unwrap OK because team_count is non-zero
+            }
+        };
 
-            return ResponseCode::OK;
-        } else {
-            return ResponseCode::BadRequest {
-                error_msg: format!("room name already in use"),
-            };
-        }
+        let player = self.get_player_mut(player_id);
+        player.game_info.as_mut().unwrap().team = Some(team); // unwrap OK because in a room
+        let broadcast_msg = format!("Player {} has joined team {}.", player.name, team + 1);
+        self.get_room_mut(player_id).unwrap().broadcast(broadcast_msg); // unwrap OK because in a room
+        let room_id = self.get_room_id(player_id).unwrap(); // unwrap OK because in a room
+        self.send_roster(room_id);
+
+        ResponseCode::JoinedTeam { team }
     }
 
-    pub fn join_room(&mut self, player_id: PlayerID, room_name: &str) -> ResponseCode {
-        let already_playing = self.is_player_in_game(player_id);
-        if already_playing {
+    /// Changes the number of teams in the player's room. Players on teams which no longer exist
+    /// must pick a new team. Only the host may change it.
+    pub fn set_team_count(&mut self, player_id: PlayerID, team_count: u8) -> ResponseCode {
+        if team_count > MAX_TEAMS {
             return ResponseCode::BadRequest {
-                error_msg: "cannot join game because in-game".to_owned(),
+                error_msg: format!("too many teams; max {}", MAX_TEAMS),
             };
         }
 
-        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
+        let player_ids = match self.get_room_mut(player_id) {
+            Some(room) => {
+                if !room.is_host(player_id) {
+                    return ResponseCode::BadRequest {
+                        error_msg: "only the host can change the number of teams".to_owned(),
+                    };
+                }
+                if room.game_running || room.series.is_some() {
+                    return ResponseCode::BadRequest {
+                        error_msg: "cannot change teams while game is running".to_owned(),
+                    };
+                }
+                room.team_count = team_count;
+                room.broadcast(format!("Room now has {} teams.", team_count));
+                room.player_ids.clone()
+            }
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set team count because in lobby".to_owned(),
+                };
+            }
+        };
 
-        // TODO replace loop with `get_key_value` once it reaches stable. Same thing with `leave_room` algorithm
-        for ref mut gs in self.rooms.values_mut() {
-            if gs.name == room_name {
-                gs.player_ids.push(player_id);
-                player.game_info = Some(PlayerInGameInfo {
-                    room_id:          gs.room_id.clone(),
-                    chat_msg_seq_num: None,
-                });
-                return ResponseCode::JoinedRoom {
-                    room_name: room_name.to_owned(),
+        for p_id in player_ids {
+            if let Some(game_info) = self.players.get_mut(&p_id).and_then(|p| p.game_info.as_mut()) {
+                if game_info.team.map_or(false, |team| team >= team_count) {
+                    game_info.team = None;
+                }
+            }
+        }
+
+        // Bots can't pick their own teams, so deal them out again
+        let room_id = self.get_room_id(player_id).unwrap(); // unwrap OK because in a room
+        let mut bots = mem::replace(&mut self.rooms.get_mut(&room_id).unwrap().bots, vec![]);
+        let mut sizes = self.team_sizes(&self.rooms[&room_id]);
+        for bot in bots.iter_mut() {
+            bot.team = smallest_team(&sizes);
+            if let Some(team) = bot.team {
+                sizes[team as usize] += 1;
+            }
+        }
+        self.rooms.get_mut(&room_id).unwrap().bots = bots;
+        self.send_roster(room_id);
+        self.send_room_settings(room_id);
+
+        ResponseCode::OK
+    }
+
+    /// Sets how the winner of the next game in the player's room is decided. Only the host may
+    /// change it.
+    pub fn set_win_condition(&mut self, player_id: PlayerID, condition: WinCondition) -> ResponseCode {
+        match condition {
+            WinCondition::MostLiveCells { generations } | WinCondition::TerritoryControl { generations, .. }
+                if generations == 0 =>
+            {
+                return ResponseCode::BadRequest {
+                    error_msg: "game must last at least one generation".to_owned(),
+                };
+            }
+            WinCondition::TerritoryControl { ref region, .. } if region.is_empty() => {
+                return ResponseCode::BadRequest {
+                    error_msg: "territory must not be empty".to_owned(),
                 };
             }
+            _ => {}
         }
-        ResponseCode::BadRequest {
-            error_msg: format!("no room named {:?}", room_name),
+
+        match self.get_room_mut(player_id) {
+            Some(room) => {
+                if !room.is_host(player_id) {
+                    return ResponseCode::BadRequest {
+                        error_msg: "only the host can change the win condition".to_owned(),
+                    };
+                }
+                if room.game_running || room.series.is_some() {
+                    return ResponseCode::BadRequest {
+                        error_msg: "cannot change the win condition while game is running".to_owned(),
+                    };
+                }
+                room.broadcast(format!("Win condition is now {:?}.", condition));
+                room.win_condition = condition;
+                let room_id = room.room_id;
+                self.send_room_settings(room_id);
+                ResponseCode::OK
+            }
+            None => ResponseCode::BadRequest {
+                error_msg: "cannot set win condition because in lobby".to_owned(),
+            },
         }
     }
 
-    pub fn leave_room(&mut self, player_id: PlayerID) -> ResponseCode {
-        let already_playing = self.is_player_in_game(player_id);
-        if !already_playing {
+    /// Sets how many rounds the next game in the player's room lasts. One means a single game. Only
+    /// the host may change it.
+    pub fn set_series_length(&mut self, player_id: PlayerID, rounds: u8) -> ResponseCode {
+        if rounds == 0 || rounds > MAX_SERIES_ROUNDS {
             return ResponseCode::BadRequest {
-                error_msg: "cannot leave game because in lobby".to_owned(),
+                error_msg: format!("series must have between 1 and {} rounds", MAX_SERIES_ROUNDS),
             };
         }
 
-        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
-        {
-            let room_id = &player.game_info.as_ref().unwrap().room_id; // unwrap ok because of test above
-            for ref mut gs in self.rooms.values_mut() {
-                if gs.room_id == *room_id {
-                    // remove player_id from room's player_ids
-                    gs.player_ids.retain(|&p_id| p_id != player.player_id);
-                    break;
+        match self.get_room_mut(player_id) {
+            Some(room) => {
+                if !room.is_host(player_id) {
+                    return ResponseCode::BadRequest {
+                        error_msg: "only the host can change the series length".to_owned(),
+                    };
+                }
+                if room.game_running || room.series.is_some() {
+                    return ResponseCode::BadRequest {
+                        error_msg: "cannot change the series length while a game is running".to_owned(),
+                    };
                 }
+                room.series_length = rounds;
+                room.broadcast(format!("Games are now best of {}.", rounds));
+                let room_id = room.room_id;
+                self.send_room_settings(room_id);
+                ResponseCode::OK
             }
+            None => ResponseCode::BadRequest {
+                error_msg: "cannot set series length because in lobby".to_owned(),
+            },
         }
-        player.game_info = None;
-
-        return ResponseCode::LeaveRoom;
     }
 
-    pub fn remove_player(&mut self, player_id: PlayerID, player_cookie: &str) {
-        if self.is_player_in_game(player_id) {
-            let player = self.get_player(player_id);
-            let broadcast_msg = format!("Player {} has left.", player.name);
-            let room: &mut Room = self.get_room_mut(player_id).unwrap(); // safe because in game check verifies room's existence
-            room.broadcast(broadcast_msg);
-            let _left = self.leave_room(player_id); // Ignore return since we don't care
+    /// Sets how many generations per second the player's room runs at, taking effect right away if
+    /// a game is running. Only the host may change it.
+    pub fn set_generation_rate(&mut self, player_id: PlayerID, gens_per_sec: u8) -> ResponseCode {
+        if gens_per_sec == 0 || gens_per_sec > MAX_GENERATIONS_PER_SEC {
+            return ResponseCode::BadRequest {
+                error_msg: format!(
+                    "generation rate must be from 1 to {} per second",
+                    MAX_GENERATIONS_PER_SEC
+                ),
+            };
         }
-        self.player_map.remove(player_cookie);
-        self.players.remove(&player_id);
-    }
-
-    pub fn handle_disconnect(&mut self, player_id: PlayerID) -> ResponseCode {
-        let player = self.get_player(player_id);
-        let player_cookie = player.cookie.clone();
-        self.remove_player(player_id, &player_cookie);
 
-        ResponseCode::OK
+        match self.get_room_mut(player_id) {
+            Some(room) => {
+                if !room.is_host(player_id) {
+                    return ResponseCode::BadRequest {
+                        error_msg: "only the host can change the generation rate".to_owned(),
+                    };
+                }
+                room.generations_per_sec = gens_per_sec;
+                room.broadcast(format!("The game now runs at {} generations per second.", gens_per_sec));
+                ResponseCode::OK
+            }
+            None => ResponseCode::BadRequest {
+                error_msg: "cannot set the generation rate because in lobby".to_owned(),
+            },
+        }
     }
 
-    // not used for connect
-    pub fn process_request_action(&mut self, player_id: PlayerID, action: RequestAction) -> ResponseCode {
-        match action {
-            RequestAction::Disconnect => {
-                return self.handle_disconnect(player_id);
-            }
-            RequestAction::KeepAlive { latest_response_ack: _ } => {
-                return ResponseCode::OK;
-            }
-            RequestAction::ListPlayers => {
-                return self.list_players(player_id);
+    /// Changes the map of the player's room, which its next game starts from, and tells everyone
+    /// in the room. Only the host may, and not while a game or series is running.
+    pub fn edit_room_map(&mut self, player_id: PlayerID, edit: MapEdit) -> ResponseCode {
+        let room = match self.get_room_mut(player_id) {
+            Some(room) => room,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot edit the map because in lobby".to_owned(),
+                };
             }
-            RequestAction::ChatMessage { message } => {
-                return self.handle_chat_message(player_id, message);
+        };
+        if !room.is_host(player_id) {
+            return ResponseCode::BadRequest {
+                error_msg: "only the host can edit the room's map".to_owned(),
+            };
+        }
+        if room.game_running || room.series.is_some() {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot edit the map while game is running".to_owned(),
+            };
+        }
+        let universe_region = Region::new(0, 0, GAME_WIDTH as usize, GAME_HEIGHT as usize);
+
+        match edit {
+            MapEdit::Walls { ref cells, wall } => {
+                if cells.len() > MAX_WALL_EDIT_CELLS {
+                    return ResponseCode::BadRequest {
+                        error_msg: format!("at most {} walls can be changed at once", MAX_WALL_EDIT_CELLS),
+                    };
+                }
+                let mut walls = room.walls.clone();
+                let op = if wall { BitOperation::Set } else { BitOperation::Clear };
+                for &(col, row) in cells {
+                    if !universe_region.contains(col as isize, row as isize) {
+                        return ResponseCode::BadRequest {
+                            error_msg: format!("cell ({}, {}) is outside of the universe", col, row),
+                        };
+                    }
+                    let (col, row) = (col as usize, row as usize);
+                    walls.modify_bits_in_word(row, col / 64, 1 << (63 - col % 64), op);
+                }
+                if walls.to_pattern(None).0.len() > MAX_ROOM_MAP_LEN {
+                    return ResponseCode::BadRequest {
+                        error_msg: "the map has too many walls".to_owned(),
+                    };
+                }
+                room.walls = walls;
             }
-            RequestAction::ListRooms => {
-                return self.list_rooms();
+            MapEdit::SpawnRegion { slot, ref region } => {
+                if slot >= MAX_SPAWN_REGIONS {
+                    return ResponseCode::BadRequest {
+                        error_msg: format!("a map has at most {} spawn regions", MAX_SPAWN_REGIONS),
+                    };
+                }
+                let slot = slot as usize;
+                if let Some(region) = region {
+                    let spawn_region = Region::from(region);
+                    if region.is_empty() || universe_region.intersection(spawn_region) != Some(spawn_region) {
+                        return ResponseCode::BadRequest {
+                            error_msg: "spawn region must be inside the universe".to_owned(),
+                        };
+                    }
+                    let overlaps = room.spawn_regions.iter().enumerate().any(|(other_slot, other)| {
+                        other_slot != slot
+                            && other
+                                .as_ref()
+                                .map_or(false, |other| Region::from(other).intersection(spawn_region).is_some())
+                    });
+                    if overlaps {
+                        return ResponseCode::BadRequest {
+                            error_msg: "spawn regions can't overlap".to_owned(),
+                        };
+                    }
+                }
+                set_map_region(&mut room.spawn_regions, slot, region.clone());
             }
-            RequestAction::NewRoom { room_name } => {
-                return self.create_new_room(Some(player_id), room_name);
+            MapEdit::FogFreeRegion { slot, ref region } => {
+                if slot >= MAX_FOG_FREE_REGIONS {
+                    return ResponseCode::BadRequest {
+                        error_msg: format!("a map has at most {} fog-free regions", MAX_FOG_FREE_REGIONS),
+                    };
+                }
+                if let Some(region) = region {
+                    let fog_free_region = Region::from(region);
+                    if region.is_empty() || universe_region.intersection(fog_free_region) != Some(fog_free_region) {
+                        return ResponseCode::BadRequest {
+                            error_msg: "fog-free region must be inside the universe".to_owned(),
+                        };
+                    }
+                }
+                set_map_region(&mut room.fog_free_regions, slot as usize, region.clone());
             }
-            RequestAction::JoinRoom { room_name } => {
-                return self.join_room(player_id, &room_name);
+            MapEdit::Clear => {
+                room.walls = new_wall_grid();
+                room.spawn_regions.clear();
+                room.fog_free_regions.clear();
             }
-            RequestAction::LeaveRoom => {
-                return self.leave_room(player_id);
+        }
+        room.add_game_update(GameUpdate::RoomMapEdit { edit });
+        ResponseCode::OK
+    }
+
+    /// Replies with the map of the player's room.
+    pub fn get_room_map(&self, player_id: PlayerID) -> ResponseCode {
+        match self.get_room(player_id) {
+            Some(room) => ResponseCode::RoomMap { map: room.room_map() },
+            None => ResponseCode::BadRequest {
+                error_msg: "cannot get the map because in lobby".to_owned(),
+            },
+        }
+    }
+
+    /// Shows an annotation the player drew to everyone in their room, replacing any they shared
+    /// before under the same ID. Each player can share at most `MAX_ANNOTATION_SHARES_PER_WINDOW`
+    /// every `ANNOTATION_SHARE_WINDOW`, so that they can't crowd the room's game updates.
+    pub fn share_annotation(&mut self, player_id: PlayerID, id: u32, annotation: Annotation) -> ResponseCode {
+        let universe_region = Region::new(0, 0, GAME_WIDTH as usize, GAME_HEIGHT as usize);
+        let cells = match annotation {
+            Annotation::Marker { cell, ref label } => {
+                if label.chars().count() > MAX_ANNOTATION_LABEL_LEN {
+                    return ResponseCode::BadRequest {
+                        error_msg: format!("a marker's label can be at most {} characters", MAX_ANNOTATION_LABEL_LEN),
+                    };
+                }
+                vec![cell]
             }
-            RequestAction::Connect { .. } => {
+            Annotation::Arrow { from, to } | Annotation::Measure { from, to } => vec![from, to],
+        };
+        if let Some((col, row)) = cells
+            .into_iter()
+            .find(|&(col, row)| !universe_region.contains(col as isize, row as isize))
+        {
+            return ResponseCode::BadRequest {
+                error_msg: format!("cell ({}, {}) is outside of the universe", col, row),
+            };
+        }
+        let now = Instant::now();
+        let player = self.get_player_mut(player_id);
+        let name = player.name.clone();
+        let game_info = match player.game_info {
+            Some(ref mut game_info) => game_info,
+            None => {
                 return ResponseCode::BadRequest {
-                    error_msg: "Already connected".to_owned(),
+                    error_msg: "cannot share an annotation because in lobby".to_owned(),
                 };
             }
-            RequestAction::SetClientOptions { .. } => {
-                unimplemented!(); // TODO: add support ("auto_match" bool key, see issue #101)
-            }
-            RequestAction::DropPattern { .. } => {
-                unimplemented!(); // TODO: add support
-            }
-            RequestAction::ClearArea { .. } => {
-                unimplemented!(); // TODO: add support
-            }
-            RequestAction::None => {
+        };
+        let (start, count) = game_info.annotation_shares.get_or_insert((now, 0));
+        if now.duration_since(*start) >= ANNOTATION_SHARE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= MAX_ANNOTATION_SHARES_PER_WINDOW {
+            return ResponseCode::TooManyRequests {
+                error_msg: "too many annotations shared; wait a few seconds".to_owned(),
+            };
+        }
+        *count += 1;
+        let room = self.get_room_mut(player_id).unwrap(); // unwrap OK because in a room
+        room.add_game_update(GameUpdate::AnnotationShare { name, id, annotation });
+        ResponseCode::OK
+    }
+
+    /// Seats a new bot in the player's room, on the smallest team if the room has teams. Only the
+    /// host may add bots.
+    pub fn add_bot(&mut self, player_id: PlayerID) -> ResponseCode {
+        let room = match self.get_room(player_id) {
+            Some(room) => room,
+            None => {
                 return ResponseCode::BadRequest {
-                    error_msg: format!("Invalid request: {:?}", action),
+                    error_msg: "cannot add a bot because in lobby".to_owned(),
                 };
             }
+        };
+        if !room.is_host(player_id) {
+            return ResponseCode::BadRequest {
+                error_msg: "only the host can add bots".to_owned(),
+            };
+        }
+        if room.game_running || room.series.is_some() {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot add a bot while a game is running".to_owned(),
+            };
+        }
+        if room.bots.len() >= MAX_BOTS_PER_ROOM {
+            return ResponseCode::BadRequest {
+                error_msg: format!("too many bots; max {}", MAX_BOTS_PER_ROOM),
+            };
         }
-    }
 
-    pub fn is_player_in_game(&self, player_id: PlayerID) -> bool {
-        let player: Option<&Player> = self.players.get(&player_id);
-        player.is_some() && player.unwrap().game_info.is_some()
+        // unwrap OK because the range is unbounded
+        let name = (1..)
+            .map(|i| format!("Bot {}", i))
+            .find(|name| room.bots.iter().all(|bot| bot.name != *name) && self.is_unique_player_name(name))
+            .unwrap();
+        let team = smallest_team(&self.team_sizes(room));
+
+        let room = self.get_room_mut(player_id).unwrap(); // unwrap OK because of check above
+        room.broadcast(format!("{} has joined.", name));
+        room.bots.push(RoomBot { name, team });
+        let room_id = room.room_id;
+        self.send_roster(room_id);
+        ResponseCode::OK
     }
 
-    pub fn is_unique_player_name(&self, name: &str) -> bool {
-        for ref player in self.players.values() {
-            if player.name == name {
-                return false;
+    /// Removes the named bot from the player's room. Only the host may remove bots.
+    pub fn remove_bot(&mut self, player_id: PlayerID, name: &str) -> ResponseCode {
+        let room = match self.get_room_mut(player_id) {
+            Some(room) => room,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot remove a bot because in lobby".to_owned(),
+                };
             }
+        };
+        if !room.is_host(player_id) {
+            return ResponseCode::BadRequest {
+                error_msg: "only the host can remove bots".to_owned(),
+            };
         }
-        return true;
+        if room.game_running || room.series.is_some() {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot remove a bot while a game is running".to_owned(),
+            };
+        }
+
+        let bot_count = room.bots.len();
+        room.bots.retain(|bot| bot.name != name);
+        if room.bots.len() == bot_count {
+            return ResponseCode::BadRequest {
+                error_msg: format!("no bot named {:?}", name),
+            };
+        }
+        room.broadcast(format!("{} has left.", name));
+        let room_id = room.room_id;
+        self.send_roster(room_id);
+        ResponseCode::OK
     }
 
-    // Request_ack contains the last processed sequence number. If one arrives older (less than)
-    // than the last processed, it must be rejected.
-    // FIXME Does not handle wrapped sequence number case yet.
-    pub fn is_previously_processed_packet(&mut self, player_id: PlayerID, sequence: u64) -> bool {
-        let player: &Player = self.get_player(player_id);
-        if let Some(request_ack) = player.request_ack {
-            if sequence <= request_ack {
-                return true;
+    /// Gives the player `requested` as their cell color, or the nearest color that the other players
+    /// in the room can tell apart from their own. Everyone in the room is told the result.
+    pub fn set_color(&mut self, player_id: PlayerID, requested: RgbColor) -> ResponseCode {
+        let room = match self.get_room(player_id) {
+            Some(room) => room,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot pick a color because in lobby".to_owned(),
+                };
             }
-        }
-        false
+        };
+        let taken: Vec<RgbColor> = room
+            .player_ids
+            .iter()
+            .filter(|&&p_id| p_id != player_id)
+            .filter_map(|p_id| self.players.get(p_id))
+            .filter_map(|p| p.game_info.as_ref().and_then(|game_info| game_info.color))
+            .collect();
+        let color = player_color::resolve_color(requested, &taken);
+
+        let player = self.get_player_mut(player_id);
+        player.game_info.as_mut().unwrap().color = Some(color); // unwrap OK because in a room
+        let name = player.name.clone();
+        let room = self.get_room_mut(player_id).unwrap(); // unwrap OK because in a room
+        room.add_game_update(GameUpdate::PlayerColor { name, color });
+        ResponseCode::OK
     }
 
-    pub fn get_player_id_by_cookie(&self, cookie: &str) -> Option<PlayerID> {
-        match self.player_map.get(cookie) {
-            Some(player_id) => Some(*player_id),
-            None => None,
+    /// Tells the room that the player is typing. Clients send this every `TYPING_NOTICE_INTERVAL_MS`
+    /// at most; any more often than half that are ignored, so that they can't crowd the room's game
+    /// updates.
+    pub fn set_typing(&mut self, player_id: PlayerID) -> ResponseCode {
+        let now = Instant::now();
+        let player = self.get_player_mut(player_id);
+        let name = player.name.clone();
+        let game_info = match player.game_info {
+            Some(ref mut game_info) => game_info,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot type to a room because in lobby".to_owned(),
+                };
+            }
+        };
+        let min_interval = Duration::from_millis(TYPING_NOTICE_INTERVAL_MS / 2);
+        if game_info
+            .typing_at
+            .map_or(false, |at| now.duration_since(at) < min_interval)
+        {
+            return ResponseCode::OK;
         }
+        game_info.typing_at = Some(now);
+        let room = self.get_room_mut(player_id).unwrap(); // unwrap OK because in a room
+        room.add_game_update(GameUpdate::PlayerTyping { name });
+        ResponseCode::OK
     }
 
-    /// Returns true if the packet already exists in the queue, otherwise it will return false, and
-    /// will be added in sequence_number order.
-    pub fn add_packet_to_queue(&mut self, player_id: PlayerID, packet: Packet) -> bool {
-        // Unwrap should be safe since a player ID was already found.
-        let network: &mut NetworkManager = self.network_map.get_mut(&player_id).unwrap();
-        let already_exists = network.rx_packets.buffer_item(packet);
-        already_exists
+    /// Records what part of the universe the player is looking at, so that they are only sent
+    /// diffs for the chunks around it. Moving to another chunk resends those chunks in full.
+    pub fn set_client_viewport(&mut self, player_id: PlayerID, viewport: NetRegion) -> ResponseCode {
+        if viewport.is_empty() {
+            return ResponseCode::BadRequest {
+                error_msg: "viewport is empty".to_owned(),
+            };
+        }
+        let universe_region = Region::new(0, 0, GAME_WIDTH as usize, GAME_HEIGHT as usize);
+        let interest = interest_region(Region::from(&viewport), universe_region);
+        let game_info = match self.get_player_mut(player_id).game_info.as_mut() {
+            Some(game_info) => game_info,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set viewport because in lobby".to_owned(),
+                };
+            }
+        };
+        if game_info.interest != interest {
+            game_info.interest = interest;
+            game_info.start_universe_over();
+        }
+        ResponseCode::OK
     }
 
-    /// Checks to see if the incoming packet is immediately processable
-    pub fn can_process_packet(&mut self, player_id: PlayerID, sequence_number: u64) -> bool {
-        let player: &mut Player = self.get_player_mut(player_id);
-        if let Some(ack) = player.request_ack {
-            trace!("[CAN PROCESS?] Ack: {} Sqn: {}", ack, sequence_number);
-            ack + 1 == sequence_number
-        } else {
-            // request_ack has not been set yet, likely first packet
-            player.request_ack = Some(0);
-            true
+    /// Starts the player over with the whole universe around their viewport, for when they can't
+    /// catch up with the diffs.
+    pub fn resync_universe(&mut self, player_id: PlayerID) -> ResponseCode {
+        match self.get_player_mut(player_id).game_info.as_mut() {
+            Some(game_info) => {
+                game_info.start_universe_over();
+                ResponseCode::OK
+            }
+            None => ResponseCode::BadRequest {
+                error_msg: "cannot resync the universe because in lobby".to_owned(),
+            },
         }
     }
 
-    /// Processes a player's request action for all non-logged in requests. If necessary, a response is buffered
-    /// for later transmission
-    pub fn process_player_request_action(
-        &mut self,
-        player_id: PlayerID,
-        action: RequestAction,
-    ) -> Result<Option<Packet>, Box<dyn Error>> {
-        match action {
-            RequestAction::Connect { .. } => unreachable!(),
+    /// Queues changes to cells in the player's writable region, each (column, row, alive), to be made
+    /// as the room's universe next advances. Answers with the generation that will first show them.
+    /// If any cell is outside the region, none are changed.
+    pub fn set_cells(&mut self, player_id: PlayerID, cells: Vec<(u32, u32, bool)>) -> ResponseCode {
+        if cells.len() > MAX_SET_CELLS {
+            return ResponseCode::BadRequest {
+                error_msg: format!("at most {} cells can be set at once", MAX_SET_CELLS),
+            };
+        }
+        let player_index = match self.get_player(player_id).game_info.as_ref() {
+            Some(game_info) => game_info.universe_index,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set cells because in lobby".to_owned(),
+                };
+            }
+        };
+        let room = self.get_room_mut(player_id).unwrap(); // unwrap OK because in a room
+        let (uni, player_index) = match (room.universe.as_ref(), player_index) {
+            (Some(uni), Some(player_index)) if room.game_running => (uni, player_index),
             _ => {
-                if let Some(response) = self.prepare_response(player_id, action.clone()) {
-                    // Buffer all responses to the client for [re-]transmission
-                    let network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
-                    if let Some(player_net) = network {
-                        trace!("[A Response to Client Request added to TX Buffer]{:?}", response);
-                        player_net.tx_packets.buffer_item(response.clone());
-                    }
-                    Ok(Some(response))
-                } else {
-                    Ok(None)
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set cells because not playing".to_owned(),
+                };
+            }
+        };
+        let writable = |col: u32, row: u32| {
+            let (col, row) = (col as usize, row as usize);
+            uni.region().contains(col as isize, row as isize) && uni.writable(col, row, player_index).unwrap_or(false)
+        };
+        if let Some(&(col, row, _)) = cells.iter().find(|&&(col, row, _)| !writable(col, row)) {
+            return ResponseCode::BadRequest {
+                error_msg: format!("cannot set cell ({}, {}) outside of your writable region", col, row),
+            };
+        }
+        let generation = uni.latest_gen() as u64 + 1;
+        let cells_set = cells.iter().map(|&(col, row, _)| (col, row)).collect();
+        room.cell_changes.extend(cells.into_iter().map(|(col, row, alive)| CellChange {
+            player_index,
+            col: col as usize,
+            row: row as usize,
+            alive,
+        }));
+        ResponseCode::CellsSet {
+            cells: cells_set,
+            generation,
+        }
+    }
+
+    /// Starts a game in the player's room, or the first round of a series if the room's series
+    /// length is more than one. Only the host may start one.
+    pub fn start_game(&mut self, player_id: PlayerID) -> ResponseCode {
+        let room_id = match self.get_room_mut(player_id) {
+            Some(room) if !room.is_host(player_id) => {
+                return ResponseCode::BadRequest {
+                    error_msg: "only the host can start a game".to_owned(),
+                };
+            }
+            Some(room) if room.game_running || room.series.is_some() => {
+                return ResponseCode::BadRequest {
+                    error_msg: "game is already running".to_owned(),
+                };
+            }
+            Some(room) => {
+                if room.series_length > 1 {
+                    room.series = Some(Series::default());
                 }
+                room.room_id
+            }
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot start a game because in lobby".to_owned(),
+                };
             }
+        };
+
+        let resp_code = self.start_round(room_id);
+        if resp_code != ResponseCode::OK {
+            self.rooms.get_mut(&room_id).unwrap().series = None; // unwrap OK because room was found above
         }
+        resp_code
     }
 
-    /// Determine how many contiguous packets are processable and process their requests for the given player.
-    pub fn process_queued_rx_packets(&mut self, player_id: PlayerID) {
-        // If we can, start popping off the RX queue and handle contiguous packets immediately
-        let mut dequeue_count = 0;
+    /// Starts a single game in the room. Everyone in the room plays, bots included, except that in
+    /// a team game, players who have not picked a team only watch. Starting regions are rotated by one for each
+    /// round of a series that has already been played, and the rounds after the first keep its players.
+    fn start_round(&mut self, room_id: RoomID) -> ResponseCode {
+        let (team_count, win_condition, room_map, player_ids, bots, rotation, series_roster) =
+            match self.rooms.get(&room_id) {
+                Some(room) => (
+                    room.team_count,
+                    room.win_condition.clone(),
+                    room.room_map(),
+                    room.player_ids.clone(),
+                    room.bots.clone(),
+                    room.series.as_ref().map_or(0, |series| series.round as usize - 1),
+                    room.series
+                        .as_ref()
+                        .filter(|series| series.round > 1)
+                        .map(|series| series.roster.clone()),
+                ),
+                None => {
+                    return ResponseCode::ServerError {
+                        error_msg: format!("no room with ID {:?}", room_id),
+                    };
+                }
+            };
 
-        // Get the last packet we've sent to this player
-        let player_processed_seq_num = self.get_player(player_id).request_ack;
-        let mut latest_processed_seq_num;
+        // (name, team) of each participant, in universe player index order. Bots come last.
+        let (participant_ids, mut participants): (Vec<PlayerID>, Vec<(String, Option<u8>)>) = match series_roster {
+            Some(roster) => roster
+                .into_iter()
+                .filter_map(|(name, team)| {
+                    // those who left the room since are out of the series
+                    player_ids
+                        .iter()
+                        .find(|&&p_id| self.get_player(p_id).name == name)
+                        .map(|&p_id| (p_id, (name, team)))
+                })
+                .unzip(),
+            None => player_ids
+                .iter()
+                .filter(|&&p_id| team_count == 0 || self.get_team(p_id).is_some())
+                .map(|&p_id| (p_id, (self.get_player(p_id).name.clone(), self.get_team(p_id))))
+                .unzip(),
+        };
+        let first_bot_index = participants.len();
+        participants.extend(bots.into_iter().map(|bot| (bot.name, bot.team)));
+        if participants.is_empty() {
+            return ResponseCode::BadRequest {
+                error_msg: "nobody is on a team".to_owned(),
+            };
+        }
 
-        if let Some(seq_num) = player_processed_seq_num {
-            latest_processed_seq_num = seq_num;
+        let options = if team_count > 0 {
+            GameOptions::with_teams(GAME_WIDTH, GAME_HEIGHT, GAME_HISTORY, GAME_FOG_RADIUS, team_count)
         } else {
-            // if request_ack is None, we shouldn't have processed anything yet
-            latest_processed_seq_num = 0;
+            GameOptions::free_for_all(
+                GAME_WIDTH,
+                GAME_HEIGHT,
+                GAME_HISTORY,
+                GAME_FOG_RADIUS,
+                participants.len() as u32,
+            )
         }
+        .with_win_condition(win_condition)
+        .with_room_map(&room_map)
+        .with_rotated_regions(rotation);
 
-        // Collect the next batch of received packets we can process.
-        let rx_queue_count;
-        let mut processable_packets: Vec<Packet> = vec![];
+        let players = participants
+            .iter()
+            .enumerate()
+            .map(|(i, (_, opt_team))| {
+                // unwrap OK because there is a region for each participant, or for each team
+                PlayerBuilder::new(Region::from(options.writable_region(i, *opt_team).unwrap()))
+            })
+            .collect();
+        let universe = match BigBang::new()
+            .width(GAME_WIDTH as usize)
+            .height(GAME_HEIGHT as usize)
+            .server_mode(true)
+            .history(GAME_HISTORY as usize)
+            .fog_radius(GAME_FOG_RADIUS as usize)
+            .add_players(players)
+            .birth()
+            .and_then(|mut uni| options.add_map(&mut uni).map(|_| uni))
         {
-            let network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
-            if let Some(player_net) = network {
-                rx_queue_count = player_net
-                    .rx_packets
-                    .get_contiguous_packets_count(latest_processed_seq_num + 1);
-                // ameen: can I use take().filter().collect()?
-                while dequeue_count < rx_queue_count {
-                    let packet = player_net.rx_packets.as_queue_type_mut().pop_front().unwrap();
+            Ok(uni) => uni,
+            Err(e) => {
+                return ResponseCode::ServerError {
+                    error_msg: format!("could not create universe: {:?}", e),
+                };
+            }
+        };
 
-                    // It is possible that a previously buffered packet (due to out-of-order) was resent by the client,
-                    // and processed immediately upon receipt. We need to skip these.
-                    if packet.sequence_number() > latest_processed_seq_num {
-                        processable_packets.push(packet);
-                    }
+        let sides = if team_count > 0 {
+            (0..team_count)
+                .map(|team| Side {
+                    name:           format!("Team {}", team + 1),
+                    player_indices: (0..participants.len())
+                        .filter(|&i| participants[i].1 == Some(team))
+                        .collect(),
+                })
+                .collect()
+        } else {
+            participants
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| Side {
+                    name:           name.clone(),
+                    player_indices: vec![i],
+                })
+                .collect()
+        };
 
-                    dequeue_count += 1;
-                }
+        for &p_id in &player_ids {
+            let universe_index = participant_ids.iter().position(|&id| id == p_id);
+            if let Some(game_info) = self.get_player_mut(p_id).game_info.as_mut() {
+                game_info.universe_index = universe_index;
+                game_info.start_universe_over(); // generations start over
+                game_info.congestion.forget_in_flight();
             }
         }
 
-        for packet in processable_packets {
-            trace!("[Processing Client Request from RX Buffer]: {:?}", packet);
-            match packet {
-                Packet::Request {
-                    sequence,
-                    response_ack: _,
-                    cookie: _,
-                    action,
-                } => {
-                    latest_processed_seq_num += 1;
-                    assert!(sequence == latest_processed_seq_num);
-                    let _response_packet = self.process_player_request_action(player_id, action);
-                }
-                _ => panic!("Development bug: Non-response packet found in client buffered RX queue"),
-            }
+        let room = self.rooms.get_mut(&room_id).unwrap(); // unwrap OK because of check above
+        room.universe_hash = Some(UniverseHash {
+            generation: universe.latest_gen() as u32,
+            hash:       universe.state_hash(),
+        });
+        room.universe = Some(universe);
+        room.cell_changes.clear();
+        room.active_bots = (first_bot_index..participants.len())
+            .map(|i| Bot::new(i, BOT_TURN_INTERVAL_IN_GENS))
+            .collect();
+        room.sides = sides;
+        if let Some(series) = room.series.as_mut().filter(|series| series.round == 1) {
+            series.roster = participants[..first_bot_index].to_vec();
         }
-    }
+        room.contenders = participants
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, _))| Contender::new(name, i >= first_bot_index))
+            .collect();
+        room.game_running = true;
+        room.game_options = Some(options.clone());
+        self.send_roster(room_id); // with everyone's index in the universe
+
+        let room = self.rooms.get_mut(&room_id).unwrap(); // unwrap OK because of check above
+        let players = room.game_players();
+        room.add_game_update(GameUpdate::GameStart { options, players });
+        let start_msg = match room.series {
+            Some(ref series) => format!("Round {} of {} has started.", series.round, room.series_length),
+            None => "The game has started.".to_owned(),
+        };
+        room.broadcast(start_msg);
 
-    pub fn process_player_buffered_packets(&mut self, players_to_update: &Vec<PlayerID>) {
-        for player_id in players_to_update {
-            self.process_queued_rx_packets(*player_id);
-        }
+        ResponseCode::OK
     }
 
-    pub fn process_buffered_packets_in_lobby(&mut self) {
-        let mut players_to_update: Vec<PlayerID> = vec![];
+    /// Advances every running game by one generation, and starts the next round of any series
+    /// whose intermission is over.
+    pub fn advance_games(&mut self) {
+        self.advance_games_by(|_| 1);
+    }
 
-        if self.players.len() == 0 {
-            return;
+    /// The leaderboards and the file to save them to, if a game has finished since they were last
+    /// taken. The main loop hands them to the leaderboard saver, so that the file isn't written
+    /// during a tick.
+    pub fn take_unsaved_leaderboard(&mut self) -> Option<(Leaderboard, String)> {
+        if !std::mem::take(&mut self.save_leaderboard) {
+            return None;
         }
+        self.leaderboard_file
+            .clone()
+            .map(|path| (self.leaderboard.clone(), path))
+    }
 
-        // Skip players if they're in a game
-        for player in self.players.values() {
-            if player.game_info.is_some() {
-                continue;
-            }
-            players_to_update.push(player.player_id);
+    /// The shared patterns and the file to save them to, if one has been shared since they were last
+    /// taken. Like the leaderboards, they are saved off the main loop.
+    pub fn take_unsaved_patterns(&mut self) -> Option<(SharedPatterns, String)> {
+        if !std::mem::take(&mut self.save_patterns) {
+            return None;
         }
+        self.patterns_file
+            .clone()
+            .map(|path| (self.shared_patterns.clone(), path))
+    }
 
-        if players_to_update.len() != 0 {
-            self.process_player_buffered_packets(&players_to_update);
-        }
+    /// Lets `elapsed_ms` pass in every room, advancing each game by as many generations as its
+    /// speed calls for.
+    pub fn tick_games(&mut self, elapsed_ms: u64) {
+        self.advance_games_by(|room| room.generations_due(elapsed_ms));
     }
 
-    pub fn collect_expired_tx_packets(&mut self) -> Vec<(Packet, SocketAddr)> {
-        if self.players.len() == 0 {
-            return vec![];
+    fn advance_games_by<F: FnMut(&mut Room) -> usize>(&mut self, mut generations_for: F) {
+        let mut next_rounds = vec![];
+        let mut finished = vec![];
+        for room in self.rooms.values_mut() {
+            for _ in 0..generations_for(room) {
+                if room.advance_intermission() {
+                    next_rounds.push(room.room_id);
+                }
+                if let Some(results) = room.advance_game() {
+                    finished.push((room.name.clone(), results));
+                }
+            }
         }
-
-        let mut players_to_update: Vec<PlayerID> = vec![];
-
-        for player in self.players.values() {
-            players_to_update.push(player.player_id);
+        if !finished.is_empty() {
+            for (room_name, results) in &finished {
+                self.leaderboard.record(room_name, results);
+            }
+            self.save_leaderboard = true;
         }
 
-        let mut expired_responses = vec![];
-        if players_to_update.len() != 0 {
-            for player_id in players_to_update {
-                // If any processed packets result in responses, prepare them below for transmission
-                let player_addr: SocketAddr = self.get_player_mut(player_id).addr;
-                let ack = self.get_player_mut(player_id).request_ack;
-
-                let player_network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
-                if let Some(player_net) = player_network {
-                    if player_net.tx_packets.len() == 0 {
-                        continue;
-                    }
-
-                    let indices = player_net.tx_packets.get_retransmit_indices();
-                    trace!(
-                        "[Sending expired responses to client from TX Buffer]: {:?} Len: {}",
-                        player_id,
-                        indices.len()
-                    );
-                    let retransmissions = player_net.get_expired_tx_packets(player_addr, ack, &indices);
-                    expired_responses.extend_from_slice(retransmissions.as_slice());
-                } else {
-                    error!("I haven't found a NetworkManager for Player: {}", player_id);
-                    continue;
-                }
+        for room_id in next_rounds {
+            let resp_code = self.start_round(room_id);
+            if resp_code != ResponseCode::OK {
+                let room = self.rooms.get_mut(&room_id).unwrap(); // unwrap OK because it was just advanced
+                room.series = None;
+                room.broadcast(format!("Series abandoned: {:?}", resp_code));
             }
         }
+    }
 
-        return expired_responses;
+    pub fn list_rooms(&mut self) -> ResponseCode {
+        let mut rooms = vec![];
+        self.rooms.values().for_each(|gs| {
+            let room_details = RoomList {
+                room_name:    gs.name.clone(),
+                player_count: (gs.player_ids.len() + gs.bots.len()) as u8,
+                in_progress:  gs.game_running,
+            };
+            rooms.push(room_details);
+        });
+        ResponseCode::RoomList { rooms }
     }
 
-    pub fn process_buffered_packets_in_rooms(&mut self) {
-        let mut players_to_update: Vec<PlayerID> = vec![];
+    /// Creates a new room. Does _not_ check whether it already exists!
+    pub fn new_room(&mut self, name: String) -> RoomID {
+        let mut room = Room::new(name.clone(), vec![]);
+        room.team_count = self.room_defaults.team_count;
+        room.series_length = self.room_defaults.series_length;
+        room.generations_per_sec = self.room_defaults.generations_per_sec;
+        room.share_cell_inputs = self.room_defaults.share_cell_inputs;
+        room.win_condition = WinCondition::MostLiveCells {
+            generations: self.room_defaults.match_generations,
+        };
+        let id = room.room_id;
 
-        if self.rooms.len() == 0 {
-            return;
+        self.room_map.insert(name, room.room_id);
+        self.rooms.insert(room.room_id, room);
+        id
+    }
+
+    pub fn create_new_room(&mut self, opt_player_id: Option<PlayerID>, room_name: String) -> ResponseCode {
+        // validate length
+        if room_name.len() > MAX_ROOM_NAME {
+            return ResponseCode::BadRequest {
+                error_msg: format!("room name too long; max {} characters", MAX_ROOM_NAME),
+            };
         }
 
-        for room in self.rooms.values() {
-            if room.player_ids.len() == 0 {
-                continue;
+        if let Some(player_id) = opt_player_id {
+            if self.is_player_in_game(player_id) {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot create room because in-game".to_owned(),
+                };
             }
+        }
 
-            for &player_id in &room.player_ids {
-                let opt_player: Option<&Player> = self.players.get(&player_id);
-                if opt_player.is_none() {
-                    continue;
-                }
-
-                let player: &Player = opt_player.unwrap();
-                if player.game_info.is_none() {
-                    continue;
-                }
-
-                players_to_update.push(player_id);
-            }
+        if self.rooms.len() >= self.max_rooms {
+            return ResponseCode::BadRequest {
+                error_msg: format!("too many rooms; max {}", self.max_rooms),
+            };
         }
 
-        if players_to_update.len() != 0 {
-            self.process_player_buffered_packets(&players_to_update);
+        // Create room if the room name is not already taken
+        if !self.room_map.get(&room_name).is_some() {
+            self.new_room(room_name);
+
+            return ResponseCode::OK;
+        } else {
+            return ResponseCode::BadRequest {
+                error_msg: format!("room name already in use"),
+            };
         }
     }
 
-    /// Clear out the transmission queue of any packets the client has acknowledged
-    pub fn clear_transmission_queue_on_ack(&mut self, player_id: PlayerID, response_ack: Option<u64>) {
-        if let Some(response_ack) = response_ack {
-            if let Some(ref mut player_network) = self.network_map.get_mut(&player_id) {
-                let mut removal_count = 0;
-                for resp_pkt in player_network.tx_packets.as_queue_type_mut().iter() {
-                    if response_ack > 0 && (resp_pkt.sequence_number() <= response_ack - 1) {
-                        removal_count += 1;
-                    } else {
-                        break;
-                    }
-                    // else {
-                    // TODO handle wrapped case & unit tests
-                    // }
-                }
+    pub fn join_room(&mut self, player_id: PlayerID, room_name: &str) -> ResponseCode {
+        let already_playing = self.is_player_in_game(player_id);
+        if already_playing {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot join game because in-game".to_owned(),
+            };
+        }
 
-                if removal_count != 0 {
-                    player_network.tx_pop_front_with_count(removal_count);
-                }
+        // A player rejoining a running game they played in, such as one restored after a restart,
+        // gets their seat back unless someone in the room already has it
+        let name = self.get_player(player_id).name.clone();
+        let opt_seat = self
+            .rooms
+            .values()
+            .find(|room| room.name == room_name)
+            .and_then(|room| room.seat_of(&name).map(|seat| (room, seat)))
+            .filter(|(room, (index, _))| {
+                !room.player_ids.iter().any(|p_id| {
+                    let game_info = self.players.get(p_id).and_then(|player| player.game_info.as_ref());
+                    game_info.and_then(|game_info| game_info.universe_index) == Some(*index)
+                })
+            })
+            .map(|(_, seat)| seat);
+
+        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
+
+        // TODO replace loop with `get_key_value` once it reaches stable. Same thing with `leave_room` algorithm
+        for ref mut gs in self.rooms.values_mut() {
+            if gs.name == room_name {
+                gs.player_ids.push(player_id);
+                player.game_info = Some(PlayerInGameInfo {
+                    room_id:             gs.room_id.clone(),
+                    team:                opt_seat.and_then(|(_, team)| team),
+                    color:               None,
+                    game_update_seq_num: None,
+                    chat_msg_seq_num:    None,
+                    universe_index:      opt_seat.map(|(index, _)| index),
+                    interest:            None,
+                    full_gen:            None,
+                    partial_gen:         None,
+                    universe_epoch:      0,
+                    congestion:          CongestionControl::new(),
+                    typing_at:           None,
+                    annotation_shares:   None,
+                });
+                let room_id = gs.room_id;
+                self.send_roster(room_id);
+                self.send_room_settings(room_id);
+                return ResponseCode::JoinedRoom {
+                    room_name: room_name.to_owned(),
+                };
             }
         }
+        ResponseCode::BadRequest {
+            error_msg: format!("no room named {:?}", room_name),
+        }
     }
 
-    /// Inspect the packet's contents for acceptance, or reject it.
-    /// `Response`/`Update` packets are invalid in this context
-    /// Acceptance criteria for `Request`
-    ///  1. Cookie must be present and valid
-    ///  2. Ignore KeepAlive
-    ///  3. Client should notified if version requires updating
-    ///  4. Ignore if already received or processed
-    /// Always returns either Ok(Some(Packet::Response{...})), Ok(None), or error.
-    pub fn decode_packet(&mut self, addr: SocketAddr, packet: Packet) -> Result<Option<Packet>, Box<dyn Error>> {
-        match packet.clone() {
-            Packet::Response { .. } | Packet::Update { .. } | Packet::Status { .. } => {
-                return Err(Box::new(io::Error::new(ErrorKind::InvalidData, "invalid packet type")));
+    pub fn leave_room(&mut self, player_id: PlayerID) -> ResponseCode {
+        let already_playing = self.is_player_in_game(player_id);
+        if !already_playing {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot leave game because in lobby".to_owned(),
+            };
+        }
+
+        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
+        let room_id = player.game_info.as_ref().unwrap().room_id; // unwrap ok because of test above
+        for ref mut gs in self.rooms.values_mut() {
+            if gs.room_id == room_id {
+                // remove player_id from room's player_ids
+                gs.player_ids.retain(|&p_id| p_id != player.player_id);
+                break;
             }
-            Packet::Request {
-                sequence,
-                response_ack,
-                cookie,
-                action,
-            } => {
-                match action {
-                    RequestAction::Connect { .. } => (),
-                    RequestAction::KeepAlive { latest_response_ack: _ } => (),
-                    _ => {
-                        if cookie == None {
-                            return Err(Box::new(io::Error::new(ErrorKind::InvalidData, "no cookie")));
-                        } else {
-                            trace!(
-                                "[Request] cookie: {:?} sequence: {} resp_ack: {:?} event: {:?}",
-                                cookie,
-                                sequence,
-                                response_ack,
-                                action
-                            );
-                        }
-                    }
-                }
-                // handle connect (create user, and save cookie)
-                if let RequestAction::Connect { name, client_version } = action {
-                    if validate_client_version(client_version) {
-                        let response = self.handle_new_connection(name, addr);
-                        return Ok(Some(response));
-                    } else {
-                        return Err(Box::new(io::Error::new(
-                            ErrorKind::Other,
-                            "client out of date -- please upgrade",
-                        )));
-                    };
-                } else {
-                    // look up player by cookie
-                    let cookie = match cookie {
-                        Some(cookie) => cookie,
-                        None => {
-                            return Err(Box::new(io::Error::new(
-                                ErrorKind::InvalidData,
-                                "cookie required for non-connect actions",
-                            )));
-                        }
-                    };
-                    let player_id = match self.get_player_id_by_cookie(cookie.as_str()) {
-                        Some(player_id) => player_id,
-                        None => {
-                            return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, "invalid cookie")));
-                        }
-                    };
+        }
+        player.game_info = None;
+        self.send_roster(room_id);
 
-                    let mut player: &mut Player = self.get_player_mut(player_id);
-                    player.last_received = time::Instant::now(); // reset time of last received packet from player
-                    match action.clone() {
-                        RequestAction::KeepAlive { latest_response_ack } => {
-                            // If the client does not send new requests, the Server will never get a reply for
-                            // the set of responses it may have sent. This will result in the transmission queue contents
-                            // flooding the Client on retranmission.
-                            self.clear_transmission_queue_on_ack(player_id, Some(latest_response_ack));
-                            return Ok(None);
-                        }
-                        _ => (),
-                    }
+        return ResponseCode::LeaveRoom;
+    }
 
-                    // For the non-KeepAlive case
-                    self.clear_transmission_queue_on_ack(player_id, response_ack);
+    pub fn remove_player(&mut self, player_id: PlayerID, player_cookie: &str) {
+        if self.is_player_in_game(player_id) {
+            let player = self.get_player(player_id);
+            let broadcast_msg = format!("Player {} has left.", player.name);
+            let room: &mut Room = self.get_room_mut(player_id).unwrap(); // safe because in game check verifies room's existence
+            room.broadcast(broadcast_msg);
+            let _left = self.leave_room(player_id); // Ignore return since we don't care
+        }
+        self.player_map.remove(player_cookie);
+        self.players.remove(&player_id);
+    }
 
-                    // Check to see if it can be processed right away, otherwise buffer it for later consumption.
-                    // Not sure if I like this name but it'll do for now.
-                    if self.can_process_packet(player_id, sequence) {
-                        trace!("[PROCESS IMMEDIATE]");
-                        return self.process_player_request_action(player_id, action);
-                    }
+    pub fn handle_disconnect(&mut self, player_id: PlayerID) -> ResponseCode {
+        let player = self.get_player(player_id);
+        let player_cookie = player.cookie.clone();
+        self.remove_player(player_id, &player_cookie);
 
-                    // Packet may be resent by client but has since been processed.
-                    if self.is_previously_processed_packet(player_id, sequence) {
-                        trace!("\t [ALREADY PROCESSED]");
-                        return Ok(None);
-                    }
+        ResponseCode::OK
+    }
 
-                    // Returns true if the packet already exists in the queue
-                    if self.add_packet_to_queue(player_id, packet) {
-                        trace!("\t [ALREADY QUEUED]");
-                        return Ok(None);
-                    }
+    // not used for connect
+    pub fn process_request_action(&mut self, player_id: PlayerID, action: RequestAction) -> ResponseCode {
+        match action {
+            RequestAction::Disconnect => {
+                return self.handle_disconnect(player_id);
+            }
+            RequestAction::KeepAlive { latest_response_ack: _ } => {
+                return ResponseCode::OK;
+            }
+            RequestAction::ListPlayers => {
+                return self.list_players(player_id);
+            }
+            RequestAction::ChatMessage { message } => {
+                return self.handle_chat_message(player_id, message);
+            }
+            RequestAction::ListRooms => {
+                return self.list_rooms();
+            }
+            RequestAction::NewRoom { room_name } => {
+                return self.create_new_room(Some(player_id), room_name);
+            }
+            RequestAction::JoinRoom { room_name } => {
+                return self.join_room(player_id, &room_name);
+            }
+            RequestAction::LeaveRoom => {
+                return self.leave_room(player_id);
+            }
+            RequestAction::SetTeam { team } => {
+                return self.set_team(player_id, team);
+            }
+            RequestAction::TeamChatMessage { message } => {
+                return self.handle_team_chat_message(player_id, message);
+            }
+            RequestAction::LobbyChatMessage { message } => {
+                return self.handle_lobby_chat_message(player_id, message);
+            }
+            RequestAction::GetChatHistory => {
+                return self.chat_history(player_id);
+            }
+            RequestAction::GetLeaderboard { room_name } => {
+                return self.get_leaderboard(room_name);
+            }
+            RequestAction::SharePattern { name, rle } => {
+                return self.share_pattern(player_id, name, rle);
+            }
+            RequestAction::ListSharedPatterns { offset } => {
+                return self.list_shared_patterns(offset);
+            }
+            RequestAction::GetSharedPattern { pattern_id } => {
+                return self.get_shared_pattern(pattern_id);
+            }
+            RequestAction::Typing => {
+                return self.set_typing(player_id);
+            }
+            RequestAction::SetTeamCount { team_count } => {
+                return self.set_team_count(player_id, team_count);
+            }
+            RequestAction::SetWinCondition { condition } => {
+                return self.set_win_condition(player_id, condition);
+            }
+            RequestAction::StartGame => {
+                return self.start_game(player_id);
+            }
+            RequestAction::SetSeriesLength { rounds } => {
+                return self.set_series_length(player_id, rounds);
+            }
+            RequestAction::SetGenerationRate { gens_per_sec } => {
+                return self.set_generation_rate(player_id, gens_per_sec);
+            }
+            RequestAction::AddBot => {
+                return self.add_bot(player_id);
+            }
+            RequestAction::EditRoomMap { edit } => {
+                return self.edit_room_map(player_id, edit);
+            }
+            RequestAction::GetRoomMap => {
+                return self.get_room_map(player_id);
+            }
+            RequestAction::ShareAnnotation { id, annotation } => {
+                return self.share_annotation(player_id, id, annotation);
+            }
+            RequestAction::RemoveBot { name } => {
+                return self.remove_bot(player_id, &name);
+            }
+            RequestAction::SetColor { color } => {
+                return self.set_color(player_id, color);
+            }
+            RequestAction::ReloadConfig { password } => {
+                return self.request_config_reload(player_id, &password);
+            }
+            RequestAction::ReportPlayer { player_name, reason } => {
+                return self.handle_report_player(player_id, player_name, reason);
+            }
+            RequestAction::ClientViewport { viewport } => {
+                return self.set_client_viewport(player_id, viewport);
+            }
+            RequestAction::SetCells { cells } => {
+                return self.set_cells(player_id, cells);
+            }
+            RequestAction::ResyncUniverse => {
+                return self.resync_universe(player_id);
+            }
+            RequestAction::Connect { .. } => {
+                return ResponseCode::BadRequest {
+                    error_msg: "Already connected".to_owned(),
+                };
+            }
+            RequestAction::SetClientOptions { .. } => {
+                unimplemented!(); // TODO: add support ("auto_match" bool key, see issue #101)
+            }
+            RequestAction::DropPattern { .. } => {
+                unimplemented!(); // TODO: add support
+            }
+            RequestAction::ClearArea { .. } => {
+                unimplemented!(); // TODO: add support
+            }
+            RequestAction::None => {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("Invalid request: {:?}", action),
+                };
+            }
+        }
+    }
+
+    pub fn is_player_in_game(&self, player_id: PlayerID) -> bool {
+        let player: Option<&Player> = self.players.get(&player_id);
+        player.is_some() && player.unwrap().game_info.is_some()
+    }
+
+    pub fn is_unique_player_name(&self, name: &str) -> bool {
+        for ref player in self.players.values() {
+            if player.name == name {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    // Request_ack contains the last processed sequence number. If one arrives older (less than)
+    // than the last processed, it must be rejected.
+    // FIXME Does not handle wrapped sequence number case yet.
+    pub fn is_previously_processed_packet(&mut self, player_id: PlayerID, sequence: u64) -> bool {
+        let player: &Player = self.get_player(player_id);
+        if let Some(request_ack) = player.request_ack {
+            if sequence <= request_ack {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn get_player_id_by_cookie(&self, cookie: &str) -> Option<PlayerID> {
+        match self.player_map.get(cookie) {
+            Some(player_id) => Some(*player_id),
+            None => None,
+        }
+    }
+
+    /// Points the player at `addr` if their packets have started coming from there, as when a
+    /// client switches networks or its NAT mapping changes. The caller has already checked the cookie.
+    pub fn migrate_player(&mut self, player_id: PlayerID, addr: SocketAddr) {
+        let player: &mut Player = self.get_player_mut(player_id);
+        if player.addr == addr {
+            return;
+        }
+        info!("Player {:?} moved from {:?} to {:?}", player.name, player.addr, addr);
+        player.addr = addr;
+        player.path_mtu = PathMtu::new(); // the new path may not take packets as large
+        if let Some(game_info) = player.game_info.as_mut() {
+            game_info.congestion.forget_in_flight(); // updates sent to the old address are gone
+        }
+    }
+
+    /// Returns true if the packet already exists in the queue, otherwise it will return false, and
+    /// will be added in sequence_number order.
+    pub fn add_packet_to_queue(&mut self, player_id: PlayerID, packet: Packet) -> bool {
+        // Unwrap should be safe since a player ID was already found.
+        let network: &mut NetworkManager = self.network_map.get_mut(&player_id).unwrap();
+        let already_exists = network.rx_packets.buffer_item(packet);
+        already_exists
+    }
+
+    /// Checks to see if the incoming packet is immediately processable
+    pub fn can_process_packet(&mut self, player_id: PlayerID, sequence_number: u64) -> bool {
+        let player: &mut Player = self.get_player_mut(player_id);
+        if let Some(ack) = player.request_ack {
+            trace!("[CAN PROCESS?] Ack: {} Sqn: {}", ack, sequence_number);
+            ack + 1 == sequence_number
+        } else {
+            // request_ack has not been set yet, likely first packet
+            player.request_ack = Some(0);
+            true
+        }
+    }
+
+    /// Processes a player's request action for all non-logged in requests. If necessary, a response is buffered
+    /// for later transmission
+    pub fn process_player_request_action(
+        &mut self,
+        player_id: PlayerID,
+        action: RequestAction,
+    ) -> Result<Option<Packet>, Box<dyn Error>> {
+        match action {
+            RequestAction::Connect { .. } => unreachable!(),
+            _ => {
+                if let Some(response) = self.prepare_response(player_id, action.clone()) {
+                    // Buffer all responses to the client for [re-]transmission
+                    let network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
+                    if let Some(player_net) = network {
+                        trace!("[A Response to Client Request added to TX Buffer]{:?}", response);
+                        player_net.tx_packets.buffer_item(response.clone());
+                    }
+                    Ok(Some(response))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Determine how many contiguous packets are processable and process their requests for the given player.
+    pub fn process_queued_rx_packets(&mut self, player_id: PlayerID) {
+        // If we can, start popping off the RX queue and handle contiguous packets immediately
+        let mut dequeue_count = 0;
+
+        // Get the last packet we've sent to this player
+        let player_processed_seq_num = self.get_player(player_id).request_ack;
+        let mut latest_processed_seq_num;
+
+        if let Some(seq_num) = player_processed_seq_num {
+            latest_processed_seq_num = seq_num;
+        } else {
+            // if request_ack is None, we shouldn't have processed anything yet
+            latest_processed_seq_num = 0;
+        }
+
+        // Collect the next batch of received packets we can process.
+        let rx_queue_count;
+        let mut processable_packets: Vec<Packet> = vec![];
+        {
+            let network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
+            if let Some(player_net) = network {
+                rx_queue_count = player_net
+                    .rx_packets
+                    .get_contiguous_packets_count(latest_processed_seq_num + 1);
+                // ameen: can I use take().filter().collect()?
+                while dequeue_count < rx_queue_count {
+                    let packet = player_net.rx_packets.as_queue_type_mut().pop_front().unwrap();
+
+                    // It is possible that a previously buffered packet (due to out-of-order) was resent by the client,
+                    // and processed immediately upon receipt. We need to skip these.
+                    if packet.sequence_number() > latest_processed_seq_num {
+                        processable_packets.push(packet);
+                    }
+
+                    dequeue_count += 1;
+                }
+            }
+        }
+
+        for packet in processable_packets {
+            trace!("[Processing Client Request from RX Buffer]: {:?}", packet);
+            match packet {
+                Packet::Request {
+                    sequence,
+                    response_ack: _,
+                    cookie: _,
+                    action,
+                } => {
+                    latest_processed_seq_num += 1;
+                    assert!(sequence == latest_processed_seq_num);
+                    let _response_packet = self.process_player_request_action(player_id, action);
+                }
+                _ => panic!("Development bug: Non-response packet found in client buffered RX queue"),
+            }
+        }
+    }
+
+    pub fn process_player_buffered_packets(&mut self, players_to_update: &Vec<PlayerID>) {
+        for player_id in players_to_update {
+            self.process_queued_rx_packets(*player_id);
+        }
+    }
+
+    pub fn process_buffered_packets_in_lobby(&mut self) {
+        let mut players_to_update: Vec<PlayerID> = vec![];
+
+        if self.players.len() == 0 {
+            return;
+        }
+
+        // Skip players if they're in a game
+        for player in self.players.values() {
+            if player.game_info.is_some() {
+                continue;
+            }
+            players_to_update.push(player.player_id);
+        }
+
+        if players_to_update.len() != 0 {
+            self.process_player_buffered_packets(&players_to_update);
+        }
+    }
+
+    pub fn collect_expired_tx_packets(&mut self) -> Vec<(Packet, SocketAddr)> {
+        if self.players.len() == 0 {
+            return vec![];
+        }
+
+        let mut players_to_update: Vec<PlayerID> = vec![];
+
+        for player in self.players.values() {
+            players_to_update.push(player.player_id);
+        }
+
+        let mut expired_responses = vec![];
+        if players_to_update.len() != 0 {
+            for player_id in players_to_update {
+                // If any processed packets result in responses, prepare them below for transmission
+                let player_addr: SocketAddr = self.get_player_mut(player_id).addr;
+                let ack = self.get_player_mut(player_id).request_ack;
+
+                let player_network: Option<&mut NetworkManager> = self.network_map.get_mut(&player_id);
+                if let Some(player_net) = player_network {
+                    if player_net.tx_packets.len() == 0 {
+                        continue;
+                    }
+
+                    let indices = player_net.tx_packets.get_retransmit_indices();
+                    trace!(
+                        "[Sending expired responses to client from TX Buffer]: {:?} Len: {}",
+                        player_id,
+                        indices.len()
+                    );
+                    let retransmissions = player_net.get_expired_tx_packets(player_addr, ack, &indices);
+                    expired_responses.extend_from_slice(retransmissions.as_slice());
+                } else {
+                    error!("I haven't found a NetworkManager for Player: {}", player_id);
+                    continue;
+                }
+            }
+        }
+
+        return expired_responses;
+    }
+
+    pub fn process_buffered_packets_in_rooms(&mut self) {
+        let mut players_to_update: Vec<PlayerID> = vec![];
+
+        if self.rooms.len() == 0 {
+            return;
+        }
+
+        for room in self.rooms.values() {
+            if room.player_ids.len() == 0 {
+                continue;
+            }
+
+            for &player_id in &room.player_ids {
+                let opt_player: Option<&Player> = self.players.get(&player_id);
+                if opt_player.is_none() {
+                    continue;
+                }
+
+                let player: &Player = opt_player.unwrap();
+                if player.game_info.is_none() {
+                    continue;
+                }
+
+                players_to_update.push(player_id);
+            }
+        }
+
+        if players_to_update.len() != 0 {
+            self.process_player_buffered_packets(&players_to_update);
+        }
+    }
+
+    /// Clear out the transmission queue of any packets the client has acknowledged
+    pub fn clear_transmission_queue_on_ack(&mut self, player_id: PlayerID, response_ack: Option<u64>) {
+        if let Some(response_ack) = response_ack {
+            if let Some(ref mut player_network) = self.network_map.get_mut(&player_id) {
+                let mut removal_count = 0;
+                for resp_pkt in player_network.tx_packets.as_queue_type_mut().iter() {
+                    if response_ack > 0 && (resp_pkt.sequence_number() <= response_ack - 1) {
+                        removal_count += 1;
+                    } else {
+                        break;
+                    }
+                    // else {
+                    // TODO handle wrapped case & unit tests
+                    // }
+                }
+
+                if removal_count != 0 {
+                    player_network.tx_pop_front_with_count(removal_count);
+                }
+            }
+        }
+    }
+
+    /// Inspect the packet's contents for acceptance, or reject it.
+    /// `Response`/`Update` packets are invalid in this context
+    /// Acceptance criteria for `Request`
+    ///  1. Cookie must be present and valid
+    ///  2. Ignore KeepAlive
+    ///  3. Client should notified if version requires updating
+    ///  4. Ignore if already received or processed
+    /// Always returns either Ok(Some(Packet::Response{...})), Ok(None), or error.
+    pub fn decode_packet(&mut self, addr: SocketAddr, packet: Packet) -> Result<Option<Packet>, Box<dyn Error>> {
+        match packet.clone() {
+            Packet::Response { .. } | Packet::Update { .. } | Packet::Status { .. } | Packet::MtuProbe { .. } => {
+                return Err(Box::new(io::Error::new(ErrorKind::InvalidData, "invalid packet type")));
+            }
+            Packet::Request {
+                sequence,
+                response_ack,
+                cookie,
+                action,
+            } => {
+                match action {
+                    RequestAction::Connect { .. } => (),
+                    RequestAction::KeepAlive { latest_response_ack: _ } => (),
+                    _ => {
+                        if cookie == None {
+                            return Err(Box::new(io::Error::new(ErrorKind::InvalidData, "no cookie")));
+                        } else {
+                            trace!(
+                                "[Request] cookie: {:?} sequence: {} resp_ack: {:?} event: {:?}",
+                                cookie,
+                                sequence,
+                                response_ack,
+                                action
+                            );
+                        }
+                    }
+                }
+                // handle connect (create user, and save cookie)
+                if let RequestAction::Connect { name, client_version } = action {
+                    if validate_client_version(client_version) {
+                        let response = self.handle_new_connection(name, addr);
+                        return Ok(Some(response));
+                    } else {
+                        return Err(Box::new(io::Error::new(
+                            ErrorKind::Other,
+                            "client out of date -- please upgrade",
+                        )));
+                    };
+                } else {
+                    // look up player by cookie
+                    let cookie = match cookie {
+                        Some(cookie) => cookie,
+                        None => {
+                            return Err(Box::new(io::Error::new(
+                                ErrorKind::InvalidData,
+                                "cookie required for non-connect actions",
+                            )));
+                        }
+                    };
+                    let player_id = match self.get_player_id_by_cookie(cookie.as_str()) {
+                        Some(player_id) => player_id,
+                        None => {
+                            return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, "invalid cookie")));
+                        }
+                    };
+
+                    self.migrate_player(player_id, addr);
+                    let mut player: &mut Player = self.get_player_mut(player_id);
+                    player.last_received = time::Instant::now(); // reset time of last received packet from player
+                    match action.clone() {
+                        RequestAction::KeepAlive { latest_response_ack } => {
+                            // If the client does not send new requests, the Server will never get a reply for
+                            // the set of responses it may have sent. This will result in the transmission queue contents
+                            // flooding the Client on retranmission.
+                            self.clear_transmission_queue_on_ack(player_id, Some(latest_response_ack));
+                            return Ok(None);
+                        }
+                        _ => (),
+                    }
+
+                    // For the non-KeepAlive case
+                    self.clear_transmission_queue_on_ack(player_id, response_ack);
+
+                    // Check to see if it can be processed right away, otherwise buffer it for later consumption.
+                    // Not sure if I like this name but it'll do for now.
+                    if self.can_process_packet(player_id, sequence) {
+                        trace!("[PROCESS IMMEDIATE]");
+                        return self.process_player_request_action(player_id, action);
+                    }
+
+                    // Packet may be resent by client but has since been processed.
+                    if self.is_previously_processed_packet(player_id, sequence) {
+                        trace!("\t [ALREADY PROCESSED]");
+                        return Ok(None);
+                    }
+
+                    // Returns true if the packet already exists in the queue
+                    if self.add_packet_to_queue(player_id, packet) {
+                        trace!("\t [ALREADY QUEUED]");
+                        return Ok(None);
+                    }
+
+                    // In the event we buffered it, we do not send a response
+                    trace!("\t [BUFFERED]");
+                    Ok(None)
+                }
+            }
+            Packet::UpdateReply {
+                cookie,
+                last_chat_seq,
+                last_lobby_chat_seq,
+                last_game_update_seq,
+                last_full_gen,
+                partial_gen,
+                universe_epoch,
+                pong: _,
+            } => {
+                let opt_player_id = self.get_player_id_by_cookie(cookie.as_str());
+
+                if opt_player_id.is_none() {
+                    return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, "invalid cookie")));
+                }
+
+                let player_id = opt_player_id.unwrap();
+                self.migrate_player(player_id, addr);
+                let opt_player = self.players.get_mut(&player_id);
+
+                if opt_player.is_none() {
+                    return Err(Box::new(io::Error::new(ErrorKind::NotFound, "player not found")));
+                }
+
+                let player: &mut Player = opt_player.unwrap();
+
+                player.update_lobby_chat_seq(last_lobby_chat_seq);
+                if player.game_info.is_some() {
+                    player.update_chat_seq_num(last_chat_seq);
+                    player.update_game_update_seq_num(last_game_update_seq);
+                    player.update_full_gen(universe_epoch, last_full_gen);
+                    player.update_partial_gen(universe_epoch, partial_gen);
+                }
+
+                player.latency_filter.update();
+
+                Ok(None)
+            }
+            Packet::GetStatus { ping } => Ok(Some(self.get_status(ping.nonce))),
+            Packet::MtuProbeReply { cookie, size } => {
+                let player_id = match self.get_player_id_by_cookie(cookie.as_str()) {
+                    Some(player_id) => player_id,
+                    None => {
+                        return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, "invalid cookie")));
+                    }
+                };
+                self.migrate_player(player_id, addr);
+                self.get_player_mut(player_id).path_mtu.on_reply(size);
+                Ok(None)
+            }
+        }
+    }
+
+    fn get_status(&self, nonce: u64) -> Packet {
+        Packet::Status {
+            pong:           PingPong { nonce },
+            player_count:   self.player_map.len() as u64,
+            room_count:     self.room_map.len() as u64,
+            server_name:    self.name.clone(),
+            server_version: VERSION.to_owned(),
+        }
+    }
+
+    pub fn prepare_response(&mut self, player_id: PlayerID, action: RequestAction) -> Option<Packet> {
+        let response_code = self.process_request_action(player_id, action.clone());
+
+        let (sequence, request_ack);
+
+        match action {
+            // Filtered away at the decoding packet layer
+            RequestAction::KeepAlive { latest_response_ack: _ } => unreachable!(),
+            // Prepare a response for all other requests
+            _ => {
+                let opt_player: Option<&mut Player> = self.players.get_mut(&player_id);
+
+                match opt_player {
+                    Some(player) => {
+                        sequence = player.increment_response_seq_num();
+                        if let Some(ack) = player.request_ack {
+                            player.request_ack = Some(ack + 1);
+                            request_ack = player.request_ack;
+                        } else {
+                            panic!("Player's request ack has never been set. It should have been set after the first packet!");
+                        }
+                    }
+                    None => {
+                        // This happens with Disconnect packets -- player was deleted at top of this
+                        // function.
+                        return None;
+                    }
+                }
+            }
+        }
+
+        Some(Packet::Response {
+            sequence:    sequence,
+            request_ack: request_ack,
+            code:        response_code,
+        })
+    }
+
+    pub fn handle_new_connection(&mut self, name: String, addr: SocketAddr) -> Packet {
+        if self.is_unique_player_name(&name) {
+            let player = self.add_new_player(name, addr.clone());
+            let cookie = player.cookie.clone();
+
+            // Sequence is assumed to start at 0 for all new connections
+            let response = Packet::Response {
+                sequence:    0,
+                request_ack: Some(0), // Should start at seq_num 0 unless client's network state was not properly reset
+                code:        ResponseCode::LoggedIn {
+                    cookie,
+                    server_version: VERSION.to_owned(),
+                    motd: self.motd.clone(),
+                },
+            };
+            return response;
+        } else {
+            // not a unique name
+            let response = Packet::Response {
+                sequence:    0,
+                request_ack: None,
+                code:        ResponseCode::Unauthorized {
+                    error_msg: "not a unique name".to_owned(),
+                },
+            };
+            return response;
+        }
+    }
+
+    // Right now we'll be constructing all client Update packets for _every_ room.
+    pub fn construct_client_updates(&mut self) -> Vec<(SocketAddr, Packet)> {
+        let mut client_updates: Vec<(SocketAddr, Packet)> = vec![];
+        let mut universes_sent = vec![]; // (player, generation of the universe update sent to them)
+        let now = Instant::now();
+
+        // Lobby chats go out with the player's room update, or on their own if there isn't one
+        let mut lobby_chats: HashMap<PlayerID, Vec<BroadcastChatMessage>> = self
+            .players
+            .values()
+            .filter_map(|player| Some((player.player_id, self.collect_unacknowledged_lobby_messages(player)?)))
+            .collect();
+
+        if self.rooms.len() == 0 && lobby_chats.is_empty() {
+            return vec![];
+        }
+
+        // For each room, determine if each player has unread messages based on chat_msg_seq_num,
+        // and likewise for game updates.
+        // TODO: POOR PERFORMANCE BOUNTY
+        for room in self.rooms.values() {
+            if (room.messages.is_empty() && room.game_updates.is_empty() && lobby_chats.is_empty())
+                || room.player_ids.len() == 0
+            {
+                continue;
+            }
+
+            for &player_id in &room.player_ids {
+                // Players on a lossy link are sent universe updates less often
+                let send_universe = match self.players.get_mut(&player_id).and_then(|p| p.game_info.as_mut()) {
+                    Some(game_info) => game_info.congestion.ready_to_send(now),
+                    None => continue,
+                };
+                let opt_player = self.players.get(&player_id);
+                if opt_player.is_none() {
+                    continue;
+                }
+
+                let player: &Player = opt_player.unwrap();
+                if player.game_info.is_none() {
+                    continue;
+                }
+
+                let mut unsent_messages = vec![];
+                if let Some(new_messages) = self.collect_unacknowledged_messages(&room, player) {
+                    unsent_messages = new_messages.to_vec();
+                }
+                unsent_messages.extend(lobby_chats.remove(&player_id).unwrap_or_default());
+
+                let (game_update_seq, game_updates) =
+                    room.collect_unacknowledged_game_updates(player.get_confirmed_game_update_seq_num());
+
+                // unwrap OK because of the check above
+                let epoch = player.game_info.as_ref().unwrap().universe_epoch;
+                let mut universe_parts = if send_universe {
+                    room.universe_parts_for(player.game_info.as_ref().unwrap(), player.path_mtu.diff_part_len())
+                } else {
+                    vec![]
+                };
+                // A diff in parts isn't acknowledged until the last of them arrives
+                if let Some(last_part) = universe_parts
+                    .iter()
+                    .find(|part| part.part_number + 1 == part.total_parts)
+                {
+                    universes_sent.push((player_id, last_part.gen1 as u64));
+                }
+                let mut universe_parts = universe_parts.drain(..);
+                let universe_update = match universe_parts.next() {
+                    Some(diff) => UniUpdate::Diff { diff, epoch },
+                    None => UniUpdate::NoChange,
+                };
+
+                let messages_available = unsent_messages.len() != 0;
+                let game_updates_available = game_updates.len() != 0;
+                let universe_updates_available = universe_update != UniUpdate::NoChange;
+
+                let update_packet = Packet::Update {
+                    chats:           unsent_messages,
+                    game_updates:    game_updates,
+                    game_update_seq: game_update_seq,
+                    universe_update: universe_update,
+                    universe_hash:   room.universe_hash,
+                    ping:            PingPong::ping(),
+                };
+
+                if messages_available || game_updates_available || universe_updates_available {
+                    client_updates.push((player.addr.clone(), update_packet));
+                }
+
+                // Any further parts of a diff that is too big for one packet go in packets of their own
+                for diff in universe_parts {
+                    let update_packet = Packet::Update {
+                        chats:           vec![],
+                        game_updates:    vec![],
+                        game_update_seq: None,
+                        universe_update: UniUpdate::Diff { diff, epoch },
+                        universe_hash:   None,
+                        ping:            PingPong::ping(),
+                    };
+                    client_updates.push((player.addr.clone(), update_packet));
+                }
+            }
+        }
+
+        for (player_id, chats) in lobby_chats {
+            let update_packet = Packet::Update {
+                chats:           chats,
+                game_updates:    vec![],
+                game_update_seq: None,
+                universe_update: UniUpdate::NoChange,
+                universe_hash:   None,
+                ping:            PingPong::ping(),
+            };
+            client_updates.push((self.get_player(player_id).addr, update_packet));
+        }
+
+        for (player_id, gen1) in universes_sent {
+            if let Some(game_info) = self.get_player_mut(player_id).game_info.as_mut() {
+                game_info.congestion.on_sent(gen1, now);
+            }
+        }
+        return client_updates;
+    }
+
+    /// Creates a vector of messages that the provided Player has not yet acknowledged.
+    /// Exits early if the player is already caught up.
+    pub fn collect_unacknowledged_messages(&self, room: &Room, player: &Player) -> Option<Vec<BroadcastChatMessage>> {
+        let player_team = player.game_info.as_ref().and_then(|game_info| game_info.team);
+        ServerState::unacknowledged_messages(room, player, player.get_confirmed_chat_seq_num(), player_team)
+    }
+
+    /// Same as `collect_unacknowledged_messages`, but for the lobby chat.
+    pub fn collect_unacknowledged_lobby_messages(&self, player: &Player) -> Option<Vec<BroadcastChatMessage>> {
+        ServerState::unacknowledged_messages(&self.lobby, player, player.lobby_chat_seq, None)
+            .map(|messages| messages.into_iter().map(BroadcastChatMessage::in_lobby).collect())
+    }
+
+    fn unacknowledged_messages(
+        room: &Room,
+        player: &Player,
+        opt_acked_seq: Option<u64>,
+        player_team: Option<u8>,
+    ) -> Option<Vec<BroadcastChatMessage>> {
+        // Only send what a player has not yet seen
+        let raw_unsent_messages: VecDeque<ServerChatMessage>;
+        match opt_acked_seq {
+            Some(chat_msg_seq_num) => {
+                let opt_newest_msg = room.get_newest_msg();
+                if opt_newest_msg.is_none() {
+                    return None;
+                }
+
+                let newest_msg = opt_newest_msg.unwrap();
+
+                if chat_msg_seq_num == newest_msg.seq_num {
+                    // Player is caught up
+                    return None;
+                } else if chat_msg_seq_num > newest_msg.seq_num {
+                    error!(
+                        "Misbehaving client {:?};\nClient says it has more messages than we sent!",
+                        player
+                    );
+                    return None;
+                } else {
+                    let amount_to_consume = room.get_message_skip_count(chat_msg_seq_num);
+
+                    // Cast to usize is safe because our message containers are limited by MAX_NUM_CHAT_MESSAGES
+                    raw_unsent_messages = room.messages.iter().skip(amount_to_consume as usize).cloned().collect();
+                }
+            }
+            None => {
+                // Smithers, unleash the hounds!
+                raw_unsent_messages = room.messages.clone();
+            }
+        };
+
+        if raw_unsent_messages.len() == 0 {
+            return None;
+        }
+
+        // Team chats only go to teammates
+        let unsent_messages: Vec<BroadcastChatMessage> = raw_unsent_messages
+            .iter()
+            .filter(|msg| msg.team.is_none() || msg.team == player_team)
+            .map(|msg| {
+                let chat = BroadcastChatMessage::new(msg.seq_num, msg.player_name.clone(), msg.message.clone());
+                match msg.channel() {
+                    ChatChannel::Team => chat.on_team(),
+                    _ => chat,
+                }
+            })
+            .collect();
+
+        if unsent_messages.is_empty() {
+            return None;
+        }
+
+        return Some(unsent_messages);
+    }
+
+    pub fn expire_old_messages_in_all_rooms(&mut self, current_timestamp: time::Instant) {
+        self.lobby
+            .messages
+            .retain(|ref m| current_timestamp - m.timestamp < Duration::from_secs(MAX_AGE_CHAT_MESSAGES as u64));
+        if self.rooms.len() != 0 {
+            for room in self.rooms.values_mut() {
+                if room.has_players() && !room.messages.is_empty() {
+                    room.messages.retain(|ref m| {
+                        current_timestamp - m.timestamp < Duration::from_secs(MAX_AGE_CHAT_MESSAGES as u64)
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn add_new_player(&mut self, name: String, addr: SocketAddr) -> &mut Player {
+        let cookie = new_cookie();
+        let player_id = PlayerID(new_uuid());
+        let player = Player {
+            player_id:      player_id.clone(),
+            cookie:         cookie.clone(),
+            addr:           addr,
+            name:           name,
+            request_ack:    None,
+            next_resp_seq:  0,
+            game_info:      None,
+            last_received:  Instant::now(),
+            latency_filter: LatencyFilter::new(),
+            path_mtu:       PathMtu::new(),
+            lobby_chat_seq: None,
+            pattern_shares: None,
+        };
+
+        // save player into players hash map, and save player ID into hash map using cookie
+        self.player_map.insert(cookie, player_id);
+        self.players.insert(player_id, player);
+        self.network_map.insert(player_id, NetworkManager::new());
+
+        let player = self.get_player_mut(player_id);
+
+        // We expect that the Server proceed with `1` after the connection has been established
+        player.increment_response_seq_num();
+        player
+    }
+
+    pub fn remove_timed_out_clients(&mut self) {
+        let mut timed_out_players: Vec<PlayerID> = vec![];
+
+        for (p_id, p) in self.players.iter() {
+            if has_connection_timed_out(p.last_received) {
+                info!("Player(cookie={:?}) has timed out", p.cookie);
+                timed_out_players.push(*p_id);
+            }
+        }
+
+        for player_id in timed_out_players {
+            self.handle_disconnect(player_id);
+        }
+    }
+
+    /// Applies the settings in `config` that can change while the server is running. Players
+    /// connected from a newly banned address are disconnected.
+    pub fn apply_config(&mut self, config: &ServerConfig) {
+        self.max_rooms = config.max_rooms;
+        self.motd = config.motd.clone();
+        self.admin_password = config.admin_password.clone();
+        self.banned_addresses = config.banned_addresses.iter().cloned().collect();
+        self.rate_limiter.set_limit(config.max_packets_per_sec);
+        self.room_defaults = config.room_defaults.clone();
+        self.report_log = config.report_log.clone();
+        self.max_patterns = config.max_shared_patterns;
+
+        let banned_players: Vec<PlayerID> = self
+            .players
+            .values()
+            .filter(|player| self.banned_addresses.contains(&player.addr.ip()))
+            .map(|player| player.player_id)
+            .collect();
+        for player_id in banned_players {
+            info!("Disconnecting banned player {:?}", self.get_player(player_id).name);
+            self.handle_disconnect(player_id);
+        }
+    }
+
+    /// Asks the main loop to reload the config file, if `password` is the admin password.
+    pub fn request_config_reload(&mut self, player_id: PlayerID, password: &str) -> ResponseCode {
+        match self.admin_password {
+            Some(ref admin_password) if admin_password == password => {
+                info!("Player {:?} asked for a config reload", self.get_player(player_id).name);
+                self.reload_requested = true;
+                ResponseCode::OK
+            }
+            _ => ResponseCode::Unauthorized {
+                error_msg: "wrong admin password".to_owned(),
+            },
+        }
+    }
+
+    /// Creates a new struct representing the global state of this server. Initially, there is one
+    /// room -- "general".
+    pub fn new() -> Self {
+        ServerState::with_config(&ServerConfig::default())
+    }
+
+    /// Like `new`, but with the runtime settings from `config`, which also apply to "general".
+    pub fn with_config(config: &ServerConfig) -> Self {
+        let mut server_state = ServerState {
+            tick:             0,
+            name:             DEFAULT_NAME.to_owned(),
+            reg_params:       None,
+            players:          HashMap::<PlayerID, Player>::new(),
+            rooms:            HashMap::<RoomID, Room>::new(),
+            lobby:            Room::new("lobby".to_owned(), vec![]),
+            player_map:       HashMap::<String, PlayerID>::new(),
+            room_map:         HashMap::<String, RoomID>::new(),
+            network_map:      HashMap::<PlayerID, NetworkManager>::new(),
+            max_rooms:        DEFAULT_MAX_ROOMS,
+            motd:             None,
+            admin_password:   None,
+            banned_addresses: HashSet::new(),
+            rate_limiter:     RateLimiter::default(),
+            room_defaults:    RoomDefaults::default(),
+            reload_requested: false,
+            report_log:       None,
+            ms_per_tick:      config.tick_interval_in_ms,
+            leaderboard:      Leaderboard::default(),
+            leaderboard_file: config.leaderboard_file.clone(),
+            save_leaderboard: false,
+            shared_patterns:  SharedPatterns::default(),
+            patterns_file:    config.shared_patterns_file.clone(),
+            save_patterns:    false,
+            max_patterns:     config.max_shared_patterns,
+            probe_path_mtu:   true,
+        };
+        server_state.apply_config(config);
+        server_state.new_room("general".to_owned());
+        server_state
+    }
+
+    fn process_packet(&mut self, packet_tuple: (Packet, SocketAddr)) -> Vec<(Packet, SocketAddr)> {
+        let (packet, addr) = packet_tuple;
+
+        if self.banned_addresses.contains(&addr.ip()) {
+            return vec![];
+        }
+        if !self.rate_limiter.allow(addr.ip(), Instant::now()) {
+            trace!("Dropping packet from {:?}; over the rate limit", addr);
+            return vec![];
+        }
+
+        debug!("{:?}", packet);
+
+        // Decode incoming and send a Response to the Requester
+        let decode_result = self.decode_packet(addr, packet.clone());
+        if let Ok(opt_response_packet) = decode_result {
+            if let Some(response_packet) = opt_response_packet {
+                let response = (response_packet, addr.clone());
+                return vec![response];
+            }
+        } else {
+            let err = decode_result.unwrap_err();
+            error!("Decoding packet failed, from {:?}: {:?}", addr, err);
+        }
+
+        vec![]
+    }
+
+    fn send_heartbeats(&mut self) -> Vec<(Packet, SocketAddr)> {
+        let mut heartbeats = vec![];
+        for player in self.players.values() {
+            let keep_alive = Packet::Response {
+                sequence:    0,
+                request_ack: None,
+                code:        ResponseCode::KeepAlive,
+            };
+            heartbeats.push((keep_alive, player.addr));
+        }
+        return heartbeats;
+    }
+
+    fn maintain_network_state(&mut self) -> Vec<(Packet, SocketAddr)> {
+        // Process players in rooms
+        self.process_buffered_packets_in_rooms();
+
+        // Process players in lobby
+        self.process_buffered_packets_in_lobby();
+
+        let mut packets = self.collect_expired_tx_packets();
+        if !self.probe_path_mtu {
+            return packets;
+        }
+        let now = Instant::now();
+        for player in self.players.values_mut() {
+            if let Some(size) = player.path_mtu.next_probe(now) {
+                packets.push((Packet::mtu_probe(size), player.addr));
+            }
+        }
+        packets
+    }
+
+    fn garbage_collection(&mut self) -> Vec<(SocketAddr, Packet)> {
+        self.tick_games(self.ms_per_tick);
+        self.expire_old_messages_in_all_rooms(time::Instant::now());
+        let update_packets_vec = self.construct_client_updates();
+
+        self.remove_timed_out_clients();
+        self.rate_limiter.forget_idle(Instant::now());
+        self.tick = 1usize.wrapping_add(self.tick);
+        return update_packets_vec;
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RegisterRequestBody {
+    host_and_port: String,
+}
+
+async fn register(reg_params: &RegistryParams) -> Result<(), Box<dyn Error>> {
+    let req_body = RegisterRequestBody {
+        host_and_port: reg_params.public_addr.clone(),
+    };
+    let response = reqwest::Client::new()
+        .post(reg_params.registry_url.clone())
+        .json(&req_body)
+        .send()
+        .await?;
+    debug!("Response from registration attempt: {:?}", response);
+    if response.status() != reqwest::StatusCode::OK {
+        return Err("failed to register".to_owned().into());
+    }
+    Ok(())
+}
+
+async fn try_register(reg_params: RegistryParams) {
+    debug!("attempting to register server with {:?}", reg_params.registry_url);
+    for attempt in 1..=REGISTER_RETRIES {
+        match register(&reg_params).await {
+            Ok(_) => {
+                debug!("registration success!");
+                break;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to register server (was attempt {} of {}): {:?}",
+                    attempt, REGISTER_RETRIES, e
+                );
+            }
+        }
+        TokioTime::sleep(REGISTER_RETRY_SLEEP).await;
+    }
+}
+
+/// Re-reads the config file and applies the settings that can change without a restart. If the
+/// file can't be loaded, the current settings stay in effect.
+fn reload_config(server_state: &mut ServerState, config: &mut ServerConfig, path: &Path, must_exist: bool) {
+    let new_config = match ServerConfig::load(path, must_exist) {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            error!("Not reloading config: {}", e);
+            return;
+        }
+    };
+    for setting in config.changes_needing_restart(&new_config) {
+        warn!(
+            "Config setting {:?} was changed, but a restart is needed for it to take effect",
+            setting
+        );
+    }
+    server_state.apply_config(&new_config);
+    *config = new_config;
+    info!("Reloaded config from {:?}", path);
+}
+
+/// Yields each time the process receives SIGHUP. On platforms without SIGHUP, it never yields.
+fn hangup_stream() -> io::Result<UnboundedReceiverStream<()>> {
+    #[allow(unused_variables)]
+    let (tx, rx) = mpsc::unbounded_channel();
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sighup = signal(SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while sighup.recv().await.is_some() {
+                if tx.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(UnboundedReceiverStream::new(rx))
+}
+
+/// Saves the values sent to it with `save`, one at a time and on a blocking thread, so that the
+/// server's loop doesn't wait on the file. Only the newest of those waiting is saved. `what` names
+/// the values in error messages.
+fn spawn_saver<T, F>(what: &'static str, save: F) -> mpsc::UnboundedSender<(T, String)>
+where
+    T: Send + 'static,
+    F: Fn(&T, &Path) -> Result<(), Box<dyn Error>> + Copy + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<(T, String)>();
+    tokio::spawn(async move {
+        while let Some(mut newest) = rx.recv().await {
+            while let Ok(newer) = rx.try_recv() {
+                newest = newer;
+            }
+            let (value, path) = newest;
+            let result = tokio::task::spawn_blocking(move || {
+                save(&value, Path::new(&path)).map_err(|e| format!("Could not save the {} to {:?}: {}", what, path, e))
+            })
+            .await;
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(msg)) => error!("{}", msg),
+                Err(e) => error!("The {} saver failed: {}", what, e),
+            }
+        }
+    });
+    tx
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    env_logger::Builder::new()
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "{} [{:5}] - {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S%.6fZ"),
+                record.level(),
+                record.args(),
+            )
+        })
+        .filter(None, LevelFilter::Trace)
+        .filter(Some("futures"), LevelFilter::Off)
+        .filter(Some("tokio_core"), LevelFilter::Off)
+        .filter(Some("tokio_reactor"), LevelFilter::Off)
+        .init();
+
+    let matches = App::new("conwaysted")
+        .about("dedicated game server for Conwayste")
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .help(&format!(
+                    "TOML file to read settings from [default {}, if it exists]; options given here override it",
+                    DEFAULT_CONFIG_PATH
+                ))
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("address")
+                .short("l")
+                .long("listen")
+                .help(&format!(
+                    "address to listen for connections on [default {}]",
+                    DEFAULT_HOST
+                ))
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("port")
+                .short("p")
+                .long("port")
+                .help(&format!("port to listen for connections on [default {}]", DEFAULT_PORT))
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .help(&format!("name of the server [default {}]", DEFAULT_NAME))
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("public-address")
+                .long("public-address")
+                .help("public-facing address for clients to connect to; this gets sent to registrar")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("registrar-url")
+                .long("registrar-url")
+                .help(&format!(
+                    "URL of registrar [default {}]; only used if public-address is set",
+                    REGISTRY_DEFAULT_URL
+                ))
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let config_path = PathBuf::from(matches.value_of("config").unwrap_or(DEFAULT_CONFIG_PATH));
+    let config_must_exist = matches.value_of("config").is_some();
+    let mut config = ServerConfig::load(&config_path, config_must_exist).unwrap_or_else(|e| {
+        error!("Error while loading config: {}", e);
+        exit(1);
+    });
+    debug!("Config: {:?}", config);
+
+    let opt_host = matches.value_of("address").or(config.listen.as_deref());
+    let opt_port = matches
+        .value_of("port")
+        .map(|port_str| {
+            port_str.parse::<u16>().unwrap_or_else(|e| {
+                error!("Error while attempting to parse {:?} as port number: {:?}", port_str, e);
+                exit(1);
+            })
+        })
+        .or(config.port);
+
+    let udp = bind(opt_host, opt_port).await.unwrap_or_else(|e| {
+        error!("Error while trying to bind UDP socket: {:?}", e);
+        exit(1);
+    });
+
+    trace!("Listening for connections on {:?}...", udp.local_addr()?);
+
+    let udp = Arc::new(udp);
+    let (mut udp_sink, udp_stream) = UdpFramed::new(Arc::clone(&udp), NetwaystePacketCodec).split();
+    let mut udp_stream = udp_stream.fuse();
+
+    // Without a bridge, the sender is dropped right away and the stream just ends
+    let (opt_ws_bridge, from_ws_peers) = match config.websocket_port {
+        Some(ws_port) => {
+            let ws_addr = format!("{}:{}", opt_host.unwrap_or(DEFAULT_HOST), ws_port);
+            let listener = TcpListener::bind(&ws_addr).await.unwrap_or_else(|e| {
+                error!("Error while trying to listen for WebSocket connections: {:?}", e);
+                exit(1)
+            });
+            info!("Listening for WebSocket connections on {}", ws_addr);
+            let (ws_bridge, from_ws_peers) = WsBridge::start(listener);
+            (Some(ws_bridge), from_ws_peers)
+        }
+        None => (None, mpsc::unbounded_channel().1),
+    };
+    let mut ws_stream = UnboundedReceiverStream::new(from_ws_peers).fuse();
+
+    let mut server_state = ServerState::with_config(&config);
+    if let Err(e) = mtu::forbid_fragmenting(&udp) {
+        warn!("Packets are kept to the default size, since MTU probes could be fragmented: {}", e);
+        server_state.probe_path_mtu = false;
+    }
+
+    if let Some(name) = matches.value_of("name").or(config.name.as_deref()) {
+        server_state.name = name.to_owned();
+    }
+
+    if let Some(public_addr) = matches.value_of("public-address").or(config.public_address.as_deref()) {
+        let mut reg_params = RegistryParams::new(public_addr.to_owned());
+        if let Some(registrar_url) = matches.value_of("registrar-url").or(config.registrar_url.as_deref()) {
+            reg_params.registry_url = registrar_url.to_owned();
+        }
+        server_state.reg_params = Some(reg_params);
+    }
+
+    if let Some(ref path) = config.leaderboard_file {
+        match Leaderboard::load(Path::new(path)) {
+            Ok(leaderboard) => server_state.leaderboard = leaderboard,
+            Err(e) => {
+                // Carrying on would overwrite them with empty ones after the next game
+                error!("Error while loading the leaderboards from {:?}: {}", path, e);
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(ref path) = config.shared_patterns_file {
+        match SharedPatterns::load(Path::new(path)) {
+            Ok(shared_patterns) => server_state.shared_patterns = shared_patterns,
+            Err(e) => {
+                // Carrying on would overwrite them after the next share
+                error!("Error while loading the shared patterns from {:?}: {}", path, e);
+                exit(1);
+            }
+        }
+    }
+
+    let opt_snapshot_store = config.snapshot_dir.as_ref().map(|dir| {
+        let store = SnapshotStore::new(Path::new(dir), config.snapshot_retention).unwrap_or_else(|e| {
+            error!("Error while opening snapshot directory {:?}: {}", dir, e);
+            exit(1);
+        });
+        match store.load_latest() {
+            Ok(Some(snapshot)) => {
+                info!("Restoring {} rooms from snapshot", snapshot.rooms.len());
+                server_state.restore(snapshot);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Error while loading snapshot from {:?}: {}", dir, e);
+                exit(1);
+            }
+        }
+        store
+    });
+
+    let tick_interval = TokioTime::interval(Duration::from_millis(config.tick_interval_in_ms));
+    let mut tick_interval_stream = IntervalStream::new(tick_interval).fuse();
+
+    let network_interval = TokioTime::interval(Duration::from_millis(NETWORK_INTERVAL_IN_MS));
+    let mut network_interval_stream = IntervalStream::new(network_interval).fuse();
+
+    let heartbeat_interval = TokioTime::interval(Duration::from_millis(HEARTBEAT_INTERVAL_IN_MS));
+    let mut heartbeat_interval_stream = IntervalStream::new(heartbeat_interval).fuse();
+
+    let register_interval = TokioTime::interval(Duration::from_millis(REGISTER_INTERVAL_IN_MS));
+    let mut register_interval_stream = IntervalStream::new(register_interval).fuse();
+
+    // The first tick is immediate; skip it so that we don't save right after restoring
+    let snapshot_period = Duration::from_secs(config.snapshot_interval_in_secs);
+    let snapshot_interval = TokioTime::interval_at(TokioTime::Instant::now() + snapshot_period, snapshot_period);
+    let mut snapshot_interval_stream = IntervalStream::new(snapshot_interval).fuse();
+
+    let mut hangup_stream = hangup_stream()?.fuse();
+
+    let leaderboard_saver = spawn_saver("leaderboards", Leaderboard::save);
+    let patterns_saver = spawn_saver("shared patterns", SharedPatterns::save);
+
+    let mut send_queue = SendQueue::default();
+    let mut send_budget = MAX_PACKETS_PER_TICK;
+
+    loop {
+        select! {
+            _ = tick_interval_stream.select_next_some() => {
+                send_budget = MAX_PACKETS_PER_TICK;
+                let update_packets = server_state.garbage_collection();
+                for (addr, packet) in update_packets {
+                    send_queue.push(packet, addr);
+                }
+                if let Some(save) = server_state.take_unsaved_leaderboard() {
+                    let _ = leaderboard_saver.send(save); // the saver only stops with the server
+                }
+                if let Some(save) = server_state.take_unsaved_patterns() {
+                    let _ = patterns_saver.send(save);
+                }
+            },
+            _ = network_interval_stream.select_next_some() => {
+                let retransmissions = server_state.maintain_network_state();
+                for (packet, addr) in retransmissions {
+                    send_queue.push(packet, addr);
+                }
+            },
+            _ = heartbeat_interval_stream.select_next_some() => {
+                let heartbeats = server_state.send_heartbeats();
+                for (packet, addr) in heartbeats {
+                    send_queue.push(packet, addr);
+                }
+            },
+            _ = register_interval_stream.select_next_some() => {
+                if let Some(ref reg_params) = server_state.reg_params {
+                    tokio::spawn(try_register(reg_params.clone()));
+                }
+            },
+            _ = snapshot_interval_stream.select_next_some() => {
+                if let Some(ref store) = opt_snapshot_store {
+                    match store.save(&server_state.snapshot()) {
+                        Ok(path) => debug!("Saved snapshot to {:?}", path),
+                        Err(e) => error!("Error while saving snapshot: {}", e),
+                    }
+                }
+            },
+            _ = hangup_stream.select_next_some() => {
+                info!("Got SIGHUP");
+                reload_config(&mut server_state, &mut config, &config_path, config_must_exist);
+            },
+            addr_packet_result = udp_stream.select_next_some() => {
+                if let Ok(addr_packet_tuple) = addr_packet_result {
+                    let responses = server_state.process_packet(addr_packet_tuple);
+                    for (packet, addr) in responses {
+                        send_queue.push(packet, addr);
+                    }
+                }
+                if server_state.reload_requested {
+                    server_state.reload_requested = false;
+                    reload_config(&mut server_state, &mut config, &config_path, config_must_exist);
+                }
+            },
+            addr_packet_tuple = ws_stream.select_next_some() => {
+                let responses = server_state.process_packet(addr_packet_tuple);
+                for (packet, addr) in responses {
+                    send_queue.push(packet, addr);
+                }
+                if server_state.reload_requested {
+                    server_state.reload_requested = false;
+                    reload_config(&mut server_state, &mut config, &config_path, config_must_exist);
+                }
+            }
+        }
+
+        while send_budget > 0 {
+            match send_queue.pop() {
+                Some((packet, addr)) => match opt_ws_bridge {
+                    Some(ref ws_bridge) if ws_bridge.has_peer(addr) => ws_bridge.send(packet, addr),
+                    _ if matches!(packet, Packet::MtuProbe { .. }) => mtu::send_probe(&udp, packet, addr).await,
+                    _ => udp_sink.send((packet, addr)).await?,
+                },
+                None => break,
+            }
+            send_budget -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod netwayste_server_tests {
+    use super::*;
+    use ::proptest::strategy::*;
+    use netwayste::net::{
+        ChatChannel, LeaderboardEntry, NetAttempt, SharedPatternInfo, MAX_DIFF_PART_LEN, MAX_SHARED_PATTERN_LEN,
+        MAX_SHARED_PATTERN_NAME_LEN,
+    };
+
+    fn fake_socket_addr() -> SocketAddr {
+        use std::net::{IpAddr, Ipv4Addr};
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 5678)
+    }
+
+    fn universe_epoch(server: &ServerState, player_id: PlayerID) -> u32 {
+        server.get_player(player_id).game_info.as_ref().unwrap().universe_epoch
+    }
+
+    #[test]
+    fn list_players_player_shows_up_in_player_list() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+        // make a new room
+        server.create_new_room(None, String::from(room_name));
+
+        let (player_id, player_name) = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            (p.player_id, p.name.clone())
+        };
+        // make the player join the room
+        {
+            server.join_room(player_id, room_name);
+        }
+        let resp_code: ResponseCode = server.list_players(player_id);
+        match resp_code {
+            ResponseCode::PlayerList { players } => {
+                assert_eq!(players.len(), 1);
+                assert_eq!(*players.first().unwrap(), player_name);
+            }
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
+
+    #[test]
+    fn has_chatted_player_did_not_chat_on_join() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+        // make a new room
+        server.create_new_room(None, String::from(room_name));
+        let player_id = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+            p.player_id
+        };
+        // make the player join the room
+        {
+            server.join_room(player_id, room_name);
+        }
+        let player = server.get_player(player_id);
+        assert_eq!(player.has_chatted(), false);
+    }
+
+    #[test]
+    fn get_confirmed_chat_seq_num_server_tracks_players_chat_updates() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+        // make a new room
+        server.create_new_room(None, String::from(room_name));
+
+        let (player_id, player_cookie) = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            (p.player_id, p.cookie.clone())
+        };
+        // make the player join the room
+        {
+            server.join_room(player_id, room_name);
+        }
+
+        // A chat-less player now has something to to say
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie:               player_cookie.clone(),
+                    last_chat_seq:        Some(1),
+                    last_lobby_chat_seq:  None,
+                    last_game_update_seq: None,
+                    last_full_gen:        None,
+                    partial_gen:          None,
+                    universe_epoch:       0,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+
+        {
+            let player = server.get_player(player_id);
+            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
+        }
+
+        // Older messages are ignored
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie:               player_cookie.clone(),
+                    last_chat_seq:        Some(0),
+                    last_lobby_chat_seq:  None,
+                    last_game_update_seq: None,
+                    last_full_gen:        None,
+                    partial_gen:          None,
+                    universe_epoch:       0,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+
+        {
+            let player = server.get_player(player_id);
+            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
+        }
+
+        // So are absent messages
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie:               player_cookie,
+                    last_chat_seq:        None,
+                    last_lobby_chat_seq:  None,
+                    last_game_update_seq: None,
+                    last_full_gen:        None,
+                    partial_gen:          None,
+                    universe_epoch:       0,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+
+        {
+            let player = server.get_player(player_id);
+            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
+        }
+    }
+
+    #[test]
+    fn get_message_skip_count_player_acked_messages_are_not_included_in_skip_count() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, String::from(room_name));
+
+        let (player_id, _) = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            (p.player_id, p.cookie.clone())
+        };
+        // make the player join the room
+        // Give it a single message
+        {
+            server.join_room(player_id, room_name);
+            server.handle_chat_message(player_id, "ChatMessage".to_owned());
+        }
+
+        {
+            let room: &Room = server.get_room(player_id).unwrap();
+            // The check below does not affect any player acknowledgement as we are not
+            // involving the player yet. This is a simple test to ensure that if a chat
+            // message decoded from a would-be player was less than the latest chat message,
+            // we handle it properly by not skipping any.
+            assert_eq!(room.get_message_skip_count(0), 0);
+        }
+
+        let number_of_messages = 6;
+        for _ in 1..number_of_messages {
+            server.handle_chat_message(player_id, "ChatMessage".to_owned());
+        }
+
+        {
+            //let player = server.get_player_mut(player_id);
+            let player = server.get_player_mut(player_id);
+            // player has not acknowledged any yet
+            #[should_panic]
+            assert_eq!(player.get_confirmed_chat_seq_num(), None);
+        }
+
+        // player acknowledged four of the six
+        let acked_message_count = {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(4));
+
+            player.get_confirmed_chat_seq_num().unwrap()
+        };
+        {
+            let room: &Room = server.get_room(player_id).unwrap();
+            assert_eq!(room.get_message_skip_count(acked_message_count), acked_message_count);
+        }
+
+        // player acknowledged all six
+        let acked_message_count = {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(6));
+
+            player.get_confirmed_chat_seq_num().unwrap()
+        };
+        {
+            let room: &Room = server.get_room(player_id).unwrap();
+            assert_eq!(room.get_message_skip_count(acked_message_count), acked_message_count);
+        }
+    }
+
+    #[test]
+    // Send fifteen messages, but only leave nine unacknowledged, while wrapping on the sequence number
+    fn get_message_skip_count_player_acked_messages_are_not_included_in_skip_count_wrapped_case() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, String::from(room_name));
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            p.player_id
+        };
+        {
+            server.join_room(player_id, room_name);
+        }
+
+        // Picking a value slightly less than max of u64
+        let start_seq_num = u64::max_value() - 6;
+        // First pass, add messages with sequence numbers through the max of u64
+        for seq_num in start_seq_num..u64::max_value() {
+            let room: &mut Room = server.get_room_mut(player_id).unwrap();
+            room.add_message(ServerChatMessage::new(
+                player_id,
+                String::from("some name"),
+                String::from("some msg"),
+                seq_num,
+            ));
+        }
+        // Second pass, from wrap-point, `0`, eight times
+        for seq_num in 0..8 {
+            let room: &mut Room = server.get_room_mut(player_id).unwrap();
+            room.add_message(ServerChatMessage::new(
+                player_id,
+                String::from("some name"),
+                String::from("some msg"),
+                seq_num,
+            ));
+        }
+
+        let acked_message_count = {
+            // Ack up until 0xFFFFFFFFFFFFFFFD
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(start_seq_num + 4));
+
+            player.get_confirmed_chat_seq_num().unwrap()
+        };
+        {
+            let room: &Room = server.get_room(player_id).unwrap();
+            // Fifteen total messages sent.
+            // 2 unacked which are less than u64::max_value()
+            // 8 unacked which are after the numerical wrap
+            let unacked_count = 15 - (8 + 2);
+            assert_eq!(room.get_message_skip_count(acked_message_count), unacked_count);
+        }
+    }
+
+    #[test]
+    fn collect_unacknowledged_messages_a_rooms_unacknowledged_chat_messages_are_collected_for_their_player() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, String::from(room_name));
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            p.player_id
+        };
+        {
+            server.join_room(player_id, room_name);
+        }
+
+        {
+            // Room has no messages, None to send to player
+            let room = server.get_room(player_id).unwrap();
+            let player = server.get_player(player_id);
+            let messages = server.collect_unacknowledged_messages(room, player);
+            assert_eq!(messages, None);
+        }
+
+        {
+            let room: &mut Room = server.get_room_mut(player_id).unwrap();
+            room.add_message(ServerChatMessage::new(
+                player_id,
+                String::from("some name"),
+                String::from("some msg"),
+                1,
+            ));
+        }
+        {
+            // Room has a message, player has yet to ack it
+            let room = server.get_room(player_id).unwrap();
+            let player = server.get_player(player_id);
+            let messages = server.collect_unacknowledged_messages(room, player);
+            assert_eq!(messages.is_some(), true);
+            assert_eq!(messages.unwrap().len(), 1);
+        }
+
+        {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(1));
+        }
+        {
+            // Room has a message, player acked, None
+            let room = server.get_room(player_id).unwrap();
+            let player = server.get_player(player_id);
+            let messages = server.collect_unacknowledged_messages(room, player);
+            assert_eq!(messages, None);
+        }
+    }
+
+    #[test]
+    fn collect_unacknowledged_messages_an_active_room_which_expired_all_messages_returns_none() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, String::from(room_name));
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+
+            p.player_id
+        };
+        {
+            server.join_room(player_id, room_name);
+        }
+
+        {
+            // Add a message to the room and then age it so it will expire
+            let room: &mut Room = server.get_room_mut(player_id).unwrap();
+            room.add_message(ServerChatMessage::new(
+                player_id,
+                String::from("some name"),
+                String::from("some msg"),
+                1,
+            ));
+
+            let message: &mut ServerChatMessage = room.messages.get_mut(0).unwrap();
+            let travel_to_the_past = Instant::now().checked_sub(Duration::from_secs(MAX_AGE_CHAT_MESSAGES as u64));
+            if travel_to_the_past.is_none() {
+                warn!("skipping rest of test; cannot travel to the past :(");
+                return;
+            }
+            message.timestamp = travel_to_the_past.unwrap();
+        }
+        {
+            // Sanity check to ensure player gets the chat message if left unacknowledged
+            let room = server.get_room(player_id).unwrap();
+            let player = server.get_player(player_id);
+            let messages = server.collect_unacknowledged_messages(room, player);
+            assert_eq!(messages.is_some(), true);
+            assert_eq!(messages.unwrap().len(), 1);
+        }
+        {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(1));
+        }
+
+        {
+            // Server drains expired messages for the room
+            server.expire_old_messages_in_all_rooms(time::Instant::now());
+        }
+        {
+            // A room that has no messages, but has player(s) who have acknowledged past messages
+            let room = server.get_room(player_id).unwrap();
+            let player = server.get_player(player_id);
+            let messages = server.collect_unacknowledged_messages(room, player);
+            assert_eq!(messages, None);
+        }
+    }
+
+    #[test]
+    fn handle_chat_message_player_not_in_game() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some name".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+
+        let response = server.handle_chat_message(player_id, "test msg".to_owned());
+        assert_eq!(
+            response,
+            ResponseCode::BadRequest {
+                error_msg: format!("Player {} has not joined a game.", player_id),
+            }
+        );
+    }
+
+    #[test]
+    fn handle_chat_message_player_in_game_one_message() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_string(), fake_socket_addr());
+
+            p.player_id
+        };
+        server.join_room(player_id, room_name);
+
+        let response = server.handle_chat_message(player_id, "test msg".to_owned());
+        assert_eq!(response, ResponseCode::OK);
+        let room: &Room = server.get_room(player_id).unwrap();
+        assert_eq!(room.messages.len(), 1);
+        assert_eq!(room.latest_seq_num, 1);
+        assert_eq!(room.get_newest_msg(), room.get_oldest_msg());
+    }
+
+    #[test]
+    fn lobby_chat_reaches_players_in_rooms_and_in_lobby() {
+        let mut server = ServerState::new();
+        let in_room = add_players_to_room(&mut server, "some room", 1)[0];
+        let lobby_addr: SocketAddr = "5.6.7.8:9012".parse().unwrap();
+        let in_lobby = server.add_new_player("lobbyist".to_owned(), lobby_addr).player_id;
+
+        let response = server.handle_lobby_chat_message(in_lobby, "anyone up for a game?".to_owned());
+        assert_eq!(response, ResponseCode::OK);
+        let response = server.handle_chat_message(in_room, "room only".to_owned());
+        assert_eq!(response, ResponseCode::OK);
+
+        let updates = server.construct_client_updates();
+        assert_eq!(updates.len(), 2);
+        for (addr, packet) in updates {
+            let chats = match packet {
+                Packet::Update { chats, .. } => chats,
+                _ => panic!("Unexpected packet: {:?}", packet),
+            };
+            let lobby_chats: Vec<&str> = chats
+                .iter()
+                .filter(|chat| chat.channel == ChatChannel::Lobby)
+                .map(|chat| chat.message.as_str())
+                .collect();
+            assert_eq!(lobby_chats, vec!["anyone up for a game?"]);
+            let room_chat_sent = chats.iter().any(|chat| chat.message == "room only");
+            assert_eq!(room_chat_sent, addr == fake_socket_addr());
+        }
+
+        // Once acknowledged, lobby chats aren't sent again
+        server.get_player_mut(in_lobby).update_lobby_chat_seq(Some(1));
+        let updates = server.construct_client_updates();
+        assert!(updates.iter().all(|(addr, _)| *addr != lobby_addr));
+    }
+
+    #[test]
+    fn handle_chat_message_player_in_game_many_messages() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        server.join_room(player_id, room_name);
+
+        let response = server.handle_chat_message(player_id, "test msg first".to_owned());
+        assert_eq!(response, ResponseCode::OK);
+        let response = server.handle_chat_message(player_id, "test msg second".to_owned());
+        assert_eq!(response, ResponseCode::OK);
+
+        let room: &Room = server.get_room(player_id).unwrap();
+        assert_eq!(room.messages.len(), 2);
+        assert_eq!(room.latest_seq_num, 2);
+    }
+
+    #[test]
+    fn create_new_room_good_case() {
+        {
+            let mut server = ServerState::new();
+            let room_name = "some name".to_owned();
+
+            assert_eq!(server.create_new_room(None, room_name), ResponseCode::OK);
+        }
+        // Room name length is within bounds
+        {
+            let mut server = ServerState::new();
+            let room_name = "0123456789ABCDEF".to_owned();
+
+            assert_eq!(server.create_new_room(None, room_name), ResponseCode::OK);
+        }
+    }
+
+    #[test]
+    fn create_new_room_name_is_too_long() {
+        let mut server = ServerState::new();
+        let room_name = "0123456789ABCDEF_#".to_owned();
+
+        assert_eq!(
+            server.create_new_room(None, room_name),
+            ResponseCode::BadRequest {
+                error_msg: "room name too long; max 16 characters".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn create_new_room_name_taken() {
+        let mut server = ServerState::new();
+        let room_name = "some room".to_owned();
+        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
+        assert_eq!(
+            server.create_new_room(None, room_name),
+            ResponseCode::BadRequest {
+                error_msg: "room name already in use".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn create_new_room_player_already_in_room() {
+        let mut server = ServerState::new();
+        let room_name = "some room".to_owned();
+        let other_room_name = "another room".to_owned();
+        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        server.join_room(player_id, &room_name);
+
+        assert_eq!(
+            server.create_new_room(Some(player_id), other_room_name),
+            ResponseCode::BadRequest {
+                error_msg: "cannot create room because in-game".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn create_new_room_join_room_good_case() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        assert_eq!(server.create_new_room(None, room_name.to_owned()), ResponseCode::OK);
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        assert_eq!(
+            server.join_room(player_id, room_name),
+            ResponseCode::JoinedRoom {
+                room_name: "some room".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn join_room_player_already_in_room() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        assert_eq!(server.create_new_room(None, room_name.to_owned()), ResponseCode::OK);
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        assert_eq!(
+            server.join_room(player_id, room_name),
+            ResponseCode::JoinedRoom {
+                room_name: "some room".to_owned(),
+            }
+        );
+        assert_eq!(
+            server.join_room(player_id, room_name),
+            ResponseCode::BadRequest {
+                error_msg: "cannot join game because in-game".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn join_room_room_does_not_exist() {
+        let mut server = ServerState::new();
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        assert_eq!(
+            server.join_room(player_id, "some room"),
+            ResponseCode::BadRequest {
+                error_msg: "no room named \"some room\"".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn leave_room_good_case() {
+        let mut server = ServerState::new();
+        let room_name = "some name";
+
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+        server.join_room(player_id, room_name);
+
+        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+    }
+
+    #[test]
+    fn leave_room_player_not_in_room() {
+        let mut server = ServerState::new();
+        let room_name = "some room".to_owned();
+        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
+
+        let player_id = {
+            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+
+            p.player_id
+        };
+
+        assert_eq!(
+            server.leave_room(player_id),
+            ResponseCode::BadRequest {
+                error_msg: "cannot leave game because in lobby".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn leave_room_unregistered_player_id() {
+        let mut server = ServerState::new();
+        let room_name = "some room".to_owned();
+        let rand_player_id = PlayerID(0x2457); //RUST
+        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
+
+        assert_eq!(
+            server.leave_room(rand_player_id),
+            ResponseCode::BadRequest {
+                error_msg: "cannot leave game because in lobby".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn add_new_player_player_added_with_initial_sequence_number() {
+        let mut server = ServerState::new();
+        let name = "some player".to_owned();
+
+        let p: &mut Player = server.add_new_player(name.clone(), fake_socket_addr());
+        assert_eq!(p.name, name);
+    }
+
+    #[test]
+    fn is_unique_player_name_yes_and_no_case() {
+        let mut server = ServerState::new();
+        let name = "some player".to_owned();
+        assert_eq!(server.is_unique_player_name("some player"), true);
+
+        {
+            server.add_new_player(name.clone(), fake_socket_addr());
+        }
+        assert_eq!(server.is_unique_player_name("some player"), false);
+    }
+
+    #[test]
+    fn expire_old_messages_in_all_rooms_room_is_empty() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+
+        server.create_new_room(None, room_name.to_owned().clone());
+        server.expire_old_messages_in_all_rooms(time::Instant::now());
+
+        for room in server.rooms.values() {
+            assert_eq!(room.messages.len(), 0);
+        }
+    }
+
+    #[test]
+    fn expire_old_messages_in_all_rooms_one_room_good_case() {
+        let mut server = ServerState::new();
+
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
+
+        server.join_room(player_id, "general");
+
+        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
+        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
+        server.handle_chat_message(player_id, "It is free!".to_owned());
+        server.handle_chat_message(player_id, "What's not to love?".to_owned());
+
+        let message_count = {
+            let room: &Room = server.get_room(player_id).unwrap();
+            room.messages.len()
+        };
+        assert_eq!(message_count, 4);
 
-                    // In the event we buffered it, we do not send a response
-                    trace!("\t [BUFFERED]");
-                    Ok(None)
-                }
-            }
-            Packet::UpdateReply {
-                cookie,
-                last_chat_seq,
-                last_game_update_seq: _,
-                last_full_gen: _,
-                partial_gen: _,
-                pong: _,
-            } => {
-                let opt_player_id = self.get_player_id_by_cookie(cookie.as_str());
+        // Messages are not old enough to be expired
+        server.expire_old_messages_in_all_rooms(time::Instant::now());
 
-                if opt_player_id.is_none() {
-                    return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, "invalid cookie")));
-                }
+        for room in server.rooms.values() {
+            assert_eq!(room.messages.len(), 4);
+        }
+    }
 
-                let player_id = opt_player_id.unwrap();
-                let opt_player = self.players.get_mut(&player_id);
+    #[test]
+    fn expire_old_messages_in_all_rooms_several_rooms_good_case() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        let room_name2 = "some room2";
 
-                if opt_player.is_none() {
-                    return Err(Box::new(io::Error::new(ErrorKind::NotFound, "player not found")));
-                }
+        let room_id = server.new_room(room_name.to_owned());
+        let room_id2 = server.new_room(room_name2.to_owned());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
+        let player_id2: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
 
-                let player: &mut Player = opt_player.unwrap();
+        server.join_room(player_id, room_name);
+        server.join_room(player_id2, room_name2);
 
-                if player.game_info.is_some() {
-                    player.update_chat_seq_num(last_chat_seq);
-                }
+        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
+        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
+        server.handle_chat_message(player_id2, "It is free!".to_owned());
+        server.handle_chat_message(player_id2, "What's not to love?".to_owned());
 
-                player.latency_filter.update();
+        let message_count = {
+            let room: &Room = server.get_room(player_id).unwrap();
+            room.messages.len()
+        };
+        assert_eq!(message_count, 2);
+        let message_count2 = {
+            let room: &Room = server.get_room(player_id2).unwrap();
+            room.messages.len()
+        };
+        assert_eq!(message_count2, 2);
 
-                Ok(None)
-            }
-            Packet::GetStatus { ping } => Ok(Some(self.get_status(ping.nonce))),
-        }
-    }
+        // Messages are not old enough to be expired
+        server.expire_old_messages_in_all_rooms(time::Instant::now());
 
-    fn get_status(&self, nonce: u64) -> Packet {
-        Packet::Status {
-            pong:           PingPong { nonce },
-            player_count:   self.player_map.len() as u64,
-            room_count:     self.room_map.len() as u64,
-            server_name:    self.name.clone(),
-            server_version: VERSION.to_owned(),
-        }
+        assert_eq!(server.rooms[&room_id].messages.len(), 2);
+        assert_eq!(server.rooms[&room_id2].messages.len(), 2);
     }
 
-    pub fn prepare_response(&mut self, player_id: PlayerID, action: RequestAction) -> Option<Packet> {
-        let response_code = self.process_request_action(player_id, action.clone());
+    #[test]
+    fn expire_old_messages_in_all_rooms_one_room_old_messages_are_wiped() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
 
-        let (sequence, request_ack);
+        server.create_new_room(None, room_name.to_owned().clone());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
 
-        match action {
-            // Filtered away at the decoding packet layer
-            RequestAction::KeepAlive { latest_response_ack: _ } => unreachable!(),
-            // Prepare a response for all other requests
-            _ => {
-                let opt_player: Option<&mut Player> = self.players.get_mut(&player_id);
+        server.join_room(player_id, room_name);
 
-                match opt_player {
-                    Some(player) => {
-                        sequence = player.increment_response_seq_num();
-                        if let Some(ack) = player.request_ack {
-                            player.request_ack = Some(ack + 1);
-                            request_ack = player.request_ack;
-                        } else {
-                            panic!("Player's request ack has never been set. It should have been set after the first packet!");
-                        }
-                    }
-                    None => {
-                        // This happens with Disconnect packets -- player was deleted at top of this
-                        // function.
-                        return None;
-                    }
-                }
+        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
+        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
+        server.handle_chat_message(player_id, "It is free!".to_owned());
+        server.handle_chat_message(player_id, "What's not to love?".to_owned());
+
+        let current_timestamp = Instant::now();
+        let travel_to_the_past = current_timestamp.checked_sub(Duration::from_secs((MAX_AGE_CHAT_MESSAGES + 1) as u64));
+        if travel_to_the_past.is_none() {
+            warn!("skipping rest of test; cannot travel to the past :(");
+            return;
+        }
+        let travel_to_the_past = travel_to_the_past.unwrap();
+        for ref mut room in server.rooms.values_mut() {
+            println!("Room: {:?}", room.name);
+            for m in room.messages.iter_mut() {
+                println!(
+                    "{:?}, {:?},       {:?}",
+                    m.timestamp,
+                    travel_to_the_past,
+                    m.timestamp - travel_to_the_past
+                );
+                m.timestamp = travel_to_the_past;
             }
         }
 
-        Some(Packet::Response {
-            sequence:    sequence,
-            request_ack: request_ack,
-            code:        response_code,
-        })
-    }
-
-    pub fn handle_new_connection(&mut self, name: String, addr: SocketAddr) -> Packet {
-        if self.is_unique_player_name(&name) {
-            let player = self.add_new_player(name, addr.clone());
-            let cookie = player.cookie.clone();
+        // Messages are not old enough to be expired
+        server.expire_old_messages_in_all_rooms(time::Instant::now());
 
-            // Sequence is assumed to start at 0 for all new connections
-            let response = Packet::Response {
-                sequence:    0,
-                request_ack: Some(0), // Should start at seq_num 0 unless client's network state was not properly reset
-                code:        ResponseCode::LoggedIn {
-                    cookie,
-                    server_version: VERSION.to_owned(),
-                },
-            };
-            return response;
-        } else {
-            // not a unique name
-            let response = Packet::Response {
-                sequence:    0,
-                request_ack: None,
-                code:        ResponseCode::Unauthorized {
-                    error_msg: "not a unique name".to_owned(),
-                },
-            };
-            return response;
+        for room in server.rooms.values() {
+            assert_eq!(room.messages.len(), 0);
         }
     }
 
-    // Right now we'll be constructing all client Update packets for _every_ room.
-    pub fn construct_client_updates(&mut self) -> Vec<(SocketAddr, Packet)> {
-        let mut client_updates: Vec<(SocketAddr, Packet)> = vec![];
+    #[test]
+    fn expire_old_messages_in_all_rooms_several_rooms_old_messages_are_wiped() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        let room_name2 = "some room 2";
 
-        if self.rooms.len() == 0 {
-            return vec![];
-        }
+        server.create_new_room(None, room_name.to_owned().clone());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
+        server.create_new_room(None, room_name2.to_owned().clone());
+        let player_id2: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
 
-        // For each room, determine if each player has unread messages based on chat_msg_seq_num
-        // TODO: POOR PERFORMANCE BOUNTY
-        for room in self.rooms.values() {
-            if room.messages.is_empty() || room.player_ids.len() == 0 {
-                continue;
+        server.join_room(player_id, room_name);
+        server.join_room(player_id2, room_name);
+
+        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
+        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
+        server.handle_chat_message(player_id2, "It is free!".to_owned());
+        server.handle_chat_message(player_id2, "What's not to love?".to_owned());
+
+        let current_timestamp = Instant::now();
+        let travel_to_the_past = current_timestamp.checked_sub(Duration::from_secs((MAX_AGE_CHAT_MESSAGES + 1) as u64));
+        if travel_to_the_past.is_none() {
+            warn!("skipping rest of test; cannot travel to the past :(");
+            return;
+        }
+        let travel_to_the_past = travel_to_the_past.unwrap();
+        for ref mut room in server.rooms.values_mut() {
+            println!("Room: {:?}", room.name);
+            for m in room.messages.iter_mut() {
+                println!(
+                    "{:?}, {:?},       {:?}",
+                    m.timestamp,
+                    travel_to_the_past,
+                    m.timestamp - travel_to_the_past
+                );
+                m.timestamp = travel_to_the_past;
             }
+        }
 
-            for &player_id in &room.player_ids {
-                let opt_player = self.players.get(&player_id);
-                if opt_player.is_none() {
-                    continue;
-                }
+        // Messages are not old enough to be expired
+        server.expire_old_messages_in_all_rooms(time::Instant::now());
 
-                let player: &Player = opt_player.unwrap();
-                if player.game_info.is_none() {
-                    continue;
-                }
+        for room in server.rooms.values() {
+            assert_eq!(room.messages.len(), 0);
+        }
+    }
 
-                let mut unsent_messages = vec![];
-                if let Some(new_messages) = self.collect_unacknowledged_messages(&room, player) {
-                    unsent_messages = new_messages.to_vec();
-                }
+    #[test]
+    fn handle_new_connection_good_case() {
+        let mut server = ServerState::new();
+        let player_name = "some name".to_owned();
+        let pkt = server.handle_new_connection(player_name, fake_socket_addr());
+        match pkt {
+            Packet::Response {
+                sequence: _,
+                request_ack: _,
+                code,
+            } => match code {
+                ResponseCode::LoggedIn {
+                    cookie: _,
+                    server_version: _,
+                    motd: _,
+                } => {}
+                _ => panic!("Unexpected ResponseCode: {:?}", code),
+            },
+            _ => panic!("Unexpected Packet Type: {:?}", pkt),
+        }
+    }
 
-                let messages_available = unsent_messages.len() != 0;
-                // TODO: add support
-                let game_updates_available = false;
-                let universe_updates_available = false;
+    #[test]
+    fn handle_new_connection_player_name_taken() {
+        let mut server = ServerState::new();
+        let player_name = "some name".to_owned();
 
-                let update_packet = Packet::Update {
-                    chats:           unsent_messages,
-                    game_updates:    vec![],
-                    game_update_seq: None,
-                    universe_update: UniUpdate::NoChange,
-                    ping:            PingPong::ping(),
-                };
+        let pkt = server.handle_new_connection(player_name.clone(), fake_socket_addr());
+        match pkt {
+            Packet::Response {
+                sequence: _,
+                request_ack: _,
+                code,
+            } => match code {
+                ResponseCode::LoggedIn {
+                    cookie: _,
+                    server_version,
+                    motd: _,
+                } => assert_eq!(server_version, VERSION.to_owned()),
+                _ => panic!("Unexpected ResponseCode: {:?}", code),
+            },
+            _ => panic!("Unexpected Packet Type: {:?}", pkt),
+        }
 
-                if messages_available || game_updates_available || universe_updates_available {
-                    client_updates.push((player.addr.clone(), update_packet));
+        let pkt = server.handle_new_connection(player_name, fake_socket_addr());
+        match pkt {
+            Packet::Response {
+                sequence: _,
+                request_ack: _,
+                code,
+            } => match code {
+                ResponseCode::Unauthorized { error_msg } => {
+                    assert_eq!(error_msg, "not a unique name".to_owned());
                 }
-            }
+                _ => panic!("Unexpected ResponseCode: {:?}", code),
+            },
+            _ => panic!("Unexpected Packet Type: {:?}", pkt),
         }
+    }
 
-        return client_updates;
+    fn a_request_action_strat() -> BoxedStrategy<RequestAction> {
+        prop_oneof![
+            //Just(RequestAction::Disconnect), // not yet implemented
+            //Just(RequestAction::KeepAlive),  // same
+            Just(RequestAction::LeaveRoom),
+            Just(RequestAction::ListPlayers),
+            Just(RequestAction::ListRooms),
+            Just(RequestAction::None),
+        ]
+        .boxed()
     }
 
-    /// Creates a vector of messages that the provided Player has not yet acknowledged.
-    /// Exits early if the player is already caught up.
-    pub fn collect_unacknowledged_messages(&self, room: &Room, player: &Player) -> Option<Vec<BroadcastChatMessage>> {
-        // Only send what a player has not yet seen
-        let raw_unsent_messages: VecDeque<ServerChatMessage>;
-        match player.get_confirmed_chat_seq_num() {
-            Some(chat_msg_seq_num) => {
-                let opt_newest_msg = room.get_newest_msg();
-                if opt_newest_msg.is_none() {
-                    return None;
+    fn a_request_action_complex_strat() -> BoxedStrategy<RequestAction> {
+        prop_oneof![
+            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::ChatMessage { message: a }),
+            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::NewRoom { room_name: a }),
+            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::JoinRoom { room_name: a }),
+            ("([A-Z]{1,4} [0-9]{1,2}){3}", "[0-9].[0-9].[0-9]").prop_map(|(a, b)| {
+                RequestAction::Connect {
+                    name:           a,
+                    client_version: b,
                 }
+            })
+        ]
+        .boxed()
+    }
 
-                let newest_msg = opt_newest_msg.unwrap();
-
-                if chat_msg_seq_num == newest_msg.seq_num {
-                    // Player is caught up
-                    return None;
-                } else if chat_msg_seq_num > newest_msg.seq_num {
-                    error!(
-                        "Misbehaving client {:?};\nClient says it has more messages than we sent!",
-                        player
-                    );
-                    return None;
-                } else {
-                    let amount_to_consume = room.get_message_skip_count(chat_msg_seq_num);
-
-                    // Cast to usize is safe because our message containers are limited by MAX_NUM_CHAT_MESSAGES
-                    raw_unsent_messages = room.messages.iter().skip(amount_to_consume as usize).cloned().collect();
-                }
-            }
-            None => {
-                // Smithers, unleash the hounds!
-                raw_unsent_messages = room.messages.clone();
-            }
-        };
+    // These tests are checking that we do not panic on each RequestAction
+    proptest! {
+        #[test]
+        fn process_request_action_simple(ref request in a_request_action_strat()) {
+            let mut server = ServerState::new();
+            server.create_new_room(None, "some room".to_owned().clone());
+            let player_id: PlayerID = {
+                let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+                player.player_id
+            };
+            server.process_request_action(player_id, request.to_owned());
+        }
 
-        if raw_unsent_messages.len() == 0 {
-            return None;
+        #[test]
+        fn process_request_action_complex(ref request in a_request_action_complex_strat()) {
+            let mut server = ServerState::new();
+            server.create_new_room(None, "some room".to_owned().clone());
+            let player_id: PlayerID = {
+                let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+                player.player_id
+            };
+            server.process_request_action(player_id, request.to_owned());
         }
+    }
 
-        let unsent_messages: Vec<BroadcastChatMessage> = raw_unsent_messages
-            .iter()
-            .map(|msg| BroadcastChatMessage::new(msg.seq_num, msg.player_name.clone(), msg.message.clone()))
-            .collect();
+    #[test]
+    fn process_request_action_connect_while_connected() {
+        let mut server = ServerState::new();
+        let player_name = "some player".to_owned();
+        server.create_new_room(None, "some room".to_owned().clone());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
+        let result = server.process_request_action(
+            player_id,
+            RequestAction::Connect {
+                name:           player_name,
+                client_version: "0.1.0".to_owned(),
+            },
+        );
+        assert_eq!(
+            result,
+            ResponseCode::BadRequest {
+                error_msg: "Already connected".to_owned(),
+            }
+        );
+    }
 
-        return Some(unsent_messages);
+    #[test]
+    fn process_request_action_none_is_invalid() {
+        let mut server = ServerState::new();
+        server.create_new_room(None, "some room".to_owned().clone());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.player_id
+        };
+        let result = server.process_request_action(player_id, RequestAction::None);
+        assert_eq!(
+            result,
+            ResponseCode::BadRequest {
+                error_msg: "Invalid request: None".to_owned(),
+            }
+        );
     }
 
-    pub fn expire_old_messages_in_all_rooms(&mut self, current_timestamp: time::Instant) {
-        if self.rooms.len() != 0 {
-            for room in self.rooms.values_mut() {
-                if room.has_players() && !room.messages.is_empty() {
-                    room.messages.retain(|ref m| {
-                        current_timestamp - m.timestamp < Duration::from_secs(MAX_AGE_CHAT_MESSAGES as u64)
-                    });
+    #[test]
+    fn prepare_response_spot_check_response_packet() {
+        let mut server = ServerState::new();
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.request_ack = Some(1);
+            player.player_id
+        };
+        let pkt: Packet = server.prepare_response(player_id, RequestAction::ListRooms).unwrap();
+        match pkt {
+            Packet::Response {
+                code,
+                sequence,
+                request_ack,
+            } => {
+                if let ResponseCode::RoomList { rooms } = code {
+                    assert_eq!(rooms.len(), 1); // 1 room - general
+                } else {
+                    panic!("`code` is not a RoomList! code is {:?}", code);
                 }
+                assert_eq!(sequence, 1);
+                assert_eq!(request_ack, Some(2));
             }
+            _ => panic!("Unexpected Packet type on Response path: {:?}", pkt),
         }
+        let player: &Player = server.get_player(player_id);
+        assert_eq!(player.next_resp_seq, 2);
     }
 
-    pub fn add_new_player(&mut self, name: String, addr: SocketAddr) -> &mut Player {
-        let cookie = new_cookie();
-        let player_id = PlayerID(new_uuid());
-        let player = Player {
-            player_id:      player_id.clone(),
-            cookie:         cookie.clone(),
-            addr:           addr,
-            name:           name,
-            request_ack:    None,
-            next_resp_seq:  0,
-            game_info:      None,
-            last_received:  Instant::now(),
-            latency_filter: LatencyFilter::new(),
-        };
-
-        // save player into players hash map, and save player ID into hash map using cookie
-        self.player_map.insert(cookie, player_id);
-        self.players.insert(player_id, player);
-        self.network_map.insert(player_id, NetworkManager::new());
+    #[test]
+    fn validate_client_version_client_is_up_to_date() {
+        assert_eq!(validate_client_version(env!("CARGO_PKG_VERSION").to_owned()), true);
+    }
 
-        let player = self.get_player_mut(player_id);
+    #[test]
+    fn validate_client_version_client_is_very_old() {
+        assert_eq!(validate_client_version("0.0.1".to_owned()), true);
+    }
 
-        // We expect that the Server proceed with `1` after the connection has been established
-        player.increment_response_seq_num();
-        player
+    #[test]
+    fn validate_client_version_client_is_from_the_future() {
+        assert_eq!(
+            validate_client_version(
+                format!("{}.{}.{}", <i32>::max_value(), <i32>::max_value(), <i32>::max_value()).to_owned()
+            ),
+            false
+        );
     }
 
-    pub fn remove_timed_out_clients(&mut self) {
-        let mut timed_out_players: Vec<PlayerID> = vec![];
+    #[test]
+    fn decode_packet_update_reply_good_case() {
+        let mut server = ServerState::new();
+        let cookie: String = {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.cookie.clone()
+        };
 
-        for (p_id, p) in self.players.iter() {
-            if has_connection_timed_out(p.last_received) {
-                info!("Player(cookie={:?}) has timed out", p.cookie);
-                timed_out_players.push(*p_id);
-            }
-        }
+        // TODO: Move this into a private helper
+        let update_reply_packet = Packet::UpdateReply {
+            cookie:               cookie,
+            last_chat_seq:        Some(0),
+            last_lobby_chat_seq:  None,
+            last_game_update_seq: None,
+            last_full_gen:        None,
+            partial_gen:          None,
+            universe_epoch:       0,
+            pong:                 PingPong::pong(0),
+        };
 
-        for player_id in timed_out_players {
-            self.handle_disconnect(player_id);
-        }
+        let result = server.decode_packet(fake_socket_addr(), update_reply_packet);
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
     }
 
-    /// Creates a new struct representing the global state of this server. Initially, there is one
-    /// room -- "general".
-    pub fn new() -> Self {
-        let mut server_state = ServerState {
-            tick:        0,
-            name:        DEFAULT_NAME.to_owned(),
-            reg_params:  None,
-            players:     HashMap::<PlayerID, Player>::new(),
-            rooms:       HashMap::<RoomID, Room>::new(),
-            player_map:  HashMap::<String, PlayerID>::new(),
-            room_map:    HashMap::<String, RoomID>::new(),
-            network_map: HashMap::<PlayerID, NetworkManager>::new(),
+    #[test]
+    fn decode_packet_update_reply_invalid_cookie() {
+        let mut server = ServerState::new();
+        {
+            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+            player.cookie.clone()
         };
-        server_state.new_room("general".to_owned());
-        server_state
-    }
-
-    fn process_packet(&mut self, packet_tuple: (Packet, SocketAddr)) -> Vec<(Packet, SocketAddr)> {
-        let (packet, addr) = packet_tuple;
 
-        debug!("{:?}", packet);
+        let cookie = "CookieMonster".to_owned();
 
-        // Decode incoming and send a Response to the Requester
-        let decode_result = self.decode_packet(addr, packet.clone());
-        if let Ok(opt_response_packet) = decode_result {
-            if let Some(response_packet) = opt_response_packet {
-                let response = (response_packet, addr.clone());
-                return vec![response];
-            }
-        } else {
-            let err = decode_result.unwrap_err();
-            error!("Decoding packet failed, from {:?}: {:?}", addr, err);
-        }
+        let update_reply_packet = Packet::UpdateReply {
+            cookie:               cookie,
+            last_chat_seq:        Some(0),
+            last_lobby_chat_seq:  None,
+            last_game_update_seq: None,
+            last_full_gen:        None,
+            partial_gen:          None,
+            universe_epoch:       0,
+            pong:                 PingPong::pong(0),
+        };
 
-        vec![]
+        let result = server.decode_packet(fake_socket_addr(), update_reply_packet);
+        assert!(result.is_err());
     }
 
-    fn send_heartbeats(&mut self) -> Vec<(Packet, SocketAddr)> {
-        let mut heartbeats = vec![];
-        for player in self.players.values() {
-            let keep_alive = Packet::Response {
-                sequence:    0,
-                request_ack: None,
-                code:        ResponseCode::KeepAlive,
-            };
-            heartbeats.push((keep_alive, player.addr));
-        }
-        return heartbeats;
+    #[test]
+    fn construct_client_updates_no_rooms() {
+        let mut server = ServerState::new();
+        let updates = server.construct_client_updates();
+        assert!(updates.is_empty());
     }
 
-    fn maintain_network_state(&mut self) -> Vec<(Packet, SocketAddr)> {
-        // Process players in rooms
-        self.process_buffered_packets_in_rooms();
+    #[test]
+    fn construct_client_updates_empty_rooms() {
+        let mut server = ServerState::new();
+        server.create_new_room(None, "some room".to_owned().clone());
+        let updates = server.construct_client_updates();
+        assert!(updates.is_empty());
+    }
 
-        // Process players in lobby
-        self.process_buffered_packets_in_lobby();
+    #[test]
+    fn construct_client_updates_populated_room_returns_all_messages() {
+        let mut server = ServerState::new();
+        let room_name = "some_room";
+        let player_name = "some player".to_owned();
+        let message_text = "Message".to_owned();
 
-        self.collect_expired_tx_packets()
-    }
+        server.create_new_room(None, room_name.to_owned());
 
-    fn garbage_collection(&mut self) -> Vec<(SocketAddr, Packet)> {
-        self.expire_old_messages_in_all_rooms(time::Instant::now());
-        let update_packets_vec = self.construct_client_updates();
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
+        server.join_room(player_id, room_name);
+        server.handle_chat_message(player_id, message_text.clone());
+        server.handle_chat_message(player_id, message_text.clone());
+        server.handle_chat_message(player_id, message_text.clone());
 
-        self.remove_timed_out_clients();
-        self.tick = 1usize.wrapping_add(self.tick);
-        return update_packets_vec;
-    }
-}
+        let mut updates = server.construct_client_updates();
 
-#[derive(Debug, Serialize)]
-struct RegisterRequestBody {
-    host_and_port: String,
-}
+        // Vector should contain a single item for this test
+        assert_eq!(updates.len(), 1);
 
-async fn register(reg_params: &RegistryParams) -> Result<(), Box<dyn Error>> {
-    let req_body = RegisterRequestBody {
-        host_and_port: reg_params.public_addr.clone(),
-    };
-    let response = reqwest::Client::new()
-        .post(reg_params.registry_url.clone())
-        .json(&req_body)
-        .send()
-        .await?;
-    debug!("Response from registration attempt: {:?}", response);
-    if response.status() != reqwest::StatusCode::OK {
-        return Err("failed to register".to_owned().into());
-    }
-    Ok(())
-}
+        let (addr, pkt) = updates.pop().unwrap();
+        assert_eq!(addr, fake_socket_addr());
 
-async fn try_register(reg_params: RegistryParams) {
-    debug!("attempting to register server with {:?}", reg_params.registry_url);
-    for attempt in 1..=REGISTER_RETRIES {
-        match register(&reg_params).await {
-            Ok(_) => {
-                debug!("registration success!");
-                break;
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to register server (was attempt {} of {}): {:?}",
-                    attempt, REGISTER_RETRIES, e
+        match pkt {
+            Packet::Update {
+                chats,
+                game_updates,
+                game_update_seq,
+                universe_update,
+                universe_hash,
+                ping: _,
+            } => {
+                // the roster and the room's settings, sent when the player joined
+                assert_eq!(
+                    game_updates,
+                    vec![
+                        GameUpdate::PlayerList {
+                            players: vec![PlayerInfo::new(player_name.clone(), None, None)],
+                        },
+                        GameUpdate::RoomSettings {
+                            settings: RoomSettings {
+                                team_count:    0,
+                                win_condition: WinCondition::MostLiveCells {
+                                    generations: DEFAULT_MATCH_GENERATIONS,
+                                },
+                                series_length: 1,
+                            },
+                        },
+                    ]
                 );
+                assert_eq!(game_update_seq, Some(1));
+                assert_eq!(universe_update, UniUpdate::NoChange);
+                assert_eq!(universe_hash, None);
+                assert!(!chats.is_empty());
+
+                // All client chat sequence numbers start counting at 1
+                let mut i = 1;
+
+                for msg in chats {
+                    assert_eq!(msg.player_name, player_name);
+                    assert_eq!(msg.chat_seq, Some(i));
+                    assert_eq!(msg.message, message_text);
+                    i += 1;
+                }
             }
+            _ => panic!("Unexpected packet in client update construction!"),
         }
-        TokioTime::sleep(REGISTER_RETRY_SLEEP).await;
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
-    env_logger::Builder::new()
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "{} [{:5}] - {}",
-                Local::now().format("%Y-%m-%dT%H:%M:%S%.6fZ"),
-                record.level(),
-                record.args(),
-            )
-        })
-        .filter(None, LevelFilter::Trace)
-        .filter(Some("futures"), LevelFilter::Off)
-        .filter(Some("tokio_core"), LevelFilter::Off)
-        .filter(Some("tokio_reactor"), LevelFilter::Off)
-        .init();
+    #[test]
+    fn construct_client_updates_populated_room_returns_updates_after_client_acked() {
+        let mut server = ServerState::new();
+        let room_name = "some_room";
+        let player_name = "some player".to_owned();
+        let message_text = "Message".to_owned();
 
-    let matches = App::new("server")
-        .about("game server for Conwayste")
-        .arg(
-            Arg::with_name("address")
-                .short("l")
-                .long("listen")
-                .help(&format!(
-                    "address to listen for connections on [default {}]",
-                    DEFAULT_HOST
-                ))
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("port")
-                .short("p")
-                .long("port")
-                .help(&format!("port to listen for connections on [default {}]", DEFAULT_PORT))
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("name")
-                .long("name")
-                .help(&format!("name of the server [default {}]", DEFAULT_NAME))
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("public-address")
-                .long("public-address")
-                .help("public-facing address for clients to connect to; this gets sent to registrar")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("registrar-url")
-                .long("registrar-url")
-                .help(&format!(
-                    "URL of registrar [default {}]; only used if public-address is set",
-                    REGISTRY_DEFAULT_URL
-                ))
-                .takes_value(true),
-        )
-        .get_matches();
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
+        server.join_room(player_id, room_name);
+        server.handle_chat_message(player_id, message_text.clone());
+        server.handle_chat_message(player_id, message_text.clone());
+        server.handle_chat_message(player_id, message_text.clone());
+
+        // Assume that the client has acknowledged two chats
+        {
+            let player: &mut Player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(2));
+        }
+
+        // We should then only return the last chat
+        let mut updates = server.construct_client_updates();
+
+        // Vector should contain a single item for this test
+        assert_eq!(updates.len(), 1);
+
+        let (addr, pkt) = updates.pop().unwrap();
+        assert_eq!(addr, fake_socket_addr());
+
+        match pkt {
+            Packet::Update {
+                mut chats,
+                game_updates,
+                game_update_seq,
+                universe_update,
+                universe_hash,
+                ping: _,
+            } => {
+                // the roster and the room's settings, sent when the player joined
+                assert_eq!(
+                    game_updates,
+                    vec![
+                        GameUpdate::PlayerList {
+                            players: vec![PlayerInfo::new(player_name.clone(), None, None)],
+                        },
+                        GameUpdate::RoomSettings {
+                            settings: RoomSettings {
+                                team_count:    0,
+                                win_condition: WinCondition::MostLiveCells {
+                                    generations: DEFAULT_MATCH_GENERATIONS,
+                                },
+                                series_length: 1,
+                            },
+                        },
+                    ]
+                );
+                assert_eq!(game_update_seq, Some(1));
+                assert_eq!(universe_update, UniUpdate::NoChange);
+                assert_eq!(universe_hash, None);
+                assert!(!chats.is_empty());
 
-    let opt_host = matches.value_of("address");
-    let opt_port = matches.value_of("port").map(|port_str| {
-        port_str.parse::<u16>().unwrap_or_else(|e| {
-            error!("Error while attempting to parse {:?} as port number: {:?}", port_str, e);
-            exit(1);
-        })
-    });
+                assert_eq!(chats.len(), 1);
+                let msg = chats.pop().unwrap();
 
-    let udp = bind(opt_host, opt_port).await.unwrap_or_else(|e| {
-        error!("Error while trying to bind UDP socket: {:?}", e);
-        exit(1);
-    });
+                assert_eq!(msg.player_name, player_name);
+                assert_eq!(msg.chat_seq, Some(3));
+                assert_eq!(msg.message, message_text);
+            }
+            _ => panic!("Unexpected packet in client update construction!"),
+        }
+    }
 
-    trace!("Listening for connections on {:?}...", udp.local_addr()?);
+    #[test]
+    fn broadcast_message_to_two_players_in_room() {
+        let mut server = ServerState::new();
+        let room_name = "some_room";
+        let player_name = "some player".to_owned();
 
-    let (mut udp_sink, udp_stream) = UdpFramed::new(udp, NetwaystePacketCodec).split();
-    let mut udp_stream = udp_stream.fuse();
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
+        let player_id2: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
 
-    let mut server_state = ServerState::new();
+        server.join_room(player_id, room_name.clone());
+        {
+            let room: &mut Room = server.get_room_mut(player_id).unwrap();
+            room.broadcast("Silver birch against a Swedish sky".to_owned());
+        }
+        server.join_room(player_id2, room_name);
+        let room: &Room = server.get_room(player_id).unwrap();
 
-    if let Some(name) = matches.value_of("name") {
-        server_state.name = name.to_owned();
+        let player = (*server.get_player(player_id)).clone();
+        let msgs = server.collect_unacknowledged_messages(room, &player).unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].message, "Silver birch against a Swedish sky".to_owned());
+
+        let player = (*server.get_player(player_id2)).clone();
+        let msgs = server.collect_unacknowledged_messages(room, &player).unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].message, "Silver birch against a Swedish sky".to_owned());
     }
 
-    if let Some(public_addr) = matches.value_of("public-address") {
-        let mut reg_params = RegistryParams::new(public_addr.to_owned());
-        if let Some(registrar_url) = matches.value_of("registrar-url") {
-            reg_params.registry_url = registrar_url.to_owned();
+    #[test]
+    fn broadcast_message_to_an_empty_room() {
+        let mut server = ServerState::new();
+        let room_name = "some_room".to_owned();
+
+        server.create_new_room(None, room_name.clone());
+        let room_id: &RoomID = server.room_map.get(&room_name.clone()).unwrap();
+
+        {
+            let room: &mut Room = server.rooms.get_mut(&room_id).unwrap();
+            room.broadcast("Silver birch against a Swedish sky".to_owned());
         }
-        server_state.reg_params = Some(reg_params);
+        let room: &Room = server.rooms.get(&room_id).unwrap();
+        assert_eq!(room.latest_seq_num, 1);
+        assert_eq!(room.messages.len(), 1);
+        let msgs: &ServerChatMessage = room.messages.get(0).unwrap();
+        assert_eq!(msgs.player_name, "Server".to_owned());
+        assert_eq!(msgs.seq_num, 1);
+        assert_eq!(msgs.player_id, PlayerID(0xFFFFFFFFFFFFFFFF));
     }
 
-    let tick_interval = TokioTime::interval(Duration::from_millis(TICK_INTERVAL_IN_MS));
-    let mut tick_interval_stream = IntervalStream::new(tick_interval).fuse();
+    #[test]
+    #[should_panic]
+    fn disconnect_get_player_by_id_fails() {
+        let mut server = ServerState::new();
+        let player_name = "some player".to_owned();
 
-    let network_interval = TokioTime::interval(Duration::from_millis(NETWORK_INTERVAL_IN_MS));
-    let mut network_interval_stream = IntervalStream::new(network_interval).fuse();
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
 
-    let heartbeat_interval = TokioTime::interval(Duration::from_millis(HEARTBEAT_INTERVAL_IN_MS));
-    let mut heartbeat_interval_stream = IntervalStream::new(heartbeat_interval).fuse();
+        server.handle_disconnect(player_id);
+        server.get_player(player_id);
+    }
 
-    let register_interval = TokioTime::interval(Duration::from_millis(REGISTER_INTERVAL_IN_MS));
-    let mut register_interval_stream = IntervalStream::new(register_interval).fuse();
+    #[test]
+    fn disconnect_get_player_by_cookie_fails() {
+        let mut server = ServerState::new();
+        let player_name = "some player".to_owned();
 
-    loop {
-        select! {
-            _ = tick_interval_stream.select_next_some() => {
-                let update_packets = server_state.garbage_collection();
-                for (addr, packet) in update_packets {
-                    udp_sink.send((packet, addr)).await?;
-                }
-            },
-            _ = network_interval_stream.select_next_some() => {
-                let retransmissions = server_state.maintain_network_state();
-                for packet_addr_tuple in retransmissions {
-                    udp_sink.send(packet_addr_tuple).await?;
-                }
-            },
-            _ = heartbeat_interval_stream.select_next_some() => {
-                let heartbeats = server_state.send_heartbeats();
-                for packet_addr_tuple in heartbeats {
-                    udp_sink.send(packet_addr_tuple).await?;
-                }
-            },
-            _ = register_interval_stream.select_next_some() => {
-                if let Some(ref reg_params) = server_state.reg_params {
-                    tokio::spawn(try_register(reg_params.clone()));
-                }
-            },
-            addr_packet_result = udp_stream.select_next_some() => {
-                if let Ok(addr_packet_tuple) = addr_packet_result {
-                    let responses = server_state.process_packet(addr_packet_tuple);
-                    for response in responses {
-                        udp_sink.send(response).await?;
-                    }
-                }
-            }
-        }
+        let (player_id, cookie) = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            (player.player_id, player.cookie.clone())
+        };
+
+        server.handle_disconnect(player_id);
+        assert_eq!(server.get_player_id_by_cookie(cookie.as_str()), None);
     }
-}
 
-#[cfg(test)]
-mod netwayste_server_tests {
-    use super::*;
-    use ::proptest::strategy::*;
-    use netwayste::net::NetAttempt;
+    #[test]
+    fn disconnect_while_in_room_removes_all_traces_of_player() {
+        let mut server = ServerState::new();
+        let room_name = "some_room";
+        let player_name = "some player".to_owned();
 
-    fn fake_socket_addr() -> SocketAddr {
-        use std::net::{IpAddr, Ipv4Addr};
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 5678)
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.player_id
+        };
+
+        server.create_new_room(None, room_name.to_owned());
+        server.join_room(player_id, room_name);
+        let room_id = {
+            let room: &Room = server.get_room(player_id).unwrap();
+            assert_eq!(room.player_ids.contains(&player_id), true);
+            room.room_id
+        };
+        server.handle_disconnect(player_id);
+        // Cannot go through player_id because the player has been removed
+        let room: &Room = server.rooms.get(&room_id).unwrap();
+        assert_eq!(room.player_ids.contains(&player_id), false);
     }
 
     #[test]
-    fn list_players_player_shows_up_in_player_list() {
+    fn test_is_previously_processed_packet() {
         let mut server = ServerState::new();
-        let room_name = "some name";
-        // make a new room
-        server.create_new_room(None, String::from(room_name));
+        let player_name = "some player".to_owned();
 
-        let (player_id, player_name) = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(4);
+            player.player_id
+        };
 
-            (p.player_id, p.name.clone())
+        let player_id2: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = None;
+            player.player_id
         };
-        // make the player join the room
-        {
-            server.join_room(player_id, room_name);
-        }
-        let resp_code: ResponseCode = server.list_players(player_id);
-        match resp_code {
-            ResponseCode::PlayerList { players } => {
-                assert_eq!(players.len(), 1);
-                assert_eq!(*players.first().unwrap(), player_name);
-            }
-            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+
+        assert_eq!(server.is_previously_processed_packet(player_id2, 0), false);
+
+        assert_eq!(server.is_previously_processed_packet(player_id, 0), true);
+        assert_eq!(server.is_previously_processed_packet(player_id, 4), true);
+        assert_eq!(server.is_previously_processed_packet(player_id, 5), false);
+    }
+
+    #[test]
+    fn test_clear_transmission_queue_on_ack() {
+        let mut server = ServerState::new();
+        let player_name = "some player".to_owned();
+
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(4);
+            player.player_id
+        };
+
+        for i in 0..5 {
+            let pkt = Packet::Response {
+                sequence:    i,
+                request_ack: None,
+                code:        ResponseCode::OK,
+            };
+
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            nm.tx_packets.buffer_item(pkt.clone());
         }
+
+        server.clear_transmission_queue_on_ack(player_id, None);
+        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 5);
+        server.clear_transmission_queue_on_ack(player_id, Some(0));
+        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 5);
+        server.clear_transmission_queue_on_ack(player_id, Some(1));
+        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 4);
+        server.clear_transmission_queue_on_ack(player_id, Some(5));
+        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 0);
     }
 
     #[test]
-    fn has_chatted_player_did_not_chat_on_join() {
+    fn test_resend_expired_tx_packets() {
         let mut server = ServerState::new();
-        let room_name = "some name";
-        // make a new room
-        server.create_new_room(None, String::from(room_name));
-        let player_id = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
-            p.player_id
+        let player_name = "some player".to_owned();
+
+        let player_id: PlayerID = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(5);
+            player.player_id
         };
-        // make the player join the room
-        {
-            server.join_room(player_id, room_name);
+
+        for i in 0..5 {
+            let pkt = Packet::Response {
+                sequence:    i,
+                request_ack: None,
+                code:        ResponseCode::OK,
+            };
+
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            nm.tx_packets.buffer_item(pkt.clone());
+
+            if i < 3 {
+                let attempt: &mut NetAttempt = nm.tx_packets.attempts.back_mut().unwrap();
+                attempt.time = Instant::now() - Duration::from_secs(i + 1);
+            }
+        }
+
+        let _expired_packets_vec = server.collect_expired_tx_packets();
+
+        for i in 0..5 {
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            let packet_retries: &NetAttempt = nm.tx_packets.attempts.get(i).unwrap();
+
+            if i >= 3 {
+                assert_eq!(packet_retries.retries, 0);
+            } else {
+                assert_eq!(packet_retries.retries, 1);
+            }
         }
-        let player = server.get_player(player_id);
-        assert_eq!(player.has_chatted(), false);
     }
 
     #[test]
-    fn get_confirmed_chat_seq_num_server_tracks_players_chat_updates() {
+    fn test_process_queued_rx_packets_first_non_connect_player_packet() {
         let mut server = ServerState::new();
-        let room_name = "some name";
-        // make a new room
-        server.create_new_room(None, String::from(room_name));
-
-        let (player_id, player_cookie) = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+        let player_name = "some player".to_owned();
 
-            (p.player_id, p.cookie.clone())
+        let (player_id, player_cookie): (PlayerID, String) = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
+            (player.player_id, player.cookie.clone())
         };
-        // make the player join the room
-        {
-            server.join_room(player_id, room_name);
-        }
-
-        // A chat-less player now has something to to say
-        server
-            .decode_packet(
-                fake_socket_addr(),
-                Packet::UpdateReply {
-                    cookie:               player_cookie.clone(),
-                    last_chat_seq:        Some(1),
-                    last_game_update_seq: None,
-                    last_full_gen:        None,
-                    partial_gen:          None,
-                    pong:                 PingPong::pong(0),
-                },
-            )
-            .unwrap();
 
         {
-            let player = server.get_player(player_id);
-            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
-        }
+            let pkt = Packet::Request {
+                cookie:       Some(player_cookie),
+                sequence:     2,
+                response_ack: None,
+                action:       RequestAction::ListPlayers,
+            };
 
-        // Older messages are ignored
-        server
-            .decode_packet(
-                fake_socket_addr(),
-                Packet::UpdateReply {
-                    cookie:               player_cookie.clone(),
-                    last_chat_seq:        Some(0),
-                    last_game_update_seq: None,
-                    last_full_gen:        None,
-                    partial_gen:          None,
-                    pong:                 PingPong::pong(0),
-                },
-            )
-            .unwrap();
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            nm.rx_packets.buffer_item(pkt.clone());
 
-        {
-            let player = server.get_player(player_id);
-            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
+            assert_eq!(nm.tx_packets.len(), 0);
         }
 
-        // So are absent messages
-        server
-            .decode_packet(
-                fake_socket_addr(),
-                Packet::UpdateReply {
-                    cookie:               player_cookie,
-                    last_chat_seq:        None,
-                    last_game_update_seq: None,
-                    last_full_gen:        None,
-                    partial_gen:          None,
-                    pong:                 PingPong::pong(0),
-                },
-            )
-            .unwrap();
+        server.process_queued_rx_packets(player_id);
 
         {
-            let player = server.get_player(player_id);
-            assert_eq!(player.get_confirmed_chat_seq_num(), Some(1));
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            assert_eq!(nm.tx_packets.len(), 1);
         }
     }
 
     #[test]
-    fn get_message_skip_count_player_acked_messages_are_not_included_in_skip_count() {
+    fn test_process_queued_rx_packets_contiguous() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_name = "some player".to_owned();
 
-        server.create_new_room(None, String::from(room_name));
+        let (player_id, player_cookie): (PlayerID, String) = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
+            (player.player_id, player.cookie.clone())
+        };
 
-        let (player_id, _) = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+        for i in 2..10 {
+            let pkt = Packet::Request {
+                cookie:       Some(player_cookie.clone()),
+                sequence:     i,
+                response_ack: None,
+                action:       RequestAction::ListPlayers,
+            };
 
-            (p.player_id, p.cookie.clone())
-        };
-        // make the player join the room
-        // Give it a single message
-        {
-            server.join_room(player_id, room_name);
-            server.handle_chat_message(player_id, "ChatMessage".to_owned());
-        }
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            nm.rx_packets.buffer_item(pkt.clone());
 
-        {
-            let room: &Room = server.get_room(player_id).unwrap();
-            // The check below does not affect any player acknowledgement as we are not
-            // involving the player yet. This is a simple test to ensure that if a chat
-            // message decoded from a would-be player was less than the latest chat message,
-            // we handle it properly by not skipping any.
-            assert_eq!(room.get_message_skip_count(0), 0);
+            assert_eq!(nm.tx_packets.len(), 0);
         }
 
-        let number_of_messages = 6;
-        for _ in 1..number_of_messages {
-            server.handle_chat_message(player_id, "ChatMessage".to_owned());
-        }
+        server.process_queued_rx_packets(player_id);
 
         {
-            //let player = server.get_player_mut(player_id);
-            let player = server.get_player_mut(player_id);
-            // player has not acknowledged any yet
-            #[should_panic]
-            assert_eq!(player.get_confirmed_chat_seq_num(), None);
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            assert_eq!(nm.tx_packets.len(), 8);
         }
+    }
 
-        // player acknowledged four of the six
-        let acked_message_count = {
-            let player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(4));
+    #[test]
+    fn test_process_queued_rx_packets_swiss_cheese_queue() {
+        let mut server = ServerState::new();
+        let player_name = "some player".to_owned();
 
-            player.get_confirmed_chat_seq_num().unwrap()
+        let (player_id, player_cookie): (PlayerID, String) = {
+            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
+            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
+            (player.player_id, player.cookie.clone())
         };
-        {
-            let room: &Room = server.get_room(player_id).unwrap();
-            assert_eq!(room.get_message_skip_count(acked_message_count), acked_message_count);
+
+        for i in [2, 3, 4, 6, 8, 9, 10].iter() {
+            let pkt = Packet::Request {
+                cookie:       Some(player_cookie.clone()),
+                sequence:     *i,
+                response_ack: None,
+                action:       RequestAction::ListPlayers,
+            };
+
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            nm.rx_packets.buffer_item(pkt.clone());
+
+            assert_eq!(nm.tx_packets.len(), 0);
         }
 
-        // player acknowledged all six
-        let acked_message_count = {
-            let player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(6));
+        server.process_queued_rx_packets(player_id);
 
-            player.get_confirmed_chat_seq_num().unwrap()
-        };
         {
-            let room: &Room = server.get_room(player_id).unwrap();
-            assert_eq!(room.get_message_skip_count(acked_message_count), acked_message_count);
+            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
+            assert_eq!(nm.tx_packets.len(), 3); // only 2, 3, and 4 are processed
         }
     }
 
+    fn add_players_to_room(server: &mut ServerState, room_name: &str, count: usize) -> Vec<PlayerID> {
+        server.create_new_room(None, room_name.to_owned());
+        (0..count)
+            .map(|i| {
+                let player_id = server
+                    .add_new_player(format!("player {}", i), fake_socket_addr())
+                    .player_id;
+                server.join_room(player_id, room_name);
+                player_id
+            })
+            .collect()
+    }
+
     #[test]
-    // Send fifteen messages, but only leave nine unacknowledged, while wrapping on the sequence number
-    fn get_message_skip_count_player_acked_messages_are_not_included_in_skip_count_wrapped_case() {
+    fn set_team_room_without_teams_is_rejected() {
         let mut server = ServerState::new();
-        let room_name = "some name";
-
-        server.create_new_room(None, String::from(room_name));
-
-        let player_id = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+        let player_ids = add_players_to_room(&mut server, "teamless", 1);
 
-            p.player_id
-        };
-        {
-            server.join_room(player_id, room_name);
+        match server.set_team(player_ids[0], Some(0)) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+        assert_eq!(server.get_team(player_ids[0]), None);
+    }
 
-        // Picking a value slightly less than max of u64
-        let start_seq_num = u64::max_value() - 6;
-        // First pass, add messages with sequence numbers through the max of u64
-        for seq_num in start_seq_num..u64::max_value() {
-            let room: &mut Room = server.get_room_mut(player_id).unwrap();
-            room.add_message(ServerChatMessage::new(
-                player_id,
-                String::from("some name"),
-                String::from("some msg"),
-                seq_num,
-            ));
-        }
-        // Second pass, from wrap-point, `0`, eight times
-        for seq_num in 0..8 {
-            let room: &mut Room = server.get_room_mut(player_id).unwrap();
-            room.add_message(ServerChatMessage::new(
-                player_id,
-                String::from("some name"),
-                String::from("some msg"),
-                seq_num,
-            ));
+    #[test]
+    fn set_team_out_of_range_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 1);
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+
+        match server.set_team(player_ids[0], Some(2)) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+    }
 
-        let acked_message_count = {
-            // Ack up until 0xFFFFFFFFFFFFFFFD
-            let player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(start_seq_num + 4));
+    #[test]
+    fn set_team_none_balances_teams() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 4);
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
 
-            player.get_confirmed_chat_seq_num().unwrap()
-        };
-        {
-            let room: &Room = server.get_room(player_id).unwrap();
-            // Fifteen total messages sent.
-            // 2 unacked which are less than u64::max_value()
-            // 8 unacked which are after the numerical wrap
-            let unacked_count = 15 - (8 + 2);
-            assert_eq!(room.get_message_skip_count(acked_message_count), unacked_count);
-        }
+        assert_eq!(
+            server.set_team(player_ids[0], None),
+            ResponseCode::JoinedTeam { team: 0 }
+        );
+        assert_eq!(
+            server.set_team(player_ids[1], None),
+            ResponseCode::JoinedTeam { team: 1 }
+        );
+        assert_eq!(
+            server.set_team(player_ids[2], Some(1)),
+            ResponseCode::JoinedTeam { team: 1 }
+        );
+        assert_eq!(
+            server.set_team(player_ids[3], None),
+            ResponseCode::JoinedTeam { team: 0 }
+        );
+
+        let room = server.get_room(player_ids[0]).unwrap();
+        assert_eq!(server.team_sizes(room), vec![2, 2]);
     }
 
     #[test]
-    fn collect_unacknowledged_messages_a_rooms_unacknowledged_chat_messages_are_collected_for_their_player() {
+    fn roster_is_sent_when_players_come_go_or_change_teams() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_ids = add_players_to_room(&mut server, "teams", 2);
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
+        let latest_roster = |server: &ServerState| {
+            server.rooms[&room_id]
+                .game_updates
+                .iter()
+                .rev()
+                .find_map(|(_, update)| match update {
+                    GameUpdate::PlayerList { players } => Some(players.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(latest_roster(&server).len(), 2);
 
-        server.create_new_room(None, String::from(room_name));
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+        server.set_team(player_ids[1], Some(1));
+        assert_eq!(
+            latest_roster(&server),
+            vec![
+                PlayerInfo::new("player 0".to_owned(), None, None),
+                PlayerInfo::new("player 1".to_owned(), None, Some(1)),
+            ]
+        );
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+        server.leave_room(player_ids[0]);
+        assert_eq!(
+            latest_roster(&server),
+            vec![PlayerInfo::new("player 1".to_owned(), None, Some(1))]
+        );
+    }
 
-            p.player_id
-        };
-        {
-            server.join_room(player_id, room_name);
-        }
+    #[test]
+    fn set_team_count_too_many_teams_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 1);
 
-        {
-            // Room has no messages, None to send to player
-            let room = server.get_room(player_id).unwrap();
-            let player = server.get_player(player_id);
-            let messages = server.collect_unacknowledged_messages(room, player);
-            assert_eq!(messages, None);
+        match server.set_team_count(player_ids[0], MAX_TEAMS + 1) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+    }
 
-        {
-            let room: &mut Room = server.get_room_mut(player_id).unwrap();
-            room.add_message(ServerChatMessage::new(
-                player_id,
-                String::from("some name"),
-                String::from("some msg"),
-                1,
-            ));
-        }
-        {
-            // Room has a message, player has yet to ack it
-            let room = server.get_room(player_id).unwrap();
-            let player = server.get_player(player_id);
-            let messages = server.collect_unacknowledged_messages(room, player);
-            assert_eq!(messages.is_some(), true);
-            assert_eq!(messages.unwrap().len(), 1);
-        }
+    #[test]
+    fn set_team_count_only_by_host() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 2);
 
-        {
-            let player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(1));
+        match server.set_team_count(player_ids[1], 2) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
-        {
-            // Room has a message, player acked, None
-            let room = server.get_room(player_id).unwrap();
-            let player = server.get_player(player_id);
-            let messages = server.collect_unacknowledged_messages(room, player);
-            assert_eq!(messages, None);
+        assert_eq!(server.get_room(player_ids[0]).unwrap().team_count, 0);
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+    }
+
+    #[test]
+    fn set_team_while_running_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 2);
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+        server.set_team(player_ids[0], Some(0));
+        server.set_team(player_ids[1], Some(1));
+        server.start_game(player_ids[0]);
+
+        match server.set_team(player_ids[1], Some(0)) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+        assert_eq!(server.get_team(player_ids[1]), Some(1));
     }
 
     #[test]
-    fn collect_unacknowledged_messages_an_active_room_which_expired_all_messages_returns_none() {
+    fn room_settings_are_sent_on_joining_and_when_changed() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_ids = add_players_to_room(&mut server, "teams", 1);
+        let latest_settings = |server: &ServerState| {
+            server
+                .get_room(player_ids[0])
+                .unwrap()
+                .game_updates
+                .iter()
+                .rev()
+                .find_map(|(_, update)| match update {
+                    GameUpdate::RoomSettings { settings } => Some(settings.clone()),
+                    _ => None,
+                })
+        };
+        assert_eq!(latest_settings(&server).map(|settings| settings.team_count), Some(0));
 
-        server.create_new_room(None, String::from(room_name));
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+        assert_eq!(server.set_series_length(player_ids[0], 3), ResponseCode::OK);
+        assert_eq!(
+            server.set_win_condition(player_ids[0], WinCondition::Elimination),
+            ResponseCode::OK
+        );
+        assert_eq!(
+            latest_settings(&server),
+            Some(RoomSettings {
+                team_count:    2,
+                win_condition: WinCondition::Elimination,
+                series_length: 3,
+            })
+        );
+    }
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player(String::from("some name"), fake_socket_addr());
+    #[test]
+    fn set_team_count_lower_removes_players_from_dropped_teams() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 2);
+        assert_eq!(server.set_team_count(player_ids[0], 3), ResponseCode::OK);
+        server.set_team(player_ids[0], Some(0));
+        server.set_team(player_ids[1], Some(2));
+
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+        assert_eq!(server.get_team(player_ids[0]), Some(0));
+        assert_eq!(server.get_team(player_ids[1]), None);
+    }
 
-            p.player_id
-        };
-        {
-            server.join_room(player_id, room_name);
+    #[test]
+    fn team_chat_message_without_team_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 1);
+
+        match server.handle_team_chat_message(player_ids[0], "hi team".to_owned()) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+    }
 
-        {
-            // Add a message to the room and then age it so it will expire
-            let room: &mut Room = server.get_room_mut(player_id).unwrap();
-            room.add_message(ServerChatMessage::new(
-                player_id,
-                String::from("some name"),
-                String::from("some msg"),
-                1,
-            ));
+    #[test]
+    fn team_chat_message_only_sent_to_teammates() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 3);
+        server.set_team_count(player_ids[0], 2);
+        server.set_team(player_ids[0], Some(0));
+        server.set_team(player_ids[1], Some(0));
+        server.set_team(player_ids[2], Some(1));
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
+        // Drop the team change announcements so only the chat is left
+        server.rooms.get_mut(&room_id).unwrap().messages.clear();
 
-            let message: &mut ServerChatMessage = room.messages.get_mut(0).unwrap();
-            let travel_to_the_past = Instant::now().checked_sub(Duration::from_secs(MAX_AGE_CHAT_MESSAGES as u64));
-            if travel_to_the_past.is_none() {
-                warn!("skipping rest of test; cannot travel to the past :(");
-                return;
-            }
-            message.timestamp = travel_to_the_past.unwrap();
-        }
-        {
-            // Sanity check to ensure player gets the chat message if left unacknowledged
-            let room = server.get_room(player_id).unwrap();
-            let player = server.get_player(player_id);
-            let messages = server.collect_unacknowledged_messages(room, player);
-            assert_eq!(messages.is_some(), true);
-            assert_eq!(messages.unwrap().len(), 1);
-        }
-        {
-            let player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(1));
-        }
+        assert_eq!(
+            server.handle_team_chat_message(player_ids[0], "flank left".to_owned()),
+            ResponseCode::OK
+        );
 
-        {
-            // Server drains expired messages for the room
-            server.expire_old_messages_in_all_rooms(time::Instant::now());
+        let room = server.rooms.get(&room_id).unwrap();
+        let teammate_msgs = server
+            .collect_unacknowledged_messages(room, server.get_player(player_ids[1]))
+            .unwrap();
+        assert_eq!(teammate_msgs[0].message, "flank left");
+        assert_eq!(teammate_msgs[0].channel, ChatChannel::Team);
+        let opponent_msgs = server.collect_unacknowledged_messages(room, server.get_player(player_ids[2]));
+        assert!(opponent_msgs.is_none());
+    }
+
+    #[test]
+    fn chat_history_keeps_only_the_latest_messages() {
+        let mut server = ServerState::new();
+        let player_id = add_players_to_room(&mut server, "chatty", 1)[0];
+        let room_id = server.get_room_id(player_id).unwrap();
+        server.rooms.get_mut(&room_id).unwrap().history.clear();
+
+        for i in 0..CHAT_HISTORY_LEN + 5 {
+            assert_eq!(
+                server.handle_chat_message(player_id, format!("msg {}", i)),
+                ResponseCode::OK
+            );
         }
-        {
-            // A room that has no messages, but has player(s) who have acknowledged past messages
-            let room = server.get_room(player_id).unwrap();
-            let player = server.get_player(player_id);
-            let messages = server.collect_unacknowledged_messages(room, player);
-            assert_eq!(messages, None);
+        // Expired messages are still replayed
+        server.rooms.get_mut(&room_id).unwrap().messages.clear();
+
+        match server.chat_history(player_id) {
+            ResponseCode::ChatHistory { messages } => {
+                assert_eq!(messages.len(), CHAT_HISTORY_LEN);
+                assert_eq!(messages[0].message, "msg 5");
+                assert_eq!(
+                    messages.last().unwrap().message,
+                    format!("msg {}", CHAT_HISTORY_LEN + 4)
+                );
+                assert!(messages.windows(2).all(|pair| pair[0].chat_seq < pair[1].chat_seq));
+            }
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn handle_chat_message_player_not_in_game() {
+    fn chat_history_is_cut_to_fit_in_a_packet() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_id = add_players_to_room(&mut server, "chatty", 1)[0];
+        let room_id = server.get_room_id(player_id).unwrap();
+        server.rooms.get_mut(&room_id).unwrap().history.clear();
+
+        let long_message = "x".repeat(MAX_CHAT_HISTORY_LEN / 4);
+        for i in 0..CHAT_HISTORY_LEN {
+            assert_eq!(
+                server.handle_chat_message(player_id, format!("{} {}", i, long_message)),
+                ResponseCode::OK
+            );
+        }
 
-        server.create_new_room(None, room_name.to_owned());
+        match server.chat_history(player_id) {
+            ResponseCode::ChatHistory { messages } => {
+                let len: usize = messages
+                    .iter()
+                    .map(|entry| entry.player_name.len() + entry.message.len())
+                    .sum();
+                assert!(len <= MAX_CHAT_HISTORY_LEN);
+                assert_eq!(messages.len(), 3);
+                // the newest are kept
+                assert!(messages
+                    .last()
+                    .unwrap()
+                    .message
+                    .starts_with(&format!("{} ", CHAT_HISTORY_LEN - 1)));
+            }
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some name".to_owned(), fake_socket_addr());
+    #[test]
+    fn chat_history_hides_other_teams_messages() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "teams", 2);
+        server.set_team_count(player_ids[0], 2);
+        server.set_team(player_ids[0], Some(0));
+        server.set_team(player_ids[1], Some(1));
+        assert_eq!(
+            server.handle_team_chat_message(player_ids[0], "flank left".to_owned()),
+            ResponseCode::OK
+        );
 
-            p.player_id
+        let saw_team_chat = |server: &ServerState, player_id| match server.chat_history(player_id) {
+            ResponseCode::ChatHistory { messages } => messages
+                .iter()
+                .any(|entry| entry.message == "flank left" && entry.channel == ChatChannel::Team),
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         };
+        assert!(saw_team_chat(&server, player_ids[0]));
+        assert!(!saw_team_chat(&server, player_ids[1]));
+    }
 
-        let response = server.handle_chat_message(player_id, "test msg".to_owned());
+    #[test]
+    fn report_player_is_appended_to_report_log() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "reports", 2);
+        let reporter = server.get_player(player_ids[0]).name.clone();
+        let reported = server.get_player(player_ids[1]).name.clone();
         assert_eq!(
-            response,
-            ResponseCode::BadRequest {
-                error_msg: format!("Player {} has not joined a game.", player_id),
-            }
+            server.handle_chat_message(player_ids[1], "rude".to_owned()),
+            ResponseCode::OK
+        );
+        assert_eq!(
+            server.handle_chat_message(player_ids[0], "hey!".to_owned()),
+            ResponseCode::OK
+        );
+        assert_eq!(
+            server.handle_lobby_chat_message(player_ids[1], "also rude".to_owned()),
+            ResponseCode::OK
+        );
+        let path = std::env::temp_dir().join(format!("conwaysted-reports-{}.jsonl", std::process::id()));
+        server.report_log = Some(path.to_string_lossy().into_owned());
+
+        assert_eq!(
+            server.handle_report_player(player_ids[0], reported.clone(), "spam".to_owned()),
+            ResponseCode::OK
         );
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let report: PlayerReport = serde_json::from_str(log.trim_end()).unwrap();
+        assert_eq!(report.reporter, reporter);
+        assert_eq!(report.reported, reported);
+        assert_eq!(report.room, Some("reports".to_owned()));
+        assert_eq!(report.reason, "spam");
+        assert_eq!(report.recent_messages, vec!["rude", "also rude"]);
     }
 
     #[test]
-    fn handle_chat_message_player_in_game_one_message() {
+    fn report_player_needs_another_player() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_ids = add_players_to_room(&mut server, "reports", 1);
+        let name = server.get_player(player_ids[0]).name.clone();
 
-        server.create_new_room(None, room_name.to_owned());
-
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_string(), fake_socket_addr());
+        for player_name in vec![name, "nobody".to_owned()] {
+            match server.handle_report_player(player_ids[0], player_name, String::new()) {
+                ResponseCode::BadRequest { .. } => {}
+                resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+            }
+        }
+    }
 
-            p.player_id
+    #[test]
+    fn typing_is_passed_on_to_the_room_but_throttled() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "typists", 2);
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
+        let typing_updates = |server: &ServerState| {
+            server.rooms[&room_id]
+                .game_updates
+                .iter()
+                .filter(|(_, update)| matches!(update, GameUpdate::PlayerTyping { .. }))
+                .count()
         };
-        server.join_room(player_id, room_name);
 
-        let response = server.handle_chat_message(player_id, "test msg".to_owned());
-        assert_eq!(response, ResponseCode::OK);
-        let room: &Room = server.get_room(player_id).unwrap();
-        assert_eq!(room.messages.len(), 1);
-        assert_eq!(room.latest_seq_num, 1);
-        assert_eq!(room.get_newest_msg(), room.get_oldest_msg());
+        assert_eq!(server.set_typing(player_ids[0]), ResponseCode::OK);
+        assert_eq!(server.set_typing(player_ids[0]), ResponseCode::OK);
+        assert_eq!(typing_updates(&server), 1);
+        assert_eq!(server.set_typing(player_ids[1]), ResponseCode::OK);
+        assert_eq!(typing_updates(&server), 2);
+
+        let lobbyist = server
+            .add_new_player("lobbyist".to_owned(), fake_socket_addr())
+            .player_id;
+        match server.set_typing(lobbyist) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
     }
 
     #[test]
-    fn handle_chat_message_player_in_game_many_messages() {
+    fn chat_history_in_lobby_is_rejected() {
         let mut server = ServerState::new();
-        let room_name = "some name";
-
-        server.create_new_room(None, room_name.to_owned());
-
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        let player_id = server
+            .add_new_player("lobbyist".to_owned(), fake_socket_addr())
+            .player_id;
 
-            p.player_id
-        };
-        server.join_room(player_id, room_name);
+        match server.chat_history(player_id) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
 
-        let response = server.handle_chat_message(player_id, "test msg first".to_owned());
-        assert_eq!(response, ResponseCode::OK);
-        let response = server.handle_chat_message(player_id, "test msg second".to_owned());
-        assert_eq!(response, ResponseCode::OK);
+    #[test]
+    fn decide_winner_most_live_cells_waits_for_last_generation() {
+        let condition = WinCondition::MostLiveCells { generations: 10 };
+        assert_eq!(decide_winner(&condition, 9, &[5, 3]), None);
+        assert_eq!(decide_winner(&condition, 10, &[5, 3]), Some(Some(0)));
+        assert_eq!(decide_winner(&condition, 10, &[4, 4]), Some(None));
+    }
 
-        let room: &Room = server.get_room(player_id).unwrap();
-        assert_eq!(room.messages.len(), 2);
-        assert_eq!(room.latest_seq_num, 2);
+    #[test]
+    fn decide_winner_elimination_needs_a_single_survivor() {
+        let condition = WinCondition::Elimination;
+        assert_eq!(decide_winner(&condition, ELIMINATION_GRACE_GENS - 1, &[0, 3]), None);
+        assert_eq!(decide_winner(&condition, ELIMINATION_GRACE_GENS, &[2, 3]), None);
+        assert_eq!(
+            decide_winner(&condition, ELIMINATION_GRACE_GENS, &[0, 3]),
+            Some(Some(1))
+        );
+        assert_eq!(decide_winner(&condition, ELIMINATION_GRACE_GENS, &[0, 0]), Some(None));
     }
 
     #[test]
-    fn create_new_room_good_case() {
-        {
-            let mut server = ServerState::new();
-            let room_name = "some name".to_owned();
+    fn elimination_game_outlasts_the_grace_period() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        assert_eq!(
+            server.set_win_condition(player_ids[0], WinCondition::Elimination),
+            ResponseCode::OK
+        );
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
 
-            assert_eq!(server.create_new_room(None, room_name), ResponseCode::OK);
+        // Nobody has drawn anything, yet nobody is out until everyone has had the chance to
+        let first_gen = server.rooms[&room_id].universe.as_ref().unwrap().latest_gen();
+        for _ in first_gen..ELIMINATION_GRACE_GENS - 1 {
+            server.advance_games();
         }
-        // Room name length is within bounds
-        {
-            let mut server = ServerState::new();
-            let room_name = "0123456789ABCDEF".to_owned();
-
-            assert_eq!(server.create_new_room(None, room_name), ResponseCode::OK);
+        assert!(server.rooms[&room_id].game_running);
+
+        server.advance_games();
+        let room = &server.rooms[&room_id];
+        assert!(!room.game_running);
+        match room.game_updates.back() {
+            Some((_, GameUpdate::GameFinish { outcome })) => assert_eq!(outcome.winner, None),
+            other @ _ => panic!("Unexpected game update: {:?}", other),
         }
     }
 
     #[test]
-    fn create_new_room_name_is_too_long() {
+    fn standings_are_sorted_best_first() {
+        let sides: Vec<Side> = ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Side {
+                name:           name.to_string(),
+                player_indices: vec![i],
+            })
+            .collect();
+        let names: Vec<String> = standings(&sides, &[1, 7, 3]).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn start_game_queues_game_start() {
         let mut server = ServerState::new();
-        let room_name = "0123456789ABCDEF_#".to_owned();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
 
-        assert_eq!(
-            server.create_new_room(None, room_name),
-            ResponseCode::BadRequest {
-                error_msg: "room name too long; max 16 characters".to_owned(),
-            }
-        );
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+
+        let room = server.get_room(player_ids[0]).unwrap();
+        assert!(room.game_running);
+        assert!(room.universe.is_some());
+        assert_eq!(room.sides.len(), 2);
+        match room.game_updates.back() {
+            Some((_, GameUpdate::GameStart { .. })) => {}
+            other @ _ => panic!("Unexpected game update: {:?}", other),
+        }
+
+        match server.start_game(player_ids[1]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
     }
 
     #[test]
-    fn create_new_room_name_taken() {
+    fn start_game_only_by_host() {
         let mut server = ServerState::new();
-        let room_name = "some room".to_owned();
-        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
-        assert_eq!(
-            server.create_new_room(None, room_name),
-            ResponseCode::BadRequest {
-                error_msg: "room name already in use".to_owned(),
-            }
-        );
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+
+        match server.start_game(player_ids[1]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        assert!(!server.get_room(player_ids[0]).unwrap().game_running);
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
     }
 
     #[test]
-    fn create_new_room_player_already_in_room() {
+    fn start_game_team_game_leaves_out_players_without_team() {
         let mut server = ServerState::new();
-        let room_name = "some room".to_owned();
-        let other_room_name = "another room".to_owned();
-        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
-
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        let player_ids = add_players_to_room(&mut server, "arena", 3);
+        server.set_team_count(player_ids[0], 2);
+        server.set_team(player_ids[0], Some(1));
+        server.set_team(player_ids[1], Some(1));
+
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+
+        let room = server.get_room(player_ids[0]).unwrap();
+        assert_eq!(room.sides[0].player_indices, Vec::<usize>::new());
+        assert_eq!(room.sides[1].player_indices, vec![0, 1]);
+        match room.game_updates.back() {
+            Some((_, GameUpdate::GameStart { players, .. })) => {
+                let indices: Vec<_> = players.iter().map(|p| (p.index(), p.team())).collect();
+                assert_eq!(indices, vec![(Some(0), Some(1)), (Some(1), Some(1))]);
+            }
+            other @ _ => panic!("Unexpected game update: {:?}", other),
+        }
+    }
 
-            p.player_id
+    #[test]
+    fn edit_room_map_only_by_host() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let edit = MapEdit::Walls {
+            cells: vec![(3, 4)],
+            wall:  true,
         };
-        server.join_room(player_id, &room_name);
 
-        assert_eq!(
-            server.create_new_room(Some(player_id), other_room_name),
-            ResponseCode::BadRequest {
-                error_msg: "cannot create room because in-game".to_owned(),
-            }
-        );
+        match server.edit_room_map(player_ids[1], edit.clone()) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        assert_eq!(server.edit_room_map(player_ids[0], edit.clone()), ResponseCode::OK);
+
+        let room = server.get_room(player_ids[0]).unwrap();
+        match room.game_updates.back() {
+            Some((_, GameUpdate::RoomMapEdit { edit: sent })) => assert_eq!(*sent, edit),
+            other @ _ => panic!("Unexpected game update: {:?}", other),
+        }
     }
 
     #[test]
-    fn create_new_room_join_room_good_case() {
+    fn start_game_uses_room_map() {
         let mut server = ServerState::new();
-        let room_name = "some room";
-        assert_eq!(server.create_new_room(None, room_name.to_owned()), ResponseCode::OK);
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let spawn_region = NetRegion::new(10, 20, 30, 40);
+        server.edit_room_map(
+            player_ids[0],
+            MapEdit::Walls {
+                cells: vec![(3, 4)],
+                wall:  true,
+            },
+        );
+        server.edit_room_map(
+            player_ids[0],
+            MapEdit::SpawnRegion {
+                slot:   1,
+                region: Some(spawn_region.clone()),
+            },
+        );
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
 
-            p.player_id
-        };
-        assert_eq!(
-            server.join_room(player_id, room_name),
-            ResponseCode::JoinedRoom {
-                room_name: "some room".to_owned(),
-            }
-        );
+        let room = server.get_room(player_ids[0]).unwrap();
+        let uni = room.universe.as_ref().unwrap();
+        assert_eq!(uni.cell_state(3, 4), CellState::Wall);
+        assert_eq!(uni.cell_state(4, 4), CellState::Dead);
+        assert_eq!(uni.writable_region(1), Region::from(&spawn_region));
     }
 
     #[test]
-    fn join_room_player_already_in_room() {
+    fn edit_room_map_while_running_or_between_rounds_is_rejected() {
         let mut server = ServerState::new();
-        let room_name = "some room";
-        assert_eq!(server.create_new_room(None, room_name.to_owned()), ResponseCode::OK);
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        let edit = MapEdit::Walls {
+            cells: vec![(3, 4)],
+            wall:  true,
+        };
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        match server.edit_room_map(player_ids[0], edit.clone()) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
 
-            p.player_id
+        // In a series' intermission, no game is running but the next round is still to come
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        room.game_running = false;
+        room.series = Some(Series::default());
+        match server.edit_room_map(player_ids[0], edit) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
+
+    #[test]
+    fn edit_room_map_rejects_overlapping_spawn_regions() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let spawn_region = |slot, region| MapEdit::SpawnRegion {
+            slot,
+            region: Some(region),
         };
+
         assert_eq!(
-            server.join_room(player_id, room_name),
-            ResponseCode::JoinedRoom {
-                room_name: "some room".to_owned(),
-            }
+            server.edit_room_map(player_ids[0], spawn_region(0, NetRegion::new(10, 20, 30, 40))),
+            ResponseCode::OK
+        );
+        match server.edit_room_map(player_ids[0], spawn_region(1, NetRegion::new(39, 59, 10, 10))) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        // touching is fine, and so is moving a region over where it was before
+        assert_eq!(
+            server.edit_room_map(player_ids[0], spawn_region(1, NetRegion::new(40, 20, 10, 10))),
+            ResponseCode::OK
         );
         assert_eq!(
-            server.join_room(player_id, room_name),
-            ResponseCode::BadRequest {
-                error_msg: "cannot join game because in-game".to_owned(),
-            }
+            server.edit_room_map(player_ids[0], spawn_region(0, NetRegion::new(5, 20, 30, 40))),
+            ResponseCode::OK
         );
     }
 
     #[test]
-    fn join_room_room_does_not_exist() {
+    fn start_game_lifts_fog_from_fog_free_regions() {
         let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let fog_free_region = NetRegion::new(GAME_WIDTH as i32 / 2 - 4, 10, 8, 4);
+        server.edit_room_map(
+            player_ids[0],
+            MapEdit::FogFreeRegion {
+                slot:   0,
+                region: Some(fog_free_region.clone()),
+            },
+        );
+        match server.edit_room_map(
+            player_ids[0],
+            MapEdit::FogFreeRegion {
+                slot:   MAX_FOG_FREE_REGIONS,
+                region: Some(fog_free_region.clone()),
+            },
+        ) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
 
-            p.player_id
-        };
-        assert_eq!(
-            server.join_room(player_id, "some room"),
-            ResponseCode::BadRequest {
-                error_msg: "no room named \"some room\"".to_owned(),
-            }
-        );
+        let room = server.get_room(player_ids[0]).unwrap();
+        let uni = room.universe.as_ref().unwrap();
+        let region = Region::from(&fog_free_region);
+        for player_id in 0..2 {
+            uni.each_non_dead_full(Some(player_id), &mut |col, row, state| {
+                assert!(state != CellState::Fog || !region.contains(col as isize, row as isize));
+            });
+        }
     }
 
     #[test]
-    fn leave_room_good_case() {
+    fn share_annotation_goes_to_the_room() {
         let mut server = ServerState::new();
-        let room_name = "some name";
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let name = server.get_player(player_ids[1]).name.clone();
+        let annotation = Annotation::Marker {
+            cell:  (3, 4),
+            label: "glider".to_owned(),
+        };
 
-        server.create_new_room(None, room_name.to_owned());
+        assert_eq!(server.share_annotation(player_ids[1], 7, annotation.clone()), ResponseCode::OK);
 
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
+        let room = server.get_room(player_ids[0]).unwrap();
+        match room.game_updates.back() {
+            Some((
+                _,
+                GameUpdate::AnnotationShare {
+                    name: sent_name,
+                    id,
+                    annotation: sent,
+                },
+            )) => {
+                assert_eq!(*sent_name, name);
+                assert_eq!(*id, 7);
+                assert_eq!(*sent, annotation);
+            }
+            other @ _ => panic!("Unexpected game update: {:?}", other),
+        }
+    }
 
-            p.player_id
+    #[test]
+    fn share_annotation_outside_of_universe_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        let arrow = Annotation::Arrow {
+            from: (3, 4),
+            to:   (GAME_WIDTH, 4),
         };
-        server.join_room(player_id, room_name);
 
-        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+        match server.share_annotation(player_ids[0], 0, arrow) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        let lonely = server.add_new_player("lonely".to_owned(), fake_socket_addr()).player_id;
+        let measure = Annotation::Measure {
+            from: (0, 0),
+            to:   (1, 1),
+        };
+        match server.share_annotation(lonely, 0, measure) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
     }
 
     #[test]
-    fn leave_room_player_not_in_room() {
+    fn share_annotation_is_throttled() {
         let mut server = ServerState::new();
-        let room_name = "some room".to_owned();
-        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
-
-        let player_id = {
-            let p: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-
-            p.player_id
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let arrow = Annotation::Arrow {
+            from: (3, 4),
+            to:   (5, 6),
         };
-
-        assert_eq!(
-            server.leave_room(player_id),
-            ResponseCode::BadRequest {
-                error_msg: "cannot leave game because in lobby".to_owned(),
-            }
-        );
+        for id in 0..MAX_ANNOTATION_SHARES_PER_WINDOW {
+            assert_eq!(server.share_annotation(player_ids[0], id, arrow.clone()), ResponseCode::OK);
+        }
+        match server.share_annotation(player_ids[0], 0, arrow.clone()) {
+            ResponseCode::TooManyRequests { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        // Others in the room can still share
+        assert_eq!(server.share_annotation(player_ids[1], 0, arrow), ResponseCode::OK);
     }
 
     #[test]
-    fn leave_room_unregistered_player_id() {
+    fn set_win_condition_only_by_host() {
         let mut server = ServerState::new();
-        let room_name = "some room".to_owned();
-        let rand_player_id = PlayerID(0x2457); //RUST
-        assert_eq!(server.create_new_room(None, room_name.clone()), ResponseCode::OK);
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
 
+        match server.set_win_condition(player_ids[1], WinCondition::Elimination) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
         assert_eq!(
-            server.leave_room(rand_player_id),
-            ResponseCode::BadRequest {
-                error_msg: "cannot leave game because in lobby".to_owned(),
-            }
+            server.set_win_condition(player_ids[0], WinCondition::Elimination),
+            ResponseCode::OK
         );
     }
 
     #[test]
-    fn add_new_player_player_added_with_initial_sequence_number() {
+    fn set_win_condition_while_running_is_rejected() {
         let mut server = ServerState::new();
-        let name = "some player".to_owned();
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        server.start_game(player_ids[0]);
 
-        let p: &mut Player = server.add_new_player(name.clone(), fake_socket_addr());
-        assert_eq!(p.name, name);
+        match server.set_win_condition(player_ids[0], WinCondition::Elimination) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
     }
 
     #[test]
-    fn is_unique_player_name_yes_and_no_case() {
+    fn advance_game_updates_universe_hash_with_scores() {
         let mut server = ServerState::new();
-        let name = "some player".to_owned();
-        assert_eq!(server.is_unique_player_name("some player"), true);
-
-        {
-            server.add_new_player(name.clone(), fake_socket_addr());
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        server.start_game(player_ids[0]);
+
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        let start_hash = room.universe_hash.unwrap();
+        assert_eq!(start_hash.generation, 1);
+        for _ in 0..SCORE_UPDATE_INTERVAL_IN_GENS - 2 {
+            room.advance_game();
         }
-        assert_eq!(server.is_unique_player_name("some player"), false);
+        assert_eq!(room.universe_hash, Some(start_hash));
+        room.advance_game();
+        let uni = room.universe.as_ref().unwrap();
+        assert_eq!(
+            room.universe_hash,
+            Some(UniverseHash {
+                generation: SCORE_UPDATE_INTERVAL_IN_GENS as u32,
+                hash:       uni.state_hash(),
+            })
+        );
     }
 
     #[test]
-    fn expire_old_messages_in_all_rooms_room_is_empty() {
+    fn advance_game_most_live_cells_finishes_with_winner() {
         let mut server = ServerState::new();
-        let room_name = "some room";
-
-        server.create_new_room(None, room_name.to_owned().clone());
-        server.expire_old_messages_in_all_rooms(time::Instant::now());
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let condition = WinCondition::MostLiveCells { generations: 3 };
+        assert_eq!(server.set_win_condition(player_ids[0], condition), ResponseCode::OK);
+        server.start_game(player_ids[0]);
 
-        for room in server.rooms.values() {
-            assert_eq!(room.messages.len(), 0);
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        {
+            // A block is a still life, so player 0 keeps 4 cells
+            let uni = room.universe.as_mut().unwrap();
+            for &(col, row) in &[(10, 10), (11, 10), (10, 11), (11, 11)] {
+                uni.toggle(col, row, 0).unwrap();
+            }
+        }
+        room.advance_game();
+        assert!(room.game_running);
+        room.advance_game();
+        assert!(!room.game_running);
+
+        match room.game_updates.back() {
+            Some((_, GameUpdate::GameFinish { outcome })) => {
+                assert_eq!(outcome.winner, Some("player 0".to_owned()));
+                assert_eq!(outcome.winning_players, vec!["player 0".to_owned()]);
+                assert_eq!(outcome.standings[0].score, 4);
+                assert_eq!(outcome.standings[1].score, 0);
+            }
+            other @ _ => panic!("Unexpected game update: {:?}", other),
         }
     }
 
     #[test]
-    fn expire_old_messages_in_all_rooms_one_room_good_case() {
+    fn advance_game_team_win_names_the_teams_players() {
         let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 3);
+        server.set_team_count(player_ids[0], 2);
+        server.set_team(player_ids[0], Some(1));
+        server.set_team(player_ids[1], Some(0));
+        server.set_team(player_ids[2], Some(1));
+        let condition = WinCondition::MostLiveCells { generations: 3 };
+        assert_eq!(server.set_win_condition(player_ids[0], condition), ResponseCode::OK);
+        server.start_game(player_ids[0]);
+
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        {
+            // A block is a still life, so player 0's team keeps 4 cells
+            let uni = room.universe.as_mut().unwrap();
+            let region = uni.writable_region(0);
+            let (col, row) = (region.left() as usize + 1, region.top() as usize + 1);
+            for &(col, row) in &[(col, row), (col + 1, row), (col, row + 1), (col + 1, row + 1)] {
+                uni.toggle(col, row, 0).unwrap();
+            }
+        }
+        room.advance_game();
+        room.advance_game();
+        assert!(!room.game_running);
+
+        match room.game_updates.back() {
+            Some((_, GameUpdate::GameFinish { outcome })) => {
+                assert_eq!(outcome.winner, Some("Team 2".to_owned()));
+                assert_eq!(
+                    outcome.winning_players,
+                    vec!["player 0".to_owned(), "player 2".to_owned()]
+                );
+            }
+            other @ _ => panic!("Unexpected game update: {:?}", other),
+        }
+    }
 
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
-        };
-
-        server.join_room(player_id, "general");
+    #[test]
+    fn finished_game_is_added_to_leaderboards() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        server.add_bot(player_ids[0]);
+        let condition = WinCondition::MostLiveCells { generations: 3 };
+        assert_eq!(server.set_win_condition(player_ids[0], condition), ResponseCode::OK);
+        server.start_game(player_ids[0]);
+        {
+            let room = server.get_room_mut(player_ids[0]).unwrap();
+            let uni = room.universe.as_mut().unwrap();
+            for &(col, row) in &[(10, 10), (11, 10), (10, 11), (11, 11)] {
+                uni.toggle(col, row, 0).unwrap();
+            }
+        }
+        server.advance_games();
+        server.advance_games();
 
-        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
-        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
-        server.handle_chat_message(player_id, "It is free!".to_owned());
-        server.handle_chat_message(player_id, "What's not to love?".to_owned());
+        let entries = server.leaderboard.top(None, LEADERBOARD_LEN);
+        assert_eq!(entries.len(), 2); // the bot isn't ranked
+        assert_eq!(
+            entries[0],
+            LeaderboardEntry {
+                player_name:      "player 0".to_owned(),
+                wins:             1,
+                cells_captured:   4,
+                longest_survival: 3,
+            }
+        );
+        assert_eq!(entries[1].player_name, "player 1");
+        assert_eq!(entries[1].wins, 0);
+        assert_eq!(server.leaderboard.top(Some("arena"), LEADERBOARD_LEN), entries);
+        assert!(server.save_leaderboard);
+        assert_eq!(server.take_unsaved_leaderboard(), None); // no file to save to
+        assert!(!server.save_leaderboard);
+        assert_eq!(
+            server.get_leaderboard(Some("elsewhere".to_owned())),
+            ResponseCode::Leaderboard {
+                room_name: Some("elsewhere".to_owned()),
+                entries:   vec![],
+            }
+        );
+    }
 
-        let message_count = {
-            let room: &Room = server.get_room(player_id).unwrap();
-            room.messages.len()
+    #[test]
+    fn leaderboard_ranks_by_wins_then_cells_and_survives_save() {
+        let result = |name: &str, won, cells_captured, survival| GameResult {
+            name: name.to_owned(),
+            won,
+            cells_captured,
+            survival,
         };
-        assert_eq!(message_count, 4);
-
-        // Messages are not old enough to be expired
-        server.expire_old_messages_in_all_rooms(time::Instant::now());
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record("arena", &[result("ann", true, 10, 500), result("bea", false, 90, 900)]);
+        leaderboard.record("dojo", &[result("bea", true, 5, 100), result("cal", true, 50, 1000)]);
+        let names = |entries: Vec<LeaderboardEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.player_name).collect()
+        };
+        assert_eq!(names(leaderboard.top(None, 10)), vec!["bea", "cal", "ann"]);
+        assert_eq!(names(leaderboard.top(None, 2)), vec!["bea", "cal"]);
+        assert_eq!(names(leaderboard.top(Some("arena"), 10)), vec!["ann", "bea"]);
+        assert_eq!(leaderboard.top(None, 10)[0].longest_survival, 900);
+
+        let path = std::env::temp_dir().join(format!("conwaysted-leaderboard-{}.json", new_uuid()));
+        assert_eq!(Leaderboard::load(&path).unwrap(), Leaderboard::default());
+        leaderboard.save(&path).unwrap();
+        assert_eq!(Leaderboard::load(&path).unwrap(), leaderboard);
+        std::fs::remove_file(&path).unwrap();
+    }
 
-        for room in server.rooms.values() {
-            assert_eq!(room.messages.len(), 4);
+    #[test]
+    fn share_pattern_is_throttled() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let glider = "#N Glider\nx = 3, y = 3\nbo$2bo$3o!\n";
+        for i in 0..MAX_PATTERN_SHARES_PER_WINDOW {
+            match server.share_pattern(player_ids[0], format!("Glider {}", i), glider.to_owned()) {
+                ResponseCode::PatternShared { .. } => {}
+                resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+            }
+        }
+        match server.share_pattern(player_ids[0], "One more".to_owned(), glider.to_owned()) {
+            ResponseCode::TooManyRequests { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        // Others can still share
+        match server.share_pattern(player_ids[1], "Glider".to_owned(), glider.to_owned()) {
+            ResponseCode::PatternShared { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn expire_old_messages_in_all_rooms_several_rooms_good_case() {
+    fn shared_pattern_can_be_listed_and_downloaded() {
         let mut server = ServerState::new();
-        let room_name = "some room";
-        let room_name2 = "some room2";
-
-        let room_id = server.new_room(room_name.to_owned());
-        let room_id2 = server.new_room(room_name2.to_owned());
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
-        };
-        let player_id2: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let glider = "#N Glider\nx = 3, y = 3\nbo$2bo$3o!\n".to_owned();
+        let share = RequestAction::SharePattern {
+            name: " Glider ".to_owned(),
+            rle:  glider.clone(),
         };
+        assert_eq!(
+            server.process_request_action(player_ids[0], share),
+            ResponseCode::PatternShared {
+                pattern_id: 0,
+                name:       "Glider".to_owned(),
+            }
+        );
 
-        server.join_room(player_id, room_name);
-        server.join_room(player_id2, room_name2);
-
-        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
-        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
-        server.handle_chat_message(player_id2, "It is free!".to_owned());
-        server.handle_chat_message(player_id2, "What's not to love?".to_owned());
-
-        let message_count = {
-            let room: &Room = server.get_room(player_id).unwrap();
-            room.messages.len()
+        let info = SharedPatternInfo {
+            pattern_id: 0,
+            name:       "Glider".to_owned(),
+            author:     "player 0".to_owned(),
+            width:      3,
+            height:     3,
         };
-        assert_eq!(message_count, 2);
-        let message_count2 = {
-            let room: &Room = server.get_room(player_id2).unwrap();
-            room.messages.len()
+        assert_eq!(
+            server.process_request_action(player_ids[1], RequestAction::ListSharedPatterns { offset: 0 }),
+            ResponseCode::SharedPatternList {
+                offset:   0,
+                total:    1,
+                patterns: vec![info.clone()],
+            }
+        );
+        assert_eq!(
+            server.process_request_action(player_ids[1], RequestAction::GetSharedPattern { pattern_id: 0 }),
+            ResponseCode::SharedPattern { info, rle: glider }
+        );
+        match server.process_request_action(player_ids[1], RequestAction::GetSharedPattern { pattern_id: 1 }) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
+
+    #[test]
+    fn shared_patterns_are_limited_in_size_and_number() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        let mut share = |name: &str, rle: String| server.share_pattern(player_ids[0], name.to_owned(), rle);
+        let bad = |resp_code: ResponseCode| match resp_code {
+            ResponseCode::BadRequest { .. } => true,
+            _ => false,
         };
-        assert_eq!(message_count2, 2);
+        let block = || "x = 2, y = 2\n2o$2o!\n".to_owned();
+        assert!(bad(share("", block())));
+        assert!(bad(share(&"x".repeat(MAX_SHARED_PATTERN_NAME_LEN + 1), block())));
+        assert!(bad(share("not RLE", "hello".to_owned())));
+        assert!(bad(share("too wide", "x = 2, y = 1\n3o!\n".to_owned())));
+        let huge = format!("x = 1000, y = 1000\n{}!\n", "bo".repeat(MAX_SHARED_PATTERN_LEN));
+        assert!(bad(share("huge", huge)));
+
+        assert!(!server.save_patterns);
+        server.max_patterns = 3;
+        for i in 0..5 {
+            let resp_code = server.share_pattern(player_ids[0], format!("block {}", i), block());
+            assert!(!bad(resp_code));
+        }
+        let names: Vec<String> = server
+            .shared_patterns
+            .page(0, SHARED_PATTERN_PAGE_LEN)
+            .into_iter()
+            .map(|info| info.name)
+            .collect();
+        assert_eq!(names, vec!["block 4", "block 3", "block 2"]);
+        assert_eq!(server.shared_patterns.page(2, SHARED_PATTERN_PAGE_LEN).len(), 1);
+        assert!(server.save_patterns);
+        assert_eq!(server.take_unsaved_patterns(), None); // no file to save to
+        assert!(!server.save_patterns);
+        server.patterns_file = Some("patterns.json".to_owned());
+        server.get_player_mut(player_ids[0]).pattern_shares = None;
+        server.share_pattern(player_ids[0], "block 5".to_owned(), block());
+        assert_eq!(
+            server.take_unsaved_patterns(),
+            Some((server.shared_patterns.clone(), "patterns.json".to_owned()))
+        );
 
-        // Messages are not old enough to be expired
-        server.expire_old_messages_in_all_rooms(time::Instant::now());
+        server.max_patterns = 0;
+        server.get_player_mut(player_ids[0]).pattern_shares = None; // not throttled, so it's the limit that refuses
+        assert!(bad(server.share_pattern(player_ids[0], "block".to_owned(), block())));
+    }
 
-        assert_eq!(server.rooms[&room_id].messages.len(), 2);
-        assert_eq!(server.rooms[&room_id2].messages.len(), 2);
+    #[test]
+    fn shared_patterns_survive_save() {
+        let mut shared_patterns = SharedPatterns::default();
+        shared_patterns
+            .add("Blinker", "ann", "x = 3, y = 1\n3o!\n".to_owned(), 10)
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("conwaysted-shared-patterns-{}.json", new_uuid()));
+        assert_eq!(SharedPatterns::load(&path).unwrap(), SharedPatterns::default());
+        shared_patterns.save(&path).unwrap();
+        let loaded = SharedPatterns::load(&path).unwrap();
+        assert_eq!(loaded, shared_patterns);
+        std::fs::remove_file(&path).unwrap();
     }
 
     #[test]
-    fn expire_old_messages_in_all_rooms_one_room_old_messages_are_wiped() {
+    fn construct_client_updates_resends_game_updates_until_acked() {
         let mut server = ServerState::new();
-        let room_name = "some room";
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        server.start_game(player_ids[0]);
+        server
+            .get_player_mut(player_ids[0])
+            .update_chat_seq_num(Some(u64::max_value()));
 
-        server.create_new_room(None, room_name.to_owned().clone());
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
+        let updates = server.construct_client_updates();
+        assert_eq!(updates.len(), 1);
+        let (_, packet) = &updates[0];
+        let first_seq = match packet {
+            Packet::Update {
+                game_updates,
+                game_update_seq,
+                ..
+            } => {
+                // the roster and settings from joining, the roster from starting, then the game start
+                assert_eq!(game_updates.len(), 4);
+                game_update_seq.unwrap()
+            }
+            _ => panic!("Unexpected packet: {:?}", packet),
         };
 
-        server.join_room(player_id, room_name);
+        server
+            .get_player_mut(player_ids[0])
+            .update_game_update_seq_num(Some(first_seq + 3));
+        assert!(server.construct_client_updates().is_empty());
+    }
 
-        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
-        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
-        server.handle_chat_message(player_id, "It is free!".to_owned());
-        server.handle_chat_message(player_id, "What's not to love?".to_owned());
+    #[test]
+    fn series_add_round_accumulates_scores() {
+        let mut series = Series::default();
+        let score = |name: &str, score| PlayerScore {
+            name: name.to_owned(),
+            score,
+        };
+        series.add_round(&[score("a", 5), score("b", 3)]);
+        series.add_round(&[score("b", 4), score("a", 1)]);
 
-        let current_timestamp = Instant::now();
-        let travel_to_the_past = current_timestamp.checked_sub(Duration::from_secs((MAX_AGE_CHAT_MESSAGES + 1) as u64));
-        if travel_to_the_past.is_none() {
-            warn!("skipping rest of test; cannot travel to the past :(");
-            return;
+        assert_eq!(series.standings, vec![score("b", 7), score("a", 6)]);
+    }
+
+    #[test]
+    fn set_series_length_only_by_host() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+
+        match server.set_series_length(player_ids[1], 3) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
-        let travel_to_the_past = travel_to_the_past.unwrap();
-        for ref mut room in server.rooms.values_mut() {
-            println!("Room: {:?}", room.name);
-            for m in room.messages.iter_mut() {
-                println!(
-                    "{:?}, {:?},       {:?}",
-                    m.timestamp,
-                    travel_to_the_past,
-                    m.timestamp - travel_to_the_past
-                );
-                m.timestamp = travel_to_the_past;
+        assert_eq!(server.get_room(player_ids[0]).unwrap().series_length, 1);
+        assert_eq!(server.set_series_length(player_ids[0], 3), ResponseCode::OK);
+    }
+
+    #[test]
+    fn set_series_length_out_of_range_is_rejected() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+
+        for &rounds in &[0, MAX_SERIES_ROUNDS + 1] {
+            match server.set_series_length(player_ids[0], rounds) {
+                ResponseCode::BadRequest { .. } => {}
+                resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
             }
         }
+    }
 
-        // Messages are not old enough to be expired
-        server.expire_old_messages_in_all_rooms(time::Instant::now());
-
-        for room in server.rooms.values() {
-            assert_eq!(room.messages.len(), 0);
+    #[test]
+    fn series_plays_every_round_then_finishes() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let condition = WinCondition::MostLiveCells { generations: 2 };
+        server.set_win_condition(player_ids[0], condition);
+        assert_eq!(server.set_series_length(player_ids[0], 2), ResponseCode::OK);
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
+
+        // Round 1 ends after one generation, then the intermission runs before round 2 starts
+        server.advance_games();
+        {
+            let room = server.rooms.get(&room_id).unwrap();
+            assert!(!room.game_running);
+            match room.game_updates.back() {
+                Some((
+                    _,
+                    GameUpdate::Intermission {
+                        round: 1, rounds: 2, ..
+                    },
+                )) => {}
+                other @ _ => panic!("Unexpected game update: {:?}", other),
+            }
+        }
+        for _ in 0..INTERMISSION_GENS {
+            server.advance_games();
+        }
+        assert!(server.rooms.get(&room_id).unwrap().game_running);
+        assert_eq!(server.rooms.get(&room_id).unwrap().series.as_ref().unwrap().round, 2);
+
+        server.advance_games();
+        let room = server.rooms.get(&room_id).unwrap();
+        assert!(room.series.is_none());
+        match room.game_updates.back() {
+            Some((_, GameUpdate::SeriesFinish { outcome })) => assert_eq!(outcome.standings.len(), 2),
+            other @ _ => panic!("Unexpected game update: {:?}", other),
         }
     }
 
     #[test]
-    fn expire_old_messages_in_all_rooms_several_rooms_old_messages_are_wiped() {
+    fn series_keeps_the_first_rounds_players_and_teams() {
         let mut server = ServerState::new();
-        let room_name = "some room";
-        let room_name2 = "some room 2";
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+        let condition = WinCondition::MostLiveCells { generations: 2 };
+        server.set_win_condition(player_ids[0], condition);
+        assert_eq!(server.set_team_count(player_ids[0], 2), ResponseCode::OK);
+        server.set_team(player_ids[0], Some(0));
+        server.set_team(player_ids[1], Some(1));
+        assert_eq!(server.set_series_length(player_ids[0], 2), ResponseCode::OK);
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+        let room_id = server.get_room_id(player_ids[0]).unwrap();
+        server.advance_games();
+
+        // During the intermission, a newcomer picks a team, and a player switches teams
+        let newcomer_id = server
+            .add_new_player("newcomer".to_owned(), fake_socket_addr())
+            .player_id;
+        server.join_room(newcomer_id, "arena");
+        server.set_team(newcomer_id, Some(0));
+        server.set_team(player_ids[1], Some(0));
+        match server.set_team_count(player_ids[0], 3) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        for _ in 0..INTERMISSION_GENS {
+            server.advance_games();
+        }
 
-        server.create_new_room(None, room_name.to_owned().clone());
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
-        };
-        server.create_new_room(None, room_name2.to_owned().clone());
-        let player_id2: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
+        let room = server.rooms.get(&room_id).unwrap();
+        assert!(room.game_running);
+        let roster = server.roster(room);
+        let seat = |name: &str| {
+            let player = roster.iter().find(|player| player.name() == name).unwrap();
+            let index = player.index().map(|i| i as usize);
+            let team = index.and_then(|i| room.sides.iter().position(|side| side.player_indices.contains(&i)));
+            (index, team)
         };
+        assert_eq!(seat("player 0"), (Some(0), Some(0)));
+        assert_eq!(seat("player 1"), (Some(1), Some(1)));
+        assert_eq!(seat("newcomer"), (None, None)); // only watches
+    }
 
-        server.join_room(player_id, room_name);
-        server.join_room(player_id2, room_name);
+    #[test]
+    fn game_options_rotated_regions_shift_starting_positions() {
+        let options = GameOptions::free_for_all(GAME_WIDTH, GAME_HEIGHT, GAME_HISTORY, GAME_FOG_RADIUS, 2);
+        let rotated = options.clone().with_rotated_regions(1);
 
-        server.handle_chat_message(player_id, "Conwayste is such a fun game".to_owned());
-        server.handle_chat_message(player_id, "There are not loot boxes".to_owned());
-        server.handle_chat_message(player_id2, "It is free!".to_owned());
-        server.handle_chat_message(player_id2, "What's not to love?".to_owned());
+        assert_eq!(rotated.writable_region(0, None), options.writable_region(1, None));
+        assert_eq!(rotated.writable_region(1, None), options.writable_region(0, None));
+    }
 
-        let current_timestamp = Instant::now();
-        let travel_to_the_past = current_timestamp.checked_sub(Duration::from_secs((MAX_AGE_CHAT_MESSAGES + 1) as u64));
-        if travel_to_the_past.is_none() {
-            warn!("skipping rest of test; cannot travel to the past :(");
-            return;
-        }
-        let travel_to_the_past = travel_to_the_past.unwrap();
-        for ref mut room in server.rooms.values_mut() {
-            println!("Room: {:?}", room.name);
-            for m in room.messages.iter_mut() {
-                println!(
-                    "{:?}, {:?},       {:?}",
-                    m.timestamp,
-                    travel_to_the_past,
-                    m.timestamp - travel_to_the_past
-                );
-                m.timestamp = travel_to_the_past;
+    #[test]
+    fn add_bot_shows_up_in_player_list() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+
+        assert_eq!(server.add_bot(player_ids[0]), ResponseCode::OK);
+        assert_eq!(server.add_bot(player_ids[0]), ResponseCode::OK);
+
+        match server.list_players(player_ids[1]) {
+            ResponseCode::PlayerList { players } => {
+                assert!(players.contains(&"Bot 1".to_owned()));
+                assert!(players.contains(&"Bot 2".to_owned()));
+                assert_eq!(players.len(), 4);
             }
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+    }
 
-        // Messages are not old enough to be expired
-        server.expire_old_messages_in_all_rooms(time::Instant::now());
+    #[test]
+    fn add_bot_and_remove_bot_only_by_host() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
 
-        for room in server.rooms.values() {
-            assert_eq!(room.messages.len(), 0);
+        match server.add_bot(player_ids[1]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        server.add_bot(player_ids[0]);
+        match server.remove_bot(player_ids[1], "Bot 1") {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+
+        assert_eq!(server.remove_bot(player_ids[0], "Bot 1"), ResponseCode::OK);
+        assert!(server.get_room(player_ids[0]).unwrap().bots.is_empty());
+        match server.remove_bot(player_ids[0], "Bot 1") {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn handle_new_connection_good_case() {
+    fn set_generation_rate_only_by_host_and_in_range() {
         let mut server = ServerState::new();
-        let player_name = "some name".to_owned();
-        let pkt = server.handle_new_connection(player_name, fake_socket_addr());
-        match pkt {
-            Packet::Response {
-                sequence: _,
-                request_ack: _,
-                code,
-            } => match code {
-                ResponseCode::LoggedIn {
-                    cookie: _,
-                    server_version: _,
-                } => {}
-                _ => panic!("Unexpected ResponseCode: {:?}", code),
-            },
-            _ => panic!("Unexpected Packet Type: {:?}", pkt),
+        let player_ids = add_players_to_room(&mut server, "arena", 2);
+
+        for &(player_id, gens_per_sec) in &[(player_ids[1], 20), (player_ids[0], 0), (player_ids[0], 255)] {
+            match server.set_generation_rate(player_id, gens_per_sec) {
+                ResponseCode::BadRequest { .. } => {}
+                resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+            }
         }
+        assert_eq!(server.set_generation_rate(player_ids[0], 20), ResponseCode::OK);
+        assert_eq!(server.get_room(player_ids[0]).unwrap().generations_per_sec, 20);
     }
 
     #[test]
-    fn handle_new_connection_player_name_taken() {
+    fn tick_games_advances_each_room_at_its_own_rate() {
         let mut server = ServerState::new();
-        let player_name = "some name".to_owned();
-
-        let pkt = server.handle_new_connection(player_name.clone(), fake_socket_addr());
-        match pkt {
-            Packet::Response {
-                sequence: _,
-                request_ack: _,
-                code,
-            } => match code {
-                ResponseCode::LoggedIn {
-                    cookie: _,
-                    server_version,
-                } => assert_eq!(server_version, VERSION.to_owned()),
-                _ => panic!("Unexpected ResponseCode: {:?}", code),
-            },
-            _ => panic!("Unexpected Packet Type: {:?}", pkt),
+        let slow_player = add_players_to_room(&mut server, "slow", 1)[0];
+        let fast_player = add_players_to_room(&mut server, "fast", 1)[0];
+        assert_eq!(server.set_generation_rate(fast_player, 50), ResponseCode::OK);
+        for &player_id in &[slow_player, fast_player] {
+            assert_eq!(server.start_game(player_id), ResponseCode::OK);
         }
+        let latest_gen = |server: &ServerState, player_id| {
+            server
+                .get_room(player_id)
+                .unwrap()
+                .universe
+                .as_ref()
+                .unwrap()
+                .latest_gen()
+        };
+        let (slow_start, fast_start) = (latest_gen(&server, slow_player), latest_gen(&server, fast_player));
 
-        let pkt = server.handle_new_connection(player_name, fake_socket_addr());
-        match pkt {
-            Packet::Response {
-                sequence: _,
-                request_ack: _,
-                code,
-            } => match code {
-                ResponseCode::Unauthorized { error_msg } => {
-                    assert_eq!(error_msg, "not a unique name".to_owned());
-                }
-                _ => panic!("Unexpected ResponseCode: {:?}", code),
-            },
-            _ => panic!("Unexpected Packet Type: {:?}", pkt),
+        // One second of 10 ms ticks
+        for _ in 0..100 {
+            server.tick_games(10);
         }
-    }
+        assert_eq!(
+            latest_gen(&server, slow_player) - slow_start,
+            DEFAULT_GENERATIONS_PER_SEC as usize
+        );
+        assert_eq!(latest_gen(&server, fast_player) - fast_start, 50);
 
-    fn a_request_action_strat() -> BoxedStrategy<RequestAction> {
-        prop_oneof![
-            //Just(RequestAction::Disconnect), // not yet implemented
-            //Just(RequestAction::KeepAlive),  // same
-            Just(RequestAction::LeaveRoom),
-            Just(RequestAction::ListPlayers),
-            Just(RequestAction::ListRooms),
-            Just(RequestAction::None),
-        ]
-        .boxed()
+        // A long stall doesn't make a room race through everything it missed
+        server.tick_games(10_000);
+        assert_eq!(
+            latest_gen(&server, fast_player) - fast_start,
+            50 + MAX_GENERATIONS_PER_TICK
+        );
     }
 
-    fn a_request_action_complex_strat() -> BoxedStrategy<RequestAction> {
-        prop_oneof![
-            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::ChatMessage { message: a }),
-            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::NewRoom { room_name: a }),
-            ("([A-Z]{1,4} [0-9]{1,2}){3}").prop_map(|a| RequestAction::JoinRoom { room_name: a }),
-            ("([A-Z]{1,4} [0-9]{1,2}){3}", "[0-9].[0-9].[0-9]").prop_map(|(a, b)| {
-                RequestAction::Connect {
-                    name:           a,
-                    client_version: b,
-                }
-            })
-        ]
-        .boxed()
-    }
+    #[test]
+    fn add_bot_joins_smallest_team_and_is_dealt_out_again_on_team_count_change() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        server.set_team_count(player_ids[0], 2);
+        server.set_team(player_ids[0], Some(0));
 
-    // These tests are checking that we do not panic on each RequestAction
-    proptest! {
-        #[test]
-        fn process_request_action_simple(ref request in a_request_action_strat()) {
-            let mut server = ServerState::new();
-            server.create_new_room(None, "some room".to_owned().clone());
-            let player_id: PlayerID = {
-                let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-                player.player_id
-            };
-            server.process_request_action(player_id, request.to_owned());
+        server.add_bot(player_ids[0]);
+        server.add_bot(player_ids[0]);
+        {
+            let room = server.get_room(player_ids[0]).unwrap();
+            assert_eq!(room.bots[0].team, Some(1));
+            assert_eq!(room.bots[1].team, Some(0));
+            assert_eq!(server.team_sizes(room), vec![2, 1]);
         }
 
-        #[test]
-        fn process_request_action_complex(ref request in a_request_action_complex_strat()) {
-            let mut server = ServerState::new();
-            server.create_new_room(None, "some room".to_owned().clone());
-            let player_id: PlayerID = {
-                let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-                player.player_id
-            };
-            server.process_request_action(player_id, request.to_owned());
-        }
+        server.set_team_count(player_ids[0], 0);
+        let room = server.get_room(player_ids[0]).unwrap();
+        assert!(room.bots.iter().all(|bot| bot.team.is_none()));
     }
 
     #[test]
-    fn process_request_action_connect_while_connected() {
+    fn start_game_bots_play_their_turns() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-        server.create_new_room(None, "some room".to_owned().clone());
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
-        };
-        let result = server.process_request_action(
-            player_id,
-            RequestAction::Connect {
-                name:           player_name,
-                client_version: "0.1.0".to_owned(),
-            },
-        );
-        assert_eq!(
-            result,
-            ResponseCode::BadRequest {
-                error_msg: "Already connected".to_owned(),
-            }
-        );
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        server.add_bot(player_ids[0]);
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        assert_eq!(room.sides[1].name, "Bot 1");
+        assert_eq!(room.active_bots.len(), 1);
+        match server.add_bot(player_ids[0]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        for _ in 0..BOT_TURN_INTERVAL_IN_GENS {
+            room.advance_game();
+        }
+        let counts = room.universe.as_ref().unwrap().player_cell_counts(None);
+        assert_eq!(counts[0], 0);
+        assert!(counts[1] > 0);
     }
 
     #[test]
-    fn process_request_action_none_is_invalid() {
+    fn create_new_room_too_many_rooms() {
         let mut server = ServerState::new();
-        server.create_new_room(None, "some room".to_owned().clone());
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.player_id
-        };
-        let result = server.process_request_action(player_id, RequestAction::None);
+        server.max_rooms = 2; // "general" plus one more
+        assert_eq!(server.create_new_room(None, "first".to_owned()), ResponseCode::OK);
         assert_eq!(
-            result,
+            server.create_new_room(None, "second".to_owned()),
             ResponseCode::BadRequest {
-                error_msg: "Invalid request: None".to_owned(),
+                error_msg: "too many rooms; max 2".to_owned(),
             }
         );
+        assert!(!server.room_map.contains_key("second"));
     }
 
     #[test]
-    fn prepare_response_spot_check_response_packet() {
+    fn handle_new_connection_sends_motd() {
         let mut server = ServerState::new();
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.request_ack = Some(1);
-            player.player_id
-        };
-        let pkt: Packet = server.prepare_response(player_id, RequestAction::ListRooms).unwrap();
+        server.motd = Some("Welcome!".to_owned());
+        let pkt = server.handle_new_connection("some player".to_owned(), fake_socket_addr());
         match pkt {
             Packet::Response {
-                code,
-                sequence,
-                request_ack,
-            } => {
-                if let ResponseCode::RoomList { rooms } = code {
-                    assert_eq!(rooms.len(), 1); // 1 room - general
-                } else {
-                    panic!("`code` is not a RoomList! code is {:?}", code);
-                }
-                assert_eq!(sequence, 1);
-                assert_eq!(request_ack, Some(2));
-            }
-            _ => panic!("Unexpected Packet type on Response path: {:?}", pkt),
+                code: ResponseCode::LoggedIn { motd, .. },
+                ..
+            } => assert_eq!(motd, Some("Welcome!".to_owned())),
+            _ => panic!("Unexpected packet: {:?}", pkt),
         }
-        let player: &Player = server.get_player(player_id);
-        assert_eq!(player.next_resp_seq, 2);
     }
 
     #[test]
-    fn validate_client_version_client_is_up_to_date() {
-        assert_eq!(validate_client_version(env!("CARGO_PKG_VERSION").to_owned()), true);
+    fn server_config_from_toml_full() {
+        let config = ServerConfig::from_toml(
+            r#"
+            name = "Example Server"
+            listen = "0.0.0.0"
+            port = 2017
+            max_rooms = 10
+            tick_interval_in_ms = 20
+            motd = "Be nice."
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.name, Some("Example Server".to_owned()));
+        assert_eq!(config.listen, Some("0.0.0.0".to_owned()));
+        assert_eq!(config.port, Some(2017));
+        assert_eq!(config.max_rooms, 10);
+        assert_eq!(config.tick_interval_in_ms, 20);
+        assert_eq!(config.motd, Some("Be nice.".to_owned()));
+        assert_eq!(config.public_address, None);
     }
 
     #[test]
-    fn validate_client_version_client_is_very_old() {
-        assert_eq!(validate_client_version("0.0.1".to_owned()), true);
+    fn server_config_from_toml_empty_gives_defaults() {
+        assert_eq!(ServerConfig::from_toml("").unwrap(), ServerConfig::default());
     }
 
     #[test]
-    fn validate_client_version_client_is_from_the_future() {
-        assert_eq!(
-            validate_client_version(
-                format!("{}.{}.{}", <i32>::max_value(), <i32>::max_value(), <i32>::max_value()).to_owned()
-            ),
-            false
-        );
+    fn server_config_from_toml_rejects_bad_settings() {
+        assert!(ServerConfig::from_toml("max_room = 10").is_err()); // misspelled
+        assert!(ServerConfig::from_toml("max_rooms = 0").is_err());
+        assert!(ServerConfig::from_toml("tick_interval_in_ms = 0").is_err());
+        assert!(ServerConfig::from_toml("port = 70000").is_err());
+        assert!(ServerConfig::from_toml("snapshot_retention = 0").is_err());
     }
 
     #[test]
-    fn decode_packet_update_reply_good_case() {
-        let mut server = ServerState::new();
-        let cookie: String = {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.cookie.clone()
-        };
-
-        // TODO: Move this into a private helper
-        let update_reply_packet = Packet::UpdateReply {
-            cookie:               cookie,
-            last_chat_seq:        Some(0),
-            last_game_update_seq: None,
-            last_full_gen:        None,
-            partial_gen:          None,
-            pong:                 PingPong::pong(0),
-        };
-
-        let result = server.decode_packet(fake_socket_addr(), update_reply_packet);
-        assert!(result.is_ok());
-        assert!(result.unwrap().is_none());
+    fn server_config_load_missing_file() {
+        let path = Path::new("this/file/does/not/exist.toml");
+        assert_eq!(ServerConfig::load(path, false).unwrap(), ServerConfig::default());
+        assert!(ServerConfig::load(path, true).is_err());
     }
 
     #[test]
-    fn decode_packet_update_reply_invalid_cookie() {
+    fn snapshot_restore_running_game() {
         let mut server = ServerState::new();
-        {
-            let player: &mut Player = server.add_new_player("some player".to_owned(), fake_socket_addr());
-            player.cookie.clone()
-        };
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        server.add_bot(player_ids[0]);
+        assert_eq!(server.set_series_length(player_ids[0], 3), ResponseCode::OK);
+        assert_eq!(server.start_game(player_ids[0]), ResponseCode::OK);
+        let room = server.get_room_mut(player_ids[0]).unwrap();
+        for _ in 0..BOT_TURN_INTERVAL_IN_GENS + 1 {
+            room.advance_game();
+        }
+        let old_uni = room.universe.as_ref().unwrap();
+        let (old_gen, old_counts) = (old_uni.latest_gen(), old_uni.player_cell_counts(None));
+
+        let json = serde_json::to_string(&server.snapshot()).unwrap();
+        let mut restored = ServerState::new();
+        restored.restore(serde_json::from_str(&json).unwrap());
+
+        let room_id = *restored.room_map.get("arena").unwrap();
+        let room = restored.rooms.get(&room_id).unwrap();
+        assert!(room.player_ids.is_empty());
+        assert_eq!(room.bots.len(), 1);
+        assert_eq!(room.series_length, 3);
+        assert!(room.game_running);
+        assert_eq!(room.active_bots.len(), 1);
+        assert_eq!(room.sides[1].name, "Bot 1");
+        let uni = room.universe.as_ref().unwrap();
+        assert_eq!(uni.latest_gen(), old_gen);
+        assert_eq!(uni.player_cell_counts(None), old_counts);
+        assert_eq!(restored.rooms.len(), 2); // "general" is not duplicated
+
+        // The player gets their seat back on rejoining, and nobody else can have it
+        let rejoined = restored
+            .add_new_player("player 0".to_owned(), fake_socket_addr())
+            .player_id;
+        let newcomer = restored
+            .add_new_player("player 9".to_owned(), fake_socket_addr())
+            .player_id;
+        restored.join_room(rejoined, "arena");
+        restored.join_room(newcomer, "arena");
+        let universe_index =
+            |server: &ServerState, player_id| server.get_player(player_id).game_info.as_ref().unwrap().universe_index;
+        assert_eq!(universe_index(&restored, rejoined), Some(0));
+        assert_eq!(universe_index(&restored, newcomer), None);
+    }
 
-        let cookie = "CookieMonster".to_owned();
+    #[test]
+    fn snapshot_store_keeps_newest_files() {
+        let dir = std::env::temp_dir().join(format!("conwaysted-test-{}", new_uuid()));
+        let store = SnapshotStore::new(&dir, 2).unwrap();
+        assert_eq!(store.load_latest().unwrap(), None);
 
-        let update_reply_packet = Packet::UpdateReply {
-            cookie:               cookie,
-            last_chat_seq:        Some(0),
-            last_game_update_seq: None,
-            last_full_gen:        None,
-            partial_gen:          None,
-            pong:                 PingPong::pong(0),
-        };
+        let mut server = ServerState::new();
+        for name in &["a", "b", "c"] {
+            server.create_new_room(None, name.to_string());
+            store.save(&server.snapshot()).unwrap();
+            std::thread::sleep(Duration::from_millis(2)); // file names have millisecond resolution
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        let latest = store.load_latest().unwrap().unwrap();
+        assert_eq!(latest.rooms.len(), 4);
 
-        let result = server.decode_packet(fake_socket_addr(), update_reply_packet);
-        assert!(result.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn construct_client_updates_no_rooms() {
-        let mut server = ServerState::new();
-        let updates = server.construct_client_updates();
-        assert!(updates.is_empty());
+    fn server_config_from_toml_runtime_settings() {
+        let config = ServerConfig::from_toml(
+            r#"
+            admin_password = "secret"
+            banned_addresses = ["192.0.2.1", "2001:db8::1"]
+            max_packets_per_sec = 50
+            max_shared_patterns = 100
+
+            [room_defaults]
+            team_count = 2
+            series_length = 3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.admin_password, Some("secret".to_owned()));
+        assert_eq!(config.banned_addresses.len(), 2);
+        assert_eq!(config.max_packets_per_sec, Some(50));
+        assert_eq!(config.max_shared_patterns, 100);
+        assert_eq!(config.room_defaults.team_count, 2);
+        assert_eq!(config.room_defaults.series_length, 3);
+        assert_eq!(config.room_defaults.match_generations, DEFAULT_MATCH_GENERATIONS);
+
+        assert!(ServerConfig::from_toml("banned_addresses = [\"not an address\"]").is_err());
+        assert!(ServerConfig::from_toml("[room_defaults]\nseries_length = 0").is_err());
+        assert!(ServerConfig::from_toml("[room_defaults]\ngenerations_per_sec = 0").is_err());
     }
 
     #[test]
-    fn construct_client_updates_empty_rooms() {
-        let mut server = ServerState::new();
-        server.create_new_room(None, "some room".to_owned().clone());
-        let updates = server.construct_client_updates();
-        assert!(updates.is_empty());
+    fn server_config_changes_needing_restart() {
+        let old = ServerConfig::default();
+        let mut new = old.clone();
+        new.motd = Some("hi".to_owned());
+        new.max_rooms = 3;
+        assert!(old.changes_needing_restart(&new).is_empty());
+        new.port = Some(1234);
+        new.tick_interval_in_ms = 20;
+        assert_eq!(old.changes_needing_restart(&new), vec!["port", "tick_interval_in_ms"]);
     }
 
     #[test]
-    fn construct_client_updates_populated_room_returns_all_messages() {
+    fn apply_config_bans_connected_players() {
         let mut server = ServerState::new();
-        let room_name = "some_room";
-        let player_name = "some player".to_owned();
-        let message_text = "Message".to_owned();
-
-        server.create_new_room(None, room_name.to_owned());
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
+        let player_ids = add_players_to_room(&mut server, "some room", 1);
+        let mut config = ServerConfig::default();
+        config.banned_addresses = vec![fake_socket_addr().ip()];
+        server.apply_config(&config);
+        assert!(!server.players.contains_key(&player_ids[0]));
+
+        let connect = Packet::Request {
+            sequence:     0,
+            response_ack: None,
+            cookie:       None,
+            action:       RequestAction::Connect {
+                name:           "returning player".to_owned(),
+                client_version: VERSION.to_owned(),
+            },
         };
-        server.join_room(player_id, room_name);
-        server.handle_chat_message(player_id, message_text.clone());
-        server.handle_chat_message(player_id, message_text.clone());
-        server.handle_chat_message(player_id, message_text.clone());
-
-        let mut updates = server.construct_client_updates();
-
-        // Vector should contain a single item for this test
-        assert_eq!(updates.len(), 1);
-
-        let (addr, pkt) = updates.pop().unwrap();
-        assert_eq!(addr, fake_socket_addr());
-
-        match pkt {
-            Packet::Update {
-                chats,
-                game_updates,
-                game_update_seq,
-                universe_update,
-                ping: _,
-            } => {
-                assert!(game_updates.is_empty());
-                assert!(game_update_seq.is_none());
-                assert_eq!(universe_update, UniUpdate::NoChange);
-                assert!(!chats.is_empty());
-
-                // All client chat sequence numbers start counting at 1
-                let mut i = 1;
+        assert!(server.process_packet((connect, fake_socket_addr())).is_empty());
+        assert!(server.players.is_empty());
+    }
 
-                for msg in chats {
-                    assert_eq!(msg.player_name, player_name);
-                    assert_eq!(msg.chat_seq, Some(i));
-                    assert_eq!(msg.message, message_text);
-                    i += 1;
-                }
-            }
-            _ => panic!("Unexpected packet in client update construction!"),
-        }
+    #[test]
+    fn apply_config_room_defaults_only_apply_to_new_rooms() {
+        let mut config = ServerConfig::default();
+        config.room_defaults.team_count = 2;
+        config.room_defaults.match_generations = 500;
+        let mut server = ServerState::with_config(&config);
+        server.create_new_room(None, "before".to_owned());
+
+        config.room_defaults.team_count = 3;
+        server.apply_config(&config);
+        server.create_new_room(None, "after".to_owned());
+
+        let team_count = |server: &ServerState, name: &str| server.rooms[&server.room_map[name]].team_count;
+        assert_eq!(team_count(&server, "general"), 2);
+        assert_eq!(team_count(&server, "before"), 2);
+        assert_eq!(team_count(&server, "after"), 3);
+        let room = &server.rooms[&server.room_map["after"]];
+        assert_eq!(room.win_condition, WinCondition::MostLiveCells { generations: 500 });
     }
 
     #[test]
-    fn construct_client_updates_populated_room_returns_updates_after_client_acked() {
+    fn request_config_reload_needs_admin_password() {
         let mut server = ServerState::new();
-        let room_name = "some_room";
-        let player_name = "some player".to_owned();
-        let message_text = "Message".to_owned();
-
-        server.create_new_room(None, room_name.to_owned());
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
+        let player_ids = add_players_to_room(&mut server, "some room", 1);
+        let reload = |password: &str| RequestAction::ReloadConfig {
+            password: password.to_owned(),
         };
-        server.join_room(player_id, room_name);
-        server.handle_chat_message(player_id, message_text.clone());
-        server.handle_chat_message(player_id, message_text.clone());
-        server.handle_chat_message(player_id, message_text.clone());
 
-        // Assume that the client has acknowledged two chats
-        {
-            let player: &mut Player = server.get_player_mut(player_id);
-            player.update_chat_seq_num(Some(2));
+        // No admin password configured, so nobody is an admin
+        match server.process_request_action(player_ids[0], reload("")) {
+            ResponseCode::Unauthorized { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
 
-        // We should then only return the last chat
-        let mut updates = server.construct_client_updates();
-
-        // Vector should contain a single item for this test
-        assert_eq!(updates.len(), 1);
+        server.admin_password = Some("secret".to_owned());
+        match server.process_request_action(player_ids[0], reload("guess")) {
+            ResponseCode::Unauthorized { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+        assert!(!server.reload_requested);
+        assert_eq!(
+            server.process_request_action(player_ids[0], reload("secret")),
+            ResponseCode::OK
+        );
+        assert!(server.reload_requested);
+    }
 
-        let (addr, pkt) = updates.pop().unwrap();
-        assert_eq!(addr, fake_socket_addr());
+    #[test]
+    fn rate_limiter_drops_packets_over_the_limit() {
+        let addr = fake_socket_addr().ip();
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(Some(2));
+        assert!(limiter.allow(addr, start));
+        assert!(limiter.allow(addr, start));
+        assert!(!limiter.allow(addr, start + Duration::from_millis(500)));
+        assert!(limiter.allow(addr, start + Duration::from_millis(1000)));
+
+        limiter.set_limit(None);
+        for _ in 0..10 {
+            assert!(limiter.allow(addr, start));
+        }
+    }
 
-        match pkt {
-            Packet::Update {
-                mut chats,
-                game_updates,
-                game_update_seq,
-                universe_update,
-                ping: _,
-            } => {
-                assert!(game_updates.is_empty());
-                assert!(game_update_seq.is_none());
-                assert_eq!(universe_update, UniUpdate::NoChange);
-                assert!(!chats.is_empty());
+    #[test]
+    fn congestion_control_backs_off_on_loss_and_recovers() {
+        let start = Instant::now();
+        let mut congestion = CongestionControl::new();
+        let sends_in = |congestion: &mut CongestionControl, ticks: usize, now: Instant| {
+            (0..ticks).filter(|_| congestion.ready_to_send(now)).count()
+        };
+        assert_eq!(sends_in(&mut congestion, 10, start), 10);
 
-                assert_eq!(chats.len(), 1);
-                let msg = chats.pop().unwrap();
+        // None of these are acknowledged, so they are all lost once a second has passed
+        for gen in 0..20 {
+            congestion.on_sent(gen, start);
+        }
+        congestion.ready_to_send(start + Duration::from_secs(2));
+        assert_eq!(congestion.interval(), 2);
 
-                assert_eq!(msg.player_name, player_name);
-                assert_eq!(msg.chat_seq, Some(3));
-                assert_eq!(msg.message, message_text);
-            }
-            _ => panic!("Unexpected packet in client update construction!"),
+        // A clean sample speeds it back up
+        for gen in 20..40 {
+            congestion.on_sent(gen, start);
         }
+        congestion.on_acked(39);
+        assert_eq!(congestion.interval(), 1);
     }
 
     #[test]
-    fn broadcast_message_to_two_players_in_room() {
+    fn construct_client_updates_sends_universe_less_often_after_losses() {
         let mut server = ServerState::new();
-        let room_name = "some_room";
-        let player_name = "some player".to_owned();
-
-        server.create_new_room(None, room_name.to_owned());
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
-        };
-        let player_id2: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(0, 0, 10, 10)),
+            ResponseCode::OK
+        );
+        let universe_updates = |server: &mut ServerState| {
+            server
+                .construct_client_updates()
+                .iter()
+                .filter(|(_, packet)| match packet {
+                    Packet::Update {
+                        universe_update: UniUpdate::Diff { .. },
+                        ..
+                    } => true,
+                    _ => false,
+                })
+                .count()
         };
+        assert_eq!((0..4).map(|_| universe_updates(&mut server)).sum::<usize>(), 4);
 
-        server.join_room(player_id, room_name.clone());
         {
-            let room: &mut Room = server.get_room_mut(player_id).unwrap();
-            room.broadcast("Silver birch against a Swedish sky".to_owned());
+            let game_info = server.get_player_mut(player_id).game_info.as_mut().unwrap();
+            game_info.congestion.forget_in_flight();
+            let long_ago = Instant::now() - Duration::from_secs(5);
+            for gen in 100..120 {
+                game_info.congestion.on_sent(gen, long_ago);
+            }
         }
-        server.join_room(player_id2, room_name);
-        let room: &Room = server.get_room(player_id).unwrap();
-
-        let player = (*server.get_player(player_id)).clone();
-        let msgs = server.collect_unacknowledged_messages(room, &player).unwrap();
-        assert_eq!(msgs.len(), 1);
-        assert_eq!(msgs[0].message, "Silver birch against a Swedish sky".to_owned());
-
-        let player = (*server.get_player(player_id2)).clone();
-        let msgs = server.collect_unacknowledged_messages(room, &player).unwrap();
-        assert_eq!(msgs.len(), 1);
-        assert_eq!(msgs[0].message, "Silver birch against a Swedish sky".to_owned());
+        // The client never acknowledged any of those, so only every other tick has a universe update
+        assert_eq!((0..4).map(|_| universe_updates(&mut server)).sum::<usize>(), 2);
     }
 
     #[test]
-    fn broadcast_message_to_an_empty_room() {
-        let mut server = ServerState::new();
-        let room_name = "some_room".to_owned();
-
-        server.create_new_room(None, room_name.clone());
-        let room_id: &RoomID = server.room_map.get(&room_name.clone()).unwrap();
-
-        {
-            let room: &mut Room = server.rooms.get_mut(&room_id).unwrap();
-            room.broadcast("Silver birch against a Swedish sky".to_owned());
+    fn path_mtu_probing_finds_largest_packet_that_gets_through() {
+        let mut path_mtu = PathMtu::new();
+        let default_part_len = path_mtu.diff_part_len();
+        assert_eq!(default_part_len, MAX_DIFF_PART_LEN);
+
+        // Probes bigger than this are lost; each one that is lost is tried a second time
+        let link_mtu = 4000;
+        let mut now = Instant::now();
+        let mut probes = 0;
+        while let Some(size) = path_mtu.next_probe(now) {
+            probes += 1;
+            if size <= link_mtu {
+                path_mtu.on_reply(size);
+            } else {
+                now += Duration::from_secs(2);
+            }
         }
-        let room: &Room = server.rooms.get(&room_id).unwrap();
-        assert_eq!(room.latest_seq_num, 1);
-        assert_eq!(room.messages.len(), 1);
-        let msgs: &ServerChatMessage = room.messages.get(0).unwrap();
-        assert_eq!(msgs.player_name, "Server".to_owned());
-        assert_eq!(msgs.seq_num, 1);
-        assert_eq!(msgs.player_id, PlayerID(0xFFFFFFFFFFFFFFFF));
+        assert!(probes < 20);
+        assert!(path_mtu.largest_packet() <= link_mtu);
+        assert!(path_mtu.largest_packet() + 64 >= link_mtu);
+        assert!(path_mtu.diff_part_len() > default_part_len);
+
+        // Nothing more is sent once the search is over
+        now += Duration::from_secs(10);
+        assert_eq!(path_mtu.next_probe(now), None);
     }
 
     #[test]
-    #[should_panic]
-    fn disconnect_get_player_by_id_fails() {
+    fn path_mtu_ignores_sizes_not_probed() {
+        let mut path_mtu = PathMtu::new();
+        path_mtu.on_reply(8000);
+        assert_eq!(path_mtu, PathMtu::new());
+
+        let now = Instant::now();
+        let size = path_mtu.next_probe(now).unwrap();
+        path_mtu.on_reply(size + 1);
+        assert_eq!(path_mtu.diff_part_len(), MAX_DIFF_PART_LEN);
+        path_mtu.on_reply(size);
+        assert_eq!(path_mtu.largest_packet(), size);
+
+        // Once answered, the same size can't be claimed again to skip the next probe
+        let next_size = path_mtu.next_probe(now).unwrap();
+        path_mtu.on_reply(8000);
+        assert_eq!(path_mtu.largest_packet(), size);
+        assert!(next_size > size);
+    }
+
+    #[test]
+    fn mtu_probe_reply_raises_universe_part_size() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
+        let player_id = server.add_new_player("jumbo".to_owned(), fake_socket_addr()).player_id;
+        let cookie = server.get_player(player_id).cookie.clone();
+
+        let probes: Vec<_> = server
+            .maintain_network_state()
+            .into_iter()
+            .filter_map(|(packet, _)| match packet {
+                Packet::MtuProbe { size, .. } => Some(size),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(probes.len(), 1);
+        let size = probes[0];
 
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
+        // A reply claiming a larger size than the probe sent is ignored
+        let spoofed_reply = Packet::MtuProbeReply {
+            cookie: cookie.clone(),
+            size:   size + 100,
         };
+        assert_eq!(server.decode_packet(fake_socket_addr(), spoofed_reply).unwrap(), None);
+        assert_eq!(server.get_player(player_id).path_mtu.diff_part_len(), MAX_DIFF_PART_LEN);
 
-        server.handle_disconnect(player_id);
-        server.get_player(player_id);
+        let reply = Packet::MtuProbeReply { cookie, size };
+        assert_eq!(server.decode_packet(fake_socket_addr(), reply).unwrap(), None);
+        assert!(server.get_player(player_id).path_mtu.diff_part_len() > MAX_DIFF_PART_LEN);
+
+        let bad_reply = Packet::MtuProbeReply {
+            cookie: "nope".to_owned(),
+            size:   8000,
+        };
+        assert!(server.decode_packet(fake_socket_addr(), bad_reply).is_err());
     }
 
     #[test]
-    fn disconnect_get_player_by_cookie_fails() {
+    fn player_follows_cookie_to_new_address() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let (player_id, cookie) = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            (player.player_id, player.cookie.clone())
+        let player_ids = add_players_to_room(&mut server, "some room", 1);
+        let player_id = player_ids[0];
+        let cookie = server.get_player(player_id).cookie.clone();
+        let path_mtu = &mut server.get_player_mut(player_id).path_mtu;
+        let size = path_mtu.next_probe(Instant::now()).unwrap();
+        path_mtu.on_reply(size);
+        assert_ne!(*path_mtu, PathMtu::new());
+        let new_addr: SocketAddr = "5.6.7.8:9012".parse().unwrap();
+
+        let update_reply = Packet::UpdateReply {
+            cookie,
+            last_chat_seq: None,
+            last_lobby_chat_seq: None,
+            last_game_update_seq: None,
+            last_full_gen: None,
+            partial_gen: None,
+            universe_epoch: 0,
+            pong: PingPong::pong(0),
         };
+        assert!(server.process_packet((update_reply, new_addr)).is_empty());
+        let player = server.get_player(player_id);
+        assert_eq!(player.addr, new_addr);
+        assert_eq!(player.path_mtu, PathMtu::new());
 
-        server.handle_disconnect(player_id);
-        assert_eq!(server.get_player_id_by_cookie(cookie.as_str()), None);
+        // The path gets probed afresh, at the new address
+        let packets = server.maintain_network_state();
+        assert!(packets
+            .iter()
+            .any(|(packet, _)| matches!(packet, Packet::MtuProbe { .. })));
+        assert!(packets.iter().all(|(_, addr)| *addr == new_addr));
     }
 
     #[test]
-    fn disconnect_while_in_room_removes_all_traces_of_player() {
+    fn player_does_not_move_without_valid_cookie() {
         let mut server = ServerState::new();
-        let room_name = "some_room";
-        let player_name = "some player".to_owned();
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.player_id
+        let player_id = server
+            .add_new_player("stays put".to_owned(), fake_socket_addr())
+            .player_id;
+        let new_addr: SocketAddr = "5.6.7.8:9012".parse().unwrap();
+
+        let request = Packet::Request {
+            sequence:     1,
+            response_ack: None,
+            cookie:       Some("not a cookie".to_owned()),
+            action:       RequestAction::ListPlayers,
         };
-
-        server.create_new_room(None, room_name.to_owned());
-        server.join_room(player_id, room_name);
-        let room_id = {
-            let room: &Room = server.get_room(player_id).unwrap();
-            assert_eq!(room.player_ids.contains(&player_id), true);
-            room.room_id
+        assert!(server.decode_packet(new_addr, request).is_err());
+        assert_eq!(server.get_player(player_id).addr, fake_socket_addr());
+
+        // A valid cookie from a banned address doesn't get through either
+        let cookie = server.get_player(player_id).cookie.clone();
+        server.banned_addresses.insert(new_addr.ip());
+        let request = Packet::Request {
+            sequence:     1,
+            response_ack: None,
+            cookie:       Some(cookie),
+            action:       RequestAction::ListPlayers,
         };
-        server.handle_disconnect(player_id);
-        // Cannot go through player_id because the player has been removed
-        let room: &Room = server.rooms.get(&room_id).unwrap();
-        assert_eq!(room.player_ids.contains(&player_id), false);
+        assert!(server.process_packet((request, new_addr)).is_empty());
+        assert_eq!(server.get_player(player_id).addr, fake_socket_addr());
     }
 
     #[test]
-    fn test_is_previously_processed_packet() {
+    fn set_color_in_lobby_is_rejected() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
+        let player_id = server.add_new_player("lonely".to_owned(), fake_socket_addr()).player_id;
 
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(4);
-            player.player_id
-        };
+        match server.set_color(player_id, RgbColor::new(255, 0, 0)) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
+        }
+    }
 
-        let player_id2: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = None;
-            player.player_id
-        };
+    #[test]
+    fn set_color_nudges_hue_away_from_taken_colors() {
+        let mut server = ServerState::new();
+        let player_ids = add_players_to_room(&mut server, "colorful", 3);
+        let red = RgbColor::new(255, 0, 0);
+        let blue = RgbColor::new(0, 0, 255);
+        assert_eq!(server.set_color(player_ids[0], red), ResponseCode::OK);
+        assert_eq!(server.set_color(player_ids[1], blue), ResponseCode::OK);
+        // Almost red, so it gets moved
+        assert_eq!(
+            server.set_color(player_ids[2], RgbColor::new(255, 20, 0)),
+            ResponseCode::OK
+        );
 
-        assert_eq!(server.is_previously_processed_packet(player_id2, 0), false);
+        let room = server.get_room(player_ids[0]).unwrap();
+        let (_, updates) = room.collect_unacknowledged_game_updates(None);
+        let colors: Vec<(String, RgbColor)> = updates
+            .into_iter()
+            .filter_map(|update| match update {
+                GameUpdate::PlayerColor { name, color } => Some((name, color)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors[0], ("player 0".to_owned(), red));
+        assert_eq!(colors[1], ("player 1".to_owned(), blue));
+        let (ref name, nudged) = colors[2];
+        assert_eq!(name, "player 2");
+        assert_ne!(nudged, RgbColor::new(255, 20, 0));
+        assert_eq!(player_color::resolve_color(nudged, &[red, blue]), nudged);
+    }
 
-        assert_eq!(server.is_previously_processed_packet(player_id, 0), true);
-        assert_eq!(server.is_previously_processed_packet(player_id, 4), true);
-        assert_eq!(server.is_previously_processed_packet(player_id, 5), false);
+    #[test]
+    fn interest_region_grows_viewport_to_chunks() {
+        let universe = Region::new(0, 0, GAME_WIDTH as usize, GAME_HEIGHT as usize);
+        assert_eq!(
+            interest_region(Region::new(40, 40, 20, 10), universe),
+            Some(Region::new(0, 0, 96, 96))
+        );
+        assert_eq!(
+            interest_region(Region::new(200, 100, 100, 100), universe),
+            Some(Region::new(160, 64, 96, 64))
+        );
+        assert_eq!(interest_region(Region::new(-50, -50, 10, 10), universe), None);
     }
 
     #[test]
-    fn test_clear_transmission_queue_on_ack() {
+    fn construct_client_updates_sends_universe_around_viewport() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(4);
-            player.player_id
+        let player_ids = add_players_to_room(&mut server, "arena", 1);
+        let player_id = player_ids[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(u64::max_value()));
+            player.update_game_update_seq_num(Some(u64::max_value()));
+        }
+        let universe_diff = |server: &mut ServerState| {
+            let updates = server.construct_client_updates();
+            match updates.first() {
+                Some((
+                    _,
+                    Packet::Update {
+                        universe_update: UniUpdate::Diff { diff, .. },
+                        ..
+                    },
+                )) => {
+                    assert!(diff.checksum_matches());
+                    Some(diff.clone())
+                }
+                None => None,
+                Some(other) => panic!("Unexpected update: {:?}", other),
+            }
         };
 
-        for i in 0..5 {
-            let pkt = Packet::Response {
-                sequence:    i,
-                request_ack: None,
-                code:        ResponseCode::OK,
-            };
+        // Nothing until the client says what it's looking at
+        assert_eq!(universe_diff(&mut server), None);
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(0, 0, 10, 10)),
+            ResponseCode::OK
+        );
+        let diff = universe_diff(&mut server).unwrap();
+        assert_eq!(diff.gen0, 0);
+        let epoch = universe_epoch(&server, player_id);
+        server.get_player_mut(player_id).update_full_gen(epoch, Some(diff.gen1 as u64));
+        assert_eq!(universe_diff(&mut server), None);
 
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            nm.tx_packets.buffer_item(pkt.clone());
-        }
+        server.advance_games();
+        let next_diff = universe_diff(&mut server).unwrap();
+        assert_eq!((next_diff.gen0, next_diff.gen1), (diff.gen1, diff.gen1 + 1));
 
-        server.clear_transmission_queue_on_ack(player_id, None);
-        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 5);
-        server.clear_transmission_queue_on_ack(player_id, Some(0));
-        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 5);
-        server.clear_transmission_queue_on_ack(player_id, Some(1));
-        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 4);
-        server.clear_transmission_queue_on_ack(player_id, Some(5));
-        assert_eq!(server.network_map.get(&player_id).unwrap().tx_packets.len(), 0);
+        // Scrolling within the same chunks changes nothing, but farther away needs everything again
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(1, 1, 10, 10)),
+            ResponseCode::OK
+        );
+        assert_eq!(universe_diff(&mut server).unwrap().gen0, diff.gen1);
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(150, 50, 10, 10)),
+            ResponseCode::OK
+        );
+        assert_eq!(universe_diff(&mut server).unwrap().gen0, 0);
     }
 
     #[test]
-    fn test_resend_expired_tx_packets() {
+    fn update_reply_from_before_viewport_change_is_ignored() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let player_id: PlayerID = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(5);
-            player.player_id
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        let cookie = server.get_player(player_id).cookie.clone();
+        let update_reply = |epoch, last_full_gen| Packet::UpdateReply {
+            cookie:               cookie.clone(),
+            last_chat_seq:        Some(u64::max_value()),
+            last_lobby_chat_seq:  None,
+            last_game_update_seq: Some(u64::max_value()),
+            last_full_gen:        last_full_gen,
+            partial_gen:          None,
+            universe_epoch:       epoch,
+            pong:                 PingPong::pong(0),
+        };
+        let universe_diff = |server: &mut ServerState| match server.construct_client_updates().first() {
+            Some((
+                _,
+                Packet::Update {
+                    universe_update: UniUpdate::Diff { diff, epoch },
+                    ..
+                },
+            )) => Some((diff.gen0, diff.gen1, *epoch)),
+            _ => None,
         };
 
-        for i in 0..5 {
-            let pkt = Packet::Response {
-                sequence:    i,
-                request_ack: None,
-                code:        ResponseCode::OK,
-            };
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(0, 0, 10, 10)),
+            ResponseCode::OK
+        );
+        let (_, gen1, old_epoch) = universe_diff(&mut server).unwrap();
+        server
+            .decode_packet(fake_socket_addr(), update_reply(old_epoch, Some(gen1 as u64)))
+            .unwrap();
+        server.advance_games();
+        assert_eq!(universe_diff(&mut server).map(|(gen0, ..)| gen0), Some(gen1));
 
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            nm.tx_packets.buffer_item(pkt.clone());
+        // The client's reply about the old chunks arrives after it has moved far away
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(150, 50, 10, 10)),
+            ResponseCode::OK
+        );
+        server
+            .decode_packet(fake_socket_addr(), update_reply(old_epoch, Some(gen1 as u64 + 1)))
+            .unwrap();
+        let (gen0, gen1, epoch) = universe_diff(&mut server).unwrap();
+        assert_eq!(gen0, 0);
+        assert_ne!(epoch, old_epoch);
 
-            if i < 3 {
-                let attempt: &mut NetAttempt = nm.tx_packets.attempts.back_mut().unwrap();
-                attempt.time = Instant::now() - Duration::from_secs(i + 1);
-            }
+        // Once it has the new chunks, it is sent just what changes
+        server
+            .decode_packet(fake_socket_addr(), update_reply(epoch, Some(gen1 as u64)))
+            .unwrap();
+        server.advance_games();
+        assert_eq!(universe_diff(&mut server).map(|(gen0, ..)| gen0), Some(gen1));
+    }
+
+    #[test]
+    fn resync_universe_sends_everything_again() {
+        let mut server = ServerState::new();
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(u64::max_value()));
+            player.update_game_update_seq_num(Some(u64::max_value()));
         }
+        assert_eq!(
+            server.set_client_viewport(player_id, NetRegion::new(0, 0, 10, 10)),
+            ResponseCode::OK
+        );
+        server.advance_games();
+        let universe_gens = |server: &mut ServerState| match server.construct_client_updates().first() {
+            Some((
+                _,
+                Packet::Update {
+                    universe_update: UniUpdate::Diff { diff, .. },
+                    ..
+                },
+            )) => Some((diff.gen0, diff.gen1)),
+            _ => None,
+        };
+        let (_, gen1) = universe_gens(&mut server).unwrap();
+        let epoch = universe_epoch(&server, player_id);
+        server.get_player_mut(player_id).update_full_gen(epoch, Some(gen1 as u64));
+        assert_eq!(universe_gens(&mut server), None);
 
-        let _expired_packets_vec = server.collect_expired_tx_packets();
+        assert_eq!(server.resync_universe(player_id), ResponseCode::OK);
+        assert_eq!(universe_gens(&mut server), Some((0, gen1)));
+    }
 
-        for i in 0..5 {
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            let packet_retries: &NetAttempt = nm.tx_packets.attempts.get(i).unwrap();
+    #[test]
+    fn resync_universe_in_lobby_is_rejected() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("lonely".to_owned(), fake_socket_addr()).player_id;
 
-            if i >= 3 {
-                assert_eq!(packet_retries.retries, 0);
-            } else {
-                assert_eq!(packet_retries.retries, 1);
-            }
+        match server.resync_universe(player_id) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn test_process_queued_rx_packets_first_non_connect_player_packet() {
+    fn construct_client_updates_splits_big_diffs_and_resumes_them() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let (player_id, player_cookie): (PlayerID, String) = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
-            (player.player_id, player.cookie.clone())
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        {
+            let player = server.get_player_mut(player_id);
+            player.update_chat_seq_num(Some(u64::max_value()));
+            player.update_game_update_seq_num(Some(u64::max_value()));
+        }
+        let room_id = server.get_room_id(player_id).unwrap();
+        let writable = {
+            let uni = server.rooms.get_mut(&room_id).unwrap().universe.as_mut().unwrap();
+            let writable = uni.writable_region(0);
+            // Lone cells, with nothing in a row for the pattern to run-length encode
+            for row in (writable.top()..=writable.bottom()).step_by(2) {
+                for col in (writable.left()..=writable.right()).step_by(2) {
+                    uni.set_unchecked(col as usize, row as usize, CellState::Alive(Some(0)));
+                }
+            }
+            writable
+        };
+        let viewport = NetRegion::new(
+            writable.left() as i32,
+            writable.top() as i32,
+            writable.width() as u32,
+            writable.height() as u32,
+        );
+        assert_eq!(server.set_client_viewport(player_id, viewport), ResponseCode::OK);
+        let universe_parts = |server: &mut ServerState| -> Vec<GenStateDiffPart> {
+            server
+                .construct_client_updates()
+                .into_iter()
+                .filter_map(|(_, packet)| match packet {
+                    Packet::Update {
+                        universe_update: UniUpdate::Diff { diff, .. },
+                        ..
+                    } => Some(diff),
+                    _ => None,
+                })
+                .collect()
         };
 
-        {
-            let pkt = Packet::Request {
-                cookie:       Some(player_cookie),
-                sequence:     2,
-                response_ack: None,
-                action:       RequestAction::ListPlayers,
-            };
+        let first_parts = universe_parts(&mut server);
+        assert_eq!(first_parts.len(), DIFF_PARTS_PER_UPDATE);
+        let (gen0, gen1, total_parts) = (first_parts[0].gen0, first_parts[0].gen1, first_parts[0].total_parts);
+        assert!(total_parts as usize > DIFF_PARTS_PER_UPDATE);
+
+        // The rest of the same diff comes next, even though the game has moved on
+        server.advance_games();
+        let epoch = universe_epoch(&server, player_id);
+        server.get_player_mut(player_id).update_partial_gen(epoch, Some(GenPartInfo {
+            gen0,
+            gen1,
+            have_bitmask: (1 << DIFF_PARTS_PER_UPDATE) - 1,
+        }));
+        let next_parts = universe_parts(&mut server);
+        assert_eq!(next_parts[0].part_number as usize, DIFF_PARTS_PER_UPDATE);
+        assert!(next_parts
+            .iter()
+            .all(|part| (part.gen0, part.gen1, part.total_parts) == (gen0, gen1, total_parts)));
+
+        // A nonsensical diff from the client starts over with a new one
+        server.get_player_mut(player_id).update_partial_gen(epoch, Some(GenPartInfo {
+            gen0:         gen1,
+            gen1:         gen1,
+            have_bitmask: 0,
+        }));
+        let new_parts = universe_parts(&mut server);
+        assert_eq!(new_parts[0].part_number, 0);
+        assert!(new_parts[0].gen1 > gen1);
+    }
 
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            nm.rx_packets.buffer_item(pkt.clone());
+    #[test]
+    fn set_cells_takes_effect_in_next_generation() {
+        let mut server = ServerState::new();
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        let room_id = server.get_room_id(player_id).unwrap();
+        let (writable, latest_gen) = {
+            let uni = server.rooms.get(&room_id).unwrap().universe.as_ref().unwrap();
+            (uni.writable_region(0), uni.latest_gen() as u64)
+        };
+        let (col, row) = (writable.left() as u32 + 1, writable.top() as u32 + 1);
 
-            assert_eq!(nm.tx_packets.len(), 0);
+        assert_eq!(
+            server.set_cells(player_id, vec![(col, row, true), (col + 1, row, true)]),
+            ResponseCode::CellsSet {
+                cells:      vec![(col, row), (col + 1, row)],
+                generation: latest_gen + 1,
+            }
+        );
+        server.advance_games();
+        let uni = server.rooms.get_mut(&room_id).unwrap().universe.as_mut().unwrap();
+        assert_eq!(uni.latest_gen() as u64, latest_gen + 1);
+        let (col, row) = (col as usize, row as usize);
+        assert_eq!(uni.get_cell_state(col, row, Some(0)), CellState::Alive(Some(0)));
+        assert_eq!(uni.get_cell_state(col + 1, row, Some(0)), CellState::Alive(Some(0)));
+
+        // One cell outside of the writable region spoils the whole batch
+        let outside = writable.right() as u32 + 1;
+        match server.set_cells(player_id, vec![(col as u32, row as u32 + 1, true), (outside, row as u32, true)]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+        assert!(server.rooms[&room_id].cell_changes.is_empty());
 
-        server.process_queued_rx_packets(player_id);
-
-        {
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            assert_eq!(nm.tx_packets.len(), 1);
+        let too_many = vec![(col as u32, row as u32, true); MAX_SET_CELLS + 1];
+        match server.set_cells(player_id, too_many) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn test_process_queued_rx_packets_contiguous() {
+    fn set_cells_is_shared_only_with_share_cell_inputs() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let (player_id, player_cookie): (PlayerID, String) = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
-            (player.player_id, player.cookie.clone())
+        let player_id = add_players_to_room(&mut server, "arena", 1)[0];
+        assert_eq!(server.start_game(player_id), ResponseCode::OK);
+        let room_id = server.get_room_id(player_id).unwrap();
+        let writable = server.rooms[&room_id].universe.as_ref().unwrap().writable_region(0);
+        let (col, row) = (writable.left() as u32 + 1, writable.top() as u32 + 1);
+        let cell_inputs = |server: &ServerState| -> Vec<(u32, Vec<CellInput>)> {
+            server.rooms[&room_id]
+                .game_updates
+                .iter()
+                .filter_map(|(_, update)| match update {
+                    GameUpdate::CellInputs { generation, inputs } => Some((*generation, inputs.clone())),
+                    _ => None,
+                })
+                .collect()
         };
 
-        for i in 2..10 {
-            let pkt = Packet::Request {
-                cookie:       Some(player_cookie.clone()),
-                sequence:     i,
-                response_ack: None,
-                action:       RequestAction::ListPlayers,
-            };
-
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            nm.rx_packets.buffer_item(pkt.clone());
+        server.set_cells(player_id, vec![(col, row, true)]);
+        server.advance_games();
+        assert!(cell_inputs(&server).is_empty());
 
-            assert_eq!(nm.tx_packets.len(), 0);
-        }
+        server.rooms.get_mut(&room_id).unwrap().share_cell_inputs = true;
+        server.set_cells(player_id, vec![(col, row, false)]);
+        server.advance_games();
+        let generation = server.rooms[&room_id].universe.as_ref().unwrap().latest_gen() as u32;
+        assert_eq!(
+            cell_inputs(&server),
+            vec![(
+                generation,
+                vec![CellInput {
+                    player_index: 0,
+                    col,
+                    row,
+                    alive: false,
+                }]
+            )]
+        );
+    }
 
-        server.process_queued_rx_packets(player_id);
+    #[test]
+    fn set_cells_in_lobby_is_rejected() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("lonely".to_owned(), fake_socket_addr()).player_id;
 
-        {
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            assert_eq!(nm.tx_packets.len(), 8);
+        match server.set_cells(player_id, vec![(0, 0, true)]) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
     }
 
     #[test]
-    fn test_process_queued_rx_packets_swiss_cheese_queue() {
+    fn set_client_viewport_in_lobby_is_rejected() {
         let mut server = ServerState::new();
-        let player_name = "some player".to_owned();
-
-        let (player_id, player_cookie): (PlayerID, String) = {
-            let player: &mut Player = server.add_new_player(player_name.clone(), fake_socket_addr());
-            player.request_ack = Some(1); // Player connected and we've confirmed the first transaction
-            (player.player_id, player.cookie.clone())
-        };
-
-        for i in [2, 3, 4, 6, 8, 9, 10].iter() {
-            let pkt = Packet::Request {
-                cookie:       Some(player_cookie.clone()),
-                sequence:     *i,
-                response_ack: None,
-                action:       RequestAction::ListPlayers,
-            };
-
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            nm.rx_packets.buffer_item(pkt.clone());
+        let player_id = server.add_new_player("lonely".to_owned(), fake_socket_addr()).player_id;
 
-            assert_eq!(nm.tx_packets.len(), 0);
+        match server.set_client_viewport(player_id, NetRegion::new(0, 0, 10, 10)) {
+            ResponseCode::BadRequest { .. } => {}
+            resp_code @ _ => panic!("Unexpected response code: {:?}", resp_code),
         }
+    }
 
-        server.process_queued_rx_packets(player_id);
+    #[test]
+    fn resolve_color_keeps_distinct_colors() {
+        let taken = [RgbColor::new(255, 0, 0), RgbColor::new(0, 0, 255)];
+        let green = RgbColor::new(0, 200, 0);
+        assert_eq!(player_color::resolve_color(green, &taken), green);
+        // A gray doesn't collide with colorful colors, however close in brightness
+        let gray = RgbColor::new(128, 128, 128);
+        assert_eq!(player_color::resolve_color(gray, &taken), gray);
+    }
 
-        {
-            let nm: &mut NetworkManager = server.network_map.get_mut(&player_id).unwrap();
-            assert_eq!(nm.tx_packets.len(), 3); // only 2, 3, and 4 are processed
-        }
+    #[test]
+    fn resolve_color_separates_grays_by_brightness() {
+        let taken = [RgbColor::new(128, 128, 128)];
+        let resolved = player_color::resolve_color(RgbColor::new(140, 140, 140), &taken);
+        assert_eq!(resolved.r, resolved.g);
+        assert_eq!(resolved.g, resolved.b);
+        assert!((resolved.r as i32 - 128).abs() >= 50);
     }
 }
@@ -0,0 +1,277 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    DEFAULT_GENERATIONS_PER_SEC, DEFAULT_MATCH_GENERATIONS, DEFAULT_MAX_ROOMS, MAX_GENERATIONS_PER_SEC,
+    MAX_SERIES_ROUNDS, MAX_TEAMS, TICK_INTERVAL_IN_MS,
+};
+
+/// Used when no `--config` option is given. It's fine if this file doesn't exist.
+pub const DEFAULT_CONFIG_PATH: &str = "conwaysted.toml";
+pub const DEFAULT_SNAPSHOT_INTERVAL_IN_SECS: u64 = 5 * 60;
+pub const DEFAULT_SNAPSHOT_RETENTION: usize = 10;
+pub const DEFAULT_MAX_SHARED_PATTERNS: usize = 500;
+
+#[derive(Error, Debug)]
+pub enum ServerConfigError {
+    #[error("could not read {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+    #[error("could not parse {path}: {source}")]
+    Parse { path: String, source: toml::de::Error },
+    #[error("invalid setting: {0}")]
+    Invalid(String),
+}
+
+/// Settings for the dedicated server, loaded from a TOML file. Any setting left out of the file
+/// gets its default value. Command line options take precedence over the file.
+///
+/// The file is read again on SIGHUP, or when an admin sends a `ReloadConfig` request. Only the
+/// settings handled by `ServerState::apply_config` take effect then; the rest need a restart.
+///
+/// ```toml
+/// name = "Example Server"
+/// listen = "0.0.0.0"
+/// port = 2016
+/// websocket_port = 2018
+/// max_rooms = 64
+/// tick_interval_in_ms = 10
+/// motd = "Welcome! Be nice."
+/// snapshot_dir = "snapshots"
+/// snapshot_interval_in_secs = 300
+/// snapshot_retention = 10
+/// admin_password = "hunter2"
+/// banned_addresses = ["192.0.2.1"]
+/// max_packets_per_sec = 100
+/// report_log = "reports.jsonl"
+/// leaderboard_file = "leaderboards.json"
+/// shared_patterns_file = "shared_patterns.json"
+/// max_shared_patterns = 500
+///
+/// [room_defaults]
+/// team_count = 2
+/// series_length = 3
+/// match_generations = 2000
+/// generations_per_sec = 10
+/// share_cell_inputs = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub name: Option<String>,   // None means the server's built-in default name
+    pub listen: Option<String>, // address to bind to
+    pub port: Option<u16>,
+    pub websocket_port: Option<u16>, // for clients that can't use UDP; None means no WebSocket bridge
+    pub public_address: Option<String>, // sent to the registrar, if set
+    pub registrar_url: Option<String>,
+    pub max_rooms: usize,             // including the "general" room
+    pub tick_interval_in_ms: u64,     // how often clients are sent updates; game speed is set per room
+    pub motd: Option<String>,         // message of the day, shown to each player on login
+    pub snapshot_dir: Option<String>, // where to save rooms periodically; None means never
+    pub snapshot_interval_in_secs: u64,
+    pub snapshot_retention: usize, // number of snapshot files kept; older ones are deleted
+    pub admin_password: Option<String>, // None means nobody can send admin requests
+    pub banned_addresses: Vec<IpAddr>,
+    pub max_packets_per_sec: Option<u32>, // per client address; None means no limit
+    pub report_log: Option<String>,       // players' reports are appended here; None means they're only logged
+    pub leaderboard_file: Option<String>, // None means the leaderboards start over when the server restarts
+    pub shared_patterns_file: Option<String>, // None means the shared patterns are lost when the server restarts
+    pub max_shared_patterns: usize,           // the oldest are dropped beyond this; zero turns sharing off
+    pub room_defaults: RoomDefaults,
+}
+
+/// Settings given to each new room. Rooms that already exist keep theirs when these change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomDefaults {
+    pub team_count:          u8, // zero means free-for-all
+    pub series_length:       u8,
+    pub match_generations:   u32,
+    pub generations_per_sec: u8,
+    pub share_cell_inputs:   bool, // for clients' lockstep checks; shows players cells hidden by the fog
+}
+
+impl Default for RoomDefaults {
+    fn default() -> Self {
+        RoomDefaults {
+            team_count:          0,
+            series_length:       1,
+            match_generations:   DEFAULT_MATCH_GENERATIONS,
+            generations_per_sec: DEFAULT_GENERATIONS_PER_SEC,
+            share_cell_inputs:   false,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            name: None,
+            listen: None,
+            port: None,
+            websocket_port: None,
+            public_address: None,
+            registrar_url: None,
+            max_rooms: DEFAULT_MAX_ROOMS,
+            tick_interval_in_ms: TICK_INTERVAL_IN_MS,
+            motd: None,
+            snapshot_dir: None,
+            snapshot_interval_in_secs: DEFAULT_SNAPSHOT_INTERVAL_IN_SECS,
+            snapshot_retention: DEFAULT_SNAPSHOT_RETENTION,
+            admin_password: None,
+            banned_addresses: vec![],
+            max_packets_per_sec: None,
+            report_log: None,
+            leaderboard_file: None,
+            shared_patterns_file: None,
+            max_shared_patterns: DEFAULT_MAX_SHARED_PATTERNS,
+            room_defaults: RoomDefaults::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads the config file at `path`. If `must_exist` is false, a missing file results in the
+    /// default config rather than an error.
+    pub fn load(path: &Path, must_exist: bool) -> Result<Self, ServerConfigError> {
+        let path_str = path.to_string_lossy().into_owned();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if !must_exist && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ServerConfig::default());
+            }
+            Err(source) => return Err(ServerConfigError::Io { path: path_str, source }),
+        };
+        ServerConfig::from_toml(&text).map_err(|e| match e {
+            ServerConfigError::Parse { source, .. } => ServerConfigError::Parse { path: path_str, source },
+            e => e,
+        })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ServerConfigError> {
+        let config: ServerConfig = toml::from_str(text).map_err(|source| ServerConfigError::Parse {
+            path: "<string>".to_owned(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ServerConfigError> {
+        if self.max_rooms == 0 {
+            return Err(ServerConfigError::Invalid(
+                "max_rooms must be at least 1 to make room for \"general\"".to_owned(),
+            ));
+        }
+        if self.tick_interval_in_ms == 0 {
+            return Err(ServerConfigError::Invalid(
+                "tick_interval_in_ms must be greater than 0".to_owned(),
+            ));
+        }
+        if self.snapshot_interval_in_secs == 0 {
+            return Err(ServerConfigError::Invalid(
+                "snapshot_interval_in_secs must be greater than 0".to_owned(),
+            ));
+        }
+        if self.snapshot_retention == 0 {
+            return Err(ServerConfigError::Invalid(
+                "snapshot_retention must be at least 1".to_owned(),
+            ));
+        }
+        if self.max_packets_per_sec == Some(0) {
+            return Err(ServerConfigError::Invalid(
+                "max_packets_per_sec must be greater than 0".to_owned(),
+            ));
+        }
+        let defaults = &self.room_defaults;
+        if defaults.team_count > MAX_TEAMS {
+            return Err(ServerConfigError::Invalid(format!(
+                "room_defaults.team_count must be at most {}",
+                MAX_TEAMS
+            )));
+        }
+        if defaults.series_length == 0 || defaults.series_length > MAX_SERIES_ROUNDS {
+            return Err(ServerConfigError::Invalid(format!(
+                "room_defaults.series_length must be from 1 to {}",
+                MAX_SERIES_ROUNDS
+            )));
+        }
+        if defaults.match_generations == 0 {
+            return Err(ServerConfigError::Invalid(
+                "room_defaults.match_generations must be greater than 0".to_owned(),
+            ));
+        }
+        if defaults.generations_per_sec == 0 || defaults.generations_per_sec > MAX_GENERATIONS_PER_SEC {
+            return Err(ServerConfigError::Invalid(format!(
+                "room_defaults.generations_per_sec must be from 1 to {}",
+                MAX_GENERATIONS_PER_SEC
+            )));
+        }
+        Ok(())
+    }
+
+    /// Names of the settings that differ between `self` and `other` but can't be changed without
+    /// restarting the server.
+    pub fn changes_needing_restart(&self, other: &ServerConfig) -> Vec<&'static str> {
+        let mut changed = vec![];
+        if self.name != other.name {
+            changed.push("name");
+        }
+        if self.listen != other.listen {
+            changed.push("listen");
+        }
+        if self.port != other.port {
+            changed.push("port");
+        }
+        if self.websocket_port != other.websocket_port {
+            changed.push("websocket_port");
+        }
+        if self.public_address != other.public_address {
+            changed.push("public_address");
+        }
+        if self.registrar_url != other.registrar_url {
+            changed.push("registrar_url");
+        }
+        if self.tick_interval_in_ms != other.tick_interval_in_ms {
+            changed.push("tick_interval_in_ms");
+        }
+        if self.snapshot_dir != other.snapshot_dir {
+            changed.push("snapshot_dir");
+        }
+        if self.snapshot_interval_in_secs != other.snapshot_interval_in_secs {
+            changed.push("snapshot_interval_in_secs");
+        }
+        if self.snapshot_retention != other.snapshot_retention {
+            changed.push("snapshot_retention");
+        }
+        if self.leaderboard_file != other.leaderboard_file {
+            changed.push("leaderboard_file");
+        }
+        if self.shared_patterns_file != other.shared_patterns_file {
+            changed.push("shared_patterns_file");
+        }
+        changed
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The patterns players have shared with everyone on the server. Each is kept as the RLE file it
+//! was shared as, up to `MAX_SHARED_PATTERN_LEN` bytes, along with its name and the name of the
+//! player who shared it. Once there are `max_shared_patterns` of them, the oldest is dropped to make
+//! room for each new one. They are saved to the `shared_patterns_file` after every share if one is
+//! set.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use conway::rle::PatternFile;
+use netwayste::net::{SharedPatternInfo, MAX_SHARED_PATTERN_LEN, MAX_SHARED_PATTERN_NAME_LEN};
+use serde::{Deserialize, Serialize};
+
+const MAX_SHARED_PATTERN_SIDE: usize = 1024; // cells
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct StoredPattern {
+    info: SharedPatternInfo,
+    rle:  String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct SharedPatterns {
+    next_id:  u64,
+    patterns: VecDeque<StoredPattern>, // oldest first
+}
+
+impl SharedPatterns {
+    /// Reads the shared patterns saved at `path`. A missing file means nothing has been shared yet.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SharedPatterns::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Writes the shared patterns to `path`, by way of a temporary file so that a crash mid-write
+    /// can't leave them truncated.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Adds a pattern shared by `author`, dropping the oldest ones beyond `limit`. Fails with a
+    /// message for the player if the name or the RLE won't do.
+    pub fn add(&mut self, name: &str, author: &str, rle: String, limit: usize) -> Result<SharedPatternInfo, String> {
+        if limit == 0 {
+            return Err("pattern sharing is turned off on this server".to_owned());
+        }
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("the pattern needs a name".to_owned());
+        }
+        if name.chars().count() > MAX_SHARED_PATTERN_NAME_LEN {
+            return Err(format!(
+                "pattern name too long; max {} characters",
+                MAX_SHARED_PATTERN_NAME_LEN
+            ));
+        }
+        if rle.len() > MAX_SHARED_PATTERN_LEN {
+            return Err(format!("pattern too large; max {} bytes of RLE", MAX_SHARED_PATTERN_LEN));
+        }
+        let pattern: PatternFile = rle.parse().map_err(|e| format!("not a valid RLE pattern: {}", e))?;
+        let (width, height) = (pattern.width(), pattern.height());
+        if width == 0 || height == 0 {
+            return Err("the pattern is empty".to_owned());
+        }
+        if width > MAX_SHARED_PATTERN_SIDE || height > MAX_SHARED_PATTERN_SIDE {
+            return Err(format!(
+                "pattern too large; max {} cells wide and high",
+                MAX_SHARED_PATTERN_SIDE
+            ));
+        }
+        // Those who download it can then place it without writing outside of its size
+        let (cells_width, cells_height) = pattern
+            .pattern
+            .calc_size()
+            .map_err(|e| format!("not a valid RLE pattern: {}", e))?;
+        if cells_width > width || cells_height > height {
+            return Err("the pattern's cells don't fit in its size".to_owned());
+        }
+
+        let info = SharedPatternInfo {
+            pattern_id: self.next_id,
+            name:       name.to_owned(),
+            author:     author.to_owned(),
+            width:      width as u32,
+            height:     height as u32,
+        };
+        self.next_id += 1;
+        self.patterns.push_back(StoredPattern { info: info.clone(), rle });
+        while self.patterns.len() > limit {
+            self.patterns.pop_front();
+        }
+        Ok(info)
+    }
+
+    /// Up to `len` of the patterns, newest first, skipping the newest `offset`.
+    pub fn page(&self, offset: usize, len: usize) -> Vec<SharedPatternInfo> {
+        self.patterns
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(len)
+            .map(|stored| stored.info.clone())
+            .collect()
+    }
+
+    /// The pattern with this ID and its RLE, unless it has been dropped.
+    pub fn get(&self, pattern_id: u64) -> Option<(&SharedPatternInfo, &str)> {
+        self.patterns
+            .iter()
+            .find(|stored| stored.info.pattern_id == pattern_id)
+            .map(|stored| (&stored.info, stored.rle.as_str()))
+    }
+}
@@ -0,0 +1,258 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Snapshots of the server's rooms, saved to disk periodically so that rooms and the games running
+//! in them survive a restart. Players and chat are not saved; players simply log in again.
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use conway::bot::Bot;
+use conway::grids::CharGrid;
+use conway::rle::Pattern;
+use conway::universe::{BigBang, GenStateDiff, PlayerBuilder, Region, Universe};
+use netwayste::net::{GameOptions, GameUpdate, NetRegion, RoomMap, WinCondition, VERSION};
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::Contender;
+use crate::{Room, RoomBot, Series, ServerState, Side, BOT_TURN_INTERVAL_IN_GENS, DEFAULT_GENERATIONS_PER_SEC};
+
+const SNAPSHOT_FILE_PREFIX: &str = "rooms-";
+const SNAPSHOT_FILE_EXTENSION: &str = "json";
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ServerSnapshot {
+    pub server_version: String,
+    pub rooms:          Vec<RoomSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RoomSnapshot {
+    pub name:                String,
+    pub team_count:          u8,
+    pub win_condition:       WinCondition,
+    pub series_length:       u8,
+    #[serde(default = "default_generations_per_sec")] // not in snapshots from older servers
+    pub generations_per_sec: u8,
+    pub bots:                Vec<RoomBot>,
+    pub series:              Option<Series>,
+    #[serde(default)] // not in snapshots from older servers
+    pub map:                 RoomMap,
+    pub game:                Option<GameSnapshot>, // Some if a game was running
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct GameSnapshot {
+    pub options:     GameOptions,
+    pub regions:     Vec<NetRegion>, // writable region of each player in the universe
+    pub generation:  usize,
+    pub cells:       String, // RLE of the latest generation
+    pub sides:       Vec<Side>,
+    pub bot_indices: Vec<usize>, // which players in the universe are bots
+    #[serde(default)] // not in snapshots from older servers, whose games then count for no one
+    pub contenders: Vec<Contender>,
+}
+
+impl RoomSnapshot {
+    pub fn new(room: &Room) -> Self {
+        let game = match (room.game_running, room.universe.as_ref(), room.game_options.as_ref()) {
+            (true, Some(uni), Some(options)) => Some(GameSnapshot {
+                options:     options.clone(),
+                regions:     (0..uni.num_players())
+                    .map(|i| NetRegion::from(uni.writable_region(i)))
+                    .collect(),
+                generation:  uni.latest_gen(),
+                cells:       uni.to_pattern(None).0,
+                sides:       room.sides.clone(),
+                bot_indices: room.active_bots.iter().map(|bot| bot.player_id()).collect(),
+                contenders:  room.contenders.clone(),
+            }),
+            _ => None,
+        };
+        RoomSnapshot {
+            name: room.name.clone(),
+            team_count: room.team_count,
+            win_condition: room.win_condition.clone(),
+            series_length: room.series_length,
+            generations_per_sec: room.generations_per_sec,
+            bots: room.bots.clone(),
+            series: room.series.clone(),
+            map: room.room_map(),
+            game,
+        }
+    }
+
+    /// Copies the saved settings, and any saved game, into `room`. Nobody is in a restored room, so
+    /// a restored game carries on with just its bots until players rejoin.
+    pub fn restore(self, room: &mut Room) -> Result<(), Box<dyn Error>> {
+        room.team_count = self.team_count;
+        room.win_condition = self.win_condition;
+        room.series_length = self.series_length;
+        room.generations_per_sec = self.generations_per_sec;
+        room.bots = self.bots;
+        room.series = self.series;
+        room.set_room_map(self.map)?;
+
+        if let Some(game) = self.game {
+            room.universe = Some(restore_universe(&game)?);
+            room.active_bots = game
+                .bot_indices
+                .iter()
+                .map(|&i| Bot::new(i, BOT_TURN_INTERVAL_IN_GENS))
+                .collect();
+            room.sides = game.sides;
+            room.contenders = game.contenders;
+            room.game_options = Some(game.options.clone());
+            room.game_running = true;
+            let players = room.game_players();
+            room.add_game_update(GameUpdate::GameStart {
+                options: game.options,
+                players,
+            });
+            room.broadcast("The game was restored after a server restart.".to_owned());
+        }
+        Ok(())
+    }
+}
+
+fn default_generations_per_sec() -> u8 {
+    DEFAULT_GENERATIONS_PER_SEC
+}
+
+fn restore_universe(game: &GameSnapshot) -> Result<Universe, Box<dyn Error>> {
+    let options = &game.options;
+    let players = game
+        .regions
+        .iter()
+        .map(|region| PlayerBuilder::new(Region::from(region)))
+        .collect();
+    let mut uni = BigBang::new()
+        .width(options.width() as usize)
+        .height(options.height() as usize)
+        .server_mode(true)
+        .history(options.history() as usize)
+        .fog_radius(options.fog_radius() as usize)
+        .add_players(players)
+        .birth()?;
+    // A new universe is at generation 1, so a game saved during its first generation needs no
+    // cells copied over (apply() refuses to go backwards and would return None).
+    if game.generation > uni.latest_gen() {
+        let diff = GenStateDiff {
+            gen0:    0,
+            gen1:    game.generation,
+            pattern: Pattern(game.cells.clone()),
+        };
+        uni.apply(&diff, None)?;
+    }
+    Ok(uni)
+}
+
+impl ServerState {
+    pub fn snapshot(&self) -> ServerSnapshot {
+        let mut rooms: Vec<RoomSnapshot> = self.rooms.values().map(RoomSnapshot::new).collect();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+        ServerSnapshot {
+            server_version: VERSION.to_owned(),
+            rooms,
+        }
+    }
+
+    /// Recreates the rooms in `snapshot`. A room that already exists (such as "general") has its
+    /// settings replaced. Rooms whose game can't be restored are kept without the game.
+    pub fn restore(&mut self, snapshot: ServerSnapshot) {
+        for room_snapshot in snapshot.rooms {
+            let room_id = match self.room_map.get(&room_snapshot.name) {
+                Some(&room_id) => room_id,
+                None => self.new_room(room_snapshot.name.clone()),
+            };
+            let room = self.rooms.get_mut(&room_id).unwrap(); // unwrap OK because room is in room_map
+            let name = room_snapshot.name.clone();
+            if let Err(e) = room_snapshot.restore(room) {
+                warn!("Could not restore the game in room {:?}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// A directory of snapshot files, of which only the newest `retention` are kept.
+pub struct SnapshotStore {
+    dir:       PathBuf,
+    retention: usize,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: &Path, retention: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(SnapshotStore {
+            dir: dir.to_owned(),
+            retention,
+        })
+    }
+
+    /// Writes `snapshot` to a new file, then deletes the oldest files beyond the retention count.
+    pub fn save(&self, snapshot: &ServerSnapshot) -> Result<PathBuf, Box<dyn Error>> {
+        let file_name = format!(
+            "{}{}.{}",
+            SNAPSHOT_FILE_PREFIX,
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            SNAPSHOT_FILE_EXTENSION
+        );
+        let path = self.dir.join(file_name);
+        // Write to a temporary file first, so a crash mid-write can't leave a truncated snapshot
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(snapshot)?)?;
+        fs::rename(&tmp_path, &path)?;
+
+        let files = self.list()?;
+        if files.len() > self.retention {
+            for old_path in &files[..files.len() - self.retention] {
+                fs::remove_file(old_path)?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Reads the newest snapshot, if there are any.
+    pub fn load_latest(&self) -> Result<Option<ServerSnapshot>, Box<dyn Error>> {
+        match self.list()?.last() {
+            Some(path) => Ok(Some(serde_json::from_slice(&fs::read(path)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Snapshot files, oldest first. The timestamps in the names sort in chronological order.
+    fn list(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_snapshot = path.extension().map_or(false, |ext| ext == SNAPSHOT_FILE_EXTENSION)
+                && path
+                    .file_name()
+                    .map_or(false, |name| name.to_string_lossy().starts_with(SNAPSHOT_FILE_PREFIX));
+            if is_snapshot {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
@@ -17,6 +17,8 @@
 extern crate tokio_test;
 
 use crate::net::*;
+use crate::utils::PingPong;
+use bincode::serialize;
 use std::net::SocketAddr;
 use std::{
     thread,
@@ -1179,6 +1181,99 @@ mod netwayste_net_tests {
         ];
         assert_eq!(bytes, expected);
     }
+
+    fn update_packet(chats: Vec<BroadcastChatMessage>, universe_update: UniUpdate) -> Packet {
+        Packet::Update {
+            chats,
+            game_update_seq: None,
+            game_updates: vec![],
+            universe_update,
+            universe_hash: None,
+            ping: PingPong::pong(0),
+        }
+    }
+
+    #[test]
+    fn test_packet_priority() {
+        let keep_alive = Packet::Request {
+            sequence:     1,
+            response_ack: None,
+            cookie:       None,
+            action:       RequestAction::KeepAlive { latest_response_ack: 0 },
+        };
+        assert_eq!(keep_alive.priority(), PacketPriority::Control);
+        let chat = BroadcastChatMessage::new(0, "a".to_owned(), "hi".to_owned());
+        assert_eq!(
+            update_packet(vec![chat], UniUpdate::NoChange).priority(),
+            PacketPriority::Chat
+        );
+        let first_part = GenStateDiffPart::new(0, 2, 0, 5, "!".to_owned());
+        let diff = UniUpdate::Diff { diff: first_part, epoch: 0 };
+        assert_eq!(update_packet(vec![], diff).priority(), PacketPriority::GameState);
+        let second_part = GenStateDiffPart::new(1, 2, 0, 5, "!".to_owned());
+        let diff = UniUpdate::Diff { diff: second_part, epoch: 0 };
+        assert_eq!(update_packet(vec![], diff).priority(), PacketPriority::Bulk);
+    }
+
+    #[test]
+    fn test_mtu_probe_is_padded_to_size() {
+        for &size in &[1472u16, 4000, 8972] {
+            let probe = Packet::mtu_probe(size);
+            assert_eq!(serialize(&probe).unwrap().len(), size as usize);
+            assert_eq!(probe.priority(), PacketPriority::Bulk);
+        }
+    }
+
+    #[test]
+    fn test_send_queue_sends_control_packets_first() {
+        let mut queue = SendQueue::default();
+        let addr = fake_socket_addr();
+        for part_number in 1..4 {
+            let part = GenStateDiffPart::new(part_number, 4, 0, 5, "!".to_owned());
+            queue.push(update_packet(vec![], UniUpdate::Diff { diff: part, epoch: 0 }), addr);
+        }
+        queue.push(Packet::GetStatus { ping: PingPong::ping() }, addr);
+        assert_eq!(queue.len(), 4);
+
+        assert_eq!(queue.pop().unwrap().0.priority(), PacketPriority::Control);
+        // Same priority: in the order queued
+        for part_number in 1..4 {
+            match queue.pop() {
+                Some((
+                    Packet::Update {
+                        universe_update: UniUpdate::Diff { diff, .. },
+                        ..
+                    },
+                    _,
+                )) => {
+                    assert_eq!(diff.part_number, part_number);
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert!(queue.pop().is_none());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_send_queue_drops_oldest_when_full() {
+        let mut queue = SendQueue::default();
+        let addr = fake_socket_addr();
+        for sequence in 0..SEND_QUEUE_LENGTH as u64 + 1 {
+            let packet = Packet::Request {
+                sequence,
+                response_ack: None,
+                cookie: None,
+                action: RequestAction::None,
+            };
+            queue.push(packet, addr);
+        }
+        assert_eq!(queue.len(), SEND_QUEUE_LENGTH);
+        match queue.pop() {
+            Some((Packet::Request { sequence, .. }, _)) => assert_eq!(sequence, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
 
 mod netwayste_client_tests {
@@ -1291,4 +1386,793 @@ mod netwayste_client_tests {
             seq_num += 1;
         }
     }
+
+    #[tokio::test]
+    async fn handle_incoming_chats_keeps_lobby_chats_apart() {
+        let mut client_state = create_client_net_state();
+        client_state.name = Some("client name".to_owned());
+        client_state.chat_msg_seq_num = 3;
+
+        let incoming_messages = vec![
+            BroadcastChatMessage::new(4, "a player".to_owned(), "in the room".to_owned()),
+            BroadcastChatMessage::new(1, "a player".to_owned(), "in the lobby".to_owned()).in_lobby(),
+            BroadcastChatMessage::new(2, "a player".to_owned(), "also in the lobby".to_owned()).in_lobby(),
+        ];
+        client_state.handle_incoming_chats(incoming_messages).await;
+
+        assert_eq!(client_state.chat_msg_seq_num, 4);
+        assert_eq!(client_state.lobby_chat_seq, 2);
+        let chat_queue = &client_state.network.rx_chat_messages.as_ref().unwrap().queue;
+        assert_eq!(chat_queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn handle_chat_history_skips_chats_already_received() {
+        let (to_conwayste, mut from_netwayste) = futures::channel::mpsc::channel::<NetwaysteEvent>(5);
+        let mut client_state = ClientNetState::new(to_conwayste);
+        client_state.name = Some("client name".to_owned());
+
+        let incoming_messages = vec![
+            BroadcastChatMessage::new(4, "a player".to_owned(), "fourth".to_owned()),
+            BroadcastChatMessage::new(5, "a player".to_owned(), "fifth".to_owned()),
+        ];
+        client_state.handle_incoming_chats(incoming_messages).await;
+        assert_eq!(client_state.first_chat_seq, Some(4));
+        from_netwayste.try_recv().unwrap(); // the chats themselves
+
+        let history: Vec<ChatHistoryEntry> = (2..=6)
+            .map(|chat_seq| ChatHistoryEntry {
+                chat_seq,
+                player_name: "a player".to_owned(),
+                message: format!("number {}", chat_seq),
+                age_secs: 10 - chat_seq,
+                channel: ChatChannel::Room,
+            })
+            .collect();
+        client_state.handle_chat_history(history.clone()).await;
+        match from_netwayste.try_recv().unwrap() {
+            NetwaysteEvent::ChatHistory(entries) => {
+                let messages: Vec<&str> = entries.iter().map(|(_, message, _, _)| message.as_str()).collect();
+                assert_eq!(messages, vec!["number 2", "number 3", "number 6"]);
+            }
+            event @ _ => panic!("Unexpected event: {:?}", event),
+        }
+        assert_eq!(client_state.first_chat_seq, Some(2));
+        assert_eq!(client_state.chat_msg_seq_num, 6);
+
+        // Asking again shows nothing new
+        client_state.handle_chat_history(history).await;
+        assert!(from_netwayste.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_event_drops_update_with_bad_universe_checksum() {
+        let mut client_state = create_client_net_state();
+        client_state.cookie = Some("cookie".to_owned());
+        let update = |diff: GenStateDiffPart| Packet::Update {
+            chats:           vec![],
+            game_updates:    vec![],
+            game_update_seq: None,
+            universe_update: UniUpdate::Diff { diff, epoch: 0 },
+            universe_hash:   None,
+            ping:            PingPong::ping(),
+        };
+
+        let diff = GenStateDiffPart::new(0, 1, 0, 5, "2o$2o!".to_owned());
+        assert!(diff.checksum_matches());
+        let replies = client_state
+            .handle_incoming_event(update(diff.clone()), fake_socket_addr())
+            .await;
+        assert_eq!(replies.len(), 1);
+        assert_eq!(client_state.corrupt_updates, 0);
+
+        let mut corrupted = diff;
+        corrupted.pattern_part = "2o$2b!".to_owned();
+        assert!(!corrupted.checksum_matches());
+        let replies = client_state
+            .handle_incoming_event(update(corrupted), fake_socket_addr())
+            .await;
+        assert!(replies.is_empty());
+        assert_eq!(client_state.corrupt_updates, 1);
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_event_answers_mtu_probe() {
+        let mut client_state = create_client_net_state();
+        let replies = client_state
+            .handle_incoming_event(Packet::mtu_probe(3000), fake_socket_addr())
+            .await;
+        assert!(replies.is_empty()); // not logged in
+
+        client_state.cookie = Some("cookie".to_owned());
+        let replies = client_state
+            .handle_incoming_event(Packet::mtu_probe(3000), fake_socket_addr())
+            .await;
+        match replies.as_slice() {
+            [(Packet::MtuProbeReply { cookie, size }, _)] => {
+                assert_eq!(cookie, "cookie");
+                assert_eq!(*size, 3000);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_universe_diff_only_takes_diffs_that_follow_on() {
+        let mut client_state = create_client_net_state();
+        let diff = |gen0, gen1| GenStateDiffPart::new(0, 1, gen0, gen1, "!".to_owned());
+
+        client_state.handle_incoming_universe_diff(diff(3, 5), 0).await;
+        assert_eq!(client_state.full_gen, None); // don't have generation 3
+        client_state.handle_incoming_universe_diff(diff(0, 5), 0).await;
+        assert_eq!(client_state.full_gen, Some(5));
+        client_state.handle_incoming_universe_diff(diff(0, 4), 0).await;
+        assert_eq!(client_state.full_gen, Some(5)); // older than what we have
+        client_state.handle_incoming_universe_diff(diff(4, 7), 0).await;
+        assert_eq!(client_state.full_gen, Some(5));
+        client_state.handle_incoming_universe_diff(diff(5, 7), 0).await;
+        assert_eq!(client_state.full_gen, Some(7));
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_universe_diff_starts_over_in_a_new_epoch() {
+        let mut client_state = create_client_net_state();
+        let diff = |gen0, gen1| GenStateDiffPart::new(0, 1, gen0, gen1, "!".to_owned());
+
+        client_state.handle_incoming_universe_diff(diff(0, 5), 0).await;
+        client_state.handle_incoming_universe_diff(diff(5, 7), 1).await;
+        assert_eq!((client_state.full_gen, client_state.universe_epoch), (Some(5), 0));
+        // The same generation again, but of what the server is sending us now
+        client_state.handle_incoming_universe_diff(diff(0, 5), 1).await;
+        assert_eq!((client_state.full_gen, client_state.universe_epoch), (Some(5), 1));
+        client_state.handle_incoming_universe_diff(diff(5, 7), 1).await;
+        assert_eq!(client_state.full_gen, Some(7));
+    }
+
+    #[test]
+    fn gen_state_diff_part_split_covers_whole_pattern() {
+        let pattern = "2o$".repeat(MAX_DIFF_PART_LEN); // three parts' worth
+        let parts = GenStateDiffPart::split(0, 9, &pattern);
+        assert_eq!(parts.len(), 3);
+        for (i, part) in parts.iter().enumerate() {
+            assert_eq!(part.part_number as usize, i);
+            assert_eq!(part.total_parts, 3);
+            assert!(part.pattern_part.len() <= MAX_DIFF_PART_LEN);
+            assert!(part.checksum_matches());
+        }
+        let joined: String = parts.iter().map(|part| part.pattern_part.as_str()).collect();
+        assert_eq!(joined, pattern);
+
+        // Too big for MAX_DIFF_PARTS parts of the usual length, so the parts get longer
+        let parts = GenStateDiffPart::split(0, 9, &"o".repeat(MAX_DIFF_PART_LEN * MAX_DIFF_PARTS * 2));
+        assert_eq!(parts.len(), MAX_DIFF_PARTS);
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_universe_diff_reassembles_parts() {
+        let mut client_state = create_client_net_state();
+        let mut parts = GenStateDiffPart::split(0, 5, &"3o$".repeat(MAX_DIFF_PART_LEN));
+        assert_eq!(parts.len(), 3);
+        let last = parts.pop().unwrap();
+        let first = parts.remove(0);
+
+        client_state.handle_incoming_universe_diff(last, 0).await;
+        client_state.handle_incoming_universe_diff(first, 0).await;
+        assert_eq!(client_state.full_gen, None);
+        assert_eq!(
+            client_state.partial_gen(),
+            Some(GenPartInfo {
+                gen0:         0,
+                gen1:         5,
+                have_bitmask: 0b101,
+            })
+        );
+
+        client_state.handle_incoming_universe_diff(parts.pop().unwrap(), 0).await;
+        assert_eq!(client_state.full_gen, Some(5));
+        assert_eq!(client_state.partial_gen(), None);
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_universe_diff_ignores_bad_part_numbers() {
+        let mut client_state = create_client_net_state();
+        client_state
+            .handle_incoming_universe_diff(GenStateDiffPart::new(2, 2, 0, 5, "!".to_owned()), 0)
+            .await;
+        client_state
+            .handle_incoming_universe_diff(GenStateDiffPart::new(0, 0, 0, 5, "!".to_owned()), 0)
+            .await;
+        assert_eq!(client_state.partial_gen(), None);
+        assert_eq!(client_state.full_gen, None);
+    }
+}
+
+/// Property tests for the wire format: every packet must survive a trip through the codec, and
+/// anything short of a whole packet must be turned away without panicking.
+mod netwayste_serialization_tests {
+    use super::*;
+    use bincode::deserialize;
+    use bytes::BytesMut;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    const MAX_LIST: usize = 4; // longest Vec generated; keeps cases small
+
+    fn a_region() -> impl Strategy<Value = NetRegion> {
+        (any::<i32>(), any::<i32>(), any::<u32>(), any::<u32>())
+            .prop_map(|(left, top, width, height)| NetRegion::new(left, top, width, height))
+    }
+
+    fn a_color() -> impl Strategy<Value = RgbColor> {
+        any::<[u8; 3]>().prop_map(|[r, g, b]| RgbColor::new(r, g, b))
+    }
+
+    fn a_win_condition() -> impl Strategy<Value = WinCondition> {
+        prop_oneof![
+            any::<u32>().prop_map(|generations| WinCondition::MostLiveCells { generations }),
+            Just(WinCondition::Elimination),
+            (a_region(), any::<u32>())
+                .prop_map(|(region, generations)| WinCondition::TerritoryControl { region, generations }),
+        ]
+    }
+
+    fn a_map_edit() -> impl Strategy<Value = MapEdit> {
+        prop_oneof![
+            (vec(any::<(u32, u32)>(), 0..MAX_LIST), any::<bool>())
+                .prop_map(|(cells, wall)| MapEdit::Walls { cells, wall }),
+            (any::<u8>(), proptest::option::of(a_region()))
+                .prop_map(|(slot, region)| MapEdit::SpawnRegion { slot, region }),
+            (any::<u8>(), proptest::option::of(a_region()))
+                .prop_map(|(slot, region)| MapEdit::FogFreeRegion { slot, region }),
+            Just(MapEdit::Clear),
+        ]
+    }
+
+    fn an_annotation() -> impl Strategy<Value = Annotation> {
+        prop_oneof![
+            (any::<(u32, u32)>(), any::<String>()).prop_map(|(cell, label)| Annotation::Marker { cell, label }),
+            (any::<(u32, u32)>(), any::<(u32, u32)>()).prop_map(|(from, to)| Annotation::Arrow { from, to }),
+            (any::<(u32, u32)>(), any::<(u32, u32)>()).prop_map(|(from, to)| Annotation::Measure { from, to }),
+        ]
+    }
+
+    fn a_room_map() -> impl Strategy<Value = RoomMap> {
+        (
+            any::<String>(),
+            vec(proptest::option::of(a_region()), 0..MAX_LIST),
+            vec(proptest::option::of(a_region()), 0..MAX_LIST),
+        )
+            .prop_map(|(walls, spawn_regions, fog_free_regions)| RoomMap {
+                walls,
+                spawn_regions,
+                fog_free_regions,
+            })
+    }
+
+    fn a_client_option_value() -> impl Strategy<Value = ClientOptionValue> {
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(|value| ClientOptionValue::Bool { value }),
+            any::<u8>().prop_map(|value| ClientOptionValue::U8 { value }),
+            any::<u16>().prop_map(|value| ClientOptionValue::U16 { value }),
+            any::<u32>().prop_map(|value| ClientOptionValue::U32 { value }),
+            any::<u64>().prop_map(|value| ClientOptionValue::U64 { value }),
+            any::<i8>().prop_map(|value| ClientOptionValue::I8 { value }),
+            any::<i16>().prop_map(|value| ClientOptionValue::I16 { value }),
+            any::<i32>().prop_map(|value| ClientOptionValue::I32 { value }),
+            any::<i64>().prop_map(|value| ClientOptionValue::I64 { value }),
+            any::<String>().prop_map(|value| ClientOptionValue::Str { value }),
+        ];
+        leaf.prop_recursive(3, 16, MAX_LIST as u32, |inner| {
+            vec(inner, 0..MAX_LIST).prop_map(|value| ClientOptionValue::List { value })
+        })
+    }
+
+    fn a_request_action() -> impl Strategy<Value = RequestAction> {
+        prop_oneof![
+            Just(RequestAction::None),
+            (any::<String>(), any::<String>())
+                .prop_map(|(name, client_version)| RequestAction::Connect { name, client_version }),
+            Just(RequestAction::Disconnect),
+            any::<u64>().prop_map(|latest_response_ack| RequestAction::KeepAlive { latest_response_ack }),
+            Just(RequestAction::ListPlayers),
+            any::<String>().prop_map(|message| RequestAction::ChatMessage { message }),
+            Just(RequestAction::ListRooms),
+            any::<String>().prop_map(|room_name| RequestAction::NewRoom { room_name }),
+            any::<String>().prop_map(|room_name| RequestAction::JoinRoom { room_name }),
+            Just(RequestAction::LeaveRoom),
+            any::<Option<u8>>().prop_map(|team| RequestAction::SetTeam { team }),
+            any::<String>().prop_map(|message| RequestAction::TeamChatMessage { message }),
+            any::<String>().prop_map(|message| RequestAction::LobbyChatMessage { message }),
+            Just(RequestAction::GetChatHistory),
+            Just(RequestAction::Typing),
+            any::<u8>().prop_map(|team_count| RequestAction::SetTeamCount { team_count }),
+            a_win_condition().prop_map(|condition| RequestAction::SetWinCondition { condition }),
+            Just(RequestAction::StartGame),
+            any::<u8>().prop_map(|rounds| RequestAction::SetSeriesLength { rounds }),
+            any::<u8>().prop_map(|gens_per_sec| RequestAction::SetGenerationRate { gens_per_sec }),
+            Just(RequestAction::AddBot),
+            any::<String>().prop_map(|name| RequestAction::RemoveBot { name }),
+            a_color().prop_map(|color| RequestAction::SetColor { color }),
+            a_region().prop_map(|viewport| RequestAction::ClientViewport { viewport }),
+            vec(any::<(u32, u32, bool)>(), 0..MAX_LIST).prop_map(|cells| RequestAction::SetCells { cells }),
+            Just(RequestAction::ResyncUniverse),
+            any::<String>().prop_map(|password| RequestAction::ReloadConfig { password }),
+            (any::<String>(), any::<String>())
+                .prop_map(|(player_name, reason)| RequestAction::ReportPlayer { player_name, reason }),
+            any::<Option<String>>().prop_map(|room_name| RequestAction::GetLeaderboard { room_name }),
+            (any::<String>(), any::<String>()).prop_map(|(name, rle)| RequestAction::SharePattern { name, rle }),
+            any::<u32>().prop_map(|offset| RequestAction::ListSharedPatterns { offset }),
+            any::<u64>().prop_map(|pattern_id| RequestAction::GetSharedPattern { pattern_id }),
+            a_map_edit().prop_map(|edit| RequestAction::EditRoomMap { edit }),
+            Just(RequestAction::GetRoomMap),
+            (any::<u32>(), an_annotation())
+                .prop_map(|(id, annotation)| RequestAction::ShareAnnotation { id, annotation }),
+            (any::<String>(), proptest::option::of(a_client_option_value()))
+                .prop_map(|(key, value)| RequestAction::SetClientOptions { key, value }),
+            (any::<i32>(), any::<i32>(), any::<String>()).prop_map(|(x, y, pattern)| RequestAction::DropPattern {
+                x,
+                y,
+                pattern
+            }),
+            (any::<i32>(), any::<i32>(), any::<u32>(), any::<u32>())
+                .prop_map(|(x, y, w, h)| RequestAction::ClearArea { x, y, w, h }),
+        ]
+    }
+
+    fn a_room_list() -> impl Strategy<Value = RoomList> {
+        (any::<String>(), any::<u8>(), any::<bool>()).prop_map(|(room_name, player_count, in_progress)| RoomList {
+            room_name,
+            player_count,
+            in_progress,
+        })
+    }
+
+    fn a_chat_history_entry() -> impl Strategy<Value = ChatHistoryEntry> {
+        let channel = prop_oneof![Just(ChatChannel::Room), Just(ChatChannel::Team)];
+        (any::<u64>(), any::<String>(), any::<String>(), any::<u64>(), channel).prop_map(
+            |(chat_seq, player_name, message, age_secs, channel)| ChatHistoryEntry {
+                chat_seq,
+                player_name,
+                message,
+                age_secs,
+                channel,
+            },
+        )
+    }
+
+    fn a_leaderboard_entry() -> impl Strategy<Value = LeaderboardEntry> {
+        (any::<String>(), any::<u64>(), any::<u64>(), any::<u64>()).prop_map(
+            |(player_name, wins, cells_captured, longest_survival)| LeaderboardEntry {
+                player_name,
+                wins,
+                cells_captured,
+                longest_survival,
+            },
+        )
+    }
+
+    fn a_shared_pattern_info() -> impl Strategy<Value = SharedPatternInfo> {
+        (any::<u64>(), any::<String>(), any::<String>(), any::<u32>(), any::<u32>()).prop_map(
+            |(pattern_id, name, author, width, height)| SharedPatternInfo {
+                pattern_id,
+                name,
+                author,
+                width,
+                height,
+            },
+        )
+    }
+
+    fn a_response_code() -> impl Strategy<Value = ResponseCode> {
+        prop_oneof![
+            Just(ResponseCode::OK),
+            (any::<String>(), any::<String>(), any::<Option<String>>()).prop_map(|(cookie, server_version, motd)| {
+                ResponseCode::LoggedIn {
+                    cookie,
+                    server_version,
+                    motd,
+                }
+            }),
+            any::<String>().prop_map(|room_name| ResponseCode::JoinedRoom { room_name }),
+            Just(ResponseCode::LeaveRoom),
+            any::<u8>().prop_map(|team| ResponseCode::JoinedTeam { team }),
+            vec(any::<String>(), 0..MAX_LIST).prop_map(|players| ResponseCode::PlayerList { players }),
+            vec(a_room_list(), 0..MAX_LIST).prop_map(|rooms| ResponseCode::RoomList { rooms }),
+            (vec(any::<(u32, u32)>(), 0..MAX_LIST), any::<u64>())
+                .prop_map(|(cells, generation)| ResponseCode::CellsSet { cells, generation }),
+            vec(a_chat_history_entry(), 0..MAX_LIST).prop_map(|messages| ResponseCode::ChatHistory { messages }),
+            (any::<Option<String>>(), vec(a_leaderboard_entry(), 0..MAX_LIST))
+                .prop_map(|(room_name, entries)| ResponseCode::Leaderboard { room_name, entries }),
+            (any::<u64>(), any::<String>())
+                .prop_map(|(pattern_id, name)| ResponseCode::PatternShared { pattern_id, name }),
+            (any::<u32>(), any::<u32>(), vec(a_shared_pattern_info(), 0..MAX_LIST)).prop_map(
+                |(offset, total, patterns)| ResponseCode::SharedPatternList {
+                    offset,
+                    total,
+                    patterns,
+                }
+            ),
+            (a_shared_pattern_info(), any::<String>())
+                .prop_map(|(info, rle)| ResponseCode::SharedPattern { info, rle }),
+            a_room_map().prop_map(|map| ResponseCode::RoomMap { map }),
+            any::<String>().prop_map(|error_msg| ResponseCode::BadRequest { error_msg }),
+            any::<String>().prop_map(|error_msg| ResponseCode::Unauthorized { error_msg }),
+            any::<String>().prop_map(|error_msg| ResponseCode::TooManyRequests { error_msg }),
+            any::<String>().prop_map(|error_msg| ResponseCode::ServerError { error_msg }),
+            any::<String>().prop_map(|error_msg| ResponseCode::NotConnected { error_msg }),
+            Just(ResponseCode::KeepAlive),
+        ]
+    }
+
+    fn a_player_info() -> impl Strategy<Value = PlayerInfo> {
+        (any::<String>(), any::<Option<u64>>(), any::<Option<u8>>())
+            .prop_map(|(name, index, team)| PlayerInfo::new(name, index, team))
+    }
+
+    fn a_cell_input() -> impl Strategy<Value = CellInput> {
+        (any::<u64>(), any::<u32>(), any::<u32>(), any::<bool>()).prop_map(|(player_index, col, row, alive)| {
+            CellInput {
+                player_index,
+                col,
+                row,
+                alive,
+            }
+        })
+    }
+
+    fn some_scores() -> impl Strategy<Value = Vec<PlayerScore>> {
+        vec(
+            (any::<String>(), any::<u64>()).prop_map(|(name, score)| PlayerScore { name, score }),
+            0..MAX_LIST,
+        )
+    }
+
+    fn an_outcome() -> impl Strategy<Value = GameOutcome> {
+        (
+            any::<Option<String>>(),
+            some_scores(),
+            vec(any::<String>(), 0..MAX_LIST),
+        )
+            .prop_map(|(winner, standings, winning_players)| GameOutcome {
+                winner,
+                standings,
+                winning_players,
+            })
+    }
+
+    fn some_game_options() -> impl Strategy<Value = GameOptions> {
+        (
+            any::<u32>(),
+            any::<u32>(),
+            any::<u16>(),
+            any::<u32>(),
+            0..8u8,
+            a_win_condition(),
+        )
+            .prop_map(|(width, height, history, fog_radius, team_count, win_condition)| {
+                GameOptions::with_teams(width, height, history, fog_radius, team_count)
+                    .with_win_condition(win_condition)
+            })
+    }
+
+    fn a_game_update() -> impl Strategy<Value = GameUpdate> {
+        prop_oneof![
+            any::<String>().prop_map(|msg| GameUpdate::GameNotification { msg }),
+            (some_game_options(), vec(a_player_info(), 0..MAX_LIST))
+                .prop_map(|(options, players)| GameUpdate::GameStart { options, players }),
+            vec(a_player_info(), 0..MAX_LIST).prop_map(|players| GameUpdate::PlayerList { players }),
+            (any::<u8>(), a_win_condition(), any::<u8>()).prop_map(|(team_count, win_condition, series_length)| {
+                GameUpdate::RoomSettings {
+                    settings: RoomSettings {
+                        team_count,
+                        win_condition,
+                        series_length,
+                    },
+                }
+            }),
+            (a_player_info(), any::<Option<String>>())
+                .prop_map(|(player, old_name)| GameUpdate::PlayerChange { player, old_name }),
+            a_player_info().prop_map(|player| GameUpdate::PlayerJoin { player }),
+            any::<String>().prop_map(|name| GameUpdate::PlayerLeave { name }),
+            (any::<String>(), a_color()).prop_map(|(name, color)| GameUpdate::PlayerColor { name, color }),
+            any::<String>().prop_map(|name| GameUpdate::PlayerTyping { name }),
+            a_map_edit().prop_map(|edit| GameUpdate::RoomMapEdit { edit }),
+            (any::<String>(), any::<u32>(), an_annotation())
+                .prop_map(|(name, id, annotation)| GameUpdate::AnnotationShare { name, id, annotation }),
+            (any::<u32>(), vec(a_cell_input(), 0..MAX_LIST))
+                .prop_map(|(generation, inputs)| GameUpdate::CellInputs { generation, inputs }),
+            (any::<u32>(), some_scores())
+                .prop_map(|(generation, scores)| GameUpdate::ScoreUpdate { generation, scores }),
+            an_outcome().prop_map(|outcome| GameUpdate::GameFinish { outcome }),
+            (any::<u8>(), any::<u8>(), some_scores()).prop_map(|(round, rounds, standings)| {
+                GameUpdate::Intermission {
+                    round,
+                    rounds,
+                    standings,
+                }
+            }),
+            an_outcome().prop_map(|outcome| GameUpdate::SeriesFinish { outcome }),
+            Just(GameUpdate::RoomDeleted),
+            (any::<String>(), any::<u32>()).prop_map(|(room, expire_secs)| GameUpdate::Match { room, expire_secs }),
+        ]
+    }
+
+    fn a_uni_update() -> impl Strategy<Value = UniUpdate> {
+        prop_oneof![
+            Just(UniUpdate::NoChange),
+            (
+                any::<u8>(),
+                any::<u8>(),
+                any::<u32>(),
+                any::<u32>(),
+                any::<String>(),
+                any::<u32>(),
+                any::<u32>()
+            )
+                .prop_map(|(part_number, total_parts, gen0, gen1, pattern_part, checksum, epoch)| {
+                    UniUpdate::Diff {
+                        diff: GenStateDiffPart {
+                            part_number,
+                            total_parts,
+                            gen0,
+                            gen1,
+                            pattern_part,
+                            checksum,
+                        },
+                        epoch,
+                    }
+                }),
+        ]
+    }
+
+    fn a_chat() -> impl Strategy<Value = BroadcastChatMessage> {
+        let channel = prop_oneof![
+            Just(ChatChannel::Room),
+            Just(ChatChannel::Team),
+            Just(ChatChannel::Lobby)
+        ];
+        (any::<Option<u64>>(), any::<String>(), any::<String>(), channel).prop_map(
+            |(chat_seq, player_name, message, channel)| BroadcastChatMessage {
+                chat_seq,
+                player_name,
+                message,
+                channel,
+            },
+        )
+    }
+
+    fn a_packet() -> impl Strategy<Value = Packet> {
+        let ping = any::<u64>().prop_map(|nonce| PingPong { nonce });
+        prop_oneof![
+            (
+                any::<u64>(),
+                any::<Option<u64>>(),
+                any::<Option<String>>(),
+                a_request_action()
+            )
+                .prop_map(|(sequence, response_ack, cookie, action)| Packet::Request {
+                    sequence,
+                    response_ack,
+                    cookie,
+                    action,
+                }),
+            (any::<u64>(), any::<Option<u64>>(), a_response_code()).prop_map(|(sequence, request_ack, code)| {
+                Packet::Response {
+                    sequence,
+                    request_ack,
+                    code,
+                }
+            }),
+            (
+                vec(a_chat(), 0..MAX_LIST),
+                any::<Option<u64>>(),
+                vec(a_game_update(), 0..MAX_LIST),
+                a_uni_update(),
+                any::<Option<(u32, u64)>>(),
+                ping.clone(),
+            )
+                .prop_map(
+                    |(chats, game_update_seq, game_updates, universe_update, universe_hash, ping)| Packet::Update {
+                        chats,
+                        game_update_seq,
+                        game_updates,
+                        universe_update,
+                        universe_hash: universe_hash.map(|(generation, hash)| UniverseHash { generation, hash }),
+                        ping,
+                    }
+                ),
+            (
+                any::<String>(),
+                any::<Option<u64>>(),
+                any::<Option<u64>>(),
+                any::<Option<u64>>(),
+                any::<Option<u64>>(),
+                any::<Option<(u32, u32, u32)>>(),
+                any::<u32>(),
+                ping.clone(),
+            )
+                .prop_map(
+                    |(
+                        cookie,
+                        last_chat_seq,
+                        last_lobby_chat_seq,
+                        last_game_update_seq,
+                        last_full_gen,
+                        partial_gen,
+                        universe_epoch,
+                        pong,
+                    )| {
+                        Packet::UpdateReply {
+                            cookie,
+                            last_chat_seq,
+                            last_lobby_chat_seq,
+                            last_game_update_seq,
+                            last_full_gen,
+                            partial_gen: partial_gen.map(|(gen0, gen1, have_bitmask)| GenPartInfo {
+                                gen0,
+                                gen1,
+                                have_bitmask,
+                            }),
+                            universe_epoch,
+                            pong,
+                        }
+                    }
+                ),
+            ping.clone().prop_map(|ping| Packet::GetStatus { ping }),
+            (ping, any::<String>(), any::<u64>(), any::<u64>(), any::<String>()).prop_map(
+                |(pong, server_version, player_count, room_count, server_name)| Packet::Status {
+                    pong,
+                    server_version,
+                    player_count,
+                    room_count,
+                    server_name,
+                }
+            ),
+            (any::<u16>(), vec(any::<u8>(), 0..MAX_LIST))
+                .prop_map(|(size, padding)| Packet::MtuProbe { size, padding }),
+            (any::<String>(), any::<u16>()).prop_map(|(cookie, size)| Packet::MtuProbeReply { cookie, size }),
+        ]
+    }
+
+    /// Packet's PartialEq only compares sequence numbers, so packets are compared by their bytes.
+    fn encode(packet: &Packet) -> BytesMut {
+        let mut buf = BytesMut::new();
+        NetwaystePacketCodec.encode(packet.clone(), &mut buf).unwrap();
+        buf
+    }
+
+    proptest! {
+        #[test]
+        fn request_action_round_trips(action in a_request_action()) {
+            let bytes = serialize(&action).unwrap();
+            prop_assert_eq!(deserialize::<RequestAction>(&bytes).unwrap(), action);
+        }
+
+        #[test]
+        fn response_code_round_trips(code in a_response_code()) {
+            let bytes = serialize(&code).unwrap();
+            prop_assert_eq!(deserialize::<ResponseCode>(&bytes).unwrap(), code);
+        }
+
+        #[test]
+        fn game_update_round_trips(update in a_game_update()) {
+            let bytes = serialize(&update).unwrap();
+            prop_assert_eq!(deserialize::<GameUpdate>(&bytes).unwrap(), update);
+        }
+
+        #[test]
+        fn uni_update_round_trips(update in a_uni_update()) {
+            let bytes = serialize(&update).unwrap();
+            prop_assert_eq!(deserialize::<UniUpdate>(&bytes).unwrap(), update);
+        }
+
+        #[test]
+        fn packet_round_trips_through_codec(packet in a_packet()) {
+            let mut buf = encode(&packet);
+            let encoded = buf.clone();
+            let decoded = NetwaystePacketCodec.decode(&mut buf).unwrap().unwrap();
+            prop_assert!(buf.is_empty(), "decoder left {} bytes behind", buf.len());
+            prop_assert_eq!(encode(&decoded), encoded);
+        }
+
+        #[test]
+        fn truncated_packet_is_rejected(packet in a_packet(), cut in any::<prop::sample::Index>()) {
+            let encoded = encode(&packet);
+            let cut = cut.index(encoded.len()); // always short of the whole packet
+            prop_assert!(deserialize::<Packet>(&encoded[..cut]).is_err());
+
+            // the decoder waits for the rest, leaving what it has alone
+            let mut buf = BytesMut::from(&encoded[..cut]);
+            prop_assert!(NetwaystePacketCodec.decode(&mut buf).unwrap().is_none());
+            prop_assert_eq!(buf.len(), cut);
+            buf.extend_from_slice(&encoded[cut..]);
+            let decoded = NetwaystePacketCodec.decode(&mut buf).unwrap().unwrap();
+            prop_assert_eq!(encode(&decoded), encoded);
+        }
+
+        #[test]
+        fn arbitrary_bytes_do_not_panic_decoder(bytes in vec(any::<u8>(), 0..256)) {
+            let mut buf = BytesMut::from(&bytes[..]);
+            if let Ok(Some(packet)) = NetwaystePacketCodec.decode(&mut buf) {
+                // whatever was decoded must be exactly what was consumed
+                prop_assert_eq!(&encode(&packet)[..], &bytes[..bytes.len() - buf.len()]);
+            }
+        }
+
+        #[test]
+        fn huge_lengths_are_rejected(
+            tag in (0..6u32).prop_filter("GetStatus holds only a nonce", |tag| *tag != 4),
+            len in (1u64 << 40)..u64::MAX,
+        ) {
+            // a variant tag followed by what would be the length of the first string or list (or,
+            // for Status, the pong nonce with nothing after it)
+            let mut bytes = tag.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&len.to_le_bytes());
+            let mut buf = BytesMut::from(&bytes[..]);
+            prop_assert!(NetwaystePacketCodec.decode(&mut buf).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn unknown_packet_variant_is_rejected() {
+        let mut bytes = 6u32.to_le_bytes().to_vec(); // one past Status, the last variant
+        bytes.extend_from_slice(
+            &encode(&Packet::GetStatus {
+                ping: PingPong { nonce: 1 },
+            })[4..],
+        );
+        assert!(deserialize::<Packet>(&bytes).is_err());
+    }
+}
+
+mod netwayste_websocket_tests {
+    use super::*;
+    use crate::websocket::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn transport_exchanges_packets_with_bridge() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut framed = accept(stream).await.unwrap();
+            // Echo one message back
+            let msg = framed.next().await.unwrap().unwrap();
+            framed.send(msg).await.unwrap();
+        });
+
+        let url = format!("ws://{}/", server_address);
+        let mut transport = WsTransport::connect(&url, server_address).await.unwrap();
+        let packet = Packet::GetStatus { ping: PingPong::ping() };
+        transport.send((packet.clone(), fake_socket_addr())).await.unwrap();
+        let (echoed, from) = transport.next().await.unwrap().unwrap();
+        assert_eq!(serialize(&echoed).unwrap(), serialize(&packet).unwrap());
+        assert_eq!(from, server_address);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn bridge_refuses_oversized_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws_stream = accept(stream).await.unwrap();
+            assert!(ws_stream.next().await.unwrap().is_err());
+        });
+
+        let url = format!("ws://{}/", server_address);
+        let mut client = tokio_tungstenite_wasm::connect(url).await.unwrap();
+        let message = tokio_tungstenite_wasm::Message::binary(vec![0; MAX_MESSAGE_LEN + 1]);
+        client.send(message).await.unwrap();
+        server.await.unwrap();
+    }
+
+    fn fake_socket_addr() -> SocketAddr {
+        use std::net::{IpAddr, Ipv4Addr};
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 5678)
+    }
 }
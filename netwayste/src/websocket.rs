@@ -0,0 +1,119 @@
+/*
+ * A networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Packets over WebSocket, for clients that can't use UDP, such as one running in a browser. Each
+//! packet travels as one binary message, encoded the same as over UDP. The server bridges these
+//! connections onto its UDP side, so nothing else about the protocol changes.
+//!
+//! `WsTransport` is built on tokio-tungstenite-wasm, which uses a TCP socket natively and the
+//! browser's WebSocket when compiled for wasm32.
+
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bincode::{deserialize, serialize};
+use futures::{ready, Sink, Stream};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::TcpStream;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio_tungstenite::{tungstenite, WebSocketStream};
+use tokio_tungstenite_wasm::{self as ws, Message};
+
+use crate::net::Packet;
+
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 2018;
+/// Messages bigger than this are refused; no packet comes close.
+pub const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+/// Completes the server side of the handshake on a newly accepted connection.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn accept(stream: TcpStream) -> Result<WebSocketStream<TcpStream>, tungstenite::Error> {
+    let config = tungstenite::protocol::WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE_LEN));
+    tokio_tungstenite::accept_async_with_config(stream, Some(config)).await
+}
+
+fn io_error(e: ws::Error) -> io::Error {
+    match e {
+        ws::Error::Io(e) => e,
+        e => io::Error::new(ErrorKind::Other, e),
+    }
+}
+
+/// A client's connection to a server's WebSocket bridge, for use in place of a UDP socket. Every
+/// packet received is from the server, and every packet sent goes to it whatever its address.
+pub struct WsTransport {
+    stream:         ws::WebSocketStream,
+    server_address: SocketAddr,
+}
+
+impl WsTransport {
+    /// Connects to the bridge at `url` (such as "ws://example.com:2018/"). Packets received are
+    /// reported as coming from `server_address`.
+    pub async fn connect(url: &str, server_address: SocketAddr) -> io::Result<Self> {
+        let stream = ws::connect(url).await.map_err(io_error)?;
+        Ok(WsTransport { stream, server_address })
+    }
+}
+
+impl Stream for WsTransport {
+    type Item = io::Result<(Packet, SocketAddr)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(Message::Binary(bytes))) => {
+                    let result = deserialize(&bytes)
+                        .map(|packet| (packet, this.server_address))
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e));
+                    return Poll::Ready(Some(result));
+                }
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(None),
+                Some(Ok(Message::Text(_))) => {} // the bridge only sends binary messages
+                Some(Err(e)) => return Poll::Ready(Some(Err(io_error(e)))),
+            }
+        }
+    }
+}
+
+impl Sink<(Packet, SocketAddr)> for WsTransport {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_ready(cx).map_err(io_error)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (Packet, SocketAddr)) -> Result<(), Self::Error> {
+        let (packet, _) = item;
+        let bytes = serialize(&packet).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Pin::new(&mut self.get_mut().stream)
+            .start_send(Message::binary(bytes))
+            .map_err(io_error)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx).map_err(io_error)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx).map_err(io_error)
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * Herein lies a networking library for the multiplayer game, Conwayste.
+ *
+ * Copyright (C) 2021 The Conwayste Developers
+ *
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of  MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for
+ * more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use bincode::{deserialize, serialize};
+use futures::prelude::*;
+use netwayste::net::Packet;
+use netwayste::websocket;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+type Peers = Arc<Mutex<HashMap<SocketAddr, mpsc::UnboundedSender<Packet>>>>;
+
+/// Lets clients that can't use UDP reach the server over WebSocket. To the rest of the server,
+/// each connection is a client at the connection's remote address.
+pub struct WsBridge {
+    peers: Peers,
+}
+
+impl WsBridge {
+    /// Starts accepting connections on `listener`. Packets from them come out of the receiver.
+    pub fn start(listener: TcpListener) -> (WsBridge, mpsc::UnboundedReceiver<(Packet, SocketAddr)>) {
+        let peers: Peers = Arc::new(Mutex::new(HashMap::new()));
+        let (to_server, from_peers) = mpsc::unbounded_channel();
+        tokio::spawn(accept_connections(listener, peers.clone(), to_server));
+        (WsBridge { peers }, from_peers)
+    }
+
+    /// Whether packets for `addr` should go to a WebSocket connection rather than out over UDP.
+    pub fn has_peer(&self, addr: SocketAddr) -> bool {
+        self.peers.lock().unwrap().contains_key(&addr)
+    }
+
+    pub fn send(&self, packet: Packet, addr: SocketAddr) {
+        if let Some(to_peer) = self.peers.lock().unwrap().get(&addr) {
+            // An error means the connection just closed, and it's about to be forgotten
+            let _ = to_peer.send(packet);
+        }
+    }
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    peers: Peers,
+    to_server: mpsc::UnboundedSender<(Packet, SocketAddr)>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                tokio::spawn(serve_connection(stream, addr, peers.clone(), to_server.clone()));
+            }
+            Err(e) => warn!("Error while accepting WebSocket connection: {:?}", e),
+        }
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    addr: SocketAddr,
+    peers: Peers,
+    to_server: mpsc::UnboundedSender<(Packet, SocketAddr)>,
+) {
+    let ws_stream = match websocket::accept(stream).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            debug!("WebSocket handshake with {:?} failed: {}", addr, e);
+            return;
+        }
+    };
+    let (to_peer, mut for_peer) = mpsc::unbounded_channel();
+    peers.lock().unwrap().insert(addr, to_peer);
+    debug!("WebSocket connection from {:?}", addr);
+
+    // Pings are answered by tungstenite itself
+    let (mut ws_sink, mut ws_stream) = ws_stream.split();
+    loop {
+        tokio::select! {
+            opt_msg = ws_stream.next() => match opt_msg {
+                Some(Ok(Message::Binary(bytes))) => match deserialize::<Packet>(&bytes) {
+                    Ok(packet) => {
+                        if to_server.send((packet, addr)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => debug!("Undecodable packet over WebSocket from {:?}: {:?}", addr, e),
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    debug!("Error on WebSocket connection from {:?}: {}", addr, e);
+                    break;
+                }
+            },
+            opt_packet = for_peer.recv() => match opt_packet {
+                Some(packet) => {
+                    let bytes = serialize(&packet).unwrap();
+                    if ws_sink.send(Message::binary(bytes)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+        }
+    }
+
+    peers.lock().unwrap().remove(&addr);
+    debug!("WebSocket connection from {:?} closed", addr);
+}
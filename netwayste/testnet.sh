@@ -115,7 +115,7 @@ function main()
     tmuxkill
     cargo build
 
-    tmux new-session -d -s server "export RUST_BACKTRACE=1; export RUST_LOG=server; ./target/debug/server; read"
+    tmux new-session -d -s server "export RUST_BACKTRACE=1; export RUST_LOG=conwaysted; ./target/debug/conwaysted; read"
     for i in ${CLIENTLIST[@]}; do
         printf "Starting cli-client $i"
         tmux new-session -d -s $i "export RUST_BACKTRACE=1; export RUST_LOG=client; ./target/debug/cli-client; read"